@@ -1,4 +1,4 @@
-fn main() {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     // tauri::generate_context! 在编译期会校验 `frontendDist` 路径是否存在。
     // 开发/CI 场景下可能只跑 `cargo check/test` 而未先构建前端，从而导致宏 panic。
     // 这里提前创建配置中的 `../dist` 目录，避免无关的编译阻塞。
@@ -6,5 +6,16 @@ fn main() {
         let dist_dir = std::path::PathBuf::from(manifest_dir).join("../dist");
         let _ = std::fs::create_dir_all(dist_dir);
     }
-    tauri_build::build()
+
+    // gRPC 接口代码生成，优先使用内置的 protoc，无需本机安装；
+    // 当前平台没有内置版本时回退到 PROTOC 环境变量或 PATH 中的 protoc
+    match protoc_bin_vendored::protoc_bin_path() {
+        Ok(path) => std::env::set_var("PROTOC", path),
+        Err(e) => println!("cargo:warning=找不到内置的 protoc（{e}），改用系统 protoc"),
+    }
+    tonic_build::compile_protos("proto/proxycast.proto")
+        .map_err(|e| format!("编译 proto 文件失败: {e}"))?;
+
+    tauri_build::build();
+    Ok(())
 }
@@ -0,0 +1,677 @@
+//! 配置与凭证变更审计日志
+//!
+//! 以 JSON Lines 格式追加写入 `~/.proxycast/audit/audit.log`，每条记录包含：
+//! - 时间、操作者、操作类型、操作对象
+//! - 脱敏后的变更列表（密钥类字段只记录"已变更"，不记录内容）
+//! - 上一条记录的哈希（`prev_hash`）与本条记录的哈希（`hash`），构成哈希链
+//! - 使用本地审计密钥（`audit.key`）对 `hash` 计算的 HMAC-SHA256 签名
+//!
+//! 修改、插入或删除中间的任意记录都会使哈希链断开；没有审计密钥的情况下
+//! 也无法重新计算签名。通过 [`AuditLog::verify`] 校验整个日志。
+//!
+//! 全局审计日志需在启动时通过 [`init`] 初始化；未初始化时 [`record`] 不做任何事，
+//! 因此单元测试不会写入用户目录。
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// 审计日志文件名
+const LOG_FILE: &str = "audit.log";
+/// 审计密钥文件名
+const KEY_FILE: &str = "audit.key";
+/// 哈希链起点
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+/// 脱敏占位符
+pub const REDACTED: &str = "***REDACTED***";
+
+/// 审计错误
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("审计日志读写失败: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("审计记录序列化失败: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    #[error("审计密钥无效: {0}")]
+    InvalidKey(String),
+}
+
+/// 审计操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// 保存配置
+    ConfigSave,
+    /// 导入配置或配置包
+    ConfigImport,
+    /// 导出配置或配置包
+    ConfigExport,
+    /// 添加凭证
+    CredentialAdd,
+    /// 修改凭证属性（名称、启用状态等）
+    CredentialUpdate,
+    /// 轮换凭证（更换 API Key 或重新上传凭证文件）
+    CredentialRotate,
+    /// 删除凭证
+    CredentialRemove,
+}
+
+/// 单个字段的变更（已脱敏）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditChange {
+    /// 字段路径（如 `server.port`、`api_keys[0].label`）
+    pub path: String,
+    /// 变更前的值（新增时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<Value>,
+    /// 变更后的值（删除时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<Value>,
+}
+
+/// 审计记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// 序号（从 0 开始连续递增）
+    pub seq: u64,
+    /// 记录时间
+    pub timestamp: DateTime<Utc>,
+    /// 操作者（如 `desktop:alice`、`management-api`）
+    pub actor: String,
+    /// 操作类型
+    pub action: AuditAction,
+    /// 操作对象（如凭证 UUID、配置文件路径）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// 脱敏后的变更列表
+    #[serde(default)]
+    pub changes: Vec<AuditChange>,
+    /// 上一条记录的哈希
+    pub prev_hash: String,
+    /// 本条记录的哈希
+    pub hash: String,
+    /// 对 `hash` 的 HMAC-SHA256 签名
+    pub signature: String,
+}
+
+/// 参与哈希计算的字段（不含 `hash` 和 `signature`）
+#[derive(Serialize)]
+struct HashedFields<'a> {
+    seq: u64,
+    timestamp: &'a DateTime<Utc>,
+    actor: &'a str,
+    action: AuditAction,
+    target: &'a Option<String>,
+    changes: &'a [AuditChange],
+    prev_hash: &'a str,
+}
+
+impl AuditEntry {
+    fn compute_hash(&self) -> Result<String, AuditError> {
+        let fields = HashedFields {
+            seq: self.seq,
+            timestamp: &self.timestamp,
+            actor: &self.actor,
+            action: self.action,
+            target: &self.target,
+            changes: &self.changes,
+            prev_hash: &self.prev_hash,
+        };
+        let bytes = serde_json::to_vec(&fields)?;
+        Ok(hex(&Sha256::digest(&bytes)))
+    }
+}
+
+/// 审计日志校验结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditVerifyReport {
+    /// 哈希链与签名是否全部有效
+    pub valid: bool,
+    /// 已校验通过的记录数
+    pub verified_entries: u64,
+    /// 第一条无效记录所在的行号（从 1 开始）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_invalid_line: Option<u64>,
+    /// 失败原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 链尾状态
+#[derive(Debug)]
+struct ChainTail {
+    next_seq: u64,
+    last_hash: String,
+}
+
+/// 追加写入的审计日志
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    key: Vec<u8>,
+    tail: Mutex<ChainTail>,
+}
+
+impl AuditLog {
+    /// 打开（或创建）目录下的审计日志，审计密钥不存在时自动生成
+    pub fn open(dir: &Path) -> Result<Self, AuditError> {
+        fs::create_dir_all(dir)?;
+        let key = load_or_create_key(&dir.join(KEY_FILE))?;
+        let path = dir.join(LOG_FILE);
+
+        let mut tail = ChainTail {
+            next_seq: 0,
+            last_hash: GENESIS_HASH.to_string(),
+        };
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                // 尾部无法解析时仍以最后一条有效记录续写，由 verify 报告问题
+                if let Ok(entry) = serde_json::from_str::<AuditEntry>(&line) {
+                    tail.next_seq = entry.seq + 1;
+                    tail.last_hash = entry.hash;
+                }
+            }
+        }
+
+        Ok(Self {
+            path,
+            key,
+            tail: Mutex::new(tail),
+        })
+    }
+
+    /// 审计日志文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 追加一条记录
+    pub fn append(
+        &self,
+        actor: &str,
+        action: AuditAction,
+        target: Option<String>,
+        changes: Vec<AuditChange>,
+    ) -> Result<AuditEntry, AuditError> {
+        let mut tail = self.tail.lock();
+        let mut entry = AuditEntry {
+            seq: tail.next_seq,
+            timestamp: Utc::now(),
+            actor: actor.to_string(),
+            action,
+            target,
+            changes,
+            prev_hash: tail.last_hash.clone(),
+            hash: String::new(),
+            signature: String::new(),
+        };
+        entry.hash = entry.compute_hash()?;
+        entry.signature = self.sign(&entry.hash);
+
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+
+        tail.next_seq += 1;
+        tail.last_hash = entry.hash.clone();
+        Ok(entry)
+    }
+
+    /// 读取全部记录（跳过无法解析的行）
+    pub fn entries(&self) -> Result<Vec<AuditEntry>, AuditError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut entries = Vec::new();
+        for line in BufReader::new(File::open(&self.path)?).lines() {
+            if let Ok(entry) = serde_json::from_str(&line?) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// 校验哈希链与签名
+    pub fn verify(&self) -> Result<AuditVerifyReport, AuditError> {
+        let _tail = self.tail.lock();
+        let mut report = AuditVerifyReport {
+            valid: true,
+            verified_entries: 0,
+            first_invalid_line: None,
+            error: None,
+        };
+        if !self.path.exists() {
+            return Ok(report);
+        }
+
+        let mut expected_hash = GENESIS_HASH.to_string();
+        let mut expected_seq = 0u64;
+        for (index, line) in BufReader::new(File::open(&self.path)?).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let failure = match serde_json::from_str::<AuditEntry>(&line) {
+                Err(e) => Some(format!("记录无法解析: {}", e)),
+                Ok(entry) => {
+                    let failure = self.check_entry(&entry, expected_seq, &expected_hash)?;
+                    expected_seq = entry.seq + 1;
+                    expected_hash = entry.hash;
+                    failure
+                }
+            };
+            if let Some(error) = failure {
+                report.valid = false;
+                report.first_invalid_line = Some(index as u64 + 1);
+                report.error = Some(error);
+                break;
+            }
+            report.verified_entries += 1;
+        }
+        Ok(report)
+    }
+
+    fn check_entry(
+        &self,
+        entry: &AuditEntry,
+        expected_seq: u64,
+        expected_hash: &str,
+    ) -> Result<Option<String>, AuditError> {
+        if entry.seq != expected_seq {
+            return Ok(Some(format!(
+                "序号不连续: 期望 {}，实际 {}",
+                expected_seq, entry.seq
+            )));
+        }
+        if entry.prev_hash != expected_hash {
+            return Ok(Some("哈希链断开: prev_hash 与上一条记录不一致".to_string()));
+        }
+        if entry.compute_hash()? != entry.hash {
+            return Ok(Some("记录内容已被修改: 哈希不匹配".to_string()));
+        }
+        let mut mac = self.mac();
+        mac.update(entry.hash.as_bytes());
+        let valid_signature = unhex(&entry.signature)
+            .map(|sig| mac.verify_slice(&sig).is_ok())
+            .unwrap_or(false);
+        if !valid_signature {
+            return Ok(Some("签名无效".to_string()));
+        }
+        Ok(None)
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC 支持任意长度的密钥")
+    }
+
+    fn sign(&self, hash: &str) -> String {
+        let mut mac = self.mac();
+        mac.update(hash.as_bytes());
+        hex(&mac.finalize().into_bytes())
+    }
+}
+
+/// 读取审计密钥，不存在时生成 32 字节随机密钥（仅当前用户可读）
+fn load_or_create_key(path: &Path) -> Result<Vec<u8>, AuditError> {
+    if path.exists() {
+        let content = fs::read_to_string(path)?;
+        return unhex(content.trim())
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| AuditError::InvalidKey(path.display().to_string()));
+    }
+
+    let key: [u8; 32] = rand::random();
+    fs::write(path, hex(&key))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(key.to_vec())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// ============================================================================
+// 脱敏差异
+// ============================================================================
+
+/// 字段名是否为密钥类字段
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    [
+        "key",
+        "token",
+        "secret",
+        "password",
+        "cookie",
+        "cookies",
+        "authorization",
+    ]
+    .iter()
+    .any(|suffix| key == *suffix || key.ends_with(&format!("_{}", suffix)))
+}
+
+/// 递归脱敏 JSON 值中的密钥类字段
+pub fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let v = if is_secret_key(k) && !v.is_null() {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        redact(v)
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        other => other.clone(),
+    }
+}
+
+/// 计算两个 JSON 值之间的脱敏变更列表
+///
+/// 比较在脱敏之前进行，密钥被更换时仍会记录一条（值为占位符的）变更。
+pub fn redacted_diff(before: Option<&Value>, after: Option<&Value>) -> Vec<AuditChange> {
+    let mut changes = Vec::new();
+    diff_into(&mut changes, "", false, before, after);
+    changes
+}
+
+fn diff_into(
+    changes: &mut Vec<AuditChange>,
+    path: &str,
+    secret: bool,
+    before: Option<&Value>,
+    after: Option<&Value>,
+) {
+    let empty = serde_json::Map::new();
+    // 新增或删除整个对象时同样展开到字段，便于逐字段脱敏
+    let objects = match (before, after) {
+        (Some(Value::Object(l)), Some(Value::Object(r))) => Some((l, r)),
+        (None, Some(Value::Object(r))) => Some((&empty, r)),
+        (Some(Value::Object(l)), None) => Some((l, &empty)),
+        _ => None,
+    };
+    if let Some((l, r)) = objects {
+        let mut keys: Vec<&String> = l.keys().chain(r.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let child = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", path, key)
+            };
+            diff_into(
+                changes,
+                &child,
+                secret || is_secret_key(key),
+                l.get(key),
+                r.get(key),
+            );
+        }
+        return;
+    }
+
+    match (before, after) {
+        (Some(Value::Array(l)), Some(Value::Array(r))) => {
+            for i in 0..l.len().max(r.len()) {
+                let child = format!("{}[{}]", path, i);
+                diff_into(changes, &child, secret, l.get(i), r.get(i));
+            }
+        }
+        (l, r) if l == r => {}
+        (l, r) => {
+            let mask = |v: Option<&Value>| {
+                v.map(|v| {
+                    if secret && !v.is_null() {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        redact(v)
+                    }
+                })
+            };
+            changes.push(AuditChange {
+                path: path.to_string(),
+                before: mask(l),
+                after: mask(r),
+            });
+        }
+    }
+}
+
+// ============================================================================
+// 全局审计日志
+// ============================================================================
+
+static AUDIT_LOG: OnceLock<AuditLog> = OnceLock::new();
+
+/// 默认审计目录（`~/.proxycast/audit`）
+pub fn default_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".proxycast")
+        .join("audit")
+}
+
+/// 初始化全局审计日志（重复调用时忽略）
+pub fn init(dir: &Path) -> Result<(), AuditError> {
+    if AUDIT_LOG.get().is_none() {
+        let _ = AUDIT_LOG.set(AuditLog::open(dir)?);
+    }
+    Ok(())
+}
+
+/// 全局审计日志（未初始化时为 `None`）
+pub fn global() -> Option<&'static AuditLog> {
+    AUDIT_LOG.get()
+}
+
+/// 本地桌面用户作为操作者
+pub fn local_actor() -> String {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    format!("desktop:{}", user)
+}
+
+/// 写入一条审计记录
+///
+/// 没有任何变更的更新（`before` 与 `after` 都存在且相同）不会记录。
+/// 写入失败只记录警告，不影响调用方的操作。
+pub fn record(
+    actor: &str,
+    action: AuditAction,
+    target: Option<String>,
+    before: Option<&Value>,
+    after: Option<&Value>,
+) {
+    let Some(log) = global() else {
+        return;
+    };
+    let changes = redacted_diff(before, after);
+    if changes.is_empty() && before.is_some() && after.is_some() {
+        return;
+    }
+    if let Err(e) = log.append(actor, action, target, changes) {
+        tracing::warn!("[AUDIT] 写入审计日志失败: {}", e);
+    }
+}
+
+/// 序列化为 JSON 后写入审计记录
+pub fn record_serialized<T: Serialize>(
+    actor: &str,
+    action: AuditAction,
+    target: Option<String>,
+    before: Option<&T>,
+    after: Option<&T>,
+) {
+    if global().is_none() {
+        return;
+    }
+    let to_value = |v: Option<&T>| v.and_then(|v| serde_json::to_value(v).ok());
+    record(
+        actor,
+        action,
+        target,
+        to_value(before).as_ref(),
+        to_value(after).as_ref(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn rewrite_line(log: &AuditLog, index: usize, edit: impl FnOnce(&mut AuditEntry)) {
+        let content = fs::read_to_string(log.path()).unwrap();
+        let mut lines: Vec<String> = content.lines().map(String::from).collect();
+        let mut entry: AuditEntry = serde_json::from_str(&lines[index]).unwrap();
+        edit(&mut entry);
+        lines[index] = serde_json::to_string(&entry).unwrap();
+        fs::write(log.path(), lines.join("\n") + "\n").unwrap();
+    }
+
+    #[test]
+    fn test_redacted_diff_hides_secrets() {
+        let before = json!({
+            "server": {"port": 8999, "api_key": "sk-old"},
+            "api_keys": [{"label": "ci", "key": "pc-1"}]
+        });
+        let after = json!({
+            "server": {"port": 9000, "api_key": "sk-new"},
+            "api_keys": [{"label": "ci", "key": "pc-1"}, {"label": "dev", "key": "pc-2"}]
+        });
+        let changes = redacted_diff(Some(&before), Some(&after));
+        let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "api_keys[1].key",
+                "api_keys[1].label",
+                "server.api_key",
+                "server.port"
+            ]
+        );
+
+        let serialized = serde_json::to_string(&changes).unwrap();
+        assert!(!serialized.contains("sk-old"));
+        assert!(!serialized.contains("sk-new"));
+        assert!(!serialized.contains("pc-2"));
+        assert_eq!(changes[0].after, Some(json!(REDACTED)));
+        assert_eq!(changes[2].before, Some(json!(REDACTED)));
+
+        assert!(redacted_diff(Some(&before), Some(&before)).is_empty());
+    }
+
+    #[test]
+    fn test_append_and_verify_chain() {
+        let dir = TempDir::new().unwrap();
+        let log = AuditLog::open(dir.path()).unwrap();
+        let first = log
+            .append("desktop:test", AuditAction::ConfigSave, None, Vec::new())
+            .unwrap();
+        let second = log
+            .append(
+                "management-api",
+                AuditAction::CredentialAdd,
+                Some("cred-1".to_string()),
+                redacted_diff(None, Some(&json!({"api_key": "sk-1"}))),
+            )
+            .unwrap();
+        assert_eq!(first.prev_hash, GENESIS_HASH);
+        assert_eq!(second.prev_hash, first.hash);
+
+        let report = log.verify().unwrap();
+        assert!(report.valid);
+        assert_eq!(report.verified_entries, 2);
+
+        // 重新打开后继续在链尾追加
+        let reopened = AuditLog::open(dir.path()).unwrap();
+        let third = reopened
+            .append("desktop:test", AuditAction::ConfigExport, None, Vec::new())
+            .unwrap();
+        assert_eq!(third.seq, 2);
+        assert_eq!(third.prev_hash, second.hash);
+        assert!(reopened.verify().unwrap().valid);
+    }
+
+    #[test]
+    fn test_verify_detects_tampering() {
+        let dir = TempDir::new().unwrap();
+        let log = AuditLog::open(dir.path()).unwrap();
+        for _ in 0..3 {
+            log.append("desktop:test", AuditAction::ConfigSave, None, Vec::new())
+                .unwrap();
+        }
+
+        // 修改内容但保留哈希
+        rewrite_line(&log, 1, |entry| entry.actor = "someone-else".to_string());
+        let report = log.verify().unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.verified_entries, 1);
+        assert_eq!(report.first_invalid_line, Some(2));
+
+        // 重新计算哈希但无法伪造签名
+        rewrite_line(&log, 1, |entry| entry.hash = entry.compute_hash().unwrap());
+        let report = log.verify().unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.error.as_deref(), Some("签名无效"));
+    }
+
+    #[test]
+    fn test_verify_detects_removed_entry() {
+        let dir = TempDir::new().unwrap();
+        let log = AuditLog::open(dir.path()).unwrap();
+        for _ in 0..3 {
+            log.append("desktop:test", AuditAction::ConfigSave, None, Vec::new())
+                .unwrap();
+        }
+        let content = fs::read_to_string(log.path()).unwrap();
+        let kept: Vec<&str> = content
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .map(|(_, l)| l)
+            .collect();
+        fs::write(log.path(), kept.join("\n") + "\n").unwrap();
+
+        let report = log.verify().unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.first_invalid_line, Some(2));
+    }
+}
@@ -0,0 +1,22 @@
+//! 审计日志相关 Tauri 命令
+
+use crate::audit::{self, AuditEntry, AuditVerifyReport};
+
+/// 校验审计日志的哈希链与签名
+#[tauri::command]
+pub fn verify_audit_log() -> Result<AuditVerifyReport, String> {
+    let log = audit::global().ok_or_else(|| "审计日志未初始化".to_string())?;
+    log.verify().map_err(|e| e.to_string())
+}
+
+/// 获取最近的审计记录（按时间倒序）
+#[tauri::command]
+pub fn get_audit_entries(limit: Option<usize>) -> Result<Vec<AuditEntry>, String> {
+    let log = audit::global().ok_or_else(|| "审计日志未初始化".to_string())?;
+    let entries = log.entries().map_err(|e| e.to_string())?;
+    Ok(entries
+        .into_iter()
+        .rev()
+        .take(limit.unwrap_or(100))
+        .collect())
+}
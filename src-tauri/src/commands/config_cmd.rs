@@ -1,6 +1,6 @@
 use crate::config::{
     Config, ConfigManager, ExportBundle, ExportOptions as ExportServiceOptions, ExportService,
-    ImportOptions as ImportServiceOptions, ImportService, ValidationResult,
+    HotReloadManager, ImportOptions as ImportServiceOptions, ImportService, ValidationResult,
 };
 use crate::models::AppType;
 use serde::{Deserialize, Serialize};
@@ -207,6 +207,28 @@ pub async fn set_auto_launch(app: AppHandle, enabled: bool) -> Result<bool, Stri
 
 // ============ Config Import/Export Commands ============
 
+/// 记录配置导出（`scope` 为导出内容，如 `config`、`bundle:full`）
+fn audit_export(scope: &str, redacted: bool) {
+    crate::audit::record(
+        &crate::audit::local_actor(),
+        crate::audit::AuditAction::ConfigExport,
+        Some(scope.to_string()),
+        None,
+        Some(&serde_json::json!({ "redact_secrets": redacted })),
+    );
+}
+
+/// 记录配置导入，变更为导入前后配置的脱敏差异
+fn audit_import(source: &str, before: &Config, after: &Config) {
+    crate::audit::record_serialized(
+        &crate::audit::local_actor(),
+        crate::audit::AuditAction::ConfigImport,
+        Some(source.to_string()),
+        Some(before),
+        Some(after),
+    );
+}
+
 /// 配置导出选项
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportOptions {
@@ -237,6 +259,7 @@ pub fn export_config(config: Config, redact_secrets: bool) -> Result<ExportResul
     let content = manager_with_config
         .export(redact_secrets)
         .map_err(|e| e.to_string())?;
+    audit_export("config", redact_secrets);
 
     // 生成带时间戳的文件名
     let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
@@ -289,7 +312,7 @@ pub fn import_config(
     merge: bool,
 ) -> Result<ImportResult, String> {
     let mut manager = ConfigManager::new(PathBuf::from("temp.yaml"));
-    manager.set_config(current_config);
+    manager.set_config(current_config.clone());
 
     let mut warnings = Vec::new();
 
@@ -328,6 +351,7 @@ pub fn import_config(
 
     // 如果导入的配置包含脱敏的密钥，恢复原有值
     let final_config = manager.config().clone();
+    audit_import("config_yaml", &current_config, &final_config);
 
     Ok(ImportResult {
         success: true,
@@ -419,6 +443,15 @@ pub fn export_bundle(
         ExportService::export(&config, &export_options, &app_version).map_err(|e| e.to_string())?;
 
     let content = bundle.to_json().map_err(|e| e.to_string())?;
+    audit_export(
+        match (options.include_config, options.include_credentials) {
+            (true, true) => "bundle:full",
+            (true, false) => "bundle:config",
+            (false, true) => "bundle:credentials",
+            (false, false) => "bundle:empty",
+        },
+        bundle.redacted,
+    );
 
     // 生成带时间戳的文件名
     let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
@@ -454,6 +487,7 @@ pub fn export_bundle(
 #[tauri::command]
 pub fn export_config_yaml(config: Config, redact_secrets: bool) -> Result<ExportResult, String> {
     let content = ExportService::export_yaml(&config, redact_secrets).map_err(|e| e.to_string())?;
+    audit_export("config_yaml", redact_secrets);
 
     // 生成带时间戳的文件名
     let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
@@ -477,12 +511,67 @@ pub fn validate_import(content: String) -> Result<ValidationResult, String> {
     Ok(ImportService::validate(&content))
 }
 
+/// 仅验证候选配置文件，不应用
+///
+/// 使用与热重载相同的验证规则，便于在保存前确认修改不会被拒绝。
+///
+/// # Arguments
+/// * `path` - 候选配置文件路径（为空时使用默认配置文件）
+#[tauri::command]
+pub fn reload_validate_only(path: Option<String>) -> Result<ValidationResult, String> {
+    let path = match path {
+        Some(path) => crate::config::expand_tilde(&path),
+        None => ConfigManager::default_config_path(),
+    };
+    Ok(HotReloadManager::validate_file(&path))
+}
+
+/// 演练导入，列出计划变更和冲突，不应用
+///
+/// # Arguments
+/// * `current_config` - 当前配置
+/// * `content` - 导入内容（JSON 导出包或 YAML 配置）
+/// * `merge` - 是否合并到现有配置
+#[tauri::command]
+pub fn preview_import(
+    current_config: Config,
+    content: String,
+    merge: bool,
+) -> Result<ValidationResult, String> {
+    let bundle = ExportBundle::from_json(&content).unwrap_or_else(|_| {
+        let mut bundle = ExportBundle::new(env!("CARGO_PKG_VERSION"));
+        bundle.config_yaml = Some(content);
+        bundle
+    });
+    let options = import_service_options(merge, false);
+    Ok(ImportService::validate_only(
+        &bundle,
+        &current_config,
+        &options,
+        &current_config.auth_dir,
+    ))
+}
+
+fn import_service_options(merge: bool, fail_on_conflict: bool) -> ImportServiceOptions {
+    let options = if merge {
+        ImportServiceOptions::merge()
+    } else {
+        ImportServiceOptions::replace()
+    };
+    if fail_on_conflict {
+        options.with_fail_on_conflict()
+    } else {
+        options
+    }
+}
+
 /// 导入完整的导出包
 ///
 /// # Arguments
 /// * `current_config` - 当前配置
 /// * `content` - 导出包内容（JSON 格式）
 /// * `merge` - 是否合并到现有配置
+/// * `fail_on_conflict` - 存在冲突时中止导入（默认 false）
 ///
 /// # Requirements: 4.1, 4.3
 #[tauri::command]
@@ -490,13 +579,16 @@ pub fn import_bundle(
     current_config: Config,
     content: String,
     merge: bool,
+    fail_on_conflict: Option<bool>,
 ) -> Result<ImportResult, String> {
+    let options = import_service_options(merge, fail_on_conflict.unwrap_or(false));
+
     // 首先尝试解析为 ExportBundle
     if let Ok(bundle) = ExportBundle::from_json(&content) {
-        let options = ImportServiceOptions { merge };
         let result =
             ImportService::import(&bundle, &current_config, &options, &current_config.auth_dir)
                 .map_err(|e| e.to_string())?;
+        audit_import("bundle", &current_config, &result.config);
 
         return Ok(ImportResult {
             success: result.success,
@@ -506,9 +598,9 @@ pub fn import_bundle(
     }
 
     // 尝试解析为 YAML 配置
-    let options = ImportServiceOptions { merge };
     let result = ImportService::import_yaml(&content, &current_config, &options)
         .map_err(|e| e.to_string())?;
+    audit_import("yaml", &current_config, &result.config);
 
     Ok(ImportResult {
         success: result.success,
@@ -4,17 +4,24 @@
 //!
 //! **Validates: Requirements 10.1-10.7**
 
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tauri::State;
 
-use crate::flow_monitor::monitor::{NotificationConfig, NotificationSettings};
+use crate::config::save_config;
+use crate::flow_monitor::monitor::{FlowMonitorConfig, NotificationConfig, NotificationSettings};
 use crate::flow_monitor::{
-    get_filter_help, BatchOperation, BatchOperations, BatchResult, DiffConfig, ExportFormat,
-    ExportOptions, FilterExpr, FilterParser, FlowAnnotations, FlowDiff, FlowDiffResult,
-    FlowExporter, FlowFilter, FlowMonitor, FlowQueryResult, FlowQueryService, FlowSearchResult,
-    FlowSortBy, FlowStats, LLMFlow, FILTER_HELP,
+    get_filter_help, ActiveFlowSummary, BatchOperation, BatchOperations, BatchResult, BatchTarget,
+    CaptureDecision, DeleteByFilterResult, DeletePreview, DiffConfig, DiskStatus, ExportFormat,
+    ExportManifest, ExportOptions, ExportVerification, FilterExpr, FilterFieldHelp, FilterParser,
+    FilterTestResult, FlowAnnotations, FlowDiff, FlowDiffResult, FlowExporter, FlowFilter,
+    FlowMonitor, FlowOp, FlowOpResult, FlowQueryResult, FlowQueryService, FlowSearchResult,
+    FlowSortBy, FlowStats, FlowThread, LLMFlow, MitmImportSummary, ReadCacheStats, SloStatus,
+    FILTER_FIELD_HELP, FILTER_HELP,
 };
+use crate::router::RoutingTrace;
+use crate::AppState;
 
 // ============================================================================
 // 状态封装
@@ -105,6 +112,24 @@ pub struct ExportFlowsRequest {
     /// 是否脱敏敏感数据
     #[serde(default)]
     pub redact_sensitive: bool,
+    /// 脱敏后是否校验输出（残留疑似密钥时导出失败并返回对应的 JSON 路径）
+    #[serde(default)]
+    pub verify_redaction: bool,
+    /// 是否按稳定顺序输出（对象键按字典序，便于用 `git diff` 对比导出结果）
+    #[serde(default)]
+    pub stable_key_order: bool,
+    /// JSON / HAR 是否缩进输出（否则为紧凑格式）
+    #[serde(default = "default_true")]
+    pub pretty: bool,
+    /// 是否把提取出的内联推理放回响应正文
+    #[serde(default)]
+    pub restore_inline_reasoning: bool,
+    /// JSON / JSONL 只导出的字段（点分路径，如 `request.model`、`response.usage`）
+    #[serde(default)]
+    pub field_mask: Vec<String>,
+    /// JSON / JSONL 不导出的字段（点分路径）
+    #[serde(default)]
+    pub exclude_fields: Vec<String>,
     /// Flow ID 列表（如果指定，则只导出这些 Flow）
     #[serde(default)]
     pub flow_ids: Option<Vec<String>>,
@@ -113,12 +138,14 @@ pub struct ExportFlowsRequest {
 /// 导出结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportFlowsResponse {
-    /// 导出的数据（JSON 字符串）
+    /// 导出的数据（JSON 字符串；Parquet 为 base64 编码的文件内容）
     pub data: String,
     /// 导出的 Flow 数量
     pub count: usize,
     /// 导出格式
     pub format: ExportFormat,
+    /// 导出清单（选项、脱敏规则、Flow 数量和 `data` 的哈希），供接收方校验
+    pub manifest: ExportManifest,
 }
 
 /// 更新标注请求参数
@@ -205,6 +232,38 @@ pub async fn get_flow_detail(
         .map_err(|e| format!("获取 Flow 详情失败: {}", e))
 }
 
+/// Flow 的模型路由解析说明
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowRoutingExplanation {
+    /// 解析轨迹
+    pub trace: RoutingTrace,
+    /// 可读描述（如 ``别名 `fast` → `gpt-4o-mini`，命中规则 `gpt-*`（优先级 10）→ openai``）
+    pub description: String,
+}
+
+/// 获取 Flow 的模型路由解析说明
+///
+/// # Returns
+/// * `Ok(Some(FlowRoutingExplanation))` - 解析轨迹和可读描述
+/// * `Ok(None)` - Flow 不存在或未记录解析轨迹
+#[tauri::command]
+pub async fn explain_flow_routing(
+    flow_id: String,
+    query_service: State<'_, FlowQueryServiceState>,
+) -> Result<Option<FlowRoutingExplanation>, String> {
+    let flow = query_service
+        .0
+        .get_flow(&flow_id)
+        .await
+        .map_err(|e| format!("获取 Flow 详情失败: {}", e))?;
+    Ok(flow
+        .and_then(|f| f.metadata.routing_info.routing_trace)
+        .map(|trace| FlowRoutingExplanation {
+            description: trace.describe(),
+            trace,
+        }))
+}
+
 /// 全文搜索 Flow
 ///
 /// **Validates: Requirements 10.3**
@@ -248,6 +307,24 @@ pub async fn get_flow_stats(
     Ok(query_service.0.get_stats(&filter).await)
 }
 
+/// 重建会话线程
+///
+/// # Arguments
+/// * `filter` - 过滤条件（可选）
+/// * `query_service` - 查询服务状态
+///
+/// # Returns
+/// * `Ok(Vec<FlowThread>)` - 按开始时间排序的线程列表
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn get_flow_threads(
+    filter: Option<FlowFilter>,
+    query_service: State<'_, FlowQueryServiceState>,
+) -> Result<Vec<FlowThread>, String> {
+    let filter = filter.unwrap_or_default();
+    Ok(query_service.0.reconstruct_threads(&filter).await)
+}
+
 /// 导出 Flow
 ///
 /// **Validates: Requirements 10.5**
@@ -295,32 +372,121 @@ pub async fn export_flows(
         include_stream_chunks: request.include_stream_chunks,
         redact_sensitive: request.redact_sensitive,
         redaction_rules: Vec::new(),
+        verify_redaction: request.verify_redaction,
         compress: false,
+        stable_key_order: request.stable_key_order,
+        pretty: request.pretty,
+        restore_inline_reasoning: request.restore_inline_reasoning,
+        field_mask: request.field_mask,
+        exclude_fields: request.exclude_fields,
     };
     let exporter = FlowExporter::new(options);
+    exporter.validate_field_mask().map_err(|e| e.to_string())?;
+
+    // 安全分享模式：脱敏后仍有疑似密钥时拒绝导出
+    if request.verify_redaction {
+        exporter.verify(&flows).map_err(|e| e.to_string())?;
+    }
 
     // 导出数据
     let data = match request.format {
         ExportFormat::HAR => {
             let har = exporter.export_har(&flows);
-            serde_json::to_string_pretty(&har).map_err(|e| format!("序列化 HAR 失败: {}", e))?
+            exporter
+                .to_json_string(&har)
+                .map_err(|e| format!("序列化 HAR 失败: {}", e))?
         }
         ExportFormat::JSON => {
             let json = exporter.export_json(&flows);
-            serde_json::to_string_pretty(&json).map_err(|e| format!("序列化 JSON 失败: {}", e))?
+            exporter
+                .to_json_string(&json)
+                .map_err(|e| format!("序列化 JSON 失败: {}", e))?
         }
         ExportFormat::JSONL => exporter.export_jsonl(&flows),
         ExportFormat::Markdown => exporter.export_markdown_multiple(&flows),
         ExportFormat::CSV => exporter.export_csv(&flows),
+        ExportFormat::OpenAiBatch => exporter.export_batch(&flows, BatchTarget::OpenAi),
+        ExportFormat::AnthropicBatch => exporter.export_batch(&flows, BatchTarget::Anthropic),
+        ExportFormat::Parquet => exporter
+            .export_parquet(&flows)
+            .map(|data| BASE64_STANDARD.encode(data))
+            .map_err(|e| format!("导出 Parquet 失败: {}", e))?,
     };
 
+    let manifest = exporter.manifest(count, &data);
     Ok(ExportFlowsResponse {
         data,
         count,
         format: request.format,
+        manifest,
     })
 }
 
+/// 校验导出数据
+///
+/// 按清单重新计算导出数据的哈希，确认数据在传递过程中没有被修改。
+///
+/// # Arguments
+/// * `data` - 导出的数据（`ExportFlowsResponse::data`）
+/// * `manifest` - 随数据一起导出的清单
+///
+/// # Returns
+/// * `ExportVerification` - 校验结果，不一致时包含原因
+#[tauri::command]
+pub fn verify_export(data: String, manifest: ExportManifest) -> ExportVerification {
+    manifest.verify(&data)
+}
+
+/// 导出会话记录请求参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportConversationRequest {
+    /// 按会话顺序排列的 Flow ID 列表
+    pub flow_ids: Vec<String>,
+    /// 导出格式（仅支持 Markdown 和 JSON）
+    pub format: ExportFormat,
+    /// 是否脱敏敏感数据
+    #[serde(default)]
+    pub redact_sensitive: bool,
+}
+
+/// 将多个 Flow 合并导出为一份会话记录
+///
+/// 去除每轮重复发送的历史消息，只保留每轮新增的部分；
+/// Flow 未构成线性链时记录中会包含警告。
+///
+/// # Arguments
+/// * `request` - 导出请求参数
+/// * `query_service` - 查询服务状态
+///
+/// # Returns
+/// * `Ok(String)` - 成功时返回 Markdown 或 JSON 文本
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn export_flow_conversation(
+    request: ExportConversationRequest,
+    query_service: State<'_, FlowQueryServiceState>,
+) -> Result<String, String> {
+    let mut flows = Vec::new();
+    for id in &request.flow_ids {
+        if let Ok(Some(flow)) = query_service.0.get_flow(id).await {
+            flows.push(flow);
+        }
+    }
+
+    let exporter = FlowExporter::new(ExportOptions {
+        format: request.format,
+        redact_sensitive: request.redact_sensitive,
+        ..Default::default()
+    });
+    match request.format {
+        ExportFormat::Markdown => Ok(exporter.export_conversation_markdown(&flows)),
+        ExportFormat::JSON => exporter
+            .to_json_string(&exporter.export_conversation(&flows))
+            .map_err(|e| format!("序列化会话记录失败: {}", e)),
+        other => Err(format!("会话记录不支持导出为 {:?} 格式", other)),
+    }
+}
+
 /// 更新 Flow 标注
 ///
 /// **Validates: Requirements 10.6**
@@ -537,6 +703,14 @@ pub struct FlowMonitorStatus {
     pub memory_flow_count: usize,
     /// 最大内存 Flow 数量
     pub max_memory_flows: usize,
+    /// 最大活跃 Flow 数量（为空时不限制）
+    pub max_active_flows: Option<usize>,
+    /// 因活跃 Flow 达到上限而跳过的捕获数
+    pub dropped_capture_count: u64,
+    /// 磁盘状态（未启用文件存储时为空）
+    pub disk: Option<DiskStatus>,
+    /// 文件存储读缓存统计（未启用文件存储时为空）
+    pub read_cache: Option<ReadCacheStats>,
 }
 
 #[tauri::command]
@@ -549,9 +723,134 @@ pub async fn get_flow_monitor_status(
         active_flow_count: monitor.0.active_flow_count().await,
         memory_flow_count: monitor.0.memory_flow_count().await,
         max_memory_flows: config.max_memory_flows,
+        max_active_flows: config.max_active_flows,
+        dropped_capture_count: monitor.0.dropped_capture_count(),
+        disk: monitor.0.disk_status().await,
+        read_cache: monitor.0.read_cache_stats(),
+    })
+}
+
+/// 获取各 Provider 和模型的成功率 SLO 状态
+///
+/// # Returns
+/// * `Ok(Vec<SloStatus>)` - 短窗口和长窗口的成功率、错误预算消耗速率及告警状态
+#[tauri::command]
+pub async fn get_slo_status(
+    monitor: State<'_, FlowMonitorState>,
+) -> Result<Vec<SloStatus>, String> {
+    Ok(monitor.0.slo_status().await)
+}
+
+/// 说明指定模型和路径的请求当前是否会被捕获，不捕获时给出原因
+///
+/// # Arguments
+/// * `model` - 请求的模型（别名解析后的模型）
+/// * `path` - 请求路径（如 `/v1/chat/completions`）
+#[tauri::command]
+pub async fn explain_flow_capture_decision(
+    model: String,
+    path: String,
+    monitor: State<'_, FlowMonitorState>,
+) -> Result<CaptureDecision, String> {
+    Ok(monitor.0.explain_capture_decision(&model, &path).await)
+}
+
+/// 列出正在进行中的 Flow（按开始时间从早到晚排序）
+#[tauri::command]
+pub async fn list_active_flows(
+    monitor: State<'_, FlowMonitorState>,
+) -> Result<Vec<ActiveFlowSummary>, String> {
+    Ok(monitor.0.list_active().await)
+}
+
+/// 强制取消正在进行中的 Flow
+///
+/// # Returns
+/// * `Ok(true)` - 已取消
+/// * `Ok(false)` - Flow 不存在或已结束
+#[tauri::command]
+pub async fn force_cancel_active_flow(
+    flow_id: String,
+    monitor: State<'_, FlowMonitorState>,
+) -> Result<bool, String> {
+    Ok(monitor.0.force_cancel_flow(&flow_id).await)
+}
+
+/// 导入 mitmproxy 保存的 Flow 文件
+///
+/// 只导入 LLM 端点（OpenAI Chat Completions、Anthropic Messages）的请求，
+/// 其他 Flow 计入跳过数。
+///
+/// # Arguments
+/// * `path` - Flow 文件路径（`mitmdump -w` 的输出或 `.mitm` 文件）
+#[tauri::command]
+pub async fn import_mitm_flows(
+    path: String,
+    monitor: State<'_, FlowMonitorState>,
+) -> Result<MitmImportSummary, String> {
+    let data = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("读取文件失败: {}", e))?;
+    let result = crate::flow_monitor::parse_mitm_flows(&data).map_err(|e| e.to_string())?;
+    let imported = monitor.0.import_flows(result.flows).await;
+    Ok(MitmImportSummary {
+        imported,
+        skipped: result.skipped,
     })
 }
 
+/// 获取 Flow Monitor 配置
+#[tauri::command]
+pub async fn get_flow_monitor_config(
+    monitor: State<'_, FlowMonitorState>,
+) -> Result<FlowMonitorConfig, String> {
+    Ok(monitor.0.config().await)
+}
+
+/// 更新 Flow Monitor 配置
+///
+/// # Arguments
+/// * `config` - 新的 Flow Monitor 配置（`request_id_header` 需为合法的 HTTP 头名称）
+/// * `monitor` - Flow 监控服务状态
+/// * `query_service` - 查询服务状态（同步相关度排序权重和成本单价表）
+/// * `app_state` - 应用状态（`wal_path` 写入配置文件，下次启动时生效）
+#[tauri::command]
+pub async fn set_flow_monitor_config(
+    config: FlowMonitorConfig,
+    monitor: State<'_, FlowMonitorState>,
+    query_service: State<'_, FlowQueryServiceState>,
+    app_state: State<'_, AppState>,
+) -> Result<(), String> {
+    if let Some(header) = config.request_id_header.as_deref() {
+        let header = header.trim();
+        if !header.is_empty() && reqwest::header::HeaderName::from_bytes(header.as_bytes()).is_err()
+        {
+            return Err(format!("无效的请求头名称: {}", header));
+        }
+    }
+    query_service
+        .0
+        .set_relevance_weights(config.relevance_weights.clone());
+    query_service
+        .0
+        .set_model_pricing(config.model_pricing.clone());
+
+    let wal_path = config
+        .wal_path
+        .as_ref()
+        .map(|path| path.to_string_lossy().into_owned());
+    {
+        let mut s = app_state.write().await;
+        if s.config.flow_monitor.wal_path != wal_path {
+            s.config.flow_monitor.wal_path = wal_path;
+            save_config(&s.config).map_err(|e| e.to_string())?;
+        }
+    }
+
+    monitor.0.update_config(config).await;
+    Ok(())
+}
+
 /// 获取 Flow Monitor 状态（调试用）
 ///
 /// **Validates: Requirements 10.1**
@@ -665,10 +964,13 @@ pub async fn create_test_flows(
                 max_tokens: Some(1000),
                 stop: None,
                 stream: false,
+                response_format: None,
+                seed: None,
                 extra: std::collections::HashMap::new(),
             },
             size_bytes: 100 + i * 10,
             timestamp: Utc::now(),
+            attachments: Vec::new(),
         };
 
         // 创建测试元数据
@@ -681,14 +983,28 @@ pub async fn create_test_flows(
                 ip: Some("127.0.0.1".to_string()),
                 user_agent: Some("test-agent".to_string()),
                 request_id: Some(format!("test-req-{}", i)),
+                api_key_label: None,
             },
             routing_info: RoutingInfo {
                 target_url: Some("https://api.openai.com".to_string()),
                 route_rule: None,
                 load_balance_strategy: None,
+                session_key: None,
+                session_affinity: None,
+                model_downgrade: None,
+                history_truncation: None,
+                routing_trace: None,
+                remap_bypassed: false,
+                extra_headers: std::collections::HashMap::new(),
             },
             injected_params: None,
+            defaulted_params: None,
             context_usage_percentage: Some(50.0),
+            shadow_of: None,
+            idempotent_replay_of: None,
+            upstream_request_id: None,
+            usage_source: None,
+            content_filter: None,
         };
 
         // 启动 Flow
@@ -713,10 +1029,16 @@ pub async fn create_test_flows(
                     total_tokens: 30 + i as u32 * 2,
                 },
                 stop_reason: Some(crate::flow_monitor::StopReason::Stop),
+                raw_stop_reason: None,
                 size_bytes: 200 + i * 15,
                 timestamp_start: Utc::now(),
                 timestamp_end: Utc::now(),
                 stream_info: None,
+                schema_valid: None,
+                logprobs: None,
+                body_info: None,
+                system_fingerprint: None,
+                turns: Vec::new(),
             };
 
             monitor.0.complete_flow(&flow_id, Some(response)).await;
@@ -776,6 +1098,12 @@ mod tests {
             include_raw: true,
             include_stream_chunks: false,
             redact_sensitive: false,
+            verify_redaction: false,
+            stable_key_order: true,
+            pretty: true,
+            restore_inline_reasoning: false,
+            field_mask: Vec::new(),
+            exclude_fields: Vec::new(),
             flow_ids: None,
         };
 
@@ -836,21 +1164,36 @@ pub async fn subscribe_flow_events(
     Ok(())
 }
 
+/// Flow 标签及使用次数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowTagCount {
+    /// 标签
+    pub tag: String,
+    /// 带有该标签的 Flow 数量
+    pub count: usize,
+}
+
 /// 获取所有可用的 Flow 标签
 ///
 /// # Arguments
 /// * `query_service` - 查询服务状态
 ///
 /// # Returns
-/// * `Ok(Vec<String>)` - 成功时返回标签列表
+/// * `Ok(Vec<FlowTagCount>)` - 成功时返回标签及使用次数（按次数降序）
 /// * `Err(String)` - 失败时返回错误消息
 #[tauri::command]
 pub async fn get_all_flow_tags(
-    _query_service: State<'_, FlowQueryServiceState>,
-) -> Result<Vec<String>, String> {
-    // TODO: 实现从存储中获取所有标签
-    // 目前返回空列表
-    Ok(Vec::new())
+    query_service: State<'_, FlowQueryServiceState>,
+) -> Result<Vec<FlowTagCount>, String> {
+    let tags = query_service
+        .0
+        .collect_tags()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(tags
+        .into_iter()
+        .map(|(tag, count)| FlowTagCount { tag, count })
+        .collect())
 }
 
 // ============================================================================
@@ -922,6 +1265,32 @@ pub async fn validate_filter(expression: String) -> Result<bool, String> {
     Ok(FilterParser::validate(&expression).is_ok())
 }
 
+/// 试运行过滤表达式
+///
+/// 解析表达式并对内存中的 Flow 求值，返回匹配总数、最近的若干个匹配 Flow 和耗时，
+/// 便于在保存过滤器前反复调整复杂的表达式。
+///
+/// # Arguments
+/// * `expression` - 过滤表达式字符串
+/// * `limit` - 返回的匹配 Flow 数量上限（默认 20）
+/// * `query_service` - 查询服务状态
+///
+/// # Returns
+/// * `Ok(FilterTestResult)` - 试运行结果
+/// * `Err(String)` - 表达式无效
+#[tauri::command]
+pub async fn test_filter(
+    expression: String,
+    limit: Option<usize>,
+    query_service: State<'_, FlowQueryServiceState>,
+) -> Result<FilterTestResult, String> {
+    query_service
+        .0
+        .test_expression(&expression, limit.unwrap_or_else(default_page_size))
+        .await
+        .map_err(|e| format!("过滤表达式无效: {}", e))
+}
+
 /// 获取过滤表达式帮助信息
 ///
 /// **Validates: Requirements 1.1-1.16**
@@ -942,6 +1311,17 @@ pub async fn get_filter_help_items() -> Result<Vec<FilterHelpItem>, String> {
     Ok(items)
 }
 
+/// 获取结构化的过滤字段帮助
+///
+/// 返回每个过滤字段支持的匹配方式或比较运算符和示例，供 CLI 和脚本使用。
+///
+/// # Returns
+/// * `Ok(Vec<FilterFieldHelp>)` - 过滤字段帮助列表
+#[tauri::command]
+pub async fn get_filter_help_fields() -> Result<Vec<FilterFieldHelp>, String> {
+    Ok(FILTER_FIELD_HELP.to_vec())
+}
+
 /// 获取过滤表达式帮助文本
 ///
 /// **Validates: Requirements 1.1-1.16**
@@ -1015,7 +1395,7 @@ use crate::flow_monitor::{
 };
 
 use crate::flow_monitor::{
-    BatchReplayResult, FlowReplayer, ReplayConfig, ReplayResult, RequestModification,
+    BatchReplayResult, FlowReplayer, ReplayConfig, ReplayResult, RequestModification, StopCondition,
 };
 
 /// 拦截器状态封装
@@ -1306,6 +1686,9 @@ pub struct ReplayFlowRequest {
 /// 批量重放 Flow 请求参数
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReplayFlowsBatchRequest {
+    /// 运行 ID（用于取消本次批量重放，为空时自动生成）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
     /// 要重放的 Flow ID 列表
     pub flow_ids: Vec<String>,
     /// 重放配置
@@ -1352,12 +1735,160 @@ pub async fn replay_flows_batch(
     request: ReplayFlowsBatchRequest,
     replayer: State<'_, FlowReplayerState>,
 ) -> Result<BatchReplayResult, String> {
+    let run_id = request
+        .run_id
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
     Ok(replayer
         .0
-        .replay_batch(&request.flow_ids, request.config)
+        .replay_batch(&run_id, &request.flow_ids, request.config)
         .await)
 }
 
+/// 循环重放 Flow 请求参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayFlowRepeatRequest {
+    /// 运行 ID（用于取消本次循环重放，为空时自动生成）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    /// 要重放的 Flow ID
+    pub flow_id: String,
+    /// 最大重放次数
+    pub count: usize,
+    /// 停止条件
+    #[serde(default)]
+    pub stop_on: StopCondition,
+    /// 重放配置
+    #[serde(default)]
+    pub config: ReplayConfig,
+}
+
+/// 循环重放单个 Flow
+///
+/// # Arguments
+/// * `request` - 循环重放请求参数
+/// * `replayer` - 重放器状态
+///
+/// # Returns
+/// * `Ok(BatchReplayResult)` - 成功时返回统计结果
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn replay_flow_repeat(
+    request: ReplayFlowRepeatRequest,
+    replayer: State<'_, FlowReplayerState>,
+) -> Result<BatchReplayResult, String> {
+    if request.count == 0 {
+        return Err("重放次数必须大于 0".to_string());
+    }
+    let run_id = request
+        .run_id
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    Ok(replayer
+        .0
+        .replay_repeat(
+            &run_id,
+            &request.flow_id,
+            request.count,
+            request.stop_on,
+            request.config,
+        )
+        .await)
+}
+
+/// 取消正在进行的循环重放
+///
+/// # Arguments
+/// * `run_id` - 要取消的运行 ID（为空时取消所有循环重放）
+///
+/// # Returns
+/// 是否有重放被取消
+#[tauri::command]
+pub fn cancel_flow_replay_repeat(
+    run_id: Option<String>,
+    replayer: State<'_, FlowReplayerState>,
+) -> bool {
+    replayer.0.cancel_repeat(run_id.as_deref())
+}
+
+/// 取消正在进行的批量重放
+///
+/// # Arguments
+/// * `run_id` - 要取消的运行 ID（为空时取消所有批量重放）
+///
+/// # Returns
+/// 是否有重放被取消
+#[tauri::command]
+pub fn cancel_flow_replay_batch(
+    run_id: Option<String>,
+    replayer: State<'_, FlowReplayerState>,
+) -> bool {
+    replayer.0.cancel_batch(run_id.as_deref())
+}
+
+/// 以流式方式重放单个 Flow
+///
+/// 重放过程中的 chunk 通过 `subscribe_replay_stream_events` 推送到前端，
+/// 结束后返回重放结果。
+///
+/// # Arguments
+/// * `request` - 重放请求参数
+/// * `replayer` - 重放器状态
+///
+/// # Returns
+/// * `Ok(ReplayResult)` - 成功时返回重放结果
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn replay_flow_streaming(
+    request: ReplayFlowRequest,
+    replayer: State<'_, FlowReplayerState>,
+) -> Result<ReplayResult, String> {
+    replayer
+        .0
+        .replay_streaming(&request.flow_id, request.config)
+        .await
+        .map_err(|e| format!("流式重放 Flow 失败: {}", e))
+}
+
+/// 订阅流式重放事件
+///
+/// 启动一个后台任务，将流式重放的 chunk 通过 Tauri 事件系统推送到前端。
+/// 前端可以通过 `listen("replay-stream-event", ...)` 来接收事件。
+///
+/// # Arguments
+/// * `app` - Tauri AppHandle
+/// * `replayer` - 重放器状态
+///
+/// # Returns
+/// * `Ok(())` - 成功启动订阅
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn subscribe_replay_stream_events(
+    app: AppHandle,
+    replayer: State<'_, FlowReplayerState>,
+) -> Result<(), String> {
+    let mut receiver = replayer.0.subscribe_stream();
+
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    if let Err(e) = app.emit("replay-stream-event", &event) {
+                        tracing::warn!("发送流式重放事件到前端失败: {}", e);
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!("流式重放事件接收器落后 {} 条消息", n);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                    tracing::debug!("流式重放事件通道已关闭");
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
 // ============================================================================
 // 差异对比命令
 // ============================================================================
@@ -1437,11 +1968,13 @@ mod replayer_tests {
     #[test]
     fn test_replay_flows_batch_request_serialization() {
         let request = ReplayFlowsBatchRequest {
+            run_id: None,
             flow_ids: vec!["flow-1".to_string(), "flow-2".to_string()],
             config: ReplayConfig {
                 credential_id: Some("cred-1".to_string()),
                 modify_request: None,
                 interval_ms: 500,
+                ..Default::default()
             },
         };
 
@@ -1499,6 +2032,7 @@ mod diff_tests {
                 ignore_fields: vec!["custom_field".to_string()],
                 ignore_timestamps: false,
                 ignore_ids: false,
+                align_by_role: true,
             },
         };
 
@@ -1508,6 +2042,7 @@ mod diff_tests {
         assert_eq!(deserialized.config.ignore_fields.len(), 1);
         assert!(!deserialized.config.ignore_timestamps);
         assert!(!deserialized.config.ignore_ids);
+        assert!(deserialized.config.align_by_role);
     }
 }
 
@@ -2676,8 +3211,9 @@ pub async fn toggle_bookmark(
 // ============================================================================
 
 use crate::flow_monitor::{
-    Distribution, EnhancedStats, EnhancedStatsService, ReportFormat, StatsTimeRange,
-    TimeSeriesPoint, TrendData,
+    Distribution, EnhancedStats, EnhancedStatsService, PromptTemplateOptions, PromptTemplateReport,
+    ReportFormat, StatsOutputFormat, StatsReport, StatsTimeRange, TimeRange, TimeSeriesPoint,
+    TrendData,
 };
 
 /// 增强统计服务状态封装
@@ -2832,6 +3368,23 @@ pub async fn get_latency_histogram(
         .await)
 }
 
+/// 获取各模型的流式延迟分位数
+///
+/// 返回每个模型 TTFB 与 chunk 间隔的 p50/p95/p99 及分布，
+/// 用于区分首包慢和中途卡顿的模型。
+#[tauri::command]
+pub async fn get_stream_latency_percentiles(
+) -> Result<Vec<crate::streaming::ModelStreamLatency>, String> {
+    Ok(crate::streaming::stream_latency_histograms().snapshot())
+}
+
+/// 重置流式延迟直方图
+#[tauri::command]
+pub async fn reset_stream_latency_histograms() -> Result<(), String> {
+    crate::streaming::stream_latency_histograms().reset();
+    Ok(())
+}
+
 /// 导出统计报告
 ///
 /// **Validates: Requirements 9.7**
@@ -2853,6 +3406,96 @@ pub async fn export_stats_report(
         .export_report(&request.filter, &request.time_range, &request.format)
         .await)
 }
+
+/// 获取提示词模板成本报告请求参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetPromptTemplateStatsRequest {
+    /// 过滤条件
+    #[serde(default)]
+    pub filter: FlowFilter,
+    /// 时间范围
+    #[serde(default)]
+    pub time_range: StatsTimeRange,
+    /// 聚类依据和单价表
+    #[serde(default)]
+    pub options: PromptTemplateOptions,
+}
+
+/// 获取提示词模板成本报告
+///
+/// 把只有插值不同的提示词归为同一模板，按成本降序返回各模板的请求数、Token 和成本。
+///
+/// # Arguments
+/// * `request` - 获取提示词模板成本报告请求参数
+/// * `stats_service` - 增强统计服务状态
+/// * `monitor` - Flow 监控器状态（提供内容规范化规则）
+///
+/// # Returns
+/// * `Ok(PromptTemplateReport)` - 成功时返回模板报告
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn get_prompt_template_stats(
+    request: GetPromptTemplateStatsRequest,
+    stats_service: State<'_, EnhancedStatsServiceState>,
+    monitor: State<'_, FlowMonitorState>,
+) -> Result<PromptTemplateReport, String> {
+    let normalizer = monitor.0.content_normalizer().await;
+    Ok(stats_service
+        .0
+        .by_prompt_template(
+            &request.filter,
+            &request.time_range,
+            &request.options,
+            &normalizer,
+        )
+        .await)
+}
+
+/// 获取统计输出请求参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetStatsOutputRequest {
+    /// 过滤条件
+    #[serde(default)]
+    pub filter: FlowFilter,
+    /// 时间范围
+    #[serde(default)]
+    pub time_range: StatsTimeRange,
+    /// 输出格式
+    #[serde(default)]
+    pub format: StatsOutputFormat,
+}
+
+/// 获取统计输出
+///
+/// 合并基础统计和增强统计，按 `format` 输出为文本或稳定结构的 JSON
+/// （字段带单位后缀，见 `STATS_SCHEMA_VERSION`），供 CLI 和脚本使用。
+///
+/// # Arguments
+/// * `request` - 获取统计输出请求参数
+/// * `query_service` - 查询服务状态
+/// * `stats_service` - 增强统计服务状态
+///
+/// # Returns
+/// * `Ok(String)` - 成功时返回格式化的统计输出
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn get_stats_output(
+    request: GetStatsOutputRequest,
+    query_service: State<'_, FlowQueryServiceState>,
+    stats_service: State<'_, EnhancedStatsServiceState>,
+) -> Result<String, String> {
+    let mut filter = request.filter.clone();
+    filter.time_range = Some(TimeRange {
+        start: Some(request.time_range.start),
+        end: Some(request.time_range.end),
+    });
+    let stats = query_service.0.get_stats(&filter).await;
+    let enhanced = stats_service
+        .0
+        .get_stats(&request.filter, &request.time_range)
+        .await;
+    Ok(StatsReport::new(&stats, &enhanced).render(request.format))
+}
 // ============================================================================
 // 批量操作状态封装
 // ============================================================================
@@ -2921,6 +3564,15 @@ pub struct BatchAddToSessionRequest {
     pub session_id: String,
 }
 
+/// 按过滤条件删除请求参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteFlowsByFilterRequest {
+    /// 过滤条件（需与预览时一致）
+    pub filter: FlowFilter,
+    /// 预览时返回的确认令牌
+    pub confirm_token: String,
+}
+
 // ============================================================================
 // 批量操作 Tauri 命令
 // ============================================================================
@@ -3095,6 +3747,66 @@ pub async fn batch_add_to_session(
         .await)
 }
 
+/// 预览按过滤条件删除
+///
+/// # Arguments
+/// * `filter` - 过滤条件
+/// * `batch_ops` - 批量操作服务状态
+///
+/// # Returns
+/// * `Ok(DeletePreview)` - 匹配的 Flow 数量和确认令牌
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn preview_delete_flows_by_filter(
+    filter: FlowFilter,
+    batch_ops: State<'_, BatchOperationsState>,
+) -> Result<DeletePreview, String> {
+    batch_ops
+        .0
+        .preview_delete(&filter)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 按过滤条件删除 Flow（同时删除内存和文件存储）
+///
+/// # Arguments
+/// * `request` - 过滤条件和确认令牌
+/// * `batch_ops` - 批量操作服务状态
+///
+/// # Returns
+/// * `Ok(DeleteByFilterResult)` - 各存储中删除的数量
+/// * `Err(String)` - 令牌无效、过期或过滤条件不一致时返回错误消息
+#[tauri::command]
+pub async fn delete_flows_by_filter(
+    request: DeleteFlowsByFilterRequest,
+    batch_ops: State<'_, BatchOperationsState>,
+) -> Result<DeleteByFilterResult, String> {
+    batch_ops
+        .0
+        .delete_flows_by_filter(&request.filter, &request.confirm_token)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 一次执行多个单 Flow 操作（获取、标注、标签、删除、添加到会话）
+///
+/// 各操作相互独立，某个操作失败不影响其余操作。
+///
+/// # Arguments
+/// * `ops` - 操作列表
+/// * `batch_ops` - 批量操作服务状态
+///
+/// # Returns
+/// * `Ok(Vec<FlowOpResult>)` - 按请求顺序返回的各操作结果
+#[tauri::command]
+pub async fn flow_batch_ops(
+    ops: Vec<FlowOp>,
+    batch_ops: State<'_, BatchOperationsState>,
+) -> Result<Vec<FlowOpResult>, String> {
+    Ok(batch_ops.0.execute_ops(ops).await)
+}
+
 // ============================================================================
 // 实时监控增强命令
 // ============================================================================
@@ -3223,6 +3935,50 @@ pub async fn set_rate_window(
     monitor.0.set_rate_window(window_seconds).await;
     Ok(())
 }
+
+// ============================================================================
+// 自动标签命令
+// ============================================================================
+
+use crate::flow_monitor::AutoTagConfig;
+
+/// 获取自动标签配置
+///
+/// # Arguments
+/// * `monitor` - Flow 监控服务状态
+///
+/// # Returns
+/// * `Ok(AutoTagConfig)` - 成功时返回自动标签配置
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn get_auto_tag_config(
+    monitor: State<'_, FlowMonitorState>,
+) -> Result<AutoTagConfig, String> {
+    Ok(monitor.0.auto_tag_config().await)
+}
+
+/// 更新自动标签配置
+///
+/// 规则会立即生效（热重载）；任一规则条件无效时返回错误，原有规则保持不变。
+///
+/// # Arguments
+/// * `config` - 新的自动标签配置
+/// * `monitor` - Flow 监控服务状态
+///
+/// # Returns
+/// * `Ok(())` - 成功
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn update_auto_tag_config(
+    config: AutoTagConfig,
+    monitor: State<'_, FlowMonitorState>,
+) -> Result<(), String> {
+    monitor
+        .0
+        .update_auto_tag_config(config)
+        .await
+        .map_err(|e| e.to_string())
+}
 // ============================================================================
 // 通知配置命令
 // ============================================================================
@@ -1,3 +1,4 @@
+pub mod audit_cmd;
 pub mod config_cmd;
 pub mod flow_monitor_cmd;
 pub mod injection_cmd;
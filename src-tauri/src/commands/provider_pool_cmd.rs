@@ -1,5 +1,6 @@
 //! Provider Pool Tauri 命令
 
+use crate::audit::AuditAction;
 use crate::credential::CredentialSyncService;
 use crate::database::dao::provider_pool::ProviderPoolDao;
 use crate::database::DbConnection;
@@ -7,7 +8,7 @@ use crate::models::provider_pool_model::{
     AddCredentialRequest, CredentialData, CredentialDisplay, HealthCheckResult, OAuthStatus,
     PoolProviderType, ProviderCredential, ProviderPoolOverview, UpdateCredentialRequest,
 };
-use crate::services::provider_pool_service::ProviderPoolService;
+use crate::services::provider_pool_service::{audit_credential, ProviderPoolService};
 use chrono::Utc;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -352,6 +353,7 @@ pub fn update_provider_pool_credential(
         };
 
         // 更新凭证数据
+        let before = current_credential.clone();
         let mut updated_cred = current_credential;
 
         // 更新凭证数据中的文件路径
@@ -410,6 +412,12 @@ pub fn update_provider_pool_credential(
 
         // 保存到数据库
         ProviderPoolDao::update(&conn, &updated_cred).map_err(|e| e.to_string())?;
+        audit_credential(
+            AuditAction::CredentialRotate,
+            &uuid,
+            Some(&before),
+            Some(&updated_cred),
+        );
 
         updated_cred
     } else if request.new_base_url.is_some() || request.new_api_key.is_some() {
@@ -418,6 +426,7 @@ pub fn update_provider_pool_credential(
         let mut current_credential = ProviderPoolDao::get_by_uuid(&conn, &uuid)
             .map_err(|e| e.to_string())?
             .ok_or_else(|| format!("凭证不存在: {}", uuid))?;
+        let before = current_credential.clone();
 
         // 更新 api_key 和 base_url
         match &mut current_credential.credential {
@@ -481,6 +490,12 @@ pub fn update_provider_pool_credential(
 
         // 保存到数据库
         ProviderPoolDao::update(&conn, &current_credential).map_err(|e| e.to_string())?;
+        audit_credential(
+            AuditAction::CredentialRotate,
+            &uuid,
+            Some(&before),
+            Some(&current_credential),
+        );
 
         current_credential
     } else {
@@ -38,6 +38,18 @@ pub struct RetryConfigDto {
     pub base_delay_ms: u64,
     pub max_delay_ms: u64,
     pub retryable_codes: Vec<u16>,
+    #[serde(default = "default_overload_base_delay_ms")]
+    pub overload_base_delay_ms: u64,
+    #[serde(default = "default_overload_max_delay_ms")]
+    pub overload_max_delay_ms: u64,
+}
+
+fn default_overload_base_delay_ms() -> u64 {
+    RetryConfig::default().overload_base_delay_ms
+}
+
+fn default_overload_max_delay_ms() -> u64 {
+    RetryConfig::default().overload_max_delay_ms
 }
 
 impl From<RetryConfig> for RetryConfigDto {
@@ -47,6 +59,8 @@ impl From<RetryConfig> for RetryConfigDto {
             base_delay_ms: config.base_delay_ms,
             max_delay_ms: config.max_delay_ms,
             retryable_codes: config.retryable_codes,
+            overload_base_delay_ms: config.overload_base_delay_ms,
+            overload_max_delay_ms: config.overload_max_delay_ms,
         }
     }
 }
@@ -58,6 +72,8 @@ impl From<RetryConfigDto> for RetryConfig {
             base_delay_ms: dto.base_delay_ms,
             max_delay_ms: dto.max_delay_ms,
             retryable_codes: dto.retryable_codes,
+            overload_base_delay_ms: dto.overload_base_delay_ms,
+            overload_max_delay_ms: dto.overload_max_delay_ms,
         }
     }
 }
@@ -67,6 +83,12 @@ impl From<RetryConfigDto> for RetryConfig {
 pub struct FailoverConfigDto {
     pub auto_switch: bool,
     pub switch_on_quota: bool,
+    #[serde(default = "default_true")]
+    pub switch_on_overload: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl From<FailoverConfig> for FailoverConfigDto {
@@ -74,6 +96,7 @@ impl From<FailoverConfig> for FailoverConfigDto {
         Self {
             auto_switch: config.auto_switch,
             switch_on_quota: config.switch_on_quota,
+            switch_on_overload: config.switch_on_overload,
         }
     }
 }
@@ -83,6 +106,7 @@ impl From<FailoverConfigDto> for FailoverConfig {
         Self {
             auto_switch: dto.auto_switch,
             switch_on_quota: dto.switch_on_quota,
+            switch_on_overload: dto.switch_on_overload,
         }
     }
 }
@@ -115,6 +139,12 @@ pub async fn update_retry_config(
     if config.max_delay_ms > 120000 {
         return Err("最大延迟不能超过 120 秒".to_string());
     }
+    if config.overload_max_delay_ms < config.overload_base_delay_ms {
+        return Err("过载最大延迟不能小于过载基础延迟".to_string());
+    }
+    if config.overload_max_delay_ms > 300000 {
+        return Err("过载最大延迟不能超过 300 秒".to_string());
+    }
 
     let mut retry_config = state.retry_config.write().await;
     *retry_config = RetryConfig::from(config);
@@ -61,6 +61,8 @@ impl WsServiceState {
                 active_connections: 0,
                 total_messages: 0,
                 total_errors: 0,
+                dropped_chunks: 0,
+                backpressure_events: 0,
             })),
             connections: Arc::new(RwLock::new(Vec::new())),
         }
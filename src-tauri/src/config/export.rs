@@ -289,6 +289,9 @@ impl ExportService {
 
         // 脱敏服务器 API 密钥
         redacted.server.api_key = REDACTED_PLACEHOLDER.to_string();
+        for entry in &mut redacted.server.api_keys {
+            entry.key = REDACTED_PLACEHOLDER.to_string();
+        }
 
         // 脱敏 Provider API 密钥
         if redacted.providers.openai.api_key.is_some() {
@@ -301,6 +304,25 @@ impl ExportService {
         // 脱敏凭证池中的 API Key
         redacted.credential_pool = Self::redact_credential_pool(&config.credential_pool);
 
+        // 脱敏客户端证书密码
+        let provider_tls = [
+            &mut redacted.providers.kiro.tls,
+            &mut redacted.providers.gemini.tls,
+            &mut redacted.providers.qwen.tls,
+            &mut redacted.providers.openai.tls,
+            &mut redacted.providers.claude.tls,
+        ];
+        let passwords = provider_tls
+            .into_iter()
+            .filter_map(|tls| tls.as_mut())
+            .map(|tls| &mut tls.client_cert_password)
+            .chain([&mut redacted.server.tls.client_cert_password]);
+        for password in passwords {
+            if password.is_some() {
+                *password = Some(REDACTED_PLACEHOLDER.to_string());
+            }
+        }
+
         redacted
     }
 
@@ -347,6 +369,14 @@ impl ExportService {
         if !config.server.api_key.is_empty() && config.server.api_key != REDACTED_PLACEHOLDER {
             return true;
         }
+        if config
+            .server
+            .api_keys
+            .iter()
+            .any(|entry| !entry.key.is_empty() && entry.key != REDACTED_PLACEHOLDER)
+        {
+            return true;
+        }
 
         // 检查 Provider API 密钥
         if let Some(ref key) = config.providers.openai.api_key {
@@ -632,10 +662,24 @@ mod unit_tests {
             disabled: false,
             proxy_url: None,
         });
+        config.server.tls.client_cert_password = Some("p12-secret".to_string());
+        config.providers.kiro.tls = Some(crate::config::ClientTlsConfig {
+            client_cert_password: Some("p12-secret".to_string()),
+            ..Default::default()
+        });
 
         let redacted = ExportService::redact_config(&config);
 
         assert_eq!(redacted.server.api_key, REDACTED_PLACEHOLDER);
+        assert_eq!(
+            redacted.server.tls.client_cert_password,
+            Some(REDACTED_PLACEHOLDER.to_string())
+        );
+        let kiro_tls = redacted.providers.kiro.tls.as_ref().unwrap();
+        assert_eq!(
+            kiro_tls.client_cert_password,
+            Some(REDACTED_PLACEHOLDER.to_string())
+        );
         assert_eq!(
             redacted.providers.openai.api_key,
             Some(REDACTED_PLACEHOLDER.to_string())
@@ -3,17 +3,24 @@
 //! 提供配置文件监控和热重载功能
 //! - 使用 `notify` crate 监控配置文件变化
 //! - 支持原子性配置更新
+//! - 先验证后应用：验证失败的配置会被拒绝，之前的配置保持生效
 //! - 失败时自动回滚到之前的配置
+//! - 按配置分区比较新旧配置，订阅者只需应用发生变化的部分
 
-use super::types::{is_default_api_key, Config};
+use super::import::{ImportService, ValidationResult};
+use super::types::{is_default_api_key, Config, ServerConfig, TlsConfig};
 use super::yaml::ConfigManager;
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use parking_lot::RwLock;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc};
+
+/// 配置分区变更通知通道容量
+const SECTION_CHANNEL_CAPACITY: usize = 16;
 
 /// 热重载错误类型
 #[derive(Debug, Clone)]
@@ -53,6 +60,15 @@ pub enum ReloadResult {
     Success {
         /// 重载时间戳
         timestamp: Instant,
+        /// 发生变化的配置分区（为空表示内容未变）
+        changed_sections: Vec<ConfigSection>,
+    },
+    /// 新配置未通过验证，已拒绝（之前的配置保持生效）
+    Rejected {
+        /// 验证结果（包含全部验证错误）
+        validation: ValidationResult,
+        /// 拒绝时间戳
+        timestamp: Instant,
     },
     /// 重载失败，已回滚
     RolledBack {
@@ -94,6 +110,55 @@ pub enum ConfigChangeKind {
     Removed,
 }
 
+/// 配置分区
+///
+/// 热重载时按分区比较新旧配置，订阅者只需重建受影响的组件，
+/// 例如仅路由变化时不必重建 Provider 凭证池和上游连接。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigSection {
+    /// 服务器配置（监听地址、API Key、gRPC、OTLP）和远程管理，不含 TLS
+    Server,
+    /// TLS 配置（`server.tls`，含上游客户端证书）
+    Tls,
+    /// Provider 配置、默认 Provider、凭证池、认证目录和配额超限策略
+    Providers,
+    /// 路由配置和端点 Provider 配置
+    Routing,
+    /// 重试配置
+    Retry,
+    /// 日志配置
+    Logging,
+    /// 参数注入配置
+    Injection,
+    /// 全局代理和不走代理的主机列表
+    Proxy,
+    /// 其余配置（Amp CLI、Flow 插件、故障注入、内容过滤、影子镜像、流式响应、托盘行为）
+    Other,
+}
+
+impl ConfigSection {
+    /// 分区名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigSection::Server => "server",
+            ConfigSection::Tls => "tls",
+            ConfigSection::Providers => "providers",
+            ConfigSection::Routing => "routing",
+            ConfigSection::Retry => "retry",
+            ConfigSection::Logging => "logging",
+            ConfigSection::Injection => "injection",
+            ConfigSection::Proxy => "proxy",
+            ConfigSection::Other => "other",
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// 文件监控器
 ///
 /// 监控配置文件变化并触发回调
@@ -238,6 +303,8 @@ pub struct HotReloadManager {
     last_reload: Arc<RwLock<Option<Instant>>>,
     /// 重载状态
     reload_in_progress: Arc<AtomicBool>,
+    /// 配置分区变更通知
+    section_tx: broadcast::Sender<Vec<ConfigSection>>,
 }
 
 impl HotReloadManager {
@@ -249,9 +316,85 @@ impl HotReloadManager {
             config_path,
             last_reload: Arc::new(RwLock::new(None)),
             reload_in_progress: Arc::new(AtomicBool::new(false)),
+            section_tx: broadcast::channel(SECTION_CHANNEL_CAPACITY).0,
         }
     }
 
+    /// 订阅配置分区变更
+    ///
+    /// 每次配置生效且内容有变化时，发送发生变化的分区列表；
+    /// 新配置可通过 [`HotReloadManager::config`] 读取。
+    pub fn subscribe(&self) -> broadcast::Receiver<Vec<ConfigSection>> {
+        self.section_tx.subscribe()
+    }
+
+    /// 比较新旧配置，返回发生变化的分区（按 [`ConfigSection`] 声明顺序）
+    pub fn changed_sections(old: &Config, new: &Config) -> Vec<ConfigSection> {
+        let without_tls = |config: &Config| ServerConfig {
+            tls: TlsConfig::default(),
+            ..config.server.clone()
+        };
+
+        let checks = [
+            (
+                ConfigSection::Server,
+                without_tls(old) != without_tls(new)
+                    || old.remote_management != new.remote_management,
+            ),
+            (ConfigSection::Tls, old.server.tls != new.server.tls),
+            (
+                ConfigSection::Providers,
+                old.providers != new.providers
+                    || old.default_provider != new.default_provider
+                    || old.credential_pool != new.credential_pool
+                    || old.auth_dir != new.auth_dir
+                    || old.quota_exceeded != new.quota_exceeded,
+            ),
+            (
+                ConfigSection::Routing,
+                old.routing != new.routing || old.endpoint_providers != new.endpoint_providers,
+            ),
+            (ConfigSection::Retry, old.retry != new.retry),
+            (ConfigSection::Logging, old.logging != new.logging),
+            (ConfigSection::Injection, old.injection != new.injection),
+            (
+                ConfigSection::Proxy,
+                old.proxy_url != new.proxy_url || old.no_proxy != new.no_proxy,
+            ),
+            (
+                ConfigSection::Other,
+                old.ampcode != new.ampcode
+                    || old.flow_plugins != new.flow_plugins
+                    || old.chaos != new.chaos
+                    || old.content_filter != new.content_filter
+                    || old.shadow != new.shadow
+                    || old.streaming != new.streaming
+                    || old.minimize_to_tray != new.minimize_to_tray,
+            ),
+        ];
+
+        checks
+            .into_iter()
+            .filter_map(|(section, changed)| changed.then_some(section))
+            .collect()
+    }
+
+    /// 替换当前配置并通知订阅者，返回发生变化的分区
+    fn apply_config(&self, config: Config) -> Vec<ConfigSection> {
+        let changed = {
+            let mut current = self.current_config.write();
+            let changed = Self::changed_sections(&current, &config);
+            *current = config;
+            changed
+        };
+
+        if !changed.is_empty() {
+            // 没有订阅者时发送失败，可以忽略
+            let _ = self.section_tx.send(changed.clone());
+        }
+        changed
+    }
+
     /// 获取当前配置
     pub fn config(&self) -> Config {
         self.current_config.read().clone()
@@ -308,9 +451,9 @@ impl HotReloadManager {
             *backup = Some(current);
         }
 
-        // 2. 尝试加载新配置
-        let new_config = match self.load_config_from_file() {
-            Ok(config) => config,
+        // 2. 读取候选配置
+        let content = match self.read_config_file() {
+            Ok(content) => content,
             Err(e) => {
                 // 加载失败，清除备份（无需回滚，因为当前配置未变）
                 let mut backup = self.backup_config.write();
@@ -322,22 +465,22 @@ impl HotReloadManager {
             }
         };
 
-        // 3. 验证新配置
-        if let Err(e) = self.validate_config(&new_config) {
-            // 验证失败，清除备份
-            let mut backup = self.backup_config.write();
-            *backup = None;
-            return ReloadResult::RolledBack {
-                error: e.to_string(),
-                timestamp: now,
-            };
-        }
+        // 3. 验证新配置，未通过时拒绝并保留当前配置
+        let new_config = match Self::check_candidate(&content) {
+            Ok(config) => config,
+            Err(validation) => {
+                let mut backup = self.backup_config.write();
+                *backup = None;
+                tracing::warn!("配置热重载被拒绝: {}", validation.errors.join("; "));
+                return ReloadResult::Rejected {
+                    validation,
+                    timestamp: now,
+                };
+            }
+        };
 
-        // 4. 原子性地应用新配置
-        {
-            let mut current = self.current_config.write();
-            *current = new_config;
-        }
+        // 4. 原子性地应用新配置，并通知发生变化的分区
+        let changed_sections = self.apply_config(new_config);
 
         // 5. 更新最后重载时间
         {
@@ -351,12 +494,22 @@ impl HotReloadManager {
             *backup = None;
         }
 
-        tracing::info!("配置热重载成功");
-        ReloadResult::Success { timestamp: now }
+        tracing::info!(
+            "配置热重载成功，变更分区: {}",
+            changed_sections
+                .iter()
+                .map(ConfigSection::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        ReloadResult::Success {
+            timestamp: now,
+            changed_sections,
+        }
     }
 
-    /// 从文件加载配置
-    fn load_config_from_file(&self) -> Result<Config, HotReloadError> {
+    /// 读取配置文件内容
+    fn read_config_file(&self) -> Result<String, HotReloadError> {
         if !self.config_path.exists() {
             return Err(HotReloadError::LoadError(format!(
                 "配置文件不存在: {:?}",
@@ -364,76 +517,54 @@ impl HotReloadManager {
             )));
         }
 
-        let content = std::fs::read_to_string(&self.config_path)
-            .map_err(|e| HotReloadError::LoadError(e.to_string()))?;
-
-        ConfigManager::parse_yaml(&content).map_err(|e| HotReloadError::LoadError(e.to_string()))
+        std::fs::read_to_string(&self.config_path)
+            .map_err(|e| HotReloadError::LoadError(e.to_string()))
     }
 
-    /// 验证配置
-    fn validate_config(&self, config: &Config) -> Result<(), HotReloadError> {
-        let is_localhost = is_localhost_host(&config.server.host);
-
-        // 验证端口范围
-        if config.server.port == 0 {
-            return Err(HotReloadError::ValidationError(
-                "端口号不能为 0".to_string(),
-            ));
-        }
-
-        if !is_localhost {
-            return Err(HotReloadError::ValidationError(
-                "当前版本仅支持本地监听，请使用 127.0.0.1/localhost/::1".to_string(),
-            ));
-        }
-
-        // 验证重试配置
-        if config.retry.max_retries > 100 {
-            return Err(HotReloadError::ValidationError(
-                "最大重试次数不能超过 100".to_string(),
-            ));
-        }
-
-        if config.retry.base_delay_ms == 0 {
-            return Err(HotReloadError::ValidationError(
-                "基础延迟不能为 0".to_string(),
-            ));
+    /// 验证候选配置文件（不应用）
+    pub fn validate_file(path: &Path) -> ValidationResult {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Self::validate_content(&content),
+            Err(e) => ValidationResult::invalid(format!("无法读取配置文件 {:?}: {}", path, e)),
         }
+    }
 
-        // 验证日志保留天数
-        if config.logging.retention_days == 0 {
-            return Err(HotReloadError::ValidationError(
-                "日志保留天数不能为 0".to_string(),
-            ));
+    /// 验证候选配置内容（不应用）
+    pub fn validate_content(content: &str) -> ValidationResult {
+        match Self::check_candidate(content) {
+            Ok(_) => {
+                let mut result = ValidationResult::valid();
+                result.has_config = true;
+                result.version = Some("yaml".to_string());
+                result
+            }
+            Err(result) => result,
         }
+    }
 
-        if config.server.api_key.trim().is_empty() {
-            return Err(HotReloadError::ValidationError(
-                "API Key 不能为空".to_string(),
-            ));
+    /// 验证候选配置，通过时返回解析后的配置
+    fn check_candidate(content: &str) -> Result<Config, ValidationResult> {
+        let mut result = ImportService::validate(content);
+        if !result.valid {
+            return Err(result);
         }
 
-        if (!is_localhost || config.remote_management.allow_remote)
-            && is_default_api_key(&config.server.api_key)
-        {
-            return Err(HotReloadError::ValidationError(
-                "非本地访问场景下禁止使用默认 API Key，请设置强口令".to_string(),
-            ));
-        }
+        let config = match ConfigManager::parse_yaml(content) {
+            Ok(config) => config,
+            Err(e) => return Err(ValidationResult::invalid(e.to_string())),
+        };
 
-        if config.server.tls.enable {
-            return Err(HotReloadError::ValidationError(
-                "当前版本暂不支持 TLS，请关闭 TLS 配置".to_string(),
-            ));
+        // 拼写错误的顶层配置项会被忽略并回落到默认值，这里显式拒绝
+        for key in unknown_top_level_keys(content) {
+            result.add_error(format!("未知的配置项: {}", key));
         }
+        validate_config(&config, &mut result);
 
-        if config.remote_management.allow_remote {
-            return Err(HotReloadError::ValidationError(
-                "当前版本未启用 TLS，禁止开启远程管理".to_string(),
-            ));
+        if result.valid {
+            Ok(config)
+        } else {
+            Err(result)
         }
-
-        Ok(())
     }
 
     /// 手动回滚到备份配置
@@ -445,8 +576,7 @@ impl HotReloadManager {
 
         match backup {
             Some(config) => {
-                let mut current = self.current_config.write();
-                *current = config;
+                self.apply_config(config);
 
                 // 清除备份
                 let mut backup = self.backup_config.write();
@@ -463,8 +593,7 @@ impl HotReloadManager {
 
     /// 更新配置（用于外部更新）
     pub fn update_config(&self, config: Config) {
-        let mut current = self.current_config.write();
-        *current = config;
+        self.apply_config(config);
     }
 
     /// 获取配置文件路径
@@ -473,6 +602,101 @@ impl HotReloadManager {
     }
 }
 
+/// 验证配置语义，收集全部错误
+fn validate_config(config: &Config, result: &mut ValidationResult) {
+    let is_localhost = is_localhost_host(&config.server.host);
+
+    // 验证端口范围
+    if config.server.port == 0 {
+        result.add_error("端口号不能为 0");
+    }
+
+    if !is_localhost {
+        result.add_error("当前版本仅支持本地监听，请使用 127.0.0.1/localhost/::1");
+    }
+
+    // 验证重试配置
+    if config.retry.max_retries > 100 {
+        result.add_error("最大重试次数不能超过 100");
+    }
+
+    if config.retry.base_delay_ms == 0 {
+        result.add_error("基础延迟不能为 0");
+    }
+
+    // 验证日志保留天数
+    if config.logging.retention_days == 0 {
+        result.add_error("日志保留天数不能为 0");
+    }
+
+    if config.server.api_key.trim().is_empty() {
+        result.add_error("API Key 不能为空");
+    }
+
+    if (!is_localhost || config.remote_management.allow_remote)
+        && is_default_api_key(&config.server.api_key)
+    {
+        result.add_error("非本地访问场景下禁止使用默认 API Key，请设置强口令");
+    }
+
+    if config.server.tls.enable {
+        result.add_error("当前版本暂不支持 TLS，请关闭 TLS 配置");
+    }
+
+    if config.remote_management.allow_remote {
+        result.add_error("当前版本未启用 TLS，禁止开启远程管理");
+    }
+
+    // 验证代理 URL、上游客户端证书和附加请求头
+    for error in crate::proxy::validate_proxy_settings(config)
+        .into_iter()
+        .chain(crate::proxy::validate_client_tls_settings(config))
+        .chain(crate::proxy::validate_extra_headers(config))
+    {
+        result.add_error(error);
+    }
+
+    // 验证内容过滤规则（正则必须可编译）
+    for error in config.content_filter.validate() {
+        result.add_error(error);
+    }
+}
+
+/// `Config` 的顶层配置项
+///
+/// 由默认配置序列化后的对象键推导，默认值不序列化的配置项先填入占位值。
+fn known_top_level_keys() -> &'static HashSet<String> {
+    static KEYS: OnceLock<HashSet<String>> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let config = Config {
+            proxy_url: Some(String::new()),
+            no_proxy: vec![String::new()],
+            ..Config::default()
+        };
+        match serde_json::to_value(&config) {
+            Ok(serde_json::Value::Object(fields)) => fields.into_iter().map(|(k, _)| k).collect(),
+            _ => HashSet::new(),
+        }
+    })
+}
+
+/// 找出配置内容中无法识别的顶层配置项
+///
+/// 不在 [`known_top_level_keys`] 中的非空顶层键即为未知项。
+fn unknown_top_level_keys(content: &str) -> Vec<String> {
+    let Ok(serde_yaml::Value::Mapping(original)) = serde_yaml::from_str(content) else {
+        return Vec::new();
+    };
+
+    original
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .filter_map(|(key, _)| key.as_str())
+        .filter(|key| !known_top_level_keys().contains(*key))
+        .map(|key| key.to_string())
+        .collect()
+}
+
 fn is_localhost_host(host: &str) -> bool {
     if host == "localhost" {
         return true;
@@ -497,6 +721,7 @@ pub struct HotReloadStatus {
 
 #[cfg(test)]
 mod unit_tests {
+    use super::super::types::RoutingRuleConfig;
     use super::*;
     use std::io::Write;
     use tempfile::NamedTempFile;
@@ -588,11 +813,12 @@ logging:
 
         let result = manager.reload();
         match result {
-            ReloadResult::RolledBack { .. } => {
+            ReloadResult::Rejected { validation, .. } => {
+                assert!(!validation.valid);
                 // 配置应该保持不变
                 assert_eq!(manager.config(), config);
             }
-            _ => panic!("Expected RolledBack result"),
+            _ => panic!("Expected Rejected result"),
         }
     }
 
@@ -613,13 +839,138 @@ server:
 
         let result = manager.reload();
         match result {
-            ReloadResult::RolledBack { error, .. } => {
-                assert!(error.contains("端口号"));
+            ReloadResult::Rejected { validation, .. } => {
+                assert!(validation.errors.iter().any(|e| e.contains("端口号")));
                 // 配置应该保持不变
                 assert_eq!(manager.config(), config);
             }
-            _ => panic!("Expected RolledBack result"),
+            _ => panic!("Expected Rejected result"),
+        }
+    }
+
+    #[test]
+    fn test_hot_reload_rejects_unknown_top_level_keys() {
+        // `sever` 是 `server` 的拼写错误，不应静默回落到默认配置
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file
+            .write_all(b"sever:\n  host: \"127.0.0.1\"\n  port: 9000\n")
+            .unwrap();
+
+        let config = Config::default();
+        let manager = HotReloadManager::new(config.clone(), temp_file.path().to_path_buf());
+
+        match manager.reload() {
+            ReloadResult::Rejected { validation, .. } => {
+                assert_eq!(validation.errors, vec!["未知的配置项: sever".to_string()]);
+                assert_eq!(manager.config(), config);
+            }
+            other => panic!("Expected Rejected result, got {:?}", other),
+        }
+
+        // 默认不序列化的配置项也是已知项
+        let validation = HotReloadManager::validate_content("no_proxy: []\nproxy_url: null\n");
+        assert!(validation.valid, "{:?}", validation.errors);
+    }
+
+    #[test]
+    fn test_known_top_level_keys_cover_config_fields() {
+        let mut config = Config::default();
+        config.proxy_url = Some("http://127.0.0.1:7890".to_string());
+        config.no_proxy = vec!["localhost".to_string()];
+        let serde_yaml::Value::Mapping(fields) = serde_yaml::to_value(&config).unwrap() else {
+            panic!("Config should serialize to a mapping");
+        };
+        let keys = known_top_level_keys();
+        for key in fields.keys() {
+            let key = key.as_str().unwrap();
+            assert!(keys.contains(key), "missing {}", key);
+        }
+        assert_eq!(fields.len(), keys.len());
+    }
+
+    #[test]
+    fn test_validate_file_does_not_apply() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file
+            .write_all(b"server:\n  port: 0\nretry:\n  base_delay_ms: 0\n")
+            .unwrap();
+
+        let validation = HotReloadManager::validate_file(temp_file.path());
+        assert!(!validation.valid);
+        assert_eq!(validation.errors.len(), 2);
+
+        let validation = HotReloadManager::validate_content(
+            &ConfigManager::to_yaml(&Config::default()).unwrap(),
+        );
+        assert!(validation.valid, "{:?}", validation.errors);
+    }
+
+    #[test]
+    fn test_changed_sections_routing_only() {
+        let old = Config::default();
+        let mut new = old.clone();
+        new.routing
+            .model_aliases
+            .insert("fast".to_string(), "claude-haiku".to_string());
+        new.routing.rules.push(RoutingRuleConfig {
+            pattern: "gpt-*".to_string(),
+            provider: "openai".to_string(),
+            priority: 10,
+        });
+
+        assert_eq!(
+            HotReloadManager::changed_sections(&old, &new),
+            vec![ConfigSection::Routing]
+        );
+        assert!(HotReloadManager::changed_sections(&old, &old).is_empty());
+
+        // TLS 与其余服务器配置分开上报
+        let mut new = old.clone();
+        new.server.tls.ca_bundle_path = Some("/tmp/ca.pem".to_string());
+        new.logging.retention_days = 30;
+        assert_eq!(
+            HotReloadManager::changed_sections(&old, &new),
+            vec![ConfigSection::Tls, ConfigSection::Logging]
+        );
+    }
+
+    #[test]
+    fn test_reload_reports_only_routing_section() {
+        let config = Config::default();
+        let mut edited = config.clone();
+        edited
+            .routing
+            .model_aliases
+            .insert("fast".to_string(), "claude-haiku".to_string());
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file
+            .write_all(ConfigManager::to_yaml(&edited).unwrap().as_bytes())
+            .unwrap();
+
+        let manager = HotReloadManager::new(config, temp_file.path().to_path_buf());
+        let mut sections_rx = manager.subscribe();
+
+        match manager.reload() {
+            ReloadResult::Success {
+                changed_sections, ..
+            } => assert_eq!(changed_sections, vec![ConfigSection::Routing]),
+            other => panic!("Expected Success result, got {:?}", other),
+        }
+        assert_eq!(
+            sections_rx.try_recv().unwrap(),
+            vec![ConfigSection::Routing]
+        );
+        assert_eq!(manager.config(), edited);
+
+        // 再次重载相同内容：没有分区变化，也不通知订阅者
+        match manager.reload() {
+            ReloadResult::Success {
+                changed_sections, ..
+            } => assert!(changed_sections.is_empty()),
+            other => panic!("Expected Success result, got {:?}", other),
         }
+        assert!(sections_rx.try_recv().is_err());
     }
 
     #[test]
@@ -4,6 +4,7 @@
 //! - YAML 配置导入
 //! - 完整导入包导入（配置 + 凭证 + OAuth Token 文件）
 //! - 导入验证（格式、版本、脱敏状态）
+//! - 演练模式：列出计划变更和冲突，不修改任何状态
 //! - 合并和替换模式
 
 use super::export::{base64_decode, ExportBundle, REDACTED_PLACEHOLDER};
@@ -11,6 +12,7 @@ use super::path_utils::expand_tilde;
 use super::types::{ApiKeyEntry, Config, CredentialEntry, CredentialPoolConfig};
 use super::yaml::{ConfigError, ConfigManager, YamlService};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::{Component, Path};
 
 /// 导入选项
@@ -18,23 +20,89 @@ use std::path::{Component, Path};
 pub struct ImportOptions {
     /// 是否合并（false 则替换）
     pub merge: bool,
+    /// 导入前先演练，存在冲突或错误时中止导入
+    #[serde(default)]
+    pub fail_on_conflict: bool,
 }
 
 impl Default for ImportOptions {
     fn default() -> Self {
-        Self { merge: true }
+        Self::merge()
     }
 }
 
 impl ImportOptions {
     /// 创建合并模式选项
     pub fn merge() -> Self {
-        Self { merge: true }
+        Self {
+            merge: true,
+            fail_on_conflict: false,
+        }
     }
 
     /// 创建替换模式选项
     pub fn replace() -> Self {
-        Self { merge: false }
+        Self {
+            merge: false,
+            fail_on_conflict: false,
+        }
+    }
+
+    /// 存在冲突时中止导入
+    pub fn with_fail_on_conflict(mut self) -> Self {
+        self.fail_on_conflict = true;
+        self
+    }
+}
+
+/// 计划变更的条目类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportItemKind {
+    /// 凭证池条目
+    Credential,
+    /// OAuth Token 文件
+    TokenFile,
+    /// 路由规则
+    RoutingRule,
+    /// 模型别名映射
+    ModelMapping,
+}
+
+/// 计划变更的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    /// 新增
+    Added,
+    /// 覆盖现有条目
+    Updated,
+    /// 与现有条目相同
+    Unchanged,
+    /// 导入包中存在但不会被导入（脱敏、路径不安全等）
+    Skipped,
+    /// 现有条目将被移除（替换模式）
+    Removed,
+}
+
+/// 导入演练中的单条计划变更
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedChange {
+    /// 条目类型
+    pub kind: ImportItemKind,
+    /// 条目名称（如 `kiro/main`、`gpt-*`、Token 文件相对路径）
+    pub name: String,
+    /// 动作
+    pub action: ImportAction,
+}
+
+impl PlannedChange {
+    fn new(kind: ImportItemKind, name: impl Into<String>, action: ImportAction) -> Self {
+        Self {
+            kind,
+            name: name.into(),
+            action,
+        }
     }
 }
 
@@ -55,6 +123,12 @@ pub struct ValidationResult {
     pub errors: Vec<String>,
     /// 警告信息列表
     pub warnings: Vec<String>,
+    /// 计划变更（仅演练模式填充）
+    #[serde(default)]
+    pub changes: Vec<PlannedChange>,
+    /// 冲突列表（仅演练模式填充，每条冲突同时记为错误）
+    #[serde(default)]
+    pub conflicts: Vec<String>,
 }
 
 impl ValidationResult {
@@ -68,6 +142,8 @@ impl ValidationResult {
             has_credentials: false,
             errors: Vec::new(),
             warnings: Vec::new(),
+            changes: Vec::new(),
+            conflicts: Vec::new(),
         }
     }
 
@@ -81,6 +157,8 @@ impl ValidationResult {
             has_credentials: false,
             errors: vec![error.into()],
             warnings: Vec::new(),
+            changes: Vec::new(),
+            conflicts: Vec::new(),
         }
     }
 
@@ -94,6 +172,13 @@ impl ValidationResult {
     pub fn add_warning(&mut self, warning: impl Into<String>) {
         self.warnings.push(warning.into());
     }
+
+    /// 添加冲突（同时记为错误）
+    pub fn add_conflict(&mut self, conflict: impl Into<String>) {
+        let conflict = conflict.into();
+        self.add_error(conflict.clone());
+        self.conflicts.push(conflict);
+    }
 }
 
 /// 导入结果
@@ -254,6 +339,12 @@ impl ImportService {
         current_config: &Config,
         options: &ImportOptions,
     ) -> Result<ImportResult, ImportError> {
+        if options.fail_on_conflict {
+            let mut bundle = ExportBundle::new(env!("CARGO_PKG_VERSION"));
+            bundle.config_yaml = Some(yaml.to_string());
+            Self::ensure_no_conflicts(&bundle, current_config, options, &current_config.auth_dir)?;
+        }
+
         // 解析 YAML
         let imported_config = ConfigManager::parse_yaml(yaml)?;
 
@@ -284,6 +375,10 @@ impl ImportService {
         options: &ImportOptions,
         auth_dir: &str,
     ) -> Result<ImportResult, ImportError> {
+        if options.fail_on_conflict {
+            Self::ensure_no_conflicts(bundle, current_config, options, auth_dir)?;
+        }
+
         let mut warnings = Vec::new();
 
         // 检查脱敏状态
@@ -291,7 +386,81 @@ impl ImportService {
             warnings.push("导出包已脱敏，凭证数据将使用占位符".to_string());
         }
 
-        // 导入配置
+        let (config, server_key_cleared) = Self::build_config(bundle, current_config, options)?;
+
+        // 恢复 OAuth token 文件
+        if !bundle.token_files.is_empty() {
+            let token_warnings = Self::restore_token_files(&bundle.token_files, auth_dir)?;
+            warnings.extend(token_warnings);
+        }
+
+        if server_key_cleared {
+            warnings.push("检测到脱敏的服务器 API Key，已清空，需要手动设置".to_string());
+        }
+
+        Ok(ImportResult::success_with_warnings(config, warnings))
+    }
+
+    /// 演练导入导出包，不修改任何状态
+    ///
+    /// 与 [`ImportService::import`] 使用相同的合并逻辑计算导入后的配置，
+    /// 列出凭证、Token 文件、路由规则和模型别名的计划变更，并报告冲突：
+    /// - 覆盖已存在且内容不同的凭证或 Token 文件
+    /// - 导入后同一凭证池中出现重复的凭证 ID
+    /// - 导入后存在模式和优先级相同但目标 Provider 不同的路由规则
+    pub fn validate_only(
+        bundle: &ExportBundle,
+        current_config: &Config,
+        options: &ImportOptions,
+        auth_dir: &str,
+    ) -> ValidationResult {
+        let mut result = Self::validate_bundle(bundle);
+        if !result.valid {
+            return result;
+        }
+
+        let (config, _) = match Self::build_config(bundle, current_config, options) {
+            Ok(built) => built,
+            Err(e) => {
+                result.add_error(e.to_string());
+                return result;
+            }
+        };
+        let imported = bundle
+            .config_yaml
+            .as_deref()
+            .and_then(|yaml| ConfigManager::parse_yaml(yaml).ok());
+
+        Self::plan_credentials(current_config, imported.as_ref(), &config, &mut result);
+        Self::plan_routing(current_config, &config, &mut result);
+        Self::plan_token_files(&bundle.token_files, auth_dir, &mut result);
+
+        result
+    }
+
+    /// 演练导入，存在冲突或错误时返回验证错误
+    fn ensure_no_conflicts(
+        bundle: &ExportBundle,
+        current_config: &Config,
+        options: &ImportOptions,
+        auth_dir: &str,
+    ) -> Result<(), ImportError> {
+        let result = Self::validate_only(bundle, current_config, options, auth_dir);
+        if result.valid {
+            Ok(())
+        } else {
+            Err(ImportError::ValidationError(result.errors.join("; ")))
+        }
+    }
+
+    /// 计算导入后的配置（不恢复 Token 文件）
+    ///
+    /// 返回导入后的配置，以及服务器 API Key 是否因脱敏被清空。
+    fn build_config(
+        bundle: &ExportBundle,
+        current_config: &Config,
+        options: &ImportOptions,
+    ) -> Result<(Config, bool), ImportError> {
         let mut config = if let Some(ref yaml) = bundle.config_yaml {
             let imported = ConfigManager::parse_yaml(yaml)?;
             if options.merge {
@@ -305,21 +474,196 @@ impl ImportService {
             Config::default()
         };
 
-        // 恢复 OAuth token 文件
-        if !bundle.token_files.is_empty() {
-            let token_warnings = Self::restore_token_files(&bundle.token_files, auth_dir)?;
-            warnings.extend(token_warnings);
+        // 如果是脱敏数据，清理凭证池中的占位符
+        let server_key_cleared = bundle.redacted && Self::clean_redacted_credentials(&mut config);
+
+        Ok((config, server_key_cleared))
+    }
+
+    /// 列出凭证池的计划变更
+    fn plan_credentials(
+        current: &Config,
+        imported: Option<&Config>,
+        planned: &Config,
+        result: &mut ValidationResult,
+    ) {
+        let empty = CredentialPoolConfig::default();
+        let (cur, imp, new) = (
+            &current.credential_pool,
+            imported.map_or(&empty, |c| &c.credential_pool),
+            &planned.credential_pool,
+        );
+
+        plan_entries("kiro", &cur.kiro, &imp.kiro, &new.kiro, |e| &e.id, result);
+        plan_entries(
+            "gemini",
+            &cur.gemini,
+            &imp.gemini,
+            &new.gemini,
+            |e| &e.id,
+            result,
+        );
+        plan_entries("qwen", &cur.qwen, &imp.qwen, &new.qwen, |e| &e.id, result);
+        plan_entries(
+            "openai",
+            &cur.openai,
+            &imp.openai,
+            &new.openai,
+            |e| &e.id,
+            result,
+        );
+        plan_entries(
+            "claude",
+            &cur.claude,
+            &imp.claude,
+            &new.claude,
+            |e| &e.id,
+            result,
+        );
+        plan_entries(
+            "gemini_api_keys",
+            &cur.gemini_api_keys,
+            &imp.gemini_api_keys,
+            &new.gemini_api_keys,
+            |e| &e.id,
+            result,
+        );
+        plan_entries(
+            "vertex_api_keys",
+            &cur.vertex_api_keys,
+            &imp.vertex_api_keys,
+            &new.vertex_api_keys,
+            |e| &e.id,
+            result,
+        );
+        plan_entries(
+            "codex",
+            &cur.codex,
+            &imp.codex,
+            &new.codex,
+            |e| &e.id,
+            result,
+        );
+        plan_entries(
+            "iflow",
+            &cur.iflow,
+            &imp.iflow,
+            &new.iflow,
+            |e| &e.id,
+            result,
+        );
+    }
+
+    /// 列出路由规则和模型别名的计划变更
+    fn plan_routing(current: &Config, planned: &Config, result: &mut ValidationResult) {
+        let (cur, new) = (&current.routing, &planned.routing);
+
+        // 路由规则按模型模式对应
+        for rule in &new.rules {
+            let action = match cur.rules.iter().find(|r| r.pattern == rule.pattern) {
+                None => ImportAction::Added,
+                Some(existing) if existing == rule => ImportAction::Unchanged,
+                Some(_) => ImportAction::Updated,
+            };
+            result.changes.push(PlannedChange::new(
+                ImportItemKind::RoutingRule,
+                &rule.pattern,
+                action,
+            ));
+        }
+        for rule in &cur.rules {
+            if !new.rules.iter().any(|r| r.pattern == rule.pattern) {
+                result.changes.push(PlannedChange::new(
+                    ImportItemKind::RoutingRule,
+                    &rule.pattern,
+                    ImportAction::Removed,
+                ));
+            }
         }
 
-        // 如果是脱敏数据，清理凭证池中的占位符
-        if bundle.redacted {
-            let server_key_cleared = Self::clean_redacted_credentials(&mut config);
-            if server_key_cleared {
-                warnings.push("检测到脱敏的服务器 API Key，已清空，需要手动设置".to_string());
+        // 模式和优先级相同时无法确定命中哪个 Provider
+        for (i, rule) in new.rules.iter().enumerate() {
+            if let Some(other) = new.rules[..i].iter().find(|r| {
+                r.pattern == rule.pattern
+                    && r.priority == rule.priority
+                    && r.provider != rule.provider
+            }) {
+                result.add_conflict(format!(
+                    "路由规则 {} 重叠：优先级 {} 同时指向 {} 和 {}",
+                    rule.pattern, rule.priority, other.provider, rule.provider
+                ));
             }
         }
 
-        Ok(ImportResult::success_with_warnings(config, warnings))
+        // 模型别名（按别名排序，保证输出稳定）
+        let mut aliases: Vec<&String> = new.model_aliases.keys().collect();
+        aliases.sort();
+        for alias in aliases {
+            let action = match cur.model_aliases.get(alias) {
+                None => ImportAction::Added,
+                Some(model) if *model == new.model_aliases[alias] => ImportAction::Unchanged,
+                Some(_) => ImportAction::Updated,
+            };
+            result.changes.push(PlannedChange::new(
+                ImportItemKind::ModelMapping,
+                alias,
+                action,
+            ));
+        }
+        let mut removed: Vec<&String> = cur
+            .model_aliases
+            .keys()
+            .filter(|alias| !new.model_aliases.contains_key(*alias))
+            .collect();
+        removed.sort();
+        for alias in removed {
+            result.changes.push(PlannedChange::new(
+                ImportItemKind::ModelMapping,
+                alias,
+                ImportAction::Removed,
+            ));
+        }
+    }
+
+    /// 列出 Token 文件的计划变更（只读取 auth_dir，不写入）
+    fn plan_token_files(
+        token_files: &HashMap<String, String>,
+        auth_dir: &str,
+        result: &mut ValidationResult,
+    ) {
+        let auth_path = expand_tilde(auth_dir);
+        let mut paths: Vec<&String> = token_files.keys().collect();
+        paths.sort();
+
+        for relative_path in paths {
+            let rel = Path::new(relative_path);
+            let content = base64_decode(&token_files[relative_path]).ok();
+            let action = match content {
+                _ if !is_safe_relative_path(rel) || has_symlink_in_prefix(&auth_path, rel) => {
+                    ImportAction::Skipped
+                }
+                None => ImportAction::Skipped,
+                Some(ref content) if content == REDACTED_PLACEHOLDER.as_bytes() => {
+                    ImportAction::Skipped
+                }
+                Some(ref content) => match std::fs::read(auth_path.join(rel)) {
+                    Err(_) => ImportAction::Added,
+                    Ok(existing) if existing == *content => ImportAction::Unchanged,
+                    Ok(_) => {
+                        result.add_conflict(format!(
+                            "Token 文件 {} 已存在且内容不同，导入将覆盖",
+                            relative_path
+                        ));
+                        ImportAction::Updated
+                    }
+                },
+            };
+            result.changes.push(PlannedChange::new(
+                ImportItemKind::TokenFile,
+                relative_path,
+                action,
+            ));
+        }
     }
 
     /// 合并配置
@@ -429,40 +773,12 @@ impl ImportService {
     /// # Returns
     /// * `Ok(Vec<String>)` - 警告信息列表
     fn restore_token_files(
-        token_files: &std::collections::HashMap<String, String>,
+        token_files: &HashMap<String, String>,
         auth_dir: &str,
     ) -> Result<Vec<String>, ImportError> {
         let mut warnings = Vec::new();
         let auth_path = expand_tilde(auth_dir);
 
-        fn is_safe_relative_path(rel: &Path) -> bool {
-            if rel.as_os_str().is_empty() || rel.is_absolute() {
-                return false;
-            }
-
-            rel.components().all(|c| match c {
-                Component::Normal(_) => true,
-                Component::CurDir => true,
-                Component::ParentDir | Component::RootDir | Component::Prefix(_) => false,
-            })
-        }
-
-        fn has_symlink_in_prefix(base: &Path, rel: &Path) -> bool {
-            let mut current = base.to_path_buf();
-            for c in rel.components() {
-                let Component::Normal(part) = c else {
-                    continue;
-                };
-                current.push(part);
-                if let Ok(meta) = std::fs::symlink_metadata(&current) {
-                    if meta.file_type().is_symlink() {
-                        return true;
-                    }
-                }
-            }
-            false
-        }
-
         // 确保 auth_dir 存在
         std::fs::create_dir_all(&auth_path)?;
 
@@ -546,6 +862,12 @@ impl ImportService {
             server_key_cleared = true;
         }
 
+        // 移除脱敏的客户端 API Key（无法恢复原值）
+        config
+            .server
+            .api_keys
+            .retain(|entry| entry.key != REDACTED_PLACEHOLDER);
+
         server_key_cleared
     }
 
@@ -590,8 +912,95 @@ impl ImportService {
     }
 }
 
+/// 列出单个凭证池的计划变更
+///
+/// 按 ID 对应当前、导入和导入后的条目：导入包中有但导入后不存在的条目记为跳过，
+/// 覆盖内容不同的现有条目和导入后重复的 ID 记为冲突。
+fn plan_entries<T: PartialEq>(
+    pool: &str,
+    current: &[T],
+    imported: &[T],
+    planned: &[T],
+    id: impl Fn(&T) -> &String,
+    result: &mut ValidationResult,
+) {
+    let name = |entry: &T| format!("{}/{}", pool, id(entry));
+    let mut seen = HashSet::new();
+
+    for entry in planned {
+        if !seen.insert(id(entry)) {
+            result.add_conflict(format!("凭证 ID 重复: {}", name(entry)));
+            continue;
+        }
+        let action = match current.iter().find(|&e| id(e) == id(entry)) {
+            None => ImportAction::Added,
+            Some(existing) if existing == entry => ImportAction::Unchanged,
+            Some(_) => {
+                result.add_conflict(format!("凭证 {} 已存在且内容不同，导入将覆盖", name(entry)));
+                ImportAction::Updated
+            }
+        };
+        result.changes.push(PlannedChange::new(
+            ImportItemKind::Credential,
+            name(entry),
+            action,
+        ));
+    }
+
+    for entry in imported {
+        if !planned.iter().any(|e| id(e) == id(entry)) {
+            result.changes.push(PlannedChange::new(
+                ImportItemKind::Credential,
+                name(entry),
+                ImportAction::Skipped,
+            ));
+        }
+    }
+
+    for entry in current {
+        if !planned.iter().any(|e| id(e) == id(entry)) {
+            result.changes.push(PlannedChange::new(
+                ImportItemKind::Credential,
+                name(entry),
+                ImportAction::Removed,
+            ));
+        }
+    }
+}
+
+/// 检查 Token 文件路径是否为安全的相对路径（不允许路径穿越）
+fn is_safe_relative_path(rel: &Path) -> bool {
+    if rel.as_os_str().is_empty() || rel.is_absolute() {
+        return false;
+    }
+
+    rel.components().all(|c| match c {
+        Component::Normal(_) => true,
+        Component::CurDir => true,
+        Component::ParentDir | Component::RootDir | Component::Prefix(_) => false,
+    })
+}
+
+/// 检查 Token 文件路径的各级路径段中是否存在符号链接
+fn has_symlink_in_prefix(base: &Path, rel: &Path) -> bool {
+    let mut current = base.to_path_buf();
+    for c in rel.components() {
+        let Component::Normal(part) = c else {
+            continue;
+        };
+        current.push(part);
+        if let Ok(meta) = std::fs::symlink_metadata(&current) {
+            if meta.file_type().is_symlink() {
+                return true;
+            }
+        }
+    }
+    false
+}
+
 #[cfg(test)]
 mod unit_tests {
+    use super::super::types::RoutingRuleConfig;
     use super::*;
 
     use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
@@ -885,4 +1294,146 @@ credential_pool:
         assert!(warnings.iter().any(|w| w.contains("符号链接")));
         assert!(!outside_dir.join("evil.txt").exists());
     }
+
+    fn kiro_entry(id: &str, token_file: &str) -> CredentialEntry {
+        CredentialEntry {
+            id: id.to_string(),
+            token_file: token_file.to_string(),
+            disabled: false,
+            proxy_url: None,
+        }
+    }
+
+    fn bundle_with_config(config: &Config) -> ExportBundle {
+        let mut bundle = ExportBundle::new("1.0.0");
+        bundle.config_yaml = Some(ConfigManager::to_yaml(config).expect("序列化应成功"));
+        bundle
+    }
+
+    fn change(kind: ImportItemKind, name: &str, action: ImportAction) -> PlannedChange {
+        PlannedChange::new(kind, name, action)
+    }
+
+    #[test]
+    fn test_validate_only_clean_import() {
+        let auth_dir = tempfile::tempdir().expect("tempdir");
+        let auth_dir_str = auth_dir.path().to_string_lossy().to_string();
+
+        let mut current = Config::default();
+        current.credential_pool.kiro = vec![kiro_entry("main", "kiro_main.json")];
+
+        let mut imported = Config::default();
+        imported.credential_pool.kiro = vec![kiro_entry("backup", "kiro_backup.json")];
+        imported.routing.rules = vec![RoutingRuleConfig {
+            pattern: "gpt-*".to_string(),
+            provider: "openai".to_string(),
+            priority: 10,
+        }];
+        imported
+            .routing
+            .model_aliases
+            .insert("fast".to_string(), "claude-haiku".to_string());
+
+        let mut bundle = bundle_with_config(&imported);
+        bundle.token_files.insert(
+            "kiro_backup.json".to_string(),
+            BASE64_STANDARD.encode(b"{}"),
+        );
+
+        let result =
+            ImportService::validate_only(&bundle, &current, &ImportOptions::merge(), &auth_dir_str);
+
+        assert!(result.valid, "{:?}", result.errors);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(
+            result.changes,
+            vec![
+                change(
+                    ImportItemKind::Credential,
+                    "kiro/main",
+                    ImportAction::Unchanged
+                ),
+                change(
+                    ImportItemKind::Credential,
+                    "kiro/backup",
+                    ImportAction::Added
+                ),
+                change(ImportItemKind::RoutingRule, "gpt-*", ImportAction::Added),
+                change(ImportItemKind::ModelMapping, "fast", ImportAction::Added),
+                change(
+                    ImportItemKind::TokenFile,
+                    "kiro_backup.json",
+                    ImportAction::Added
+                ),
+            ]
+        );
+        // 演练不写入 Token 文件
+        assert!(!auth_dir.path().join("kiro_backup.json").exists());
+    }
+
+    #[test]
+    fn test_validate_only_reports_credential_name_collision() {
+        let auth_dir = tempfile::tempdir().expect("tempdir");
+        let auth_dir_str = auth_dir.path().to_string_lossy().to_string();
+
+        let mut current = Config::default();
+        current.credential_pool.kiro = vec![kiro_entry("main", "kiro_main.json")];
+
+        // 同名凭证指向不同的 Token 文件，导入会覆盖现有凭证
+        let mut imported = Config::default();
+        imported.credential_pool.kiro = vec![kiro_entry("main", "kiro_other.json")];
+        let bundle = bundle_with_config(&imported);
+
+        let result =
+            ImportService::validate_only(&bundle, &current, &ImportOptions::merge(), &auth_dir_str);
+        assert!(!result.valid);
+        assert_eq!(result.conflicts.len(), 1);
+        assert!(result.conflicts[0].contains("kiro/main"));
+        assert_eq!(result.errors, result.conflicts);
+        assert_eq!(
+            result.changes,
+            vec![change(
+                ImportItemKind::Credential,
+                "kiro/main",
+                ImportAction::Updated
+            )]
+        );
+
+        // fail_on_conflict 时中止导入
+        let options = ImportOptions::merge().with_fail_on_conflict();
+        match ImportService::import(&bundle, &current, &options, &auth_dir_str) {
+            Err(ImportError::ValidationError(msg)) => assert!(msg.contains("kiro/main")),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+
+        // 未设置时照常导入并覆盖
+        let result =
+            ImportService::import(&bundle, &current, &ImportOptions::merge(), &auth_dir_str)
+                .expect("import");
+        assert_eq!(
+            result.config.credential_pool.kiro,
+            vec![kiro_entry("main", "kiro_other.json")]
+        );
+    }
+
+    #[test]
+    fn test_validate_only_reports_overlapping_routing_rules() {
+        let rule = |provider: &str| RoutingRuleConfig {
+            pattern: "claude-*".to_string(),
+            provider: provider.to_string(),
+            priority: 50,
+        };
+        let mut imported = Config::default();
+        imported.routing.rules = vec![rule("claude"), rule("kiro")];
+
+        let result = ImportService::validate_only(
+            &bundle_with_config(&imported),
+            &Config::default(),
+            &ImportOptions::replace(),
+            "/nonexistent-auth-dir",
+        );
+        assert!(!result.valid);
+        assert_eq!(result.conflicts.len(), 1);
+        assert!(result.conflicts[0].contains("claude-*"));
+    }
 }
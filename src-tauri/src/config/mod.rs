@@ -12,16 +12,20 @@ mod yaml;
 
 pub use export::{ExportBundle, ExportOptions, ExportService, REDACTED_PLACEHOLDER};
 pub use hot_reload::{
-    ConfigChangeEvent, ConfigChangeKind, FileWatcher, HotReloadManager, ReloadResult,
+    ConfigChangeEvent, ConfigChangeKind, ConfigSection, FileWatcher, HotReloadManager, ReloadResult,
+};
+pub use import::{
+    ImportAction, ImportItemKind, ImportOptions, ImportService, PlannedChange, ValidationResult,
 };
-pub use import::{ImportOptions, ImportService, ValidationResult};
 pub use path_utils::{collapse_tilde, contains_tilde, expand_tilde};
 pub use types::{
-    generate_secure_api_key, is_default_api_key, AmpConfig, AmpModelMapping, ApiKeyEntry, Config,
-    CredentialEntry, CredentialPoolConfig, CustomProviderConfig, EndpointProvidersConfig,
-    GeminiApiKeyEntry, IFlowCredentialEntry, InjectionRuleConfig, InjectionSettings, LoggingConfig,
-    ProviderConfig, ProvidersConfig, QuotaExceededConfig, RemoteManagementConfig, RetrySettings,
-    RoutingConfig, ServerConfig, TlsConfig, VertexApiKeyEntry, VertexModelAlias, DEFAULT_API_KEY,
+    generate_secure_api_key, is_default_api_key, AmpConfig, AmpModelMapping, ApiKeyEntry,
+    ClientApiKey, ClientTlsConfig, Config, ConnectionPoolConfig, CredentialEntry,
+    CredentialPoolConfig, CustomProviderConfig, EndpointProvidersConfig, FlowMonitorSettings,
+    FlowPluginsConfig, GeminiApiKeyEntry, GrpcConfig, IFlowCredentialEntry, InjectionRuleConfig,
+    InjectionSettings, LoggingConfig, OtlpConfig, ProviderConfig, ProvidersConfig,
+    QuotaExceededConfig, RemoteManagementConfig, RetrySettings, RoutingConfig, ServerConfig,
+    StreamingSettings, TlsConfig, VertexApiKeyEntry, VertexModelAlias, DEFAULT_API_KEY,
 };
 pub use yaml::{load_config, save_config, ConfigError, ConfigManager, YamlService};
 
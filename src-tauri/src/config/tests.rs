@@ -38,6 +38,10 @@ fn arb_server_config() -> impl Strategy<Value = ServerConfig> {
         port,
         api_key,
         tls: crate::config::TlsConfig::default(),
+        api_keys: Vec::new(),
+        api_keys_file: None,
+        grpc: crate::config::GrpcConfig::default(),
+        otlp: crate::config::OtlpConfig::default(),
     })
 }
 
@@ -59,6 +63,11 @@ fn arb_provider_config() -> impl Strategy<Value = ProviderConfig> {
                 credentials_path,
                 region,
                 project_id,
+                proxy: None,
+                tls: None,
+                connection_pool: None,
+                request_timeout_ms: None,
+                extra_headers: std::collections::HashMap::new(),
             },
         )
 }
@@ -78,6 +87,11 @@ fn arb_custom_provider_config() -> impl Strategy<Value = CustomProviderConfig> {
             enabled,
             api_key,
             base_url,
+            proxy: None,
+            tls: None,
+            connection_pool: None,
+            request_timeout_ms: None,
+            extra_headers: std::collections::HashMap::new(),
         })
 }
 
@@ -149,6 +163,12 @@ fn arb_routing_config() -> impl Strategy<Value = RoutingConfig> {
                     .collect(),
                 model_aliases,
                 exclusions,
+                param_constraints: std::collections::HashMap::new(),
+                size_downgrades: std::collections::HashMap::new(),
+                history_truncation: std::collections::HashMap::new(),
+                model_defaults: std::collections::HashMap::new(),
+                reasoning_effort: std::collections::HashMap::new(),
+                session_affinity: Default::default(),
             },
         )
 }
@@ -216,8 +236,15 @@ fn arb_config() -> impl Strategy<Value = Config> {
             remote_management: crate::config::RemoteManagementConfig::default(),
             quota_exceeded: crate::config::QuotaExceededConfig::default(),
             proxy_url: None,
+            no_proxy: Vec::new(),
             ampcode: crate::config::AmpConfig::default(),
             endpoint_providers: crate::config::EndpointProvidersConfig::default(),
+            flow_plugins: crate::config::FlowPluginsConfig::default(),
+            flow_monitor: crate::config::FlowMonitorSettings::default(),
+            streaming: crate::config::StreamingSettings::default(),
+            chaos: crate::processor::ChaosConfig::default(),
+            content_filter: crate::processor::ContentFilterConfig::default(),
+            shadow: crate::processor::ShadowConfig::default(),
             minimize_to_tray: true,
         })
 }
@@ -423,6 +450,10 @@ fn arb_valid_server_config() -> impl Strategy<Value = ServerConfig> {
         port,
         api_key,
         tls: crate::config::TlsConfig::default(),
+        api_keys: Vec::new(),
+        api_keys_file: None,
+        grpc: crate::config::GrpcConfig::default(),
+        otlp: crate::config::OtlpConfig::default(),
     })
 }
 
@@ -489,8 +520,15 @@ fn arb_valid_config() -> impl Strategy<Value = Config> {
             remote_management: crate::config::RemoteManagementConfig::default(),
             quota_exceeded: crate::config::QuotaExceededConfig::default(),
             proxy_url: None,
+            no_proxy: Vec::new(),
             ampcode: crate::config::AmpConfig::default(),
             endpoint_providers: crate::config::EndpointProvidersConfig::default(),
+            flow_plugins: crate::config::FlowPluginsConfig::default(),
+            flow_monitor: crate::config::FlowMonitorSettings::default(),
+            streaming: crate::config::StreamingSettings::default(),
+            chaos: crate::processor::ChaosConfig::default(),
+            content_filter: crate::processor::ContentFilterConfig::default(),
+            shadow: crate::processor::ShadowConfig::default(),
             minimize_to_tray: true,
         })
 }
@@ -534,8 +572,15 @@ fn arb_invalid_config() -> impl Strategy<Value = Config> {
                     remote_management: crate::config::RemoteManagementConfig::default(),
                     quota_exceeded: crate::config::QuotaExceededConfig::default(),
                     proxy_url: None,
+                    no_proxy: Vec::new(),
                     ampcode: crate::config::AmpConfig::default(),
                     endpoint_providers: crate::config::EndpointProvidersConfig::default(),
+                    flow_plugins: crate::config::FlowPluginsConfig::default(),
+                    flow_monitor: crate::config::FlowMonitorSettings::default(),
+                    streaming: crate::config::StreamingSettings::default(),
+                    chaos: crate::processor::ChaosConfig::default(),
+                    content_filter: crate::processor::ContentFilterConfig::default(),
+                    shadow: crate::processor::ShadowConfig::default(),
                     minimize_to_tray: true,
                 };
                 // 根据类型使配置无效
@@ -649,6 +694,16 @@ proptest! {
                     "成功时配置应完全更新"
                 );
             }
+            ReloadResult::Rejected { validation, .. } => {
+                // 验证失败被拒绝，配置应保持不变
+                prop_assert!(!validation.valid, "被拒绝时应包含验证错误");
+                let current = manager.config();
+                prop_assert_eq!(
+                    current,
+                    initial_config,
+                    "被拒绝时配置应保持不变"
+                );
+            }
             ReloadResult::Failed { .. } => {
                 // 完全失败的情况，配置应保持不变
                 let current = manager.config();
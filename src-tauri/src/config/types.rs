@@ -4,6 +4,11 @@
 //! 保持与旧版 JSON 配置的向后兼容性
 
 use crate::injection::{InjectionMode, InjectionRule};
+use crate::processor::{ChaosConfig, ContentFilterConfig, ShadowConfig};
+use crate::router::{
+    HistoryTruncationRule, ParamConstraint, ReasoningEffortMapping, SessionAffinityConfig,
+    SizeDowngradeRule,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -198,6 +203,34 @@ pub struct EndpointProvidersConfig {
     pub other: Option<String>,
 }
 
+/// Flow 插件配置
+///
+/// 控制内置 Flow 插件的启用，配置热重载时重新注册。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct FlowPluginsConfig {
+    /// 系统提示词前缀（为空表示不启用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt_prefix: Option<String>,
+}
+
+/// Flow Monitor 持久化配置
+///
+/// 只包含需要在创建 FlowMonitor 之前确定的选项，其余选项在运行时通过命令设置。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct FlowMonitorSettings {
+    /// 请求预写日志路径（为空表示不启用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wal_path: Option<String>,
+}
+
+/// 流式响应配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct StreamingSettings {
+    /// 透传无法识别的 SSE 事件（默认丢弃，便于客户端使用上游新增的事件类型）
+    #[serde(default)]
+    pub passthrough_unknown_events: bool,
+}
+
 impl EndpointProvidersConfig {
     /// 根据客户端类型获取配置的 Provider
     ///
@@ -301,6 +334,9 @@ pub struct Config {
     /// 全局代理 URL
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub proxy_url: Option<String>,
+    /// 不走代理的主机列表（域名、IP 或 CIDR）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_proxy: Vec<String>,
     /// Amp CLI 配置
     #[serde(default)]
     pub ampcode: AmpConfig,
@@ -308,6 +344,24 @@ pub struct Config {
     /// 允许为不同的客户端端点（CC/Codex）配置不同的 Provider
     #[serde(default)]
     pub endpoint_providers: EndpointProvidersConfig,
+    /// Flow 插件配置
+    #[serde(default)]
+    pub flow_plugins: FlowPluginsConfig,
+    /// Flow Monitor 配置（启动时加载）
+    #[serde(default)]
+    pub flow_monitor: FlowMonitorSettings,
+    /// 流式响应配置
+    #[serde(default)]
+    pub streaming: StreamingSettings,
+    /// 故障注入配置（混沌测试，默认关闭，切勿在生产环境启用）
+    #[serde(default)]
+    pub chaos: ChaosConfig,
+    /// 响应内容过滤配置（默认关闭）
+    #[serde(default)]
+    pub content_filter: ContentFilterConfig,
+    /// 影子镜像配置（默认关闭）
+    #[serde(default)]
+    pub shadow: ShadowConfig,
     /// 关闭时最小化到托盘（而不是退出应用）
     #[serde(default = "default_minimize_to_tray")]
     pub minimize_to_tray: bool,
@@ -332,6 +386,108 @@ pub struct ServerConfig {
     /// TLS 配置
     #[serde(default)]
     pub tls: TlsConfig,
+    /// 额外的客户端 API Key（按 Key 区分身份和访问范围）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_keys: Vec<ClientApiKey>,
+    /// 客户端 API Key 文件路径（YAML/JSON 列表，与 `api_keys` 合并）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_keys_file: Option<String>,
+    /// gRPC 服务配置
+    #[serde(default)]
+    pub grpc: GrpcConfig,
+    /// OpenTelemetry OTLP 导出配置
+    #[serde(default)]
+    pub otlp: OtlpConfig,
+}
+
+fn default_grpc_port() -> u16 {
+    50051
+}
+
+/// gRPC 服务配置
+///
+/// 启用后在独立端口上提供与 HTTP 端点对应的 gRPC 接口，监听地址与 HTTP 服务相同。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GrpcConfig {
+    /// 是否启用 gRPC 服务
+    #[serde(default)]
+    pub enable: bool,
+    /// 监听端口
+    #[serde(default = "default_grpc_port")]
+    pub port: u16,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            port: default_grpc_port(),
+        }
+    }
+}
+
+fn default_otlp_endpoint() -> String {
+    "http://127.0.0.1:4318/v1/traces".to_string()
+}
+
+fn default_otlp_service_name() -> String {
+    "proxycast".to_string()
+}
+
+fn default_otlp_timeout_ms() -> u64 {
+    10_000
+}
+
+/// OpenTelemetry OTLP 导出配置
+///
+/// 启用后每个完成或失败的 Flow 会转换为一个 Span，以 OTLP/HTTP（JSON 编码）发送到采集器。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OtlpConfig {
+    /// 是否启用导出
+    #[serde(default)]
+    pub enable: bool,
+    /// 采集器的 Traces 端点
+    #[serde(default = "default_otlp_endpoint")]
+    pub endpoint: String,
+    /// 附加请求头（如采集器的认证 Token）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// 上报的服务名（`service.name` 资源属性）
+    #[serde(default = "default_otlp_service_name")]
+    pub service_name: String,
+    /// 单次请求超时（毫秒）
+    #[serde(default = "default_otlp_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            endpoint: default_otlp_endpoint(),
+            headers: HashMap::new(),
+            service_name: default_otlp_service_name(),
+            timeout_ms: default_otlp_timeout_ms(),
+        }
+    }
+}
+
+/// 客户端 API Key
+///
+/// 调用方使用此 Key 访问代理服务，`allowed_models`/`allowed_providers`
+/// 为空表示不限制，模型名支持 `*` 通配符。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClientApiKey {
+    /// API Key
+    pub key: String,
+    /// 身份标签（记录到 Flow 的客户端信息中）
+    pub label: String,
+    /// 允许访问的模型
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_models: Vec<String>,
+    /// 允许使用的 Provider
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_providers: Vec<String>,
 }
 
 /// TLS 配置
@@ -348,6 +504,78 @@ pub struct TlsConfig {
     /// 私钥文件路径
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub key_path: Option<String>,
+    /// 访问上游时出示的客户端证书路径（PEM，可同时包含私钥；或 .p12 / .pfx）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert_path: Option<String>,
+    /// 客户端证书私钥路径（PEM）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key_path: Option<String>,
+    /// PKCS#12 客户端证书的密码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert_password: Option<String>,
+    /// 额外信任的 CA 证书包路径（PEM，追加到内置信任库）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle_path: Option<String>,
+}
+
+impl TlsConfig {
+    /// 获取全局的上游客户端 TLS 配置
+    pub fn client_tls(&self) -> ClientTlsConfig {
+        ClientTlsConfig {
+            client_cert_path: self.client_cert_path.clone(),
+            client_key_path: self.client_key_path.clone(),
+            client_cert_password: self.client_cert_password.clone(),
+            ca_bundle_path: self.ca_bundle_path.clone(),
+        }
+    }
+}
+
+/// 上游客户端 TLS 配置
+///
+/// 用于 mTLS：向上游出示客户端证书，并信任自定义 CA
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ClientTlsConfig {
+    /// 客户端证书路径（PEM，可同时包含私钥；或 .p12 / .pfx）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert_path: Option<String>,
+    /// 客户端证书私钥路径（PEM，PKCS#12 证书无需配置）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key_path: Option<String>,
+    /// PKCS#12 客户端证书的密码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert_password: Option<String>,
+    /// 额外信任的 CA 证书包路径（PEM）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle_path: Option<String>,
+}
+
+impl ClientTlsConfig {
+    /// 是否未配置任何证书
+    pub fn is_empty(&self) -> bool {
+        self.client_cert_path.is_none()
+            && self.client_key_path.is_none()
+            && self.ca_bundle_path.is_none()
+    }
+}
+
+/// 上游连接池配置
+///
+/// 未配置的项沿用 reqwest 默认值（每主机空闲连接数不限、空闲 90 秒回收、
+/// 不启用 TCP keep-alive、通过 ALPN 协商 HTTP 版本）。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ConnectionPoolConfig {
+    /// 每个主机保留的最大空闲连接数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_max_idle_per_host: Option<usize>,
+    /// 空闲连接的保留时间（秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_idle_timeout_secs: Option<u64>,
+    /// TCP keep-alive 探测间隔（秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive_secs: Option<u64>,
+    /// 直接以 HTTP/2 连接上游（跳过协商，仅适用于确认支持 HTTP/2 的上游）
+    #[serde(default)]
+    pub http2_prior_knowledge: bool,
 }
 
 /// 远程管理配置
@@ -468,6 +696,10 @@ impl Default for ServerConfig {
             port: default_port(),
             api_key: default_api_key(),
             tls: TlsConfig::default(),
+            api_keys: Vec::new(),
+            api_keys_file: None,
+            grpc: GrpcConfig::default(),
+            otlp: OtlpConfig::default(),
         }
     }
 }
@@ -500,28 +732,53 @@ impl Default for ProvidersConfig {
                 credentials_path: Some("~/.aws/sso/cache/kiro-auth-token.json".to_string()),
                 region: Some("us-east-1".to_string()),
                 project_id: None,
+                proxy: None,
+                tls: None,
+                connection_pool: None,
+                request_timeout_ms: None,
+                extra_headers: HashMap::new(),
             },
             gemini: ProviderConfig {
                 enabled: false,
                 credentials_path: Some("~/.gemini/oauth_creds.json".to_string()),
                 region: None,
                 project_id: None,
+                proxy: None,
+                tls: None,
+                connection_pool: None,
+                request_timeout_ms: None,
+                extra_headers: HashMap::new(),
             },
             qwen: ProviderConfig {
                 enabled: false,
                 credentials_path: Some("~/.qwen/oauth_creds.json".to_string()),
                 region: None,
                 project_id: None,
+                proxy: None,
+                tls: None,
+                connection_pool: None,
+                request_timeout_ms: None,
+                extra_headers: HashMap::new(),
             },
             openai: CustomProviderConfig {
                 enabled: false,
                 api_key: None,
                 base_url: Some("https://api.openai.com/v1".to_string()),
+                proxy: None,
+                tls: None,
+                connection_pool: None,
+                request_timeout_ms: None,
+                extra_headers: HashMap::new(),
             },
             claude: CustomProviderConfig {
                 enabled: false,
                 api_key: None,
                 base_url: Some("https://api.anthropic.com".to_string()),
+                proxy: None,
+                tls: None,
+                connection_pool: None,
+                request_timeout_ms: None,
+                extra_headers: HashMap::new(),
             },
         }
     }
@@ -542,6 +799,21 @@ pub struct ProviderConfig {
     /// 项目 ID
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub project_id: Option<String>,
+    /// 上游代理 URL（覆盖全局代理，支持 http/https/socks5）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// 上游客户端 TLS 配置（覆盖 `server.tls` 中的全局客户端证书）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<ClientTlsConfig>,
+    /// 上游连接池配置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_pool: Option<ConnectionPoolConfig>,
+    /// 非流式请求的总超时（毫秒，可被请求头 `x-request-timeout-ms` 覆盖）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_timeout_ms: Option<u64>,
+    /// 附加到每个上游请求的请求头（值支持 `${ENV_VAR}` 环境变量插值）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extra_headers: HashMap<String, String>,
 }
 
 /// 自定义 Provider 配置（API Key 方式）
@@ -556,6 +828,21 @@ pub struct CustomProviderConfig {
     /// 基础 URL
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub base_url: Option<String>,
+    /// 上游代理 URL（覆盖全局代理，支持 http/https/socks5）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// 上游客户端 TLS 配置（覆盖 `server.tls` 中的全局客户端证书）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<ClientTlsConfig>,
+    /// 上游连接池配置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_pool: Option<ConnectionPoolConfig>,
+    /// 非流式请求的总超时（毫秒，可被请求头 `x-request-timeout-ms` 覆盖）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_timeout_ms: Option<u64>,
+    /// 附加到每个上游请求的请求头（值支持 `${ENV_VAR}` 环境变量插值）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extra_headers: HashMap<String, String>,
 }
 
 /// 路由配置
@@ -573,6 +860,24 @@ pub struct RoutingConfig {
     /// 排除列表（按 Provider）
     #[serde(default)]
     pub exclusions: HashMap<String, Vec<String>>,
+    /// 按别名的参数约束（别名 -> 参数名 -> 约束）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub param_constraints: HashMap<String, HashMap<String, ParamConstraint>>,
+    /// 按别名的请求大小降级规则（提示词估算 Token 超过阈值时改用替代模型）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub size_downgrades: HashMap<String, SizeDowngradeRule>,
+    /// 按别名的消息历史截断规则（转发前只保留最近的消息）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub history_truncation: HashMap<String, HistoryTruncationRule>,
+    /// 按模型的默认参数（模型模式 -> 参数名 -> 默认值），仅在客户端未发送该参数时应用
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub model_defaults: HashMap<String, HashMap<String, serde_json::Value>>,
+    /// 按 Provider 的推理强度映射（Provider 名称 -> 映射，覆盖内置的 OpenAI/Claude 映射）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub reasoning_effort: HashMap<String, ReasoningEffortMapping>,
+    /// 会话亲和（粘性会话）配置
+    #[serde(default)]
+    pub session_affinity: SessionAffinityConfig,
 }
 
 fn default_provider() -> String {
@@ -586,6 +891,12 @@ impl Default for RoutingConfig {
             rules: Vec::new(),
             model_aliases: HashMap::new(),
             exclusions: HashMap::new(),
+            param_constraints: HashMap::new(),
+            size_downgrades: HashMap::new(),
+            history_truncation: HashMap::new(),
+            model_defaults: HashMap::new(),
+            reasoning_effort: HashMap::new(),
+            session_affinity: SessionAffinityConfig::default(),
         }
     }
 }
@@ -776,8 +1087,15 @@ impl Default for Config {
             remote_management: RemoteManagementConfig::default(),
             quota_exceeded: QuotaExceededConfig::default(),
             proxy_url: None,
+            no_proxy: Vec::new(),
             ampcode: AmpConfig::default(),
             endpoint_providers: EndpointProvidersConfig::default(),
+            flow_plugins: FlowPluginsConfig::default(),
+            flow_monitor: FlowMonitorSettings::default(),
+            streaming: StreamingSettings::default(),
+            chaos: ChaosConfig::default(),
+            content_filter: ContentFilterConfig::default(),
+            shadow: ShadowConfig::default(),
             minimize_to_tray: default_minimize_to_tray(),
         }
     }
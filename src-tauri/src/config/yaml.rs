@@ -75,6 +75,7 @@ impl ConfigManager {
         } else {
             Config::default()
         };
+        validate_upstream(&config)?;
 
         Ok(Self {
             config,
@@ -108,6 +109,7 @@ impl ConfigManager {
             let backup_path = path.with_extension("yaml.backup");
             let _ = std::fs::copy(path, backup_path);
         }
+        audit_config_write(path, &self.config);
         let yaml = Self::to_yaml(&self.config)?;
         std::fs::write(path, yaml).map_err(|e| ConfigError::WriteError(e.to_string()))
     }
@@ -149,6 +151,9 @@ impl ConfigManager {
             let mut config = self.config.clone();
             // 脱敏 API 密钥
             config.server.api_key = "***REDACTED***".to_string();
+            for entry in &mut config.server.api_keys {
+                entry.key = "***REDACTED***".to_string();
+            }
             if config.providers.openai.api_key.is_some() {
                 config.providers.openai.api_key = Some("***REDACTED***".to_string());
             }
@@ -647,6 +652,18 @@ impl YamlService {
     }
 }
 
+/// 验证配置中的代理 URL、上游客户端证书和附加请求头
+fn validate_upstream(config: &Config) -> Result<(), ConfigError> {
+    let mut errors = crate::proxy::validate_proxy_settings(config);
+    errors.extend(crate::proxy::validate_client_tls_settings(config));
+    errors.extend(crate::proxy::validate_extra_headers(config));
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigError::ValidationError(errors.join("; ")))
+    }
+}
+
 // ============ 向后兼容的 JSON 配置函数 ============
 
 /// 获取 JSON 配置文件路径（向后兼容）
@@ -671,6 +688,7 @@ pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
     if yaml_path.exists() {
         let content = std::fs::read_to_string(&yaml_path)?;
         let mut config: Config = serde_yaml::from_str(&content)?;
+        validate_upstream(&config)?;
         // 如果配置中使用默认 API Key，生成强随机 Key 并保存
         if is_default_api_key(&config.server.api_key) {
             let new_key = generate_secure_api_key();
@@ -688,6 +706,7 @@ pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
     if json_path.exists() {
         let content = std::fs::read_to_string(&json_path)?;
         let mut config: Config = serde_json::from_str(&content)?;
+        validate_upstream(&config)?;
         // 如果配置中使用默认 API Key，生成强随机 Key 并保存
         if is_default_api_key(&config.server.api_key) {
             let new_key = generate_secure_api_key();
@@ -713,6 +732,23 @@ pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
     Ok(config)
 }
 
+/// 将配置写入记录到审计日志（与磁盘上的旧配置比较）
+fn audit_config_write(path: &Path, config: &Config) {
+    if crate::audit::global().is_none() {
+        return;
+    }
+    let previous = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| ConfigManager::parse_yaml(&content).ok());
+    crate::audit::record_serialized(
+        &crate::audit::local_actor(),
+        crate::audit::AuditAction::ConfigSave,
+        Some(path.display().to_string()),
+        previous.as_ref(),
+        Some(config),
+    );
+}
+
 /// 保存配置（同时写入 YAML 与 JSON，兼容旧版）
 pub fn save_config(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
     // 主配置优先写入 YAML
@@ -738,6 +774,7 @@ pub fn save_config_yaml(config: &Config) -> Result<(), Box<dyn std::error::Error
         let backup_path = path.with_extension("yaml.backup");
         let _ = std::fs::copy(&path, &backup_path);
     }
+    audit_config_write(&path, config);
     let content = serde_yaml::to_string(config)?;
     std::fs::write(&path, content)?;
     Ok(())
@@ -765,6 +802,55 @@ providers:
         assert!(config.providers.kiro.enabled);
     }
 
+    #[test]
+    fn test_load_rejects_invalid_proxy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(
+            &path,
+            r#"
+proxy_url: "http://corp.proxy:3128"
+no_proxy: ["localhost", "10.0.0.0/8"]
+providers:
+  claude:
+    proxy: "socks5://corp.proxy:1080"
+"#,
+        )
+        .unwrap();
+        let manager = ConfigManager::load(&path).unwrap();
+        assert_eq!(manager.config().no_proxy.len(), 2);
+        assert_eq!(
+            manager.config().providers.claude.proxy.as_deref(),
+            Some("socks5://corp.proxy:1080")
+        );
+
+        std::fs::write(
+            &path,
+            "providers:\n  openai:\n    proxy: \"corp.proxy:3128\"\n",
+        )
+        .unwrap();
+        let err = ConfigManager::load(&path).unwrap_err();
+        assert!(
+            matches!(err, ConfigError::ValidationError(ref msg) if msg.contains("providers.openai.proxy"))
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_missing_client_cert() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(
+            &path,
+            "providers:\n  claude:\n    tls:\n      client_cert_path: \"/nonexistent/gateway.pem\"\n",
+        )
+        .unwrap();
+
+        let err = ConfigManager::load(&path).unwrap_err();
+        assert!(
+            matches!(err, ConfigError::ValidationError(ref msg) if msg.contains("providers.claude.tls"))
+        );
+    }
+
     #[test]
     fn test_parse_yaml_full() {
         let yaml = r#"
@@ -49,7 +49,13 @@ pub fn convert_anthropic_to_openai(request: &AnthropicMessagesRequest) -> ChatCo
         stream: request.stream,
         tools,
         tool_choice: request.tool_choice.clone(),
-        reasoning_effort: None,
+        reasoning_effort: request.reasoning_effort.clone(),
+        thinking: request.thinking.clone(),
+        response_format: None,
+        logprobs: None,
+        top_logprobs: None,
+        seed: None,
+        stream_options: None,
     }
 }
 
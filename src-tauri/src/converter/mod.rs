@@ -1,5 +1,6 @@
 pub mod anthropic_to_openai;
 pub mod cw_to_openai;
+pub mod openai_responses;
 pub mod openai_to_antigravity;
 pub mod openai_to_cw;
 pub mod protocol_selector;
@@ -9,6 +10,8 @@ pub use anthropic_to_openai::*;
 #[allow(unused_imports)]
 pub use cw_to_openai::*;
 #[allow(unused_imports)]
+pub use openai_responses::*;
+#[allow(unused_imports)]
 pub use openai_to_antigravity::*;
 #[allow(unused_imports)]
 pub use openai_to_cw::*;
@@ -0,0 +1,917 @@
+//! OpenAI Responses 格式与 Chat Completions 格式互转
+//!
+//! `/v1/responses` 请求先转换为 Chat Completions 请求，复用现有的路由、注入和 Flow 捕获流程；
+//! 响应（包括流式事件）再从 Chat Completions 格式转换回 Responses 格式。
+use crate::models::openai::*;
+use crate::models::openai_responses::*;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+use uuid::Uuid;
+
+/// 将 Responses 请求转换为 Chat Completions 请求
+pub fn convert_responses_to_openai(request: &ResponsesRequest) -> ChatCompletionRequest {
+    let mut messages: Vec<ChatMessage> = Vec::new();
+
+    if let Some(instructions) = request.instructions.as_ref().filter(|s| !s.is_empty()) {
+        messages.push(text_message("system", instructions.clone()));
+    }
+
+    match &request.input {
+        ResponsesInput::Text(text) => messages.push(text_message("user", text.clone())),
+        ResponsesInput::Items(items) => {
+            for item in items {
+                convert_input_item(item, &mut messages);
+            }
+        }
+    }
+
+    let tools: Vec<Tool> = request
+        .tools
+        .iter()
+        .flatten()
+        .filter(|t| t.tool_type == "function")
+        .filter_map(|t| {
+            Some(Tool {
+                tool_type: "function".to_string(),
+                function: FunctionDef {
+                    name: t.name.clone()?,
+                    description: t.description.clone(),
+                    parameters: t.parameters.clone(),
+                },
+            })
+        })
+        .collect();
+
+    let stream_options = request.stream.then(|| json!({"include_usage": true}));
+
+    ChatCompletionRequest {
+        model: request.model.clone(),
+        messages,
+        temperature: request.temperature,
+        max_tokens: request.max_output_tokens,
+        stream: request.stream,
+        tools: (!tools.is_empty()).then_some(tools),
+        tool_choice: request.tool_choice.as_ref().and_then(convert_tool_choice),
+        reasoning_effort: request.reasoning.as_ref().and_then(|r| r.effort.clone()),
+        thinking: None,
+        response_format: request
+            .text
+            .as_ref()
+            .and_then(|t| t.get("format"))
+            .and_then(convert_text_format),
+        logprobs: None,
+        top_logprobs: None,
+        seed: None,
+        stream_options,
+    }
+}
+
+/// 将 Responses 请求中出现的顶层字段映射为 Chat Completions 字段名
+///
+/// 用于填充模型默认参数时判断客户端是否显式设置了某个参数。
+pub fn responses_fields_to_openai(fields: &HashSet<String>) -> HashSet<String> {
+    fields
+        .iter()
+        .map(|field| {
+            match field.as_str() {
+                "input" => "messages",
+                "max_output_tokens" => "max_tokens",
+                "reasoning" => "reasoning_effort",
+                "text" => "response_format",
+                other => other,
+            }
+            .to_string()
+        })
+        .collect()
+}
+
+fn text_message(role: &str, text: String) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content: Some(MessageContent::Text(text)),
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
+
+fn convert_input_item(item: &ResponseInputItem, messages: &mut Vec<ChatMessage>) {
+    match item {
+        ResponseInputItem::Message { role, content } => {
+            let role = match role.as_str() {
+                "developer" | "system" => "system",
+                "assistant" => "assistant",
+                _ => "user",
+            };
+            messages.push(ChatMessage {
+                role: role.to_string(),
+                content: Some(convert_message_content(content)),
+                tool_calls: None,
+                tool_call_id: None,
+            });
+        }
+        ResponseInputItem::FunctionCall {
+            call_id,
+            name,
+            arguments,
+            ..
+        } => {
+            let tool_call = ToolCall {
+                id: call_id.clone(),
+                call_type: "function".to_string(),
+                function: FunctionCall {
+                    name: name.clone(),
+                    arguments: arguments.clone(),
+                },
+            };
+            // 同一轮的多个工具调用（以及之前的助手文本）合并为一条助手消息
+            match messages.last_mut() {
+                Some(last) if last.role == "assistant" => {
+                    last.tool_calls.get_or_insert_with(Vec::new).push(tool_call);
+                }
+                _ => messages.push(ChatMessage {
+                    role: "assistant".to_string(),
+                    content: None,
+                    tool_calls: Some(vec![tool_call]),
+                    tool_call_id: None,
+                }),
+            }
+        }
+        ResponseInputItem::FunctionCallOutput { call_id, output } => {
+            let text = match output {
+                Value::String(s) => s.clone(),
+                Value::Array(parts) => parts
+                    .iter()
+                    .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(""),
+                other => other.to_string(),
+            };
+            messages.push(ChatMessage {
+                role: "tool".to_string(),
+                content: Some(MessageContent::Text(text)),
+                tool_calls: None,
+                tool_call_id: Some(call_id.clone()),
+            });
+        }
+        // Chat Completions 没有对应的推理输入，忽略
+        ResponseInputItem::Reasoning { .. } | ResponseInputItem::Unsupported => {}
+    }
+}
+
+fn convert_message_content(content: &ResponseMessageContent) -> MessageContent {
+    let ResponseMessageContent::Parts(parts) = content else {
+        return MessageContent::Text(content.text());
+    };
+    let has_image = parts
+        .iter()
+        .any(|p| matches!(p, ResponseContentPart::InputImage { .. }));
+    if !has_image {
+        return MessageContent::Text(content.text());
+    }
+
+    let converted = parts
+        .iter()
+        .filter_map(|p| match p {
+            ResponseContentPart::InputText { text }
+            | ResponseContentPart::OutputText { text, .. } => {
+                Some(ContentPart::Text { text: text.clone() })
+            }
+            ResponseContentPart::InputImage { image_url, detail } => Some(ContentPart::ImageUrl {
+                image_url: ImageUrl {
+                    url: image_url.clone()?,
+                    detail: detail.clone(),
+                },
+            }),
+            ResponseContentPart::Refusal { .. } | ResponseContentPart::Unsupported => None,
+        })
+        .collect();
+    MessageContent::Parts(converted)
+}
+
+/// `{"type": "function", "name": ...}` 转换为 `{"type": "function", "function": {"name": ...}}`
+fn convert_tool_choice(choice: &Value) -> Option<Value> {
+    match choice {
+        Value::String(_) => Some(choice.clone()),
+        Value::Object(obj) if obj.get("type").and_then(|t| t.as_str()) == Some("function") => {
+            let name = obj.get("name")?;
+            Some(json!({"type": "function", "function": {"name": name}}))
+        }
+        _ => None,
+    }
+}
+
+/// `text.format` 转换为 `response_format`
+fn convert_text_format(format: &Value) -> Option<Value> {
+    match format.get("type").and_then(|t| t.as_str())? {
+        "json_object" => Some(json!({"type": "json_object"})),
+        "json_schema" => {
+            let mut schema = format.as_object()?.clone();
+            schema.remove("type");
+            Some(json!({"type": "json_schema", "json_schema": schema}))
+        }
+        _ => None,
+    }
+}
+
+fn new_id(prefix: &str) -> String {
+    format!("{}_{}", prefix, Uuid::new_v4().simple())
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn convert_usage(usage: &Value) -> ResponsesUsage {
+    let get = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    let input_tokens = get("prompt_tokens");
+    let output_tokens = get("completion_tokens");
+    let reasoning_tokens = usage
+        .pointer("/completion_tokens_details/reasoning_tokens")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32);
+    ResponsesUsage {
+        input_tokens,
+        output_tokens,
+        total_tokens: usage
+            .get("total_tokens")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32)
+            .unwrap_or(input_tokens + output_tokens),
+        output_tokens_details: reasoning_tokens
+            .map(|reasoning_tokens| OutputTokensDetails { reasoning_tokens }),
+    }
+}
+
+/// 根据 `finish_reason` 得到响应状态和未完成原因
+fn response_status(finish_reason: Option<&str>) -> (&'static str, Option<Value>) {
+    match finish_reason {
+        Some("length") => ("incomplete", Some(json!({"reason": "max_output_tokens"}))),
+        Some("content_filter") => ("incomplete", Some(json!({"reason": "content_filter"}))),
+        _ => ("completed", None),
+    }
+}
+
+/// 将 Chat Completions 非流式响应转换为 Responses 响应
+pub fn convert_openai_to_responses(response: &Value, fallback_model: &str) -> ResponsesResponse {
+    let choice = response.pointer("/choices/0");
+    let message = choice.and_then(|c| c.get("message"));
+    let mut output = Vec::new();
+
+    if let Some(reasoning) = message
+        .and_then(|m| m.get("reasoning_content"))
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+    {
+        output.push(ResponseOutputItem::Reasoning {
+            id: new_id("rs"),
+            summary: vec![ReasoningSummary::text(reasoning)],
+        });
+    }
+
+    if let Some(content) = message
+        .and_then(|m| m.get("content"))
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+    {
+        output.push(ResponseOutputItem::Message {
+            id: new_id("msg"),
+            role: "assistant".to_string(),
+            status: "completed".to_string(),
+            content: vec![ResponseOutputContent::OutputText {
+                text: content.to_string(),
+                annotations: Vec::new(),
+            }],
+        });
+    }
+
+    for tc in message
+        .and_then(|m| m.get("tool_calls"))
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        output.push(ResponseOutputItem::FunctionCall {
+            id: new_id("fc"),
+            call_id: tc
+                .get("id")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| new_id("call")),
+            name: tc
+                .pointer("/function/name")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            arguments: tc
+                .pointer("/function/arguments")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            status: "completed".to_string(),
+        });
+    }
+
+    let (status, incomplete_details) = response_status(
+        choice
+            .and_then(|c| c.get("finish_reason"))
+            .and_then(|v| v.as_str()),
+    );
+
+    ResponsesResponse {
+        id: new_id("resp"),
+        object: "response".to_string(),
+        created_at: response
+            .get("created")
+            .and_then(|v| v.as_u64())
+            .unwrap_or_else(now_secs),
+        model: response
+            .get("model")
+            .and_then(|v| v.as_str())
+            .unwrap_or(fallback_model)
+            .to_string(),
+        status: status.to_string(),
+        output,
+        usage: response.get("usage").map(convert_usage),
+        incomplete_details,
+    }
+}
+
+/// 流式输出中正在构建的输出项
+enum PendingItem {
+    Reasoning {
+        id: String,
+        text: String,
+    },
+    Message {
+        id: String,
+        text: String,
+    },
+    FunctionCall {
+        id: String,
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+}
+
+/// Chat Completions 流式响应（SSE）到 Responses 流式事件的转换器
+///
+/// 输入为任意切分的 SSE 字节，输出为完整的 Responses SSE 事件
+/// （`response.created`、`response.output_text.delta`、`response.completed` 等）。
+pub struct ResponsesStreamConverter {
+    response_id: String,
+    model: String,
+    created_at: u64,
+    sequence_number: u64,
+    /// 未处理完的 SSE 字节（可能在多字节字符中间被切分）
+    buffer: Vec<u8>,
+    started: bool,
+    finished: bool,
+    /// 已结束的输出项（按输出顺序）
+    output: Vec<(usize, ResponseOutputItem)>,
+    /// 正在构建的输出项（输出序号 -> 输出项）
+    pending: BTreeMap<usize, PendingItem>,
+    next_output_index: usize,
+    reasoning_index: Option<usize>,
+    message_index: Option<usize>,
+    /// Chat 工具调用序号 -> 输出序号
+    tool_call_indices: BTreeMap<u64, usize>,
+    finish_reason: Option<String>,
+    usage: Option<ResponsesUsage>,
+}
+
+impl ResponsesStreamConverter {
+    pub fn new(model: &str) -> Self {
+        Self {
+            response_id: new_id("resp"),
+            model: model.to_string(),
+            created_at: now_secs(),
+            sequence_number: 0,
+            buffer: Vec::new(),
+            started: false,
+            finished: false,
+            output: Vec::new(),
+            pending: BTreeMap::new(),
+            next_output_index: 0,
+            reasoning_index: None,
+            message_index: None,
+            tool_call_indices: BTreeMap::new(),
+            finish_reason: None,
+            usage: None,
+        }
+    }
+
+    /// 处理一段 Chat Completions SSE 字节，返回转换后的事件
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim();
+            if data == "[DONE]" {
+                events.extend(self.finish());
+            } else if let Ok(chunk) = serde_json::from_str::<Value>(data) {
+                events.extend(self.handle_chunk(&chunk));
+            }
+        }
+        events
+    }
+
+    /// 结束流，关闭所有输出项并发送 `response.completed`（已结束时返回空）
+    pub fn finish(&mut self) -> Vec<String> {
+        if self.finished {
+            return Vec::new();
+        }
+        let mut events = self.start();
+        let open: Vec<usize> = self.pending.keys().copied().collect();
+        for index in open {
+            events.extend(self.close_item(index));
+        }
+        self.finished = true;
+
+        let (status, incomplete_details) = response_status(self.finish_reason.as_deref());
+        let event_type = if status == "completed" {
+            "response.completed"
+        } else {
+            "response.incomplete"
+        };
+        let mut response = self.response_object(status);
+        response.incomplete_details = incomplete_details;
+        response.usage = self.usage.clone();
+        events.push(self.event(event_type, json!({"response": response})));
+        events
+    }
+
+    fn handle_chunk(&mut self, chunk: &Value) -> Vec<String> {
+        if self.finished {
+            return Vec::new();
+        }
+        if let Some(error) = chunk.get("error") {
+            self.finished = true;
+            let message = error
+                .get("message")
+                .and_then(|v| v.as_str())
+                .unwrap_or("upstream error");
+            let code = error.get("code").cloned().unwrap_or(Value::Null);
+            return vec![self.event("error", json!({"code": code, "message": message}))];
+        }
+
+        let mut events = self.start();
+        if let Some(model) = chunk.get("model").and_then(|v| v.as_str()) {
+            self.model = model.to_string();
+        }
+        if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+            self.usage = Some(convert_usage(usage));
+        }
+
+        let Some(choice) = chunk.pointer("/choices/0") else {
+            return events;
+        };
+        let delta = choice.get("delta").cloned().unwrap_or_default();
+
+        if let Some(text) = delta
+            .get("reasoning_content")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+        {
+            events.extend(self.reasoning_delta(text));
+        }
+        if let Some(text) = delta
+            .get("content")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+        {
+            events.extend(self.close_reasoning());
+            events.extend(self.text_delta(text));
+        }
+        for tc in delta
+            .get("tool_calls")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            events.extend(self.close_reasoning());
+            if let Some(index) = self.message_index.take() {
+                events.extend(self.close_item(index));
+            }
+            events.extend(self.tool_call_delta(tc));
+        }
+
+        if let Some(reason) = choice.get("finish_reason").and_then(|v| v.as_str()) {
+            self.finish_reason = Some(reason.to_string());
+        }
+        events
+    }
+
+    fn start(&mut self) -> Vec<String> {
+        if self.started {
+            return Vec::new();
+        }
+        self.started = true;
+        let response = self.response_object("in_progress");
+        vec![
+            self.event("response.created", json!({"response": response})),
+            self.event("response.in_progress", json!({"response": response})),
+        ]
+    }
+
+    fn reasoning_delta(&mut self, text: &str) -> Vec<String> {
+        let mut events = Vec::new();
+        let index = match self.reasoning_index {
+            Some(index) => index,
+            None => {
+                let id = new_id("rs");
+                let index = self.open_item(
+                    PendingItem::Reasoning {
+                        id: id.clone(),
+                        text: String::new(),
+                    },
+                    &mut events,
+                );
+                events.push(self.event(
+                    "response.reasoning_summary_part.added",
+                    json!({
+                        "item_id": id,
+                        "output_index": index,
+                        "summary_index": 0,
+                        "part": {"type": "summary_text", "text": ""},
+                    }),
+                ));
+                self.reasoning_index = Some(index);
+                index
+            }
+        };
+        if let Some(PendingItem::Reasoning { id, text: buf }) = self.pending.get_mut(&index) {
+            buf.push_str(text);
+            let id = id.clone();
+            events.push(self.event(
+                "response.reasoning_summary_text.delta",
+                json!({"item_id": id, "output_index": index, "summary_index": 0, "delta": text}),
+            ));
+        }
+        events
+    }
+
+    fn text_delta(&mut self, text: &str) -> Vec<String> {
+        let mut events = Vec::new();
+        let index = match self.message_index {
+            Some(index) => index,
+            None => {
+                let id = new_id("msg");
+                let index = self.open_item(
+                    PendingItem::Message {
+                        id: id.clone(),
+                        text: String::new(),
+                    },
+                    &mut events,
+                );
+                events.push(self.event(
+                    "response.content_part.added",
+                    json!({
+                        "item_id": id,
+                        "output_index": index,
+                        "content_index": 0,
+                        "part": {"type": "output_text", "text": "", "annotations": []},
+                    }),
+                ));
+                self.message_index = Some(index);
+                index
+            }
+        };
+        if let Some(PendingItem::Message { id, text: buf }) = self.pending.get_mut(&index) {
+            buf.push_str(text);
+            let id = id.clone();
+            events.push(self.event(
+                "response.output_text.delta",
+                json!({"item_id": id, "output_index": index, "content_index": 0, "delta": text}),
+            ));
+        }
+        events
+    }
+
+    fn tool_call_delta(&mut self, tc: &Value) -> Vec<String> {
+        let mut events = Vec::new();
+        let tc_index = tc.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+        let index = match self.tool_call_indices.get(&tc_index) {
+            Some(&index) => index,
+            None => {
+                let item = PendingItem::FunctionCall {
+                    id: new_id("fc"),
+                    call_id: tc
+                        .get("id")
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string)
+                        .unwrap_or_else(|| new_id("call")),
+                    name: tc
+                        .pointer("/function/name")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    arguments: String::new(),
+                };
+                let index = self.open_item(item, &mut events);
+                self.tool_call_indices.insert(tc_index, index);
+                index
+            }
+        };
+        let delta = tc
+            .pointer("/function/arguments")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty());
+        if let (Some(delta), Some(PendingItem::FunctionCall { id, arguments, .. })) =
+            (delta, self.pending.get_mut(&index))
+        {
+            arguments.push_str(delta);
+            let id = id.clone();
+            events.push(self.event(
+                "response.function_call_arguments.delta",
+                json!({"item_id": id, "output_index": index, "delta": delta}),
+            ));
+        }
+        events
+    }
+
+    fn close_reasoning(&mut self) -> Vec<String> {
+        match self.reasoning_index.take() {
+            Some(index) => self.close_item(index),
+            None => Vec::new(),
+        }
+    }
+
+    fn open_item(&mut self, item: PendingItem, events: &mut Vec<String>) -> usize {
+        let index = self.next_output_index;
+        self.next_output_index += 1;
+        let added = match &item {
+            PendingItem::Reasoning { id, .. } => {
+                json!({"type": "reasoning", "id": id, "summary": []})
+            }
+            PendingItem::Message { id, .. } => json!({
+                "type": "message",
+                "id": id,
+                "role": "assistant",
+                "status": "in_progress",
+                "content": [],
+            }),
+            PendingItem::FunctionCall {
+                id, call_id, name, ..
+            } => json!({
+                "type": "function_call",
+                "id": id,
+                "call_id": call_id,
+                "name": name,
+                "arguments": "",
+                "status": "in_progress",
+            }),
+        };
+        self.pending.insert(index, item);
+        events.push(self.event(
+            "response.output_item.added",
+            json!({"output_index": index, "item": added}),
+        ));
+        index
+    }
+
+    fn close_item(&mut self, index: usize) -> Vec<String> {
+        let Some(item) = self.pending.remove(&index) else {
+            return Vec::new();
+        };
+        let mut events = Vec::new();
+        let done = match item {
+            PendingItem::Reasoning { id, text } => {
+                events.push(self.event(
+                    "response.reasoning_summary_text.done",
+                    json!({"item_id": id, "output_index": index, "summary_index": 0, "text": text}),
+                ));
+                events.push(self.event(
+                    "response.reasoning_summary_part.done",
+                    json!({
+                        "item_id": id,
+                        "output_index": index,
+                        "summary_index": 0,
+                        "part": {"type": "summary_text", "text": text},
+                    }),
+                ));
+                ResponseOutputItem::Reasoning {
+                    id,
+                    summary: vec![ReasoningSummary::text(text)],
+                }
+            }
+            PendingItem::Message { id, text } => {
+                events.push(self.event(
+                    "response.output_text.done",
+                    json!({"item_id": id, "output_index": index, "content_index": 0, "text": text}),
+                ));
+                events.push(self.event(
+                    "response.content_part.done",
+                    json!({
+                        "item_id": id,
+                        "output_index": index,
+                        "content_index": 0,
+                        "part": {"type": "output_text", "text": text, "annotations": []},
+                    }),
+                ));
+                ResponseOutputItem::Message {
+                    id,
+                    role: "assistant".to_string(),
+                    status: "completed".to_string(),
+                    content: vec![ResponseOutputContent::OutputText {
+                        text,
+                        annotations: Vec::new(),
+                    }],
+                }
+            }
+            PendingItem::FunctionCall {
+                id,
+                call_id,
+                name,
+                arguments,
+            } => {
+                events.push(self.event(
+                    "response.function_call_arguments.done",
+                    json!({"item_id": id, "output_index": index, "arguments": arguments}),
+                ));
+                ResponseOutputItem::FunctionCall {
+                    id,
+                    call_id,
+                    name,
+                    arguments,
+                    status: "completed".to_string(),
+                }
+            }
+        };
+        events.push(self.event(
+            "response.output_item.done",
+            json!({"output_index": index, "item": done}),
+        ));
+        self.output.push((index, done));
+        events
+    }
+
+    fn response_object(&self, status: &str) -> ResponsesResponse {
+        let mut output = self.output.clone();
+        output.sort_by_key(|(index, _)| *index);
+        ResponsesResponse {
+            id: self.response_id.clone(),
+            object: "response".to_string(),
+            created_at: self.created_at,
+            model: self.model.clone(),
+            status: status.to_string(),
+            output: output.into_iter().map(|(_, item)| item).collect(),
+            usage: None,
+            incomplete_details: None,
+        }
+    }
+
+    fn event(&mut self, event_type: &str, mut data: Value) -> String {
+        if let Some(obj) = data.as_object_mut() {
+            obj.insert("type".to_string(), json!(event_type));
+            obj.insert("sequence_number".to_string(), json!(self.sequence_number));
+        }
+        self.sequence_number += 1;
+        format!("event: {}\ndata: {}\n\n", event_type, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_events(events: &[String]) -> Vec<Value> {
+        events
+            .iter()
+            .filter_map(|e| e.lines().find_map(|l| l.strip_prefix("data: ")))
+            .map(|d| serde_json::from_str(d).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_convert_request_with_tool_round_trip() {
+        let request: ResponsesRequest = serde_json::from_value(json!({
+            "model": "gpt-5",
+            "instructions": "Be brief.",
+            "input": [
+                {"role": "user", "content": "天气如何？"},
+                {"type": "function_call", "call_id": "call_1", "name": "get_weather", "arguments": "{\"city\":\"北京\"}"},
+                {"type": "function_call_output", "call_id": "call_1", "output": "晴"},
+                {"type": "reasoning", "summary": []},
+                {"type": "web_search_call", "id": "ws_1"}
+            ],
+            "tools": [
+                {"type": "function", "name": "get_weather", "parameters": {"type": "object"}},
+                {"type": "web_search"}
+            ],
+            "tool_choice": {"type": "function", "name": "get_weather"},
+            "reasoning": {"effort": "high"},
+            "text": {"format": {"type": "json_schema", "name": "answer", "schema": {"type": "object"}}},
+            "max_output_tokens": 256,
+            "stream": true
+        }))
+        .unwrap();
+
+        let chat = convert_responses_to_openai(&request);
+        let roles: Vec<_> = chat.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "tool"]);
+        assert_eq!(
+            chat.messages[2].tool_calls.as_ref().unwrap()[0].id,
+            "call_1"
+        );
+        assert_eq!(chat.messages[3].tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(chat.tools.as_ref().unwrap().len(), 1);
+        assert_eq!(
+            chat.tool_choice,
+            Some(json!({"type": "function", "function": {"name": "get_weather"}}))
+        );
+        assert_eq!(chat.reasoning_effort.as_deref(), Some("high"));
+        assert_eq!(
+            chat.response_format.unwrap()["json_schema"]["name"],
+            json!("answer")
+        );
+        assert_eq!(chat.max_tokens, Some(256));
+        assert_eq!(chat.stream_options, Some(json!({"include_usage": true})));
+    }
+
+    #[test]
+    fn test_convert_response_with_reasoning_and_tool_calls() {
+        let chat = json!({
+            "id": "chatcmpl-1",
+            "created": 1700000000,
+            "model": "gpt-5",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "reasoning_content": "先查天气",
+                    "content": "我来查一下",
+                    "tool_calls": [{"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{}"}}]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        });
+
+        let response = convert_openai_to_responses(&chat, "fallback");
+        assert_eq!(response.status, "completed");
+        assert_eq!(response.model, "gpt-5");
+        assert_eq!(response.output.len(), 3);
+        assert!(matches!(
+            response.output[0],
+            ResponseOutputItem::Reasoning { .. }
+        ));
+        assert!(matches!(
+            &response.output[2],
+            ResponseOutputItem::FunctionCall { call_id, .. } if call_id == "call_1"
+        ));
+        assert_eq!(response.usage.unwrap().input_tokens, 10);
+    }
+
+    #[test]
+    fn test_stream_converter_emits_response_events() {
+        let mut converter = ResponsesStreamConverter::new("gpt-5");
+        let sse = concat!(
+            "data: {\"model\":\"gpt-5\",\"choices\":[{\"index\":0,\"delta\":{\"reasoning_content\":\"想\"}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"你\"}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"好\"},\"finish_reason\":\"length\"}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":3,\"completion_tokens\":2}}\n\n",
+            "data: [DONE]\n\n"
+        );
+        // 任意切分的输入也能正确解析
+        let (a, b) = sse.split_at(37);
+        let mut events = converter.push(a.as_bytes());
+        events.extend(converter.push(b.as_bytes()));
+        assert!(converter.finish().is_empty());
+
+        let events = parse_events(&events);
+        let types: Vec<_> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+        assert_eq!(types.first(), Some(&"response.created"));
+        assert_eq!(types.last(), Some(&"response.incomplete"));
+        assert!(types.contains(&"response.reasoning_summary_text.delta"));
+        assert_eq!(
+            types
+                .iter()
+                .filter(|t| **t == "response.output_text.delta")
+                .count(),
+            2
+        );
+
+        let seqs: Vec<_> = events
+            .iter()
+            .map(|e| e["sequence_number"].as_u64().unwrap())
+            .collect();
+        assert!(seqs.windows(2).all(|w| w[1] == w[0] + 1));
+
+        let done = events.last().unwrap();
+        assert_eq!(done["response"]["output"][1]["content"][0]["text"], "你好");
+        assert_eq!(done["response"]["usage"]["total_tokens"], 5);
+        assert_eq!(
+            done["response"]["incomplete_details"]["reason"],
+            "max_output_tokens"
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! 自动标签引擎
+//!
+//! 在 Flow 完成或失败时，按规则自动为 Flow 打标签。
+//! 每条规则由一个过滤表达式（复用 `FilterExpr` 语法）和需要应用的标签组成，
+//! 例如 `~latency >5s => slow`。
+//!
+//! 规则在更新配置时一次性解析编译，之后每个 Flow 只需执行已编译的判断函数，
+//! 以保证在每个完成的 Flow 上运行时的开销足够低。
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::filter_parser::{FilterParseError, FilterParser};
+use super::models::LLMFlow;
+
+// ============================================================================
+// 错误类型
+// ============================================================================
+
+/// 自动标签错误
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum AutoTagError {
+    /// 规则条件无效
+    #[error("规则 #{index} 的条件无效: {source}")]
+    InvalidCondition {
+        index: usize,
+        #[source]
+        source: FilterParseError,
+    },
+}
+
+// ============================================================================
+// 配置结构
+// ============================================================================
+
+/// 自动标签规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoTagRule {
+    /// 规则名称（用于日志和展示）
+    #[serde(default)]
+    pub name: String,
+    /// 匹配条件（过滤表达式，如 `~latency >5s`）
+    pub condition: String,
+    /// 匹配时应用的标签
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 匹配时是否自动收藏
+    #[serde(default)]
+    pub star: bool,
+    /// 是否启用
+    #[serde(default = "default_rule_enabled")]
+    pub enabled: bool,
+}
+
+fn default_rule_enabled() -> bool {
+    true
+}
+
+/// 自动标签配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoTagConfig {
+    /// 是否启用自动标签
+    #[serde(default = "default_auto_tag_enabled")]
+    pub enabled: bool,
+    /// 规则列表（按顺序评估，所有匹配的规则都会生效）
+    #[serde(default)]
+    pub rules: Vec<AutoTagRule>,
+}
+
+fn default_auto_tag_enabled() -> bool {
+    true
+}
+
+impl Default for AutoTagConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_auto_tag_enabled(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+// ============================================================================
+// 规则引擎
+// ============================================================================
+
+/// 已编译的规则
+struct CompiledRule {
+    /// 规则索引（在配置中的位置）
+    index: usize,
+    /// 编译后的匹配函数
+    matcher: Box<dyn Fn(&LLMFlow) -> bool + Send + Sync>,
+}
+
+/// 自动标签引擎
+///
+/// 持有配置和编译后的规则。更新配置时会先校验所有规则，
+/// 只有全部合法时才替换当前规则集。
+#[derive(Default)]
+pub struct AutoTagger {
+    /// 当前配置
+    config: AutoTagConfig,
+    /// 编译后的规则
+    compiled: Vec<CompiledRule>,
+}
+
+impl std::fmt::Debug for AutoTagger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AutoTagger")
+            .field("config", &self.config)
+            .field("compiled_rules", &self.compiled.len())
+            .finish()
+    }
+}
+
+impl AutoTagger {
+    /// 从配置创建自动标签引擎
+    ///
+    /// # 错误
+    /// 任一启用的规则条件无法解析时返回 `AutoTagError::InvalidCondition`
+    pub fn new(config: AutoTagConfig) -> Result<Self, AutoTagError> {
+        let compiled = Self::compile_rules(&config)?;
+        Ok(Self { config, compiled })
+    }
+
+    /// 获取当前配置
+    pub fn config(&self) -> &AutoTagConfig {
+        &self.config
+    }
+
+    /// 替换配置（热重载）
+    ///
+    /// 校验失败时保留原有规则不变。
+    pub fn update(&mut self, config: AutoTagConfig) -> Result<(), AutoTagError> {
+        let compiled = Self::compile_rules(&config)?;
+        self.config = config;
+        self.compiled = compiled;
+        Ok(())
+    }
+
+    /// 编译配置中所有启用的规则
+    fn compile_rules(config: &AutoTagConfig) -> Result<Vec<CompiledRule>, AutoTagError> {
+        config
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.enabled)
+            .map(|(index, rule)| {
+                let expr = FilterParser::parse(&rule.condition)
+                    .map_err(|source| AutoTagError::InvalidCondition { index, source })?;
+                Ok(CompiledRule {
+                    index,
+                    matcher: FilterParser::compile(&expr),
+                })
+            })
+            .collect()
+    }
+
+    /// 对 Flow 应用自动标签规则
+    ///
+    /// 匹配的标签写入 `annotations.auto_tags`（去重），不会修改手动标签。
+    ///
+    /// # 返回
+    /// 命中的规则数量
+    pub fn apply(&self, flow: &mut LLMFlow) -> usize {
+        if !self.config.enabled || self.compiled.is_empty() {
+            return 0;
+        }
+
+        let mut matched = 0;
+        for compiled in &self.compiled {
+            if !(compiled.matcher)(flow) {
+                continue;
+            }
+            matched += 1;
+
+            let rule = &self.config.rules[compiled.index];
+            for tag in &rule.tags {
+                if !flow.annotations.auto_tags.contains(tag) {
+                    flow.annotations.auto_tags.push(tag.clone());
+                }
+            }
+            if rule.star {
+                flow.annotations.starred = true;
+            }
+        }
+
+        matched
+    }
+}
+
+// ============================================================================
+// 测试模块
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::models::{
+        FlowError, FlowErrorType, FlowMetadata, FlowState, FlowType, LLMRequest,
+    };
+
+    fn create_test_flow(duration_ms: u64) -> LLMFlow {
+        let request = LLMRequest {
+            model: "gpt-4".to_string(),
+            ..Default::default()
+        };
+        let mut flow = LLMFlow::new(
+            "flow-1".to_string(),
+            FlowType::ChatCompletions,
+            request,
+            FlowMetadata::default(),
+        );
+        flow.state = FlowState::Completed;
+        flow.timestamps.duration_ms = duration_ms;
+        flow
+    }
+
+    fn rule(condition: &str, tags: &[&str]) -> AutoTagRule {
+        AutoTagRule {
+            name: String::new(),
+            condition: condition.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            star: false,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_slow_rule_applies_auto_tag() {
+        let tagger = AutoTagger::new(AutoTagConfig {
+            enabled: true,
+            rules: vec![rule("~latency >5s", &["slow"])],
+        })
+        .unwrap();
+
+        let mut slow = create_test_flow(6000);
+        assert_eq!(tagger.apply(&mut slow), 1);
+        assert_eq!(slow.annotations.auto_tags, vec!["slow".to_string()]);
+        assert!(slow.annotations.tags.is_empty());
+
+        let mut fast = create_test_flow(100);
+        assert_eq!(tagger.apply(&mut fast), 0);
+        assert!(fast.annotations.auto_tags.is_empty());
+    }
+
+    #[test]
+    fn test_star_and_dedup() {
+        let mut auth_rule = rule("~e", &["errored-auth"]);
+        auth_rule.star = true;
+        let tagger = AutoTagger::new(AutoTagConfig {
+            enabled: true,
+            rules: vec![auth_rule, rule("~e", &["errored-auth"])],
+        })
+        .unwrap();
+
+        let mut flow = create_test_flow(10);
+        flow.error = Some(FlowError::new(FlowErrorType::Authentication, "denied"));
+        assert_eq!(tagger.apply(&mut flow), 2);
+        assert_eq!(flow.annotations.auto_tags, vec!["errored-auth".to_string()]);
+        assert!(flow.annotations.starred);
+    }
+
+    #[test]
+    fn test_invalid_rule_keeps_previous_rules() {
+        let mut tagger = AutoTagger::new(AutoTagConfig {
+            enabled: true,
+            rules: vec![rule("~latency >5s", &["slow"])],
+        })
+        .unwrap();
+
+        let result = tagger.update(AutoTagConfig {
+            enabled: true,
+            rules: vec![rule("~latency >1s", &["a"]), rule("~unknown", &["b"])],
+        });
+        assert!(matches!(
+            result,
+            Err(AutoTagError::InvalidCondition { index: 1, .. })
+        ));
+        assert_eq!(tagger.config().rules[0].tags, vec!["slow".to_string()]);
+    }
+
+    #[test]
+    fn test_disabled_rules_and_config() {
+        let mut disabled_rule = rule("~unknown", &["x"]);
+        disabled_rule.enabled = false;
+        // 禁用的规则不参与编译，即使条件非法
+        let tagger = AutoTagger::new(AutoTagConfig {
+            enabled: false,
+            rules: vec![disabled_rule, rule("~latency >1s", &["slow"])],
+        })
+        .unwrap();
+
+        let mut flow = create_test_flow(6000);
+        assert_eq!(tagger.apply(&mut flow), 0);
+        assert!(flow.annotations.auto_tags.is_empty());
+    }
+}
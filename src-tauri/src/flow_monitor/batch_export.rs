@@ -0,0 +1,574 @@
+//! 批处理 API 导出
+//!
+//! 将捕获的 Flow 请求重建为 OpenAI / Anthropic 批处理 API 的 JSONL 输入，
+//! 每行一个 `{custom_id, method, url, body}` 对象，`custom_id` 为 Flow ID。
+//!
+//! 重建规则：
+//! - 以捕获的原始请求体为准，同格式的请求体原样保留（多模态、工具等字段不丢失）
+//! - 跨格式的请求体在 JSON 层面转换文本、图片、工具定义、工具调用和工具结果
+//! - 批处理不支持流式，`stream` 与 `stream_options` 会被移除
+//! - 非 Chat Completions / Messages 类型的 Flow 会被跳过
+
+use serde_json::{json, Map, Value};
+
+use super::models::{FlowType, LLMFlow};
+
+/// OpenAI 批处理请求的目标端点
+pub const OPENAI_BATCH_URL: &str = "/v1/chat/completions";
+
+/// Anthropic 批处理请求的目标端点
+pub const ANTHROPIC_BATCH_URL: &str = "/v1/messages";
+
+/// OpenAI 请求未指定 `max_tokens` 时，转换为 Anthropic 请求使用的默认值
+const DEFAULT_ANTHROPIC_MAX_TOKENS: u64 = 4096;
+
+/// 批处理目标格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchTarget {
+    /// OpenAI Batch API
+    OpenAi,
+    /// Anthropic Message Batches API
+    Anthropic,
+}
+
+impl BatchTarget {
+    fn url(self) -> &'static str {
+        match self {
+            BatchTarget::OpenAi => OPENAI_BATCH_URL,
+            BatchTarget::Anthropic => ANTHROPIC_BATCH_URL,
+        }
+    }
+}
+
+/// 将多个 Flow 导出为批处理 JSONL
+pub fn export_batch_jsonl(flows: &[LLMFlow], target: BatchTarget) -> String {
+    flows
+        .iter()
+        .filter_map(|flow| batch_line(flow, target))
+        .filter_map(|line| serde_json::to_string(&line).ok())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 构建单个 Flow 的批处理请求行
+///
+/// Flow 类型不受支持或请求体不是 JSON 对象时返回 `None`。
+pub fn batch_line(flow: &LLMFlow, target: BatchTarget) -> Option<Value> {
+    let source = match flow.flow_type {
+        // Responses 请求在捕获时已转换为 Chat Completions 格式
+        FlowType::ChatCompletions | FlowType::Responses => BatchTarget::OpenAi,
+        FlowType::AnthropicMessages => BatchTarget::Anthropic,
+        _ => return None,
+    };
+    let body = flow.request.body.as_object()?;
+
+    let mut body = match (source, target) {
+        (BatchTarget::OpenAi, BatchTarget::Anthropic) => openai_to_anthropic(body),
+        (BatchTarget::Anthropic, BatchTarget::OpenAi) => anthropic_to_openai(body),
+        _ => body.clone(),
+    };
+    body.remove("stream");
+    body.remove("stream_options");
+    if !flow.request.model.is_empty() {
+        body.insert("model".to_string(), json!(flow.request.model));
+    }
+
+    Some(json!({
+        "custom_id": flow.id,
+        "method": "POST",
+        "url": target.url(),
+        "body": body,
+    }))
+}
+
+// ============================================================================
+// OpenAI -> Anthropic
+// ============================================================================
+
+fn openai_to_anthropic(body: &Map<String, Value>) -> Map<String, Value> {
+    let mut out = Map::new();
+    copy_fields(
+        body,
+        &mut out,
+        &["model", "temperature", "top_p", "metadata"],
+    );
+
+    let max_tokens = body
+        .get("max_tokens")
+        .or_else(|| body.get("max_completion_tokens"))
+        .cloned()
+        .unwrap_or_else(|| json!(DEFAULT_ANTHROPIC_MAX_TOKENS));
+    out.insert("max_tokens".to_string(), max_tokens);
+
+    match body.get("stop") {
+        Some(Value::String(stop)) => {
+            out.insert("stop_sequences".to_string(), json!([stop]));
+        }
+        Some(stop @ Value::Array(_)) => {
+            out.insert("stop_sequences".to_string(), stop.clone());
+        }
+        _ => {}
+    }
+
+    let mut system = Vec::new();
+    let mut messages: Vec<Value> = Vec::new();
+    for message in body
+        .get("messages")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let content = message.get("content");
+        match message.get("role").and_then(Value::as_str) {
+            Some("system") | Some("developer") => system.push(text_of(content)),
+            Some("assistant") => {
+                let mut blocks = openai_content_blocks(content);
+                for call in message
+                    .get("tool_calls")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    let function = call.get("function");
+                    let input = function
+                        .and_then(|f| f.get("arguments"))
+                        .and_then(Value::as_str)
+                        .and_then(|args| serde_json::from_str::<Value>(args).ok())
+                        .filter(Value::is_object)
+                        .unwrap_or_else(|| json!({}));
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.get("id").cloned().unwrap_or(Value::Null),
+                        "name": function.and_then(|f| f.get("name")).cloned().unwrap_or(Value::Null),
+                        "input": input,
+                    }));
+                }
+                push_anthropic_message(&mut messages, "assistant", blocks);
+            }
+            Some("tool") => {
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.get("tool_call_id").cloned().unwrap_or(Value::Null),
+                    "content": text_of(content),
+                });
+                push_anthropic_message(&mut messages, "user", vec![block]);
+            }
+            _ => {
+                push_anthropic_message(&mut messages, "user", openai_content_blocks(content));
+            }
+        }
+    }
+    let system: Vec<String> = system.into_iter().filter(|s| !s.is_empty()).collect();
+    if !system.is_empty() {
+        out.insert("system".to_string(), json!(system.join("\n\n")));
+    }
+    out.insert("messages".to_string(), Value::Array(messages));
+
+    if let Some(tools) = body.get("tools").and_then(Value::as_array) {
+        let tools: Vec<Value> = tools
+            .iter()
+            .filter_map(|tool| tool.get("function").and_then(Value::as_object))
+            .map(|function| {
+                let mut tool = Map::new();
+                copy_fields(function, &mut tool, &["name", "description"]);
+                let schema = function
+                    .get("parameters")
+                    .cloned()
+                    .unwrap_or_else(|| json!({"type": "object"}));
+                tool.insert("input_schema".to_string(), schema);
+                Value::Object(tool)
+            })
+            .collect();
+        out.insert("tools".to_string(), Value::Array(tools));
+    }
+
+    let tool_choice = match body.get("tool_choice") {
+        Some(Value::String(choice)) => match choice.as_str() {
+            "required" => Some(json!({"type": "any"})),
+            "none" => Some(json!({"type": "none"})),
+            _ => Some(json!({"type": "auto"})),
+        },
+        Some(choice @ Value::Object(_)) => choice
+            .pointer("/function/name")
+            .map(|name| json!({"type": "tool", "name": name})),
+        _ => None,
+    };
+    if let Some(tool_choice) = tool_choice {
+        out.insert("tool_choice".to_string(), tool_choice);
+    }
+
+    out
+}
+
+/// 将 OpenAI 消息内容转换为 Anthropic 内容块
+fn openai_content_blocks(content: Option<&Value>) -> Vec<Value> {
+    match content {
+        Some(Value::String(text)) if !text.is_empty() => {
+            vec![json!({"type": "text", "text": text})]
+        }
+        Some(Value::Array(parts)) => parts.iter().map(openai_part_to_block).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn openai_part_to_block(part: &Value) -> Value {
+    if part.get("type").and_then(Value::as_str) != Some("image_url") {
+        return part.clone();
+    }
+    let url = part
+        .pointer("/image_url/url")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let data_url = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(";base64,"));
+    match data_url {
+        Some((media_type, data)) => json!({
+            "type": "image",
+            "source": {"type": "base64", "media_type": media_type, "data": data},
+        }),
+        None => json!({"type": "image", "source": {"type": "url", "url": url}}),
+    }
+}
+
+/// 追加 Anthropic 消息，合并相邻的同角色消息以满足角色交替要求
+fn push_anthropic_message(messages: &mut Vec<Value>, role: &str, blocks: Vec<Value>) {
+    if blocks.is_empty() {
+        return;
+    }
+    if let Some(last) = messages.last_mut() {
+        if last.get("role").and_then(Value::as_str) == Some(role) {
+            if let Some(content) = last.get_mut("content").and_then(Value::as_array_mut) {
+                content.extend(blocks);
+                return;
+            }
+        }
+    }
+    messages.push(json!({"role": role, "content": blocks}));
+}
+
+// ============================================================================
+// Anthropic -> OpenAI
+// ============================================================================
+
+fn anthropic_to_openai(body: &Map<String, Value>) -> Map<String, Value> {
+    let mut out = Map::new();
+    copy_fields(
+        body,
+        &mut out,
+        &["model", "max_tokens", "temperature", "top_p", "metadata"],
+    );
+    if let Some(stop) = body.get("stop_sequences") {
+        out.insert("stop".to_string(), stop.clone());
+    }
+
+    let mut messages = Vec::new();
+    let system = text_of(body.get("system"));
+    if !system.is_empty() {
+        messages.push(json!({"role": "system", "content": system}));
+    }
+
+    for message in body
+        .get("messages")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let role = message
+            .get("role")
+            .and_then(Value::as_str)
+            .unwrap_or("user");
+        let blocks = match message.get("content") {
+            Some(Value::Array(blocks)) => blocks.as_slice(),
+            Some(content) => {
+                messages.push(json!({"role": role, "content": content}));
+                continue;
+            }
+            None => continue,
+        };
+
+        if role == "assistant" {
+            let text = blocks
+                .iter()
+                .filter(|b| b.get("type").and_then(Value::as_str) == Some("text"))
+                .filter_map(|b| b.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("");
+            let tool_calls: Vec<Value> = blocks
+                .iter()
+                .filter(|b| b.get("type").and_then(Value::as_str) == Some("tool_use"))
+                .map(|b| {
+                    let arguments = b.get("input").cloned().unwrap_or_else(|| json!({}));
+                    json!({
+                        "id": b.get("id").cloned().unwrap_or(Value::Null),
+                        "type": "function",
+                        "function": {
+                            "name": b.get("name").cloned().unwrap_or(Value::Null),
+                            "arguments": arguments.to_string(),
+                        },
+                    })
+                })
+                .collect();
+            let mut converted = json!({"role": "assistant", "content": text});
+            if !tool_calls.is_empty() {
+                converted["tool_calls"] = Value::Array(tool_calls);
+            }
+            messages.push(converted);
+            continue;
+        }
+
+        // 工具结果必须紧跟在 assistant 消息之后，先于其余用户内容输出
+        let mut parts = Vec::new();
+        for block in blocks {
+            match block.get("type").and_then(Value::as_str) {
+                Some("tool_result") => messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": block.get("tool_use_id").cloned().unwrap_or(Value::Null),
+                    "content": text_of(block.get("content")),
+                })),
+                Some("image") => parts.push(anthropic_image_to_part(block)),
+                _ => parts.push(block.clone()),
+            }
+        }
+        if !parts.is_empty() {
+            messages.push(json!({"role": role, "content": parts}));
+        }
+    }
+    out.insert("messages".to_string(), Value::Array(messages));
+
+    if let Some(tools) = body.get("tools").and_then(Value::as_array) {
+        let tools: Vec<Value> = tools
+            .iter()
+            .filter_map(Value::as_object)
+            .map(|tool| {
+                let mut function = Map::new();
+                copy_fields(tool, &mut function, &["name", "description"]);
+                if let Some(schema) = tool.get("input_schema") {
+                    function.insert("parameters".to_string(), schema.clone());
+                }
+                json!({"type": "function", "function": function})
+            })
+            .collect();
+        out.insert("tools".to_string(), Value::Array(tools));
+    }
+
+    let tool_choice = match body
+        .get("tool_choice")
+        .and_then(|c| c.get("type"))
+        .and_then(Value::as_str)
+    {
+        Some("any") => Some(json!("required")),
+        Some("none") => Some(json!("none")),
+        Some("auto") => Some(json!("auto")),
+        Some("tool") => body
+            .get("tool_choice")
+            .and_then(|c| c.get("name"))
+            .map(|name| json!({"type": "function", "function": {"name": name}})),
+        _ => None,
+    };
+    if let Some(tool_choice) = tool_choice {
+        out.insert("tool_choice".to_string(), tool_choice);
+    }
+
+    out
+}
+
+fn anthropic_image_to_part(block: &Value) -> Value {
+    let source = block.get("source");
+    let url = match source.and_then(|s| s.get("type")).and_then(Value::as_str) {
+        Some("base64") => format!(
+            "data:{};base64,{}",
+            source
+                .and_then(|s| s.get("media_type"))
+                .and_then(Value::as_str)
+                .unwrap_or("image/png"),
+            source
+                .and_then(|s| s.get("data"))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+        ),
+        _ => source
+            .and_then(|s| s.get("url"))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+    };
+    json!({"type": "image_url", "image_url": {"url": url}})
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+/// 复制存在的字段
+fn copy_fields(from: &Map<String, Value>, to: &mut Map<String, Value>, fields: &[&str]) {
+    for field in fields {
+        if let Some(value) = from.get(*field) {
+            to.insert(field.to_string(), value.clone());
+        }
+    }
+}
+
+/// 提取字符串或文本块数组中的文本
+fn text_of(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::models::{FlowMetadata, LLMRequest};
+
+    fn flow(id: &str, flow_type: FlowType, body: Value) -> LLMFlow {
+        let request = LLMRequest {
+            model: body["model"].as_str().unwrap_or_default().to_string(),
+            body,
+            ..Default::default()
+        };
+        let mut flow = LLMFlow::new(id.to_string(), flow_type, request, FlowMetadata::default());
+        flow.id = id.to_string();
+        flow
+    }
+
+    fn openai_flow() -> LLMFlow {
+        flow(
+            "flow-openai",
+            FlowType::ChatCompletions,
+            json!({
+                "model": "gpt-4o",
+                "stream": true,
+                "stream_options": {"include_usage": true},
+                "messages": [
+                    {"role": "system", "content": "Be brief."},
+                    {"role": "user", "content": [
+                        {"type": "text", "text": "What is this?"},
+                        {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+                    ]},
+                    {"role": "assistant", "content": null, "tool_calls": [{
+                        "id": "call_1", "type": "function",
+                        "function": {"name": "lookup", "arguments": "{\"q\":\"cat\"}"}
+                    }]},
+                    {"role": "tool", "tool_call_id": "call_1", "content": "a cat"}
+                ],
+                "tools": [{"type": "function", "function": {
+                    "name": "lookup", "description": "Search", "parameters": {"type": "object"}
+                }}],
+                "tool_choice": "required"
+            }),
+        )
+    }
+
+    fn anthropic_flow() -> LLMFlow {
+        flow(
+            "flow-anthropic",
+            FlowType::AnthropicMessages,
+            json!({
+                "model": "claude-sonnet-4-5",
+                "max_tokens": 1024,
+                "stream": true,
+                "system": [{"type": "text", "text": "Be brief."}],
+                "messages": [
+                    {"role": "user", "content": [
+                        {"type": "image", "source": {"type": "base64", "media_type": "image/jpeg", "data": "BBBB"}},
+                        {"type": "text", "text": "What is this?"}
+                    ]},
+                    {"role": "assistant", "content": [
+                        {"type": "tool_use", "id": "toolu_1", "name": "lookup", "input": {"q": "cat"}}
+                    ]},
+                    {"role": "user", "content": [
+                        {"type": "tool_result", "tool_use_id": "toolu_1", "content": "a cat"}
+                    ]}
+                ],
+                "tools": [{"name": "lookup", "description": "Search", "input_schema": {"type": "object"}}]
+            }),
+        )
+    }
+
+    #[test]
+    fn test_same_format_keeps_body_and_strips_stream() {
+        let line = batch_line(&anthropic_flow(), BatchTarget::Anthropic).unwrap();
+        assert_eq!(line["custom_id"], "flow-anthropic");
+        assert_eq!(line["method"], "POST");
+        assert_eq!(line["url"], ANTHROPIC_BATCH_URL);
+        let body = &line["body"];
+        assert!(body.get("stream").is_none());
+        assert_eq!(body["messages"][0]["content"][0]["source"]["data"], "BBBB");
+        assert_eq!(body["messages"][1]["content"][0]["type"], "tool_use");
+
+        let line = batch_line(&openai_flow(), BatchTarget::OpenAi).unwrap();
+        assert!(line["body"].get("stream").is_none());
+        assert!(line["body"].get("stream_options").is_none());
+        assert_eq!(
+            line["body"]["messages"][1]["content"][1]["image_url"]["url"],
+            "data:image/png;base64,AAAA"
+        );
+    }
+
+    #[test]
+    fn test_openai_flow_to_anthropic_batch() {
+        let body = batch_line(&openai_flow(), BatchTarget::Anthropic).unwrap()["body"].clone();
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["max_tokens"], DEFAULT_ANTHROPIC_MAX_TOKENS);
+        assert_eq!(
+            body["messages"][0]["content"][1],
+            json!({"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}})
+        );
+        assert_eq!(
+            body["messages"][1]["content"][0],
+            json!({"type": "tool_use", "id": "call_1", "name": "lookup", "input": {"q": "cat"}})
+        );
+        assert_eq!(body["messages"][2]["content"][0]["type"], "tool_result");
+        assert_eq!(body["tools"][0]["input_schema"], json!({"type": "object"}));
+        assert_eq!(body["tool_choice"], json!({"type": "any"}));
+        assert!(body.get("stream").is_none());
+    }
+
+    #[test]
+    fn test_anthropic_flow_to_openai_batch() {
+        let body = batch_line(&anthropic_flow(), BatchTarget::OpenAi).unwrap()["body"].clone();
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(
+            messages[0],
+            json!({"role": "system", "content": "Be brief."})
+        );
+        assert_eq!(
+            messages[1]["content"][0]["image_url"]["url"],
+            "data:image/jpeg;base64,BBBB"
+        );
+        assert_eq!(
+            messages[2]["tool_calls"][0]["function"]["arguments"],
+            "{\"q\":\"cat\"}"
+        );
+        assert_eq!(
+            messages[3],
+            json!({"role": "tool", "tool_call_id": "toolu_1", "content": "a cat"})
+        );
+        assert_eq!(
+            body["tools"][0]["function"]["parameters"],
+            json!({"type": "object"})
+        );
+        assert!(body.get("stream").is_none());
+    }
+
+    #[test]
+    fn test_export_skips_unsupported_flows() {
+        let embeddings = flow("flow-embed", FlowType::Embeddings, json!({"input": "x"}));
+        let output = export_batch_jsonl(
+            &[openai_flow(), embeddings, anthropic_flow()],
+            BatchTarget::OpenAi,
+        );
+        let ids: Vec<String> = output
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["custom_id"].to_string())
+            .collect();
+        assert_eq!(ids, ["\"flow-openai\"", "\"flow-anthropic\""]);
+    }
+}
@@ -1,18 +1,22 @@
 //! 批量操作服务
 //!
 //! 该模块实现 Flow 批量操作功能，支持对多个 Flow 进行批量收藏、
-//! 添加标签、导出、删除等操作。
+//! 添加标签、导出、删除等操作，以及在一次调用中执行多种不同的单 Flow 操作（[`FlowOp`]）。
 //!
 //! **Validates: Requirements 11.2-11.6**
 
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
+use uuid::Uuid;
 
 use super::exporter::{ExportFormat, ExportOptions, FlowExporter};
-use super::models::LLMFlow;
+use super::memory_store::FlowFilter;
+use super::models::{FlowAnnotations, LLMFlow};
 use super::monitor::FlowMonitor;
-use super::session::SessionManager;
+use super::session::{SessionError, SessionManager};
 
 /// 批量操作错误
 #[derive(Debug, Error)]
@@ -25,8 +29,15 @@ pub enum BatchOpsError {
     ExportError(String),
     #[error("操作失败: {0}")]
     OperationFailed(String),
+    #[error("确认令牌无效或已过期")]
+    InvalidConfirmToken,
+    #[error("过滤条件与预览时不一致")]
+    FilterMismatch,
 }
 
+/// 删除确认令牌的有效期（秒）
+const DELETE_CONFIRM_TTL_SECS: i64 = 120;
+
 pub type Result<T> = std::result::Result<T, BatchOpsError>;
 
 /// 批量操作类型
@@ -81,10 +92,103 @@ impl BatchResult {
     }
 }
 
+/// 单个 Flow 操作
+///
+/// 与 [`BatchOperation`] 对多个 Flow 执行同一操作不同，一批 `FlowOp` 可以混合不同的操作和 Flow，
+/// 用于减少前端对多选 Flow 逐个调用命令的往返次数。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FlowOp {
+    /// 获取 Flow（内存中不存在时从文件存储读取）
+    Get { flow_id: String },
+    /// 替换 Flow 的标注
+    Annotate {
+        flow_id: String,
+        annotations: FlowAnnotations,
+    },
+    /// 添加和移除标签
+    Tag {
+        flow_id: String,
+        #[serde(default)]
+        add: Vec<String>,
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+    /// 删除 Flow
+    Delete { flow_id: String },
+    /// 添加到会话
+    AddToSession { flow_id: String, session_id: String },
+}
+
+impl FlowOp {
+    /// 操作的 Flow ID
+    pub fn flow_id(&self) -> &str {
+        match self {
+            FlowOp::Get { flow_id }
+            | FlowOp::Annotate { flow_id, .. }
+            | FlowOp::Tag { flow_id, .. }
+            | FlowOp::Delete { flow_id }
+            | FlowOp::AddToSession { flow_id, .. } => flow_id,
+        }
+    }
+}
+
+/// 单个 Flow 操作的结果（与请求中的操作一一对应）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowOpResult {
+    /// Flow ID
+    pub flow_id: String,
+    /// 是否成功
+    pub success: bool,
+    /// `Get` 操作返回的 Flow
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow: Option<LLMFlow>,
+    /// 失败原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 按过滤条件删除的预览
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DeletePreview {
+    /// 将被删除的 Flow 数量
+    pub count: usize,
+    /// 确认令牌（执行删除时需回传）
+    pub confirm_token: String,
+    /// 令牌过期时间
+    pub expires_at: DateTime<Utc>,
+}
+
+/// 按过滤条件删除的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DeleteByFilterResult {
+    /// 从内存中删除的 Flow 数
+    pub memory_deleted: usize,
+    /// 从文件存储中删除的 Flow 数
+    pub file_deleted: usize,
+    /// 删除的 JSONL 文件数
+    pub files_deleted: usize,
+    /// 释放的空间（字节）
+    pub bytes_freed: u64,
+}
+
+/// 待确认的删除
+#[derive(Debug, Clone)]
+struct PendingDelete {
+    /// 预览时的过滤条件（序列化后用于比对）
+    filter_key: String,
+    /// 预览时匹配的 Flow ID
+    flow_ids: Vec<String>,
+    /// 过期时间
+    expires_at: DateTime<Utc>,
+}
+
 /// 批量操作服务
 pub struct BatchOperations {
     flow_monitor: Arc<FlowMonitor>,
     session_manager: Option<Arc<SessionManager>>,
+    /// 确认令牌 -> 待确认的删除
+    pending_deletes: Mutex<HashMap<String, PendingDelete>>,
 }
 
 impl BatchOperations {
@@ -95,7 +199,100 @@ impl BatchOperations {
         Self {
             flow_monitor,
             session_manager,
+            pending_deletes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 预览按过滤条件删除
+    ///
+    /// 返回内存和文件存储中匹配的 Flow 数量，以及执行删除所需的短期确认令牌
+    pub async fn preview_delete(&self, filter: &FlowFilter) -> Result<DeletePreview> {
+        let flow_ids = self.collect_matching_ids(filter).await?;
+        let confirm_token = Uuid::new_v4().simple().to_string();
+        let expires_at = Utc::now() + Duration::seconds(DELETE_CONFIRM_TTL_SECS);
+        let count = flow_ids.len();
+
+        let mut pending = self.pending_deletes.lock().unwrap();
+        pending.retain(|_, p| p.expires_at > Utc::now());
+        pending.insert(
+            confirm_token.clone(),
+            PendingDelete {
+                filter_key: filter_key(filter)?,
+                flow_ids,
+                expires_at,
+            },
+        );
+
+        Ok(DeletePreview {
+            count,
+            confirm_token,
+            expires_at,
+        })
+    }
+
+    /// 按过滤条件删除 Flow
+    ///
+    /// 令牌只能使用一次，且过滤条件必须与预览时一致。
+    /// 删除范围为预览时匹配的 Flow，预览之后新增的 Flow 不受影响。
+    pub async fn delete_flows_by_filter(
+        &self,
+        filter: &FlowFilter,
+        confirm_token: &str,
+    ) -> Result<DeleteByFilterResult> {
+        let pending = self
+            .pending_deletes
+            .lock()
+            .unwrap()
+            .remove(confirm_token)
+            .filter(|p| p.expires_at > Utc::now())
+            .ok_or(BatchOpsError::InvalidConfirmToken)?;
+        if pending.filter_key != filter_key(filter)? {
+            return Err(BatchOpsError::FilterMismatch);
+        }
+
+        let mut result = DeleteByFilterResult::default();
+        {
+            let memory_store = self.flow_monitor.memory_store();
+            let mut store = memory_store.write().await;
+            for flow_id in &pending.flow_ids {
+                if store.remove(flow_id) {
+                    result.memory_deleted += 1;
+                }
+            }
         }
+
+        if let Some(file_store) = self.flow_monitor.file_store() {
+            let cleanup = file_store
+                .delete_flows(&pending.flow_ids)
+                .map_err(|e| BatchOpsError::OperationFailed(e.to_string()))?;
+            result.file_deleted = cleanup.flows_deleted;
+            result.files_deleted = cleanup.files_deleted;
+            result.bytes_freed = cleanup.bytes_freed;
+        }
+
+        Ok(result)
+    }
+
+    /// 收集内存和文件存储中匹配过滤条件的 Flow ID（去重）
+    async fn collect_matching_ids(&self, filter: &FlowFilter) -> Result<Vec<String>> {
+        let mut ids: BTreeSet<String> = {
+            let memory_store = self.flow_monitor.memory_store();
+            let store = memory_store.read().await;
+            store
+                .query(filter)
+                .into_iter()
+                .map(|flow| flow.id)
+                .collect()
+        };
+
+        if let Some(file_store) = self.flow_monitor.file_store() {
+            let flows = file_store
+                .query_all(filter)
+                .map_err(|e| BatchOpsError::OperationFailed(e.to_string()))?;
+            ids.extend(flows.into_iter().map(|flow| flow.id));
+        }
+
+        Ok(ids.into_iter().collect())
     }
 
     pub async fn execute(&self, flow_ids: &[String], operation: BatchOperation) -> BatchResult {
@@ -143,6 +340,125 @@ impl BatchOperations {
         result
     }
 
+    /// 依次执行一批单 Flow 操作
+    ///
+    /// 各操作相互独立，某个操作失败不影响其余操作，结果按请求顺序返回。
+    pub async fn execute_ops(&self, ops: Vec<FlowOp>) -> Vec<FlowOpResult> {
+        let mut results = Vec::with_capacity(ops.len());
+        for op in ops {
+            let flow_id = op.flow_id().to_string();
+            results.push(match self.execute_op(op).await {
+                Ok(flow) => FlowOpResult {
+                    flow_id,
+                    success: true,
+                    flow,
+                    error: None,
+                },
+                Err(e) => FlowOpResult {
+                    flow_id,
+                    success: false,
+                    flow: None,
+                    error: Some(e.to_string()),
+                },
+            });
+        }
+        results
+    }
+
+    async fn execute_op(&self, op: FlowOp) -> Result<Option<LLMFlow>> {
+        match op {
+            FlowOp::Get { flow_id } => {
+                let cached = self
+                    .flow_monitor
+                    .memory_store()
+                    .read()
+                    .await
+                    .get(&flow_id)
+                    .and_then(|flow| flow.read().ok().map(|flow| flow.clone()));
+                let flow = match cached {
+                    Some(flow) => Some(flow),
+                    None => match self.flow_monitor.file_store() {
+                        Some(file_store) => file_store
+                            .get(&flow_id)
+                            .map_err(|e| BatchOpsError::OperationFailed(e.to_string()))?,
+                        None => None,
+                    },
+                };
+                flow.map(Some).ok_or(BatchOpsError::FlowNotFound(flow_id))
+            }
+            FlowOp::Annotate {
+                flow_id,
+                annotations,
+            } => {
+                if !self
+                    .flow_monitor
+                    .update_annotations(&flow_id, annotations)
+                    .await
+                {
+                    return Err(BatchOpsError::FlowNotFound(flow_id));
+                }
+                Ok(None)
+            }
+            FlowOp::Tag {
+                flow_id,
+                add,
+                remove,
+            } => {
+                self.ensure_in_memory(&flow_id).await?;
+                for tag in add {
+                    self.flow_monitor.add_tag(&flow_id, tag).await;
+                }
+                for tag in &remove {
+                    self.flow_monitor.remove_tag(&flow_id, tag).await;
+                }
+                Ok(None)
+            }
+            FlowOp::Delete { flow_id } => {
+                if !self
+                    .flow_monitor
+                    .memory_store()
+                    .write()
+                    .await
+                    .remove(&flow_id)
+                {
+                    return Err(BatchOpsError::FlowNotFound(flow_id));
+                }
+                Ok(None)
+            }
+            FlowOp::AddToSession {
+                flow_id,
+                session_id,
+            } => {
+                let session_manager = self.session_manager.as_ref().ok_or_else(|| {
+                    BatchOpsError::OperationFailed("会话管理器不可用".to_string())
+                })?;
+                self.ensure_in_memory(&flow_id).await?;
+                session_manager
+                    .add_flow(&session_id, &flow_id)
+                    .map_err(|e| match e {
+                        SessionError::SessionNotFound(id) => BatchOpsError::SessionNotFound(id),
+                        e => BatchOpsError::OperationFailed(e.to_string()),
+                    })?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// 确认 Flow 在内存中存在
+    async fn ensure_in_memory(&self, flow_id: &str) -> Result<()> {
+        if self
+            .flow_monitor
+            .memory_store()
+            .read()
+            .await
+            .get(flow_id)
+            .is_none()
+        {
+            return Err(BatchOpsError::FlowNotFound(flow_id.to_string()));
+        }
+        Ok(())
+    }
+
     async fn batch_star<F>(
         &self,
         flow_ids: &[String],
@@ -273,8 +589,16 @@ impl BatchOperations {
                 ..Default::default()
             };
             let exporter = FlowExporter::new(options);
-            let export_result = exporter.export(&flows);
-            result.export_data = Some(export_result.to_string_pretty());
+            match exporter.export(&flows) {
+                Ok(export_result) => result.export_data = Some(export_result.to_string_pretty()),
+                Err(e) => {
+                    // 导出失败时已读取的 Flow 也算失败
+                    result.success -= flows.len();
+                    for flow in &flows {
+                        result.record_failure(&flow.id, format!("导出失败: {}", e));
+                    }
+                }
+            }
         }
     }
 
@@ -351,6 +675,11 @@ impl BatchOperations {
     }
 }
 
+/// 过滤条件的比对键
+fn filter_key(filter: &FlowFilter) -> Result<String> {
+    serde_json::to_string(filter).map_err(|e| BatchOpsError::OperationFailed(e.to_string()))
+}
+
 // ============================================================================
 // 属性测试
 // ============================================================================
@@ -568,3 +897,176 @@ mod property_tests {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::file_store::{FlowFileStore, RotationConfig};
+    use crate::flow_monitor::models::{FlowMetadata, FlowType, LLMRequest};
+    use crate::flow_monitor::monitor::FlowMonitorConfig;
+    use tempfile::TempDir;
+
+    fn create_flow(id: &str, model: &str) -> LLMFlow {
+        let request = LLMRequest {
+            method: "POST".to_string(),
+            path: "/v1/chat/completions".to_string(),
+            model: model.to_string(),
+            ..Default::default()
+        };
+        LLMFlow::new(
+            id.to_string(),
+            FlowType::ChatCompletions,
+            request,
+            FlowMetadata::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_preview_then_delete_by_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_store = Arc::new(
+            FlowFileStore::new(temp_dir.path().to_path_buf(), RotationConfig::default()).unwrap(),
+        );
+        let monitor = Arc::new(FlowMonitor::new(
+            FlowMonitorConfig::default(),
+            Some(file_store.clone()),
+        ));
+
+        for (id, model) in [
+            ("flow-1", "gpt-4"),
+            ("flow-2", "claude-3"),
+            ("flow-3", "gpt-4"),
+            ("flow-4", "claude-3"),
+        ] {
+            let flow = create_flow(id, model);
+            file_store.write(&flow).unwrap();
+            monitor.memory_store().write().await.add(flow);
+        }
+
+        let ops = BatchOperations::new(monitor.clone(), None);
+        let filter = FlowFilter {
+            models: Some(vec!["gpt-4".to_string()]),
+            ..Default::default()
+        };
+        let preview = ops.preview_delete(&filter).await.unwrap();
+        assert_eq!(preview.count, 2);
+
+        // 过滤条件被修改或令牌错误时拒绝删除
+        let other = FlowFilter {
+            models: Some(vec!["claude-3".to_string()]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ops.delete_flows_by_filter(&other, &preview.confirm_token)
+                .await,
+            Err(BatchOpsError::FilterMismatch)
+        ));
+        let preview = ops.preview_delete(&filter).await.unwrap();
+        assert!(matches!(
+            ops.delete_flows_by_filter(&filter, "bogus").await,
+            Err(BatchOpsError::InvalidConfirmToken)
+        ));
+
+        let result = ops
+            .delete_flows_by_filter(&filter, &preview.confirm_token)
+            .await
+            .unwrap();
+        assert_eq!(result.memory_deleted, 2);
+        assert_eq!(result.file_deleted, 2);
+        assert!(result.bytes_freed > 0);
+
+        // 令牌只能使用一次
+        assert!(matches!(
+            ops.delete_flows_by_filter(&filter, &preview.confirm_token)
+                .await,
+            Err(BatchOpsError::InvalidConfirmToken)
+        ));
+
+        // 索引与 JSONL 保持一致：剩余 Flow 均可按新偏移量读取
+        assert_eq!(monitor.memory_store().read().await.len(), 2);
+        assert_eq!(file_store.count().unwrap(), 2);
+        assert!(file_store.get("flow-1").unwrap().is_none());
+        assert_eq!(file_store.get("flow-2").unwrap().unwrap().id, "flow-2");
+        assert_eq!(file_store.get("flow-4").unwrap().unwrap().id, "flow-4");
+
+        let jsonl_lines: usize = walk_jsonl(temp_dir.path())
+            .iter()
+            .map(|path| std::fs::read_to_string(path).unwrap().lines().count())
+            .sum();
+        assert_eq!(jsonl_lines, 2);
+
+        // 删除后仍可继续追加写入
+        file_store.write(&create_flow("flow-5", "gpt-4")).unwrap();
+        assert_eq!(file_store.get("flow-5").unwrap().unwrap().id, "flow-5");
+        assert_eq!(file_store.get("flow-4").unwrap().unwrap().id, "flow-4");
+    }
+
+    #[tokio::test]
+    async fn test_execute_mixed_flow_ops() {
+        let temp_dir = TempDir::new().unwrap();
+        let monitor = Arc::new(FlowMonitor::new(FlowMonitorConfig::default(), None));
+        for id in ["flow-1", "flow-2", "flow-3"] {
+            monitor
+                .memory_store()
+                .write()
+                .await
+                .add(create_flow(id, "gpt-4"));
+        }
+        let session_manager =
+            Arc::new(SessionManager::new(temp_dir.path().join("sessions.db")).unwrap());
+        let session = session_manager.create_session("bulk", None).unwrap();
+        let ops = BatchOperations::new(monitor.clone(), Some(session_manager.clone()));
+
+        let batch: Vec<FlowOp> = serde_json::from_value(serde_json::json!([
+            { "op": "get", "flow_id": "flow-1" },
+            { "op": "annotate", "flow_id": "flow-1", "annotations": { "starred": true, "comment": "check" } },
+            { "op": "tag", "flow_id": "flow-2", "add": ["slow", "retry"], "remove": ["retry"] },
+            { "op": "delete", "flow_id": "missing" },
+            { "op": "add_to_session", "flow_id": "flow-2", "session_id": session.id },
+            { "op": "add_to_session", "flow_id": "flow-3", "session_id": "no-such-session" },
+            { "op": "delete", "flow_id": "flow-3" },
+        ]))
+        .unwrap();
+        let results = ops.execute_ops(batch).await;
+
+        assert_eq!(results.len(), 7);
+        assert_eq!(
+            results.iter().map(|r| r.success).collect::<Vec<_>>(),
+            vec![true, true, true, false, true, false, true]
+        );
+        assert_eq!(results[0].flow.as_ref().unwrap().id, "flow-1");
+        assert_eq!(results[3].flow_id, "missing");
+        assert!(results[3].error.as_deref().unwrap().contains("missing"));
+        assert!(results[5]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("no-such-session"));
+
+        // 失败的操作不影响之后的操作
+        let store = monitor.memory_store();
+        let store = store.read().await;
+        let flow_1 = store.get("flow-1").unwrap().read().unwrap().clone();
+        assert!(flow_1.annotations.starred);
+        assert_eq!(flow_1.annotations.comment.as_deref(), Some("check"));
+        let flow_2 = store.get("flow-2").unwrap().read().unwrap().clone();
+        assert_eq!(flow_2.annotations.tags, vec!["slow".to_string()]);
+        assert!(store.get("flow-3").is_none());
+        drop(store);
+        let session = session_manager.get_session(&session.id).unwrap().unwrap();
+        assert_eq!(session.flow_ids, vec!["flow-2".to_string()]);
+    }
+
+    fn walk_jsonl(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                files.extend(walk_jsonl(&path));
+            } else if path.extension().is_some_and(|ext| ext == "jsonl") {
+                files.push(path);
+            }
+        }
+        files
+    }
+}
@@ -0,0 +1,234 @@
+//! 上游响应体解码
+//!
+//! 按 `Content-Encoding`（gzip / deflate / br）解压上游响应体，并根据 `Content-Type`
+//! 判断是否为文本内容。所有路径都不会因无效 UTF-8 或损坏的压缩数据而 panic。
+
+use std::io::Read;
+
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+
+use super::models::ResponseBodyInfo;
+
+/// 解压后响应体的最大字节数（防止压缩炸弹）
+pub const MAX_DECODED_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+/// 解码后的响应体
+#[derive(Debug, Clone)]
+pub struct DecodedBody {
+    /// 解压后的字节（解压失败时为原始字节）
+    pub bytes: Vec<u8>,
+    /// 内容类型和解码结果
+    pub info: ResponseBodyInfo,
+}
+
+impl DecodedBody {
+    /// 获取文本内容（非文本内容返回 `None`，无效 UTF-8 会被替换）
+    pub fn text(&self) -> Option<String> {
+        (!self.info.binary).then(|| String::from_utf8_lossy(&self.bytes).into_owned())
+    }
+
+    /// 获取文本内容，非文本内容返回说明
+    pub fn text_or_note(&self) -> String {
+        self.text()
+            .unwrap_or_else(|| self.info.note.clone().unwrap_or_default())
+    }
+
+    /// 按字节 lossy 转换为文本（用于 AWS event stream 等按字节解析的二进制协议）
+    pub fn lossy_text(&self) -> String {
+        String::from_utf8_lossy(&self.bytes).into_owned()
+    }
+
+    /// 解析为 JSON（非文本内容返回 `None`）
+    pub fn json(&self) -> Option<serde_json::Value> {
+        if self.info.binary {
+            return None;
+        }
+        serde_json::from_slice(&self.bytes).ok()
+    }
+}
+
+/// 按响应头解码响应体
+///
+/// # Arguments
+/// * `content_type` - `Content-Type` 响应头
+/// * `content_encoding` - `Content-Encoding` 响应头（可能包含多层编码，如 `gzip, br`）
+/// * `bytes` - 收到的原始字节
+pub fn decode_body(
+    content_type: Option<&str>,
+    content_encoding: Option<&str>,
+    bytes: &[u8],
+) -> DecodedBody {
+    let mut info = ResponseBodyInfo {
+        content_type: content_type.map(str::to_string),
+        content_encoding: content_encoding
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(str::to_string),
+        decoded: true,
+        binary: false,
+        encoded_size_bytes: bytes.len(),
+        decoded_size_bytes: bytes.len(),
+        note: None,
+    };
+
+    // 多层编码按逆序解码
+    let mut data = bytes.to_vec();
+    if let Some(encoding) = info.content_encoding.as_deref() {
+        for coding in encoding.rsplit(',').map(|c| c.trim().to_ascii_lowercase()) {
+            match decompress(&coding, &data) {
+                Ok(decompressed) => data = decompressed,
+                Err(reason) => {
+                    info.decoded = false;
+                    info.note = Some(format!("{} 解码失败: {}", coding, reason));
+                    data = bytes.to_vec();
+                    break;
+                }
+            }
+        }
+    }
+    info.decoded_size_bytes = data.len();
+
+    let textual = match content_type {
+        Some(content_type) => is_text_content_type(content_type),
+        // 未声明类型时按内容判断
+        None => info.decoded && std::str::from_utf8(&data).is_ok(),
+    };
+    if !info.decoded || !textual {
+        info.binary = true;
+        let note = format!(
+            "非文本响应（{}，{} 字节）",
+            content_type.unwrap_or("未知类型"),
+            data.len()
+        );
+        info.note = Some(match info.note.take() {
+            Some(reason) => format!("{}；{}", reason, note),
+            None => note,
+        });
+    } else if std::str::from_utf8(&data).is_err() {
+        info.note = Some("包含无效 UTF-8，已替换为 U+FFFD".to_string());
+    }
+
+    DecodedBody { bytes: data, info }
+}
+
+/// 按单层编码解压
+fn decompress(coding: &str, data: &[u8]) -> Result<Vec<u8>, String> {
+    match coding {
+        "" | "identity" => Ok(data.to_vec()),
+        "gzip" | "x-gzip" => read_limited(MultiGzDecoder::new(data)),
+        // HTTP 的 deflate 通常带 zlib 头，少数服务端发送裸 deflate
+        "deflate" => read_limited(ZlibDecoder::new(data))
+            .or_else(|_| read_limited(DeflateDecoder::new(data))),
+        "br" => read_limited(brotli::Decompressor::new(data, 4096)),
+        other => Err(format!("不支持的编码 {}", other)),
+    }
+}
+
+/// 读取解压数据，超过上限时返回错误
+fn read_limited(reader: impl Read) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    reader
+        .take(MAX_DECODED_BODY_BYTES as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| e.to_string())?;
+    if out.len() > MAX_DECODED_BODY_BYTES {
+        return Err(format!("解压后超过 {} 字节", MAX_DECODED_BODY_BYTES));
+    }
+    Ok(out)
+}
+
+/// 判断 `Content-Type` 是否为文本内容
+pub fn is_text_content_type(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    mime.starts_with("text/")
+        || mime.ends_with("+json")
+        || mime.ends_with("+xml")
+        || matches!(
+            mime.as_str(),
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/x-ndjson"
+                | "application/jsonl"
+                | "application/x-www-form-urlencoded"
+        )
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_decode_gzip_json() {
+        let json = br#"{"choices":[{"message":{"content":"Hello"}}]}"#;
+        let compressed = gzip(json);
+
+        let decoded = decode_body(
+            Some("application/json; charset=utf-8"),
+            Some("gzip"),
+            &compressed,
+        );
+        assert!(decoded.info.decoded);
+        assert!(!decoded.info.binary);
+        assert_eq!(decoded.info.content_encoding.as_deref(), Some("gzip"));
+        assert_eq!(decoded.info.encoded_size_bytes, compressed.len());
+        assert_eq!(decoded.info.decoded_size_bytes, json.len());
+
+        let body = decoded.json().unwrap();
+        assert_eq!(body["choices"][0]["message"]["content"], "Hello");
+    }
+
+    #[test]
+    fn test_decode_corrupt_gzip() {
+        let decoded = decode_body(Some("application/json"), Some("gzip"), b"not gzip");
+        assert!(!decoded.info.decoded);
+        assert!(decoded.info.binary);
+        assert!(decoded
+            .info
+            .note
+            .as_deref()
+            .unwrap()
+            .contains("gzip 解码失败"));
+        assert!(decoded.json().is_none());
+        assert_eq!(decoded.bytes, b"not gzip");
+    }
+
+    #[test]
+    fn test_decode_binary_content_type() {
+        let decoded = decode_body(Some("image/png"), None, &[0x89, 0x50, 0x4e, 0x47]);
+        assert!(decoded.info.decoded);
+        assert!(decoded.info.binary);
+        assert!(decoded.text().is_none());
+        assert!(decoded.text_or_note().contains("image/png，4 字节"));
+    }
+
+    #[test]
+    fn test_decode_invalid_utf8_text() {
+        let decoded = decode_body(Some("text/plain"), None, &[b'o', b'k', 0xff]);
+        assert!(!decoded.info.binary);
+        assert_eq!(decoded.text().unwrap(), "ok\u{fffd}");
+        assert!(decoded.info.note.is_some());
+
+        // 未声明类型时，无效 UTF-8 视为二进制
+        let decoded = decode_body(None, None, &[0xff, 0xfe]);
+        assert!(decoded.info.binary);
+    }
+}
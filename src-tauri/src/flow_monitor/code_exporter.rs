@@ -1,6 +1,7 @@
 //! 代码导出器
 //!
-//! 提供将 LLM Flow 导出为可执行代码的功能，支持 curl、Python、TypeScript 等格式。
+//! 提供将 LLM Flow 导出为可执行代码的功能，支持 curl、HTTPie、Python（requests /
+//! openai SDK / anthropic SDK）、TypeScript 等格式。
 //!
 //! **Validates: Requirements 7.7, 7.8**
 
@@ -24,6 +25,12 @@ pub enum CodeFormat {
     TypeScript,
     /// JavaScript 代码
     JavaScript,
+    /// Python 代码（官方 openai SDK）
+    PythonOpenAI,
+    /// Python 代码（官方 anthropic SDK）
+    PythonAnthropic,
+    /// HTTPie 命令
+    HttpIe,
 }
 
 impl Default for CodeFormat {
@@ -56,6 +63,9 @@ impl CodeExporter {
             CodeFormat::Python => Self::to_python(flow),
             CodeFormat::TypeScript => Self::to_typescript(flow),
             CodeFormat::JavaScript => Self::to_javascript(flow),
+            CodeFormat::PythonOpenAI => Self::to_python_openai(flow),
+            CodeFormat::PythonAnthropic => Self::to_python_anthropic(flow),
+            CodeFormat::HttpIe => Self::to_httpie(flow),
         }
     }
 
@@ -77,6 +87,7 @@ impl CodeExporter {
 
     /// 将请求转换为 curl 命令
     pub fn request_to_curl(request: &LLMRequest, base_url: Option<&str>) -> String {
+        let body = request_body(request);
         let mut parts = vec!["curl".to_string()];
 
         // 添加方法（如果不是 GET）
@@ -92,17 +103,14 @@ impl CodeExporter {
         };
         parts.push(format!("'{}'", url));
 
-        // 添加请求头
-        for (key, value) in &request.headers {
-            // 跳过敏感头部或使用占位符
-            let header_value = if key.to_lowercase() == "authorization" {
-                "$API_KEY".to_string()
-            } else if key.to_lowercase() == "x-api-key" {
-                "$API_KEY".to_string()
-            } else {
-                escape_shell_string(value)
-            };
-            parts.push(format!("-H '{}: {}'", key, header_value));
+        // 流式请求禁用输出缓冲，逐块打印 SSE 事件
+        if is_streaming(&body) {
+            parts.push("-N".to_string());
+        }
+
+        // 添加请求头（密钥使用 `$API_KEY` 占位符）
+        for (key, value) in shell_headers(request) {
+            parts.push(format!("-H {}", shell_header(key, value, ": ")));
         }
 
         // 确保有 Content-Type 头
@@ -115,8 +123,8 @@ impl CodeExporter {
         }
 
         // 添加请求体
-        if !request.body.is_null() {
-            let body_str = serde_json::to_string(&request.body).unwrap_or_default();
+        if !body.is_null() {
+            let body_str = serde_json::to_string(&body).unwrap_or_default();
             parts.push(format!("-d '{}'", escape_shell_string(&body_str)));
         }
 
@@ -141,6 +149,7 @@ impl CodeExporter {
 
     /// 将请求转换为 Python 代码
     pub fn request_to_python(request: &LLMRequest, base_url: Option<&str>) -> String {
+        let body = request_body(request);
         let mut code = String::new();
 
         // 导入语句
@@ -182,8 +191,8 @@ impl CodeExporter {
         code.push_str("}\n\n");
 
         // 请求体
-        if !request.body.is_null() {
-            let body_str = serde_json::to_string_pretty(&request.body).unwrap_or_default();
+        if !body.is_null() {
+            let body_str = serde_json::to_string_pretty(&body).unwrap_or_default();
             code.push_str(&format!("data = {}\n\n", body_str));
         } else {
             code.push_str("data = {}\n\n");
@@ -223,6 +232,7 @@ impl CodeExporter {
 
     /// 将请求转换为 TypeScript 代码
     pub fn request_to_typescript(request: &LLMRequest, base_url: Option<&str>) -> String {
+        let body = request_body(request);
         let mut code = String::new();
 
         // URL
@@ -258,8 +268,8 @@ impl CodeExporter {
         code.push_str("};\n\n");
 
         // 请求体
-        if !request.body.is_null() {
-            let body_str = serde_json::to_string_pretty(&request.body).unwrap_or_default();
+        if !body.is_null() {
+            let body_str = serde_json::to_string_pretty(&body).unwrap_or_default();
             code.push_str("const data = ");
             code.push_str(&body_str);
             code.push_str(";\n\n");
@@ -303,6 +313,7 @@ impl CodeExporter {
 
     /// 将请求转换为 JavaScript 代码
     pub fn request_to_javascript(request: &LLMRequest, base_url: Option<&str>) -> String {
+        let body = request_body(request);
         let mut code = String::new();
 
         // URL
@@ -338,8 +349,8 @@ impl CodeExporter {
         code.push_str("};\n\n");
 
         // 请求体
-        if !request.body.is_null() {
-            let body_str = serde_json::to_string_pretty(&request.body).unwrap_or_default();
+        if !body.is_null() {
+            let body_str = serde_json::to_string_pretty(&body).unwrap_or_default();
             code.push_str("const data = ");
             code.push_str(&body_str);
             code.push_str(";\n\n");
@@ -364,15 +375,339 @@ impl CodeExporter {
 
         code
     }
+
+    /// 导出为 HTTPie 命令
+    ///
+    /// # Arguments
+    /// * `flow` - 要导出的 Flow
+    ///
+    /// # Returns
+    /// HTTPie 命令字符串
+    pub fn to_httpie(flow: &LLMFlow) -> String {
+        Self::request_to_httpie(
+            &flow.request,
+            flow.metadata.routing_info.target_url.as_deref(),
+        )
+    }
+
+    /// 将请求转换为 HTTPie 命令
+    ///
+    /// 请求体通过标准输入传入，以原样保留嵌套的消息和工具定义。
+    pub fn request_to_httpie(request: &LLMRequest, base_url: Option<&str>) -> String {
+        let body = request_body(request);
+        let mut parts = Vec::new();
+
+        if !body.is_null() {
+            let body_str = serde_json::to_string(&body).unwrap_or_default();
+            parts.push(format!("echo '{}' |", escape_shell_string(&body_str)));
+        }
+
+        let mut command = vec!["http".to_string()];
+        if is_streaming(&body) {
+            command.push("--stream".to_string());
+        }
+        command.push(request.method.clone());
+        command.push(format!("'{}'", build_url(request, base_url)));
+        parts.push(command.join(" "));
+
+        // 请求头（HTTPie 使用 `Name:Value` 语法）
+        for (key, value) in shell_headers(request) {
+            parts.push(shell_header(key, value, ":"));
+        }
+        if !request
+            .headers
+            .keys()
+            .any(|k| k.to_lowercase() == "content-type")
+        {
+            parts.push("'Content-Type:application/json'".to_string());
+        }
+
+        let (first, rest) = parts.split_at(1);
+        let mut result = first[0].clone();
+        for part in rest {
+            // 管道符后直接换行，其余参数使用续行符
+            if result.ends_with('|') {
+                result.push_str("\n  ");
+            } else {
+                result.push_str(" \\\n  ");
+            }
+            result.push_str(part);
+        }
+        result
+    }
+
+    /// 导出为 Python 代码（官方 openai SDK）
+    ///
+    /// # Arguments
+    /// * `flow` - 要导出的 Flow
+    ///
+    /// # Returns
+    /// Python 代码字符串
+    pub fn to_python_openai(flow: &LLMFlow) -> String {
+        Self::request_to_python_openai(
+            &flow.request,
+            flow.metadata.routing_info.target_url.as_deref(),
+        )
+    }
+
+    /// 将请求转换为使用 openai SDK 的 Python 代码
+    pub fn request_to_python_openai(request: &LLMRequest, base_url: Option<&str>) -> String {
+        let body = request_body(request);
+        // SDK 的 base_url 包含版本前缀（如 `/v1`），方法名决定具体端点
+        let (prefix, method) = [
+            ("/chat/completions", "chat.completions.create"),
+            ("/completions", "completions.create"),
+            ("/embeddings", "embeddings.create"),
+        ]
+        .iter()
+        .find_map(|(suffix, method)| {
+            request
+                .path
+                .strip_suffix(suffix)
+                .map(|prefix| (prefix.to_string(), *method))
+        })
+        .unwrap_or_else(|| ("/v1".to_string(), "chat.completions.create"));
+        let sdk_base_url = format!("{}{}", build_base_url(base_url), prefix);
+
+        build_python_sdk_code(
+            "from openai import OpenAI",
+            "OpenAI",
+            &sdk_base_url,
+            method,
+            &body,
+        )
+    }
+
+    /// 导出为 Python 代码（官方 anthropic SDK）
+    ///
+    /// # Arguments
+    /// * `flow` - 要导出的 Flow
+    ///
+    /// # Returns
+    /// Python 代码字符串
+    pub fn to_python_anthropic(flow: &LLMFlow) -> String {
+        Self::request_to_python_anthropic(
+            &flow.request,
+            flow.metadata.routing_info.target_url.as_deref(),
+        )
+    }
+
+    /// 将请求转换为使用 anthropic SDK 的 Python 代码
+    pub fn request_to_python_anthropic(request: &LLMRequest, base_url: Option<&str>) -> String {
+        let body = request_body(request);
+        // SDK 会自行拼接 `/v1/messages`，base_url 只保留其之前的部分
+        let prefix = request
+            .path
+            .strip_suffix("/v1/messages")
+            .unwrap_or_default();
+        let sdk_base_url = format!("{}{}", build_base_url(base_url), prefix);
+
+        build_python_sdk_code(
+            "from anthropic import Anthropic",
+            "Anthropic",
+            &sdk_base_url,
+            "messages.create",
+            &body,
+        )
+    }
 }
 
 // ============================================================================
 // 辅助函数
 // ============================================================================
 
-/// 转义 shell 字符串中的特殊字符
+/// 获取导出用的请求体
+///
+/// 捕获的 `seed` 参数未出现在请求体中时补充进去，保证导出的代码可复现采样。
+fn request_body(request: &LLMRequest) -> serde_json::Value {
+    let mut body = request.body.clone();
+    if let (Some(seed), Some(obj)) = (request.parameters.seed, body.as_object_mut()) {
+        obj.entry("seed").or_insert_with(|| serde_json::json!(seed));
+    }
+    body
+}
+
+/// 构建请求的完整 URL
+fn build_url(request: &LLMRequest, base_url: Option<&str>) -> String {
+    format!("{}{}", build_base_url(base_url), request.path)
+}
+
+/// 构建基础 URL（未知时使用 localhost）
+fn build_base_url(base_url: Option<&str>) -> String {
+    match base_url {
+        Some(base) => base.trim_end_matches('/').to_string(),
+        None => "http://localhost".to_string(),
+    }
+}
+
+/// 判断是否为包含密钥的请求头
+fn is_secret_header(key: &str) -> bool {
+    matches!(
+        key.to_lowercase().as_str(),
+        "authorization" | "proxy-authorization" | "x-api-key" | "api-key" | "x-goog-api-key"
+    )
+}
+
+/// 判断是否为重放时不应携带的请求头
+///
+/// 连接相关的头部由客户端重新生成；Cookie 属于会话信息；`Accept-Encoding` 会让上游返回
+/// 压缩内容，命令行直接输出时不可读。
+fn is_skipped_shell_header(key: &str) -> bool {
+    matches!(
+        key.to_lowercase().as_str(),
+        "host"
+            | "content-length"
+            | "connection"
+            | "transfer-encoding"
+            | "accept-encoding"
+            | "cookie"
+    )
+}
+
+/// shell 命令中携带的请求头（按名称排序，保证输出稳定）
+fn shell_headers(request: &LLMRequest) -> Vec<(&String, &String)> {
+    let mut headers: Vec<(&String, &String)> = request
+        .headers
+        .iter()
+        .filter(|(key, _)| !is_skipped_shell_header(key))
+        .collect();
+    headers.sort();
+    headers
+}
+
+/// 渲染 shell 命令中的单个请求头参数
+///
+/// 密钥头部使用双引号，使 `$API_KEY` 在执行时展开为环境变量，并保留 `Bearer` 等认证方案；
+/// 其余头部使用单引号原样传递。
+fn shell_header(key: &str, value: &str, separator: &str) -> String {
+    if !is_secret_header(key) {
+        return format!("'{}{}{}'", key, separator, escape_shell_string(value));
+    }
+    let placeholder = match value.trim().split_once(char::is_whitespace) {
+        Some((scheme, _)) if scheme.chars().all(|c| c.is_ascii_alphabetic()) => {
+            format!("{} $API_KEY", scheme)
+        }
+        _ => "$API_KEY".to_string(),
+    };
+    format!("\"{}{}{}\"", key, separator, placeholder)
+}
+
+/// 判断请求体是否为流式请求
+fn is_streaming(body: &serde_json::Value) -> bool {
+    body.get("stream")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// 判断字符串是否为合法的 Python 关键字参数名
+fn is_python_identifier(s: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+        "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global",
+        "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return",
+        "try", "while", "with", "yield",
+    ];
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+        && !KEYWORDS.contains(&s)
+}
+
+/// 生成使用官方 SDK 的 Python 代码
+///
+/// 请求体的每个字段作为关键字参数传入，非法参数名通过 `extra_body` 传入。
+fn build_python_sdk_code(
+    import: &str,
+    client_class: &str,
+    base_url: &str,
+    method: &str,
+    body: &serde_json::Value,
+) -> String {
+    let mut code = String::new();
+    code.push_str("import os\n\n");
+    code.push_str(import);
+    code.push_str("\n\n");
+
+    code.push_str(&format!("client = {}(\n", client_class));
+    code.push_str("    api_key=os.environ.get(\"API_KEY\", \"$API_KEY\"),\n");
+    code.push_str(&format!(
+        "    base_url=\"{}\",\n",
+        escape_python_string(base_url)
+    ));
+    code.push_str(")\n\n");
+
+    code.push_str(&format!("response = client.{}(\n", method));
+    let mut extra_body = serde_json::Map::new();
+    if let Some(obj) = body.as_object() {
+        for (key, value) in obj {
+            if is_python_identifier(key) {
+                code.push_str(&format!("    {}={},\n", key, to_python_literal(value, 1)));
+            } else {
+                extra_body.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    if !extra_body.is_empty() {
+        code.push_str(&format!(
+            "    extra_body={},\n",
+            to_python_literal(&serde_json::Value::Object(extra_body), 1)
+        ));
+    }
+    code.push_str(")\n\n");
+
+    if is_streaming(body) {
+        code.push_str("for event in response:\n");
+        code.push_str("    print(event.model_dump_json())\n");
+    } else {
+        code.push_str("print(response.model_dump_json(indent=2))\n");
+    }
+
+    code
+}
+
+/// 将 JSON 值转换为 Python 字面量
+///
+/// `indent` 为当前缩进层级（每级 4 个空格），对象和数组会展开为多行。
+fn to_python_literal(value: &serde_json::Value, indent: usize) -> String {
+    let pad = "    ".repeat(indent + 1);
+    let close_pad = "    ".repeat(indent);
+    match value {
+        serde_json::Value::Null => "None".to_string(),
+        serde_json::Value::Bool(true) => "True".to_string(),
+        serde_json::Value::Bool(false) => "False".to_string(),
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::String(s) => format!("\"{}\"", escape_python_string(s)),
+        serde_json::Value::Array(items) if items.is_empty() => "[]".to_string(),
+        serde_json::Value::Array(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| format!("{}{},\n", pad, to_python_literal(item, indent + 1)))
+                .collect();
+            format!("[\n{}{}]", items.concat(), close_pad)
+        }
+        serde_json::Value::Object(obj) if obj.is_empty() => "{}".to_string(),
+        serde_json::Value::Object(obj) => {
+            let entries: Vec<String> = obj
+                .iter()
+                .map(|(key, value)| {
+                    format!(
+                        "{}\"{}\": {},\n",
+                        pad,
+                        escape_python_string(key),
+                        to_python_literal(value, indent + 1)
+                    )
+                })
+                .collect();
+            format!("{{\n{}{}}}", entries.concat(), close_pad)
+        }
+    }
+}
+
+/// 转义单引号 shell 字符串中的特殊字符
+///
+/// 单引号内除单引号本身外的字符（包括反斜杠）都按字面传递，只需转义单引号。
 fn escape_shell_string(s: &str) -> String {
-    s.replace('\\', "\\\\").replace('\'', "'\\''")
+    s.replace('\'', "'\\''")
 }
 
 /// 转义 Python 字符串中的特殊字符
@@ -446,6 +781,7 @@ mod tests {
             },
             size_bytes: 100,
             timestamp: Utc::now(),
+            attachments: Vec::new(),
         };
 
         let mut metadata = FlowMetadata::default();
@@ -454,6 +790,13 @@ mod tests {
             target_url: Some("https://api.openai.com".to_string()),
             route_rule: None,
             load_balance_strategy: None,
+            session_key: None,
+            session_affinity: None,
+            model_downgrade: None,
+            history_truncation: None,
+            routing_trace: None,
+            remap_bypassed: false,
+            extra_headers: HashMap::new(),
         };
 
         LLMFlow {
@@ -479,7 +822,7 @@ mod tests {
         assert!(curl.contains("-X POST"));
         assert!(curl.contains("https://api.openai.com/v1/chat/completions"));
         assert!(curl.contains("-H 'Content-Type: application/json'"));
-        assert!(curl.contains("-H 'Authorization: $API_KEY'"));
+        assert!(curl.contains("-H \"Authorization: Bearer $API_KEY\""));
         assert!(curl.contains("-d '"));
         assert!(curl.contains("gpt-4"));
     }
@@ -557,7 +900,8 @@ mod tests {
     fn test_escape_shell_string() {
         assert_eq!(escape_shell_string("hello"), "hello");
         assert_eq!(escape_shell_string("it's"), "it'\\''s");
-        assert_eq!(escape_shell_string("back\\slash"), "back\\\\slash");
+        // 单引号内的反斜杠按字面传递，不能重复转义（否则 JSON 中的 `\n` 会被改写）
+        assert_eq!(escape_shell_string("back\\slash"), "back\\slash");
     }
 
     #[test]
@@ -599,6 +943,171 @@ mod tests {
         assert!(typescript.contains("process.env.API_KEY"));
         assert!(!typescript.contains("sk-test-key"));
     }
+
+    fn create_multi_turn_body() -> serde_json::Value {
+        serde_json::json!({
+            "model": "gpt-4",
+            "messages": [
+                {"role": "system", "content": "You are \"helpful\"."},
+                {"role": "user", "content": "What's the weather?"},
+                {"role": "assistant", "content": null, "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}
+                }]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "Sunny"}
+            ],
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "get_weather",
+                    "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+                }
+            }],
+            "stream": true,
+            "temperature": 0.2
+        })
+    }
+
+    #[test]
+    fn test_to_python_openai_multi_turn_with_tools() {
+        let mut flow = create_test_flow();
+        flow.request.body = create_multi_turn_body();
+        let python = CodeExporter::export(&flow, CodeFormat::PythonOpenAI);
+
+        assert!(python.contains("from openai import OpenAI"));
+        assert!(python.contains("base_url=\"https://api.openai.com/v1\""));
+        assert!(python.contains("response = client.chat.completions.create("));
+        assert!(python.contains("    model=\"gpt-4\",\n"));
+        assert!(python.contains("\"content\": None,"));
+        assert!(python.contains("\"content\": \"You are \\\"helpful\\\".\","));
+        assert!(python.contains("\"arguments\": \"{\\\"city\\\":\\\"Paris\\\"}\""));
+        assert!(python.contains("    tools=[\n"));
+        assert!(python.contains("    stream=True,\n"));
+        assert!(python.contains("    temperature=0.2,\n"));
+        assert!(python.contains("for event in response:"));
+        assert!(python.contains("\"$API_KEY\""));
+        assert!(!python.contains("sk-test-key"));
+        assert!(!python.contains("true") && !python.contains("null"));
+    }
+
+    #[test]
+    fn test_to_python_anthropic() {
+        let mut flow = create_test_flow();
+        flow.request.path = "/v1/messages".to_string();
+        flow.request.body = serde_json::json!({
+            "model": "claude-3-opus",
+            "max_tokens": 1024,
+            "system": "Be brief.",
+            "messages": [{"role": "user", "content": [{"type": "text", "text": "Hi"}]}]
+        });
+        flow.metadata.routing_info.target_url = Some("https://api.anthropic.com/".to_string());
+        let python = CodeExporter::export(&flow, CodeFormat::PythonAnthropic);
+
+        assert!(python.contains("from anthropic import Anthropic"));
+        assert!(python.contains("base_url=\"https://api.anthropic.com\""));
+        assert!(python.contains("response = client.messages.create("));
+        assert!(python.contains("    max_tokens=1024,\n"));
+        assert!(python.contains("    system=\"Be brief.\",\n"));
+        assert!(python.contains("print(response.model_dump_json(indent=2))"));
+    }
+
+    #[test]
+    fn test_python_sdk_extra_body_for_invalid_identifiers() {
+        let mut flow = create_test_flow();
+        flow.request.body = serde_json::json!({"model": "gpt-4", "x-custom": 1, "from": 2});
+        let python = CodeExporter::to_python_openai(&flow);
+
+        assert!(python.contains("    model=\"gpt-4\",\n"));
+        assert!(python.contains("    extra_body={\n"));
+        assert!(python.contains("\"x-custom\": 1,"));
+        assert!(python.contains("\"from\": 2,"));
+    }
+
+    #[test]
+    fn test_export_includes_captured_seed() {
+        let mut flow = create_test_flow();
+        flow.request.parameters.seed = Some(42);
+
+        let curl = CodeExporter::to_curl(&flow);
+        assert!(curl.contains("\"seed\":42"));
+        let python = CodeExporter::export(&flow, CodeFormat::PythonOpenAI);
+        assert!(python.contains("    seed=42,\n"));
+
+        // 请求体中已有 seed 时保持原值
+        flow.request.body["seed"] = serde_json::json!(7);
+        assert!(CodeExporter::to_curl(&flow).contains("\"seed\":7"));
+    }
+
+    #[test]
+    fn test_curl_streaming_flag() {
+        let mut flow = create_test_flow();
+        assert!(!CodeExporter::to_curl(&flow).contains("-N"));
+
+        flow.request.body = create_multi_turn_body();
+        let curl = CodeExporter::to_curl(&flow);
+        assert!(curl.contains(" \\\n  -N \\\n"));
+        assert!(curl.contains("What'\\''s the weather?"));
+    }
+
+    #[test]
+    fn test_curl_reproduces_request_without_secrets() {
+        let mut flow = create_test_flow();
+        let headers = &mut flow.request.headers;
+        headers.insert("x-goog-api-key".to_string(), "AIza-captured".to_string());
+        headers.insert("Host".to_string(), "127.0.0.1:8999".to_string());
+        headers.insert("Content-Length".to_string(), "42".to_string());
+        headers.insert("Accept-Encoding".to_string(), "gzip".to_string());
+        flow.request.body = serde_json::json!({
+            "model": "claude-sonnet-4",
+            "messages": [{"role": "user", "content": "line 1\nline 2"}]
+        });
+
+        let curl = CodeExporter::export(&flow, CodeFormat::Curl);
+        assert!(curl.contains("\"model\":\"claude-sonnet-4\""));
+        // JSON 转义序列原样保留
+        assert!(curl.contains("line 1\\nline 2"));
+        assert!(!curl.contains("line 1\\\\nline 2"));
+
+        // 密钥替换为可展开的占位符，请求头按名称排序
+        assert!(curl.contains(
+            "-H \"Authorization: Bearer $API_KEY\" \\\n  -H 'Content-Type: application/json' \\\n  -H \"x-goog-api-key: $API_KEY\""
+        ));
+        assert!(!curl.contains("sk-test-key"));
+        assert!(!curl.contains("AIza-captured"));
+
+        // 连接相关的头部不随命令重放
+        assert!(!curl.contains("Host"));
+        assert!(!curl.contains("Content-Length"));
+        assert!(!curl.contains("gzip"));
+    }
+
+    #[test]
+    fn test_to_httpie() {
+        let mut flow = create_test_flow();
+        flow.request.body = create_multi_turn_body();
+        let httpie = CodeExporter::export(&flow, CodeFormat::HttpIe);
+
+        assert!(httpie.starts_with("echo '{"));
+        assert!(
+            httpie.contains("|\n  http --stream POST 'https://api.openai.com/v1/chat/completions'")
+        );
+        assert!(httpie.contains("\"Authorization:Bearer $API_KEY\""));
+        assert!(httpie.contains("'Content-Type:application/json'"));
+        assert!(!httpie.contains("sk-test-key"));
+    }
+
+    #[test]
+    fn test_code_format_serde() {
+        assert_eq!(
+            serde_json::to_string(&CodeFormat::PythonOpenAI).unwrap(),
+            "\"pythonopenai\""
+        );
+        assert_eq!(
+            serde_json::from_str::<CodeFormat>("\"httpie\"").unwrap(),
+            CodeFormat::HttpIe
+        );
+    }
 }
 
 // ============================================================================
@@ -718,6 +1227,7 @@ mod property_tests {
                 parameters: RequestParameters::default(),
                 size_bytes: 0,
                 timestamp: Utc::now(),
+                attachments: Vec::new(),
             })
     }
 
@@ -730,6 +1240,13 @@ mod property_tests {
                 target_url: base_url,
                 route_rule: None,
                 load_balance_strategy: None,
+                session_key: None,
+                session_affinity: None,
+                model_downgrade: None,
+                history_truncation: None,
+                routing_trace: None,
+                remap_bypassed: false,
+                extra_headers: HashMap::new(),
             };
 
             LLMFlow {
@@ -0,0 +1,240 @@
+//! 请求内容哈希
+//!
+//! Mock 响应匹配、幂等键请求体校验和提示词模板聚类都需要按请求内容计算哈希，但客户端
+//! 注入的时间戳、行尾空白等细微差异会让内容相同的请求得到不同的哈希。这里提供统一的
+//! 规范化流程：先移除配置的模式（时间戳、UUID 及自定义正则），再合并空白、去除首尾空白，
+//! 最后计算 SHA-256。规范化配置只在 `FlowMonitorConfig::content_normalization` 一处维护，
+//! 各功能通过 `FlowMonitor::content_normalizer` 共享同一个编译好的规范化器，行为保持一致。
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::models::LLMRequest;
+
+/// ISO 8601 时间戳（如 `2024-05-01T12:30:45.123Z`、`2024-05-01 12:30:45+08:00`）
+pub const ISO_TIMESTAMP_PATTERN: &str =
+    r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?";
+
+/// UUID（不区分大小写）
+pub const UUID_PATTERN: &str =
+    r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b";
+
+/// 内容规范化配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentNormalizationConfig {
+    /// 去除首尾空白
+    #[serde(default = "default_true")]
+    pub trim: bool,
+    /// 将连续空白合并为一个空格
+    #[serde(default = "default_true")]
+    pub collapse_whitespace: bool,
+    /// 移除 ISO 8601 时间戳
+    #[serde(default)]
+    pub strip_timestamps: bool,
+    /// 移除 UUID
+    #[serde(default)]
+    pub strip_uuids: bool,
+    /// 额外移除的正则
+    #[serde(default)]
+    pub strip_patterns: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for ContentNormalizationConfig {
+    fn default() -> Self {
+        Self {
+            trim: true,
+            collapse_whitespace: true,
+            strip_timestamps: false,
+            strip_uuids: false,
+            strip_patterns: Vec::new(),
+        }
+    }
+}
+
+/// 编译后的内容规范化器
+#[derive(Debug, Clone)]
+pub struct ContentNormalizer {
+    trim: bool,
+    collapse_whitespace: bool,
+    strip: Vec<Regex>,
+}
+
+impl Default for ContentNormalizer {
+    fn default() -> Self {
+        Self::new(&ContentNormalizationConfig::default())
+    }
+}
+
+impl ContentNormalizer {
+    /// 按配置编译规范化器（无效的正则记录警告后忽略）
+    pub fn new(config: &ContentNormalizationConfig) -> Self {
+        let builtin = [
+            (config.strip_timestamps, ISO_TIMESTAMP_PATTERN),
+            (config.strip_uuids, UUID_PATTERN),
+        ];
+        let strip = builtin
+            .into_iter()
+            .filter_map(|(enabled, pattern)| enabled.then_some(pattern))
+            .chain(
+                config
+                    .strip_patterns
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|pattern| !pattern.trim().is_empty()),
+            )
+            .filter_map(|pattern| {
+                Regex::new(pattern)
+                    .map_err(|e| tracing::warn!("内容规范化正则无效，已忽略: {}", e))
+                    .ok()
+            })
+            .collect();
+
+        Self {
+            trim: config.trim,
+            collapse_whitespace: config.collapse_whitespace,
+            strip,
+        }
+    }
+
+    /// 规范化文本
+    pub fn normalize(&self, text: &str) -> String {
+        let mut normalized = text.to_string();
+        for regex in &self.strip {
+            if let std::borrow::Cow::Owned(replaced) = regex.replace_all(&normalized, "") {
+                normalized = replaced;
+            }
+        }
+
+        if self.collapse_whitespace {
+            let mut collapsed = String::with_capacity(normalized.len());
+            let mut in_whitespace = false;
+            for c in normalized.chars() {
+                if c.is_whitespace() {
+                    if !in_whitespace {
+                        collapsed.push(' ');
+                    }
+                    in_whitespace = true;
+                } else {
+                    collapsed.push(c);
+                    in_whitespace = false;
+                }
+            }
+            normalized = collapsed;
+        }
+
+        if self.trim {
+            normalized.trim().to_string()
+        } else {
+            normalized
+        }
+    }
+
+    /// 规范化后计算 SHA-256（十六进制）
+    pub fn hash(&self, text: &str) -> String {
+        hex_digest(Sha256::digest(self.normalize(text).as_bytes()).as_slice())
+    }
+
+    /// 计算请求内容的哈希
+    ///
+    /// 只包含模型、系统提示词和各消息的角色与规范化后的文本，不包含采样参数和请求头。
+    pub fn hash_request(&self, request: &LLMRequest) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(request.model.as_bytes());
+        hasher.update([0]);
+        if let Some(system) = &request.system_prompt {
+            hasher.update(b"system");
+            hasher.update([0]);
+            hasher.update(self.normalize(system).as_bytes());
+            hasher.update([0]);
+        }
+        for message in &request.messages {
+            hasher.update(format!("{:?}", message.role).as_bytes());
+            hasher.update([0]);
+            hasher.update(self.normalize(&message.content.get_all_text()).as_bytes());
+            hasher.update([0]);
+        }
+        hex_digest(hasher.finalize().as_slice())
+    }
+}
+
+fn hex_digest(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::models::{Message, MessageContent, MessageRole};
+
+    fn request(user: &str) -> LLMRequest {
+        LLMRequest {
+            model: "gpt-4o".to_string(),
+            system_prompt: Some("You are helpful.".to_string()),
+            messages: vec![Message {
+                role: MessageRole::User,
+                content: MessageContent::Text(user.to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_whitespace_normalization() {
+        let normalizer = ContentNormalizer::default();
+        assert_eq!(normalizer.normalize("  hello \n\n world  "), "hello world");
+        assert_eq!(
+            normalizer.hash("hello world"),
+            normalizer.hash("hello   world\n")
+        );
+
+        let raw = ContentNormalizer::new(&ContentNormalizationConfig {
+            trim: false,
+            collapse_whitespace: false,
+            ..Default::default()
+        });
+        assert_ne!(raw.hash("hello world"), raw.hash("hello world "));
+    }
+
+    #[test]
+    fn test_injected_timestamp_hashes_to_same_key() {
+        let first = request("[2024-05-01T12:30:45.123Z] What is the weather?");
+        let second = request("[2024-05-02T08:00:00Z] What is the weather?");
+
+        let plain = ContentNormalizer::default();
+        assert_ne!(plain.hash_request(&first), plain.hash_request(&second));
+
+        let config = ContentNormalizationConfig {
+            strip_timestamps: true,
+            ..Default::default()
+        };
+        let normalizer = ContentNormalizer::new(&config);
+        assert_eq!(
+            normalizer.hash_request(&first),
+            normalizer.hash_request(&second)
+        );
+        assert_ne!(
+            normalizer.hash_request(&first),
+            normalizer.hash_request(&request("[2024-05-01T12:30:45Z] Other question"))
+        );
+    }
+
+    #[test]
+    fn test_uuid_and_custom_patterns() {
+        let config = ContentNormalizationConfig {
+            strip_uuids: true,
+            strip_patterns: vec![r"req#\d+".to_string(), "(".to_string()],
+            ..Default::default()
+        };
+        let normalizer = ContentNormalizer::new(&config);
+        assert_eq!(
+            normalizer.normalize("session 3F2504E0-4F89-11D3-9A0C-0305E82C3301 req#42 done"),
+            "session done"
+        );
+    }
+}
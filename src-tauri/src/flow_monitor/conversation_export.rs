@@ -0,0 +1,298 @@
+//! 会话记录导出
+//!
+//! 将按顺序排列的多个 Flow 合并为一份完整的会话记录。Agent 每轮请求都会重发之前的
+//! 全部消息，逐个导出会得到 N 份重叠的内容；合并时检测第 N 轮的消息是否以第 N-1 轮的
+//! 消息为前缀，只追加新增的部分。
+//!
+//! 合并规则：
+//! - 新增消息的第一条若是与上一轮响应内容相同的助手消息，视为上一轮响应的回放并跳过
+//! - 某一轮不以上一轮为前缀（分支或新会话）或系统提示词变化时记录警告，该轮输出完整消息
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::models::{LLMFlow, Message, MessageRole, ToolCall};
+
+/// 会话记录中的一轮
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationTurn {
+    /// Flow ID
+    pub flow_id: String,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 模型
+    pub model: String,
+    /// 本轮新增的消息（已去除与之前轮次重复的前缀）
+    pub new_messages: Vec<Message>,
+    /// 响应内容
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    /// 响应中的工具调用
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// 错误信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 合并后的会话记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct ConversationTranscript {
+    /// 系统提示词（取第一轮）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// 按顺序排列的轮次
+    pub turns: Vec<ConversationTurn>,
+    /// Flow 未构成线性链时的警告
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// 将按顺序排列的 Flow 合并为会话记录
+pub fn merge_conversation(flows: &[LLMFlow]) -> ConversationTranscript {
+    let mut transcript = ConversationTranscript {
+        system_prompt: flows.first().and_then(|f| f.request.system_prompt.clone()),
+        ..Default::default()
+    };
+
+    let mut previous: Option<&LLMFlow> = None;
+    for flow in flows {
+        let messages = &flow.request.messages;
+        let mut new_messages: &[Message] = messages;
+
+        if let Some(prev) = previous {
+            if prev.request.system_prompt != flow.request.system_prompt {
+                transcript.warnings.push(format!(
+                    "Flow {} 的系统提示词与上一轮 Flow {} 不同",
+                    flow.id, prev.id
+                ));
+            }
+
+            let prefix = &prev.request.messages;
+            if messages.starts_with(prefix) {
+                new_messages = &messages[prefix.len()..];
+                // 上一轮响应在本轮以助手消息的形式回放
+                let replayed = prev.response.as_ref().is_some_and(|r| {
+                    new_messages.first().is_some_and(|m| {
+                        m.role == MessageRole::Assistant && m.content.get_all_text() == r.content
+                    })
+                });
+                if replayed {
+                    new_messages = &new_messages[1..];
+                }
+            } else {
+                transcript.warnings.push(format!(
+                    "Flow {} 的消息不以上一轮 Flow {} 的消息为前缀（分支或新会话），输出完整消息",
+                    flow.id, prev.id
+                ));
+            }
+        }
+
+        transcript.turns.push(ConversationTurn {
+            flow_id: flow.id.clone(),
+            created_at: flow.timestamps.created,
+            model: flow.request.model.clone(),
+            new_messages: new_messages.to_vec(),
+            response: flow
+                .response
+                .as_ref()
+                .map(|r| r.content.clone())
+                .filter(|c| !c.is_empty()),
+            tool_calls: flow
+                .response
+                .as_ref()
+                .map(|r| r.tool_calls.clone())
+                .unwrap_or_default(),
+            error: flow.error.as_ref().map(|e| e.message.clone()),
+        });
+        previous = Some(flow);
+    }
+
+    transcript
+}
+
+/// 将会话记录渲染为 Markdown
+pub fn conversation_to_markdown(transcript: &ConversationTranscript) -> String {
+    let mut md = String::from("# 会话记录\n\n");
+    md.push_str(&format!("- **轮数**: {}\n", transcript.turns.len()));
+    if let (Some(first), Some(last)) = (transcript.turns.first(), transcript.turns.last()) {
+        md.push_str(&format!(
+            "- **时间**: {} ~ {}\n",
+            first.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            last.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+    }
+    md.push('\n');
+
+    for warning in &transcript.warnings {
+        md.push_str(&format!("> ⚠️ {}\n", warning));
+    }
+    if !transcript.warnings.is_empty() {
+        md.push('\n');
+    }
+
+    if let Some(ref system) = transcript.system_prompt {
+        md.push_str("## 系统提示词\n\n```\n");
+        md.push_str(system);
+        md.push_str("\n```\n\n");
+    }
+
+    for (i, turn) in transcript.turns.iter().enumerate() {
+        md.push_str(&format!(
+            "## 第 {} 轮\n\n`{}` · {} · {}\n\n",
+            i + 1,
+            turn.flow_id,
+            turn.model,
+            turn.created_at.format("%H:%M:%S")
+        ));
+
+        for msg in &turn.new_messages {
+            md.push_str(&format!(
+                "**{}**\n\n",
+                format!("{:?}", msg.role).to_uppercase()
+            ));
+            let content = match &msg.tool_result {
+                Some(result) if msg.content.get_all_text().is_empty() => result.content.clone(),
+                _ => msg.content.get_all_text(),
+            };
+            if !content.is_empty() {
+                md.push_str(&quote(&content));
+            }
+            for tc in msg.tool_calls.iter().flatten() {
+                md.push_str(&format!(
+                    "- 🔧 `{}`({})\n",
+                    tc.function.name, tc.function.arguments
+                ));
+            }
+            md.push('\n');
+        }
+
+        if turn.response.is_some() || !turn.tool_calls.is_empty() {
+            md.push_str("**ASSISTANT**\n\n");
+            if let Some(ref response) = turn.response {
+                md.push_str(&quote(response));
+            }
+            for tc in &turn.tool_calls {
+                md.push_str(&format!(
+                    "- 🔧 `{}`({})\n",
+                    tc.function.name, tc.function.arguments
+                ));
+            }
+            md.push('\n');
+        }
+
+        if let Some(ref error) = turn.error {
+            md.push_str(&format!("**错误**: {}\n\n", error));
+        }
+    }
+
+    md
+}
+
+/// 以 Markdown 引用块输出多行文本
+fn quote(text: &str) -> String {
+    let mut quoted: String = text.lines().map(|line| format!("> {}\n", line)).collect();
+    quoted.push('\n');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::models::{
+        FlowMetadata, FlowType, LLMRequest, LLMResponse, MessageContent,
+    };
+
+    fn message(role: MessageRole, text: &str) -> Message {
+        Message {
+            role,
+            content: MessageContent::Text(text.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn flow(id: &str, messages: Vec<Message>, response: &str) -> LLMFlow {
+        let request = LLMRequest {
+            messages,
+            system_prompt: Some("You are an agent.".to_string()),
+            model: "claude-sonnet-4-5".to_string(),
+            ..Default::default()
+        };
+        let mut flow = LLMFlow::new(
+            id.to_string(),
+            FlowType::AnthropicMessages,
+            request,
+            FlowMetadata::default(),
+        );
+        flow.response = Some(LLMResponse {
+            content: response.to_string(),
+            ..Default::default()
+        });
+        flow
+    }
+
+    #[test]
+    fn test_merge_linear_conversation() {
+        use MessageRole::*;
+        let flows = vec![
+            flow("f1", vec![message(User, "列出文件")], "a.txt b.txt"),
+            flow(
+                "f2",
+                vec![
+                    message(User, "列出文件"),
+                    message(Assistant, "a.txt b.txt"),
+                    message(User, "打开 a.txt"),
+                ],
+                "内容：hello",
+            ),
+            flow(
+                "f3",
+                vec![
+                    message(User, "列出文件"),
+                    message(Assistant, "a.txt b.txt"),
+                    message(User, "打开 a.txt"),
+                    message(Assistant, "内容：hello"),
+                    message(User, "谢谢"),
+                ],
+                "不客气",
+            ),
+        ];
+
+        let transcript = merge_conversation(&flows);
+        assert!(transcript.warnings.is_empty());
+        assert_eq!(
+            transcript.system_prompt.as_deref(),
+            Some("You are an agent.")
+        );
+        let new_counts: Vec<_> = transcript
+            .turns
+            .iter()
+            .map(|t| t.new_messages.len())
+            .collect();
+        assert_eq!(new_counts, vec![1, 1, 1]);
+        assert_eq!(
+            transcript.turns[2].new_messages[0].content.get_all_text(),
+            "谢谢"
+        );
+
+        let md = conversation_to_markdown(&transcript);
+        assert_eq!(md.matches("> 列出文件").count(), 1);
+        assert_eq!(md.matches("> a.txt b.txt").count(), 1);
+        assert!(md.contains("## 第 3 轮"));
+    }
+
+    #[test]
+    fn test_merge_branch_emits_warning() {
+        use MessageRole::*;
+        let flows = vec![
+            flow("f1", vec![message(User, "问题 A")], "回答 A"),
+            flow("f2", vec![message(User, "问题 B")], "回答 B"),
+        ];
+
+        let transcript = merge_conversation(&flows);
+        assert_eq!(transcript.warnings.len(), 1);
+        assert!(transcript.warnings[0].contains("f2"));
+        assert_eq!(transcript.turns[1].new_messages.len(), 1);
+        assert!(conversation_to_markdown(&transcript).contains("⚠️"));
+    }
+}
@@ -6,14 +6,20 @@
 //!
 //! - 对比两个 Flow 的请求差异
 //! - 对比两个 Flow 的响应差异
-//! - 对比消息列表的差异
+//! - 对比消息列表的差异（按位置，或按角色和内容对齐）
 //! - 计算 Token 使用量差异
+//! - 按重建内容偏移对齐流式 Chunk 序列，定位首个分歧点
+//! - 按输出位置对比 Token 级对数概率
 //! - 支持忽略动态字段（时间戳、ID 等）
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 
-use super::models::{LLMFlow, Message, MessageContent, TokenUsage};
+use super::models::{
+    LLMFlow, Message, MessageContent, MessageRole, ResponseLogprobs, StreamChunk, TokenUsage,
+};
 
 // ============================================================================
 // 差异类型
@@ -110,6 +116,9 @@ pub struct DiffConfig {
     pub ignore_timestamps: bool,
     /// 是否忽略 ID
     pub ignore_ids: bool,
+    /// 按角色和内容对齐消息（插入或删除的消息报告为新增/删除，而不是后续消息全部修改）
+    #[serde(default)]
+    pub align_by_role: bool,
 }
 
 impl Default for DiffConfig {
@@ -118,6 +127,7 @@ impl Default for DiffConfig {
             ignore_fields: vec![],
             ignore_timestamps: true,
             ignore_ids: true,
+            align_by_role: false,
         }
     }
 }
@@ -146,6 +156,12 @@ impl DiffConfig {
         self
     }
 
+    /// 设置是否按角色和内容对齐消息
+    pub fn with_align_by_role(mut self, align: bool) -> Self {
+        self.align_by_role = align;
+        self
+    }
+
     /// 检查字段是否应该被忽略
     pub fn should_ignore(&self, path: &str) -> bool {
         // 检查自定义忽略字段
@@ -237,6 +253,117 @@ pub struct MessageDiffItem {
     pub content_diffs: Vec<DiffItem>,
 }
 
+// ============================================================================
+// 流式 Chunk 差异
+// ============================================================================
+
+/// 流式 Chunk 差异项
+///
+/// 对应重建内容中的一个区间，区间边界取两侧 Chunk 边界的并集，
+/// 因此一侧的单个 Chunk 可能对应另一侧的多个区间。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamChunkDiffItem {
+    /// 区间在重建内容中的起始偏移（字符）
+    pub offset: usize,
+    /// 差异类型
+    pub diff_type: DiffType,
+    /// 左侧覆盖该区间的 Chunk 索引
+    pub left_chunk_index: Option<u32>,
+    /// 右侧覆盖该区间的 Chunk 索引
+    pub right_chunk_index: Option<u32>,
+    /// 左侧区间文本
+    pub left_text: Option<String>,
+    /// 右侧区间文本
+    pub right_text: Option<String>,
+}
+
+/// 流式 Chunk 序列差异
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct StreamChunkDiff {
+    /// 左侧 Chunk 数量
+    pub left_chunk_count: usize,
+    /// 右侧 Chunk 数量
+    pub right_chunk_count: usize,
+    /// 首个分歧点在重建内容中的偏移（字符，`None` 表示内容一致）
+    pub first_divergence_offset: Option<usize>,
+    /// 首个分歧点所在的左侧 Chunk 索引
+    pub first_divergence_left_index: Option<u32>,
+    /// 首个分歧点所在的右侧 Chunk 索引
+    pub first_divergence_right_index: Option<u32>,
+    /// 有差异的区间
+    pub chunk_diffs: Vec<StreamChunkDiffItem>,
+}
+
+impl StreamChunkDiff {
+    /// 检查是否有差异
+    pub fn has_diff(&self) -> bool {
+        self.first_divergence_offset.is_some()
+    }
+}
+
+/// 带偏移的 Chunk 文本
+struct ChunkSpan {
+    /// Chunk 索引
+    index: u32,
+    /// 在重建内容中的起始偏移（字符）
+    start: usize,
+    /// 在重建内容中的结束偏移（字符，不含）
+    end: usize,
+}
+
+impl ChunkSpan {
+    /// 查找覆盖指定偏移的 Chunk（区间按偏移升序且首尾相接）
+    fn at(spans: &[ChunkSpan], offset: usize) -> Option<&ChunkSpan> {
+        let i = spans.partition_point(|s| s.end <= offset);
+        spans.get(i).filter(|s| s.start <= offset)
+    }
+}
+
+// ============================================================================
+// 对数概率差异
+// ============================================================================
+
+/// 单个输出位置的对数概率差异
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogprobDiffItem {
+    /// 输出 Token 位置
+    pub index: usize,
+    /// 左侧 Token
+    pub left_token: Option<String>,
+    /// 右侧 Token
+    pub right_token: Option<String>,
+    /// 左侧对数概率
+    pub left_logprob: Option<f64>,
+    /// 右侧对数概率
+    pub right_logprob: Option<f64>,
+    /// 对数概率变化（右 - 左，仅两侧 Token 相同时提供）
+    pub delta: Option<f64>,
+}
+
+/// 对数概率差异
+///
+/// 两侧按输出位置逐个对齐，首个 Token 不同的位置之后的对比仅供参考。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct LogprobDiff {
+    /// 首个 Token 不同的位置（`None` 表示 Token 序列一致）
+    pub first_divergence_index: Option<usize>,
+    /// 分歧点之前相同 Token 的最大对数概率变化（绝对值）
+    pub max_abs_delta: f64,
+    /// 分歧点之前相同 Token 的平均对数概率变化（绝对值）
+    pub mean_abs_delta: f64,
+    /// 任一侧被截断
+    pub truncated: bool,
+    /// 逐位置差异
+    pub items: Vec<LogprobDiffItem>,
+}
+
+impl LogprobDiff {
+    /// 检查是否有差异
+    pub fn has_diff(&self) -> bool {
+        self.first_divergence_index.is_some() || self.max_abs_delta > 0.0
+    }
+}
+
 // ============================================================================
 // Flow 差异结果
 // ============================================================================
@@ -258,6 +385,12 @@ pub struct FlowDiffResult {
     pub message_diffs: Vec<MessageDiffItem>,
     /// Token 差异
     pub token_diff: TokenDiff,
+    /// 流式 Chunk 差异（两侧都保存了原始 Chunk 时提供）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_chunk_diff: Option<StreamChunkDiff>,
+    /// 对数概率差异（两侧都保存了对数概率时提供）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprob_diff: Option<LogprobDiff>,
 }
 
 impl FlowDiffResult {
@@ -280,6 +413,14 @@ impl FlowDiffResult {
                 .iter()
                 .all(|d| d.diff_type == DiffType::Unchanged)
             || self.token_diff.has_diff()
+            || self
+                .stream_chunk_diff
+                .as_ref()
+                .is_some_and(StreamChunkDiff::has_diff)
+            || self
+                .logprob_diff
+                .as_ref()
+                .is_some_and(LogprobDiff::has_diff)
     }
 
     /// 获取所有有变化的差异项
@@ -318,11 +459,27 @@ impl FlowDiff {
         let response_diffs =
             Self::diff_responses(left.response.as_ref(), right.response.as_ref(), config);
         let metadata_diffs = Self::diff_metadata(&left.metadata, &right.metadata, config);
-        let message_diffs = Self::diff_messages(&left.request.messages, &right.request.messages);
+        let message_diffs = if config.align_by_role {
+            Self::diff_messages_aligned(&left.request.messages, &right.request.messages)
+        } else {
+            Self::diff_messages(&left.request.messages, &right.request.messages)
+        };
         let token_diff = Self::diff_tokens(
             left.response.as_ref().map(|r| &r.usage),
             right.response.as_ref().map(|r| &r.usage),
         );
+        let stream_chunk_diff = match (Self::raw_chunks(left), Self::raw_chunks(right)) {
+            (Some(left_chunks), Some(right_chunks)) => {
+                Some(Self::diff_stream_chunks(left_chunks, right_chunks))
+            }
+            _ => None,
+        };
+        let logprob_diff = match (Self::logprobs(left), Self::logprobs(right)) {
+            (Some(left_logprobs), Some(right_logprobs)) => {
+                Some(Self::diff_logprobs(left_logprobs, right_logprobs))
+            }
+            _ => None,
+        };
 
         FlowDiffResult {
             left_flow_id: left.id.clone(),
@@ -332,9 +489,167 @@ impl FlowDiff {
             metadata_diffs,
             message_diffs,
             token_diff,
+            stream_chunk_diff,
+            logprob_diff,
         }
     }
 
+    /// 获取 Flow 响应中保存的对数概率
+    fn logprobs(flow: &LLMFlow) -> Option<&ResponseLogprobs> {
+        flow.response.as_ref()?.logprobs.as_ref()
+    }
+
+    /// 按输出位置对比两侧的 Token 级对数概率
+    pub fn diff_logprobs(left: &ResponseLogprobs, right: &ResponseLogprobs) -> LogprobDiff {
+        let len = left.tokens.len().max(right.tokens.len());
+        let mut items = Vec::with_capacity(len);
+        let mut first_divergence_index = None;
+        let mut deltas = Vec::new();
+
+        for index in 0..len {
+            let l = left.tokens.get(index);
+            let r = right.tokens.get(index);
+            let same_token = matches!((l, r), (Some(l), Some(r)) if l.token == r.token);
+            if !same_token && first_divergence_index.is_none() {
+                first_divergence_index = Some(index);
+            }
+            let delta = match (l, r) {
+                (Some(l), Some(r)) if same_token => Some(r.logprob - l.logprob),
+                _ => None,
+            };
+            if let (Some(delta), None) = (delta, first_divergence_index) {
+                deltas.push(delta.abs());
+            }
+            items.push(LogprobDiffItem {
+                index,
+                left_token: l.map(|t| t.token.clone()),
+                right_token: r.map(|t| t.token.clone()),
+                left_logprob: l.map(|t| t.logprob),
+                right_logprob: r.map(|t| t.logprob),
+                delta,
+            });
+        }
+
+        LogprobDiff {
+            first_divergence_index,
+            max_abs_delta: deltas.iter().copied().fold(0.0, f64::max),
+            mean_abs_delta: if deltas.is_empty() {
+                0.0
+            } else {
+                deltas.iter().sum::<f64>() / deltas.len() as f64
+            },
+            truncated: left.truncated || right.truncated,
+            items,
+        }
+    }
+
+    /// 获取 Flow 保存的原始 Chunk（需开启 `save_stream_chunks`）
+    fn raw_chunks(flow: &LLMFlow) -> Option<&[StreamChunk]> {
+        flow.response
+            .as_ref()?
+            .stream_info
+            .as_ref()?
+            .raw_chunks
+            .as_deref()
+    }
+
+    /// 对比两个流式 Chunk 序列
+    ///
+    /// 按重建内容（思维链、文本和工具参数增量依次拼接）的字符偏移对齐，
+    /// 而不是按 Chunk 索引对齐，因此同一内容被拆分成不同数量的 Chunk 时
+    /// 不会被视为差异。
+    pub fn diff_stream_chunks(left: &[StreamChunk], right: &[StreamChunk]) -> StreamChunkDiff {
+        let (left_text, left_spans) = Self::chunk_spans(left);
+        let (right_text, right_spans) = Self::chunk_spans(right);
+
+        let first_divergence_offset = left_text
+            .iter()
+            .zip(&right_text)
+            .position(|(l, r)| l != r)
+            .or_else(|| {
+                (left_text.len() != right_text.len()).then(|| left_text.len().min(right_text.len()))
+            });
+
+        // 区间边界取两侧 Chunk 边界的并集
+        let mut boundaries: Vec<usize> = left_spans
+            .iter()
+            .chain(&right_spans)
+            .flat_map(|span| [span.start, span.end])
+            .collect();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let chunk_diffs = boundaries
+            .windows(2)
+            .filter_map(|range| {
+                let (start, end) = (range[0], range[1]);
+                let left_span = ChunkSpan::at(&left_spans, start);
+                let right_span = ChunkSpan::at(&right_spans, start);
+                let left_segment = left_span.map(|_| left_text[start..end].iter().collect());
+                let right_segment = right_span.map(|_| right_text[start..end].iter().collect());
+                let diff_type = match (&left_segment, &right_segment) {
+                    (Some(l), Some(r)) if l == r => return None,
+                    (Some(_), Some(_)) => DiffType::Modified,
+                    (Some(_), None) => DiffType::Removed,
+                    (None, Some(_)) => DiffType::Added,
+                    (None, None) => return None,
+                };
+                Some(StreamChunkDiffItem {
+                    offset: start,
+                    diff_type,
+                    left_chunk_index: left_span.map(|s| s.index),
+                    right_chunk_index: right_span.map(|s| s.index),
+                    left_text: left_segment,
+                    right_text: right_segment,
+                })
+            })
+            .collect();
+
+        // 一侧内容是另一侧的前缀时，较短一侧取最后一个 Chunk
+        let chunk_at = |spans: &[ChunkSpan], offset: usize| {
+            ChunkSpan::at(spans, offset)
+                .or(spans.last())
+                .map(|s| s.index)
+        };
+
+        StreamChunkDiff {
+            left_chunk_count: left.len(),
+            right_chunk_count: right.len(),
+            first_divergence_offset,
+            first_divergence_left_index: first_divergence_offset
+                .and_then(|offset| chunk_at(&left_spans, offset)),
+            first_divergence_right_index: first_divergence_offset
+                .and_then(|offset| chunk_at(&right_spans, offset)),
+            chunk_diffs,
+        }
+    }
+
+    /// 重建 Chunk 序列的内容，并记录每个有内容的 Chunk 所占的区间
+    fn chunk_spans(chunks: &[StreamChunk]) -> (Vec<char>, Vec<ChunkSpan>) {
+        let mut text = Vec::new();
+        let mut spans = Vec::new();
+        for chunk in chunks {
+            let start = text.len();
+            let deltas = [
+                chunk.thinking_delta.as_deref(),
+                chunk.content_delta.as_deref(),
+                chunk
+                    .tool_call_delta
+                    .as_ref()
+                    .and_then(|d| d.arguments_delta.as_deref()),
+            ];
+            text.extend(deltas.into_iter().flatten().flat_map(str::chars));
+            if text.len() > start {
+                spans.push(ChunkSpan {
+                    index: chunk.index,
+                    start,
+                    end: text.len(),
+                });
+            }
+        }
+        (text, spans)
+    }
+
     /// 对比请求
     fn diff_requests(
         left: &super::models::LLMRequest,
@@ -530,6 +845,39 @@ impl FlowDiff {
                     ));
                 }
 
+                // 对比响应头（请求 ID 等每次都不同的头按 ID 字段处理）
+                if !config.should_ignore("response.headers") {
+                    let mut names: Vec<&String> =
+                        l.headers.keys().chain(r.headers.keys()).collect();
+                    names.sort();
+                    names.dedup();
+                    for name in names {
+                        if config.ignore_ids
+                            && (name.contains("request-id") || name.contains("requestid"))
+                        {
+                            continue;
+                        }
+                        let path = format!("response.headers.{}", name);
+                        if config.should_ignore(&path) {
+                            continue;
+                        }
+                        match (l.headers.get(name), r.headers.get(name)) {
+                            (Some(lv), Some(rv)) if lv != rv => diffs.push(DiffItem::modified(
+                                path,
+                                Value::String(lv.clone()),
+                                Value::String(rv.clone()),
+                            )),
+                            (Some(lv), None) => {
+                                diffs.push(DiffItem::removed(path, Value::String(lv.clone())))
+                            }
+                            (None, Some(rv)) => {
+                                diffs.push(DiffItem::added(path, Value::String(rv.clone())))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
                 // 对比内容
                 if !config.should_ignore("response.content") && l.content != r.content {
                     diffs.push(DiffItem::modified(
@@ -728,6 +1076,106 @@ impl FlowDiff {
         diffs
     }
 
+    /// 按角色和内容对齐后对比消息列表
+    ///
+    /// 先对 `(角色, 内容哈希)` 序列求最长公共子序列，相同的消息互相对齐；两个对齐点之间
+    /// 剩余的消息再按角色序列对齐为修改，其余报告为新增或删除。新增和对齐的消息使用右侧索引，
+    /// 删除的消息使用左侧索引。
+    pub fn diff_messages_aligned(left: &[Message], right: &[Message]) -> Vec<MessageDiffItem> {
+        let keys = |messages: &[Message]| -> Vec<(MessageRole, u64)> {
+            messages
+                .iter()
+                .map(|m| {
+                    let mut hasher = DefaultHasher::new();
+                    Self::get_message_text(&m.content).hash(&mut hasher);
+                    (m.role.clone(), hasher.finish())
+                })
+                .collect()
+        };
+
+        let mut diffs = Vec::new();
+        let (mut left_start, mut right_start) = (0, 0);
+        let anchors = lcs_pairs(&keys(left), &keys(right));
+        for (li, ri) in anchors
+            .into_iter()
+            .chain(std::iter::once((left.len(), right.len())))
+        {
+            Self::diff_message_gap(left, left_start..li, right, right_start..ri, &mut diffs);
+            if let (Some(l), Some(r)) = (left.get(li), right.get(ri)) {
+                diffs.push(Self::paired_message_diff(l, r, ri));
+            }
+            left_start = li + 1;
+            right_start = ri + 1;
+        }
+
+        diffs
+    }
+
+    /// 对比两个对齐点之间的消息：角色相同的按顺序配对为修改，其余为新增或删除
+    fn diff_message_gap(
+        left: &[Message],
+        left_range: std::ops::Range<usize>,
+        right: &[Message],
+        right_range: std::ops::Range<usize>,
+        diffs: &mut Vec<MessageDiffItem>,
+    ) {
+        let roles = |messages: &[Message], range: &std::ops::Range<usize>| -> Vec<MessageRole> {
+            messages[range.clone()]
+                .iter()
+                .map(|m| m.role.clone())
+                .collect()
+        };
+        let pairs = lcs_pairs(&roles(left, &left_range), &roles(right, &right_range));
+
+        let (mut li, mut ri) = (left_range.start, right_range.start);
+        for (pl, pr) in pairs
+            .into_iter()
+            .map(|(pl, pr)| (left_range.start + pl, right_range.start + pr))
+            .chain(std::iter::once((left_range.end, right_range.end)))
+        {
+            for (index, message) in left.iter().enumerate().take(pl).skip(li) {
+                diffs.push(MessageDiffItem {
+                    index,
+                    diff_type: DiffType::Removed,
+                    left_message: Some(message.clone()),
+                    right_message: None,
+                    content_diffs: vec![],
+                });
+            }
+            for (index, message) in right.iter().enumerate().take(pr).skip(ri) {
+                diffs.push(MessageDiffItem {
+                    index,
+                    diff_type: DiffType::Added,
+                    left_message: None,
+                    right_message: Some(message.clone()),
+                    content_diffs: vec![],
+                });
+            }
+            if pl < left_range.end {
+                diffs.push(Self::paired_message_diff(&left[pl], &right[pr], pr));
+            }
+            li = pl + 1;
+            ri = pr + 1;
+        }
+    }
+
+    /// 对比已对齐的两条消息
+    fn paired_message_diff(left: &Message, right: &Message, index: usize) -> MessageDiffItem {
+        let content_diffs = Self::diff_message_content(left, right, index);
+        let diff_type = if content_diffs.is_empty() {
+            DiffType::Unchanged
+        } else {
+            DiffType::Modified
+        };
+        MessageDiffItem {
+            index,
+            diff_type,
+            left_message: Some(left.clone()),
+            right_message: Some(right.clone()),
+            content_diffs,
+        }
+    }
+
     /// 对比单个消息的内容
     fn diff_message_content(left: &Message, right: &Message, index: usize) -> Vec<DiffItem> {
         let mut diffs = Vec::new();
@@ -918,6 +1366,37 @@ impl FlowDiff {
     }
 }
 
+/// 最长公共子序列，返回按顺序排列的对齐下标对 `(左侧下标, 右侧下标)`
+fn lcs_pairs<T: PartialEq>(left: &[T], right: &[T]) -> Vec<(usize, usize)> {
+    let (n, m) = (left.len(), right.len());
+    // lengths[i][j] 为 left[i..] 与 right[j..] 的 LCS 长度
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if left[i] == right[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::with_capacity(lengths[0][0]);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if left[i] == right[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
 // ============================================================================
 // 单元测试
 // ============================================================================
@@ -1047,6 +1526,39 @@ mod tests {
         assert!(!config.should_ignore("request.model"));
     }
 
+    #[test]
+    fn test_diff_response_headers() {
+        let mut flow1 = create_test_flow("id1", "gpt-4", "Hello");
+        let mut flow2 = create_test_flow("id2", "gpt-4", "Hello");
+        for (flow, remaining, request_id) in
+            [(&mut flow1, "100", "req_1"), (&mut flow2, "3", "req_2")]
+        {
+            let headers = &mut flow.response.as_mut().unwrap().headers;
+            headers.insert(
+                "x-ratelimit-remaining-requests".to_string(),
+                remaining.to_string(),
+            );
+            headers.insert("x-request-id".to_string(), request_id.to_string());
+        }
+
+        let result = FlowDiff::diff(&flow1, &flow2, &DiffConfig::default());
+        let paths: Vec<&str> = result
+            .response_diffs
+            .iter()
+            .map(|d| d.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["response.headers.x-ratelimit-remaining-requests"]
+        );
+
+        let result = FlowDiff::diff(&flow1, &flow2, &DiffConfig::new().with_ignore_ids(false));
+        assert!(result
+            .response_diffs
+            .iter()
+            .any(|d| d.path == "response.headers.x-request-id"));
+    }
+
     #[test]
     fn test_diff_json_objects() {
         let left = serde_json::json!({
@@ -1131,6 +1643,177 @@ mod tests {
         assert_eq!(diffs[0].diff_type, DiffType::Unchanged);
         assert_eq!(diffs[1].diff_type, DiffType::Removed);
     }
+
+    #[test]
+    fn test_diff_messages_aligned_by_role() {
+        let message = |role: MessageRole, text: &str| Message {
+            role,
+            content: MessageContent::Text(text.to_string()),
+            ..Default::default()
+        };
+        let left = vec![
+            message(MessageRole::User, "Hello"),
+            message(MessageRole::Assistant, "Hi there"),
+            message(MessageRole::User, "How are you?"),
+        ];
+        let mut right = left.clone();
+        right.insert(0, message(MessageRole::System, "Be concise"));
+
+        // 按位置对比时插入点之后的消息全部显示为修改
+        let positional = FlowDiff::diff_messages(&left, &right);
+        assert_eq!(
+            positional
+                .iter()
+                .filter(|d| d.diff_type == DiffType::Modified)
+                .count(),
+            3
+        );
+
+        let diffs = FlowDiff::diff_messages_aligned(&left, &right);
+        let added: Vec<_> = diffs
+            .iter()
+            .filter(|d| d.diff_type == DiffType::Added)
+            .collect();
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].index, 0);
+        assert_eq!(diffs.len(), 4);
+        assert!(diffs[1..]
+            .iter()
+            .all(|d| d.diff_type == DiffType::Unchanged));
+
+        // 对齐点之间角色相同的消息配对为修改，而不是删除加新增
+        let mut edited = left.clone();
+        edited[2] = message(MessageRole::User, "How old are you?");
+        let diffs = FlowDiff::diff_messages_aligned(&left, &edited);
+        let types: Vec<_> = diffs.iter().map(|d| d.diff_type).collect();
+        assert_eq!(
+            types,
+            [DiffType::Unchanged, DiffType::Unchanged, DiffType::Modified]
+        );
+
+        let config = DiffConfig::new().with_align_by_role(true);
+        let left_flow = LLMFlow::new(
+            "left".to_string(),
+            FlowType::ChatCompletions,
+            LLMRequest {
+                messages: left,
+                ..Default::default()
+            },
+            FlowMetadata::default(),
+        );
+        let mut right_flow = left_flow.clone();
+        right_flow.request.messages = right;
+        let result = FlowDiff::diff(&left_flow, &right_flow, &config);
+        assert_eq!(
+            result
+                .message_diffs
+                .iter()
+                .filter(|d| d.diff_type == DiffType::Added)
+                .count(),
+            1
+        );
+    }
+
+    fn text_chunks(deltas: &[&str]) -> Vec<StreamChunk> {
+        deltas
+            .iter()
+            .enumerate()
+            .map(|(i, delta)| StreamChunk {
+                index: i as u32,
+                event: None,
+                data: String::new(),
+                timestamp: chrono::Utc::now(),
+                content_delta: Some(delta.to_string()),
+                tool_call_delta: None,
+                thinking_delta: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_stream_chunks_aligns_on_content_offsets() {
+        // 相同内容拆分为不同数量的 Chunk 不视为差异
+        let left = text_chunks(&["Hello", ", world"]);
+        let right = text_chunks(&["Hel", "lo, ", "wor", "ld"]);
+        let diff = FlowDiff::diff_stream_chunks(&left, &right);
+        assert!(!diff.has_diff());
+        assert!(diff.chunk_diffs.is_empty());
+        assert_eq!((diff.left_chunk_count, diff.right_chunk_count), (2, 4));
+
+        // 在右侧第 3 个 Chunk 中出现分歧
+        let right = text_chunks(&["Hel", "lo, ", "wir", "ld!"]);
+        let diff = FlowDiff::diff_stream_chunks(&left, &right);
+        assert_eq!(diff.first_divergence_offset, Some(8));
+        assert_eq!(diff.first_divergence_left_index, Some(1));
+        assert_eq!(diff.first_divergence_right_index, Some(2));
+        assert_eq!(diff.chunk_diffs.len(), 2);
+        assert_eq!(diff.chunk_diffs[0].diff_type, DiffType::Modified);
+        assert_eq!(diff.chunk_diffs[0].left_text.as_deref(), Some("wor"));
+        assert_eq!(diff.chunk_diffs[0].right_text.as_deref(), Some("wir"));
+        assert_eq!(diff.chunk_diffs[1].diff_type, DiffType::Added);
+        assert_eq!(diff.chunk_diffs[1].offset, 12);
+        assert_eq!(diff.chunk_diffs[1].right_text.as_deref(), Some("!"));
+    }
+
+    #[test]
+    fn test_diff_includes_stream_chunks_when_saved() {
+        use crate::flow_monitor::models::StreamInfo;
+
+        let mut left = create_test_flow("id1", "gpt-4", "Hello");
+        let mut right = create_test_flow("id2", "gpt-4", "Hello");
+        let config = DiffConfig::default();
+        assert!(FlowDiff::diff(&left, &right, &config)
+            .stream_chunk_diff
+            .is_none());
+
+        for (flow, deltas) in [(&mut left, ["a", "b"]), (&mut right, ["a", "c"])] {
+            flow.response.as_mut().unwrap().stream_info = Some(StreamInfo {
+                chunk_count: 2,
+                first_chunk_latency_ms: 0,
+                avg_chunk_interval_ms: 0.0,
+                raw_chunks: Some(text_chunks(&deltas)),
+                completed_cleanly: true,
+            });
+        }
+        let result = FlowDiff::diff(&left, &right, &config);
+        let stream_diff = result.stream_chunk_diff.as_ref().unwrap();
+        assert_eq!(stream_diff.first_divergence_offset, Some(1));
+        assert!(result.has_diff());
+    }
+
+    #[test]
+    fn test_diff_logprobs() {
+        use crate::flow_monitor::models::TokenLogprob;
+
+        let logprobs = |entries: &[(&str, f64)]| ResponseLogprobs {
+            tokens: entries
+                .iter()
+                .map(|(token, logprob)| TokenLogprob {
+                    token: token.to_string(),
+                    logprob: *logprob,
+                    top_logprobs: Vec::new(),
+                })
+                .collect(),
+            total_tokens: entries.len(),
+            truncated: false,
+        };
+        let mut left = create_test_flow("id1", "gpt-4", "Hello");
+        let mut right = create_test_flow("id2", "gpt-4", "Hello");
+        left.response.as_mut().unwrap().logprobs =
+            Some(logprobs(&[("Hello", -0.5), (" world", -1.0), ("!", -0.2)]));
+        right.response.as_mut().unwrap().logprobs =
+            Some(logprobs(&[("Hello", -0.25), (" world", -1.0), ("?", -0.3)]));
+
+        let result = FlowDiff::diff(&left, &right, &DiffConfig::default());
+        let diff = result.logprob_diff.as_ref().unwrap();
+        assert_eq!(diff.first_divergence_index, Some(2));
+        assert_eq!(diff.max_abs_delta, 0.25);
+        assert_eq!(diff.mean_abs_delta, 0.125);
+        assert_eq!(diff.items[0].delta, Some(0.25));
+        assert_eq!(diff.items[2].delta, None);
+        assert_eq!(diff.items[2].right_token.as_deref(), Some("?"));
+        assert!(result.has_diff());
+    }
 }
 
 // ============================================================================
@@ -1203,6 +1886,8 @@ mod property_tests {
                     max_tokens,
                     stop: None,
                     stream,
+                    response_format: None,
+                    seed: None,
                     extra: std::collections::HashMap::new(),
                 },
             )
@@ -1239,6 +1924,7 @@ mod property_tests {
                 parameters,
                 size_bytes: 0,
                 timestamp: Utc::now(),
+                attachments: Vec::new(),
             })
     }
 
@@ -1258,10 +1944,16 @@ mod property_tests {
                 tool_calls: vec![],
                 usage,
                 stop_reason: None,
+                raw_stop_reason: None,
                 size_bytes: 0,
                 timestamp_start: Utc::now(),
                 timestamp_end: Utc::now(),
                 stream_info: None,
+                schema_valid: None,
+                logprobs: None,
+                body_info: None,
+                system_fingerprint: None,
+                turns: Vec::new(),
             })
     }
 
@@ -1275,7 +1967,13 @@ mod property_tests {
             client_info: Default::default(),
             routing_info: Default::default(),
             injected_params: None,
+            defaulted_params: None,
             context_usage_percentage: None,
+            shadow_of: None,
+            idempotent_replay_of: None,
+            upstream_request_id: None,
+            usage_source: None,
+            content_filter: None,
         })
     }
 
@@ -1300,6 +1998,7 @@ mod property_tests {
             ignore_fields: vec![],
             ignore_timestamps,
             ignore_ids,
+            align_by_role: false,
         })
     }
 
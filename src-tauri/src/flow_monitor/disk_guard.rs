@@ -0,0 +1,274 @@
+//! 磁盘空间保护
+//!
+//! 磁盘写满后 `file_store.write` 对每个 Flow 都会失败并刷屏错误日志。写入前检查存储目录
+//! 所在磁盘的剩余空间，低于配置的下限时暂停持久化（Flow 仍保留在内存中），空间恢复后自动恢复。
+//!
+//! 剩余空间按检查间隔缓存，间隔内的写入直接使用上次的结果，不会每个 Flow 都查询一次文件系统。
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// 磁盘空间保护配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskGuardConfig {
+    /// 存储目录所在磁盘的最小剩余空间（MB，0 表示不检查）
+    #[serde(default = "default_min_free_mb")]
+    pub min_free_mb: u64,
+    /// 剩余空间的检查间隔（秒）
+    #[serde(default = "default_check_interval_secs")]
+    pub check_interval_secs: u64,
+}
+
+fn default_min_free_mb() -> u64 {
+    512
+}
+
+fn default_check_interval_secs() -> u64 {
+    30
+}
+
+impl Default for DiskGuardConfig {
+    fn default() -> Self {
+        Self {
+            min_free_mb: default_min_free_mb(),
+            check_interval_secs: default_check_interval_secs(),
+        }
+    }
+}
+
+impl DiskGuardConfig {
+    /// 最小剩余空间（字节）
+    pub fn min_free_bytes(&self) -> u64 {
+        self.min_free_mb.saturating_mul(1024 * 1024)
+    }
+
+    /// 检查间隔（至少 1 秒）
+    pub fn check_interval(&self) -> Duration {
+        Duration::from_secs(self.check_interval_secs.max(1))
+    }
+}
+
+/// 持久化状态的变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskTransition {
+    /// 剩余空间低于下限，暂停持久化
+    Paused { available_bytes: u64 },
+    /// 剩余空间恢复，恢复持久化
+    Resumed { available_bytes: u64 },
+}
+
+/// 一次写入前检查的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskCheck {
+    /// 是否可以写入
+    pub writable: bool,
+    /// 本次检查引起的状态变化（调用方据此输出一次日志和通知）
+    pub transition: Option<DiskTransition>,
+}
+
+/// 磁盘状态（供监控状态命令展示）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskStatus {
+    /// 是否启用检查
+    pub enabled: bool,
+    /// 持久化是否因空间不足暂停
+    pub persistence_paused: bool,
+    /// 最近一次检查时的剩余空间（字节，尚未检查或查询失败时为空）
+    pub available_bytes: Option<u64>,
+    /// 最小剩余空间（字节）
+    pub min_free_bytes: u64,
+    /// 最近一次检查时间
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct GuardState {
+    checked: Option<(Instant, DateTime<Utc>)>,
+    available_bytes: Option<u64>,
+    paused: bool,
+}
+
+/// 磁盘空间保护
+#[derive(Debug, Default)]
+pub struct DiskGuard {
+    state: Mutex<GuardState>,
+}
+
+impl DiskGuard {
+    /// 创建磁盘空间保护
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 写入前检查存储目录所在磁盘的剩余空间
+    pub fn check(&self, config: &DiskGuardConfig, dir: &Path) -> DiskCheck {
+        self.check_with(config, Instant::now(), || fs2::available_space(dir))
+    }
+
+    /// 使用给定的查询函数检查（检查间隔内不调用查询函数）
+    fn check_with(
+        &self,
+        config: &DiskGuardConfig,
+        now: Instant,
+        available_space: impl FnOnce() -> std::io::Result<u64>,
+    ) -> DiskCheck {
+        let mut state = self.state.lock();
+
+        if config.min_free_mb == 0 {
+            // 关闭检查时立即恢复持久化
+            let transition = std::mem::take(&mut state.paused).then_some(DiskTransition::Resumed {
+                available_bytes: state.available_bytes.unwrap_or_default(),
+            });
+            return DiskCheck {
+                writable: true,
+                transition,
+            };
+        }
+
+        let due = state
+            .checked
+            .is_none_or(|(at, _)| now.duration_since(at) >= config.check_interval());
+        if !due {
+            return DiskCheck {
+                writable: !state.paused,
+                transition: None,
+            };
+        }
+
+        state.checked = Some((now, Utc::now()));
+        let available_bytes = match available_space() {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                // 查询失败时不阻止写入，写入本身的错误仍会记录
+                tracing::debug!("查询磁盘剩余空间失败: {}", e);
+                state.available_bytes = None;
+                let transition = std::mem::take(&mut state.paused)
+                    .then_some(DiskTransition::Resumed { available_bytes: 0 });
+                return DiskCheck {
+                    writable: true,
+                    transition,
+                };
+            }
+        };
+        state.available_bytes = Some(available_bytes);
+
+        let low = available_bytes < config.min_free_bytes();
+        let transition = match (state.paused, low) {
+            (false, true) => Some(DiskTransition::Paused { available_bytes }),
+            (true, false) => Some(DiskTransition::Resumed { available_bytes }),
+            _ => None,
+        };
+        state.paused = low;
+        DiskCheck {
+            writable: !low,
+            transition,
+        }
+    }
+
+    /// 当前磁盘状态
+    pub fn status(&self, config: &DiskGuardConfig) -> DiskStatus {
+        let state = self.state.lock();
+        DiskStatus {
+            enabled: config.min_free_mb > 0,
+            persistence_paused: state.paused,
+            available_bytes: state.available_bytes,
+            min_free_bytes: config.min_free_bytes(),
+            checked_at: state.checked.map(|(_, at)| at),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    fn config() -> DiskGuardConfig {
+        DiskGuardConfig {
+            min_free_mb: 100,
+            check_interval_secs: 30,
+        }
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let guard = DiskGuard::new();
+        let config = config();
+        let start = Instant::now();
+
+        let check = guard.check_with(&config, start, || Ok(500 * MB));
+        assert_eq!(
+            check,
+            DiskCheck {
+                writable: true,
+                transition: None
+            }
+        );
+
+        // 间隔内使用缓存结果，不查询文件系统
+        let check = guard.check_with(&config, start + Duration::from_secs(10), || {
+            panic!("should use cached result")
+        });
+        assert!(check.writable);
+
+        let check = guard.check_with(&config, start + Duration::from_secs(30), || Ok(50 * MB));
+        assert_eq!(
+            check,
+            DiskCheck {
+                writable: false,
+                transition: Some(DiskTransition::Paused {
+                    available_bytes: 50 * MB
+                })
+            }
+        );
+        assert!(guard.status(&config).persistence_paused);
+
+        // 仍然不足时只在首次变化时报告
+        let check = guard.check_with(&config, start + Duration::from_secs(60), || Ok(60 * MB));
+        assert_eq!(
+            check,
+            DiskCheck {
+                writable: false,
+                transition: None
+            }
+        );
+
+        let check = guard.check_with(&config, start + Duration::from_secs(90), || Ok(200 * MB));
+        assert_eq!(
+            check.transition,
+            Some(DiskTransition::Resumed {
+                available_bytes: 200 * MB
+            })
+        );
+        assert!(check.writable);
+
+        let status = guard.status(&config);
+        assert!(!status.persistence_paused);
+        assert_eq!(status.available_bytes, Some(200 * MB));
+        assert_eq!(status.min_free_bytes, 100 * MB);
+    }
+
+    #[test]
+    fn test_disabled_and_query_failure_allow_writes() {
+        let guard = DiskGuard::new();
+        let disabled = DiskGuardConfig {
+            min_free_mb: 0,
+            ..config()
+        };
+        let check = guard.check_with(&disabled, Instant::now(), || {
+            panic!("disabled guard should not query")
+        });
+        assert!(check.writable);
+        assert!(!guard.status(&disabled).enabled);
+
+        let check = guard.check_with(&config(), Instant::now(), || {
+            Err(std::io::Error::other("unsupported"))
+        });
+        assert!(check.writable);
+        assert_eq!(guard.status(&config()).available_bytes, None);
+    }
+}
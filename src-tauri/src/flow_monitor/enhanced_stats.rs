@@ -9,8 +9,11 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use super::content_hash::ContentNormalizer;
 use super::memory_store::{FlowFilter, FlowMemoryStore, TimeRange};
-use super::models::{FlowState, LLMFlow};
+use super::models::{FlowState, LLMFlow, MessageRole, TokenUsage};
+use crate::ProviderType;
+use sha2::{Digest, Sha256};
 use tokio::sync::RwLock;
 
 // ============================================================================
@@ -62,6 +65,25 @@ impl Default for TrendData {
     }
 }
 
+/// 提示词缓存效果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheEffectiveness {
+    /// 上报了缓存用量的 Flow 数
+    pub flows_with_cache_usage: u64,
+    /// 命中缓存的 Flow 数
+    pub cache_hit_flows: u64,
+    /// 上述 Flow 的输入 Token 总数
+    pub input_tokens: u64,
+    /// 缓存读取 Token 总数
+    pub cache_read_tokens: u64,
+    /// 缓存写入 Token 总数
+    pub cache_write_tokens: u64,
+    /// 缓存命中率（缓存读取 / 输入 Token）
+    pub hit_ratio: f64,
+    /// 节省的成本，折算为全价输入 Token 数（扣除缓存写入的额外费用，可能为负）
+    pub saved_input_tokens: f64,
+}
+
 /// 增强统计结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnhancedStats {
@@ -71,10 +93,19 @@ pub struct EnhancedStats {
     pub token_by_model: Distribution,
     /// 按提供商的成功率
     pub success_by_provider: Vec<(String, f64)>,
+    /// 按提供商的未正常结束（可能被截断）的流式响应比例，只统计流式响应
+    #[serde(default)]
+    pub incomplete_stream_by_provider: Vec<(String, f64)>,
     /// 延迟直方图
     pub latency_histogram: Distribution,
     /// 错误分布
     pub error_distribution: Distribution,
+    /// 停止原因分布（规范化后）
+    #[serde(default)]
+    pub stop_reason_distribution: Distribution,
+    /// 提示词缓存效果
+    #[serde(default)]
+    pub cache_effectiveness: CacheEffectiveness,
     /// 请求速率（每秒）
     pub request_rate: f64,
     /// 时间范围
@@ -87,14 +118,116 @@ impl Default for EnhancedStats {
             request_trend: TrendData::default(),
             token_by_model: Distribution::default(),
             success_by_provider: Vec::new(),
+            incomplete_stream_by_provider: Vec::new(),
             latency_histogram: Distribution::default(),
             error_distribution: Distribution::default(),
+            stop_reason_distribution: Distribution::default(),
+            cache_effectiveness: CacheEffectiveness::default(),
             request_rate: 0.0,
             time_range: StatsTimeRange::default(),
         }
     }
 }
 
+/// 提示词模板聚类依据的消息
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptClusterRole {
+    /// 系统提示词
+    System,
+    /// 第一条用户消息
+    FirstUser,
+    /// 系统提示词 + 第一条用户消息
+    SystemAndFirstUser,
+}
+
+impl Default for PromptClusterRole {
+    fn default() -> Self {
+        PromptClusterRole::System
+    }
+}
+
+/// 模型单价（美元 / 百万 Token）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    /// 输入单价
+    pub input_per_million: f64,
+    /// 输出单价
+    pub output_per_million: f64,
+}
+
+/// 提示词模板聚合选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplateOptions {
+    /// 聚类依据的消息
+    #[serde(default)]
+    pub role: PromptClusterRole,
+    /// 按模型名的单价表
+    #[serde(default)]
+    pub prices: HashMap<String, ModelPrice>,
+    /// 单价表未覆盖的模型使用的单价（为空时这些 Flow 不计成本）
+    #[serde(default)]
+    pub default_price: Option<ModelPrice>,
+    /// 返回的模板数量上限
+    #[serde(default = "default_template_limit")]
+    pub limit: usize,
+}
+
+impl Default for PromptTemplateOptions {
+    fn default() -> Self {
+        Self {
+            role: PromptClusterRole::default(),
+            prices: HashMap::new(),
+            default_price: None,
+            limit: default_template_limit(),
+        }
+    }
+}
+
+/// 单个提示词模板的聚合统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptTemplateStats {
+    /// 模板哈希（规范化文本的 SHA-256 前 16 位，跨运行稳定）
+    pub template_hash: String,
+    /// 规范化后的模板文本（截断）
+    pub sample: String,
+    /// 请求数
+    pub count: u64,
+    /// 输入 Token 总数
+    pub input_tokens: u64,
+    /// 输出 Token 总数
+    pub output_tokens: u64,
+    /// Token 总数
+    pub total_tokens: u64,
+    /// 成本（美元）
+    pub cost: f64,
+    /// 占总成本的比例
+    pub cost_share: f64,
+    /// 占总 Token 的比例
+    pub token_share: f64,
+    /// 使用过该模板的模型
+    pub models: Vec<String>,
+}
+
+/// 提示词模板成本报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptTemplateReport {
+    /// 聚类依据的消息
+    pub role: PromptClusterRole,
+    /// 按成本降序排列的模板
+    pub templates: Vec<PromptTemplateStats>,
+    /// 时间范围内的 Flow 总数
+    pub total_flows: u64,
+    /// 参与聚类的 Flow 数（缺少对应消息的 Flow 不参与）
+    pub clustered_flows: u64,
+    /// 参与聚类的 Flow 的总成本（美元）
+    pub total_cost: f64,
+    /// 参与聚类的 Flow 的 Token 总数
+    pub total_tokens: u64,
+    /// 没有单价而未计成本的 Flow 数
+    pub unpriced_flows: u64,
+}
+
 /// 统计时间范围
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StatsTimeRange {
@@ -179,17 +312,23 @@ impl EnhancedStatsService {
         let request_trend = self.calculate_request_trend(&flows, "1h");
         let token_by_model = self.calculate_token_distribution(&flows);
         let success_by_provider = self.calculate_success_by_provider(&flows);
+        let incomplete_stream_by_provider = self.calculate_incomplete_stream_by_provider(&flows);
         let latency_histogram =
             self.calculate_latency_histogram(&flows, &default_latency_buckets());
         let error_distribution = self.calculate_error_distribution(&flows);
+        let stop_reason_distribution = self.calculate_stop_reason_distribution(&flows);
+        let cache_effectiveness = self.calculate_cache_effectiveness(&flows);
         let request_rate = self.calculate_request_rate(&flows, time_range);
 
         EnhancedStats {
             request_trend,
             token_by_model,
             success_by_provider,
+            incomplete_stream_by_provider,
             latency_histogram,
             error_distribution,
+            stop_reason_distribution,
+            cache_effectiveness,
             request_rate,
             time_range: time_range.clone(),
         }
@@ -256,6 +395,30 @@ impl EnhancedStatsService {
         self.calculate_latency_histogram(&flows, buckets)
     }
 
+    /// 按提示词模板聚合请求数、Token 和成本
+    ///
+    /// 对系统提示词或第一条用户消息先按共享的内容规范化规则处理，再把数字替换为 `#`
+    /// 后哈希，把只有插值不同的提示词归为同一模板。
+    ///
+    /// # Arguments
+    /// * `filter` - 过滤条件
+    /// * `time_range` - 时间范围
+    /// * `options` - 聚类依据和单价表
+    /// * `normalizer` - 内容规范化器（见 `FlowMonitor::content_normalizer`）
+    ///
+    /// # Returns
+    /// 按成本降序排列的模板报告
+    pub async fn by_prompt_template(
+        &self,
+        filter: &FlowFilter,
+        time_range: &StatsTimeRange,
+        options: &PromptTemplateOptions,
+        normalizer: &ContentNormalizer,
+    ) -> PromptTemplateReport {
+        let flows = self.get_flows_in_range(filter, time_range).await;
+        self.calculate_prompt_templates(&flows, options, normalizer)
+    }
+
     /// 导出统计报告
     ///
     /// **Validates: Requirements 9.7**
@@ -411,6 +574,38 @@ impl EnhancedStatsService {
         result
     }
 
+    /// 计算按提供商的未正常结束的流式响应比例
+    fn calculate_incomplete_stream_by_provider(&self, flows: &[LLMFlow]) -> Vec<(String, f64)> {
+        let mut provider_stats: HashMap<String, (usize, usize)> = HashMap::new();
+
+        for flow in flows {
+            let Some(stream_info) = flow.response.as_ref().and_then(|r| r.stream_info.as_ref())
+            else {
+                continue;
+            };
+            let provider = format!("{:?}", flow.metadata.provider);
+            let entry = provider_stats.entry(provider).or_insert((0, 0));
+            entry.0 += 1;
+            if !stream_info.completed_cleanly {
+                entry.1 += 1;
+            }
+        }
+
+        let mut result: Vec<(String, f64)> = provider_stats
+            .into_iter()
+            .map(|(provider, (total, incomplete))| (provider, incomplete as f64 / total as f64))
+            .collect();
+
+        // 按比例降序排序
+        result.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.cmp(&b.0))
+        });
+
+        result
+    }
+
     /// 计算延迟直方图
     fn calculate_latency_histogram(&self, flows: &[LLMFlow], buckets: &[u64]) -> Distribution {
         let mut bucket_counts: Vec<u64> = vec![0; buckets.len() + 1];
@@ -467,6 +662,144 @@ impl EnhancedStatsService {
         Distribution { buckets, total }
     }
 
+    /// 计算停止原因分布
+    ///
+    /// 旧数据可能保存了 `end_turn` 等未归并的取值，统计前统一规范化。
+    fn calculate_stop_reason_distribution(&self, flows: &[LLMFlow]) -> Distribution {
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        let mut total: u64 = 0;
+
+        for flow in flows {
+            let Some(stop_reason) = flow.response.as_ref().and_then(|r| r.stop_reason.clone())
+            else {
+                continue;
+            };
+            *counts
+                .entry(stop_reason.normalized().as_str().to_string())
+                .or_insert(0) += 1;
+            total += 1;
+        }
+
+        let mut buckets: Vec<(String, u64)> = counts.into_iter().collect();
+        buckets.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        Distribution { buckets, total }
+    }
+
+    /// 计算提示词缓存效果
+    fn calculate_cache_effectiveness(&self, flows: &[LLMFlow]) -> CacheEffectiveness {
+        let mut stats = CacheEffectiveness::default();
+
+        for flow in flows {
+            let Some(usage) = flow.response.as_ref().map(|r| &r.usage) else {
+                continue;
+            };
+            if !usage.has_cache_usage() {
+                continue;
+            }
+            let cache_read = usage.cache_read_tokens.unwrap_or(0);
+            let cache_write = usage.cache_write_tokens.unwrap_or(0);
+            let (read_price, write_price) = cache_price_ratios(&flow.metadata.provider);
+
+            stats.flows_with_cache_usage += 1;
+            if usage.is_cache_hit() {
+                stats.cache_hit_flows += 1;
+            }
+            stats.input_tokens += usage.input_tokens as u64;
+            stats.cache_read_tokens += cache_read as u64;
+            stats.cache_write_tokens += cache_write as u64;
+            stats.saved_input_tokens +=
+                cache_read as f64 * (1.0 - read_price) - cache_write as f64 * (write_price - 1.0);
+        }
+
+        if stats.input_tokens > 0 {
+            stats.hit_ratio = stats.cache_read_tokens as f64 / stats.input_tokens as f64;
+        }
+        stats
+    }
+
+    /// 计算提示词模板聚合
+    fn calculate_prompt_templates(
+        &self,
+        flows: &[LLMFlow],
+        options: &PromptTemplateOptions,
+        normalizer: &ContentNormalizer,
+    ) -> PromptTemplateReport {
+        let mut report = PromptTemplateReport {
+            role: options.role,
+            total_flows: flows.len() as u64,
+            ..Default::default()
+        };
+        let mut clusters: HashMap<String, PromptTemplateStats> = HashMap::new();
+
+        for flow in flows {
+            let Some(text) = prompt_text(flow, options.role) else {
+                continue;
+            };
+            let normalized = normalize_prompt(&normalizer.normalize(&text));
+            if normalized.is_empty() {
+                continue;
+            }
+            let hash = prompt_template_hash(&normalized);
+            let entry = clusters
+                .entry(hash.clone())
+                .or_insert_with(|| PromptTemplateStats {
+                    template_hash: hash,
+                    sample: normalized.chars().take(PROMPT_SAMPLE_CHARS).collect(),
+                    ..Default::default()
+                });
+
+            let usage = flow.response.as_ref().map(|r| &r.usage);
+            let input = usage.map(|u| u.input_tokens as u64).unwrap_or(0);
+            let output = usage.map(|u| u.output_tokens as u64).unwrap_or(0);
+            entry.count += 1;
+            entry.input_tokens += input;
+            entry.output_tokens += output;
+            entry.total_tokens += input + output;
+            if !entry.models.contains(&flow.request.model) {
+                entry.models.push(flow.request.model.clone());
+            }
+
+            let price = options
+                .prices
+                .get(&flow.request.model)
+                .or(options.default_price.as_ref());
+            match (price, usage) {
+                (Some(price), Some(usage)) => {
+                    entry.cost += flow_cost(flow, usage, price);
+                }
+                (None, _) => report.unpriced_flows += 1,
+                _ => {}
+            }
+            report.clustered_flows += 1;
+        }
+
+        report.total_cost = clusters.values().map(|c| c.cost).sum();
+        report.total_tokens = clusters.values().map(|c| c.total_tokens).sum();
+
+        let mut templates: Vec<PromptTemplateStats> = clusters.into_values().collect();
+        for template in &mut templates {
+            template.models.sort();
+            if report.total_cost > 0.0 {
+                template.cost_share = template.cost / report.total_cost;
+            }
+            if report.total_tokens > 0 {
+                template.token_share = template.total_tokens as f64 / report.total_tokens as f64;
+            }
+        }
+        // 成本相同时按 Token 和哈希排序，保证输出稳定
+        templates.sort_by(|a, b| {
+            b.cost
+                .partial_cmp(&a.cost)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.total_tokens.cmp(&a.total_tokens))
+                .then_with(|| a.template_hash.cmp(&b.template_hash))
+        });
+        templates.truncate(options.limit);
+        report.templates = templates;
+        report
+    }
+
     /// 计算请求速率（每秒）
     fn calculate_request_rate(&self, flows: &[LLMFlow], time_range: &StatsTimeRange) -> f64 {
         if flows.is_empty() {
@@ -542,6 +875,36 @@ impl EnhancedStatsService {
             md.push('\n');
         }
 
+        // 提示词缓存
+        let cache = &stats.cache_effectiveness;
+        if cache.flows_with_cache_usage > 0 {
+            md.push_str("## 提示词缓存\n\n");
+            md.push_str(&format!(
+                "- 命中请求: {} / {}\n",
+                cache.cache_hit_flows, cache.flows_with_cache_usage
+            ));
+            md.push_str(&format!("- 命中率: {:.2}%\n", cache.hit_ratio * 100.0));
+            md.push_str(&format!(
+                "- 缓存读取 / 写入 Token: {} / {}\n",
+                cache.cache_read_tokens, cache.cache_write_tokens
+            ));
+            md.push_str(&format!(
+                "- 节省（等价输入 Token）: {:.0}\n\n",
+                cache.saved_input_tokens
+            ));
+        }
+
+        // 停止原因分布
+        if !stats.stop_reason_distribution.buckets.is_empty() {
+            md.push_str("## 停止原因分布\n\n");
+            md.push_str("| 停止原因 | 数量 |\n");
+            md.push_str("|----------|------|\n");
+            for (reason, count) in &stats.stop_reason_distribution.buckets {
+                md.push_str(&format!("| {} | {} |\n", reason, count));
+            }
+            md.push('\n');
+        }
+
         md
     }
 
@@ -579,6 +942,14 @@ impl EnhancedStatsService {
         for (error_type, count) in &stats.error_distribution.buckets {
             csv.push_str(&format!("{},{}\n", error_type, count));
         }
+        csv.push('\n');
+
+        // 停止原因分布
+        csv.push_str("# Stop Reason Distribution\n");
+        csv.push_str("StopReason,Count\n");
+        for (reason, count) in &stats.stop_reason_distribution.buckets {
+            csv.push_str(&format!("{},{}\n", reason, count));
+        }
 
         csv
     }
@@ -614,6 +985,98 @@ fn parse_interval(interval: &str) -> Duration {
     Duration::hours(1)
 }
 
+/// 缓存读取、写入相对普通输入 Token 的价格倍数（按各 Provider 公开价目）
+fn cache_price_ratios(provider: &ProviderType) -> (f64, f64) {
+    match provider {
+        ProviderType::Claude | ProviderType::ClaudeOAuth | ProviderType::Kiro => (0.1, 1.25),
+        ProviderType::Gemini
+        | ProviderType::GeminiApiKey
+        | ProviderType::Vertex
+        | ProviderType::Antigravity => (0.25, 1.0),
+        _ => (0.5, 1.0),
+    }
+}
+
+/// 默认返回的提示词模板数量
+fn default_template_limit() -> usize {
+    20
+}
+
+/// 模板样本文本的最大字符数
+const PROMPT_SAMPLE_CHARS: usize = 200;
+
+/// 提取用于聚类的提示词文本
+fn prompt_text(flow: &LLMFlow, role: PromptClusterRole) -> Option<String> {
+    let request = &flow.request;
+    let system = || {
+        request.system_prompt.clone().or_else(|| {
+            request
+                .messages
+                .iter()
+                .find(|m| m.role == MessageRole::System)
+                .map(|m| m.content.get_all_text())
+        })
+    };
+    let first_user = || {
+        request
+            .messages
+            .iter()
+            .find(|m| m.role == MessageRole::User)
+            .map(|m| m.content.get_all_text())
+    };
+
+    match role {
+        PromptClusterRole::System => system(),
+        PromptClusterRole::FirstUser => first_user(),
+        PromptClusterRole::SystemAndFirstUser => match (system(), first_user()) {
+            (None, None) => None,
+            (system, user) => Some(format!(
+                "{}\n{}",
+                system.unwrap_or_default(),
+                user.unwrap_or_default()
+            )),
+        },
+    }
+}
+
+/// 规范化提示词：连续数字替换为 `#`，合并空白
+fn normalize_prompt(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut last = None;
+    for c in text.chars() {
+        let c = if c.is_ascii_digit() {
+            '#'
+        } else if c.is_whitespace() {
+            ' '
+        } else {
+            c
+        };
+        if matches!(c, '#' | ' ') && last == Some(c) {
+            continue;
+        }
+        normalized.push(c);
+        last = Some(c);
+    }
+    normalized.trim().to_string()
+}
+
+/// 计算模板哈希（不使用随机种子，跨运行稳定）
+fn prompt_template_hash(normalized: &str) -> String {
+    let digest = Sha256::digest(normalized.as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 按单价计算单个 Flow 的成本（缓存读写按 Provider 价格倍数折算）
+fn flow_cost(flow: &LLMFlow, usage: &TokenUsage, price: &ModelPrice) -> f64 {
+    let cache_read = usage.cache_read_tokens.unwrap_or(0) as f64;
+    let cache_write = usage.cache_write_tokens.unwrap_or(0) as f64;
+    let uncached = (usage.input_tokens as f64 - cache_read - cache_write).max(0.0);
+    let (read_price, write_price) = cache_price_ratios(&flow.metadata.provider);
+    let input = uncached + cache_read * read_price + cache_write * write_price;
+    (input * price.input_per_million + usage.output_tokens as f64 * price.output_per_million)
+        / 1_000_000.0
+}
+
 /// 默认延迟桶边界（毫秒）
 fn default_latency_buckets() -> Vec<u64> {
     vec![100, 500, 1000, 2000, 5000, 10000]
@@ -679,6 +1142,193 @@ mod tests {
         let diff = range.end - range.start;
         assert_eq!(diff.num_hours(), 24);
     }
+
+    #[test]
+    fn test_stop_reason_distribution() {
+        use crate::flow_monitor::models::{
+            FlowMetadata, FlowType, LLMRequest, LLMResponse, StopReason,
+        };
+
+        let flow_with = |stop_reason: Option<StopReason>| {
+            let mut flow = LLMFlow::new(
+                uuid::Uuid::new_v4().to_string(),
+                FlowType::ChatCompletions,
+                LLMRequest::default(),
+                FlowMetadata::default(),
+            );
+            flow.response = Some(LLMResponse {
+                stop_reason,
+                ..Default::default()
+            });
+            flow
+        };
+        let flows = vec![
+            flow_with(Some(StopReason::Stop)),
+            flow_with(Some(StopReason::EndTurn)),
+            flow_with(Some(StopReason::ToolCalls)),
+            flow_with(Some(StopReason::ContentFilter)),
+            flow_with(None),
+        ];
+
+        let service = EnhancedStatsService::new(Arc::new(RwLock::new(FlowMemoryStore::new(10))));
+        let dist = service.calculate_stop_reason_distribution(&flows);
+        assert_eq!(dist.total, 4);
+        assert_eq!(dist.buckets[0], ("stop".to_string(), 2));
+        assert!(dist.buckets.contains(&("content_filter".to_string(), 1)));
+    }
+
+    #[test]
+    fn test_cache_effectiveness() {
+        use crate::flow_monitor::models::{
+            FlowMetadata, FlowType, LLMRequest, LLMResponse, TokenUsage,
+        };
+
+        let flow_with = |provider: ProviderType, usage: TokenUsage| {
+            let mut flow = LLMFlow::new(
+                uuid::Uuid::new_v4().to_string(),
+                FlowType::ChatCompletions,
+                LLMRequest::default(),
+                FlowMetadata {
+                    provider,
+                    ..Default::default()
+                },
+            );
+            flow.response = Some(LLMResponse {
+                usage,
+                ..Default::default()
+            });
+            flow
+        };
+        let flows = vec![
+            flow_with(
+                ProviderType::Claude,
+                TokenUsage {
+                    input_tokens: 1000,
+                    cache_read_tokens: Some(800),
+                    cache_write_tokens: Some(100),
+                    ..Default::default()
+                },
+            ),
+            flow_with(
+                ProviderType::OpenAI,
+                TokenUsage {
+                    input_tokens: 1000,
+                    cache_read_tokens: Some(0),
+                    ..Default::default()
+                },
+            ),
+            flow_with(
+                ProviderType::OpenAI,
+                TokenUsage {
+                    input_tokens: 500,
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        let service = EnhancedStatsService::new(Arc::new(RwLock::new(FlowMemoryStore::new(10))));
+        let cache = service.calculate_cache_effectiveness(&flows);
+        assert_eq!(cache.flows_with_cache_usage, 2);
+        assert_eq!(cache.cache_hit_flows, 1);
+        assert_eq!(cache.input_tokens, 2000);
+        assert!((cache.hit_ratio - 0.4).abs() < 1e-9);
+        // 800 × 0.9 − 100 × 0.25
+        assert!((cache.saved_input_tokens - 695.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_normalize_prompt() {
+        assert_eq!(
+            normalize_prompt("  Order 12345\n\n  total:  99.5 items "),
+            "Order # total: #.# items"
+        );
+        assert_eq!(
+            prompt_template_hash(&normalize_prompt("User 1 asked")),
+            prompt_template_hash(&normalize_prompt("User   42 asked"))
+        );
+        // 固定哈希，保证跨运行稳定
+        assert_eq!(prompt_template_hash("hello"), "2cf24dba5fb0a30e");
+    }
+
+    #[test]
+    fn test_prompt_templates() {
+        use crate::flow_monitor::models::{
+            FlowMetadata, FlowType, LLMRequest, LLMResponse, Message, MessageContent,
+        };
+
+        let flow_with = |model: &str, system: &str, user: &str, input: u32, output: u32| {
+            let request = LLMRequest {
+                model: model.to_string(),
+                system_prompt: Some(system.to_string()),
+                messages: vec![Message {
+                    role: MessageRole::User,
+                    content: MessageContent::Text(user.to_string()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            };
+            let mut flow = LLMFlow::new(
+                uuid::Uuid::new_v4().to_string(),
+                FlowType::ChatCompletions,
+                request,
+                FlowMetadata::default(),
+            );
+            flow.response = Some(LLMResponse {
+                usage: TokenUsage {
+                    input_tokens: input,
+                    output_tokens: output,
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+            flow
+        };
+        let flows = vec![
+            flow_with("gpt-4o", "Summarize ticket 101", "hi", 1000, 100),
+            flow_with("gpt-4o", "Summarize ticket 202", "hello", 3000, 300),
+            flow_with("gpt-4o-mini", "Translate", "hi", 500, 50),
+            flow_with("unknown", "Translate", "hi", 500, 50),
+        ];
+
+        let service = EnhancedStatsService::new(Arc::new(RwLock::new(FlowMemoryStore::new(10))));
+        let normalizer = ContentNormalizer::default();
+        let mut options = PromptTemplateOptions::default();
+        options.prices.insert(
+            "gpt-4o".to_string(),
+            ModelPrice {
+                input_per_million: 2.0,
+                output_per_million: 10.0,
+            },
+        );
+        options.prices.insert(
+            "gpt-4o-mini".to_string(),
+            ModelPrice {
+                input_per_million: 1.0,
+                output_per_million: 2.0,
+            },
+        );
+
+        let report = service.calculate_prompt_templates(&flows, &options, &normalizer);
+        assert_eq!(report.clustered_flows, 4);
+        assert_eq!(report.unpriced_flows, 1);
+        assert_eq!(report.templates.len(), 2);
+
+        let top = &report.templates[0];
+        assert_eq!(top.sample, "Summarize ticket #");
+        assert_eq!(top.count, 2);
+        assert_eq!(top.total_tokens, 4400);
+        // (4000 × 2 + 400 × 10) / 1e6
+        assert!((top.cost - 0.012).abs() < 1e-12);
+        assert!(top.cost_share > 0.9);
+        assert_eq!(report.templates[1].models, vec!["gpt-4o-mini", "unknown"]);
+
+        // 按第一条用户消息聚类
+        options.role = PromptClusterRole::FirstUser;
+        let report = service.calculate_prompt_templates(&flows, &options, &normalizer);
+        assert_eq!(report.templates.len(), 2);
+        assert_eq!(report.templates[0].sample, "hello");
+        assert_eq!(report.templates[1].count, 3);
+    }
 }
 
 // ============================================================================
@@ -973,14 +1623,18 @@ mod property_tests {
             let success_by_provider = service.calculate_success_by_provider(&flows);
             let latency_hist = service.calculate_latency_histogram(&flows, &default_latency_buckets());
             let error_dist = service.calculate_error_distribution(&flows);
+            let stop_reason_dist = service.calculate_stop_reason_distribution(&flows);
             let request_rate = service.calculate_request_rate(&flows, &time_range);
 
             let stats = EnhancedStats {
                 request_trend: TrendData::default(),
                 token_by_model: token_dist,
                 success_by_provider,
+                incomplete_stream_by_provider: service.calculate_incomplete_stream_by_provider(&flows),
                 latency_histogram: latency_hist,
                 error_distribution: error_dist,
+                stop_reason_distribution: stop_reason_dist,
+                cache_effectiveness: service.calculate_cache_effectiveness(&flows),
                 request_rate,
                 time_range: time_range.clone(),
             };
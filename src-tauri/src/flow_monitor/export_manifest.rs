@@ -0,0 +1,215 @@
+//! 导出清单
+//!
+//! 把一组 Flow 导出给别人排查问题时，接收方需要知道导出用了哪些选项、应用了哪些脱敏规则、
+//! 包含多少个 Flow，并能确认内容在传递过程中没有被修改。清单随导出数据一起返回，记录这些
+//! 信息以及导出数据的 SHA-256；`verify` 对收到的数据重新计算哈希并与清单比对。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::exporter::{ExportFormat, ExportOptions, RedactionRule};
+
+/// 清单格式版本
+pub const EXPORT_MANIFEST_VERSION: u32 = 1;
+
+/// 内容哈希算法
+pub const EXPORT_HASH_ALGORITHM: &str = "sha256";
+
+/// 导出时使用的选项（不含过滤条件等与导出内容无关的字段）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestExportOptions {
+    /// 导出格式
+    pub format: ExportFormat,
+    /// 是否包含原始请求/响应体
+    pub include_raw: bool,
+    /// 是否包含流式 chunks
+    pub include_stream_chunks: bool,
+    /// 是否脱敏
+    pub redact_sensitive: bool,
+    /// 是否校验了脱敏结果
+    pub verify_redaction: bool,
+    /// 是否按稳定顺序输出
+    pub stable_key_order: bool,
+    /// 是否缩进输出
+    pub pretty: bool,
+    /// 是否还原了内联推理
+    #[serde(default)]
+    pub restore_inline_reasoning: bool,
+    /// 只导出的字段
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub field_mask: Vec<String>,
+    /// 不导出的字段
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_fields: Vec<String>,
+}
+
+impl From<&ExportOptions> for ManifestExportOptions {
+    fn from(options: &ExportOptions) -> Self {
+        Self {
+            format: options.format,
+            include_raw: options.include_raw,
+            include_stream_chunks: options.include_stream_chunks,
+            redact_sensitive: options.redact_sensitive,
+            verify_redaction: options.verify_redaction,
+            stable_key_order: options.stable_key_order,
+            pretty: options.pretty,
+            restore_inline_reasoning: options.restore_inline_reasoning,
+            field_mask: options.field_mask.clone(),
+            exclude_fields: options.exclude_fields.clone(),
+        }
+    }
+}
+
+/// 应用过的脱敏规则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestRedactionRule {
+    /// 规则名称
+    pub name: String,
+    /// 匹配模式（正则表达式）
+    pub pattern: String,
+    /// 替换文本
+    pub replacement: String,
+    /// 请求体 / 响应体中脱敏的位置（JSONPath）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_path: Option<String>,
+}
+
+impl From<&RedactionRule> for ManifestRedactionRule {
+    fn from(rule: &RedactionRule) -> Self {
+        Self {
+            name: rule.name.clone(),
+            pattern: rule.pattern.clone(),
+            replacement: rule.replacement.clone(),
+            json_path: rule.json_path.clone(),
+        }
+    }
+}
+
+/// 导出清单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportManifest {
+    /// 清单格式版本
+    pub manifest_version: u32,
+    /// 生成导出的 ProxyCast 版本
+    pub generator_version: String,
+    /// 导出时间
+    pub created_at: DateTime<Utc>,
+    /// 导出选项
+    pub options: ManifestExportOptions,
+    /// 应用过的脱敏规则（未脱敏时为空）
+    pub redaction_rules: Vec<ManifestRedactionRule>,
+    /// 导出的 Flow 数量
+    pub flow_count: usize,
+    /// 哈希算法
+    pub hash_algorithm: String,
+    /// 导出数据的哈希（十六进制）
+    pub content_hash: String,
+    /// 导出数据的字节数
+    pub content_bytes: usize,
+}
+
+/// 导出校验结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportVerification {
+    /// 数据是否与清单一致
+    pub valid: bool,
+    /// 清单中记录的哈希
+    pub expected_hash: String,
+    /// 重新计算的哈希
+    pub actual_hash: String,
+    /// 不一致的原因（一致时为空）
+    pub error: Option<String>,
+}
+
+impl ExportManifest {
+    /// 为导出数据生成清单
+    pub fn new(
+        options: &ExportOptions,
+        redaction_rules: &[RedactionRule],
+        flow_count: usize,
+        data: &str,
+    ) -> Self {
+        Self {
+            manifest_version: EXPORT_MANIFEST_VERSION,
+            generator_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now(),
+            options: options.into(),
+            redaction_rules: redaction_rules.iter().map(Into::into).collect(),
+            flow_count,
+            hash_algorithm: EXPORT_HASH_ALGORITHM.to_string(),
+            content_hash: content_hash(data),
+            content_bytes: data.len(),
+        }
+    }
+
+    /// 重新计算导出数据的哈希并与清单比对
+    pub fn verify(&self, data: &str) -> ExportVerification {
+        let actual_hash = content_hash(data);
+        let error = if self.hash_algorithm != EXPORT_HASH_ALGORITHM {
+            Some(format!("不支持的哈希算法: {}", self.hash_algorithm))
+        } else if self.content_bytes != data.len() {
+            Some(format!(
+                "数据长度不一致: 清单记录 {} 字节，实际 {} 字节",
+                self.content_bytes,
+                data.len()
+            ))
+        } else if !actual_hash.eq_ignore_ascii_case(&self.content_hash) {
+            Some("数据哈希与清单不一致".to_string())
+        } else {
+            None
+        };
+
+        ExportVerification {
+            valid: error.is_none(),
+            expected_hash: self.content_hash.clone(),
+            actual_hash,
+            error,
+        }
+    }
+}
+
+/// 计算导出数据的 SHA-256（十六进制）
+fn content_hash(data: &str) -> String {
+    Sha256::digest(data.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::exporter::default_redaction_rules;
+
+    #[test]
+    fn test_manifest_round_trip_and_tamper_detection() {
+        let options = ExportOptions {
+            redact_sensitive: true,
+            ..Default::default()
+        };
+        let rules = default_redaction_rules();
+        let data = r#"[{"id":"flow-1"}]"#;
+        let manifest = ExportManifest::new(&options, &rules, 1, data);
+
+        assert_eq!(manifest.flow_count, 1);
+        assert_eq!(manifest.redaction_rules.len(), rules.len());
+        assert!(manifest.options.redact_sensitive);
+        assert_eq!(manifest.content_hash.len(), 64);
+
+        // 清单经过序列化传递后仍可校验
+        let json = serde_json::to_string(&manifest).unwrap();
+        let received: ExportManifest = serde_json::from_str(&json).unwrap();
+        let verification = received.verify(data);
+        assert!(verification.valid);
+        assert_eq!(verification.error, None);
+
+        let tampered = received.verify(r#"[{"id":"flow-2"}]"#);
+        assert!(!tampered.valid);
+        assert_ne!(tampered.actual_hash, tampered.expected_hash);
+
+        let truncated = received.verify(r#"[{"id":"flow-1"}"#);
+        assert!(!truncated.valid);
+        assert!(truncated.error.unwrap().contains("长度"));
+    }
+}
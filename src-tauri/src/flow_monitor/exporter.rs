@@ -1,16 +1,31 @@
 //! LLM Flow 导出服务
 //!
-//! 提供多种格式的 Flow 导出功能，包括 HAR、JSON、JSONL、Markdown 和 CSV。
-//! 支持敏感数据脱敏和导出前过滤。
+//! 提供多种格式的 Flow 导出功能，包括 HAR、JSON、JSONL、Markdown、CSV、Parquet
+//! 以及 OpenAI / Anthropic 批处理 API 输入，并可将一次 Agent 会话的多个 Flow 合并为一份会话记录。
+//! 支持敏感数据脱敏和导出前过滤，并可在脱敏后校验输出中没有残留的密钥。
+//! 开启稳定键顺序后对象键按字典序输出，不同版本的导出结果可以直接用 `git diff` 对比。
 
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
+use super::batch_export::{export_batch_jsonl, BatchTarget};
+use super::conversation_export::{
+    conversation_to_markdown, merge_conversation, ConversationTranscript,
+};
+use super::export_manifest::ExportManifest;
+use super::field_mask::{FieldMask, FieldMaskError};
+use super::inline_reasoning::restore_inline_reasoning;
+use super::json_path::JsonPath;
 use super::models::{
     FlowAnnotations, FlowError, LLMFlow, LLMRequest, LLMResponse, Message, MessageContent,
     ThinkingContent,
 };
+use super::parquet_export::write_parquet;
+use super::redaction_verify::{verify_value, RedactionVerificationError};
 use super::FlowFilter;
+use crate::config::REDACTED_PLACEHOLDER;
 #[cfg(test)]
 use crate::ProviderType;
 
@@ -32,6 +47,14 @@ pub enum ExportFormat {
     Markdown,
     /// CSV 格式（仅元数据）
     CSV,
+    /// OpenAI Batch API 输入（JSONL）
+    #[serde(rename = "openai_batch")]
+    OpenAiBatch,
+    /// Anthropic Message Batches API 输入（JSONL）
+    #[serde(rename = "anthropic_batch")]
+    AnthropicBatch,
+    /// Parquet 格式（仅元数据和请求消息，供数据仓库导入）
+    Parquet,
 }
 
 impl Default for ExportFormat {
@@ -64,9 +87,29 @@ pub struct ExportOptions {
     /// 脱敏规则
     #[serde(default)]
     pub redaction_rules: Vec<RedactionRule>,
+    /// 脱敏后是否校验输出（残留已知密钥格式或高熵字符串时导出失败）
+    ///
+    /// 启用时总是先应用脱敏。
+    #[serde(default)]
+    pub verify_redaction: bool,
     /// 是否压缩输出
     #[serde(default)]
     pub compress: bool,
+    /// 是否按稳定顺序输出（对象键按字典序，HAR 头部按名称排序）
+    #[serde(default)]
+    pub stable_key_order: bool,
+    /// JSON / HAR 是否缩进输出（否则为紧凑格式）
+    #[serde(default = "default_true")]
+    pub pretty: bool,
+    /// 是否把提取出的内联推理放回响应正文（导出上游返回的原始内容）
+    #[serde(default)]
+    pub restore_inline_reasoning: bool,
+    /// JSON / JSONL 只导出的字段（点分路径，如 `request.model`；为空时导出全部字段）
+    #[serde(default)]
+    pub field_mask: Vec<String>,
+    /// JSON / JSONL 不导出的字段（点分路径，在 `field_mask` 之后应用）
+    #[serde(default)]
+    pub exclude_fields: Vec<String>,
 }
 
 fn default_true() -> bool {
@@ -82,7 +125,13 @@ impl Default for ExportOptions {
             include_stream_chunks: false,
             redact_sensitive: false,
             redaction_rules: Vec::new(),
+            verify_redaction: false,
             compress: false,
+            stable_key_order: false,
+            pretty: true,
+            restore_inline_reasoning: false,
+            field_mask: Vec::new(),
+            exclude_fields: Vec::new(),
         }
     }
 }
@@ -92,17 +141,24 @@ impl Default for ExportOptions {
 // ============================================================================
 
 /// 脱敏规则
+///
+/// 设置 `json_path` 时按位置脱敏：请求体和响应体中匹配到的标量整体替换为
+/// `replacement`，此时不使用 `pattern`。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RedactionRule {
     /// 规则名称
     pub name: String,
     /// 匹配模式（正则表达式）
+    #[serde(default)]
     pub pattern: String,
     /// 替换文本
     pub replacement: String,
     /// 是否启用
     #[serde(default = "default_true")]
     pub enabled: bool,
+    /// 请求体 / 响应体中要脱敏的位置（JSONPath，如 `$.messages[*].content`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_path: Option<String>,
 }
 
 impl RedactionRule {
@@ -117,6 +173,18 @@ impl RedactionRule {
             pattern: pattern.into(),
             replacement: replacement.into(),
             enabled: true,
+            json_path: None,
+        }
+    }
+
+    /// 创建按 JSONPath 脱敏的规则（匹配到的标量替换为 `REDACTED_PLACEHOLDER`）
+    pub fn for_json_path(name: impl Into<String>, json_path: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            pattern: String::new(),
+            replacement: REDACTED_PLACEHOLDER.to_string(),
+            enabled: true,
+            json_path: Some(json_path.into()),
         }
     }
 }
@@ -182,14 +250,19 @@ pub fn default_redaction_rules() -> Vec<RedactionRule> {
 /// 敏感数据脱敏器
 pub struct Redactor {
     rules: Vec<(String, Regex, String)>,
+    /// 按 JSONPath 脱敏的规则（只作用于请求体和响应体）
+    json_paths: Vec<(JsonPath, String)>,
 }
 
 impl Redactor {
     /// 创建新的脱敏器
+    ///
+    /// 无法编译的正则和无法解析的 JSONPath 会被忽略。
     pub fn new(rules: &[RedactionRule]) -> Self {
-        let compiled_rules: Vec<_> = rules
-            .iter()
-            .filter(|r| r.enabled)
+        let enabled = || rules.iter().filter(|r| r.enabled);
+
+        let compiled_rules: Vec<_> = enabled()
+            .filter(|r| r.json_path.is_none())
             .filter_map(|r| {
                 Regex::new(&r.pattern)
                     .ok()
@@ -197,8 +270,16 @@ impl Redactor {
             })
             .collect();
 
+        let json_paths = enabled()
+            .filter_map(|r| {
+                let path = JsonPath::parse(r.json_path.as_deref()?).ok()?;
+                Some((path, r.replacement.clone()))
+            })
+            .collect();
+
         Self {
             rules: compiled_rules,
+            json_paths,
         }
     }
 
@@ -234,6 +315,15 @@ impl Redactor {
         }
     }
 
+    /// 对请求体 / 响应体应用脱敏（先按 JSONPath 替换，再按正则脱敏）
+    fn redact_body(&self, body: &serde_json::Value) -> serde_json::Value {
+        let mut body = body.clone();
+        for (path, replacement) in &self.json_paths {
+            path.mask(&mut body, replacement);
+        }
+        self.redact_json(&body)
+    }
+
     /// 对 Flow 应用脱敏
     pub fn redact_flow(&self, flow: &LLMFlow) -> LLMFlow {
         let mut redacted = flow.clone();
@@ -278,7 +368,7 @@ impl Redactor {
             .collect();
 
         // 脱敏请求体
-        redacted.body = self.redact_json(&request.body);
+        redacted.body = self.redact_body(&request.body);
 
         // 脱敏消息
         redacted.messages = request
@@ -290,6 +380,11 @@ impl Redactor {
         // 脱敏系统提示词
         redacted.system_prompt = request.system_prompt.as_ref().map(|s| self.redact(s));
 
+        // 脱敏 multipart 文本字段
+        for attachment in &mut redacted.attachments {
+            attachment.value = attachment.value.as_ref().map(|v| self.redact(v));
+        }
+
         redacted
     }
 
@@ -327,7 +422,7 @@ impl Redactor {
             .collect();
 
         // 脱敏响应体
-        redacted.body = self.redact_json(&response.body);
+        redacted.body = self.redact_body(&response.body);
 
         // 脱敏内容
         redacted.content = self.redact(&response.content);
@@ -338,6 +433,8 @@ impl Redactor {
                 text: self.redact(&thinking.text),
                 tokens: thinking.tokens,
                 signature: thinking.signature.clone(),
+                // 脱敏会改变正文长度，片段位置随之失效，不再保留
+                inline_segments: Vec::new(),
             });
         }
 
@@ -406,6 +503,24 @@ pub struct HarEntry {
     /// LLM 特定扩展
     #[serde(rename = "_llm", skip_serializing_if = "Option::is_none")]
     pub llm_extension: Option<HarLlmExtension>,
+    /// 流式响应各 chunk 的到达时间（只在保存了原始 chunks 时才有）
+    #[serde(
+        rename = "_streamTimings",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stream_timings: Option<Vec<HarStreamTiming>>,
+}
+
+/// 流式 chunk 的到达时间
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HarStreamTiming {
+    /// Chunk 索引
+    pub chunk_index: u32,
+    /// 相对请求开始（`startedDateTime`）的毫秒数
+    pub relative_ms: f64,
+    /// Chunk 数据的字节数
+    pub content_len: usize,
 }
 
 /// HAR 请求
@@ -615,23 +730,44 @@ pub struct HarLlmTokens {
 pub struct FlowExporter {
     options: ExportOptions,
     redactor: Option<Redactor>,
+    /// 实际应用的脱敏规则（记录到导出清单）
+    applied_rules: Vec<RedactionRule>,
+    /// JSON / JSONL 的字段选择
+    field_mask: FieldMask,
 }
 
 impl FlowExporter {
     /// 创建新的导出器
     pub fn new(options: ExportOptions) -> Self {
-        let redactor = if options.redact_sensitive {
+        let (redactor, applied_rules) = if options.redact_sensitive || options.verify_redaction {
             let rules = if options.redaction_rules.is_empty() {
                 default_redaction_rules()
             } else {
                 options.redaction_rules.clone()
             };
-            Some(Redactor::new(&rules))
+            let applied = rules.iter().filter(|r| r.enabled).cloned().collect();
+            (Some(Redactor::new(&rules)), applied)
         } else {
-            None
+            (None, Vec::new())
         };
 
-        Self { options, redactor }
+        let field_mask = FieldMask::new(&options.field_mask, &options.exclude_fields);
+        Self {
+            options,
+            redactor,
+            applied_rules,
+            field_mask,
+        }
+    }
+
+    /// 校验字段选择中的路径（导出时不校验，未知路径不匹配任何字段）
+    pub fn validate_field_mask(&self) -> Result<(), FieldMaskError> {
+        self.field_mask.validate()
+    }
+
+    /// 为导出数据生成清单（记录选项、脱敏规则、Flow 数量和内容哈希）
+    pub fn manifest(&self, flow_count: usize, data: &str) -> ExportManifest {
+        ExportManifest::new(&self.options, &self.applied_rules, flow_count, data)
     }
 
     /// 使用默认选项创建导出器
@@ -641,6 +777,19 @@ impl FlowExporter {
 
     /// 预处理 Flow（应用脱敏等）
     fn preprocess_flow(&self, flow: &LLMFlow) -> LLMFlow {
+        // 先还原内联推理，脱敏后片段位置不再可靠
+        let restored;
+        let flow = if self.options.restore_inline_reasoning {
+            let mut copy = flow.clone();
+            if let Some(response) = copy.response.as_mut() {
+                restore_inline_reasoning(response);
+            }
+            restored = copy;
+            &restored
+        } else {
+            flow
+        };
+
         if let Some(ref redactor) = self.redactor {
             redactor.redact_flow(flow)
         } else {
@@ -690,22 +839,34 @@ impl FlowExporter {
         let url = format!("{}{}", base_url, request.path);
 
         // 构建请求头
-        let headers: Vec<HarHeader> = request
-            .headers
-            .iter()
-            .map(|(k, v)| HarHeader {
-                name: k.clone(),
-                value: v.clone(),
-                comment: None,
-            })
-            .collect();
+        let headers = self.har_headers(&request.headers);
 
         // 构建 POST 数据
-        let post_data = if self.options.include_raw {
+        let post_data = if !request.attachments.is_empty() {
+            // multipart 请求：以 params 形式列出各个 part（不包含文件内容）
+            Some(HarPostData {
+                mime_type: "multipart/form-data".to_string(),
+                params: Some(
+                    request
+                        .attachments
+                        .iter()
+                        .map(|a| HarParam {
+                            name: a.field_name.clone(),
+                            value: a.value.clone(),
+                            file_name: a.file_name.clone(),
+                            content_type: a.content_type.clone(),
+                            comment: Some(format!("{} bytes", a.size_bytes)),
+                        })
+                        .collect(),
+                ),
+                text: String::new(),
+                comment: None,
+            })
+        } else if self.options.include_raw {
             Some(HarPostData {
                 mime_type: "application/json".to_string(),
                 params: None,
-                text: serde_json::to_string(&request.body).unwrap_or_default(),
+                text: self.body_text(&request.body),
                 comment: None,
             })
         } else {
@@ -714,18 +875,10 @@ impl FlowExporter {
 
         // 构建响应
         let (har_response, _response_body_size) = if let Some(resp) = response {
-            let resp_headers: Vec<HarHeader> = resp
-                .headers
-                .iter()
-                .map(|(k, v)| HarHeader {
-                    name: k.clone(),
-                    value: v.clone(),
-                    comment: None,
-                })
-                .collect();
+            let resp_headers = self.har_headers(&resp.headers);
 
             let content_text = if self.options.include_raw {
-                Some(serde_json::to_string(&resp.body).unwrap_or_default())
+                Some(self.body_text(&resp.body))
             } else {
                 None
             };
@@ -807,6 +960,24 @@ impl FlowExporter {
             },
         });
 
+        // 流式 chunk 到达时间（未保存 chunks 时省略）
+        let stream_timings = response
+            .and_then(|r| r.stream_info.as_ref())
+            .and_then(|info| info.raw_chunks.as_ref())
+            .filter(|chunks| !chunks.is_empty())
+            .map(|chunks| {
+                chunks
+                    .iter()
+                    .map(|chunk| HarStreamTiming {
+                        chunk_index: chunk.index,
+                        relative_ms: (chunk.timestamp - flow.timestamps.request_start)
+                            .num_microseconds()
+                            .map_or(0.0, |us| us as f64 / 1000.0),
+                        content_len: chunk.data.len(),
+                    })
+                    .collect()
+            });
+
         // 计算时间
         let ttfb = flow.timestamps.ttfb_ms.unwrap_or(0) as f64;
         let total_time = flow.timestamps.duration_ms as f64;
@@ -846,13 +1017,24 @@ impl FlowExporter {
             connection: None,
             comment: flow.annotations.comment.clone(),
             llm_extension,
+            stream_timings,
         }
     }
 
     /// 导出为 JSON 格式
     pub fn export_json(&self, flows: &[LLMFlow]) -> serde_json::Value {
         let processed = self.preprocess_flows(flows);
-        serde_json::to_value(&processed).unwrap_or(serde_json::Value::Array(Vec::new()))
+        if self.field_mask.is_empty() {
+            return serde_json::to_value(&processed)
+                .unwrap_or(serde_json::Value::Array(Vec::new()));
+        }
+        serde_json::Value::Array(
+            processed
+                .iter()
+                .filter_map(|f| serde_json::to_value(f).ok())
+                .map(|v| self.field_mask.apply(v))
+                .collect(),
+        )
     }
 
     /// 导出为 JSONL 格式
@@ -860,11 +1042,92 @@ impl FlowExporter {
         let processed = self.preprocess_flows(flows);
         processed
             .iter()
-            .filter_map(|f| serde_json::to_string(f).ok())
+            .filter_map(|f| {
+                if self.options.stable_key_order || !self.field_mask.is_empty() {
+                    serde_json::to_value(f)
+                        .map(|v| self.field_mask.apply(v))
+                        .and_then(|v| {
+                            if self.options.stable_key_order {
+                                serde_json::to_string(&canonicalize_json(v))
+                            } else {
+                                serde_json::to_string(&v)
+                            }
+                        })
+                        .ok()
+                } else {
+                    serde_json::to_string(f).ok()
+                }
+            })
             .collect::<Vec<_>>()
             .join("\n")
     }
 
+    /// 按导出选项序列化 JSON / HAR
+    ///
+    /// 开启 `stable_key_order` 时先规范化键顺序，`pretty` 决定缩进或紧凑输出。
+    pub fn to_json_string<T: Serialize>(&self, value: &T) -> serde_json::Result<String> {
+        if self.options.stable_key_order {
+            let value = canonicalize_json(serde_json::to_value(value)?);
+            self.format_json(&value)
+        } else {
+            self.format_json(value)
+        }
+    }
+
+    fn format_json<T: Serialize + ?Sized>(&self, value: &T) -> serde_json::Result<String> {
+        if self.options.pretty {
+            serde_json::to_string_pretty(value)
+        } else {
+            serde_json::to_string(value)
+        }
+    }
+
+    /// 构建 HAR 头部列表（稳定顺序时按名称排序）
+    fn har_headers(&self, headers: &HashMap<String, String>) -> Vec<HarHeader> {
+        let mut har_headers: Vec<HarHeader> = headers
+            .iter()
+            .map(|(k, v)| HarHeader {
+                name: k.clone(),
+                value: v.clone(),
+                comment: None,
+            })
+            .collect();
+        if self.options.stable_key_order {
+            har_headers.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.value.cmp(&b.value)));
+        }
+        har_headers
+    }
+
+    /// 序列化 HAR 中内嵌的请求/响应体
+    fn body_text(&self, body: &serde_json::Value) -> String {
+        if self.options.stable_key_order {
+            serde_json::to_string(&canonicalize_json(body.clone())).unwrap_or_default()
+        } else {
+            serde_json::to_string(body).unwrap_or_default()
+        }
+    }
+
+    /// 导出为批处理 API 输入（JSONL）
+    ///
+    /// 不支持的 Flow 类型会被跳过，详见 [`batch_export`](super::batch_export)。
+    pub fn export_batch(&self, flows: &[LLMFlow], target: BatchTarget) -> String {
+        let processed = self.preprocess_flows(flows);
+        export_batch_jsonl(&processed, target)
+    }
+
+    /// 将按顺序排列的多个 Flow 合并为一份会话记录
+    ///
+    /// 去除各轮重复发送的历史消息，只保留每轮新增的部分；
+    /// 未构成线性链时在 `warnings` 中说明，详见 [`conversation_export`](super::conversation_export)。
+    pub fn export_conversation(&self, flows: &[LLMFlow]) -> ConversationTranscript {
+        merge_conversation(&self.preprocess_flows(flows))
+    }
+
+    /// 将多个 Flow 合并为 Markdown 格式的会话记录
+    pub fn export_conversation_markdown(&self, flows: &[LLMFlow]) -> String {
+        conversation_to_markdown(&self.export_conversation(flows))
+    }
+
     /// 导出单个 Flow 为 Markdown 格式
     pub fn export_markdown(&self, flow: &LLMFlow) -> String {
         let processed = self.preprocess_flow(flow);
@@ -953,6 +1216,28 @@ impl FlowExporter {
             md.push_str("\n```\n\n");
         }
 
+        // 附件
+        if !flow.request.attachments.is_empty() {
+            md.push_str("### 附件\n\n");
+            md.push_str("| 字段 | 文件名 | 类型 | 大小 |\n");
+            md.push_str("|------|--------|------|------|\n");
+            for attachment in &flow.request.attachments {
+                md.push_str(&format!(
+                    "| {} | {} | {} | {} bytes{} |\n",
+                    attachment.field_name,
+                    attachment.file_name.as_deref().unwrap_or("-"),
+                    attachment.content_type.as_deref().unwrap_or("-"),
+                    attachment.size_bytes,
+                    if attachment.truncated {
+                        "（已截断）"
+                    } else {
+                        ""
+                    }
+                ));
+            }
+            md.push('\n');
+        }
+
         // 消息
         if !flow.request.messages.is_empty() {
             md.push_str("### 消息\n\n");
@@ -979,6 +1264,19 @@ impl FlowExporter {
                 response.status_code, response.status_text
             ));
 
+            // 响应头（按名称排序，便于查看限流信息）
+            if !response.headers.is_empty() {
+                let mut headers: Vec<_> = response.headers.iter().collect();
+                headers.sort();
+                md.push_str("### 响应头\n\n");
+                md.push_str("| 名称 | 值 |\n");
+                md.push_str("|------|----|\n");
+                for (name, value) in headers {
+                    md.push_str(&format!("| `{}` | {} |\n", name, value));
+                }
+                md.push('\n');
+            }
+
             // 思维链
             if let Some(ref thinking) = response.thinking {
                 md.push_str("### 思维链\n\n");
@@ -1108,9 +1406,14 @@ impl FlowExporter {
         csv
     }
 
+    /// 导出为 Parquet 格式
+    pub fn export_parquet(&self, flows: &[LLMFlow]) -> Result<Vec<u8>, ExportError> {
+        Ok(write_parquet(&self.preprocess_flows(flows))?)
+    }
+
     /// 根据选项导出
-    pub fn export(&self, flows: &[LLMFlow]) -> ExportResult {
-        match self.options.format {
+    pub fn export(&self, flows: &[LLMFlow]) -> Result<ExportResult, ExportError> {
+        let result = match self.options.format {
             ExportFormat::HAR => {
                 let har = self.export_har(flows);
                 ExportResult::Har(har)
@@ -1131,10 +1434,78 @@ impl FlowExporter {
                 let csv = self.export_csv(flows);
                 ExportResult::Text(csv)
             }
+            ExportFormat::OpenAiBatch => {
+                ExportResult::Text(self.export_batch(flows, BatchTarget::OpenAi))
+            }
+            ExportFormat::AnthropicBatch => {
+                ExportResult::Text(self.export_batch(flows, BatchTarget::Anthropic))
+            }
+            ExportFormat::Parquet => ExportResult::Binary(self.export_parquet(flows)?),
+        };
+        Ok(result)
+    }
+
+    /// 导出并校验脱敏结果
+    ///
+    /// 未启用 `verify_redaction` 时等同于 [`FlowExporter::export`]。
+    pub fn export_verified(&self, flows: &[LLMFlow]) -> Result<ExportResult, ExportError> {
+        let result = self.export(flows)?;
+        if self.options.verify_redaction {
+            verify_value(&self.verification_target(flows, &result))?;
+        }
+        Ok(result)
+    }
+
+    /// 校验脱敏结果（不返回导出内容）
+    pub fn verify(&self, flows: &[LLMFlow]) -> Result<(), ExportError> {
+        self.export_verified(flows).map(|_| ())
+    }
+
+    /// 获取校验用的 JSON
+    ///
+    /// JSON / HAR / JSONL / 批处理格式直接扫描输出（JSONL 每行对应数组的一项），
+    /// Markdown / CSV / Parquet 不是 JSON，扫描生成它们的脱敏后 Flow 列表。
+    fn verification_target(&self, flows: &[LLMFlow], result: &ExportResult) -> serde_json::Value {
+        match (self.options.format, result) {
+            (_, ExportResult::Har(har)) => serde_json::to_value(har).unwrap_or_default(),
+            (_, ExportResult::Json(json)) => json.clone(),
+            (ExportFormat::Markdown | ExportFormat::CSV, _) | (_, ExportResult::Binary(_)) => {
+                serde_json::to_value(self.preprocess_flows(flows)).unwrap_or_default()
+            }
+            (_, ExportResult::Text(text)) => serde_json::Value::Array(
+                text.lines()
+                    .map(|line| {
+                        serde_json::from_str(line)
+                            .unwrap_or_else(|_| serde_json::Value::String(line.to_string()))
+                    })
+                    .collect(),
+            ),
         }
     }
 }
 
+/// 规范化 JSON 值：递归地按字典序重排对象键
+///
+/// 数组保持原有顺序（顺序有语义）；不依赖 `serde_json` 是否启用 `preserve_order`。
+pub fn canonicalize_json(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            serde_json::Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, canonicalize_json(v)))
+                    .collect(),
+            )
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(canonicalize_json).collect())
+        }
+        other => other,
+    }
+}
+
 /// CSV 字段转义
 fn escape_csv(s: &str) -> String {
     if s.contains(',') || s.contains('"') || s.contains('\n') {
@@ -1144,6 +1515,17 @@ fn escape_csv(s: &str) -> String {
     }
 }
 
+/// 导出失败
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    /// 写入 Parquet 文件失败
+    #[error("写入 Parquet 失败: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+    /// 脱敏校验失败
+    #[error(transparent)]
+    Redaction(#[from] RedactionVerificationError),
+}
+
 /// 导出结果
 #[derive(Debug, Clone)]
 pub enum ExportResult {
@@ -1151,8 +1533,10 @@ pub enum ExportResult {
     Har(HarArchive),
     /// JSON 格式
     Json(serde_json::Value),
-    /// 文本格式（JSONL、Markdown、CSV）
+    /// 文本格式（JSONL、Markdown、CSV、批处理 JSONL）
     Text(String),
+    /// 二进制格式（Parquet），转换为字符串时使用 base64 编码
+    Binary(Vec<u8>),
 }
 
 impl ExportResult {
@@ -1162,6 +1546,7 @@ impl ExportResult {
             ExportResult::Har(har) => serde_json::to_string_pretty(har).unwrap_or_default(),
             ExportResult::Json(json) => serde_json::to_string_pretty(json).unwrap_or_default(),
             ExportResult::Text(text) => text.clone(),
+            ExportResult::Binary(data) => BASE64_STANDARD.encode(data),
         }
     }
 
@@ -1171,6 +1556,7 @@ impl ExportResult {
             ExportResult::Har(har) => serde_json::to_string(har).unwrap_or_default(),
             ExportResult::Json(json) => serde_json::to_string(json).unwrap_or_default(),
             ExportResult::Text(text) => text.clone(),
+            ExportResult::Binary(data) => BASE64_STANDARD.encode(data),
         }
     }
 }
@@ -1221,6 +1607,7 @@ mod tests {
             },
             size_bytes: 256,
             timestamp: Utc::now(),
+            attachments: Vec::new(),
         };
 
         let response = LLMResponse {
@@ -1238,10 +1625,16 @@ mod tests {
                 ..Default::default()
             },
             stop_reason: Some(StopReason::Stop),
+            raw_stop_reason: None,
             size_bytes: 128,
             timestamp_start: Utc::now(),
             timestamp_end: Utc::now(),
             stream_info: None,
+            schema_valid: None,
+            logprobs: None,
+            body_info: None,
+            system_fingerprint: None,
+            turns: Vec::new(),
         };
 
         let metadata = FlowMetadata {
@@ -1340,6 +1733,47 @@ mod tests {
         assert!(!redacted_str.contains("13812345678"));
     }
 
+    #[test]
+    fn test_redactor_json_path_masks_body_content() {
+        let mut flow = create_test_flow();
+        flow.request.body = serde_json::json!({
+            "model": "gpt-4",
+            "messages": [
+                {"role": "system", "content": "key: abc123"},
+                {"role": "user", "content": [{"type": "text", "text": "my token is xyz"}]}
+            ]
+        });
+        flow.response.as_mut().unwrap().body = serde_json::json!({
+            "choices": [{"message": {"role": "assistant", "content": "secret reply"}}]
+        });
+
+        let exporter = FlowExporter::new(ExportOptions {
+            redact_sensitive: true,
+            redaction_rules: vec![
+                RedactionRule::for_json_path("request_content", "$.messages[*].content"),
+                RedactionRule::for_json_path("response_content", "$..message.content"),
+            ],
+            ..Default::default()
+        });
+        let exported = exporter.export_json(&[flow]);
+        let request_body = &exported[0]["request"]["body"];
+        let response_body = &exported[0]["response"]["body"];
+
+        // 内容被替换，角色和结构保持不变
+        assert_eq!(
+            request_body["messages"],
+            serde_json::json!([
+                {"role": "system", "content": REDACTED_PLACEHOLDER},
+                {"role": "user", "content": [{"type": REDACTED_PLACEHOLDER, "text": REDACTED_PLACEHOLDER}]}
+            ])
+        );
+        assert_eq!(request_body["model"], "gpt-4");
+        assert_eq!(
+            response_body["choices"][0]["message"],
+            serde_json::json!({"role": "assistant", "content": REDACTED_PLACEHOLDER})
+        );
+    }
+
     #[test]
     fn test_export_json() {
         let flow = create_test_flow();
@@ -1351,6 +1785,62 @@ mod tests {
         assert_eq!(arr.len(), 1);
     }
 
+    #[test]
+    fn test_export_json_includes_logprobs() {
+        let mut flow = create_test_flow();
+        flow.response.as_mut().unwrap().logprobs = Some(ResponseLogprobs {
+            tokens: vec![TokenLogprob {
+                token: "Hi".to_string(),
+                logprob: -0.25,
+                top_logprobs: Vec::new(),
+            }],
+            total_tokens: 4,
+            truncated: true,
+        });
+        let json = FlowExporter::with_defaults().export_json(&[flow]);
+
+        let logprobs = &json[0]["response"]["logprobs"];
+        assert_eq!(logprobs["tokens"][0]["token"], "Hi");
+        assert_eq!(logprobs["tokens"][0]["logprob"], -0.25);
+        assert_eq!(logprobs["total_tokens"], 4);
+        assert_eq!(logprobs["truncated"], true);
+    }
+
+    #[test]
+    fn test_export_json_field_mask() {
+        let flow = create_test_flow();
+        let exporter = FlowExporter::new(ExportOptions {
+            field_mask: vec![
+                "id".to_string(),
+                "request.model".to_string(),
+                "response.usage".to_string(),
+            ],
+            ..Default::default()
+        });
+        assert!(exporter.validate_field_mask().is_ok());
+
+        let json = exporter.export_json(std::slice::from_ref(&flow));
+        let usage = serde_json::to_value(&flow.response.as_ref().unwrap().usage).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([{
+                "id": flow.id,
+                "request": {"model": flow.request.model},
+                "response": {"usage": usage}
+            }])
+        );
+
+        let line: serde_json::Value =
+            serde_json::from_str(&exporter.export_jsonl(&[flow])).unwrap();
+        assert_eq!(line, json[0]);
+
+        let invalid = FlowExporter::new(ExportOptions {
+            field_mask: vec!["request.modle".to_string()],
+            ..Default::default()
+        });
+        assert!(invalid.validate_field_mask().is_err());
+    }
+
     #[test]
     fn test_export_jsonl() {
         let flow = create_test_flow();
@@ -1395,6 +1885,51 @@ mod tests {
         assert!(md.contains("gpt-4"));
         assert!(md.contains("## 请求"));
         assert!(md.contains("## 响应"));
+        assert!(!md.contains("### 响应头"));
+    }
+
+    #[test]
+    fn test_export_markdown_response_headers() {
+        let mut flow = create_test_flow();
+        let headers = &mut flow.response.as_mut().unwrap().headers;
+        headers.insert("retry-after".to_string(), "5".to_string());
+        headers.insert(
+            "x-ratelimit-remaining-tokens".to_string(),
+            "1200".to_string(),
+        );
+
+        let md = FlowExporter::with_defaults().export_markdown(&flow);
+        assert!(md.contains("### 响应头"));
+        let retry = md.find("| `retry-after` | 5 |").unwrap();
+        let remaining = md
+            .find("| `x-ratelimit-remaining-tokens` | 1200 |")
+            .unwrap();
+        assert!(retry < remaining);
+    }
+
+    #[test]
+    fn test_export_multipart_attachments() {
+        use crate::flow_monitor::models::RequestAttachment;
+
+        let mut flow = create_test_flow();
+        flow.request.attachments = vec![RequestAttachment {
+            field_name: "file".to_string(),
+            file_name: Some("audio.mp3".to_string()),
+            content_type: Some("audio/mpeg".to_string()),
+            size_bytes: 2048,
+            ..Default::default()
+        }];
+
+        let exporter = FlowExporter::with_defaults();
+        let md = exporter.export_markdown(&flow);
+        assert!(md.contains("### 附件"));
+        assert!(md.contains("| file | audio.mp3 | audio/mpeg | 2048 bytes |"));
+
+        let har = exporter.export_har(std::slice::from_ref(&flow));
+        let post_data = har.log.entries[0].request.post_data.as_ref().unwrap();
+        assert_eq!(post_data.mime_type, "multipart/form-data");
+        let params = post_data.params.as_ref().unwrap();
+        assert_eq!(params[0].file_name.as_deref(), Some("audio.mp3"));
     }
 
     #[test]
@@ -1429,11 +1964,51 @@ mod tests {
         assert!(!json_str.contains("test@example.com"));
     }
 
+    #[test]
+    fn test_verify_redaction_catches_key_in_tool_result() {
+        let mut flow = create_test_flow();
+        // 工具结果不在脱敏规则覆盖范围内，且密钥不符合任何脱敏规则
+        flow.request.messages.push(Message {
+            role: MessageRole::Tool,
+            content: MessageContent::Text(String::new()),
+            tool_calls: None,
+            tool_result: Some(ToolResult {
+                tool_call_id: "call_1".to_string(),
+                content: "config loaded, token=Zx9Qw2Lm8Rt4Vb7Nc1Kp5Jh3Gf6Ds0A".to_string(),
+                is_error: false,
+            }),
+            name: None,
+        });
+
+        let exporter = FlowExporter::new(ExportOptions {
+            verify_redaction: true,
+            ..Default::default()
+        });
+        let ExportError::Redaction(err) = exporter.export_verified(&[flow.clone()]).unwrap_err()
+        else {
+            panic!("expected redaction error");
+        };
+        assert_eq!(
+            err.paths(),
+            vec!["$[0].request.messages[1].tool_result.content".to_string()]
+        );
+        assert_eq!(err.findings[0].kind, "high_entropy");
+        assert!(!err.to_string().contains("Zx9Qw2Lm8Rt4Vb7Nc1Kp5Jh3Gf6Ds0A"));
+
+        // 没有残留时正常导出
+        let exporter = FlowExporter::new(ExportOptions {
+            format: ExportFormat::HAR,
+            verify_redaction: true,
+            ..Default::default()
+        });
+        assert!(exporter.export_verified(&[create_test_flow()]).is_ok());
+    }
+
     #[test]
     fn test_export_result_to_string() {
         let flow = create_test_flow();
         let exporter = FlowExporter::with_defaults();
-        let result = exporter.export(&[flow]);
+        let result = exporter.export(&[flow]).unwrap();
 
         let pretty = result.to_string_pretty();
         let compact = result.to_string_compact();
@@ -1444,6 +2019,60 @@ mod tests {
         assert!(pretty.len() >= compact.len());
     }
 
+    #[test]
+    fn test_stable_key_order_is_byte_identical() {
+        let mut flow_a = create_test_flow();
+        flow_a
+            .request
+            .headers
+            .insert("Accept".to_string(), "*/*".to_string());
+        flow_a
+            .request
+            .headers
+            .insert("X-Extra".to_string(), "1".to_string());
+
+        // 语义相同但键的插入顺序不同
+        let mut flow_b = flow_a.clone();
+        let mut headers: Vec<_> = flow_a.request.headers.clone().into_iter().collect();
+        headers.reverse();
+        flow_b.request.headers = headers.into_iter().collect();
+        flow_a.request.body = serde_json::from_str(
+            r#"{"model":"gpt-4","messages":[{"role":"user","content":"Hi"}],"extra":{"b":1,"a":[{"y":2,"x":1}]}}"#,
+        )
+        .unwrap();
+        flow_b.request.body = serde_json::from_str(
+            r#"{"extra":{"a":[{"x":1,"y":2}],"b":1},"messages":[{"content":"Hi","role":"user"}],"model":"gpt-4"}"#,
+        )
+        .unwrap();
+
+        for format in [ExportFormat::HAR, ExportFormat::JSON] {
+            let exporter = FlowExporter::new(ExportOptions {
+                format,
+                stable_key_order: true,
+                ..Default::default()
+            });
+            let render = |flow: &LLMFlow| match exporter.export(std::slice::from_ref(flow)).unwrap()
+            {
+                ExportResult::Har(har) => exporter.to_json_string(&har).unwrap(),
+                ExportResult::Json(json) => exporter.to_json_string(&json).unwrap(),
+                ExportResult::Text(text) => text,
+                ExportResult::Binary(_) => unreachable!(),
+            };
+            assert_eq!(render(&flow_a), render(&flow_b));
+        }
+
+        // 紧凑格式
+        let exporter = FlowExporter::new(ExportOptions {
+            stable_key_order: true,
+            pretty: false,
+            ..Default::default()
+        });
+        let json = exporter
+            .to_json_string(&serde_json::json!({"b": {"d": 1, "c": 2}, "a": []}))
+            .unwrap();
+        assert_eq!(json, r#"{"a":[],"b":{"c":2,"d":1}}"#);
+    }
+
     #[test]
     fn test_escape_csv() {
         assert_eq!(escape_csv("simple"), "simple");
@@ -1469,6 +2098,50 @@ mod tests {
         assert_eq!(tokens.output, 5);
         assert_eq!(tokens.total, 15);
     }
+
+    #[test]
+    fn test_har_stream_timings() {
+        let exporter = FlowExporter::with_defaults();
+
+        // 未保存 chunks 时保持原有输出
+        let flow = create_test_flow();
+        let har = exporter.export_har(std::slice::from_ref(&flow));
+        assert!(har.log.entries[0].stream_timings.is_none());
+        let json = serde_json::to_string(&har).unwrap();
+        assert!(!json.contains("_streamTimings"));
+
+        let mut flow = flow;
+        let start = flow.timestamps.request_start;
+        let chunks: Vec<StreamChunk> = ["{\"a\":1}", "{\"b\":22}", "[DONE]"]
+            .iter()
+            .enumerate()
+            .map(|(i, data)| StreamChunk {
+                index: i as u32,
+                event: None,
+                data: data.to_string(),
+                timestamp: start + chrono::Duration::milliseconds(100 + 50 * i as i64),
+                content_delta: None,
+                tool_call_delta: None,
+                thinking_delta: None,
+            })
+            .collect();
+        flow.response.as_mut().unwrap().stream_info = Some(StreamInfo {
+            chunk_count: chunks.len() as u32,
+            first_chunk_latency_ms: 100,
+            avg_chunk_interval_ms: 50.0,
+            raw_chunks: Some(chunks),
+            completed_cleanly: true,
+        });
+
+        // 导出后重新加载
+        let json = serde_json::to_string(&exporter.export_har(&[flow])).unwrap();
+        let har: HarArchive = serde_json::from_str(&json).unwrap();
+        let timings = har.log.entries[0].stream_timings.as_ref().unwrap();
+        assert_eq!(timings.len(), 3);
+        assert_eq!(timings[1].chunk_index, 1);
+        assert_eq!(timings[1].relative_ms, 150.0);
+        assert_eq!(timings[1].content_len, 8);
+    }
 }
 
 // ============================================================================
@@ -1554,6 +2227,8 @@ mod property_tests {
                     max_tokens,
                     stop: None,
                     stream,
+                    response_format: None,
+                    seed: None,
                     extra: HashMap::new(),
                 },
             )
@@ -1580,6 +2255,7 @@ mod property_tests {
                 parameters,
                 size_bytes: 0,
                 timestamp: Utc::now(),
+                attachments: Vec::new(),
             })
     }
 
@@ -1607,10 +2283,16 @@ mod property_tests {
             tool_calls: Vec::new(),
             usage,
             stop_reason: Some(StopReason::Stop),
+            raw_stop_reason: None,
             size_bytes: 0,
             timestamp_start: Utc::now(),
             timestamp_end: Utc::now(),
             stream_info: None,
+            schema_valid: None,
+            logprobs: None,
+            body_info: None,
+            system_fingerprint: None,
+            turns: Vec::new(),
         })
     }
 
@@ -1624,7 +2306,13 @@ mod property_tests {
             client_info: ClientInfo::default(),
             routing_info: RoutingInfo::default(),
             injected_params: None,
+            defaulted_params: None,
             context_usage_percentage: None,
+            shadow_of: None,
+            idempotent_replay_of: None,
+            upstream_request_id: None,
+            usage_source: None,
+            content_filter: None,
         })
     }
 
@@ -1949,6 +2637,7 @@ mod redaction_property_tests {
                         parameters: RequestParameters::default(),
                         size_bytes: 0,
                         timestamp: Utc::now(),
+                        attachments: Vec::new(),
                     };
 
                     let response = LLMResponse {
@@ -1961,10 +2650,16 @@ mod redaction_property_tests {
                         tool_calls: Vec::new(),
                         usage: TokenUsage::default(),
                         stop_reason: Some(StopReason::Stop),
+                        raw_stop_reason: None,
                         size_bytes: 0,
                         timestamp_start: Utc::now(),
                         timestamp_end: Utc::now(),
                         stream_info: None,
+                        schema_valid: None,
+                        logprobs: None,
+                        body_info: None,
+                        system_fingerprint: None,
+                        turns: Vec::new(),
                     };
 
                     let metadata = FlowMetadata {
@@ -1975,7 +2670,13 @@ mod redaction_property_tests {
                         client_info: ClientInfo::default(),
                         routing_info: RoutingInfo::default(),
                         injected_params: None,
+                        defaulted_params: None,
                         context_usage_percentage: None,
+                        shadow_of: None,
+                        idempotent_replay_of: None,
+                        upstream_request_id: None,
+                        usage_source: None,
+                        content_filter: None,
                     };
 
                     let mut flow = LLMFlow::new(id, flow_type, request, metadata);
@@ -0,0 +1,295 @@
+//! 导出字段选择
+//!
+//! `include_raw` / `include_stream_chunks` 只能整体开关，分享时常常只想给出部分字段（例如只要
+//! 内容不要系统提示词，只要用量不要请求头）。字段掩码用点分路径（如 `request.model`、
+//! `response.usage`）选择 JSON / JSONL 导出的字段：
+//!
+//! - 包含列表：只保留列出的字段及其全部子字段，中间的对象只保留通向这些字段的键
+//! - 排除列表：在包含列表的结果上再移除列出的字段
+//!
+//! 路径经过数组时作用于每个元素（如 `request.messages.role`）。已知结构体的字段名从其
+//! `Deserialize` 实现读取，随模型定义自动更新；请求头、请求体等自由格式字段之下的路径不做校验。
+
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+use serde_json::{Map, Value};
+
+use super::models::{
+    ClientInfo, FlowAnnotations, FlowError, FlowMetadata, FlowTimestamps, LLMFlow, LLMRequest,
+    LLMResponse, Message, RoutingInfo, StreamInfo, ThinkingContent, TokenUsage,
+};
+
+/// 字段路径无效
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FieldMaskError {
+    /// 路径为空或包含空的路径段（如 `request..model`）
+    #[error("无效的字段路径 `{0}`")]
+    InvalidPath(String),
+    /// 路径中的字段不存在
+    #[error("未知的字段 `{path}`（`{parent}` 下可用的字段: {}）", .available.join(", "))]
+    UnknownField {
+        /// 完整路径
+        path: String,
+        /// 未知字段所在的对象路径（顶层为 `flow`）
+        parent: String,
+        /// 该对象下可用的字段
+        available: Vec<String>,
+    },
+}
+
+/// 导出字段掩码
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldMask {
+    include: Vec<Vec<String>>,
+    exclude: Vec<Vec<String>>,
+}
+
+impl FieldMask {
+    /// 创建字段掩码（包含列表为空时保留全部字段）
+    pub fn new(include: &[String], exclude: &[String]) -> Self {
+        Self {
+            include: include.iter().map(|path| split_path(path)).collect(),
+            exclude: exclude.iter().map(|path| split_path(path)).collect(),
+        }
+    }
+
+    /// 是否不做任何字段选择
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// 校验所有路径，返回第一个无效路径的错误
+    pub fn validate(&self) -> Result<(), FieldMaskError> {
+        self.include
+            .iter()
+            .chain(&self.exclude)
+            .try_for_each(|segments| validate_segments(segments))
+    }
+
+    /// 对单个 Flow 的 JSON 应用掩码
+    ///
+    /// 未知路径不匹配任何字段；需要报错时先调用 [`FieldMask::validate`]。
+    pub fn apply(&self, value: Value) -> Value {
+        let mut value = if self.include.is_empty() {
+            value
+        } else {
+            let paths: Vec<&[String]> = self.include.iter().map(Vec::as_slice).collect();
+            select(value, &paths)
+        };
+        for path in &self.exclude {
+            remove(&mut value, path);
+        }
+        value
+    }
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    path.trim().split('.').map(str::to_string).collect()
+}
+
+fn validate_segments(segments: &[String]) -> Result<(), FieldMaskError> {
+    let path = segments.join(".");
+    if segments.iter().any(|segment| segment.is_empty()) {
+        return Err(FieldMaskError::InvalidPath(path));
+    }
+
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    for (i, segment) in segments.iter().enumerate() {
+        // 自由格式字段（或标量）之下不再校验
+        let Some(fields) = known_fields(&segments[..i]) else {
+            break;
+        };
+        if !fields.contains(segment) {
+            let parent = if i == 0 {
+                "flow".to_string()
+            } else {
+                segments[..i].join(".")
+            };
+            return Err(FieldMaskError::UnknownField {
+                path,
+                parent,
+                available: fields.iter().map(|f| f.to_string()).collect(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 某个对象路径下的已知字段（自由格式字段或标量返回 `None`）
+fn known_fields(parent: &[&str]) -> Option<&'static [&'static str]> {
+    Some(match parent {
+        [] => struct_fields::<LLMFlow>(),
+        ["request"] => struct_fields::<LLMRequest>(),
+        ["request", "messages"] | ["response", "turns"] => struct_fields::<Message>(),
+        ["response"] => struct_fields::<LLMResponse>(),
+        ["response", "usage"] => struct_fields::<TokenUsage>(),
+        ["response", "thinking"] => struct_fields::<ThinkingContent>(),
+        ["response", "stream_info"] => struct_fields::<StreamInfo>(),
+        ["error"] => struct_fields::<FlowError>(),
+        ["metadata"] => struct_fields::<FlowMetadata>(),
+        ["metadata", "client_info"] => struct_fields::<ClientInfo>(),
+        ["metadata", "routing_info"] => struct_fields::<RoutingInfo>(),
+        ["timestamps"] => struct_fields::<FlowTimestamps>(),
+        ["annotations"] => struct_fields::<FlowAnnotations>(),
+        _ => return None,
+    })
+}
+
+/// 读取结构体 `Deserialize` 实现声明的字段名
+fn struct_fields<T: DeserializeOwned>() -> &'static [&'static str] {
+    let mut fields = None;
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields.unwrap_or(&[])
+}
+
+/// 只记录 `deserialize_struct` 收到的字段名，不产生任何值
+struct FieldNames<'a>(&'a mut Option<&'static [&'static str]>);
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = Some(fields);
+        Err(de::Error::custom("field names only"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+/// 只保留匹配路径的字段（空路径表示保留整个值）
+fn select(value: Value, paths: &[&[String]]) -> Value {
+    if paths.iter().any(|path| path.is_empty()) {
+        return value;
+    }
+    match value {
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| select(v, paths)).collect()),
+        Value::Object(map) => {
+            let mut selected = Map::new();
+            for (key, child) in map {
+                let rest: Vec<&[String]> = paths
+                    .iter()
+                    .filter(|path| path[0] == key)
+                    .map(|path| &path[1..])
+                    .collect();
+                if !rest.is_empty() {
+                    selected.insert(key, select(child, &rest));
+                }
+            }
+            Value::Object(selected)
+        }
+        // 路径经过空值（如未完成 Flow 的 `response`）时保留空值
+        other => other,
+    }
+}
+
+/// 移除匹配路径的字段
+fn remove(value: &mut Value, path: &[String]) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(|item| remove(item, path)),
+        Value::Object(map) => match path {
+            [last] => {
+                map.remove(last);
+            }
+            [first, rest @ ..] => {
+                if let Some(child) = map.get_mut(first) {
+                    remove(child, rest);
+                }
+            }
+            [] => {}
+        },
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_validate_paths() {
+        let valid = FieldMask::new(
+            &paths(&[
+                "id",
+                "request.model",
+                "request.messages.role",
+                "request.headers.x-custom",
+                "response.usage.input_tokens",
+            ]),
+            &paths(&["request.system_prompt"]),
+        );
+        assert!(valid.validate().is_ok());
+
+        let err = FieldMask::new(&paths(&["response.usages"]), &[])
+            .validate()
+            .unwrap_err();
+        match err {
+            FieldMaskError::UnknownField {
+                path,
+                parent,
+                available,
+            } => {
+                assert_eq!(path, "response.usages");
+                assert_eq!(parent, "response");
+                assert!(available.contains(&"usage".to_string()));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        assert!(matches!(
+            FieldMask::new(&[], &paths(&["bogus"])).validate(),
+            Err(FieldMaskError::UnknownField { .. })
+        ));
+        assert_eq!(
+            FieldMask::new(&paths(&["request..model"]), &[]).validate(),
+            Err(FieldMaskError::InvalidPath("request..model".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_include_then_exclude() {
+        let value = serde_json::json!({
+            "id": "flow-1",
+            "request": {
+                "model": "gpt-4",
+                "system_prompt": "secret",
+                "messages": [
+                    {"role": "user", "content": "hi"},
+                    {"role": "assistant", "content": "hello"}
+                ]
+            },
+            "response": null
+        });
+        let mask = FieldMask::new(
+            &paths(&["id", "request", "response.usage"]),
+            &paths(&["request.system_prompt", "request.messages.content"]),
+        );
+
+        assert_eq!(
+            mask.apply(value),
+            serde_json::json!({
+                "id": "flow-1",
+                "request": {
+                    "model": "gpt-4",
+                    "messages": [{"role": "user"}, {"role": "assistant"}]
+                },
+                "response": null
+            })
+        );
+    }
+}
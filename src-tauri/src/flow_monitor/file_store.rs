@@ -6,14 +6,17 @@
 use chrono::{DateTime, NaiveDate, Utc};
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use thiserror::Error;
 
-use super::memory_store::FlowFilter;
+use super::memory_store::{FlowFilter, TagMatchMode};
 use super::models::LLMFlow;
+use super::read_cache::{FlowReadCache, ReadCacheStats};
+use super::retention::RetentionPolicy;
 
 // ============================================================================
 // 错误类型
@@ -225,6 +228,42 @@ impl FlowWriter {
     }
 }
 
+/// 已写入临时文件、尚未替换原文件的 JSONL 重写
+///
+/// 索引提交后调用 [`FileRewrite::apply`] 替换原文件；提交失败时调用
+/// [`FileRewrite::discard`] 删除临时文件，原文件保持不变。
+#[derive(Debug)]
+struct FileRewrite {
+    /// 原文件路径
+    path: PathBuf,
+    /// 临时文件路径
+    temp_path: PathBuf,
+    /// 保留行的偏移量变化（旧偏移量, 新偏移量），按旧偏移量升序
+    relocations: Vec<(i64, i64)>,
+    /// 释放的空间（字节）
+    bytes_freed: u64,
+    /// 文件是否因为没有剩余内容而被删除
+    removed: bool,
+}
+
+impl FileRewrite {
+    /// 用临时文件替换原文件（没有剩余内容时删除原文件）
+    fn apply(self) -> Result<()> {
+        if self.removed {
+            fs::remove_file(&self.temp_path)?;
+            fs::remove_file(&self.path)?;
+        } else {
+            fs::rename(&self.temp_path, &self.path)?;
+        }
+        Ok(())
+    }
+
+    /// 删除临时文件，保留原文件
+    fn discard(self) {
+        let _ = fs::remove_file(&self.temp_path);
+    }
+}
+
 // ============================================================================
 // Flow 文件存储
 // ============================================================================
@@ -245,6 +284,8 @@ pub struct FlowFileStore {
     rotation_config: RotationConfig,
     /// SQLite 连接
     index_db: Mutex<Connection>,
+    /// 最近读取的 Flow 缓存
+    read_cache: FlowReadCache,
 }
 
 impl FlowFileStore {
@@ -273,6 +314,7 @@ impl FlowFileStore {
             current_file_index: Mutex::new(1),
             rotation_config: config,
             index_db: Mutex::new(conn),
+            read_cache: FlowReadCache::default(),
         })
     }
 
@@ -310,6 +352,7 @@ impl FlowFileStore {
                 starred INTEGER DEFAULT 0,
                 marker TEXT,
                 comment TEXT,
+                priority INTEGER DEFAULT 0,
                 updated_at TEXT NOT NULL,
                 FOREIGN KEY (flow_id) REFERENCES flow_index(id)
             );
@@ -336,6 +379,12 @@ impl FlowFileStore {
             "#,
         )?;
 
+        // 旧版本的标注表缺少优先级列（列已存在时忽略错误）
+        let _ = conn.execute(
+            "ALTER TABLE flow_annotations ADD COLUMN priority INTEGER DEFAULT 0",
+            [],
+        );
+
         Ok(())
     }
 
@@ -344,6 +393,16 @@ impl FlowFileStore {
         &self.base_dir
     }
 
+    /// 调整读缓存的内存预算（字节，0 表示不缓存）
+    pub fn set_read_cache_budget(&self, max_memory_bytes: u64) {
+        self.read_cache.set_max_memory_bytes(max_memory_bytes);
+    }
+
+    /// 读缓存统计
+    pub fn read_cache_stats(&self) -> ReadCacheStats {
+        self.read_cache.stats()
+    }
+
     /// 获取轮转配置
     pub fn rotation_config(&self) -> &RotationConfig {
         &self.rotation_config
@@ -365,7 +424,8 @@ impl FlowFileStore {
 
         let writer = writer_guard.as_mut().unwrap();
 
-        // 写入 Flow
+        // 写入 Flow（同 ID 重写时旧的缓存失效）
+        self.read_cache.invalidate(&flow.id);
         let offset = writer.write(flow)?;
         let file_path = writer.path().to_string_lossy().to_string();
 
@@ -470,18 +530,20 @@ impl FlowFileStore {
         if flow.annotations.starred
             || flow.annotations.marker.is_some()
             || flow.annotations.comment.is_some()
+            || flow.annotations.priority != 0
         {
             conn.execute(
                 r#"
                 INSERT OR REPLACE INTO flow_annotations (
-                    flow_id, starred, marker, comment, updated_at
-                ) VALUES (?1, ?2, ?3, ?4, ?5)
+                    flow_id, starred, marker, comment, priority, updated_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
                 "#,
                 params![
                     flow.id,
                     flow.annotations.starred as i32,
                     flow.annotations.marker,
                     flow.annotations.comment,
+                    flow.annotations.priority,
                     Utc::now().to_rfc3339(),
                 ],
             )?;
@@ -540,25 +602,47 @@ impl FlowFileStore {
     }
 
     /// 根据 ID 获取 Flow
+    ///
+    /// 优先从读缓存返回；未命中时从文件读取，用索引中的标注覆盖后放入缓存。
     pub fn get(&self, id: &str) -> Result<Option<LLMFlow>> {
-        let conn = self.index_db.lock().unwrap();
+        if let Some(flow) = self.read_cache.get(id) {
+            return Ok(Some(flow));
+        }
 
-        let result: Option<(String, i64)> = conn
-            .query_row(
+        let result: Option<(String, i64)> = {
+            let conn = self.index_db.lock().unwrap();
+            conn.query_row(
                 "SELECT file_path, file_offset FROM flow_index WHERE id = ?1",
                 params![id],
                 |row| Ok((row.get(0)?, row.get(1)?)),
             )
-            .optional()?;
+            .optional()?
+        };
 
-        match result {
-            Some((file_path, file_offset)) => self.read_flow_from_file(&file_path, file_offset),
-            None => Ok(None),
-        }
+        let Some((file_path, file_offset)) = result else {
+            return Ok(None);
+        };
+        let Some((mut flow, size)) = self.read_line_from_file(&file_path, file_offset)? else {
+            return Ok(None);
+        };
+        self.apply_indexed_annotations(&mut flow)?;
+        self.read_cache.insert(flow.clone(), size);
+        Ok(Some(flow))
     }
 
     /// 从文件读取 Flow
     fn read_flow_from_file(&self, file_path: &str, file_offset: i64) -> Result<Option<LLMFlow>> {
+        Ok(self
+            .read_line_from_file(file_path, file_offset)?
+            .map(|(flow, _)| flow))
+    }
+
+    /// 从文件读取 Flow 及其所在行的字节数
+    fn read_line_from_file(
+        &self,
+        file_path: &str,
+        file_offset: i64,
+    ) -> Result<Option<(LLMFlow, u64)>> {
         let path = Path::new(file_path);
         if !path.exists() {
             return Ok(None);
@@ -578,19 +662,37 @@ impl FlowFileStore {
             return Ok(None);
         }
 
-        let mut flow: LLMFlow = serde_json::from_str(&line)?;
-        Ok(Some(flow))
+        let flow: LLMFlow = serde_json::from_str(&line)?;
+        Ok(Some((flow, line.len() as u64)))
     }
 
     /// 查询 Flow（从索引）
     pub fn query(&self, filter: &FlowFilter, limit: usize, offset: usize) -> Result<Vec<LLMFlow>> {
+        self.query_page(filter, Some((limit, offset)))
+    }
+
+    /// 查询所有匹配的 Flow（不分页）
+    ///
+    /// 用于批量操作收集目标，匹配数量较多时会读取大量文件。
+    pub fn query_all(&self, filter: &FlowFilter) -> Result<Vec<LLMFlow>> {
+        self.query_page(filter, None)
+    }
+
+    /// 按 `(limit, offset)` 分页查询，`None` 表示返回全部结果
+    fn query_page(
+        &self,
+        filter: &FlowFilter,
+        page: Option<(usize, usize)>,
+    ) -> Result<Vec<LLMFlow>> {
         // 先获取所有文件位置信息
-        let file_locations = self.query_index(filter, limit, offset)?;
+        let file_locations = self.query_index(filter, page)?;
 
         // 读取 Flow
         let mut flows = Vec::new();
         for (file_path, file_offset) in file_locations {
-            if let Some(flow) = self.read_flow_from_file(&file_path, file_offset)? {
+            if let Some(mut flow) = self.read_flow_from_file(&file_path, file_offset)? {
+                // 文件中的标注可能已过期，以索引中的标注为准
+                self.apply_indexed_annotations(&mut flow)?;
                 // 再次用内存过滤器验证（处理复杂条件）
                 if filter.matches(&flow) {
                     flows.push(flow);
@@ -605,8 +707,7 @@ impl FlowFileStore {
     fn query_index(
         &self,
         filter: &FlowFilter,
-        limit: usize,
-        offset: usize,
+        page: Option<(usize, usize)>,
     ) -> Result<Vec<(String, i64)>> {
         let conn = self.index_db.lock().unwrap();
 
@@ -666,6 +767,60 @@ impl FlowFileStore {
             params_vec.push(Box::new(has_thinking as i32));
         }
 
+        // 收藏过滤
+        let starred = filter.starred.or(filter.starred_only.then_some(true));
+        if let Some(starred) = starred {
+            conditions.push(
+                "COALESCE((SELECT starred FROM flow_annotations WHERE flow_id = flow_index.id), 0) = ?"
+                    .to_string(),
+            );
+            params_vec.push(Box::new(starred as i32));
+        }
+
+        // 评论过滤
+        if let Some(has_comment) = filter.has_comment {
+            let exists = "EXISTS (SELECT 1 FROM flow_annotations WHERE flow_id = flow_index.id AND TRIM(COALESCE(comment, '')) != '')";
+            conditions.push(if has_comment {
+                exists.to_string()
+            } else {
+                format!("NOT {}", exists)
+            });
+        }
+
+        // 标记过滤
+        if let Some(ref marker) = filter.marker {
+            conditions.push(
+                "EXISTS (SELECT 1 FROM flow_annotations WHERE flow_id = flow_index.id AND marker = ?)"
+                    .to_string(),
+            );
+            params_vec.push(Box::new(marker.clone()));
+        }
+
+        // 标签过滤
+        if let Some(ref tags) = filter.tags {
+            let placeholders: Vec<String> = tags.iter().map(|_| "?".to_string()).collect();
+            let subquery = format!(
+                "SELECT flow_id FROM flow_tags WHERE tag IN ({})",
+                placeholders.join(", ")
+            );
+            match filter.tag_match {
+                TagMatchMode::Any => conditions.push(format!("id IN ({})", subquery)),
+                TagMatchMode::All => {
+                    let mut unique_tags = tags.clone();
+                    unique_tags.sort();
+                    unique_tags.dedup();
+                    conditions.push(format!(
+                        "id IN ({} GROUP BY flow_id HAVING COUNT(DISTINCT tag) = {})",
+                        subquery,
+                        unique_tags.len()
+                    ));
+                }
+            }
+            for tag in tags {
+                params_vec.push(Box::new(tag.clone()));
+            }
+        }
+
         // 构建 SQL
         let where_clause = if conditions.is_empty() {
             String::new()
@@ -673,13 +828,15 @@ impl FlowFileStore {
             format!("WHERE {}", conditions.join(" AND "))
         };
 
-        let sql = format!(
-            "SELECT file_path, file_offset FROM flow_index {} ORDER BY created_at DESC LIMIT ? OFFSET ?",
+        let mut sql = format!(
+            "SELECT file_path, file_offset FROM flow_index {} ORDER BY created_at DESC",
             where_clause
         );
-
-        params_vec.push(Box::new(limit as i64));
-        params_vec.push(Box::new(offset as i64));
+        if let Some((limit, offset)) = page {
+            sql.push_str(" LIMIT ? OFFSET ?");
+            params_vec.push(Box::new(limit.min(i64::MAX as usize) as i64));
+            params_vec.push(Box::new(offset.min(i64::MAX as usize) as i64));
+        }
 
         // 执行查询
         let params_refs: Vec<&dyn rusqlite::ToSql> =
@@ -697,6 +854,59 @@ impl FlowFileStore {
         Ok(results)
     }
 
+    /// 用索引中的标注覆盖 Flow 的标注
+    ///
+    /// 标注更新只写入索引，不回写 JSONL 文件；没有标注记录时保留文件中的值。
+    fn apply_indexed_annotations(&self, flow: &mut LLMFlow) -> Result<()> {
+        let conn = self.index_db.lock().unwrap();
+
+        let annotation: Option<(bool, Option<String>, Option<String>, i32)> = conn
+            .query_row(
+                "SELECT starred, marker, comment, COALESCE(priority, 0) FROM flow_annotations WHERE flow_id = ?1",
+                params![flow.id],
+                |row| {
+                    Ok((
+                        row.get::<_, i32>(0)? != 0,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        if let Some((starred, marker, comment, priority)) = annotation {
+            let mut stmt = conn.prepare("SELECT tag FROM flow_tags WHERE flow_id = ?1")?;
+            let tags = stmt
+                .query_map(params![flow.id], |row| row.get::<_, String>(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+
+            flow.annotations.starred = starred;
+            flow.annotations.marker = marker;
+            flow.annotations.comment = comment;
+            flow.annotations.priority = priority;
+            flow.annotations.tags = tags;
+        }
+
+        Ok(())
+    }
+
+    /// 读取索引中每个 Flow 的手动标签
+    pub fn tags_by_flow(&self) -> Result<HashMap<String, Vec<String>>> {
+        let conn = self.index_db.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT flow_id, tag FROM flow_tags")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+        for row in rows {
+            let (flow_id, tag) = row?;
+            tags.entry(flow_id).or_default().push(tag);
+        }
+        Ok(tags)
+    }
+
     /// 获取索引中的 Flow 数量
     pub fn count(&self) -> Result<usize> {
         let conn = self.index_db.lock().unwrap();
@@ -776,14 +986,15 @@ impl FlowFileStore {
         conn.execute(
             r#"
             INSERT OR REPLACE INTO flow_annotations (
-                flow_id, starred, marker, comment, updated_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5)
+                flow_id, starred, marker, comment, priority, updated_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
             "#,
             params![
                 flow_id,
                 annotations.starred as i32,
                 annotations.marker,
                 annotations.comment,
+                annotations.priority,
                 Utc::now().to_rfc3339(),
             ],
         )?;
@@ -799,6 +1010,7 @@ impl FlowFileStore {
                 params![flow_id, tag],
             )?;
         }
+        self.read_cache.invalidate(flow_id);
 
         Ok(())
     }
@@ -849,6 +1061,7 @@ impl FlowFileStore {
 
             file_paths
         }; // conn 在这里被释放
+        self.read_cache.clear();
 
         // 删除文件
         for file_path in file_paths {
@@ -869,6 +1082,197 @@ impl FlowFileStore {
         Ok(result)
     }
 
+    /// 按保留策略清理过期数据
+    ///
+    /// 错误 Flow 与普通 Flow 分别按各自的保留天数清理，已收藏的 Flow 始终保留。
+    /// 与 [`cleanup`](Self::cleanup) 不同，仅删除过期的记录而非整个文件，
+    /// 以免误删同一文件中需要保留的 Flow。
+    ///
+    /// # 参数
+    /// - `policy`: 保留策略
+    /// - `now`: 当前时间
+    pub fn apply_retention(
+        &self,
+        policy: &RetentionPolicy,
+        now: DateTime<Utc>,
+    ) -> Result<CleanupResult> {
+        let ids = {
+            let conn = self.index_db.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT i.id FROM flow_index i
+                 LEFT JOIN flow_annotations a ON a.flow_id = i.id
+                 WHERE COALESCE(a.starred, 0) = 0 AND i.has_error = ?1 AND i.created_at < ?2",
+            )?;
+
+            let mut ids = Vec::new();
+            for (has_error, days) in [(true, policy.error_days), (false, policy.normal_days)] {
+                let Some(days) = days else {
+                    continue;
+                };
+                let before = now - chrono::Duration::days(days as i64);
+                let rows = stmt.query_map(params![has_error, before.to_rfc3339()], |row| {
+                    row.get::<_, String>(0)
+                })?;
+                for id in rows {
+                    ids.push(id?);
+                }
+            }
+            ids
+        };
+
+        self.delete_flows(&ids)
+    }
+
+    /// 删除指定的 Flow
+    ///
+    /// 移除索引、标注、标签和全文搜索记录，并重写受影响的 JSONL 文件，
+    /// 同步更新其余 Flow 的偏移量。重写后为空的文件会被删除。
+    ///
+    /// # 参数
+    /// - `ids`: 要删除的 Flow ID
+    pub fn delete_flows(&self, ids: &[String]) -> Result<CleanupResult> {
+        let mut result = CleanupResult::default();
+        if ids.is_empty() {
+            return Ok(result);
+        }
+
+        // 持有写入器锁直到重写完成，避免并发追加写入被覆盖；
+        // 写入器关闭后会在下次写入时按文件大小重新定位偏移量
+        let mut writer_guard = self.current_writer.lock().unwrap();
+        *writer_guard = None;
+
+        let conn = self.index_db.lock().unwrap();
+        let mut file_paths = BTreeSet::new();
+        for id in ids {
+            let file_path: Option<String> = conn
+                .query_row(
+                    "SELECT file_path FROM flow_index WHERE id = ?1",
+                    params![id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            file_paths.extend(file_path);
+        }
+
+        // 先把重写结果写入临时文件，索引提交成功后再替换原文件：
+        // 任一步骤失败时原文件和索引都保持不变
+        let deleted: HashSet<&str> = ids.iter().map(String::as_str).collect();
+        let mut rewrites = Vec::new();
+        for file_path in &file_paths {
+            match Self::stage_rewrite(Path::new(file_path), &deleted) {
+                Ok(Some(rewrite)) => rewrites.push(rewrite),
+                Ok(None) => {}
+                Err(e) => {
+                    rewrites.into_iter().for_each(FileRewrite::discard);
+                    return Err(e);
+                }
+            }
+        }
+
+        let committed = (|| -> Result<usize> {
+            let tx = conn.unchecked_transaction()?;
+            let mut flows_deleted = 0;
+            for id in ids {
+                tx.execute(
+                    "DELETE FROM flow_annotations WHERE flow_id = ?1",
+                    params![id],
+                )?;
+                tx.execute("DELETE FROM flow_tags WHERE flow_id = ?1", params![id])?;
+                tx.execute("DELETE FROM flow_fts WHERE id = ?1", params![id])?;
+                flows_deleted += tx.execute("DELETE FROM flow_index WHERE id = ?1", params![id])?;
+            }
+            for rewrite in &rewrites {
+                let file_path = rewrite.path.to_string_lossy();
+                // 偏移量按升序更新，新偏移量不大于旧偏移量，不会与未处理的记录冲突
+                for (old_offset, new_offset) in &rewrite.relocations {
+                    tx.execute(
+                        "UPDATE flow_index SET file_offset = ?1 WHERE file_path = ?2 AND file_offset = ?3",
+                        params![new_offset, file_path, old_offset],
+                    )?;
+                }
+            }
+            tx.commit()?;
+            Ok(flows_deleted)
+        })();
+        result.flows_deleted = match committed {
+            Ok(count) => count,
+            Err(e) => {
+                rewrites.into_iter().for_each(FileRewrite::discard);
+                return Err(e);
+            }
+        };
+
+        for rewrite in rewrites {
+            result.bytes_freed += rewrite.bytes_freed;
+            if rewrite.removed {
+                result.files_deleted += 1;
+            }
+            rewrite.apply()?;
+        }
+        self.read_cache.invalidate_many(deleted.iter().copied());
+        drop(conn);
+        drop(writer_guard);
+
+        self.cleanup_empty_dirs()?;
+        Ok(result)
+    }
+
+    /// 把去除指定 ID 后的 JSONL 内容写入临时文件（文件不存在时返回 `None`）
+    fn stage_rewrite(path: &Path, deleted: &HashSet<&str>) -> Result<Option<FileRewrite>> {
+        /// 仅解析 Flow ID
+        #[derive(Deserialize)]
+        struct FlowIdOnly {
+            id: String,
+        }
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let original_size = fs::metadata(path)?.len();
+        let mut rewrite = FileRewrite {
+            path: path.to_path_buf(),
+            temp_path: path.with_extension("jsonl.tmp"),
+            relocations: Vec::new(),
+            bytes_freed: 0,
+            removed: false,
+        };
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut writer = BufWriter::new(File::create(&rewrite.temp_path)?);
+
+        let mut line = String::new();
+        let mut old_offset = 0u64;
+        let mut new_offset = 0u64;
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+
+            // 无法解析的行原样保留
+            let is_deleted = serde_json::from_str::<FlowIdOnly>(&line)
+                .map(|flow| deleted.contains(flow.id.as_str()))
+                .unwrap_or(false);
+            if !is_deleted {
+                writer.write_all(line.as_bytes())?;
+                if old_offset != new_offset {
+                    rewrite
+                        .relocations
+                        .push((old_offset as i64, new_offset as i64));
+                }
+                new_offset += read as u64;
+            }
+            old_offset += read as u64;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        rewrite.bytes_freed = original_size.saturating_sub(new_offset);
+        rewrite.removed = new_offset == 0;
+        Ok(Some(rewrite))
+    }
+
     /// 清理空目录
     fn cleanup_empty_dirs(&self) -> Result<()> {
         if let Ok(entries) = fs::read_dir(&self.base_dir) {
@@ -915,7 +1319,9 @@ impl FlowFileStore {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::flow_monitor::models::{FlowMetadata, FlowType, LLMRequest, RequestParameters};
+    use crate::flow_monitor::models::{
+        FlowAnnotations, FlowMetadata, FlowType, LLMRequest, RequestParameters,
+    };
     use crate::ProviderType;
     use tempfile::TempDir;
 
@@ -1027,6 +1433,107 @@ mod tests {
         };
         let results = store.query(&filter, 100, 0).unwrap();
         assert_eq!(results.len(), 2);
+
+        // 分页与不分页查询
+        assert_eq!(store.query(&FlowFilter::default(), 1, 1).unwrap().len(), 1);
+        assert_eq!(store.query_all(&FlowFilter::default()).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_file_store_query_by_annotations() {
+        let temp_dir = TempDir::new().unwrap();
+        let store =
+            FlowFileStore::new(temp_dir.path().to_path_buf(), RotationConfig::default()).unwrap();
+
+        let tagged = [
+            ("flow-1", vec!["bug", "prod"]),
+            ("flow-2", vec!["bug"]),
+            ("flow-3", vec!["prod"]),
+        ];
+        for (id, tags) in tagged {
+            let mut flow = create_test_flow(id, "gpt-4", ProviderType::OpenAI);
+            flow.annotations.tags = tags.into_iter().map(String::from).collect();
+            store.write(&flow).unwrap();
+        }
+
+        // 标注更新只写入索引，查询应以索引为准
+        let annotations = FlowAnnotations {
+            starred: true,
+            marker: Some("🔴".to_string()),
+            comment: Some("已复现".to_string()),
+            tags: vec!["bug".to_string(), "prod".to_string()],
+            auto_tags: Vec::new(),
+            priority: 0,
+        };
+        store.update_annotations("flow-2", &annotations).unwrap();
+
+        let ids = |filter: &FlowFilter| {
+            let mut ids: Vec<String> = store
+                .query(filter, 100, 0)
+                .unwrap()
+                .into_iter()
+                .map(|f| f.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        let all_tags = FlowFilter {
+            tags: Some(vec!["bug".to_string(), "prod".to_string()]),
+            tag_match: TagMatchMode::All,
+            ..Default::default()
+        };
+        assert_eq!(ids(&all_tags), vec!["flow-1", "flow-2"]);
+
+        let annotated = FlowFilter {
+            starred: Some(true),
+            has_comment: Some(true),
+            marker: Some("🔴".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(&annotated), vec!["flow-2"]);
+
+        let not_starred = FlowFilter {
+            starred: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(ids(&not_starred), vec!["flow-1", "flow-3"]);
+    }
+
+    #[test]
+    fn test_read_cache_hits_and_invalidation() {
+        let temp_dir = TempDir::new().unwrap();
+        let store =
+            FlowFileStore::new(temp_dir.path().to_path_buf(), RotationConfig::default()).unwrap();
+        store
+            .write(&create_test_flow("flow-1", "gpt-4", ProviderType::OpenAI))
+            .unwrap();
+        store
+            .write(&create_test_flow("flow-2", "gpt-4", ProviderType::OpenAI))
+            .unwrap();
+
+        assert!(store.get("flow-1").unwrap().is_some());
+        assert!(store.get("flow-1").unwrap().is_some());
+        let stats = store.read_cache_stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.entries, 1);
+
+        // 标注更新后重新读取，返回索引中的新标注
+        let annotations = FlowAnnotations {
+            starred: true,
+            ..Default::default()
+        };
+        store.update_annotations("flow-1", &annotations).unwrap();
+        assert!(store.get("flow-1").unwrap().unwrap().annotations.starred);
+        assert_eq!(store.read_cache_stats().misses, 2);
+
+        // 删除后不再从缓存返回
+        store.delete_flows(&["flow-1".to_string()]).unwrap();
+        assert!(store.get("flow-1").unwrap().is_none());
+        assert_eq!(store.get("flow-2").unwrap().unwrap().id, "flow-2");
+
+        store.set_read_cache_budget(0);
+        assert_eq!(store.read_cache_stats().entries, 0);
     }
 
     #[test]
@@ -1073,6 +1580,61 @@ mod tests {
         assert_eq!(store.count().unwrap(), 0);
     }
 
+    #[test]
+    fn test_apply_retention_by_flow_class() {
+        use crate::flow_monitor::models::{FlowError, FlowErrorType};
+
+        let temp_dir = TempDir::new().unwrap();
+        let store =
+            FlowFileStore::new(temp_dir.path().to_path_buf(), RotationConfig::default()).unwrap();
+
+        let now = Utc::now();
+        for (id, age_days, has_error) in [
+            ("recent-error", 10, true),
+            ("expired-error", 40, true),
+            ("expired-normal", 10, false),
+            ("starred-normal", 10, false),
+            ("recent-normal", 1, false),
+        ] {
+            let mut flow = create_test_flow(id, "gpt-4", ProviderType::OpenAI);
+            flow.timestamps.created = now - chrono::Duration::days(age_days);
+            if has_error {
+                flow.error = Some(FlowError::new(FlowErrorType::ServerError, "boom"));
+            }
+            store.write(&flow).unwrap();
+        }
+        store
+            .update_annotations(
+                "starred-normal",
+                &FlowAnnotations {
+                    starred: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let result = store
+            .apply_retention(&RetentionPolicy::default(), now)
+            .unwrap();
+        assert_eq!(result.flows_deleted, 2);
+        assert!(store.get("expired-error").unwrap().is_none());
+        assert!(store.get("expired-normal").unwrap().is_none());
+        for id in ["recent-error", "starred-normal", "recent-normal"] {
+            assert!(store.get(id).unwrap().is_some(), "{}", id);
+        }
+
+        // 保留天数为空时永久保留该类别
+        let keep_errors = RetentionPolicy {
+            error_days: None,
+            normal_days: Some(0),
+            ..Default::default()
+        };
+        let result = store.apply_retention(&keep_errors, now).unwrap();
+        assert_eq!(result.flows_deleted, 1);
+        assert!(store.get("recent-error").unwrap().is_some());
+        assert!(store.get("starred-normal").unwrap().is_some());
+    }
+
     #[test]
     fn test_index_record_from_flow() {
         let flow = create_test_flow("test-1", "gpt-4", ProviderType::OpenAI);
@@ -1233,7 +1795,9 @@ mod property_tests {
                 starred,
                 comment,
                 tags,
+                auto_tags: Vec::new(),
                 marker: None,
+                priority: 0,
             })
     }
 
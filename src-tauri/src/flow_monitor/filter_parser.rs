@@ -11,12 +11,14 @@
 //! - `~t`: 有工具调用
 //! - `~k`: 有思维链
 //! - `~starred`: 已收藏
+//! - `~cache`: 命中提示词缓存
 //! - `~tag <name>`: 包含标签
 //! - `~b <regex>`: 请求或响应内容匹配
 //! - `~bq <regex>`: 请求内容匹配
 //! - `~bs <regex>`: 响应内容匹配
 //! - `~tokens <op> <n>`: Token 数量比较
 //! - `~latency <op> <n>`: 延迟比较 (支持 s/ms 后缀)
+//! - `<field> ~= <regex>`: 字段正则匹配 (model/provider/content/request/response，正则在解析时编译一次)
 //! - `&`: AND 逻辑
 //! - `|`: OR 逻辑
 //! - `!`: NOT 逻辑
@@ -112,6 +114,52 @@ impl fmt::Display for ComparisonOp {
     }
 }
 
+/// 解析时编译好的正则表达式
+///
+/// 按模式字符串比较和序列化，反序列化时重新编译。
+#[derive(Debug, Clone)]
+pub struct FilterRegex(Regex);
+
+impl FilterRegex {
+    /// 编译正则表达式
+    pub fn new(pattern: &str) -> Result<Self, FilterParseError> {
+        Regex::new(pattern)
+            .map(Self)
+            .map_err(|e| FilterParseError::InvalidRegex(e.to_string()))
+    }
+
+    /// 模式字符串
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// 是否匹配
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+}
+
+impl PartialEq for FilterRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for FilterRegex {}
+
+impl Serialize for FilterRegex {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for FilterRegex {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Self::new(&pattern).map_err(serde::de::Error::custom)
+    }
+}
+
 /// 数值比较
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Comparison {
@@ -138,6 +186,47 @@ impl fmt::Display for Comparison {
     }
 }
 
+/// 支持正则匹配（`<field> ~= <regex>`）的文本字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchField {
+    /// 模型名称 (model)
+    Model,
+    /// 提供商 (provider)
+    Provider,
+    /// 请求或响应内容 (content)
+    Content,
+    /// 请求内容 (request)
+    Request,
+    /// 响应内容 (response)
+    Response,
+}
+
+impl MatchField {
+    /// 表达式中的字段名称
+    pub fn name(self) -> &'static str {
+        match self {
+            MatchField::Model => "model",
+            MatchField::Provider => "provider",
+            MatchField::Content => "content",
+            MatchField::Request => "request",
+            MatchField::Response => "response",
+        }
+    }
+
+    /// 从字段名称解析（不区分大小写）
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "model" => Some(MatchField::Model),
+            "provider" => Some(MatchField::Provider),
+            "content" => Some(MatchField::Content),
+            "request" => Some(MatchField::Request),
+            "response" => Some(MatchField::Response),
+            _ => None,
+        }
+    }
+}
+
 // ============================================================================
 // Token 类型
 // ============================================================================
@@ -160,8 +249,18 @@ pub enum FilterToken {
     HasThinking,
     /// 已收藏 (~starred)
     Starred,
+    /// 命中提示词缓存 (~cache)
+    CacheHit,
     /// 包含标签 (~tag <name>)
     Tag(String),
+    /// 标记匹配 (~marker <marker>)
+    Marker(String),
+    /// 上游请求 ID 匹配 (~reqid <id>)
+    UpstreamRequestId(String),
+    /// Provider 错误码匹配 (~errcode <code>)
+    ErrorCode(String),
+    /// Provider 错误类型匹配 (~errtype <type>)
+    ErrorType(String),
 
     // 内容搜索
     /// 请求或响应内容匹配 (~b <regex>)
@@ -170,6 +269,8 @@ pub enum FilterToken {
     BodyRequest(String),
     /// 响应内容匹配 (~bs <regex>)
     BodyResponse(String),
+    /// 字段正则匹配 (<field> ~= <regex>)
+    Regex(MatchField, FilterRegex),
 
     // 数值比较
     /// Token 数量比较 (~tokens <op> <value>)
@@ -202,10 +303,16 @@ impl fmt::Display for FilterToken {
             FilterToken::HasToolCalls => write!(f, "~t"),
             FilterToken::HasThinking => write!(f, "~k"),
             FilterToken::Starred => write!(f, "~starred"),
+            FilterToken::CacheHit => write!(f, "~cache"),
             FilterToken::Tag(s) => write!(f, "~tag {}", s),
+            FilterToken::Marker(s) => write!(f, "~marker {}", s),
+            FilterToken::UpstreamRequestId(s) => write!(f, "~reqid {}", s),
+            FilterToken::ErrorCode(s) => write!(f, "~errcode {}", s),
+            FilterToken::ErrorType(s) => write!(f, "~errtype {}", s),
             FilterToken::Body(s) => write!(f, "~b {}", s),
             FilterToken::BodyRequest(s) => write!(f, "~bq {}", s),
             FilterToken::BodyResponse(s) => write!(f, "~bs {}", s),
+            FilterToken::Regex(field, re) => write_regex_match(f, *field, re),
             FilterToken::Tokens(c) => write!(f, "~tokens {}", c),
             FilterToken::Latency(c) => write!(f, "~latency {}", c),
             FilterToken::And => write!(f, "&"),
@@ -217,6 +324,20 @@ impl fmt::Display for FilterToken {
     }
 }
 
+/// 输出 `<field> ~= "<regex>"`
+fn write_regex_match(
+    f: &mut fmt::Formatter<'_>,
+    field: MatchField,
+    re: &FilterRegex,
+) -> fmt::Result {
+    write!(
+        f,
+        "{} ~= \"{}\"",
+        field.name(),
+        re.as_str().replace('"', "\\\"")
+    )
+}
+
 /// 将 FlowState 转换为字符串
 fn state_to_string(state: &FlowState) -> &'static str {
     match state {
@@ -249,6 +370,11 @@ fn parse_state(s: &str) -> Result<FlowState, FilterParseError> {
 pub enum FilterExpr {
     /// 单个 Token
     Token(FilterToken),
+    /// 字段正则匹配
+    Regex {
+        field: MatchField,
+        pattern: FilterRegex,
+    },
     /// AND 表达式
     And(Box<FilterExpr>, Box<FilterExpr>),
     /// OR 表达式
@@ -261,6 +387,7 @@ impl fmt::Display for FilterExpr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             FilterExpr::Token(t) => write!(f, "{}", t),
+            FilterExpr::Regex { field, pattern } => write_regex_match(f, *field, pattern),
             FilterExpr::And(left, right) => write!(f, "({} & {})", left, right),
             FilterExpr::Or(left, right) => write!(f, "({} | {})", left, right),
             FilterExpr::Not(expr) => write!(f, "!{}", expr),
@@ -354,6 +481,48 @@ impl<'a> Lexer<'a> {
         Ok(word)
     }
 
+    /// 读取正则表达式参数
+    ///
+    /// 引号内只有 `\<引号>` 表示引号本身，其余反斜杠原样保留（如 `"gpt-4\.1"`）；
+    /// 不带引号时读取到空白或 `&`、`|`、`)` 为止。
+    fn read_regex_argument(&mut self) -> Result<String, FilterParseError> {
+        self.skip_whitespace();
+
+        let quote = match self.chars.peek() {
+            Some(&(_, c)) if c == '"' || c == '\'' => c,
+            _ => {
+                let mut s = String::new();
+                while let Some(&(_, c)) = self.chars.peek() {
+                    if c.is_whitespace() || matches!(c, '&' | '|' | ')') {
+                        break;
+                    }
+                    s.push(c);
+                    self.chars.next();
+                }
+                if s.is_empty() {
+                    return Err(FilterParseError::UnexpectedEof);
+                }
+                return Ok(s);
+            }
+        };
+        self.chars.next();
+
+        let mut s = String::new();
+        while let Some((pos, c)) = self.chars.next() {
+            self.pos = pos;
+            if c == quote {
+                return Ok(s);
+            }
+            if c == '\\' && matches!(self.chars.peek(), Some(&(_, next)) if next == quote) {
+                self.chars.next();
+                s.push(quote);
+            } else {
+                s.push(c);
+            }
+        }
+        Err(FilterParseError::UnexpectedEof)
+    }
+
     /// 解析比较运算符和数值
     fn parse_comparison(&mut self, filter_name: &str) -> Result<Comparison, FilterParseError> {
         self.skip_whitespace();
@@ -403,6 +572,26 @@ impl<'a> Lexer<'a> {
         Ok(Comparison { op, value })
     }
 
+    /// 解析字段正则匹配（`<field> ~= <regex>`），正则在此编译
+    fn parse_regex_match(&mut self) -> Result<FilterToken, FilterParseError> {
+        let name = self.read_word();
+        let field =
+            MatchField::from_name(&name).ok_or(FilterParseError::UnknownFilter(name.clone()))?;
+
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some(&(pos, '~')) if self.input[pos..].starts_with("~=") => {
+                self.chars.next();
+                self.chars.next();
+            }
+            Some(&(_, c)) => return Err(FilterParseError::InvalidComparisonOp(c.to_string())),
+            None => return Err(FilterParseError::MissingArgument(name)),
+        }
+
+        let pattern = self.read_regex_argument()?;
+        Ok(FilterToken::Regex(field, FilterRegex::new(&pattern)?))
+    }
+
     /// 解析带单位的数值
     fn parse_value_with_unit(&self, s: &str, filter_name: &str) -> Result<i64, FilterParseError> {
         let s = s.to_lowercase();
@@ -451,10 +640,27 @@ impl<'a> Lexer<'a> {
             "t" => Ok(FilterToken::HasToolCalls),
             "k" => Ok(FilterToken::HasThinking),
             "starred" => Ok(FilterToken::Starred),
+            "cache" => Ok(FilterToken::CacheHit),
             "tag" => {
                 let tag = self.read_argument()?;
                 Ok(FilterToken::Tag(tag))
             }
+            "marker" => {
+                let marker = self.read_argument()?;
+                Ok(FilterToken::Marker(marker))
+            }
+            "reqid" => {
+                let request_id = self.read_argument()?;
+                Ok(FilterToken::UpstreamRequestId(request_id))
+            }
+            "errcode" => {
+                let code = self.read_argument()?;
+                Ok(FilterToken::ErrorCode(code))
+            }
+            "errtype" => {
+                let error_type = self.read_argument()?;
+                Ok(FilterToken::ErrorType(error_type))
+            }
             "b" => {
                 let pattern = self.read_argument()?;
                 // 验证正则表达式
@@ -517,6 +723,7 @@ impl<'a> Lexer<'a> {
                         self.chars.next();
                         Ok(Some(FilterToken::RightParen))
                     }
+                    c if c.is_alphabetic() => self.parse_regex_match().map(Some),
                     _ => Err(FilterParseError::UnexpectedChar(c, pos)),
                 }
             }
@@ -635,10 +842,12 @@ impl Parser {
                     FilterToken::And | FilterToken::Or | FilterToken::RightParen => Err(
                         FilterParseError::UnexpectedToken(format!("{}", token), self.pos),
                     ),
-                    _ => {
-                        let token = self.advance().unwrap();
-                        Ok(FilterExpr::Token(token))
-                    }
+                    _ => match self.advance().unwrap() {
+                        FilterToken::Regex(field, pattern) => {
+                            Ok(FilterExpr::Regex { field, pattern })
+                        }
+                        token => Ok(FilterExpr::Token(token)),
+                    },
                 }
             }
             None => Err(FilterParseError::UnexpectedEof),
@@ -698,6 +907,7 @@ impl FilterParser {
     fn evaluate(expr: &FilterExpr, flow: &LLMFlow) -> bool {
         match expr {
             FilterExpr::Token(token) => Self::evaluate_token(token, flow),
+            FilterExpr::Regex { field, pattern } => Self::match_regex_field(*field, pattern, flow),
             FilterExpr::And(left, right) => {
                 Self::evaluate(left, flow) && Self::evaluate(right, flow)
             }
@@ -727,11 +937,30 @@ impl FilterParser {
                 .as_ref()
                 .map_or(false, |r| r.thinking.is_some()),
             FilterToken::Starred => flow.annotations.starred,
+            FilterToken::CacheHit => flow
+                .response
+                .as_ref()
+                .is_some_and(|r| r.usage.is_cache_hit()),
             FilterToken::Tag(tag) => flow
                 .annotations
                 .tags
                 .iter()
+                .chain(flow.annotations.auto_tags.iter())
                 .any(|t| t.to_lowercase() == tag.to_lowercase()),
+            FilterToken::Marker(marker) => flow.annotations.marker.as_ref() == Some(marker),
+            FilterToken::UpstreamRequestId(request_id) => {
+                flow.metadata.upstream_request_id.as_ref() == Some(request_id)
+            }
+            FilterToken::ErrorCode(code) => flow
+                .error
+                .as_ref()
+                .and_then(|e| e.provider_error_code.as_deref())
+                .is_some_and(|c| c.eq_ignore_ascii_case(code)),
+            FilterToken::ErrorType(error_type) => flow
+                .error
+                .as_ref()
+                .and_then(|e| e.provider_error_type.as_deref())
+                .is_some_and(|t| t.eq_ignore_ascii_case(error_type)),
             FilterToken::Body(pattern) => {
                 let request_text = Self::get_request_text(flow);
                 let response_text = flow
@@ -771,6 +1000,7 @@ impl FilterParser {
                         .contains(&pattern.to_lowercase())
                 }
             }
+            FilterToken::Regex(field, pattern) => Self::match_regex_field(*field, pattern, flow),
             FilterToken::Tokens(comparison) => {
                 let total_tokens = flow
                     .response
@@ -832,6 +1062,26 @@ impl FilterParser {
         }
     }
 
+    /// 字段正则匹配
+    fn match_regex_field(field: MatchField, pattern: &FilterRegex, flow: &LLMFlow) -> bool {
+        let response_matches = || {
+            flow.response
+                .as_ref()
+                .is_some_and(|r| pattern.is_match(&r.content))
+        };
+        match field {
+            MatchField::Model => pattern.is_match(&flow.request.model),
+            MatchField::Provider => {
+                pattern.is_match(&format!("{:?}", flow.metadata.provider).to_lowercase())
+            }
+            MatchField::Content => {
+                pattern.is_match(&Self::get_request_text(flow)) || response_matches()
+            }
+            MatchField::Request => pattern.is_match(&Self::get_request_text(flow)),
+            MatchField::Response => response_matches(),
+        }
+    }
+
     /// 获取请求文本（用于搜索）
     fn get_request_text(flow: &LLMFlow) -> String {
         let mut text = String::new();
@@ -878,18 +1128,193 @@ pub const FILTER_HELP: &[(&str, &str)] = &[
     ("~t", "有工具调用"),
     ("~k", "有思维链"),
     ("~starred", "已收藏"),
-    ("~tag <name>", "包含标签"),
+    ("~cache", "命中提示词缓存（!~cache 查找未命中的请求）"),
+    ("~tag <name>", "包含标签（含自动标签）"),
+    ("~marker <marker>", "标记匹配（emoji 需加引号，如 \"🔴\"）"),
+    (
+        "~reqid <id>",
+        "上游请求 ID 匹配（x-request-id / request-id）",
+    ),
+    (
+        "~errcode <code>",
+        "Provider 错误码匹配（如 context_length_exceeded）",
+    ),
+    (
+        "~errtype <type>",
+        "Provider 错误类型匹配（如 rate_limit_error）",
+    ),
     ("~b <regex>", "请求或响应内容匹配（正则表达式）"),
     ("~bq <regex>", "请求内容匹配（正则表达式）"),
     ("~bs <regex>", "响应内容匹配（正则表达式）"),
     ("~tokens <op> <n>", "Token 数量比较 (>, >=, <, <=, =)"),
     ("~latency <op> <n>", "延迟比较 (支持 s/ms 后缀)"),
+    (
+        "<field> ~= <regex>",
+        "字段正则匹配 (model/provider/content/request/response，(?i) 忽略大小写)",
+    ),
     ("&", "AND 逻辑"),
     ("|", "OR 逻辑"),
     ("!", "NOT 逻辑"),
     ("()", "分组"),
 ];
 
+/// 结构化的过滤字段帮助
+#[derive(Debug, Clone, Serialize)]
+pub struct FilterFieldHelp {
+    /// 过滤字段（如 `~m`）
+    pub field: &'static str,
+    /// 支持的匹配方式（`glob`、`contains`、`equals`、`regex`、`flag`）或比较运算符
+    pub operators: &'static [&'static str],
+    /// 示例表达式
+    pub example: &'static str,
+    /// 描述
+    pub description: &'static str,
+}
+
+const COMPARISON_OPERATORS: &[&str] = &[">", ">=", "<", "<=", "="];
+
+/// 过滤字段帮助（供 CLI 和脚本使用，与 `FILTER_HELP` 中的过滤器一一对应）
+pub const FILTER_FIELD_HELP: &[FilterFieldHelp] = &[
+    FilterFieldHelp {
+        field: "~m",
+        operators: &["glob", "contains"],
+        example: "~m claude*",
+        description: "模型名称匹配（支持 * 通配符，无通配符时为包含匹配）",
+    },
+    FilterFieldHelp {
+        field: "~p",
+        operators: &["contains"],
+        example: "~p kiro",
+        description: "提供商匹配",
+    },
+    FilterFieldHelp {
+        field: "~s",
+        operators: &["equals"],
+        example: "~s failed",
+        description: "状态匹配 (pending/streaming/completed/failed/cancelled)",
+    },
+    FilterFieldHelp {
+        field: "~e",
+        operators: &["flag"],
+        example: "~e",
+        description: "有错误",
+    },
+    FilterFieldHelp {
+        field: "~t",
+        operators: &["flag"],
+        example: "~t",
+        description: "有工具调用",
+    },
+    FilterFieldHelp {
+        field: "~k",
+        operators: &["flag"],
+        example: "~k",
+        description: "有思维链",
+    },
+    FilterFieldHelp {
+        field: "~starred",
+        operators: &["flag"],
+        example: "~starred",
+        description: "已收藏",
+    },
+    FilterFieldHelp {
+        field: "~cache",
+        operators: &["flag"],
+        example: "!~cache",
+        description: "命中提示词缓存",
+    },
+    FilterFieldHelp {
+        field: "~tag",
+        operators: &["equals"],
+        example: "~tag slow",
+        description: "包含标签（含自动标签，忽略大小写）",
+    },
+    FilterFieldHelp {
+        field: "~marker",
+        operators: &["equals"],
+        example: "~marker \"🔴\"",
+        description: "标记匹配",
+    },
+    FilterFieldHelp {
+        field: "~reqid",
+        operators: &["equals"],
+        example: "~reqid req_123",
+        description: "上游请求 ID 匹配",
+    },
+    FilterFieldHelp {
+        field: "~errcode",
+        operators: &["equals"],
+        example: "~errcode context_length_exceeded",
+        description: "Provider 错误码匹配（忽略大小写）",
+    },
+    FilterFieldHelp {
+        field: "~errtype",
+        operators: &["equals"],
+        example: "~errtype rate_limit_error",
+        description: "Provider 错误类型匹配（忽略大小写）",
+    },
+    FilterFieldHelp {
+        field: "~b",
+        operators: &["regex"],
+        example: "~b timeout",
+        description: "请求或响应内容匹配",
+    },
+    FilterFieldHelp {
+        field: "~bq",
+        operators: &["regex"],
+        example: "~bq weather",
+        description: "请求内容匹配",
+    },
+    FilterFieldHelp {
+        field: "~bs",
+        operators: &["regex"],
+        example: "~bs error",
+        description: "响应内容匹配",
+    },
+    FilterFieldHelp {
+        field: "~tokens",
+        operators: COMPARISON_OPERATORS,
+        example: "~tokens >1000",
+        description: "Token 数量比较",
+    },
+    FilterFieldHelp {
+        field: "~latency",
+        operators: COMPARISON_OPERATORS,
+        example: "~latency >5s",
+        description: "延迟比较 (支持 s/ms 后缀)",
+    },
+    FilterFieldHelp {
+        field: "model",
+        operators: &["~="],
+        example: "model ~= \"^claude-3-(opus|sonnet)\"",
+        description: "模型名称正则匹配",
+    },
+    FilterFieldHelp {
+        field: "provider",
+        operators: &["~="],
+        example: "provider ~= ^kiro",
+        description: "提供商正则匹配",
+    },
+    FilterFieldHelp {
+        field: "content",
+        operators: &["~="],
+        example: "content ~= \"error.*timeout\"",
+        description: "请求或响应内容正则匹配",
+    },
+    FilterFieldHelp {
+        field: "request",
+        operators: &["~="],
+        example: "request ~= \"(?i)weather\"",
+        description: "请求内容正则匹配",
+    },
+    FilterFieldHelp {
+        field: "response",
+        operators: &["~="],
+        example: "response ~= \"^Error\"",
+        description: "响应内容正则匹配",
+    },
+];
+
 /// 获取帮助文本
 pub fn get_filter_help() -> String {
     let mut help = String::from("过滤表达式语法:\n\n");
@@ -902,6 +1327,7 @@ pub fn get_filter_help() -> String {
     help.push_str("  ~e | ~latency >5s      有错误或延迟超过 5 秒\n");
     help.push_str("  !~e                    没有错误\n");
     help.push_str("  (~p kiro | ~p gemini) & ~tokens >1000\n");
+    help.push_str("  content ~= \"error.*timeout\"  内容匹配正则表达式\n");
     help
 }
 
@@ -913,8 +1339,8 @@ pub fn get_filter_help() -> String {
 mod tests {
     use super::*;
     use crate::flow_monitor::models::{
-        FlowAnnotations, FlowMetadata, FlowTimestamps, FlowType, LLMRequest, LLMResponse,
-        RequestParameters, TokenUsage,
+        FlowAnnotations, FlowError, FlowErrorType, FlowMetadata, FlowTimestamps, FlowType,
+        LLMRequest, LLMResponse, RequestParameters, TokenUsage,
     };
     use crate::ProviderType;
 
@@ -986,6 +1412,15 @@ mod tests {
         assert!(matches!(expr, FilterExpr::Token(FilterToken::Starred)));
     }
 
+    #[test]
+    fn test_parse_cache_hit_filter() {
+        let expr = FilterParser::parse("!~cache").unwrap();
+        assert_eq!(
+            expr,
+            FilterExpr::Not(Box::new(FilterExpr::Token(FilterToken::CacheHit)))
+        );
+    }
+
     #[test]
     fn test_parse_tag_filter() {
         let expr = FilterParser::parse("~tag important").unwrap();
@@ -1098,6 +1533,77 @@ mod tests {
         assert!(matches!(result, Err(FilterParseError::UnmatchedParen)));
     }
 
+    #[test]
+    fn test_parse_regex_match() {
+        let expr = FilterParser::parse(r#"model ~= "claude-3-(opus|sonnet)\d*""#).unwrap();
+        match &expr {
+            FilterExpr::Regex {
+                field: MatchField::Model,
+                pattern,
+            } => assert_eq!(pattern.as_str(), r"claude-3-(opus|sonnet)\d*"),
+            other => panic!("Expected regex match, got {:?}", other),
+        }
+        assert_eq!(expr.to_string(), r#"model ~= "claude-3-(opus|sonnet)\d*""#);
+        assert_eq!(FilterParser::parse(&expr.to_string()).unwrap(), expr);
+
+        // 未加引号的模式和组合表达式
+        let expr = FilterParser::parse("content ~= error.*timeout & ~e").unwrap();
+        assert!(matches!(
+            expr,
+            FilterExpr::And(ref left, _)
+                if matches!(**left, FilterExpr::Regex { field: MatchField::Content, .. })
+        ));
+        let expr = FilterParser::parse("(Response ~= ^Error|~e)").unwrap();
+        assert!(matches!(
+            expr,
+            FilterExpr::Or(ref left, _)
+                if matches!(**left, FilterExpr::Regex { field: MatchField::Response, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_error_invalid_regex_match() {
+        for input in [r#"model ~= "claude-(3""#, r#"response ~= "[a-z""#] {
+            let result = FilterParser::parse(input);
+            assert!(
+                matches!(result, Err(FilterParseError::InvalidRegex(_))),
+                "{} 应解析失败: {:?}",
+                input,
+                result
+            );
+        }
+        assert!(matches!(
+            FilterParser::parse("provider ~="),
+            Err(FilterParseError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            FilterParser::parse("provider"),
+            Err(FilterParseError::MissingArgument(_))
+        ));
+        assert!(matches!(
+            FilterParser::parse("model = claude"),
+            Err(FilterParseError::InvalidComparisonOp(_))
+        ));
+        assert!(matches!(
+            FilterParser::parse("latency ~= 100"),
+            Err(FilterParseError::UnknownFilter(_))
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_regex_match() {
+        let flow = create_test_flow("claude-3-opus", ProviderType::Kiro);
+        let matches =
+            |input: &str| FilterParser::compile(&FilterParser::parse(input).unwrap())(&flow);
+
+        assert!(matches(r#"model ~= "^claude-3-(opus|sonnet)$""#));
+        assert!(!matches(r#"model ~= "^claude-3-haiku""#));
+        assert!(!matches(r#"model ~= "^CLAUDE""#));
+        assert!(matches(r#"model ~= "(?i)^CLAUDE""#));
+        assert!(matches("provider ~= ^kiro"));
+        assert!(!matches("!provider ~= ^kiro"));
+    }
+
     #[test]
     fn test_evaluate_model_filter() {
         let flow = create_test_flow("claude-3-opus", ProviderType::Kiro);
@@ -1162,6 +1668,53 @@ mod tests {
         assert!(!filter(&flow));
     }
 
+    #[test]
+    fn test_evaluate_marker_and_tag_intersection() {
+        let mut flow = create_test_flow("claude-3", ProviderType::Kiro);
+        flow.annotations.tags = vec!["bug".to_string()];
+        flow.annotations.marker = Some("🔴".to_string());
+
+        let expr = FilterParser::parse("~marker \"🔴\" & ~tag bug & ~tag prod").unwrap();
+        let filter = FilterParser::compile(&expr);
+        assert!(!filter(&flow));
+
+        flow.annotations.tags.push("prod".to_string());
+        assert!(filter(&flow));
+
+        flow.annotations.marker = Some("🟢".to_string());
+        assert!(!filter(&flow));
+    }
+
+    #[test]
+    fn test_evaluate_upstream_request_id() {
+        let mut flow = create_test_flow("claude-3", ProviderType::Claude);
+        let expr = FilterParser::parse("~reqid req_011CabcXYZ").unwrap();
+        let filter = FilterParser::compile(&expr);
+        assert!(!filter(&flow));
+
+        flow.metadata.upstream_request_id = Some("req_011CabcXYZ".to_string());
+        assert!(filter(&flow));
+    }
+
+    #[test]
+    fn test_evaluate_provider_error_filters() {
+        let mut flow = create_test_flow("gpt-4", ProviderType::OpenAI);
+        let expr = FilterParser::parse("~errcode context_length_exceeded").unwrap();
+        let filter = FilterParser::compile(&expr);
+        assert!(!filter(&flow));
+
+        let body = r#"{"error":{"message":"too long","type":"invalid_request_error","param":"messages","code":"context_length_exceeded"}}"#;
+        flow.error = Some(
+            FlowError::new(FlowErrorType::BadRequest, body)
+                .with_status_code(400)
+                .with_provider_body(body),
+        );
+        assert!(filter(&flow));
+
+        let expr = FilterParser::parse("~errtype invalid_request_error & ~errcode other").unwrap();
+        assert!(!FilterParser::compile(&expr)(&flow));
+    }
+
     #[test]
     fn test_evaluate_tokens_filter() {
         let mut flow = create_test_flow("claude-3", ProviderType::Kiro);
@@ -1184,6 +1737,23 @@ mod tests {
         assert!(!filter(&flow));
     }
 
+    #[test]
+    fn test_evaluate_cache_hit_filter() {
+        let mut flow = create_test_flow("claude-3", ProviderType::Claude);
+        let filter = FilterParser::compile(&FilterParser::parse("~cache").unwrap());
+        assert!(!filter(&flow));
+
+        flow.response = Some(LLMResponse {
+            usage: TokenUsage {
+                input_tokens: 1000,
+                cache_read_tokens: Some(800),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert!(filter(&flow));
+    }
+
     #[test]
     fn test_evaluate_latency_filter() {
         let mut flow = create_test_flow("claude-3", ProviderType::Kiro);
@@ -1249,6 +1819,26 @@ mod tests {
         let reparsed = FilterParser::parse(&display).unwrap();
         assert_eq!(format!("{}", expr), format!("{}", reparsed));
     }
+
+    #[test]
+    fn test_filter_field_help_covers_filters() {
+        for help in FILTER_FIELD_HELP {
+            assert!(
+                FilterParser::validate(help.example).is_ok(),
+                "示例无法解析: {}",
+                help.example
+            );
+            assert!(!help.operators.is_empty());
+        }
+        for (syntax, _) in FILTER_HELP.iter().filter(|(s, _)| s.starts_with('~')) {
+            let field = syntax.split_whitespace().next().unwrap();
+            assert!(
+                FILTER_FIELD_HELP.iter().any(|h| h.field == field),
+                "缺少结构化帮助: {}",
+                field
+            );
+        }
+    }
 }
 
 // ============================================================================
@@ -1384,6 +1974,7 @@ mod property_tests {
                                 name: "test_function".to_string(),
                                 arguments: "{}".to_string(),
                             },
+                            arguments_truncated: false,
                         }];
                     }
 
@@ -1393,6 +1984,7 @@ mod property_tests {
                             text: "Thinking...".to_string(),
                             tokens: Some(100),
                             signature: None,
+                            inline_segments: Vec::new(),
                         });
                     }
 
@@ -1691,7 +2283,12 @@ mod property_tests {
             Just(FilterToken::HasToolCalls),
             Just(FilterToken::HasThinking),
             Just(FilterToken::Starred),
+            Just(FilterToken::CacheHit),
             "[a-z]{3,8}".prop_map(FilterToken::Tag),
+            "[a-z]{3,8}".prop_map(FilterToken::Marker),
+            "req_[a-z0-9]{6,12}".prop_map(FilterToken::UpstreamRequestId),
+            "[a-z_]{3,12}".prop_map(FilterToken::ErrorCode),
+            "[a-z_]{3,12}".prop_map(FilterToken::ErrorType),
             arb_comparison().prop_map(FilterToken::Tokens),
             arb_comparison().prop_map(FilterToken::Latency),
         ]
@@ -1800,8 +2397,8 @@ mod property_tests {
             Just("bar".to_string()),
             "[a-z]{5,10}".prop_filter("Filter out valid names", |s| {
                 ![
-                    "m", "p", "s", "e", "t", "k", "b", "bq", "bs", "starred", "tag", "tokens",
-                    "latency",
+                    "m", "p", "s", "e", "t", "k", "b", "bq", "bs", "starred", "cache", "tag",
+                    "tokens", "latency",
                 ]
                 .contains(&s.as_str())
             }),
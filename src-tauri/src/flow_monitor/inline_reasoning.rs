@@ -0,0 +1,228 @@
+//! 内联推理提取
+//!
+//! 部分模型不使用独立的思维链字段，而是把推理过程写在正文里（如 `<think>...</think>`）。
+//! 启用后按配置的标签把这些片段从正文移到 `thinking`，正文只保留最终回答，便于展示和统计。
+//!
+//! 提取是可还原的：每个被移除的片段都按原样记录在 `ThinkingContent::inline_segments` 中，
+//! 连同它在提取后正文中的位置，导出时可以据此拼回原始正文（见 [`restore_inline_reasoning`]）。
+//! 上游已返回独立思维链的响应不做提取。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::models::{InlineReasoningSegment, LLMResponse, ThinkingContent};
+
+/// 内联推理提取配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InlineReasoningConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 推理片段的标签名（`think` 对应 `<think>...</think>`）
+    #[serde(default = "default_tags")]
+    pub tags: Vec<String>,
+    /// 按 Provider 的标签名，配置后替代该 Provider 的 `tags`
+    #[serde(default)]
+    pub provider_tags: HashMap<String, Vec<String>>,
+}
+
+fn default_tags() -> Vec<String> {
+    vec![
+        "think".to_string(),
+        "thinking".to_string(),
+        "reasoning".to_string(),
+    ]
+}
+
+impl Default for InlineReasoningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tags: default_tags(),
+            provider_tags: HashMap::new(),
+        }
+    }
+}
+
+impl InlineReasoningConfig {
+    /// 某个 Provider 使用的标签名
+    pub fn tags_for(&self, provider: &str) -> &[String] {
+        self.provider_tags
+            .get(provider)
+            .map_or(self.tags.as_slice(), Vec::as_slice)
+    }
+}
+
+/// 把响应正文中的内联推理片段提取到 `thinking`
+///
+/// # 返回
+/// 是否提取到推理片段（未启用、已有思维链或没有匹配的片段时为 false）
+pub fn extract_inline_reasoning(
+    config: &InlineReasoningConfig,
+    provider: &str,
+    response: &mut LLMResponse,
+) -> bool {
+    if !config.enabled || response.thinking.is_some() {
+        return false;
+    }
+
+    let delimiters: Vec<(String, String)> = config
+        .tags_for(provider)
+        .iter()
+        .map(|tag| tag.trim())
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| (format!("<{}>", tag), format!("</{}>", tag)))
+        .collect();
+    if delimiters.is_empty() {
+        return false;
+    }
+
+    let content = &response.content;
+    let mut cleaned = String::with_capacity(content.len());
+    let mut reasoning = Vec::new();
+    let mut segments = Vec::new();
+    let mut rest = content.as_str();
+
+    // 每次取最靠前的起始标签；找不到对应的结束标签时停止（未闭合的片段保留在正文中）
+    while let Some((start, open, close)) = delimiters
+        .iter()
+        .filter_map(|(open, close)| rest.find(open.as_str()).map(|i| (i, open, close)))
+        .min_by_key(|(i, _, _)| *i)
+    {
+        let inner_start = start + open.len();
+        let Some(close_at) = rest[inner_start..].find(close.as_str()) else {
+            break;
+        };
+        let inner_end = inner_start + close_at;
+        let after_close = inner_end + close.len();
+        // 一并移除片段后的空白，避免正文开头留下空行
+        let end =
+            after_close + (rest[after_close..].len() - rest[after_close..].trim_start().len());
+
+        cleaned.push_str(&rest[..start]);
+        segments.push(InlineReasoningSegment {
+            offset: cleaned.len(),
+            raw: rest[start..end].to_string(),
+        });
+        let text = rest[inner_start..inner_end].trim();
+        if !text.is_empty() {
+            reasoning.push(text.to_string());
+        }
+        rest = &rest[end..];
+    }
+
+    if segments.is_empty() {
+        return false;
+    }
+    cleaned.push_str(rest);
+
+    response.content = cleaned;
+    response.thinking = Some(ThinkingContent {
+        text: reasoning.join("\n\n"),
+        tokens: None,
+        signature: None,
+        inline_segments: segments,
+    });
+    true
+}
+
+/// 把提取出的内联推理片段放回正文，还原上游返回的原始内容
+///
+/// # 返回
+/// 是否还原（没有内联片段，或正文已被修改导致位置无效时为 false）
+pub fn restore_inline_reasoning(response: &mut LLMResponse) -> bool {
+    let Some(segments) = response
+        .thinking
+        .as_ref()
+        .map(|thinking| &thinking.inline_segments)
+        .filter(|segments| !segments.is_empty())
+    else {
+        return false;
+    };
+
+    let content = &response.content;
+    let mut restored =
+        String::with_capacity(content.len() + segments.iter().map(|s| s.raw.len()).sum::<usize>());
+    let mut pos = 0;
+    for segment in segments {
+        let Some(before) = content.get(pos..segment.offset) else {
+            return false;
+        };
+        restored.push_str(before);
+        restored.push_str(&segment.raw);
+        pos = segment.offset;
+    }
+    restored.push_str(&content[pos..]);
+
+    response.content = restored;
+    response.thinking = None;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(content: &str) -> LLMResponse {
+        LLMResponse {
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn enabled() -> InlineReasoningConfig {
+        InlineReasoningConfig {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_extract_and_restore_tagged_reasoning() {
+        let original = "<think>\nFirst, add 2 and 2.\n</think>\n\nThe answer is 4. <reasoning>double-check</reasoning> Done.";
+        let mut resp = response(original);
+
+        assert!(extract_inline_reasoning(&enabled(), "openai", &mut resp));
+        assert_eq!(resp.content, "The answer is 4. Done.");
+        let thinking = resp.thinking.as_ref().unwrap();
+        assert_eq!(thinking.text, "First, add 2 and 2.\n\ndouble-check");
+        assert_eq!(thinking.inline_segments.len(), 2);
+
+        assert!(restore_inline_reasoning(&mut resp));
+        assert_eq!(resp.content, original);
+        assert!(resp.thinking.is_none());
+    }
+
+    #[test]
+    fn test_no_reasoning_present() {
+        let mut resp = response("Plain answer with a <b>tag</b> and an unclosed <think> marker.");
+        assert!(!extract_inline_reasoning(&enabled(), "openai", &mut resp));
+        assert_eq!(
+            resp.content,
+            "Plain answer with a <b>tag</b> and an unclosed <think> marker."
+        );
+        assert!(resp.thinking.is_none());
+        assert!(!restore_inline_reasoning(&mut resp));
+
+        // 未启用时不提取
+        let mut resp = response("<think>x</think>answer");
+        assert!(!extract_inline_reasoning(
+            &InlineReasoningConfig::default(),
+            "openai",
+            &mut resp
+        ));
+    }
+
+    #[test]
+    fn test_provider_specific_tags() {
+        let config = InlineReasoningConfig {
+            provider_tags: HashMap::from([("qwen".to_string(), vec!["scratchpad".to_string()])]),
+            ..enabled()
+        };
+        let mut resp = response("<think>ignored</think><scratchpad>plan</scratchpad>answer");
+
+        assert!(extract_inline_reasoning(&config, "qwen", &mut resp));
+        assert_eq!(resp.content, "<think>ignored</think>answer");
+        assert_eq!(resp.thinking.unwrap().text, "plan");
+    }
+}
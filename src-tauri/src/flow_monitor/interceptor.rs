@@ -646,6 +646,7 @@ mod tests {
             parameters: RequestParameters::default(),
             size_bytes: 0,
             timestamp: Utc::now(),
+            attachments: Vec::new(),
         }
     }
 
@@ -661,10 +662,16 @@ mod tests {
             tool_calls: Vec::new(),
             usage: TokenUsage::default(),
             stop_reason: None,
+            raw_stop_reason: None,
             size_bytes: 0,
             timestamp_start: Utc::now(),
             timestamp_end: Utc::now(),
             stream_info: None,
+            schema_valid: None,
+            logprobs: None,
+            body_info: None,
+            system_fingerprint: None,
+            turns: Vec::new(),
         }
     }
 
@@ -1134,6 +1141,7 @@ mod property_tests {
                                 name: "test_function".to_string(),
                                 arguments: "{}".to_string(),
                             },
+                            arguments_truncated: false,
                         }];
                     }
 
@@ -1142,6 +1150,7 @@ mod property_tests {
                             text: "Thinking...".to_string(),
                             tokens: Some(100),
                             signature: None,
+                            inline_segments: Vec::new(),
                         });
                     }
 
@@ -0,0 +1,229 @@
+//! 导出脱敏用的 JSONPath 子集
+//!
+//! 敏感数据常藏在嵌套的请求体里（如 `messages[*].content` 中粘贴的 API Key），只按
+//! 请求头或正则匹配难以覆盖。脱敏规则可以用 JSONPath 指定请求体 / 响应体中的位置，
+//! 匹配到的标量被替换为占位文本，对象和数组的结构保持不变。
+//!
+//! 支持的语法：
+//!
+//! - `$`：根节点（必须位于开头）
+//! - `.name` / `['name']` / `["name"]`：对象字段
+//! - `[0]`：数组下标
+//! - `.*` / `[*]`：对象的所有字段或数组的所有元素
+//! - `..name` / `..*`：递归匹配任意深度的字段
+
+use serde_json::Value;
+
+/// JSONPath 无效
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("无效的 JSONPath `{path}`: {reason}")]
+pub struct JsonPathError {
+    /// 原始路径
+    pub path: String,
+    /// 错误原因
+    pub reason: String,
+}
+
+/// 路径段
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// 对象字段
+    Key(String),
+    /// 数组下标
+    Index(usize),
+    /// 所有字段或元素
+    Wildcard,
+    /// 任意深度的字段（`None` 表示任意字段或元素）
+    Descendant(Option<String>),
+}
+
+/// 已解析的 JSONPath
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    /// 解析 JSONPath
+    pub fn parse(path: &str) -> Result<Self, JsonPathError> {
+        let error = |reason: &str| JsonPathError {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let rest = path.trim();
+        let mut rest = rest
+            .strip_prefix('$')
+            .ok_or_else(|| error("必须以 `$` 开头"))?;
+        let mut segments = Vec::new();
+
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("..") {
+                let (name, remaining) = take_name(after);
+                match name {
+                    "" => return Err(error("`..` 后缺少字段名")),
+                    "*" => segments.push(Segment::Descendant(None)),
+                    name => segments.push(Segment::Descendant(Some(name.to_string()))),
+                }
+                rest = remaining;
+            } else if let Some(after) = rest.strip_prefix('.') {
+                let (name, remaining) = take_name(after);
+                match name {
+                    "" => return Err(error("`.` 后缺少字段名")),
+                    "*" => segments.push(Segment::Wildcard),
+                    name => segments.push(Segment::Key(name.to_string())),
+                }
+                rest = remaining;
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| error("缺少 `]`"))?;
+                let inner = after[..end].trim();
+                segments.push(parse_bracket(inner).ok_or_else(|| error("无效的下标"))?);
+                rest = &after[end + 1..];
+            } else {
+                return Err(error("路径段必须以 `.` 或 `[` 开头"));
+            }
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// 把匹配到的标量替换为 `replacement`，返回被改写的标量数量
+    ///
+    /// 匹配到对象或数组时替换其中的全部标量；`null` 不含敏感数据，保持不变。
+    pub fn mask(&self, value: &mut Value, replacement: &str) -> usize {
+        mask_at(value, &self.segments, replacement)
+    }
+}
+
+/// 读取 `.` 之后的字段名，到下一个 `.` 或 `[` 为止
+fn take_name(s: &str) -> (&str, &str) {
+    let end = s.find(['.', '[']).unwrap_or(s.len());
+    (&s[..end], &s[end..])
+}
+
+fn parse_bracket(inner: &str) -> Option<Segment> {
+    if inner == "*" {
+        return Some(Segment::Wildcard);
+    }
+    if let Ok(index) = inner.parse::<usize>() {
+        return Some(Segment::Index(index));
+    }
+    let quoted = inner
+        .strip_prefix('\'')
+        .and_then(|s| s.strip_suffix('\''))
+        .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')))?;
+    Some(Segment::Key(quoted.to_string()))
+}
+
+fn mask_at(value: &mut Value, segments: &[Segment], replacement: &str) -> usize {
+    let Some((segment, rest)) = segments.split_first() else {
+        return mask_all(value, replacement);
+    };
+
+    match segment {
+        Segment::Key(key) => value
+            .get_mut(key.as_str())
+            .map_or(0, |child| mask_at(child, rest, replacement)),
+        Segment::Index(index) => value
+            .get_mut(*index)
+            .map_or(0, |child| mask_at(child, rest, replacement)),
+        Segment::Wildcard => children(value)
+            .map(|child| mask_at(child, rest, replacement))
+            .sum(),
+        Segment::Descendant(name) => {
+            // 先处理当前节点的直接子节点，再向下递归（已替换的标量不会重复计数）
+            let direct = match name {
+                Some(key) => value
+                    .get_mut(key.as_str())
+                    .map_or(0, |child| mask_at(child, rest, replacement)),
+                None => children(value)
+                    .map(|child| mask_at(child, rest, replacement))
+                    .sum(),
+            };
+            direct
+                + children(value)
+                    .map(|child| mask_at(child, segments, replacement))
+                    .sum::<usize>()
+        }
+    }
+}
+
+/// 对象的所有字段值或数组的所有元素
+fn children(value: &mut Value) -> Box<dyn Iterator<Item = &mut Value> + '_> {
+    match value {
+        Value::Object(map) => Box::new(map.values_mut()),
+        Value::Array(items) => Box::new(items.iter_mut()),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
+/// 替换节点中的全部标量
+fn mask_all(value: &mut Value, replacement: &str) -> usize {
+    match &*value {
+        Value::Null => 0,
+        Value::String(s) if s == replacement => 0,
+        Value::Object(_) | Value::Array(_) => children(value)
+            .map(|child| mask_all(child, replacement))
+            .sum(),
+        _ => {
+            *value = Value::String(replacement.to_string());
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_rejects_invalid_paths() {
+        assert!(JsonPath::parse("messages").is_err());
+        assert!(JsonPath::parse("$.").is_err());
+        assert!(JsonPath::parse("$[abc]").is_err());
+        assert!(JsonPath::parse("$.messages[0").is_err());
+        assert!(JsonPath::parse("$.messages[*]['content']").is_ok());
+    }
+
+    #[test]
+    fn test_mask_arrays_wildcards_and_descendants() {
+        let mut value = json!({
+            "api_key": "sk-secret",
+            "messages": [
+                {"role": "user", "content": "hi", "meta": {"token": "t1"}},
+                {"role": "assistant", "content": [{"type": "text", "text": "ok"}]}
+            ],
+            "n": 3,
+            "stop": null
+        });
+
+        // 匹配对象或数组时替换其中的所有标量
+        let path = JsonPath::parse("$.messages[1].content").unwrap();
+        assert_eq!(path.mask(&mut value, "X"), 2);
+        assert_eq!(
+            value["messages"][1]["content"],
+            json!([{"type": "X", "text": "X"}])
+        );
+
+        let path = JsonPath::parse("$..token").unwrap();
+        assert_eq!(path.mask(&mut value, "X"), 1);
+        assert_eq!(value["messages"][0]["meta"]["token"], "X");
+
+        let path = JsonPath::parse("$['api_key']").unwrap();
+        assert_eq!(path.mask(&mut value, "X"), 1);
+
+        // null 保持不变，不存在的路径不匹配
+        let path = JsonPath::parse("$.*").unwrap();
+        path.mask(&mut value, "X");
+        assert_eq!(value["n"], "X");
+        assert_eq!(value["stop"], Value::Null);
+        assert_eq!(value["messages"][0]["role"], "X");
+        assert_eq!(
+            JsonPath::parse("$.missing[0]")
+                .unwrap()
+                .mask(&mut value, "X"),
+            0
+        );
+    }
+}
@@ -1,9 +1,9 @@
 //! Flow 内存存储
 //!
-//! 该模块实现 LLM Flow 的内存缓存存储，支持 LRU 驱逐策略。
-//! 提供快速的 Flow 访问和查询功能。
+//! 该模块实现 LLM Flow 的内存缓存存储，支持 LRU 驱逐策略，并可按创建时间驱逐过期的 Flow。
+//! 提供快速的 Flow 访问和查询功能，并支持快照/恢复以便跨进程重启保留会话。
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, RwLock};
@@ -74,6 +74,17 @@ impl LatencyRange {
     }
 }
 
+/// 标签匹配模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatchMode {
+    /// 包含任一标签即匹配
+    #[default]
+    Any,
+    /// 必须包含全部标签
+    All,
+}
+
 /// Flow 过滤器
 ///
 /// 支持多维度过滤条件，用于查询 Flow。
@@ -103,6 +114,12 @@ pub struct FlowFilter {
     /// 是否是流式响应
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_streaming: Option<bool>,
+    /// 结构化输出是否符合 JSON Schema（仅匹配带 Schema 的请求）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_valid: Option<bool>,
+    /// 是否命中提示词缓存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_hit: Option<bool>,
     /// 内容搜索（响应内容）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content_search: Option<String>,
@@ -118,12 +135,27 @@ pub struct FlowFilter {
     /// 标签列表
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
+    /// 标签匹配模式（任一 / 全部）
+    #[serde(default)]
+    pub tag_match: TagMatchMode,
     /// 仅收藏
     #[serde(default)]
     pub starred_only: bool,
+    /// 收藏状态
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starred: Option<bool>,
+    /// 是否有评论
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_comment: Option<bool>,
+    /// 标记（精确匹配）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marker: Option<String>,
     /// 凭证 ID
     #[serde(skip_serializing_if = "Option::is_none")]
     pub credential_id: Option<String>,
+    /// 上游请求 ID（精确匹配）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_request_id: Option<String>,
     /// Flow 类型
     #[serde(skip_serializing_if = "Option::is_none")]
     pub flow_types: Option<Vec<FlowType>>,
@@ -206,6 +238,25 @@ impl FlowFilter {
             }
         }
 
+        // 结构化输出校验结果过滤
+        if let Some(schema_valid) = self.schema_valid {
+            let flow_schema_valid = flow.response.as_ref().and_then(|r| r.schema_valid);
+            if flow_schema_valid != Some(schema_valid) {
+                return false;
+            }
+        }
+
+        // 提示词缓存命中过滤
+        if let Some(cache_hit) = self.cache_hit {
+            let flow_cache_hit = flow
+                .response
+                .as_ref()
+                .is_some_and(|r| r.usage.is_cache_hit());
+            if cache_hit != flow_cache_hit {
+                return false;
+            }
+        }
+
         // 内容搜索（搜索响应内容、模型名称、提供商名称）
         if let Some(ref search) = self.content_search {
             let search_lower = search.to_lowercase();
@@ -255,8 +306,11 @@ impl FlowFilter {
 
         // 标签过滤
         if let Some(ref tags) = self.tags {
-            let has_any_tag = tags.iter().any(|t| flow.annotations.tags.contains(t));
-            if !has_any_tag {
+            let tag_matched = match self.tag_match {
+                TagMatchMode::Any => tags.iter().any(|t| flow.annotations.tags.contains(t)),
+                TagMatchMode::All => tags.iter().all(|t| flow.annotations.tags.contains(t)),
+            };
+            if !tag_matched {
                 return false;
             }
         }
@@ -265,6 +319,30 @@ impl FlowFilter {
         if self.starred_only && !flow.annotations.starred {
             return false;
         }
+        if let Some(starred) = self.starred {
+            if flow.annotations.starred != starred {
+                return false;
+            }
+        }
+
+        // 评论过滤
+        if let Some(has_comment) = self.has_comment {
+            let flow_has_comment = flow
+                .annotations
+                .comment
+                .as_ref()
+                .is_some_and(|c| !c.trim().is_empty());
+            if flow_has_comment != has_comment {
+                return false;
+            }
+        }
+
+        // 标记过滤
+        if let Some(ref marker) = self.marker {
+            if flow.annotations.marker.as_ref() != Some(marker) {
+                return false;
+            }
+        }
 
         // 凭证 ID 过滤
         if let Some(ref credential_id) = self.credential_id {
@@ -273,6 +351,13 @@ impl FlowFilter {
             }
         }
 
+        // 上游请求 ID 过滤
+        if let Some(ref request_id) = self.upstream_request_id {
+            if flow.metadata.upstream_request_id.as_deref() != Some(request_id.trim()) {
+                return false;
+            }
+        }
+
         // Flow 类型过滤
         if let Some(ref flow_types) = self.flow_types {
             if !flow_types.contains(&flow.flow_type) {
@@ -284,7 +369,7 @@ impl FlowFilter {
     }
 
     /// 模式匹配（支持 * 通配符）
-    fn match_pattern(pattern: &str, text: &str) -> bool {
+    pub(crate) fn match_pattern(pattern: &str, text: &str) -> bool {
         if pattern == "*" {
             return true;
         }
@@ -347,6 +432,18 @@ impl FlowFilter {
 // 内存存储
 // ============================================================================
 
+/// 内存快照格式版本
+pub const MEMORY_SNAPSHOT_VERSION: u32 = 1;
+
+/// 内存快照（JSON）
+#[derive(Serialize, Deserialize)]
+struct MemorySnapshot {
+    /// 格式版本
+    version: u32,
+    /// Flow 列表（按 LRU 顺序，从最旧到最新）
+    flows: Vec<LLMFlow>,
+}
+
 /// Flow 内存存储
 ///
 /// 使用 LRU 策略管理内存中的 Flow 缓存。
@@ -358,6 +455,8 @@ pub struct FlowMemoryStore {
     ordered_ids: VecDeque<String>,
     /// 最大缓存大小
     max_size: usize,
+    /// 最长保留时间（按创建时间，为空时只按数量驱逐）
+    max_age: Option<Duration>,
 }
 
 impl FlowMemoryStore {
@@ -370,9 +469,26 @@ impl FlowMemoryStore {
             flows: HashMap::with_capacity(max_size),
             ordered_ids: VecDeque::with_capacity(max_size),
             max_size,
+            max_age: None,
         }
     }
 
+    /// 设置最长保留时间
+    pub fn with_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// 获取最长保留时间
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    /// 更新最长保留时间（下次添加 Flow 时生效）
+    pub fn set_max_age(&mut self, max_age: Option<Duration>) {
+        self.max_age = max_age;
+    }
+
     /// 获取当前缓存大小
     pub fn len(&self) -> usize {
         self.flows.len()
@@ -390,7 +506,8 @@ impl FlowMemoryStore {
 
     /// 添加 Flow 到缓存
     ///
-    /// 如果缓存已满，会驱逐最旧的 Flow。
+    /// 先驱逐超过最长保留时间的 Flow（新 Flow 本身已过期时不再加入）；
+    /// 如果缓存仍然已满，再驱逐最旧的 Flow。
     pub fn add(&mut self, flow: LLMFlow) {
         let id = flow.id.clone();
 
@@ -399,6 +516,13 @@ impl FlowMemoryStore {
             self.ordered_ids.retain(|i| i != &id);
         }
 
+        let now = Utc::now();
+        self.evict_expired(now);
+        if self.is_expired(&flow, now) {
+            self.flows.remove(&id);
+            return;
+        }
+
         // 检查是否需要驱逐
         while self.flows.len() >= self.max_size {
             self.evict_oldest();
@@ -506,6 +630,47 @@ impl FlowMemoryStore {
         self.ordered_ids.clear();
     }
 
+    /// 驱逐创建时间早于 `now - max_age` 的 Flow
+    ///
+    /// 进行中的 Flow（等待响应或流式传输中）不会被驱逐，以免丢失后续更新。
+    ///
+    /// # 返回
+    /// 驱逐的 Flow 数量
+    pub fn evict_expired(&mut self, now: DateTime<Utc>) -> usize {
+        if self.max_age.is_none() {
+            return 0;
+        }
+
+        let expired: Vec<String> = self
+            .ordered_ids
+            .iter()
+            .filter(|id| {
+                self.flows
+                    .get(*id)
+                    .and_then(|flow_lock| flow_lock.read().ok())
+                    .is_some_and(|flow| self.is_expired(&flow, now))
+            })
+            .cloned()
+            .collect();
+        if expired.is_empty() {
+            return 0;
+        }
+
+        for id in &expired {
+            self.flows.remove(id);
+        }
+        self.ordered_ids.retain(|id| self.flows.contains_key(id));
+        expired.len()
+    }
+
+    /// Flow 是否已过期（进行中的 Flow 不算过期）
+    fn is_expired(&self, flow: &LLMFlow, now: DateTime<Utc>) -> bool {
+        self.max_age.is_some_and(|max_age| {
+            flow.timestamps.created < now - max_age
+                && !matches!(flow.state, FlowState::Pending | FlowState::Streaming)
+        })
+    }
+
     /// 驱逐最旧的 Flow
     fn evict_oldest(&mut self) {
         if let Some(oldest_id) = self.ordered_ids.pop_front() {
@@ -522,6 +687,45 @@ impl FlowMemoryStore {
     pub fn contains(&self, id: &str) -> bool {
         self.flows.contains_key(id)
     }
+
+    /// 生成快照
+    ///
+    /// 快照为紧凑 JSON，按 LRU 顺序保存完整 Flow（含标注）。
+    pub fn snapshot(&self) -> Vec<u8> {
+        let flows = self
+            .ordered_ids
+            .iter()
+            .filter_map(|id| self.flows.get(id))
+            .filter_map(|flow_lock| flow_lock.read().ok().map(|flow| flow.clone()))
+            .collect();
+        let snapshot = MemorySnapshot {
+            version: MEMORY_SNAPSHOT_VERSION,
+            flows,
+        };
+        serde_json::to_vec(&snapshot).unwrap_or_default()
+    }
+
+    /// 从快照恢复
+    ///
+    /// 替换当前所有 Flow 并保持快照中的 LRU 顺序；快照超过容量时只保留最新的部分。
+    ///
+    /// # 返回
+    /// 恢复后的 Flow 数量
+    pub fn restore(&mut self, data: &[u8]) -> Result<usize, serde_json::Error> {
+        let snapshot: MemorySnapshot = serde_json::from_slice(data)?;
+        if snapshot.version > MEMORY_SNAPSHOT_VERSION {
+            return Err(serde::de::Error::custom(format!(
+                "不支持的快照版本 {}",
+                snapshot.version
+            )));
+        }
+
+        self.clear();
+        for flow in snapshot.flows {
+            self.add(flow);
+        }
+        Ok(self.len())
+    }
 }
 
 // ============================================================================
@@ -593,6 +797,40 @@ mod tests {
         assert!(store.contains("flow-4"));
     }
 
+    #[test]
+    fn test_memory_store_ttl_eviction() {
+        let mut store = FlowMemoryStore::new(10).with_max_age(Some(Duration::minutes(30)));
+
+        let mut expired = create_test_flow("expired", "gpt-4", ProviderType::OpenAI);
+        expired.state = FlowState::Completed;
+        expired.timestamps.created = Utc::now() - Duration::hours(2);
+        store.add(expired);
+        // 新添加的过期 Flow 会在本次添加时被立即驱逐
+        assert!(!store.contains("expired"));
+
+        let mut pending = create_test_flow("pending", "gpt-4", ProviderType::OpenAI);
+        pending.timestamps.created = Utc::now() - Duration::hours(2);
+        store.add(pending);
+
+        let mut fresh = create_test_flow("fresh", "gpt-4", ProviderType::OpenAI);
+        fresh.state = FlowState::Completed;
+        store.add(fresh);
+
+        // 进行中的 Flow 即使过期也保留
+        assert!(store.contains("pending"));
+        assert!(store.contains("fresh"));
+
+        store.update("pending", |f| f.state = FlowState::Completed);
+        assert_eq!(store.evict_expired(Utc::now()), 1);
+        assert!(!store.contains("pending"));
+        assert_eq!(store.len(), 1);
+
+        // 关闭 TTL 后不再按时间驱逐
+        store.set_max_age(None);
+        assert_eq!(store.evict_expired(Utc::now() + Duration::days(1)), 0);
+        assert!(store.contains("fresh"));
+    }
+
     #[test]
     fn test_memory_store_update() {
         let mut store = FlowMemoryStore::new(10);
@@ -644,6 +882,44 @@ mod tests {
         assert!(!store.remove("flow-999"));
     }
 
+    #[test]
+    fn test_memory_store_snapshot_restore() {
+        let mut store = FlowMemoryStore::new(10);
+        store.add(create_test_flow("flow-1", "gpt-4", ProviderType::OpenAI));
+        store.add(create_test_flow("flow-2", "claude-3", ProviderType::Claude));
+        store.add(create_test_flow("flow-3", "gpt-4", ProviderType::OpenAI));
+        store.update("flow-2", |flow| {
+            flow.annotations.starred = true;
+            flow.annotations.tags = vec!["bug".to_string()];
+            flow.annotations.comment = Some("复现".to_string());
+        });
+        // 重新添加会移到最新
+        let flow_1 = store.get("flow-1").unwrap().read().unwrap().clone();
+        store.add(flow_1);
+
+        let snapshot = store.snapshot();
+
+        let mut restored = FlowMemoryStore::new(10);
+        restored.add(create_test_flow("other", "gpt-4", ProviderType::OpenAI));
+        assert_eq!(restored.restore(&snapshot).unwrap(), 3);
+        assert_eq!(restored.get_all_ids(), vec!["flow-2", "flow-3", "flow-1"]);
+        assert!(!restored.contains("other"));
+
+        let flow = restored.get("flow-2").unwrap();
+        let flow = flow.read().unwrap();
+        assert!(flow.annotations.starred);
+        assert_eq!(flow.annotations.tags, vec!["bug".to_string()]);
+        assert_eq!(flow.annotations.comment.as_deref(), Some("复现"));
+
+        // 容量不足时保留最新的
+        let mut small = FlowMemoryStore::new(2);
+        assert_eq!(small.restore(&snapshot).unwrap(), 2);
+        assert_eq!(small.get_all_ids(), vec!["flow-3", "flow-1"]);
+
+        assert!(small.restore(b"not json").is_err());
+        assert_eq!(small.len(), 2);
+    }
+
     #[test]
     fn test_memory_store_clear() {
         let mut store = FlowMemoryStore::new(10);
@@ -732,6 +1008,75 @@ mod tests {
         assert!(filter.matches(&flow));
     }
 
+    #[test]
+    fn test_flow_filter_tag_intersection() {
+        let mut store = FlowMemoryStore::new(10);
+        let tagged = [
+            ("flow-1", vec!["bug", "prod"]),
+            ("flow-2", vec!["bug"]),
+            ("flow-3", vec!["prod", "slow"]),
+            ("flow-4", vec![]),
+        ];
+        for (id, tags) in tagged {
+            let mut flow = create_test_flow(id, "gpt-4", ProviderType::OpenAI);
+            flow.annotations.tags = tags.into_iter().map(String::from).collect();
+            store.add(flow);
+        }
+
+        let tags = Some(vec!["bug".to_string(), "prod".to_string()]);
+        let ids = |filter: &FlowFilter| {
+            let mut ids: Vec<String> = store.query(filter).iter().map(|f| f.id.clone()).collect();
+            ids.sort();
+            ids
+        };
+
+        let any = FlowFilter {
+            tags: tags.clone(),
+            ..Default::default()
+        };
+        assert_eq!(ids(&any), vec!["flow-1", "flow-2", "flow-3"]);
+
+        let all = FlowFilter {
+            tags,
+            tag_match: TagMatchMode::All,
+            ..Default::default()
+        };
+        assert_eq!(ids(&all), vec!["flow-1"]);
+    }
+
+    #[test]
+    fn test_flow_filter_annotation_fields() {
+        let mut flow = create_test_flow("test-1", "gpt-4", ProviderType::OpenAI);
+        flow.annotations.marker = Some("🔴".to_string());
+        flow.annotations.comment = Some("  ".to_string());
+
+        let not_starred = FlowFilter {
+            starred: Some(false),
+            ..Default::default()
+        };
+        assert!(not_starred.matches(&flow));
+
+        // 空白评论视为没有评论
+        let has_comment = FlowFilter {
+            has_comment: Some(true),
+            ..Default::default()
+        };
+        assert!(!has_comment.matches(&flow));
+        flow.annotations.comment = Some("需要复查".to_string());
+        assert!(has_comment.matches(&flow));
+
+        let marker = FlowFilter {
+            marker: Some("🔴".to_string()),
+            ..Default::default()
+        };
+        assert!(marker.matches(&flow));
+        let marker = FlowFilter {
+            marker: Some("🟢".to_string()),
+            ..Default::default()
+        };
+        assert!(!marker.matches(&flow));
+    }
+
     #[test]
     fn test_memory_store_query() {
         let mut store = FlowMemoryStore::new(10);
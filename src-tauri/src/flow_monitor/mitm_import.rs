@@ -0,0 +1,773 @@
+//! mitmproxy Flow 文件导入
+//!
+//! 读取 mitmproxy 保存的 Flow 文件（`mitmdump -w` / 界面导出的 `.mitm`），把其中
+//! LLM 端点的 HTTP 请求/响应对重建为 `LLMFlow`，以便在 diff、统计、回放等工具中分析
+//! 本工具之外抓到的流量。
+//!
+//! 目标格式为 mitmproxy 10.x 的序列化格式：文件由连续的 tnetstring 记录组成，每条记录是
+//! 一个 `type` 为 `http` 的 Flow 字典。只读取 `request` / `response` / `error` 中
+//! 稳定的字段（method、host、path、headers、content、status_code、timestamp_*），
+//! 字段相同的相邻版本同样可以导入。
+//!
+//! 支持的端点：
+//! - OpenAI Chat Completions（路径以 `/chat/completions` 结尾）
+//! - Anthropic Messages（路径以 `/messages` 结尾）
+//!
+//! 非 HTTP Flow、其他端点以及请求体无法解析的 Flow 会被跳过。
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::body_decode::decode_body;
+use super::models::{
+    FlowError, FlowErrorType, FlowMetadata, FlowState, FlowType, FunctionCall, LLMFlow, LLMRequest,
+    LLMResponse, ToolCall,
+};
+use super::stream_rebuilder::{StreamFormat, StreamRebuilder};
+use crate::models::anthropic::AnthropicMessagesRequest;
+use crate::models::openai::ChatCompletionRequest;
+use crate::server::handlers::api::{
+    build_llm_request_from_anthropic, build_llm_request_from_openai, build_llm_response,
+};
+use crate::streaming::parse_sse_block;
+use crate::ProviderType;
+
+/// 导入的 Flow 自动添加的标签
+pub const MITM_IMPORT_TAG: &str = "mitmproxy";
+
+/// mitmproxy 文件解析错误
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("mitmproxy Flow 文件格式错误（偏移 {offset}）: {reason}")]
+pub struct MitmImportError {
+    /// 出错记录的字节偏移
+    pub offset: usize,
+    /// 错误原因
+    pub reason: String,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Default)]
+pub struct MitmImport {
+    /// 重建的 LLM Flow
+    pub flows: Vec<LLMFlow>,
+    /// 跳过的 Flow 数（非 HTTP、非 LLM 端点或请求体无法解析）
+    pub skipped: usize,
+}
+
+/// 导入统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MitmImportSummary {
+    /// 导入的 Flow 数
+    pub imported: usize,
+    /// 跳过的 Flow 数
+    pub skipped: usize,
+}
+
+/// 解析 mitmproxy Flow 文件
+pub fn parse_mitm_flows(data: &[u8]) -> Result<MitmImport, MitmImportError> {
+    let mut result = MitmImport::default();
+    let mut offset = 0;
+    while offset < data.len() {
+        // 记录之间可能有换行等空白
+        if data[offset].is_ascii_whitespace() {
+            offset += 1;
+            continue;
+        }
+        let (value, consumed) =
+            tnetstring::parse(&data[offset..]).map_err(|reason| MitmImportError {
+                offset,
+                reason: reason.to_string(),
+            })?;
+        offset += consumed;
+        match flow_from_mitm(&value) {
+            Some(flow) => result.flows.push(flow),
+            None => result.skipped += 1,
+        }
+    }
+    Ok(result)
+}
+
+/// LLM 端点格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endpoint {
+    OpenAI,
+    Anthropic,
+}
+
+impl Endpoint {
+    fn detect(path: &str) -> Option<Self> {
+        let path = path.split('?').next().unwrap_or(path).trim_end_matches('/');
+        if path.ends_with("/chat/completions") {
+            Some(Self::OpenAI)
+        } else if path.ends_with("/messages") {
+            Some(Self::Anthropic)
+        } else {
+            None
+        }
+    }
+
+    fn stream_format(self) -> StreamFormat {
+        match self {
+            Self::OpenAI => StreamFormat::OpenAI,
+            Self::Anthropic => StreamFormat::Anthropic,
+        }
+    }
+
+    fn flow_type(self) -> FlowType {
+        match self {
+            Self::OpenAI => FlowType::ChatCompletions,
+            Self::Anthropic => FlowType::AnthropicMessages,
+        }
+    }
+
+    /// 按目标主机推断 Provider，无法判断时按端点格式
+    fn provider(self, host: &str) -> ProviderType {
+        let host = host.to_ascii_lowercase();
+        if host.contains("anthropic") {
+            ProviderType::Claude
+        } else if host.contains("openai") {
+            ProviderType::OpenAI
+        } else if host.contains("googleapis") {
+            ProviderType::Gemini
+        } else {
+            match self {
+                Self::OpenAI => ProviderType::OpenAI,
+                Self::Anthropic => ProviderType::Claude,
+            }
+        }
+    }
+}
+
+/// 从 mitmproxy Flow 重建 LLMFlow，不是 LLM 请求时返回 `None`
+fn flow_from_mitm(value: &tnetstring::Value) -> Option<LLMFlow> {
+    if value.get("type")?.as_str()? != "http" {
+        return None;
+    }
+    let request = value.get("request")?;
+    if !request
+        .get("method")?
+        .as_str()?
+        .eq_ignore_ascii_case("POST")
+    {
+        return None;
+    }
+    let path = request.get("path")?.as_str()?;
+    let endpoint = Endpoint::detect(&path)?;
+    let host = request
+        .get("host")
+        .and_then(tnetstring::Value::as_str)
+        .unwrap_or_default();
+
+    let request_headers = headers_from_mitm(request.get("headers"));
+    let request_start = timestamp(request.get("timestamp_start")).unwrap_or_else(Utc::now);
+    let body = request.get("content")?.as_bytes()?;
+    let body = decode_body(
+        request_headers.get("content-type").map(String::as_str),
+        request_headers.get("content-encoding").map(String::as_str),
+        body,
+    );
+    let path = path.split('?').next().unwrap_or(&path).to_string();
+    let llm_request = build_request(endpoint, &body.bytes, &path, request_headers, request_start)?;
+
+    let id = value
+        .get("id")
+        .and_then(tnetstring::Value::as_str)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let metadata = FlowMetadata {
+        provider: endpoint.provider(&host),
+        ..Default::default()
+    };
+    let mut flow = LLMFlow::new(id, endpoint.flow_type(), llm_request, metadata);
+    flow.timestamps.created = request_start;
+    flow.timestamps.request_end = timestamp(request.get("timestamp_end"));
+    flow.annotations.tags.push(MITM_IMPORT_TAG.to_string());
+
+    match value.get("response").filter(|r| !r.is_null()) {
+        Some(response) => {
+            let (llm_response, error) = build_response(endpoint, response);
+            flow.timestamps.response_start = Some(llm_response.timestamp_start);
+            flow.timestamps.response_end = Some(llm_response.timestamp_end);
+            flow.state = if error.is_some() {
+                FlowState::Failed
+            } else {
+                FlowState::Completed
+            };
+            flow.metadata.upstream_request_id = llm_response.upstream_request_id();
+            flow.error = error;
+            flow.response = Some(llm_response);
+        }
+        None => {
+            // 没有响应：连接错误或抓包时请求尚未完成
+            let message = value
+                .get("error")
+                .and_then(|e| e.get("msg"))
+                .and_then(tnetstring::Value::as_str)
+                .unwrap_or_else(|| "mitmproxy 未记录响应".to_string());
+            flow.error = Some(FlowError::new(FlowErrorType::Network, message));
+            flow.state = FlowState::Failed;
+        }
+    }
+    flow.timestamps.calculate_duration();
+    flow.timestamps.calculate_ttfb();
+    flow.timestamps.calculate_breakdown();
+    Some(flow)
+}
+
+/// 用代理自身的请求构建函数解析请求体
+fn build_request(
+    endpoint: Endpoint,
+    body: &[u8],
+    path: &str,
+    headers: HashMap<String, String>,
+    received_at: DateTime<Utc>,
+) -> Option<LLMRequest> {
+    match endpoint {
+        Endpoint::OpenAI => {
+            let request: ChatCompletionRequest = serde_json::from_slice(body).ok()?;
+            Some(build_llm_request_from_openai(
+                &request,
+                path,
+                headers,
+                received_at,
+            ))
+        }
+        Endpoint::Anthropic => {
+            let request: AnthropicMessagesRequest = serde_json::from_slice(body).ok()?;
+            Some(build_llm_request_from_anthropic(
+                &request,
+                path,
+                headers,
+                received_at,
+            ))
+        }
+    }
+}
+
+/// 重建响应，错误状态码同时返回对应的 Flow 错误
+fn build_response(
+    endpoint: Endpoint,
+    response: &tnetstring::Value,
+) -> (LLMResponse, Option<FlowError>) {
+    let status_code = response
+        .get("status_code")
+        .and_then(tnetstring::Value::as_int)
+        .unwrap_or(0) as u16;
+    let headers = headers_from_mitm(response.get("headers"));
+    let decoded = decode_body(
+        headers.get("content-type").map(String::as_str),
+        headers.get("content-encoding").map(String::as_str),
+        response
+            .get("content")
+            .and_then(tnetstring::Value::as_bytes)
+            .unwrap_or_default(),
+    );
+    let text = decoded.text_or_note();
+    let is_sse = headers
+        .get("content-type")
+        .is_some_and(|t| t.contains("text/event-stream"));
+
+    let mut llm_response = if is_sse && (200..300).contains(&status_code) {
+        let mut rebuilder = StreamRebuilder::new(endpoint.stream_format());
+        for block in text.replace("\r\n", "\n").split("\n\n") {
+            if let Some(event) = parse_sse_block(block) {
+                let _ = rebuilder.process_event(event.event.as_deref(), &event.data);
+            }
+        }
+        rebuilder.finish()
+    } else {
+        let body = decoded.json().unwrap_or(Value::Null);
+        let (content, usage, tool_calls) = match endpoint {
+            Endpoint::OpenAI => parse_openai_body(&body),
+            Endpoint::Anthropic => parse_anthropic_body(&body),
+        };
+        let mut llm_response = build_llm_response(status_code, &content, usage);
+        llm_response.tool_calls = tool_calls;
+        llm_response.body = body;
+        llm_response
+    };
+
+    llm_response.status_code = status_code;
+    if let Some(reason) = response
+        .get("reason")
+        .and_then(tnetstring::Value::as_str)
+        .filter(|r| !r.is_empty())
+    {
+        llm_response.status_text = reason;
+    }
+    llm_response.size_bytes = decoded.info.encoded_size_bytes;
+    llm_response.body_info = Some(decoded.info.clone());
+    llm_response.headers = headers;
+    let now = Utc::now();
+    llm_response.timestamp_start = timestamp(response.get("timestamp_start")).unwrap_or(now);
+    llm_response.timestamp_end = timestamp(response.get("timestamp_end")).unwrap_or(now);
+    llm_response.normalize_stop_reason();
+    llm_response.fill_cache_usage();
+    llm_response.capture_system_fingerprint();
+
+    let error = (status_code >= 400).then(|| {
+        FlowError::new(
+            FlowErrorType::from_status_code(status_code),
+            "Request failed",
+        )
+        .with_status_code(status_code)
+        .with_provider_body(&text)
+    });
+    (llm_response, error)
+}
+
+/// 解析 OpenAI 非流式响应体：内容、用量和工具调用
+fn parse_openai_body(body: &Value) -> (String, Option<(u32, u32)>, Vec<ToolCall>) {
+    let message = &body["choices"][0]["message"];
+    let content = message["content"].as_str().unwrap_or_default().to_string();
+    let usage = body.get("usage").map(|usage| {
+        (
+            usage["prompt_tokens"].as_u64().unwrap_or(0) as u32,
+            usage["completion_tokens"].as_u64().unwrap_or(0) as u32,
+        )
+    });
+    let tool_calls = message["tool_calls"]
+        .as_array()
+        .map(|calls| {
+            calls
+                .iter()
+                .map(|call| ToolCall {
+                    id: call["id"].as_str().unwrap_or_default().to_string(),
+                    tool_type: call["type"].as_str().unwrap_or("function").to_string(),
+                    function: FunctionCall {
+                        name: call["function"]["name"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        arguments: call["function"]["arguments"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                    },
+                    arguments_truncated: false,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    (content, usage, tool_calls)
+}
+
+/// 解析 Anthropic 非流式响应体：文本内容、用量和 `tool_use` 块
+fn parse_anthropic_body(body: &Value) -> (String, Option<(u32, u32)>, Vec<ToolCall>) {
+    let blocks = body["content"].as_array().cloned().unwrap_or_default();
+    let content = blocks
+        .iter()
+        .filter(|block| block["type"] == "text")
+        .filter_map(|block| block["text"].as_str())
+        .collect::<String>();
+    let usage = body.get("usage").map(|usage| {
+        (
+            usage["input_tokens"].as_u64().unwrap_or(0) as u32,
+            usage["output_tokens"].as_u64().unwrap_or(0) as u32,
+        )
+    });
+    let tool_calls = blocks
+        .iter()
+        .filter(|block| block["type"] == "tool_use")
+        .map(|block| ToolCall {
+            id: block["id"].as_str().unwrap_or_default().to_string(),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: block["name"].as_str().unwrap_or_default().to_string(),
+                arguments: block["input"].to_string(),
+            },
+            arguments_truncated: false,
+        })
+        .collect();
+    (content, usage, tool_calls)
+}
+
+/// mitmproxy 的头是 `[[name, value], ...]` 列表；名称转为小写，同名的多个值以 `, ` 连接
+fn headers_from_mitm(value: Option<&tnetstring::Value>) -> HashMap<String, String> {
+    let mut headers: HashMap<String, String> = HashMap::new();
+    let Some(tnetstring::Value::List(items)) = value else {
+        return headers;
+    };
+    for item in items {
+        let tnetstring::Value::List(pair) = item else {
+            continue;
+        };
+        let (Some(name), Some(value)) = (
+            pair.first().and_then(tnetstring::Value::as_str),
+            pair.get(1).and_then(tnetstring::Value::as_str),
+        ) else {
+            continue;
+        };
+        headers
+            .entry(name.to_ascii_lowercase())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert(value);
+    }
+    headers
+}
+
+/// mitmproxy 的时间戳是 Unix 秒（浮点数）
+fn timestamp(value: Option<&tnetstring::Value>) -> Option<DateTime<Utc>> {
+    let secs = value?.as_float()?;
+    Utc.timestamp_millis_opt((secs * 1000.0).round() as i64)
+        .single()
+}
+
+/// tnetstring 解析（mitmproxy 的 Flow 序列化格式）
+///
+/// 每个值编码为 `<长度>:<数据><类型>`，类型为 `,` 字节串、`;` 字符串、`#` 整数、
+/// `^` 浮点数、`!` 布尔值、`~` 空值、`]` 列表、`}` 字典（键值交替排列）。
+mod tnetstring {
+    /// 长度前缀的最大位数
+    const MAX_LENGTH_DIGITS: usize = 12;
+    /// 列表/字典的最大嵌套深度（防止恶意文件导致栈溢出）
+    const MAX_DEPTH: usize = 64;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Value {
+        Bytes(Vec<u8>),
+        String(String),
+        Int(i64),
+        Float(f64),
+        Bool(bool),
+        Null,
+        List(Vec<Value>),
+        Dict(Vec<(Value, Value)>),
+    }
+
+    impl Value {
+        /// 按键查找字典值（键可以是字节串或字符串）
+        pub fn get(&self, key: &str) -> Option<&Value> {
+            let Value::Dict(entries) = self else {
+                return None;
+            };
+            entries
+                .iter()
+                .find(|(k, _)| k.as_str().as_deref() == Some(key))
+                .map(|(_, v)| v)
+        }
+
+        /// 字节串或字符串转为文本（无效 UTF-8 会被替换）
+        pub fn as_str(&self) -> Option<String> {
+            match self {
+                Value::Bytes(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+                Value::String(s) => Some(s.clone()),
+                _ => None,
+            }
+        }
+
+        pub fn as_bytes(&self) -> Option<&[u8]> {
+            match self {
+                Value::Bytes(bytes) => Some(bytes),
+                Value::String(s) => Some(s.as_bytes()),
+                _ => None,
+            }
+        }
+
+        pub fn as_int(&self) -> Option<i64> {
+            match self {
+                Value::Int(n) => Some(*n),
+                _ => None,
+            }
+        }
+
+        pub fn as_float(&self) -> Option<f64> {
+            match self {
+                Value::Float(n) => Some(*n),
+                Value::Int(n) => Some(*n as f64),
+                _ => None,
+            }
+        }
+
+        pub fn is_null(&self) -> bool {
+            matches!(self, Value::Null)
+        }
+    }
+
+    /// 解析一个值，返回值和消耗的字节数
+    pub fn parse(data: &[u8]) -> Result<(Value, usize), &'static str> {
+        parse_nested(data, 0)
+    }
+
+    fn parse_nested(data: &[u8], depth: usize) -> Result<(Value, usize), &'static str> {
+        if depth > MAX_DEPTH {
+            return Err("嵌套层级过深");
+        }
+        let colon = data
+            .iter()
+            .take(MAX_LENGTH_DIGITS + 1)
+            .position(|&b| b == b':')
+            .ok_or("缺少长度前缀")?;
+        let length: usize = std::str::from_utf8(&data[..colon])
+            .ok()
+            .filter(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|digits| digits.parse().ok())
+            .ok_or("长度前缀无效")?;
+        let start = colon + 1;
+        let end = start.checked_add(length).ok_or("长度前缀无效")?;
+        let tag = *data.get(end).ok_or("数据被截断")?;
+        let payload = &data[start..end];
+
+        let value = match tag {
+            b',' => Value::Bytes(payload.to_vec()),
+            b';' => Value::String(
+                String::from_utf8(payload.to_vec()).map_err(|_| "字符串不是有效的 UTF-8")?,
+            ),
+            b'#' => Value::Int(
+                std::str::from_utf8(payload)
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or("整数无效")?,
+            ),
+            b'^' => Value::Float(
+                std::str::from_utf8(payload)
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or("浮点数无效")?,
+            ),
+            b'!' => match payload {
+                b"true" => Value::Bool(true),
+                b"false" => Value::Bool(false),
+                _ => return Err("布尔值无效"),
+            },
+            b'~' if payload.is_empty() => Value::Null,
+            b']' => Value::List(parse_all(payload, depth + 1)?),
+            b'}' => {
+                let items = parse_all(payload, depth + 1)?;
+                if items.len() % 2 != 0 {
+                    return Err("字典的键值数量不匹配");
+                }
+                let mut entries = Vec::with_capacity(items.len() / 2);
+                let mut items = items.into_iter();
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    entries.push((key, value));
+                }
+                Value::Dict(entries)
+            }
+            _ => return Err("未知的类型标记"),
+        };
+        Ok((value, end + 1))
+    }
+
+    fn parse_all(mut data: &[u8], depth: usize) -> Result<Vec<Value>, &'static str> {
+        let mut values = Vec::new();
+        while !data.is_empty() {
+            let (value, consumed) = parse_nested(data, depth)?;
+            values.push(value);
+            data = &data[consumed..];
+        }
+        Ok(values)
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::models::StopReason;
+
+    fn bytes(data: &[u8]) -> Vec<u8> {
+        let mut out = format!("{}:", data.len()).into_bytes();
+        out.extend_from_slice(data);
+        out.push(b',');
+        out
+    }
+
+    fn float(n: f64) -> Vec<u8> {
+        let s = n.to_string();
+        format!("{}:{}^", s.len(), s).into_bytes()
+    }
+
+    fn int(n: i64) -> Vec<u8> {
+        let s = n.to_string();
+        format!("{}:{}#", s.len(), s).into_bytes()
+    }
+
+    fn container(items: &[Vec<u8>], tag: u8) -> Vec<u8> {
+        let payload = items.concat();
+        let mut out = format!("{}:", payload.len()).into_bytes();
+        out.extend_from_slice(&payload);
+        out.push(tag);
+        out
+    }
+
+    fn dict(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let items: Vec<Vec<u8>> = entries
+            .iter()
+            .flat_map(|(k, v)| [bytes(k.as_bytes()), v.clone()])
+            .collect();
+        container(&items, b'}')
+    }
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<u8> {
+        let items: Vec<Vec<u8>> = pairs
+            .iter()
+            .map(|(k, v)| container(&[bytes(k.as_bytes()), bytes(v.as_bytes())], b']'))
+            .collect();
+        container(&items, b']')
+    }
+
+    fn http_flow(id: &str, path: &str, request_body: &str, response: Vec<u8>) -> Vec<u8> {
+        dict(&[
+            ("id", bytes(id.as_bytes())),
+            ("type", bytes(b"http")),
+            (
+                "request",
+                dict(&[
+                    ("host", bytes(b"api.openai.com")),
+                    ("method", bytes(b"POST")),
+                    ("path", bytes(path.as_bytes())),
+                    (
+                        "headers",
+                        headers(&[("Content-Type", "application/json"), ("User-Agent", "curl")]),
+                    ),
+                    ("content", bytes(request_body.as_bytes())),
+                    ("timestamp_start", float(1_700_000_000.0)),
+                    ("timestamp_end", float(1_700_000_000.05)),
+                ]),
+            ),
+            ("response", response),
+        ])
+    }
+
+    #[test]
+    fn test_import_openai_flows_and_skip_others() {
+        let request = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"hi"}]}"#;
+        let response = dict(&[
+            ("status_code", int(200)),
+            ("reason", bytes(b"OK")),
+            (
+                "headers",
+                headers(&[
+                    ("Content-Type", "application/json"),
+                    ("x-request-id", "req_123"),
+                ]),
+            ),
+            (
+                "content",
+                bytes(br#"{"choices":[{"message":{"role":"assistant","content":"hello"},"finish_reason":"stop"}],"usage":{"prompt_tokens":5,"completion_tokens":2}}"#),
+            ),
+            ("timestamp_start", float(1_700_000_000.5)),
+            ("timestamp_end", float(1_700_000_001.0)),
+        ]);
+        let stream = concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"he\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"llo\"},\"finish_reason\":\"stop\"}]}\n\n",
+            "data: [DONE]\n\n"
+        );
+        let stream_response = dict(&[
+            ("status_code", int(200)),
+            ("headers", headers(&[("Content-Type", "text/event-stream")])),
+            ("content", bytes(stream.as_bytes())),
+            ("timestamp_start", float(1_700_000_000.5)),
+            ("timestamp_end", float(1_700_000_002.0)),
+        ]);
+        let error_response = dict(&[
+            ("status_code", int(429)),
+            ("headers", headers(&[("Content-Type", "application/json")])),
+            (
+                "content",
+                bytes(br#"{"error":{"message":"slow down","type":"requests","code":"rate_limit_exceeded"}}"#),
+            ),
+            ("timestamp_start", float(1_700_000_000.5)),
+            ("timestamp_end", float(1_700_000_000.6)),
+        ]);
+
+        let mut data = http_flow("a", "/v1/chat/completions", request, response);
+        data.push(b'\n');
+        data.extend(http_flow(
+            "b",
+            "/v1/chat/completions?x=1",
+            request,
+            stream_response,
+        ));
+        data.extend(http_flow(
+            "c",
+            "/v1/chat/completions",
+            request,
+            error_response,
+        ));
+        // 非 LLM 端点和请求体无法解析的 Flow 被跳过
+        data.extend(http_flow("d", "/v1/models", "", container(&[], b'}')));
+        data.extend(http_flow(
+            "e",
+            "/v1/chat/completions",
+            "not json",
+            b"0:~".to_vec(),
+        ));
+        data.extend(dict(&[("type", bytes(b"tcp"))]));
+
+        let result = parse_mitm_flows(&data).unwrap();
+        assert_eq!(result.skipped, 3);
+        assert_eq!(result.flows.len(), 3);
+
+        let flow = &result.flows[0];
+        assert_eq!(flow.id, "a");
+        assert_eq!(flow.state, FlowState::Completed);
+        assert_eq!(flow.metadata.provider, ProviderType::OpenAI);
+        assert_eq!(
+            flow.metadata.upstream_request_id.as_deref(),
+            Some("req_123")
+        );
+        assert_eq!(flow.request.model, "gpt-4o");
+        assert_eq!(
+            flow.request.headers.get("user-agent").map(String::as_str),
+            Some("curl")
+        );
+        assert!(flow.annotations.tags.contains(&MITM_IMPORT_TAG.to_string()));
+        assert_eq!(flow.timestamps.duration_ms, 1000);
+        assert_eq!(flow.timestamps.ttfb_ms, Some(500));
+        let response = flow.response.as_ref().unwrap();
+        assert_eq!(response.content, "hello");
+        assert_eq!(response.usage.input_tokens, 5);
+        assert_eq!(response.stop_reason, Some(StopReason::Stop));
+
+        let flow = &result.flows[1];
+        assert_eq!(flow.request.path, "/v1/chat/completions");
+        assert_eq!(flow.response.as_ref().unwrap().content, "hello");
+
+        let flow = &result.flows[2];
+        assert_eq!(flow.state, FlowState::Failed);
+        let error = flow.error.as_ref().unwrap();
+        assert_eq!(error.error_type, FlowErrorType::RateLimit);
+        assert_eq!(error.status_code, Some(429));
+    }
+
+    #[test]
+    fn test_truncated_file_reports_offset() {
+        let request = r#"{"model":"gpt-4o","messages":[]}"#;
+        let data = http_flow("a", "/v1/chat/completions", request, b"0:~".to_vec());
+        let mut truncated = data.clone();
+        truncated.extend_from_slice(&data[..data.len() / 2]);
+
+        let error = parse_mitm_flows(&truncated).unwrap_err();
+        assert_eq!(error.offset, data.len());
+    }
+
+    #[test]
+    fn test_deeply_nested_value_is_rejected() {
+        let mut nested = b"0:~".to_vec();
+        for _ in 0..1_000 {
+            nested = container(&[nested], b']');
+        }
+
+        let error = parse_mitm_flows(&nested).unwrap_err();
+        assert_eq!(error.offset, 0);
+        assert_eq!(error.reason, "嵌套层级过深");
+
+        let mut shallow = b"0:~".to_vec();
+        for _ in 0..8 {
+            shallow = container(&[shallow], b']');
+        }
+        let shallow = dict(&[("type", bytes(b"tcp")), ("data", shallow)]);
+        assert_eq!(parse_mitm_flows(&shallow).unwrap().skipped, 1);
+    }
+}
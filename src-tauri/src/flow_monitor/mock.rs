@@ -0,0 +1,570 @@
+//! 离线 Mock 模式
+//!
+//! 前端开发和测试时不调用任何上游，直接用捕获的 Flow 响应请求：在同一端点上已成功完成的
+//! Flow 中按规范化内容哈希（见 [`ContentNormalizer::hash_request`]）查找内容相同的 Flow，
+//! 返回其保存的响应。流式请求按保存的 `StreamChunk` 及其原始间隔重新推送；Flow 未保存
+//! chunk 时由响应体合成等价的事件序列。
+//!
+//! 没有精确匹配时按配置返回 404 或内容最接近的 Flow。Mock 响应不调用上游，也不记录新的 Flow。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::content_hash::ContentNormalizer;
+use super::models::{FlowState, FlowType, LLMFlow, LLMRequest, CHAOS_TAG};
+
+/// 按单个请求启用（`true`）或关闭（`false`）Mock 模式的请求头，优先于全局配置
+pub const MOCK_HEADER: &str = "x-mock";
+
+/// Mock 响应上附加的响应头，值为提供响应的 Flow ID
+pub const MOCK_FLOW_ID_HEADER: &str = "x-mock-flow-id";
+
+/// Mock 响应上附加的响应头，值为匹配方式（`exact` / `nearest`）
+pub const MOCK_MATCH_HEADER: &str = "x-mock-match";
+
+/// 没有精确匹配时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MockFallback {
+    /// 返回 404
+    #[default]
+    NotFound,
+    /// 返回内容最接近的 Flow
+    Nearest,
+}
+
+/// Mock 模式配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockConfig {
+    /// 是否对所有请求启用（单个请求可用 `x-mock` 请求头覆盖）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 没有精确匹配时的处理方式
+    #[serde(default)]
+    pub fallback: MockFallback,
+    /// 流式响应按捕获时的间隔推送（关闭时立即推送全部事件）
+    #[serde(default = "default_replay_timing")]
+    pub replay_timing: bool,
+    /// 单次等待的上限（毫秒），避免按很长的原始间隔等待
+    #[serde(default = "default_max_chunk_delay_ms")]
+    pub max_chunk_delay_ms: u64,
+}
+
+fn default_replay_timing() -> bool {
+    true
+}
+
+fn default_max_chunk_delay_ms() -> u64 {
+    2000
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fallback: MockFallback::NotFound,
+            replay_timing: default_replay_timing(),
+            max_chunk_delay_ms: default_max_chunk_delay_ms(),
+        }
+    }
+}
+
+/// 匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MockMatchKind {
+    /// 内容哈希相同
+    Exact,
+    /// 内容最接近（`fallback: nearest`）
+    Nearest,
+}
+
+impl MockMatchKind {
+    /// 响应头中使用的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MockMatchKind::Exact => "exact",
+            MockMatchKind::Nearest => "nearest",
+        }
+    }
+}
+
+/// 用于响应请求的 Flow
+#[derive(Debug, Clone)]
+pub struct MockMatch {
+    /// 匹配到的 Flow（保证有响应）
+    pub flow: LLMFlow,
+    /// 匹配方式
+    pub kind: MockMatchKind,
+}
+
+/// 待推送的 SSE 事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockEvent {
+    /// 推送前的等待时间
+    pub delay: Duration,
+    /// 完整的 SSE 事件文本（以空行结尾）
+    pub text: String,
+}
+
+/// 在已捕获的 Flow 中查找用于响应请求的 Flow
+///
+/// 只考虑同一端点上成功完成的 Flow（故障注入的 Flow 除外）。多个 Flow 内容相同时优先选择
+/// 流式方式与请求一致的 Flow（流式请求优先保存了 chunk 的 Flow），再选择最新的。
+pub fn find_match(
+    flows: &[LLMFlow],
+    request: &LLMRequest,
+    normalizer: &ContentNormalizer,
+    fallback: MockFallback,
+    stream: bool,
+) -> Option<MockMatch> {
+    let candidates: Vec<&LLMFlow> = flows
+        .iter()
+        .filter(|flow| {
+            flow.state == FlowState::Completed
+                && flow.error.is_none()
+                && flow.response.is_some()
+                && flow.request.path == request.path
+                && !flow.annotations.tags.iter().any(|tag| tag == CHAOS_TAG)
+        })
+        .collect();
+
+    let hash = normalizer.hash_request(request);
+    let exact = candidates
+        .iter()
+        .filter(|flow| normalizer.hash_request(&flow.request) == hash)
+        .max_by_key(|flow| (has_chunks(flow) == stream, flow.timestamps.created));
+    if let Some(flow) = exact {
+        return Some(MockMatch {
+            flow: (*flow).clone(),
+            kind: MockMatchKind::Exact,
+        });
+    }
+
+    if fallback == MockFallback::NotFound {
+        return None;
+    }
+    let keys = content_keys(normalizer, request);
+    candidates
+        .into_iter()
+        .max_by_key(|flow| {
+            let common = keys
+                .iter()
+                .zip(content_keys(normalizer, &flow.request))
+                .take_while(|(a, b)| **a == *b)
+                .count();
+            (
+                common,
+                flow.request.model == request.model,
+                flow.timestamps.created,
+            )
+        })
+        .map(|flow| MockMatch {
+            flow: flow.clone(),
+            kind: MockMatchKind::Nearest,
+        })
+}
+
+/// 生成流式 Mock 响应的 SSE 事件
+///
+/// 有保存的 chunk 时按原样推送（合并过的 chunk 拆回多个事件），首个事件等待原始首字节延迟，
+/// 之后按 chunk 时间戳的间隔等待；否则由响应体合成事件，只在首个事件前等待首字节延迟。
+pub fn stream_events(flow: &LLMFlow, config: &MockConfig) -> Vec<MockEvent> {
+    let Some(response) = &flow.response else {
+        return Vec::new();
+    };
+    let mut events = Vec::new();
+    match response
+        .stream_info
+        .as_ref()
+        .and_then(|info| info.raw_chunks.as_ref().map(|chunks| (info, chunks)))
+        .filter(|(_, chunks)| !chunks.is_empty())
+    {
+        Some((info, chunks)) => {
+            let mut previous: Option<DateTime<Utc>> = None;
+            for chunk in chunks {
+                let delay_ms = match previous {
+                    None => info.first_chunk_latency_ms,
+                    Some(previous) => (chunk.timestamp - previous).num_milliseconds().max(0) as u64,
+                };
+                previous = Some(chunk.timestamp);
+                for (i, data) in chunk.data.split('\n').enumerate() {
+                    events.push((
+                        if i == 0 { delay_ms } else { 0 },
+                        sse_event(chunk.event.as_deref(), data),
+                    ));
+                }
+            }
+        }
+        None => {
+            let synthesized = if flow.flow_type == FlowType::AnthropicMessages {
+                synthesize_anthropic(&response.body)
+            } else {
+                synthesize_openai(&response.body)
+            };
+            let ttfb_ms = flow.timestamps.ttfb_ms.unwrap_or(0);
+            events.extend(
+                synthesized
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, text)| (if i == 0 { ttfb_ms } else { 0 }, text)),
+            );
+        }
+    }
+
+    events
+        .into_iter()
+        .map(|(delay_ms, text)| MockEvent {
+            delay: if config.replay_timing {
+                Duration::from_millis(delay_ms.min(config.max_chunk_delay_ms))
+            } else {
+                Duration::ZERO
+            },
+            text,
+        })
+        .collect()
+}
+
+/// Flow 是否保存了流式 chunk
+pub(crate) fn has_chunks(flow: &LLMFlow) -> bool {
+    flow.response
+        .as_ref()
+        .and_then(|response| response.stream_info.as_ref())
+        .and_then(|info| info.raw_chunks.as_ref())
+        .is_some_and(|chunks| !chunks.is_empty())
+}
+
+/// 系统提示词和各消息规范化后的内容，用于计算最长公共前缀
+fn content_keys(normalizer: &ContentNormalizer, request: &LLMRequest) -> Vec<String> {
+    request
+        .system_prompt
+        .iter()
+        .map(|system| format!("system\0{}", normalizer.normalize(system)))
+        .chain(request.messages.iter().map(|message| {
+            format!(
+                "{:?}\0{}",
+                message.role,
+                normalizer.normalize(&message.content.get_all_text())
+            )
+        }))
+        .collect()
+}
+
+fn sse_event(event: Option<&str>, data: &str) -> String {
+    match event {
+        Some(event) => format!("event: {}\ndata: {}\n\n", event, data),
+        None => format!("data: {}\n\n", data),
+    }
+}
+
+/// 由 OpenAI Chat Completions 响应体合成流式事件
+fn synthesize_openai(body: &Value) -> Vec<String> {
+    let chunk = |choices: Value| {
+        json!({
+            "id": body.get("id").cloned().unwrap_or_else(|| json!("chatcmpl-mock")),
+            "object": "chat.completion.chunk",
+            "created": body.get("created").cloned().unwrap_or_else(|| json!(0)),
+            "model": body.get("model").cloned().unwrap_or(Value::Null),
+            "choices": choices,
+        })
+    };
+    let choices = body
+        .get("choices")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut events = Vec::new();
+    for choice in &choices {
+        let index = choice.get("index").cloned().unwrap_or_else(|| json!(0));
+        let mut delta = choice.get("message").cloned().unwrap_or_else(|| json!({}));
+        if let Some(tool_calls) = delta.get_mut("tool_calls").and_then(Value::as_array_mut) {
+            for (i, call) in tool_calls.iter_mut().enumerate() {
+                call["index"] = json!(i);
+            }
+        }
+        events.push(sse_event(
+            None,
+            &chunk(json!([{"index": index, "delta": delta, "finish_reason": null}])).to_string(),
+        ));
+    }
+
+    let mut last = chunk(Value::Array(
+        choices
+            .iter()
+            .map(|choice| {
+                json!({
+                    "index": choice.get("index").cloned().unwrap_or_else(|| json!(0)),
+                    "delta": {},
+                    "finish_reason": choice.get("finish_reason").cloned().unwrap_or(Value::Null),
+                })
+            })
+            .collect(),
+    ));
+    if let Some(usage) = body.get("usage") {
+        last["usage"] = usage.clone();
+    }
+    events.push(sse_event(None, &last.to_string()));
+    events.push(sse_event(None, "[DONE]"));
+    events
+}
+
+/// 由 Anthropic Messages 响应体合成流式事件
+fn synthesize_anthropic(body: &Value) -> Vec<String> {
+    let mut events = Vec::new();
+    let mut push = |event: &str, data: Value| {
+        events.push(sse_event(Some(event), &data.to_string()));
+    };
+
+    let mut message = body.clone();
+    message["content"] = json!([]);
+    message["stop_reason"] = Value::Null;
+    message["stop_sequence"] = Value::Null;
+    push(
+        "message_start",
+        json!({"type": "message_start", "message": message}),
+    );
+
+    let blocks = body
+        .get("content")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for (index, block) in blocks.iter().enumerate() {
+        let text = |key: &str| block.get(key).cloned().unwrap_or_else(|| json!(""));
+        let (start, deltas) = match block.get("type").and_then(Value::as_str) {
+            Some("text") => (
+                json!({"type": "text", "text": ""}),
+                vec![json!({"type": "text_delta", "text": text("text")})],
+            ),
+            Some("thinking") => {
+                let mut deltas =
+                    vec![json!({"type": "thinking_delta", "thinking": text("thinking")})];
+                if let Some(signature) = block.get("signature") {
+                    deltas.push(json!({"type": "signature_delta", "signature": signature}));
+                }
+                (json!({"type": "thinking", "thinking": ""}), deltas)
+            }
+            Some("tool_use") => {
+                let mut start = block.clone();
+                start["input"] = json!({});
+                let input = block.get("input").cloned().unwrap_or_else(|| json!({}));
+                (
+                    start,
+                    vec![json!({"type": "input_json_delta", "partial_json": input.to_string()})],
+                )
+            }
+            _ => (block.clone(), Vec::new()),
+        };
+        push(
+            "content_block_start",
+            json!({"type": "content_block_start", "index": index, "content_block": start}),
+        );
+        for delta in deltas {
+            push(
+                "content_block_delta",
+                json!({"type": "content_block_delta", "index": index, "delta": delta}),
+            );
+        }
+        push(
+            "content_block_stop",
+            json!({"type": "content_block_stop", "index": index}),
+        );
+    }
+
+    let output_tokens = body
+        .pointer("/usage/output_tokens")
+        .cloned()
+        .unwrap_or_else(|| json!(0));
+    push(
+        "message_delta",
+        json!({
+            "type": "message_delta",
+            "delta": {
+                "stop_reason": body.get("stop_reason").cloned().unwrap_or(Value::Null),
+                "stop_sequence": body.get("stop_sequence").cloned().unwrap_or(Value::Null),
+            },
+            "usage": {"output_tokens": output_tokens},
+        }),
+    );
+    push("message_stop", json!({"type": "message_stop"}));
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::models::{
+        FlowMetadata, LLMResponse, Message, MessageContent, MessageRole, StreamChunk, StreamInfo,
+    };
+
+    fn request(path: &str, user: &str) -> LLMRequest {
+        LLMRequest {
+            path: path.to_string(),
+            model: "gpt-4o".to_string(),
+            messages: vec![Message {
+                role: MessageRole::User,
+                content: MessageContent::Text(user.to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn completed(id: &str, request: LLMRequest, body: Value) -> LLMFlow {
+        let flow_type = if request.path == "/v1/messages" {
+            FlowType::AnthropicMessages
+        } else {
+            FlowType::ChatCompletions
+        };
+        let mut flow = LLMFlow::new(id.to_string(), flow_type, request, FlowMetadata::default());
+        flow.state = FlowState::Completed;
+        flow.response = Some(LLMResponse {
+            body,
+            ..Default::default()
+        });
+        flow
+    }
+
+    #[test]
+    fn test_find_match() {
+        let normalizer = ContentNormalizer::default();
+        let path = "/v1/chat/completions";
+        let flows = vec![
+            completed("hello", request(path, "Hello  there"), json!({"id": "a"})),
+            completed("other", request(path, "Something else"), json!({"id": "b"})),
+            completed(
+                "anthropic",
+                request("/v1/messages", "Hi"),
+                json!({"id": "c"}),
+            ),
+        ];
+
+        // 规范化后内容相同即精确匹配
+        let found = find_match(
+            &flows,
+            &request(path, "Hello there\n"),
+            &normalizer,
+            MockFallback::NotFound,
+            false,
+        )
+        .unwrap();
+        assert_eq!(found.flow.id, "hello");
+        assert_eq!(found.kind, MockMatchKind::Exact);
+
+        // 其他端点的 Flow 不参与匹配
+        let unmatched = request(path, "Hi");
+        assert!(find_match(
+            &flows,
+            &unmatched,
+            &normalizer,
+            MockFallback::NotFound,
+            false
+        )
+        .is_none());
+        let nearest = find_match(
+            &flows,
+            &unmatched,
+            &normalizer,
+            MockFallback::Nearest,
+            false,
+        )
+        .unwrap();
+        assert_eq!(nearest.kind, MockMatchKind::Nearest);
+        assert_ne!(nearest.flow.id, "anthropic");
+    }
+
+    #[test]
+    fn test_stream_events_replay_chunks() {
+        let mut flow = completed("stream", request("/v1/chat/completions", "Hi"), json!({}));
+        let start = Utc::now();
+        let chunk = |index: u32, offset_ms: i64, data: &str| StreamChunk {
+            index,
+            event: None,
+            data: data.to_string(),
+            timestamp: start + chrono::Duration::milliseconds(offset_ms),
+            content_delta: None,
+            tool_call_delta: None,
+            thinking_delta: None,
+        };
+        flow.response.as_mut().unwrap().stream_info = Some(StreamInfo {
+            chunk_count: 3,
+            first_chunk_latency_ms: 300,
+            avg_chunk_interval_ms: 0.0,
+            // 合并过的 chunk 以换行拼接多个事件
+            raw_chunks: Some(vec![
+                chunk(0, 0, r#"{"n":1}"#),
+                chunk(1, 50, "{\"n\":2}\n{\"n\":3}"),
+                chunk(2, 10_050, "[DONE]"),
+            ]),
+            completed_cleanly: true,
+        });
+
+        let config = MockConfig::default();
+        let events = stream_events(&flow, &config);
+        let texts: Vec<&str> = events.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "data: {\"n\":1}\n\n",
+                "data: {\"n\":2}\n\n",
+                "data: {\"n\":3}\n\n",
+                "data: [DONE]\n\n"
+            ]
+        );
+        let delays: Vec<u64> = events.iter().map(|e| e.delay.as_millis() as u64).collect();
+        assert_eq!(delays, [300, 50, 0, config.max_chunk_delay_ms]);
+
+        let immediate = MockConfig {
+            replay_timing: false,
+            ..Default::default()
+        };
+        assert!(stream_events(&flow, &immediate)
+            .iter()
+            .all(|e| e.delay.is_zero()));
+    }
+
+    #[test]
+    fn test_synthesized_anthropic_stream() {
+        let flow = completed(
+            "anthropic",
+            request("/v1/messages", "Hi"),
+            json!({
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-3",
+                "content": [
+                    {"type": "text", "text": "Let me check."},
+                    {"type": "tool_use", "id": "t1", "name": "lookup", "input": {"q": "x"}}
+                ],
+                "stop_reason": "tool_use",
+                "usage": {"input_tokens": 10, "output_tokens": 5}
+            }),
+        );
+
+        let events = stream_events(&flow, &MockConfig::default());
+        let names: Vec<&str> = events
+            .iter()
+            .map(|e| e.text.lines().next().unwrap().trim_start_matches("event: "))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop"
+            ]
+        );
+        assert!(events[5].text.contains(r#"\"q\":\"x\""#));
+        assert!(events[7].text.contains(r#""stop_reason":"tool_use""#));
+    }
+}
@@ -7,34 +7,83 @@
 //!
 //! - `models`: 核心数据模型，包括 LLMFlow、LLMRequest、LLMResponse 等
 //! - `stream_rebuilder`: SSE 流式响应重建器
-//! - `memory_store`: 内存存储，支持 LRU 驱逐策略
+//! - `memory_store`: 内存存储，支持 LRU 驱逐策略和按保留时间驱逐
 //! - `file_store`: 文件存储，支持 JSONL 格式和 SQLite 索引
 //! - `query_service`: 查询服务，支持多维度过滤、排序、分页和全文搜索
-//! - `exporter`: 导出服务，支持 HAR、JSON、JSONL、Markdown、CSV 格式
+//! - `exporter`: 导出服务，支持 HAR、JSON、JSONL、Markdown、CSV、Parquet 格式
+//! - `parquet_export`: 以稳定的列式 Schema 写出 Parquet 文件，供数据仓库导入
+//! - `field_mask`: 按点分路径选择 JSON 导出包含或排除的字段
+//! - `json_path`: 导出脱敏用的 JSONPath 子集，按位置替换请求体 / 响应体中的标量
 //! - `monitor`: 核心监控服务
 //! - `filter_parser`: 高级过滤表达式解析器，支持类似 mitmproxy 的语法
+//! - `auto_tag`: 自动标签引擎，在 Flow 完成时按规则自动打标签
+//! - `multipart`: multipart/form-data 上传请求的增量捕获
+//! - `webhook`: 通知事件的 Webhook 推送（带重试和 HMAC 签名）
+//! - `notification_coalesce`: 同一 Provider 同类错误的通知合并
+//! - `structured_output`: 按请求的 JSON Schema 校验结构化输出
+//! - `stats_output`: 供 CLI 和脚本使用的机器可读统计输出
+//! - `body_decode`: 按 Content-Encoding 解压上游响应体并识别非文本内容
+//! - `provider_error`: 解析各 Provider 的错误响应体为结构化错误字段
+//! - `redaction_verify`: 脱敏后扫描导出内容中残留的密钥和高熵字符串
+//! - `mitm_import`: 导入 mitmproxy 保存的 Flow 文件中的 LLM 请求
+//! - `conversation_export`: 将一次会话的多个 Flow 合并为去重后的会话记录
+//! - `disk_guard`: 磁盘剩余空间不足时暂停持久化，空间恢复后自动恢复
+//! - `slo`: 按 Provider 和模型跟踪滚动成功率，按错误预算消耗速率多窗口告警
+//! - `content_hash`: 计算内容哈希前的统一规范化（空白、时间戳、UUID、自定义正则）
+//! - `export_manifest`: 随导出数据生成清单（选项、脱敏规则、内容哈希）并校验完整性
+//! - `read_cache`: 文件存储读路径的 LRU 缓存，按内存预算淘汰并统计命中率
+//! - `inline_reasoning`: 把响应正文中标签包裹的推理过程提取到思维链，导出时可还原
+//! - `wal`: 请求预写日志，崩溃后把未完成的请求恢复为结果未知的 Flow
+//! - `mock`: 离线 Mock 模式，按内容哈希用捕获的 Flow 响应请求
 
+pub mod auto_tag;
+pub mod batch_export;
 pub mod batch_ops;
+pub mod body_decode;
 pub mod bookmark;
 pub mod code_exporter;
+pub mod content_hash;
+pub mod conversation_export;
 pub mod diff;
+pub mod disk_guard;
 pub mod enhanced_stats;
+pub mod export_manifest;
 pub mod exporter;
+pub mod field_mask;
 pub mod file_store;
 pub mod filter_parser;
+pub mod inline_reasoning;
 pub mod interceptor;
+pub mod json_path;
 pub mod memory_store;
+pub mod mitm_import;
+pub mod mock;
 pub mod models;
 pub mod monitor;
+pub mod multipart;
+pub mod notification_coalesce;
+pub mod parquet_export;
+pub mod provider_error;
 pub mod query_service;
 pub mod quick_filter;
+pub mod read_cache;
+pub mod redaction_verify;
 pub mod replayer;
+pub mod retention;
 pub mod session;
+pub mod slo;
+pub mod stats_output;
 pub mod stream_rebuilder;
+pub mod structured_output;
+pub mod wal;
+pub mod webhook;
 
 // 重新导出核心类型
 pub use models::{
+    upstream_request_id_from_headers,
     ClientInfo,
+    ContentFilterAction,
+    ContentFilterOutcome,
     ContentPart,
     FlowAnnotations,
     // 错误
@@ -45,6 +94,7 @@ pub use models::{
     FlowState,
     FlowTimestamps,
     FlowType,
+    InlineReasoningSegment,
     // 核心 Flow 结构
     LLMFlow,
     // 请求相关
@@ -54,24 +104,41 @@ pub use models::{
     Message,
     MessageContent,
     MessageRole,
+    RequestAttachment,
     RequestParameters,
+    ResponseBodyInfo,
+    ResponseLogprobs,
     RoutingInfo,
     StopReason,
     StreamChunk,
     StreamInfo,
     ThinkingContent,
+    TokenLogprob,
     TokenUsage,
     ToolCall,
     ToolCallDelta,
     ToolDefinition,
     ToolResult,
+    TopLogprob,
+    UsageSource,
+    CHAOS_TAG,
+    CONTENT_FILTERED_TAG,
+    IDEMPOTENT_REPLAY_TAG,
+    SHADOW_TAG,
+    UNKNOWN_OUTCOME_TAG,
+    UPSTREAM_REQUEST_ID_HEADERS,
 };
 
+// 重新导出响应体解码
+pub use body_decode::{decode_body, DecodedBody};
+
 // 重新导出流重建器
 pub use stream_rebuilder::{StreamFormat, StreamRebuilder, StreamRebuilderError};
 
 // 重新导出内存存储
-pub use memory_store::{FlowFilter, FlowMemoryStore, LatencyRange, TimeRange, TokenRange};
+pub use memory_store::{
+    FlowFilter, FlowMemoryStore, LatencyRange, TagMatchMode, TimeRange, TokenRange,
+};
 
 // 重新导出文件存储
 pub use file_store::{
@@ -80,28 +147,99 @@ pub use file_store::{
 
 // 重新导出查询服务
 pub use query_service::{
-    FlowQueryResult, FlowQueryService, FlowSearchResult, FlowSortBy, FlowStats, ModelStats,
-    ProviderStats, QueryWithExpressionError, StateStats,
+    FilterTestResult, FlowQueryResult, FlowQueryService, FlowSearchResult, FlowSortBy, FlowStats,
+    FlowThread, FlowThreadTurn, ModelPricing, ModelStats, ProviderStats, QueryWithExpressionError,
+    RelevanceWeights, StateStats, TokenPricing,
 };
 
 // 重新导出导出服务
 pub use exporter::{
-    default_redaction_rules, ExportFormat, ExportOptions, ExportResult, FlowExporter, HarArchive,
-    HarEntry, HarLlmExtension, HarLog, RedactionRule, Redactor,
+    canonicalize_json, default_redaction_rules, ExportError, ExportFormat, ExportOptions,
+    ExportResult, FlowExporter, HarArchive, HarEntry, HarLlmExtension, HarLog, HarStreamTiming,
+    RedactionRule, Redactor,
+};
+
+// 重新导出 Parquet 导出
+pub use parquet_export::PARQUET_COLUMNS;
+
+// 重新导出导出字段选择
+pub use field_mask::{FieldMask, FieldMaskError};
+
+// 重新导出脱敏用的 JSONPath
+pub use json_path::{JsonPath, JsonPathError};
+
+// 重新导出导出清单
+pub use export_manifest::{
+    ExportManifest, ExportVerification, ManifestExportOptions, ManifestRedactionRule,
+};
+
+// 重新导出 Provider 错误解析
+pub use provider_error::{parse_retry_after, ProviderErrorInfo};
+
+// 重新导出脱敏校验
+pub use redaction_verify::{RedactionFinding, RedactionVerificationError};
+// 重新导出 mitmproxy 导入
+pub use mitm_import::{
+    parse_mitm_flows, MitmImport, MitmImportError, MitmImportSummary, MITM_IMPORT_TAG,
 };
 
 // 重新导出监控服务
 pub use monitor::{
-    FlowEvent, FlowMonitor, FlowMonitorConfig, FlowSummary, FlowUpdate, RequestRateTracker,
-    ThresholdCheckResult, ThresholdConfig,
+    ActiveFlowCapExceeded, ActiveFlowHandle, ActiveFlowOverflow, ActiveFlowSummary,
+    CaptureDecision, CaptureSkipReason, FlowEvent, FlowMonitor, FlowMonitorConfig, FlowSummary,
+    FlowUpdate, RequestRateTracker, ThresholdCheckResult, ThresholdConfig,
+};
+
+// 重新导出磁盘空间保护
+pub use disk_guard::{DiskGuard, DiskGuardConfig, DiskStatus};
+
+// 重新导出文件存储读缓存
+pub use read_cache::{FlowReadCache, ReadCacheConfig, ReadCacheStats};
+
+// 重新导出请求预写日志
+pub use wal::RequestWal;
+
+// 重新导出离线 Mock 模式
+pub use mock::{
+    MockConfig, MockEvent, MockFallback, MockMatch, MockMatchKind, MOCK_FLOW_ID_HEADER,
+    MOCK_HEADER, MOCK_MATCH_HEADER,
+};
+
+// 重新导出内联推理提取
+pub use inline_reasoning::{
+    extract_inline_reasoning, restore_inline_reasoning, InlineReasoningConfig,
 };
 
+// 重新导出成功率 SLO 跟踪
+pub use slo::{SloConfig, SloStatus, SloTracker, SloWindowStats};
+
+// 重新导出内容哈希
+pub use content_hash::{ContentNormalizationConfig, ContentNormalizer};
+
+// 重新导出保留策略
+pub use retention::{next_retention_run, RetentionPolicy};
+
 // 重新导出过滤表达式解析器
 pub use filter_parser::{
-    get_filter_help, Comparison, ComparisonOp, FilterExpr, FilterParseError, FilterParser,
-    FilterToken, FILTER_HELP,
+    get_filter_help, Comparison, ComparisonOp, FilterExpr, FilterFieldHelp, FilterParseError,
+    FilterParser, FilterRegex, FilterToken, MatchField, FILTER_FIELD_HELP, FILTER_HELP,
+};
+
+// 重新导出 multipart 捕获
+pub use multipart::{
+    apply_multipart_attachments, build_multipart_request, capture_multipart, is_multipart,
+    MultipartCapture, MultipartCaptureConfig,
 };
 
+// 重新导出自动标签引擎
+pub use auto_tag::{AutoTagConfig, AutoTagError, AutoTagRule, AutoTagger};
+
+// 重新导出 Webhook 类型
+pub use webhook::{WebhookError, WebhookSettings, WebhookSink, SIGNATURE_HEADER};
+
+// 重新导出错误通知合并
+pub use notification_coalesce::{CoalescedErrors, CoalescingSettings, ErrorCoalescer};
+
 // 重新导出拦截器
 pub use interceptor::{
     FlowInterceptor, InterceptAction, InterceptConfig, InterceptEvent, InterceptState,
@@ -110,12 +248,14 @@ pub use interceptor::{
 
 // 重新导出重放器
 pub use replayer::{
-    BatchReplayResult, FlowReplayer, ReplayConfig, ReplayResult, ReplayerError, RequestModification,
+    BatchReplayResult, FlowReplayer, LatencyDistribution, ReplayConfig, ReplayResult,
+    ReplayStreamEvent, ReplayerError, RequestModification, StopCondition,
 };
 
 // 重新导出差异对比器
 pub use diff::{
-    DiffConfig, DiffItem, DiffType, FlowDiff, FlowDiffResult, MessageDiffItem, TokenDiff,
+    DiffConfig, DiffItem, DiffType, FlowDiff, FlowDiffResult, LogprobDiff, LogprobDiffItem,
+    MessageDiffItem, StreamChunkDiff, StreamChunkDiffItem, TokenDiff,
 };
 
 // 重新导出会话管理器
@@ -136,13 +276,23 @@ pub use code_exporter::{CodeExporter, CodeFormat};
 pub use bookmark::{BookmarkError, BookmarkExport, BookmarkManager, FlowBookmark};
 
 // 重新导出增强统计服务
+pub use batch_export::{export_batch_jsonl, BatchTarget};
+pub use conversation_export::{ConversationTranscript, ConversationTurn};
+
 pub use enhanced_stats::{
-    Distribution, EnhancedStats, EnhancedStatsService, ReportFormat, StatsTimeRange,
-    TimeSeriesPoint, TrendData,
+    CacheEffectiveness, Distribution, EnhancedStats, EnhancedStatsService, ModelPrice,
+    PromptClusterRole, PromptTemplateOptions, PromptTemplateReport, PromptTemplateStats,
+    ReportFormat, StatsTimeRange, TimeSeriesPoint, TrendData,
 };
 
+// 重新导出机器可读统计输出
+pub use stats_output::{StatsOutputFormat, StatsReport, STATS_SCHEMA_VERSION};
+
 // 重新导出批量操作服务
-pub use batch_ops::{BatchOperation, BatchOperations, BatchOpsError, BatchResult};
+pub use batch_ops::{
+    BatchOperation, BatchOperations, BatchOpsError, BatchResult, DeleteByFilterResult,
+    DeletePreview, FlowOp, FlowOpResult,
+};
 
 // 重新导出 ProviderType（从 lib.rs）
 pub use crate::ProviderType;
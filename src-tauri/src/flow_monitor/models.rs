@@ -6,6 +6,9 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use super::provider_error::ProviderErrorInfo;
+use crate::resilience::is_overloaded_status;
+use crate::router::{AffinityOutcome, HistoryTruncation, ModelDowngrade, RoutingTrace};
 use crate::ProviderType;
 
 // ============================================================================
@@ -62,6 +65,8 @@ impl LLMFlow {
                 response_end: None,
                 duration_ms: 0,
                 ttfb_ms: None,
+                pipeline_ms: None,
+                generation_ms: None,
             },
             state: FlowState::Pending,
             annotations: FlowAnnotations::default(),
@@ -74,12 +79,18 @@ impl LLMFlow {
 pub enum FlowType {
     /// OpenAI Chat Completions
     ChatCompletions,
+    /// OpenAI Responses
+    Responses,
     /// Anthropic Messages
     AnthropicMessages,
     /// Gemini Generate Content
     GeminiGenerateContent,
     /// Embeddings
     Embeddings,
+    /// 音频转写/翻译（multipart 上传）
+    AudioTranscription,
+    /// 文件上传（multipart 上传）
+    FileUpload,
     /// 其他类型
     Other(String),
 }
@@ -142,6 +153,9 @@ pub struct LLMRequest {
     pub size_bytes: usize,
     /// 请求开始时间戳
     pub timestamp: DateTime<Utc>,
+    /// multipart 上传的附件（字段和文件摘要）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<RequestAttachment>,
 }
 
 impl Default for LLMRequest {
@@ -159,12 +173,59 @@ impl Default for LLMRequest {
             parameters: RequestParameters::default(),
             size_bytes: 0,
             timestamp: Utc::now(),
+            attachments: Vec::new(),
         }
     }
 }
 
+impl LLMRequest {
+    /// 是否请求了结构化输出（JSON 模式或 JSON Schema）
+    pub fn is_structured_output(&self) -> bool {
+        self.parameters
+            .response_format
+            .as_ref()
+            .and_then(|format| format.get("type"))
+            .and_then(|t| t.as_str())
+            .is_some_and(|t| t == "json_object" || t == "json_schema")
+    }
+}
+
+/// 请求附件
+///
+/// 记录 multipart/form-data 请求中每个 part 的摘要信息。
+/// 文件内容默认不保存，只有在配置允许时才以 base64 形式保存（并受大小上限约束）。
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RequestAttachment {
+    /// 表单字段名
+    pub field_name: String,
+    /// 文件名（文件字段才有）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    /// Content-Type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// 内容大小（字节）
+    pub size_bytes: u64,
+    /// 文本字段的值（非文件字段）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// 文件内容（base64，仅在配置允许时保存）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    /// 保存的内容是否被截断
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl RequestAttachment {
+    /// 是否为文件字段
+    pub fn is_file(&self) -> bool {
+        self.file_name.is_some()
+    }
+}
+
 /// 消息结构
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
     /// 消息角色
     pub role: MessageRole,
@@ -213,7 +274,7 @@ impl Default for MessageRole {
 }
 
 /// 消息内容（支持多模态）
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum MessageContent {
     /// 纯文本内容
@@ -257,7 +318,7 @@ impl MessageContent {
 }
 
 /// 内容部分（多模态消息的组成部分）
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ContentPart {
     /// 文本部分
@@ -276,7 +337,7 @@ pub enum ContentPart {
 }
 
 /// 图片 URL
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ImageUrl {
     pub url: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -307,7 +368,7 @@ pub struct FunctionDefinition {
 }
 
 /// 工具调用
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolCall {
     /// 工具调用 ID
     pub id: String,
@@ -316,10 +377,13 @@ pub struct ToolCall {
     pub tool_type: String,
     /// 函数调用详情
     pub function: FunctionCall,
+    /// 流式参数是否被截断（`arguments` 为补全后的 JSON，无法补全时为原始片段）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub arguments_truncated: bool,
 }
 
 /// 函数调用
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FunctionCall {
     /// 函数名称
     pub name: String,
@@ -328,7 +392,7 @@ pub struct FunctionCall {
 }
 
 /// 工具结果
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolResult {
     /// 工具调用 ID
     pub tool_call_id: String,
@@ -357,6 +421,12 @@ pub struct RequestParameters {
     /// 是否流式响应
     #[serde(default)]
     pub stream: bool,
+    /// 响应格式（OpenAI `response_format`，如 JSON 模式或 JSON Schema 结构化输出）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
+    /// 随机种子（用于可复现的采样）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
     /// 其他参数
     #[serde(flatten)]
     pub extra: HashMap<String, serde_json::Value>,
@@ -385,8 +455,11 @@ pub struct LLMResponse {
     pub tool_calls: Vec<ToolCall>,
     /// Token 使用统计
     pub usage: TokenUsage,
-    /// 停止原因
+    /// 停止原因（已规范化，见 [`StopReason::from_raw`]）
     pub stop_reason: Option<StopReason>,
+    /// 上游返回的原始停止原因（如 `end_turn`、`SAFETY`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_stop_reason: Option<String>,
     /// 响应体大小（字节）
     pub size_bytes: usize,
     /// 响应开始时间戳
@@ -395,6 +468,24 @@ pub struct LLMResponse {
     pub timestamp_end: DateTime<Utc>,
     /// 流式响应信息（如果是流式）
     pub stream_info: Option<StreamInfo>,
+    /// 内容是否符合请求中的 JSON Schema（未提供 Schema 时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_valid: Option<bool>,
+    /// Token 级对数概率（请求了 `logprobs` 时才有）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<ResponseLogprobs>,
+    /// 上游响应体的内容类型和解码结果
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_info: Option<ResponseBodyInfo>,
+    /// 上游后端配置指纹（OpenAI `system_fingerprint`，与 `seed` 一起用于复现分析）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
+    /// 单个 Flow 内的工具往返序列（流中包含内联工具结果时才有）
+    ///
+    /// 按出现顺序排列：助手消息（文本和工具调用）、工具结果消息、后续助手消息……
+    /// 每个工具结果结束当前助手轮次，结果之后的文本或工具调用开始新的助手轮次。
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub turns: Vec<Message>,
 }
 
 impl Default for LLMResponse {
@@ -410,10 +501,180 @@ impl Default for LLMResponse {
             tool_calls: Vec::new(),
             usage: TokenUsage::default(),
             stop_reason: None,
+            raw_stop_reason: None,
             size_bytes: 0,
             timestamp_start: now,
             timestamp_end: now,
             stream_info: None,
+            schema_valid: None,
+            logprobs: None,
+            body_info: None,
+            system_fingerprint: None,
+            turns: Vec::new(),
+        }
+    }
+}
+
+impl LLMResponse {
+    /// 规范化停止原因
+    ///
+    /// 未记录原始值时从响应体中提取（OpenAI `choices[0].finish_reason`、
+    /// Anthropic `stop_reason`、Gemini `candidates[0].finishReason`），
+    /// 再映射为统一的 [`StopReason`]。
+    pub fn normalize_stop_reason(&mut self) {
+        if self.raw_stop_reason.is_none() {
+            self.raw_stop_reason =
+                raw_stop_reason_from_body(&self.body).or_else(|| match &self.stop_reason {
+                    Some(StopReason::Other(raw)) => Some(raw.clone()),
+                    _ => None,
+                });
+        }
+        self.stop_reason = match &self.raw_stop_reason {
+            Some(raw) => Some(StopReason::from_raw(raw)),
+            None => self.stop_reason.take().map(StopReason::normalized),
+        };
+    }
+
+    /// 未记录缓存用量时，从响应体的 `usage` / `usageMetadata` 中解析
+    pub fn fill_cache_usage(&mut self) {
+        if self.usage.has_cache_usage() {
+            return;
+        }
+        if let Some(usage) = self
+            .body
+            .get("usage")
+            .or_else(|| self.body.get("usageMetadata"))
+        {
+            self.usage.apply_cache_usage(usage);
+        }
+    }
+
+    /// 未记录对数概率时从响应体中提取，并截断到 `max_tokens` 个 Token
+    ///
+    /// `max_tokens` 为 0 时不保存对数概率。
+    pub fn capture_logprobs(&mut self, max_tokens: usize) {
+        if self.logprobs.is_none() {
+            self.logprobs = ResponseLogprobs::from_body(&self.body);
+        }
+        if max_tokens == 0 {
+            self.logprobs = None;
+        } else if let Some(logprobs) = self.logprobs.as_mut() {
+            logprobs.truncate(max_tokens);
+        }
+    }
+
+    /// 未记录系统指纹时从响应体（`system_fingerprint`）中提取
+    pub fn capture_system_fingerprint(&mut self) {
+        if self.system_fingerprint.is_none() {
+            self.system_fingerprint = self
+                .body
+                .get("system_fingerprint")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+                .map(str::to_string);
+        }
+    }
+
+    /// 从响应头中提取上游请求 ID（忽略大小写）
+    pub fn upstream_request_id(&self) -> Option<String> {
+        UPSTREAM_REQUEST_ID_HEADERS.iter().find_map(|name| {
+            self.headers
+                .iter()
+                .find(|(key, value)| key.eq_ignore_ascii_case(name) && !value.trim().is_empty())
+                .map(|(_, value)| value.trim().to_string())
+        })
+    }
+}
+
+/// 上游响应体的内容类型和解码结果
+///
+/// 压缩的响应体按 `Content-Encoding` 解压后再解析；非文本内容不保存文本，
+/// 只记录说明和字节数。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ResponseBodyInfo {
+    /// 原始 `Content-Type`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// 原始 `Content-Encoding`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<String>,
+    /// 解码是否成功（未压缩时为 true）
+    pub decoded: bool,
+    /// 是否为非文本内容
+    #[serde(default)]
+    pub binary: bool,
+    /// 收到的字节数（解压前）
+    pub encoded_size_bytes: usize,
+    /// 解压后的字节数
+    pub decoded_size_bytes: usize,
+    /// 说明（解码失败原因、二进制内容或无效 UTF-8）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// 候选 Token 的对数概率
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TopLogprob {
+    /// Token 文本
+    pub token: String,
+    /// 对数概率
+    pub logprob: f64,
+}
+
+/// 单个输出 Token 的对数概率
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TokenLogprob {
+    /// Token 文本
+    pub token: String,
+    /// 对数概率
+    pub logprob: f64,
+    /// 概率最高的候选 Token（请求了 `top_logprobs` 时才有）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+/// 响应的 Token 级对数概率
+///
+/// 对应 OpenAI `choices[0].logprobs.content`，只保留首个 choice。
+/// 保存的 Token 数受 `max_logprob_tokens` 限制，超出部分被丢弃并标记为已截断。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ResponseLogprobs {
+    /// 输出 Token 的对数概率（按输出顺序）
+    pub tokens: Vec<TokenLogprob>,
+    /// 上游返回的 Token 总数（截断前）
+    pub total_tokens: usize,
+    /// 是否因超出上限被截断
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl ResponseLogprobs {
+    /// 从 OpenAI 响应体（`choices[0].logprobs`）中提取
+    pub fn from_body(body: &serde_json::Value) -> Option<Self> {
+        let logprobs = body.get("choices")?.get(0)?.get("logprobs")?;
+        let mut result = Self::default();
+        result.extend(logprobs);
+        (result.total_tokens > 0).then_some(result)
+    }
+
+    /// 追加一段 `logprobs` 对象（流式响应的每个 chunk 各带一段）
+    pub fn extend(&mut self, logprobs: &serde_json::Value) {
+        let Some(content) = logprobs.get("content").and_then(|c| c.as_array()) else {
+            return;
+        };
+        for entry in content {
+            if let Ok(token) = serde_json::from_value::<TokenLogprob>(entry.clone()) {
+                self.tokens.push(token);
+                self.total_tokens += 1;
+            }
+        }
+    }
+
+    /// 截断到最多 `max_tokens` 个 Token
+    pub fn truncate(&mut self, max_tokens: usize) {
+        if self.tokens.len() > max_tokens {
+            self.tokens.truncate(max_tokens);
+            self.truncated = true;
         }
     }
 }
@@ -429,12 +690,24 @@ pub struct ThinkingContent {
     /// 签名（用于验证）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signature: Option<String>,
+    /// 从正文中提取的内联推理片段（用于还原原始正文）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inline_segments: Vec<InlineReasoningSegment>,
+}
+
+/// 从响应正文中移除的内联推理片段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InlineReasoningSegment {
+    /// 片段在提取后正文中的位置（字节偏移）
+    pub offset: usize,
+    /// 原始片段（含标签及其后的空白）
+    pub raw: String,
 }
 
 /// Token 使用统计
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct TokenUsage {
-    /// 输入 Token 数
+    /// 输入 Token 数（含缓存读取和写入的部分）
     pub input_tokens: u32,
     /// 输出 Token 数
     pub output_tokens: u32,
@@ -456,28 +729,172 @@ impl TokenUsage {
     pub fn calculate_total(&mut self) {
         self.total_tokens = self.input_tokens + self.output_tokens;
     }
+
+    /// 从上游 `usage` 对象中解析提示词缓存用量
+    ///
+    /// 支持 Anthropic（`cache_read_input_tokens` / `cache_creation_input_tokens`）、
+    /// OpenAI（`prompt_tokens_details.cached_tokens`）、DeepSeek（`prompt_cache_hit_tokens`）
+    /// 和 Gemini（`cachedContentTokenCount`）。Anthropic 的 `input_tokens` 不含缓存部分，
+    /// 这里将缓存部分计入输入 Token，使各 Provider 的输入 Token 含义一致。
+    pub fn apply_cache_usage(&mut self, usage: &serde_json::Value) {
+        let read = |pointer: &str| {
+            usage
+                .pointer(pointer)
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32)
+        };
+
+        let anthropic_read = read("/cache_read_input_tokens");
+        let anthropic_write = read("/cache_creation_input_tokens");
+        if anthropic_read.is_some() || anthropic_write.is_some() {
+            let cache_read = anthropic_read.unwrap_or(0);
+            let cache_write = anthropic_write.unwrap_or(0);
+            self.cache_read_tokens = Some(cache_read);
+            self.cache_write_tokens = Some(cache_write);
+            // 同一响应中可能多次出现（message_start / message_delta），取较大值保证幂等
+            let input = read("/input_tokens").unwrap_or(0) + cache_read + cache_write;
+            if input > self.input_tokens {
+                self.total_tokens += input - self.input_tokens;
+                self.input_tokens = input;
+            }
+            return;
+        }
+
+        if let Some(cache_read) = [
+            "/prompt_tokens_details/cached_tokens",
+            "/input_tokens_details/cached_tokens",
+            "/prompt_cache_hit_tokens",
+            "/cachedContentTokenCount",
+            "/cache_read_tokens",
+        ]
+        .into_iter()
+        .find_map(read)
+        {
+            self.cache_read_tokens = Some(cache_read);
+        }
+        if let Some(cache_write) =
+            read("/cache_creation_tokens").or_else(|| read("/cache_write_tokens"))
+        {
+            self.cache_write_tokens = Some(cache_write);
+        }
+    }
+
+    /// 是否上报了缓存用量
+    pub fn has_cache_usage(&self) -> bool {
+        self.cache_read_tokens.is_some() || self.cache_write_tokens.is_some()
+    }
+
+    /// 是否命中提示词缓存
+    pub fn is_cache_hit(&self) -> bool {
+        self.cache_read_tokens.is_some_and(|tokens| tokens > 0)
+    }
+
+    /// 缓存命中率（缓存读取 Token / 输入 Token）
+    ///
+    /// 上游未上报缓存用量或没有输入 Token 时返回 `None`。
+    pub fn cache_hit_ratio(&self) -> Option<f64> {
+        if !self.has_cache_usage() || self.input_tokens == 0 {
+            return None;
+        }
+        let cache_read = self.cache_read_tokens.unwrap_or(0) as f64;
+        Some((cache_read / self.input_tokens as f64).min(1.0))
+    }
+}
+
+/// Token 用量来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageSource {
+    /// 输出 Token 按内容长度估算（输入 Token 可能来自上游）
+    #[default]
+    Estimated,
+    /// 上游在流末尾返回了输出 Token 的准确用量
+    Upstream,
 }
 
 /// 停止原因
+///
+/// 新捕获的响应只会出现 `Stop`、`Length`、`ToolCalls`、`ContentFilter` 和 `Other`，
+/// `FunctionCall` 与 `EndTurn` 仅用于兼容旧数据，统计前通过 [`StopReason::normalized`] 归并。
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum StopReason {
-    /// 正常结束
+    /// 正常结束（含命中停止序列）
     Stop,
     /// 达到最大长度
     Length,
     /// 工具调用
     ToolCalls,
-    /// 内容过滤
+    /// 内容过滤（安全策略拦截、拒绝回答等）
     ContentFilter,
     /// 函数调用（兼容旧版）
     FunctionCall,
-    /// 结束 Token
+    /// 结束 Token（兼容旧版）
     EndTurn,
     /// 其他原因
     Other(String),
 }
 
+impl StopReason {
+    /// 将各 Provider 的原始停止原因映射为统一的停止原因（忽略大小写）
+    ///
+    /// | 统一值 | OpenAI | Anthropic | Gemini |
+    /// |--------|--------|-----------|--------|
+    /// | `Stop` | `stop` | `end_turn`、`stop_sequence` | `STOP` |
+    /// | `Length` | `length` | `max_tokens`、`model_context_window_exceeded` | `MAX_TOKENS` |
+    /// | `ToolCalls` | `tool_calls`、`function_call` | `tool_use` | `FUNCTION_CALL` |
+    /// | `ContentFilter` | `content_filter` | `refusal` | `SAFETY`、`RECITATION`、`BLOCKLIST`、`PROHIBITED_CONTENT`、`SPII`、`IMAGE_SAFETY` |
+    ///
+    /// 其余值（如 Anthropic `pause_turn`）保留为 `Other`。
+    pub fn from_raw(raw: &str) -> Self {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "stop" | "end_turn" | "stop_sequence" => Self::Stop,
+            "length" | "max_tokens" | "model_context_window_exceeded" => Self::Length,
+            "tool_calls" | "tool_use" | "function_call" => Self::ToolCalls,
+            "content_filter" | "refusal" | "safety" | "recitation" | "blocklist"
+            | "prohibited_content" | "spii" | "image_safety" => Self::ContentFilter,
+            _ => Self::Other(raw.trim().to_string()),
+        }
+    }
+
+    /// 归并兼容旧版的取值
+    pub fn normalized(self) -> Self {
+        match self {
+            Self::EndTurn => Self::Stop,
+            Self::FunctionCall => Self::ToolCalls,
+            Self::Other(raw) => Self::from_raw(&raw),
+            other => other,
+        }
+    }
+
+    /// 统计与展示使用的名称
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Stop => "stop",
+            Self::Length => "length",
+            Self::ToolCalls => "tool_calls",
+            Self::ContentFilter => "content_filter",
+            Self::FunctionCall => "function_call",
+            Self::EndTurn => "end_turn",
+            Self::Other(raw) => raw,
+        }
+    }
+}
+
+/// 从响应体中提取原始停止原因
+fn raw_stop_reason_from_body(body: &serde_json::Value) -> Option<String> {
+    [
+        body.pointer("/choices/0/finish_reason"),
+        body.get("stop_reason"),
+        body.pointer("/candidates/0/finishReason"),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|v| v.as_str())
+    .filter(|v| !v.trim().is_empty())
+    .map(str::to_string)
+}
+
 /// 流式响应信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamInfo {
@@ -490,6 +907,16 @@ pub struct StreamInfo {
     /// 原始 Chunks（可选，根据配置决定是否保存）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub raw_chunks: Option<Vec<StreamChunk>>,
+    /// 流是否以终止事件正常结束（OpenAI `[DONE]`、Anthropic `message_stop`、Gemini 停止原因）
+    ///
+    /// 为 `false` 时响应可能被截断，Flow 会带有 `IncompleteStream` 错误。
+    #[serde(default = "default_completed_cleanly")]
+    pub completed_cleanly: bool,
+}
+
+/// 旧数据没有记录该字段，视为正常结束
+fn default_completed_cleanly() -> bool {
+    true
 }
 
 /// 流式 Chunk
@@ -555,9 +982,90 @@ pub struct FlowMetadata {
     /// 注入的参数
     #[serde(skip_serializing_if = "Option::is_none")]
     pub injected_params: Option<HashMap<String, serde_json::Value>>,
+    /// 客户端未发送、按模型默认值填充的参数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defaulted_params: Option<HashMap<String, serde_json::Value>>,
     /// 上下文使用百分比
     #[serde(skip_serializing_if = "Option::is_none")]
     pub context_usage_percentage: Option<f32>,
+    /// 影子 Flow 对应的主 Flow ID（仅影子镜像请求设置）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow_of: Option<String>,
+    /// 幂等重放 Flow 对应的原始 Flow ID（仅 `Idempotency-Key` 命中缓存时设置）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent_replay_of: Option<String>,
+    /// 上游返回的请求 ID（如 OpenAI 的 `x-request-id`），向 Provider 反馈问题时用于对应请求
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_request_id: Option<String>,
+    /// 流式响应 Token 用量的来源（非流式响应为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_source: Option<UsageSource>,
+    /// 内容过滤结果（未命中任何规则时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_filter: Option<ContentFilterOutcome>,
+}
+
+/// 影子 Flow 的标签
+pub const SHADOW_TAG: &str = "shadow";
+
+/// 故障注入 Flow 的标签
+pub const CHAOS_TAG: &str = "chaos";
+
+/// 响应被内容过滤修改或阻止的 Flow 的标签
+pub const CONTENT_FILTERED_TAG: &str = "content_filtered";
+
+/// 幂等键命中缓存、未调用上游而直接返回的 Flow 的标签
+pub const IDEMPOTENT_REPLAY_TAG: &str = "idempotent_replay";
+
+/// 从预写日志恢复、上游结果未知的 Flow 的标签
+pub const UNKNOWN_OUTCOME_TAG: &str = "unknown_outcome";
+
+/// 内容过滤动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentFilterAction {
+    /// 把匹配替换为占位文本
+    #[default]
+    Redact,
+    /// 用占位内容替换整个响应
+    Block,
+}
+
+/// 内容过滤结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentFilterOutcome {
+    /// 最终动作（任一阻止规则命中即为 `Block`）
+    pub action: ContentFilterAction,
+    /// 命中的规则名称（按首次命中顺序）
+    pub rules: Vec<String>,
+    /// 脱敏的匹配次数
+    #[serde(default)]
+    pub redactions: usize,
+}
+
+/// 上游携带请求 ID 的响应头（按优先级排列）
+///
+/// - `x-request-id`: OpenAI 及多数兼容服务
+/// - `request-id`: Anthropic
+/// - `x-amzn-requestid`: AWS（Kiro / CodeWhisperer）
+/// - `x-goog-request-id`: Google
+pub const UPSTREAM_REQUEST_ID_HEADERS: &[&str] = &[
+    "x-request-id",
+    "request-id",
+    "x-amzn-requestid",
+    "x-goog-request-id",
+];
+
+/// 从上游响应头中提取请求 ID
+pub fn upstream_request_id_from_headers(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    UPSTREAM_REQUEST_ID_HEADERS.iter().find_map(|name| {
+        headers
+            .get(*name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    })
 }
 
 impl Default for FlowMetadata {
@@ -570,7 +1078,13 @@ impl Default for FlowMetadata {
             client_info: ClientInfo::default(),
             routing_info: RoutingInfo::default(),
             injected_params: None,
+            defaulted_params: None,
             context_usage_percentage: None,
+            shadow_of: None,
+            idempotent_replay_of: None,
+            upstream_request_id: None,
+            usage_source: None,
+            content_filter: None,
         }
     }
 }
@@ -587,6 +1101,9 @@ pub struct ClientInfo {
     /// 请求 ID
     #[serde(skip_serializing_if = "Option::is_none")]
     pub request_id: Option<String>,
+    /// 调用方 API Key 的身份标签
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_label: Option<String>,
 }
 
 /// 路由信息
@@ -601,19 +1118,47 @@ pub struct RoutingInfo {
     /// 负载均衡策略
     #[serde(skip_serializing_if = "Option::is_none")]
     pub load_balance_strategy: Option<String>,
+    /// 会话亲和使用的会话键
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_key: Option<String>,
+    /// 会话亲和结果
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_affinity: Option<AffinityOutcome>,
+    /// 按请求大小的模型降级
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_downgrade: Option<ModelDowngrade>,
+    /// 转发前的消息历史截断
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_truncation: Option<HistoryTruncation>,
+    /// 模型解析轨迹（别名 → 中间映射 → 最终模型，以及命中的路由规则）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_trace: Option<RoutingTrace>,
+    /// 客户端要求按字面模型名转发（`x-no-remap`），跳过了别名解析和降级规则
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub remap_bypassed: bool,
+    /// Provider 配置的附加上游请求头（敏感头的值已脱敏）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extra_headers: HashMap<String, String>,
 }
 
 /// 时间戳集合
+///
+/// 各阶段耗时的划分：
+/// - `pipeline_ms`：`request_start` → `request_end`，代理内部的路由、凭证选择、参数注入等
+/// - `ttfb_ms`：`request_start` → `response_start`，包含内部处理和等待上游开始响应
+/// - `generation_ms`：`response_start` → `response_end`，上游生成并传输响应
+///
+/// 非流式响应的首字节与响应体同时到达，生成时间计入 TTFB。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FlowTimestamps {
     /// 创建时间
     pub created: DateTime<Utc>,
-    /// 请求开始时间
+    /// 请求开始时间（代理收到请求）
     pub request_start: DateTime<Utc>,
-    /// 请求结束时间
+    /// 请求结束时间（请求发往上游）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub request_end: Option<DateTime<Utc>>,
-    /// 响应开始时间
+    /// 响应开始时间（收到上游首字节）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub response_start: Option<DateTime<Utc>>,
     /// 响应结束时间
@@ -624,6 +1169,12 @@ pub struct FlowTimestamps {
     /// 首字节时间（毫秒）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ttfb_ms: Option<u64>,
+    /// 代理内部处理耗时（毫秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pipeline_ms: Option<u64>,
+    /// 生成耗时（毫秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation_ms: Option<u64>,
 }
 
 impl Default for FlowTimestamps {
@@ -637,6 +1188,8 @@ impl Default for FlowTimestamps {
             response_end: None,
             duration_ms: 0,
             ttfb_ms: None,
+            pipeline_ms: None,
+            generation_ms: None,
         }
     }
 }
@@ -655,6 +1208,16 @@ impl FlowTimestamps {
             self.ttfb_ms = Some((start - self.request_start).num_milliseconds().max(0) as u64);
         }
     }
+
+    /// 计算各阶段耗时（内部处理、生成）
+    pub fn calculate_breakdown(&mut self) {
+        if let Some(end) = self.request_end {
+            self.pipeline_ms = Some((end - self.request_start).num_milliseconds().max(0) as u64);
+        }
+        if let (Some(start), Some(end)) = (self.response_start, self.response_end) {
+            self.generation_ms = Some((end - start).num_milliseconds().max(0) as u64);
+        }
+    }
 }
 
 /// 用户标注
@@ -669,9 +1232,15 @@ pub struct FlowAnnotations {
     /// 标签
     #[serde(default)]
     pub tags: Vec<String>,
+    /// 自动标签（由自动标签规则生成，与手动标签分开存放）
+    #[serde(default)]
+    pub auto_tags: Vec<String>,
     /// 是否收藏
     #[serde(default)]
     pub starred: bool,
+    /// 优先级（越大越靠前，用于相关度排序，默认 0）
+    #[serde(default)]
+    pub priority: i32,
 }
 
 // ============================================================================
@@ -695,6 +1264,18 @@ pub struct FlowError {
     pub timestamp: DateTime<Utc>,
     /// 是否可重试
     pub retryable: bool,
+    /// Provider 返回的错误类型（如 `invalid_request_error`、`rate_limit_error`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_error_type: Option<String>,
+    /// Provider 返回的错误码（如 `context_length_exceeded`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_error_code: Option<String>,
+    /// Provider 指出的出错参数（如 `messages`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_error_param: Option<String>,
+    /// 建议的重试等待（秒，来自 `Retry-After` 响应头或错误消息）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
 }
 
 impl FlowError {
@@ -707,6 +1288,10 @@ impl FlowError {
             raw_response: None,
             timestamp: Utc::now(),
             retryable: false,
+            provider_error_type: None,
+            provider_error_code: None,
+            provider_error_param: None,
+            retry_after: None,
         }
     }
 
@@ -727,10 +1312,42 @@ impl FlowError {
         self.retryable = retryable;
         self
     }
+
+    /// 解析 Provider 错误响应体，填充结构化字段并按错误内容修正错误类型
+    ///
+    /// 无法解析的响应体只作为原始响应保存。
+    pub fn with_provider_body(mut self, body: &str) -> Self {
+        if self.raw_response.is_none() {
+            self.raw_response = Some(body.to_string());
+        }
+        let Some(info) = ProviderErrorInfo::parse(body) else {
+            return self;
+        };
+
+        if let Some(error_type) = info.classify() {
+            self.error_type = error_type;
+        }
+        if let Some(message) = info.message {
+            self.message = message;
+        }
+        self.provider_error_type = info.error_type;
+        self.provider_error_code = info.code;
+        self.provider_error_param = info.param;
+        self.retry_after = self.retry_after.or(info.retry_after);
+        self
+    }
+
+    /// 设置建议的重试等待（秒）
+    pub fn with_retry_after(mut self, retry_after: Option<u64>) -> Self {
+        if retry_after.is_some() {
+            self.retry_after = retry_after;
+        }
+        self
+    }
 }
 
 /// 错误类型
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum FlowErrorType {
     /// 网络错误
@@ -745,6 +1362,8 @@ pub enum FlowErrorType {
     ContentFilter,
     /// 服务器错误
     ServerError,
+    /// 上游过载（503、Anthropic 529）
+    Overloaded,
     /// 请求错误
     BadRequest,
     /// 模型不可用
@@ -753,6 +1372,12 @@ pub enum FlowErrorType {
     TokenLimitExceeded,
     /// 请求被取消（用户拦截后取消）
     Cancelled,
+    /// 请求被路由闸门拒绝（维护期间暂停路由）
+    GateRejected,
+    /// 流在终止事件之前结束（响应可能被截断）
+    IncompleteStream,
+    /// 进程在请求结束前退出，上游结果未知（从预写日志恢复）
+    UnknownOutcome,
     /// 其他错误
     Other,
 }
@@ -771,11 +1396,27 @@ impl FlowErrorType {
             429 => FlowErrorType::RateLimit,
             400 => FlowErrorType::BadRequest,
             404 => FlowErrorType::ModelUnavailable,
+            code if is_overloaded_status(code) => FlowErrorType::Overloaded,
             500..=599 => FlowErrorType::ServerError,
             _ => FlowErrorType::Other,
         }
     }
 
+    /// 根据 HTTP 状态码和错误响应体推断错误类型
+    ///
+    /// 响应体可解析且能分类时以响应体为准（如 400 + `rate_limit` → `RateLimit`），
+    /// 否则回退到 [`FlowErrorType::from_status_code`]。503 / 529 的响应体被归为一般服务器错误时
+    /// 仍按过载处理。
+    pub fn from_status_and_body(code: u16, body: &str) -> Self {
+        match ProviderErrorInfo::parse(body).and_then(|info| info.classify()) {
+            Some(FlowErrorType::ServerError) if is_overloaded_status(code) => {
+                FlowErrorType::Overloaded
+            }
+            Some(error_type) => error_type,
+            None => Self::from_status_code(code),
+        }
+    }
+
     /// 判断是否可重试
     pub fn is_retryable(&self) -> bool {
         matches!(
@@ -784,6 +1425,7 @@ impl FlowErrorType {
                 | FlowErrorType::Timeout
                 | FlowErrorType::RateLimit
                 | FlowErrorType::ServerError
+                | FlowErrorType::Overloaded
         )
     }
 }
@@ -796,6 +1438,52 @@ impl FlowErrorType {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_flow_error_with_provider_body() {
+        let body = r#"{"error":{"message":"Rate limit reached for requests","type":"requests","param":null,"code":"rate_limit_exceeded"}}"#;
+        assert_eq!(
+            FlowErrorType::from_status_and_body(400, body),
+            FlowErrorType::RateLimit
+        );
+        assert_eq!(
+            FlowErrorType::from_status_and_body(400, "bad"),
+            FlowErrorType::BadRequest
+        );
+
+        let error = FlowError::new(FlowErrorType::from_status_code(400), body)
+            .with_status_code(400)
+            .with_retry_after(Some(7))
+            .with_provider_body(body);
+        assert_eq!(error.error_type, FlowErrorType::RateLimit);
+        assert_eq!(error.message, "Rate limit reached for requests");
+        assert_eq!(error.provider_error_type.as_deref(), Some("requests"));
+        assert_eq!(
+            error.provider_error_code.as_deref(),
+            Some("rate_limit_exceeded")
+        );
+        assert_eq!(error.retry_after, Some(7));
+        assert_eq!(error.raw_response.as_deref(), Some(body));
+    }
+
+    #[test]
+    fn test_upstream_request_id_from_headers() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert!(upstream_request_id_from_headers(&headers).is_none());
+
+        headers.insert("x-amzn-requestid", "aws-123".parse().unwrap());
+        assert_eq!(
+            upstream_request_id_from_headers(&headers).as_deref(),
+            Some("aws-123")
+        );
+
+        // OpenAI 的 x-request-id 优先
+        headers.insert("X-Request-Id", "req_abc".parse().unwrap());
+        assert_eq!(
+            upstream_request_id_from_headers(&headers).as_deref(),
+            Some("req_abc")
+        );
+    }
+
     #[test]
     fn test_flow_creation() {
         let request = LLMRequest {
@@ -870,6 +1558,14 @@ mod tests {
             FlowErrorType::from_status_code(500),
             FlowErrorType::ServerError
         );
+        assert_eq!(
+            FlowErrorType::from_status_code(529),
+            FlowErrorType::Overloaded
+        );
+        assert_eq!(
+            FlowErrorType::from_status_code(503),
+            FlowErrorType::Overloaded
+        );
         assert_eq!(FlowErrorType::from_status_code(200), FlowErrorType::Other);
     }
 
@@ -897,6 +1593,8 @@ mod tests {
             response_end: Some(end),
             duration_ms: 0,
             ttfb_ms: None,
+            pipeline_ms: None,
+            generation_ms: None,
         };
 
         timestamps.calculate_duration();
@@ -906,6 +1604,33 @@ mod tests {
         assert_eq!(timestamps.ttfb_ms, Some(100));
     }
 
+    #[test]
+    fn test_flow_timestamps_breakdown() {
+        let start = Utc::now();
+        let mut timestamps = FlowTimestamps {
+            request_start: start,
+            request_end: Some(start + chrono::Duration::milliseconds(30)),
+            ..Default::default()
+        };
+
+        // 尚未收到响应时只有内部处理耗时
+        timestamps.calculate_breakdown();
+        assert_eq!(timestamps.pipeline_ms, Some(30));
+        assert_eq!(timestamps.generation_ms, None);
+
+        timestamps.response_start = Some(start + chrono::Duration::milliseconds(230));
+        timestamps.response_end = Some(start + chrono::Duration::milliseconds(1230));
+        timestamps.calculate_duration();
+        timestamps.calculate_ttfb();
+        timestamps.calculate_breakdown();
+        assert_eq!(timestamps.ttfb_ms, Some(230));
+        assert_eq!(timestamps.generation_ms, Some(1000));
+        assert_eq!(
+            timestamps.ttfb_ms.unwrap() + timestamps.generation_ms.unwrap(),
+            timestamps.duration_ms
+        );
+    }
+
     #[test]
     fn test_flow_error_builder() {
         let error = FlowError::new(FlowErrorType::RateLimit, "Too many requests")
@@ -933,6 +1658,174 @@ mod tests {
         assert_eq!(flow.id, deserialized.id);
         assert_eq!(flow.state, deserialized.state);
     }
+
+    #[test]
+    fn test_stop_reason_from_raw_per_provider() {
+        // OpenAI
+        assert_eq!(StopReason::from_raw("stop"), StopReason::Stop);
+        assert_eq!(StopReason::from_raw("length"), StopReason::Length);
+        assert_eq!(StopReason::from_raw("tool_calls"), StopReason::ToolCalls);
+        assert_eq!(StopReason::from_raw("function_call"), StopReason::ToolCalls);
+        assert_eq!(
+            StopReason::from_raw("content_filter"),
+            StopReason::ContentFilter
+        );
+        // Anthropic
+        assert_eq!(StopReason::from_raw("end_turn"), StopReason::Stop);
+        assert_eq!(StopReason::from_raw("stop_sequence"), StopReason::Stop);
+        assert_eq!(StopReason::from_raw("max_tokens"), StopReason::Length);
+        assert_eq!(StopReason::from_raw("tool_use"), StopReason::ToolCalls);
+        assert_eq!(StopReason::from_raw("refusal"), StopReason::ContentFilter);
+        assert_eq!(
+            StopReason::from_raw("pause_turn"),
+            StopReason::Other("pause_turn".to_string())
+        );
+        // Gemini
+        assert_eq!(StopReason::from_raw("STOP"), StopReason::Stop);
+        assert_eq!(StopReason::from_raw("MAX_TOKENS"), StopReason::Length);
+        assert_eq!(StopReason::from_raw("SAFETY"), StopReason::ContentFilter);
+        assert_eq!(
+            StopReason::from_raw("RECITATION"),
+            StopReason::ContentFilter
+        );
+    }
+
+    #[test]
+    fn test_apply_cache_usage_per_provider() {
+        // Anthropic：input_tokens 不含缓存部分
+        let mut usage = TokenUsage {
+            input_tokens: 10,
+            output_tokens: 5,
+            total_tokens: 15,
+            ..Default::default()
+        };
+        let raw = serde_json::json!({
+            "input_tokens": 10,
+            "cache_read_input_tokens": 900,
+            "cache_creation_input_tokens": 90,
+            "output_tokens": 5
+        });
+        usage.apply_cache_usage(&raw);
+        usage.apply_cache_usage(&raw);
+        assert_eq!(usage.input_tokens, 1000);
+        assert_eq!(usage.total_tokens, 1005);
+        assert_eq!(usage.cache_read_tokens, Some(900));
+        assert_eq!(usage.cache_write_tokens, Some(90));
+        assert_eq!(usage.cache_hit_ratio(), Some(0.9));
+
+        // OpenAI：cached_tokens 已包含在 prompt_tokens 中
+        let mut usage = TokenUsage {
+            input_tokens: 2000,
+            ..Default::default()
+        };
+        usage.apply_cache_usage(&serde_json::json!({
+            "prompt_tokens": 2000,
+            "prompt_tokens_details": {"cached_tokens": 1024}
+        }));
+        assert_eq!(usage.input_tokens, 2000);
+        assert_eq!(usage.cache_read_tokens, Some(1024));
+        assert!(usage.is_cache_hit());
+
+        // Gemini
+        let mut usage = TokenUsage {
+            input_tokens: 400,
+            ..Default::default()
+        };
+        usage.apply_cache_usage(&serde_json::json!({
+            "promptTokenCount": 400,
+            "cachedContentTokenCount": 0
+        }));
+        assert!(!usage.is_cache_hit());
+        assert_eq!(usage.cache_hit_ratio(), Some(0.0));
+
+        // 未上报缓存用量
+        let mut usage = TokenUsage::default();
+        usage.apply_cache_usage(&serde_json::json!({"prompt_tokens": 10}));
+        assert!(usage.cache_hit_ratio().is_none());
+    }
+
+    #[test]
+    fn test_normalize_stop_reason() {
+        let mut response = LLMResponse {
+            body: serde_json::json!({"stop_reason": "refusal"}),
+            ..Default::default()
+        };
+        response.normalize_stop_reason();
+        assert_eq!(response.stop_reason, Some(StopReason::ContentFilter));
+        assert_eq!(response.raw_stop_reason.as_deref(), Some("refusal"));
+
+        let mut response = LLMResponse {
+            body: serde_json::json!({"candidates": [{"finishReason": "MAX_TOKENS"}]}),
+            ..Default::default()
+        };
+        response.normalize_stop_reason();
+        assert_eq!(response.stop_reason, Some(StopReason::Length));
+
+        // 没有原始值时归并旧版取值
+        let mut response = LLMResponse {
+            stop_reason: Some(StopReason::EndTurn),
+            ..Default::default()
+        };
+        response.normalize_stop_reason();
+        assert_eq!(response.stop_reason, Some(StopReason::Stop));
+        assert!(response.raw_stop_reason.is_none());
+    }
+
+    #[test]
+    fn test_seed_and_system_fingerprint() {
+        // 旧数据中 seed 位于 extra，反序列化后进入专用字段
+        let parameters: RequestParameters =
+            serde_json::from_value(serde_json::json!({"stream": false, "seed": 42})).unwrap();
+        assert_eq!(parameters.seed, Some(42));
+        assert!(parameters.extra.is_empty());
+        let json = serde_json::to_value(&parameters).unwrap();
+        assert_eq!(json["seed"], 42);
+
+        let mut response = LLMResponse {
+            body: serde_json::json!({"system_fingerprint": "fp_44709d6fcb"}),
+            ..Default::default()
+        };
+        response.capture_system_fingerprint();
+        assert_eq!(
+            response.system_fingerprint.as_deref(),
+            Some("fp_44709d6fcb")
+        );
+    }
+
+    #[test]
+    fn test_capture_logprobs_truncates() {
+        let body = serde_json::json!({"choices": [{"logprobs": {"content": [
+            {"token": "Hi", "logprob": -0.1, "bytes": [72, 105],
+             "top_logprobs": [{"token": "Hi", "logprob": -0.1}, {"token": "Hello", "logprob": -2.4}]},
+            {"token": "!", "logprob": -0.3, "top_logprobs": []},
+            {"token": " there", "logprob": -1.2}
+        ]}}]});
+
+        let mut response = LLMResponse {
+            body: body.clone(),
+            ..Default::default()
+        };
+        response.capture_logprobs(2);
+        let logprobs = response.logprobs.as_ref().unwrap();
+        assert_eq!(logprobs.tokens.len(), 2);
+        assert_eq!(logprobs.total_tokens, 3);
+        assert!(logprobs.truncated);
+        assert_eq!(logprobs.tokens[0].top_logprobs[1].token, "Hello");
+
+        let mut response = LLMResponse {
+            body,
+            ..Default::default()
+        };
+        response.capture_logprobs(0);
+        assert!(response.logprobs.is_none());
+
+        let mut response = LLMResponse {
+            body: serde_json::json!({"choices": [{"logprobs": null}]}),
+            ..Default::default()
+        };
+        response.capture_logprobs(10);
+        assert!(response.logprobs.is_none());
+    }
 }
 
 // ============================================================================
@@ -1025,6 +1918,8 @@ mod property_tests {
                     max_tokens,
                     stop: None,
                     stream,
+                    response_format: None,
+                    seed: None,
                     extra: HashMap::new(),
                 },
             )
@@ -1051,6 +1946,7 @@ mod property_tests {
                 parameters,
                 size_bytes: 0,
                 timestamp: Utc::now(),
+                attachments: Vec::new(),
             })
     }
 
@@ -1069,7 +1965,13 @@ mod property_tests {
                 client_info: ClientInfo::default(),
                 routing_info: RoutingInfo::default(),
                 injected_params: None,
+                defaulted_params: None,
                 context_usage_percentage: None,
+                shadow_of: None,
+                idempotent_replay_of: None,
+                upstream_request_id: None,
+                usage_source: None,
+                content_filter: None,
             })
     }
 
@@ -1205,7 +2107,8 @@ mod property_tests {
                 FlowErrorType::Network
                 | FlowErrorType::Timeout
                 | FlowErrorType::RateLimit
-                | FlowErrorType::ServerError => {
+                | FlowErrorType::ServerError
+                | FlowErrorType::Overloaded => {
                     prop_assert!(is_retryable, "{:?} 应该是可重试的", error_type);
                 }
                 FlowErrorType::Authentication
@@ -1214,6 +2117,9 @@ mod property_tests {
                 | FlowErrorType::ModelUnavailable
                 | FlowErrorType::TokenLimitExceeded
                 | FlowErrorType::Cancelled
+                | FlowErrorType::GateRejected
+                | FlowErrorType::IncompleteStream
+                | FlowErrorType::UnknownOutcome
                 | FlowErrorType::Other => {
                     prop_assert!(!is_retryable, "{:?} 不应该是可重试的", error_type);
                 }
@@ -1265,6 +2171,8 @@ mod property_tests {
                 response_end: Some(end),
                 duration_ms: 0,
                 ttfb_ms: None,
+                pipeline_ms: None,
+                generation_ms: None,
             };
 
             timestamps.calculate_duration();
@@ -11,17 +11,38 @@
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, Mutex, Notify, RwLock};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
-use super::file_store::FlowFileStore;
-use super::memory_store::FlowMemoryStore;
+use super::auto_tag::{AutoTagConfig, AutoTagError, AutoTagger};
+use super::content_hash::{ContentNormalizationConfig, ContentNormalizer};
+use super::disk_guard::{DiskGuard, DiskGuardConfig, DiskStatus, DiskTransition};
+use super::file_store::{CleanupResult, FileStoreError, FlowFileStore};
+use super::inline_reasoning::{extract_inline_reasoning, InlineReasoningConfig};
+use super::memory_store::{FlowFilter, FlowMemoryStore};
+use super::mock::{self, MockConfig, MockMatch};
 use super::models::{
-    FlowAnnotations, FlowError, FlowMetadata, FlowState, FlowType, LLMFlow, LLMRequest,
-    LLMResponse, TokenUsage,
+    ContentFilterOutcome, FlowAnnotations, FlowError, FlowErrorType, FlowMetadata, FlowState,
+    FlowTimestamps, FlowType, LLMFlow, LLMRequest, LLMResponse, ResponseBodyInfo, TokenUsage,
+    UsageSource, CONTENT_FILTERED_TAG, IDEMPOTENT_REPLAY_TAG, SHADOW_TAG, UNKNOWN_OUTCOME_TAG,
 };
+use super::multipart::{apply_multipart_attachments, MultipartCapture, MultipartCaptureConfig};
+use super::notification_coalesce::{
+    CoalesceDecision, CoalescedErrors, CoalescingSettings, ErrorCoalescer,
+};
+use super::query_service::{ModelPricing, RelevanceWeights};
+use super::read_cache::{ReadCacheConfig, ReadCacheStats};
+use super::retention::RetentionPolicy;
+use super::slo::{SloConfig, SloStatus, SloTracker};
 use super::stream_rebuilder::{StreamFormat, StreamRebuilder};
+use super::structured_output;
+use super::wal::RequestWal;
+use super::webhook::{WebhookSettings, WebhookSink};
+use crate::ProviderType;
 
 // ============================================================================
 // 配置结构
@@ -38,6 +59,9 @@ pub struct FlowMonitorConfig {
     /// 最大内存 Flow 数量
     #[serde(default = "default_max_memory_flows")]
     pub max_memory_flows: usize,
+    /// 内存中 Flow 的最长保留分钟数（按创建时间，为空时只按数量驱逐）
+    #[serde(default)]
+    pub memory_retention_minutes: Option<u64>,
     /// 是否持久化到文件
     #[serde(default = "default_persist_to_file")]
     pub persist_to_file: bool,
@@ -47,6 +71,9 @@ pub struct FlowMonitorConfig {
     /// 是否保存原始流式 chunks
     #[serde(default)]
     pub save_stream_chunks: bool,
+    /// 流结束时合并连续的同类增量 chunk（减少保存的 chunk 数量，仅在保存 chunks 时生效）
+    #[serde(default)]
+    pub compact_stream_chunks: bool,
     /// 最大请求体大小（字节）
     #[serde(default = "default_max_request_body_size")]
     pub max_request_body_size: usize,
@@ -68,6 +95,156 @@ pub struct FlowMonitorConfig {
     /// 排除的路径列表（支持通配符）
     #[serde(default)]
     pub excluded_paths: Vec<String>,
+    /// multipart/form-data 上传捕获配置
+    #[serde(default)]
+    pub multipart: MultipartCaptureConfig,
+    /// 向上游透传 Flow ID 的请求头名称（如 `X-ProxyCast-Flow-Id`，为空时不透传）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id_header: Option<String>,
+    /// 最大活跃 Flow 数量（为空时不限制）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_active_flows: Option<usize>,
+    /// 活跃 Flow 达到上限时的处理方式
+    #[serde(default)]
+    pub active_flow_overflow: ActiveFlowOverflow,
+    /// 按类别的保留策略（定时清理）
+    #[serde(default)]
+    pub retention_policy: RetentionPolicy,
+    /// 捕获的上游响应头白名单（忽略大小写，支持 * 通配符）
+    ///
+    /// 命中 `sensitive_headers` 的响应头即使在白名单中也不会被捕获。
+    #[serde(default = "default_response_header_allowlist")]
+    pub response_header_allowlist: Vec<String>,
+    /// 不捕获的敏感头名称（请求头和响应头共用，忽略大小写，支持 * 通配符）
+    #[serde(default = "default_sensitive_headers")]
+    pub sensitive_headers: Vec<String>,
+    /// 额外的敏感头名称正则（忽略大小写，无效时忽略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensitive_header_regex: Option<String>,
+    /// 每个请求或响应最多捕获的头数量
+    #[serde(default = "default_max_captured_headers")]
+    pub max_captured_headers: usize,
+    /// 单个头值最多保存的字节数（超出部分截断）
+    #[serde(default = "default_max_header_value_bytes")]
+    pub max_header_value_bytes: usize,
+    /// 每个响应最多保存的对数概率 Token 数（超出部分截断，0 表示不保存）
+    #[serde(default = "default_max_logprob_tokens")]
+    pub max_logprob_tokens: usize,
+    /// 正常退出时把内存中的 Flow 保存为快照，下次启动时恢复
+    #[serde(default)]
+    pub snapshot_on_shutdown: bool,
+    /// 相关度排序（`FlowSortBy::Relevance`）的评分权重
+    #[serde(default)]
+    pub relevance_weights: RelevanceWeights,
+    /// 统计成本估算的单价表（按模型名通配符，美元 / 千 Token）
+    #[serde(default)]
+    pub model_pricing: ModelPricing,
+    /// 磁盘空间保护（剩余空间不足时暂停持久化）
+    #[serde(default)]
+    pub disk_guard: DiskGuardConfig,
+    /// 按 Provider 和模型的成功率 SLO 告警
+    #[serde(default)]
+    pub slo: SloConfig,
+    /// 计算内容哈希（模板聚类、缓存、去重）前的规范化规则
+    #[serde(default)]
+    pub content_normalization: ContentNormalizationConfig,
+    /// 文件存储读缓存（按内存预算缓存最近读取的 Flow）
+    #[serde(default)]
+    pub read_cache: ReadCacheConfig,
+    /// 把响应正文中的内联推理（如 `<think>...</think>`）提取到思维链
+    #[serde(default)]
+    pub extract_inline_reasoning: InlineReasoningConfig,
+    /// 请求预写日志路径
+    ///
+    /// 设置后在调用上游前把请求落盘，进程崩溃后未完成的请求恢复为结果未知的 Flow。
+    /// 比普通捕获开销大（每个请求一次 fsync），只在需要崩溃安全时启用。
+    #[serde(default)]
+    pub wal_path: Option<PathBuf>,
+    /// 离线 Mock 模式（用捕获的 Flow 响应请求，不调用上游）
+    #[serde(default)]
+    pub mock: MockConfig,
+}
+
+/// 活跃 Flow 达到上限时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ActiveFlowOverflow {
+    /// 跳过超出部分的捕获，请求照常处理
+    #[default]
+    SkipCapture,
+    /// 拒绝超出部分的请求（返回 503）
+    RejectRequest,
+}
+
+/// 活跃 Flow 数量已达上限（`RejectRequest` 模式下返回）
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("活跃 Flow 数量已达上限 ({max})")]
+pub struct ActiveFlowCapExceeded {
+    /// 配置的上限
+    pub max: usize,
+}
+
+/// Flow 捕获判定结果（用于排查"请求没有出现在 Flow 列表中"）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureDecision {
+    /// 请求是否会被捕获（按采样率随机捕获时也为 `true`）
+    pub would_capture: bool,
+    /// 被捕获的概率（0.0-1.0）
+    pub capture_probability: f32,
+    /// 不捕获的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<CaptureSkipReason>,
+    /// 可读的说明
+    pub message: String,
+}
+
+/// 不捕获 Flow 的原因
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CaptureSkipReason {
+    /// 监控未启用
+    Disabled,
+    /// 采样率为 0，所有请求都被采样排除
+    SampledOut { sampling_rate: f32 },
+    /// 命中排除的模型
+    ExcludedModel { pattern: String },
+    /// 命中排除的路径
+    ExcludedPath { pattern: String },
+    /// 活跃 Flow 数量已达上限
+    ActiveFlowCap {
+        max: usize,
+        active: usize,
+        overflow: ActiveFlowOverflow,
+    },
+}
+
+impl CaptureSkipReason {
+    /// 可读的说明
+    pub fn message(&self) -> String {
+        match self {
+            CaptureSkipReason::Disabled => "Flow 监控未启用".to_string(),
+            CaptureSkipReason::SampledOut { .. } => "采样率为 0，所有请求都不会被捕获".to_string(),
+            CaptureSkipReason::ExcludedModel { pattern } => {
+                format!("模型命中排除规则 `{}`", pattern)
+            }
+            CaptureSkipReason::ExcludedPath { pattern } => {
+                format!("路径命中排除规则 `{}`", pattern)
+            }
+            CaptureSkipReason::ActiveFlowCap {
+                max,
+                active,
+                overflow,
+            } => format!(
+                "活跃 Flow 数量 ({}) 已达上限 ({})，请求将{}",
+                active,
+                max,
+                match overflow {
+                    ActiveFlowOverflow::SkipCapture => "跳过捕获",
+                    ActiveFlowOverflow::RejectRequest => "被拒绝（503）",
+                }
+            ),
+        }
+    }
 }
 
 fn default_enabled() -> bool {
@@ -102,14 +279,76 @@ fn default_sampling_rate() -> f32 {
     1.0
 }
 
+fn default_max_logprob_tokens() -> usize {
+    2048
+}
+
+fn default_response_header_allowlist() -> Vec<String> {
+    [
+        "x-ratelimit-*",
+        "anthropic-ratelimit-*",
+        "retry-after",
+        "x-request-id",
+        "request-id",
+        "x-amzn-requestid",
+        "x-goog-request-id",
+        "openai-processing-ms",
+        "openai-version",
+        "anthropic-organization-id",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+/// 默认的敏感头列表
+fn default_sensitive_headers() -> Vec<String> {
+    [
+        "authorization",
+        "proxy-authorization",
+        "www-authenticate",
+        "proxy-authenticate",
+        "cookie",
+        "cookie2",
+        "set-cookie",
+        "set-cookie2",
+        "x-api-key",
+        "api-key",
+        "x-goog-api-key",
+        "x-amz-security-token",
+        "x-amz-credential",
+        "*api-key*",
+        "*apikey*",
+        "*-token",
+        "*secret*",
+        "*password*",
+        "*session*",
+        "*credential*",
+        "*signature*",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+fn default_max_captured_headers() -> usize {
+    64
+}
+
+fn default_max_header_value_bytes() -> usize {
+    4096
+}
+
 impl Default for FlowMonitorConfig {
     fn default() -> Self {
         Self {
             enabled: default_enabled(),
             max_memory_flows: default_max_memory_flows(),
+            memory_retention_minutes: None,
             persist_to_file: default_persist_to_file(),
             retention_days: default_retention_days(),
             save_stream_chunks: false,
+            compact_stream_chunks: false,
             max_request_body_size: default_max_request_body_size(),
             max_response_body_size: default_max_response_body_size(),
             save_image_content: false,
@@ -117,11 +356,38 @@ impl Default for FlowMonitorConfig {
             sampling_rate: default_sampling_rate(),
             excluded_models: Vec::new(),
             excluded_paths: Vec::new(),
+            multipart: MultipartCaptureConfig::default(),
+            request_id_header: None,
+            max_active_flows: None,
+            active_flow_overflow: ActiveFlowOverflow::default(),
+            retention_policy: RetentionPolicy::default(),
+            response_header_allowlist: default_response_header_allowlist(),
+            max_logprob_tokens: default_max_logprob_tokens(),
+            sensitive_headers: default_sensitive_headers(),
+            sensitive_header_regex: None,
+            max_captured_headers: default_max_captured_headers(),
+            max_header_value_bytes: default_max_header_value_bytes(),
+            snapshot_on_shutdown: false,
+            relevance_weights: RelevanceWeights::default(),
+            model_pricing: ModelPricing::default(),
+            disk_guard: DiskGuardConfig::default(),
+            slo: SloConfig::default(),
+            content_normalization: ContentNormalizationConfig::default(),
+            read_cache: ReadCacheConfig::default(),
+            extract_inline_reasoning: InlineReasoningConfig::default(),
+            wal_path: None,
+            mock: MockConfig::default(),
         }
     }
 }
 
 impl FlowMonitorConfig {
+    /// 内存存储的最长保留时间
+    pub fn memory_max_age(&self) -> Option<Duration> {
+        self.memory_retention_minutes
+            .and_then(|minutes| Duration::try_minutes(i64::try_from(minutes).ok()?))
+    }
+
     /// 检查是否应该监控该请求
     pub fn should_monitor(&self, model: &str, path: &str) -> bool {
         if !self.enabled {
@@ -136,21 +402,122 @@ impl FlowMonitorConfig {
             }
         }
 
-        // 检查排除的模型
-        for pattern in &self.excluded_models {
-            if Self::match_pattern(pattern, model) {
-                return false;
-            }
+        self.exclusion_reason(model, path).is_none()
+    }
+
+    /// 检查请求是否命中排除的模型或路径，返回命中的规则
+    pub fn exclusion_reason(&self, model: &str, path: &str) -> Option<CaptureSkipReason> {
+        if let Some(pattern) = self
+            .excluded_models
+            .iter()
+            .find(|pattern| Self::match_pattern(pattern, model))
+        {
+            return Some(CaptureSkipReason::ExcludedModel {
+                pattern: pattern.clone(),
+            });
         }
+        self.excluded_paths
+            .iter()
+            .find(|pattern| Self::match_pattern(pattern, path))
+            .map(|pattern| CaptureSkipReason::ExcludedPath {
+                pattern: pattern.clone(),
+            })
+    }
 
-        // 检查排除的路径
-        for pattern in &self.excluded_paths {
-            if Self::match_pattern(pattern, path) {
-                return false;
+    /// 判断头名称是否为敏感头
+    pub fn is_sensitive_header(&self, name: &str) -> bool {
+        (self.sensitive_header_matcher())(name)
+    }
+
+    /// 过滤客户端请求头，排除敏感头
+    ///
+    /// 头名称统一转为小写，同名的多个值以 `, ` 连接；非 UTF-8 的值被忽略。
+    pub fn filter_request_headers(
+        &self,
+        headers: &reqwest::header::HeaderMap,
+    ) -> HashMap<String, String> {
+        self.capture_headers(headers, |_| true)
+    }
+
+    /// 脱敏请求头：保留全部请求头，敏感头的值替换为占位符
+    pub fn redact_headers(&self, headers: &reqwest::header::HeaderMap) -> HashMap<String, String> {
+        let is_sensitive = self.sensitive_header_matcher();
+        headers
+            .iter()
+            .filter_map(|(name, value)| {
+                let name = name.as_str().to_ascii_lowercase();
+                let value = if is_sensitive(&name) {
+                    crate::config::REDACTED_PLACEHOLDER.to_string()
+                } else {
+                    value.to_str().ok()?.to_string()
+                };
+                Some((name, value))
+            })
+            .collect()
+    }
+
+    /// 按白名单过滤上游响应头，排除敏感头
+    ///
+    /// 头名称统一转为小写，同名的多个值以 `, ` 连接；非 UTF-8 的值被忽略。
+    pub fn filter_response_headers(
+        &self,
+        headers: &reqwest::header::HeaderMap,
+    ) -> HashMap<String, String> {
+        self.capture_headers(headers, |name| {
+            self.response_header_allowlist
+                .iter()
+                .any(|pattern| Self::match_pattern(pattern.trim(), name))
+        })
+    }
+
+    /// 捕获头，按 `max_captured_headers` 和 `max_header_value_bytes` 限制数量和大小
+    fn capture_headers(
+        &self,
+        headers: &reqwest::header::HeaderMap,
+        include: impl Fn(&str) -> bool,
+    ) -> HashMap<String, String> {
+        let is_sensitive = self.sensitive_header_matcher();
+        let mut captured: HashMap<String, String> = HashMap::new();
+        for (name, value) in headers {
+            let name = name.as_str().to_ascii_lowercase();
+            if is_sensitive(&name) || !include(&name) {
+                continue;
+            }
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            if let Some(existing) = captured.get_mut(&name) {
+                existing.push_str(", ");
+                existing.push_str(value);
+            } else if captured.len() < self.max_captured_headers {
+                captured.insert(name, value.to_string());
             }
         }
+        for value in captured.values_mut() {
+            truncate_at_char_boundary(value, self.max_header_value_bytes);
+        }
+        captured
+    }
 
-        true
+    /// 构建敏感头判断函数（正则只编译一次）
+    fn sensitive_header_matcher(&self) -> impl Fn(&str) -> bool + '_ {
+        let regex = self
+            .sensitive_header_regex
+            .as_deref()
+            .filter(|pattern| !pattern.trim().is_empty())
+            .and_then(|pattern| {
+                regex::RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| tracing::warn!("敏感头正则无效，已忽略: {}", e))
+                    .ok()
+            });
+        move |name: &str| {
+            self.sensitive_headers
+                .iter()
+                .any(|pattern| Self::match_pattern(pattern.trim(), name))
+                || regex.as_ref().is_some_and(|regex| regex.is_match(name))
+        }
     }
 
     /// 模式匹配（支持 * 通配符）
@@ -191,6 +558,18 @@ impl FlowMonitorConfig {
     }
 }
 
+/// 按字节数截断字符串（不拆分 UTF-8 字符）
+fn truncate_at_char_boundary(value: &mut String, max_bytes: usize) {
+    if value.len() <= max_bytes {
+        return;
+    }
+    let mut end = max_bytes;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    value.truncate(end);
+}
+
 // ============================================================================
 // 阈值配置
 // ============================================================================
@@ -308,6 +687,10 @@ pub enum NotificationType {
     LatencyWarning,
     /// Token 阈值警告
     TokenWarning,
+    /// 磁盘空间不足，持久化已暂停
+    DiskSpaceLow,
+    /// 成功率低于 SLO
+    SloBreach,
 }
 
 /// 通知配置
@@ -332,6 +715,12 @@ pub struct NotificationConfig {
     /// Token 警告通知配置
     #[serde(default = "default_token_warning")]
     pub token_warning: NotificationSettings,
+    /// Webhook 投递设置（各类型的地址在对应的通知设置中配置）
+    #[serde(default)]
+    pub webhook: WebhookSettings,
+    /// 错误通知合并设置
+    #[serde(default)]
+    pub coalescing: CoalescingSettings,
 }
 
 /// 通知设置
@@ -345,6 +734,9 @@ pub struct NotificationSettings {
     pub sound: bool,
     /// 声音文件路径（可选）
     pub sound_file: Option<String>,
+    /// Webhook 地址（可选，配置后会 POST JSON 载荷）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
 }
 
 fn default_notification_enabled() -> bool {
@@ -357,6 +749,7 @@ fn default_error_notification() -> NotificationSettings {
         desktop: true,
         sound: true,
         sound_file: None,
+        webhook_url: None,
     }
 }
 
@@ -366,6 +759,7 @@ fn default_latency_warning() -> NotificationSettings {
         desktop: false,
         sound: false,
         sound_file: None,
+        webhook_url: None,
     }
 }
 
@@ -375,6 +769,7 @@ fn default_token_warning() -> NotificationSettings {
         desktop: false,
         sound: false,
         sound_file: None,
+        webhook_url: None,
     }
 }
 
@@ -385,6 +780,7 @@ impl Default for NotificationSettings {
             desktop: false,
             sound: false,
             sound_file: None,
+            webhook_url: None,
         }
     }
 }
@@ -397,6 +793,8 @@ impl Default for NotificationConfig {
             error_flow: default_error_notification(),
             latency_warning: default_latency_warning(),
             token_warning: default_token_warning(),
+            webhook: WebhookSettings::default(),
+            coalescing: CoalescingSettings::default(),
         }
     }
 }
@@ -416,6 +814,9 @@ pub struct NotificationEvent {
     pub message: String,
     /// 关联的 Flow ID
     pub flow_id: String,
+    /// 关联的模型
+    #[serde(default)]
+    pub model: String,
     /// 通知时间
     pub timestamp: DateTime<Utc>,
     /// 是否需要桌面通知
@@ -424,6 +825,13 @@ pub struct NotificationEvent {
     pub sound: bool,
     /// 声音文件路径
     pub sound_file: Option<String>,
+    /// 合并的错误数（单独通知时为 1）
+    #[serde(default = "default_notification_count")]
+    pub count: u32,
+}
+
+fn default_notification_count() -> u32 {
+    1
 }
 
 impl NotificationEvent {
@@ -434,10 +842,12 @@ impl NotificationEvent {
             title: "新的 LLM 请求".to_string(),
             message: format!("模型: {}", model),
             flow_id,
+            model,
             timestamp: Utc::now(),
             desktop: settings.desktop,
             sound: settings.sound,
             sound_file: settings.sound_file.clone(),
+            count: 1,
         }
     }
 
@@ -453,10 +863,12 @@ impl NotificationEvent {
             title: "LLM 请求失败".to_string(),
             message: format!("模型: {}, 错误: {}", model, error),
             flow_id,
+            model,
             timestamp: Utc::now(),
             desktop: settings.desktop,
             sound: settings.sound,
             sound_file: settings.sound_file.clone(),
+            count: 1,
         }
     }
 
@@ -476,10 +888,12 @@ impl NotificationEvent {
                 model, actual_ms, threshold_ms
             ),
             flow_id,
+            model,
             timestamp: Utc::now(),
             desktop: settings.desktop,
             sound: settings.sound,
             sound_file: settings.sound_file.clone(),
+            count: 1,
         }
     }
 
@@ -499,10 +913,91 @@ impl NotificationEvent {
                 model, actual_tokens, threshold_tokens
             ),
             flow_id,
+            model,
+            timestamp: Utc::now(),
+            desktop: settings.desktop,
+            sound: settings.sound,
+            sound_file: settings.sound_file.clone(),
+            count: 1,
+        }
+    }
+
+    /// 创建合并后的错误通知
+    pub fn coalesced_errors(summary: &CoalescedErrors, settings: &NotificationSettings) -> Self {
+        Self {
+            notification_type: NotificationType::ErrorFlow,
+            title: format!("LLM 请求失败 ×{}", summary.count),
+            message: format!(
+                "{} 最近 {} 秒内 {} 次 {:?} 错误（{} 次未单独通知），示例模型: {}",
+                summary.provider,
+                summary.window_secs,
+                summary.count,
+                summary.error_type,
+                summary.suppressed,
+                summary.model
+            ),
+            flow_id: summary.flow_id.clone(),
+            model: summary.model.clone(),
+            timestamp: Utc::now(),
+            desktop: settings.desktop,
+            sound: settings.sound,
+            sound_file: settings.sound_file.clone(),
+            count: summary.count,
+        }
+    }
+
+    /// 创建磁盘空间不足通知
+    pub fn disk_space_low(
+        available_bytes: u64,
+        min_free_bytes: u64,
+        settings: &NotificationSettings,
+    ) -> Self {
+        const MB: u64 = 1024 * 1024;
+        Self {
+            notification_type: NotificationType::DiskSpaceLow,
+            title: "磁盘空间不足".to_string(),
+            message: format!(
+                "剩余 {} MB，低于 {} MB，Flow 暂时只保存在内存中，空间恢复后自动恢复持久化",
+                available_bytes / MB,
+                min_free_bytes / MB
+            ),
+            flow_id: String::new(),
+            model: String::new(),
+            timestamp: Utc::now(),
+            desktop: settings.desktop,
+            sound: settings.sound,
+            sound_file: settings.sound_file.clone(),
+            count: 1,
+        }
+    }
+
+    /// 创建成功率低于 SLO 的通知
+    pub fn slo_breach(status: &SloStatus, settings: &NotificationSettings) -> Self {
+        let target = match &status.model {
+            Some(model) => format!("{} / {}", status.provider, model),
+            None => status.provider.clone(),
+        };
+        let percent =
+            |rate: Option<f64>| rate.map_or("-".to_string(), |r| format!("{:.2}%", r * 100.0));
+        Self {
+            notification_type: NotificationType::SloBreach,
+            title: "成功率低于 SLO".to_string(),
+            message: format!(
+                "{} 最近 {} 秒成功率 {}、{} 秒成功率 {}，目标 {:.2}%",
+                target,
+                status.short_window.window_secs,
+                percent(status.short_window.success_rate),
+                status.long_window.window_secs,
+                percent(status.long_window.success_rate),
+                status.target * 100.0
+            ),
+            flow_id: String::new(),
+            model: status.model.clone().unwrap_or_default(),
             timestamp: Utc::now(),
             desktop: settings.desktop,
             sound: settings.sound,
             sound_file: settings.sound_file.clone(),
+            count: status.short_window.failures,
         }
     }
 }
@@ -720,6 +1215,8 @@ pub enum FlowEvent {
     ///
     /// **Validates: Requirements 10.7**
     RequestRateUpdate { rate: f64, count: usize },
+    /// SLO 告警状态变化（进入告警或恢复）
+    SloStatusChanged { status: SloStatus },
 }
 
 // ============================================================================
@@ -736,6 +1233,56 @@ struct ActiveFlow {
     stream_rebuilder: Option<StreamRebuilder>,
     /// 请求开始时间
     request_start: DateTime<Utc>,
+    /// 按白名单捕获的上游响应头（完成时合并到响应中）
+    response_headers: HashMap<String, String>,
+    /// 上游响应体的内容类型和解码结果（完成时合并到响应中）
+    body_info: Option<ResponseBodyInfo>,
+    /// 强制取消时用于中止上游流
+    cancel_token: CancellationToken,
+    /// 已从上游收到的字节数
+    bytes_received: Arc<AtomicU64>,
+    /// 已处理的流式 chunk 数
+    chunk_count: usize,
+}
+
+/// 活跃 Flow 概要（用于查看正在进行中的请求）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveFlowSummary {
+    /// Flow ID
+    pub id: String,
+    /// 模型名称
+    pub model: String,
+    /// 提供商
+    pub provider: ProviderType,
+    /// 当前状态
+    pub state: FlowState,
+    /// 是否为流式响应
+    pub streaming: bool,
+    /// 请求开始时间
+    pub started_at: DateTime<Utc>,
+    /// 已进行的时长（毫秒）
+    pub age_ms: u64,
+    /// 已从上游收到的字节数
+    pub bytes_received: u64,
+    /// 已处理的流式 chunk 数
+    pub chunk_count: usize,
+}
+
+/// 活跃 Flow 句柄
+///
+/// 供流式转发路径统计收到的字节数，并在 Flow 被强制取消时中止上游流。
+#[derive(Debug, Clone)]
+pub struct ActiveFlowHandle {
+    /// 取消令牌（Flow 被强制取消时触发）
+    pub cancel_token: CancellationToken,
+    bytes_received: Arc<AtomicU64>,
+}
+
+impl ActiveFlowHandle {
+    /// 记录从上游收到的字节数
+    pub fn record_bytes(&self, len: usize) {
+        self.bytes_received.fetch_add(len as u64, Ordering::Relaxed);
+    }
 }
 
 // ============================================================================
@@ -761,7 +1308,66 @@ pub struct FlowMonitor {
     /// 请求速率追踪器
     rate_tracker: RwLock<RequestRateTracker>,
     /// 通知配置
-    notification_config: RwLock<NotificationConfig>,
+    notification_config: Arc<RwLock<NotificationConfig>>,
+    /// 错误通知合并器
+    error_coalescer: Arc<Mutex<ErrorCoalescer>>,
+    /// 自动标签引擎
+    auto_tagger: RwLock<AutoTagger>,
+    /// 通知 Webhook 投递器
+    webhook_sink: WebhookSink,
+    /// 因活跃 Flow 达到上限而跳过的捕获数
+    dropped_captures: AtomicU64,
+    /// 是否已输出过达到上限的警告
+    cap_warned: AtomicBool,
+    /// 配置更新通知（唤醒保留策略调度器）
+    retention_notify: Notify,
+    /// 磁盘空间保护
+    disk_guard: DiskGuard,
+    /// 成功率 SLO 跟踪器
+    slo_tracker: Mutex<SloTracker>,
+    /// 请求预写日志（配置了 `wal_path` 时启用）
+    wal: RwLock<Option<Arc<RequestWal>>>,
+    /// 按当前配置编译的内容规范化器（配置变化时重新编译）
+    content_normalizer: RwLock<Arc<ContentNormalizer>>,
+}
+
+/// 按配置打开请求预写日志，打开失败时不启用
+fn open_wal(config: &FlowMonitorConfig) -> Option<Arc<RequestWal>> {
+    let path = config.wal_path.as_ref()?;
+    match RequestWal::open(path) {
+        Ok(wal) => Some(Arc::new(wal)),
+        Err(e) => {
+            tracing::error!("无法打开请求预写日志 {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// 按通知配置发送通知事件，配置了 Webhook 时在后台投递，不阻塞调用方
+fn dispatch_notification(
+    config: &NotificationConfig,
+    webhook_sink: &WebhookSink,
+    event_sender: &broadcast::Sender<FlowEvent>,
+    notification: NotificationEvent,
+) {
+    if !config.enabled {
+        return;
+    }
+
+    let settings = match notification.notification_type {
+        NotificationType::NewFlow => &config.new_flow,
+        NotificationType::ErrorFlow => &config.error_flow,
+        NotificationType::LatencyWarning => &config.latency_warning,
+        NotificationType::TokenWarning => &config.token_warning,
+        // 系统警告沿用错误通知的设置
+        NotificationType::DiskSpaceLow | NotificationType::SloBreach => &config.error_flow,
+    };
+    if let Some(url) = settings.webhook_url.as_ref().filter(|u| !u.is_empty()) {
+        webhook_sink.dispatch(url.clone(), notification.clone(), config.webhook.clone());
+    }
+
+    // 发送通知事件
+    let _ = event_sender.send(FlowEvent::Notification { notification });
 }
 
 impl FlowMonitor {
@@ -771,8 +1377,17 @@ impl FlowMonitor {
     /// - `config`: 监控配置
     /// - `file_store`: 文件存储（可选）
     pub fn new(config: FlowMonitorConfig, file_store: Option<Arc<FlowFileStore>>) -> Self {
-        let memory_store = Arc::new(RwLock::new(FlowMemoryStore::new(config.max_memory_flows)));
+        let memory_store = Arc::new(RwLock::new(
+            FlowMemoryStore::new(config.max_memory_flows).with_max_age(config.memory_max_age()),
+        ));
         let (event_sender, _) = broadcast::channel(1000);
+        if let Some(ref file_store) = file_store {
+            file_store.set_read_cache_budget(config.read_cache.max_memory_bytes());
+        }
+        let wal = RwLock::new(open_wal(&config));
+        let content_normalizer = RwLock::new(Arc::new(ContentNormalizer::new(
+            &config.content_normalization,
+        )));
 
         Self {
             config: RwLock::new(config),
@@ -782,7 +1397,17 @@ impl FlowMonitor {
             event_sender,
             threshold_config: RwLock::new(ThresholdConfig::default()),
             rate_tracker: RwLock::new(RequestRateTracker::default()),
-            notification_config: RwLock::new(NotificationConfig::default()),
+            notification_config: Arc::new(RwLock::new(NotificationConfig::default())),
+            error_coalescer: Arc::new(Mutex::new(ErrorCoalescer::new())),
+            auto_tagger: RwLock::new(AutoTagger::default()),
+            webhook_sink: WebhookSink::new(),
+            dropped_captures: AtomicU64::new(0),
+            cap_warned: AtomicBool::new(false),
+            retention_notify: Notify::new(),
+            disk_guard: DiskGuard::new(),
+            slo_tracker: Mutex::new(SloTracker::new()),
+            wal,
+            content_normalizer,
         }
     }
 
@@ -799,8 +1424,17 @@ impl FlowMonitor {
         threshold_config: ThresholdConfig,
         notification_config: NotificationConfig,
     ) -> Self {
-        let memory_store = Arc::new(RwLock::new(FlowMemoryStore::new(config.max_memory_flows)));
+        let memory_store = Arc::new(RwLock::new(
+            FlowMemoryStore::new(config.max_memory_flows).with_max_age(config.memory_max_age()),
+        ));
         let (event_sender, _) = broadcast::channel(1000);
+        if let Some(ref file_store) = file_store {
+            file_store.set_read_cache_budget(config.read_cache.max_memory_bytes());
+        }
+        let wal = RwLock::new(open_wal(&config));
+        let content_normalizer = RwLock::new(Arc::new(ContentNormalizer::new(
+            &config.content_normalization,
+        )));
 
         Self {
             config: RwLock::new(config),
@@ -810,7 +1444,17 @@ impl FlowMonitor {
             event_sender,
             threshold_config: RwLock::new(threshold_config),
             rate_tracker: RwLock::new(RequestRateTracker::default()),
-            notification_config: RwLock::new(notification_config),
+            notification_config: Arc::new(RwLock::new(notification_config)),
+            error_coalescer: Arc::new(Mutex::new(ErrorCoalescer::new())),
+            auto_tagger: RwLock::new(AutoTagger::default()),
+            webhook_sink: WebhookSink::new(),
+            dropped_captures: AtomicU64::new(0),
+            cap_warned: AtomicBool::new(false),
+            retention_notify: Notify::new(),
+            disk_guard: DiskGuard::new(),
+            slo_tracker: Mutex::new(SloTracker::new()),
+            wal,
+            content_normalizer,
         }
     }
 
@@ -827,8 +1471,17 @@ impl FlowMonitor {
         threshold_config: ThresholdConfig,
         notification_config: NotificationConfig,
     ) -> Self {
-        let memory_store = Arc::new(RwLock::new(FlowMemoryStore::new(config.max_memory_flows)));
+        let memory_store = Arc::new(RwLock::new(
+            FlowMemoryStore::new(config.max_memory_flows).with_max_age(config.memory_max_age()),
+        ));
         let (event_sender, _) = broadcast::channel(1000);
+        if let Some(ref file_store) = file_store {
+            file_store.set_read_cache_budget(config.read_cache.max_memory_bytes());
+        }
+        let wal = RwLock::new(open_wal(&config));
+        let content_normalizer = RwLock::new(Arc::new(ContentNormalizer::new(
+            &config.content_normalization,
+        )));
 
         Self {
             config: RwLock::new(config),
@@ -838,7 +1491,17 @@ impl FlowMonitor {
             event_sender,
             threshold_config: RwLock::new(threshold_config),
             rate_tracker: RwLock::new(RequestRateTracker::default()),
-            notification_config: RwLock::new(notification_config),
+            notification_config: Arc::new(RwLock::new(notification_config)),
+            error_coalescer: Arc::new(Mutex::new(ErrorCoalescer::new())),
+            auto_tagger: RwLock::new(AutoTagger::default()),
+            webhook_sink: WebhookSink::new(),
+            dropped_captures: AtomicU64::new(0),
+            cap_warned: AtomicBool::new(false),
+            retention_notify: Notify::new(),
+            disk_guard: DiskGuard::new(),
+            slo_tracker: Mutex::new(SloTracker::new()),
+            wal,
+            content_normalizer,
         }
     }
 
@@ -868,118 +1531,547 @@ impl FlowMonitor {
             let mut store = self.memory_store.write().await;
             *store = FlowMemoryStore::new(config.max_memory_flows);
         }
+        self.memory_store
+            .write()
+            .await
+            .set_max_age(config.memory_max_age());
+        if let Some(ref file_store) = self.file_store {
+            file_store.set_read_cache_budget(config.read_cache.max_memory_bytes());
+        }
+        let wal_changed = current.wal_path != config.wal_path;
+        if wal_changed {
+            *self.wal.write().await = open_wal(&config);
+        }
+        if current.content_normalization != config.content_normalization {
+            *self.content_normalizer.write().await =
+                Arc::new(ContentNormalizer::new(&config.content_normalization));
+        }
 
         *current = config;
-    }
+        drop(current);
+        // 新配置下首次达到上限时重新输出警告
+        self.cap_warned.store(false, Ordering::Relaxed);
+        self.retention_notify.notify_one();
 
-    /// 获取阈值配置
-    ///
-    /// **Validates: Requirements 10.3, 10.4**
-    pub async fn threshold_config(&self) -> ThresholdConfig {
-        self.threshold_config.read().await.clone()
+        if wal_changed {
+            self.recover_wal().await;
+        }
     }
 
-    /// 更新阈值配置
+    /// 恢复预写日志中上次运行未完成的请求
     ///
-    /// **Validates: Requirements 10.3, 10.4**
-    pub async fn update_threshold_config(&self, config: ThresholdConfig) {
-        let mut current = self.threshold_config.write().await;
-        *current = config;
-    }
-
-    /// 获取当前请求速率（每秒）
+    /// 进程在这些请求结束前退出，上游是否已处理未知。它们以失败状态、`UnknownOutcome`
+    /// 错误和 `unknown_outcome` 标签写入存储，可以查看或重放，写入后在日志中标记完成。
     ///
-    /// **Validates: Requirements 10.7**
-    pub async fn get_request_rate(&self) -> f64 {
-        self.rate_tracker.read().await.get_rate()
+    /// # 返回
+    /// 恢复的 Flow 数量
+    pub async fn recover_wal(&self) -> usize {
+        let Some(wal) = self.wal.read().await.clone() else {
+            return 0;
+        };
+        let flows: Vec<LLMFlow> = wal
+            .take_recovered()
+            .into_iter()
+            .map(|mut flow| {
+                flow.state = FlowState::Failed;
+                flow.error = Some(FlowError::new(
+                    FlowErrorType::UnknownOutcome,
+                    "进程在请求结束前退出，上游是否已处理未知",
+                ));
+                flow.annotations.tags.push(UNKNOWN_OUTCOME_TAG.to_string());
+                flow
+            })
+            .collect();
+        if flows.is_empty() {
+            return 0;
+        }
+
+        let ids: Vec<String> = flows.iter().map(|flow| flow.id.clone()).collect();
+        let count = self.import_flows(flows).await;
+        for id in ids {
+            if let Err(e) = wal.complete(&id) {
+                tracing::warn!("标记 WAL 条目 {} 完成失败: {}", id, e);
+            }
+        }
+        tracing::warn!("从请求预写日志恢复了 {} 个结果未知的 Flow", count);
+        count
     }
 
-    /// 获取时间窗口内的请求数量
-    ///
-    /// **Validates: Requirements 10.7**
-    pub async fn get_request_count(&self) -> usize {
-        self.rate_tracker.read().await.get_count()
+    /// 在预写日志中记录 Flow 结束（未启用时忽略）
+    async fn wal_complete(&self, flow_id: &str) {
+        if let Some(wal) = self.wal.read().await.as_ref() {
+            if let Err(e) = wal.complete(flow_id) {
+                tracing::warn!("标记 WAL 条目 {} 完成失败: {}", flow_id, e);
+            }
+        }
     }
 
-    /// 设置请求速率追踪器的时间窗口
+    /// 按当前配置编译的内容规范化器，计算内容哈希的功能都应使用它
     ///
-    /// **Validates: Requirements 10.7**
-    pub async fn set_rate_window(&self, window_seconds: i64) {
-        self.rate_tracker
-            .write()
-            .await
-            .set_window_seconds(window_seconds);
+    /// 正则只在创建监控服务和规范化配置变化时编译，这里返回共享的实例。
+    pub async fn content_normalizer(&self) -> Arc<ContentNormalizer> {
+        self.content_normalizer.read().await.clone()
     }
 
-    /// 获取通知配置
-    ///
-    /// **Validates: Requirements 10.1, 10.2**
-    pub async fn notification_config(&self) -> NotificationConfig {
-        self.notification_config.read().await.clone()
+    /// 离线 Mock 模式配置
+    pub async fn mock_config(&self) -> MockConfig {
+        self.config.read().await.mock.clone()
     }
 
-    /// 更新通知配置
+    /// 在内存中的已完成 Flow 里查找用于 Mock 响应的 Flow（见 [`mock::find_match`]）
     ///
-    /// **Validates: Requirements 10.1, 10.2**
-    pub async fn update_notification_config(&self, config: NotificationConfig) {
-        let mut current = self.notification_config.write().await;
-        *current = config;
+    /// 归档的 Flow 需先导入内存（如 `import_flows`）才能参与匹配。
+    pub async fn find_mock_match(&self, request: &LLMRequest, stream: bool) -> Option<MockMatch> {
+        let fallback = self.config.read().await.mock.fallback;
+        let normalizer = self.content_normalizer().await;
+        let flows = self.memory_store.read().await.query(&FlowFilter {
+            states: Some(vec![FlowState::Completed]),
+            ..Default::default()
+        });
+        mock::find_match(&flows, request, &normalizer, fallback, stream)
     }
 
-    /// 触发通知
-    ///
-    /// **Validates: Requirements 10.1, 10.2, 10.3, 10.4**
-    ///
-    /// # Arguments
-    /// * `notification` - 通知事件
-    async fn trigger_notification(&self, notification: NotificationEvent) {
-        let config = self.notification_config.read().await;
+    /// 当前各 Provider 和模型的成功率 SLO 状态
+    pub async fn slo_status(&self) -> Vec<SloStatus> {
+        let config = self.config.read().await.slo.clone();
+        self.slo_tracker.lock().await.status(&config, Utc::now())
+    }
 
+    /// 将 Flow 的结果计入成功率 SLO，告警状态变化时发送事件，进入告警时发送通知
+    async fn record_slo(&self, flow: &LLMFlow, success: bool) {
+        let config = self.config.read().await.slo.clone();
         if !config.enabled {
             return;
         }
+        let changed = self.slo_tracker.lock().await.record(
+            &config,
+            &format!("{:?}", flow.metadata.provider),
+            &flow.request.model,
+            success,
+            Utc::now(),
+        );
+        if changed.is_empty() {
+            return;
+        }
 
-        // 发送通知事件
-        let _ = self.event_sender.send(FlowEvent::Notification {
-            notification: notification.clone(),
-        });
+        // Provider 整体告警时不再单独通知其下的模型
+        let provider_breached = changed.iter().any(|s| s.model.is_none() && s.alerting);
+        let settings = self.notification_config.read().await.error_flow.clone();
+        for status in changed {
+            if status.alerting {
+                tracing::warn!(
+                    "{} {:?} 成功率低于 SLO ({:.2}%)",
+                    status.provider,
+                    status.model,
+                    status.target * 100.0
+                );
+                if settings.enabled && (status.model.is_none() || !provider_breached) {
+                    self.trigger_notification(NotificationEvent::slo_breach(&status, &settings))
+                        .await;
+                }
+            } else {
+                tracing::info!("{} {:?} 成功率已恢复", status.provider, status.model);
+            }
+            let _ = self
+                .event_sender
+                .send(FlowEvent::SloStatusChanged { status });
+        }
     }
 
-    /// 检查并触发新 Flow 通知
-    ///
-    /// **Validates: Requirements 10.1**
-    async fn check_new_flow_notification(&self, flow: &LLMFlow) {
-        let config = self.notification_config.read().await;
+    /// 当前磁盘状态（未启用文件存储时为空）
+    pub async fn disk_status(&self) -> Option<DiskStatus> {
+        self.file_store.as_ref()?;
+        let config = self.config.read().await;
+        Some(self.disk_guard.status(&config.disk_guard))
+    }
 
-        if config.new_flow.enabled {
-            let notification = NotificationEvent::new_flow(
-                flow.id.clone(),
-                flow.request.model.clone(),
-                &config.new_flow,
-            );
-            drop(config);
-            self.trigger_notification(notification).await;
-        }
+    /// 文件存储读缓存统计（未启用文件存储时为空）
+    pub fn read_cache_stats(&self) -> Option<ReadCacheStats> {
+        self.file_store
+            .as_ref()
+            .map(|store| store.read_cache_stats())
     }
 
-    /// 检查并触发错误 Flow 通知
+    /// 将 Flow 写入文件存储
     ///
-    /// **Validates: Requirements 10.2**
-    async fn check_error_flow_notification(&self, flow: &LLMFlow, error: &FlowError) {
-        let config = self.notification_config.read().await;
+    /// 存储目录所在磁盘的剩余空间低于下限时跳过写入（Flow 只保留在内存中）。
+    /// 暂停时输出一次警告并发送一次通知，空间恢复后自动恢复写入。
+    async fn persist_flow(&self, flow: &LLMFlow) {
+        let Some(ref file_store) = self.file_store else {
+            return;
+        };
 
-        if config.error_flow.enabled {
-            let notification = NotificationEvent::error_flow(
+        let guard_config = self.config.read().await.disk_guard.clone();
+        let check = self.disk_guard.check(&guard_config, file_store.base_dir());
+        match check.transition {
+            Some(DiskTransition::Paused { available_bytes }) => {
+                tracing::warn!(
+                    "磁盘剩余空间不足 ({} 字节，下限 {} 字节)，暂停 Flow 持久化",
+                    available_bytes,
+                    guard_config.min_free_bytes()
+                );
+                let settings = self.notification_config.read().await.error_flow.clone();
+                if settings.enabled {
+                    self.trigger_notification(NotificationEvent::disk_space_low(
+                        available_bytes,
+                        guard_config.min_free_bytes(),
+                        &settings,
+                    ))
+                    .await;
+                }
+            }
+            Some(DiskTransition::Resumed { available_bytes }) => {
+                tracing::info!(
+                    "磁盘剩余空间已恢复 ({} 字节)，恢复 Flow 持久化",
+                    available_bytes
+                );
+            }
+            None => {}
+        }
+        if !check.writable {
+            return;
+        }
+
+        if let Err(e) = file_store.write(flow) {
+            tracing::error!("保存 Flow 到文件失败: {}", e);
+        }
+    }
+
+    /// 等待下一次配置更新
+    pub(crate) async fn retention_config_changed(&self) {
+        self.retention_notify.notified().await;
+    }
+
+    /// 按保留策略清理文件存储
+    ///
+    /// # 返回
+    /// - `None`: 未启用文件存储
+    pub async fn apply_retention_policy(
+        &self,
+        policy: &RetentionPolicy,
+    ) -> Option<Result<CleanupResult, FileStoreError>> {
+        let file_store = self.file_store.as_ref()?;
+        Some(file_store.apply_retention(policy, Utc::now()))
+    }
+
+    /// 导出内存存储快照
+    pub async fn dump_memory_snapshot(&self) -> Vec<u8> {
+        self.memory_store.read().await.snapshot()
+    }
+
+    /// 从快照恢复内存存储（替换当前内存中的 Flow）
+    ///
+    /// # 返回
+    /// 恢复后的 Flow 数量
+    pub async fn load_memory_snapshot(&self, data: &[u8]) -> Result<usize, serde_json::Error> {
+        self.memory_store.write().await.restore(data)
+    }
+
+    /// 把内存存储快照写入文件（先写临时文件再替换，避免留下不完整的快照）
+    ///
+    /// # 返回
+    /// 写入的 Flow 数量
+    pub async fn save_memory_snapshot(&self, path: &Path) -> std::io::Result<usize> {
+        let (data, count) = {
+            let store = self.memory_store.read().await;
+            (store.snapshot(), store.len())
+        };
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, data)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(count)
+    }
+
+    /// 从快照文件恢复内存存储，恢复成功后删除快照文件
+    ///
+    /// # 返回
+    /// - `Ok(None)`: 快照文件不存在
+    /// - `Ok(Some(n))`: 恢复了 n 个 Flow
+    pub async fn restore_memory_snapshot(&self, path: &Path) -> std::io::Result<Option<usize>> {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let count = self
+            .load_memory_snapshot(&data)
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::remove_file(path)?;
+        Ok(Some(count))
+    }
+
+    /// 导入外部捕获的已完成 Flow（如 mitmproxy 文件）
+    ///
+    /// 按当前配置排除敏感请求/响应头并应用自动标签，然后写入内存存储和文件存储。
+    /// 不触发 Flow 事件和阈值通知。
+    ///
+    /// # 返回
+    /// 导入的 Flow 数量
+    pub async fn import_flows(&self, flows: Vec<LLMFlow>) -> usize {
+        let config = self.config.read().await.clone();
+        let count = flows.len();
+        for mut flow in flows {
+            flow.request
+                .headers
+                .retain(|name, _| !config.is_sensitive_header(name));
+            if let Some(response) = flow.response.as_mut() {
+                response
+                    .headers
+                    .retain(|name, _| !config.is_sensitive_header(name));
+            }
+            self.apply_auto_tags(&mut flow).await;
+
+            self.persist_flow(&flow).await;
+            self.memory_store.write().await.add(flow);
+        }
+        count
+    }
+
+    /// 获取因活跃 Flow 达到上限而跳过的捕获数
+    pub fn dropped_capture_count(&self) -> u64 {
+        self.dropped_captures.load(Ordering::Relaxed)
+    }
+
+    /// 说明指定模型和路径的请求当前是否会被捕获，不捕获时给出原因
+    ///
+    /// 按实时配置和当前活跃 Flow 数量判定。采样是随机的，采样率大于 0 时视为会被捕获，
+    /// 并在 `capture_probability` 中给出被捕获的概率。
+    pub async fn explain_capture_decision(&self, model: &str, path: &str) -> CaptureDecision {
+        let config = self.config.read().await;
+        let reason = if !config.enabled {
+            Some(CaptureSkipReason::Disabled)
+        } else if let Some(reason) = config.exclusion_reason(model, path) {
+            Some(reason)
+        } else if config.sampling_rate <= 0.0 {
+            Some(CaptureSkipReason::SampledOut {
+                sampling_rate: config.sampling_rate,
+            })
+        } else {
+            let active = self.active_flows.read().await.len();
+            config
+                .max_active_flows
+                .filter(|max| active >= *max)
+                .map(|max| CaptureSkipReason::ActiveFlowCap {
+                    max,
+                    active,
+                    overflow: config.active_flow_overflow,
+                })
+        };
+
+        match reason {
+            Some(reason) => CaptureDecision {
+                would_capture: false,
+                capture_probability: 0.0,
+                message: reason.message(),
+                reason: Some(reason),
+            },
+            None => {
+                let probability = config.sampling_rate.min(1.0);
+                CaptureDecision {
+                    would_capture: true,
+                    capture_probability: probability,
+                    reason: None,
+                    message: if probability < 1.0 {
+                        format!("请求会按采样率 {:.0}% 随机捕获", probability * 100.0)
+                    } else {
+                        "请求会被捕获".to_string()
+                    },
+                }
+            }
+        }
+    }
+
+    /// 获取阈值配置
+    ///
+    /// **Validates: Requirements 10.3, 10.4**
+    pub async fn threshold_config(&self) -> ThresholdConfig {
+        self.threshold_config.read().await.clone()
+    }
+
+    /// 更新阈值配置
+    ///
+    /// **Validates: Requirements 10.3, 10.4**
+    pub async fn update_threshold_config(&self, config: ThresholdConfig) {
+        let mut current = self.threshold_config.write().await;
+        *current = config;
+    }
+
+    /// 获取当前请求速率（每秒）
+    ///
+    /// **Validates: Requirements 10.7**
+    pub async fn get_request_rate(&self) -> f64 {
+        self.rate_tracker.read().await.get_rate()
+    }
+
+    /// 获取时间窗口内的请求数量
+    ///
+    /// **Validates: Requirements 10.7**
+    pub async fn get_request_count(&self) -> usize {
+        self.rate_tracker.read().await.get_count()
+    }
+
+    /// 设置请求速率追踪器的时间窗口
+    ///
+    /// **Validates: Requirements 10.7**
+    pub async fn set_rate_window(&self, window_seconds: i64) {
+        self.rate_tracker
+            .write()
+            .await
+            .set_window_seconds(window_seconds);
+    }
+
+    /// 获取通知配置
+    ///
+    /// **Validates: Requirements 10.1, 10.2**
+    pub async fn notification_config(&self) -> NotificationConfig {
+        self.notification_config.read().await.clone()
+    }
+
+    /// 更新通知配置
+    ///
+    /// **Validates: Requirements 10.1, 10.2**
+    pub async fn update_notification_config(&self, config: NotificationConfig) {
+        let mut current = self.notification_config.write().await;
+        *current = config;
+    }
+
+    /// 获取自动标签配置
+    pub async fn auto_tag_config(&self) -> AutoTagConfig {
+        self.auto_tagger.read().await.config().clone()
+    }
+
+    /// 更新自动标签配置（热重载）
+    ///
+    /// 规则会在此处一次性编译；任一规则无效时保留原有规则并返回错误。
+    pub async fn update_auto_tag_config(&self, config: AutoTagConfig) -> Result<(), AutoTagError> {
+        let rule_count = config.rules.len();
+        self.auto_tagger.write().await.update(config)?;
+        tracing::info!("自动标签规则已更新，共 {} 条", rule_count);
+        Ok(())
+    }
+
+    /// 对 Flow 应用自动标签规则
+    async fn apply_auto_tags(&self, flow: &mut LLMFlow) {
+        let tagger = self.auto_tagger.read().await;
+        let matched = tagger.apply(flow);
+        if matched > 0 {
+            tracing::debug!(
+                "Flow {} 命中 {} 条自动标签规则: {:?}",
+                flow.id,
+                matched,
+                flow.annotations.auto_tags
+            );
+        }
+    }
+
+    /// 触发通知
+    ///
+    /// **Validates: Requirements 10.1, 10.2, 10.3, 10.4**
+    ///
+    /// # Arguments
+    /// * `notification` - 通知事件
+    async fn trigger_notification(&self, notification: NotificationEvent) {
+        let config = self.notification_config.read().await;
+        dispatch_notification(
+            &config,
+            &self.webhook_sink,
+            &self.event_sender,
+            notification,
+        );
+    }
+
+    /// 检查并触发新 Flow 通知
+    ///
+    /// **Validates: Requirements 10.1**
+    async fn check_new_flow_notification(&self, flow: &LLMFlow) {
+        let config = self.notification_config.read().await;
+
+        if config.new_flow.enabled {
+            let notification = NotificationEvent::new_flow(
                 flow.id.clone(),
                 flow.request.model.clone(),
-                error.message.clone(),
-                &config.error_flow,
+                &config.new_flow,
             );
             drop(config);
             self.trigger_notification(notification).await;
         }
     }
 
+    /// 检查并触发错误 Flow 通知
+    ///
+    /// **Validates: Requirements 10.2**
+    ///
+    /// 同一 Provider 的同类错误在合并窗口内超过阈值后不再单独通知，窗口结束时发送汇总通知
+    async fn check_error_flow_notification(&self, flow: &LLMFlow, error: &FlowError) {
+        let config = self.notification_config.read().await;
+        if !config.error_flow.enabled {
+            return;
+        }
+        let settings = config.error_flow.clone();
+        let coalescing = config.coalescing.clone();
+        drop(config);
+
+        let now = Utc::now();
+        let (due, decision) = {
+            let mut coalescer = self.error_coalescer.lock().await;
+            let due = coalescer.take_due(&coalescing, now);
+            let decision = coalescer.record(
+                &coalescing,
+                &format!("{:?}", flow.metadata.provider),
+                error.error_type.clone(),
+                &flow.id,
+                &flow.request.model,
+                now,
+            );
+            (due, decision)
+        };
+
+        for summary in &due {
+            self.trigger_notification(NotificationEvent::coalesced_errors(summary, &settings))
+                .await;
+        }
+        match decision {
+            CoalesceDecision::Notify => {
+                let notification = NotificationEvent::error_flow(
+                    flow.id.clone(),
+                    flow.request.model.clone(),
+                    error.message.clone(),
+                    &settings,
+                );
+                self.trigger_notification(notification).await;
+            }
+            CoalesceDecision::Suppress { first: true } => {
+                self.schedule_coalesced_flush(std::time::Duration::from_secs(
+                    coalescing.window_secs.max(1),
+                ));
+            }
+            CoalesceDecision::Suppress { first: false } => {}
+        }
+    }
+
+    /// 在合并窗口结束后发送汇总通知
+    ///
+    /// 窗口结束前又有新错误到达时，汇总可能已在 `check_error_flow_notification` 中发出，此时不会重复发送
+    fn schedule_coalesced_flush(&self, delay: std::time::Duration) {
+        let coalescer = self.error_coalescer.clone();
+        let notification_config = self.notification_config.clone();
+        let webhook_sink = self.webhook_sink.clone();
+        let event_sender = self.event_sender.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let config = notification_config.read().await;
+            let due = coalescer
+                .lock()
+                .await
+                .take_due(&config.coalescing, Utc::now());
+            for summary in &due {
+                let notification = NotificationEvent::coalesced_errors(summary, &config.error_flow);
+                dispatch_notification(&config, &webhook_sink, &event_sender, notification);
+            }
+        });
+    }
+
     /// 检查并触发阈值警告通知
     ///
     /// **Validates: Requirements 10.3, 10.4**
@@ -1046,12 +2138,29 @@ impl FlowMonitor {
     /// - `Some(flow_id)`: 成功创建 Flow，返回 Flow ID
     /// - `None`: 根据配置跳过监控
     pub async fn start_flow(&self, request: LLMRequest, metadata: FlowMetadata) -> Option<String> {
+        self.try_start_flow(request, metadata).await.ok().flatten()
+    }
+
+    /// 开始捕获 Flow，活跃 Flow 达到上限时按配置跳过或拒绝
+    ///
+    /// # 返回
+    /// - `Ok(Some(flow_id))`: 开始捕获
+    /// - `Ok(None)`: 不捕获（未启用、被排除或超出上限后跳过），请求照常处理
+    /// - `Err(ActiveFlowCapExceeded)`: 超出上限且配置为拒绝请求
+    pub async fn try_start_flow(
+        &self,
+        request: LLMRequest,
+        metadata: FlowMetadata,
+    ) -> Result<Option<String>, ActiveFlowCapExceeded> {
         let config = self.config.read().await;
 
         // 检查是否应该监控
         if !config.should_monitor(&request.model, &request.path) {
-            return None;
+            return Ok(None);
         }
+        let max_active_flows = config.max_active_flows;
+        let overflow = config.active_flow_overflow;
+        drop(config);
 
         // 记录请求到速率追踪器
         {
@@ -1062,25 +2171,48 @@ impl FlowMonitor {
         // 生成唯一 ID
         let flow_id = Uuid::new_v4().to_string();
 
-        // 确定 Flow 类型
-        let flow_type = Self::determine_flow_type(&request.path);
+        // 确定 Flow 类型（未知路径上的 multipart 上传归类为文件上传）
+        let flow_type = match Self::determine_flow_type(&request.path) {
+            FlowType::Other(_) if !request.attachments.is_empty() => FlowType::FileUpload,
+            flow_type => flow_type,
+        };
 
-        // 创建 Flow
-        let flow = LLMFlow::new(flow_id.clone(), flow_type, request.clone(), metadata);
+        // 创建 Flow（影子镜像请求自动标记 shadow 标签）
+        let is_shadow = metadata.shadow_of.is_some();
+        let mut flow = LLMFlow::new(flow_id.clone(), flow_type, request.clone(), metadata);
+        if is_shadow {
+            flow.annotations.tags.push(SHADOW_TAG.to_string());
+        }
 
         // 创建活跃 Flow 状态
         let active_flow = ActiveFlow {
             flow: flow.clone(),
             stream_rebuilder: None,
             request_start: Utc::now(),
+            response_headers: HashMap::new(),
+            body_info: None,
+            cancel_token: CancellationToken::new(),
+            bytes_received: Arc::new(AtomicU64::new(0)),
+            chunk_count: 0,
         };
 
-        // 添加到活跃 Flow
+        // 添加到活跃 Flow（在写锁内检查上限，避免并发请求越过上限）
         {
             let mut active = self.active_flows.write().await;
+            if let Some(max) = max_active_flows.filter(|max| active.len() >= *max) {
+                drop(active);
+                return self.on_active_flow_cap(max, overflow);
+            }
             active.insert(flow_id.clone(), active_flow);
         }
 
+        // 调用上游之前把请求写入预写日志
+        if let Some(wal) = self.wal.read().await.as_ref() {
+            if let Err(e) = wal.begin(&flow) {
+                tracing::error!("写入请求预写日志失败: {}", e);
+            }
+        }
+
         // 发送事件
         let summary = FlowSummary::from(&flow);
         let _ = self
@@ -1093,7 +2225,30 @@ impl FlowMonitor {
         // 发送请求速率更新
         self.send_rate_update().await;
 
-        Some(flow_id)
+        Ok(Some(flow_id))
+    }
+
+    /// 处理活跃 Flow 达到上限的情况，仅在首次达到上限时输出警告
+    fn on_active_flow_cap(
+        &self,
+        max: usize,
+        overflow: ActiveFlowOverflow,
+    ) -> Result<Option<String>, ActiveFlowCapExceeded> {
+        self.dropped_captures.fetch_add(1, Ordering::Relaxed);
+        if !self.cap_warned.swap(true, Ordering::Relaxed) {
+            tracing::warn!(
+                "[FLOW_MONITOR] 活跃 Flow 数量达到上限 {}，超出的请求将{}",
+                max,
+                match overflow {
+                    ActiveFlowOverflow::SkipCapture => "跳过捕获",
+                    ActiveFlowOverflow::RejectRequest => "被拒绝",
+                }
+            );
+        }
+        match overflow {
+            ActiveFlowOverflow::SkipCapture => Ok(None),
+            ActiveFlowOverflow::RejectRequest => Err(ActiveFlowCapExceeded { max }),
+        }
     }
 
     /// 根据路径确定 Flow 类型
@@ -1102,12 +2257,20 @@ impl FlowMonitor {
 
         if path_lower.contains("/chat/completions") {
             FlowType::ChatCompletions
+        } else if path_lower.contains("/responses") {
+            FlowType::Responses
         } else if path_lower.contains("/messages") {
             FlowType::AnthropicMessages
         } else if path_lower.contains(":generatecontent") || path_lower.contains("/generate") {
             FlowType::GeminiGenerateContent
         } else if path_lower.contains("/embeddings") {
             FlowType::Embeddings
+        } else if path_lower.contains("/audio/transcriptions")
+            || path_lower.contains("/audio/translations")
+        {
+            FlowType::AudioTranscription
+        } else if path_lower.contains("/files") || path_lower.contains("/uploads") {
+            FlowType::FileUpload
         } else {
             FlowType::Other(path.to_string())
         }
@@ -1121,13 +2284,17 @@ impl FlowMonitor {
     pub async fn set_streaming(&self, flow_id: &str, format: StreamFormat) {
         let config = self.config.read().await;
         let save_chunks = config.save_stream_chunks;
+        let compact_chunks = config.compact_stream_chunks;
         drop(config);
 
         let mut active = self.active_flows.write().await;
         if let Some(active_flow) = active.get_mut(flow_id) {
             active_flow.flow.state = FlowState::Streaming;
-            active_flow.stream_rebuilder =
-                Some(StreamRebuilder::new(format).with_save_raw_chunks(save_chunks));
+            active_flow.stream_rebuilder = Some(
+                StreamRebuilder::new(format)
+                    .with_save_raw_chunks(save_chunks)
+                    .with_compact_chunks(compact_chunks),
+            );
 
             // 发送更新事件
             let _ = self.event_sender.send(FlowEvent::FlowUpdated {
@@ -1142,6 +2309,110 @@ impl FlowMonitor {
         }
     }
 
+    /// 记录上游返回的请求 ID
+    ///
+    /// 需在 `complete_flow` / `fail_flow` 之前调用，重试时以最后一次上游响应为准
+    pub async fn set_upstream_request_id(&self, flow_id: &str, request_id: String) {
+        let mut active = self.active_flows.write().await;
+        if let Some(active_flow) = active.get_mut(flow_id) {
+            active_flow.flow.metadata.upstream_request_id = Some(request_id);
+        }
+    }
+
+    /// 按当前配置捕获客户端请求头（排除敏感头）
+    pub async fn capture_request_headers(
+        &self,
+        headers: &reqwest::header::HeaderMap,
+    ) -> HashMap<String, String> {
+        self.config.read().await.filter_request_headers(headers)
+    }
+
+    /// 记录 Provider 配置的附加上游请求头（敏感头的值脱敏）
+    pub async fn set_extra_headers(&self, flow_id: &str, headers: &reqwest::header::HeaderMap) {
+        let redacted = self.config.read().await.redact_headers(headers);
+        let mut active = self.active_flows.write().await;
+        if let Some(active_flow) = active.get_mut(flow_id) {
+            active_flow.flow.metadata.routing_info.extra_headers = redacted;
+        }
+    }
+
+    /// 记录上游响应头
+    ///
+    /// 按 `response_header_allowlist` 过滤后暂存，在 `complete_flow` 时合并到响应中；
+    /// 重试时以最后一次上游响应为准
+    pub async fn set_response_headers(&self, flow_id: &str, headers: &reqwest::header::HeaderMap) {
+        let captured = self.config.read().await.filter_response_headers(headers);
+        let mut active = self.active_flows.write().await;
+        if let Some(active_flow) = active.get_mut(flow_id) {
+            active_flow.response_headers = captured;
+        }
+    }
+
+    /// 为 multipart 上传请求创建增量捕获器
+    ///
+    /// 未启用 multipart 捕获或请求体不是 multipart 时返回 `None`。
+    pub async fn multipart_capture(&self, content_type: &str) -> Option<MultipartCapture> {
+        let config = self.config.read().await;
+        if !config.multipart.enabled {
+            return None;
+        }
+        MultipartCapture::from_content_type(content_type, config.multipart.clone())
+    }
+
+    /// 把 multipart 捕获结果写入活跃 Flow 的请求
+    pub async fn set_multipart_request(&self, flow_id: &str, capture: MultipartCapture) {
+        let size_bytes = capture.total_bytes() as usize;
+        let attachments = capture.finish();
+        let mut active = self.active_flows.write().await;
+        if let Some(active_flow) = active.get_mut(flow_id) {
+            let request = &mut active_flow.flow.request;
+            request.size_bytes = size_bytes;
+            apply_multipart_attachments(request, attachments);
+        }
+    }
+
+    /// 记录上游响应体的内容类型和解码结果
+    pub async fn set_response_body_info(&self, flow_id: &str, info: ResponseBodyInfo) {
+        let mut active = self.active_flows.write().await;
+        if let Some(active_flow) = active.get_mut(flow_id) {
+            active_flow.body_info = Some(info);
+        }
+    }
+
+    /// 记录请求发往上游的时间
+    ///
+    /// 在路由、凭证选择、参数注入和请求拦截完成后调用，重试时以最后一次为准
+    pub async fn mark_request_sent(&self, flow_id: &str) {
+        let mut active = self.active_flows.write().await;
+        if let Some(active_flow) = active.get_mut(flow_id) {
+            active_flow.flow.timestamps.request_end = Some(Utc::now());
+        }
+    }
+
+    /// 记录收到上游首字节的时间（仅首次调用生效）
+    pub async fn mark_response_start(&self, flow_id: &str) {
+        let mut active = self.active_flows.write().await;
+        if let Some(active_flow) = active.get_mut(flow_id) {
+            active_flow
+                .flow
+                .timestamps
+                .response_start
+                .get_or_insert_with(Utc::now);
+        }
+    }
+
+    /// 获取向上游透传 Flow ID 的请求头名称
+    pub async fn request_id_header(&self) -> Option<String> {
+        self.config
+            .read()
+            .await
+            .request_id_header
+            .as_deref()
+            .map(str::trim)
+            .filter(|h| !h.is_empty())
+            .map(str::to_string)
+    }
+
     /// 处理流式 chunk
     ///
     /// # 参数
@@ -1151,6 +2422,12 @@ impl FlowMonitor {
     pub async fn process_chunk(&self, flow_id: &str, event: Option<&str>, data: &str) {
         let mut active = self.active_flows.write().await;
         if let Some(active_flow) = active.get_mut(flow_id) {
+            active_flow
+                .flow
+                .timestamps
+                .response_start
+                .get_or_insert_with(Utc::now);
+            active_flow.chunk_count += 1;
             if let Some(ref mut rebuilder) = active_flow.stream_rebuilder {
                 // 处理 chunk
                 if let Err(e) = rebuilder.process_event(event, data) {
@@ -1170,24 +2447,98 @@ impl FlowMonitor {
     /// - `flow_id`: Flow ID
     /// - `response`: LLM 响应（如果是非流式响应）
     pub async fn complete_flow(&self, flow_id: &str, response: Option<LLMResponse>) {
+        let (max_logprob_tokens, inline_reasoning) = {
+            let config = self.config.read().await;
+            (
+                config.max_logprob_tokens,
+                config.extract_inline_reasoning.clone(),
+            )
+        };
         let mut active = self.active_flows.write().await;
 
         if let Some(mut active_flow) = active.remove(flow_id) {
             let now = Utc::now();
 
             // 如果有流式重建器，使用重建的响应
-            let final_response = if let Some(rebuilder) = active_flow.stream_rebuilder.take() {
-                Some(rebuilder.finish())
+            let mut final_response = if let Some(rebuilder) = active_flow.stream_rebuilder.take() {
+                let usage_source = rebuilder.usage_source();
+                // 没有终止事件的流可能被截断，保留已收到的内容并标记错误
+                if !rebuilder.completed_cleanly() {
+                    tracing::warn!("Flow {} 的流在终止事件之前结束，响应可能被截断", flow_id);
+                    active_flow.flow.error = Some(FlowError::new(
+                        FlowErrorType::IncompleteStream,
+                        "流在终止事件之前结束，响应可能被截断",
+                    ));
+                }
+                let mut rebuilt = rebuilder.finish();
+                // 上游未返回输出用量时，优先使用调用方提供的估算值；
+                // 上游已返回的输入 Token（如 Anthropic message_start）仍是准确值
+                if usage_source == UsageSource::Estimated {
+                    if let Some(estimated) = response.filter(|r| r.usage.total_tokens > 0) {
+                        let upstream_input_tokens = rebuilt.usage.input_tokens;
+                        rebuilt.usage = estimated.usage;
+                        if upstream_input_tokens > 0 {
+                            rebuilt.usage.input_tokens = upstream_input_tokens;
+                            rebuilt.usage.calculate_total();
+                        }
+                    }
+                }
+                active_flow.flow.metadata.usage_source = Some(usage_source);
+                Some(rebuilt)
             } else {
                 response
             };
 
+            // 提取正文中的内联推理，结构化输出校验只针对最终回答
+            if let Some(response) = final_response.as_mut() {
+                let provider = active_flow.flow.metadata.provider.to_string();
+                extract_inline_reasoning(&inline_reasoning, &provider, response);
+            }
+
+            // 按请求中的 JSON Schema 校验结构化输出
+            if let (Some(format), Some(response)) = (
+                active_flow.flow.request.parameters.response_format.as_ref(),
+                final_response.as_mut(),
+            ) {
+                response.schema_valid =
+                    structured_output::validate_response(format, &response.content);
+            }
+
+            // 合并捕获的上游响应头和响应体解码结果（调用方显式提供的优先），
+            // 统一停止原因和缓存用量，按上限截断对数概率并提取系统指纹
+            if let Some(response) = final_response.as_mut() {
+                response.normalize_stop_reason();
+                response.fill_cache_usage();
+                response.capture_logprobs(max_logprob_tokens);
+                response.capture_system_fingerprint();
+                for (name, value) in std::mem::take(&mut active_flow.response_headers) {
+                    response.headers.entry(name).or_insert(value);
+                }
+                if response.body_info.is_none() {
+                    response.body_info = active_flow.body_info.take();
+                }
+            }
+
+            // 未单独记录上游请求 ID 时，从响应头中提取
+            if active_flow.flow.metadata.upstream_request_id.is_none() {
+                active_flow.flow.metadata.upstream_request_id = final_response
+                    .as_ref()
+                    .and_then(LLMResponse::upstream_request_id);
+            }
+
             // 更新 Flow
             active_flow.flow.response = final_response;
             active_flow.flow.state = FlowState::Completed;
-            active_flow.flow.timestamps.response_end = Some(now);
-            active_flow.flow.timestamps.calculate_duration();
-            active_flow.flow.timestamps.calculate_ttfb();
+            // 非流式响应没有单独记录首字节时间，以完成时间为准
+            let timestamps = &mut active_flow.flow.timestamps;
+            timestamps.response_start.get_or_insert(now);
+            timestamps.response_end = Some(now);
+            timestamps.calculate_duration();
+            timestamps.calculate_ttfb();
+            timestamps.calculate_breakdown();
+
+            // 应用自动标签规则
+            self.apply_auto_tags(&mut active_flow.flow).await;
 
             // 检查阈值
             let threshold_result = self.check_threshold(&active_flow.flow).await;
@@ -1199,11 +2550,8 @@ impl FlowMonitor {
             }
 
             // 保存到文件存储
-            if let Some(ref file_store) = self.file_store {
-                if let Err(e) = file_store.write(&active_flow.flow) {
-                    tracing::error!("保存 Flow 到文件失败: {}", e);
-                }
-            }
+            self.persist_flow(&active_flow.flow).await;
+            self.wal_complete(flow_id).await;
 
             // 发送完成事件
             let summary = FlowSummary::from(&active_flow.flow);
@@ -1223,6 +2571,9 @@ impl FlowMonitor {
                 self.check_threshold_notifications(&active_flow.flow, &threshold_result)
                     .await;
             }
+
+            let success = active_flow.flow.error.is_none();
+            self.record_slo(&active_flow.flow, success).await;
         }
     }
 
@@ -1242,6 +2593,11 @@ impl FlowMonitor {
             active_flow.flow.state = FlowState::Failed;
             active_flow.flow.timestamps.response_end = Some(now);
             active_flow.flow.timestamps.calculate_duration();
+            active_flow.flow.timestamps.calculate_ttfb();
+            active_flow.flow.timestamps.calculate_breakdown();
+
+            // 应用自动标签规则
+            self.apply_auto_tags(&mut active_flow.flow).await;
 
             // 保存到内存存储
             {
@@ -1250,11 +2606,8 @@ impl FlowMonitor {
             }
 
             // 保存到文件存储
-            if let Some(ref file_store) = self.file_store {
-                if let Err(e) = file_store.write(&active_flow.flow) {
-                    tracing::error!("保存 Flow 到文件失败: {}", e);
-                }
-            }
+            self.persist_flow(&active_flow.flow).await;
+            self.wal_complete(flow_id).await;
 
             // 发送失败事件
             let _ = self.event_sender.send(FlowEvent::FlowFailed {
@@ -1265,6 +2618,8 @@ impl FlowMonitor {
             // 检查错误 Flow 通知
             self.check_error_flow_notification(&active_flow.flow, &error)
                 .await;
+
+            self.record_slo(&active_flow.flow, false).await;
         }
     }
 
@@ -1282,6 +2637,8 @@ impl FlowMonitor {
             active_flow.flow.state = FlowState::Cancelled;
             active_flow.flow.timestamps.response_end = Some(now);
             active_flow.flow.timestamps.calculate_duration();
+            active_flow.flow.timestamps.calculate_ttfb();
+            active_flow.flow.timestamps.calculate_breakdown();
 
             // 保存到内存存储
             {
@@ -1290,14 +2647,68 @@ impl FlowMonitor {
             }
 
             // 保存到文件存储
-            if let Some(ref file_store) = self.file_store {
-                if let Err(e) = file_store.write(&active_flow.flow) {
-                    tracing::error!("保存 Flow 到文件失败: {}", e);
-                }
-            }
+            self.persist_flow(&active_flow.flow).await;
+            self.wal_complete(flow_id).await;
         }
     }
 
+    /// 强制取消活跃 Flow
+    ///
+    /// 触发 Flow 的取消令牌以中止上游流，并将 Flow 标记为已取消。
+    ///
+    /// # 返回
+    /// - `true`: 已取消
+    /// - `false`: Flow 不存在或已结束
+    pub async fn force_cancel_flow(&self, flow_id: &str) -> bool {
+        let Some(token) = self
+            .active_flows
+            .read()
+            .await
+            .get(flow_id)
+            .map(|f| f.cancel_token.clone())
+        else {
+            return false;
+        };
+        token.cancel();
+        self.cancel_flow(flow_id).await;
+        tracing::info!("[FLOW] 已强制取消 Flow: {}", flow_id);
+        true
+    }
+
+    /// 获取活跃 Flow 句柄（Flow 不存在时返回 `None`）
+    pub async fn active_flow_handle(&self, flow_id: &str) -> Option<ActiveFlowHandle> {
+        self.active_flows
+            .read()
+            .await
+            .get(flow_id)
+            .map(|f| ActiveFlowHandle {
+                cancel_token: f.cancel_token.clone(),
+                bytes_received: f.bytes_received.clone(),
+            })
+    }
+
+    /// 列出活跃 Flow（按开始时间从早到晚排序）
+    pub async fn list_active(&self) -> Vec<ActiveFlowSummary> {
+        let now = Utc::now();
+        let active = self.active_flows.read().await;
+        let mut flows: Vec<ActiveFlowSummary> = active
+            .values()
+            .map(|f| ActiveFlowSummary {
+                id: f.flow.id.clone(),
+                model: f.flow.request.model.clone(),
+                provider: f.flow.metadata.provider,
+                state: f.flow.state.clone(),
+                streaming: f.stream_rebuilder.is_some(),
+                started_at: f.request_start,
+                age_ms: (now - f.request_start).num_milliseconds().max(0) as u64,
+                bytes_received: f.bytes_received.load(Ordering::Relaxed),
+                chunk_count: f.chunk_count,
+            })
+            .collect();
+        flows.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+        flows
+    }
+
     /// 更新 Flow 标注
     ///
     /// # 参数
@@ -1362,6 +2773,83 @@ impl FlowMonitor {
         })
     }
 
+    /// 记录内容过滤结果并添加 `content_filtered` 标签
+    ///
+    /// Flow 仍在进行中时写入活跃 Flow；已完成时更新内存中的 Flow，并同步文件存储的标注
+    pub async fn set_content_filter(&self, flow_id: &str, outcome: ContentFilterOutcome) -> bool {
+        let apply = |flow: &mut LLMFlow| {
+            flow.metadata.content_filter = Some(outcome.clone());
+            if !flow
+                .annotations
+                .tags
+                .iter()
+                .any(|t| t == CONTENT_FILTERED_TAG)
+            {
+                flow.annotations.tags.push(CONTENT_FILTERED_TAG.to_string());
+            }
+        };
+
+        if let Some(active_flow) = self.active_flows.write().await.get_mut(flow_id) {
+            apply(&mut active_flow.flow);
+            return true;
+        }
+
+        let mut annotations = None;
+        let updated = {
+            let store = self.memory_store.read().await;
+            store.update(flow_id, |flow| {
+                apply(flow);
+                annotations = Some(flow.annotations.clone());
+            })
+        };
+        if let (Some(file_store), Some(annotations)) = (&self.file_store, annotations) {
+            if let Err(e) = file_store.update_annotations(flow_id, &annotations) {
+                tracing::error!("更新文件存储标注失败: {}", e);
+            }
+        }
+        updated
+    }
+
+    /// 记录幂等重放
+    ///
+    /// 复制原始 Flow 的请求和响应生成新的已完成 Flow，标记 `idempotent_replay` 标签并关联原始 Flow。
+    /// 未启用监控或原始 Flow 不在内存中（未捕获或已淘汰）时返回 `None`。不触发阈值通知。
+    pub async fn record_idempotent_replay(&self, original_id: &str) -> Option<String> {
+        if !self.is_enabled().await {
+            return None;
+        }
+        let original = self.memory_store.read().await.get(original_id)?;
+        let original = original.read().ok()?.clone();
+
+        let now = Utc::now();
+        let flow_id = Uuid::new_v4().to_string();
+        let mut flow = LLMFlow {
+            id: flow_id.clone(),
+            timestamps: FlowTimestamps {
+                created: now,
+                request_start: now,
+                response_start: Some(now),
+                response_end: Some(now),
+                ..Default::default()
+            },
+            annotations: FlowAnnotations {
+                tags: vec![IDEMPOTENT_REPLAY_TAG.to_string()],
+                ..Default::default()
+            },
+            ..original
+        };
+        flow.metadata.idempotent_replay_of = Some(original_id.to_string());
+        self.apply_auto_tags(&mut flow).await;
+
+        self.memory_store.write().await.add(flow.clone());
+        self.persist_flow(&flow).await;
+        let _ = self.event_sender.send(FlowEvent::FlowCompleted {
+            id: flow_id.clone(),
+            summary: FlowSummary::from(&flow),
+        });
+        Some(flow_id)
+    }
+
     /// 设置标记
     pub async fn set_marker(&self, flow_id: &str, marker: Option<String>) -> bool {
         let store = self.memory_store.read().await;
@@ -1519,6 +3007,7 @@ impl FlowMonitor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::flow_monitor::memory_store::FlowFilter;
     use crate::flow_monitor::models::{
         FlowMetadata, LLMRequest, Message, MessageContent, MessageRole, RequestParameters,
     };
@@ -1545,6 +3034,7 @@ mod tests {
             parameters: RequestParameters::default(),
             size_bytes: 0,
             timestamp: Utc::now(),
+            attachments: Vec::new(),
         }
     }
 
@@ -1599,6 +3089,141 @@ mod tests {
         assert_eq!(monitor.memory_flow_count().await, 1);
     }
 
+    #[tokio::test]
+    async fn test_multipart_upload_capture() {
+        const CONTENT_TYPE: &str = "multipart/form-data; boundary=XyZ";
+        let body = b"--XyZ\r\n\
+Content-Disposition: form-data; name=\"model\"\r\n\r\nwhisper-1\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"file\"; filename=\"a.mp3\"\r\n\
+Content-Type: audio/mpeg\r\n\r\n0123456789\r\n\
+--XyZ--\r\n";
+
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        assert!(monitor
+            .multipart_capture("application/json")
+            .await
+            .is_none());
+
+        let request = LLMRequest {
+            method: "POST".to_string(),
+            path: "/v1/audio/transcriptions".to_string(),
+            ..Default::default()
+        };
+        let metadata = create_test_metadata(ProviderType::OpenAI);
+        let flow_id = monitor.start_flow(request, metadata).await.unwrap();
+
+        // 按 chunk 输入，模拟转发上传请求体
+        let mut capture = monitor.multipart_capture(CONTENT_TYPE).await.unwrap();
+        for chunk in body.chunks(7) {
+            capture.feed(chunk);
+        }
+        monitor.set_multipart_request(&flow_id, capture).await;
+        monitor.complete_flow(&flow_id, None).await;
+
+        let flow = monitor.memory_store().read().await.get(&flow_id).unwrap();
+        let flow = flow.read().unwrap();
+        assert_eq!(flow.flow_type, FlowType::AudioTranscription);
+        assert_eq!(flow.request.model, "whisper-1");
+        assert_eq!(flow.request.size_bytes, body.len());
+        assert_eq!(flow.request.attachments.len(), 2);
+        assert_eq!(
+            flow.request.attachments[1].file_name.as_deref(),
+            Some("a.mp3")
+        );
+        assert_eq!(flow.request.attachments[1].size_bytes, 10);
+
+        // 禁用后不再捕获
+        let mut config = monitor.config().await;
+        config.multipart.enabled = false;
+        monitor.update_config(config).await;
+        assert!(monitor.multipart_capture(CONTENT_TYPE).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_memory_snapshot_file_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("memory_snapshot.json");
+
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        for model in ["gpt-4", "claude-3"] {
+            let request = create_test_request(model, "/v1/chat/completions");
+            let metadata = create_test_metadata(ProviderType::OpenAI);
+            let flow_id = monitor.start_flow(request, metadata).await.unwrap();
+            monitor.complete_flow(&flow_id, None).await;
+        }
+        assert_eq!(monitor.save_memory_snapshot(&path).await.unwrap(), 2);
+
+        // 模拟重启
+        let restarted = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        assert_eq!(
+            restarted.restore_memory_snapshot(&path).await.unwrap(),
+            Some(2)
+        );
+        assert_eq!(restarted.memory_flow_count().await, 2);
+        assert_eq!(
+            restarted.memory_store().read().await.get_all_ids(),
+            monitor.memory_store().read().await.get_all_ids()
+        );
+
+        // 快照恢复后被删除
+        assert!(!path.exists());
+        assert_eq!(
+            restarted.restore_memory_snapshot(&path).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wal_recovers_flows_interrupted_by_crash() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = FlowMonitorConfig {
+            wal_path: Some(temp_dir.path().join("requests.wal")),
+            ..Default::default()
+        };
+
+        let (completed, interrupted) = {
+            let monitor = FlowMonitor::new(config.clone(), None);
+            let completed = monitor
+                .start_flow(
+                    create_test_request("gpt-4", "/v1/chat/completions"),
+                    create_test_metadata(ProviderType::OpenAI),
+                )
+                .await
+                .unwrap();
+            let interrupted = monitor
+                .start_flow(
+                    create_test_request("claude-3", "/v1/messages"),
+                    create_test_metadata(ProviderType::Claude),
+                )
+                .await
+                .unwrap();
+            monitor.complete_flow(&completed, None).await;
+            // 模拟崩溃：第二个请求从未结束
+            (completed, interrupted)
+        };
+
+        let restarted = FlowMonitor::new(config, None);
+        assert_eq!(restarted.recover_wal().await, 1);
+        assert_eq!(restarted.recover_wal().await, 0);
+
+        let store = restarted.memory_store();
+        let store = store.read().await;
+        assert!(store.get(&completed).is_none());
+        let recovered = store.get(&interrupted).unwrap();
+        let recovered = recovered.read().unwrap();
+        assert_eq!(recovered.request.model, "claude-3");
+        assert_eq!(recovered.state, FlowState::Failed);
+        assert_eq!(
+            recovered.error.as_ref().unwrap().error_type,
+            FlowErrorType::UnknownOutcome
+        );
+        assert!(recovered
+            .annotations
+            .tags
+            .contains(&UNKNOWN_OUTCOME_TAG.to_string()));
+    }
+
     #[tokio::test]
     async fn test_fail_flow() {
         let config = FlowMonitorConfig::default();
@@ -1620,6 +3245,256 @@ mod tests {
         assert_eq!(monitor.memory_flow_count().await, 1);
     }
 
+    #[tokio::test]
+    async fn test_identical_errors_coalesced() {
+        let notification_config = NotificationConfig {
+            coalescing: CoalescingSettings {
+                enabled: true,
+                window_secs: 60,
+                threshold: 1,
+            },
+            ..Default::default()
+        };
+        let monitor = FlowMonitor::with_full_config(
+            FlowMonitorConfig::default(),
+            None,
+            ThresholdConfig::default(),
+            notification_config,
+        );
+        let mut receiver = monitor.subscribe();
+
+        for _ in 0..5 {
+            let request = create_test_request("gpt-4", "/v1/chat/completions");
+            let metadata = create_test_metadata(ProviderType::OpenAI);
+            let flow_id = monitor.start_flow(request, metadata).await.unwrap();
+            let error = FlowError::new(
+                crate::flow_monitor::models::FlowErrorType::Authentication,
+                "Invalid API key",
+            );
+            monitor.fail_flow(&flow_id, error).await;
+        }
+
+        let mut notifications = 0;
+        while let Ok(event) = receiver.try_recv() {
+            if matches!(event, FlowEvent::Notification { .. }) {
+                notifications += 1;
+            }
+        }
+        assert_eq!(notifications, 1);
+    }
+
+    #[tokio::test]
+    async fn test_slo_breach_emits_status_change() {
+        let config = FlowMonitorConfig {
+            slo: SloConfig {
+                min_requests: 5,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let monitor = FlowMonitor::new(config, None);
+        let mut receiver = monitor.subscribe();
+
+        for _ in 0..5 {
+            let request = create_test_request("gpt-4", "/v1/chat/completions");
+            let metadata = create_test_metadata(ProviderType::OpenAI);
+            let flow_id = monitor.start_flow(request, metadata).await.unwrap();
+            let error = FlowError::new(
+                crate::flow_monitor::models::FlowErrorType::ServerError,
+                "Internal error",
+            );
+            monitor.fail_flow(&flow_id, error).await;
+        }
+
+        let mut changed = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            if let FlowEvent::SloStatusChanged { status } = event {
+                changed.push(status);
+            }
+        }
+        assert_eq!(changed.len(), 2);
+        assert!(changed.iter().all(|s| s.alerting));
+
+        let status = monitor.slo_status().await;
+        assert_eq!(status.len(), 2);
+        assert_eq!(status[0].short_window.failures, 5);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_request_id_captured() {
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        let stored_request_id = |flow_id: String| {
+            let store = monitor.memory_store();
+            async move {
+                let store = store.read().await;
+                let flow = store.get(&flow_id).unwrap();
+                let request_id = flow.read().unwrap().metadata.upstream_request_id.clone();
+                request_id
+            }
+        };
+
+        // 成功的 Flow 从响应头中提取（忽略大小写）
+        let flow_id = monitor
+            .start_flow(
+                create_test_request("claude-3", "/v1/messages"),
+                create_test_metadata(ProviderType::Claude),
+            )
+            .await
+            .unwrap();
+        let mut response = LLMResponse::default();
+        response
+            .headers
+            .insert("Request-Id".to_string(), "req_011Cabc".to_string());
+        monitor.complete_flow(&flow_id, Some(response)).await;
+        assert_eq!(
+            stored_request_id(flow_id).await.as_deref(),
+            Some("req_011Cabc")
+        );
+
+        // 失败的 Flow 需在 fail_flow 之前单独记录
+        let flow_id = monitor
+            .start_flow(
+                create_test_request("gpt-4", "/v1/chat/completions"),
+                create_test_metadata(ProviderType::OpenAI),
+            )
+            .await
+            .unwrap();
+        monitor
+            .set_upstream_request_id(&flow_id, "req_openai_1".to_string())
+            .await;
+        let error = FlowError::new(
+            crate::flow_monitor::models::FlowErrorType::ServerError,
+            "Internal error",
+        );
+        monitor.fail_flow(&flow_id, error).await;
+        assert_eq!(
+            stored_request_id(flow_id).await.as_deref(),
+            Some("req_openai_1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_response_headers_captured_by_allowlist() {
+        use reqwest::header::{HeaderMap, HeaderValue};
+
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        let flow_id = monitor
+            .start_flow(
+                create_test_request("gpt-4", "/v1/chat/completions"),
+                create_test_metadata(ProviderType::OpenAI),
+            )
+            .await
+            .unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-ratelimit-remaining-requests",
+            HeaderValue::from_static("42"),
+        );
+        headers.insert("retry-after", HeaderValue::from_static("3"));
+        headers.insert("x-request-id", HeaderValue::from_static("req_abc"));
+        headers.insert("set-cookie", HeaderValue::from_static("session=secret"));
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        monitor.set_response_headers(&flow_id, &headers).await;
+        monitor
+            .complete_flow(&flow_id, Some(LLMResponse::default()))
+            .await;
+
+        let store = monitor.memory_store();
+        let store = store.read().await;
+        let flow = store.get(&flow_id).unwrap();
+        let flow = flow.read().unwrap();
+        let captured = &flow.response.as_ref().unwrap().headers;
+        assert_eq!(
+            captured
+                .get("x-ratelimit-remaining-requests")
+                .map(String::as_str),
+            Some("42")
+        );
+        assert_eq!(captured.get("retry-after").map(String::as_str), Some("3"));
+        assert!(!captured.contains_key("set-cookie"));
+        assert!(!captured.contains_key("content-type"));
+        assert_eq!(
+            flow.metadata.upstream_request_id.as_deref(),
+            Some("req_abc")
+        );
+
+        // 通配符白名单也不会捕获敏感响应头
+        let config = FlowMonitorConfig {
+            response_header_allowlist: vec!["*".to_string()],
+            ..Default::default()
+        };
+        let captured = config.filter_response_headers(&headers);
+        assert!(captured.contains_key("content-type"));
+        assert!(!captured.contains_key("set-cookie"));
+    }
+
+    #[test]
+    fn test_sensitive_request_headers_not_captured() {
+        use reqwest::header::{HeaderMap, HeaderValue};
+
+        let mut headers = HeaderMap::new();
+        headers.insert("cookie", HeaderValue::from_static("session=secret"));
+        headers.insert("x-internal-token", HeaderValue::from_static("tok_123"));
+        headers.insert("x-amz-security-token", HeaderValue::from_static("aws"));
+        headers.insert("authorization", HeaderValue::from_static("Bearer sk"));
+        headers.insert("x-tenant-auth", HeaderValue::from_static("tenant"));
+        headers.insert("user-agent", HeaderValue::from_static("curl/8.0"));
+
+        let config = FlowMonitorConfig::default();
+        let captured = config.filter_request_headers(&headers);
+        assert!(!captured.contains_key("cookie"));
+        assert!(!captured.contains_key("x-internal-token"));
+        assert!(!captured.contains_key("x-amz-security-token"));
+        assert!(!captured.contains_key("authorization"));
+        assert_eq!(
+            captured.get("user-agent").map(String::as_str),
+            Some("curl/8.0")
+        );
+        assert!(captured.contains_key("x-tenant-auth"));
+
+        // 正则补充自定义认证头，请求头和响应头一致
+        let config = FlowMonitorConfig {
+            sensitive_header_regex: Some("^x-tenant-".to_string()),
+            response_header_allowlist: vec!["*".to_string()],
+            ..Default::default()
+        };
+        assert!(!config
+            .filter_request_headers(&headers)
+            .contains_key("x-tenant-auth"));
+        let response = config.filter_response_headers(&headers);
+        assert!(!response.contains_key("x-tenant-auth"));
+        assert!(!response.contains_key("x-internal-token"));
+        assert!(response.contains_key("user-agent"));
+    }
+
+    #[test]
+    fn test_captured_headers_capped() {
+        use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+        let mut headers = HeaderMap::new();
+        for i in 0..10 {
+            headers.insert(
+                HeaderName::from_bytes(format!("x-custom-{}", i).as_bytes()).unwrap(),
+                HeaderValue::from_static("value"),
+            );
+        }
+        headers.insert("x-long", HeaderValue::from_str(&"a".repeat(100)).unwrap());
+
+        let config = FlowMonitorConfig {
+            max_captured_headers: 5,
+            max_header_value_bytes: 8,
+            ..Default::default()
+        };
+        let captured = config.filter_request_headers(&headers);
+        assert_eq!(captured.len(), 5);
+        assert!(captured.values().all(|v| v.len() <= 8));
+
+        let mut value = "你好世界".to_string();
+        truncate_at_char_boundary(&mut value, 7);
+        assert_eq!(value, "你好");
+    }
+
     #[tokio::test]
     async fn test_config_should_monitor() {
         let config = FlowMonitorConfig {
@@ -1640,6 +3515,65 @@ mod tests {
         assert!(!config.should_monitor("gpt-4", "/health"));
     }
 
+    #[tokio::test]
+    async fn test_explain_capture_decision() {
+        let monitor = FlowMonitor::new(
+            FlowMonitorConfig {
+                excluded_models: vec!["test-*".to_string()],
+                excluded_paths: vec!["/health".to_string()],
+                max_active_flows: Some(1),
+                ..Default::default()
+            },
+            None,
+        );
+
+        let decision = monitor
+            .explain_capture_decision("gpt-4", "/v1/chat/completions")
+            .await;
+        assert!(decision.would_capture);
+        assert_eq!(decision.capture_probability, 1.0);
+
+        let decision = monitor
+            .explain_capture_decision("test-model", "/v1/chat/completions")
+            .await;
+        assert!(!decision.would_capture);
+        assert_eq!(
+            decision.reason,
+            Some(CaptureSkipReason::ExcludedModel {
+                pattern: "test-*".to_string()
+            })
+        );
+        assert!(decision.message.contains("test-*"));
+
+        // 活跃 Flow 达到上限
+        monitor
+            .start_flow(
+                create_test_request("gpt-4", "/v1/chat/completions"),
+                FlowMetadata::default(),
+            )
+            .await
+            .unwrap();
+        let decision = monitor
+            .explain_capture_decision("gpt-4", "/v1/chat/completions")
+            .await;
+        assert!(matches!(
+            decision.reason,
+            Some(CaptureSkipReason::ActiveFlowCap {
+                max: 1,
+                active: 1,
+                ..
+            })
+        ));
+
+        let mut config = monitor.config().await;
+        config.enabled = false;
+        monitor.update_config(config).await;
+        let decision = monitor
+            .explain_capture_decision("gpt-4", "/v1/chat/completions")
+            .await;
+        assert_eq!(decision.reason, Some(CaptureSkipReason::Disabled));
+    }
+
     #[tokio::test]
     async fn test_disabled_monitor() {
         let config = FlowMonitorConfig {
@@ -1685,6 +3619,10 @@ mod tests {
             FlowMonitor::determine_flow_type("/v1/chat/completions"),
             FlowType::ChatCompletions
         );
+        assert_eq!(
+            FlowMonitor::determine_flow_type("/v1/responses"),
+            FlowType::Responses
+        );
         assert_eq!(
             FlowMonitor::determine_flow_type("/v1/messages"),
             FlowType::AnthropicMessages
@@ -1697,6 +3635,297 @@ mod tests {
             FlowMonitor::determine_flow_type("/v1/embeddings"),
             FlowType::Embeddings
         );
+        assert_eq!(
+            FlowMonitor::determine_flow_type("/v1/audio/transcriptions"),
+            FlowType::AudioTranscription
+        );
+        assert_eq!(
+            FlowMonitor::determine_flow_type("/v1/files"),
+            FlowType::FileUpload
+        );
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_validated_on_complete() {
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        let mut request = create_test_request("gpt-4o", "/v1/chat/completions");
+        request.parameters.response_format = Some(serde_json::json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": "answer",
+                "schema": {
+                    "type": "object",
+                    "properties": {"answer": {"type": "integer"}},
+                    "required": ["answer"]
+                }
+            }
+        }));
+        assert!(request.is_structured_output());
+
+        let mut ids = Vec::new();
+        for content in [r#"{"answer": 42}"#, r#"{"answer": "forty-two"}"#] {
+            let flow_id = monitor
+                .start_flow(request.clone(), create_test_metadata(ProviderType::OpenAI))
+                .await
+                .unwrap();
+            let response = LLMResponse {
+                content: content.to_string(),
+                ..Default::default()
+            };
+            monitor.complete_flow(&flow_id, Some(response)).await;
+            ids.push(flow_id);
+        }
+
+        let store = monitor.memory_store.read().await;
+        let invalid = store.query(&FlowFilter {
+            schema_valid: Some(false),
+            ..Default::default()
+        });
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].id, ids[1]);
+        let valid = store.get(&ids[0]).unwrap();
+        let valid = valid.read().unwrap();
+        assert_eq!(valid.response.as_ref().unwrap().schema_valid, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_active_flow_cap() {
+        let config = FlowMonitorConfig {
+            max_active_flows: Some(2),
+            ..Default::default()
+        };
+        let monitor = FlowMonitor::new(config.clone(), None);
+        let start = || {
+            monitor.try_start_flow(
+                create_test_request("gpt-4", "/v1/chat/completions"),
+                create_test_metadata(ProviderType::OpenAI),
+            )
+        };
+
+        let first = start().await.unwrap().unwrap();
+        assert!(start().await.unwrap().is_some());
+
+        // 默认跳过超出部分的捕获，请求照常处理
+        assert_eq!(start().await, Ok(None));
+        assert_eq!(start().await, Ok(None));
+        assert_eq!(monitor.active_flow_count().await, 2);
+        assert_eq!(monitor.dropped_capture_count(), 2);
+
+        // 拒绝模式下返回错误，由调用方拒绝请求
+        monitor
+            .update_config(FlowMonitorConfig {
+                active_flow_overflow: ActiveFlowOverflow::RejectRequest,
+                ..config
+            })
+            .await;
+        assert_eq!(start().await, Err(ActiveFlowCapExceeded { max: 2 }));
+        assert_eq!(monitor.dropped_capture_count(), 3);
+
+        // 活跃 Flow 完成后恢复捕获
+        monitor.complete_flow(&first, None).await;
+        assert!(start().await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_list_and_force_cancel_active_flows() {
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        let first = monitor
+            .start_flow(
+                create_test_request("gpt-4", "/v1/chat/completions"),
+                create_test_metadata(ProviderType::OpenAI),
+            )
+            .await
+            .unwrap();
+        let second = monitor
+            .start_flow(
+                create_test_request("claude-3", "/v1/messages"),
+                create_test_metadata(ProviderType::Claude),
+            )
+            .await
+            .unwrap();
+
+        monitor
+            .set_streaming(&second, StreamFormat::Anthropic)
+            .await;
+        let handle = monitor.active_flow_handle(&second).await.unwrap();
+        handle.record_bytes(128);
+        monitor
+            .process_chunk(&second, Some("ping"), r#"{"type":"ping"}"#)
+            .await;
+
+        let active = monitor.list_active().await;
+        assert_eq!(active.len(), 2);
+        assert_eq!(active[0].id, first);
+        assert!(!active[0].streaming);
+        assert_eq!(active[1].model, "claude-3");
+        assert_eq!(active[1].provider, ProviderType::Claude);
+        assert!(active[1].streaming);
+        assert_eq!(active[1].bytes_received, 128);
+        assert_eq!(active[1].chunk_count, 1);
+
+        // 强制取消会触发取消令牌，并把 Flow 标记为已取消
+        assert!(monitor.force_cancel_flow(&second).await);
+        assert!(handle.cancel_token.is_cancelled());
+        assert!(!monitor.force_cancel_flow(&second).await);
+        assert_eq!(monitor.active_flow_count().await, 1);
+        let cancelled = monitor.memory_store().read().await.get(&second).unwrap();
+        assert_eq!(cancelled.read().unwrap().state, FlowState::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_usage_reconciliation() {
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+
+        let flow_id = monitor
+            .start_flow(
+                create_test_request("claude-3", "/v1/messages"),
+                create_test_metadata(ProviderType::Claude),
+            )
+            .await
+            .unwrap();
+        monitor
+            .set_streaming(&flow_id, StreamFormat::Anthropic)
+            .await;
+        for (event, data) in [
+            (
+                "message_start",
+                r#"{"type":"message_start","message":{"id":"msg_1","model":"claude-3","usage":{"input_tokens":42}}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi there"}}"#,
+            ),
+            (
+                "message_delta",
+                r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":7}}"#,
+            ),
+        ] {
+            monitor.process_chunk(&flow_id, Some(event), data).await;
+        }
+
+        // 调用方传入的估算值被上游的准确用量覆盖
+        let mut estimated = LLMResponse::default();
+        estimated.usage.input_tokens = 100;
+        estimated.usage.output_tokens = 100;
+        estimated.usage.calculate_total();
+        monitor.complete_flow(&flow_id, Some(estimated)).await;
+
+        let store = monitor.memory_store.read().await;
+        let flow = store.get(&flow_id).unwrap();
+        let flow = flow.read().unwrap();
+        let usage = &flow.response.as_ref().unwrap().usage;
+        assert_eq!((usage.input_tokens, usage.output_tokens), (42, 7));
+        assert_eq!(flow.metadata.usage_source, Some(UsageSource::Upstream));
+        drop(flow);
+        drop(store);
+
+        // 流在 message_delta 之前中断：输入 Token 来自上游，输出 Token 为估算值
+        let flow_id = monitor
+            .start_flow(
+                create_test_request("claude-3", "/v1/messages"),
+                create_test_metadata(ProviderType::Claude),
+            )
+            .await
+            .unwrap();
+        monitor
+            .set_streaming(&flow_id, StreamFormat::Anthropic)
+            .await;
+        monitor
+            .process_chunk(
+                &flow_id,
+                Some("message_start"),
+                r#"{"type":"message_start","message":{"id":"msg_2","model":"claude-3","usage":{"input_tokens":42}}}"#,
+            )
+            .await;
+        let mut estimated = LLMResponse::default();
+        estimated.usage.input_tokens = 100;
+        estimated.usage.output_tokens = 100;
+        estimated.usage.calculate_total();
+        monitor.complete_flow(&flow_id, Some(estimated)).await;
+
+        let store = monitor.memory_store.read().await;
+        let flow = store.get(&flow_id).unwrap();
+        let flow = flow.read().unwrap();
+        let usage = &flow.response.as_ref().unwrap().usage;
+        assert_eq!((usage.input_tokens, usage.output_tokens), (42, 100));
+        assert_eq!(flow.metadata.usage_source, Some(UsageSource::Estimated));
+    }
+
+    #[tokio::test]
+    async fn test_truncated_stream_marked_incomplete() {
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        let flow_id = monitor
+            .start_flow(
+                create_test_request("gpt-4", "/v1/chat/completions"),
+                create_test_metadata(ProviderType::OpenAI),
+            )
+            .await
+            .unwrap();
+        monitor.set_streaming(&flow_id, StreamFormat::OpenAI).await;
+        monitor
+            .process_chunk(
+                &flow_id,
+                None,
+                r#"{"id":"chatcmpl-1","choices":[{"index":0,"delta":{"content":"Hel"}}]}"#,
+            )
+            .await;
+        monitor.complete_flow(&flow_id, None).await;
+
+        let store = monitor.memory_store.read().await;
+        let flow = store.get(&flow_id).unwrap();
+        let flow = flow.read().unwrap();
+        assert_eq!(flow.state, FlowState::Completed);
+        assert_eq!(flow.response.as_ref().unwrap().content, "Hel");
+        assert_eq!(
+            flow.error.as_ref().unwrap().error_type,
+            FlowErrorType::IncompleteStream
+        );
+    }
+
+    #[tokio::test]
+    async fn test_captured_flow_served_in_mock_mode() {
+        let config = FlowMonitorConfig {
+            save_stream_chunks: true,
+            ..Default::default()
+        };
+        let monitor = FlowMonitor::new(config, None);
+        let request = create_test_request("gpt-4", "/v1/chat/completions");
+        let chunks = [
+            r#"{"id":"chatcmpl-1","choices":[{"index":0,"delta":{"content":"Hi"}}]}"#,
+            r#"{"id":"chatcmpl-1","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#,
+            "[DONE]",
+        ];
+
+        let flow_id = monitor
+            .start_flow(request.clone(), create_test_metadata(ProviderType::OpenAI))
+            .await
+            .unwrap();
+        monitor.set_streaming(&flow_id, StreamFormat::OpenAI).await;
+        for data in chunks {
+            monitor.process_chunk(&flow_id, None, data).await;
+        }
+        monitor.complete_flow(&flow_id, None).await;
+
+        // 相同内容的流式请求按保存的 chunk 重新推送
+        let found = monitor.find_mock_match(&request, true).await.unwrap();
+        assert_eq!(found.flow.id, flow_id);
+        assert_eq!(found.kind, mock::MockMatchKind::Exact);
+        let events = mock::stream_events(&found.flow, &MockConfig::default());
+        let replayed: Vec<String> = events.iter().map(|e| e.text.clone()).collect();
+        let expected: Vec<String> = chunks.iter().map(|c| format!("data: {}\n\n", c)).collect();
+        assert_eq!(replayed, expected);
+
+        // 非流式请求返回重建的响应体
+        let body = &found.flow.response.as_ref().unwrap().body;
+        assert_eq!(body["choices"][0]["message"]["content"], "Hi");
+
+        // 内容不同时按配置返回 404
+        let other = create_test_request("gpt-4", "/v1/chat/completions");
+        let other = LLMRequest {
+            system_prompt: Some("Be brief.".to_string()),
+            ..other
+        };
+        assert!(monitor.find_mock_match(&other, true).await.is_none());
     }
 
     #[tokio::test]
@@ -1726,6 +3955,136 @@ mod tests {
         // 测试设置标记
         assert!(monitor.set_marker(&flow_id, Some("⭐".to_string())).await);
     }
+
+    #[tokio::test]
+    async fn test_set_content_filter_on_completed_flow() {
+        use crate::flow_monitor::models::ContentFilterAction;
+
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        let request = create_test_request("gpt-4", "/v1/chat/completions");
+        let metadata = create_test_metadata(ProviderType::OpenAI);
+        let flow_id = monitor.start_flow(request, metadata).await.unwrap();
+        monitor.complete_flow(&flow_id, None).await;
+
+        let outcome = ContentFilterOutcome {
+            action: ContentFilterAction::Block,
+            rules: vec!["codename".to_string()],
+            redactions: 0,
+        };
+        assert!(monitor.set_content_filter(&flow_id, outcome.clone()).await);
+        assert!(!monitor.set_content_filter("missing", outcome.clone()).await);
+
+        let store = monitor.memory_store.read().await;
+        let flow = store.get(&flow_id).unwrap();
+        let flow = flow.read().unwrap();
+        assert_eq!(flow.metadata.content_filter, Some(outcome));
+        assert_eq!(
+            flow.annotations.tags,
+            vec![CONTENT_FILTERED_TAG.to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_idempotent_replay() {
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        let request = create_test_request("gpt-4", "/v1/chat/completions");
+        let metadata = create_test_metadata(ProviderType::OpenAI);
+        let original_id = monitor.start_flow(request, metadata).await.unwrap();
+        monitor.complete_flow(&original_id, None).await;
+
+        let replay_id = monitor
+            .record_idempotent_replay(&original_id)
+            .await
+            .unwrap();
+        assert_ne!(replay_id, original_id);
+        assert!(monitor.record_idempotent_replay("missing").await.is_none());
+
+        let store = monitor.memory_store.read().await;
+        let flow = store.get(&replay_id).unwrap();
+        let flow = flow.read().unwrap();
+        assert_eq!(flow.state, FlowState::Completed);
+        assert_eq!(
+            flow.metadata.idempotent_replay_of.as_deref(),
+            Some(original_id.as_str())
+        );
+        assert!(flow
+            .annotations
+            .tags
+            .contains(&IDEMPOTENT_REPLAY_TAG.to_string()));
+        assert_eq!(flow.timestamps.duration_ms, 0);
+    }
+
+    #[tokio::test]
+    async fn test_auto_tags_applied_on_fail() {
+        use crate::flow_monitor::auto_tag::AutoTagRule;
+        use crate::flow_monitor::models::FlowErrorType;
+
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        monitor
+            .update_auto_tag_config(AutoTagConfig {
+                enabled: true,
+                rules: vec![AutoTagRule {
+                    name: "auth".to_string(),
+                    condition: "~e".to_string(),
+                    tags: vec!["errored-auth".to_string()],
+                    star: true,
+                    enabled: true,
+                }],
+            })
+            .await
+            .unwrap();
+
+        // 无效规则不应替换已有规则
+        assert!(monitor
+            .update_auto_tag_config(AutoTagConfig {
+                enabled: true,
+                rules: vec![AutoTagRule {
+                    name: String::new(),
+                    condition: "~nope".to_string(),
+                    tags: vec![],
+                    star: false,
+                    enabled: true,
+                }],
+            })
+            .await
+            .is_err());
+        assert_eq!(monitor.auto_tag_config().await.rules[0].name, "auth");
+
+        let request = create_test_request("gpt-4", "/v1/chat/completions");
+        let metadata = create_test_metadata(ProviderType::OpenAI);
+        let flow_id = monitor.start_flow(request, metadata).await.unwrap();
+        monitor
+            .fail_flow(
+                &flow_id,
+                FlowError::new(FlowErrorType::Authentication, "denied"),
+            )
+            .await;
+
+        let store = monitor.memory_store.read().await;
+        let flow = store.get(&flow_id).unwrap();
+        let flow = flow.read().unwrap();
+        assert_eq!(flow.annotations.auto_tags, vec!["errored-auth".to_string()]);
+        assert!(flow.annotations.tags.is_empty());
+        assert!(flow.annotations.starred);
+    }
+
+    #[tokio::test]
+    async fn test_content_normalizer_is_cached_until_config_changes() {
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        let first = monitor.content_normalizer().await;
+        assert!(Arc::ptr_eq(&first, &monitor.content_normalizer().await));
+
+        let mut config = monitor.config().await;
+        config.content_normalization.strip_timestamps = true;
+        monitor.update_config(config.clone()).await;
+        let updated = monitor.content_normalizer().await;
+        assert!(!Arc::ptr_eq(&first, &updated));
+        assert_eq!(updated.normalize("at 2024-05-01T12:30:45Z"), "at");
+
+        // 规范化配置未变化时沿用已编译的实例
+        monitor.update_config(config).await;
+        assert!(Arc::ptr_eq(&updated, &monitor.content_normalizer().await));
+    }
 }
 
 // ============================================================================
@@ -1801,6 +4160,7 @@ mod property_tests {
             parameters: RequestParameters::default(),
             size_bytes: 0,
             timestamp: Utc::now(),
+            attachments: Vec::new(),
         })
     }
 
@@ -1854,6 +4214,8 @@ mod property_tests {
                     error_flow: NotificationSettings::default(),
                     latency_warning: NotificationSettings::default(),
                     token_warning: NotificationSettings::default(),
+                    webhook: WebhookSettings::default(),
+                    coalescing: CoalescingSettings::default(),
                 };
 
                 let monitor = FlowMonitor::with_notification_config(
@@ -1939,6 +4301,8 @@ mod property_tests {
                     error_flow: NotificationSettings::default(),
                     latency_warning: NotificationSettings::default(),
                     token_warning: NotificationSettings::default(),
+                    webhook: WebhookSettings::default(),
+                    coalescing: CoalescingSettings::default(),
                 };
 
                 let monitor = FlowMonitor::with_notification_config(
@@ -2026,6 +4390,8 @@ mod property_tests {
                     comment: comment.clone(),
                     marker: marker.clone(),
                     tags: tags.clone(),
+                    auto_tags: Vec::new(),
+                    priority: 0,
                 };
 
                 let updated = monitor.update_annotations(&flow_id, annotations.clone()).await;
@@ -2390,25 +4756,31 @@ mod property_tests {
                         desktop: true,
                         sound: false,
                         sound_file: None,
+                        webhook_url: None,
                     },
                     error_flow: NotificationSettings {
                         enabled: error_flow_enabled,
                         desktop: true,
                         sound: false,
                         sound_file: None,
+                        webhook_url: None,
                     },
                     latency_warning: NotificationSettings {
                         enabled: latency_warning_enabled,
                         desktop: false,
                         sound: false,
                         sound_file: None,
+                        webhook_url: None,
                     },
                     token_warning: NotificationSettings {
                         enabled: token_warning_enabled,
                         desktop: false,
                         sound: false,
                         sound_file: None,
+                        webhook_url: None,
                     },
+                    webhook: WebhookSettings::default(),
+                    coalescing: CoalescingSettings::default(),
                 };
 
                 // 创建阈值配置（低阈值，容易触发）
@@ -2522,12 +4894,14 @@ mod property_tests {
                         desktop: true,
                         sound: false,
                         sound_file: None,
+                        webhook_url: None,
                     },
                     error_flow: NotificationSettings {
                         enabled: true, // 即使启用也不应该触发
                         desktop: true,
                         sound: false,
                         sound_file: None,
+                        webhook_url: None,
                     },
                     ..Default::default()
                 };
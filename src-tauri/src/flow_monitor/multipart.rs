@@ -0,0 +1,555 @@
+//! multipart/form-data 请求捕获
+//!
+//! 部分 Provider 接受文件上传（音频转写、文档分析等），请求体为 multipart 格式，
+//! 无法按 JSON 解析。该模块提供一个增量解析器，按 chunk 消费请求体，
+//! 只记录每个 part 的字段名、文件名、大小和 Content-Type，
+//! 默认不保存文件原始内容，且内存占用与上传大小无关。
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::models::{LLMRequest, RequestAttachment};
+
+/// part 头部的最大字节数，超过则视为格式错误
+const MAX_PART_HEADER_BYTES: usize = 8 * 1024;
+
+// ============================================================================
+// 配置结构
+// ============================================================================
+
+/// multipart 捕获配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MultipartCaptureConfig {
+    /// 是否捕获 multipart 请求
+    #[serde(default = "default_multipart_enabled")]
+    pub enabled: bool,
+    /// 是否保存文件内容（base64 编码）
+    #[serde(default)]
+    pub save_file_content: bool,
+    /// 单个文件最多保存的字节数
+    #[serde(default = "default_max_file_content_bytes")]
+    pub max_file_content_bytes: usize,
+    /// 文本字段最多保存的字节数
+    #[serde(default = "default_max_field_value_bytes")]
+    pub max_field_value_bytes: usize,
+    /// 最多记录的 part 数量
+    #[serde(default = "default_max_attachments")]
+    pub max_attachments: usize,
+}
+
+fn default_multipart_enabled() -> bool {
+    true
+}
+
+fn default_max_file_content_bytes() -> usize {
+    64 * 1024 // 64KB
+}
+
+fn default_max_field_value_bytes() -> usize {
+    4 * 1024 // 4KB
+}
+
+fn default_max_attachments() -> usize {
+    32
+}
+
+impl Default for MultipartCaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_multipart_enabled(),
+            save_file_content: false,
+            max_file_content_bytes: default_max_file_content_bytes(),
+            max_field_value_bytes: default_max_field_value_bytes(),
+            max_attachments: default_max_attachments(),
+        }
+    }
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+/// 判断 Content-Type 是否为 multipart/form-data
+pub fn is_multipart(content_type: &str) -> bool {
+    content_type
+        .trim()
+        .to_ascii_lowercase()
+        .starts_with("multipart/form-data")
+}
+
+/// 从 Content-Type 中提取 boundary
+pub fn parse_boundary(content_type: &str) -> Option<String> {
+    if !is_multipart(content_type) {
+        return None;
+    }
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        if key.trim().eq_ignore_ascii_case("boundary") {
+            let value = value.trim().trim_matches('"');
+            if value.is_empty() {
+                None
+            } else {
+                Some(value.to_string())
+            }
+        } else {
+            None
+        }
+    })
+}
+
+/// 解析 Content-Disposition 中的参数（name、filename）
+fn parse_disposition_param(disposition: &str, param: &str) -> Option<String> {
+    disposition.split(';').skip(1).find_map(|item| {
+        let (key, value) = item.split_once('=')?;
+        if key.trim().eq_ignore_ascii_case(param) {
+            Some(value.trim().trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// 在字节序列中查找子序列
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+// ============================================================================
+// 增量解析器
+// ============================================================================
+
+/// 解析状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    /// 等待第一个 boundary
+    Preamble,
+    /// 读取 part 头部
+    Headers,
+    /// 读取 part 内容
+    Body,
+    /// 已遇到结束 boundary
+    Done,
+}
+
+/// 正在解析的 part
+struct PartInProgress {
+    attachment: RequestAttachment,
+    /// 已保存的内容（文本字段值或文件内容）
+    retained: Vec<u8>,
+    /// 是否记录该 part（超过数量上限时只计数）
+    recorded: bool,
+}
+
+/// multipart 增量捕获器
+///
+/// 通过 `feed` 逐块输入请求体，缓冲区只保留尚未确认不属于 boundary 的尾部字节，
+/// 因此内存占用只与 chunk 大小和保存上限有关，不会加载整个上传内容。
+pub struct MultipartCapture {
+    config: MultipartCaptureConfig,
+    /// 首个 boundary（`--boundary`）
+    first_delimiter: Vec<u8>,
+    /// part 之间的分隔符（`\r\n--boundary`）
+    delimiter: Vec<u8>,
+    state: ParseState,
+    buffer: Vec<u8>,
+    current: Option<PartInProgress>,
+    attachments: Vec<RequestAttachment>,
+    /// 因数量上限被省略的 part 数量
+    omitted_parts: usize,
+    /// 已处理的总字节数
+    total_bytes: u64,
+    /// 是否遇到格式错误
+    malformed: bool,
+}
+
+impl MultipartCapture {
+    /// 创建捕获器
+    pub fn new(boundary: &str, config: MultipartCaptureConfig) -> Self {
+        Self {
+            config,
+            first_delimiter: format!("--{}", boundary).into_bytes(),
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            state: ParseState::Preamble,
+            buffer: Vec::new(),
+            current: None,
+            attachments: Vec::new(),
+            omitted_parts: 0,
+            total_bytes: 0,
+            malformed: false,
+        }
+    }
+
+    /// 根据 Content-Type 创建捕获器，非 multipart 时返回 None
+    pub fn from_content_type(content_type: &str, config: MultipartCaptureConfig) -> Option<Self> {
+        parse_boundary(content_type).map(|boundary| Self::new(&boundary, config))
+    }
+
+    /// 输入一块请求体数据
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.total_bytes += chunk.len() as u64;
+        if self.state == ParseState::Done || self.malformed {
+            return;
+        }
+        self.buffer.extend_from_slice(chunk);
+        self.process();
+    }
+
+    /// 已处理的总字节数
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// 因数量上限被省略的 part 数量
+    pub fn omitted_parts(&self) -> usize {
+        self.omitted_parts
+    }
+
+    /// 是否遇到格式错误
+    pub fn is_malformed(&self) -> bool {
+        self.malformed
+    }
+
+    /// 结束解析，返回捕获到的附件列表
+    ///
+    /// 如果请求体被截断（没有结束 boundary），最后一个 part 会按已读取的内容记录。
+    pub fn finish(mut self) -> Vec<RequestAttachment> {
+        if self.state == ParseState::Body {
+            let remaining = std::mem::take(&mut self.buffer);
+            self.consume_body(&remaining);
+            self.finish_part();
+        }
+        self.attachments
+    }
+
+    fn process(&mut self) {
+        loop {
+            match self.state {
+                ParseState::Preamble => {
+                    let Some(pos) = find_bytes(&self.buffer, &self.first_delimiter) else {
+                        // 保留可能是 boundary 前缀的尾部
+                        let keep = self.first_delimiter.len().saturating_sub(1);
+                        if self.buffer.len() > keep {
+                            self.buffer.drain(..self.buffer.len() - keep);
+                        }
+                        return;
+                    };
+                    let after = pos + self.first_delimiter.len();
+                    if !self.after_boundary(after) {
+                        return;
+                    }
+                }
+                ParseState::Headers => {
+                    let Some(pos) = find_bytes(&self.buffer, b"\r\n\r\n") else {
+                        if self.buffer.len() > MAX_PART_HEADER_BYTES {
+                            self.malformed = true;
+                            self.buffer.clear();
+                        }
+                        return;
+                    };
+                    let header_bytes: Vec<u8> = self.buffer.drain(..pos + 4).collect();
+                    self.start_part(&String::from_utf8_lossy(&header_bytes[..pos]));
+                    self.state = ParseState::Body;
+                }
+                ParseState::Body => {
+                    if let Some(pos) = find_bytes(&self.buffer, &self.delimiter) {
+                        let body: Vec<u8> = self.buffer.drain(..pos).collect();
+                        self.consume_body(&body);
+                        self.finish_part();
+                        let after = self.delimiter.len();
+                        if !self.after_boundary(after) {
+                            return;
+                        }
+                    } else {
+                        // 尾部可能包含不完整的分隔符，暂不消费
+                        let keep = self.delimiter.len().saturating_sub(1);
+                        if self.buffer.len() > keep {
+                            let body: Vec<u8> =
+                                self.buffer.drain(..self.buffer.len() - keep).collect();
+                            self.consume_body(&body);
+                        }
+                        return;
+                    }
+                }
+                ParseState::Done => {
+                    self.buffer.clear();
+                    return;
+                }
+            }
+        }
+    }
+
+    /// 处理 boundary 之后的内容（`--` 表示结束，`\r\n` 表示新 part）
+    ///
+    /// `offset` 为 boundary 结束位置。数据不足时返回 false 等待更多输入。
+    fn after_boundary(&mut self, offset: usize) -> bool {
+        if self.buffer.len() < offset + 2 {
+            return false;
+        }
+        let marker = [self.buffer[offset], self.buffer[offset + 1]];
+        self.buffer.drain(..offset + 2);
+        if &marker == b"--" {
+            self.state = ParseState::Done;
+            self.buffer.clear();
+        } else {
+            self.state = ParseState::Headers;
+        }
+        true
+    }
+
+    fn start_part(&mut self, raw_headers: &str) {
+        let mut attachment = RequestAttachment::default();
+        for line in raw_headers.split("\r\n") {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            if name.trim().eq_ignore_ascii_case("content-disposition") {
+                attachment.field_name = parse_disposition_param(value, "name").unwrap_or_default();
+                attachment.file_name = parse_disposition_param(value, "filename");
+            } else if name.trim().eq_ignore_ascii_case("content-type") {
+                attachment.content_type = Some(value.to_string());
+            }
+        }
+
+        let recorded = self.attachments.len() < self.config.max_attachments;
+        if !recorded {
+            self.omitted_parts += 1;
+        }
+        self.current = Some(PartInProgress {
+            attachment,
+            retained: Vec::new(),
+            recorded,
+        });
+    }
+
+    fn consume_body(&mut self, bytes: &[u8]) {
+        let Some(part) = self.current.as_mut() else {
+            return;
+        };
+        part.attachment.size_bytes += bytes.len() as u64;
+        if !part.recorded {
+            return;
+        }
+
+        let is_file = part.attachment.file_name.is_some();
+        let limit = if is_file {
+            if !self.config.save_file_content {
+                return;
+            }
+            self.config.max_file_content_bytes
+        } else {
+            self.config.max_field_value_bytes
+        };
+
+        let room = limit.saturating_sub(part.retained.len());
+        if bytes.len() > room {
+            part.attachment.truncated = true;
+        }
+        part.retained
+            .extend_from_slice(&bytes[..bytes.len().min(room)]);
+    }
+
+    fn finish_part(&mut self) {
+        let Some(mut part) = self.current.take() else {
+            return;
+        };
+        if !part.recorded {
+            return;
+        }
+        if part.attachment.file_name.is_some() {
+            if self.config.save_file_content {
+                part.attachment.data =
+                    Some(base64::engine::general_purpose::STANDARD.encode(&part.retained));
+            }
+        } else {
+            part.attachment.value = Some(String::from_utf8_lossy(&part.retained).into_owned());
+        }
+        self.attachments.push(part.attachment);
+    }
+}
+
+/// 一次性捕获完整的 multipart 请求体
+///
+/// 非 multipart 请求返回 None。
+pub fn capture_multipart(
+    content_type: &str,
+    body: &[u8],
+    config: &MultipartCaptureConfig,
+) -> Option<Vec<RequestAttachment>> {
+    let mut capture = MultipartCapture::from_content_type(content_type, config.clone())?;
+    capture.feed(body);
+    Some(capture.finish())
+}
+
+/// 根据捕获到的附件构建 LLMRequest
+pub fn build_multipart_request(
+    method: &str,
+    path: &str,
+    headers: HashMap<String, String>,
+    attachments: Vec<RequestAttachment>,
+    size_bytes: usize,
+) -> LLMRequest {
+    let mut request = LLMRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers,
+        size_bytes,
+        ..Default::default()
+    };
+    apply_multipart_attachments(&mut request, attachments);
+    request
+}
+
+/// 把捕获到的附件写入请求
+///
+/// 文本字段中的 `model`、`stream` 会被提取到对应字段，其余文本字段放入参数的 `extra`。
+pub fn apply_multipart_attachments(request: &mut LLMRequest, attachments: Vec<RequestAttachment>) {
+    for attachment in &attachments {
+        let Some(ref value) = attachment.value else {
+            continue;
+        };
+        match attachment.field_name.as_str() {
+            "model" => request.model = value.clone(),
+            "stream" => request.parameters.stream = value.eq_ignore_ascii_case("true"),
+            name if !name.is_empty() => {
+                request
+                    .parameters
+                    .extra
+                    .insert(name.to_string(), serde_json::Value::String(value.clone()));
+            }
+            _ => {}
+        }
+    }
+
+    request.attachments = attachments;
+}
+
+// ============================================================================
+// 测试模块
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT_TYPE: &str = "multipart/form-data; boundary=----XyZ";
+
+    fn sample_body(file_len: usize) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(b"------XyZ\r\n");
+        body.extend_from_slice(b"Content-Disposition: form-data; name=\"model\"\r\n\r\n");
+        body.extend_from_slice(b"whisper-1\r\n");
+        body.extend_from_slice(b"------XyZ\r\n");
+        body.extend_from_slice(
+            b"Content-Disposition: form-data; name=\"file\"; filename=\"audio.mp3\"\r\n",
+        );
+        body.extend_from_slice(b"Content-Type: audio/mpeg\r\n\r\n");
+        body.extend(std::iter::repeat(b'a').take(file_len));
+        body.extend_from_slice(b"\r\n------XyZ--\r\n");
+        body
+    }
+
+    #[test]
+    fn test_parse_boundary() {
+        assert_eq!(parse_boundary(CONTENT_TYPE), Some("----XyZ".to_string()));
+        assert_eq!(
+            parse_boundary("multipart/form-data; charset=utf-8; boundary=\"abc\""),
+            Some("abc".to_string())
+        );
+        assert_eq!(parse_boundary("application/json"), None);
+        assert!(is_multipart("Multipart/Form-Data; boundary=x"));
+    }
+
+    #[test]
+    fn test_capture_fields_and_files() {
+        let body = sample_body(1000);
+        let attachments =
+            capture_multipart(CONTENT_TYPE, &body, &MultipartCaptureConfig::default()).unwrap();
+
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(attachments[0].field_name, "model");
+        assert_eq!(attachments[0].value.as_deref(), Some("whisper-1"));
+        assert_eq!(attachments[1].field_name, "file");
+        assert_eq!(attachments[1].file_name.as_deref(), Some("audio.mp3"));
+        assert_eq!(attachments[1].content_type.as_deref(), Some("audio/mpeg"));
+        assert_eq!(attachments[1].size_bytes, 1000);
+        // 默认不保存文件内容
+        assert!(attachments[1].data.is_none());
+    }
+
+    #[test]
+    fn test_chunked_feed_matches_single_feed() {
+        let body = sample_body(5000);
+        let config = MultipartCaptureConfig {
+            save_file_content: true,
+            max_file_content_bytes: 100,
+            ..Default::default()
+        };
+
+        for chunk_size in [1, 3, 7, 64, 4096] {
+            let mut capture =
+                MultipartCapture::from_content_type(CONTENT_TYPE, config.clone()).unwrap();
+            for chunk in body.chunks(chunk_size) {
+                capture.feed(chunk);
+            }
+            assert_eq!(capture.total_bytes(), body.len() as u64);
+            let attachments = capture.finish();
+            assert_eq!(attachments.len(), 2, "chunk_size={}", chunk_size);
+            assert_eq!(attachments[1].size_bytes, 5000, "chunk_size={}", chunk_size);
+            assert!(attachments[1].truncated);
+            let data = base64::engine::general_purpose::STANDARD
+                .decode(attachments[1].data.as_ref().unwrap())
+                .unwrap();
+            assert_eq!(data.len(), 100);
+        }
+    }
+
+    #[test]
+    fn test_max_attachments_cap() {
+        let mut body = Vec::new();
+        for i in 0..5 {
+            body.extend_from_slice(b"------XyZ\r\n");
+            body.extend_from_slice(
+                format!(
+                    "Content-Disposition: form-data; name=\"f{}\"\r\n\r\nv\r\n",
+                    i
+                )
+                .as_bytes(),
+            );
+        }
+        body.extend_from_slice(b"------XyZ--\r\n");
+
+        let config = MultipartCaptureConfig {
+            max_attachments: 2,
+            ..Default::default()
+        };
+        let mut capture = MultipartCapture::from_content_type(CONTENT_TYPE, config).unwrap();
+        capture.feed(&body);
+        assert_eq!(capture.omitted_parts(), 3);
+        assert_eq!(capture.finish().len(), 2);
+    }
+
+    #[test]
+    fn test_build_multipart_request() {
+        let attachments = capture_multipart(
+            CONTENT_TYPE,
+            &sample_body(10),
+            &MultipartCaptureConfig::default(),
+        )
+        .unwrap();
+        let request = build_multipart_request(
+            "POST",
+            "/v1/audio/transcriptions",
+            HashMap::new(),
+            attachments,
+            100,
+        );
+        assert_eq!(request.model, "whisper-1");
+        assert_eq!(request.attachments.len(), 2);
+        assert_eq!(request.size_bytes, 100);
+    }
+}
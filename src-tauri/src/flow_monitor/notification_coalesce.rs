@@ -0,0 +1,283 @@
+//! 错误通知合并
+//!
+//! Provider 故障时每个失败请求都会触发一次错误通知，桌面通知和 Webhook 会被刷屏。
+//! 同一 Provider 的同类错误在时间窗口内超过阈值后不再逐个通知，窗口结束时发送一条
+//! 带计数的汇总通知（如"最近 60 秒内 47 次认证错误"），并附带一个代表性的 Flow ID。
+//!
+//! 窗口从该组错误的第一次出现开始计时，窗口内前 `threshold` 次错误照常单独通知。
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::models::FlowErrorType;
+
+/// 错误通知合并设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoalescingSettings {
+    /// 是否启用合并
+    #[serde(default = "default_coalescing_enabled")]
+    pub enabled: bool,
+    /// 合并窗口（秒）
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    /// 窗口内单独通知的次数，超出部分合并为汇总通知
+    #[serde(default = "default_threshold")]
+    pub threshold: u32,
+}
+
+fn default_coalescing_enabled() -> bool {
+    true
+}
+
+fn default_window_secs() -> u64 {
+    60
+}
+
+fn default_threshold() -> u32 {
+    3
+}
+
+impl Default for CoalescingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_coalescing_enabled(),
+            window_secs: default_window_secs(),
+            threshold: default_threshold(),
+        }
+    }
+}
+
+impl CoalescingSettings {
+    /// 合并窗口长度（至少 1 秒）
+    pub fn window(&self) -> Duration {
+        Duration::seconds(self.window_secs.max(1) as i64)
+    }
+}
+
+/// 合并结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoalesceDecision {
+    /// 照常单独通知
+    Notify,
+    /// 暂不通知，计入窗口结束时的汇总
+    Suppress {
+        /// 是否为本窗口第一次被合并的错误（调用方需在窗口结束时取出汇总）
+        first: bool,
+    },
+}
+
+/// 一个窗口内被合并的错误汇总
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoalescedErrors {
+    /// Provider
+    pub provider: String,
+    /// 错误类型
+    pub error_type: FlowErrorType,
+    /// 窗口内的错误总数（含单独通知的）
+    pub count: u32,
+    /// 未单独通知的错误数
+    pub suppressed: u32,
+    /// 代表性的 Flow ID（第一个被合并的错误）
+    pub flow_id: String,
+    /// 代表性 Flow 的模型
+    pub model: String,
+    /// 窗口长度（秒）
+    pub window_secs: u64,
+}
+
+#[derive(Debug)]
+struct ErrorGroup {
+    window_start: DateTime<Utc>,
+    count: u32,
+    suppressed: u32,
+    representative: Option<(String, String)>,
+}
+
+/// 错误通知合并器
+#[derive(Debug, Default)]
+pub struct ErrorCoalescer {
+    groups: HashMap<(String, FlowErrorType), ErrorGroup>,
+}
+
+impl ErrorCoalescer {
+    /// 创建合并器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次错误，返回是否需要单独通知
+    ///
+    /// 调用前应先调用 [`take_due`](Self::take_due) 取出已结束的窗口，否则过期的分组会被视为仍在窗口内。
+    pub fn record(
+        &mut self,
+        settings: &CoalescingSettings,
+        provider: &str,
+        error_type: FlowErrorType,
+        flow_id: &str,
+        model: &str,
+        now: DateTime<Utc>,
+    ) -> CoalesceDecision {
+        if !settings.enabled {
+            return CoalesceDecision::Notify;
+        }
+
+        let group = self
+            .groups
+            .entry((provider.to_string(), error_type))
+            .or_insert_with(|| ErrorGroup {
+                window_start: now,
+                count: 0,
+                suppressed: 0,
+                representative: None,
+            });
+        group.count += 1;
+        if group.count <= settings.threshold.max(1) {
+            return CoalesceDecision::Notify;
+        }
+
+        group.suppressed += 1;
+        if group.representative.is_none() {
+            group.representative = Some((flow_id.to_string(), model.to_string()));
+        }
+        CoalesceDecision::Suppress {
+            first: group.suppressed == 1,
+        }
+    }
+
+    /// 取出窗口已结束的分组，返回其中有被合并错误的汇总
+    pub fn take_due(
+        &mut self,
+        settings: &CoalescingSettings,
+        now: DateTime<Utc>,
+    ) -> Vec<CoalescedErrors> {
+        let window = settings.window();
+        let due: Vec<(String, FlowErrorType)> = self
+            .groups
+            .iter()
+            .filter(|(_, group)| now >= group.window_start + window)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        due.into_iter()
+            .filter_map(|key| {
+                let group = self.groups.remove(&key)?;
+                let (flow_id, model) = group.representative?;
+                Some(CoalescedErrors {
+                    provider: key.0,
+                    error_type: key.1,
+                    count: group.count,
+                    suppressed: group.suppressed,
+                    flow_id,
+                    model,
+                    window_secs: settings.window_secs.max(1),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(threshold: u32) -> CoalescingSettings {
+        CoalescingSettings {
+            enabled: true,
+            window_secs: 60,
+            threshold,
+        }
+    }
+
+    #[test]
+    fn test_errors_over_threshold_are_coalesced() {
+        let settings = settings(2);
+        let mut coalescer = ErrorCoalescer::new();
+        let now = Utc::now();
+
+        let decisions: Vec<_> = (0..5)
+            .map(|i| {
+                coalescer.record(
+                    &settings,
+                    "Claude",
+                    FlowErrorType::Authentication,
+                    &format!("flow-{}", i),
+                    "claude-3",
+                    now + Duration::seconds(i),
+                )
+            })
+            .collect();
+        assert_eq!(
+            decisions,
+            vec![
+                CoalesceDecision::Notify,
+                CoalesceDecision::Notify,
+                CoalesceDecision::Suppress { first: true },
+                CoalesceDecision::Suppress { first: false },
+                CoalesceDecision::Suppress { first: false },
+            ]
+        );
+
+        // 其他 Provider 或错误类型独立计数
+        assert_eq!(
+            coalescer.record(
+                &settings,
+                "Claude",
+                FlowErrorType::RateLimit,
+                "flow-x",
+                "claude-3",
+                now
+            ),
+            CoalesceDecision::Notify
+        );
+
+        assert!(coalescer
+            .take_due(&settings, now + Duration::seconds(30))
+            .is_empty());
+        let due = coalescer.take_due(&settings, now + Duration::seconds(60));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].count, 5);
+        assert_eq!(due[0].suppressed, 3);
+        assert_eq!(due[0].flow_id, "flow-2");
+        assert_eq!(due[0].error_type, FlowErrorType::Authentication);
+
+        // 窗口结束后重新计数
+        assert_eq!(
+            coalescer.record(
+                &settings,
+                "Claude",
+                FlowErrorType::Authentication,
+                "flow-6",
+                "claude-3",
+                now + Duration::seconds(61)
+            ),
+            CoalesceDecision::Notify
+        );
+    }
+
+    #[test]
+    fn test_disabled_always_notifies() {
+        let settings = CoalescingSettings {
+            enabled: false,
+            ..settings(1)
+        };
+        let mut coalescer = ErrorCoalescer::new();
+        let now = Utc::now();
+        for i in 0..3 {
+            assert_eq!(
+                coalescer.record(
+                    &settings,
+                    "OpenAI",
+                    FlowErrorType::ServerError,
+                    &format!("flow-{}", i),
+                    "gpt-4",
+                    now
+                ),
+                CoalesceDecision::Notify
+            );
+        }
+        assert!(coalescer
+            .take_due(&settings, now + Duration::seconds(120))
+            .is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+//! Parquet 导出
+//!
+//! 把 Flow 写成单个行组的 Parquet 文件，供数据仓库按列导入。Schema 保持稳定，
+//! 新增列只追加在末尾：
+//!
+//! - `flow_id`、`model`、`provider`、`state`：字符串
+//! - `created_at`：UTC 毫秒时间戳
+//! - `duration_ms`、`input_tokens`、`output_tokens`、`total_tokens`：整数（未完成的 Flow 为 0）
+//! - `has_error`：布尔值
+//! - `messages`：请求消息列表序列化后的 JSON 字符串
+
+use std::sync::Arc;
+
+use parquet::basic::Compression;
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DataType, Int64Type};
+use parquet::errors::{ParquetError, Result};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedFileWriter, SerializedRowGroupWriter};
+use parquet::schema::parser::parse_message_type;
+
+use super::models::LLMFlow;
+
+/// 导出文件的列名（按列顺序）
+pub const PARQUET_COLUMNS: [&str; 11] = [
+    "flow_id",
+    "model",
+    "provider",
+    "state",
+    "created_at",
+    "duration_ms",
+    "input_tokens",
+    "output_tokens",
+    "total_tokens",
+    "has_error",
+    "messages",
+];
+
+const PARQUET_SCHEMA: &str = "
+message llm_flow {
+    REQUIRED BYTE_ARRAY flow_id (UTF8);
+    REQUIRED BYTE_ARRAY model (UTF8);
+    REQUIRED BYTE_ARRAY provider (UTF8);
+    REQUIRED BYTE_ARRAY state (UTF8);
+    REQUIRED INT64 created_at (TIMESTAMP(MILLIS,true));
+    REQUIRED INT64 duration_ms;
+    REQUIRED INT64 input_tokens;
+    REQUIRED INT64 output_tokens;
+    REQUIRED INT64 total_tokens;
+    REQUIRED BOOLEAN has_error;
+    REQUIRED BYTE_ARRAY messages (JSON);
+}
+";
+
+/// 把 Flow 写成 Parquet 文件
+pub fn write_parquet(flows: &[LLMFlow]) -> Result<Vec<u8>> {
+    let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+    let properties = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::UNCOMPRESSED)
+            .build(),
+    );
+    let mut writer = SerializedFileWriter::new(Vec::new(), schema, properties)?;
+
+    let usage = |f: fn(&LLMFlow) -> Option<u32>| -> Vec<i64> {
+        flows
+            .iter()
+            .map(|flow| f(flow).unwrap_or(0) as i64)
+            .collect()
+    };
+    let strings = |f: fn(&LLMFlow) -> String| -> Vec<ByteArray> {
+        flows
+            .iter()
+            .map(|flow| ByteArray::from(f(flow).into_bytes()))
+            .collect()
+    };
+
+    let mut row_group = writer.next_row_group()?;
+    write_column::<ByteArrayType>(&mut row_group, &strings(|f| f.id.clone()))?;
+    write_column::<ByteArrayType>(&mut row_group, &strings(|f| f.request.model.clone()))?;
+    write_column::<ByteArrayType>(
+        &mut row_group,
+        &strings(|f| f.metadata.provider.to_string()),
+    )?;
+    write_column::<ByteArrayType>(&mut row_group, &strings(|f| format!("{:?}", f.state)))?;
+    write_column::<Int64Type>(
+        &mut row_group,
+        &flows
+            .iter()
+            .map(|f| f.timestamps.created.timestamp_millis())
+            .collect::<Vec<_>>(),
+    )?;
+    write_column::<Int64Type>(
+        &mut row_group,
+        &flows
+            .iter()
+            .map(|f| f.timestamps.duration_ms as i64)
+            .collect::<Vec<_>>(),
+    )?;
+    write_column::<Int64Type>(
+        &mut row_group,
+        &usage(|f| f.response.as_ref().map(|r| r.usage.input_tokens)),
+    )?;
+    write_column::<Int64Type>(
+        &mut row_group,
+        &usage(|f| f.response.as_ref().map(|r| r.usage.output_tokens)),
+    )?;
+    write_column::<Int64Type>(
+        &mut row_group,
+        &usage(|f| f.response.as_ref().map(|r| r.usage.total_tokens)),
+    )?;
+    write_column::<BoolType>(
+        &mut row_group,
+        &flows.iter().map(|f| f.error.is_some()).collect::<Vec<_>>(),
+    )?;
+    write_column::<ByteArrayType>(
+        &mut row_group,
+        &strings(|f| serde_json::to_string(&f.request.messages).unwrap_or_default()),
+    )?;
+    row_group.close()?;
+
+    writer.into_inner()
+}
+
+/// 写入行组的下一列
+fn write_column<T: DataType>(
+    row_group: &mut SerializedRowGroupWriter<'_, Vec<u8>>,
+    values: &[T::T],
+) -> Result<()> {
+    let mut column = row_group
+        .next_column()?
+        .ok_or_else(|| ParquetError::General("写入的列数超出 schema".to_string()))?;
+    column.typed::<T>().write_batch(values, None, None)?;
+    column.close()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::models::{FlowMetadata, FlowType, LLMRequest};
+    use bytes::Bytes;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    #[test]
+    fn test_schema_round_trip() {
+        let flows: Vec<LLMFlow> = (0..3)
+            .map(|i| {
+                let request = LLMRequest {
+                    model: "gpt-4".to_string(),
+                    ..Default::default()
+                };
+                LLMFlow::new(
+                    format!("flow-{}", i),
+                    FlowType::ChatCompletions,
+                    request,
+                    FlowMetadata::default(),
+                )
+            })
+            .collect();
+
+        let data = write_parquet(&flows).unwrap();
+        let reader = SerializedFileReader::new(Bytes::from(data)).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 3);
+
+        let columns: Vec<&str> = metadata
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|column| column.name())
+            .collect();
+        assert_eq!(columns, PARQUET_COLUMNS);
+    }
+}
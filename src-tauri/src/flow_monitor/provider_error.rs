@@ -0,0 +1,287 @@
+//! Provider 错误响应体解析
+//!
+//! 将各 Provider 的错误 JSON 解析为结构化字段（错误类型、错误码、参数、重试等待），
+//! 并按错误内容补充基于状态码的错误分类（如 400 + `rate_limit` → `RateLimit`）。
+//!
+//! 支持的格式：
+//! - OpenAI: `{"error": {"message", "type", "param", "code"}}`
+//! - Anthropic: `{"type": "error", "error": {"type", "message"}}`
+//! - Gemini: `{"error": {"code", "message", "status", "details": [...]}}`
+//! - AWS（Kiro）: `{"__type", "message"}`
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde_json::Value;
+
+use super::models::FlowErrorType;
+
+/// 解析后的 Provider 错误
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProviderErrorInfo {
+    /// 错误类型（如 `invalid_request_error`、`RESOURCE_EXHAUSTED`）
+    pub error_type: Option<String>,
+    /// 错误码（如 `context_length_exceeded`）
+    pub code: Option<String>,
+    /// 出错的参数
+    pub param: Option<String>,
+    /// 错误消息
+    pub message: Option<String>,
+    /// 建议的重试等待（秒）
+    pub retry_after: Option<u64>,
+}
+
+impl ProviderErrorInfo {
+    /// 解析错误响应体，不是可识别的错误 JSON 时返回 `None`
+    pub fn parse(body: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(body.trim()).ok()?;
+        let info = match value.get("error") {
+            // OpenAI / Anthropic / Gemini
+            Some(Value::Object(error)) => {
+                let error_type =
+                    string_field(error.get("type")).or_else(|| string_field(error.get("status")));
+                let code = match error.get("code") {
+                    // Gemini 的 code 是 HTTP 状态码，错误原因在 details 中
+                    Some(Value::Number(_)) => gemini_reason(error.get("details")),
+                    other => string_field(other),
+                };
+                Self {
+                    error_type,
+                    code,
+                    param: string_field(error.get("param")),
+                    message: string_field(error.get("message")),
+                    retry_after: gemini_retry_delay(error.get("details")),
+                }
+            }
+            Some(Value::String(message)) => Self {
+                error_type: string_field(value.get("type")),
+                message: Some(message.clone()),
+                ..Default::default()
+            },
+            // AWS
+            _ => Self {
+                error_type: string_field(value.get("__type"))
+                    .map(|t| t.rsplit('#').next().unwrap_or(&t).to_string()),
+                code: string_field(value.get("reason")),
+                message: string_field(value.get("message"))
+                    .or_else(|| string_field(value.get("Message"))),
+                ..Default::default()
+            },
+        };
+
+        if info.error_type.is_none() && info.code.is_none() && info.message.is_none() {
+            return None;
+        }
+        // 代理转发时上游错误体会被包装为 {"error": {"message": "<上游错误体>"}}
+        if let Some(inner) = info
+            .message
+            .as_deref()
+            .filter(|m| m.trim_start().starts_with('{'))
+            .and_then(Self::parse)
+        {
+            return Some(inner);
+        }
+        let retry_after = info
+            .retry_after
+            .or_else(|| info.message.as_deref().and_then(retry_after_from_message));
+        Some(Self {
+            retry_after,
+            ..info
+        })
+    }
+
+    /// 按错误类型、错误码和消息分类，无法判断时返回 `None`
+    pub fn classify(&self) -> Option<FlowErrorType> {
+        let keys = [self.error_type.as_deref(), self.code.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(str::to_ascii_lowercase)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let message = self
+            .message
+            .as_deref()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let any_key = |patterns: &[&str]| patterns.iter().any(|p| keys.contains(p));
+        let any_message = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+
+        if any_key(&["context_length", "too_many_tokens", "token_limit"])
+            || any_message(&[
+                "maximum context length",
+                "prompt is too long",
+                "context window",
+            ])
+        {
+            Some(FlowErrorType::TokenLimitExceeded)
+        } else if any_key(&[
+            "rate_limit",
+            "insufficient_quota",
+            "resource_exhausted",
+            "throttling",
+        ]) || any_message(&["rate limit"])
+        {
+            Some(FlowErrorType::RateLimit)
+        } else if any_key(&["content_filter", "content_policy", "safety"]) {
+            Some(FlowErrorType::ContentFilter)
+        } else if any_key(&[
+            "authentication",
+            "invalid_api_key",
+            "permission",
+            "unauthenticated",
+            "accessdenied",
+        ]) {
+            Some(FlowErrorType::Authentication)
+        } else if any_key(&["model_not_found", "not_found"]) {
+            Some(FlowErrorType::ModelUnavailable)
+        } else if any_key(&["overloaded"]) {
+            Some(FlowErrorType::Overloaded)
+        } else if any_key(&["server_error", "api_error", "unavailable", "internal"]) {
+            Some(FlowErrorType::ServerError)
+        } else if any_key(&["invalid_request", "invalid_argument", "validation"]) {
+            Some(FlowErrorType::BadRequest)
+        } else {
+            None
+        }
+    }
+}
+
+/// 解析 `Retry-After` 响应头（秒数或 HTTP 日期）
+pub fn parse_retry_after(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        return if secs >= 0.0 {
+            Some(secs.ceil() as u64)
+        } else {
+            None
+        };
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&Utc) - Utc::now()).num_seconds().max(0) as u64)
+}
+
+fn string_field(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Gemini `ErrorInfo.reason`
+fn gemini_reason(details: Option<&Value>) -> Option<String> {
+    details?
+        .as_array()?
+        .iter()
+        .find_map(|d| string_field(d.get("reason")))
+}
+
+/// Gemini `RetryInfo.retryDelay`（如 `"30s"`）
+fn gemini_retry_delay(details: Option<&Value>) -> Option<u64> {
+    details?.as_array()?.iter().find_map(|d| {
+        let delay = d.get("retryDelay")?.as_str()?;
+        parse_retry_after(delay.trim_end_matches('s'))
+    })
+}
+
+/// 从消息中提取重试等待（如 OpenAI 的 "Please try again in 20s" / "in 250ms"）
+fn retry_after_from_message(message: &str) -> Option<u64> {
+    let re = Regex::new(r"(?i)try again in (\d+(?:\.\d+)?)\s*(ms|s)\b").ok()?;
+    let caps = re.captures(message)?;
+    let amount: f64 = caps[1].parse().ok()?;
+    let secs = if caps[2].eq_ignore_ascii_case("ms") {
+        amount / 1000.0
+    } else {
+        amount
+    };
+    Some(secs.ceil() as u64)
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_openai_context_length() {
+        let body = r#"{
+            "error": {
+                "message": "This model's maximum context length is 8192 tokens. However, your messages resulted in 9000 tokens. Please reduce the length of the messages.",
+                "type": "invalid_request_error",
+                "param": "messages",
+                "code": "context_length_exceeded"
+            }
+        }"#;
+        let info = ProviderErrorInfo::parse(body).unwrap();
+        assert_eq!(info.error_type.as_deref(), Some("invalid_request_error"));
+        assert_eq!(info.code.as_deref(), Some("context_length_exceeded"));
+        assert_eq!(info.param.as_deref(), Some("messages"));
+        assert_eq!(info.retry_after, None);
+        assert_eq!(info.classify(), Some(FlowErrorType::TokenLimitExceeded));
+    }
+
+    #[test]
+    fn test_parse_openai_rate_limit() {
+        let body = r#"{"error":{"message":"Rate limit reached for gpt-4o in organization org-abc on tokens per min (TPM): Limit 30000, Used 29800, Requested 900. Please try again in 1.4s.","type":"tokens","param":null,"code":"rate_limit_exceeded"}}"#;
+        let info = ProviderErrorInfo::parse(body).unwrap();
+        assert_eq!(info.code.as_deref(), Some("rate_limit_exceeded"));
+        assert_eq!(info.param, None);
+        assert_eq!(info.retry_after, Some(2));
+        assert_eq!(info.classify(), Some(FlowErrorType::RateLimit));
+    }
+
+    #[test]
+    fn test_parse_anthropic_errors() {
+        let body = r#"{"type":"error","error":{"type":"rate_limit_error","message":"Number of request tokens has exceeded your per-minute rate limit"}}"#;
+        let info = ProviderErrorInfo::parse(body).unwrap();
+        assert_eq!(info.error_type.as_deref(), Some("rate_limit_error"));
+        assert_eq!(info.code, None);
+        assert_eq!(info.classify(), Some(FlowErrorType::RateLimit));
+
+        let body = r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        let info = ProviderErrorInfo::parse(body).unwrap();
+        assert_eq!(info.classify(), Some(FlowErrorType::Overloaded));
+        assert_eq!(
+            FlowErrorType::from_status_and_body(529, body),
+            FlowErrorType::Overloaded
+        );
+
+        let body = r#"{"type":"error","error":{"type":"invalid_request_error","message":"prompt is too long: 210000 tokens > 200000 maximum"}}"#;
+        let info = ProviderErrorInfo::parse(body).unwrap();
+        assert_eq!(info.classify(), Some(FlowErrorType::TokenLimitExceeded));
+    }
+
+    #[test]
+    fn test_parse_gemini_and_aws() {
+        let body = r#"{"error":{"code":429,"message":"Resource has been exhausted","status":"RESOURCE_EXHAUSTED","details":[{"@type":"type.googleapis.com/google.rpc.RetryInfo","retryDelay":"31s"}]}}"#;
+        let info = ProviderErrorInfo::parse(body).unwrap();
+        assert_eq!(info.error_type.as_deref(), Some("RESOURCE_EXHAUSTED"));
+        assert_eq!(info.retry_after, Some(31));
+        assert_eq!(info.classify(), Some(FlowErrorType::RateLimit));
+
+        let body = r#"{"__type":"com.amazon.coral.service#ThrottlingException","message":"Too many requests"}"#;
+        let info = ProviderErrorInfo::parse(body).unwrap();
+        assert_eq!(info.error_type.as_deref(), Some("ThrottlingException"));
+        assert_eq!(info.classify(), Some(FlowErrorType::RateLimit));
+
+        // 被代理包装过的上游错误体
+        let wrapped = serde_json::json!({"error": {"message": body}}).to_string();
+        assert_eq!(
+            ProviderErrorInfo::parse(&wrapped),
+            ProviderErrorInfo::parse(body)
+        );
+
+        assert!(ProviderErrorInfo::parse("Bad Gateway").is_none());
+        assert!(ProviderErrorInfo::parse(r#"{"ok":true}"#).is_none());
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("20"), Some(20));
+        assert_eq!(parse_retry_after("0.5"), Some(1));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(0));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
@@ -6,14 +6,16 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Instant;
 use thiserror::Error;
 use tokio::sync::RwLock;
 
 use super::file_store::{FileStoreError, FlowFileStore};
 use super::filter_parser::{FilterParseError, FilterParser};
 use super::memory_store::{FlowFilter, FlowMemoryStore};
-use super::models::{FlowState, LLMFlow};
+use super::models::{ContentFilterAction, FlowState, LLMFlow, Message, TokenUsage};
 
 // ============================================================================
 // 错误类型
@@ -48,6 +50,8 @@ pub enum FlowSortBy {
     ContentLength,
     /// 按模型名称排序
     Model,
+    /// 按相关度排序（综合时间衰减、优先级、收藏和错误状态）
+    Relevance,
 }
 
 impl Default for FlowSortBy {
@@ -56,6 +60,123 @@ impl Default for FlowSortBy {
     }
 }
 
+/// 相关度排序的评分权重
+///
+/// 相关度分数 = `recency * 0.5^(距今秒数 / recency_half_life_secs)`
+/// + `priority * 优先级` + `starred * 是否收藏` + `error * 是否出错`。
+/// 时间项随时间衰减，收藏、高优先级和出错的 Flow 即使较旧也能排在前面。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelevanceWeights {
+    /// 时间项的权重（刚创建的 Flow 得满分）
+    pub recency: f64,
+    /// 时间项的半衰期（秒）
+    pub recency_half_life_secs: f64,
+    /// 每级优先级的权重
+    pub priority: f64,
+    /// 收藏的权重
+    pub starred: f64,
+    /// 出错的权重
+    pub error: f64,
+}
+
+impl Default for RelevanceWeights {
+    fn default() -> Self {
+        Self {
+            recency: 1.0,
+            recency_half_life_secs: 3600.0,
+            priority: 1.0,
+            starred: 2.0,
+            error: 0.5,
+        }
+    }
+}
+
+impl RelevanceWeights {
+    /// 计算 Flow 的相关度分数
+    pub fn score(&self, flow: &LLMFlow, now: DateTime<Utc>) -> f64 {
+        let age_secs = (now - flow.timestamps.created).num_milliseconds().max(0) as f64 / 1000.0;
+        let recency = if self.recency_half_life_secs > 0.0 {
+            0.5f64.powf(age_secs / self.recency_half_life_secs)
+        } else {
+            0.0
+        };
+        let starred = if flow.annotations.starred { 1.0 } else { 0.0 };
+        let has_error = flow.error.is_some() || flow.state == FlowState::Failed;
+        let error = if has_error { 1.0 } else { 0.0 };
+
+        self.recency * recency
+            + self.priority * flow.annotations.priority as f64
+            + self.starred * starred
+            + self.error * error
+    }
+}
+
+// ============================================================================
+// 成本估算
+// ============================================================================
+
+/// 单个模型的 Token 单价（美元 / 千 Token）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenPricing {
+    /// 输入单价
+    pub input_per_1k: f64,
+    /// 输出单价
+    pub output_per_1k: f64,
+    /// 缓存读取单价（为空时按输入单价计）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_per_1k: Option<f64>,
+    /// 缓存写入单价（为空时按输入单价计）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_per_1k: Option<f64>,
+}
+
+impl TokenPricing {
+    /// 按用量计算成本（美元）
+    ///
+    /// `input_tokens` 包含缓存读写的 Token，扣除后按输入单价计。
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        let cache_read = usage.cache_read_tokens.unwrap_or(0) as f64;
+        let cache_write = usage.cache_write_tokens.unwrap_or(0) as f64;
+        let uncached = (usage.input_tokens as f64 - cache_read - cache_write).max(0.0);
+        (uncached * self.input_per_1k
+            + cache_read * self.cache_read_per_1k.unwrap_or(self.input_per_1k)
+            + cache_write * self.cache_write_per_1k.unwrap_or(self.input_per_1k)
+            + usage.output_tokens as f64 * self.output_per_1k)
+            / 1000.0
+    }
+}
+
+/// 按模型名通配符（如 `claude-*`）的单价表
+///
+/// 精确匹配优先，其次为最长的匹配模式；未匹配的模型成本为 0。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ModelPricing(pub HashMap<String, TokenPricing>);
+
+impl ModelPricing {
+    /// 查找模型的单价
+    pub fn get(&self, model: &str) -> Option<&TokenPricing> {
+        self.0.get(model).or_else(|| {
+            self.0
+                .iter()
+                .filter(|(pattern, _)| {
+                    pattern.contains('*') && FlowFilter::match_pattern(pattern, model)
+                })
+                .max_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then_with(|| b.cmp(a)))
+                .map(|(_, pricing)| pricing)
+        })
+    }
+
+    /// 估算单个 Flow 的成本（美元）
+    pub fn flow_cost(&self, flow: &LLMFlow) -> f64 {
+        match (&flow.response, self.get(&flow.request.model)) {
+            (Some(response), Some(pricing)) => pricing.cost(&response.usage),
+            _ => 0.0,
+        }
+    }
+}
+
 // ============================================================================
 // 查询结果
 // ============================================================================
@@ -94,6 +215,21 @@ impl FlowQueryResult {
     }
 }
 
+/// 过滤表达式试运行结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterTestResult {
+    /// 最近的匹配 Flow（按创建时间降序，最多 `limit` 个）
+    pub flows: Vec<LLMFlow>,
+    /// 匹配总数
+    pub total_matches: usize,
+    /// 参与求值的 Flow 数
+    pub scanned: usize,
+    /// 解析和编译耗时（毫秒）
+    pub parse_ms: f64,
+    /// 对全部 Flow 求值的耗时（毫秒）
+    pub eval_ms: f64,
+}
+
 /// 搜索结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FlowSearchResult {
@@ -132,6 +268,15 @@ pub struct FlowStats {
     pub min_latency_ms: u64,
     /// 最大延迟（毫秒）
     pub max_latency_ms: u64,
+    /// 平均内部处理耗时（毫秒，仅统计有该阶段记录的 Flow）
+    #[serde(default)]
+    pub avg_pipeline_ms: f64,
+    /// 平均首字节时间（毫秒，仅统计有该阶段记录的 Flow）
+    #[serde(default)]
+    pub avg_ttfb_ms: f64,
+    /// 平均生成耗时（毫秒，仅统计有该阶段记录的 Flow）
+    #[serde(default)]
+    pub avg_generation_ms: f64,
     /// 总输入 Token 数
     pub total_input_tokens: u64,
     /// 总输出 Token 数
@@ -140,6 +285,15 @@ pub struct FlowStats {
     pub avg_input_tokens: f64,
     /// 平均输出 Token 数
     pub avg_output_tokens: f64,
+    /// 按单价表估算的总成本（美元）
+    #[serde(default)]
+    pub estimated_cost_usd: f64,
+    /// 触发内容过滤的请求数（含拦截）
+    #[serde(default)]
+    pub content_filtered_count: usize,
+    /// 被内容过滤拦截的请求数
+    #[serde(default)]
+    pub content_blocked_count: usize,
     /// 按提供商统计
     pub by_provider: Vec<ProviderStats>,
     /// 按模型统计
@@ -148,6 +302,30 @@ pub struct FlowStats {
     pub by_state: Vec<StateStats>,
 }
 
+/// 单个耗时阶段的平均值累加器
+#[derive(Debug, Default)]
+struct StageAverage {
+    total: u64,
+    count: u64,
+}
+
+impl StageAverage {
+    fn record(&mut self, value: Option<u64>) {
+        if let Some(value) = value {
+            self.total += value;
+            self.count += 1;
+        }
+    }
+
+    fn average(&self) -> f64 {
+        if self.count > 0 {
+            self.total as f64 / self.count as f64
+        } else {
+            0.0
+        }
+    }
+}
+
 /// 按提供商统计
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderStats {
@@ -164,6 +342,9 @@ pub struct ModelStats {
     pub count: usize,
     pub success_rate: f64,
     pub avg_latency_ms: f64,
+    /// 按单价表估算的成本（美元）
+    #[serde(default)]
+    pub estimated_cost_usd: f64,
 }
 
 /// 按状态统计
@@ -173,6 +354,47 @@ pub struct StateStats {
     pub count: usize,
 }
 
+// ============================================================================
+// 会话线程
+// ============================================================================
+
+/// 重建会话线程时，每个 Flow 向前比较的最大 Flow 数
+const THREAD_COMPARE_WINDOW: usize = 200;
+
+/// 会话线程中的一轮请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowThreadTurn {
+    /// Flow ID
+    pub flow_id: String,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 模型名称
+    pub model: String,
+    /// Flow 状态
+    pub state: FlowState,
+    /// 相对上一轮新增的消息
+    pub new_messages: Vec<Message>,
+    /// 响应内容
+    pub response: Option<String>,
+}
+
+/// 会话线程
+///
+/// 由消息前缀逐轮增长的一组 Flow 组成。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowThread {
+    /// 线程 ID（首轮 Flow 的 ID）
+    pub id: String,
+    /// 分叉点 Flow ID（与其他线程共享前缀时）
+    pub forked_from: Option<String>,
+    /// 首轮时间
+    pub started_at: DateTime<Utc>,
+    /// 末轮时间
+    pub updated_at: DateTime<Utc>,
+    /// 各轮请求（按时间升序）
+    pub turns: Vec<FlowThreadTurn>,
+}
+
 // ============================================================================
 // 查询服务
 // ============================================================================
@@ -185,6 +407,10 @@ pub struct FlowQueryService {
     memory_store: Arc<RwLock<FlowMemoryStore>>,
     /// 文件存储
     file_store: Arc<FlowFileStore>,
+    /// 相关度排序的评分权重
+    relevance_weights: parking_lot::RwLock<RelevanceWeights>,
+    /// 统计成本使用的单价表
+    model_pricing: parking_lot::RwLock<ModelPricing>,
 }
 
 impl FlowQueryService {
@@ -193,9 +419,31 @@ impl FlowQueryService {
         Self {
             memory_store,
             file_store,
+            relevance_weights: parking_lot::RwLock::new(RelevanceWeights::default()),
+            model_pricing: parking_lot::RwLock::new(ModelPricing::default()),
         }
     }
 
+    /// 获取相关度排序的评分权重
+    pub fn relevance_weights(&self) -> RelevanceWeights {
+        self.relevance_weights.read().clone()
+    }
+
+    /// 设置相关度排序的评分权重
+    pub fn set_relevance_weights(&self, weights: RelevanceWeights) {
+        *self.relevance_weights.write() = weights;
+    }
+
+    /// 获取统计成本使用的单价表
+    pub fn model_pricing(&self) -> ModelPricing {
+        self.model_pricing.read().clone()
+    }
+
+    /// 设置统计成本使用的单价表
+    pub fn set_model_pricing(&self, pricing: ModelPricing) {
+        *self.model_pricing.write() = pricing;
+    }
+
     /// 查询 Flow
     ///
     /// # 参数
@@ -243,7 +491,12 @@ impl FlowQueryService {
         }
 
         // 排序
-        Self::sort_flows(&mut all_flows, sort_by, sort_desc);
+        Self::sort_flows(
+            &mut all_flows,
+            sort_by,
+            sort_desc,
+            &self.relevance_weights(),
+        );
 
         // 计算分页
         let total = all_flows.len();
@@ -340,7 +593,12 @@ impl FlowQueryService {
         }
 
         // 排序
-        Self::sort_flows(&mut all_flows, sort_by, sort_desc);
+        Self::sort_flows(
+            &mut all_flows,
+            sort_by,
+            sort_desc,
+            &self.relevance_weights(),
+        );
 
         // 计算分页
         let total = all_flows.len();
@@ -372,8 +630,59 @@ impl FlowQueryService {
         })
     }
 
+    /// 试运行过滤表达式
+    ///
+    /// 对内存中的全部 Flow 求值，返回匹配总数、最近的 `limit` 个匹配 Flow 和耗时，
+    /// 便于在保存过滤器前确认表达式是否符合预期、是否过慢。
+    pub async fn test_expression(
+        &self,
+        filter_expr: &str,
+        limit: usize,
+    ) -> Result<FilterTestResult, FilterParseError> {
+        let flows = {
+            let store = self.memory_store.read().await;
+            store.query(&FlowFilter::default())
+        };
+        Self::evaluate_expression(filter_expr, flows, limit)
+    }
+
+    /// 对给定 Flow 列表求值过滤表达式
+    fn evaluate_expression(
+        filter_expr: &str,
+        flows: Vec<LLMFlow>,
+        limit: usize,
+    ) -> Result<FilterTestResult, FilterParseError> {
+        let started = Instant::now();
+        let expr = FilterParser::parse(filter_expr)?;
+        let filter_fn = FilterParser::compile(&expr);
+        let parse_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let scanned = flows.len();
+        let started = Instant::now();
+        let mut matched: Vec<LLMFlow> = flows.into_iter().filter(|f| filter_fn(f)).collect();
+        let eval_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let total_matches = matched.len();
+        matched.sort_by(|a, b| b.timestamps.created.cmp(&a.timestamps.created));
+        matched.truncate(limit);
+
+        Ok(FilterTestResult {
+            flows: matched,
+            total_matches,
+            scanned,
+            parse_ms,
+            eval_ms,
+        })
+    }
+
     /// 排序 Flow 列表
-    fn sort_flows(flows: &mut [LLMFlow], sort_by: FlowSortBy, desc: bool) {
+    fn sort_flows(
+        flows: &mut [LLMFlow],
+        sort_by: FlowSortBy,
+        desc: bool,
+        weights: &RelevanceWeights,
+    ) {
+        let now = Utc::now();
         flows.sort_by(|a, b| {
             let cmp = match sort_by {
                 FlowSortBy::CreatedAt => a.timestamps.created.cmp(&b.timestamps.created),
@@ -389,6 +698,11 @@ impl FlowQueryService {
                     a_len.cmp(&b_len)
                 }
                 FlowSortBy::Model => a.request.model.cmp(&b.request.model),
+                FlowSortBy::Relevance => weights
+                    .score(a, now)
+                    .partial_cmp(&weights.score(b, now))
+                    .unwrap_or(Ordering::Equal)
+                    .then(a.timestamps.created.cmp(&b.timestamps.created)),
             };
 
             if desc {
@@ -459,6 +773,16 @@ impl FlowQueryService {
                 match_text = flow.id.clone();
             }
 
+            // 搜索上游请求 ID
+            if !matches {
+                if let Some(ref request_id) = flow.metadata.upstream_request_id {
+                    if request_id.to_lowercase().contains(&query_lower) {
+                        matches = true;
+                        match_text = request_id.clone();
+                    }
+                }
+            }
+
             // 搜索模型名称
             if !matches && flow.request.model.to_lowercase().contains(&query_lower) {
                 matches = true;
@@ -592,11 +916,11 @@ impl FlowQueryService {
             store.query(filter)
         };
 
-        Self::calculate_stats(&flows)
+        Self::calculate_stats(&flows, &self.model_pricing())
     }
 
     /// 计算统计信息
-    fn calculate_stats(flows: &[LLMFlow]) -> FlowStats {
+    fn calculate_stats(flows: &[LLMFlow], pricing: &ModelPricing) -> FlowStats {
         if flows.is_empty() {
             return FlowStats::default();
         }
@@ -609,11 +933,17 @@ impl FlowQueryService {
         let mut max_latency = 0u64;
         let mut total_input_tokens: u64 = 0;
         let mut total_output_tokens: u64 = 0;
+        let mut total_cost = 0.0;
+        let mut pipeline = StageAverage::default();
+        let mut ttfb = StageAverage::default();
+        let mut generation = StageAverage::default();
+        let mut content_filtered = 0;
+        let mut content_blocked = 0;
 
         // 按提供商和模型分组
         let mut provider_map: std::collections::HashMap<String, (usize, usize, u64)> =
             std::collections::HashMap::new();
-        let mut model_map: std::collections::HashMap<String, (usize, usize, u64)> =
+        let mut model_map: std::collections::HashMap<String, (usize, usize, u64, f64)> =
             std::collections::HashMap::new();
         let mut state_map: std::collections::HashMap<String, usize> =
             std::collections::HashMap::new();
@@ -635,12 +965,25 @@ impl FlowQueryService {
             total_latency += latency;
             min_latency = min_latency.min(latency);
             max_latency = max_latency.max(latency);
+            pipeline.record(flow.timestamps.pipeline_ms);
+            ttfb.record(flow.timestamps.ttfb_ms);
+            generation.record(flow.timestamps.generation_ms);
 
             // Token 统计
             if let Some(ref response) = flow.response {
                 total_input_tokens += response.usage.input_tokens as u64;
                 total_output_tokens += response.usage.output_tokens as u64;
             }
+            let cost = pricing.flow_cost(flow);
+            total_cost += cost;
+
+            // 内容过滤统计
+            if let Some(ref outcome) = flow.metadata.content_filter {
+                content_filtered += 1;
+                if outcome.action == ContentFilterAction::Block {
+                    content_blocked += 1;
+                }
+            }
 
             // 按提供商分组
             let provider_str = format!("{:?}", flow.metadata.provider);
@@ -654,12 +997,13 @@ impl FlowQueryService {
             // 按模型分组
             let model_entry = model_map
                 .entry(flow.request.model.clone())
-                .or_insert((0, 0, 0));
+                .or_insert((0, 0, 0, 0.0));
             model_entry.0 += 1;
             if flow.state == FlowState::Completed {
                 model_entry.1 += 1;
             }
             model_entry.2 += latency;
+            model_entry.3 += cost;
         }
 
         // 构建统计结果
@@ -683,7 +1027,7 @@ impl FlowQueryService {
 
         let by_model: Vec<ModelStats> = model_map
             .into_iter()
-            .map(|(model, (count, success, latency))| ModelStats {
+            .map(|(model, (count, success, latency, cost))| ModelStats {
                 model,
                 count,
                 success_rate: if count > 0 {
@@ -696,6 +1040,7 @@ impl FlowQueryService {
                 } else {
                     0.0
                 },
+                estimated_cost_usd: cost,
             })
             .collect();
 
@@ -724,6 +1069,9 @@ impl FlowQueryService {
                 min_latency
             },
             max_latency_ms: max_latency,
+            avg_pipeline_ms: pipeline.average(),
+            avg_ttfb_ms: ttfb.average(),
+            avg_generation_ms: generation.average(),
             total_input_tokens,
             total_output_tokens,
             avg_input_tokens: if total > 0 {
@@ -736,12 +1084,98 @@ impl FlowQueryService {
             } else {
                 0.0
             },
+            estimated_cost_usd: total_cost,
+            content_filtered_count: content_filtered,
+            content_blocked_count: content_blocked,
             by_provider,
             by_model,
             by_state,
         }
     }
 
+    /// 重建会话线程
+    ///
+    /// 当一个 Flow 的消息是之后某个 Flow 消息的严格前缀时，两者属于同一线程。
+    /// 多个 Flow 延续同一前缀时，后来者分叉为新线程。返回的线程按开始时间排序。
+    ///
+    /// # 参数
+    /// - `filter`: 过滤条件
+    pub async fn reconstruct_threads(&self, filter: &FlowFilter) -> Vec<FlowThread> {
+        let flows = {
+            let store = self.memory_store.read().await;
+            store.query(filter)
+        };
+
+        Self::build_threads(flows)
+    }
+
+    /// 按消息前缀将 Flow 分组为线程
+    fn build_threads(mut flows: Vec<LLMFlow>) -> Vec<FlowThread> {
+        flows.sort_by_key(|f| f.timestamps.created);
+
+        let mut threads: Vec<FlowThread> = Vec::new();
+        // 每个 Flow 所在的线程索引
+        let mut thread_of: Vec<usize> = Vec::with_capacity(flows.len());
+
+        for (index, flow) in flows.iter().enumerate() {
+            let window_start = index.saturating_sub(THREAD_COMPARE_WINDOW);
+            // 选择前缀最长的候选，长度相同时取最近的
+            let mut parent: Option<usize> = None;
+            for candidate in (window_start..index).rev() {
+                let longer = parent.is_none_or(|p| {
+                    flows[candidate].request.messages.len() > flows[p].request.messages.len()
+                });
+                if longer && Self::continues(&flows[candidate], flow) {
+                    parent = Some(candidate);
+                }
+            }
+
+            let parent_len = parent.map_or(0, |p| flows[p].request.messages.len());
+            let turn = FlowThreadTurn {
+                flow_id: flow.id.clone(),
+                created_at: flow.timestamps.created,
+                model: flow.request.model.clone(),
+                state: flow.state.clone(),
+                new_messages: flow.request.messages[parent_len..].to_vec(),
+                response: flow.response.as_ref().map(|r| r.content.clone()),
+            };
+
+            // 父 Flow 是所在线程的末轮时延续，否则分叉
+            let extend = parent.map(|p| (p, thread_of[p])).filter(|&(p, t)| {
+                threads[t].turns.last().map(|last| last.flow_id.as_str())
+                    == Some(flows[p].id.as_str())
+            });
+            match extend {
+                Some((_, t)) => {
+                    threads[t].updated_at = turn.created_at;
+                    threads[t].turns.push(turn);
+                    thread_of.push(t);
+                }
+                None => {
+                    thread_of.push(threads.len());
+                    threads.push(FlowThread {
+                        id: flow.id.clone(),
+                        forked_from: parent.map(|p| flows[p].id.clone()),
+                        started_at: turn.created_at,
+                        updated_at: turn.created_at,
+                        turns: vec![turn],
+                    });
+                }
+            }
+        }
+
+        threads
+    }
+
+    /// `next` 是否延续 `prev` 的对话
+    fn continues(prev: &LLMFlow, next: &LLMFlow) -> bool {
+        let prefix = &prev.request.messages;
+        !prefix.is_empty()
+            && prefix.len() < next.request.messages.len()
+            && prev.request.system_prompt == next.request.system_prompt
+            && next.request.messages.starts_with(prefix)
+    }
+
     /// 根据 ID 获取单个 Flow
     pub async fn get_flow(&self, id: &str) -> Result<Option<LLMFlow>, FileStoreError> {
         // 先从内存查找
@@ -763,6 +1197,40 @@ impl FlowQueryService {
         let store = self.memory_store.read().await;
         store.get_recent(limit)
     }
+
+    /// 汇总所有 Flow 的手动标签及使用次数
+    ///
+    /// 合并文件索引和内存中的标签，只在磁盘上的 Flow 也会被统计；同一 Flow 以内存中的
+    /// 标注为准。次数为带有该标签的 Flow 数量，按次数降序、次数相同时按标签名排序。
+    pub async fn collect_tags(&self) -> Result<Vec<(String, usize)>, FileStoreError> {
+        let mut tags_by_flow = self.file_store.tags_by_flow()?;
+        {
+            let store = self.memory_store.read().await;
+            for id in store.get_all_ids() {
+                let Some(flow_lock) = store.get(&id) else {
+                    continue;
+                };
+                let tags = flow_lock
+                    .read()
+                    .ok()
+                    .map(|flow| flow.annotations.tags.clone());
+                if let Some(tags) = tags {
+                    tags_by_flow.insert(id, tags);
+                }
+            }
+        }
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for tags in tags_by_flow.values() {
+            for tag in tags.iter().collect::<HashSet<_>>() {
+                *counts.entry(tag.clone()).or_default() += 1;
+            }
+        }
+
+        let mut tags: Vec<(String, usize)> = counts.into_iter().collect();
+        tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(tags)
+    }
 }
 
 // ============================================================================
@@ -773,10 +1241,141 @@ impl FlowQueryService {
 mod tests {
     use super::*;
     use crate::flow_monitor::models::{
-        FlowMetadata, FlowType, LLMRequest, LLMResponse, RequestParameters, TokenUsage,
+        FlowMetadata, FlowType, LLMRequest, LLMResponse, MessageContent, MessageRole,
+        RequestParameters, TokenUsage,
     };
     use crate::ProviderType;
 
+    fn text_message(role: MessageRole, text: &str) -> Message {
+        Message {
+            role,
+            content: MessageContent::Text(text.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// 创建带有指定消息的 Flow，创建时间按 `minute` 递增
+    fn create_thread_flow(id: &str, minute: i64, texts: &[&str]) -> LLMFlow {
+        let mut flow = create_test_flow(id, "gpt-4", ProviderType::OpenAI, FlowState::Completed);
+        flow.timestamps.created =
+            Utc::now() - chrono::Duration::hours(1) + chrono::Duration::minutes(minute);
+        flow.request.messages = texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let role = if i % 2 == 0 {
+                    MessageRole::User
+                } else {
+                    MessageRole::Assistant
+                };
+                text_message(role, text)
+            })
+            .collect();
+        flow
+    }
+
+    #[test]
+    fn test_build_threads_links_growing_prefix() {
+        let flows = vec![
+            create_thread_flow("b", 2, &["hi", "hello", "more"]),
+            create_thread_flow("a", 1, &["hi"]),
+            create_thread_flow("other", 3, &["unrelated"]),
+            create_thread_flow("c", 4, &["hi", "hello", "more", "ok", "done"]),
+        ];
+
+        let threads = FlowQueryService::build_threads(flows);
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].id, "a");
+        let ids: Vec<_> = threads[0]
+            .turns
+            .iter()
+            .map(|t| t.flow_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert_eq!(threads[0].turns[1].new_messages.len(), 2);
+        assert_eq!(
+            threads[0].turns[2].new_messages[1].content.as_text(),
+            Some("done")
+        );
+        assert_eq!(threads[0].updated_at, threads[0].turns[2].created_at);
+        assert_eq!(threads[1].id, "other");
+        assert!(threads[1].forked_from.is_none());
+    }
+
+    #[test]
+    fn test_build_threads_forks_on_shared_prefix() {
+        let flows = vec![
+            create_thread_flow("root", 1, &["hi"]),
+            create_thread_flow("left", 2, &["hi", "hello", "left"]),
+            create_thread_flow("right", 3, &["hi", "hello", "right"]),
+            create_thread_flow("left-2", 4, &["hi", "hello", "left", "ok", "again"]),
+        ];
+
+        let threads = FlowQueryService::build_threads(flows);
+        assert_eq!(threads.len(), 2);
+        let ids: Vec<_> = threads[0]
+            .turns
+            .iter()
+            .map(|t| t.flow_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["root", "left", "left-2"]);
+        assert_eq!(threads[1].id, "right");
+        assert_eq!(threads[1].forked_from.as_deref(), Some("root"));
+        assert_eq!(threads[1].turns[0].new_messages.len(), 2);
+    }
+
+    #[test]
+    fn test_build_threads_requires_same_system_prompt() {
+        let first = create_thread_flow("a", 1, &["hi"]);
+        let mut second = create_thread_flow("b", 2, &["hi", "hello", "more"]);
+        second.request.system_prompt = Some("different".to_string());
+
+        let threads = FlowQueryService::build_threads(vec![first, second]);
+        assert_eq!(threads.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_collect_tags_merges_memory_and_file_store() {
+        use crate::flow_monitor::file_store::RotationConfig;
+        use tempfile::TempDir;
+
+        let tagged = |id: &str, tags: &[&str]| {
+            let mut flow =
+                create_test_flow(id, "gpt-4", ProviderType::OpenAI, FlowState::Completed);
+            flow.annotations.tags = tags.iter().map(|t| t.to_string()).collect();
+            flow
+        };
+
+        let temp_dir = TempDir::new().unwrap();
+        let file_store = Arc::new(
+            FlowFileStore::new(temp_dir.path().to_path_buf(), RotationConfig::default()).unwrap(),
+        );
+        // 只在磁盘上的 Flow
+        file_store
+            .write(&tagged("disk-1", &["bug", "slow"]))
+            .unwrap();
+        file_store.write(&tagged("disk-2", &["bug"])).unwrap();
+        // 内存中的标注覆盖磁盘上的旧标签
+        file_store.write(&tagged("shared", &["stale"])).unwrap();
+
+        let mut memory_store = FlowMemoryStore::new(10);
+        memory_store.add(tagged("shared", &["bug", "review"]));
+        memory_store.add(tagged("mem-1", &["review", "review", "slow"]));
+        memory_store.add(tagged("mem-2", &[]));
+
+        let service = FlowQueryService::new(Arc::new(RwLock::new(memory_store)), file_store);
+        let tags = service.collect_tags().await.unwrap();
+
+        assert_eq!(
+            tags,
+            vec![
+                ("bug".to_string(), 3),
+                ("review".to_string(), 2),
+                ("slow".to_string(), 2),
+            ]
+        );
+    }
+
     /// 创建测试用的 Flow
     fn create_test_flow(
         id: &str,
@@ -834,13 +1433,23 @@ mod tests {
         flows[2].timestamps.created = Utc::now();
 
         // 升序排序
-        FlowQueryService::sort_flows(&mut flows, FlowSortBy::CreatedAt, false);
+        FlowQueryService::sort_flows(
+            &mut flows,
+            FlowSortBy::CreatedAt,
+            false,
+            &RelevanceWeights::default(),
+        );
         assert_eq!(flows[0].id, "flow-1");
         assert_eq!(flows[1].id, "flow-2");
         assert_eq!(flows[2].id, "flow-3");
 
         // 降序排序
-        FlowQueryService::sort_flows(&mut flows, FlowSortBy::CreatedAt, true);
+        FlowQueryService::sort_flows(
+            &mut flows,
+            FlowSortBy::CreatedAt,
+            true,
+            &RelevanceWeights::default(),
+        );
         assert_eq!(flows[0].id, "flow-3");
         assert_eq!(flows[1].id, "flow-2");
         assert_eq!(flows[2].id, "flow-1");
@@ -874,7 +1483,12 @@ mod tests {
         flows[2].timestamps.duration_ms = 200;
 
         // 升序排序
-        FlowQueryService::sort_flows(&mut flows, FlowSortBy::Duration, false);
+        FlowQueryService::sort_flows(
+            &mut flows,
+            FlowSortBy::Duration,
+            false,
+            &RelevanceWeights::default(),
+        );
         assert_eq!(flows[0].timestamps.duration_ms, 100);
         assert_eq!(flows[1].timestamps.duration_ms, 200);
         assert_eq!(flows[2].timestamps.duration_ms, 300);
@@ -904,12 +1518,49 @@ mod tests {
         ];
 
         // 升序排序
-        FlowQueryService::sort_flows(&mut flows, FlowSortBy::Model, false);
+        FlowQueryService::sort_flows(
+            &mut flows,
+            FlowSortBy::Model,
+            false,
+            &RelevanceWeights::default(),
+        );
         assert_eq!(flows[0].request.model, "claude-3");
         assert_eq!(flows[1].request.model, "gemini-pro");
         assert_eq!(flows[2].request.model, "gpt-4");
     }
 
+    #[test]
+    fn test_flow_sort_by_relevance() {
+        let now = Utc::now();
+        let mut flows: Vec<LLMFlow> = ["recent", "old-starred", "old", "old-priority"]
+            .into_iter()
+            .map(|id| create_test_flow(id, "gpt-4", ProviderType::OpenAI, FlowState::Completed))
+            .collect();
+        flows[0].timestamps.created = now;
+        for flow in &mut flows[1..] {
+            flow.timestamps.created = now - chrono::Duration::hours(24);
+        }
+        flows[1].annotations.starred = true;
+        flows[3].annotations.priority = 5;
+
+        FlowQueryService::sort_flows(
+            &mut flows,
+            FlowSortBy::Relevance,
+            true,
+            &RelevanceWeights::default(),
+        );
+        let ids: Vec<_> = flows.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(ids, vec!["old-priority", "old-starred", "recent", "old"]);
+
+        // 调整权重后时间项占主导
+        let weights = RelevanceWeights {
+            recency: 10.0,
+            ..Default::default()
+        };
+        FlowQueryService::sort_flows(&mut flows, FlowSortBy::Relevance, true, &weights);
+        assert_eq!(flows[0].id, "recent");
+    }
+
     #[test]
     fn test_calculate_stats() {
         let mut flows = vec![
@@ -953,7 +1604,7 @@ mod tests {
             ..Default::default()
         });
 
-        let stats = FlowQueryService::calculate_stats(&flows);
+        let stats = FlowQueryService::calculate_stats(&flows, &ModelPricing::default());
 
         assert_eq!(stats.total_requests, 3);
         assert_eq!(stats.successful_requests, 2);
@@ -965,6 +1616,134 @@ mod tests {
         assert_eq!(stats.total_output_tokens, 150);
     }
 
+    #[test]
+    fn test_calculate_estimated_cost() {
+        let mut flows = vec![
+            create_test_flow(
+                "flow-1",
+                "gpt-4o",
+                ProviderType::OpenAI,
+                FlowState::Completed,
+            ),
+            create_test_flow(
+                "flow-2",
+                "claude-3-5-sonnet",
+                ProviderType::Claude,
+                FlowState::Completed,
+            ),
+            create_test_flow(
+                "flow-3",
+                "unknown-model",
+                ProviderType::OpenAI,
+                FlowState::Completed,
+            ),
+        ];
+        let usage = |input_tokens, output_tokens, cache_read_tokens| LLMResponse {
+            usage: TokenUsage {
+                input_tokens,
+                output_tokens,
+                cache_read_tokens,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        flows[0].response = Some(usage(1000, 500, None));
+        flows[1].response = Some(usage(2000, 1000, Some(1000)));
+        flows[2].response = Some(usage(1000, 1000, None));
+
+        let mut pricing = ModelPricing::default();
+        pricing.0.insert(
+            "gpt-4o".to_string(),
+            TokenPricing {
+                input_per_1k: 0.005,
+                output_per_1k: 0.015,
+                ..Default::default()
+            },
+        );
+        pricing.0.insert(
+            "claude-*".to_string(),
+            TokenPricing {
+                input_per_1k: 0.003,
+                output_per_1k: 0.015,
+                cache_read_per_1k: Some(0.0003),
+                cache_write_per_1k: None,
+            },
+        );
+
+        let stats = FlowQueryService::calculate_stats(&flows, &pricing);
+
+        // gpt-4o: 1 * 0.005 + 0.5 * 0.015 = 0.0125
+        // claude: 1 * 0.003 + 1 * 0.0003 + 1 * 0.015 = 0.0183
+        // 未知模型不计成本
+        assert!((stats.estimated_cost_usd - 0.0308).abs() < 1e-9);
+        let model_cost = |model: &str| {
+            stats
+                .by_model
+                .iter()
+                .find(|m| m.model == model)
+                .unwrap()
+                .estimated_cost_usd
+        };
+        assert!((model_cost("gpt-4o") - 0.0125).abs() < 1e-9);
+        assert!((model_cost("claude-3-5-sonnet") - 0.0183).abs() < 1e-9);
+        assert_eq!(model_cost("unknown-model"), 0.0);
+    }
+
+    #[test]
+    fn test_calculate_stage_averages() {
+        let mut flows: Vec<LLMFlow> = (0..3)
+            .map(|i| {
+                create_test_flow(
+                    &format!("flow-{}", i),
+                    "gpt-4",
+                    ProviderType::OpenAI,
+                    FlowState::Completed,
+                )
+            })
+            .collect();
+        flows[0].timestamps.pipeline_ms = Some(10);
+        flows[0].timestamps.ttfb_ms = Some(300);
+        flows[0].timestamps.generation_ms = Some(1000);
+        flows[1].timestamps.pipeline_ms = Some(30);
+        flows[1].timestamps.ttfb_ms = Some(500);
+        flows[1].timestamps.generation_ms = Some(2000);
+        // 第三个 Flow 没有阶段记录，不计入平均值
+
+        let stats = FlowQueryService::calculate_stats(&flows, &ModelPricing::default());
+        assert!((stats.avg_pipeline_ms - 20.0).abs() < 0.001);
+        assert!((stats.avg_ttfb_ms - 400.0).abs() < 0.001);
+        assert!((stats.avg_generation_ms - 1500.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_evaluate_expression() {
+        let mut flows = vec![
+            create_test_flow(
+                "flow-1",
+                "claude-3",
+                ProviderType::Claude,
+                FlowState::Completed,
+            ),
+            create_test_flow("flow-2", "gpt-4", ProviderType::OpenAI, FlowState::Failed),
+            create_test_flow(
+                "flow-3",
+                "claude-3",
+                ProviderType::Claude,
+                FlowState::Completed,
+            ),
+        ];
+        flows[0].timestamps.created = Utc::now() - chrono::Duration::minutes(1);
+
+        let result = FlowQueryService::evaluate_expression("~m claude", flows.clone(), 1).unwrap();
+        assert_eq!(result.total_matches, 2);
+        assert_eq!(result.scanned, 3);
+        assert_eq!(result.flows.len(), 1);
+        assert_eq!(result.flows[0].id, "flow-3");
+        assert!(result.eval_ms >= 0.0);
+
+        assert!(FlowQueryService::evaluate_expression("(~m claude", flows, 10).is_err());
+    }
+
     #[test]
     fn test_extract_snippet() {
         let content = "This is a test content with some keywords for searching.";
@@ -1208,7 +1987,7 @@ mod property_tests {
             }
 
             // 排序
-            FlowQueryService::sort_flows(&mut flows, sort_by, desc);
+            FlowQueryService::sort_flows(&mut flows, sort_by, desc, &RelevanceWeights::default());
 
             // 验证排序正确性
             for i in 1..flows.len() {
@@ -1226,6 +2005,7 @@ mod property_tests {
                         a.cmp(&b)
                     }
                     FlowSortBy::Model => flows[i-1].request.model.cmp(&flows[i].request.model),
+                    FlowSortBy::Relevance => unreachable!("arb_sort_by 不生成 Relevance"),
                 };
 
                 let expected = if desc {
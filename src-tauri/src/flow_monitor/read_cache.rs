@@ -0,0 +1,272 @@
+//! 文件存储读缓存
+//!
+//! 在 UI 中翻看已归档的 Flow 时，同一个 Flow 会被反复读取，每次都要定位 JSONL 文件、
+//! 读取并解析整行。这里按 Flow ID 缓存最近读取的 `LLMFlow`，按最近最少使用淘汰，
+//! 总占用不超过配置的内存预算，不会把所有归档 Flow 都留在内存中。
+//!
+//! 占用按 Flow 在 JSONL 中的字节数估算。单个 Flow 超过预算的四分之一时不缓存，
+//! 避免一个超大的 Flow 把其他条目全部挤出。
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use super::models::LLMFlow;
+
+/// 读缓存配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadCacheConfig {
+    /// 内存预算（MB，0 表示不缓存）
+    #[serde(default = "default_max_memory_mb")]
+    pub max_memory_mb: u64,
+}
+
+fn default_max_memory_mb() -> u64 {
+    32
+}
+
+impl Default for ReadCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_memory_mb: default_max_memory_mb(),
+        }
+    }
+}
+
+impl ReadCacheConfig {
+    /// 内存预算（字节）
+    pub fn max_memory_bytes(&self) -> u64 {
+        self.max_memory_mb.saturating_mul(1024 * 1024)
+    }
+}
+
+/// 读缓存统计（供监控状态命令展示和调整预算）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadCacheStats {
+    /// 缓存的 Flow 数量
+    pub entries: usize,
+    /// 估算占用（字节）
+    pub memory_bytes: u64,
+    /// 内存预算（字节）
+    pub max_memory_bytes: u64,
+    /// 命中次数
+    pub hits: u64,
+    /// 未命中次数
+    pub misses: u64,
+    /// 命中率（尚无读取时为空）
+    pub hit_rate: Option<f64>,
+    /// 因超出预算被淘汰的条目数
+    pub evictions: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    flow: LLMFlow,
+    size: u64,
+    /// 最近一次访问的序号（越大越新）
+    tick: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    /// 访问序号 -> Flow ID，最小的序号最久未使用
+    lru: BTreeMap<u64, String>,
+    next_tick: u64,
+    memory_bytes: u64,
+    max_memory_bytes: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl CacheState {
+    fn touch(&mut self, id: &str) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        if let Some(entry) = self.entries.get_mut(id) {
+            self.lru.remove(&entry.tick);
+            entry.tick = tick;
+            self.lru.insert(tick, id.to_string());
+        }
+    }
+
+    fn remove(&mut self, id: &str) {
+        if let Some(entry) = self.entries.remove(id) {
+            self.lru.remove(&entry.tick);
+            self.memory_bytes -= entry.size;
+        }
+    }
+
+    /// 淘汰最久未使用的条目直到占用不超过预算
+    fn shrink_to_budget(&mut self) {
+        while self.memory_bytes > self.max_memory_bytes {
+            let Some((_, id)) = self.lru.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&id) {
+                self.memory_bytes -= entry.size;
+                self.evictions += 1;
+            }
+        }
+    }
+}
+
+/// 按内存预算淘汰的 LRU 读缓存
+#[derive(Debug)]
+pub struct FlowReadCache {
+    state: Mutex<CacheState>,
+}
+
+impl Default for FlowReadCache {
+    fn default() -> Self {
+        Self::new(ReadCacheConfig::default().max_memory_bytes())
+    }
+}
+
+impl FlowReadCache {
+    /// 创建读缓存
+    pub fn new(max_memory_bytes: u64) -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                max_memory_bytes,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// 读取缓存的 Flow，并计入命中率
+    pub fn get(&self, id: &str) -> Option<LLMFlow> {
+        let mut state = self.state.lock();
+        let flow = state.entries.get(id).map(|entry| entry.flow.clone());
+        if flow.is_some() {
+            state.hits += 1;
+            state.touch(id);
+        } else {
+            state.misses += 1;
+        }
+        flow
+    }
+
+    /// 缓存 Flow（`size` 为估算占用的字节数）
+    pub fn insert(&self, flow: LLMFlow, size: u64) {
+        let mut state = self.state.lock();
+        state.remove(&flow.id);
+        if size > state.max_memory_bytes / 4 {
+            return;
+        }
+
+        let id = flow.id.clone();
+        let tick = state.next_tick;
+        state.next_tick += 1;
+        state.lru.insert(tick, id.clone());
+        state.entries.insert(id, CacheEntry { flow, size, tick });
+        state.memory_bytes += size;
+        state.shrink_to_budget();
+    }
+
+    /// 使某个 Flow 的缓存失效
+    pub fn invalidate(&self, id: &str) {
+        self.state.lock().remove(id);
+    }
+
+    /// 使多个 Flow 的缓存失效
+    pub fn invalidate_many<'a>(&self, ids: impl IntoIterator<Item = &'a str>) {
+        let mut state = self.state.lock();
+        for id in ids {
+            state.remove(id);
+        }
+    }
+
+    /// 清空缓存（保留命中率统计）
+    pub fn clear(&self) {
+        let mut state = self.state.lock();
+        state.entries.clear();
+        state.lru.clear();
+        state.memory_bytes = 0;
+    }
+
+    /// 调整内存预算，超出新预算的条目立即淘汰
+    pub fn set_max_memory_bytes(&self, max_memory_bytes: u64) {
+        let mut state = self.state.lock();
+        state.max_memory_bytes = max_memory_bytes;
+        state.shrink_to_budget();
+    }
+
+    /// 当前统计
+    pub fn stats(&self) -> ReadCacheStats {
+        let state = self.state.lock();
+        let lookups = state.hits + state.misses;
+        ReadCacheStats {
+            entries: state.entries.len(),
+            memory_bytes: state.memory_bytes,
+            max_memory_bytes: state.max_memory_bytes,
+            hits: state.hits,
+            misses: state.misses,
+            hit_rate: (lookups > 0).then(|| state.hits as f64 / lookups as f64),
+            evictions: state.evictions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::models::{FlowMetadata, FlowType, LLMRequest};
+
+    fn flow(id: &str) -> LLMFlow {
+        LLMFlow::new(
+            id.to_string(),
+            FlowType::ChatCompletions,
+            LLMRequest::default(),
+            FlowMetadata::default(),
+        )
+    }
+
+    #[test]
+    fn test_lru_eviction_within_budget() {
+        let cache = FlowReadCache::new(1000);
+        cache.insert(flow("a"), 200);
+        cache.insert(flow("b"), 200);
+        cache.insert(flow("c"), 200);
+        cache.insert(flow("d"), 200);
+
+        // 访问 a 后 b 成为最久未使用的条目
+        assert!(cache.get("a").is_some());
+        cache.insert(flow("e"), 250);
+
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("e").is_some());
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 4);
+        assert_eq!(stats.memory_bytes, 850);
+        assert_eq!(stats.evictions, 1);
+        assert_eq!((stats.hits, stats.misses), (3, 1));
+        assert_eq!(stats.hit_rate, Some(0.75));
+
+        // 超过预算四分之一的 Flow 不缓存
+        cache.insert(flow("huge"), 300);
+        assert!(cache.get("huge").is_none());
+
+        cache.set_max_memory_bytes(400);
+        let stats = cache.stats();
+        assert!(stats.memory_bytes <= 400);
+        assert!(cache.get("e").is_some());
+    }
+
+    #[test]
+    fn test_invalidate_and_disabled() {
+        let cache = FlowReadCache::new(1000);
+        cache.insert(flow("a"), 100);
+        cache.insert(flow("b"), 100);
+        cache.invalidate("a");
+        cache.invalidate_many(["b", "missing"]);
+        assert_eq!(cache.stats().entries, 0);
+        assert_eq!(cache.stats().memory_bytes, 0);
+
+        let disabled = FlowReadCache::new(0);
+        disabled.insert(flow("a"), 1);
+        assert!(disabled.get("a").is_none());
+    }
+}
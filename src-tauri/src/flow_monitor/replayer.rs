@@ -5,26 +5,36 @@
 //! # 功能
 //!
 //! - 重放单个 Flow
-//! - 批量重放多个 Flow
+//! - 批量重放多个 Flow（可并发，受全局和单凭证速率限制，可中途取消）
+//! - 循环重放单个 Flow（用于复现偶发错误，可中途取消）
+//! - 流式重放：逐个 chunk 推送到订阅者，用于实时展示重放响应
 //! - 支持修改请求参数后重放
 //! - 支持选择不同的凭证
 //! - 重放的 Flow 会被标记为 "replay"
 
 use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
-use std::time::Duration;
+use std::future::{ready, Future};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
 use tokio::time::sleep;
 use uuid::Uuid;
 
+use super::body_decode::decode_body;
+use super::mock::{self, MockConfig};
 use super::models::{
     FlowAnnotations, FlowMetadata, FlowState, FlowTimestamps, LLMFlow, LLMRequest, LLMResponse,
-    Message, RequestParameters, TokenUsage,
+    Message, RequestParameters, ResponseLogprobs, TokenUsage,
 };
 use super::monitor::FlowMonitor;
+use super::stream_rebuilder::{StreamFormat, StreamRebuilder};
 use crate::database::DbConnection;
+use crate::streaming::{parse_sse_block, SseEvent, StreamError, StreamResponse};
 use crate::ProviderPoolService;
 use crate::ProviderType;
 
@@ -42,20 +52,42 @@ pub struct ReplayConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub modify_request: Option<RequestModification>,
     /// 重放间隔（毫秒），用于批量重放时避免触发速率限制
+    ///
+    /// 批量重放中为相邻两次重放开始之间的最小间隔（全局）。
     #[serde(default = "default_interval_ms")]
     pub interval_ms: u64,
+    /// 同一凭证相邻两次重放开始之间的最小间隔（毫秒），0 表示只受全局间隔限制
+    #[serde(default)]
+    pub credential_interval_ms: u64,
+    /// 批量重放的最大并发数
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// 批量重放结果是否按输入顺序排列（否则按完成顺序）
+    #[serde(default = "default_ordered_results")]
+    pub ordered_results: bool,
 }
 
 fn default_interval_ms() -> u64 {
     1000 // 默认 1 秒间隔
 }
 
+fn default_concurrency() -> usize {
+    1
+}
+
+fn default_ordered_results() -> bool {
+    true
+}
+
 impl Default for ReplayConfig {
     fn default() -> Self {
         Self {
             credential_id: None,
             modify_request: None,
             interval_ms: default_interval_ms(),
+            credential_interval_ms: 0,
+            concurrency: default_concurrency(),
+            ordered_results: default_ordered_results(),
         }
     }
 }
@@ -77,6 +109,30 @@ pub struct RequestModification {
     pub system_prompt: Option<String>,
 }
 
+/// 循环重放的停止条件
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StopCondition {
+    /// 首次失败时停止
+    FirstError,
+    /// 首次成功时停止
+    FirstSuccess,
+    /// 跑满指定次数
+    #[default]
+    Never,
+}
+
+impl StopCondition {
+    /// 检查重放结果是否满足停止条件
+    pub fn is_met(&self, result: &ReplayResult) -> bool {
+        match self {
+            StopCondition::FirstError => !result.success,
+            StopCondition::FirstSuccess => result.success,
+            StopCondition::Never => false,
+        }
+    }
+}
+
 // ============================================================================
 // 重放结果
 // ============================================================================
@@ -141,6 +197,40 @@ impl ReplayResult {
     }
 }
 
+// ============================================================================
+// 流式重放事件
+// ============================================================================
+
+/// 流式重放事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplayStreamEvent {
+    /// 重放开始（重放 Flow 已创建）
+    Started {
+        original_flow_id: String,
+        replay_flow_id: String,
+    },
+    /// 收到上游的一个 chunk
+    Chunk {
+        replay_flow_id: String,
+        /// Chunk 索引
+        index: u32,
+        /// SSE 事件类型
+        #[serde(skip_serializing_if = "Option::is_none")]
+        event: Option<String>,
+        /// SSE 数据内容
+        data: String,
+        /// 解析出的内容增量
+        #[serde(skip_serializing_if = "Option::is_none")]
+        content_delta: Option<String>,
+    },
+    /// 重放结束
+    Finished {
+        replay_flow_id: String,
+        result: ReplayResult,
+    },
+}
+
 // ============================================================================
 // 批量重放结果
 // ============================================================================
@@ -160,8 +250,91 @@ pub struct BatchReplayResult {
     pub started_at: DateTime<Utc>,
     /// 批量重放结束时间
     pub completed_at: DateTime<Utc>,
-    /// 总耗时（毫秒）
+    /// 总耗时（毫秒，墙钟时间）
     pub total_duration_ms: u64,
+    /// 实际吞吐量（每秒完成的重放数）
+    #[serde(default)]
+    pub throughput_per_second: f64,
+    /// 各次重放的耗时分布
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<LatencyDistribution>,
+    /// 触发提前停止的条件（循环重放）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped_by: Option<StopCondition>,
+    /// 是否被取消
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+impl BatchReplayResult {
+    /// 汇总各次重放结果
+    fn from_results(
+        results: Vec<ReplayResult>,
+        started_at: DateTime<Utc>,
+        stopped_by: Option<StopCondition>,
+        cancelled: bool,
+    ) -> Self {
+        let completed_at = Utc::now();
+        let total_duration_ms = (completed_at - started_at).num_milliseconds().max(0) as u64;
+        let success_count = results.iter().filter(|r| r.success).count();
+        let durations: Vec<u64> = results.iter().map(|r| r.duration_ms).collect();
+        let throughput_per_second = if total_duration_ms > 0 {
+            results.len() as f64 * 1000.0 / total_duration_ms as f64
+        } else {
+            0.0
+        };
+
+        Self {
+            total: results.len(),
+            success_count,
+            failure_count: results.len() - success_count,
+            latency: LatencyDistribution::from_durations(&durations),
+            results,
+            started_at,
+            completed_at,
+            total_duration_ms,
+            throughput_per_second,
+            stopped_by,
+            cancelled,
+        }
+    }
+}
+
+/// 重放耗时分布（毫秒）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyDistribution {
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub mean_ms: f64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+}
+
+impl LatencyDistribution {
+    /// 从耗时列表计算分布，列表为空时返回 None
+    pub fn from_durations(durations: &[u64]) -> Option<Self> {
+        if durations.is_empty() {
+            return None;
+        }
+        let mut sorted = durations.to_vec();
+        sorted.sort_unstable();
+
+        // 最近秩法计算百分位
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+
+        Some(Self {
+            min_ms: sorted[0],
+            max_ms: sorted[sorted.len() - 1],
+            mean_ms: sorted.iter().sum::<u64>() as f64 / sorted.len() as f64,
+            p50_ms: percentile(50.0),
+            p90_ms: percentile(90.0),
+            p99_ms: percentile(99.0),
+        })
+    }
 }
 
 // ============================================================================
@@ -180,6 +353,9 @@ pub enum ReplayerError {
     /// 请求失败
     #[error("请求失败: {0}")]
     RequestFailed(String),
+    /// Flow 没有保存流式 chunk
+    #[error("Flow '{0}' 没有保存流式 chunk")]
+    NoStreamChunks(String),
     /// 内部错误
     #[error("内部错误: {0}")]
     Internal(String),
@@ -201,6 +377,12 @@ pub struct FlowReplayer {
     provider_pool: Arc<ProviderPoolService>,
     /// 数据库连接
     db: DbConnection,
+    /// 进行中的循环重放
+    repeat_runs: ReplayRuns,
+    /// 进行中的批量重放
+    batch_runs: ReplayRuns,
+    /// 流式重放事件发送器
+    stream_sender: broadcast::Sender<ReplayStreamEvent>,
 }
 
 impl FlowReplayer {
@@ -214,15 +396,24 @@ impl FlowReplayer {
             .timeout(Duration::from_secs(120))
             .build()
             .unwrap_or_default();
+        let (stream_sender, _) = broadcast::channel(1000);
 
         Self {
             client,
             flow_monitor,
             provider_pool,
             db,
+            repeat_runs: ReplayRuns::default(),
+            batch_runs: ReplayRuns::default(),
+            stream_sender,
         }
     }
 
+    /// 订阅流式重放事件
+    pub fn subscribe_stream(&self) -> broadcast::Receiver<ReplayStreamEvent> {
+        self.stream_sender.subscribe()
+    }
+
     /// 重放单个 Flow
     ///
     /// **Validates: Requirements 3.1, 3.3, 3.4**
@@ -286,11 +477,101 @@ impl FlowReplayer {
         }
     }
 
+    /// 以流式方式重放单个 Flow
+    ///
+    /// 强制以 `stream: true` 发送请求，收到的每个 chunk 通过 `subscribe_stream`
+    /// 的广播通道实时推送；开启 `save_stream_chunks` 时重放 Flow 会保存原始 chunk。
+    /// 上游未返回 SSE（如错误响应）时按非流式响应记录。
+    ///
+    /// # Arguments
+    /// * `flow_id` - 要重放的 Flow ID
+    /// * `config` - 重放配置
+    ///
+    /// # Returns
+    /// * `Ok(ReplayResult)` - 重放结果
+    /// * `Err(ReplayerError)` - 重放失败
+    pub async fn replay_streaming(
+        &self,
+        flow_id: &str,
+        config: ReplayConfig,
+    ) -> Result<ReplayResult, ReplayerError> {
+        let started_at = Utc::now();
+
+        let original_flow = self.get_flow(flow_id).await?;
+        let request = self.apply_modifications(&original_flow.request, &config.modify_request);
+        let credential_id = self.resolve_credential(&original_flow, &config).await?;
+        let replay_flow_id = self
+            .create_replay_flow(&original_flow, &request, &credential_id)
+            .await;
+
+        let _ = self.stream_sender.send(ReplayStreamEvent::Started {
+            original_flow_id: flow_id.to_string(),
+            replay_flow_id: replay_flow_id.clone(),
+        });
+
+        let result = match self
+            .execute_replay_streaming(
+                &request,
+                &original_flow.metadata,
+                &credential_id,
+                &replay_flow_id,
+            )
+            .await
+        {
+            Ok(response) => {
+                self.complete_replay_flow(&replay_flow_id, Some(response))
+                    .await;
+                ReplayResult::success(
+                    flow_id.to_string(),
+                    replay_flow_id.clone(),
+                    started_at,
+                    Utc::now(),
+                )
+            }
+            Err(e) => {
+                self.fail_replay_flow(&replay_flow_id, &e.to_string()).await;
+                ReplayResult::failure(flow_id.to_string(), e.to_string(), started_at, Utc::now())
+            }
+        };
+
+        let _ = self.stream_sender.send(ReplayStreamEvent::Finished {
+            replay_flow_id,
+            result: result.clone(),
+        });
+        Ok(result)
+    }
+
+    /// 按捕获时的时间重新推送 Flow 保存的流式响应
+    ///
+    /// 不调用上游，逐个推送保存的 `StreamChunk`（SSE 格式，合并过的 chunk 拆回多个事件）。
+    /// 首个 chunk 前等待原始首字节延迟，之后按 chunk 时间戳的间隔等待，等待时间乘以
+    /// `speed_multiplier`：1.0 为原速，0.5 为两倍速，0（或负数）立即推送全部。
+    ///
+    /// # Arguments
+    /// * `flow_id` - 要重放的 Flow ID
+    /// * `speed_multiplier` - 等待时间的缩放系数
+    ///
+    /// # Returns
+    /// * `Ok(StreamResponse)` - SSE 字节流
+    /// * `Err(ReplayerError)` - Flow 不存在或没有保存流式 chunk
+    pub async fn replay_stream(
+        &self,
+        flow_id: &str,
+        speed_multiplier: f64,
+    ) -> Result<StreamResponse, ReplayerError> {
+        let flow = self.get_flow(flow_id).await?;
+        captured_stream(&flow, speed_multiplier)
+    }
+
     /// 批量重放多个 Flow
     ///
     /// **Validates: Requirements 3.6, 3.7**
     ///
+    /// 最多 `concurrency` 个重放同时进行；每次重放开始前按 `interval_ms`（全局）
+    /// 和 `credential_interval_ms`（同一凭证）等待，并发较高时也不会超出速率限制。
+    ///
     /// # Arguments
+    /// * `run_id` - 运行 ID（用于 [`Self::cancel_batch`] 取消本次批量重放）
     /// * `flow_ids` - 要重放的 Flow ID 列表
     /// * `config` - 重放配置
     ///
@@ -298,49 +579,101 @@ impl FlowReplayer {
     /// * `BatchReplayResult` - 批量重放结果
     pub async fn replay_batch(
         &self,
+        run_id: &str,
         flow_ids: &[String],
         config: ReplayConfig,
     ) -> BatchReplayResult {
-        let started_at = Utc::now();
-        let mut results = Vec::with_capacity(flow_ids.len());
-        let mut success_count = 0;
-        let mut failure_count = 0;
-
-        for (i, flow_id) in flow_ids.iter().enumerate() {
-            // 执行重放
-            let result = match self.replay(flow_id, config.clone()).await {
-                Ok(r) => r,
-                Err(e) => {
-                    ReplayResult::failure(flow_id.clone(), e.to_string(), Utc::now(), Utc::now())
+        let run = self.batch_runs.start(run_id);
+        let gate = RateGate::new(config.interval_ms, config.credential_interval_ms);
+        let cancel = run.flag();
+
+        run_batch(
+            flow_ids,
+            config.concurrency,
+            config.ordered_results,
+            cancel,
+            |flow_id| {
+                let config = config.clone();
+                let gate = &gate;
+                async move {
+                    // 按实际使用的凭证限速（Flow 不存在时只受全局间隔限制）
+                    let credential_id = match self.get_flow(&flow_id).await {
+                        Ok(flow) => self.resolve_credential(&flow, &config).await.ok().flatten(),
+                        Err(_) => None,
+                    };
+                    if !gate.acquire(credential_id.as_deref(), cancel).await {
+                        return None;
+                    }
+
+                    Some(match self.replay(&flow_id, config).await {
+                        Ok(r) => r,
+                        Err(e) => {
+                            ReplayResult::failure(flow_id, e.to_string(), Utc::now(), Utc::now())
+                        }
+                    })
                 }
-            };
+            },
+        )
+        .await
+    }
 
-            if result.success {
-                success_count += 1;
-            } else {
-                failure_count += 1;
-            }
+    /// 取消正在进行的批量重放
+    ///
+    /// 已开始的重放继续完成，不再发起新的重放。`run_id` 为空时取消所有批量重放。
+    ///
+    /// # Returns
+    /// 是否有重放被取消
+    pub fn cancel_batch(&self, run_id: Option<&str>) -> bool {
+        self.batch_runs.cancel(run_id)
+    }
 
-            results.push(result);
+    /// 循环重放单个 Flow
+    ///
+    /// 重复重放同一个 Flow 最多 `count` 次，直到满足停止条件或被取消，
+    /// 用于复现偶发的上游错误（如"五十次出现一次超时"）。
+    ///
+    /// # Arguments
+    /// * `run_id` - 运行 ID（用于 [`Self::cancel_repeat`] 取消本次循环重放）
+    /// * `flow_id` - 要重放的 Flow ID
+    /// * `count` - 最大重放次数
+    /// * `stop_on` - 停止条件
+    /// * `config` - 重放配置（`interval_ms` 为每次重放之间的间隔）
+    ///
+    /// # Returns
+    /// * `BatchReplayResult` - 包含成功/失败统计和耗时分布
+    pub async fn replay_repeat(
+        &self,
+        run_id: &str,
+        flow_id: &str,
+        count: usize,
+        stop_on: StopCondition,
+        config: ReplayConfig,
+    ) -> BatchReplayResult {
+        let run = self.repeat_runs.start(run_id);
+        let interval_ms = config.interval_ms;
 
-            // 如果不是最后一个，等待间隔时间
-            if i < flow_ids.len() - 1 && config.interval_ms > 0 {
-                sleep(Duration::from_millis(config.interval_ms)).await;
+        run_repeat(count, stop_on, interval_ms, run.flag(), || async {
+            match self.replay(flow_id, config.clone()).await {
+                Ok(r) => r,
+                Err(e) => ReplayResult::failure(
+                    flow_id.to_string(),
+                    e.to_string(),
+                    Utc::now(),
+                    Utc::now(),
+                ),
             }
-        }
-
-        let completed_at = Utc::now();
-        let total_duration_ms = (completed_at - started_at).num_milliseconds().max(0) as u64;
+        })
+        .await
+    }
 
-        BatchReplayResult {
-            total: flow_ids.len(),
-            success_count,
-            failure_count,
-            results,
-            started_at,
-            completed_at,
-            total_duration_ms,
-        }
+    /// 取消正在进行的循环重放
+    ///
+    /// 当前这次重放完成后停止，不再发起新的重放。`run_id` 为空时取消所有循环重放。
+    ///
+    /// # Returns
+    /// 是否有重放被取消
+    pub fn cancel_repeat(&self, run_id: Option<&str>) -> bool {
+        self.repeat_runs.cancel(run_id)
     }
 
     /// 获取 Flow
@@ -448,13 +781,17 @@ impl FlowReplayer {
                 response_end: None,
                 duration_ms: 0,
                 ttfb_ms: None,
+                pipeline_ms: None,
+                generation_ms: None,
             },
             state: FlowState::Pending,
             annotations: FlowAnnotations {
                 marker: Some("🔄".to_string()), // 重放标记
                 comment: Some(format!("重放自 Flow: {}", original_flow.id)),
                 tags: vec!["replay".to_string()],
+                auto_tags: Vec::new(),
                 starred: false,
+                priority: 0,
             },
         };
 
@@ -531,20 +868,165 @@ impl FlowReplayer {
             .bytes()
             .await
             .map_err(|e| ReplayerError::RequestFailed(e.to_string()))?;
+
+        Ok(self.build_response(
+            status_code,
+            status_text,
+            headers,
+            &body_bytes,
+            start_time,
+            end_time,
+            &metadata.provider,
+        ))
+    }
+
+    /// 以流式方式执行重放请求，逐个 chunk 推送到订阅者
+    async fn execute_replay_streaming(
+        &self,
+        request: &LLMRequest,
+        metadata: &FlowMetadata,
+        credential_id: &Option<String>,
+        replay_flow_id: &str,
+    ) -> Result<LLMResponse, ReplayerError> {
+        let base_url = self.get_base_url(&metadata.provider);
+        let url = format!("{}{}", base_url, request.path);
+
+        let auth_header = self
+            .get_auth_header(&metadata.provider, credential_id)
+            .await?;
+
+        let mut req_builder = self.client.post(&url);
+        if let Some(auth) = auth_header {
+            req_builder = req_builder.header("Authorization", auth);
+        }
+        req_builder = req_builder
+            .header("Content-Type", "application/json")
+            .header("Accept", "text/event-stream");
+
+        // 强制流式请求（原始请求可能是非流式的）
+        let mut body = request.body.clone();
+        if let Some(obj) = body.as_object_mut() {
+            obj.insert("stream".to_string(), serde_json::Value::Bool(true));
+        }
+        req_builder = req_builder.json(&body);
+
+        let start_time = Utc::now();
+        let response = req_builder
+            .send()
+            .await
+            .map_err(|e| ReplayerError::RequestFailed(e.to_string()))?;
+
+        let status_code = response.status().as_u16();
+        let status_text = response.status().to_string();
+        let mut headers = HashMap::new();
+        for (key, value) in response.headers() {
+            if let Ok(v) = value.to_str() {
+                headers.insert(key.to_string(), v.to_string());
+            }
+        }
+
+        // 错误响应或非 SSE 响应按非流式处理
+        let is_sse = headers
+            .get("content-type")
+            .is_some_and(|t| t.contains("text/event-stream"));
+        if !response.status().is_success() || !is_sse {
+            let body_bytes = response
+                .bytes()
+                .await
+                .map_err(|e| ReplayerError::RequestFailed(e.to_string()))?;
+            return Ok(self.build_response(
+                status_code,
+                status_text,
+                headers,
+                &body_bytes,
+                start_time,
+                Utc::now(),
+                &metadata.provider,
+            ));
+        }
+
+        let config = self.flow_monitor.config().await;
+        let mut rebuilder = StreamRebuilder::new(Self::stream_format(&metadata.provider))
+            .with_save_raw_chunks(config.save_stream_chunks)
+            .with_compact_chunks(config.compact_stream_chunks);
+        let mut pending = Vec::new();
+        let mut size_bytes = 0;
+
+        let mut stream = response.bytes_stream();
+        while let Some(bytes) = stream.next().await {
+            let bytes = bytes.map_err(|e| ReplayerError::RequestFailed(e.to_string()))?;
+            size_bytes += bytes.len();
+
+            for event in take_sse_events(&mut pending, &bytes) {
+                let index = rebuilder.chunk_count();
+                let content_len = rebuilder.content().len();
+                if let Err(e) = rebuilder.process_event(event.event.as_deref(), &event.data) {
+                    tracing::warn!("处理重放流式 chunk 失败: {}", e);
+                }
+                let content_delta = Some(&rebuilder.content()[content_len..])
+                    .filter(|delta| !delta.is_empty())
+                    .map(str::to_string);
+
+                let _ = self.stream_sender.send(ReplayStreamEvent::Chunk {
+                    replay_flow_id: replay_flow_id.to_string(),
+                    index,
+                    event: event.event,
+                    data: event.data,
+                    content_delta,
+                });
+            }
+        }
+
+        let mut llm_response = rebuilder.finish();
+        llm_response.status_code = status_code;
+        llm_response.status_text = status_text;
+        llm_response.headers = headers;
+        llm_response.size_bytes = size_bytes;
+        llm_response.timestamp_start = start_time;
+        Ok(llm_response)
+    }
+
+    /// 从非流式响应体构建 LLM 响应
+    #[allow(clippy::too_many_arguments)]
+    fn build_response(
+        &self,
+        status_code: u16,
+        status_text: String,
+        headers: HashMap<String, String>,
+        body_bytes: &[u8],
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        provider: &ProviderType,
+    ) -> LLMResponse {
+        // 按 Content-Encoding 解压
         let size_bytes = body_bytes.len();
+        let decoded = decode_body(
+            headers.get("content-type").map(String::as_str),
+            headers.get("content-encoding").map(String::as_str),
+            body_bytes,
+        );
 
-        // 解析响应体
-        let body: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap_or_else(|_| {
-            serde_json::Value::String(String::from_utf8_lossy(&body_bytes).to_string())
-        });
+        // 解析响应体（非文本内容只保存说明）
+        let body: serde_json::Value = decoded
+            .json()
+            .unwrap_or_else(|| serde_json::Value::String(decoded.text_or_note()));
 
         // 提取内容
-        let content = self.extract_content(&body, &metadata.provider);
+        let content = self.extract_content(&body, provider);
 
         // 提取 token 使用量
-        let usage = self.extract_usage(&body, &metadata.provider);
+        let usage = self.extract_usage(&body, provider);
+
+        // 提取对数概率（重放请求保留了原始的 logprobs 参数）
+        let logprobs = ResponseLogprobs::from_body(&body);
+
+        // 提取系统指纹（与请求中的 seed 一起用于复现分析）
+        let system_fingerprint = body
+            .get("system_fingerprint")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
 
-        Ok(LLMResponse {
+        LLMResponse {
             status_code,
             status_text,
             headers,
@@ -554,11 +1036,26 @@ impl FlowReplayer {
             tool_calls: Vec::new(),
             usage,
             stop_reason: None,
+            raw_stop_reason: None,
             size_bytes,
             timestamp_start: start_time,
             timestamp_end: end_time,
             stream_info: None,
-        })
+            schema_valid: None,
+            logprobs,
+            body_info: Some(decoded.info),
+            system_fingerprint,
+            turns: Vec::new(),
+        }
+    }
+
+    /// 获取 Provider 的流式响应格式
+    fn stream_format(provider: &ProviderType) -> StreamFormat {
+        match provider {
+            ProviderType::Claude | ProviderType::ClaudeOAuth => StreamFormat::Anthropic,
+            ProviderType::Gemini | ProviderType::GeminiApiKey => StreamFormat::Gemini,
+            _ => StreamFormat::OpenAI,
+        }
     }
 
     /// 获取基础 URL
@@ -642,7 +1139,7 @@ impl FlowReplayer {
     fn extract_usage(&self, body: &serde_json::Value, provider: &ProviderType) -> TokenUsage {
         let usage = &body["usage"];
 
-        match provider {
+        let mut extracted = match provider {
             ProviderType::OpenAI | ProviderType::Kiro => TokenUsage {
                 input_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0) as u32,
                 output_tokens: usage["completion_tokens"].as_u64().unwrap_or(0) as u32,
@@ -658,7 +1155,9 @@ impl FlowReplayer {
                 ..Default::default()
             },
             _ => TokenUsage::default(),
-        }
+        };
+        extracted.apply_cache_usage(usage);
+        extracted
     }
 
     /// 完成重放 Flow
@@ -716,6 +1215,296 @@ impl FlowReplayer {
     }
 }
 
+/// 把 Flow 保存的流式 chunk 转换为按缩放后的原始间隔推送的 SSE 字节流
+fn captured_stream(flow: &LLMFlow, speed_multiplier: f64) -> Result<StreamResponse, ReplayerError> {
+    if !mock::has_chunks(flow) {
+        return Err(ReplayerError::NoStreamChunks(flow.id.clone()));
+    }
+
+    let config = MockConfig {
+        replay_timing: speed_multiplier > 0.0,
+        max_chunk_delay_ms: u64::MAX,
+        ..Default::default()
+    };
+    let events: Vec<(Duration, String)> = mock::stream_events(flow, &config)
+        .into_iter()
+        .map(|event| {
+            let delay = Duration::try_from_secs_f64(event.delay.as_secs_f64() * speed_multiplier)
+                .unwrap_or(Duration::ZERO);
+            (delay, event.text)
+        })
+        .collect();
+
+    let stream = stream::iter(events).then(|(delay, text)| async move {
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+        Ok::<_, StreamError>(bytes::Bytes::from(text))
+    });
+    Ok(Box::pin(stream))
+}
+
+/// 从 SSE 字节流中取出完整的事件
+///
+/// 不完整的事件保留在 `pending` 中等待后续数据；事件边界都是 ASCII 换行，
+/// 按块解码不会截断多字节字符。
+fn take_sse_events(pending: &mut Vec<u8>, bytes: &[u8]) -> Vec<SseEvent> {
+    pending.extend(bytes.iter().filter(|&&b| b != b'\r'));
+
+    let mut events = Vec::new();
+    while let Some(end) = pending.windows(2).position(|w| w == b"\n\n") {
+        let block: Vec<u8> = pending.drain(..end + 2).collect();
+        if let Some(event) = parse_sse_block(&String::from_utf8_lossy(&block)) {
+            events.push(event);
+        }
+    }
+    events
+}
+
+/// 并发执行批量重放
+///
+/// 最多 `concurrency` 个重放同时进行；`replay_one` 返回 `None` 表示开始前已被取消。
+/// 取消后不再发起新的重放，已开始的重放照常完成。
+async fn run_batch<F, Fut>(
+    flow_ids: &[String],
+    concurrency: usize,
+    ordered_results: bool,
+    cancel: &AtomicBool,
+    replay_one: F,
+) -> BatchReplayResult
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Option<ReplayResult>>,
+{
+    let started_at = Utc::now();
+
+    // 先构建全部重放 future（惰性执行，开始前检查取消标志），
+    // 流中不保留借用参数的闭包，外层 future 才能满足 Send
+    let replays: Vec<_> = flow_ids
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(index, flow_id)| {
+            let replay = replay_one(flow_id);
+            async move {
+                if cancel.load(Ordering::SeqCst) {
+                    return None;
+                }
+                replay.await.map(|result| (index, result))
+            }
+        })
+        .collect();
+
+    let mut results: Vec<(usize, ReplayResult)> = stream::iter(replays)
+        .buffer_unordered(concurrency.max(1))
+        .filter_map(ready)
+        .collect()
+        .await;
+
+    if ordered_results {
+        results.sort_by_key(|(index, _)| *index);
+    }
+    let cancelled = results.len() < flow_ids.len() && cancel.load(Ordering::SeqCst);
+
+    tracing::info!(
+        "[REPLAY] 批量重放结束: 完成 {}/{} 个, 并发={}, 已取消={}",
+        results.len(),
+        flow_ids.len(),
+        concurrency.max(1),
+        cancelled
+    );
+
+    let results = results.into_iter().map(|(_, result)| result).collect();
+    BatchReplayResult::from_results(results, started_at, None, cancelled)
+}
+
+type RunFlags = Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>;
+
+/// 进行中的重放运行
+///
+/// 每个运行持有独立的取消标志，取消一个运行不影响同时进行的其他运行。
+#[derive(Default)]
+struct ReplayRuns {
+    runs: RunFlags,
+}
+
+impl ReplayRuns {
+    /// 登记运行，返回的守卫释放时移除登记
+    fn start(&self, run_id: &str) -> ReplayRun {
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.runs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(run_id.to_string(), cancel.clone());
+        ReplayRun {
+            runs: self.runs.clone(),
+            run_id: run_id.to_string(),
+            cancel,
+        }
+    }
+
+    /// 取消指定运行（`None` 表示全部），返回是否有运行被取消
+    fn cancel(&self, run_id: Option<&str>) -> bool {
+        let runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        let mut cancelled = false;
+        for (id, cancel) in runs.iter() {
+            if run_id.is_some() && run_id != Some(id.as_str()) {
+                continue;
+            }
+            cancel.store(true, Ordering::SeqCst);
+            cancelled = true;
+        }
+        cancelled
+    }
+}
+
+/// 已登记的重放运行
+struct ReplayRun {
+    runs: RunFlags,
+    run_id: String,
+    cancel: Arc<AtomicBool>,
+}
+
+impl ReplayRun {
+    /// 本次运行的取消标志
+    fn flag(&self) -> &AtomicBool {
+        &self.cancel
+    }
+}
+
+impl Drop for ReplayRun {
+    fn drop(&mut self) {
+        let mut runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        // 同一 ID 被新的运行复用时保留新的登记
+        if runs
+            .get(&self.run_id)
+            .is_some_and(|cancel| Arc::ptr_eq(cancel, &self.cancel))
+        {
+            runs.remove(&self.run_id);
+        }
+    }
+}
+
+/// 重放速率闸门
+///
+/// 为每次重放预约开始时刻：相邻两次开始至少间隔 `global_interval`，
+/// 同一凭证相邻两次开始至少间隔 `credential_interval`。
+struct RateGate {
+    global_interval: Duration,
+    credential_interval: Duration,
+    state: Mutex<RateGateState>,
+}
+
+#[derive(Default)]
+struct RateGateState {
+    /// 下一次重放最早的开始时刻
+    next_global: Option<Instant>,
+    /// 各凭证下一次重放最早的开始时刻
+    next_by_credential: HashMap<String, Instant>,
+}
+
+impl RateGate {
+    fn new(global_interval_ms: u64, credential_interval_ms: u64) -> Self {
+        Self {
+            global_interval: Duration::from_millis(global_interval_ms),
+            credential_interval: Duration::from_millis(credential_interval_ms),
+            state: Mutex::new(RateGateState::default()),
+        }
+    }
+
+    /// 预约开始时刻
+    fn reserve(&self, credential_id: Option<&str>) -> Instant {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut start = state.next_global.map_or(now, |next| next.max(now));
+        if let Some(next) = credential_id.and_then(|id| state.next_by_credential.get(id)) {
+            start = start.max(*next);
+        }
+
+        // 未设置全局间隔时不限制其他凭证，等待某个凭证的重放不会推迟其他凭证
+        if !self.global_interval.is_zero() {
+            state.next_global = Some(start + self.global_interval);
+        }
+        if let Some(id) = credential_id {
+            state
+                .next_by_credential
+                .insert(id.to_string(), start + self.credential_interval);
+        }
+        start
+    }
+
+    /// 等待到预约的开始时刻，期间被取消时返回 false
+    async fn acquire(&self, credential_id: Option<&str>, cancel: &AtomicBool) -> bool {
+        let wait = self
+            .reserve(credential_id)
+            .saturating_duration_since(Instant::now());
+        sleep_unless_cancelled(wait.as_millis() as u64, cancel).await
+    }
+}
+
+/// 循环执行重放直到次数用尽、满足停止条件或被取消
+async fn run_repeat<F, Fut>(
+    count: usize,
+    stop_on: StopCondition,
+    interval_ms: u64,
+    cancel: &AtomicBool,
+    mut replay_once: F,
+) -> BatchReplayResult
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ReplayResult>,
+{
+    let started_at = Utc::now();
+    let mut results = Vec::with_capacity(count);
+    let mut stopped_by = None;
+    let mut cancelled = false;
+
+    for i in 0..count {
+        if cancel.load(Ordering::SeqCst) {
+            cancelled = true;
+            break;
+        }
+
+        let result = replay_once().await;
+        let stop = stop_on.is_met(&result);
+        results.push(result);
+        if stop {
+            stopped_by = Some(stop_on);
+            break;
+        }
+
+        if i + 1 < count && interval_ms > 0 && !sleep_unless_cancelled(interval_ms, cancel).await {
+            cancelled = true;
+            break;
+        }
+    }
+
+    tracing::info!(
+        "[REPLAY] 循环重放结束: 执行 {} 次, 停止条件={:?}, 已取消={}",
+        results.len(),
+        stopped_by,
+        cancelled
+    );
+
+    BatchReplayResult::from_results(results, started_at, stopped_by, cancelled)
+}
+
+/// 分段等待，期间被取消时立即返回 false
+async fn sleep_unless_cancelled(interval_ms: u64, cancel: &AtomicBool) -> bool {
+    const CHECK_STEP_MS: u64 = 100;
+    let mut remaining = interval_ms;
+    while remaining > 0 {
+        if cancel.load(Ordering::SeqCst) {
+            return false;
+        }
+        let step = remaining.min(CHECK_STEP_MS);
+        sleep(Duration::from_millis(step)).await;
+        remaining -= step;
+    }
+    !cancel.load(Ordering::SeqCst)
+}
+
 // ============================================================================
 // 单元测试
 // ============================================================================
@@ -807,6 +1596,179 @@ mod tests {
         );
     }
 
+    fn timed_result(success: bool, duration_ms: i64) -> ReplayResult {
+        let started_at = Utc::now();
+        let completed_at = started_at + chrono::Duration::milliseconds(duration_ms);
+        if success {
+            ReplayResult::success("f".to_string(), "r".to_string(), started_at, completed_at)
+        } else {
+            ReplayResult::failure(
+                "f".to_string(),
+                "timeout".to_string(),
+                started_at,
+                completed_at,
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_repeat_stops_on_first_error() {
+        let cancel = AtomicBool::new(false);
+        let mut calls = 0;
+        let result = run_repeat(50, StopCondition::FirstError, 0, &cancel, || {
+            calls += 1;
+            let ok = calls != 3;
+            async move { timed_result(ok, 10) }
+        })
+        .await;
+
+        assert_eq!(result.total, 3);
+        assert_eq!(result.success_count, 2);
+        assert_eq!(result.failure_count, 1);
+        assert_eq!(result.stopped_by, Some(StopCondition::FirstError));
+        assert!(!result.cancelled);
+
+        let result = run_repeat(5, StopCondition::Never, 0, &cancel, || async {
+            timed_result(false, 10)
+        })
+        .await;
+        assert_eq!(result.total, 5);
+        assert_eq!(result.failure_count, 5);
+        assert!(result.stopped_by.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_repeat_cancelled_mid_run() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = cancel.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(150)).await;
+            flag.store(true, Ordering::SeqCst);
+        });
+
+        let result = run_repeat(100, StopCondition::Never, 1000, &cancel, || async {
+            timed_result(true, 10)
+        })
+        .await;
+
+        // 间隔等待期间被取消，不再发起新的重放，且无需等满间隔
+        assert_eq!(result.total, 1);
+        assert!(result.cancelled);
+        assert!(result.total_duration_ms < 1000);
+    }
+
+    #[tokio::test]
+    async fn test_run_batch_concurrency_and_order() {
+        use std::sync::atomic::AtomicUsize;
+
+        let flow_ids: Vec<String> = (0..6).map(|i| i.to_string()).collect();
+        let cancel = AtomicBool::new(false);
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+
+        // 越靠前的 Flow 越晚完成
+        let replay_one = |flow_id: String| {
+            let index: u64 = flow_id.parse().unwrap();
+            let in_flight = &in_flight;
+            let max_in_flight = &max_in_flight;
+            async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                sleep(Duration::from_millis(60 - index * 10)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                let now = Utc::now();
+                Some(ReplayResult::success(flow_id, "r".to_string(), now, now))
+            }
+        };
+
+        let result = run_batch(&flow_ids, 3, true, &cancel, replay_one).await;
+        assert_eq!(result.total, 6);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+        let order: Vec<&str> = result
+            .results
+            .iter()
+            .map(|r| r.original_flow_id.as_str())
+            .collect();
+        assert_eq!(order, vec!["0", "1", "2", "3", "4", "5"]);
+        assert!(result.throughput_per_second > 0.0);
+
+        // 不要求顺序时按完成顺序返回
+        let result = run_batch(&flow_ids, 6, false, &cancel, replay_one).await;
+        assert_eq!(result.results[0].original_flow_id, "5");
+        assert!(!result.cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_run_batch_cancelled() {
+        let flow_ids: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+        let cancel = AtomicBool::new(false);
+
+        let result = run_batch(&flow_ids, 2, true, &cancel, |flow_id| {
+            let cancel = &cancel;
+            async move {
+                // 第二个重放完成时取消
+                if flow_id == "1" {
+                    cancel.store(true, Ordering::SeqCst);
+                }
+                let now = Utc::now();
+                Some(ReplayResult::success(flow_id, "r".to_string(), now, now))
+            }
+        })
+        .await;
+
+        assert!(result.cancelled);
+        assert!(result.total < 10);
+    }
+
+    #[test]
+    fn test_replay_runs_cancel_by_id() {
+        let runs = ReplayRuns::default();
+        let first = runs.start("run-1");
+        let second = runs.start("run-2");
+
+        // 只取消指定的运行
+        assert!(runs.cancel(Some("run-1")));
+        assert!(first.flag().load(Ordering::SeqCst));
+        assert!(!second.flag().load(Ordering::SeqCst));
+        assert!(!runs.cancel(Some("missing")));
+
+        // 结束的运行不再登记
+        drop(first);
+        assert!(!runs.cancel(Some("run-1")));
+        assert!(runs.cancel(None));
+        assert!(second.flag().load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_rate_gate_spacing() {
+        let gate = RateGate::new(0, 100);
+        let first = gate.reserve(Some("cred-a"));
+        let second = gate.reserve(Some("cred-a"));
+        let other = gate.reserve(Some("cred-b"));
+        assert!(second.duration_since(first) >= Duration::from_millis(100));
+        // 其他凭证不受影响
+        assert!(other < second);
+
+        let gate = RateGate::new(50, 0);
+        let first = gate.reserve(Some("cred-a"));
+        let second = gate.reserve(Some("cred-b"));
+        assert!(second.duration_since(first) >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_latency_distribution() {
+        assert!(LatencyDistribution::from_durations(&[]).is_none());
+
+        let durations: Vec<u64> = (1..=100).collect();
+        let latency = LatencyDistribution::from_durations(&durations).unwrap();
+        assert_eq!(latency.min_ms, 1);
+        assert_eq!(latency.max_ms, 100);
+        assert_eq!(latency.p50_ms, 50);
+        assert_eq!(latency.p90_ms, 90);
+        assert_eq!(latency.p99_ms, 99);
+        assert!((latency.mean_ms - 50.5).abs() < f64::EPSILON);
+    }
+
     #[test]
     fn test_request_modification_serialization() {
         let modification = RequestModification {
@@ -825,6 +1787,106 @@ mod tests {
             Some("You are a helpful assistant.".to_string())
         );
     }
+
+    #[test]
+    fn test_take_sse_events_across_chunks() {
+        let mut pending = Vec::new();
+        let text =
+            "event: content_block_delta\r\ndata: {\"text\":\"你好\"}\r\n\r\ndata: [DONE]\n\n";
+        let bytes = text.as_bytes();
+
+        // 在多字节字符中间切分
+        let split = text.find("你").unwrap() + 1;
+        assert!(take_sse_events(&mut pending, &bytes[..split]).is_empty());
+
+        let events = take_sse_events(&mut pending, &bytes[split..]);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.as_deref(), Some("content_block_delta"));
+        assert_eq!(events[0].data, "{\"text\":\"你好\"}");
+        assert_eq!(events[1].data, "[DONE]");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_replay_stream_event_serialization() {
+        let event = ReplayStreamEvent::Chunk {
+            replay_flow_id: "replay-id".to_string(),
+            index: 0,
+            event: None,
+            data: "{}".to_string(),
+            content_delta: Some("Hi".to_string()),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "chunk");
+        assert_eq!(json["content_delta"], "Hi");
+        assert!(json.get("event").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_captured_stream_timing() {
+        use super::super::models::{StreamChunk, StreamInfo};
+
+        let mut flow = LLMFlow::new(
+            "stream-id".to_string(),
+            FlowType::ChatCompletions,
+            LLMRequest::default(),
+            FlowMetadata::default(),
+        );
+        assert!(matches!(
+            captured_stream(&flow, 1.0),
+            Err(ReplayerError::NoStreamChunks(_))
+        ));
+
+        let start = Utc::now();
+        let chunk = |index: u32, offset_ms: i64, data: &str| StreamChunk {
+            index,
+            event: None,
+            data: data.to_string(),
+            timestamp: start + chrono::Duration::milliseconds(offset_ms),
+            content_delta: None,
+            tool_call_delta: None,
+            thinking_delta: None,
+        };
+        flow.response = Some(LLMResponse {
+            stream_info: Some(StreamInfo {
+                chunk_count: 3,
+                first_chunk_latency_ms: 0,
+                avg_chunk_interval_ms: 0.0,
+                raw_chunks: Some(vec![
+                    chunk(0, 0, r#"{"n":1}"#),
+                    chunk(1, 100, r#"{"n":2}"#),
+                    chunk(2, 200, "[DONE]"),
+                ]),
+                completed_cleanly: true,
+            }),
+            ..Default::default()
+        });
+
+        // 0.5 倍等待时间：总耗时约 100ms
+        let started = Instant::now();
+        let chunks: Vec<bytes::Bytes> = captured_stream(&flow, 0.5)
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let elapsed = started.elapsed();
+        assert_eq!(
+            chunks,
+            [
+                "data: {\"n\":1}\n\n",
+                "data: {\"n\":2}\n\n",
+                "data: [DONE]\n\n"
+            ]
+        );
+        assert!(elapsed >= Duration::from_millis(90), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+
+        // 0 倍立即推送全部
+        let started = Instant::now();
+        let immediate: Vec<_> = captured_stream(&flow, 0.0).unwrap().collect().await;
+        assert_eq!(immediate.len(), 3);
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
 }
 
 // ============================================================================
@@ -873,6 +1935,7 @@ mod property_tests {
             parameters: RequestParameters::default(),
             size_bytes: 0,
             timestamp: Utc::now(),
+            attachments: Vec::new(),
         })
     }
 
@@ -943,13 +2006,17 @@ mod property_tests {
                     response_end: None,
                     duration_ms: 0,
                     ttfb_ms: None,
+                    pipeline_ms: None,
+                    generation_ms: None,
                 },
                 state: FlowState::Pending,
                 annotations: FlowAnnotations {
                     marker: Some("🔄".to_string()), // 重放标记
                     comment: Some(format!("重放自 Flow: {}", original_flow_id)),
                     tags: vec!["replay".to_string()],
+                    auto_tags: Vec::new(),
                     starred: false,
+                    priority: 0,
                 },
             };
 
@@ -0,0 +1,275 @@
+//! Flow 保留策略
+//!
+//! 按 Flow 类别设置不同的保留天数，并在每天本地时间的固定整点自动清理：
+//! - 错误 Flow：默认保留 30 天
+//! - 普通 Flow：默认保留 7 天
+//! - 已收藏（starred）的 Flow：永久保留，不参与清理
+//!
+//! 调度器按本地日期记录上次运行，夏令时切换时：
+//! - 跳过的时刻（如 02:00-03:00 不存在）顺延到该日第一个有效时刻
+//! - 重复的时刻（如 02:00-03:00 出现两次）只在第一次出现时运行
+
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::monitor::FlowMonitor;
+
+/// 单次等待的最长时间
+///
+/// 定期醒来重新计算下次运行时间，以应对系统休眠或时钟调整。
+const MAX_SCHEDULER_SLEEP_SECS: i64 = 3600;
+
+/// 无效本地时刻的顺延步长（分钟）
+const DST_GAP_STEP_MINUTES: i64 = 15;
+
+/// 无效本地时刻的最大顺延时间（分钟）
+const DST_GAP_MAX_MINUTES: i64 = 180;
+
+fn default_run_at_hour() -> u32 {
+    3
+}
+
+fn default_normal_days() -> Option<u32> {
+    Some(7)
+}
+
+fn default_error_days() -> Option<u32> {
+    Some(30)
+}
+
+/// Flow 保留策略
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// 是否启用定时清理
+    #[serde(default)]
+    pub enabled: bool,
+    /// 每天运行的本地时间（整点，0-23）
+    #[serde(default = "default_run_at_hour")]
+    pub run_at_hour: u32,
+    /// 普通 Flow 保留天数（为空表示永久保留）
+    #[serde(default = "default_normal_days")]
+    pub normal_days: Option<u32>,
+    /// 错误 Flow 保留天数（为空表示永久保留）
+    #[serde(default = "default_error_days")]
+    pub error_days: Option<u32>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            run_at_hour: default_run_at_hour(),
+            normal_days: default_normal_days(),
+            error_days: default_error_days(),
+        }
+    }
+}
+
+/// 计算下次运行时间
+///
+/// 返回 `now` 之后第一个本地时间为 `hour` 点、且本地日期不同于
+/// `last_run` 的时刻。
+pub fn next_retention_run<Tz: TimeZone>(
+    now: &DateTime<Tz>,
+    hour: u32,
+    last_run: Option<NaiveDate>,
+) -> DateTime<Tz> {
+    let hour = hour.min(23);
+    let mut date = now.date_naive();
+    loop {
+        if last_run.is_none_or(|last| date > last) {
+            if let Some(run) = resolve_local(&now.timezone(), date, hour) {
+                if run > *now {
+                    return run;
+                }
+            }
+        }
+        date = date.succ_opt().expect("日期溢出");
+    }
+}
+
+/// 将本地日期和整点解析为时刻
+///
+/// 重复的时刻取较早者；不存在的时刻顺延到第一个有效时刻。
+fn resolve_local<Tz: TimeZone>(tz: &Tz, date: NaiveDate, hour: u32) -> Option<DateTime<Tz>> {
+    let start = date.and_hms_opt(hour, 0, 0)?;
+    (0..=DST_GAP_MAX_MINUTES)
+        .step_by(DST_GAP_STEP_MINUTES as usize)
+        .find_map(|minutes| {
+            tz.from_local_datetime(&(start + Duration::minutes(minutes)))
+                .earliest()
+        })
+}
+
+/// 运行保留策略调度器
+///
+/// 调度器每次循环都读取监控服务的最新配置，配置热重载后立即生效。
+pub async fn run_retention_scheduler(monitor: Arc<FlowMonitor>) {
+    let mut last_run: Option<NaiveDate> = None;
+    loop {
+        let policy = monitor.config().await.retention_policy;
+        if !policy.enabled {
+            monitor.retention_config_changed().await;
+            continue;
+        }
+
+        let next = next_retention_run(&Local::now(), policy.run_at_hour, last_run);
+        let wait = (next - Local::now())
+            .clamp(
+                Duration::zero(),
+                Duration::seconds(MAX_SCHEDULER_SLEEP_SECS),
+            )
+            .to_std()
+            .unwrap_or_default();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = monitor.retention_config_changed() => continue,
+        }
+        if Local::now() < next {
+            continue;
+        }
+
+        match monitor.apply_retention_policy(&policy).await {
+            Some(Ok(result)) => tracing::info!("[RETENTION] 定时清理完成: {:?}", result),
+            Some(Err(e)) => tracing::error!("[RETENTION] 定时清理失败: {}", e),
+            None => tracing::debug!("[RETENTION] 未启用文件存储，跳过定时清理"),
+        }
+        last_run = Some(next.date_naive());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, LocalResult, NaiveDateTime, Offset, TimeZone, Utc};
+
+    /// 模拟欧洲中部时间的夏令时规则（2024 年）
+    ///
+    /// 标准时间 UTC+1，2024-03-31 01:00 UTC 至 2024-10-27 01:00 UTC 为 UTC+2。
+    #[derive(Debug, Clone, Copy)]
+    struct TestDstZone;
+
+    impl TestDstZone {
+        fn offset_at(utc: &NaiveDateTime) -> FixedOffset {
+            let start = Utc
+                .with_ymd_and_hms(2024, 3, 31, 1, 0, 0)
+                .unwrap()
+                .naive_utc();
+            let end = Utc
+                .with_ymd_and_hms(2024, 10, 27, 1, 0, 0)
+                .unwrap()
+                .naive_utc();
+            let hours = if *utc >= start && *utc < end { 2 } else { 1 };
+            FixedOffset::east_opt(hours * 3600).unwrap()
+        }
+    }
+
+    impl TimeZone for TestDstZone {
+        type Offset = FixedOffset;
+
+        fn from_offset(_offset: &FixedOffset) -> Self {
+            TestDstZone
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let candidates: Vec<FixedOffset> = [1, 2]
+                .into_iter()
+                .map(|hours| FixedOffset::east_opt(hours * 3600).unwrap())
+                .filter(|offset| {
+                    let utc = *local - Duration::seconds(offset.local_minus_utc() as i64);
+                    Self::offset_at(&utc) == *offset
+                })
+                .collect();
+            match candidates.as_slice() {
+                [] => LocalResult::None,
+                [single] => LocalResult::Single(*single),
+                [first, second] => LocalResult::Ambiguous(*second, *first),
+                _ => unreachable!(),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            Self::offset_at(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            Self::offset_at(utc).fix()
+        }
+    }
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<TestDstZone> {
+        TestDstZone
+            .from_local_datetime(
+                &NaiveDate::from_ymd_opt(y, m, d)
+                    .unwrap()
+                    .and_hms_opt(h, min, 0)
+                    .unwrap(),
+            )
+            .earliest()
+            .unwrap()
+    }
+
+    /// 连续模拟调度，返回每次运行的本地时间
+    fn simulate_runs(start: DateTime<TestDstZone>, hour: u32, count: usize) -> Vec<String> {
+        let mut now = start;
+        let mut last_run = None;
+        let mut runs = Vec::new();
+        for _ in 0..count {
+            let run = next_retention_run(&now, hour, last_run);
+            runs.push(run.format("%m-%d %H:%M %:z").to_string());
+            last_run = Some(run.date_naive());
+            now = run;
+        }
+        runs
+    }
+
+    #[test]
+    fn test_next_run_same_day_and_next_day() {
+        let now = local(2024, 6, 1, 1, 30);
+        assert_eq!(next_retention_run(&now, 3, None), local(2024, 6, 1, 3, 0));
+
+        let now = local(2024, 6, 1, 10, 0);
+        assert_eq!(next_retention_run(&now, 3, None), local(2024, 6, 2, 3, 0));
+
+        // 今天已运行过时顺延到明天
+        let now = local(2024, 6, 1, 1, 30);
+        let today = now.date_naive();
+        assert_eq!(
+            next_retention_run(&now, 3, Some(today)),
+            local(2024, 6, 2, 3, 0)
+        );
+    }
+
+    #[test]
+    fn test_spring_forward_gap_is_not_skipped() {
+        // 2024-03-31 本地 02:00-03:00 不存在
+        let runs = simulate_runs(local(2024, 3, 30, 12, 0), 2, 3);
+        assert_eq!(
+            runs,
+            [
+                "03-31 03:00 +02:00",
+                "04-01 02:00 +02:00",
+                "04-02 02:00 +02:00"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fall_back_overlap_runs_once() {
+        // 2024-10-27 本地 02:00-03:00 出现两次
+        let runs = simulate_runs(local(2024, 10, 26, 12, 0), 2, 3);
+        assert_eq!(
+            runs,
+            [
+                "10-27 02:00 +02:00",
+                "10-28 02:00 +01:00",
+                "10-29 02:00 +01:00"
+            ]
+        );
+    }
+}
@@ -5,6 +5,7 @@
 //!
 //! **Validates: Requirements 5.1-5.7**
 
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
 use chrono::{DateTime, Utc};
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
@@ -14,7 +15,8 @@ use std::sync::Mutex;
 use thiserror::Error;
 use uuid::Uuid;
 
-use super::exporter::{ExportFormat, ExportOptions, FlowExporter};
+use super::batch_export::BatchTarget;
+use super::exporter::{ExportError, ExportFormat, ExportOptions, FlowExporter};
 use super::models::LLMFlow;
 
 // ============================================================================
@@ -38,6 +40,9 @@ pub enum SessionError {
 
     #[error("IO 错误: {0}")]
     Io(#[from] std::io::Error),
+
+    #[error("导出错误: {0}")]
+    Export(#[from] ExportError),
 }
 
 pub type Result<T> = std::result::Result<T, SessionError>;
@@ -112,7 +117,7 @@ impl Default for AutoSessionConfig {
 pub struct SessionExportResult {
     /// 会话信息
     pub session: FlowSession,
-    /// 导出的数据
+    /// 导出的数据（Parquet 为 base64 编码）
     pub data: String,
     /// 导出格式
     pub format: ExportFormat,
@@ -736,7 +741,13 @@ impl SessionManager {
             include_stream_chunks: false,
             redact_sensitive: false,
             redaction_rules: Vec::new(),
+            verify_redaction: false,
             compress: false,
+            stable_key_order: false,
+            pretty: true,
+            restore_inline_reasoning: false,
+            field_mask: Vec::new(),
+            exclude_fields: Vec::new(),
         };
         let exporter = FlowExporter::new(options);
 
@@ -771,6 +782,9 @@ impl SessionManager {
                 md
             }
             ExportFormat::CSV => exporter.export_csv(flows),
+            ExportFormat::OpenAiBatch => exporter.export_batch(flows, BatchTarget::OpenAi),
+            ExportFormat::AnthropicBatch => exporter.export_batch(flows, BatchTarget::Anthropic),
+            ExportFormat::Parquet => BASE64_STANDARD.encode(exporter.export_parquet(flows)?),
         };
 
         Ok(SessionExportResult {
@@ -0,0 +1,370 @@
+//! 成功率 SLO 跟踪
+//!
+//! 按 Provider 以及 Provider + 模型维护滚动窗口内的成功率，并用错误预算消耗速率（burn rate）
+//! 做多窗口告警：短窗口和长窗口的消耗速率同时达到阈值时告警；告警后短窗口的消耗速率回落到
+//! 阈值的 `recovery_ratio` 倍以下才恢复，避免成功率在阈值附近波动时反复告警。
+//!
+//! 消耗速率 = 错误率 / (1 - 目标成功率)。速率为 1 表示恰好按 SLO 允许的速度消耗错误预算，
+//! 即成功率正好等于目标值。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// 计数桶的时长（秒）
+const BUCKET_SECS: i64 = 10;
+
+/// 成功率 SLO 配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SloConfig {
+    /// 是否启用
+    #[serde(default = "default_slo_enabled")]
+    pub enabled: bool,
+    /// 目标成功率（如 0.99）
+    #[serde(default = "default_target")]
+    pub target: f64,
+    /// 短窗口（秒）
+    #[serde(default = "default_short_window_secs")]
+    pub short_window_secs: u64,
+    /// 长窗口（秒）
+    #[serde(default = "default_long_window_secs")]
+    pub long_window_secs: u64,
+    /// 告警的消耗速率阈值（两个窗口都达到时告警）
+    #[serde(default = "default_burn_rate_threshold")]
+    pub burn_rate_threshold: f64,
+    /// 恢复比例：短窗口消耗速率低于 `burn_rate_threshold * recovery_ratio` 时恢复
+    #[serde(default = "default_recovery_ratio")]
+    pub recovery_ratio: f64,
+    /// 短窗口内至少有多少请求才会告警
+    #[serde(default = "default_min_requests")]
+    pub min_requests: u32,
+}
+
+fn default_slo_enabled() -> bool {
+    true
+}
+
+fn default_target() -> f64 {
+    0.99
+}
+
+fn default_short_window_secs() -> u64 {
+    300
+}
+
+fn default_long_window_secs() -> u64 {
+    3600
+}
+
+fn default_burn_rate_threshold() -> f64 {
+    1.0
+}
+
+fn default_recovery_ratio() -> f64 {
+    0.5
+}
+
+fn default_min_requests() -> u32 {
+    20
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_slo_enabled(),
+            target: default_target(),
+            short_window_secs: default_short_window_secs(),
+            long_window_secs: default_long_window_secs(),
+            burn_rate_threshold: default_burn_rate_threshold(),
+            recovery_ratio: default_recovery_ratio(),
+            min_requests: default_min_requests(),
+        }
+    }
+}
+
+impl SloConfig {
+    /// 错误预算（允许的错误率）
+    fn error_budget(&self) -> f64 {
+        (1.0 - self.target).max(1e-6)
+    }
+}
+
+/// 单个窗口的统计
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SloWindowStats {
+    /// 窗口长度（秒）
+    pub window_secs: u64,
+    /// 请求总数
+    pub total: u32,
+    /// 失败数
+    pub failures: u32,
+    /// 成功率（窗口内没有请求时为空）
+    pub success_rate: Option<f64>,
+    /// 错误预算消耗速率（窗口内没有请求时为空）
+    pub burn_rate: Option<f64>,
+}
+
+/// 一个 Provider 或 Provider + 模型的 SLO 状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SloStatus {
+    /// Provider
+    pub provider: String,
+    /// 模型（为空时为 Provider 汇总）
+    pub model: Option<String>,
+    /// 目标成功率
+    pub target: f64,
+    /// 短窗口统计
+    pub short_window: SloWindowStats,
+    /// 长窗口统计
+    pub long_window: SloWindowStats,
+    /// 是否处于告警状态
+    pub alerting: bool,
+    /// 告警开始时间
+    pub alerting_since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    /// 桶序号（时间戳 / BUCKET_SECS）
+    index: i64,
+    successes: u32,
+    failures: u32,
+}
+
+#[derive(Debug, Default)]
+struct SloSeries {
+    buckets: VecDeque<Bucket>,
+    alerting_since: Option<DateTime<Utc>>,
+}
+
+impl SloSeries {
+    fn record(&mut self, success: bool, now: DateTime<Utc>, retain_secs: u64) {
+        let index = now.timestamp().div_euclid(BUCKET_SECS);
+        match self.buckets.back_mut() {
+            Some(bucket) if bucket.index == index => {}
+            _ => self.buckets.push_back(Bucket {
+                index,
+                successes: 0,
+                failures: 0,
+            }),
+        }
+        if let Some(bucket) = self.buckets.back_mut() {
+            if success {
+                bucket.successes += 1;
+            } else {
+                bucket.failures += 1;
+            }
+        }
+        self.prune(now, retain_secs);
+    }
+
+    fn prune(&mut self, now: DateTime<Utc>, retain_secs: u64) {
+        let oldest = first_bucket_in_window(now, retain_secs);
+        while self.buckets.front().is_some_and(|b| b.index < oldest) {
+            self.buckets.pop_front();
+        }
+    }
+
+    fn window(&self, config: &SloConfig, now: DateTime<Utc>, window_secs: u64) -> SloWindowStats {
+        let oldest = first_bucket_in_window(now, window_secs);
+        let (successes, failures) = self
+            .buckets
+            .iter()
+            .filter(|b| b.index >= oldest)
+            .fold((0u32, 0u32), |(s, f), b| (s + b.successes, f + b.failures));
+        let total = successes + failures;
+        let error_rate = (total > 0).then(|| failures as f64 / total as f64);
+        SloWindowStats {
+            window_secs,
+            total,
+            failures,
+            success_rate: error_rate.map(|rate| 1.0 - rate),
+            burn_rate: error_rate.map(|rate| rate / config.error_budget()),
+        }
+    }
+}
+
+/// 窗口内最早的桶序号
+fn first_bucket_in_window(now: DateTime<Utc>, window_secs: u64) -> i64 {
+    let window = window_secs.max(1).div_ceil(BUCKET_SECS as u64) as i64;
+    now.timestamp().div_euclid(BUCKET_SECS) - window + 1
+}
+
+/// 成功率 SLO 跟踪器
+#[derive(Debug, Default)]
+pub struct SloTracker {
+    series: HashMap<(String, Option<String>), SloSeries>,
+}
+
+impl SloTracker {
+    /// 创建跟踪器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次请求结果，返回告警状态发生变化的 SLO（进入告警或恢复）
+    ///
+    /// 同时计入 Provider 汇总和 Provider + 模型两个维度。
+    pub fn record(
+        &mut self,
+        config: &SloConfig,
+        provider: &str,
+        model: &str,
+        success: bool,
+        now: DateTime<Utc>,
+    ) -> Vec<SloStatus> {
+        let retain_secs = config.short_window_secs.max(config.long_window_secs);
+        let keys = [
+            (provider.to_string(), None),
+            (provider.to_string(), Some(model.to_string())),
+        ];
+
+        let mut changed = Vec::new();
+        for key in keys {
+            let series = self.series.entry(key.clone()).or_default();
+            series.record(success, now, retain_secs);
+
+            let short = series.window(config, now, config.short_window_secs);
+            let long = series.window(config, now, config.long_window_secs);
+            let short_burn = short.burn_rate.unwrap_or(0.0);
+            let long_burn = long.burn_rate.unwrap_or(0.0);
+
+            let transition = match series.alerting_since {
+                None => {
+                    let breached = short.total >= config.min_requests.max(1)
+                        && short_burn >= config.burn_rate_threshold
+                        && long_burn >= config.burn_rate_threshold;
+                    if breached {
+                        series.alerting_since = Some(now);
+                    }
+                    breached
+                }
+                Some(_) => {
+                    let recovered = short_burn < config.burn_rate_threshold * config.recovery_ratio;
+                    if recovered {
+                        series.alerting_since = None;
+                    }
+                    recovered
+                }
+            };
+
+            if transition {
+                changed.push(SloStatus {
+                    provider: key.0,
+                    model: key.1,
+                    target: config.target,
+                    short_window: short,
+                    long_window: long,
+                    alerting: series.alerting_since.is_some(),
+                    alerting_since: series.alerting_since,
+                });
+            }
+        }
+        changed
+    }
+
+    /// 当前所有 SLO 状态（长窗口内没有请求的维度会被移除）
+    pub fn status(&mut self, config: &SloConfig, now: DateTime<Utc>) -> Vec<SloStatus> {
+        let retain_secs = config.short_window_secs.max(config.long_window_secs);
+        self.series.retain(|_, series| {
+            series.prune(now, retain_secs);
+            !series.buckets.is_empty() || series.alerting_since.is_some()
+        });
+
+        let mut statuses: Vec<SloStatus> = self
+            .series
+            .iter()
+            .map(|((provider, model), series)| SloStatus {
+                provider: provider.clone(),
+                model: model.clone(),
+                target: config.target,
+                short_window: series.window(config, now, config.short_window_secs),
+                long_window: series.window(config, now, config.long_window_secs),
+                alerting: series.alerting_since.is_some(),
+                alerting_since: series.alerting_since,
+            })
+            .collect();
+        statuses.sort_by(|a, b| (&a.provider, &a.model).cmp(&(&b.provider, &b.model)));
+        statuses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn config() -> SloConfig {
+        SloConfig {
+            min_requests: 10,
+            ..SloConfig::default()
+        }
+    }
+
+    fn record_many(
+        tracker: &mut SloTracker,
+        config: &SloConfig,
+        model: &str,
+        success: bool,
+        count: usize,
+        now: DateTime<Utc>,
+    ) -> Vec<SloStatus> {
+        (0..count)
+            .flat_map(|_| tracker.record(config, "Claude", model, success, now))
+            .collect()
+    }
+
+    #[test]
+    fn test_breach_and_recover_with_hysteresis() {
+        let config = config();
+        let mut tracker = SloTracker::new();
+        let start = Utc::now();
+
+        // 样本不足时不告警
+        assert!(record_many(&mut tracker, &config, "claude-3", false, 5, start).is_empty());
+
+        // 第 10 个请求达到最小样本数，两个窗口的消耗速率都远超阈值
+        let changed = record_many(&mut tracker, &config, "claude-3", false, 5, start);
+        assert_eq!(changed.len(), 2);
+        assert!(changed.iter().all(|s| s.alerting));
+        assert_eq!(changed[0].model, None);
+        assert_eq!(changed[1].model.as_deref(), Some("claude-3"));
+
+        // 成功率回升后消耗速率低于告警阈值但高于恢复线，仍保持告警且不重复报告
+        let changed = record_many(&mut tracker, &config, "claude-3", true, 1490, start);
+        assert!(changed.is_empty());
+        let status = tracker.status(&config, start);
+        assert!(status.iter().all(|s| s.alerting));
+        assert!(status[0].short_window.burn_rate.unwrap() < config.burn_rate_threshold);
+        let changed = record_many(&mut tracker, &config, "claude-3", true, 400, start);
+        assert!(changed.is_empty());
+
+        // 消耗速率低于恢复线后恢复
+        let changed = record_many(&mut tracker, &config, "claude-3", true, 200, start);
+        assert_eq!(changed.len(), 2);
+        assert!(changed.iter().all(|s| !s.alerting));
+
+        let status = tracker.status(&config, start);
+        assert_eq!(status.len(), 2);
+        assert_eq!(status[1].short_window.total, 2100);
+        assert_eq!(status[1].short_window.failures, 10);
+        assert_eq!(status[1].long_window.total, 2100);
+    }
+
+    #[test]
+    fn test_windows_expire() {
+        let config = config();
+        let mut tracker = SloTracker::new();
+        let start = Utc::now();
+        record_many(&mut tracker, &config, "claude-3", true, 10, start);
+
+        let status = tracker.status(&config, start + Duration::seconds(600));
+        assert_eq!(status[0].short_window.total, 0);
+        assert_eq!(status[0].short_window.success_rate, None);
+        assert_eq!(status[0].long_window.success_rate, Some(1.0));
+
+        assert!(tracker
+            .status(&config, start + Duration::seconds(7200))
+            .is_empty());
+    }
+}
@@ -0,0 +1,396 @@
+//! 机器可读的统计输出
+//!
+//! 将 `FlowStats` 和 `EnhancedStats` 合并为稳定的 JSON 结构，供 CLI 和脚本使用。
+//! 字段名带有明确的单位后缀（`_ms`、`_tokens`、`_ratio`、`_per_second`），
+//! 分布数据单独标注 `unit`。结构发生不兼容变化时递增 `STATS_SCHEMA_VERSION`。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::enhanced_stats::{Distribution, EnhancedStats};
+use super::query_service::{FlowStats, ModelStats, ProviderStats};
+
+/// 统计输出的 JSON 结构版本
+pub const STATS_SCHEMA_VERSION: u32 = 1;
+
+/// 统计输出格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsOutputFormat {
+    /// 人类可读的文本
+    #[default]
+    Text,
+    /// 稳定结构的 JSON
+    Json,
+}
+
+/// 统计汇总
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatsSummary {
+    pub requests_total: u64,
+    pub requests_successful: u64,
+    pub requests_failed: u64,
+    /// 成功率（0-1）
+    pub success_ratio: f64,
+    pub latency_avg_ms: f64,
+    pub latency_min_ms: u64,
+    pub latency_max_ms: u64,
+    pub pipeline_avg_ms: f64,
+    pub ttfb_avg_ms: f64,
+    pub generation_avg_ms: f64,
+    pub input_tokens_total: u64,
+    pub output_tokens_total: u64,
+    pub input_tokens_avg: f64,
+    pub output_tokens_avg: f64,
+    pub request_rate_per_second: f64,
+}
+
+/// 按提供商或模型分组的统计
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GroupStats {
+    /// 提供商或模型名称
+    pub name: String,
+    pub requests_total: u64,
+    /// 成功率（0-1）
+    pub success_ratio: f64,
+    pub latency_avg_ms: f64,
+}
+
+impl From<&ProviderStats> for GroupStats {
+    fn from(stats: &ProviderStats) -> Self {
+        Self {
+            name: stats.provider.clone(),
+            requests_total: stats.count as u64,
+            success_ratio: stats.success_rate,
+            latency_avg_ms: stats.avg_latency_ms,
+        }
+    }
+}
+
+impl From<&ModelStats> for GroupStats {
+    fn from(stats: &ModelStats) -> Self {
+        Self {
+            name: stats.model.clone(),
+            requests_total: stats.count as u64,
+            success_ratio: stats.success_rate,
+            latency_avg_ms: stats.avg_latency_ms,
+        }
+    }
+}
+
+/// 分布桶
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatsBucket {
+    pub label: String,
+    pub value: u64,
+}
+
+/// 带单位的分布
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatsDistribution {
+    /// `value` 和 `total` 的单位（`requests` 或 `tokens`）
+    pub unit: String,
+    pub total: u64,
+    pub buckets: Vec<StatsBucket>,
+}
+
+impl StatsDistribution {
+    fn new(unit: &str, total: u64, buckets: impl IntoIterator<Item = (String, u64)>) -> Self {
+        Self {
+            unit: unit.to_string(),
+            total,
+            buckets: buckets
+                .into_iter()
+                .map(|(label, value)| StatsBucket { label, value })
+                .collect(),
+        }
+    }
+
+    fn from_distribution(unit: &str, distribution: &Distribution) -> Self {
+        Self::new(
+            unit,
+            distribution.total,
+            distribution.buckets.iter().cloned(),
+        )
+    }
+}
+
+/// 请求趋势数据点
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrendPoint {
+    pub timestamp: DateTime<Utc>,
+    pub requests: u64,
+}
+
+/// 请求趋势
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RequestTrend {
+    /// 时间间隔（如 `1h`、`5m`）
+    pub interval: String,
+    pub points: Vec<TrendPoint>,
+}
+
+/// 提示词缓存统计
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CacheStats {
+    pub flows_with_cache_usage: u64,
+    pub cache_hit_flows: u64,
+    pub input_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_write_tokens: u64,
+    /// 缓存命中率（0-1）
+    pub hit_ratio: f64,
+    pub saved_input_tokens: f64,
+}
+
+/// 统计报告时间范围
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatsReportTimeRange {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// 机器可读的统计报告
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatsReport {
+    pub schema_version: u32,
+    pub generated_at: DateTime<Utc>,
+    pub time_range: StatsReportTimeRange,
+    pub summary: StatsSummary,
+    pub by_provider: Vec<GroupStats>,
+    pub by_model: Vec<GroupStats>,
+    pub by_state: StatsDistribution,
+    pub request_trend: RequestTrend,
+    pub tokens_by_model: StatsDistribution,
+    /// 延迟直方图（桶标签为毫秒区间）
+    pub latency_histogram: StatsDistribution,
+    pub errors: StatsDistribution,
+    pub stop_reasons: StatsDistribution,
+    pub cache: CacheStats,
+}
+
+impl StatsReport {
+    /// 合并基础统计和增强统计
+    pub fn new(stats: &FlowStats, enhanced: &EnhancedStats) -> Self {
+        let cache = &enhanced.cache_effectiveness;
+        Self {
+            schema_version: STATS_SCHEMA_VERSION,
+            generated_at: Utc::now(),
+            time_range: StatsReportTimeRange {
+                start: enhanced.time_range.start,
+                end: enhanced.time_range.end,
+            },
+            summary: StatsSummary {
+                requests_total: stats.total_requests as u64,
+                requests_successful: stats.successful_requests as u64,
+                requests_failed: stats.failed_requests as u64,
+                success_ratio: stats.success_rate,
+                latency_avg_ms: stats.avg_latency_ms,
+                latency_min_ms: stats.min_latency_ms,
+                latency_max_ms: stats.max_latency_ms,
+                pipeline_avg_ms: stats.avg_pipeline_ms,
+                ttfb_avg_ms: stats.avg_ttfb_ms,
+                generation_avg_ms: stats.avg_generation_ms,
+                input_tokens_total: stats.total_input_tokens,
+                output_tokens_total: stats.total_output_tokens,
+                input_tokens_avg: stats.avg_input_tokens,
+                output_tokens_avg: stats.avg_output_tokens,
+                request_rate_per_second: enhanced.request_rate,
+            },
+            by_provider: stats.by_provider.iter().map(GroupStats::from).collect(),
+            by_model: stats.by_model.iter().map(GroupStats::from).collect(),
+            by_state: StatsDistribution::new(
+                "requests",
+                stats.total_requests as u64,
+                stats
+                    .by_state
+                    .iter()
+                    .map(|s| (s.state.clone(), s.count as u64)),
+            ),
+            request_trend: RequestTrend {
+                interval: enhanced.request_trend.interval.clone(),
+                points: enhanced
+                    .request_trend
+                    .points
+                    .iter()
+                    .map(|p| TrendPoint {
+                        timestamp: p.timestamp,
+                        requests: p.value as u64,
+                    })
+                    .collect(),
+            },
+            tokens_by_model: StatsDistribution::from_distribution(
+                "tokens",
+                &enhanced.token_by_model,
+            ),
+            latency_histogram: StatsDistribution::from_distribution(
+                "requests",
+                &enhanced.latency_histogram,
+            ),
+            errors: StatsDistribution::from_distribution("requests", &enhanced.error_distribution),
+            stop_reasons: StatsDistribution::from_distribution(
+                "requests",
+                &enhanced.stop_reason_distribution,
+            ),
+            cache: CacheStats {
+                flows_with_cache_usage: cache.flows_with_cache_usage,
+                cache_hit_flows: cache.cache_hit_flows,
+                input_tokens: cache.input_tokens,
+                cache_read_tokens: cache.cache_read_tokens,
+                cache_write_tokens: cache.cache_write_tokens,
+                hit_ratio: cache.hit_ratio,
+                saved_input_tokens: cache.saved_input_tokens,
+            },
+        }
+    }
+
+    /// 按指定格式输出
+    pub fn render(&self, format: StatsOutputFormat) -> String {
+        match format {
+            StatsOutputFormat::Json => {
+                serde_json::to_string_pretty(self).unwrap_or_else(|_| "{}".to_string())
+            }
+            StatsOutputFormat::Text => self.render_text(),
+        }
+    }
+
+    /// 输出为人类可读的文本
+    fn render_text(&self) -> String {
+        let s = &self.summary;
+        let mut text = format!(
+            "时间范围: {} ~ {}\n",
+            self.time_range.start.to_rfc3339(),
+            self.time_range.end.to_rfc3339()
+        );
+        text.push_str(&format!(
+            "请求: {} (成功 {} / 失败 {}, 成功率 {:.1}%)\n",
+            s.requests_total,
+            s.requests_successful,
+            s.requests_failed,
+            s.success_ratio * 100.0
+        ));
+        text.push_str(&format!(
+            "延迟: 平均 {:.1}ms, 最小 {}ms, 最大 {}ms\n",
+            s.latency_avg_ms, s.latency_min_ms, s.latency_max_ms
+        ));
+        text.push_str(&format!(
+            "Token: 输入 {} (平均 {:.1}), 输出 {} (平均 {:.1})\n",
+            s.input_tokens_total, s.input_tokens_avg, s.output_tokens_total, s.output_tokens_avg
+        ));
+        text.push_str(&format!("请求速率: {:.4}/s\n", s.request_rate_per_second));
+        text.push_str(&format!(
+            "缓存命中率: {:.1}%\n",
+            self.cache.hit_ratio * 100.0
+        ));
+        if !self.by_provider.is_empty() {
+            text.push_str("\n按提供商:\n");
+            for group in &self.by_provider {
+                text.push_str(&format!(
+                    "  {:<20} {:>6} 请求  成功率 {:>5.1}%  平均延迟 {:.1}ms\n",
+                    group.name,
+                    group.requests_total,
+                    group.success_ratio * 100.0,
+                    group.latency_avg_ms
+                ));
+            }
+        }
+        text
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::enhanced_stats::{CacheEffectiveness, StatsTimeRange};
+    use crate::flow_monitor::query_service::StateStats;
+
+    fn create_test_stats() -> (FlowStats, EnhancedStats) {
+        let stats = FlowStats {
+            total_requests: 4,
+            successful_requests: 3,
+            failed_requests: 1,
+            success_rate: 0.75,
+            avg_latency_ms: 250.0,
+            min_latency_ms: 100,
+            max_latency_ms: 500,
+            total_input_tokens: 400,
+            total_output_tokens: 200,
+            avg_input_tokens: 100.0,
+            avg_output_tokens: 50.0,
+            by_provider: vec![ProviderStats {
+                provider: "OpenAI".to_string(),
+                count: 4,
+                success_rate: 0.75,
+                avg_latency_ms: 250.0,
+            }],
+            by_state: vec![
+                StateStats {
+                    state: "Completed".to_string(),
+                    count: 3,
+                },
+                StateStats {
+                    state: "Failed".to_string(),
+                    count: 1,
+                },
+            ],
+            ..Default::default()
+        };
+        let enhanced = EnhancedStats {
+            token_by_model: Distribution {
+                buckets: vec![("gpt-4o".to_string(), 600)],
+                total: 600,
+            },
+            cache_effectiveness: CacheEffectiveness {
+                hit_ratio: 0.5,
+                ..Default::default()
+            },
+            request_rate: 0.25,
+            time_range: StatsTimeRange::default(),
+            ..Default::default()
+        };
+        (stats, enhanced)
+    }
+
+    #[test]
+    fn test_json_output_uses_unit_suffixed_fields() {
+        let (stats, enhanced) = create_test_stats();
+        let output = StatsReport::new(&stats, &enhanced).render(StatsOutputFormat::Json);
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+        assert_eq!(json["schema_version"], STATS_SCHEMA_VERSION);
+        assert_eq!(json["summary"]["requests_total"], 4);
+        assert_eq!(json["summary"]["success_ratio"], 0.75);
+        assert_eq!(json["summary"]["latency_max_ms"], 500);
+        assert_eq!(json["summary"]["request_rate_per_second"], 0.25);
+        assert_eq!(json["by_provider"][0]["name"], "OpenAI");
+        assert_eq!(json["by_state"]["unit"], "requests");
+        assert_eq!(json["by_state"]["buckets"][1]["label"], "Failed");
+        assert_eq!(json["tokens_by_model"]["unit"], "tokens");
+        assert_eq!(json["tokens_by_model"]["buckets"][0]["value"], 600);
+        assert_eq!(json["cache"]["hit_ratio"], 0.5);
+
+        let parsed: StatsReport = serde_json::from_str(&output).unwrap();
+        assert_eq!(parsed.summary.requests_failed, 1);
+    }
+
+    #[test]
+    fn test_text_output() {
+        let (stats, enhanced) = create_test_stats();
+        let output = StatsReport::new(&stats, &enhanced).render(StatsOutputFormat::Text);
+
+        assert!(output.contains("请求: 4 (成功 3 / 失败 1, 成功率 75.0%)"));
+        assert!(output.contains("缓存命中率: 50.0%"));
+        assert!(output.contains("OpenAI"));
+    }
+
+    #[test]
+    fn test_output_format_serde() {
+        let format: StatsOutputFormat = serde_json::from_str("\"json\"").unwrap();
+        assert_eq!(format, StatsOutputFormat::Json);
+        assert_eq!(StatsOutputFormat::default(), StatsOutputFormat::Text);
+    }
+}
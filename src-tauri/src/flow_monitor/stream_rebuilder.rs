@@ -8,9 +8,11 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use thiserror::Error;
 
+use crate::streaming::PartialJsonAccumulator;
+
 use super::models::{
-    LLMResponse, StopReason, StreamChunk, StreamInfo, ThinkingContent, TokenUsage, ToolCall,
-    ToolCallDelta,
+    LLMResponse, Message, MessageContent, MessageRole, ResponseLogprobs, StopReason, StreamChunk,
+    StreamInfo, ThinkingContent, TokenUsage, ToolCall, ToolCallDelta, ToolResult, UsageSource,
 };
 
 // ============================================================================
@@ -69,8 +71,8 @@ struct ToolCallBuilder {
     tool_type: String,
     /// 函数名称
     function_name: Option<String>,
-    /// 函数参数（累积的 JSON 字符串）
-    arguments: String,
+    /// 函数参数（累积的 JSON 片段）
+    arguments: PartialJsonAccumulator,
 }
 
 impl ToolCallBuilder {
@@ -79,25 +81,76 @@ impl ToolCallBuilder {
             id: None,
             tool_type: "function".to_string(),
             function_name: None,
-            arguments: String::new(),
+            arguments: PartialJsonAccumulator::new(),
         }
     }
 
     fn build(self) -> Option<ToolCall> {
         let id = self.id?;
         let name = self.function_name?;
+        let arguments = self.arguments.finish();
 
         Some(ToolCall {
             id,
             tool_type: self.tool_type,
+            arguments_truncated: arguments.is_truncated(),
             function: super::models::FunctionCall {
                 name,
-                arguments: self.arguments,
+                arguments: arguments.into_string(),
             },
         })
     }
 }
 
+// ============================================================================
+// 工具往返轮次
+// ============================================================================
+
+/// 按出现顺序记录的流片段，用于重建单个 Flow 内的工具往返轮次
+///
+/// 轮次划分规则：每个工具结果结束当前助手轮次并单独成为一条工具消息，
+/// 其后的文本或工具调用开始新的助手轮次。
+#[derive(Debug, Clone)]
+enum TurnSegment {
+    /// 助手文本
+    Text(String),
+    /// 工具调用（工具调用构建器的键）
+    ToolCall(u32),
+    /// 内联工具结果
+    ToolResult(ToolResult),
+}
+
+/// 将累积的助手文本和工具调用作为一个助手轮次写入
+fn push_assistant_turn(turns: &mut Vec<Message>, text: &mut String, calls: &mut Vec<ToolCall>) {
+    if text.is_empty() && calls.is_empty() {
+        return;
+    }
+    turns.push(Message {
+        role: MessageRole::Assistant,
+        content: MessageContent::Text(std::mem::take(text)),
+        tool_calls: (!calls.is_empty()).then(|| std::mem::take(calls)),
+        tool_result: None,
+        name: None,
+    });
+}
+
+/// 提取 Anthropic 工具结果块的文本
+///
+/// `content` 可以是字符串、文本块数组或结构化结果（如搜索结果列表，序列化为 JSON）。
+fn anthropic_tool_result_text(content: &serde_json::Value) -> String {
+    let is_text_block =
+        |block: &serde_json::Value| block.get("type").and_then(|v| v.as_str()) == Some("text");
+    match content {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(blocks) if blocks.iter().all(is_text_block) => blocks
+            .iter()
+            .filter_map(|block| block.get("text").and_then(|v| v.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        other => other.to_string(),
+    }
+}
+
 // ============================================================================
 // 流重建器
 // ============================================================================
@@ -124,20 +177,36 @@ pub struct StreamRebuilder {
     format: StreamFormat,
     /// chunk 计数器
     chunk_index: u32,
-    /// 停止原因
-    stop_reason: Option<StopReason>,
+    /// 上游返回的原始停止原因
+    raw_stop_reason: Option<String>,
+    /// Token 级对数概率（OpenAI 格式，请求了 `logprobs` 时才有）
+    logprobs: Option<ResponseLogprobs>,
     /// Token 使用量
     usage: TokenUsage,
+    /// 上游是否在流中返回了输出 Token 用量
+    upstream_usage: bool,
     /// 响应 ID
     response_id: Option<String>,
+    /// 上游后端配置指纹（OpenAI `system_fingerprint`）
+    system_fingerprint: Option<String>,
     /// 模型名称
     model: Option<String>,
     /// 是否保存原始 chunks
     save_raw_chunks: bool,
+    /// 结束时是否合并连续的同类增量 chunks
+    compact_chunks: bool,
     /// 当前内容块索引（Anthropic 格式）
     current_content_block_index: Option<u32>,
     /// 当前内容块类型（Anthropic 格式）
     current_content_block_type: Option<String>,
+    /// 按出现顺序记录的文本、工具调用和工具结果
+    segments: Vec<TurnSegment>,
+    /// 工具调用构建器键的偏移（OpenAI 格式每个助手轮次的工具调用索引都从 0 开始）
+    tool_index_offset: u32,
+    /// 当前是否处于注入的工具结果中（OpenAI 格式）
+    in_tool_result: bool,
+    /// 是否收到终止事件（OpenAI `[DONE]`、Anthropic `message_stop`）
+    terminated: bool,
 }
 
 impl StreamRebuilder {
@@ -152,13 +221,21 @@ impl StreamRebuilder {
             last_chunk_time: None,
             format,
             chunk_index: 0,
-            stop_reason: None,
+            raw_stop_reason: None,
+            logprobs: None,
             usage: TokenUsage::default(),
+            upstream_usage: false,
             response_id: None,
+            system_fingerprint: None,
             model: None,
             save_raw_chunks: false,
+            compact_chunks: false,
             current_content_block_index: None,
             current_content_block_type: None,
+            segments: Vec::new(),
+            tool_index_offset: 0,
+            in_tool_result: false,
+            terminated: false,
         }
     }
 
@@ -168,6 +245,12 @@ impl StreamRebuilder {
         self
     }
 
+    /// 设置结束时是否合并保存的 chunks（见 [`compact_chunks`]）
+    pub fn with_compact_chunks(mut self, compact: bool) -> Self {
+        self.compact_chunks = compact;
+        self
+    }
+
     /// 处理 SSE 事件
     ///
     /// # 参数
@@ -217,6 +300,9 @@ impl StreamRebuilder {
                         self.format = StreamFormat::OpenAI;
                         self.process_openai_chunk(data, &mut chunk)
                     }
+                } else if is_gemini_chunk(data) {
+                    self.format = StreamFormat::Gemini;
+                    self.process_gemini_chunk(data, &mut chunk)
                 } else {
                     // 尝试 OpenAI 格式
                     self.format = StreamFormat::OpenAI;
@@ -251,6 +337,7 @@ impl StreamRebuilder {
 
         // 处理 [DONE] 终止信号
         if data == "[DONE]" {
+            self.terminated = true;
             return Ok(());
         }
 
@@ -270,29 +357,61 @@ impl StreamRebuilder {
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string());
         }
+        if self.system_fingerprint.is_none() {
+            self.system_fingerprint = json
+                .get("system_fingerprint")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string());
+        }
 
-        // 处理 choices
+        // 处理 choices（只重建第一个候选，与非流式响应取 `choices[0]` 一致；
+        // `n > 1` 时其他候选的增量不混入内容）
         if let Some(choices) = json.get("choices").and_then(|v| v.as_array()) {
-            for choice in choices {
+            for choice in choices
+                .iter()
+                .filter(|c| c.get("index").and_then(|v| v.as_u64()).unwrap_or(0) == 0)
+            {
                 // 处理 delta
                 if let Some(delta) = choice.get("delta") {
-                    // 处理内容增量
-                    if let Some(content) = delta.get("content").and_then(|v| v.as_str()) {
-                        self.content_buffer.push_str(content);
-                        chunk.content_delta = Some(content.to_string());
+                    // 带 role 的 delta 开始新消息：tool 角色为注入的工具结果，
+                    // 之后不带 role 的 delta 都属于该结果，直到出现 assistant 角色
+                    match delta.get("role").and_then(|v| v.as_str()) {
+                        Some("tool") => self.in_tool_result = true,
+                        Some(_) => self.in_tool_result = false,
+                        None => {}
                     }
 
-                    // 处理工具调用增量
-                    if let Some(tool_calls) = delta.get("tool_calls").and_then(|v| v.as_array()) {
-                        for tc in tool_calls {
-                            self.process_openai_tool_call_delta(tc, chunk)?;
+                    if self.in_tool_result {
+                        self.process_openai_tool_result_delta(delta);
+                    } else {
+                        // 处理内容增量
+                        if let Some(content) = delta.get("content").and_then(|v| v.as_str()) {
+                            self.content_buffer.push_str(content);
+                            self.record_text(content);
+                            chunk.content_delta = Some(content.to_string());
+                        }
+
+                        // 处理工具调用增量
+                        if let Some(tool_calls) = delta.get("tool_calls").and_then(|v| v.as_array())
+                        {
+                            for tc in tool_calls {
+                                self.process_openai_tool_call_delta(tc, chunk)?;
+                            }
                         }
                     }
                 }
 
+                // 处理对数概率（每个 chunk 携带对应增量的部分）
+                if let Some(logprobs) = choice.get("logprobs").filter(|v| !v.is_null()) {
+                    self.logprobs
+                        .get_or_insert_with(ResponseLogprobs::default)
+                        .extend(logprobs);
+                }
+
                 // 处理 finish_reason
                 if let Some(finish_reason) = choice.get("finish_reason").and_then(|v| v.as_str()) {
-                    self.stop_reason = Some(Self::parse_openai_stop_reason(finish_reason));
+                    self.raw_stop_reason = Some(finish_reason.to_string());
                 }
             }
         }
@@ -313,10 +432,7 @@ impl StreamRebuilder {
     ) -> Result<(), StreamRebuilderError> {
         let index = tc.get("index").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
 
-        let builder = self
-            .tool_calls_buffer
-            .entry(index)
-            .or_insert_with(ToolCallBuilder::new);
+        let builder = self.tool_call_builder(self.tool_index_offset + index);
 
         // 提取 ID
         if let Some(id) = tc.get("id").and_then(|v| v.as_str()) {
@@ -329,7 +445,7 @@ impl StreamRebuilder {
                 builder.function_name = Some(name.to_string());
             }
             if let Some(args) = function.get("arguments").and_then(|v| v.as_str()) {
-                builder.arguments.push_str(args);
+                builder.arguments.append(args);
 
                 // 记录增量
                 chunk.tool_call_delta = Some(ToolCallDelta {
@@ -344,16 +460,106 @@ impl StreamRebuilder {
         Ok(())
     }
 
-    /// 解析 OpenAI 停止原因
-    fn parse_openai_stop_reason(reason: &str) -> StopReason {
-        match reason {
-            "stop" => StopReason::Stop,
-            "length" => StopReason::Length,
-            "tool_calls" => StopReason::ToolCalls,
-            "content_filter" => StopReason::ContentFilter,
-            "function_call" => StopReason::FunctionCall,
-            other => StopReason::Other(other.to_string()),
+    /// 处理 OpenAI 格式中注入的工具结果增量
+    ///
+    /// 流式工具协议下框架执行工具后以 tool 角色的 delta 将结果写回流中：
+    /// `{"role":"tool","tool_call_id":"call_1","content":"..."}`
+    fn process_openai_tool_result_delta(&mut self, delta: &serde_json::Value) {
+        let tool_call_id = delta.get("tool_call_id").and_then(|v| v.as_str());
+        let content = delta
+            .get("content")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let is_error = delta
+            .get("is_error")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        self.record_tool_result(tool_call_id, content, is_error);
+    }
+
+    /// 记录助手文本（与上一个文本片段合并）
+    fn record_text(&mut self, text: &str) {
+        if let Some(TurnSegment::Text(last)) = self.segments.last_mut() {
+            last.push_str(text);
+        } else {
+            self.segments.push(TurnSegment::Text(text.to_string()));
+        }
+    }
+
+    /// 获取工具调用构建器，首次出现时记录工具调用片段
+    fn tool_call_builder(&mut self, key: u32) -> &mut ToolCallBuilder {
+        if !self.tool_calls_buffer.contains_key(&key) {
+            self.segments.push(TurnSegment::ToolCall(key));
+        }
+        self.tool_calls_buffer
+            .entry(key)
+            .or_insert_with(ToolCallBuilder::new)
+    }
+
+    /// 记录内联工具结果
+    ///
+    /// 紧跟在同一工具结果之后且未指定其他工具调用 ID 的增量合并到该结果中。
+    fn record_tool_result(&mut self, tool_call_id: Option<&str>, content: &str, is_error: bool) {
+        if let Some(TurnSegment::ToolResult(last)) = self.segments.last_mut() {
+            if tool_call_id.is_none_or(|id| id == last.tool_call_id) {
+                last.content.push_str(content);
+                last.is_error |= is_error;
+                return;
+            }
+        }
+
+        self.segments.push(TurnSegment::ToolResult(ToolResult {
+            tool_call_id: tool_call_id.unwrap_or_default().to_string(),
+            content: content.to_string(),
+            is_error,
+        }));
+        // 结果之后的工具调用属于新的助手轮次，其索引不能与之前的工具调用冲突
+        self.tool_index_offset = self.tool_calls_buffer.keys().max().map_or(0, |k| k + 1);
+    }
+
+    /// 按记录的片段重建工具往返轮次
+    ///
+    /// 流中没有内联工具结果时返回空列表（普通响应的内容和工具调用已在顶层字段中）。
+    fn build_turns(&self) -> Vec<Message> {
+        if !self
+            .segments
+            .iter()
+            .any(|segment| matches!(segment, TurnSegment::ToolResult(_)))
+        {
+            return Vec::new();
+        }
+
+        let mut turns = Vec::new();
+        let mut text = String::new();
+        let mut calls = Vec::new();
+        for segment in &self.segments {
+            match segment {
+                TurnSegment::Text(delta) => text.push_str(delta),
+                TurnSegment::ToolCall(key) => calls.extend(
+                    self.tool_calls_buffer
+                        .get(key)
+                        .cloned()
+                        .and_then(ToolCallBuilder::build),
+                ),
+                TurnSegment::ToolResult(result) => {
+                    push_assistant_turn(&mut turns, &mut text, &mut calls);
+                    let name = self
+                        .tool_calls_buffer
+                        .values()
+                        .find(|builder| builder.id.as_deref() == Some(&result.tool_call_id))
+                        .and_then(|builder| builder.function_name.clone());
+                    turns.push(Message {
+                        role: MessageRole::Tool,
+                        content: MessageContent::Text(result.content.clone()),
+                        tool_calls: None,
+                        tool_result: Some(result.clone()),
+                        name,
+                    });
+                }
+            }
         }
+        push_assistant_turn(&mut turns, &mut text, &mut calls);
+        turns
     }
 
     /// 解析 OpenAI usage
@@ -363,10 +569,12 @@ impl StreamRebuilder {
         }
         if let Some(completion_tokens) = usage.get("completion_tokens").and_then(|v| v.as_u64()) {
             self.usage.output_tokens = completion_tokens as u32;
+            self.upstream_usage = true;
         }
         if let Some(total_tokens) = usage.get("total_tokens").and_then(|v| v.as_u64()) {
             self.usage.total_tokens = total_tokens as u32;
         }
+        self.usage.apply_cache_usage(usage);
     }
 
     /// 处理 Anthropic 格式的 chunk
@@ -415,7 +623,7 @@ impl StreamRebuilder {
                 self.process_anthropic_message_start(&json)?;
             }
             Some("content_block_start") => {
-                self.process_anthropic_content_block_start(&json)?;
+                self.process_anthropic_content_block_start(&json, chunk)?;
             }
             Some("content_block_delta") => {
                 self.process_anthropic_content_block_delta(&json, chunk)?;
@@ -427,7 +635,7 @@ impl StreamRebuilder {
                 self.process_anthropic_message_delta(&json)?;
             }
             Some("message_stop") => {
-                // 消息结束，无需特殊处理
+                self.terminated = true;
             }
             Some("ping") => {
                 // 心跳，忽略
@@ -465,11 +673,12 @@ impl StreamRebuilder {
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string());
 
-            // 处理 usage（input_tokens）
+            // 处理 usage（input_tokens 及缓存用量）
             if let Some(usage) = message.get("usage") {
                 if let Some(input_tokens) = usage.get("input_tokens").and_then(|v| v.as_u64()) {
                     self.usage.input_tokens = input_tokens as u32;
                 }
+                self.usage.apply_cache_usage(usage);
             }
         }
         Ok(())
@@ -479,6 +688,7 @@ impl StreamRebuilder {
     fn process_anthropic_content_block_start(
         &mut self,
         json: &serde_json::Value,
+        chunk: &mut StreamChunk,
     ) -> Result<(), StreamRebuilderError> {
         let index = json.get("index").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
         self.current_content_block_index = Some(index);
@@ -491,12 +701,12 @@ impl StreamRebuilder {
             self.current_content_block_type = Some(block_type.to_string());
 
             match block_type {
-                "tool_use" => {
-                    // 工具调用开始
-                    let builder = self
-                        .tool_calls_buffer
-                        .entry(index)
-                        .or_insert_with(ToolCallBuilder::new);
+                "tool_use" | "server_tool_use" | "mcp_tool_use" => {
+                    // 工具调用开始（服务端工具和 MCP 工具保留块类型）
+                    let builder = self.tool_call_builder(index);
+                    if block_type != "tool_use" {
+                        builder.tool_type = block_type.to_string();
+                    }
                     builder.id = content_block
                         .get("id")
                         .and_then(|v| v.as_str())
@@ -512,6 +722,38 @@ impl StreamRebuilder {
                         self.thinking_buffer = Some(String::new());
                     }
                 }
+                "text" => {
+                    // 部分上游在文本块开始时就给出第一段文本
+                    if let Some(text) = content_block
+                        .get("text")
+                        .and_then(|v| v.as_str())
+                        .filter(|t| !t.is_empty())
+                    {
+                        self.content_buffer.push_str(text);
+                        self.record_text(text);
+                        chunk.content_delta = Some(text.to_string());
+                    }
+                }
+                t if t == "tool_result" || t.ends_with("_tool_result") => {
+                    // 内联工具结果（服务端工具、MCP 工具的结果随块开始一次性给出）
+                    let tool_call_id = content_block
+                        .get("tool_use_id")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default();
+                    let content = content_block
+                        .get("content")
+                        .map(anthropic_tool_result_text)
+                        .unwrap_or_default();
+                    let is_error = content_block
+                        .get("is_error")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false)
+                        || content_block
+                            .pointer("/content/type")
+                            .and_then(|v| v.as_str())
+                            .is_some_and(|t| t.ends_with("_error"));
+                    self.record_tool_result(Some(tool_call_id), &content, is_error);
+                }
                 _ => {}
             }
         }
@@ -535,6 +777,7 @@ impl StreamRebuilder {
                     // 文本增量
                     if let Some(text) = delta.get("text").and_then(|v| v.as_str()) {
                         self.content_buffer.push_str(text);
+                        self.record_text(text);
                         chunk.content_delta = Some(text.to_string());
                     }
                 }
@@ -553,7 +796,7 @@ impl StreamRebuilder {
                     // 工具调用参数增量
                     if let Some(partial_json) = delta.get("partial_json").and_then(|v| v.as_str()) {
                         if let Some(builder) = self.tool_calls_buffer.get_mut(&index) {
-                            builder.arguments.push_str(partial_json);
+                            builder.arguments.append(partial_json);
 
                             chunk.tool_call_delta = Some(ToolCallDelta {
                                 index,
@@ -593,7 +836,7 @@ impl StreamRebuilder {
         // 处理停止原因
         if let Some(delta) = json.get("delta") {
             if let Some(stop_reason) = delta.get("stop_reason").and_then(|v| v.as_str()) {
-                self.stop_reason = Some(Self::parse_anthropic_stop_reason(stop_reason));
+                self.raw_stop_reason = Some(stop_reason.to_string());
             }
         }
 
@@ -601,23 +844,14 @@ impl StreamRebuilder {
         if let Some(usage) = json.get("usage") {
             if let Some(output_tokens) = usage.get("output_tokens").and_then(|v| v.as_u64()) {
                 self.usage.output_tokens = output_tokens as u32;
+                self.upstream_usage = true;
             }
+            self.usage.apply_cache_usage(usage);
         }
 
         Ok(())
     }
 
-    /// 解析 Anthropic 停止原因
-    fn parse_anthropic_stop_reason(reason: &str) -> StopReason {
-        match reason {
-            "end_turn" => StopReason::EndTurn,
-            "stop_sequence" => StopReason::Stop,
-            "max_tokens" => StopReason::Length,
-            "tool_use" => StopReason::ToolCalls,
-            other => StopReason::Other(other.to_string()),
-        }
-    }
-
     /// 处理 Gemini 格式的 chunk
     ///
     /// Gemini 流式响应格式:
@@ -647,9 +881,19 @@ impl StreamRebuilder {
                 if let Some(content) = candidate.get("content") {
                     if let Some(parts) = content.get("parts").and_then(|v| v.as_array()) {
                         for part in parts {
+                            let thought = part.get("thought").and_then(|v| v.as_bool());
                             if let Some(text) = part.get("text").and_then(|v| v.as_str()) {
-                                self.content_buffer.push_str(text);
-                                chunk.content_delta = Some(text.to_string());
+                                if thought == Some(true) {
+                                    // 思考摘要不属于回答内容
+                                    self.thinking_buffer
+                                        .get_or_insert_with(String::new)
+                                        .push_str(text);
+                                    chunk.thinking_delta = Some(text.to_string());
+                                } else {
+                                    self.content_buffer.push_str(text);
+                                    self.record_text(text);
+                                    chunk.content_delta = Some(text.to_string());
+                                }
                             }
 
                             // 处理函数调用
@@ -663,7 +907,7 @@ impl StreamRebuilder {
                 // 处理 finishReason
                 if let Some(finish_reason) = candidate.get("finishReason").and_then(|v| v.as_str())
                 {
-                    self.stop_reason = Some(Self::parse_gemini_stop_reason(finish_reason));
+                    self.raw_stop_reason = Some(finish_reason.to_string());
                 }
             }
         }
@@ -683,20 +927,25 @@ impl StreamRebuilder {
         chunk: &mut StreamChunk,
     ) -> Result<(), StreamRebuilderError> {
         let index = self.tool_calls_buffer.len() as u32;
-        let builder = self
-            .tool_calls_buffer
-            .entry(index)
-            .or_insert_with(ToolCallBuilder::new);
+        let builder = self.tool_call_builder(index);
 
         // Gemini 的函数调用通常是完整的，不是增量的
         if let Some(name) = function_call.get("name").and_then(|v| v.as_str()) {
             builder.function_name = Some(name.to_string());
-            builder.id = Some(format!("call_{}", uuid::Uuid::new_v4()));
+            // 上游给出调用 ID 时沿用，便于与后续的 functionResponse 对应
+            builder.id = Some(
+                function_call
+                    .get("id")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4())),
+            );
         }
 
         if let Some(args) = function_call.get("args") {
             let args_str = serde_json::to_string(args)?;
-            builder.arguments = args_str.clone();
+            builder.arguments.reset();
+            builder.arguments.append(&args_str);
 
             chunk.tool_call_delta = Some(ToolCallDelta {
                 index,
@@ -709,18 +958,6 @@ impl StreamRebuilder {
         Ok(())
     }
 
-    /// 解析 Gemini 停止原因
-    fn parse_gemini_stop_reason(reason: &str) -> StopReason {
-        match reason {
-            "STOP" => StopReason::Stop,
-            "MAX_TOKENS" => StopReason::Length,
-            "SAFETY" => StopReason::ContentFilter,
-            "RECITATION" => StopReason::ContentFilter,
-            "FUNCTION_CALL" => StopReason::ToolCalls,
-            other => StopReason::Other(other.to_string()),
-        }
-    }
-
     /// 解析 Gemini usage
     fn parse_gemini_usage(&mut self, usage: &serde_json::Value) {
         if let Some(prompt_tokens) = usage.get("promptTokenCount").and_then(|v| v.as_u64()) {
@@ -729,15 +966,41 @@ impl StreamRebuilder {
         if let Some(candidates_tokens) = usage.get("candidatesTokenCount").and_then(|v| v.as_u64())
         {
             self.usage.output_tokens = candidates_tokens as u32;
+            self.upstream_usage = true;
         }
         if let Some(total_tokens) = usage.get("totalTokenCount").and_then(|v| v.as_u64()) {
             self.usage.total_tokens = total_tokens as u32;
         }
+        self.usage.apply_cache_usage(usage);
+    }
+
+    /// 获取 Token 用量来源
+    ///
+    /// 上游在流中返回了输出 Token（OpenAI `include_usage`、Anthropic `message_delta` 等）时为 `Upstream`；
+    /// Anthropic `message_start` 只带输入 Token，流中断在 `message_delta` 之前时仍为估算值
+    pub fn usage_source(&self) -> UsageSource {
+        if self.upstream_usage {
+            UsageSource::Upstream
+        } else {
+            UsageSource::Estimated
+        }
+    }
+
+    /// 流是否以终止事件正常结束
+    ///
+    /// Gemini 格式没有单独的终止事件，以最后一个候选的 `finishReason` 为准。
+    /// 连接中断或上游提前关闭时返回 `false`，此时重建的响应可能被截断。
+    pub fn completed_cleanly(&self) -> bool {
+        match self.format {
+            StreamFormat::Gemini => self.raw_stop_reason.is_some(),
+            _ => self.terminated,
+        }
     }
 
     /// 完成流重建，返回完整的 LLM 响应
     ///
     /// 合并累积的内容、工具调用、思维链，计算流式统计信息。
+    /// 上游返回了用量时响应中的 `usage` 为准确值，否则为估算值。
     pub fn finish(self) -> LLMResponse {
         let now = Utc::now();
 
@@ -749,23 +1012,34 @@ impl StreamRebuilder {
             text,
             tokens: self.usage.thinking_tokens,
             signature: None,
+            inline_segments: Vec::new(),
         });
 
         // 构建工具调用列表
-        let mut tool_calls: Vec<ToolCall> = self
-            .tool_calls_buffer
-            .iter()
+        // 按流中的工具调用索引排序（Gemini 的调用 ID 是随机生成的，不能按 ID 排序）
+        let mut builders: Vec<(&u32, &ToolCallBuilder)> = self.tool_calls_buffer.iter().collect();
+        builders.sort_by_key(|(index, _)| **index);
+        let tool_calls: Vec<ToolCall> = builders
+            .into_iter()
             .filter_map(|(_, builder)| builder.clone().build())
             .collect();
 
-        // 按索引排序（如果有多个工具调用）
-        tool_calls.sort_by_key(|tc| tc.id.clone());
-
         // 构建响应体 JSON
         let body = self.build_response_body(&tool_calls, &thinking);
 
-        // 计算 Token 总数
+        // 重建工具往返轮次
+        let turns = self.build_turns();
+
+        // 上游返回了用量时使用准确值，否则按内容长度估算输出 Token
         let mut usage = self.usage.clone();
+        if !self.upstream_usage {
+            let output_chars = self.content_buffer.chars().count()
+                + self
+                    .thinking_buffer
+                    .as_ref()
+                    .map_or(0, |t| t.chars().count());
+            usage.output_tokens = output_chars.div_ceil(4) as u32;
+        }
         usage.calculate_total();
 
         // 确定时间戳
@@ -781,11 +1055,17 @@ impl StreamRebuilder {
             thinking,
             tool_calls,
             usage,
-            stop_reason: self.stop_reason,
+            stop_reason: self.raw_stop_reason.as_deref().map(StopReason::from_raw),
+            raw_stop_reason: self.raw_stop_reason,
             size_bytes: 0, // 将在外部计算
             timestamp_start,
             timestamp_end,
             stream_info: Some(stream_info),
+            schema_valid: None,
+            logprobs: self.logprobs,
+            body_info: None,
+            system_fingerprint: self.system_fingerprint,
+            turns,
         }
     }
 
@@ -812,7 +1092,10 @@ impl StreamRebuilder {
             chunk_count,
             first_chunk_latency_ms,
             avg_chunk_interval_ms,
-            raw_chunks: if self.save_raw_chunks {
+            completed_cleanly: self.completed_cleanly(),
+            raw_chunks: if self.save_raw_chunks && self.compact_chunks {
+                Some(compact_chunks(self.chunks.clone()))
+            } else if self.save_raw_chunks {
                 Some(self.chunks.clone())
             } else {
                 None
@@ -829,7 +1112,7 @@ impl StreamRebuilder {
         match self.format {
             StreamFormat::OpenAI => self.build_openai_response_body(tool_calls),
             StreamFormat::Anthropic => self.build_anthropic_response_body(tool_calls, thinking),
-            StreamFormat::Gemini => self.build_gemini_response_body(tool_calls),
+            StreamFormat::Gemini => self.build_gemini_response_body(tool_calls, thinking),
             StreamFormat::Unknown => serde_json::json!({
                 "content": self.content_buffer,
                 "tool_calls": tool_calls,
@@ -868,7 +1151,7 @@ impl StreamRebuilder {
             "choices": [{
                 "index": 0,
                 "message": message,
-                "finish_reason": self.stop_reason.as_ref().map(|r| format!("{:?}", r).to_lowercase()),
+                "finish_reason": self.raw_stop_reason,
             }],
             "usage": {
                 "prompt_tokens": self.usage.input_tokens,
@@ -906,8 +1189,13 @@ impl StreamRebuilder {
         for tc in tool_calls {
             let input: serde_json::Value =
                 serde_json::from_str(&tc.function.arguments).unwrap_or(serde_json::json!({}));
+            let block_type = if tc.tool_type == "function" {
+                "tool_use"
+            } else {
+                tc.tool_type.as_str()
+            };
             content.push(serde_json::json!({
-                "type": "tool_use",
+                "type": block_type,
                 "id": tc.id,
                 "name": tc.function.name,
                 "input": input,
@@ -920,13 +1208,7 @@ impl StreamRebuilder {
             "role": "assistant",
             "model": self.model.clone().unwrap_or_default(),
             "content": content,
-            "stop_reason": self.stop_reason.as_ref().map(|r| match r {
-                StopReason::EndTurn => "end_turn",
-                StopReason::Stop => "stop_sequence",
-                StopReason::Length => "max_tokens",
-                StopReason::ToolCalls => "tool_use",
-                _ => "end_turn",
-            }),
+            "stop_reason": self.raw_stop_reason,
             "usage": {
                 "input_tokens": self.usage.input_tokens,
                 "output_tokens": self.usage.output_tokens,
@@ -935,9 +1217,21 @@ impl StreamRebuilder {
     }
 
     /// 构建 Gemini 格式响应体
-    fn build_gemini_response_body(&self, tool_calls: &[ToolCall]) -> serde_json::Value {
+    fn build_gemini_response_body(
+        &self,
+        tool_calls: &[ToolCall],
+        thinking: &Option<ThinkingContent>,
+    ) -> serde_json::Value {
         let mut parts: Vec<serde_json::Value> = Vec::new();
 
+        // 添加思考摘要
+        if let Some(ref thinking_content) = thinking {
+            parts.push(serde_json::json!({
+                "text": thinking_content.text,
+                "thought": true,
+            }));
+        }
+
         // 添加文本内容
         if !self.content_buffer.is_empty() {
             parts.push(serde_json::json!({
@@ -963,13 +1257,7 @@ impl StreamRebuilder {
                     "parts": parts,
                     "role": "model",
                 },
-                "finishReason": self.stop_reason.as_ref().map(|r| match r {
-                    StopReason::Stop => "STOP",
-                    StopReason::Length => "MAX_TOKENS",
-                    StopReason::ContentFilter => "SAFETY",
-                    StopReason::ToolCalls => "FUNCTION_CALL",
-                    _ => "STOP",
-                }),
+                "finishReason": self.raw_stop_reason,
             }],
             "usageMetadata": {
                 "promptTokenCount": self.usage.input_tokens,
@@ -995,6 +1283,116 @@ impl StreamRebuilder {
     }
 }
 
+// ============================================================================
+// Chunk 合并
+// ============================================================================
+
+/// 单个合并组允许跨越的最大时间（毫秒），超过后开始新组以保留时间边界
+const COMPACT_MAX_SPAN_MS: i64 = 500;
+
+/// 合并时 chunk 的分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkKind {
+    /// 仅包含文本增量
+    Content,
+    /// 仅包含思维链增量
+    Thinking,
+    /// 仅包含指定工具调用的参数增量
+    ToolArguments(u32),
+    /// 其他 chunk（工具调用开始、无增量的事件等），不参与合并
+    Boundary,
+}
+
+impl ChunkKind {
+    fn of(chunk: &StreamChunk) -> Self {
+        match (
+            &chunk.content_delta,
+            &chunk.thinking_delta,
+            &chunk.tool_call_delta,
+        ) {
+            (Some(_), None, None) => ChunkKind::Content,
+            (None, Some(_), None) => ChunkKind::Thinking,
+            (None, None, Some(delta)) if delta.arguments_delta.is_some() => {
+                ChunkKind::ToolArguments(delta.index)
+            }
+            _ => ChunkKind::Boundary,
+        }
+    }
+}
+
+/// 合并连续的同类增量 chunks，减少保存的 chunk 数量
+///
+/// 只合并事件类型相同、增量类型相同且时间跨度不超过 `COMPACT_MAX_SPAN_MS` 的
+/// 相邻 chunk；每个工具调用的首个增量、思维链/文本切换等边界以及首个携带增量的
+/// chunk（TTFB）保持独立。合并后的 chunk 保留组内首个 chunk 的索引和时间戳，`data` 以换行拼接，
+/// 各增量按顺序拼接，因此由增量重建的内容与合并前逐字节一致。
+pub fn compact_chunks(chunks: Vec<StreamChunk>) -> Vec<StreamChunk> {
+    let mut compacted: Vec<StreamChunk> = Vec::with_capacity(chunks.len());
+    let mut last_kind = ChunkKind::Boundary;
+    let mut seen_first_delta = false;
+    let mut started_tools = std::collections::HashSet::new();
+
+    for chunk in chunks {
+        let has_delta = chunk.content_delta.is_some()
+            || chunk.thinking_delta.is_some()
+            || chunk.tool_call_delta.is_some();
+        let is_first_delta = !seen_first_delta && has_delta;
+        seen_first_delta |= has_delta;
+
+        // 每个工具调用的首个增量视为调用开始，保持独立
+        let kind = match (ChunkKind::of(&chunk), &chunk.tool_call_delta) {
+            (_, Some(delta)) if started_tools.insert(delta.index) => ChunkKind::Boundary,
+            (kind, _) => kind,
+        };
+
+        let mergeable = !is_first_delta
+            && kind != ChunkKind::Boundary
+            && kind == last_kind
+            && compacted.last().is_some_and(|group| {
+                group.event == chunk.event
+                    && (chunk.timestamp - group.timestamp).num_milliseconds() <= COMPACT_MAX_SPAN_MS
+            });
+
+        if !mergeable {
+            // TTFB chunk 单独成组，后续 chunk 不与其合并
+            last_kind = if is_first_delta {
+                ChunkKind::Boundary
+            } else {
+                kind
+            };
+            compacted.push(chunk);
+            continue;
+        }
+
+        let Some(group) = compacted.last_mut() else {
+            continue;
+        };
+        group.data.push('\n');
+        group.data.push_str(&chunk.data);
+        append_delta(&mut group.content_delta, chunk.content_delta);
+        append_delta(&mut group.thinking_delta, chunk.thinking_delta);
+        if let (Some(group_delta), Some(delta)) =
+            (&mut group.tool_call_delta, chunk.tool_call_delta)
+        {
+            append_delta(&mut group_delta.arguments_delta, delta.arguments_delta);
+        }
+    }
+
+    compacted
+}
+
+fn append_delta(target: &mut Option<String>, delta: Option<String>) {
+    if let (Some(target), Some(delta)) = (target, delta) {
+        target.push_str(&delta);
+    }
+}
+
+/// 数据是否为 Gemini 的 `{"candidates": [...]}` 信封
+fn is_gemini_chunk(data: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(data.trim())
+        .is_ok_and(|json| json.get("candidates").is_some_and(|c| c.is_array()))
+}
+
 // ============================================================================
 // 单元测试
 // ============================================================================
@@ -1025,6 +1423,133 @@ mod tests {
         assert_eq!(response.stop_reason, Some(StopReason::Stop));
     }
 
+    #[test]
+    fn test_openai_truncated_stream_detected() {
+        let chunks = [
+            r#"{"id":"chatcmpl-1","model":"gpt-4","choices":[{"index":0,"delta":{"content":"Hello"},"finish_reason":null}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4","choices":[{"index":0,"delta":{"content":" wor"},"finish_reason":null}]}"#,
+        ];
+
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::OpenAI);
+        for chunk in chunks {
+            rebuilder.process_event(None, chunk).unwrap();
+        }
+        assert!(!rebuilder.completed_cleanly());
+        let response = rebuilder.finish();
+        // 仍然返回已收到的内容
+        assert_eq!(response.content, "Hello wor");
+        assert!(!response.stream_info.unwrap().completed_cleanly);
+
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::OpenAI);
+        for chunk in chunks.into_iter().chain(["[DONE]"]) {
+            rebuilder.process_event(None, chunk).unwrap();
+        }
+        assert!(rebuilder.finish().stream_info.unwrap().completed_cleanly);
+    }
+
+    #[test]
+    fn test_anthropic_truncated_stream_detected() {
+        let events = [
+            (
+                "message_start",
+                r#"{"type":"message_start","message":{"id":"msg_1","model":"claude-3","usage":{"input_tokens":10}}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#,
+            ),
+            (
+                "message_delta",
+                r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":5}}"#,
+            ),
+        ];
+
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::Anthropic);
+        for (event, data) in events {
+            rebuilder.process_event(Some(event), data).unwrap();
+        }
+        let response = rebuilder.finish();
+        assert_eq!(response.content, "Hello");
+        assert!(!response.stream_info.unwrap().completed_cleanly);
+
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::Anthropic);
+        for (event, data) in events
+            .into_iter()
+            .chain([("message_stop", r#"{"type":"message_stop"}"#)])
+        {
+            rebuilder.process_event(Some(event), data).unwrap();
+        }
+        assert!(rebuilder.completed_cleanly());
+    }
+
+    #[test]
+    fn test_openai_stream_system_fingerprint() {
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::OpenAI);
+        let chunks = [
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","system_fingerprint":"fp_abc","choices":[{"index":0,"delta":{"content":"Hi"},"finish_reason":null}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","system_fingerprint":"fp_abc","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#,
+            "[DONE]",
+        ];
+        for chunk in chunks {
+            rebuilder.process_event(None, chunk).unwrap();
+        }
+
+        let response = rebuilder.finish();
+        assert_eq!(response.system_fingerprint.as_deref(), Some("fp_abc"));
+    }
+
+    #[test]
+    fn test_openai_stream_logprobs() {
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::OpenAI);
+        let chunks = vec![
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","choices":[{"index":0,"delta":{"content":"Hi"},"logprobs":{"content":[{"token":"Hi","logprob":-0.1,"top_logprobs":[{"token":"Hi","logprob":-0.1}]}]},"finish_reason":null}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","choices":[{"index":0,"delta":{"content":"!"},"logprobs":{"content":[{"token":"!","logprob":-0.5,"top_logprobs":[]}]},"finish_reason":null}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","choices":[{"index":0,"delta":{},"logprobs":null,"finish_reason":"stop"}]}"#,
+            "[DONE]",
+        ];
+        for chunk in chunks {
+            rebuilder.process_event(None, chunk).unwrap();
+        }
+
+        let response = rebuilder.finish();
+        let logprobs = response.logprobs.unwrap();
+        assert_eq!(logprobs.total_tokens, 2);
+        assert_eq!(logprobs.tokens[1].token, "!");
+        assert_eq!(logprobs.tokens[1].logprob, -0.5);
+        assert_eq!(logprobs.tokens[0].top_logprobs.len(), 1);
+    }
+
+    #[test]
+    fn test_openai_include_usage_overrides_estimate() {
+        let chunks = [
+            r#"{"id":"chatcmpl-123","model":"gpt-4","choices":[{"index":0,"delta":{"content":"Hello world"},"finish_reason":null}],"usage":null}"#,
+            r#"{"id":"chatcmpl-123","model":"gpt-4","choices":[{"index":0,"delta":{},"finish_reason":"stop"}],"usage":null}"#,
+            r#"{"id":"chatcmpl-123","model":"gpt-4","choices":[],"usage":{"prompt_tokens":12,"completion_tokens":2,"total_tokens":14}}"#,
+        ];
+
+        // 没有用量 chunk 时按内容长度估算
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::OpenAI);
+        for chunk in &chunks[..2] {
+            rebuilder.process_event(None, chunk).unwrap();
+        }
+        assert_eq!(rebuilder.usage_source(), UsageSource::Estimated);
+        let response = rebuilder.finish();
+        assert_eq!(response.usage.input_tokens, 0);
+        assert_eq!(response.usage.output_tokens, 3);
+
+        // 最后的用量 chunk（choices 为空）覆盖估算值
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::OpenAI);
+        for chunk in &chunks {
+            rebuilder.process_event(None, chunk).unwrap();
+        }
+        assert_eq!(rebuilder.usage_source(), UsageSource::Upstream);
+        let response = rebuilder.finish();
+        assert_eq!(response.content, "Hello world");
+        assert_eq!(response.usage.input_tokens, 12);
+        assert_eq!(response.usage.output_tokens, 2);
+        assert_eq!(response.usage.total_tokens, 14);
+    }
+
     #[test]
     fn test_openai_tool_calls_stream() {
         let mut rebuilder = StreamRebuilder::new(StreamFormat::OpenAI);
@@ -1052,6 +1577,25 @@ mod tests {
         assert_eq!(response.stop_reason, Some(StopReason::ToolCalls));
     }
 
+    #[test]
+    fn test_openai_tool_call_truncated_arguments() {
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::OpenAI);
+
+        // 流在参数字符串中途中断
+        let chunks = vec![
+            r#"{"id":"chatcmpl-123","object":"chat.completion.chunk","created":1234567890,"model":"gpt-4","choices":[{"index":0,"delta":{"role":"assistant","content":null,"tool_calls":[{"index":0,"id":"call_abc123","type":"function","function":{"name":"get_weather","arguments":""}}]},"finish_reason":null}]}"#,
+            r#"{"id":"chatcmpl-123","object":"chat.completion.chunk","created":1234567890,"model":"gpt-4","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"location\":\"New Yo"}}]},"finish_reason":null}]}"#,
+        ];
+        for chunk in chunks {
+            rebuilder.process_event(None, chunk).unwrap();
+        }
+
+        let response = rebuilder.finish();
+        let tool_call = &response.tool_calls[0];
+        assert!(tool_call.arguments_truncated);
+        assert_eq!(tool_call.function.arguments, r#"{"location":"New Yo"}"#);
+    }
+
     #[test]
     fn test_anthropic_simple_stream() {
         let mut rebuilder = StreamRebuilder::new(StreamFormat::Anthropic);
@@ -1090,11 +1634,74 @@ mod tests {
 
         let response = rebuilder.finish();
         assert_eq!(response.content, "Hello world");
-        assert_eq!(response.stop_reason, Some(StopReason::EndTurn));
+        assert_eq!(response.stop_reason, Some(StopReason::Stop));
+        assert_eq!(response.raw_stop_reason.as_deref(), Some("end_turn"));
+        assert!(!response.usage.has_cache_usage());
         assert_eq!(response.usage.input_tokens, 10);
         assert_eq!(response.usage.output_tokens, 5);
     }
 
+    #[test]
+    fn test_anthropic_usage_source_requires_output_tokens() {
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::Anthropic);
+        rebuilder
+            .process_event(
+                Some("message_start"),
+                r#"{"type":"message_start","message":{"id":"msg_1","model":"claude-3","usage":{"input_tokens":10}}}"#,
+            )
+            .unwrap();
+        rebuilder
+            .process_event(
+                Some("content_block_delta"),
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello world"}}"#,
+            )
+            .unwrap();
+
+        // 只有 message_start 的输入 Token，输出 Token 仍为估算值
+        assert_eq!(rebuilder.usage_source(), UsageSource::Estimated);
+
+        rebuilder
+            .process_event(
+                Some("message_delta"),
+                r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":5}}"#,
+            )
+            .unwrap();
+        assert_eq!(rebuilder.usage_source(), UsageSource::Upstream);
+        let response = rebuilder.finish();
+        assert_eq!(response.usage.input_tokens, 10);
+        assert_eq!(response.usage.output_tokens, 5);
+    }
+
+    #[test]
+    fn test_anthropic_stream_cache_usage() {
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::Anthropic);
+
+        let events = vec![
+            (
+                "message_start",
+                r#"{"type":"message_start","message":{"id":"msg_123","model":"claude-sonnet-4-5","usage":{"input_tokens":4,"cache_read_input_tokens":3000,"cache_creation_input_tokens":996}}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+            ),
+            (
+                "message_delta",
+                r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":2}}"#,
+            ),
+        ];
+        for (event, data) in events {
+            rebuilder.process_event(Some(event), data).unwrap();
+        }
+
+        let response = rebuilder.finish();
+        assert_eq!(response.usage.input_tokens, 4000);
+        assert_eq!(response.usage.total_tokens, 4002);
+        assert_eq!(response.usage.cache_read_tokens, Some(3000));
+        assert_eq!(response.usage.cache_write_tokens, Some(996));
+        assert_eq!(response.usage.cache_hit_ratio(), Some(0.75));
+    }
+
     #[test]
     fn test_anthropic_tool_use_stream() {
         let mut rebuilder = StreamRebuilder::new(StreamFormat::Anthropic);
@@ -1140,6 +1747,105 @@ mod tests {
             r#"{"location":"NYC"}"#
         );
         assert_eq!(response.stop_reason, Some(StopReason::ToolCalls));
+        assert!(response.turns.is_empty());
+    }
+
+    #[test]
+    fn test_openai_tool_round_trip_turns() {
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::OpenAI);
+
+        let chunks = vec![
+            r#"{"id":"chatcmpl-1","model":"gpt-4","choices":[{"index":0,"delta":{"role":"assistant","tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"get_weather","arguments":""}}]}}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"city\":\"NYC\"}"}}]}}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4","choices":[{"index":0,"delta":{"role":"tool","tool_call_id":"call_1","content":"{\"temp\":"}}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4","choices":[{"index":0,"delta":{"content":"20}"}}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4","choices":[{"index":0,"delta":{"role":"assistant","content":"It is "}}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4","choices":[{"index":0,"delta":{"content":"20 degrees."},"finish_reason":"stop"}]}"#,
+            "[DONE]",
+        ];
+
+        for data in chunks {
+            rebuilder.process_event(None, data).unwrap();
+        }
+
+        let response = rebuilder.finish();
+        // 工具结果不计入助手内容
+        assert_eq!(response.content, "It is 20 degrees.");
+        assert_eq!(response.turns.len(), 3);
+
+        let call = &response.turns[0];
+        assert_eq!(call.role, MessageRole::Assistant);
+        let calls = call.tool_calls.as_ref().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id, "call_1");
+        assert_eq!(calls[0].function.arguments, r#"{"city":"NYC"}"#);
+
+        let result = &response.turns[1];
+        assert_eq!(result.role, MessageRole::Tool);
+        assert_eq!(result.name.as_deref(), Some("get_weather"));
+        let tool_result = result.tool_result.as_ref().unwrap();
+        assert_eq!(tool_result.tool_call_id, "call_1");
+        assert_eq!(tool_result.content, r#"{"temp":20}"#);
+        assert!(!tool_result.is_error);
+
+        let answer = &response.turns[2];
+        assert_eq!(answer.role, MessageRole::Assistant);
+        assert!(answer.tool_calls.is_none());
+        assert!(matches!(&answer.content, MessageContent::Text(t) if t == "It is 20 degrees."));
+    }
+
+    #[test]
+    fn test_anthropic_server_tool_round_trip_turns() {
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::Anthropic);
+
+        let events = vec![
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Searching."}}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":1,"content_block":{"type":"server_tool_use","id":"srvtoolu_1","name":"web_search"}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"query\":\"rust\"}"}}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":2,"content_block":{"type":"web_search_tool_result","tool_use_id":"srvtoolu_1","content":[{"type":"web_search_result","url":"https://www.rust-lang.org"}]}}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":3,"content_block":{"type":"text","text":""}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":3,"delta":{"type":"text_delta","text":"Found it."}}"#,
+            ),
+        ];
+
+        for (event, data) in events {
+            rebuilder.process_event(Some(event), data).unwrap();
+        }
+
+        let response = rebuilder.finish();
+        assert_eq!(response.turns.len(), 3);
+        assert!(matches!(&response.turns[0].content, MessageContent::Text(t) if t == "Searching."));
+        let calls = response.turns[0].tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].tool_type, "server_tool_use");
+        assert_eq!(calls[0].function.name, "web_search");
+
+        let result = response.turns[1].tool_result.as_ref().unwrap();
+        assert_eq!(result.tool_call_id, "srvtoolu_1");
+        assert!(result.content.contains("rust-lang.org"));
+
+        assert!(matches!(&response.turns[2].content, MessageContent::Text(t) if t == "Found it."));
+        assert_eq!(response.body["content"][1]["type"], "server_tool_use");
     }
 
     #[test]
@@ -1209,10 +1915,88 @@ mod tests {
         let response = rebuilder.finish();
         assert_eq!(response.content, "Hello world!");
         assert_eq!(response.stop_reason, Some(StopReason::Stop));
+        assert_eq!(response.raw_stop_reason.as_deref(), Some("STOP"));
         assert_eq!(response.usage.input_tokens, 10);
         assert_eq!(response.usage.output_tokens, 5);
     }
 
+    #[test]
+    fn test_gemini_stream_with_function_calls() {
+        // 录制的 Gemini 流：文本、同一 chunk 中的两个函数调用（第一个带上游 ID）
+        let recorded = [
+            r#"{"candidates":[{"content":{"parts":[{"text":"Checking the "}],"role":"model"},"index":0}]}"#,
+            r#"{"candidates":[{"content":{"parts":[{"text":"weather."},{"functionCall":{"id":"fc_1","name":"get_weather","args":{"city":"Paris"}}},{"functionCall":{"name":"get_time","args":{"tz":"CET"}}}],"role":"model"},"index":0}]}"#,
+            r#"{"candidates":[{"content":{"parts":[],"role":"model"},"finishReason":"STOP","index":0}],"usageMetadata":{"promptTokenCount":12,"candidatesTokenCount":8,"totalTokenCount":20}}"#,
+        ];
+
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::Unknown);
+        for chunk in recorded {
+            rebuilder.process_event(None, chunk).unwrap();
+        }
+        assert_eq!(rebuilder.format(), StreamFormat::Gemini);
+        assert!(rebuilder.completed_cleanly());
+
+        let response = rebuilder.finish();
+        assert_eq!(response.content, "Checking the weather.");
+        let calls: Vec<(&str, &str)> = response
+            .tool_calls
+            .iter()
+            .map(|tc| (tc.function.name.as_str(), tc.function.arguments.as_str()))
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                ("get_weather", r#"{"city":"Paris"}"#),
+                ("get_time", r#"{"tz":"CET"}"#)
+            ]
+        );
+        assert_eq!(response.tool_calls[0].id, "fc_1");
+        assert!(response.tool_calls[1].id.starts_with("call_"));
+        assert_eq!(response.usage.total_tokens, 20);
+    }
+
+    #[test]
+    fn test_content_excludes_non_answer_deltas() {
+        // OpenAI：n > 1 时只重建第一个候选
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::OpenAI);
+        for chunk in [
+            r#"{"choices":[{"index":0,"delta":{"content":"A"}},{"index":1,"delta":{"content":"B"}}]}"#,
+            r#"{"choices":[{"index":1,"delta":{"content":"b"}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"content":"a"},"finish_reason":"stop"}]}"#,
+        ] {
+            rebuilder.process_event(None, chunk).unwrap();
+        }
+        assert_eq!(rebuilder.finish().content, "Aa");
+
+        // Anthropic：文本块开始时携带的初始文本计入内容
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::Anthropic);
+        rebuilder
+            .process_event(
+                Some("content_block_start"),
+                r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":"Hi"}}"#,
+            )
+            .unwrap();
+        rebuilder
+            .process_event(
+                Some("content_block_delta"),
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" there"}}"#,
+            )
+            .unwrap();
+        assert_eq!(rebuilder.finish().content, "Hi there");
+
+        // Gemini：思考摘要进入思维链而不是内容
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::Gemini);
+        rebuilder
+            .process_event(
+                None,
+                r#"{"candidates":[{"content":{"parts":[{"text":"plan","thought":true},{"text":"Answer"}],"role":"model"},"index":0}]}"#,
+            )
+            .unwrap();
+        let response = rebuilder.finish();
+        assert_eq!(response.content, "Answer");
+        assert_eq!(response.thinking.unwrap().text, "plan");
+    }
+
     #[test]
     fn test_done_signal() {
         let mut rebuilder = StreamRebuilder::new(StreamFormat::OpenAI);
@@ -1269,6 +2053,107 @@ mod tests {
         assert!(stream_info.raw_chunks.is_some());
         assert_eq!(stream_info.raw_chunks.unwrap().len(), 4);
     }
+
+    #[test]
+    fn test_compact_chunks_preserves_content() {
+        let mut rebuilder = StreamRebuilder::new(StreamFormat::Anthropic)
+            .with_save_raw_chunks(true)
+            .with_compact_chunks(true);
+
+        let events = vec![
+            (
+                "message_start",
+                r#"{"type":"message_start","message":{"id":"msg_1","model":"claude-3","usage":{"input_tokens":5,"output_tokens":0}}}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":""}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"想"}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"一想"}}"#,
+            ),
+            (
+                "content_block_stop",
+                r#"{"type":"content_block_stop","index":0}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":1,"content_block":{"type":"text","text":""}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"Hel"}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"lo"}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":" 世界"}}"#,
+            ),
+            (
+                "content_block_stop",
+                r#"{"type":"content_block_stop","index":1}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":2,"content_block":{"type":"tool_use","id":"toolu_1","name":"get_weather","input":{}}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":2,"delta":{"type":"input_json_delta","partial_json":"{\"city\":"}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":2,"delta":{"type":"input_json_delta","partial_json":"\"北京\"}"}}"#,
+            ),
+            (
+                "content_block_stop",
+                r#"{"type":"content_block_stop","index":2}"#,
+            ),
+            (
+                "message_delta",
+                r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":10}}"#,
+            ),
+            ("message_stop", r#"{"type":"message_stop"}"#),
+        ];
+        for (event, data) in &events {
+            rebuilder.process_event(Some(event), data).unwrap();
+        }
+
+        let response = rebuilder.finish();
+        let stream_info = response.stream_info.unwrap();
+        assert_eq!(stream_info.chunk_count, events.len() as u32);
+        let raw = stream_info.raw_chunks.unwrap();
+        assert!(raw.len() < events.len());
+
+        let content: String = raw
+            .iter()
+            .filter_map(|c| c.content_delta.as_deref())
+            .collect();
+        let thinking: String = raw
+            .iter()
+            .filter_map(|c| c.thinking_delta.as_deref())
+            .collect();
+        let arguments: String = raw
+            .iter()
+            .filter_map(|c| c.tool_call_delta.as_ref()?.arguments_delta.as_deref())
+            .collect();
+        assert_eq!(content, response.content);
+        assert_eq!(content, "Hello 世界");
+        assert_eq!(thinking, "想一想");
+        assert_eq!(arguments, r#"{"city":"北京"}"#);
+
+        // TTFB chunk（首个携带增量的 chunk）保持独立
+        let first_delta = raw.iter().find(|c| c.thinking_delta.is_some()).unwrap();
+        assert_eq!(first_delta.thinking_delta.as_deref(), Some("想"));
+    }
 }
 
 // ============================================================================
@@ -1278,6 +2163,9 @@ mod tests {
 #[cfg(test)]
 mod property_tests {
     use super::*;
+    use crate::streaming::{
+        parse_sse_block, serialize_event, AwsEvent, StreamConverter, StreamFormat as ConvFormat,
+    };
     use proptest::prelude::*;
 
     // ========================================================================
@@ -1616,8 +2504,8 @@ mod property_tests {
             // 验证停止原因
             prop_assert_eq!(
                 response.stop_reason,
-                Some(StopReason::EndTurn),
-                "无工具调用时停止原因应该是 EndTurn"
+                Some(StopReason::Stop),
+                "无工具调用时停止原因应该是 Stop"
             );
         }
 
@@ -1733,5 +2621,99 @@ mod property_tests {
                 "保存的 chunks 数量应该正确"
             );
         }
+
+        /// **Feature: llm-flow-monitor, Property 2c: 跨格式重建内容一致**
+        ///
+        /// *对于任意* 回答内容，经转换器转换为 OpenAI SSE、Anthropic SSE、Gemini SSE（以及
+        /// Anthropic / Gemini SSE 按任意字节切分后再转换）或按 Gemini 格式传输，重建后的内容
+        /// 都与原始内容及非流式响应体中的内容完全一致。
+        #[test]
+        fn prop_cross_format_content_equivalence(
+            pieces in prop::collection::vec("[a-zA-Z0-9\\u4e00-\\u9fff .,!?\n\t]{1,12}", 1..8),
+            chunk_size in 1usize..40,
+        ) {
+            let content = pieces.concat();
+            let body_text = |body: &serde_json::Value, pointer: &str| {
+                body.pointer(pointer).and_then(|v| v.as_str()).unwrap_or_default().to_string()
+            };
+
+            // AWS Event Stream -> OpenAI SSE / Anthropic SSE
+            let openai = convert_pieces(&pieces, ConvFormat::OpenAiSse);
+            let anthropic = convert_pieces(&pieces, ConvFormat::AnthropicSse);
+
+            // Anthropic SSE 按任意字节切分（事件和多字节字符跨 chunk）后转换为 OpenAI SSE
+            let mut converter = StreamConverter::new(ConvFormat::AnthropicSse, ConvFormat::OpenAiSse);
+            let mut reconverted = Vec::new();
+            for chunk in anthropic.concat().as_bytes().chunks(chunk_size) {
+                reconverted.extend(converter.convert(chunk));
+            }
+            reconverted.extend(converter.finish());
+
+            // AWS Event Stream -> Gemini SSE，再按任意字节切分后转换为 Anthropic SSE
+            let gemini_converted = convert_pieces(&pieces, ConvFormat::GeminiSse);
+            let mut converter = StreamConverter::new(ConvFormat::GeminiSse, ConvFormat::AnthropicSse);
+            let mut from_gemini = Vec::new();
+            for chunk in gemini_converted.concat().as_bytes().chunks(chunk_size) {
+                from_gemini.extend(converter.convert(chunk));
+            }
+            from_gemini.extend(converter.finish());
+
+            let gemini: Vec<String> = pieces
+                .iter()
+                .map(|text| {
+                    format!(
+                        "data: {}\n\n",
+                        serde_json::json!({"candidates": [{"content": {"parts": [{"text": text}], "role": "model"}, "index": 0}]})
+                    )
+                })
+                .collect();
+
+            let cases = [
+                (rebuild_sse(StreamFormat::OpenAI, &openai), "/choices/0/message/content"),
+                (rebuild_sse(StreamFormat::Anthropic, &anthropic), "/content/0/text"),
+                (rebuild_sse(StreamFormat::OpenAI, &reconverted), "/choices/0/message/content"),
+                (rebuild_sse(StreamFormat::Gemini, &gemini), "/candidates/0/content/parts/0/text"),
+                (rebuild_sse(StreamFormat::Gemini, &gemini_converted), "/candidates/0/content/parts/0/text"),
+                (rebuild_sse(StreamFormat::Anthropic, &from_gemini), "/content/0/text"),
+            ];
+            for (response, pointer) in cases {
+                prop_assert_eq!(&response.content, &content, "流式重建的内容应该与原始内容一致");
+                prop_assert_eq!(
+                    body_text(&response.body, pointer),
+                    content.clone(),
+                    "非流式响应体中的内容应该与流式重建的内容一致"
+                );
+            }
+        }
+    }
+
+    /// 把 AWS Event Stream 内容事件转换为目标 SSE 格式
+    fn convert_pieces(pieces: &[String], target: ConvFormat) -> Vec<String> {
+        let mut converter =
+            StreamConverter::with_model(ConvFormat::AwsEventStream, target, "test-model");
+        let mut output = Vec::new();
+        for text in pieces {
+            let event = AwsEvent::Content { text: text.clone() };
+            if let Some(json) = serialize_event(&event) {
+                output.extend(converter.convert(json.as_bytes()));
+            }
+        }
+        output.extend(converter.finish());
+        output
+    }
+
+    /// 按 SSE 事件拆分输出并交给重建器
+    fn rebuild_sse(format: StreamFormat, output: &[String]) -> LLMResponse {
+        let mut rebuilder = StreamRebuilder::new(format);
+        for event in output
+            .iter()
+            .flat_map(|o| o.split("\n\n"))
+            .filter_map(parse_sse_block)
+        {
+            rebuilder
+                .process_event(event.event.as_deref(), &event.data)
+                .unwrap();
+        }
+        rebuilder.finish()
     }
 }
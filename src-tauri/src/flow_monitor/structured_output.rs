@@ -0,0 +1,293 @@
+//! 结构化输出校验
+//!
+//! 对使用 `response_format: {type: "json_schema"}` 的请求，
+//! 按请求中提供的 JSON Schema 校验模型返回的内容。
+//!
+//! 仅实现结构化输出常用的关键字子集：`type`、`enum`、`const`、`properties`、
+//! `required`、`additionalProperties`、`items`、`minItems`/`maxItems`、
+//! `minimum`/`maximum`、`minLength`/`maxLength`、`anyOf`/`oneOf`/`allOf`
+//! 以及指向 `#/$defs`、`#/definitions` 的本地 `$ref`。未知关键字会被忽略。
+
+use serde_json::Value;
+
+/// `$ref` 展开的最大深度（防止递归 Schema 死循环）
+const MAX_REF_DEPTH: usize = 32;
+
+/// 从 `response_format` 中提取 JSON Schema
+///
+/// 仅 `type` 为 `json_schema` 且提供了 `json_schema.schema` 时返回
+pub fn response_schema(response_format: &Value) -> Option<&Value> {
+    if response_format.get("type").and_then(Value::as_str) != Some("json_schema") {
+        return None;
+    }
+    response_format
+        .get("json_schema")
+        .and_then(|s| s.get("schema"))
+        .filter(|schema| schema.is_object() || schema.is_boolean())
+}
+
+/// 校验响应内容是否符合 `response_format` 中的 JSON Schema
+///
+/// # 返回
+/// - `Some(true)`: 内容为合法 JSON 且符合 Schema
+/// - `Some(false)`: 内容不是 JSON 或不符合 Schema
+/// - `None`: 未提供 Schema，无法判断
+pub fn validate_response(response_format: &Value, content: &str) -> Option<bool> {
+    let schema = response_schema(response_format)?;
+    let valid = serde_json::from_str::<Value>(content.trim())
+        .map(|instance| SchemaValidator::new(schema).is_valid(schema, &instance, 0))
+        .unwrap_or(false);
+    Some(valid)
+}
+
+/// JSON Schema 校验器
+struct SchemaValidator<'a> {
+    /// 根 Schema（用于解析 `$ref`）
+    root: &'a Value,
+}
+
+impl<'a> SchemaValidator<'a> {
+    fn new(root: &'a Value) -> Self {
+        Self { root }
+    }
+
+    fn is_valid(&self, schema: &Value, instance: &Value, depth: usize) -> bool {
+        let schema = match schema {
+            Value::Bool(allowed) => return *allowed,
+            Value::Object(schema) => schema,
+            _ => return true,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            if depth >= MAX_REF_DEPTH {
+                return false;
+            }
+            return match self.resolve_ref(reference) {
+                Some(target) => self.is_valid(target, instance, depth + 1),
+                None => false,
+            };
+        }
+
+        if let Some(expected) = schema.get("type") {
+            let matches = match expected {
+                Value::String(t) => type_matches(t, instance),
+                Value::Array(types) => types
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .any(|t| type_matches(t, instance)),
+                _ => true,
+            };
+            if !matches {
+                return false;
+            }
+        }
+
+        if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+            if !options.contains(instance) {
+                return false;
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if constant != instance {
+                return false;
+            }
+        }
+
+        if let Some(all_of) = schema.get("allOf").and_then(Value::as_array) {
+            if !all_of.iter().all(|s| self.is_valid(s, instance, depth)) {
+                return false;
+            }
+        }
+        if let Some(any_of) = schema.get("anyOf").and_then(Value::as_array) {
+            if !any_of.iter().any(|s| self.is_valid(s, instance, depth)) {
+                return false;
+            }
+        }
+        if let Some(one_of) = schema.get("oneOf").and_then(Value::as_array) {
+            let matched = one_of
+                .iter()
+                .filter(|s| self.is_valid(s, instance, depth))
+                .count();
+            if matched != 1 {
+                return false;
+            }
+        }
+
+        match instance {
+            Value::Object(object) => {
+                if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                    let missing = required
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .any(|key| !object.contains_key(key));
+                    if missing {
+                        return false;
+                    }
+                }
+
+                let properties = schema.get("properties").and_then(Value::as_object);
+                for (key, value) in object {
+                    let valid = match properties.and_then(|p| p.get(key)) {
+                        Some(property) => self.is_valid(property, value, depth),
+                        None => match schema.get("additionalProperties") {
+                            Some(additional) => self.is_valid(additional, value, depth),
+                            None => true,
+                        },
+                    };
+                    if !valid {
+                        return false;
+                    }
+                }
+            }
+            Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    if !items
+                        .iter()
+                        .all(|item| self.is_valid(item_schema, item, depth))
+                    {
+                        return false;
+                    }
+                }
+                let len = items.len() as u64;
+                if exceeds_bounds(schema, "minItems", "maxItems", len) {
+                    return false;
+                }
+            }
+            Value::String(text) => {
+                let len = text.chars().count() as u64;
+                if exceeds_bounds(schema, "minLength", "maxLength", len) {
+                    return false;
+                }
+            }
+            Value::Number(number) => {
+                if let Some(value) = number.as_f64() {
+                    let below = schema
+                        .get("minimum")
+                        .and_then(Value::as_f64)
+                        .is_some_and(|min| value < min);
+                    let above = schema
+                        .get("maximum")
+                        .and_then(Value::as_f64)
+                        .is_some_and(|max| value > max);
+                    if below || above {
+                        return false;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        true
+    }
+
+    /// 解析本地 `$ref`（`#`、`#/$defs/...`、`#/definitions/...` 等 JSON Pointer）
+    fn resolve_ref(&self, reference: &str) -> Option<&'a Value> {
+        let pointer = reference.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+}
+
+/// 检查实例是否为指定的 JSON Schema 类型
+fn type_matches(expected: &str, instance: &Value) -> bool {
+    match expected {
+        "object" => instance.is_object(),
+        "array" => instance.is_array(),
+        "string" => instance.is_string(),
+        "boolean" => instance.is_boolean(),
+        "null" => instance.is_null(),
+        "number" => instance.is_number(),
+        "integer" => {
+            instance.is_i64()
+                || instance.is_u64()
+                || instance.as_f64().is_some_and(|v| v.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+/// 检查长度是否超出 min/max 关键字限制
+fn exceeds_bounds(schema: &serde_json::Map<String, Value>, min: &str, max: &str, len: u64) -> bool {
+    let below = schema
+        .get(min)
+        .and_then(Value::as_u64)
+        .is_some_and(|min| len < min);
+    let above = schema
+        .get(max)
+        .and_then(Value::as_u64)
+        .is_some_and(|max| len > max);
+    below || above
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn weather_format() -> Value {
+        json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": "weather",
+                "strict": true,
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "city": {"type": "string", "minLength": 1},
+                        "unit": {"enum": ["celsius", "fahrenheit"]},
+                        "forecast": {"type": "array", "items": {"$ref": "#/$defs/day"}}
+                    },
+                    "required": ["city", "unit"],
+                    "additionalProperties": false,
+                    "$defs": {
+                        "day": {
+                            "type": "object",
+                            "properties": {"high": {"type": "integer"}},
+                            "required": ["high"]
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_valid_response() {
+        let content = r#"{"city":"Paris","unit":"celsius","forecast":[{"high":21}]}"#;
+        assert_eq!(validate_response(&weather_format(), content), Some(true));
+    }
+
+    #[test]
+    fn test_schema_violations() {
+        let format = weather_format();
+        for content in [
+            r#"{"city":"Paris"}"#,
+            r#"{"city":"Paris","unit":"kelvin"}"#,
+            r#"{"city":"Paris","unit":"celsius","extra":1}"#,
+            r#"{"city":"Paris","unit":"celsius","forecast":[{"high":"warm"}]}"#,
+            r#"{"city":"","unit":"celsius"}"#,
+            "Sure! Here is the JSON: {}",
+        ] {
+            assert_eq!(
+                validate_response(&format, content),
+                Some(false),
+                "{}",
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_schema_leaves_flag_unset() {
+        assert_eq!(
+            validate_response(&json!({"type": "json_object"}), "not json"),
+            None
+        );
+        assert_eq!(
+            validate_response(
+                &json!({"type": "json_schema", "json_schema": {"name": "x"}}),
+                "{}"
+            ),
+            None
+        );
+    }
+}
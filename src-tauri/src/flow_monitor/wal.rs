@@ -0,0 +1,211 @@
+//! 请求预写日志（WAL）
+//!
+//! 审计场景下，进程在请求进行中崩溃时不能丢失请求。启用后，Flow 开始捕获时（调用上游之前）
+//! 先把请求和元数据追加到 WAL 并落盘，Flow 结束并持久化后再追加完成记录。
+//!
+//! 下次打开时仍未完成的条目说明进程在请求结束前退出，上游是否已处理未知。这些条目恢复为
+//! 结果未知的 Flow，可以查看或重放（至少一次语义）。
+//!
+//! 没有进行中的条目时截断日志，日志大小只与同时进行的请求数有关。
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use super::models::LLMFlow;
+
+/// 读取时的 WAL 记录
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum WalRecord {
+    /// 请求开始（调用上游之前）
+    Begin { flow: Box<LLMFlow> },
+    /// 请求结束（Flow 已持久化）
+    Done { id: String },
+}
+
+/// 写入时的 WAL 记录（借用 Flow，避免复制请求体）
+#[derive(Debug, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum WalRecordRef<'a> {
+    Begin { flow: &'a LLMFlow },
+    Done { id: &'a str },
+}
+
+#[derive(Debug)]
+struct WalState {
+    file: File,
+    /// 已开始、尚未完成的 Flow ID（包括待恢复的条目）
+    in_flight: HashSet<String>,
+}
+
+/// 请求预写日志
+#[derive(Debug)]
+pub struct RequestWal {
+    path: PathBuf,
+    state: Mutex<WalState>,
+    /// 打开时发现的未完成条目，由 [`RequestWal::take_recovered`] 取走
+    recovered: Mutex<Vec<LLMFlow>>,
+}
+
+impl RequestWal {
+    /// 打开 WAL，读取上次运行中未完成的条目
+    ///
+    /// 日志被重写为只含未完成的条目（先写临时文件再替换）。恢复的条目在 [`RequestWal::complete`]
+    /// 之前一直留在日志中，恢复过程中再次崩溃也不会丢失。
+    pub fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let unfinished = read_unfinished(&path)?;
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut tmp = File::create(&tmp_path)?;
+            for flow in &unfinished {
+                write_record(&mut tmp, &WalRecordRef::Begin { flow })?;
+            }
+            tmp.sync_all()?;
+        }
+        std::fs::rename(&tmp_path, &path)?;
+
+        let file = OpenOptions::new().append(true).open(&path)?;
+        let in_flight = unfinished.iter().map(|flow| flow.id.clone()).collect();
+        Ok(Self {
+            path,
+            state: Mutex::new(WalState { file, in_flight }),
+            recovered: Mutex::new(unfinished),
+        })
+    }
+
+    /// 日志文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 记录请求开始，返回前数据已落盘
+    pub fn begin(&self, flow: &LLMFlow) -> std::io::Result<()> {
+        let mut state = self.state.lock();
+        write_record(&mut state.file, &WalRecordRef::Begin { flow })?;
+        state.file.sync_data()?;
+        state.in_flight.insert(flow.id.clone());
+        Ok(())
+    }
+
+    /// 记录请求结束
+    ///
+    /// 完成记录不单独落盘：崩溃时丢失完成记录只会让已完成的请求多恢复一次。
+    pub fn complete(&self, flow_id: &str) -> std::io::Result<()> {
+        let mut state = self.state.lock();
+        if !state.in_flight.remove(flow_id) {
+            return Ok(());
+        }
+        if state.in_flight.is_empty() {
+            // 没有进行中的条目，整个日志都可以丢弃
+            state.file.set_len(0)?;
+            state.file.sync_data()
+        } else {
+            write_record(&mut state.file, &WalRecordRef::Done { id: flow_id })
+        }
+    }
+
+    /// 取走打开时发现的未完成条目（只返回一次）
+    pub fn take_recovered(&self) -> Vec<LLMFlow> {
+        std::mem::take(&mut *self.recovered.lock())
+    }
+
+    /// 进行中的条目数
+    pub fn in_flight_count(&self) -> usize {
+        self.state.lock().in_flight.len()
+    }
+}
+
+fn write_record(file: &mut File, record: &WalRecordRef<'_>) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+    file.write_all(&line)
+}
+
+/// 读取日志中已开始、未完成的条目（按开始顺序）
+///
+/// 崩溃时最后一行可能只写了一半，无法解析的行跳过。
+fn read_unfinished(path: &Path) -> std::io::Result<Vec<LLMFlow>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut pending: Vec<LLMFlow> = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<WalRecord>(&line) {
+            Ok(WalRecord::Begin { flow }) => pending.push(*flow),
+            Ok(WalRecord::Done { id }) => pending.retain(|flow| flow.id != id),
+            Err(e) => tracing::warn!("跳过无法解析的 WAL 记录: {}", e),
+        }
+    }
+    Ok(pending)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::models::{FlowMetadata, FlowType, LLMRequest};
+    use tempfile::TempDir;
+
+    fn flow(id: &str) -> LLMFlow {
+        LLMFlow::new(
+            id.to_string(),
+            FlowType::ChatCompletions,
+            LLMRequest {
+                model: "gpt-4".to_string(),
+                ..Default::default()
+            },
+            FlowMetadata::default(),
+        )
+    }
+
+    #[test]
+    fn test_crash_before_completion_is_recovered() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("wal").join("requests.wal");
+
+        {
+            let wal = RequestWal::open(&path).unwrap();
+            assert!(wal.take_recovered().is_empty());
+            wal.begin(&flow("done")).unwrap();
+            wal.begin(&flow("crashed")).unwrap();
+            wal.complete("done").unwrap();
+            // 模拟崩溃：`crashed` 从未标记完成，最后一行只写了一半
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(br#"{"op":"begin","flow":{"id":"#).unwrap();
+        }
+
+        let wal = RequestWal::open(&path).unwrap();
+        let recovered = wal.take_recovered();
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].id, "crashed");
+        assert_eq!(recovered[0].request.model, "gpt-4");
+        assert!(wal.take_recovered().is_empty());
+        assert_eq!(wal.in_flight_count(), 1);
+
+        // 恢复后标记完成前再次崩溃，条目仍在
+        drop(wal);
+        let wal = RequestWal::open(&path).unwrap();
+        assert_eq!(wal.take_recovered().len(), 1);
+
+        wal.complete("crashed").unwrap();
+        assert_eq!(wal.in_flight_count(), 0);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        drop(wal);
+        assert!(RequestWal::open(&path).unwrap().take_recovered().is_empty());
+    }
+}
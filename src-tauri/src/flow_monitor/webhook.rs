@@ -0,0 +1,386 @@
+//! 通知 Webhook 推送
+//!
+//! 将通知事件以 JSON 形式 POST 到配置的 Webhook 地址（如 Slack、PagerDuty），
+//! 供无界面部署使用。投递在后台任务中进行并带指数退避重试，不会阻塞 Flow 处理。
+//!
+//! 配置了密钥时，使用 HMAC-SHA256 对请求体签名，签名以 `sha256=<hex>` 形式
+//! 放在 `X-ProxyCast-Signature` 请求头中，接收方可据此校验来源。
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::Duration;
+use thiserror::Error;
+
+use super::monitor::NotificationEvent;
+
+/// 签名请求头名称
+pub const SIGNATURE_HEADER: &str = "X-ProxyCast-Signature";
+
+// ============================================================================
+// 错误类型
+// ============================================================================
+
+/// Webhook 错误
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum WebhookError {
+    /// 载荷模板渲染结果不是有效 JSON
+    #[error("Webhook 载荷模板渲染结果不是有效 JSON: {0}")]
+    InvalidTemplate(String),
+    /// 请求发送失败
+    #[error("Webhook 请求失败: {0}")]
+    Request(String),
+    /// 接收方返回非成功状态码
+    #[error("Webhook 返回非成功状态码: {0}")]
+    Status(u16),
+}
+
+// ============================================================================
+// 配置结构
+// ============================================================================
+
+/// Webhook 投递设置
+///
+/// 各通知类型的 Webhook 地址在 `NotificationSettings::webhook_url` 中配置，
+/// 这里是所有类型共用的投递参数。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookSettings {
+    /// 载荷模板（为空时使用默认 JSON 载荷）
+    ///
+    /// 支持占位符 `{{type}}`、`{{title}}`、`{{message}}`、`{{flow_id}}`、
+    /// `{{model}}`、`{{timestamp}}`、`{{count}}`，替换值已做 JSON 字符串转义。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_template: Option<String>,
+    /// HMAC 签名密钥（为空时不签名）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// 失败后的最大重试次数
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// 首次重试前的等待时间（毫秒），之后每次翻倍
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// 单次请求超时（毫秒）
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    500
+}
+
+fn default_timeout_ms() -> u64 {
+    10_000
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            payload_template: None,
+            secret: None,
+            max_retries: default_max_retries(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+// ============================================================================
+// 载荷与签名
+// ============================================================================
+
+/// 渲染通知事件的 Webhook 载荷
+pub fn render_payload(
+    template: Option<&str>,
+    event: &NotificationEvent,
+) -> Result<String, WebhookError> {
+    let notification_type = serde_json::to_value(&event.notification_type)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default();
+
+    let Some(template) = template else {
+        return Ok(serde_json::json!({
+            "type": notification_type,
+            "title": event.title,
+            "message": event.message,
+            "flow_id": event.flow_id,
+            "model": event.model,
+            "timestamp": event.timestamp,
+            "count": event.count,
+        })
+        .to_string());
+    };
+
+    let timestamp = event.timestamp.to_rfc3339();
+    let count = event.count.to_string();
+    let replacements = [
+        ("{{type}}", notification_type.as_str()),
+        ("{{title}}", event.title.as_str()),
+        ("{{message}}", event.message.as_str()),
+        ("{{flow_id}}", event.flow_id.as_str()),
+        ("{{model}}", event.model.as_str()),
+        ("{{timestamp}}", timestamp.as_str()),
+        ("{{count}}", count.as_str()),
+    ];
+    let mut payload = template.to_string();
+    for (placeholder, value) in replacements {
+        payload = payload.replace(placeholder, &escape_json_string(value));
+    }
+
+    serde_json::from_str::<serde_json::Value>(&payload)
+        .map_err(|e| WebhookError::InvalidTemplate(e.to_string()))?;
+    Ok(payload)
+}
+
+/// 计算载荷签名（`sha256=<hex>`）
+pub fn sign_payload(secret: &str, payload: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC 支持任意长度的密钥");
+    mac.update(payload.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+/// 转义为 JSON 字符串内容（不含两侧引号）
+fn escape_json_string(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+// ============================================================================
+// 投递
+// ============================================================================
+
+/// Webhook 投递器
+#[derive(Debug, Clone, Default)]
+pub struct WebhookSink {
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    /// 创建新的投递器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在后台任务中投递通知，立即返回
+    pub fn dispatch(&self, url: String, event: NotificationEvent, settings: WebhookSettings) {
+        let payload = match render_payload(settings.payload_template.as_deref(), &event) {
+            Ok(payload) => payload,
+            Err(e) => {
+                tracing::warn!("[WEBHOOK] Flow {} 通知载荷渲染失败: {}", event.flow_id, e);
+                return;
+            }
+        };
+
+        let sink = self.clone();
+        tokio::spawn(async move {
+            match sink.deliver(&url, &payload, &settings).await {
+                Ok(attempts) => tracing::debug!(
+                    "[WEBHOOK] Flow {} 通知已投递到 {}（第 {} 次尝试）",
+                    event.flow_id,
+                    url,
+                    attempts
+                ),
+                Err(e) => tracing::warn!(
+                    "[WEBHOOK] Flow {} 通知投递到 {} 失败: {}",
+                    event.flow_id,
+                    url,
+                    e
+                ),
+            }
+        });
+    }
+
+    /// 投递载荷，失败时按指数退避重试
+    ///
+    /// # 返回
+    /// 成功时返回实际尝试次数；重试耗尽时返回最后一次的错误
+    pub async fn deliver(
+        &self,
+        url: &str,
+        payload: &str,
+        settings: &WebhookSettings,
+    ) -> Result<u32, WebhookError> {
+        let signature = settings
+            .secret
+            .as_deref()
+            .filter(|s| !s.is_empty())
+            .map(|secret| sign_payload(secret, payload));
+
+        let mut backoff = Duration::from_millis(settings.initial_backoff_ms);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match self
+                .send_once(url, payload, signature.as_deref(), settings)
+                .await
+            {
+                Ok(()) => return Ok(attempt),
+                Err(e) if attempt > settings.max_retries => return Err(e),
+                Err(e) => {
+                    tracing::debug!(
+                        "[WEBHOOK] 第 {} 次投递到 {} 失败，{}ms 后重试: {}",
+                        attempt,
+                        url,
+                        backoff.as_millis(),
+                        e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+            }
+        }
+    }
+
+    /// 发送一次请求
+    async fn send_once(
+        &self,
+        url: &str,
+        payload: &str,
+        signature: Option<&str>,
+        settings: &WebhookSettings,
+    ) -> Result<(), WebhookError> {
+        let mut request = self
+            .client
+            .post(url)
+            .timeout(Duration::from_millis(settings.timeout_ms))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(payload.to_string());
+        if let Some(signature) = signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| WebhookError::Request(e.to_string()))?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(WebhookError::Status(response.status().as_u16()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::monitor::NotificationSettings;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    fn error_event() -> NotificationEvent {
+        NotificationEvent::error_flow(
+            "flow-1".to_string(),
+            "gpt-4".to_string(),
+            "upstream \"timeout\"".to_string(),
+            &NotificationSettings::default(),
+        )
+    }
+
+    #[test]
+    fn test_default_payload() {
+        let payload = render_payload(None, &error_event()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&payload).unwrap();
+
+        assert_eq!(value["type"], "ErrorFlow");
+        assert_eq!(value["flow_id"], "flow-1");
+        assert_eq!(value["model"], "gpt-4");
+        assert_eq!(value["count"], 1);
+        assert!(value["message"].as_str().unwrap().contains("\"timeout\""));
+    }
+
+    #[test]
+    fn test_template_payload_escapes_values() {
+        let template = r#"{"text": "[{{type}}] {{model}}: {{message}}"}"#;
+        let payload = render_payload(Some(template), &error_event()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&payload).unwrap();
+
+        assert_eq!(
+            value["text"],
+            "[ErrorFlow] gpt-4: 模型: gpt-4, 错误: upstream \"timeout\""
+        );
+
+        let result = render_payload(Some("{\"text\": {{message}}"), &error_event());
+        assert!(matches!(result, Err(WebhookError::InvalidTemplate(_))));
+    }
+
+    #[test]
+    fn test_sign_payload() {
+        // RFC 4231 测试用例 2
+        assert_eq!(
+            sign_payload("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deliver_retries_and_signs() {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let received = Arc::new(Mutex::new(None));
+
+        let app = {
+            let attempts = attempts.clone();
+            let received = received.clone();
+            axum::Router::new().route(
+                "/hook",
+                axum::routing::post(
+                    move |headers: axum::http::HeaderMap, body: String| async move {
+                        // 第一次返回 500，触发重试
+                        if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                            return axum::http::StatusCode::INTERNAL_SERVER_ERROR;
+                        }
+                        let signature = headers
+                            .get(SIGNATURE_HEADER)
+                            .and_then(|v| v.to_str().ok())
+                            .map(|s| s.to_string());
+                        *received.lock().unwrap() = Some((signature, body));
+                        axum::http::StatusCode::OK
+                    },
+                ),
+            )
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let settings = WebhookSettings {
+            secret: Some("s3cret".to_string()),
+            initial_backoff_ms: 1,
+            ..Default::default()
+        };
+        let payload = render_payload(None, &error_event()).unwrap();
+        let sink = WebhookSink::new();
+        let result = sink
+            .deliver(&format!("http://{}/hook", addr), &payload, &settings)
+            .await;
+
+        assert_eq!(result, Ok(2));
+        let (signature, body) = received.lock().unwrap().clone().unwrap();
+        assert_eq!(body, payload);
+        assert_eq!(signature, Some(sign_payload("s3cret", &payload)));
+    }
+
+    #[tokio::test]
+    async fn test_deliver_gives_up_after_max_retries() {
+        let settings = WebhookSettings {
+            max_retries: 1,
+            initial_backoff_ms: 1,
+            timeout_ms: 500,
+            ..Default::default()
+        };
+        let result = WebhookSink::new()
+            .deliver("http://127.0.0.1:1/hook", "{}", &settings)
+            .await;
+        assert!(matches!(result, Err(WebhookError::Request(_))));
+    }
+}
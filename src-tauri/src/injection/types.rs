@@ -16,6 +16,8 @@ const ALLOWED_INJECTION_PARAMS: &[&str] = &[
     "stop",
     "seed",
     "n",
+    "logprobs",
+    "top_logprobs",
 ];
 
 /// 禁止注入的参数黑名单（即使在白名单中也不允许 Override 模式）
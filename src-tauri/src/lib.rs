@@ -1,3 +1,4 @@
+pub mod audit;
 pub mod codex_config_loader;
 mod commands;
 mod config;
@@ -254,6 +255,36 @@ async fn get_server_status(
     Ok(s.status())
 }
 
+#[tauri::command]
+async fn get_routing_gate_status(
+    state: tauri::State<'_, AppState>,
+) -> Result<server::routing_gate::RoutingGateStatus, String> {
+    let s = state.read().await;
+    Ok(s.routing_gate.status())
+}
+
+#[tauri::command]
+async fn set_routing_gate_mode(
+    state: tauri::State<'_, AppState>,
+    logs: tauri::State<'_, LogState>,
+    mode: server::routing_gate::RoutingGateMode,
+    retry_after_seconds: Option<u64>,
+) -> Result<server::routing_gate::RoutingGateStatus, String> {
+    let s = state.read().await;
+    if let Some(seconds) = retry_after_seconds {
+        s.routing_gate.set_retry_after_seconds(seconds);
+    }
+    let status = s.routing_gate.set_mode(mode);
+    logs.write().await.add(
+        "info",
+        &format!(
+            "[GATE] 路由闸门已切换为 {} (进行中请求: {})",
+            status.mode, status.in_flight
+        ),
+    );
+    Ok(status)
+}
+
 #[tauri::command]
 async fn get_config(state: tauri::State<'_, AppState>) -> Result<config::Config, String> {
     let s = state.read().await;
@@ -1164,6 +1195,12 @@ async fn check_api_compatibility(
                     }]),
                     tool_choice: None,
                     reasoning_effort: None,
+                    thinking: None,
+                    response_format: None,
+                    logprobs: None,
+                    top_logprobs: None,
+                    seed: None,
+                    stream_options: None,
                 }
             }
             _ => {
@@ -1184,6 +1221,12 @@ async fn check_api_compatibility(
                     tools: None,
                     tool_choice: None,
                     reasoning_effort: None,
+                    thinking: None,
+                    response_format: None,
+                    logprobs: None,
+                    top_logprobs: None,
+                    seed: None,
+                    stream_options: None,
                 }
             }
         };
@@ -1498,6 +1541,11 @@ pub fn run() {
     let state: AppState = Arc::new(RwLock::new(server::ServerState::new(config.clone())));
     let logs: LogState = Arc::new(RwLock::new(logger::LogStore::with_config(&config.logging)));
 
+    // 初始化审计日志（失败时仅记录错误，不影响启动）
+    if let Err(err) = audit::init(&audit::default_dir()) {
+        tracing::error!("审计日志初始化失败: {}", err);
+    }
+
     // Initialize database for Switch functionality
     let db = match database::init_database() {
         Ok(conn) => conn,
@@ -1562,21 +1610,29 @@ pub fn run() {
     .expect("Failed to create TelemetryState");
 
     // Initialize FlowMonitor and FlowQueryService
-    let flow_monitor_config = FlowMonitorConfig::default();
+    // 预写日志路径需在创建 FlowMonitor 前确定，启动时即可恢复上次未完成的请求
+    let flow_monitor_config = FlowMonitorConfig {
+        wal_path: config
+            .flow_monitor
+            .wal_path
+            .as_ref()
+            .map(std::path::PathBuf::from),
+        ..FlowMonitorConfig::default()
+    };
+    // 获取应用数据目录
+    let flow_data_dir = dirs::data_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("proxycast")
+        .join("flows");
+    let flow_snapshot_path = flow_data_dir.join("memory_snapshot.json");
     let flow_file_store = {
-        // 获取应用数据目录
-        let data_dir = dirs::data_dir()
-            .unwrap_or_else(|| std::path::PathBuf::from("."))
-            .join("proxycast")
-            .join("flows");
-
         // 创建目录（如果不存在）
-        if let Err(e) = std::fs::create_dir_all(&data_dir) {
+        if let Err(e) = std::fs::create_dir_all(&flow_data_dir) {
             tracing::warn!("无法创建 Flow 存储目录: {}", e);
         }
 
         let rotation_config = flow_monitor::RotationConfig::default();
-        match FlowFileStore::new(data_dir, rotation_config) {
+        match FlowFileStore::new(flow_data_dir.clone(), rotation_config) {
             Ok(store) => Some(Arc::new(store)),
             Err(e) => {
                 tracing::warn!("无法初始化 Flow 文件存储: {}", e);
@@ -1590,6 +1646,20 @@ pub fn run() {
     ));
     let flow_monitor_state = FlowMonitorState(flow_monitor.clone());
 
+    // 恢复上次正常退出时保存的内存 Flow 快照
+    match tauri::async_runtime::block_on(flow_monitor.restore_memory_snapshot(&flow_snapshot_path))
+    {
+        Ok(Some(count)) => tracing::info!("[启动] 已从快照恢复 {} 个 Flow", count),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("[启动] 恢复 Flow 快照失败: {}", e),
+    }
+    // 恢复上次崩溃时进行中的请求（需配置 wal_path）
+    let recovered = tauri::async_runtime::block_on(flow_monitor.recover_wal());
+    if recovered > 0 {
+        tracing::info!("[启动] 已从预写日志恢复 {} 个结果未知的请求", recovered);
+    }
+    let snapshot_monitor = flow_monitor.clone();
+
     // 初始化 Flow 拦截器
     let flow_interceptor = Arc::new(FlowInterceptor::new(InterceptConfig::default()));
     let flow_interceptor_state = FlowInterceptorState(flow_interceptor.clone());
@@ -1819,6 +1889,10 @@ pub fn run() {
                     }
                 }
             });
+            // 启动 Flow 保留策略调度器
+            tauri::async_runtime::spawn(flow_monitor::retention::run_retention_scheduler(
+                flow_monitor_clone.clone(),
+            ));
             // 自动启动服务器
             let state = state_clone.clone();
             let logs = logs_clone.clone();
@@ -1920,6 +1994,8 @@ pub fn run() {
             start_server,
             stop_server,
             get_server_status,
+            get_routing_gate_status,
+            set_routing_gate_mode,
             get_config,
             save_config,
             get_default_provider,
@@ -1993,6 +2069,8 @@ pub fn run() {
             commands::config_cmd::export_bundle,
             commands::config_cmd::export_config_yaml,
             commands::config_cmd::validate_import,
+            commands::config_cmd::reload_validate_only,
+            commands::config_cmd::preview_import,
             commands::config_cmd::import_bundle,
             // Path utility commands
             commands::config_cmd::expand_path,
@@ -2093,6 +2171,9 @@ pub fn run() {
             commands::resilience_cmd::update_failover_config,
             commands::resilience_cmd::get_switch_log,
             commands::resilience_cmd::clear_switch_log,
+            // Audit commands
+            commands::audit_cmd::verify_audit_log,
+            commands::audit_cmd::get_audit_entries,
             // Telemetry commands
             commands::telemetry_cmd::get_request_logs,
             commands::telemetry_cmd::get_request_log_detail,
@@ -2135,9 +2216,13 @@ pub fn run() {
             // Flow Monitor commands
             commands::flow_monitor_cmd::query_flows,
             commands::flow_monitor_cmd::get_flow_detail,
+            commands::flow_monitor_cmd::explain_flow_routing,
             commands::flow_monitor_cmd::search_flows,
             commands::flow_monitor_cmd::get_flow_stats,
+            commands::flow_monitor_cmd::get_flow_threads,
             commands::flow_monitor_cmd::export_flows,
+            commands::flow_monitor_cmd::verify_export,
+            commands::flow_monitor_cmd::export_flow_conversation,
             commands::flow_monitor_cmd::update_flow_annotations,
             commands::flow_monitor_cmd::toggle_flow_starred,
             commands::flow_monitor_cmd::add_flow_comment,
@@ -2147,16 +2232,25 @@ pub fn run() {
             commands::flow_monitor_cmd::cleanup_flows,
             commands::flow_monitor_cmd::get_recent_flows,
             commands::flow_monitor_cmd::get_flow_monitor_status,
+            commands::flow_monitor_cmd::get_slo_status,
+            commands::flow_monitor_cmd::explain_flow_capture_decision,
+            commands::flow_monitor_cmd::list_active_flows,
+            commands::flow_monitor_cmd::force_cancel_active_flow,
+            commands::flow_monitor_cmd::import_mitm_flows,
             commands::flow_monitor_cmd::get_flow_monitor_debug_info,
             commands::flow_monitor_cmd::create_test_flows,
             commands::flow_monitor_cmd::enable_flow_monitor,
             commands::flow_monitor_cmd::disable_flow_monitor,
+            commands::flow_monitor_cmd::get_flow_monitor_config,
+            commands::flow_monitor_cmd::set_flow_monitor_config,
             commands::flow_monitor_cmd::subscribe_flow_events,
             commands::flow_monitor_cmd::get_all_flow_tags,
             // Flow Monitor filter expression commands
             commands::flow_monitor_cmd::parse_filter,
             commands::flow_monitor_cmd::validate_filter,
+            commands::flow_monitor_cmd::test_filter,
             commands::flow_monitor_cmd::get_filter_help_items,
+            commands::flow_monitor_cmd::get_filter_help_fields,
             commands::flow_monitor_cmd::get_filter_help_text,
             commands::flow_monitor_cmd::query_flows_with_expression,
             // Flow Interceptor commands
@@ -2177,9 +2271,17 @@ pub fn run() {
             commands::flow_monitor_cmd::update_threshold_config,
             commands::flow_monitor_cmd::get_request_rate,
             commands::flow_monitor_cmd::set_rate_window,
+            // Flow Monitor auto-tag commands
+            commands::flow_monitor_cmd::get_auto_tag_config,
+            commands::flow_monitor_cmd::update_auto_tag_config,
             // Flow Replayer commands
             commands::flow_monitor_cmd::replay_flow,
             commands::flow_monitor_cmd::replay_flows_batch,
+            commands::flow_monitor_cmd::replay_flow_repeat,
+            commands::flow_monitor_cmd::cancel_flow_replay_repeat,
+            commands::flow_monitor_cmd::cancel_flow_replay_batch,
+            commands::flow_monitor_cmd::replay_flow_streaming,
+            commands::flow_monitor_cmd::subscribe_replay_stream_events,
             // Flow Diff commands
             commands::flow_monitor_cmd::diff_flows,
             // Session Management commands
@@ -2233,7 +2335,11 @@ pub fn run() {
             commands::flow_monitor_cmd::get_request_trend,
             commands::flow_monitor_cmd::get_token_distribution,
             commands::flow_monitor_cmd::get_latency_histogram,
+            commands::flow_monitor_cmd::get_stream_latency_percentiles,
+            commands::flow_monitor_cmd::reset_stream_latency_histograms,
             commands::flow_monitor_cmd::export_stats_report,
+            commands::flow_monitor_cmd::get_stats_output,
+            commands::flow_monitor_cmd::get_prompt_template_stats,
             // Batch Operations commands
             commands::flow_monitor_cmd::batch_star_flows,
             commands::flow_monitor_cmd::batch_unstar_flows,
@@ -2242,6 +2348,9 @@ pub fn run() {
             commands::flow_monitor_cmd::batch_export_flows,
             commands::flow_monitor_cmd::batch_delete_flows,
             commands::flow_monitor_cmd::batch_add_to_session,
+            commands::flow_monitor_cmd::preview_delete_flows_by_filter,
+            commands::flow_monitor_cmd::delete_flows_by_filter,
+            commands::flow_monitor_cmd::flow_batch_ops,
             // Window control commands
             commands::window_cmd::get_window_size,
             commands::window_cmd::set_window_size,
@@ -2254,8 +2363,25 @@ pub fn run() {
             commands::window_cmd::toggle_fullscreen,
             commands::window_cmd::is_fullscreen,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(move |_app, event| {
+            // 正常退出时按配置保存内存 Flow 快照
+            if let tauri::RunEvent::Exit = event {
+                tauri::async_runtime::block_on(async {
+                    if !snapshot_monitor.config().await.snapshot_on_shutdown {
+                        return;
+                    }
+                    match snapshot_monitor
+                        .save_memory_snapshot(&flow_snapshot_path)
+                        .await
+                    {
+                        Ok(count) => tracing::info!("[退出] 已保存 {} 个 Flow 到快照", count),
+                        Err(e) => tracing::warn!("[退出] 保存 Flow 快照失败: {}", e),
+                    }
+                });
+            }
+        });
 }
 
 fn is_loopback_host(host: &str) -> bool {
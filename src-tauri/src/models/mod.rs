@@ -3,6 +3,7 @@ pub mod app_type;
 pub mod codewhisperer;
 pub mod mcp_model;
 pub mod openai;
+pub mod openai_responses;
 pub mod prompt_model;
 pub mod provider_model;
 pub mod provider_pool_model;
@@ -17,6 +18,8 @@ pub use codewhisperer::*;
 pub use mcp_model::McpServer;
 #[allow(unused_imports)]
 pub use openai::*;
+#[allow(unused_imports)]
+pub use openai_responses::*;
 pub use prompt_model::Prompt;
 pub use provider_model::Provider;
 #[allow(unused_imports)]
@@ -0,0 +1,161 @@
+//! Flow 插件
+//!
+//! 在请求发往上游之前、响应返回客户端之前，对 [`LLMRequest`] / [`LLMResponse`]
+//! 进行同步变换。与基于脚本的 [`Plugin`](super::Plugin) 不同，Flow 插件
+//! 直接编译进程序，在启动或配置热重载时注册到 [`FlowPluginRegistry`]。
+//!
+//! 插件按注册顺序依次执行：
+//! - 返回 [`FlowPluginError::Rejected`] 时短路后续插件，请求以 400 拒绝
+//! - 返回其他错误或发生 panic 时同样中止，不会影响进程
+
+use parking_lot::RwLock;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::flow_monitor::{LLMRequest, LLMResponse};
+
+/// Flow 插件错误
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FlowPluginError {
+    /// 插件主动拒绝请求
+    #[error("请求被插件 {plugin} 拒绝: {reason}")]
+    Rejected { plugin: String, reason: String },
+
+    /// 插件执行失败
+    #[error("插件 {plugin} 执行失败: {message}")]
+    Failed { plugin: String, message: String },
+}
+
+impl FlowPluginError {
+    /// 创建拒绝错误
+    pub fn rejected(plugin: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self::Rejected {
+            plugin: plugin.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// 创建执行失败错误
+    pub fn failed(plugin: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Failed {
+            plugin: plugin.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Flow 插件
+///
+/// 请求变换以 `body` 为准：处理器会用修改后的 `body` 重新解析请求。
+pub trait FlowPlugin: Send + Sync {
+    /// 插件名称（在注册表中唯一）
+    fn name(&self) -> &str;
+
+    /// 请求发往上游之前调用
+    fn on_request(&self, _req: &mut LLMRequest) -> Result<(), FlowPluginError> {
+        Ok(())
+    }
+
+    /// 响应返回客户端之前调用
+    fn on_response(&self, _resp: &mut LLMResponse) -> Result<(), FlowPluginError> {
+        Ok(())
+    }
+}
+
+/// Flow 插件注册表
+#[derive(Default)]
+pub struct FlowPluginRegistry {
+    plugins: RwLock<Vec<Arc<dyn FlowPlugin>>>,
+}
+
+impl FlowPluginRegistry {
+    /// 创建空注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册插件
+    ///
+    /// 已存在同名插件时原位替换，保持执行顺序不变。
+    pub fn register(&self, plugin: Arc<dyn FlowPlugin>) {
+        let mut plugins = self.plugins.write();
+        match plugins.iter_mut().find(|p| p.name() == plugin.name()) {
+            Some(existing) => *existing = plugin,
+            None => plugins.push(plugin),
+        }
+    }
+
+    /// 注销插件，返回是否存在
+    pub fn unregister(&self, name: &str) -> bool {
+        let mut plugins = self.plugins.write();
+        let before = plugins.len();
+        plugins.retain(|p| p.name() != name);
+        plugins.len() != before
+    }
+
+    /// 已注册插件名称（按执行顺序）
+    pub fn names(&self) -> Vec<String> {
+        self.plugins
+            .read()
+            .iter()
+            .map(|p| p.name().to_string())
+            .collect()
+    }
+
+    /// 是否没有注册任何插件
+    pub fn is_empty(&self) -> bool {
+        self.plugins.read().is_empty()
+    }
+
+    /// 依次执行请求钩子
+    ///
+    /// 返回请求体是否被修改。
+    pub fn run_on_request(&self, req: &mut LLMRequest) -> Result<bool, FlowPluginError> {
+        let plugins = self.snapshot();
+        if plugins.is_empty() {
+            return Ok(false);
+        }
+        let original = req.body.clone();
+        for plugin in &plugins {
+            guarded(plugin.name(), || plugin.on_request(req))?;
+        }
+        Ok(req.body != original)
+    }
+
+    /// 依次执行响应钩子
+    ///
+    /// 返回响应体或文本内容是否被修改。
+    pub fn run_on_response(&self, resp: &mut LLMResponse) -> Result<bool, FlowPluginError> {
+        let plugins = self.snapshot();
+        if plugins.is_empty() {
+            return Ok(false);
+        }
+        let original_body = resp.body.clone();
+        let original_content = resp.content.clone();
+        for plugin in &plugins {
+            guarded(plugin.name(), || plugin.on_response(resp))?;
+        }
+        Ok(resp.body != original_body || resp.content != original_content)
+    }
+
+    /// 复制插件列表，执行期间不持有锁
+    fn snapshot(&self) -> Vec<Arc<dyn FlowPlugin>> {
+        self.plugins.read().clone()
+    }
+}
+
+/// 执行插件钩子，将 panic 转换为执行失败错误
+fn guarded(
+    name: &str,
+    hook: impl FnOnce() -> Result<(), FlowPluginError>,
+) -> Result<(), FlowPluginError> {
+    catch_unwind(AssertUnwindSafe(hook)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panic".to_string());
+        Err(FlowPluginError::failed(name, message))
+    })
+}
@@ -5,13 +5,18 @@
 //! - 请求前/响应后钩子
 //! - 插件隔离和错误处理
 //! - 插件配置管理
+//! - 编译内置的 Flow 插件（请求/响应变换）
 
+mod flow;
 mod loader;
 mod manager;
+mod system_prompt_prefix;
 mod types;
 
+pub use flow::{FlowPlugin, FlowPluginError, FlowPluginRegistry};
 pub use loader::PluginLoader;
 pub use manager::PluginManager;
+pub use system_prompt_prefix::SystemPromptPrefixPlugin;
 pub use types::{
     HookResult, Plugin, PluginConfig, PluginContext, PluginError, PluginInfo, PluginManifest,
     PluginState, PluginStatus, PluginType,
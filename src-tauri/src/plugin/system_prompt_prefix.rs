@@ -0,0 +1,94 @@
+//! 系统提示词前缀插件
+//!
+//! 为每个请求的系统提示词添加固定前缀，没有系统提示词时插入一条。
+//! 同时支持 Anthropic（顶层 `system` 字段）和 OpenAI（`role: system` 消息）格式。
+
+use serde_json::{json, Value};
+
+use super::flow::{FlowPlugin, FlowPluginError};
+use crate::flow_monitor::LLMRequest;
+
+/// 系统提示词前缀插件
+#[derive(Debug, Clone)]
+pub struct SystemPromptPrefixPlugin {
+    prefix: String,
+}
+
+impl SystemPromptPrefixPlugin {
+    /// 插件名称
+    pub const NAME: &'static str = "system_prompt_prefix";
+
+    /// 创建插件
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+
+    fn prefixed(&self, text: &str) -> String {
+        if text.is_empty() {
+            self.prefix.clone()
+        } else {
+            format!("{}\n\n{}", self.prefix, text)
+        }
+    }
+
+    /// Anthropic 格式：`system` 可以是字符串或内容块数组
+    fn apply_anthropic(&self, body: &mut serde_json::Map<String, Value>) {
+        match body.get_mut("system") {
+            Some(Value::String(text)) => *text = self.prefixed(text),
+            Some(Value::Array(blocks)) => {
+                blocks.insert(0, json!({"type": "text", "text": self.prefix}));
+            }
+            _ => {
+                body.insert("system".to_string(), Value::String(self.prefix.clone()));
+            }
+        }
+    }
+
+    /// OpenAI 格式：修改第一条 system 消息，没有时在开头插入
+    fn apply_openai(
+        &self,
+        body: &mut serde_json::Map<String, Value>,
+    ) -> Result<(), FlowPluginError> {
+        let messages = body
+            .get_mut("messages")
+            .and_then(Value::as_array_mut)
+            .ok_or_else(|| FlowPluginError::failed(Self::NAME, "请求缺少 messages 数组"))?;
+
+        let system = messages
+            .iter_mut()
+            .find(|m| m.get("role").and_then(Value::as_str) == Some("system"));
+        match system.and_then(|m| m.get_mut("content")) {
+            Some(Value::String(text)) => *text = self.prefixed(text),
+            Some(Value::Array(parts)) => {
+                parts.insert(0, json!({"type": "text", "text": self.prefix}));
+            }
+            _ => messages.insert(0, json!({"role": "system", "content": self.prefix})),
+        }
+        Ok(())
+    }
+}
+
+impl FlowPlugin for SystemPromptPrefixPlugin {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn on_request(&self, req: &mut LLMRequest) -> Result<(), FlowPluginError> {
+        if self.prefix.is_empty() {
+            return Ok(());
+        }
+        let body = req
+            .body
+            .as_object_mut()
+            .ok_or_else(|| FlowPluginError::failed(Self::NAME, "请求体不是 JSON 对象"))?;
+        if req.path.ends_with("/messages") {
+            self.apply_anthropic(body);
+        } else {
+            self.apply_openai(body)?;
+        }
+        req.system_prompt = Some(self.prefixed(req.system_prompt.as_deref().unwrap_or("")));
+        Ok(())
+    }
+}
@@ -276,3 +276,143 @@ mod property_tests {
         }
     }
 }
+
+// ============================================================================
+// Flow 插件测试
+// ============================================================================
+
+mod flow_plugins {
+    use super::super::*;
+    use crate::flow_monitor::{LLMRequest, LLMResponse};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    struct Uppercase;
+
+    impl FlowPlugin for Uppercase {
+        fn name(&self) -> &str {
+            "uppercase"
+        }
+
+        fn on_response(&self, resp: &mut LLMResponse) -> Result<(), FlowPluginError> {
+            resp.content = resp.content.to_uppercase();
+            Ok(())
+        }
+    }
+
+    struct Reject;
+
+    impl FlowPlugin for Reject {
+        fn name(&self) -> &str {
+            "reject"
+        }
+
+        fn on_request(&self, _req: &mut LLMRequest) -> Result<(), FlowPluginError> {
+            Err(FlowPluginError::rejected("reject", "blocked"))
+        }
+    }
+
+    struct Panicky;
+
+    impl FlowPlugin for Panicky {
+        fn name(&self) -> &str {
+            "panicky"
+        }
+
+        fn on_request(&self, _req: &mut LLMRequest) -> Result<(), FlowPluginError> {
+            panic!("boom")
+        }
+    }
+
+    fn openai_request(body: serde_json::Value) -> LLMRequest {
+        LLMRequest {
+            path: "/v1/chat/completions".to_string(),
+            body,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_registry_runs_and_short_circuits() {
+        let registry = FlowPluginRegistry::new();
+        registry.register(Arc::new(Uppercase));
+        registry.register(Arc::new(Uppercase));
+        assert_eq!(registry.names(), vec!["uppercase"]);
+
+        let mut resp = LLMResponse {
+            content: "hello".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(registry.run_on_response(&mut resp), Ok(true));
+        assert_eq!(resp.content, "HELLO");
+
+        let mut req = openai_request(json!({"messages": []}));
+        assert_eq!(registry.run_on_request(&mut req), Ok(false));
+
+        registry.register(Arc::new(Reject));
+        let err = registry.run_on_request(&mut req).unwrap_err();
+        assert!(matches!(err, FlowPluginError::Rejected { .. }));
+
+        assert!(registry.unregister("reject"));
+        assert!(!registry.unregister("reject"));
+    }
+
+    #[test]
+    fn test_registry_converts_panic_to_error() {
+        let registry = FlowPluginRegistry::new();
+        registry.register(Arc::new(Panicky));
+        let mut req = openai_request(json!({"messages": []}));
+        assert_eq!(
+            registry.run_on_request(&mut req),
+            Err(FlowPluginError::failed("panicky", "boom"))
+        );
+    }
+
+    #[test]
+    fn test_system_prompt_prefix_openai() {
+        let plugin = SystemPromptPrefixPlugin::new("Be brief.");
+
+        let mut req = openai_request(json!({
+            "messages": [
+                {"role": "system", "content": "You are helpful."},
+                {"role": "user", "content": "hi"}
+            ]
+        }));
+        plugin.on_request(&mut req).unwrap();
+        assert_eq!(
+            req.body["messages"][0]["content"],
+            "Be brief.\n\nYou are helpful."
+        );
+
+        let mut req = openai_request(json!({"messages": [{"role": "user", "content": "hi"}]}));
+        plugin.on_request(&mut req).unwrap();
+        assert_eq!(
+            req.body["messages"][0],
+            json!({"role": "system", "content": "Be brief."})
+        );
+        assert_eq!(req.system_prompt.as_deref(), Some("Be brief."));
+    }
+
+    #[test]
+    fn test_system_prompt_prefix_anthropic() {
+        let plugin = SystemPromptPrefixPlugin::new("Be brief.");
+        let anthropic = |body| LLMRequest {
+            path: "/v1/messages".to_string(),
+            body,
+            ..Default::default()
+        };
+
+        let mut req = anthropic(json!({"system": "You are helpful.", "messages": []}));
+        plugin.on_request(&mut req).unwrap();
+        assert_eq!(req.body["system"], "Be brief.\n\nYou are helpful.");
+
+        let mut req = anthropic(json!({"system": [{"type": "text", "text": "x"}]}));
+        plugin.on_request(&mut req).unwrap();
+        assert_eq!(req.body["system"][0]["text"], "Be brief.");
+        assert_eq!(req.body["system"][1]["text"], "x");
+
+        let mut req = anthropic(json!({"messages": []}));
+        plugin.on_request(&mut req).unwrap();
+        assert_eq!(req.body["system"], "Be brief.");
+    }
+}
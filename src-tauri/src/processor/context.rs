@@ -32,6 +32,8 @@ pub struct RequestContext {
     pub retry_count: u32,
     /// 是否为流式请求
     pub is_stream: bool,
+    /// 是否按字面模型名转发（跳过别名解析和降级规则）
+    pub no_remap: bool,
     /// 插件上下文
     pub plugin_ctx: Option<PluginContext>,
     /// 元数据
@@ -53,6 +55,7 @@ impl RequestContext {
             credential_id: None,
             retry_count: 0,
             is_stream: false,
+            no_remap: false,
             plugin_ctx: None,
             metadata: std::collections::HashMap::new(),
         }
@@ -64,6 +67,12 @@ impl RequestContext {
         self
     }
 
+    /// 设置是否按字面模型名转发
+    pub fn with_no_remap(mut self, no_remap: bool) -> Self {
+        self.no_remap = no_remap;
+        self
+    }
+
     /// 设置 Provider
     pub fn set_provider(&mut self, provider: ProviderType) {
         self.provider = Some(provider);
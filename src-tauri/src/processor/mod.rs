@@ -9,9 +9,13 @@
 //! 2. 参数注入 (InjectionStep)
 //! 3. 路由解析 (RoutingStep)
 //! 4. 插件前置钩子 (PluginPreStep)
-//! 5. Provider 调用 (ProviderStep) - 包含重试和故障转移
-//! 6. 插件后置钩子 (PluginPostStep)
-//! 7. 统计记录 (TelemetryStep)
+//! 5. 故障注入 (ChaosStep) - 仅用于混沌测试，默认关闭
+//! 6. Provider 调用 (ProviderStep) - 包含重试和故障转移
+//! 7. 插件后置钩子 (PluginPostStep)
+//! 8. 统计记录 (TelemetryStep)
+//! 9. 影子镜像 (ShadowStep) - 按采样率异步镜像到候选模型，不影响主响应
+//!
+//! 响应返回客户端前还会经过内容过滤 (ContentFilterStep)，默认关闭。
 
 mod context;
 mod error;
@@ -20,17 +24,27 @@ mod steps;
 pub use context::RequestContext;
 pub use error::ProcessError;
 pub use steps::{
-    AuthStep, InjectionStep, PipelineStep, PluginPostStep, PluginPreStep, ProviderStep,
-    RoutingStep, TelemetryStep,
+    content_filter_outcome, injected_fault, AuthStep, ChaosConfig, ChaosFault, ChaosRule,
+    ChaosStep, ContentFilter, ContentFilterConfig, ContentFilterRule, ContentFilterStep,
+    InjectionStep, PipelineStep, PluginPostStep, PluginPreStep, ProviderCallError,
+    ProviderCallResult, ProviderStep, RoutingStep, ShadowCaller, ShadowConfig, ShadowRecord,
+    ShadowStep, SseContentFilter, StreamContentFilter, TelemetryStep, CHAOS_FAULT_KEY,
+    CONTENT_FILTER_KEY, PRIMARY_FLOW_ID_KEY, PRIMARY_RESPONSE_KEY, REQUEST_PATH_KEY,
+    SHADOW_REQUEST_KEY,
 };
 
+use crate::config::FlowPluginsConfig;
 use crate::injection::Injector;
-use crate::plugin::PluginManager;
-use crate::resilience::{Failover, Retrier, TimeoutController};
-use crate::router::{ModelMapper, Router};
+use crate::plugin::{FlowPluginRegistry, PluginManager, SystemPromptPrefixPlugin};
+use crate::resilience::{AdaptiveConcurrency, Failover, Retrier, TimeoutController};
+use crate::router::{
+    HistoryTruncation, ModelDowngrade, ModelMapper, ParamAdjustment, ResolutionStepKind,
+    RouteResult, Router, RoutingTrace, SessionAffinity,
+};
 use crate::services::provider_pool_service::ProviderPoolService;
 use crate::telemetry::{StatsAggregator, TokenTracker};
 use parking_lot::RwLock as ParkingLotRwLock;
+use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -42,6 +56,8 @@ pub struct RequestProcessor {
     pub router: Arc<RwLock<Router>>,
     /// 模型映射器
     pub mapper: Arc<RwLock<ModelMapper>>,
+    /// 会话亲和表
+    pub session_affinity: Arc<SessionAffinity>,
     /// 参数注入器
     pub injector: Arc<RwLock<Injector>>,
     /// 重试器
@@ -50,8 +66,16 @@ pub struct RequestProcessor {
     pub failover: Arc<Failover>,
     /// 超时控制器
     pub timeout: Arc<TimeoutController>,
+    /// 自适应并发控制器（按 Provider 的限流头调整并发上限）
+    pub concurrency: Arc<AdaptiveConcurrency>,
+    /// 故障注入步骤（混沌测试，默认关闭）
+    pub chaos: Arc<ChaosStep>,
+    /// 内容过滤步骤（默认关闭）
+    pub content_filter: Arc<ContentFilterStep>,
     /// 插件管理器
     pub plugins: Arc<PluginManager>,
+    /// Flow 插件注册表（请求/响应变换）
+    pub flow_plugins: Arc<FlowPluginRegistry>,
     /// 统计聚合器（使用 parking_lot::RwLock 以支持与 TelemetryState 共享）
     pub stats: Arc<ParkingLotRwLock<StatsAggregator>>,
     /// Token 追踪器（使用 parking_lot::RwLock 以支持与 TelemetryState 共享）
@@ -79,11 +103,16 @@ impl RequestProcessor {
         Self {
             router,
             mapper,
+            session_affinity: Arc::new(SessionAffinity::default()),
             injector,
             retrier,
             failover,
             timeout,
+            concurrency: Arc::new(AdaptiveConcurrency::with_defaults()),
+            chaos: Arc::new(ChaosStep::default()),
+            content_filter: Arc::new(ContentFilterStep::default()),
             plugins,
+            flow_plugins: Arc::new(FlowPluginRegistry::new()),
             stats,
             tokens,
             pool_service,
@@ -97,11 +126,16 @@ impl RequestProcessor {
         Self {
             router: Arc::new(RwLock::new(Router::new(ProviderType::Kiro))),
             mapper: Arc::new(RwLock::new(ModelMapper::new())),
+            session_affinity: Arc::new(SessionAffinity::default()),
             injector: Arc::new(RwLock::new(Injector::new())),
             retrier: Arc::new(Retrier::with_defaults()),
             failover: Arc::new(Failover::with_defaults()),
             timeout: Arc::new(TimeoutController::with_defaults()),
+            concurrency: Arc::new(AdaptiveConcurrency::with_defaults()),
+            chaos: Arc::new(ChaosStep::default()),
+            content_filter: Arc::new(ContentFilterStep::default()),
             plugins: Arc::new(PluginManager::with_defaults()),
+            flow_plugins: Arc::new(FlowPluginRegistry::new()),
             stats: Arc::new(ParkingLotRwLock::new(StatsAggregator::with_defaults())),
             tokens: Arc::new(ParkingLotRwLock::new(TokenTracker::with_defaults())),
             pool_service,
@@ -122,11 +156,16 @@ impl RequestProcessor {
         Self {
             router: Arc::new(RwLock::new(Router::new(ProviderType::Kiro))),
             mapper: Arc::new(RwLock::new(ModelMapper::new())),
+            session_affinity: Arc::new(SessionAffinity::default()),
             injector: Arc::new(RwLock::new(Injector::new())),
             retrier: Arc::new(Retrier::with_defaults()),
             failover: Arc::new(Failover::with_defaults()),
             timeout: Arc::new(TimeoutController::with_defaults()),
+            concurrency: Arc::new(AdaptiveConcurrency::with_defaults()),
+            chaos: Arc::new(ChaosStep::default()),
+            content_filter: Arc::new(ContentFilterStep::default()),
             plugins: Arc::new(PluginManager::with_defaults()),
+            flow_plugins: Arc::new(FlowPluginRegistry::new()),
             stats,
             tokens,
             pool_service,
@@ -134,6 +173,21 @@ impl RequestProcessor {
         }
     }
 
+    /// 按配置注册内置 Flow 插件
+    ///
+    /// 启动和配置热重载时调用，未配置的内置插件会被注销。
+    pub fn apply_flow_plugins_config(&self, config: &FlowPluginsConfig) {
+        self.flow_plugins.unregister(SystemPromptPrefixPlugin::NAME);
+        if let Some(prefix) = config
+            .system_prompt_prefix
+            .as_deref()
+            .filter(|p| !p.trim().is_empty())
+        {
+            self.flow_plugins
+                .register(Arc::new(SystemPromptPrefixPlugin::new(prefix)));
+        }
+    }
+
     /// 解析模型别名
     ///
     /// 使用 ModelMapper 将模型别名解析为实际模型名称
@@ -158,6 +212,7 @@ impl RequestProcessor {
     pub async fn resolve_model_for_context(&self, ctx: &mut RequestContext) -> String {
         let resolved = self.resolve_model(&ctx.original_model).await;
         ctx.set_resolved_model(resolved.clone());
+        start_routing_trace(ctx);
 
         tracing::debug!(
             "[MAPPER] request_id={} original_model={} resolved_model={}",
@@ -192,9 +247,11 @@ impl RequestProcessor {
     /// # Returns
     /// 选择的 Provider 类型
     pub async fn route_for_context(&self, ctx: &mut RequestContext) -> crate::ProviderType {
-        let (provider, is_default) = self.route_model(&ctx.resolved_model).await;
+        let route = self.router.read().await.route(&ctx.resolved_model);
+        let (provider, is_default) = (route.provider, route.is_default);
         ctx.set_provider(provider);
         ctx.set_is_default_route(is_default);
+        record_route(ctx, &route);
 
         tracing::info!(
             "[ROUTE] request_id={} model={} provider={} is_default={}",
@@ -209,7 +266,8 @@ impl RequestProcessor {
 
     /// 执行完整的路由解析流程
     ///
-    /// 包括模型别名解析和 Provider 选择
+    /// 包括模型别名解析和 Provider 选择，解析轨迹记录在上下文元数据 `routing_trace` 中。
+    /// 上下文设置了 `no_remap` 时跳过别名解析，按字面模型名选择 Provider。
     ///
     /// # Arguments
     /// * `ctx` - 请求上下文
@@ -218,12 +276,153 @@ impl RequestProcessor {
     /// 选择的 Provider 类型
     pub async fn resolve_and_route(&self, ctx: &mut RequestContext) -> crate::ProviderType {
         // 1. 解析模型别名
-        self.resolve_model_for_context(ctx).await;
+        if ctx.no_remap {
+            ctx.set_resolved_model(ctx.original_model.clone());
+            start_routing_trace(ctx);
+            tracing::info!(
+                "[MAPPER] request_id={} model={} no_remap=true，跳过别名解析",
+                ctx.request_id,
+                ctx.original_model
+            );
+        } else {
+            self.resolve_model_for_context(ctx).await;
+        }
 
         // 2. 根据解析后的模型选择 Provider
         self.route_for_context(ctx).await
     }
 
+    /// 应用别名参数约束并记录调整
+    ///
+    /// 约束按客户端请求的别名查找，超出范围的值会被钳制（而不是拒绝），
+    /// 调整结果记录在上下文元数据 `param_adjustments` 中
+    ///
+    /// # Arguments
+    /// * `ctx` - 请求上下文
+    /// * `payload` - 请求负载
+    ///
+    /// # Returns
+    /// 实际发生的参数调整
+    pub async fn apply_param_constraints(
+        &self,
+        ctx: &mut RequestContext,
+        payload: &mut serde_json::Value,
+    ) -> Vec<ParamAdjustment> {
+        let adjustments = {
+            let mapper = self.mapper.read().await;
+            mapper.apply_param_constraints(&ctx.original_model, payload)
+        };
+        record_param_adjustments(ctx, &adjustments);
+        adjustments
+    }
+
+    /// 为客户端未发送的参数填充模型默认值并记录
+    ///
+    /// 默认值按别名解析后的模型查找，客户端显式发送的值（包括 `0` 和 `null`）保持不变，
+    /// 填充结果以 `Defaulted` 动作记录在上下文元数据 `param_adjustments` 中
+    ///
+    /// # Arguments
+    /// * `ctx` - 请求上下文（需已完成别名解析）
+    /// * `payload` - 请求负载
+    /// * `client_fields` - 客户端原始请求中出现的顶层字段
+    ///
+    /// # Returns
+    /// 填充的默认参数
+    pub async fn apply_model_defaults(
+        &self,
+        ctx: &mut RequestContext,
+        payload: &mut serde_json::Value,
+        client_fields: &HashSet<String>,
+    ) -> Vec<ParamAdjustment> {
+        let adjustments = {
+            let mapper = self.mapper.read().await;
+            mapper.apply_model_defaults(&ctx.resolved_model, payload, client_fields)
+        };
+        record_param_adjustments(ctx, &adjustments);
+        adjustments
+    }
+
+    /// 将请求中的推理设置转换为目标 Provider 的原生参数并记录
+    ///
+    /// 在选定 Provider（凭证）之后、调用 Provider 之前执行，
+    /// 转换结果以 `Translated` 动作记录在上下文元数据 `param_adjustments` 中
+    ///
+    /// # Arguments
+    /// * `ctx` - 请求上下文
+    /// * `provider` - 实际调用的 Provider
+    /// * `payload` - 请求负载
+    ///
+    /// # Returns
+    /// 转换后的参数
+    pub async fn apply_reasoning_effort(
+        &self,
+        ctx: &mut RequestContext,
+        provider: crate::ProviderType,
+        payload: &mut serde_json::Value,
+    ) -> Vec<ParamAdjustment> {
+        let adjustments = {
+            let mapper = self.mapper.read().await;
+            mapper.apply_reasoning_effort(provider, payload)
+        };
+        record_param_adjustments(ctx, &adjustments);
+        adjustments
+    }
+
+    /// 提示词过大时按别名规则降级模型
+    ///
+    /// 降级后更新上下文中的解析模型并重新选择 Provider，
+    /// 降级记录保存在上下文元数据 `model_downgrade` 中；上下文设置了 `no_remap` 时不降级
+    ///
+    /// # Arguments
+    /// * `ctx` - 请求上下文（需已完成别名解析）
+    /// * `payload` - 请求负载
+    ///
+    /// # Returns
+    /// 发生的降级（未降级时为 `None`）
+    pub async fn apply_size_downgrade(
+        &self,
+        ctx: &mut RequestContext,
+        payload: &serde_json::Value,
+    ) -> Option<ModelDowngrade> {
+        if ctx.no_remap {
+            return None;
+        }
+        let downgrade = {
+            let mapper = self.mapper.read().await;
+            mapper.size_downgrade(&ctx.original_model, &ctx.resolved_model, payload)
+        }?;
+        ctx.set_resolved_model(downgrade.to.clone());
+        record_model_downgrade(ctx, &downgrade);
+        update_routing_trace(ctx, |trace| {
+            trace.push_step(ResolutionStepKind::SizeDowngrade, &downgrade.to)
+        });
+        self.route_for_context(ctx).await;
+        Some(downgrade)
+    }
+
+    /// 按别名规则截断过长的消息历史
+    ///
+    /// 截断记录保存在上下文元数据 `history_truncation` 中
+    ///
+    /// # Arguments
+    /// * `ctx` - 请求上下文（需已完成别名解析）
+    /// * `payload` - 请求负载
+    ///
+    /// # Returns
+    /// 发生的截断（未截断时为 `None`）
+    pub async fn apply_history_truncation(
+        &self,
+        ctx: &mut RequestContext,
+        payload: &mut serde_json::Value,
+    ) -> Option<HistoryTruncation> {
+        let truncation = {
+            let mapper = self.mapper.read().await;
+            mapper.truncate_history(&ctx.original_model, &ctx.resolved_model, payload)
+        }?;
+        record_history_truncation(ctx, &truncation);
+        Some(truncation)
+    }
+
     /// 检查模型是否被指定 Provider 排除
     ///
     /// # Arguments
@@ -238,5 +437,125 @@ impl RequestProcessor {
     }
 }
 
+/// 上下文元数据中参数调整记录的键
+pub const PARAM_ADJUSTMENTS_KEY: &str = "param_adjustments";
+
+/// 记录参数调整到上下文并输出日志（追加到已有的调整记录之后）
+pub(crate) fn record_param_adjustments(ctx: &mut RequestContext, adjustments: &[ParamAdjustment]) {
+    if adjustments.is_empty() {
+        return;
+    }
+
+    for adjustment in adjustments {
+        tracing::info!(
+            "[CONSTRAINT] request_id={} alias={} param={} original={:?} applied={} action={:?}",
+            ctx.request_id,
+            ctx.original_model,
+            adjustment.param,
+            adjustment.original,
+            adjustment.applied,
+            adjustment.action
+        );
+    }
+
+    let mut recorded: Vec<ParamAdjustment> = ctx
+        .get_metadata(PARAM_ADJUSTMENTS_KEY)
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    recorded.extend_from_slice(adjustments);
+    ctx.set_metadata(
+        PARAM_ADJUSTMENTS_KEY,
+        serde_json::to_value(recorded).unwrap_or_default(),
+    );
+}
+
+/// 要求按字面模型名转发的请求头，值为真时跳过别名解析和降级规则
+pub const NO_REMAP_HEADER: &str = "x-no-remap";
+
+/// 上下文元数据中模型降级记录的键
+pub const MODEL_DOWNGRADE_KEY: &str = "model_downgrade";
+
+/// 记录模型降级到上下文并输出日志
+pub(crate) fn record_model_downgrade(ctx: &mut RequestContext, downgrade: &ModelDowngrade) {
+    tracing::info!(
+        "[DOWNGRADE] request_id={} alias={} from={} to={} estimated_tokens={} threshold={}",
+        ctx.request_id,
+        ctx.original_model,
+        downgrade.from,
+        downgrade.to,
+        downgrade.estimated_tokens,
+        downgrade.threshold_tokens
+    );
+    ctx.set_metadata(
+        MODEL_DOWNGRADE_KEY,
+        serde_json::to_value(downgrade).unwrap_or_default(),
+    );
+}
+
+/// 上下文元数据中消息历史截断记录的键
+pub const HISTORY_TRUNCATION_KEY: &str = "history_truncation";
+
+/// 记录消息历史截断到上下文并输出日志
+pub(crate) fn record_history_truncation(ctx: &mut RequestContext, truncation: &HistoryTruncation) {
+    tracing::info!(
+        "[TRUNCATE] request_id={} alias={} messages={} removed={} estimated_tokens={:?}",
+        ctx.request_id,
+        ctx.original_model,
+        truncation.original_messages,
+        truncation.removed_messages,
+        truncation.estimated_tokens
+    );
+    ctx.set_metadata(
+        HISTORY_TRUNCATION_KEY,
+        serde_json::to_value(truncation).unwrap_or_default(),
+    );
+}
+
+/// 上下文元数据中路由解析轨迹的键
+pub const ROUTING_TRACE_KEY: &str = "routing_trace";
+
+/// 从客户端请求的模型开始记录解析轨迹（覆盖之前的轨迹）
+///
+/// 解析后的模型与请求的模型不同时记录一步别名映射
+pub(crate) fn start_routing_trace(ctx: &mut RequestContext) {
+    let mut trace = RoutingTrace::new(ctx.original_model.clone());
+    trace.push_step(ResolutionStepKind::Alias, ctx.resolved_model.clone());
+    store_routing_trace(ctx, &trace);
+}
+
+/// 更新上下文中的解析轨迹（尚未开始记录时从请求的模型开始）
+pub(crate) fn update_routing_trace(ctx: &mut RequestContext, f: impl FnOnce(&mut RoutingTrace)) {
+    let mut trace =
+        routing_trace(ctx).unwrap_or_else(|| RoutingTrace::new(ctx.original_model.clone()));
+    f(&mut trace);
+    store_routing_trace(ctx, &trace);
+}
+
+/// 记录路由结果到解析轨迹
+pub(crate) fn record_route(ctx: &mut RequestContext, route: &RouteResult) {
+    let request_id = ctx.request_id.clone();
+    update_routing_trace(ctx, |trace| {
+        trace.set_route(route);
+        tracing::debug!(
+            "[ROUTE_TRACE] request_id={} {}",
+            request_id,
+            trace.describe()
+        );
+    });
+}
+
+/// 读取上下文中的解析轨迹
+pub fn routing_trace(ctx: &RequestContext) -> Option<RoutingTrace> {
+    ctx.get_metadata(ROUTING_TRACE_KEY)
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
+fn store_routing_trace(ctx: &mut RequestContext, trace: &RoutingTrace) {
+    ctx.set_metadata(
+        ROUTING_TRACE_KEY,
+        serde_json::to_value(trace).unwrap_or_default(),
+    );
+}
+
 #[cfg(test)]
 mod tests;
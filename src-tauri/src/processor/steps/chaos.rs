@@ -0,0 +1,317 @@
+//! 故障注入步骤（混沌测试）
+//!
+//! 按配置的概率对匹配的 Provider/模型注入故障：延迟、429、5xx、流中途断开或畸形响应体，
+//! 用于验证重试、熔断等容错行为。注入的故障由服务端按真实请求处理并记录为 Flow
+//! （标记 `chaos` 标签），便于观察系统的反应。
+//!
+//! 故障注入默认关闭，只能通过配置显式启用；启用期间每次注入都会输出 `warn` 级别日志。
+
+use super::traits::{PipelineStep, StepError};
+use crate::models::provider_pool_model::pattern_matches;
+use crate::processor::RequestContext;
+use crate::ProviderType;
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 待注入的故障在请求上下文元数据中的键
+pub const CHAOS_FAULT_KEY: &str = "chaos_fault";
+
+/// 注入的故障类型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChaosFault {
+    /// 延迟后继续正常处理
+    Delay {
+        /// 延迟时间（毫秒）
+        ms: u64,
+    },
+    /// 返回 429 限流响应
+    RateLimit {
+        /// `Retry-After` 响应头（秒，为空时不设置）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry_after_secs: Option<u64>,
+    },
+    /// 返回 5xx 服务器错误
+    ServerError {
+        /// 状态码（默认 503）
+        #[serde(default = "default_server_error_status")]
+        status_code: u16,
+    },
+    /// 流式响应发送若干块后断开（仅对流式请求生效）
+    DropStream {
+        /// 断开前发送的块数
+        #[serde(default)]
+        after_chunks: usize,
+    },
+    /// 返回无法解析的响应体
+    MalformedBody,
+}
+
+fn default_server_error_status() -> u16 {
+    503
+}
+
+impl ChaosFault {
+    /// 故障是否只适用于流式请求
+    pub fn requires_stream(&self) -> bool {
+        matches!(self, ChaosFault::DropStream { .. })
+    }
+}
+
+/// 故障注入规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChaosRule {
+    /// 匹配的 Provider（为空时匹配所有 Provider）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderType>,
+    /// 匹配的模型模式（支持通配符，为空时匹配所有模型）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// 注入概率（0.0 - 1.0）
+    pub probability: f64,
+    /// 注入的故障
+    pub fault: ChaosFault,
+}
+
+impl ChaosRule {
+    /// 检查规则是否匹配请求
+    pub fn matches(&self, provider: Option<ProviderType>, model: &str, is_stream: bool) -> bool {
+        if self.fault.requires_stream() && !is_stream {
+            return false;
+        }
+        if let Some(expected) = self.provider {
+            if provider != Some(expected) {
+                return false;
+            }
+        }
+        self.model
+            .as_deref()
+            .is_none_or(|pattern| pattern_matches(pattern, model))
+    }
+}
+
+/// 故障注入配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct ChaosConfig {
+    /// 是否启用故障注入（切勿在生产环境启用）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 注入规则（按顺序匹配，每条命中的规则独立按概率判定，第一个触发的故障生效）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<ChaosRule>,
+}
+
+/// 故障注入步骤
+///
+/// 应放在 Provider 调用之前执行。延迟故障在步骤内完成，其余故障写入请求上下文元数据，
+/// 由服务端构造对应的故障响应并记录 Flow。
+pub struct ChaosStep {
+    /// 故障注入配置
+    config: Arc<RwLock<ChaosConfig>>,
+}
+
+impl Default for ChaosStep {
+    fn default() -> Self {
+        Self::new(ChaosConfig::default())
+    }
+}
+
+impl ChaosStep {
+    /// 创建新的故障注入步骤
+    pub fn new(config: ChaosConfig) -> Self {
+        warn_if_enabled(&config);
+        Self {
+            config: Arc::new(RwLock::new(config)),
+        }
+    }
+
+    /// 获取当前配置
+    pub fn config(&self) -> ChaosConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置
+    pub fn update_config(&self, config: ChaosConfig) {
+        warn_if_enabled(&config);
+        *self.config.write() = config;
+    }
+
+    /// 按规则和概率选择本次请求要注入的故障
+    pub fn pick(
+        &self,
+        provider: Option<ProviderType>,
+        model: &str,
+        is_stream: bool,
+    ) -> Option<ChaosFault> {
+        let config = self.config.read();
+        if !config.enabled {
+            return None;
+        }
+        config
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(provider, model, is_stream))
+            .find(|rule| roll(rule.probability))
+            .map(|rule| rule.fault.clone())
+    }
+}
+
+/// 获取请求上下文中待注入的故障
+pub fn injected_fault(ctx: &RequestContext) -> Option<ChaosFault> {
+    ctx.get_metadata(CHAOS_FAULT_KEY)
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
+/// 按概率判定是否触发
+fn roll(probability: f64) -> bool {
+    if probability <= 0.0 {
+        false
+    } else if probability >= 1.0 {
+        true
+    } else {
+        rand::random::<f64>() < probability
+    }
+}
+
+/// 故障注入启用时输出醒目的警告
+fn warn_if_enabled(config: &ChaosConfig) {
+    if config.enabled {
+        tracing::warn!(
+            "[CHAOS] ⚠️⚠️⚠️ 故障注入已启用（{} 条规则），请求将被随机注入故障，切勿在生产环境使用 ⚠️⚠️⚠️",
+            config.rules.len()
+        );
+    }
+}
+
+#[async_trait]
+impl PipelineStep for ChaosStep {
+    async fn execute(
+        &self,
+        ctx: &mut RequestContext,
+        _payload: &mut serde_json::Value,
+    ) -> Result<(), StepError> {
+        let Some(fault) = self.pick(ctx.provider, &ctx.resolved_model, ctx.is_stream) else {
+            return Ok(());
+        };
+
+        tracing::warn!(
+            "[CHAOS] ⚠️ 注入故障 request_id={} provider={:?} model={} fault={:?}",
+            ctx.request_id,
+            ctx.provider,
+            ctx.resolved_model,
+            fault
+        );
+
+        if let ChaosFault::Delay { ms } = fault {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+        }
+        ctx.set_metadata(
+            CHAOS_FAULT_KEY,
+            serde_json::to_value(&fault).unwrap_or_default(),
+        );
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "chaos"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.config.read().enabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(provider: Option<ProviderType>, model: Option<&str>, fault: ChaosFault) -> ChaosRule {
+        ChaosRule {
+            provider,
+            model: model.map(|m| m.to_string()),
+            probability: 1.0,
+            fault,
+        }
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let config: ChaosConfig = serde_json::from_str("{}").unwrap();
+        assert!(!config.enabled);
+
+        let step = ChaosStep::new(ChaosConfig {
+            enabled: false,
+            rules: vec![rule(None, None, ChaosFault::MalformedBody)],
+        });
+        assert!(!step.is_enabled());
+        assert_eq!(step.pick(None, "gpt-4", false), None);
+    }
+
+    #[test]
+    fn test_pick_matches_provider_model_and_stream() {
+        let step = ChaosStep::new(ChaosConfig {
+            enabled: true,
+            rules: vec![
+                rule(
+                    None,
+                    Some("claude-*"),
+                    ChaosFault::DropStream { after_chunks: 2 },
+                ),
+                rule(
+                    Some(ProviderType::Gemini),
+                    None,
+                    ChaosFault::ServerError { status_code: 502 },
+                ),
+                ChaosRule {
+                    probability: 0.0,
+                    ..rule(None, None, ChaosFault::MalformedBody)
+                },
+            ],
+        });
+
+        assert_eq!(
+            step.pick(Some(ProviderType::Claude), "claude-sonnet-4-5", true),
+            Some(ChaosFault::DropStream { after_chunks: 2 })
+        );
+        // 断流故障只对流式请求生效
+        assert_eq!(
+            step.pick(Some(ProviderType::Claude), "claude-sonnet-4-5", false),
+            None
+        );
+        assert_eq!(
+            step.pick(Some(ProviderType::Gemini), "gemini-2.5-pro", false),
+            Some(ChaosFault::ServerError { status_code: 502 })
+        );
+        assert_eq!(step.pick(Some(ProviderType::Kiro), "gpt-4", false), None);
+    }
+
+    #[tokio::test]
+    async fn test_execute_records_fault_in_context() {
+        let step = ChaosStep::new(ChaosConfig {
+            enabled: true,
+            rules: vec![rule(
+                None,
+                None,
+                ChaosFault::RateLimit {
+                    retry_after_secs: Some(3),
+                },
+            )],
+        });
+        let mut ctx = RequestContext::new("gpt-4".to_string());
+        let mut payload = serde_json::json!({});
+        assert!(step.execute(&mut ctx, &mut payload).await.is_ok());
+        assert_eq!(
+            injected_fault(&ctx),
+            Some(ChaosFault::RateLimit {
+                retry_after_secs: Some(3)
+            })
+        );
+
+        let fault: ChaosFault =
+            serde_json::from_value(serde_json::json!({"type": "server_error"})).unwrap();
+        assert_eq!(fault, ChaosFault::ServerError { status_code: 503 });
+    }
+}
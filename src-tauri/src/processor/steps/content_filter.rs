@@ -0,0 +1,696 @@
+//! 内容过滤步骤
+//!
+//! 在响应返回客户端之前按正则规则检查响应内容：脱敏规则把匹配替换为占位文本，
+//! 阻止规则用安全的占位内容替换整个响应。命中的规则记录在 Flow 上（`content_filter`
+//! 元数据和 `content_filtered` 标签），并计入统计。
+//!
+//! 流式响应按累积的文本增量过滤：末尾保留 `stream_boundary_chars` 个字符暂不下发，
+//! 跨 chunk 的匹配只要不超过该长度都能被捕获。阻止规则在流中命中时，之前已下发的内容
+//! 无法撤回，之后的文本增量被丢弃并以占位内容代替。
+
+use super::traits::{PipelineStep, StepError};
+use crate::flow_monitor::{ContentFilterAction, ContentFilterOutcome};
+use crate::processor::RequestContext;
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// 过滤结果在请求上下文元数据中的键
+pub const CONTENT_FILTER_KEY: &str = "content_filter";
+
+/// 默认的脱敏替换文本
+const DEFAULT_REPLACEMENT: &str = "[REDACTED]";
+
+/// 内容过滤规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentFilterRule {
+    /// 规则名称（记录在 Flow 上）
+    pub name: String,
+    /// 匹配的正则表达式
+    pub pattern: String,
+    /// 命中后的动作
+    #[serde(default)]
+    pub action: ContentFilterAction,
+    /// 脱敏替换文本（默认 `[REDACTED]`，仅脱敏规则使用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
+
+/// 内容过滤配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentFilterConfig {
+    /// 是否启用内容过滤
+    #[serde(default)]
+    pub enabled: bool,
+    /// 过滤规则（按顺序应用）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<ContentFilterRule>,
+    /// 响应被阻止时返回的占位内容
+    #[serde(default = "default_block_message")]
+    pub block_message: String,
+    /// 流式响应末尾暂不下发的字符数（跨 chunk 匹配的最大可捕获长度）
+    #[serde(default = "default_stream_boundary_chars")]
+    pub stream_boundary_chars: usize,
+}
+
+fn default_block_message() -> String {
+    "该响应包含受限内容，已被拦截。".to_string()
+}
+
+fn default_stream_boundary_chars() -> usize {
+    64
+}
+
+impl Default for ContentFilterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rules: Vec::new(),
+            block_message: default_block_message(),
+            stream_boundary_chars: default_stream_boundary_chars(),
+        }
+    }
+}
+
+impl ContentFilterConfig {
+    /// 检查规则的正则表达式，返回错误描述
+    pub fn validate(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter_map(|rule| {
+                Regex::new(&rule.pattern).err().map(|e| {
+                    format!(
+                        "content_filter.rules.{}: 无效的正则表达式: {}",
+                        rule.name, e
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// 编译后的过滤规则
+#[derive(Debug)]
+struct CompiledRule {
+    name: String,
+    regex: Regex,
+    action: ContentFilterAction,
+    replacement: String,
+}
+
+/// 编译后的内容过滤器
+#[derive(Debug)]
+pub struct ContentFilter {
+    rules: Vec<CompiledRule>,
+    block_message: String,
+    stream_boundary_chars: usize,
+}
+
+impl ContentFilter {
+    /// 编译配置，无效的正则记录警告并跳过（加载配置时已校验）
+    fn compile(config: &ContentFilterConfig) -> Self {
+        let rules = config
+            .rules
+            .iter()
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(regex) => Some(CompiledRule {
+                    name: rule.name.clone(),
+                    regex,
+                    action: rule.action,
+                    replacement: rule
+                        .replacement
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_REPLACEMENT.to_string()),
+                }),
+                Err(e) => {
+                    tracing::warn!("[FILTER] 规则 {} 的正则无效，已忽略: {}", rule.name, e);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            rules,
+            block_message: config.block_message.clone(),
+            stream_boundary_chars: config.stream_boundary_chars,
+        }
+    }
+
+    /// 过滤非流式响应体（OpenAI `choices[].message.content` 或 Anthropic `content[].text`）
+    ///
+    /// 任一文本命中阻止规则时整个响应的文本内容都替换为占位内容
+    pub fn filter_response(&self, body: &mut Value) -> Option<ContentFilterOutcome> {
+        let mut texts = response_texts(body);
+        let mut outcome = None;
+        if let Some(rule) = texts.iter().find_map(|text| self.blocking_rule(text)) {
+            record(&mut outcome, rule, 0);
+            for (i, text) in texts.iter_mut().enumerate() {
+                **text = if i == 0 {
+                    self.block_message.clone()
+                } else {
+                    String::new()
+                };
+            }
+            return outcome;
+        }
+        for text in texts {
+            *text = self.redact(text, &mut outcome);
+        }
+        outcome
+    }
+
+    /// 第一个命中的阻止规则
+    fn blocking_rule(&self, text: &str) -> Option<&CompiledRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.action == ContentFilterAction::Block)
+            .find(|rule| rule.regex.is_match(text))
+    }
+
+    /// 按顺序应用全部脱敏规则
+    fn redact(&self, text: &str, outcome: &mut Option<ContentFilterOutcome>) -> String {
+        let mut result = text.to_string();
+        for rule in &self.rules {
+            if rule.action != ContentFilterAction::Redact {
+                continue;
+            }
+            let count = rule.regex.find_iter(&result).count();
+            if count > 0 {
+                result = rule
+                    .regex
+                    .replace_all(&result, rule.replacement.as_str())
+                    .into_owned();
+                record(outcome, rule, count);
+            }
+        }
+        result
+    }
+
+    /// 计算可以安全下发的前缀长度
+    ///
+    /// 末尾保留 `stream_boundary_chars` 个字符；跨越切分点的脱敏匹配整体保留到下一轮
+    fn safe_cut(&self, pending: &str) -> usize {
+        let mut cut = match self.stream_boundary_chars {
+            0 => pending.len(),
+            n => pending
+                .char_indices()
+                .rev()
+                .nth(n - 1)
+                .map(|(i, _)| i)
+                .unwrap_or(0),
+        };
+        loop {
+            let spanning = self
+                .rules
+                .iter()
+                .filter(|rule| rule.action == ContentFilterAction::Redact)
+                .flat_map(|rule| rule.regex.find_iter(pending))
+                .filter(|m| m.start() < cut && m.end() > cut)
+                .map(|m| m.start())
+                .min();
+            match spanning {
+                Some(start) => cut = start,
+                None => return cut,
+            }
+        }
+    }
+}
+
+/// 记录命中的规则
+fn record(outcome: &mut Option<ContentFilterOutcome>, rule: &CompiledRule, redactions: usize) {
+    let outcome = outcome.get_or_insert_with(|| ContentFilterOutcome {
+        action: ContentFilterAction::Redact,
+        rules: Vec::new(),
+        redactions: 0,
+    });
+    if rule.action == ContentFilterAction::Block {
+        outcome.action = ContentFilterAction::Block;
+    }
+    if !outcome.rules.contains(&rule.name) {
+        outcome.rules.push(rule.name.clone());
+    }
+    outcome.redactions += redactions;
+}
+
+/// 响应体中的文本内容
+fn response_texts(body: &mut Value) -> Vec<&mut String> {
+    // 先判断响应格式再取可变引用，避免两次可变借用重叠
+    if body.get("choices").is_some_and(Value::is_array) {
+        let Some(Value::Array(choices)) = body.get_mut("choices") else {
+            return Vec::new();
+        };
+        choices
+            .iter_mut()
+            .filter_map(|choice| match choice.pointer_mut("/message/content") {
+                Some(Value::String(text)) => Some(text),
+                _ => None,
+            })
+            .collect()
+    } else if let Some(Value::Array(blocks)) = body.get_mut("content") {
+        blocks
+            .iter_mut()
+            .filter(|block| block.get("type").and_then(Value::as_str) == Some("text"))
+            .filter_map(|block| match block.get_mut("text") {
+                Some(Value::String(text)) => Some(text),
+                _ => None,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// 流式文本过滤器
+///
+/// 逐个接收文本增量，返回可以下发的文本
+#[derive(Debug)]
+pub struct StreamContentFilter {
+    filter: Arc<ContentFilter>,
+    /// 尚未下发的文本（未脱敏）
+    pending: String,
+    /// 是否已被阻止
+    blocked: bool,
+    outcome: Option<ContentFilterOutcome>,
+}
+
+impl StreamContentFilter {
+    /// 创建流式过滤器
+    pub fn new(filter: Arc<ContentFilter>) -> Self {
+        Self {
+            filter,
+            pending: String::new(),
+            blocked: false,
+            outcome: None,
+        }
+    }
+
+    /// 接收文本增量，返回可以下发的文本
+    ///
+    /// 首次命中阻止规则时返回占位内容，之后始终返回空字符串
+    pub fn push(&mut self, delta: &str) -> String {
+        if self.blocked {
+            return String::new();
+        }
+        self.pending.push_str(delta);
+        if let Some(rule) = self.filter.blocking_rule(&self.pending) {
+            record(&mut self.outcome, rule, 0);
+            self.blocked = true;
+            self.pending.clear();
+            return self.filter.block_message.clone();
+        }
+        let cut = self.filter.safe_cut(&self.pending);
+        let ready: String = self.pending.drain(..cut).collect();
+        self.filter.redact(&ready, &mut self.outcome)
+    }
+
+    /// 下发剩余的文本
+    pub fn flush(&mut self) -> String {
+        if self.blocked {
+            return String::new();
+        }
+        let rest = std::mem::take(&mut self.pending);
+        self.filter.redact(&rest, &mut self.outcome)
+    }
+
+    /// 是否已被阻止
+    pub fn is_blocked(&self) -> bool {
+        self.blocked
+    }
+
+    /// 过滤结果（未命中任何规则时为 `None`）
+    pub fn outcome(&self) -> Option<&ContentFilterOutcome> {
+        self.outcome.as_ref()
+    }
+}
+
+/// SSE 响应的内容过滤器
+///
+/// 按 `\n\n` 切分事件，改写 OpenAI（`choices[0].delta.content`）和 Anthropic
+/// （`content_block_delta` 的 `delta.text`）文本增量。遇到非文本增量事件前，
+/// 先以最近一个文本增量事件为模板下发暂存的文本，保证文本与其后事件的顺序不变。
+#[derive(Debug)]
+pub struct SseContentFilter {
+    text: StreamContentFilter,
+    buffer: Vec<u8>,
+    /// 最近一个文本增量事件（事件名，数据）
+    template: Option<(Option<String>, Value)>,
+}
+
+impl SseContentFilter {
+    /// 创建 SSE 过滤器
+    pub fn new(filter: Arc<ContentFilter>) -> Self {
+        Self {
+            text: StreamContentFilter::new(filter),
+            buffer: Vec::new(),
+            template: None,
+        }
+    }
+
+    /// 接收上游字节，返回过滤后的 SSE 文本
+    pub fn push(&mut self, bytes: &[u8]) -> String {
+        self.buffer.extend_from_slice(bytes);
+        let mut output = String::new();
+        while let Some(pos) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let raw: Vec<u8> = self.buffer.drain(..pos + 2).collect();
+            output.push_str(&self.process_event(&String::from_utf8_lossy(&raw)));
+        }
+        output
+    }
+
+    /// 结束时下发暂存的文本和未完整的事件
+    pub fn finish(&mut self) -> String {
+        let mut output = self.flush_pending();
+        if !self.buffer.is_empty() {
+            output.push_str(&String::from_utf8_lossy(&std::mem::take(&mut self.buffer)));
+        }
+        output
+    }
+
+    /// 过滤结果（未命中任何规则时为 `None`）
+    pub fn outcome(&self) -> Option<&ContentFilterOutcome> {
+        self.text.outcome()
+    }
+
+    fn process_event(&mut self, raw: &str) -> String {
+        let mut event_name = None;
+        let mut data_lines = Vec::new();
+        for line in raw.lines() {
+            if let Some(name) = line.strip_prefix("event:") {
+                event_name = Some(name.trim().to_string());
+            } else if let Some(data) = line.strip_prefix("data:") {
+                data_lines.push(data.strip_prefix(' ').unwrap_or(data));
+            }
+        }
+        // 注释（如保活）不影响暂存的文本
+        if data_lines.is_empty() {
+            return raw.to_string();
+        }
+        let Ok(mut data) = serde_json::from_str::<Value>(&data_lines.join("\n")) else {
+            return self.passthrough(raw);
+        };
+        let Some(text) = text_delta_mut(&mut data).map(std::mem::take) else {
+            return self.passthrough(raw);
+        };
+
+        // 暂存的文本可能为空，仍然下发事件以保留其中的其他字段（如 role）
+        let filtered = self.text.push(&text);
+        if let Some(slot) = text_delta_mut(&mut data) {
+            *slot = filtered;
+        }
+        let event = format_event(event_name.as_deref(), &data);
+        self.template = Some((event_name, data));
+        event
+    }
+
+    /// 非文本增量事件：先下发暂存的文本，再原样转发
+    fn passthrough(&mut self, raw: &str) -> String {
+        let mut output = self.flush_pending();
+        output.push_str(raw);
+        output
+    }
+
+    /// 以最近的文本增量事件为模板下发暂存的文本
+    fn flush_pending(&mut self) -> String {
+        let text = self.text.flush();
+        if text.is_empty() {
+            return String::new();
+        }
+        let Some((event_name, mut data)) = self.template.clone() else {
+            return String::new();
+        };
+        if let Some(slot) = text_delta_mut(&mut data) {
+            *slot = text;
+        }
+        format_event(event_name.as_deref(), &data)
+    }
+}
+
+/// SSE 数据中的文本增量
+fn text_delta_mut(data: &mut Value) -> Option<&mut String> {
+    if data.get("choices").is_some() {
+        return match data.pointer_mut("/choices/0/delta/content") {
+            Some(Value::String(text)) => Some(text),
+            _ => None,
+        };
+    }
+    if data.get("type").and_then(Value::as_str) == Some("content_block_delta")
+        && data.pointer("/delta/type").and_then(Value::as_str) == Some("text_delta")
+    {
+        return match data.pointer_mut("/delta/text") {
+            Some(Value::String(text)) => Some(text),
+            _ => None,
+        };
+    }
+    None
+}
+
+fn format_event(event_name: Option<&str>, data: &Value) -> String {
+    match event_name {
+        Some(name) => format!("event: {}\ndata: {}\n\n", name, data),
+        None => format!("data: {}\n\n", data),
+    }
+}
+
+/// 内容过滤步骤
+///
+/// 应在 Provider 调用之后、响应返回客户端之前执行
+pub struct ContentFilterStep {
+    /// 过滤配置
+    config: Arc<RwLock<ContentFilterConfig>>,
+    /// 编译后的过滤器（未启用或没有规则时为空）
+    filter: Arc<RwLock<Option<Arc<ContentFilter>>>>,
+}
+
+impl Default for ContentFilterStep {
+    fn default() -> Self {
+        Self::new(ContentFilterConfig::default())
+    }
+}
+
+impl ContentFilterStep {
+    /// 创建新的内容过滤步骤
+    pub fn new(config: ContentFilterConfig) -> Self {
+        let step = Self {
+            config: Arc::new(RwLock::new(ContentFilterConfig::default())),
+            filter: Arc::new(RwLock::new(None)),
+        };
+        step.update_config(config);
+        step
+    }
+
+    /// 获取当前配置
+    pub fn config(&self) -> ContentFilterConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置
+    pub fn update_config(&self, config: ContentFilterConfig) {
+        let filter = (config.enabled && !config.rules.is_empty())
+            .then(|| Arc::new(ContentFilter::compile(&config)));
+        *self.filter.write() = filter;
+        *self.config.write() = config;
+    }
+
+    /// 获取当前生效的过滤器（未启用时为 `None`）
+    pub fn filter(&self) -> Option<Arc<ContentFilter>> {
+        self.filter.read().clone()
+    }
+}
+
+/// 获取请求上下文中记录的过滤结果
+pub fn content_filter_outcome(ctx: &RequestContext) -> Option<ContentFilterOutcome> {
+    ctx.get_metadata(CONTENT_FILTER_KEY)
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
+#[async_trait]
+impl PipelineStep for ContentFilterStep {
+    async fn execute(
+        &self,
+        ctx: &mut RequestContext,
+        payload: &mut serde_json::Value,
+    ) -> Result<(), StepError> {
+        let Some(filter) = self.filter() else {
+            return Ok(());
+        };
+        if let Some(outcome) = filter.filter_response(payload) {
+            tracing::info!(
+                "[FILTER] request_id={} action={:?} rules={:?} redactions={}",
+                ctx.request_id,
+                outcome.action,
+                outcome.rules,
+                outcome.redactions
+            );
+            ctx.set_metadata(
+                CONTENT_FILTER_KEY,
+                serde_json::to_value(outcome).unwrap_or_default(),
+            );
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "content_filter"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.filter.read().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, pattern: &str, action: ContentFilterAction) -> ContentFilterRule {
+        ContentFilterRule {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            action,
+            replacement: None,
+        }
+    }
+
+    fn filter(rules: Vec<ContentFilterRule>, boundary: usize) -> Arc<ContentFilter> {
+        let step = ContentFilterStep::new(ContentFilterConfig {
+            enabled: true,
+            rules,
+            stream_boundary_chars: boundary,
+            ..Default::default()
+        });
+        step.filter().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_redact_and_block_response() {
+        let step = ContentFilterStep::new(ContentFilterConfig {
+            enabled: true,
+            rules: vec![
+                rule("email", r"[\w.]+@example\.com", ContentFilterAction::Redact),
+                rule(
+                    "codename",
+                    r"(?i)project\s+falcon",
+                    ContentFilterAction::Block,
+                ),
+            ],
+            ..Default::default()
+        });
+
+        let mut ctx = RequestContext::new("gpt-4".to_string());
+        let mut payload = serde_json::json!({
+            "choices": [{"message": {"role": "assistant", "content": "联系 a.b@example.com 或 c@example.com"}}]
+        });
+        step.execute(&mut ctx, &mut payload).await.unwrap();
+        assert_eq!(
+            payload["choices"][0]["message"]["content"],
+            "联系 [REDACTED] 或 [REDACTED]"
+        );
+        let outcome = content_filter_outcome(&ctx).unwrap();
+        assert_eq!(outcome.action, ContentFilterAction::Redact);
+        assert_eq!(outcome.rules, vec!["email".to_string()]);
+        assert_eq!(outcome.redactions, 2);
+
+        let mut ctx = RequestContext::new("claude".to_string());
+        let mut payload = serde_json::json!({
+            "content": [{"type": "text", "text": "This is Project Falcon."}]
+        });
+        step.execute(&mut ctx, &mut payload).await.unwrap();
+        assert_eq!(payload["content"][0]["text"], default_block_message());
+        let outcome = content_filter_outcome(&ctx).unwrap();
+        assert_eq!(outcome.action, ContentFilterAction::Block);
+        assert_eq!(outcome.rules, vec!["codename".to_string()]);
+
+        // 未启用时不修改响应
+        let disabled = ContentFilterStep::default();
+        assert!(!disabled.is_enabled());
+    }
+
+    #[test]
+    fn test_stream_filter_catches_split_matches() {
+        let filter = filter(
+            vec![rule(
+                "ssn",
+                r"\d{3}-\d{2}-\d{4}",
+                ContentFilterAction::Redact,
+            )],
+            16,
+        );
+        let mut stream = StreamContentFilter::new(filter);
+        let mut output = String::new();
+        for delta in ["我的号码是 12", "3-45-", "6789，请保密。", "结束"] {
+            output.push_str(&stream.push(delta));
+        }
+        output.push_str(&stream.flush());
+        assert_eq!(output, "我的号码是 [REDACTED]，请保密。结束");
+        assert_eq!(stream.outcome().unwrap().redactions, 1);
+    }
+
+    #[test]
+    fn test_stream_filter_block() {
+        let filter = filter(
+            vec![rule("codename", "falcon", ContentFilterAction::Block)],
+            8,
+        );
+        let mut stream = StreamContentFilter::new(filter);
+        let first = stream.push("hello world, fal");
+        assert_eq!(first, "hello wo");
+        assert_eq!(stream.push("con rises"), default_block_message());
+        assert_eq!(stream.push("more"), "");
+        assert_eq!(stream.flush(), "");
+        assert!(stream.is_blocked());
+        assert_eq!(stream.outcome().unwrap().action, ContentFilterAction::Block);
+    }
+
+    #[test]
+    fn test_sse_filter_rewrites_openai_deltas() {
+        let filter = filter(
+            vec![rule("key", r"sk-[a-z0-9]{6}", ContentFilterAction::Redact)],
+            16,
+        );
+        let mut sse = SseContentFilter::new(filter);
+        let chunk = |text: &str| {
+            format!(
+                "data: {}\n\n",
+                serde_json::json!({"choices": [{"index": 0, "delta": {"content": text}}]})
+            )
+        };
+        let mut raw = String::new();
+        raw.push_str(&chunk("key: sk-ab"));
+        raw.push_str(&chunk("c123 ok"));
+        raw.push_str("data: [DONE]\n\n");
+
+        let mut output = String::new();
+        // 按任意字节切分推送
+        for part in raw.as_bytes().chunks(7) {
+            output.push_str(&sse.push(part));
+        }
+        output.push_str(&sse.finish());
+
+        let mut content = String::new();
+        for event in output.split("\n\n").filter(|e| !e.is_empty()) {
+            let data = event.strip_prefix("data: ").unwrap();
+            if data == "[DONE]" {
+                continue;
+            }
+            let value: Value = serde_json::from_str(data).unwrap();
+            content.push_str(value["choices"][0]["delta"]["content"].as_str().unwrap());
+        }
+        assert_eq!(content, "key: [REDACTED] ok");
+        assert!(output.ends_with("data: [DONE]\n\n"));
+        assert_eq!(sse.outcome().unwrap().rules, vec!["key".to_string()]);
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        let config = ContentFilterConfig {
+            enabled: true,
+            rules: vec![rule("bad", "(unclosed", ContentFilterAction::Redact)],
+            ..Default::default()
+        };
+        let errors = config.validate();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("content_filter.rules.bad"));
+    }
+}
@@ -3,17 +3,29 @@
 //! 定义请求处理管道中的各个步骤
 
 mod auth;
+mod chaos;
+mod content_filter;
 mod injection;
 mod plugin;
 mod provider;
 mod routing;
+mod shadow;
 mod telemetry;
 mod traits;
 
 pub use auth::AuthStep;
+pub use chaos::{injected_fault, ChaosConfig, ChaosFault, ChaosRule, ChaosStep, CHAOS_FAULT_KEY};
+pub use content_filter::{
+    content_filter_outcome, ContentFilter, ContentFilterConfig, ContentFilterRule,
+    ContentFilterStep, SseContentFilter, StreamContentFilter, CONTENT_FILTER_KEY,
+};
 pub use injection::InjectionStep;
 pub use plugin::{PluginPostStep, PluginPreStep};
-pub use provider::ProviderStep;
+pub use provider::{ProviderCallError, ProviderCallResult, ProviderStep};
 pub use routing::RoutingStep;
+pub use shadow::{
+    ShadowCaller, ShadowConfig, ShadowRecord, ShadowStep, PRIMARY_FLOW_ID_KEY,
+    PRIMARY_RESPONSE_KEY, REQUEST_PATH_KEY, SHADOW_REQUEST_KEY,
+};
 pub use telemetry::TelemetryStep;
 pub use traits::PipelineStep;
@@ -1,12 +1,12 @@
 //! Provider 调用步骤
 //!
-//! 集成重试、故障转移和超时控制
+//! 集成重试、故障转移、超时控制和自适应并发控制
 
 use super::traits::{PipelineStep, StepError};
 use crate::processor::RequestContext;
 use crate::resilience::{
-    Failover, FailoverConfig, FailoverManager, Retrier, RetryConfig, TimeoutConfig,
-    TimeoutController, TimeoutError,
+    AdaptiveConcurrency, Failover, FailoverConfig, FailoverManager, RateLimitHeaders, Retrier,
+    RetryConfig, TimeoutConfig, TimeoutController, TimeoutError,
 };
 use crate::services::provider_pool_service::ProviderPoolService;
 use crate::ProviderType;
@@ -25,6 +25,8 @@ pub struct ProviderCallResult {
     pub latency_ms: u64,
     /// 使用的凭证 ID
     pub credential_id: Option<String>,
+    /// 响应中的限流头
+    pub rate_limit: RateLimitHeaders,
 }
 
 /// Provider 调用错误
@@ -38,6 +40,8 @@ pub struct ProviderCallError {
     pub retryable: bool,
     /// 是否应触发故障转移
     pub should_failover: bool,
+    /// 响应中的限流头
+    pub rate_limit: RateLimitHeaders,
 }
 
 impl ProviderCallError {
@@ -48,6 +52,7 @@ impl ProviderCallError {
             status_code,
             retryable: true,
             should_failover: false,
+            rate_limit: RateLimitHeaders::default(),
         }
     }
 
@@ -58,6 +63,7 @@ impl ProviderCallError {
             status_code,
             retryable: false,
             should_failover: true,
+            rate_limit: RateLimitHeaders::default(),
         }
     }
 
@@ -68,6 +74,7 @@ impl ProviderCallError {
             status_code,
             retryable: false,
             should_failover: false,
+            rate_limit: RateLimitHeaders::default(),
         }
     }
 
@@ -75,11 +82,22 @@ impl ProviderCallError {
     pub fn is_quota_exceeded(&self) -> bool {
         Failover::is_quota_exceeded(self.status_code, &self.message)
     }
+
+    /// 检查是否为上游过载错误（503 / 529 或 `overloaded` 错误消息）
+    pub fn is_overloaded(&self) -> bool {
+        Failover::is_overloaded(self.status_code, &self.message)
+    }
+
+    /// 附加响应中的限流头
+    pub fn with_rate_limit(mut self, rate_limit: RateLimitHeaders) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
 }
 
 /// Provider 调用步骤
 ///
-/// 包含重试、故障转移和超时控制的 Provider 调用
+/// 包含重试、故障转移、超时控制和自适应并发控制的 Provider 调用
 pub struct ProviderStep {
     /// 重试器
     retrier: Arc<Retrier>,
@@ -89,6 +107,8 @@ pub struct ProviderStep {
     timeout: Arc<TimeoutController>,
     /// 凭证池服务
     pool_service: Arc<ProviderPoolService>,
+    /// 自适应并发控制器
+    concurrency: Arc<AdaptiveConcurrency>,
 }
 
 impl ProviderStep {
@@ -104,6 +124,7 @@ impl ProviderStep {
             failover,
             timeout,
             pool_service,
+            concurrency: Arc::new(AdaptiveConcurrency::with_defaults()),
         }
     }
 
@@ -114,6 +135,7 @@ impl ProviderStep {
             failover: Arc::new(Failover::new(FailoverConfig::default())),
             timeout: Arc::new(TimeoutController::with_defaults()),
             pool_service,
+            concurrency: Arc::new(AdaptiveConcurrency::with_defaults()),
         }
     }
 
@@ -129,9 +151,16 @@ impl ProviderStep {
             failover: Arc::new(Failover::new(failover_config)),
             timeout: Arc::new(TimeoutController::new(timeout_config)),
             pool_service,
+            concurrency: Arc::new(AdaptiveConcurrency::with_defaults()),
         }
     }
 
+    /// 使用共享的自适应并发控制器
+    pub fn with_concurrency(mut self, concurrency: Arc<AdaptiveConcurrency>) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
     /// 获取重试器
     pub fn retrier(&self) -> &Retrier {
         &self.retrier
@@ -152,6 +181,11 @@ impl ProviderStep {
         &self.pool_service
     }
 
+    /// 获取自适应并发控制器
+    pub fn concurrency(&self) -> &AdaptiveConcurrency {
+        &self.concurrency
+    }
+
     /// 带重试执行 Provider 调用
     ///
     /// 使用 Retrier 包装 Provider 调用，自动处理可重试错误
@@ -203,7 +237,9 @@ impl ProviderStep {
                         .status_code
                         .is_none_or(|code| self.retrier.config().is_retryable(code));
 
-                    let should_failover = err.should_failover || err.is_quota_exceeded();
+                    let should_failover = err.should_failover
+                        || err.is_quota_exceeded()
+                        || (err.is_overloaded() && self.failover.config().switch_on_overload);
 
                     if !should_retry || attempts > max_retries {
                         return Err(ProviderCallError {
@@ -211,11 +247,16 @@ impl ProviderStep {
                             status_code: err.status_code,
                             retryable: false,
                             should_failover,
+                            rate_limit: err.rate_limit,
                         });
                     }
 
-                    // 等待退避时间
-                    let delay = self.retrier.backoff_delay(attempts - 1);
+                    // 等待退避时间（上游过载时更长，优先遵循 Retry-After）
+                    let delay = self.retrier.backoff_delay_for(
+                        attempts - 1,
+                        err.status_code,
+                        err.rate_limit.retry_after,
+                    );
                     tokio::time::sleep(delay).await;
                 }
             }
@@ -263,6 +304,7 @@ impl ProviderStep {
                     status_code: Some(408),
                     retryable: true,
                     should_failover: false,
+                    rate_limit: RateLimitHeaders::default(),
                 })
             }
         }
@@ -359,10 +401,26 @@ impl ProviderStep {
             let result: Result<ProviderCallResult, ProviderCallError> = loop {
                 retry_attempts += 1;
 
-                // 带超时执行调用
+                // 按自适应并发上限排队，带超时执行调用，并用响应的限流头调整上限
+                let provider_key = current_provider.to_string();
+                let permit = self.concurrency.acquire(&provider_key).await;
                 let call_result = self
                     .execute_with_timeout(ctx, operation_factory(current_provider))
                     .await;
+                match &call_result {
+                    Ok(result) => self.concurrency.observe(
+                        &provider_key,
+                        result.status_code,
+                        &result.rate_limit,
+                    ),
+                    Err(err) => {
+                        if let Some(status_code) = err.status_code {
+                            self.concurrency
+                                .observe(&provider_key, status_code, &err.rate_limit);
+                        }
+                    }
+                }
+                drop(permit);
 
                 match call_result {
                     Ok(result) => break Ok(result),
@@ -389,7 +447,9 @@ impl ProviderStep {
                             .status_code
                             .is_none_or(|code| self.retrier.config().is_retryable(code));
 
-                        let should_failover = err.should_failover || err.is_quota_exceeded();
+                        let should_failover = err.should_failover
+                            || err.is_quota_exceeded()
+                            || (err.is_overloaded() && self.failover.config().switch_on_overload);
 
                         if !should_retry || retry_attempts > max_retries {
                             break Err(ProviderCallError {
@@ -397,11 +457,16 @@ impl ProviderStep {
                                 status_code: err.status_code,
                                 retryable: false,
                                 should_failover,
+                                rate_limit: err.rate_limit,
                             });
                         }
 
-                        // 等待退避时间
-                        let delay = self.retrier.backoff_delay(retry_attempts - 1);
+                        // 等待退避时间（上游过载时更长，优先遵循 Retry-After）
+                        let delay = self.retrier.backoff_delay_for(
+                            retry_attempts - 1,
+                            err.status_code,
+                            err.rate_limit.retry_after,
+                        );
                         tokio::time::sleep(delay).await;
                     }
                 }
@@ -613,6 +678,7 @@ mod tests {
                     status_code: 200,
                     latency_ms: 100,
                     credential_id: Some("cred-1".to_string()),
+                    rate_limit: RateLimitHeaders::default(),
                 })
             })
             .await;
@@ -690,6 +756,7 @@ mod tests {
                     status_code: 200,
                     latency_ms: 50,
                     credential_id: None,
+                    rate_limit: RateLimitHeaders::default(),
                 })
             })
             .await;
@@ -717,6 +784,7 @@ mod tests {
                     status_code: 200,
                     latency_ms: 200,
                     credential_id: None,
+                    rate_limit: RateLimitHeaders::default(),
                 })
             })
             .await;
@@ -1,12 +1,16 @@
 //! 路由解析步骤
 //!
-//! 解析模型别名并选择 Provider
+//! 解析模型别名、按请求大小降级模型并选择 Provider
 
 use super::traits::{PipelineStep, StepError};
-use crate::processor::RequestContext;
-use crate::router::{ModelMapper, Router};
+use crate::processor::{
+    record_history_truncation, record_model_downgrade, record_param_adjustments, record_route,
+    start_routing_trace, update_routing_trace, RequestContext,
+};
+use crate::router::{ModelMapper, ResolutionStepKind, Router};
 use crate::ProviderType;
 use async_trait::async_trait;
+use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -62,15 +66,55 @@ impl PipelineStep for RoutingStep {
         // 解析模型别名
         let resolved_model = self.resolve_model(&ctx.original_model).await;
         ctx.set_resolved_model(resolved_model.clone());
+        start_routing_trace(ctx);
 
         // 更新 payload 中的模型名
         if let Some(obj) = payload.as_object_mut() {
             obj.insert("model".to_string(), serde_json::json!(resolved_model));
         }
 
-        // 选择 Provider
-        let provider = self.select_provider(&ctx.resolved_model).await?;
+        // 截断过长的消息历史（在降级判断之前，截断后可能不再需要降级）
+        let truncation = {
+            let mapper = self.mapper.read().await;
+            mapper.truncate_history(&ctx.original_model, &ctx.resolved_model, payload)
+        };
+        if let Some(truncation) = truncation {
+            record_history_truncation(ctx, &truncation);
+        }
+
+        // 提示词过大时按别名规则降级模型
+        let downgrade = {
+            let mapper = self.mapper.read().await;
+            mapper.size_downgrade(&ctx.original_model, &ctx.resolved_model, payload)
+        };
+        if let Some(downgrade) = downgrade {
+            ctx.set_resolved_model(downgrade.to.clone());
+            if let Some(obj) = payload.as_object_mut() {
+                obj.insert("model".to_string(), serde_json::json!(downgrade.to));
+            }
+            record_model_downgrade(ctx, &downgrade);
+            update_routing_trace(ctx, |trace| {
+                trace.push_step(ResolutionStepKind::SizeDowngrade, &downgrade.to)
+            });
+        }
+
+        // 填充模型默认参数（负载中已有的字段，包括 null，视为客户端显式发送），
+        // 再应用别名参数约束（钳制超出范围的参数）
+        let (defaults, adjustments) = {
+            let mapper = self.mapper.read().await;
+            let defaults =
+                mapper.apply_model_defaults(&ctx.resolved_model, payload, &HashSet::new());
+            let adjustments = mapper.apply_param_constraints(&ctx.original_model, payload);
+            (defaults, adjustments)
+        };
+        record_param_adjustments(ctx, &defaults);
+        record_param_adjustments(ctx, &adjustments);
+
+        // 选择 Provider（未命中规则时使用默认 Provider）
+        let route = self.router.read().await.route(&ctx.resolved_model);
+        let provider = route.provider;
         ctx.set_provider(provider);
+        record_route(ctx, &route);
 
         tracing::info!(
             "[ROUTE] request_id={} original_model={} resolved_model={} provider={}",
@@ -91,7 +135,12 @@ impl PipelineStep for RoutingStep {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::router::RoutingRule;
+    use crate::processor::{routing_trace, MODEL_DOWNGRADE_KEY, PARAM_ADJUSTMENTS_KEY};
+    use crate::router::{
+        ModelDefaults, ParamConstraint, ParamConstraints, RoutingRule, SizeDowngradeRule,
+        SizeDowngrades,
+    };
+    use std::collections::HashMap;
 
     #[tokio::test]
     async fn test_routing_step_resolve_model() {
@@ -155,4 +204,117 @@ mod tests {
         assert_eq!(ctx.provider, Some(ProviderType::Kiro));
         assert_eq!(payload["model"], "claude-sonnet-4-5");
     }
+
+    #[tokio::test]
+    async fn test_routing_step_clamps_alias_params() {
+        let mut mapper = ModelMapper::new();
+        mapper.add_alias("safe-gpt", "claude-sonnet-4-5");
+        let mut constraints = ParamConstraints::new();
+        constraints.set(
+            "safe-gpt",
+            "temperature",
+            ParamConstraint::clamp(None, Some(1.0)),
+        );
+        mapper.set_param_constraints(constraints);
+
+        let step = RoutingStep::new(
+            Arc::new(RwLock::new(Router::new(ProviderType::Kiro))),
+            Arc::new(RwLock::new(mapper)),
+            Arc::new(RwLock::new("kiro".to_string())),
+        );
+
+        let mut ctx = RequestContext::new("safe-gpt".to_string());
+        let mut payload = serde_json::json!({"model": "safe-gpt", "temperature": 2.0});
+
+        let result = step.execute(&mut ctx, &mut payload).await;
+        assert!(result.is_ok());
+        assert_eq!(payload["model"], "claude-sonnet-4-5");
+        assert_eq!(payload["temperature"], serde_json::json!(1.0));
+
+        let recorded = ctx.get_metadata(PARAM_ADJUSTMENTS_KEY).unwrap();
+        assert_eq!(recorded[0]["param"], "temperature");
+        assert_eq!(recorded[0]["applied"], serde_json::json!(1.0));
+    }
+
+    #[tokio::test]
+    async fn test_routing_step_applies_model_defaults_after_alias() {
+        let mut mapper = ModelMapper::new();
+        mapper.add_alias("fast", "claude-haiku-4-5");
+        mapper.set_model_defaults(ModelDefaults::from_map(HashMap::from([(
+            "claude-*".to_string(),
+            HashMap::from([
+                ("max_tokens".to_string(), serde_json::json!(4096)),
+                ("temperature".to_string(), serde_json::json!(0.5)),
+            ]),
+        )])));
+
+        let step = RoutingStep::new(
+            Arc::new(RwLock::new(Router::new(ProviderType::Kiro))),
+            Arc::new(RwLock::new(mapper)),
+            Arc::new(RwLock::new("kiro".to_string())),
+        );
+
+        let mut ctx = RequestContext::new("fast".to_string());
+        // 显式的 null 视为有意设置
+        let mut payload = serde_json::json!({"model": "fast", "temperature": null});
+
+        let result = step.execute(&mut ctx, &mut payload).await;
+        assert!(result.is_ok());
+        assert_eq!(payload["max_tokens"], 4096);
+        assert!(payload["temperature"].is_null());
+
+        let recorded = ctx.get_metadata(PARAM_ADJUSTMENTS_KEY).unwrap();
+        assert_eq!(recorded.as_array().unwrap().len(), 1);
+        assert_eq!(recorded[0]["param"], "max_tokens");
+        assert_eq!(recorded[0]["action"], "defaulted");
+    }
+
+    #[tokio::test]
+    async fn test_routing_step_downgrades_large_prompt() {
+        let mut mapper = ModelMapper::new();
+        mapper.add_alias("smart", "claude-opus-4-5");
+        let mut downgrades = SizeDowngrades::new();
+        downgrades.set("smart", SizeDowngradeRule::new(100, "gemini-2.5-flash"));
+        mapper.set_size_downgrades(downgrades);
+
+        let mut router = Router::new(ProviderType::Kiro);
+        router.add_rule(RoutingRule::new("gemini-*", ProviderType::Gemini, 10));
+        let step = RoutingStep::new(
+            Arc::new(RwLock::new(router)),
+            Arc::new(RwLock::new(mapper)),
+            Arc::new(RwLock::new("kiro".to_string())),
+        );
+
+        // 小请求使用解析后的模型
+        let mut ctx = RequestContext::new("smart".to_string());
+        let mut payload = serde_json::json!({
+            "model": "smart",
+            "messages": [{"role": "user", "content": "hi"}]
+        });
+        step.execute(&mut ctx, &mut payload).await.unwrap();
+        assert_eq!(ctx.resolved_model, "claude-opus-4-5");
+        assert!(ctx.get_metadata(MODEL_DOWNGRADE_KEY).is_none());
+
+        // 超过阈值时降级，并按降级后的模型选择 Provider
+        let mut ctx = RequestContext::new("smart".to_string());
+        let mut payload = serde_json::json!({
+            "model": "smart",
+            "messages": [{"role": "user", "content": "the quick brown fox ".repeat(100)}]
+        });
+        step.execute(&mut ctx, &mut payload).await.unwrap();
+        assert_eq!(ctx.resolved_model, "gemini-2.5-flash");
+        assert_eq!(payload["model"], "gemini-2.5-flash");
+        assert_eq!(ctx.provider, Some(ProviderType::Gemini));
+
+        let recorded = ctx.get_metadata(MODEL_DOWNGRADE_KEY).unwrap();
+        assert_eq!(recorded["from"], "claude-opus-4-5");
+        assert_eq!(recorded["threshold_tokens"], 100);
+
+        // 解析轨迹：别名 → 降级 → 命中规则
+        let trace = routing_trace(&ctx).unwrap();
+        assert_eq!(trace.requested_model, "smart");
+        assert_eq!(trace.steps.len(), 2);
+        assert_eq!(trace.steps[1].kind, ResolutionStepKind::SizeDowngrade);
+        assert_eq!(trace.matched_rule.unwrap().pattern, "gemini-*");
+    }
 }
@@ -0,0 +1,658 @@
+//! 影子镜像步骤
+//!
+//! 在主响应产生后，按采样率将同一请求异步镜像到候选 Provider/模型，
+//! 将结果记录为独立的影子 Flow（标记 `shadow` 标签并通过 `shadow_of` 关联主 Flow），
+//! 并记录与主 Flow 的差异。影子路径的任何失败都不会影响主响应。
+
+use super::provider::{ProviderCallError, ProviderCallResult};
+use super::traits::{PipelineStep, StepError};
+use crate::flow_monitor::{
+    DiffConfig, FlowDiff, FlowDiffResult, FlowError, FlowErrorType, FlowMetadata, FlowMonitor,
+    LLMRequest, LLMResponse, RequestParameters, TokenUsage, SHADOW_TAG,
+};
+use crate::processor::RequestContext;
+use crate::ProviderType;
+use async_trait::async_trait;
+use chrono::Utc;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+/// 主 Flow ID 在请求上下文元数据中的键
+pub const PRIMARY_FLOW_ID_KEY: &str = "flow_id";
+
+/// 原始请求体在请求上下文元数据中的键
+pub const SHADOW_REQUEST_KEY: &str = "shadow_request";
+
+/// 请求路径在请求上下文元数据中的键
+pub const REQUEST_PATH_KEY: &str = "request_path";
+
+/// 主响应体在请求上下文元数据中的键
+///
+/// 主 Flow 记录的响应可能不含完整内容（如凭证池路径），设置后差异对比以此为准
+pub const PRIMARY_RESPONSE_KEY: &str = "primary_response";
+
+/// 保留的影子记录最大数量
+const MAX_SHADOW_RECORDS: usize = 100;
+
+/// 影子镜像配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShadowConfig {
+    /// 是否启用影子镜像
+    #[serde(default)]
+    pub enabled: bool,
+    /// 影子 Provider（为空时沿用主请求的 Provider）
+    #[serde(default)]
+    pub provider: Option<ProviderType>,
+    /// 影子模型（为空时沿用主请求的模型）
+    #[serde(default)]
+    pub model: Option<String>,
+    /// 采样率（0.0 - 1.0，例如 0.05 表示镜像 5% 的流量）
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: f64,
+}
+
+fn default_sample_rate() -> f64 {
+    1.0
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider: None,
+            model: None,
+            sample_rate: default_sample_rate(),
+        }
+    }
+}
+
+/// 影子请求调用器
+///
+/// 实际的 Provider 调用逻辑由服务端实现，影子步骤只负责调度和记录
+#[async_trait]
+pub trait ShadowCaller: Send + Sync {
+    /// 向指定 Provider 发送影子请求，`path` 为主请求的路径（决定请求格式）
+    async fn call(
+        &self,
+        provider: ProviderType,
+        path: &str,
+        request: serde_json::Value,
+    ) -> Result<ProviderCallResult, ProviderCallError>;
+}
+
+/// 影子镜像记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowRecord {
+    /// 主 Flow ID
+    pub primary_flow_id: String,
+    /// 影子 Flow ID（未被监控时为空）
+    pub shadow_flow_id: Option<String>,
+    /// 影子 Provider
+    pub provider: ProviderType,
+    /// 影子模型
+    pub model: String,
+    /// 影子请求错误信息
+    pub error: Option<String>,
+    /// 主 Flow 与影子 Flow 的差异
+    pub diff: Option<FlowDiffResult>,
+}
+
+/// 影子镜像步骤
+///
+/// 应放在管道末尾（主响应已产生之后）执行。克隆后共享配置和影子记录，
+/// 服务端为每个请求克隆一份并通过 [`with_caller`](Self::with_caller) 附加调用器。
+#[derive(Clone)]
+pub struct ShadowStep {
+    /// 影子配置
+    config: Arc<RwLock<ShadowConfig>>,
+    /// Flow 监控服务
+    flow_monitor: Arc<FlowMonitor>,
+    /// 影子请求调用器（未设置时不镜像）
+    caller: Option<Arc<dyn ShadowCaller>>,
+    /// 最近的影子记录
+    records: Arc<RwLock<VecDeque<ShadowRecord>>>,
+}
+
+impl ShadowStep {
+    /// 创建新的影子镜像步骤
+    pub fn new(config: ShadowConfig, flow_monitor: Arc<FlowMonitor>) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(config)),
+            flow_monitor,
+            caller: None,
+            records: Arc::new(RwLock::new(VecDeque::new())),
+        }
+    }
+
+    /// 设置影子请求调用器
+    pub fn with_caller(mut self, caller: Arc<dyn ShadowCaller>) -> Self {
+        self.caller = Some(caller);
+        self
+    }
+
+    /// 获取当前配置
+    pub fn config(&self) -> ShadowConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置
+    pub fn update_config(&self, config: ShadowConfig) {
+        *self.config.write() = config;
+    }
+
+    /// 获取最近的影子记录
+    pub fn records(&self) -> Vec<ShadowRecord> {
+        self.records.read().iter().cloned().collect()
+    }
+
+    /// 根据采样率判断本次请求是否需要镜像
+    pub fn should_sample(&self) -> bool {
+        let sample_rate = self.config.read().sample_rate;
+        if sample_rate <= 0.0 {
+            false
+        } else if sample_rate >= 1.0 {
+            true
+        } else {
+            rand::random::<f64>() < sample_rate
+        }
+    }
+
+    /// 在后台镜像请求
+    ///
+    /// 立即返回，影子请求在独立任务中执行。未设置调用器，或配置和上下文都没有
+    /// 确定 Provider 时跳过镜像并返回 `false`。
+    pub fn mirror(
+        &self,
+        ctx: &RequestContext,
+        primary_flow_id: String,
+        request: serde_json::Value,
+    ) -> bool {
+        let Some(caller) = self.caller.clone() else {
+            tracing::debug!(
+                "[SHADOW] request_id={} 未设置影子调用器，跳过镜像",
+                ctx.request_id
+            );
+            return false;
+        };
+        let config = self.config();
+        let Some(provider) = config.provider.or(ctx.provider) else {
+            tracing::warn!(
+                "[SHADOW] request_id={} 无法确定影子 Provider，跳过镜像",
+                ctx.request_id
+            );
+            return false;
+        };
+        let model = config
+            .model
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| ctx.resolved_model.clone());
+        let path = ctx
+            .get_metadata(REQUEST_PATH_KEY)
+            .and_then(|v| v.as_str())
+            .unwrap_or("/v1/chat/completions")
+            .to_string();
+        let primary_response = ctx.get_metadata(PRIMARY_RESPONSE_KEY).cloned();
+
+        let flow_monitor = self.flow_monitor.clone();
+        let records = self.records.clone();
+        let request_id = ctx.request_id.clone();
+
+        tokio::spawn(async move {
+            let record = run_shadow(
+                &flow_monitor,
+                caller.as_ref(),
+                primary_flow_id,
+                provider,
+                model,
+                path,
+                request,
+                primary_response,
+            )
+            .await;
+
+            tracing::info!(
+                "[SHADOW] request_id={} primary_flow={} shadow_flow={:?} provider={} model={} error={:?}",
+                request_id,
+                record.primary_flow_id,
+                record.shadow_flow_id,
+                record.provider,
+                record.model,
+                record.error
+            );
+
+            let mut records = records.write();
+            if records.len() >= MAX_SHADOW_RECORDS {
+                records.pop_front();
+            }
+            records.push_back(record);
+        });
+        true
+    }
+}
+
+/// 执行影子请求并记录为影子 Flow
+async fn run_shadow(
+    flow_monitor: &FlowMonitor,
+    caller: &dyn ShadowCaller,
+    primary_flow_id: String,
+    provider: ProviderType,
+    model: String,
+    path: String,
+    mut request: serde_json::Value,
+    primary_response: Option<serde_json::Value>,
+) -> ShadowRecord {
+    if let Some(obj) = request.as_object_mut() {
+        obj.insert("model".to_string(), serde_json::json!(model));
+    }
+
+    let metadata = FlowMetadata {
+        provider,
+        shadow_of: Some(primary_flow_id.clone()),
+        ..Default::default()
+    };
+    let shadow_flow_id = flow_monitor
+        .start_flow(build_shadow_request(&path, &model, &request), metadata)
+        .await;
+
+    let mut record = ShadowRecord {
+        primary_flow_id,
+        shadow_flow_id: shadow_flow_id.clone(),
+        provider,
+        model,
+        error: None,
+        diff: None,
+    };
+
+    match caller.call(provider, &path, request).await {
+        Ok(result) => {
+            if let Some(ref fid) = shadow_flow_id {
+                flow_monitor
+                    .complete_flow(fid, Some(build_shadow_response(&result)))
+                    .await;
+            }
+        }
+        Err(err) => {
+            if let Some(ref fid) = shadow_flow_id {
+                let error_type = err
+                    .status_code
+                    .map(FlowErrorType::from_status_code)
+                    .unwrap_or(FlowErrorType::Other);
+                let mut flow_error = FlowError::new(error_type, err.message.clone());
+                if let Some(code) = err.status_code {
+                    flow_error = flow_error.with_status_code(code);
+                }
+                flow_monitor.fail_flow(fid, flow_error).await;
+            }
+            record.error = Some(err.message);
+        }
+    }
+
+    if let Some(ref fid) = shadow_flow_id {
+        record.diff = diff_with_primary(
+            flow_monitor,
+            &record.primary_flow_id,
+            fid,
+            primary_response.as_ref(),
+        )
+        .await;
+    }
+
+    record
+}
+
+/// 对比主 Flow 与影子 Flow
+///
+/// 提供了主响应体时，用它替换主 Flow 中记录的响应再对比
+async fn diff_with_primary(
+    flow_monitor: &FlowMonitor,
+    primary_flow_id: &str,
+    shadow_flow_id: &str,
+    primary_response: Option<&serde_json::Value>,
+) -> Option<FlowDiffResult> {
+    let memory_store = flow_monitor.memory_store();
+    let store = memory_store.read().await;
+    let primary = store.get(primary_flow_id)?;
+    let shadow = store.get(shadow_flow_id)?;
+    let mut primary = primary.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let shadow = shadow.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(body) = primary_response {
+        let status_code = primary.response.as_ref().map_or(200, |r| r.status_code);
+        primary.response = Some(response_from_body(status_code, body));
+    }
+    Some(FlowDiff::diff(&primary, &shadow, &DiffConfig::default()))
+}
+
+/// 从请求体构建影子 LLMRequest
+fn build_shadow_request(path: &str, model: &str, body: &serde_json::Value) -> LLMRequest {
+    let messages = body
+        .get("messages")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let stream = body
+        .get("stream")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    LLMRequest {
+        method: "POST".to_string(),
+        path: path.to_string(),
+        headers: HashMap::new(),
+        body: body.clone(),
+        messages,
+        model: model.to_string(),
+        parameters: RequestParameters {
+            stream,
+            ..Default::default()
+        },
+        size_bytes: body.to_string().len(),
+        timestamp: Utc::now(),
+        ..Default::default()
+    }
+}
+
+/// 从调用结果构建影子 LLMResponse
+fn build_shadow_response(result: &ProviderCallResult) -> LLMResponse {
+    response_from_body(result.status_code, &result.response)
+}
+
+/// 从响应体构建 LLMResponse
+///
+/// 支持 OpenAI 和 Anthropic 两种响应格式
+fn response_from_body(status_code: u16, body: &serde_json::Value) -> LLMResponse {
+    let content = body
+        .pointer("/choices/0/message/content")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| {
+            body.get("content")
+                .and_then(|v| v.as_array())
+                .map(|blocks| {
+                    blocks
+                        .iter()
+                        .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+                        .collect::<Vec<_>>()
+                        .join("")
+                })
+                .unwrap_or_default()
+        });
+
+    let usage_field = |keys: &[&str]| -> u32 {
+        keys.iter()
+            .find_map(|k| body.pointer(&format!("/usage/{}", k)))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0) as u32
+    };
+    let input_tokens = usage_field(&["prompt_tokens", "input_tokens"]);
+    let output_tokens = usage_field(&["completion_tokens", "output_tokens"]);
+
+    let now = Utc::now();
+    LLMResponse {
+        status_code,
+        status_text: if status_code == 200 { "OK" } else { "Error" }.to_string(),
+        body: body.clone(),
+        content,
+        usage: TokenUsage {
+            input_tokens,
+            output_tokens,
+            total_tokens: input_tokens + output_tokens,
+            ..Default::default()
+        },
+        size_bytes: body.to_string().len(),
+        timestamp_start: now,
+        timestamp_end: now,
+        ..Default::default()
+    }
+}
+
+#[async_trait]
+impl PipelineStep for ShadowStep {
+    async fn execute(
+        &self,
+        ctx: &mut RequestContext,
+        _payload: &mut serde_json::Value,
+    ) -> Result<(), StepError> {
+        // 影子路径的任何问题都只记录日志，不影响主响应
+        if !self.should_sample() {
+            return Ok(());
+        }
+
+        let primary_flow_id = ctx
+            .get_metadata(PRIMARY_FLOW_ID_KEY)
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let request = ctx.get_metadata(SHADOW_REQUEST_KEY).cloned();
+
+        match (primary_flow_id, request) {
+            (Some(primary_flow_id), Some(request)) => {
+                self.mirror(ctx, primary_flow_id, request);
+            }
+            _ => {
+                tracing::debug!(
+                    "[SHADOW] request_id={} 缺少主 Flow ID 或原始请求，跳过镜像",
+                    ctx.request_id
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "shadow"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.config.read().enabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::FlowMonitorConfig;
+    use std::time::Duration;
+
+    struct MockCaller {
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl ShadowCaller for MockCaller {
+        async fn call(
+            &self,
+            _provider: ProviderType,
+            _path: &str,
+            _request: serde_json::Value,
+        ) -> Result<ProviderCallResult, ProviderCallError> {
+            if self.fail {
+                return Err(ProviderCallError::fatal("shadow down", Some(500)));
+            }
+            Ok(ProviderCallResult {
+                response: serde_json::json!({
+                    "choices": [{"message": {"role": "assistant", "content": "shadow answer"}}],
+                    "usage": {"prompt_tokens": 10, "completion_tokens": 5}
+                }),
+                status_code: 200,
+                latency_ms: 10,
+                credential_id: None,
+                rate_limit: Default::default(),
+            })
+        }
+    }
+
+    fn enabled_config() -> ShadowConfig {
+        ShadowConfig {
+            enabled: true,
+            provider: Some(ProviderType::Gemini),
+            model: Some("candidate-model".to_string()),
+            sample_rate: 1.0,
+        }
+    }
+
+    async fn start_primary(monitor: &FlowMonitor) -> String {
+        let request = LLMRequest {
+            model: "gpt-4".to_string(),
+            path: "/v1/chat/completions".to_string(),
+            ..Default::default()
+        };
+        let id = monitor
+            .start_flow(request, FlowMetadata::default())
+            .await
+            .unwrap();
+        monitor.complete_flow(&id, None).await;
+        id
+    }
+
+    async fn wait_for_record(step: &ShadowStep) -> ShadowRecord {
+        for _ in 0..50 {
+            if let Some(record) = step.records().pop() {
+                return record;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("影子记录未生成");
+    }
+
+    fn test_context(primary_flow_id: &str) -> RequestContext {
+        let mut ctx = RequestContext::new("gpt-4".to_string());
+        ctx.set_metadata(PRIMARY_FLOW_ID_KEY, serde_json::json!(primary_flow_id));
+        ctx.set_metadata(
+            SHADOW_REQUEST_KEY,
+            serde_json::json!({
+                "model": "gpt-4",
+                "messages": [{"role": "user", "content": "hi"}]
+            }),
+        );
+        ctx
+    }
+
+    #[tokio::test]
+    async fn test_shadow_flow_linked_and_tagged() {
+        let monitor = Arc::new(FlowMonitor::new(FlowMonitorConfig::default(), None));
+        let primary_id = start_primary(&monitor).await;
+        let step = ShadowStep::new(enabled_config(), monitor.clone())
+            .with_caller(Arc::new(MockCaller { fail: false }));
+
+        let mut ctx = test_context(&primary_id);
+        let mut payload = serde_json::json!({});
+        assert!(step.execute(&mut ctx, &mut payload).await.is_ok());
+
+        let record = wait_for_record(&step).await;
+        assert_eq!(record.primary_flow_id, primary_id);
+        assert!(record.error.is_none());
+        assert!(record.diff.is_some());
+
+        let store = monitor.memory_store();
+        let store = store.read().await;
+        let shadow = store.get(record.shadow_flow_id.as_ref().unwrap()).unwrap();
+        let shadow = shadow.read().unwrap();
+        assert_eq!(
+            shadow.metadata.shadow_of.as_deref(),
+            Some(primary_id.as_str())
+        );
+        assert_eq!(shadow.metadata.provider, ProviderType::Gemini);
+        assert_eq!(shadow.request.model, "candidate-model");
+        assert!(shadow.annotations.tags.contains(&SHADOW_TAG.to_string()));
+        assert_eq!(
+            shadow.response.as_ref().unwrap().content,
+            "shadow answer".to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diff_uses_primary_response_body() {
+        let monitor = Arc::new(FlowMonitor::new(FlowMonitorConfig::default(), None));
+        let primary_id = start_primary(&monitor).await;
+        let step = ShadowStep::new(enabled_config(), monitor.clone())
+            .with_caller(Arc::new(MockCaller { fail: false }));
+
+        let mut ctx = test_context(&primary_id);
+        ctx.set_metadata(
+            PRIMARY_RESPONSE_KEY,
+            serde_json::json!({
+                "type": "message",
+                "content": [{"type": "text", "text": "primary answer"}],
+                "usage": {"input_tokens": 10, "output_tokens": 7}
+            }),
+        );
+        assert!(step
+            .execute(&mut ctx, &mut serde_json::Value::Null)
+            .await
+            .is_ok());
+
+        let diff = wait_for_record(&step).await.diff.unwrap();
+        let content = diff
+            .response_diffs
+            .iter()
+            .find(|d| d.path == "response.content")
+            .expect("应对比主响应内容");
+        assert_eq!(
+            content.left_value,
+            Some(serde_json::json!("primary answer"))
+        );
+        assert_eq!(
+            content.right_value,
+            Some(serde_json::json!("shadow answer"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shadow_failure_does_not_affect_primary() {
+        let monitor = Arc::new(FlowMonitor::new(FlowMonitorConfig::default(), None));
+        let primary_id = start_primary(&monitor).await;
+        let step = ShadowStep::new(enabled_config(), monitor.clone())
+            .with_caller(Arc::new(MockCaller { fail: true }));
+
+        let mut ctx = test_context(&primary_id);
+        let mut payload = serde_json::json!({"id": "primary"});
+        assert!(step.execute(&mut ctx, &mut payload).await.is_ok());
+        assert_eq!(payload, serde_json::json!({"id": "primary"}));
+
+        let record = wait_for_record(&step).await;
+        assert_eq!(record.error.as_deref(), Some("shadow down"));
+    }
+
+    #[tokio::test]
+    async fn test_mirror_skips_without_provider_or_caller() {
+        let monitor = Arc::new(FlowMonitor::new(FlowMonitorConfig::default(), None));
+        let primary_id = start_primary(&monitor).await;
+        let ctx = test_context(&primary_id);
+        let request = serde_json::json!({"model": "gpt-4"});
+
+        // 没有调用器
+        let step = ShadowStep::new(enabled_config(), monitor.clone());
+        assert!(!step.mirror(&ctx, primary_id.clone(), request.clone()));
+
+        // 配置和上下文都没有 Provider 时不回退到任意 Provider
+        let step = ShadowStep::new(
+            ShadowConfig {
+                provider: None,
+                ..enabled_config()
+            },
+            monitor.clone(),
+        )
+        .with_caller(Arc::new(MockCaller { fail: false }));
+        assert!(ctx.provider.is_none());
+        assert!(!step.mirror(&ctx, primary_id, request));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(step.records().is_empty());
+    }
+
+    #[test]
+    fn test_should_sample_bounds() {
+        let monitor = Arc::new(FlowMonitor::new(FlowMonitorConfig::default(), None));
+        let step = ShadowStep::new(
+            ShadowConfig {
+                sample_rate: 0.0,
+                ..enabled_config()
+            },
+            monitor,
+        );
+        assert!((0..100).all(|_| !step.should_sample()));
+
+        step.update_config(enabled_config());
+        assert!((0..100).all(|_| step.should_sample()));
+    }
+}
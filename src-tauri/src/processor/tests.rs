@@ -231,6 +231,52 @@ async fn test_resolve_and_route() {
     assert_eq!(ctx2.resolved_model, "gemini-2.5-flash");
     assert_eq!(provider2, ProviderType::Gemini);
     assert_eq!(ctx2.provider, Some(ProviderType::Gemini));
+
+    // 解析轨迹记录别名映射和命中的规则
+    let trace = routing_trace(&ctx).unwrap();
+    assert_eq!(trace.steps.len(), 1);
+    assert_eq!(trace.resolved_model, "claude-sonnet-4-5");
+    assert_eq!(trace.matched_rule, None);
+    let trace = routing_trace(&ctx2).unwrap();
+    assert!(trace.steps.is_empty());
+    assert_eq!(trace.matched_rule.unwrap().pattern, "gemini-*");
+    assert_eq!(trace.provider, Some(ProviderType::Gemini));
+}
+
+#[tokio::test]
+async fn test_resolve_and_route_no_remap() {
+    let pool_service = Arc::new(ProviderPoolService::new());
+    let processor = RequestProcessor::with_defaults(pool_service);
+    {
+        let mut mapper = processor.mapper.write().await;
+        mapper.add_alias("gemini-2.5-flash", "claude-sonnet-4-5");
+    }
+    {
+        let mut router = processor.router.write().await;
+        router.add_rule(RoutingRule::new("gemini-*", ProviderType::Gemini, 10));
+    }
+
+    // 未设置时按别名改写
+    let mut ctx = RequestContext::new("gemini-2.5-flash".to_string());
+    processor.resolve_and_route(&mut ctx).await;
+    assert_eq!(ctx.resolved_model, "claude-sonnet-4-5");
+
+    // 设置后按字面模型名路由到对应的 Provider
+    let mut ctx = RequestContext::new("gemini-2.5-flash".to_string()).with_no_remap(true);
+    let provider = processor.resolve_and_route(&mut ctx).await;
+    assert_eq!(ctx.resolved_model, ctx.original_model);
+    assert_eq!(provider, ProviderType::Gemini);
+
+    let trace = routing_trace(&ctx).unwrap();
+    assert!(trace.steps.is_empty());
+    assert_eq!(trace.resolved_model, "gemini-2.5-flash");
+
+    // 不应用降级规则
+    let payload = serde_json::json!({"model": "gemini-2.5-flash"});
+    assert!(processor
+        .apply_size_downgrade(&mut ctx, &payload)
+        .await
+        .is_none());
 }
 
 #[tokio::test]
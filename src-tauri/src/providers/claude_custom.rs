@@ -1,6 +1,7 @@
 //! Claude Custom Provider (自定义 Claude API)
 use crate::models::anthropic::AnthropicMessagesRequest;
 use crate::models::openai::{ChatCompletionRequest, ContentPart, MessageContent};
+use reqwest::header::HeaderMap;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
@@ -15,6 +16,8 @@ pub struct ClaudeCustomConfig {
 pub struct ClaudeCustomProvider {
     pub config: ClaudeCustomConfig,
     pub client: Client,
+    /// 逐请求附加的请求头（如透传 Flow ID），不影响共享的客户端
+    pub request_headers: HeaderMap,
 }
 
 impl Default for ClaudeCustomProvider {
@@ -22,6 +25,7 @@ impl Default for ClaudeCustomProvider {
         Self {
             config: ClaudeCustomConfig::default(),
             client: Client::new(),
+            request_headers: HeaderMap::new(),
         }
     }
 }
@@ -40,6 +44,7 @@ impl ClaudeCustomProvider {
                 enabled: true,
             },
             client: Client::new(),
+            request_headers: HeaderMap::new(),
         }
     }
 
@@ -93,6 +98,7 @@ impl ClaudeCustomProvider {
         let resp = self
             .client
             .post(&url)
+            .headers(self.request_headers.clone())
             .header("x-api-key", api_key)
             .header("anthropic-version", "2023-06-01")
             .header("Content-Type", "application/json")
@@ -166,6 +172,9 @@ impl ClaudeCustomProvider {
         if let Some(sys) = system_content {
             anthropic_body["system"] = serde_json::json!(sys);
         }
+        if let Some(thinking) = &request.thinking {
+            anthropic_body["thinking"] = thinking.clone();
+        }
 
         let api_key = self
             .config
@@ -186,6 +195,7 @@ impl ClaudeCustomProvider {
         let resp = self
             .client
             .post(&url)
+            .headers(self.request_headers.clone())
             .header("x-api-key", api_key)
             .header("anthropic-version", "2023-06-01")
             .header("Content-Type", "application/json")
@@ -208,10 +218,10 @@ impl ClaudeCustomProvider {
 
         let anthropic_resp: serde_json::Value = resp.json().await?;
 
-        // 转换回 OpenAI 格式
+        // 转换回 OpenAI 格式（启用 thinking 时首个内容块是思考块，取第一个文本块）
         let content = anthropic_resp["content"]
             .as_array()
-            .and_then(|arr| arr.first())
+            .and_then(|arr| arr.iter().find(|block| block["type"] == "text"))
             .and_then(|block| block["text"].as_str())
             .unwrap_or("");
 
@@ -270,6 +280,7 @@ impl ClaudeCustomProvider {
         let resp = self
             .client
             .post(&url)
+            .headers(self.request_headers.clone())
             .header("x-api-key", api_key)
             .header("anthropic-version", "2023-06-01")
             .header("Content-Type", "application/json")
@@ -302,6 +313,7 @@ impl ClaudeCustomProvider {
         let resp = self
             .client
             .post(&url)
+            .headers(self.request_headers.clone())
             .header("x-api-key", api_key)
             .header("anthropic-version", "2023-06-01")
             .header("Content-Type", "application/json")
@@ -396,6 +408,9 @@ impl StreamingProvider for ClaudeCustomProvider {
         if let Some(sys) = system_content {
             anthropic_body["system"] = serde_json::json!(sys);
         }
+        if let Some(thinking) = &request.thinking {
+            anthropic_body["thinking"] = thinking.clone();
+        }
 
         let url = self.build_url("messages");
 
@@ -408,6 +423,7 @@ impl StreamingProvider for ClaudeCustomProvider {
         let resp = self
             .client
             .post(&url)
+            .headers(self.request_headers.clone())
             .header("x-api-key", api_key)
             .header("anthropic-version", "2023-06-01")
             .header("Content-Type", "application/json")
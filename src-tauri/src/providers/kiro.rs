@@ -3,6 +3,7 @@ use crate::converter::openai_to_cw::convert_openai_to_codewhisperer;
 use crate::models::openai::*;
 use crate::providers::traits::{CredentialProvider, ProviderResult};
 use async_trait::async_trait;
+use reqwest::header::HeaderMap;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
@@ -231,6 +232,8 @@ impl Default for KiroCredentials {
 pub struct KiroProvider {
     pub credentials: KiroCredentials,
     pub client: Client,
+    /// 逐请求附加的请求头（如透传 Flow ID），不影响共享的客户端
+    pub request_headers: HeaderMap,
     /// 当前加载的凭证文件路径
     pub creds_path: Option<PathBuf>,
 }
@@ -240,6 +243,7 @@ impl Default for KiroProvider {
         Self {
             credentials: KiroCredentials::default(),
             client: Client::new(),
+            request_headers: HeaderMap::new(),
             creds_path: None,
         }
     }
@@ -996,6 +1000,7 @@ impl KiroProvider {
         let resp = self
             .client
             .post(&url)
+            .headers(self.request_headers.clone())
             .header("Authorization", format!("Bearer {token}"))
             .header("Content-Type", "application/json")
             .header("Accept", "application/json")
@@ -1150,6 +1155,7 @@ impl StreamingProvider for KiroProvider {
         let resp = self
             .client
             .post(&url)
+            .headers(self.request_headers.clone())
             .header("Authorization", format!("Bearer {token}"))
             .header("Content-Type", "application/json")
             .header("Accept", "application/vnd.amazon.eventstream")
@@ -1,5 +1,6 @@
 //! OpenAI Custom Provider (自定义 OpenAI 兼容 API)
 use crate::models::openai::ChatCompletionRequest;
+use reqwest::header::HeaderMap;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
@@ -14,6 +15,8 @@ pub struct OpenAICustomConfig {
 pub struct OpenAICustomProvider {
     pub config: OpenAICustomConfig,
     pub client: Client,
+    /// 逐请求附加的请求头（如透传 Flow ID），不影响共享的客户端
+    pub request_headers: HeaderMap,
 }
 
 impl Default for OpenAICustomProvider {
@@ -21,6 +24,7 @@ impl Default for OpenAICustomProvider {
         Self {
             config: OpenAICustomConfig::default(),
             client: Client::new(),
+            request_headers: HeaderMap::new(),
         }
     }
 }
@@ -39,6 +43,7 @@ impl OpenAICustomProvider {
                 enabled: true,
             },
             client: Client::new(),
+            request_headers: HeaderMap::new(),
         }
     }
 
@@ -84,6 +89,7 @@ impl OpenAICustomProvider {
         let resp = self
             .client
             .post(&url)
+            .headers(self.request_headers.clone())
             .header("Authorization", format!("Bearer {api_key}"))
             .header("Content-Type", "application/json")
             .json(request)
@@ -93,6 +99,36 @@ impl OpenAICustomProvider {
         Ok(resp)
     }
 
+    /// 转发 multipart 上传请求（音频转写、文件上传等）
+    ///
+    /// `endpoint` 为 `/v1/` 之后的路径，请求体以流的形式转发。
+    pub async fn forward_multipart(
+        &self,
+        endpoint: &str,
+        content_type: &str,
+        body: reqwest::Body,
+    ) -> Result<reqwest::Response, Box<dyn Error + Send + Sync>> {
+        let api_key = self
+            .config
+            .api_key
+            .as_ref()
+            .ok_or("OpenAI API key not configured")?;
+
+        let url = self.build_url(endpoint);
+
+        let resp = self
+            .client
+            .post(&url)
+            .headers(self.request_headers.clone())
+            .header("Authorization", format!("Bearer {api_key}"))
+            .header("Content-Type", content_type)
+            .body(body)
+            .send()
+            .await?;
+
+        Ok(resp)
+    }
+
     pub async fn chat_completions(
         &self,
         request: &serde_json::Value,
@@ -108,6 +144,7 @@ impl OpenAICustomProvider {
         let resp = self
             .client
             .post(&url)
+            .headers(self.request_headers.clone())
             .header("Authorization", format!("Bearer {api_key}"))
             .header("Content-Type", "application/json")
             .json(request)
@@ -129,6 +166,7 @@ impl OpenAICustomProvider {
         let resp = self
             .client
             .get(&url)
+            .headers(self.request_headers.clone())
             .header("Authorization", format!("Bearer {api_key}"))
             .send()
             .await?;
@@ -186,6 +224,7 @@ impl StreamingProvider for OpenAICustomProvider {
         let resp = self
             .client
             .post(&url)
+            .headers(self.request_headers.clone())
             .header("Authorization", format!("Bearer {api_key}"))
             .header("Content-Type", "application/json")
             .header("Accept", "text/event-stream")
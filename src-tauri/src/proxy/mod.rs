@@ -1,9 +1,15 @@
 //! 代理模块
 //!
-//! 提供 Per-Key 代理支持，允许为每个凭证配置独立的代理设置
+//! 提供 Per-Key 代理支持，允许为每个凭证配置独立的代理设置，
+//! 以及按 Provider 覆盖的上游代理和客户端证书（mTLS）
 
 mod client_factory;
+mod client_tls;
+mod pkcs12;
 #[cfg(test)]
 mod tests;
+mod upstream;
 
 pub use client_factory::{ProxyClientFactory, ProxyError, ProxyProtocol};
+pub use client_tls::{validate_client_tls_settings, ClientTls, ClientTlsError};
+pub use upstream::{validate_extra_headers, validate_proxy_settings, UpstreamProxies};
@@ -0,0 +1,93 @@
+//! PKCS#12 证书解析
+//!
+//! reqwest 使用 rustls，无法直接加载 PKCS#12（.p12 / .pfx），这里用 `p12-keystore`
+//! 解密后转换为 PEM。支持 OpenSSL 3 默认的 PBES2（AES）加密，以及旧版文件常用的
+//! 3DES / RC2 加密。
+
+use ::pkcs12::pfx::Pfx;
+use base64::prelude::*;
+use der::Decode;
+use p12_keystore::error::Error as KeyStoreError;
+use p12_keystore::KeyStore;
+
+/// MAC 迭代次数上限
+///
+/// OpenSSL 和 Windows 导出的文件通常为 2000 ~ 10000 次，
+/// 过大的迭代次数会让加载证书长时间占用 CPU。
+const MAX_MAC_ITERATIONS: i32 = 1_000_000;
+
+/// 解密 PKCS#12 文件并转换为 PEM（私钥在前，证书链按终端证书到根证书的顺序紧随其后）
+pub fn to_pem(data: &[u8], password: &str) -> Result<Vec<u8>, String> {
+    let pfx = Pfx::from_der(data).map_err(|e| format!("PKCS#12 格式无效: {}", e))?;
+    if let Some(mac_data) = &pfx.mac_data {
+        if mac_data.iterations > MAX_MAC_ITERATIONS {
+            return Err(format!(
+                "PKCS#12 MAC 迭代次数过大: {}（上限 {}）",
+                mac_data.iterations, MAX_MAC_ITERATIONS
+            ));
+        }
+    }
+
+    let keystore = KeyStore::from_pkcs12(data, password).map_err(|e| match e {
+        KeyStoreError::MacError(_) => {
+            "PKCS#12 MAC 校验失败，请检查 client_cert_password".to_string()
+        }
+        KeyStoreError::UnpadError => "解密失败，请检查 client_cert_password".to_string(),
+        e => format!("PKCS#12 解析失败: {}", e),
+    })?;
+    let Some((_, chain)) = keystore.private_key_chain() else {
+        return Err("PKCS#12 中没有与证书匹配的私钥".to_string());
+    };
+
+    let mut pem = encode_pem("PRIVATE KEY", chain.key());
+    for cert in chain.chain() {
+        pem.push_str(&encode_pem("CERTIFICATE", cert.as_der()));
+    }
+    Ok(pem.into_bytes())
+}
+
+fn encode_pem(label: &str, der: &[u8]) -> String {
+    let encoded = BASE64_STANDARD.encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        // base64 输出只包含 ASCII 字符
+        pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::client_tls::tests::{
+        test_legacy_p12, test_p12, TEST_CERT, TEST_KEY, TEST_P12_PASSWORD,
+    };
+
+    #[test]
+    fn test_pkcs12_to_pem() {
+        let pem = to_pem(&test_p12(), TEST_P12_PASSWORD).unwrap();
+        assert_eq!(
+            String::from_utf8(pem).unwrap(),
+            format!("{}{}", TEST_KEY, TEST_CERT)
+        );
+
+        let err = to_pem(&test_p12(), "wrong").unwrap_err();
+        assert!(err.contains("MAC"));
+
+        // 截断的文件不应 panic
+        let data = test_p12();
+        assert!(to_pem(&data[..data.len() / 2], TEST_P12_PASSWORD).is_err());
+    }
+
+    #[test]
+    fn test_legacy_pkcs12_to_pem() {
+        // 证书使用 RC2-40 加密，私钥使用 3DES 加密
+        let pem = to_pem(&test_legacy_p12(), TEST_P12_PASSWORD).unwrap();
+        assert_eq!(
+            String::from_utf8(pem).unwrap(),
+            format!("{}{}", TEST_KEY, TEST_CERT)
+        );
+    }
+}
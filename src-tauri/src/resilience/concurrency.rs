@@ -0,0 +1,514 @@
+//! 自适应并发控制
+//!
+//! 根据上游返回的限流头（`x-ratelimit-remaining`、`retry-after` 等）按 AIMD 方式
+//! 调整每个 Provider 的并发上限：收到 429 或余量耗尽时按比例收缩，
+//! 余量充足时缓慢增加，使吞吐量保持在限流阈值之下而无需手动调参。
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// 剩余请求数的响应头（按优先级）
+const REMAINING_HEADERS: &[&str] = &[
+    "x-ratelimit-remaining-requests",
+    "x-ratelimit-remaining",
+    "anthropic-ratelimit-requests-remaining",
+];
+
+/// 请求数上限的响应头（按优先级）
+const LIMIT_HEADERS: &[&str] = &[
+    "x-ratelimit-limit-requests",
+    "x-ratelimit-limit",
+    "anthropic-ratelimit-requests-limit",
+];
+
+/// 耗时移动平均的平滑系数
+const LATENCY_EWMA_ALPHA: f64 = 0.2;
+
+/// 自适应并发配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AdaptiveConcurrencyConfig {
+    /// 是否启用（禁用时不限制并发）
+    pub enabled: bool,
+    /// 初始并发上限
+    pub initial_limit: usize,
+    /// 最小并发上限
+    pub min_limit: usize,
+    /// 最大并发上限
+    pub max_limit: usize,
+    /// 加性增长步长（每次成功响应增长 `step / 当前上限`）
+    pub increase_step: f64,
+    /// 乘性收缩因子
+    pub decrease_factor: f64,
+    /// 剩余比例低于该值时视为没有余量，不再增长
+    pub headroom_ratio: f64,
+}
+
+impl Default for AdaptiveConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            initial_limit: 8,
+            min_limit: 1,
+            max_limit: 64,
+            increase_step: 1.0,
+            decrease_factor: 0.5,
+            headroom_ratio: 0.1,
+        }
+    }
+}
+
+/// 从响应头解析出的限流信息
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimitHeaders {
+    /// 当前窗口剩余请求数
+    pub remaining: Option<u64>,
+    /// 当前窗口请求数上限
+    pub limit: Option<u64>,
+    /// 建议的重试等待时间
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimitHeaders {
+    /// 通过头部查找函数解析（头部名称为小写）
+    pub fn parse<'a>(get: impl Fn(&str) -> Option<&'a str>) -> Self {
+        let first_u64 = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| get(name).and_then(|v| v.trim().parse::<u64>().ok()))
+        };
+
+        // retry-after-ms 更精确；retry-after 仅支持秒数（HTTP 日期格式忽略）
+        let retry_after = get("retry-after-ms")
+            .and_then(|v| v.trim().parse::<f64>().ok())
+            .map(|ms| Duration::from_secs_f64(ms.max(0.0) / 1000.0))
+            .or_else(|| {
+                get("retry-after")
+                    .and_then(|v| v.trim().parse::<f64>().ok())
+                    .map(|secs| Duration::from_secs_f64(secs.max(0.0)))
+            });
+
+        Self {
+            remaining: first_u64(REMAINING_HEADERS),
+            limit: first_u64(LIMIT_HEADERS),
+            retry_after,
+        }
+    }
+
+    /// 从 HTTP 响应头解析
+    pub fn from_header_map(headers: &reqwest::header::HeaderMap) -> Self {
+        Self::parse(|name| headers.get(name).and_then(|v| v.to_str().ok()))
+    }
+}
+
+/// 单个 Provider 的并发状态
+#[derive(Debug)]
+struct ProviderState {
+    /// 当前并发上限（小数部分用于累积加性增长）
+    limit: f64,
+    /// 正在进行的请求数
+    in_flight: usize,
+    /// retry-after 要求的暂停截止时间
+    blocked_until: Option<Instant>,
+    /// 最近一次观测到的剩余请求数
+    last_remaining: Option<u64>,
+    /// 调用耗时的指数移动平均（毫秒）
+    avg_latency_ms: Option<f64>,
+}
+
+impl ProviderState {
+    fn effective_limit(&self) -> usize {
+        (self.limit.floor() as usize).max(1)
+    }
+}
+
+/// Provider 并发状态快照
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProviderConcurrency {
+    /// Provider 名称
+    pub provider: String,
+    /// 当前有效并发上限
+    pub limit: usize,
+    /// 正在进行的请求数
+    pub in_flight: usize,
+    /// 最近一次观测到的剩余请求数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining: Option<u64>,
+    /// retry-after 剩余暂停时间（毫秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked_for_ms: Option<u64>,
+    /// 平均调用耗时（毫秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_latency_ms: Option<u64>,
+}
+
+struct Shared {
+    config: AdaptiveConcurrencyConfig,
+    states: Mutex<HashMap<String, ProviderState>>,
+    /// 有请求结束或上限变化时唤醒等待者
+    notify: Notify,
+}
+
+/// 自适应并发控制器
+///
+/// 按 Provider 独立维护并发上限。调用前通过 [`acquire`](Self::acquire) 获取许可，
+/// 收到响应后通过 [`observe`](Self::observe) 反馈状态码和限流头。
+#[derive(Clone)]
+pub struct AdaptiveConcurrency {
+    shared: Arc<Shared>,
+}
+
+impl AdaptiveConcurrency {
+    /// 创建控制器
+    pub fn new(config: AdaptiveConcurrencyConfig) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                config,
+                states: Mutex::new(HashMap::new()),
+                notify: Notify::new(),
+            }),
+        }
+    }
+
+    /// 使用默认配置创建
+    pub fn with_defaults() -> Self {
+        Self::new(AdaptiveConcurrencyConfig::default())
+    }
+
+    /// 获取配置
+    pub fn config(&self) -> &AdaptiveConcurrencyConfig {
+        &self.shared.config
+    }
+
+    /// 获取调用许可
+    ///
+    /// 正在进行的请求数达到上限或处于 retry-after 暂停期时等待；许可释放时计数减一。
+    pub async fn acquire(&self, provider: &str) -> ConcurrencyPermit {
+        if !self.shared.config.enabled {
+            return ConcurrencyPermit {
+                shared: None,
+                provider: String::new(),
+            };
+        }
+
+        loop {
+            // 先注册通知再检查状态，避免错过检查期间的唤醒
+            let notified = self.shared.notify.notified();
+            let wait = {
+                let mut states = self.shared.states.lock();
+                let state = self.state_entry(&mut states, provider);
+                let now = Instant::now();
+                match state.blocked_until {
+                    Some(until) if until > now => Some(until - now),
+                    _ => {
+                        state.blocked_until = None;
+                        if state.in_flight < state.effective_limit() {
+                            state.in_flight += 1;
+                            return ConcurrencyPermit {
+                                shared: Some(self.shared.clone()),
+                                provider: provider.to_string(),
+                            };
+                        }
+                        None
+                    }
+                }
+            };
+
+            match wait {
+                Some(duration) => tokio::time::sleep(duration).await,
+                None => notified.await,
+            }
+        }
+    }
+
+    /// 根据响应调整并发上限
+    ///
+    /// - 429 或剩余请求数为 0：上限乘以收缩因子，并按 retry-after 暂停
+    /// - 成功且有余量：上限增加 `increase_step / 上限`
+    /// - 成功但余量不足：保持不变
+    /// - 其他错误：保持不变
+    pub fn observe(&self, provider: &str, status_code: u16, rate_limit: &RateLimitHeaders) {
+        if !self.shared.config.enabled {
+            return;
+        }
+        let config = &self.shared.config;
+        let mut states = self.shared.states.lock();
+        let state = self.state_entry(&mut states, provider);
+        let previous = state.effective_limit();
+
+        if rate_limit.remaining.is_some() {
+            state.last_remaining = rate_limit.remaining;
+        }
+
+        if status_code == 429 || rate_limit.remaining == Some(0) {
+            state.limit = (state.limit * config.decrease_factor).max(config.min_limit as f64);
+            if let Some(retry_after) = rate_limit.retry_after {
+                let until = Instant::now() + retry_after;
+                state.blocked_until = Some(state.blocked_until.map_or(until, |u| u.max(until)));
+            }
+        } else if (200..300).contains(&status_code) && Self::has_headroom(config, state, rate_limit)
+        {
+            state.limit = (state.limit + config.increase_step / state.limit.max(1.0))
+                .min(config.max_limit as f64);
+        }
+
+        let current = state.effective_limit();
+        drop(states);
+        if current != previous {
+            tracing::debug!(
+                "[CONCURRENCY] provider={} limit {} -> {} status={} remaining={:?}",
+                provider,
+                previous,
+                current,
+                status_code,
+                rate_limit.remaining
+            );
+            self.shared.notify.notify_waiters();
+        }
+    }
+
+    /// 记录一次调用耗时（指数移动平均）
+    pub fn record_latency(&self, provider: &str, latency: Duration) {
+        if !self.shared.config.enabled {
+            return;
+        }
+        let sample = latency.as_secs_f64() * 1000.0;
+        let mut states = self.shared.states.lock();
+        let state = self.state_entry(&mut states, provider);
+        state.avg_latency_ms = Some(match state.avg_latency_ms {
+            Some(avg) => avg + LATENCY_EWMA_ALPHA * (sample - avg),
+            None => sample,
+        });
+    }
+
+    /// 获取 Provider 当前的有效并发上限
+    pub fn current_limit(&self, provider: &str) -> usize {
+        self.shared
+            .states
+            .lock()
+            .get(provider)
+            .map(ProviderState::effective_limit)
+            .unwrap_or(self.shared.config.initial_limit.max(1))
+    }
+
+    /// 获取所有 Provider 的并发状态（按名称排序）
+    pub fn snapshot(&self) -> Vec<ProviderConcurrency> {
+        let now = Instant::now();
+        let states = self.shared.states.lock();
+        let mut snapshot: Vec<_> = states
+            .iter()
+            .map(|(provider, state)| ProviderConcurrency {
+                provider: provider.clone(),
+                limit: state.effective_limit(),
+                in_flight: state.in_flight,
+                remaining: state.last_remaining,
+                blocked_for_ms: state
+                    .blocked_until
+                    .filter(|until| *until > now)
+                    .map(|until| (until - now).as_millis() as u64),
+                avg_latency_ms: state.avg_latency_ms.map(|ms| ms.round() as u64),
+            })
+            .collect();
+        snapshot.sort_by(|a, b| a.provider.cmp(&b.provider));
+        snapshot
+    }
+
+    fn state_entry<'a>(
+        &self,
+        states: &'a mut HashMap<String, ProviderState>,
+        provider: &str,
+    ) -> &'a mut ProviderState {
+        let config = &self.shared.config;
+        states
+            .entry(provider.to_string())
+            .or_insert_with(|| ProviderState {
+                limit: config
+                    .initial_limit
+                    .clamp(config.min_limit.max(1), config.max_limit.max(1))
+                    as f64,
+                in_flight: 0,
+                blocked_until: None,
+                last_remaining: None,
+                avg_latency_ms: None,
+            })
+    }
+
+    /// 判断是否还有余量
+    ///
+    /// 同时有剩余数和上限时按比例判断；只有剩余数时要求其大于当前并发上限；
+    /// 没有限流头时视为有余量。
+    fn has_headroom(
+        config: &AdaptiveConcurrencyConfig,
+        state: &ProviderState,
+        rate_limit: &RateLimitHeaders,
+    ) -> bool {
+        match (rate_limit.remaining, rate_limit.limit) {
+            (Some(remaining), Some(limit)) if limit > 0 => {
+                remaining as f64 / limit as f64 > config.headroom_ratio
+                    && remaining as f64 > state.limit
+            }
+            (Some(remaining), _) => remaining as f64 > state.limit,
+            (None, _) => true,
+        }
+    }
+}
+
+impl Default for AdaptiveConcurrency {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+impl std::fmt::Debug for AdaptiveConcurrency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdaptiveConcurrency")
+            .field("config", &self.shared.config)
+            .field("providers", &self.snapshot())
+            .finish()
+    }
+}
+
+/// 并发许可，释放时归还名额
+pub struct ConcurrencyPermit {
+    shared: Option<Arc<Shared>>,
+    provider: String,
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.take() {
+            if let Some(state) = shared.states.lock().get_mut(&self.provider) {
+                state.in_flight = state.in_flight.saturating_sub(1);
+            }
+            shared.notify.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> RateLimitHeaders {
+        let map: HashMap<String, String> = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        RateLimitHeaders::parse(|name| map.get(name).map(String::as_str))
+    }
+
+    #[test]
+    fn test_parse_rate_limit_headers() {
+        let rl = headers(&[
+            ("x-ratelimit-remaining-requests", "42"),
+            ("x-ratelimit-limit-requests", "500"),
+            ("retry-after", "2"),
+        ]);
+        assert_eq!(rl.remaining, Some(42));
+        assert_eq!(rl.limit, Some(500));
+        assert_eq!(rl.retry_after, Some(Duration::from_secs(2)));
+
+        let rl = headers(&[("retry-after-ms", "250"), ("retry-after", "9")]);
+        assert_eq!(rl.retry_after, Some(Duration::from_millis(250)));
+
+        // HTTP 日期格式不解析
+        let rl = headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")]);
+        assert_eq!(rl, RateLimitHeaders::default());
+    }
+
+    #[test]
+    fn test_simulated_header_sequences() {
+        let controller = AdaptiveConcurrency::new(AdaptiveConcurrencyConfig {
+            initial_limit: 4,
+            max_limit: 16,
+            ..Default::default()
+        });
+
+        // 余量充足：缓慢增长
+        for _ in 0..40 {
+            controller.observe(
+                "openai",
+                200,
+                &headers(&[
+                    ("x-ratelimit-remaining-requests", "900"),
+                    ("x-ratelimit-limit-requests", "1000"),
+                ]),
+            );
+        }
+        let grown = controller.current_limit("openai");
+        assert!(grown > 4 && grown <= 16, "grown={}", grown);
+
+        // 余量接近耗尽：保持不变
+        for _ in 0..20 {
+            controller.observe(
+                "openai",
+                200,
+                &headers(&[
+                    ("x-ratelimit-remaining-requests", "50"),
+                    ("x-ratelimit-limit-requests", "1000"),
+                ]),
+            );
+        }
+        assert_eq!(controller.current_limit("openai"), grown);
+
+        // 429：按比例收缩并按 retry-after 暂停
+        controller.observe("openai", 429, &headers(&[("retry-after", "30")]));
+        let shrunk = controller.current_limit("openai");
+        assert_eq!(shrunk, grown / 2);
+        let snapshot = controller.snapshot();
+        assert_eq!(snapshot[0].provider, "openai");
+        assert!(snapshot[0].blocked_for_ms.unwrap() > 29_000);
+        assert_eq!(snapshot[0].remaining, Some(50));
+
+        // 剩余为 0 同样收缩，且不低于最小值
+        for _ in 0..10 {
+            controller.observe("openai", 200, &headers(&[("x-ratelimit-remaining", "0")]));
+        }
+        assert_eq!(controller.current_limit("openai"), 1);
+
+        // 服务端错误不影响上限，其他 Provider 独立
+        controller.observe("openai", 500, &RateLimitHeaders::default());
+        assert_eq!(controller.current_limit("openai"), 1);
+        assert_eq!(controller.current_limit("claude"), 4);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_for_release() {
+        let controller = AdaptiveConcurrency::new(AdaptiveConcurrencyConfig {
+            initial_limit: 1,
+            ..Default::default()
+        });
+
+        let permit = controller.acquire("kiro").await;
+        assert_eq!(controller.snapshot()[0].in_flight, 1);
+
+        let waiter = {
+            let controller = controller.clone();
+            tokio::spawn(async move {
+                let _permit = controller.acquire("kiro").await;
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        drop(permit);
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("释放许可后应获得名额")
+            .unwrap();
+        assert_eq!(controller.snapshot()[0].in_flight, 0);
+
+        controller.record_latency("kiro", Duration::from_millis(100));
+        controller.record_latency("kiro", Duration::from_millis(200));
+        assert_eq!(controller.snapshot()[0].avg_latency_ms, Some(120));
+
+        // retry-after 暂停期间等待
+        controller.observe("kiro", 429, &headers(&[("retry-after-ms", "50")]));
+        let start = Instant::now();
+        let _permit = controller.acquire("kiro").await;
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! 提供 Provider 故障转移和自动切换功能
 
+use super::retry::is_overloaded_status;
 use crate::ProviderType;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -27,6 +28,13 @@ pub struct FailoverConfig {
     pub auto_switch: bool,
     /// 是否在配额超限时切换
     pub switch_on_quota: bool,
+    /// 是否在上游过载（重试耗尽后）时切换
+    #[serde(default = "default_switch_on_overload")]
+    pub switch_on_overload: bool,
+}
+
+fn default_switch_on_overload() -> bool {
+    true
 }
 
 impl Default for FailoverConfig {
@@ -34,6 +42,7 @@ impl Default for FailoverConfig {
         Self {
             auto_switch: true,
             switch_on_quota: true,
+            switch_on_overload: true,
         }
     }
 }
@@ -44,6 +53,7 @@ impl FailoverConfig {
         Self {
             auto_switch,
             switch_on_quota,
+            switch_on_overload: default_switch_on_overload(),
         }
     }
 
@@ -52,6 +62,7 @@ impl FailoverConfig {
         Self {
             auto_switch: false,
             switch_on_quota: false,
+            switch_on_overload: false,
         }
     }
 }
@@ -63,6 +74,8 @@ pub enum FailureType {
     QuotaExceeded,
     /// 认证失败
     AuthenticationFailed,
+    /// 上游过载（如 Anthropic 529 `overloaded_error`）
+    Overloaded,
     /// 服务不可用
     ServiceUnavailable,
     /// 其他错误
@@ -95,9 +108,14 @@ impl FailureType {
             }
         }
 
+        // 检查上游过载
+        if status_code.is_some_and(is_overloaded_status) || error_lower.contains("overloaded") {
+            return FailureType::Overloaded;
+        }
+
         // 检查服务不可用
         if let Some(code) = status_code {
-            if code == 502 || code == 503 || code == 504 {
+            if code == 502 || code == 504 {
                 return FailureType::ServiceUnavailable;
             }
         }
@@ -109,6 +127,11 @@ impl FailureType {
     pub fn is_quota_exceeded(&self) -> bool {
         matches!(self, FailureType::QuotaExceeded)
     }
+
+    /// 是否为上游过载
+    pub fn is_overloaded(&self) -> bool {
+        matches!(self, FailureType::Overloaded)
+    }
 }
 
 /// 故障转移结果
@@ -198,6 +221,7 @@ impl Failover {
         // 检查是否应该在此类故障时切换
         let should_switch = match &failure_type {
             FailureType::QuotaExceeded => self.config.switch_on_quota,
+            FailureType::Overloaded => self.config.switch_on_overload,
             FailureType::ServiceUnavailable => true,
             FailureType::AuthenticationFailed => false, // 认证失败通常不应切换
             FailureType::Other => false,
@@ -250,6 +274,11 @@ impl Failover {
     pub fn is_quota_exceeded(status_code: Option<u16>, error_message: &str) -> bool {
         FailureType::detect(status_code, error_message).is_quota_exceeded()
     }
+
+    /// 检查是否为上游过载错误（529 或错误消息含 `overloaded`；503 按服务不可用处理）
+    pub fn is_overloaded(status_code: Option<u16>, error_message: &str) -> bool {
+        FailureType::detect(status_code, error_message).is_overloaded()
+    }
 }
 
 impl Default for Failover {
@@ -339,6 +368,7 @@ impl FailoverManager {
         // 检查是否应该在此类故障时切换
         let should_switch = match &failure_type {
             FailureType::QuotaExceeded => self.failover.config().switch_on_quota,
+            FailureType::Overloaded => self.failover.config().switch_on_overload,
             FailureType::ServiceUnavailable => true,
             FailureType::AuthenticationFailed => false,
             FailureType::Other => false,
@@ -465,10 +495,6 @@ mod unit_tests {
             FailureType::detect(Some(502), "Bad Gateway"),
             FailureType::ServiceUnavailable
         );
-        assert_eq!(
-            FailureType::detect(Some(503), "Service Unavailable"),
-            FailureType::ServiceUnavailable
-        );
         assert_eq!(
             FailureType::detect(Some(504), "Gateway Timeout"),
             FailureType::ServiceUnavailable
@@ -550,16 +576,47 @@ mod unit_tests {
     }
 
     #[test]
-    fn test_handle_failure_service_unavailable() {
-        let failover = Failover::with_defaults();
+    fn test_handle_failure_overloaded() {
+        assert_eq!(
+            FailureType::detect(Some(529), r#"{"type":"overloaded_error"}"#),
+            FailureType::Overloaded
+        );
+        // 503 与 FlowErrorType、重试退避一致地归为过载
+        assert_eq!(
+            FailureType::detect(Some(503), "Service Unavailable"),
+            FailureType::Overloaded
+        );
+
         let available = vec![ProviderType::Kiro, ProviderType::Gemini];
+        let result = Failover::with_defaults().handle_failure(
+            ProviderType::Kiro,
+            Some(529),
+            "Overloaded",
+            &available,
+        );
+        assert!(result.switched);
+        assert_eq!(result.failure_type, FailureType::Overloaded);
 
-        let result = failover.handle_failure(
+        let config = FailoverConfig {
+            switch_on_overload: false,
+            ..FailoverConfig::default()
+        };
+        let result = Failover::new(config).handle_failure(
             ProviderType::Kiro,
-            Some(503),
-            "Service Unavailable",
+            Some(529),
+            "Overloaded",
             &available,
         );
+        assert!(!result.switched);
+    }
+
+    #[test]
+    fn test_handle_failure_service_unavailable() {
+        let failover = Failover::with_defaults();
+        let available = vec![ProviderType::Kiro, ProviderType::Gemini];
+
+        let result =
+            failover.handle_failure(ProviderType::Kiro, Some(502), "Bad Gateway", &available);
 
         assert!(result.switched);
         assert_eq!(result.new_provider, Some(ProviderType::Gemini));
@@ -682,8 +739,8 @@ mod manager_tests {
         manager.handle_failure_and_switch(ProviderType::Kiro, Some(429), "Rate limit", &available);
         manager.handle_failure_and_switch(
             ProviderType::Gemini,
-            Some(503),
-            "Service Unavailable",
+            Some(502),
+            "Bad Gateway",
             &available,
         );
 
@@ -735,6 +792,7 @@ mod manager_tests {
         let config = FailoverConfig {
             auto_switch: true,
             switch_on_quota: false,
+            ..FailoverConfig::default()
         };
         let mut manager = FailoverManager::new(config);
         let available = vec![ProviderType::Kiro, ProviderType::Gemini];
@@ -752,8 +810,8 @@ mod manager_tests {
         manager.reset();
         let result = manager.handle_failure_and_switch(
             ProviderType::Kiro,
-            Some(503),
-            "Service Unavailable",
+            Some(502),
+            "Bad Gateway",
             &available,
         );
         assert!(result.switched);
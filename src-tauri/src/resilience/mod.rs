@@ -1,16 +1,21 @@
 //! 容错机制模块
 //!
-//! 提供重试、故障转移和超时控制功能
+//! 提供重试、故障转移、超时控制和自适应并发控制功能
 
+mod concurrency;
 mod failover;
 mod retry;
 mod timeout;
 
+pub use concurrency::{
+    AdaptiveConcurrency, AdaptiveConcurrencyConfig, ConcurrencyPermit, ProviderConcurrency,
+    RateLimitHeaders,
+};
 pub use failover::{
     Failover, FailoverConfig, FailoverManager, FailoverResult, FailureType, SwitchEvent,
     QUOTA_EXCEEDED_KEYWORDS, QUOTA_EXCEEDED_STATUS_CODES,
 };
-pub use retry::{Retrier, RetryConfig, RetryError};
+pub use retry::{is_overloaded_status, Retrier, RetryConfig, RetryError};
 pub use timeout::{
     CancellationToken, StreamIdleDetector, StreamWithIdleTimeout, TimeoutConfig, TimeoutController,
     TimeoutError,
@@ -1,13 +1,26 @@
 //! 重试机制实现
 //!
 //! 提供带指数退避和抖动的重试逻辑
+//!
+//! 上游过载（503、Anthropic 的 529）时立即重试只会加重过载，这类错误使用更长的退避参数；
+//! 响应带 `Retry-After` 时优先按其等待。
 
 use serde::{Deserialize, Serialize};
 use std::future::Future;
 use std::time::Duration;
 
 /// 可重试的 HTTP 状态码
-pub const RETRYABLE_STATUS_CODES: &[u16] = &[408, 429, 500, 502, 503, 504];
+pub const RETRYABLE_STATUS_CODES: &[u16] = &[408, 429, 500, 502, 503, 504, 529];
+
+/// 表示上游过载的 HTTP 状态码
+pub const OVERLOADED_STATUS_CODES: &[u16] = &[503, 529];
+
+/// 检查状态码是否表示上游过载
+///
+/// 错误分类（`FlowErrorType`）、重试退避和故障转移（`FailureType`）共用此判断
+pub fn is_overloaded_status(status_code: u16) -> bool {
+    OVERLOADED_STATUS_CODES.contains(&status_code)
+}
 
 /// 重试配置
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -21,12 +34,26 @@ pub struct RetryConfig {
     /// 可重试的状态码
     #[serde(default = "default_retryable_codes")]
     pub retryable_codes: Vec<u16>,
+    /// 上游过载时的基础延迟（毫秒）
+    #[serde(default = "default_overload_base_delay_ms")]
+    pub overload_base_delay_ms: u64,
+    /// 上游过载时的最大延迟（毫秒，同时限制 `Retry-After` 的等待时间）
+    #[serde(default = "default_overload_max_delay_ms")]
+    pub overload_max_delay_ms: u64,
 }
 
 fn default_retryable_codes() -> Vec<u16> {
     RETRYABLE_STATUS_CODES.to_vec()
 }
 
+fn default_overload_base_delay_ms() -> u64 {
+    5000
+}
+
+fn default_overload_max_delay_ms() -> u64 {
+    60000
+}
+
 impl Default for RetryConfig {
     fn default() -> Self {
         Self {
@@ -34,6 +61,8 @@ impl Default for RetryConfig {
             base_delay_ms: 1000,
             max_delay_ms: 30000,
             retryable_codes: default_retryable_codes(),
+            overload_base_delay_ms: default_overload_base_delay_ms(),
+            overload_max_delay_ms: default_overload_max_delay_ms(),
         }
     }
 }
@@ -46,6 +75,8 @@ impl RetryConfig {
             base_delay_ms,
             max_delay_ms,
             retryable_codes: default_retryable_codes(),
+            overload_base_delay_ms: default_overload_base_delay_ms(),
+            overload_max_delay_ms: default_overload_max_delay_ms(),
         }
     }
 
@@ -112,19 +143,48 @@ impl Retrier {
     ///
     /// jitter_factor 应在 [0.0, 1.0) 范围内
     pub fn backoff_delay_with_jitter(&self, attempt: u32, jitter_factor: f64) -> Duration {
-        let base = self.config.base_delay_ms as f64;
-        let max = self.config.max_delay_ms as f64;
-
-        // 指数退避: base * 2^attempt
-        let exponential = base * 2_f64.powi(attempt as i32);
-
-        // 抖动: [0, base) 范围内的随机值
-        let jitter = base * jitter_factor.clamp(0.0, 1.0);
+        exponential_backoff(
+            self.config.base_delay_ms,
+            self.config.max_delay_ms,
+            attempt,
+            jitter_factor,
+        )
+    }
 
-        // 总延迟，不超过最大值
-        let delay = (exponential + jitter).min(max);
+    /// 按失败原因计算第 N 次重试的退避时间
+    ///
+    /// - 有 `Retry-After` 时按其等待（不超过 `overload_max_delay_ms`）
+    /// - 上游过载（503 / 529）时使用 `overload_base_delay_ms` / `overload_max_delay_ms`
+    /// - 其他错误同 [`Retrier::backoff_delay`]
+    pub fn backoff_delay_for(
+        &self,
+        attempt: u32,
+        status_code: Option<u16>,
+        retry_after: Option<Duration>,
+    ) -> Duration {
+        self.backoff_delay_for_with_jitter(attempt, status_code, retry_after, rand_jitter_factor())
+    }
 
-        Duration::from_millis(delay as u64)
+    /// 按失败原因计算退避时间（可指定抖动因子，用于测试）
+    pub fn backoff_delay_for_with_jitter(
+        &self,
+        attempt: u32,
+        status_code: Option<u16>,
+        retry_after: Option<Duration>,
+        jitter_factor: f64,
+    ) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(Duration::from_millis(self.config.overload_max_delay_ms));
+        }
+        if status_code.is_some_and(is_overloaded_status) {
+            return exponential_backoff(
+                self.config.overload_base_delay_ms,
+                self.config.overload_max_delay_ms,
+                attempt,
+                jitter_factor,
+            );
+        }
+        self.backoff_delay_with_jitter(attempt, jitter_factor)
     }
 
     /// 带重试执行异步操作
@@ -166,8 +226,8 @@ impl Retrier {
                         });
                     }
 
-                    // 等待退避时间
-                    let delay = self.backoff_delay(attempts - 1);
+                    // 等待退避时间（上游过载时更长）
+                    let delay = self.backoff_delay_for(attempts - 1, status_code, None);
                     tokio::time::sleep(delay).await;
                 }
             }
@@ -182,6 +242,28 @@ impl Retrier {
     }
 }
 
+/// 指数退避: min(base * 2^attempt + jitter, max)，jitter 在 [0, base) 范围内
+fn exponential_backoff(
+    base_delay_ms: u64,
+    max_delay_ms: u64,
+    attempt: u32,
+    jitter_factor: f64,
+) -> Duration {
+    let base = base_delay_ms as f64;
+    let max = max_delay_ms as f64;
+
+    // 指数退避: base * 2^attempt
+    let exponential = base * 2_f64.powi(attempt as i32);
+
+    // 抖动: [0, base) 范围内的随机值
+    let jitter = base * jitter_factor.clamp(0.0, 1.0);
+
+    // 总延迟，不超过最大值
+    let delay = (exponential + jitter).min(max);
+
+    Duration::from_millis(delay as u64)
+}
+
 /// 生成 [0.0, 1.0) 范围内的随机抖动因子
 fn rand_jitter_factor() -> f64 {
     use std::collections::hash_map::RandomState;
@@ -307,6 +389,37 @@ mod unit_tests {
         assert_eq!(sequence[2], Duration::from_millis(4000));
     }
 
+    #[test]
+    fn test_overloaded_backoff_is_longer() {
+        let retrier = Retrier::with_defaults();
+        assert!(retrier.config().is_retryable(529));
+
+        for attempt in 0..3 {
+            let server_error = retrier.backoff_delay_for_with_jitter(attempt, Some(500), None, 0.0);
+            let overloaded = retrier.backoff_delay_for_with_jitter(attempt, Some(529), None, 0.0);
+            assert!(overloaded > server_error, "attempt {}", attempt);
+        }
+        assert_eq!(
+            retrier.backoff_delay_for_with_jitter(0, Some(529), None, 0.0),
+            Duration::from_millis(5000)
+        );
+
+        // Retry-After 优先，但不超过过载最大延迟
+        assert_eq!(
+            retrier.backoff_delay_for_with_jitter(0, Some(529), Some(Duration::from_secs(2)), 0.5),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            retrier.backoff_delay_for_with_jitter(
+                0,
+                Some(503),
+                Some(Duration::from_secs(600)),
+                0.0
+            ),
+            Duration::from_millis(60000)
+        );
+    }
+
     #[tokio::test]
     async fn test_execute_success_first_try() {
         let retrier = Retrier::with_defaults();
@@ -2,7 +2,8 @@
 //!
 //! 使用 proptest 进行属性测试
 
-use crate::resilience::{Retrier, RetryConfig};
+use crate::flow_monitor::FlowErrorType;
+use crate::resilience::{is_overloaded_status, FailureType, Retrier, RetryConfig};
 use proptest::prelude::*;
 use std::time::Duration;
 
@@ -16,7 +17,12 @@ fn arb_retry_config() -> impl Strategy<Value = RetryConfig> {
         .prop_map(|(max_retries, base_delay_ms, max_delay_ms)| {
             // 确保 max_delay >= base_delay
             let max_delay_ms = max_delay_ms.max(base_delay_ms);
-            RetryConfig::new(max_retries, base_delay_ms, max_delay_ms)
+            // 过载退避同样使用毫秒级延迟
+            RetryConfig {
+                overload_base_delay_ms: base_delay_ms * 2,
+                overload_max_delay_ms: max_delay_ms * 2,
+                ..RetryConfig::new(max_retries, base_delay_ms, max_delay_ms)
+            }
         })
 }
 
@@ -311,4 +317,15 @@ proptest! {
             );
         }
     }
+
+    /// *对于任意* 状态码，错误分类、重试退避和故障转移对上游过载的判断一致
+    #[test]
+    fn prop_overloaded_status_consistent(code in 100u16..600u16) {
+        let overloaded = is_overloaded_status(code);
+        prop_assert_eq!(
+            FlowErrorType::from_status_code(code) == FlowErrorType::Overloaded,
+            overloaded
+        );
+        prop_assert_eq!(FailureType::detect(Some(code), "").is_overloaded(), overloaded);
+    }
 }
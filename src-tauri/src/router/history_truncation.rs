@@ -0,0 +1,307 @@
+//! 按别名截断消息历史
+//!
+//! 部分客户端每轮都发送完整的对话历史，历史无限增长后会超出上下文窗口并推高成本。
+//! 按别名配置后，转发前只保留最近的 N 条消息（或估算 Token 不超过上限的最近消息），
+//! 客户端看到的仍是正常响应。
+//!
+//! - 系统提示词（`system` / `developer` 消息，Anthropic 的顶层 `system`）总是保留
+//! - 可选保留第一条用户消息（通常包含任务描述）
+//! - 不拆分工具调用和工具结果：保留部分开头的工具结果对应的调用已被截掉时，这些结果一并移除；
+//!   保留部分只剩工具结果时，改为向前多保留到对应的调用
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::model_downgrade::estimate_prompt_tokens;
+
+/// 单个别名的截断规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct HistoryTruncationRule {
+    /// 最多保留的最近消息数（不含系统提示词和保留的第一条用户消息）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_messages: Option<usize>,
+    /// 估算的提示词 Token 上限（含系统提示词、保留的第一条用户消息和工具定义）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// 是否保留第一条用户消息
+    #[serde(default)]
+    pub keep_first_user_message: bool,
+}
+
+/// 历史截断记录（记录到 `RoutingInfo`）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HistoryTruncation {
+    /// 截断前的消息数
+    pub original_messages: usize,
+    /// 移除的消息数
+    pub removed_messages: usize,
+    /// 截断后估算的提示词 Token 数（配置了 Token 上限时才有）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_tokens: Option<u32>,
+}
+
+impl HistoryTruncationRule {
+    /// 截断请求负载中的 `messages`
+    ///
+    /// # 返回
+    /// 发生的截断（未超出限制或未配置限制时为 `None`）
+    pub fn apply(&self, payload: &mut Value, model: Option<&str>) -> Option<HistoryTruncation> {
+        if self.max_messages.is_none() && self.max_tokens.is_none() {
+            return None;
+        }
+        let messages = payload.get("messages")?.as_array()?;
+        let original_messages = messages.len();
+
+        let first_user = if self.keep_first_user_message {
+            messages
+                .iter()
+                .position(|m| role(m) == "user" && !is_tool_result(m))
+        } else {
+            None
+        };
+        let pinned: Vec<bool> = messages
+            .iter()
+            .enumerate()
+            .map(|(i, m)| matches!(role(m), "system" | "developer") || Some(i) == first_user)
+            .collect();
+        let candidates: Vec<usize> = (0..original_messages).filter(|&i| !pinned[i]).collect();
+        if candidates.is_empty() {
+            return None;
+        }
+
+        // 保留 candidates[keep_from..]，至少保留最后一条消息
+        let mut keep_from = self
+            .max_messages
+            .map_or(0, |max| candidates.len().saturating_sub(max.max(1)));
+        if let Some(max_tokens) = self.max_tokens {
+            match message_costs(payload, messages, model) {
+                Some((base, costs)) => {
+                    let start = candidates[keep_from];
+                    let mut total = base
+                        + (0..original_messages)
+                            .filter(|&i| pinned[i] || i >= start)
+                            .map(|i| costs[i])
+                            .sum::<u32>();
+                    while total > max_tokens && keep_from + 1 < candidates.len() {
+                        total -= costs[candidates[keep_from]];
+                        keep_from += 1;
+                    }
+                }
+                None => tracing::warn!("[TRUNCATE] Token 估算器不可用，只按消息数截断"),
+            }
+        }
+
+        // 不拆分工具调用和结果
+        if keep_from > 0 {
+            match candidates[keep_from..]
+                .iter()
+                .position(|&i| !is_tool_result(&messages[i]))
+            {
+                Some(orphans) => keep_from += orphans,
+                None => {
+                    while keep_from > 0 && is_tool_result(&messages[candidates[keep_from]]) {
+                        keep_from -= 1;
+                    }
+                }
+            }
+        }
+        if keep_from == 0 {
+            return None;
+        }
+
+        // candidates 按顺序排列，保留部分即下标不小于起点的消息
+        let start = candidates[keep_from];
+        let kept: Vec<Value> = messages
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| pinned[*i] || *i >= start)
+            .map(|(_, m)| m.clone())
+            .collect();
+        let removed_messages = original_messages - kept.len();
+        payload["messages"] = Value::Array(kept);
+
+        Some(HistoryTruncation {
+            original_messages,
+            removed_messages,
+            estimated_tokens: self
+                .max_tokens
+                .and_then(|_| estimate_prompt_tokens(payload, model)),
+        })
+    }
+}
+
+/// 按别名的截断规则集合
+#[derive(Debug, Clone, Default)]
+pub struct HistoryTruncations {
+    /// 别名 -> 截断规则
+    by_alias: HashMap<String, HistoryTruncationRule>,
+}
+
+impl HistoryTruncations {
+    /// 创建空的规则集合
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从配置映射创建
+    pub fn from_map(by_alias: HashMap<String, HistoryTruncationRule>) -> Self {
+        Self { by_alias }
+    }
+
+    /// 设置别名的截断规则
+    pub fn set(&mut self, alias: &str, rule: HistoryTruncationRule) {
+        self.by_alias.insert(alias.to_string(), rule);
+    }
+
+    /// 获取别名的截断规则
+    pub fn get(&self, alias: &str) -> Option<&HistoryTruncationRule> {
+        self.by_alias.get(alias)
+    }
+
+    /// 是否没有任何规则
+    pub fn is_empty(&self) -> bool {
+        self.by_alias.is_empty()
+    }
+
+    /// 按客户端请求的别名截断消息历史
+    pub fn apply(
+        &self,
+        alias: &str,
+        resolved_model: &str,
+        payload: &mut Value,
+    ) -> Option<HistoryTruncation> {
+        self.by_alias
+            .get(alias)?
+            .apply(payload, Some(resolved_model))
+    }
+}
+
+fn role(message: &Value) -> &str {
+    message.get("role").and_then(Value::as_str).unwrap_or("")
+}
+
+/// 是否为工具结果消息（OpenAI `role: tool` / `function`，Anthropic 含 `tool_result` 块的消息）
+fn is_tool_result(message: &Value) -> bool {
+    matches!(role(message), "tool" | "function")
+        || message
+            .get("content")
+            .and_then(Value::as_array)
+            .is_some_and(|blocks| {
+                blocks
+                    .iter()
+                    .any(|b| b.get("type").and_then(Value::as_str) == Some("tool_result"))
+            })
+}
+
+/// 估算固定部分（顶层系统提示词和工具定义）和每条消息的 Token 数
+fn message_costs(
+    payload: &Value,
+    messages: &[Value],
+    model: Option<&str>,
+) -> Option<(u32, Vec<u32>)> {
+    let mut fixed = json!({});
+    for key in ["system", "tools"] {
+        if let Some(value) = payload.get(key) {
+            fixed[key] = value.clone();
+        }
+    }
+    let base = estimate_prompt_tokens(&fixed, model)?;
+    let costs = messages
+        .iter()
+        .map(|m| estimate_prompt_tokens(&json!({ "messages": [m] }), model))
+        .collect::<Option<Vec<_>>>()?;
+    Some((base, costs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(max_messages: usize, keep_first_user_message: bool) -> HistoryTruncationRule {
+        HistoryTruncationRule {
+            max_messages: Some(max_messages),
+            keep_first_user_message,
+            ..Default::default()
+        }
+    }
+
+    fn roles(payload: &Value) -> Vec<String> {
+        payload["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| role(m).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_keeps_system_and_first_user() {
+        let mut payload = json!({"messages": [
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": "task"},
+            {"role": "assistant", "content": "a1"},
+            {"role": "user", "content": "u2"},
+            {"role": "assistant", "content": "a2"},
+            {"role": "user", "content": "u3"}
+        ]});
+
+        let truncation = rule(2, true).apply(&mut payload, None).unwrap();
+        assert_eq!(truncation.original_messages, 6);
+        assert_eq!(truncation.removed_messages, 2);
+        assert_eq!(payload["messages"][1]["content"], "task");
+        assert_eq!(roles(&payload), ["system", "user", "assistant", "user"]);
+
+        // 未超出限制时不截断
+        assert!(rule(10, true).apply(&mut payload, None).is_none());
+    }
+
+    #[test]
+    fn test_does_not_orphan_tool_results() {
+        // OpenAI：截断点落在工具结果上时，结果随调用一起移除
+        let mut payload = json!({"messages": [
+            {"role": "user", "content": "u1"},
+            {"role": "assistant", "content": null, "tool_calls": [{"id": "c1", "type": "function", "function": {"name": "f", "arguments": "{}"}}]},
+            {"role": "tool", "tool_call_id": "c1", "content": "r1"},
+            {"role": "assistant", "content": "done"},
+            {"role": "user", "content": "u2"}
+        ]});
+        let truncation = rule(3, false).apply(&mut payload, None).unwrap();
+        assert_eq!(truncation.removed_messages, 3);
+        assert_eq!(roles(&payload), ["assistant", "user"]);
+
+        // Anthropic：只剩工具结果时向前保留到对应的调用
+        let mut payload = json!({"messages": [
+            {"role": "user", "content": "u1"},
+            {"role": "assistant", "content": [{"type": "tool_use", "id": "t1", "name": "f", "input": {}}]},
+            {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "t1", "content": "r1"}]}
+        ]});
+        let truncation = rule(1, false).apply(&mut payload, None).unwrap();
+        assert_eq!(truncation.removed_messages, 1);
+        assert_eq!(payload["messages"][0]["content"][0]["type"], "tool_use");
+        assert_eq!(payload["messages"][1]["content"][0]["type"], "tool_result");
+    }
+
+    #[test]
+    fn test_token_limit() {
+        let long = "lorem ipsum dolor sit amet ".repeat(40);
+        let mut payload = json!({
+            "system": "Be brief.",
+            "messages": [
+                {"role": "user", "content": long},
+                {"role": "assistant", "content": long},
+                {"role": "user", "content": "short question"}
+            ]
+        });
+        let rule = HistoryTruncationRule {
+            max_tokens: Some(100),
+            ..Default::default()
+        };
+
+        let truncation = rule.apply(&mut payload, None).unwrap();
+        assert_eq!(truncation.removed_messages, 2);
+        assert!(truncation.estimated_tokens.unwrap() <= 100);
+        assert_eq!(payload["messages"][0]["content"], "short question");
+        assert_eq!(payload["system"], "Be brief.");
+    }
+}
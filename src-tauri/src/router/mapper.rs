@@ -2,8 +2,13 @@
 //!
 //! 提供模型别名映射和解析功能
 
+use super::history_truncation::{HistoryTruncation, HistoryTruncations};
+use super::model_defaults::ModelDefaults;
+use super::model_downgrade::{ModelDowngrade, SizeDowngrades};
+use super::param_constraints::{ParamAdjustment, ParamConstraints};
+use super::reasoning_effort::ReasoningEffortMappings;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// 模型信息
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -21,6 +26,16 @@ pub struct ModelInfo {
 pub struct ModelMapper {
     /// 别名到实际模型的映射 (alias -> actual)
     aliases: HashMap<String, String>,
+    /// 按别名的参数约束
+    param_constraints: ParamConstraints,
+    /// 按别名的请求大小降级规则
+    size_downgrades: SizeDowngrades,
+    /// 按别名的消息历史截断规则
+    history_truncations: HistoryTruncations,
+    /// 按模型的默认参数
+    model_defaults: ModelDefaults,
+    /// 按 Provider 的推理强度映射
+    reasoning_effort: ReasoningEffortMappings,
 }
 
 impl ModelMapper {
@@ -28,12 +43,24 @@ impl ModelMapper {
     pub fn new() -> Self {
         Self {
             aliases: HashMap::new(),
+            param_constraints: ParamConstraints::new(),
+            size_downgrades: SizeDowngrades::new(),
+            history_truncations: HistoryTruncations::new(),
+            model_defaults: ModelDefaults::new(),
+            reasoning_effort: ReasoningEffortMappings::new(),
         }
     }
 
     /// 从别名映射创建模型映射器
     pub fn from_aliases(aliases: HashMap<String, String>) -> Self {
-        Self { aliases }
+        Self {
+            aliases,
+            param_constraints: ParamConstraints::new(),
+            size_downgrades: SizeDowngrades::new(),
+            history_truncations: HistoryTruncations::new(),
+            model_defaults: ModelDefaults::new(),
+            reasoning_effort: ReasoningEffortMappings::new(),
+        }
     }
 
     /// 解析模型名（别名 -> 实际名）
@@ -112,6 +139,114 @@ impl ModelMapper {
     pub fn clear(&mut self) {
         self.aliases.clear();
     }
+
+    /// 替换参数约束
+    pub fn set_param_constraints(&mut self, constraints: ParamConstraints) {
+        self.param_constraints = constraints;
+    }
+
+    /// 获取参数约束
+    pub fn param_constraints(&self) -> &ParamConstraints {
+        &self.param_constraints
+    }
+
+    /// 对请求负载应用别名参数约束
+    ///
+    /// 约束按客户端请求的别名（解析前的模型名）查找
+    pub fn apply_param_constraints(
+        &self,
+        alias: &str,
+        payload: &mut serde_json::Value,
+    ) -> Vec<ParamAdjustment> {
+        self.param_constraints.apply(alias, payload)
+    }
+
+    /// 替换请求大小降级规则
+    pub fn set_size_downgrades(&mut self, downgrades: SizeDowngrades) {
+        self.size_downgrades = downgrades;
+    }
+
+    /// 获取请求大小降级规则
+    pub fn size_downgrades(&self) -> &SizeDowngrades {
+        &self.size_downgrades
+    }
+
+    /// 判断请求是否因提示词过大需要降级模型
+    ///
+    /// 规则按客户端请求的别名查找，`resolved_model` 为别名解析后的模型
+    pub fn size_downgrade(
+        &self,
+        alias: &str,
+        resolved_model: &str,
+        payload: &serde_json::Value,
+    ) -> Option<ModelDowngrade> {
+        self.size_downgrades
+            .evaluate(alias, resolved_model, payload)
+    }
+
+    /// 替换消息历史截断规则
+    pub fn set_history_truncations(&mut self, truncations: HistoryTruncations) {
+        self.history_truncations = truncations;
+    }
+
+    /// 获取消息历史截断规则
+    pub fn history_truncations(&self) -> &HistoryTruncations {
+        &self.history_truncations
+    }
+
+    /// 按别名规则截断请求的消息历史
+    ///
+    /// 规则按客户端请求的别名查找，`resolved_model` 用于估算 Token
+    pub fn truncate_history(
+        &self,
+        alias: &str,
+        resolved_model: &str,
+        payload: &mut serde_json::Value,
+    ) -> Option<HistoryTruncation> {
+        self.history_truncations
+            .apply(alias, resolved_model, payload)
+    }
+
+    /// 替换按模型的默认参数
+    pub fn set_model_defaults(&mut self, defaults: ModelDefaults) {
+        self.model_defaults = defaults;
+    }
+
+    /// 获取按模型的默认参数
+    pub fn model_defaults(&self) -> &ModelDefaults {
+        &self.model_defaults
+    }
+
+    /// 为客户端未发送的参数填充模型默认值
+    ///
+    /// 默认值按别名解析后的模型查找
+    pub fn apply_model_defaults(
+        &self,
+        model: &str,
+        payload: &mut serde_json::Value,
+        client_fields: &HashSet<String>,
+    ) -> Vec<ParamAdjustment> {
+        self.model_defaults.apply(model, payload, client_fields)
+    }
+
+    /// 替换按 Provider 的推理强度映射
+    pub fn set_reasoning_effort(&mut self, mappings: ReasoningEffortMappings) {
+        self.reasoning_effort = mappings;
+    }
+
+    /// 获取按 Provider 的推理强度映射
+    pub fn reasoning_effort(&self) -> &ReasoningEffortMappings {
+        &self.reasoning_effort
+    }
+
+    /// 将请求中的推理设置转换为目标 Provider 的原生参数
+    pub fn apply_reasoning_effort(
+        &self,
+        provider: crate::ProviderType,
+        payload: &mut serde_json::Value,
+    ) -> Vec<ParamAdjustment> {
+        self.reasoning_effort.apply(provider, payload)
+    }
 }
 
 #[cfg(test)]
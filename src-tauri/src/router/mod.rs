@@ -10,22 +10,53 @@
 //!
 //! 模型映射：
 //! - 支持模型别名映射（如 `gpt-4` -> `claude-sonnet-4-5-20250514`）
+//! - 支持按别名钳制/强制请求参数（如 `temperature <= 1.0`）
+//! - 支持按别名在提示词过大时降级到替代模型
+//! - 支持按别名截断过长的消息历史（不拆分工具调用和结果）
+//! - 支持按模型为客户端未发送的参数设置默认值
+//! - 支持将推理强度转换为目标 Provider 的原生参数（`reasoning_effort` / `thinking`）
 //!
 //! 路由规则：
 //! - 支持通配符模式匹配（前缀、后缀、包含）
 //! - 支持规则优先级排序
+//! - 记录别名映射、降级到最终命中规则的完整解析轨迹
+//!
+//! 会话亲和：
+//! - 按会话键将多轮对话固定到同一凭证，提高提示词缓存命中率
 
 mod amp_router;
+mod history_truncation;
 mod mapper;
+mod model_defaults;
+mod model_downgrade;
+mod param_constraints;
 mod provider_router;
+mod reasoning_effort;
 mod route_registry;
+mod routing_trace;
 mod rules;
+mod session_affinity;
 
 pub use amp_router::{AmpRouteMatch, AmpRouter};
+pub use history_truncation::{HistoryTruncation, HistoryTruncationRule, HistoryTruncations};
 pub use mapper::{ModelInfo, ModelMapper};
+pub use model_defaults::ModelDefaults;
+pub use model_downgrade::{
+    estimate_prompt_tokens, ModelDowngrade, SizeDowngradeRule, SizeDowngrades,
+};
+pub use param_constraints::{
+    ParamAdjustment, ParamAdjustmentAction, ParamConstraint, ParamConstraints,
+};
 pub use provider_router::ProviderRouter;
+pub use reasoning_effort::{
+    ReasoningEffort, ReasoningEffortMapping, ReasoningEffortMappings, ReasoningFormat,
+};
 pub use route_registry::{RegisteredRoute, RouteRegistry, RouteType};
+pub use routing_trace::{MatchedRoutingRule, ResolutionStep, ResolutionStepKind, RoutingTrace};
 pub use rules::{RouteResult, Router, RoutingRule};
+pub use session_affinity::{
+    conversation_key, AffinityOutcome, SessionAffinity, SessionAffinityConfig, SessionKeySource,
+};
 
 #[cfg(test)]
 mod tests;
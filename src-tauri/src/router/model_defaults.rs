@@ -0,0 +1,156 @@
+//! 按模型的默认参数
+//!
+//! 客户端未发送某个参数时按模型设置默认值，例如对 `claude-*` 缺省 `max_tokens: 4096`。
+//!
+//! 与参数注入不同，默认值只填补缺失的参数：客户端显式发送的值（包括 `0` 和 `null`）
+//! 都视为有意设置，不会被替换。默认值按别名解析后的模型匹配，精确模式优先，
+//! 通配符模式按长度从长到短（越具体越优先）。
+
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+use super::param_constraints::{ParamAdjustment, ParamAdjustmentAction};
+use super::rules::Router;
+
+/// 按模型的默认参数集合
+#[derive(Debug, Clone, Default)]
+pub struct ModelDefaults {
+    /// (模型模式, 参数名 -> 默认值)，已按匹配优先级排序
+    rules: Vec<(String, HashMap<String, Value>)>,
+}
+
+impl ModelDefaults {
+    /// 创建空的默认参数集合
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从配置创建默认参数集合（模型模式 -> 参数名 -> 默认值）
+    pub fn from_map(by_pattern: HashMap<String, HashMap<String, Value>>) -> Self {
+        let mut rules: Vec<_> = by_pattern.into_iter().collect();
+        rules.sort_by(|(a, _), (b, _)| {
+            a.contains('*')
+                .cmp(&b.contains('*'))
+                .then(b.len().cmp(&a.len()))
+                .then(a.cmp(b))
+        });
+        Self { rules }
+    }
+
+    /// 检查是否为空
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 对请求负载应用默认参数
+    ///
+    /// # Arguments
+    /// * `model` - 别名解析后的模型
+    /// * `payload` - 请求负载
+    /// * `client_fields` - 客户端原始请求中出现的顶层字段（含显式 `null`），
+    ///   类型化请求序列化后丢失了显式 `null`，需要由调用方提供
+    ///
+    /// # 返回
+    /// 填充的默认参数（按参数名排序）
+    pub fn apply(
+        &self,
+        model: &str,
+        payload: &mut Value,
+        client_fields: &HashSet<String>,
+    ) -> Vec<ParamAdjustment> {
+        let Some(obj) = payload.as_object_mut() else {
+            return Vec::new();
+        };
+
+        let mut adjustments = Vec::new();
+        for (pattern, defaults) in &self.rules {
+            if !Router::pattern_matches(pattern, model) {
+                continue;
+            }
+            let mut params: Vec<&String> = defaults.keys().collect();
+            params.sort();
+            for param in params {
+                if obj.contains_key(param) || client_fields.contains(param) {
+                    continue;
+                }
+                let applied = defaults[param].clone();
+                obj.insert(param.clone(), applied.clone());
+                adjustments.push(ParamAdjustment {
+                    param: param.clone(),
+                    original: None,
+                    applied,
+                    action: ParamAdjustmentAction::Defaulted,
+                });
+            }
+        }
+
+        adjustments.sort_by(|a, b| a.param.cmp(&b.param));
+        adjustments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn claude_defaults() -> ModelDefaults {
+        ModelDefaults::from_map(HashMap::from([
+            (
+                "claude-*".to_string(),
+                HashMap::from([
+                    ("max_tokens".to_string(), json!(4096)),
+                    ("temperature".to_string(), json!(0.5)),
+                ]),
+            ),
+            (
+                "claude-opus-4".to_string(),
+                HashMap::from([("max_tokens".to_string(), json!(8192))]),
+            ),
+        ]))
+    }
+
+    #[test]
+    fn test_defaults_fill_missing_params() {
+        let defaults = claude_defaults();
+        let mut payload = json!({"model": "claude-sonnet-4-5", "temperature": 1.0});
+        let adjustments = defaults.apply("claude-sonnet-4-5", &mut payload, &HashSet::new());
+
+        assert_eq!(payload["max_tokens"], 4096);
+        assert_eq!(payload["temperature"], 1.0);
+        assert_eq!(adjustments.len(), 1);
+        assert_eq!(adjustments[0].action, ParamAdjustmentAction::Defaulted);
+        assert_eq!(adjustments[0].original, None);
+
+        // 精确模式优先于通配符
+        let mut payload = json!({"model": "claude-opus-4"});
+        defaults.apply("claude-opus-4", &mut payload, &HashSet::new());
+        assert_eq!(payload["max_tokens"], 8192);
+        assert_eq!(payload["temperature"], 0.5);
+
+        let mut payload = json!({"model": "gpt-4o"});
+        assert!(defaults
+            .apply("gpt-4o", &mut payload, &HashSet::new())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_explicit_zero_and_null_are_respected() {
+        let defaults = claude_defaults();
+
+        let mut payload =
+            json!({"model": "claude-sonnet-4-5", "max_tokens": 0, "temperature": null});
+        let adjustments = defaults.apply("claude-sonnet-4-5", &mut payload, &HashSet::new());
+        assert!(adjustments.is_empty());
+        assert_eq!(payload["max_tokens"], 0);
+        assert!(payload["temperature"].is_null());
+
+        // 类型化请求序列化后丢失了显式 null，由客户端字段判断
+        let mut payload = json!({"model": "claude-sonnet-4-5"});
+        let client_fields = HashSet::from(["max_tokens".to_string()]);
+        let adjustments = defaults.apply("claude-sonnet-4-5", &mut payload, &client_fields);
+        assert_eq!(adjustments.len(), 1);
+        assert_eq!(adjustments[0].param, "temperature");
+        assert!(payload.get("max_tokens").is_none());
+    }
+}
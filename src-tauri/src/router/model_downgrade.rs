@@ -0,0 +1,228 @@
+//! 按请求大小降级模型
+//!
+//! 按别名配置提示词 Token 阈值：估算的提示词 Token 数超过阈值时，
+//! 将解析后的模型改写为替代模型（例如超长上下文改用更便宜或窗口更大的模型）。
+//!
+//! 估算使用 tiktoken（[`TokenEstimator`]），只有请求的别名配置了规则时才会计算，
+//! 估算器在首次使用时初始化。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::telemetry::{ChatMessage, TokenEstimator};
+
+/// 单个别名的降级规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SizeDowngradeRule {
+    /// 提示词 Token 阈值（估算值超过该值时降级）
+    pub threshold_tokens: u32,
+    /// 降级后使用的模型
+    pub target_model: String,
+}
+
+impl SizeDowngradeRule {
+    /// 创建降级规则
+    pub fn new(threshold_tokens: u32, target_model: impl Into<String>) -> Self {
+        Self {
+            threshold_tokens,
+            target_model: target_model.into(),
+        }
+    }
+}
+
+/// 模型降级记录（记录到 `RoutingInfo`）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModelDowngrade {
+    /// 降级前的模型（别名解析后）
+    pub from: String,
+    /// 降级后的模型
+    pub to: String,
+    /// 估算的提示词 Token 数
+    pub estimated_tokens: u32,
+    /// 触发降级的阈值
+    pub threshold_tokens: u32,
+}
+
+/// 按别名的降级规则集合
+#[derive(Debug, Clone, Default)]
+pub struct SizeDowngrades {
+    /// 别名 -> 降级规则
+    by_alias: HashMap<String, SizeDowngradeRule>,
+}
+
+impl SizeDowngrades {
+    /// 创建空的规则集合
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从配置映射创建
+    pub fn from_map(by_alias: HashMap<String, SizeDowngradeRule>) -> Self {
+        Self { by_alias }
+    }
+
+    /// 设置别名的降级规则
+    pub fn set(&mut self, alias: &str, rule: SizeDowngradeRule) {
+        self.by_alias.insert(alias.to_string(), rule);
+    }
+
+    /// 获取别名的降级规则
+    pub fn get(&self, alias: &str) -> Option<&SizeDowngradeRule> {
+        self.by_alias.get(alias)
+    }
+
+    /// 是否没有任何规则
+    pub fn is_empty(&self) -> bool {
+        self.by_alias.is_empty()
+    }
+
+    /// 判断请求是否需要降级
+    ///
+    /// 规则按客户端请求的别名查找；解析后的模型已经是目标模型时不降级。
+    pub fn evaluate(
+        &self,
+        alias: &str,
+        resolved_model: &str,
+        payload: &Value,
+    ) -> Option<ModelDowngrade> {
+        let rule = self.by_alias.get(alias)?;
+        if rule.target_model == resolved_model {
+            return None;
+        }
+        let estimated_tokens = estimate_prompt_tokens(payload, Some(resolved_model))?;
+        (estimated_tokens > rule.threshold_tokens).then(|| ModelDowngrade {
+            from: resolved_model.to_string(),
+            to: rule.target_model.clone(),
+            estimated_tokens,
+            threshold_tokens: rule.threshold_tokens,
+        })
+    }
+}
+
+/// 共享的 Token 估算器，初始化失败时为 `None`
+fn estimator() -> Option<&'static TokenEstimator> {
+    static ESTIMATOR: OnceLock<Option<TokenEstimator>> = OnceLock::new();
+    ESTIMATOR
+        .get_or_init(|| {
+            TokenEstimator::new()
+                .map_err(|e| tracing::warn!("[DOWNGRADE] {}，不进行模型降级", e))
+                .ok()
+        })
+        .as_ref()
+}
+
+/// 估算请求的提示词 Token 数
+///
+/// 同时支持 OpenAI（`role: system` 消息）和 Anthropic（顶层 `system` 字段）格式，
+/// 工具定义按 JSON 文本计入。估算器不可用时返回 `None`。
+pub fn estimate_prompt_tokens(payload: &Value, model: Option<&str>) -> Option<u32> {
+    let mut messages = Vec::new();
+    if let Some(system) = payload.get("system").filter(|s| !s.is_null()) {
+        messages.push(ChatMessage::new("system", content_text(system)));
+    }
+    for message in payload
+        .get("messages")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let role = message
+            .get("role")
+            .and_then(Value::as_str)
+            .unwrap_or("user");
+        let content = message.get("content").map(content_text).unwrap_or_default();
+        messages.push(ChatMessage::new(role, content));
+    }
+    if let Some(tools) = payload.get("tools").filter(|t| !t.is_null()) {
+        messages.push(ChatMessage::new("system", tools.to_string()));
+    }
+    Some(estimator()?.estimate_messages(&messages, model))
+}
+
+/// 提取消息内容中的文本
+///
+/// 文本块取 `text`，工具结果递归取 `content`，工具调用取 `input` 的 JSON，
+/// 图片等其他块忽略。
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                Value::String(text) => Some(text.clone()),
+                _ => part
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .or_else(|| part.get("content").map(content_text))
+                    .or_else(|| part.get("input").map(Value::to_string)),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rules() -> SizeDowngrades {
+        let mut rules = SizeDowngrades::new();
+        rules.set("smart", SizeDowngradeRule::new(50, "claude-haiku-4-5"));
+        rules
+    }
+
+    #[test]
+    fn test_small_request_not_downgraded() {
+        let payload = json!({"messages": [{"role": "user", "content": "hi"}]});
+        assert!(rules()
+            .evaluate("smart", "claude-sonnet-4-5", &payload)
+            .is_none());
+    }
+
+    #[test]
+    fn test_large_request_downgraded() {
+        let long = "lorem ipsum dolor sit amet ".repeat(40);
+        let payload = json!({
+            "system": [{"type": "text", "text": "Be brief."}],
+            "messages": [{"role": "user", "content": [{"type": "text", "text": long}]}]
+        });
+        let downgrade = rules()
+            .evaluate("smart", "claude-sonnet-4-5", &payload)
+            .unwrap();
+        assert_eq!(downgrade.from, "claude-sonnet-4-5");
+        assert_eq!(downgrade.to, "claude-haiku-4-5");
+        assert_eq!(downgrade.threshold_tokens, 50);
+        assert!(downgrade.estimated_tokens > 50);
+
+        // 未配置规则的别名、已是目标模型时不降级
+        assert!(rules()
+            .evaluate("other", "claude-sonnet-4-5", &payload)
+            .is_none());
+        assert!(rules()
+            .evaluate("smart", "claude-haiku-4-5", &payload)
+            .is_none());
+    }
+
+    #[test]
+    fn test_estimate_counts_tool_content() {
+        let bare = json!({"messages": [{"role": "user", "content": "hi"}]});
+        let with_tools = json!({
+            "messages": [
+                {"role": "user", "content": "hi"},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "t1", "content": "result text here"}
+                ]}
+            ],
+            "tools": [{"name": "search", "description": "Search the web"}]
+        });
+        let bare_tokens = estimate_prompt_tokens(&bare, None).unwrap();
+        let tool_tokens = estimate_prompt_tokens(&with_tools, None).unwrap();
+        assert!(tool_tokens > bare_tokens + 10);
+    }
+}
@@ -0,0 +1,259 @@
+//! 别名参数约束
+//!
+//! 按模型别名对请求参数进行钳制（min/max）或强制覆盖（force），
+//! 例如对某些别名强制 `temperature <= 1.0`、`max_tokens <= 4096`。
+//!
+//! 与参数注入不同，约束不会为缺失的参数设置默认值（`force` 除外），
+//! 超出范围的值会被钳制并记录，而不是拒绝请求。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 单个参数的约束
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ParamConstraint {
+    /// 最小值（低于时钳制到该值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// 最大值（高于时钳制到该值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// 强制值（无论客户端是否发送都覆盖为该值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub force: Option<serde_json::Value>,
+}
+
+impl ParamConstraint {
+    /// 创建钳制约束
+    pub fn clamp(min: Option<f64>, max: Option<f64>) -> Self {
+        Self {
+            min,
+            max,
+            force: None,
+        }
+    }
+
+    /// 创建强制约束
+    pub fn force(value: serde_json::Value) -> Self {
+        Self {
+            min: None,
+            max: None,
+            force: Some(value),
+        }
+    }
+}
+
+/// 参数调整动作
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamAdjustmentAction {
+    /// 低于最小值，钳制到最小值
+    ClampedMin,
+    /// 高于最大值，钳制到最大值
+    ClampedMax,
+    /// 强制覆盖
+    Forced,
+    /// 客户端未发送，使用模型默认值（见 `ModelDefaults`）
+    Defaulted,
+    /// 推理设置转换为目标 Provider 的原生参数（见 `ReasoningEffortMappings`）
+    Translated,
+}
+
+/// 参数调整记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ParamAdjustment {
+    /// 参数名
+    pub param: String,
+    /// 客户端发送的原始值（未发送时为空）
+    pub original: Option<serde_json::Value>,
+    /// 调整后的值
+    pub applied: serde_json::Value,
+    /// 调整动作
+    pub action: ParamAdjustmentAction,
+}
+
+/// 别名参数约束集合
+#[derive(Debug, Clone, Default)]
+pub struct ParamConstraints {
+    /// 别名 -> (参数名 -> 约束)
+    by_alias: HashMap<String, HashMap<String, ParamConstraint>>,
+}
+
+impl ParamConstraints {
+    /// 创建空的约束集合
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从配置创建约束集合
+    pub fn from_map(by_alias: HashMap<String, HashMap<String, ParamConstraint>>) -> Self {
+        Self { by_alias }
+    }
+
+    /// 设置某个别名的参数约束
+    pub fn set(&mut self, alias: &str, param: &str, constraint: ParamConstraint) {
+        self.by_alias
+            .entry(alias.to_string())
+            .or_default()
+            .insert(param.to_string(), constraint);
+    }
+
+    /// 获取某个别名的参数约束
+    pub fn get(&self, alias: &str) -> Option<&HashMap<String, ParamConstraint>> {
+        self.by_alias.get(alias)
+    }
+
+    /// 检查是否为空
+    pub fn is_empty(&self) -> bool {
+        self.by_alias.is_empty()
+    }
+
+    /// 清空所有约束
+    pub fn clear(&mut self) {
+        self.by_alias.clear();
+    }
+
+    /// 对请求负载应用别名约束
+    ///
+    /// # 返回
+    /// 实际发生的参数调整（按参数名排序）
+    pub fn apply(&self, alias: &str, payload: &mut serde_json::Value) -> Vec<ParamAdjustment> {
+        let Some(constraints) = self.by_alias.get(alias) else {
+            return Vec::new();
+        };
+        let Some(obj) = payload.as_object_mut() else {
+            return Vec::new();
+        };
+
+        let mut params: Vec<&String> = constraints.keys().collect();
+        params.sort();
+
+        let mut adjustments = Vec::new();
+        for param in params {
+            let constraint = &constraints[param];
+            let original = obj.get(param).cloned();
+
+            let adjusted = if let Some(forced) = &constraint.force {
+                (original.as_ref() != Some(forced))
+                    .then(|| (forced.clone(), ParamAdjustmentAction::Forced))
+            } else {
+                original
+                    .as_ref()
+                    .and_then(|value| clamp_value(value, constraint))
+            };
+
+            if let Some((applied, action)) = adjusted {
+                obj.insert(param.clone(), applied.clone());
+                adjustments.push(ParamAdjustment {
+                    param: param.clone(),
+                    original,
+                    applied,
+                    action,
+                });
+            }
+        }
+
+        adjustments
+    }
+}
+
+/// 按 min/max 钳制数值，未超出范围时返回 None
+fn clamp_value(
+    value: &serde_json::Value,
+    constraint: &ParamConstraint,
+) -> Option<(serde_json::Value, ParamAdjustmentAction)> {
+    let number = value.as_f64()?;
+    let (bound, action) = match (constraint.min, constraint.max) {
+        (Some(min), _) if number < min => (min, ParamAdjustmentAction::ClampedMin),
+        (_, Some(max)) if number > max => (max, ParamAdjustmentAction::ClampedMax),
+        _ => return None,
+    };
+
+    // 整数参数（如 max_tokens）保持整数类型
+    let applied = if (value.is_i64() || value.is_u64()) && bound.fract() == 0.0 {
+        serde_json::json!(bound as i64)
+    } else {
+        serde_json::json!(bound)
+    };
+    Some((applied, action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn capped_constraints() -> ParamConstraints {
+        let mut constraints = ParamConstraints::new();
+        constraints.set(
+            "safe-gpt",
+            "temperature",
+            ParamConstraint::clamp(None, Some(1.0)),
+        );
+        constraints.set(
+            "safe-gpt",
+            "max_tokens",
+            ParamConstraint::clamp(None, Some(4096.0)),
+        );
+        constraints
+    }
+
+    #[test]
+    fn test_temperature_clamped_to_cap() {
+        let constraints = capped_constraints();
+        let mut payload = json!({"model": "safe-gpt", "temperature": 2.0, "max_tokens": 100});
+
+        let adjustments = constraints.apply("safe-gpt", &mut payload);
+
+        assert_eq!(payload["temperature"], json!(1.0));
+        assert_eq!(payload["max_tokens"], json!(100));
+        assert_eq!(adjustments.len(), 1);
+        assert_eq!(adjustments[0].param, "temperature");
+        assert_eq!(adjustments[0].original, Some(json!(2.0)));
+        assert_eq!(adjustments[0].action, ParamAdjustmentAction::ClampedMax);
+    }
+
+    #[test]
+    fn test_integer_params_stay_integer() {
+        let constraints = capped_constraints();
+        let mut payload = json!({"max_tokens": 10000});
+
+        constraints.apply("safe-gpt", &mut payload);
+        assert_eq!(payload["max_tokens"], json!(4096));
+        assert!(payload["max_tokens"].is_i64());
+    }
+
+    #[test]
+    fn test_min_and_force() {
+        let mut constraints = ParamConstraints::new();
+        constraints.set(
+            "alias",
+            "top_p",
+            ParamConstraint::clamp(Some(0.1), Some(0.9)),
+        );
+        constraints.set("alias", "stream", ParamConstraint::force(json!(false)));
+
+        let mut payload = json!({"top_p": 0.0});
+        let adjustments = constraints.apply("alias", &mut payload);
+
+        assert_eq!(payload["top_p"], json!(0.1));
+        assert_eq!(payload["stream"], json!(false));
+        assert_eq!(adjustments.len(), 2);
+        assert_eq!(adjustments[0].action, ParamAdjustmentAction::Forced);
+        assert_eq!(adjustments[0].original, None);
+        assert_eq!(adjustments[1].action, ParamAdjustmentAction::ClampedMin);
+    }
+
+    #[test]
+    fn test_other_aliases_and_missing_params_untouched() {
+        let constraints = capped_constraints();
+
+        let mut payload = json!({"temperature": 2.0});
+        assert!(constraints.apply("other-model", &mut payload).is_empty());
+        assert_eq!(payload["temperature"], json!(2.0));
+
+        let mut payload = json!({"model": "safe-gpt"});
+        assert!(constraints.apply("safe-gpt", &mut payload).is_empty());
+        assert!(payload.get("temperature").is_none());
+    }
+}
@@ -0,0 +1,330 @@
+//! 跨 Provider 的推理强度转换
+//!
+//! 不同 Provider 表达推理预算的方式不同：OpenAI 使用 `reasoning_effort`（low/medium/high），
+//! Anthropic 使用 `thinking.budget_tokens`。别名跨 Provider 路由时，客户端的推理设置
+//! 需要转换为目标 Provider 的原生参数。
+//!
+//! 客户端可以发送规范字段 `reasoning_effort`，也可以发送 Anthropic 的 `thinking`；
+//! 转换在选定 Provider 之后进行：先解析出规范级别，再按目标 Provider 的映射写入原生参数，
+//! 并移除目标 Provider 不认识的另一种字段。`thinking.budget_tokens` 按映射中的预算反推级别：
+//! 不低于 high 预算为 high，不低于 medium 预算为 medium，否则为 low。
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+use super::param_constraints::{ParamAdjustment, ParamAdjustmentAction};
+use crate::ProviderType;
+
+/// 规范的推理强度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    /// 规范名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
+    }
+
+    /// 解析规范名称（不区分大小写，`minimal` 视为 low）
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "minimal" | "low" => Some(ReasoningEffort::Low),
+            "medium" => Some(ReasoningEffort::Medium),
+            "high" => Some(ReasoningEffort::High),
+            _ => None,
+        }
+    }
+}
+
+/// Provider 原生的推理参数格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningFormat {
+    /// OpenAI 风格：`reasoning_effort: "low" | "medium" | "high"`
+    Effort,
+    /// Anthropic 风格：`thinking: {"type": "enabled", "budget_tokens": N}`
+    BudgetTokens,
+}
+
+/// 单个 Provider 的推理强度映射
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReasoningEffortMapping {
+    /// 原生参数格式
+    pub format: ReasoningFormat,
+    /// low 对应的思考 Token 预算
+    #[serde(default = "default_low_budget_tokens")]
+    pub low_budget_tokens: u32,
+    /// medium 对应的思考 Token 预算
+    #[serde(default = "default_medium_budget_tokens")]
+    pub medium_budget_tokens: u32,
+    /// high 对应的思考 Token 预算
+    #[serde(default = "default_high_budget_tokens")]
+    pub high_budget_tokens: u32,
+}
+
+fn default_low_budget_tokens() -> u32 {
+    1024
+}
+
+fn default_medium_budget_tokens() -> u32 {
+    8192
+}
+
+fn default_high_budget_tokens() -> u32 {
+    24576
+}
+
+impl ReasoningEffortMapping {
+    /// 使用默认预算创建映射
+    pub fn new(format: ReasoningFormat) -> Self {
+        Self {
+            format,
+            low_budget_tokens: default_low_budget_tokens(),
+            medium_budget_tokens: default_medium_budget_tokens(),
+            high_budget_tokens: default_high_budget_tokens(),
+        }
+    }
+
+    /// 级别对应的思考 Token 预算
+    pub fn budget_tokens(&self, effort: ReasoningEffort) -> u32 {
+        match effort {
+            ReasoningEffort::Low => self.low_budget_tokens,
+            ReasoningEffort::Medium => self.medium_budget_tokens,
+            ReasoningEffort::High => self.high_budget_tokens,
+        }
+    }
+
+    /// 按思考 Token 预算反推级别
+    pub fn effort_for_budget(&self, budget_tokens: u64) -> ReasoningEffort {
+        if budget_tokens >= u64::from(self.high_budget_tokens) {
+            ReasoningEffort::High
+        } else if budget_tokens >= u64::from(self.medium_budget_tokens) {
+            ReasoningEffort::Medium
+        } else {
+            ReasoningEffort::Low
+        }
+    }
+}
+
+/// 按 Provider 的推理强度映射集合
+///
+/// 内置 OpenAI/Codex 使用 `reasoning_effort`，Claude/Claude OAuth 使用 `thinking`；
+/// 配置中的同名 Provider 覆盖内置映射，未配置的其他 Provider 保持请求不变。
+#[derive(Debug, Clone)]
+pub struct ReasoningEffortMappings {
+    /// Provider 名称 -> 映射
+    by_provider: HashMap<String, ReasoningEffortMapping>,
+}
+
+impl Default for ReasoningEffortMappings {
+    fn default() -> Self {
+        Self::from_map(HashMap::new())
+    }
+}
+
+impl ReasoningEffortMappings {
+    /// 创建只包含内置映射的集合
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从配置创建映射集合（Provider 名称 -> 映射，覆盖内置映射）
+    pub fn from_map(overrides: HashMap<String, ReasoningEffortMapping>) -> Self {
+        let mut by_provider: HashMap<String, ReasoningEffortMapping> = [
+            (ProviderType::OpenAI, ReasoningFormat::Effort),
+            (ProviderType::Codex, ReasoningFormat::Effort),
+            (ProviderType::Claude, ReasoningFormat::BudgetTokens),
+            (ProviderType::ClaudeOAuth, ReasoningFormat::BudgetTokens),
+        ]
+        .into_iter()
+        .map(|(provider, format)| (provider.to_string(), ReasoningEffortMapping::new(format)))
+        .collect();
+        by_provider.extend(overrides);
+        Self { by_provider }
+    }
+
+    /// 获取某个 Provider 的映射
+    pub fn get(&self, provider: ProviderType) -> Option<&ReasoningEffortMapping> {
+        self.by_provider.get(&provider.to_string())
+    }
+
+    /// 将请求中的推理设置转换为目标 Provider 的原生参数
+    ///
+    /// # 返回
+    /// 转换记录（动作为 `Translated`，`original` 为客户端发送的推理参数）
+    pub fn apply(&self, provider: ProviderType, payload: &mut Value) -> Vec<ParamAdjustment> {
+        let Some(mapping) = self.get(provider) else {
+            return Vec::new();
+        };
+        let Some(obj) = payload.as_object_mut() else {
+            return Vec::new();
+        };
+
+        match mapping.format {
+            ReasoningFormat::Effort => to_effort(mapping, obj),
+            ReasoningFormat::BudgetTokens => to_budget_tokens(mapping, obj),
+        }
+    }
+}
+
+/// 转换为 `reasoning_effort`（移除 `thinking`）
+fn to_effort(
+    mapping: &ReasoningEffortMapping,
+    obj: &mut Map<String, Value>,
+) -> Vec<ParamAdjustment> {
+    let Some(thinking) = obj.remove("thinking") else {
+        return Vec::new();
+    };
+    // 已有规范字段时以规范字段为准
+    if obj.get("reasoning_effort").is_some_and(|v| !v.is_null()) {
+        return Vec::new();
+    }
+    let Some(budget_tokens) = enabled_budget_tokens(&thinking) else {
+        return Vec::new();
+    };
+
+    let applied = json!(mapping.effort_for_budget(budget_tokens).as_str());
+    obj.insert("reasoning_effort".to_string(), applied.clone());
+    vec![ParamAdjustment {
+        param: "reasoning_effort".to_string(),
+        original: Some(json!({ "thinking": thinking })),
+        applied,
+        action: ParamAdjustmentAction::Translated,
+    }]
+}
+
+/// 转换为 `thinking.budget_tokens`（移除 `reasoning_effort`）
+///
+/// Anthropic 要求 `max_tokens` 大于思考预算，不满足时把预算追加到 `max_tokens` 上。
+fn to_budget_tokens(
+    mapping: &ReasoningEffortMapping,
+    obj: &mut Map<String, Value>,
+) -> Vec<ParamAdjustment> {
+    let Some(original) = obj.remove("reasoning_effort") else {
+        return Vec::new();
+    };
+    // 已有原生字段时以原生字段为准
+    if obj.get("thinking").is_some_and(|v| !v.is_null()) {
+        return Vec::new();
+    }
+    let Some(effort) = original.as_str().and_then(ReasoningEffort::parse) else {
+        return Vec::new();
+    };
+
+    let budget_tokens = mapping.budget_tokens(effort);
+    let applied = json!({ "type": "enabled", "budget_tokens": budget_tokens });
+    obj.insert("thinking".to_string(), applied.clone());
+    let mut adjustments = vec![ParamAdjustment {
+        param: "thinking".to_string(),
+        original: Some(json!({ "reasoning_effort": original })),
+        applied,
+        action: ParamAdjustmentAction::Translated,
+    }];
+
+    let max_tokens = obj.get("max_tokens").and_then(Value::as_u64);
+    if let Some(max_tokens) = max_tokens.filter(|&m| m <= u64::from(budget_tokens)) {
+        let applied = json!(max_tokens + u64::from(budget_tokens));
+        obj.insert("max_tokens".to_string(), applied.clone());
+        adjustments.push(ParamAdjustment {
+            param: "max_tokens".to_string(),
+            original: Some(json!(max_tokens)),
+            applied,
+            action: ParamAdjustmentAction::Translated,
+        });
+    }
+    adjustments
+}
+
+/// 读取启用状态下的 `thinking.budget_tokens`
+fn enabled_budget_tokens(thinking: &Value) -> Option<u64> {
+    (thinking.get("type").and_then(Value::as_str) == Some("enabled"))
+        .then(|| thinking.get("budget_tokens").and_then(Value::as_u64))
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_effort_to_anthropic_thinking() {
+        let mappings = ReasoningEffortMappings::new();
+        let mut payload = json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 32000,
+            "reasoning_effort": "high"
+        });
+
+        let adjustments = mappings.apply(ProviderType::Claude, &mut payload);
+
+        assert!(payload.get("reasoning_effort").is_none());
+        assert_eq!(
+            payload["thinking"],
+            json!({"type": "enabled", "budget_tokens": 24576})
+        );
+        assert_eq!(payload["max_tokens"], json!(32000));
+        assert_eq!(adjustments.len(), 1);
+        assert_eq!(adjustments[0].param, "thinking");
+        assert_eq!(adjustments[0].action, ParamAdjustmentAction::Translated);
+        assert_eq!(
+            adjustments[0].original,
+            Some(json!({"reasoning_effort": "high"}))
+        );
+    }
+
+    #[test]
+    fn test_anthropic_thinking_to_openai_effort() {
+        let mappings = ReasoningEffortMappings::new();
+        let mut payload = json!({
+            "model": "gpt-5",
+            "thinking": {"type": "enabled", "budget_tokens": 10000}
+        });
+
+        let adjustments = mappings.apply(ProviderType::OpenAI, &mut payload);
+
+        assert!(payload.get("thinking").is_none());
+        assert_eq!(payload["reasoning_effort"], json!("medium"));
+        assert_eq!(adjustments.len(), 1);
+        assert_eq!(adjustments[0].param, "reasoning_effort");
+        assert_eq!(adjustments[0].applied, json!("medium"));
+
+        // 禁用的 thinking 只移除，不设置推理强度
+        let mut payload = json!({"thinking": {"type": "disabled"}});
+        assert!(mappings.apply(ProviderType::Codex, &mut payload).is_empty());
+        assert_eq!(payload, json!({}));
+    }
+
+    #[test]
+    fn test_configured_budgets_and_max_tokens() {
+        let mappings = ReasoningEffortMappings::from_map(HashMap::from([(
+            "claude".to_string(),
+            ReasoningEffortMapping {
+                low_budget_tokens: 2048,
+                ..ReasoningEffortMapping::new(ReasoningFormat::BudgetTokens)
+            },
+        )]));
+        let mut payload = json!({"max_tokens": 1024, "reasoning_effort": "LOW"});
+
+        let adjustments = mappings.apply(ProviderType::Claude, &mut payload);
+
+        assert_eq!(payload["thinking"]["budget_tokens"], json!(2048));
+        assert_eq!(payload["max_tokens"], json!(3072));
+        assert_eq!(adjustments.len(), 2);
+        assert_eq!(adjustments[1].param, "max_tokens");
+
+        // 未配置映射的 Provider 保持不变
+        let mut payload = json!({"reasoning_effort": "high"});
+        assert!(mappings.apply(ProviderType::Kiro, &mut payload).is_empty());
+        assert_eq!(payload["reasoning_effort"], json!("high"));
+    }
+}
@@ -0,0 +1,180 @@
+//! 模型路由解析轨迹
+//!
+//! 记录一次请求从客户端请求的模型到最终模型和 Provider 的完整过程：
+//! 别名映射、按请求大小降级等中间步骤，以及最终命中的路由规则。
+//! 轨迹记录到 `RoutingInfo`，用于排查"请求 `fast` 为什么落到了某个模型"。
+
+use serde::{Deserialize, Serialize};
+
+use super::rules::RouteResult;
+use crate::ProviderType;
+
+/// 解析步骤类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionStepKind {
+    /// 模型别名映射
+    Alias,
+    /// 提示词过大时的模型降级
+    SizeDowngrade,
+}
+
+impl ResolutionStepKind {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Alias => "别名",
+            Self::SizeDowngrade => "降级",
+        }
+    }
+}
+
+/// 解析轨迹中的一步模型改写
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolutionStep {
+    /// 步骤类型
+    pub kind: ResolutionStepKind,
+    /// 改写前的模型
+    pub from: String,
+    /// 改写后的模型
+    pub to: String,
+}
+
+/// 命中的路由规则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchedRoutingRule {
+    /// 规则的模型模式
+    pub pattern: String,
+    /// 规则优先级
+    pub priority: i32,
+}
+
+/// 模型路由解析轨迹
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutingTrace {
+    /// 客户端请求的模型（可能是别名）
+    pub requested_model: String,
+    /// 依次发生的模型改写
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<ResolutionStep>,
+    /// 最终使用的模型
+    pub resolved_model: String,
+    /// 选择的 Provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderType>,
+    /// 命中的路由规则（未命中时使用默认 Provider）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_rule: Option<MatchedRoutingRule>,
+}
+
+impl RoutingTrace {
+    /// 从客户端请求的模型开始记录
+    pub fn new(requested_model: impl Into<String>) -> Self {
+        let requested_model = requested_model.into();
+        Self {
+            resolved_model: requested_model.clone(),
+            requested_model,
+            steps: Vec::new(),
+            provider: None,
+            matched_rule: None,
+        }
+    }
+
+    /// 记录一步模型改写（模型未变化时忽略）
+    pub fn push_step(&mut self, kind: ResolutionStepKind, to: impl Into<String>) {
+        let to = to.into();
+        if to == self.resolved_model {
+            return;
+        }
+        self.steps.push(ResolutionStep {
+            kind,
+            from: std::mem::replace(&mut self.resolved_model, to.clone()),
+            to,
+        });
+    }
+
+    /// 记录路由结果（重新路由时覆盖之前的结果）
+    pub fn set_route(&mut self, route: &RouteResult) {
+        self.provider = Some(route.provider);
+        self.matched_rule = route.matched_rule.as_ref().map(|rule| MatchedRoutingRule {
+            pattern: rule.pattern.clone(),
+            priority: rule.priority,
+        });
+    }
+
+    /// 生成可读的描述
+    ///
+    /// 例如：``别名 `fast` → `gpt-4o-mini`，命中规则 `gpt-*`（优先级 10）→ openai``
+    pub fn describe(&self) -> String {
+        let mut text = if self.steps.is_empty() {
+            format!("`{}`", self.requested_model)
+        } else {
+            self.steps
+                .iter()
+                .enumerate()
+                .map(|(i, step)| {
+                    if i == 0 {
+                        format!("{} `{}` → `{}`", step.kind.label(), step.from, step.to)
+                    } else {
+                        format!("{} → `{}`", step.kind.label(), step.to)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("，")
+        };
+
+        let route = match &self.matched_rule {
+            Some(rule) => format!("命中规则 `{}`（优先级 {}）", rule.pattern, rule.priority),
+            None => "未命中规则，使用默认 Provider".to_string(),
+        };
+        text.push('，');
+        text.push_str(&route);
+        if let Some(provider) = self.provider {
+            // 全角括号后不再留空格
+            let arrow = if text.ends_with('）') { "→" } else { " →" };
+            text.push_str(&format!("{} {}", arrow, provider));
+        }
+        text
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::{Router, RoutingRule};
+
+    #[test]
+    fn test_trace_alias_downgrade_and_rule() {
+        let router = Router::with_rules(
+            ProviderType::Kiro,
+            vec![RoutingRule::new("gpt-*", ProviderType::OpenAI, 10)],
+        );
+
+        let mut trace = RoutingTrace::new("fast");
+        trace.push_step(ResolutionStepKind::Alias, "gpt-4o");
+        trace.push_step(ResolutionStepKind::SizeDowngrade, "gpt-4o-mini");
+        // 未变化的改写不记录
+        trace.push_step(ResolutionStepKind::Alias, "gpt-4o-mini");
+        trace.set_route(&router.route(&trace.resolved_model));
+
+        assert_eq!(trace.steps.len(), 2);
+        assert_eq!(trace.steps[1].from, "gpt-4o");
+        assert_eq!(trace.resolved_model, "gpt-4o-mini");
+        assert_eq!(trace.provider, Some(ProviderType::OpenAI));
+        assert_eq!(
+            trace.describe(),
+            "别名 `fast` → `gpt-4o`，降级 → `gpt-4o-mini`，命中规则 `gpt-*`（优先级 10）→ openai"
+        );
+
+        let mut trace = RoutingTrace::new("claude-sonnet-4-5");
+        trace.set_route(&router.route(&trace.resolved_model));
+        assert_eq!(trace.matched_rule, None);
+        assert_eq!(
+            trace.describe(),
+            "`claude-sonnet-4-5`，未命中规则，使用默认 Provider → kiro"
+        );
+    }
+}
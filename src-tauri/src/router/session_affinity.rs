@@ -0,0 +1,304 @@
+//! 会话亲和（粘性会话）
+//!
+//! 将同一会话的请求固定到同一个凭证，避免多轮对话分散到不同凭证后
+//! Provider 的提示词缓存失效。会话键来源：
+//! - 请求头（默认 `x-session-id`）
+//! - 对话哈希：模型、系统提示词以及首条用户消息之前的全部消息，多轮对话中保持不变
+//!
+//! 固定关系在 TTL 内有效，每次命中都会续期；被固定的凭证不可用时
+//! 由调用方重新选择凭证并重新固定。
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+fn default_ttl_secs() -> u64 {
+    1800
+}
+
+fn default_header() -> String {
+    "x-session-id".to_string()
+}
+
+/// 会话键来源
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionKeySource {
+    /// 优先使用请求头，缺失时使用对话哈希
+    #[default]
+    Auto,
+    /// 仅使用请求头
+    Header,
+    /// 仅使用对话哈希
+    Conversation,
+}
+
+/// 会话亲和配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionAffinityConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 固定关系的有效期（秒），每次命中续期
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// 会话键来源
+    #[serde(default)]
+    pub key_source: SessionKeySource,
+    /// 携带会话 ID 的请求头
+    #[serde(default = "default_header")]
+    pub header: String,
+}
+
+impl Default for SessionAffinityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: default_ttl_secs(),
+            key_source: SessionKeySource::default(),
+            header: default_header(),
+        }
+    }
+}
+
+/// 会话亲和的结果（记录到 `RoutingInfo`）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AffinityOutcome {
+    /// 命中已固定的凭证
+    Hit,
+    /// 新会话，已固定本次选择的凭证
+    Pinned,
+    /// 已固定的凭证不可用，已重新固定
+    Repinned,
+}
+
+/// 固定记录
+#[derive(Debug, Clone)]
+struct Pin {
+    credential_id: String,
+    expires_at: Instant,
+}
+
+/// 会话亲和表
+///
+/// 配置热重载时通过 [`SessionAffinity::update`] 替换配置，已有的固定关系保留。
+#[derive(Debug, Default)]
+pub struct SessionAffinity {
+    config: RwLock<SessionAffinityConfig>,
+    pins: Mutex<HashMap<String, Pin>>,
+}
+
+impl SessionAffinity {
+    /// 使用配置创建
+    pub fn new(config: SessionAffinityConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            pins: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 更新配置，禁用时清空所有固定关系
+    pub fn update(&self, config: SessionAffinityConfig) {
+        if !config.enabled {
+            self.pins.lock().clear();
+        }
+        *self.config.write() = config;
+    }
+
+    /// 是否启用
+    pub fn is_enabled(&self) -> bool {
+        self.config.read().enabled
+    }
+
+    /// 从请求中提取会话键
+    ///
+    /// 未启用或无法确定会话时返回 `None`。
+    pub fn session_key(
+        &self,
+        header_value: impl Fn(&str) -> Option<String>,
+        body: &serde_json::Value,
+    ) -> Option<String> {
+        let config = self.config.read();
+        if !config.enabled {
+            return None;
+        }
+        let from_header = || {
+            header_value(&config.header)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .map(|v| format!("header:{}", v))
+        };
+        match config.key_source {
+            SessionKeySource::Header => from_header(),
+            SessionKeySource::Conversation => conversation_key(body),
+            SessionKeySource::Auto => from_header().or_else(|| conversation_key(body)),
+        }
+    }
+
+    /// 查找固定的凭证，命中时续期
+    pub fn lookup(&self, scope: &str, session_key: &str) -> Option<String> {
+        let ttl = self.ttl();
+        let key = pin_key(scope, session_key);
+        let mut pins = self.pins.lock();
+        let now = Instant::now();
+        match pins.get_mut(&key) {
+            Some(pin) if pin.expires_at > now => {
+                pin.expires_at = now + ttl;
+                Some(pin.credential_id.clone())
+            }
+            Some(_) => {
+                pins.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// 固定会话到凭证
+    ///
+    /// 顺带清理已过期的记录。
+    pub fn pin(&self, scope: &str, session_key: &str, credential_id: &str) {
+        let now = Instant::now();
+        let expires_at = now + self.ttl();
+        let mut pins = self.pins.lock();
+        pins.retain(|_, pin| pin.expires_at > now);
+        pins.insert(
+            pin_key(scope, session_key),
+            Pin {
+                credential_id: credential_id.to_string(),
+                expires_at,
+            },
+        );
+    }
+
+    /// 当前有效的固定数量
+    pub fn len(&self) -> usize {
+        let now = Instant::now();
+        self.pins
+            .lock()
+            .values()
+            .filter(|pin| pin.expires_at > now)
+            .count()
+    }
+
+    /// 是否没有任何有效的固定
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn ttl(&self) -> Duration {
+        Duration::from_secs(self.config.read().ttl_secs)
+    }
+}
+
+/// 固定关系按 Provider 隔离，同一会话在不同 Provider 下各自固定
+fn pin_key(scope: &str, session_key: &str) -> String {
+    format!("{}|{}", scope, session_key)
+}
+
+/// 计算对话哈希
+///
+/// 取模型、`system` 字段以及直到首条用户消息（含）的全部消息，
+/// 后续轮次追加的消息不影响结果。没有消息时返回 `None`。
+pub fn conversation_key(body: &serde_json::Value) -> Option<String> {
+    let messages = body.get("messages")?.as_array()?;
+    let first_user = messages
+        .iter()
+        .position(|m| m.get("role").and_then(|r| r.as_str()) == Some("user"))?;
+    let prefix = serde_json::json!({
+        "model": body.get("model"),
+        "system": body.get("system"),
+        "messages": &messages[..=first_user],
+    });
+    let digest = Sha256::digest(prefix.to_string().as_bytes());
+    let hex: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    Some(format!("conv:{}", hex))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn enabled(key_source: SessionKeySource) -> SessionAffinity {
+        SessionAffinity::new(SessionAffinityConfig {
+            enabled: true,
+            key_source,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_conversation_key_stable_across_turns() {
+        let first = json!({
+            "model": "claude-sonnet-4-5",
+            "system": "Be brief.",
+            "messages": [{"role": "user", "content": "hi"}]
+        });
+        let later = json!({
+            "model": "claude-sonnet-4-5",
+            "system": "Be brief.",
+            "messages": [
+                {"role": "user", "content": "hi"},
+                {"role": "assistant", "content": "hello"},
+                {"role": "user", "content": "more"}
+            ]
+        });
+        let other = json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [{"role": "user", "content": "different"}]
+        });
+        assert_eq!(conversation_key(&first), conversation_key(&later));
+        assert_ne!(conversation_key(&first), conversation_key(&other));
+        assert!(conversation_key(&json!({"messages": []})).is_none());
+    }
+
+    #[test]
+    fn test_session_key_sources() {
+        let body = json!({"messages": [{"role": "user", "content": "hi"}]});
+        let header = |name: &str| (name == "x-session-id").then(|| "abc".to_string());
+        let no_header = |_: &str| None;
+
+        let auto = enabled(SessionKeySource::Auto);
+        assert_eq!(
+            auto.session_key(header, &body).as_deref(),
+            Some("header:abc")
+        );
+        assert!(auto
+            .session_key(no_header, &body)
+            .unwrap()
+            .starts_with("conv:"));
+
+        let header_only = enabled(SessionKeySource::Header);
+        assert!(header_only.session_key(no_header, &body).is_none());
+
+        let disabled = SessionAffinity::default();
+        assert!(disabled.session_key(header, &body).is_none());
+    }
+
+    #[test]
+    fn test_pin_lookup_and_expiry() {
+        let affinity = enabled(SessionKeySource::Auto);
+        affinity.pin("claude", "header:abc", "cred-1");
+        assert_eq!(
+            affinity.lookup("claude", "header:abc").as_deref(),
+            Some("cred-1")
+        );
+        assert!(affinity.lookup("openai", "header:abc").is_none());
+
+        affinity.update(SessionAffinityConfig {
+            enabled: true,
+            ttl_secs: 0,
+            ..Default::default()
+        });
+        affinity.pin("claude", "header:abc", "cred-2");
+        assert!(affinity.lookup("claude", "header:abc").is_none());
+        assert!(affinity.is_empty());
+
+        affinity.update(SessionAffinityConfig::default());
+        assert!(!affinity.is_enabled());
+    }
+}
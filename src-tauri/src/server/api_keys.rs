@@ -0,0 +1,270 @@
+//! 客户端 API Key 存储
+//!
+//! 支持多个客户端 API Key，每个 Key 对应一个身份标签以及可选的访问范围
+//! （允许的模型和 Provider）。Key 来源：
+//! - `server.api_key`：兼容原有的单一共享 Key，身份为 `default`，不限制范围
+//! - `server.api_keys`：配置文件中直接声明的 Key
+//! - `server.api_keys_file`：外部 YAML/JSON 文件中的 Key 列表
+//!
+//! Key 以 SHA-256 摘要保存，校验时对所有条目做常量时间比较，
+//! 避免通过响应时间推测 Key 内容。
+
+use crate::config::{expand_tilde, ClientApiKey, Config};
+use crate::models::provider_pool_model::pattern_matches;
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use subtle::{ConditionallySelectable, ConstantTimeEq};
+use thiserror::Error;
+
+/// 兼容 `server.api_key` 的默认身份标签
+pub const DEFAULT_API_KEY_LABEL: &str = "default";
+
+/// 请求上下文元数据中调用方身份标签的键
+pub const API_KEY_LABEL_KEY: &str = "api_key_label";
+
+/// API Key 文件加载错误
+#[derive(Debug, Error)]
+pub enum ApiKeyStoreError {
+    /// 读取文件失败
+    #[error("读取 API Key 文件失败: {0}")]
+    Io(#[from] std::io::Error),
+    /// 解析文件失败
+    #[error("解析 API Key 文件失败: {0}")]
+    Parse(#[from] serde_yaml::Error),
+}
+
+/// 调用方身份
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyIdentity {
+    /// 身份标签
+    pub label: String,
+    /// Key 的 SHA-256 摘要（十六进制），唯一标识调用方使用的 Key
+    pub key_digest: String,
+    /// 允许访问的模型（为空表示不限制）
+    pub allowed_models: Vec<String>,
+    /// 允许使用的 Provider（为空表示不限制）
+    pub allowed_providers: Vec<String>,
+}
+
+impl ApiKeyIdentity {
+    /// 是否允许访问指定模型
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.allowed_models.is_empty()
+            || self
+                .allowed_models
+                .iter()
+                .any(|pattern| pattern_matches(pattern, model))
+    }
+
+    /// 是否允许使用指定 Provider（不区分大小写）
+    pub fn allows_provider(&self, provider: &str) -> bool {
+        self.allowed_providers.is_empty()
+            || self
+                .allowed_providers
+                .iter()
+                .any(|p| p.eq_ignore_ascii_case(provider))
+    }
+}
+
+/// 已注册的 Key
+#[derive(Debug)]
+struct StoredKey {
+    /// Key 的 SHA-256 摘要
+    digest: [u8; 32],
+    /// 对应的身份
+    identity: ApiKeyIdentity,
+}
+
+impl StoredKey {
+    fn new(
+        key: &str,
+        label: String,
+        allowed_models: Vec<String>,
+        allowed_providers: Vec<String>,
+    ) -> Self {
+        let digest = digest(key);
+        Self {
+            digest,
+            identity: ApiKeyIdentity {
+                label,
+                key_digest: digest.iter().map(|b| format!("{:02x}", b)).collect(),
+                allowed_models,
+                allowed_providers,
+            },
+        }
+    }
+}
+
+/// 客户端 API Key 存储
+///
+/// 配置热重载时通过 [`ApiKeyStore::update`] 整体替换。
+#[derive(Debug, Default)]
+pub struct ApiKeyStore {
+    keys: RwLock<Vec<StoredKey>>,
+}
+
+impl ApiKeyStore {
+    /// 创建仅包含单一共享 Key 的存储
+    pub fn new(api_key: &str) -> Self {
+        Self {
+            keys: RwLock::new(vec![default_key(api_key)]),
+        }
+    }
+
+    /// 从配置创建
+    pub fn from_config(config: &Config) -> Self {
+        let store = Self::default();
+        store.update(config);
+        store
+    }
+
+    /// 使用新配置替换所有 Key
+    ///
+    /// Key 文件加载失败时仅记录警告，配置中的其余 Key 仍然生效。
+    pub fn update(&self, config: &Config) {
+        let server = &config.server;
+        let mut entries = server.api_keys.clone();
+        if let Some(path) = server
+            .api_keys_file
+            .as_deref()
+            .filter(|p| !p.trim().is_empty())
+        {
+            match load_api_keys_file(&expand_tilde(path)) {
+                Ok(keys) => entries.extend(keys),
+                Err(e) => tracing::warn!("[AUTH] 加载 API Key 文件 {} 失败: {}", path, e),
+            }
+        }
+
+        let mut keys = vec![default_key(&server.api_key)];
+        keys.extend(
+            entries
+                .into_iter()
+                .filter(|entry| !entry.key.is_empty())
+                .map(|entry| {
+                    StoredKey::new(
+                        &entry.key,
+                        entry.label,
+                        entry.allowed_models,
+                        entry.allowed_providers,
+                    )
+                }),
+        );
+        *self.keys.write() = keys;
+    }
+
+    /// 已注册的 Key 数量
+    pub fn len(&self) -> usize {
+        self.keys.read().len()
+    }
+
+    /// 是否没有任何 Key
+    pub fn is_empty(&self) -> bool {
+        self.keys.read().is_empty()
+    }
+
+    /// 解析 Key 对应的身份
+    ///
+    /// 与所有条目逐一比较且不提前返回，耗时与 Key 是否匹配无关。
+    pub fn resolve(&self, key: &str) -> Option<ApiKeyIdentity> {
+        let provided = digest(key);
+        let keys = self.keys.read();
+        let mut matched = 0u32;
+        let mut index = 0u32;
+        for (i, stored) in keys.iter().enumerate() {
+            let is_match = provided[..].ct_eq(&stored.digest[..]) & matched.ct_eq(&0);
+            index.conditional_assign(&(i as u32), is_match);
+            matched.conditional_assign(&1, is_match);
+        }
+        (matched == 1).then(|| keys[index as usize].identity.clone())
+    }
+}
+
+/// 从文件加载 Key 列表（YAML 或 JSON 数组）
+pub fn load_api_keys_file(path: &Path) -> Result<Vec<ClientApiKey>, ApiKeyStoreError> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(serde_yaml::from_str(&content)?)
+}
+
+fn default_key(api_key: &str) -> StoredKey {
+    StoredKey::new(
+        api_key,
+        DEFAULT_API_KEY_LABEL.to_string(),
+        Vec::new(),
+        Vec::new(),
+    )
+}
+
+fn digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn scoped_key(key: &str, label: &str, models: &[&str], providers: &[&str]) -> ClientApiKey {
+        ClientApiKey {
+            key: key.to_string(),
+            label: label.to_string(),
+            allowed_models: models.iter().map(|s| s.to_string()).collect(),
+            allowed_providers: providers.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_identities_from_config() {
+        let mut config = Config::default();
+        config.server.api_key = "shared".to_string();
+        config.server.api_keys = vec![
+            scoped_key("alice-key", "alice", &["claude-*"], &[]),
+            scoped_key("bob-key", "bob", &[], &["gemini"]),
+        ];
+        let store = ApiKeyStore::from_config(&config);
+        assert_eq!(store.len(), 3);
+
+        let default = store.resolve("shared").unwrap();
+        assert_eq!(default.label, DEFAULT_API_KEY_LABEL);
+        assert!(default.allows_model("gpt-4o"));
+
+        let alice = store.resolve("alice-key").unwrap();
+        assert_eq!(alice.label, "alice");
+        assert!(alice.allows_model("claude-sonnet-4-5"));
+        assert!(!alice.allows_model("gpt-4o"));
+        assert!(alice.allows_provider("kiro"));
+
+        let bob = store.resolve("bob-key").unwrap();
+        assert!(bob.allows_provider("Gemini"));
+        assert_eq!(bob.key_digest.len(), 64);
+        assert_ne!(alice.key_digest, bob.key_digest);
+        assert!(!bob.allows_provider("kiro"));
+
+        assert!(store.resolve("alice").is_none());
+        assert!(store.resolve("").is_none());
+    }
+
+    #[test]
+    fn test_load_keys_file_and_update() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            "- key: file-key\n  label: ci\n  allowed_models: [\"gpt-4o-mini\"]"
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.server.api_keys_file = Some(file.path().to_string_lossy().to_string());
+        let store = ApiKeyStore::from_config(&config);
+        let ci = store.resolve("file-key").unwrap();
+        assert_eq!(ci.label, "ci");
+        assert!(ci.allows_model("gpt-4o-mini"));
+        assert!(!ci.allows_model("gpt-4o"));
+
+        // 热重载后移除的 Key 立即失效
+        config.server.api_keys_file = None;
+        store.update(&config);
+        assert!(store.resolve("file-key").is_none());
+        assert!(store.resolve(&config.server.api_key).is_some());
+    }
+}
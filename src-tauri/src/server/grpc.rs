@@ -0,0 +1,841 @@
+//! gRPC 服务
+//!
+//! 提供与 HTTP 端点对应的 gRPC 接口（定义见 `proto/proxycast.proto`）：
+//! - `ChatCompletion` / `StreamChatCompletion` 对应 `POST /v1/chat/completions`
+//! - `CreateMessage` / `StreamMessage` 对应 `POST /v1/messages`
+//!
+//! 每个 RPC 将 protobuf 消息直接转换为类型化请求，经路由闸门后调用与 HTTP 端点相同的
+//! 处理函数，因此认证、幂等键、请求处理器、凭证池、Flow 捕获、内容过滤和遥测的行为
+//! 与 HTTP 完全一致，但不经过 HTTP 路由和 JSON 请求体的序列化。
+//! Provider 调用的结果仍是 HTTP 响应，非流式响应按 JSON 解析，流式响应将 SSE 事件
+//! 逐条转换为对应的 gRPC 消息。
+
+// tonic 的 RPC 签名固定以 `Status` 作为错误类型
+#![allow(clippy::result_large_err)]
+
+use super::handlers::{handle_anthropic_messages, handle_chat_completions};
+use super::routing_gate::{record_rejected_flow, run_gated};
+use super::AppState;
+use crate::models::{anthropic, openai};
+use axum::body::Body;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use tokio::sync::oneshot;
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Status};
+
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("proxycast.v1");
+}
+
+use proto::proxy_cast_server::{ProxyCast, ProxyCastServer};
+use proto::{
+    AnthropicMessage, AnthropicTool, AnthropicUsage, ChatCompletionChunk, ChatCompletionRequest,
+    ChatCompletionResponse, ChatMessage, Choice, ContentBlock, MessagesRequest, MessagesResponse,
+    MessagesStreamEvent, ResponseMessage, StreamChoice, StreamDelta, Tool, ToolCall, Usage,
+};
+
+const CHAT_COMPLETIONS_PATH: &str = "/v1/chat/completions";
+const MESSAGES_PATH: &str = "/v1/messages";
+
+/// 单条消息及非流式响应体的大小上限（与 HTTP 请求体上限一致）
+const MAX_MESSAGE_BYTES: usize = 100 * 1024 * 1024;
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// gRPC 服务实现
+///
+/// 与 HTTP 服务共享同一个 `AppState`。
+#[derive(Clone)]
+pub struct ProxyCastGrpcService {
+    state: AppState,
+}
+
+impl ProxyCastGrpcService {
+    /// 使用 HTTP 服务的共享状态创建服务
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    /// 通过路由闸门后调用处理函数
+    ///
+    /// 被闸门拒绝的请求与 HTTP 一样记录为失败的 Flow；非 2xx 响应转换为对应的 gRPC 状态。
+    async fn dispatch<T, F, Fut>(
+        &self,
+        path: &str,
+        request: T,
+        handler: F,
+    ) -> Result<Response, Status>
+    where
+        T: Serialize,
+        F: FnOnce(AppState, T) -> Fut,
+        Fut: Future<Output = Response>,
+    {
+        let response = match self.state.routing_gate.try_enter() {
+            Ok(guard) => run_gated(guard, handler(self.state.clone(), request)).await,
+            Err(rejection) => {
+                tracing::warn!("[GATE] 拒绝 gRPC 请求: {} mode={}", path, rejection.mode);
+                let body = serde_json::to_value(&request).unwrap_or_default();
+                let size_bytes = body.to_string().len();
+                record_rejected_flow(&self.state, "POST", path, body, size_bytes, &rejection).await;
+                rejection.into_response()
+            }
+        };
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(error_status(response).await)
+        }
+    }
+
+    async fn chat(
+        &self,
+        metadata: &MetadataMap,
+        message: ChatCompletionRequest,
+        stream: bool,
+    ) -> Result<Response, Status> {
+        let request = chat_request(message, stream)?;
+        let client_fields = client_fields(&request);
+        let headers = forwarded_headers(metadata);
+        self.dispatch(CHAT_COMPLETIONS_PATH, request, |state, request| {
+            handle_chat_completions(
+                state,
+                None,
+                headers,
+                request,
+                client_fields,
+                CHAT_COMPLETIONS_PATH,
+            )
+        })
+        .await
+    }
+
+    async fn messages(
+        &self,
+        metadata: &MetadataMap,
+        message: MessagesRequest,
+        stream: bool,
+    ) -> Result<Response, Status> {
+        let request = messages_request(message, stream)?;
+        let client_fields = client_fields(&request);
+        let headers = forwarded_headers(metadata);
+        self.dispatch(MESSAGES_PATH, request, |state, request| {
+            handle_anthropic_messages(state, headers, request, client_fields)
+        })
+        .await
+    }
+}
+
+#[tonic::async_trait]
+impl ProxyCast for ProxyCastGrpcService {
+    async fn chat_completion(
+        &self,
+        request: tonic::Request<ChatCompletionRequest>,
+    ) -> Result<tonic::Response<ChatCompletionResponse>, Status> {
+        let (metadata, _, message) = request.into_parts();
+        let response = self.chat(&metadata, message, false).await?;
+        let value = read_json(response).await?;
+        Ok(tonic::Response::new(chat_response(&value)))
+    }
+
+    type StreamChatCompletionStream = ResponseStream<ChatCompletionChunk>;
+
+    async fn stream_chat_completion(
+        &self,
+        request: tonic::Request<ChatCompletionRequest>,
+    ) -> Result<tonic::Response<Self::StreamChatCompletionStream>, Status> {
+        let (metadata, _, message) = request.into_parts();
+        let response = self.chat(&metadata, message, true).await?;
+        let stream = sse_events(response.into_body()).filter_map(|event| async move {
+            match event {
+                Ok(event) => chat_chunk(&event),
+                Err(status) => Some(Err(status)),
+            }
+        });
+        Ok(tonic::Response::new(Box::pin(stream)))
+    }
+
+    async fn create_message(
+        &self,
+        request: tonic::Request<MessagesRequest>,
+    ) -> Result<tonic::Response<MessagesResponse>, Status> {
+        let (metadata, _, message) = request.into_parts();
+        let response = self.messages(&metadata, message, false).await?;
+        let value = read_json(response).await?;
+        Ok(tonic::Response::new(messages_response(&value)))
+    }
+
+    type StreamMessageStream = ResponseStream<MessagesStreamEvent>;
+
+    async fn stream_message(
+        &self,
+        request: tonic::Request<MessagesRequest>,
+    ) -> Result<tonic::Response<Self::StreamMessageStream>, Status> {
+        let (metadata, _, message) = request.into_parts();
+        let response = self.messages(&metadata, message, true).await?;
+        let stream = sse_events(response.into_body())
+            .map(|event| event.and_then(|event| messages_stream_event(&event)));
+        Ok(tonic::Response::new(Box::pin(stream)))
+    }
+}
+
+/// 启动 gRPC 服务，收到关闭信号后停止
+pub async fn serve(
+    addr: SocketAddr,
+    state: AppState,
+    shutdown: oneshot::Receiver<()>,
+) -> Result<(), tonic::transport::Error> {
+    let service = ProxyCastServer::new(ProxyCastGrpcService::new(state))
+        .max_decoding_message_size(MAX_MESSAGE_BYTES);
+
+    tracing::info!("gRPC server listening on {}", addr);
+
+    tonic::transport::Server::builder()
+        .add_service(service)
+        .serve_with_shutdown(addr, async move {
+            let _ = shutdown.await;
+        })
+        .await
+}
+
+/// 将 gRPC metadata 转换为 HTTP 请求头
+///
+/// 去掉 gRPC 传输层自身的头部，保留认证、User-Agent、幂等键等业务头部。
+fn forwarded_headers(metadata: &MetadataMap) -> HeaderMap {
+    metadata
+        .clone()
+        .into_headers()
+        .into_iter()
+        .filter_map(|(name, value)| name.map(|name| (name, value)))
+        .filter(|(name, _)| {
+            let name = name.as_str();
+            !name.starts_with("grpc-")
+                && name != header::TE.as_str()
+                && name != header::CONTENT_TYPE.as_str()
+                && name != header::CONTENT_LENGTH.as_str()
+        })
+        .collect()
+}
+
+/// 请求中出现的顶层字段（未设置的可选字段不计入），供填充模型默认参数时区分
+fn client_fields(request: &impl Serialize) -> HashSet<String> {
+    match serde_json::to_value(request) {
+        Ok(Value::Object(object)) => object.into_iter().map(|(key, _)| key).collect(),
+        _ => HashSet::new(),
+    }
+}
+
+/// 将 HTTP 错误响应转换为 gRPC 状态
+async fn error_status(response: Response) -> Status {
+    let status = response.status();
+    let retry_after = response.headers().get(header::RETRY_AFTER).cloned();
+    let bytes = axum::body::to_bytes(response.into_body(), MAX_MESSAGE_BYTES)
+        .await
+        .unwrap_or_default();
+    let message = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .as_ref()
+        .and_then(error_message)
+        .unwrap_or_else(|| String::from_utf8_lossy(&bytes).into_owned());
+
+    let mut result = Status::new(status_code(status), message);
+    if let Some(value) = retry_after.and_then(|v| v.to_str().ok()?.parse().ok()) {
+        result.metadata_mut().insert("retry-after", value);
+    }
+    result
+}
+
+/// HTTP 状态码到 gRPC 状态码的映射
+fn status_code(status: StatusCode) -> Code {
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => Code::InvalidArgument,
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::FORBIDDEN => Code::PermissionDenied,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        StatusCode::PAYLOAD_TOO_LARGE => Code::OutOfRange,
+        StatusCode::TOO_MANY_REQUESTS => Code::ResourceExhausted,
+        StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => Code::DeadlineExceeded,
+        StatusCode::NOT_IMPLEMENTED => Code::Unimplemented,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+        _ => Code::Internal,
+    }
+}
+
+/// 提取错误响应中的 `error.message`（OpenAI 与 Anthropic 格式相同）
+fn error_message(value: &Value) -> Option<String> {
+    let error = value.get("error")?;
+    Some(match error.get("message").and_then(Value::as_str) {
+        Some(message) => message.to_string(),
+        None => error.to_string(),
+    })
+}
+
+async fn read_json(response: Response) -> Result<Value, Status> {
+    let bytes = axum::body::to_bytes(response.into_body(), MAX_MESSAGE_BYTES)
+        .await
+        .map_err(|e| Status::internal(format!("读取响应失败: {e}")))?;
+    serde_json::from_slice(&bytes).map_err(|e| Status::internal(format!("解析响应失败: {e}")))
+}
+
+/// 解析以 JSON 字符串传递的字段
+fn parse_json_field<T: serde::de::DeserializeOwned>(field: &str, raw: &str) -> Result<T, Status> {
+    serde_json::from_str(raw)
+        .map_err(|e| Status::invalid_argument(format!("{field} 不是合法的 JSON: {e}")))
+}
+
+fn parse_optional_json<T: serde::de::DeserializeOwned>(
+    field: &str,
+    raw: Option<&str>,
+) -> Result<Option<T>, Status> {
+    raw.map(|raw| parse_json_field(field, raw)).transpose()
+}
+
+/// 空字符串表示未设置工具类型，按 function 处理
+fn tool_type(value: String) -> String {
+    if value.is_empty() {
+        "function".to_string()
+    } else {
+        value
+    }
+}
+
+// ===== 请求转换 =====
+
+fn chat_request(
+    request: ChatCompletionRequest,
+    stream: bool,
+) -> Result<openai::ChatCompletionRequest, Status> {
+    let messages = request
+        .messages
+        .into_iter()
+        .map(chat_message)
+        .collect::<Result<Vec<_>, _>>()?;
+    let tools = request
+        .tools
+        .into_iter()
+        .map(tool)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(openai::ChatCompletionRequest {
+        model: request.model,
+        messages,
+        temperature: request.temperature,
+        max_tokens: request.max_tokens,
+        stream,
+        tools: (!tools.is_empty()).then_some(tools),
+        tool_choice: parse_optional_json("tool_choice_json", request.tool_choice_json.as_deref())?,
+        reasoning_effort: request.reasoning_effort,
+        thinking: None,
+        response_format: parse_optional_json(
+            "response_format_json",
+            request.response_format_json.as_deref(),
+        )?,
+        logprobs: request.logprobs,
+        top_logprobs: request.top_logprobs,
+        seed: request.seed,
+        stream_options: None,
+    })
+}
+
+fn chat_message(message: ChatMessage) -> Result<openai::ChatMessage, Status> {
+    let content = match parse_optional_json("content_json", message.content_json.as_deref())? {
+        Some(parts) => Some(openai::MessageContent::Parts(parts)),
+        None => message.content.map(openai::MessageContent::Text),
+    };
+    let tool_calls: Vec<_> = message
+        .tool_calls
+        .into_iter()
+        .map(|call| openai::ToolCall {
+            id: call.id,
+            call_type: tool_type(call.r#type),
+            function: openai::FunctionCall {
+                name: call.name,
+                arguments: call.arguments,
+            },
+        })
+        .collect();
+    Ok(openai::ChatMessage {
+        role: message.role,
+        content,
+        tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+        tool_call_id: message.tool_call_id,
+    })
+}
+
+fn tool(tool: Tool) -> Result<openai::Tool, Status> {
+    Ok(openai::Tool {
+        tool_type: tool_type(tool.r#type),
+        function: openai::FunctionDef {
+            name: tool.name,
+            description: tool.description,
+            parameters: parse_optional_json("parameters_json", tool.parameters_json.as_deref())?,
+        },
+    })
+}
+
+fn messages_request(
+    request: MessagesRequest,
+    stream: bool,
+) -> Result<anthropic::AnthropicMessagesRequest, Status> {
+    let messages = request
+        .messages
+        .into_iter()
+        .map(anthropic_message)
+        .collect::<Result<Vec<_>, _>>()?;
+    let tools = request
+        .tools
+        .into_iter()
+        .map(anthropic_tool)
+        .collect::<Result<Vec<_>, _>>()?;
+    let system = match parse_optional_json("system_json", request.system_json.as_deref())? {
+        Some(system) => Some(system),
+        None => request.system.map(Value::String),
+    };
+
+    Ok(anthropic::AnthropicMessagesRequest {
+        model: request.model,
+        messages,
+        max_tokens: request.max_tokens,
+        system,
+        temperature: request.temperature,
+        stream,
+        tools: (!tools.is_empty()).then_some(tools),
+        tool_choice: parse_optional_json("tool_choice_json", request.tool_choice_json.as_deref())?,
+        thinking: None,
+        reasoning_effort: None,
+    })
+}
+
+fn anthropic_message(message: AnthropicMessage) -> Result<anthropic::AnthropicMessage, Status> {
+    let content = match parse_optional_json("content_json", message.content_json.as_deref())? {
+        Some(content) => content,
+        None => Value::String(message.content.unwrap_or_default()),
+    };
+    Ok(anthropic::AnthropicMessage {
+        role: message.role,
+        content,
+    })
+}
+
+fn anthropic_tool(tool: AnthropicTool) -> Result<anthropic::AnthropicTool, Status> {
+    Ok(anthropic::AnthropicTool {
+        name: tool.name,
+        description: tool.description,
+        input_schema: parse_optional_json("input_schema_json", tool.input_schema_json.as_deref())?,
+    })
+}
+
+// ===== 响应转换 =====
+//
+// 响应按 JSON 宽松读取：不同 Provider 转换后的响应可能缺少部分字段，缺失时取默认值。
+
+fn str_of(value: &Value, key: &str) -> String {
+    opt_str_of(value, key).unwrap_or_default()
+}
+
+fn opt_str_of(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn u32_of(value: &Value, key: &str) -> u32 {
+    value.get(key).and_then(Value::as_u64).unwrap_or_default() as u32
+}
+
+/// JSON 值转为字符串（字符串原样返回，其他类型序列化）
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn tool_call(value: &Value) -> ToolCall {
+    let function = value.get("function").unwrap_or(&Value::Null);
+    ToolCall {
+        id: str_of(value, "id"),
+        r#type: str_of(value, "type"),
+        name: str_of(function, "name"),
+        arguments: function.get("arguments").map(json_text).unwrap_or_default(),
+        index: value.get("index").and_then(Value::as_u64).map(|i| i as u32),
+    }
+}
+
+fn tool_calls(value: &Value) -> Vec<ToolCall> {
+    value
+        .get("tool_calls")
+        .and_then(Value::as_array)
+        .map(|calls| calls.iter().map(tool_call).collect())
+        .unwrap_or_default()
+}
+
+fn usage(value: &Value) -> Usage {
+    Usage {
+        prompt_tokens: u32_of(value, "prompt_tokens"),
+        completion_tokens: u32_of(value, "completion_tokens"),
+        total_tokens: u32_of(value, "total_tokens"),
+    }
+}
+
+fn chat_response(value: &Value) -> ChatCompletionResponse {
+    let choices = value
+        .get("choices")
+        .and_then(Value::as_array)
+        .map(|choices| {
+            choices
+                .iter()
+                .map(|choice| {
+                    let message = choice.get("message").unwrap_or(&Value::Null);
+                    Choice {
+                        index: u32_of(choice, "index"),
+                        message: Some(ResponseMessage {
+                            role: str_of(message, "role"),
+                            content: opt_str_of(message, "content"),
+                            tool_calls: tool_calls(message),
+                        }),
+                        finish_reason: str_of(choice, "finish_reason"),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    ChatCompletionResponse {
+        id: str_of(value, "id"),
+        object: str_of(value, "object"),
+        created: value
+            .get("created")
+            .and_then(Value::as_u64)
+            .unwrap_or_default(),
+        model: str_of(value, "model"),
+        choices,
+        usage: Some(value.get("usage").map(usage).unwrap_or_default()),
+    }
+}
+
+/// 将 OpenAI SSE 事件转换为流式分块
+///
+/// `[DONE]` 返回 `None`；错误事件转换为 gRPC 状态。
+fn chat_chunk(event: &SseEvent) -> Option<Result<ChatCompletionChunk, Status>> {
+    if event.data.trim() == "[DONE]" {
+        return None;
+    }
+    let value: Value = match serde_json::from_str(&event.data) {
+        Ok(value) => value,
+        Err(e) => return Some(Err(Status::internal(format!("解析流式响应失败: {e}")))),
+    };
+    if let Some(message) = error_message(&value) {
+        return Some(Err(Status::internal(message)));
+    }
+
+    let choices = value
+        .get("choices")
+        .and_then(Value::as_array)
+        .map(|choices| {
+            choices
+                .iter()
+                .map(|choice| {
+                    let delta = choice.get("delta").unwrap_or(&Value::Null);
+                    StreamChoice {
+                        index: u32_of(choice, "index"),
+                        delta: Some(StreamDelta {
+                            role: opt_str_of(delta, "role"),
+                            content: opt_str_of(delta, "content"),
+                            tool_calls: tool_calls(delta),
+                        }),
+                        finish_reason: opt_str_of(choice, "finish_reason"),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(Ok(ChatCompletionChunk {
+        id: str_of(&value, "id"),
+        object: str_of(&value, "object"),
+        created: value
+            .get("created")
+            .and_then(Value::as_u64)
+            .unwrap_or_default(),
+        model: str_of(&value, "model"),
+        choices,
+        usage: value.get("usage").filter(|u| u.is_object()).map(usage),
+    }))
+}
+
+fn content_block(value: &Value) -> ContentBlock {
+    ContentBlock {
+        r#type: str_of(value, "type"),
+        text: opt_str_of(value, "text"),
+        id: opt_str_of(value, "id"),
+        name: opt_str_of(value, "name"),
+        input_json: value.get("input").map(Value::to_string),
+        thinking: opt_str_of(value, "thinking"),
+    }
+}
+
+fn messages_response(value: &Value) -> MessagesResponse {
+    let usage = value.get("usage").unwrap_or(&Value::Null);
+    MessagesResponse {
+        id: str_of(value, "id"),
+        r#type: str_of(value, "type"),
+        role: str_of(value, "role"),
+        content: value
+            .get("content")
+            .and_then(Value::as_array)
+            .map(|blocks| blocks.iter().map(content_block).collect())
+            .unwrap_or_default(),
+        model: str_of(value, "model"),
+        stop_reason: opt_str_of(value, "stop_reason"),
+        usage: Some(AnthropicUsage {
+            input_tokens: u32_of(usage, "input_tokens"),
+            output_tokens: u32_of(usage, "output_tokens"),
+        }),
+    }
+}
+
+/// 将 Anthropic SSE 事件转换为流式事件，`error` 事件转换为 gRPC 状态
+fn messages_stream_event(event: &SseEvent) -> Result<MessagesStreamEvent, Status> {
+    let value: Value = serde_json::from_str(&event.data)
+        .map_err(|e| Status::internal(format!("解析流式响应失败: {e}")))?;
+    let event_type = event
+        .event
+        .clone()
+        .or_else(|| opt_str_of(&value, "type"))
+        .unwrap_or_default();
+    if event_type == "error" {
+        let message = error_message(&value).unwrap_or_else(|| event.data.clone());
+        return Err(Status::internal(message));
+    }
+
+    let text_delta = value
+        .get("delta")
+        .filter(|delta| delta.get("type").and_then(Value::as_str) == Some("text_delta"))
+        .and_then(|delta| opt_str_of(delta, "text"));
+    Ok(MessagesStreamEvent {
+        r#type: event_type,
+        data_json: event.data.clone(),
+        text_delta,
+    })
+}
+
+// ===== SSE 解析 =====
+
+/// SSE 事件
+#[derive(Debug, Clone, PartialEq)]
+struct SseEvent {
+    /// `event:` 行（可选）
+    event: Option<String>,
+    /// `data:` 行内容（多行以换行连接）
+    data: String,
+}
+
+/// 将 SSE 响应体拆分为事件流
+///
+/// 响应体分块不一定与事件边界对齐，按字节缓冲到空行后再解码，避免截断多字节字符。
+fn sse_events(body: Body) -> impl Stream<Item = Result<SseEvent, Status>> {
+    async_stream::try_stream! {
+        let mut data = body.into_data_stream();
+        let mut buffer: Vec<u8> = Vec::new();
+        while let Some(chunk) = data.next().await {
+            let chunk = chunk.map_err(|e| Status::internal(format!("读取流式响应失败: {e}")))?;
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.windows(2).position(|w| w == b"\n\n") {
+                let block: Vec<u8> = buffer.drain(..end + 2).collect();
+                if let Some(event) = parse_sse_block(&String::from_utf8_lossy(&block)) {
+                    yield event;
+                }
+            }
+        }
+        if let Some(event) = parse_sse_block(&String::from_utf8_lossy(&buffer)) {
+            yield event;
+        }
+    }
+}
+
+/// 解析单个 SSE 事件块，没有 `data:` 行时返回 `None`
+fn parse_sse_block(block: &str) -> Option<SseEvent> {
+    let mut event = None;
+    let mut data: Vec<&str> = Vec::new();
+    for line in block.lines() {
+        let line = line.trim_end_matches('\r');
+        if let Some(value) = line.strip_prefix("event:") {
+            event = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    (!data.is_empty()).then(|| SseEvent {
+        event,
+        data: data.join("\n"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::{FlowInterceptor, FlowMonitor, FlowMonitorConfig};
+    use crate::processor::{RequestProcessor, ShadowStep};
+    use crate::server::api_keys::ApiKeyStore;
+    use crate::server::routing_gate::{RoutingGate, RoutingGateMode};
+    use crate::services::provider_pool_service::ProviderPoolService;
+    use crate::websocket::{WsConfig, WsConnectionManager};
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    /// 不连接数据库和上游的最小共享状态
+    fn test_state() -> AppState {
+        let pool_service = Arc::new(ProviderPoolService::new());
+        let flow_monitor = Arc::new(FlowMonitor::new(FlowMonitorConfig::default(), None));
+        let ws_manager = Arc::new(WsConnectionManager::new(WsConfig::default()));
+        AppState {
+            api_key: "test-key".to_string(),
+            api_keys: Arc::new(ApiKeyStore::new("test-key")),
+            base_url: "http://127.0.0.1:0".to_string(),
+            default_provider: Arc::new(RwLock::new("kiro".to_string())),
+            kiro: Arc::new(RwLock::new(crate::providers::kiro::KiroProvider::new())),
+            logs: Arc::new(RwLock::new(Default::default())),
+            kiro_refresh_lock: Default::default(),
+            gemini_refresh_lock: Default::default(),
+            qwen_refresh_lock: Default::default(),
+            pool_service: pool_service.clone(),
+            token_cache: Arc::new(crate::services::token_cache_service::TokenCacheService::new()),
+            db: None,
+            injector: Arc::new(RwLock::new(crate::injection::Injector::new())),
+            injection_enabled: Arc::new(RwLock::new(false)),
+            processor: Arc::new(RequestProcessor::with_defaults(pool_service)),
+            ws_stats: ws_manager.stats().clone(),
+            ws_manager,
+            hot_reload_manager: None,
+            request_logger: None,
+            amp_router: Arc::new(crate::router::AmpRouter::new(Default::default())),
+            flow_interceptor: Arc::new(FlowInterceptor::default()),
+            endpoint_providers: Default::default(),
+            routing_gate: Arc::new(RoutingGate::new()),
+            upstream_proxies: Default::default(),
+            idempotency: Default::default(),
+            shadow: Arc::new(ShadowStep::new(Default::default(), flow_monitor.clone())),
+            flow_monitor,
+            stream_passthrough_unknown_events: Default::default(),
+        }
+    }
+
+    fn chat_request_with_key(key: &str) -> tonic::Request<ChatCompletionRequest> {
+        let mut request = tonic::Request::new(ChatCompletionRequest {
+            model: "gpt-4o".to_string(),
+            messages: vec![ChatMessage {
+                role: "user".to_string(),
+                content: Some("hello".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        request
+            .metadata_mut()
+            .insert("authorization", format!("Bearer {key}").parse().unwrap());
+        request
+    }
+
+    #[test]
+    fn test_chat_request_conversion() {
+        let request = chat_request(
+            ChatCompletionRequest {
+                model: "gpt-4o".to_string(),
+                messages: vec![ChatMessage {
+                    role: "user".to_string(),
+                    content_json: Some(r#"[{"type":"text","text":"hi"}]"#.to_string()),
+                    ..Default::default()
+                }],
+                temperature: Some(0.5),
+                tools: vec![Tool {
+                    name: "search".to_string(),
+                    parameters_json: Some(r#"{"type":"object"}"#.to_string()),
+                    ..Default::default()
+                }],
+                tool_choice_json: Some(r#""auto""#.to_string()),
+                ..Default::default()
+            },
+            true,
+        )
+        .unwrap();
+        assert!(request.stream);
+        assert_eq!(request.messages[0].get_content_text(), "hi");
+        let tools = request.tools.as_ref().unwrap();
+        assert_eq!(tools[0].tool_type, "function");
+        assert_eq!(
+            tools[0].function.parameters,
+            Some(serde_json::json!({"type": "object"}))
+        );
+        assert_eq!(request.tool_choice, Some(serde_json::json!("auto")));
+
+        // 未设置的可选字段不计入客户端字段，避免覆盖模型默认参数
+        let fields = client_fields(&request);
+        assert!(fields.contains("temperature"));
+        assert!(!fields.contains("max_tokens"));
+
+        let error = chat_request(
+            ChatCompletionRequest {
+                tool_choice_json: Some("{not json".to_string()),
+                ..Default::default()
+            },
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_goes_through_gate_and_handler() {
+        let state = test_state();
+        let service = ProxyCastGrpcService::new(state.clone());
+
+        // 认证由 HTTP 端点的处理函数完成
+        let status = service
+            .chat_completion(chat_request_with_key("wrong"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert_eq!(status.message(), "Invalid API key");
+
+        let mut request = chat_request_with_key("test-key");
+        request.get_mut().tool_choice_json = Some("{not json".to_string());
+        let status = service.chat_completion(request).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        state.routing_gate.set_mode(RoutingGateMode::Drain);
+        let status = service
+            .chat_completion(chat_request_with_key("test-key"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert!(status.metadata().get("retry-after").is_some());
+        assert_eq!(state.routing_gate.status().in_flight, 0);
+    }
+
+    #[test]
+    fn test_messages_stream_event_conversion() {
+        let block = "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n";
+        let event = parse_sse_block(block).unwrap();
+        let converted = messages_stream_event(&event).unwrap();
+        assert_eq!(converted.r#type, "content_block_delta");
+        assert_eq!(converted.text_delta.as_deref(), Some("Hi"));
+
+        let error = parse_sse_block(
+            "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}",
+        )
+        .unwrap();
+        assert_eq!(
+            messages_stream_event(&error).unwrap_err().message(),
+            "Overloaded"
+        );
+        assert!(parse_sse_block(": ping\n\n").is_none());
+    }
+}
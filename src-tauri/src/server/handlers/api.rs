@@ -15,43 +15,76 @@
 
 use axum::{
     body::Body,
-    extract::State,
+    extract::{FromRequest, OriginalUri, RawQuery, Request, State},
     http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
-use chrono::Utc;
-use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use crate::converter::anthropic_to_openai::convert_anthropic_to_openai;
+use crate::converter::openai_responses::{
+    convert_openai_to_responses, convert_responses_to_openai, responses_fields_to_openai,
+    ResponsesStreamConverter,
+};
 use crate::flow_monitor::{
-    ClientInfo, FlowError, FlowErrorType, FlowMetadata, FlowType, InterceptAction, InterceptType,
-    LLMFlow, LLMRequest, LLMResponse, Message, MessageContent, MessageRole, RequestParameters,
-    RoutingInfo, TokenUsage,
+    is_multipart, parse_retry_after, ClientInfo, FlowError, FlowErrorType, FlowMetadata, FlowType,
+    InterceptAction, InterceptType, LLMFlow, LLMRequest, LLMResponse, Message, MessageContent,
+    MessageRole, MockConfig, RequestParameters, RoutingInfo, TokenUsage, CHAOS_TAG,
+    MOCK_FLOW_ID_HEADER, MOCK_HEADER, MOCK_MATCH_HEADER,
 };
 use crate::models::anthropic::AnthropicMessagesRequest;
 use crate::models::openai::ChatCompletionRequest;
-use crate::processor::RequestContext;
+use crate::models::openai_responses::ResponsesRequest;
+use crate::models::provider_pool_model::ProviderCredential;
+use crate::plugin::FlowPluginError;
+use crate::processor::{
+    injected_fault, routing_trace, ChaosFault, PipelineStep, ProviderCallError, ProviderCallResult,
+    RequestContext, ShadowCaller, ShadowStep, SseContentFilter, HISTORY_TRUNCATION_KEY,
+    MODEL_DOWNGRADE_KEY, NO_REMAP_HEADER, PARAM_ADJUSTMENTS_KEY, PRIMARY_RESPONSE_KEY,
+    REQUEST_PATH_KEY,
+};
+use crate::resilience::RateLimitHeaders;
+use crate::router::{AffinityOutcome, ParamAdjustment, ParamAdjustmentAction};
+use crate::server::api_keys::{ApiKeyIdentity, ApiKeyStore, API_KEY_LABEL_KEY};
 use crate::server::client_detector::ClientType;
+use crate::server::idempotency::{
+    IdempotencyCache, IdempotencyGuard, IdempotencyStart, StoredResponse, IDEMPOTENT_REPLAY_HEADER,
+    MAX_CACHED_BODY_BYTES,
+};
 use crate::server::{record_request_telemetry, record_token_usage, AppState};
 use crate::server_utils::{
     build_anthropic_response, build_anthropic_stream_response, message_content_len,
     parse_cw_response, safe_truncate,
 };
-use crate::streaming::StreamFormat as StreamingFormat;
+use crate::streaming::{
+    is_truthy, StreamFormat as StreamingFormat, ACCUMULATE_STREAM_HEADER, ACCUMULATE_STREAM_QUERY,
+};
 use crate::ProviderType;
 
-use super::{call_provider_anthropic, call_provider_openai};
+use super::{
+    call_provider_anthropic, call_provider_multipart, call_provider_openai, read_upstream_body,
+    record_upstream_response,
+};
 
 // ============================================================================
 // Flow 捕获辅助函数
 // ============================================================================
 
 /// 从 OpenAI 格式请求构建 LLMRequest
-fn build_llm_request_from_openai(
+///
+/// `received_at` 为代理收到请求的时间，作为 Flow 的请求开始时间，
+/// 使内部处理耗时计入 Flow 的总耗时。`headers` 为已排除敏感头的请求头
+/// （见 `FlowMonitor::capture_request_headers`）。
+pub(crate) fn build_llm_request_from_openai(
     request: &ChatCompletionRequest,
     path: &str,
-    headers: &HeaderMap,
+    headers: HashMap<String, String>,
+    received_at: DateTime<Utc>,
 ) -> LLMRequest {
     // 转换消息
     let messages: Vec<Message> = request
@@ -111,32 +144,29 @@ fn build_llm_request_from_openai(
         .find(|m| m.role == MessageRole::System)
         .map(|m| m.content.get_all_text());
 
-    // 构建请求参数
+    // 构建请求参数（logprobs 相关参数记录在 extra 中）
+    let mut extra = HashMap::new();
+    if let Some(logprobs) = request.logprobs {
+        extra.insert("logprobs".to_string(), serde_json::json!(logprobs));
+    }
+    if let Some(top_logprobs) = request.top_logprobs {
+        extra.insert("top_logprobs".to_string(), serde_json::json!(top_logprobs));
+    }
     let parameters = RequestParameters {
         temperature: request.temperature,
         top_p: None,
         max_tokens: request.max_tokens,
         stop: None,
         stream: request.stream,
-        extra: HashMap::new(),
+        response_format: request.response_format.clone(),
+        seed: request.seed,
+        extra,
     };
 
-    // 提取请求头
-    let mut header_map = HashMap::new();
-    for (name, value) in headers.iter() {
-        if let Ok(v) = value.to_str() {
-            // 排除敏感头
-            let name_lower = name.as_str().to_lowercase();
-            if !name_lower.contains("authorization") && !name_lower.contains("api-key") {
-                header_map.insert(name.as_str().to_string(), v.to_string());
-            }
-        }
-    }
-
     LLMRequest {
         method: "POST".to_string(),
         path: path.to_string(),
-        headers: header_map,
+        headers,
         body: serde_json::to_value(request).unwrap_or_default(),
         messages,
         system_prompt,
@@ -145,15 +175,17 @@ fn build_llm_request_from_openai(
         original_model: None,
         parameters,
         size_bytes: 0,
-        timestamp: Utc::now(),
+        timestamp: received_at,
+        attachments: Vec::new(),
     }
 }
 
 /// 从 Anthropic 格式请求构建 LLMRequest
-fn build_llm_request_from_anthropic(
+pub(crate) fn build_llm_request_from_anthropic(
     request: &AnthropicMessagesRequest,
     path: &str,
-    headers: &HeaderMap,
+    headers: HashMap<String, String>,
+    received_at: DateTime<Utc>,
 ) -> LLMRequest {
     // 转换消息
     let messages: Vec<Message> = request
@@ -232,24 +264,15 @@ fn build_llm_request_from_anthropic(
         max_tokens: request.max_tokens,
         stop: None,
         stream: request.stream,
+        response_format: None,
+        seed: None,
         extra: HashMap::new(),
     };
 
-    // 提取请求头
-    let mut header_map = HashMap::new();
-    for (name, value) in headers.iter() {
-        if let Ok(v) = value.to_str() {
-            let name_lower = name.as_str().to_lowercase();
-            if !name_lower.contains("authorization") && !name_lower.contains("api-key") {
-                header_map.insert(name.as_str().to_string(), v.to_string());
-            }
-        }
-    }
-
     LLMRequest {
         method: "POST".to_string(),
         path: path.to_string(),
-        headers: header_map,
+        headers,
         body: serde_json::to_value(request).unwrap_or_default(),
         messages,
         system_prompt,
@@ -258,7 +281,175 @@ fn build_llm_request_from_anthropic(
         original_model: None,
         parameters,
         size_bytes: 0,
-        timestamp: Utc::now(),
+        timestamp: received_at,
+        attachments: Vec::new(),
+    }
+}
+
+/// 开始捕获 Flow
+///
+/// 活跃 Flow 达到上限且配置为拒绝请求时，返回带 `Retry-After` 的 503 响应
+async fn start_flow_capture(
+    state: &AppState,
+    llm_request: &LLMRequest,
+    flow_metadata: &FlowMetadata,
+) -> Result<Option<String>, Response> {
+    state
+        .flow_monitor
+        .try_start_flow(llm_request.clone(), flow_metadata.clone())
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, "1")],
+                Json(serde_json::json!({
+                    "error": {
+                        "message": e.to_string(),
+                        "type": "flow_capacity_exceeded",
+                        "code": "flow_capacity_exceeded"
+                    }
+                })),
+            )
+                .into_response()
+        })
+}
+
+/// 执行 Flow 插件的请求钩子
+///
+/// 插件修改请求体后，用修改后的请求体替换原始请求；插件拒绝请求或执行失败时
+/// 返回 400 响应（Anthropic 端点使用 Anthropic 错误格式）。
+async fn apply_request_plugins<T: serde::de::DeserializeOwned>(
+    state: &AppState,
+    llm_request: &mut LLMRequest,
+    request: &mut T,
+) -> Result<(), Response> {
+    let (error_type, message) = match state.processor.flow_plugins.run_on_request(llm_request) {
+        Ok(false) => return Ok(()),
+        Ok(true) => match serde_json::from_value(llm_request.body.clone()) {
+            Ok(updated) => {
+                *request = updated;
+                return Ok(());
+            }
+            Err(e) => ("plugin_error", format!("插件修改后的请求体无效: {}", e)),
+        },
+        Err(e @ FlowPluginError::Rejected { .. }) => ("request_rejected", e.to_string()),
+        Err(e @ FlowPluginError::Failed { .. }) => ("plugin_error", e.to_string()),
+    };
+
+    state
+        .logs
+        .write()
+        .await
+        .add("warn", &format!("[PLUGIN] {}", message));
+    let body = if llm_request.path.ends_with("/messages") {
+        serde_json::json!({
+            "type": "error",
+            "error": {"type": error_type, "message": message}
+        })
+    } else {
+        serde_json::json!({
+            "error": {"message": message, "type": error_type, "code": error_type}
+        })
+    };
+    Err((StatusCode::BAD_REQUEST, Json(body)).into_response())
+}
+
+/// 上下文元数据中路由信息的键
+const ROUTING_INFO_KEY: &str = "routing_info";
+
+/// 从请求中提取会话亲和的会话键
+fn request_session_key<T: serde::Serialize>(
+    state: &AppState,
+    headers: &HeaderMap,
+    request: &T,
+) -> Option<String> {
+    let affinity = &state.processor.session_affinity;
+    if !affinity.is_enabled() {
+        return None;
+    }
+    let body = serde_json::to_value(request).unwrap_or_default();
+    affinity.session_key(
+        |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string())
+        },
+        &body,
+    )
+}
+
+/// 从凭证池选择凭证
+///
+/// 优先按最终选择的 provider 选择凭证；如果没有可用凭证，再回退到默认 provider。
+/// 带有会话键时优先使用该会话固定的凭证，固定的凭证不可用时重新选择并重新固定，
+/// 结果记录到上下文的路由信息中。
+fn select_pool_credential(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    identity: &ApiKeyIdentity,
+    final_provider: &str,
+    default_provider: &str,
+    model: &str,
+    session_key: Option<&str>,
+) -> Option<ProviderCredential> {
+    let db = state.db.as_ref()?;
+    let affinity = &state.processor.session_affinity;
+
+    let pinned = session_key.and_then(|key| affinity.lookup(final_provider, key));
+    if let Some(uuid) = pinned.as_deref() {
+        if let Some(credential) = state
+            .pool_service
+            .get_pinned_credential(db, uuid, Some(model))
+        {
+            record_session_affinity(ctx, session_key, AffinityOutcome::Hit);
+            return Some(credential);
+        }
+    }
+
+    let credential = state
+        .pool_service
+        .select_credential(db, final_provider, Some(model))
+        .ok()
+        .flatten()
+        .or_else(|| {
+            if final_provider != default_provider && identity.allows_provider(default_provider) {
+                state
+                    .pool_service
+                    .select_credential(db, default_provider, Some(model))
+                    .ok()
+                    .flatten()
+            } else {
+                None
+            }
+        })?;
+
+    if let Some(key) = session_key {
+        affinity.pin(final_provider, key, &credential.uuid);
+        let outcome = if pinned.is_some() {
+            AffinityOutcome::Repinned
+        } else {
+            AffinityOutcome::Pinned
+        };
+        record_session_affinity(ctx, session_key, outcome);
+    }
+    Some(credential)
+}
+
+/// 记录会话亲和结果到上下文的路由信息
+fn record_session_affinity(
+    ctx: &mut RequestContext,
+    session_key: Option<&str>,
+    outcome: AffinityOutcome,
+) {
+    let routing_info = RoutingInfo {
+        load_balance_strategy: Some("session_affinity".to_string()),
+        session_key: session_key.map(|s| s.to_string()),
+        session_affinity: Some(outcome),
+        ..Default::default()
+    };
+    if let Ok(value) = serde_json::to_value(routing_info) {
+        ctx.set_metadata(ROUTING_INFO_KEY, value);
     }
 }
 
@@ -268,7 +459,7 @@ fn build_flow_metadata(
     credential_id: Option<&str>,
     credential_name: Option<&str>,
     headers: &HeaderMap,
-    request_id: &str,
+    ctx: &RequestContext,
 ) -> FlowMetadata {
     // 提取客户端信息
     let client_ip = headers
@@ -282,6 +473,26 @@ fn build_flow_metadata(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
+    // 记录别名参数约束调整和推理参数转换后的值，模型默认值单独记录
+    let (defaulted, adjusted): (Vec<_>, Vec<_>) = ctx
+        .get_metadata(PARAM_ADJUSTMENTS_KEY)
+        .and_then(|v| serde_json::from_value::<Vec<ParamAdjustment>>(v.clone()).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .partition(|a| a.action == ParamAdjustmentAction::Defaulted);
+    let to_params = |adjustments: Vec<ParamAdjustment>| {
+        (!adjustments.is_empty()).then(|| {
+            adjustments
+                .into_iter()
+                .map(|a| (a.param, a.applied))
+                .collect::<HashMap<_, _>>()
+        })
+    };
+    let injected_params = to_params(adjusted);
+    let defaulted_params = to_params(defaulted);
+
+    let trace = routing_trace(ctx);
+
     FlowMetadata {
         provider,
         credential_id: credential_id.map(|s| s.to_string()),
@@ -290,16 +501,47 @@ fn build_flow_metadata(
         client_info: ClientInfo {
             ip: client_ip,
             user_agent,
-            request_id: Some(request_id.to_string()),
+            request_id: Some(ctx.request_id.clone()),
+            api_key_label: ctx
+                .get_metadata(API_KEY_LABEL_KEY)
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+        },
+        routing_info: RoutingInfo {
+            route_rule: trace
+                .as_ref()
+                .and_then(|t| t.matched_rule.as_ref())
+                .map(|rule| rule.pattern.clone()),
+            model_downgrade: ctx
+                .get_metadata(MODEL_DOWNGRADE_KEY)
+                .and_then(|v| serde_json::from_value(v.clone()).ok()),
+            history_truncation: ctx
+                .get_metadata(HISTORY_TRUNCATION_KEY)
+                .and_then(|v| serde_json::from_value(v.clone()).ok()),
+            routing_trace: trace,
+            remap_bypassed: ctx.no_remap,
+            ..ctx
+                .get_metadata(ROUTING_INFO_KEY)
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default()
         },
-        routing_info: RoutingInfo::default(),
-        injected_params: None,
+        injected_params,
+        defaulted_params,
         context_usage_percentage: None,
+        shadow_of: None,
+        idempotent_replay_of: None,
+        upstream_request_id: None,
+        usage_source: None,
+        content_filter: None,
     }
 }
 
 /// 从响应构建 LLMResponse
-fn build_llm_response(status_code: u16, content: &str, usage: Option<(u32, u32)>) -> LLMResponse {
+pub(crate) fn build_llm_response(
+    status_code: u16,
+    content: &str,
+    usage: Option<(u32, u32)>,
+) -> LLMResponse {
     let now = Utc::now();
     let (input_tokens, output_tokens) = usage.unwrap_or((0, 0));
 
@@ -320,10 +562,16 @@ fn build_llm_response(status_code: u16, content: &str, usage: Option<(u32, u32)>
             total_tokens: input_tokens + output_tokens,
         },
         stop_reason: None,
+        raw_stop_reason: None,
         size_bytes: content.len(),
         timestamp_start: now,
         timestamp_end: now,
         stream_info: None,
+        schema_valid: None,
+        logprobs: None,
+        body_info: None,
+        system_fingerprint: None,
+        turns: Vec::new(),
     }
 }
 
@@ -465,12 +713,422 @@ async fn check_request_intercept(
     }
 }
 
+/// 记录请求发往上游的时间
+///
+/// 在路由、凭证选择、参数注入和请求拦截之后、调用上游之前调用，
+/// 用于区分代理内部处理耗时与上游耗时。
+async fn mark_upstream_dispatch(state: &AppState, flow_id: Option<&str>) {
+    if let Some(fid) = flow_id {
+        state.flow_monitor.mark_request_sent(fid).await;
+    }
+}
+
+/// 覆盖请求总超时的请求头（毫秒，0 表示不限制）
+const REQUEST_TIMEOUT_HEADER: &str = "x-request-timeout-ms";
+
+/// 非流式请求的总超时截止时间
+///
+/// 在首次调用上游前确定，Token 刷新后的重试共用同一个截止时间，不会重新计时。
+#[derive(Debug, Clone, Copy)]
+struct RequestDeadline {
+    at: tokio::time::Instant,
+    timeout: std::time::Duration,
+}
+
+/// 计算非流式请求的总超时截止时间
+///
+/// `x-request-timeout-ms` 请求头优先，其次为 Provider 配置的 `request_timeout_ms`。
+/// 未解析出 Provider 时使用全局默认超时，而不是借用某个 Provider 的配置。
+/// 流式请求由流空闲超时控制，不设置总超时。
+fn request_deadline(
+    state: &AppState,
+    headers: &HeaderMap,
+    ctx: &RequestContext,
+    stream: bool,
+) -> Option<RequestDeadline> {
+    if stream {
+        return None;
+    }
+    let from_header = headers
+        .get(REQUEST_TIMEOUT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let timeout = match from_header {
+        Some(0) => return None,
+        Some(ms) => std::time::Duration::from_millis(ms),
+        None => match ctx.provider {
+            Some(provider) => state
+                .upstream_proxies
+                .request_timeout_for(&provider.to_string())?,
+            None => state.upstream_proxies.default_request_timeout(),
+        },
+    };
+    Some(RequestDeadline {
+        at: tokio::time::Instant::now() + timeout,
+        timeout,
+    })
+}
+
+/// 在总超时内等待上游调用
+///
+/// 超时后丢弃上游调用的 future，reqwest 随之中止请求并关闭连接；
+/// Flow 以 `Timeout` 失败、记录一次超时统计，并返回 504 响应。
+async fn with_request_deadline<T>(
+    state: &AppState,
+    ctx: &RequestContext,
+    flow_id: Option<&str>,
+    deadline: Option<RequestDeadline>,
+    call: impl std::future::Future<Output = T>,
+) -> Result<T, Response> {
+    let Some(deadline) = deadline else {
+        return Ok(call.await);
+    };
+    match tokio::time::timeout_at(deadline.at, call).await {
+        Ok(result) => Ok(result),
+        Err(_) => {
+            let message = format!("上游请求超时（{}ms）", deadline.timeout.as_millis());
+            state.logs.write().await.add(
+                "warn",
+                &format!("[TIMEOUT] request_id={} {}", ctx.request_id, message),
+            );
+            record_request_telemetry(
+                state,
+                ctx,
+                crate::telemetry::RequestStatus::Timeout,
+                Some(message.clone()),
+            );
+            if let Some(fid) = flow_id {
+                let error = FlowError::new(FlowErrorType::Timeout, &message).with_retryable(true);
+                state.flow_monitor.fail_flow(fid, error).await;
+            }
+            Err((
+                StatusCode::GATEWAY_TIMEOUT,
+                Json(serde_json::json!({"error": {
+                    "message": message,
+                    "type": "timeout_error",
+                    "code": "request_timeout"
+                }})),
+            )
+                .into_response())
+        }
+    }
+}
+
+/// 执行故障注入步骤（混沌测试）
+///
+/// 延迟故障在步骤内等待后继续正常处理（返回 `None`）。其余故障不调用上游，
+/// 直接返回构造的故障响应，并按客户端收到的结果记录 Flow（标记 `chaos` 标签），
+/// 使注入的故障与真实故障一样出现在 Flow 监控和统计中。
+async fn inject_chaos(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    flow_id: Option<&str>,
+    format: StreamingFormat,
+) -> Option<Response> {
+    if !state.processor.chaos.is_enabled() {
+        return None;
+    }
+    let _ = state
+        .processor
+        .chaos
+        .execute(ctx, &mut serde_json::Value::Null)
+        .await;
+    let fault = injected_fault(ctx)?;
+    state.logs.write().await.add(
+        "warn",
+        &format!(
+            "[CHAOS] ⚠️ request_id={} model={} 注入故障: {:?}",
+            ctx.request_id, ctx.resolved_model, fault
+        ),
+    );
+
+    let anthropic = matches!(format, StreamingFormat::AnthropicSse);
+    let (response, outcome) = match fault {
+        ChaosFault::Delay { .. } => return None,
+        ChaosFault::RateLimit { retry_after_secs } => {
+            let message = "[CHAOS] 注入的 429 限流错误".to_string();
+            let mut response = chaos_error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limit_error",
+                &message,
+                anthropic,
+            );
+            if let Some(secs) = retry_after_secs {
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, secs.into());
+            }
+            let error = FlowError::new(FlowErrorType::RateLimit, &message)
+                .with_status_code(429)
+                .with_retry_after(retry_after_secs);
+            (response, Err(error))
+        }
+        ChaosFault::ServerError { status_code } => {
+            let status =
+                StatusCode::from_u16(status_code).unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+            let message = format!("[CHAOS] 注入的 {} 服务器错误", status.as_u16());
+            let response = chaos_error_response(status, "api_error", &message, anthropic);
+            let error = FlowError::new(FlowErrorType::from_status_code(status.as_u16()), &message)
+                .with_status_code(status.as_u16());
+            (response, Err(error))
+        }
+        ChaosFault::DropStream { after_chunks } => {
+            let message = format!("[CHAOS] 注入的流中断：发送 {} 个数据块后断开", after_chunks);
+            let mut events = chaos_stream_events(&ctx.resolved_model, after_chunks, anthropic)
+                .into_iter()
+                .map(Ok)
+                .collect::<Vec<Result<String, std::io::Error>>>();
+            events.push(Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionAborted,
+                message.clone(),
+            )));
+            let response = Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, "text/event-stream")
+                .header(header::CACHE_CONTROL, "no-cache")
+                .body(Body::from_stream(futures::stream::iter(events)))
+                .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response());
+            let error = FlowError::new(FlowErrorType::Network, &message).with_retryable(true);
+            (response, Err(error))
+        }
+        ChaosFault::MalformedBody => {
+            // 截断的 JSON，客户端无法解析
+            let truncated = if anthropic {
+                r#"{"id":"msg_chaos","type":"message","role":"assistant","content":[{"type":"text","text":"#
+            } else {
+                r#"{"id":"chatcmpl-chaos","object":"chat.completion","choices":[{"index":0,"message":{"role":"assistant","content":"#
+            };
+            let (content_type, body) = if ctx.is_stream {
+                ("text/event-stream", format!("data: {}\n\n", truncated))
+            } else {
+                ("application/json", truncated.to_string())
+            };
+            let mut llm_response = build_llm_response(200, "", None);
+            llm_response.body = serde_json::Value::String(body.clone());
+            llm_response.size_bytes = body.len();
+            let response = ([(header::CONTENT_TYPE, content_type)], body).into_response();
+            (response, Ok(llm_response))
+        }
+    };
+
+    record_request_telemetry(
+        state,
+        ctx,
+        crate::telemetry::RequestStatus::Failed,
+        Some(format!("[CHAOS] {:?}", fault)),
+    );
+    if let Some(fid) = flow_id {
+        match outcome {
+            Ok(llm_response) => {
+                state
+                    .flow_monitor
+                    .complete_flow(fid, Some(llm_response))
+                    .await
+            }
+            Err(error) => state.flow_monitor.fail_flow(fid, error).await,
+        }
+        state.flow_monitor.add_tag(fid, CHAOS_TAG.to_string()).await;
+    }
+    Some(response)
+}
+
+/// 构造故障注入的错误响应（按端点格式）
+fn chaos_error_response(
+    status: StatusCode,
+    error_type: &str,
+    message: &str,
+    anthropic: bool,
+) -> Response {
+    let body = if anthropic {
+        serde_json::json!({
+            "type": "error",
+            "error": {"type": error_type, "message": message}
+        })
+    } else {
+        serde_json::json!({"error": {
+            "message": message,
+            "type": error_type,
+            "code": "chaos_injected"
+        }})
+    };
+    (status, Json(body)).into_response()
+}
+
+/// 构造断流前发送的 SSE 事件（按端点格式）
+fn chaos_stream_events(model: &str, chunks: usize, anthropic: bool) -> Vec<String> {
+    let mut events = Vec::with_capacity(chunks + 2);
+    if anthropic {
+        let start = serde_json::json!({
+            "type": "message_start",
+            "message": {
+                "id": "msg_chaos",
+                "type": "message",
+                "role": "assistant",
+                "model": model,
+                "content": []
+            }
+        });
+        let block_start = serde_json::json!({
+            "type": "content_block_start",
+            "index": 0,
+            "content_block": {"type": "text", "text": ""}
+        });
+        events.push(format!("event: message_start\ndata: {}\n\n", start));
+        events.push(format!(
+            "event: content_block_start\ndata: {}\n\n",
+            block_start
+        ));
+        for i in 0..chunks {
+            let delta = serde_json::json!({
+                "type": "content_block_delta",
+                "index": 0,
+                "delta": {"type": "text_delta", "text": format!("chunk {} ", i)}
+            });
+            events.push(format!("event: content_block_delta\ndata: {}\n\n", delta));
+        }
+    } else {
+        for i in 0..chunks {
+            let chunk = serde_json::json!({
+                "id": "chatcmpl-chaos",
+                "object": "chat.completion.chunk",
+                "model": model,
+                "choices": [{
+                    "index": 0,
+                    "delta": {"content": format!("chunk {} ", i)},
+                    "finish_reason": null
+                }]
+            });
+            events.push(format!("data: {}\n\n", chunk));
+        }
+    }
+    events
+}
+
+/// 按全局配置和 `x-mock` 请求头判断请求是否使用离线 Mock 模式，使用时返回 Mock 配置
+async fn mock_config_for(state: &AppState, headers: &HeaderMap) -> Option<MockConfig> {
+    let config = state.flow_monitor.mock_config().await;
+    let enabled = headers
+        .get(MOCK_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map_or(config.enabled, is_truthy);
+    enabled.then_some(config)
+}
+
+/// 离线 Mock 模式：用捕获的 Flow 响应请求，不选择凭证也不调用上游
+///
+/// 流式请求按保存的 chunk 及原始间隔重新推送，非流式请求返回保存的响应体。
+/// 没有可用的 Flow 时返回 404。
+async fn serve_mock_response(
+    state: &AppState,
+    ctx: &RequestContext,
+    config: &MockConfig,
+    llm_request: &LLMRequest,
+    streaming: bool,
+    anthropic: bool,
+) -> Response {
+    let Some(found) = state
+        .flow_monitor
+        .find_mock_match(llm_request, streaming)
+        .await
+    else {
+        state.logs.write().await.add(
+            "warn",
+            &format!(
+                "[MOCK] request_id={} model={} 没有匹配的 Flow",
+                ctx.request_id, llm_request.model
+            ),
+        );
+        let message = "[MOCK] 没有匹配的已捕获 Flow";
+        let body = if anthropic {
+            serde_json::json!({
+                "type": "error",
+                "error": {"type": "not_found_error", "message": message}
+            })
+        } else {
+            serde_json::json!({"error": {
+                "message": message,
+                "type": "not_found_error",
+                "code": "mock_not_found"
+            }})
+        };
+        return (StatusCode::NOT_FOUND, Json(body)).into_response();
+    };
+    state.logs.write().await.add(
+        "info",
+        &format!(
+            "[MOCK] request_id={} model={} flow_id={} match={} stream={}",
+            ctx.request_id,
+            llm_request.model,
+            found.flow.id,
+            found.kind.as_str(),
+            streaming
+        ),
+    );
+
+    let mut response = if streaming {
+        let events = crate::flow_monitor::mock::stream_events(&found.flow, config);
+        let stream = futures::stream::iter(events).then(|event| async move {
+            if !event.delay.is_zero() {
+                tokio::time::sleep(event.delay).await;
+            }
+            Ok::<_, std::io::Error>(event.text)
+        });
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(Body::from_stream(stream))
+            .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+    } else {
+        let stored = found.flow.response.as_ref();
+        let status = stored
+            .and_then(|r| StatusCode::from_u16(r.status_code).ok())
+            .unwrap_or(StatusCode::OK);
+        let body = stored.map(|r| r.body.clone()).unwrap_or_default();
+        (status, Json(body)).into_response()
+    };
+    if let Ok(value) = header::HeaderValue::from_str(&found.flow.id) {
+        response.headers_mut().insert(MOCK_FLOW_ID_HEADER, value);
+    }
+    response.headers_mut().insert(
+        MOCK_MATCH_HEADER,
+        header::HeaderValue::from_static(found.kind.as_str()),
+    );
+    response
+}
+
+/// 执行 Flow 插件的响应钩子
+///
+/// 在副本上执行，响应被修改时返回修改后的响应；插件出错时保留原始响应。
+async fn apply_response_plugins(
+    state: &AppState,
+    flow_id: &str,
+    llm_response: &LLMResponse,
+) -> Option<LLMResponse> {
+    let mut response = llm_response.clone();
+    match state.processor.flow_plugins.run_on_response(&mut response) {
+        Ok(true) => Some(response),
+        Ok(false) => None,
+        Err(e) => {
+            state.logs.write().await.add(
+                "warn",
+                &format!(
+                    "[PLUGIN] 响应钩子失败，保留原始响应: flow_id={}, {}",
+                    flow_id, e
+                ),
+            );
+            None
+        }
+    }
+}
+
 /// 检查是否需要拦截响应
 ///
 /// **Validates: Requirements 2.1, 2.5**
 ///
 /// 如果拦截器启用且响应匹配拦截规则，则拦截响应并等待用户操作。
-/// 返回修改后的响应（如果有）或 None。
+/// 返回修改后的响应（如果有）或 None。Flow 插件的响应钩子也在这里执行，
+/// 因此仅对启用了 Flow 捕获的请求生效。
 async fn check_response_intercept(
     state: &AppState,
     flow_id: &str,
@@ -478,6 +1136,13 @@ async fn check_response_intercept(
     llm_request: &LLMRequest,
     flow_metadata: &FlowMetadata,
 ) -> Option<LLMResponse> {
+    // 在等待响应拦截之前记录首字节时间，避免人工处理时间计入上游耗时
+    state.flow_monitor.mark_response_start(flow_id).await;
+
+    // 先执行 Flow 插件的响应钩子，拦截器看到的是变换后的响应
+    let plugged = apply_response_plugins(state, flow_id, llm_response).await;
+    let llm_response = plugged.as_ref().unwrap_or(llm_response);
+
     // 创建临时 Flow 用于拦截检查
     let mut temp_flow = LLMFlow::new(
         flow_id.to_string(),
@@ -493,7 +1158,7 @@ async fn check_response_intercept(
         .should_intercept(&temp_flow, &InterceptType::Response)
         .await
     {
-        return None;
+        return plugged;
     }
 
     state.logs.write().await.add(
@@ -524,7 +1189,7 @@ async fn check_response_intercept(
             if let Some(crate::flow_monitor::ModifiedData::Response(resp)) = modified {
                 Some(resp)
             } else {
-                None
+                plugged
             }
         }
         InterceptAction::Cancel | InterceptAction::Timeout(_) => {
@@ -532,7 +1197,7 @@ async fn check_response_intercept(
                 "warn",
                 &format!("[INTERCEPT] 响应处理被取消或超时: flow_id={}", flow_id),
             );
-            None
+            plugged
         }
     }
 }
@@ -542,10 +1207,12 @@ async fn check_response_intercept(
 // ============================================================================
 
 /// OpenAI 格式的 API key 验证
+///
+/// 验证通过时返回调用方身份
 pub async fn verify_api_key(
     headers: &HeaderMap,
-    expected_key: &str,
-) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    api_keys: &ApiKeyStore,
+) -> Result<ApiKeyIdentity, (StatusCode, Json<serde_json::Value>)> {
     let auth = headers
         .get("authorization")
         .or_else(|| headers.get("x-api-key"))
@@ -562,21 +1229,21 @@ pub async fn verify_api_key(
         }
     };
 
-    if key != expected_key {
-        return Err((
+    api_keys.resolve(key).ok_or_else(|| {
+        (
             StatusCode::UNAUTHORIZED,
             Json(serde_json::json!({"error": {"message": "Invalid API key"}})),
-        ));
-    }
-
-    Ok(())
+        )
+    })
 }
 
 /// Anthropic 格式的 API key 验证
+///
+/// 验证通过时返回调用方身份
 pub async fn verify_api_key_anthropic(
     headers: &HeaderMap,
-    expected_key: &str,
-) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    api_keys: &ApiKeyStore,
+) -> Result<ApiKeyIdentity, (StatusCode, Json<serde_json::Value>)> {
     let auth = headers
         .get("x-api-key")
         .or_else(|| headers.get("authorization"))
@@ -599,8 +1266,8 @@ pub async fn verify_api_key_anthropic(
         }
     };
 
-    if key != expected_key {
-        return Err((
+    api_keys.resolve(key).ok_or_else(|| {
+        (
             StatusCode::UNAUTHORIZED,
             Json(serde_json::json!({
                 "type": "error",
@@ -609,34 +1276,644 @@ pub async fn verify_api_key_anthropic(
                     "message": "Invalid API key"
                 }
             })),
+        )
+    })
+}
+
+/// 检查调用方的访问范围（OpenAI 格式的 403 错误）
+///
+/// `provider` 为 `None` 时只检查模型
+pub fn check_api_key_scope(
+    identity: &ApiKeyIdentity,
+    model: &str,
+    provider: Option<&str>,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    match scope_violation(identity, model, provider) {
+        Some(message) => Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": {"message": message, "type": "permission_error"}
+            })),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// 检查调用方的访问范围（Anthropic 格式的 403 错误）
+pub fn check_api_key_scope_anthropic(
+    identity: &ApiKeyIdentity,
+    model: &str,
+    provider: Option<&str>,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    match scope_violation(identity, model, provider) {
+        Some(message) => Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "type": "error",
+                "error": {"type": "permission_error", "message": message}
+            })),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn scope_violation(
+    identity: &ApiKeyIdentity,
+    model: &str,
+    provider: Option<&str>,
+) -> Option<String> {
+    if !identity.allows_model(model) {
+        return Some(format!(
+            "API key '{}' is not allowed to access model '{}'",
+            identity.label, model
         ));
     }
+    match provider {
+        Some(provider) if !identity.allows_provider(provider) => Some(format!(
+            "API key '{}' is not allowed to use provider '{}'",
+            identity.label, provider
+        )),
+        _ => None,
+    }
+}
 
-    Ok(())
+/// 影子请求调用器
+///
+/// 从凭证池选择影子 Provider 的凭证，按主请求路径以非流式 Chat Completions
+/// 或 Anthropic Messages 请求调用。
+struct PoolShadowCaller {
+    state: AppState,
+}
+
+#[async_trait::async_trait]
+impl ShadowCaller for PoolShadowCaller {
+    async fn call(
+        &self,
+        provider: ProviderType,
+        path: &str,
+        mut request: serde_json::Value,
+    ) -> Result<ProviderCallResult, ProviderCallError> {
+        let state = &self.state;
+        if let Some(obj) = request.as_object_mut() {
+            obj.insert("stream".to_string(), serde_json::json!(false));
+        }
+        let invalid =
+            |e: serde_json::Error| ProviderCallError::fatal(format!("无效的影子请求: {}", e), None);
+        let model = request
+            .get("model")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        let db = state
+            .db
+            .as_ref()
+            .ok_or_else(|| ProviderCallError::fatal("Database not available", None))?;
+        let credential = state
+            .pool_service
+            .select_credential(db, &provider.to_string(), Some(&model))
+            .map_err(|e| ProviderCallError::fatal(e, None))?
+            .ok_or_else(|| {
+                ProviderCallError::fatal(format!("没有可用的 {} 凭证", provider), None)
+            })?;
+
+        let start = std::time::Instant::now();
+        let response = if path == "/v1/messages" {
+            let request: AnthropicMessagesRequest =
+                serde_json::from_value(request).map_err(invalid)?;
+            call_provider_anthropic(state, &credential, &request, None).await
+        } else {
+            let request: ChatCompletionRequest =
+                serde_json::from_value(request).map_err(invalid)?;
+            call_provider_openai(state, &credential, &request, None, false).await
+        };
+        let status_code = response.status().as_u16();
+        let rate_limit = RateLimitHeaders::parse(|name| {
+            response.headers().get(name).and_then(|v| v.to_str().ok())
+        });
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .map_err(|e| ProviderCallError::fatal(e.to_string(), Some(status_code)))?;
+        let body = serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| serde_json::Value::String(String::from_utf8_lossy(&bytes).into()));
+
+        if !(200..300).contains(&status_code) {
+            return Err(
+                ProviderCallError::fatal(body.to_string(), Some(status_code))
+                    .with_rate_limit(rate_limit),
+            );
+        }
+        Ok(ProviderCallResult {
+            response: body,
+            status_code,
+            latency_ms: start.elapsed().as_millis() as u64,
+            credential_id: Some(credential.uuid),
+            rate_limit,
+        })
+    }
+}
+
+/// 主请求成功后按影子配置把请求镜像到候选 Provider/模型
+///
+/// 镜像在后台执行，影子路径的任何失败都不影响主响应。被采样的非流式响应会先读出
+/// 响应体，使影子差异与实际的主响应内容对比。
+async fn mirror_to_shadow<T: serde::Serialize>(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    path: &str,
+    flow_id: &str,
+    request: &T,
+    response: Response,
+) -> Response {
+    if !state.shadow.is_enabled() || !state.shadow.should_sample() {
+        return response;
+    }
+
+    let response = if is_event_stream(&response) {
+        response
+    } else {
+        let (parts, body) = response.into_parts();
+        let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::warn!("[SHADOW] 读取主响应体失败，跳过镜像: {}", e);
+                return (
+                    StatusCode::BAD_GATEWAY,
+                    Json(serde_json::json!({"error": {
+                        "message": format!("Failed to read response body: {}", e)
+                    }})),
+                )
+                    .into_response();
+            }
+        };
+        if let Ok(body) = serde_json::from_slice::<serde_json::Value>(&bytes) {
+            ctx.set_metadata(PRIMARY_RESPONSE_KEY, body);
+        }
+        Response::from_parts(parts, Body::from(bytes))
+    };
+    ctx.set_metadata(REQUEST_PATH_KEY, serde_json::json!(path));
+
+    let step = ShadowStep::clone(&state.shadow).with_caller(Arc::new(PoolShadowCaller {
+        state: state.clone(),
+    }));
+    step.mirror(
+        ctx,
+        flow_id.to_string(),
+        serde_json::to_value(request).unwrap_or_default(),
+    );
+    response
+}
+
+/// 请求体 JSON 及客户端原始请求中出现的顶层字段
+///
+/// 类型化请求中缺失的字段和显式 `null` 都会变成 `None`，填充模型默认参数时需要区分二者，
+/// 因此额外保留原始 JSON 中出现过的字段名（包括值为 `null` 的字段）。
+pub struct JsonWithFields<T>(pub T, pub HashSet<String>);
+
+#[async_trait::async_trait]
+impl<T, S> FromRequest<S> for JsonWithFields<T>
+where
+    T: serde::de::DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<serde_json::Value>::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let fields = value
+            .as_object()
+            .map(|obj| obj.keys().cloned().collect())
+            .unwrap_or_default();
+        let request = serde_json::from_value(value).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Failed to deserialize the JSON body into the target type: {e}"),
+            )
+                .into_response()
+        })?;
+        Ok(Self(request, fields))
+    }
+}
+
+/// 将推理设置转换为实际调用的 Provider 的原生参数（`reasoning_effort` / `thinking`）
+///
+/// 在选定凭证之后、构建 Flow 元数据之前调用，转换结果记录到 Flow 的注入参数中。
+async fn apply_reasoning_effort<T>(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    provider: ProviderType,
+    request: &mut T,
+) where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    let mut payload = serde_json::to_value(&*request).unwrap_or_default();
+    let translated = state
+        .processor
+        .apply_reasoning_effort(ctx, provider, &mut payload)
+        .await;
+    if translated.is_empty() {
+        return;
+    }
+    state.logs.write().await.add(
+        "info",
+        &format!(
+            "[REASONING] request_id={} provider={} translated_params={:?}",
+            ctx.request_id,
+            provider,
+            translated.iter().map(|a| &a.param).collect::<Vec<_>>()
+        ),
+    );
+    if let Ok(updated) = serde_json::from_value(payload) {
+        *request = updated;
+    }
+}
+
+/// 为客户端未发送的参数填充模型默认值（客户端显式发送的 0 或 null 保持不变）
+///
+/// 默认值按别名解析后的模型查找，填充后的请求替换原请求。
+async fn apply_model_defaults<T>(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    request: &mut T,
+    client_fields: &HashSet<String>,
+) where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    let mut payload = serde_json::to_value(&*request).unwrap_or_default();
+    let defaulted = state
+        .processor
+        .apply_model_defaults(ctx, &mut payload, client_fields)
+        .await;
+    if defaulted.is_empty() {
+        return;
+    }
+    state.logs.write().await.add(
+        "info",
+        &format!(
+            "[DEFAULTS] request_id={} model={} defaulted_params={:?}",
+            ctx.request_id,
+            ctx.resolved_model,
+            defaulted.iter().map(|a| &a.param).collect::<Vec<_>>()
+        ),
+    );
+    if let Ok(updated) = serde_json::from_value(payload) {
+        *request = updated;
+    }
+}
+
+/// 判断客户端是否要求把流式响应缓冲为非流式 JSON
+///
+/// 通过 `x-accumulate-stream` 请求头或 `accumulate_stream` 查询参数开启。
+fn wants_accumulated_stream(headers: &HeaderMap, query: Option<&str>) -> bool {
+    let from_header = headers
+        .get(ACCUMULATE_STREAM_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(is_truthy);
+    let from_query = query.is_some_and(|query| {
+        query.split('&').any(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, "true"));
+            key == ACCUMULATE_STREAM_QUERY && is_truthy(value)
+        })
+    });
+    from_header || from_query
+}
+
+/// 判断客户端是否要求按字面模型名转发（`x-no-remap` 请求头）
+fn wants_no_remap(headers: &HeaderMap) -> bool {
+    headers
+        .get(NO_REMAP_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(is_truthy)
+}
+
+/// 根据失败的 Provider 响应构建 Flow 错误
+///
+/// 错误响应体由 `provider_calls` 构建，都是小型 JSON，读取后按原状态码和响应头重建响应。
+async fn flow_error_from_response(response: Response) -> (FlowError, Response) {
+    let status = response.status();
+    let (parts, body) = response.into_parts();
+    let retry_after = parts
+        .headers
+        .get(header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_retry_after);
+    let bytes = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .unwrap_or_default();
+
+    let text = String::from_utf8_lossy(&bytes);
+    let error = FlowError::new(
+        FlowErrorType::from_status_code(status.as_u16()),
+        "Request failed",
+    )
+    .with_status_code(status.as_u16())
+    .with_retry_after(retry_after)
+    .with_provider_body(&text);
+
+    (error, Response::from_parts(parts, Body::from(bytes)))
+}
+
+/// 响应是否为 SSE 流
+fn is_event_stream(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"))
+}
+
+/// 对返回客户端的响应应用内容过滤
+///
+/// 仅处理成功响应：JSON 响应体整体过滤后重建，SSE 响应逐事件过滤。
+/// 命中规则时将结果记录到对应 Flow（流式响应在流结束时记录）。
+async fn apply_content_filter(
+    state: &AppState,
+    flow_id: Option<String>,
+    response: Response,
+) -> Response {
+    let Some(filter) = state.processor.content_filter.filter() else {
+        return response;
+    };
+    if !response.status().is_success() {
+        return response;
+    }
+
+    let event_stream = is_event_stream(&response);
+    let (mut parts, body) = response.into_parts();
+
+    if event_stream {
+        parts.headers.remove(header::CONTENT_LENGTH);
+        let stream = content_filter_event_stream(
+            body,
+            SseContentFilter::new(filter),
+            state.clone(),
+            flow_id,
+        );
+        return Response::from_parts(parts, Body::from_stream(stream));
+    }
+
+    let bytes = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .unwrap_or_default();
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    let Some(outcome) = filter.filter_response(&mut value) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    tracing::info!(
+        "[FILTER] 响应命中内容过滤规则 {:?}: action={:?}",
+        outcome.rules,
+        outcome.action
+    );
+    if let Some(ref fid) = flow_id {
+        state.flow_monitor.set_content_filter(fid, outcome).await;
+    }
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Json(value).into_response().into_body())
+}
+
+/// 按 `Idempotency-Key` 请求头查找已缓存的响应
+///
+/// 仅对非流式请求生效，键按调用方 Key 的摘要隔离。命中缓存时返回
+/// `Err(重放的响应)` 并记录一个 `idempotent_replay` Flow；同一个键携带不同请求体时
+/// 返回 `Err(422)`；首次请求返回守卫，响应经 [`finish_idempotent_request`] 写入缓存。
+async fn begin_idempotent_request(
+    state: &AppState,
+    headers: &HeaderMap,
+    identity: &ApiKeyIdentity,
+    path: &str,
+    streaming: bool,
+    request: &impl serde::Serialize,
+) -> Result<Option<IdempotencyGuard>, Response> {
+    let Some(key) = IdempotencyCache::key_from_headers(headers) else {
+        return Ok(None);
+    };
+    if streaming {
+        return Ok(None);
+    }
+
+    let scoped_key = IdempotencyCache::scoped_key(&identity.key_digest, path, key);
+    let body = serde_json::to_string(request).unwrap_or_default();
+    let normalizer = state.flow_monitor.content_normalizer().await;
+    let fingerprint = IdempotencyCache::fingerprint(&normalizer, &body);
+    match state.idempotency.begin(scoped_key, fingerprint).await {
+        IdempotencyStart::Lead(guard) => Ok(Some(guard)),
+        IdempotencyStart::Mismatch => {
+            tracing::warn!(
+                "[IDEMPOTENCY] 幂等键已用于不同的请求体: path={}, key={}",
+                path,
+                key
+            );
+            Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(serde_json::json!({"error": {
+                    "message": "Idempotency-Key was already used with a different request body",
+                    "type": "idempotency_key_mismatch"
+                }})),
+            )
+                .into_response())
+        }
+        IdempotencyStart::Replay(stored) => {
+            let replay_flow_id = match stored.flow_id.as_deref() {
+                Some(original) => state.flow_monitor.record_idempotent_replay(original).await,
+                None => None,
+            };
+            tracing::info!(
+                "[IDEMPOTENCY] 幂等键命中缓存，直接返回首次请求的响应: path={}, key={}, original_flow={:?}, replay_flow={:?}",
+                path,
+                key,
+                stored.flow_id,
+                replay_flow_id
+            );
+            let mut response = stored.to_response();
+            response.headers_mut().insert(
+                IDEMPOTENT_REPLAY_HEADER,
+                header::HeaderValue::from_static("true"),
+            );
+            Err(response)
+        }
+    }
+}
+
+/// 缓存首次请求的成功响应，失败或流式响应不缓存
+///
+/// 读取响应体失败时返回 502，且不缓存。
+async fn finish_idempotent_request(
+    guard: Option<IdempotencyGuard>,
+    flow_id: Option<String>,
+    response: Response,
+) -> Response {
+    let Some(guard) = guard else {
+        return response;
+    };
+    if !response.status().is_success() || is_event_stream(&response) {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("[IDEMPOTENCY] 读取响应体失败，不缓存: {}", e);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(serde_json::json!({"error": {
+                    "message": format!("Failed to read response body: {}", e)
+                }})),
+            )
+                .into_response();
+        }
+    };
+    if bytes.len() <= MAX_CACHED_BODY_BYTES {
+        guard.complete(StoredResponse {
+            status: parts.status,
+            headers: parts.headers.clone(),
+            body: bytes.clone(),
+            flow_id,
+        });
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// 逐事件过滤 SSE 响应体，流结束时记录过滤结果
+fn content_filter_event_stream(
+    body: Body,
+    mut filter: SseContentFilter,
+    state: AppState,
+    flow_id: Option<String>,
+) -> impl Stream<Item = std::io::Result<String>> {
+    async_stream::try_stream! {
+        let mut data = body.into_data_stream();
+        while let Some(chunk) = data.next().await {
+            let chunk = chunk.map_err(|e| std::io::Error::other(e.to_string()))?;
+            let filtered = filter.push(&chunk);
+            if !filtered.is_empty() {
+                yield filtered;
+            }
+        }
+        let rest = filter.finish();
+        if !rest.is_empty() {
+            yield rest;
+        }
+
+        if let Some(outcome) = filter.outcome().cloned() {
+            tracing::info!(
+                "[FILTER] 流式响应命中内容过滤规则 {:?}: action={:?}",
+                outcome.rules,
+                outcome.action
+            );
+            if let Some(ref fid) = flow_id {
+                state.flow_monitor.set_content_filter(fid, outcome).await;
+            }
+        }
+    }
 }
 
 pub async fn chat_completions(
     State(state): State<AppState>,
+    RawQuery(query): RawQuery,
     headers: HeaderMap,
-    Json(mut request): Json<ChatCompletionRequest>,
+    JsonWithFields(request, client_fields): JsonWithFields<ChatCompletionRequest>,
 ) -> Response {
-    if let Err(e) = verify_api_key(&headers, &state.api_key).await {
-        state
-            .logs
-            .write()
-            .await
-            .add("warn", "Unauthorized request to /v1/chat/completions");
-        return e.into_response();
+    handle_chat_completions(
+        state,
+        query,
+        headers,
+        request,
+        client_fields,
+        "/v1/chat/completions",
+    )
+    .await
+}
+
+/// 处理 Chat Completions 格式的请求
+///
+/// `path` 为客户端请求的端点，用于日志和 Flow 类型识别（`/v1/responses` 请求转换后也由此处理）。
+/// 响应在返回前经过内容过滤，携带幂等键的非流式请求按键缓存响应。
+pub(crate) async fn handle_chat_completions(
+    state: AppState,
+    query: Option<String>,
+    headers: HeaderMap,
+    request: ChatCompletionRequest,
+    client_fields: HashSet<String>,
+    path: &str,
+) -> Response {
+    let identity = match verify_api_key(&headers, &state.api_keys).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            state
+                .logs
+                .write()
+                .await
+                .add("warn", &format!("Unauthorized request to {}", path));
+            return e.into_response();
+        }
+    };
+
+    let streaming = request.stream && !wants_accumulated_stream(&headers, query.as_deref());
+    let guard = match begin_idempotent_request(
+        &state, &headers, &identity, path, streaming, &request,
+    )
+    .await
+    {
+        Ok(guard) => guard,
+        Err(replay) => return replay,
+    };
+
+    let mut flow_id = None;
+    let response = process_chat_completions(
+        state.clone(),
+        identity,
+        query,
+        headers,
+        request,
+        client_fields,
+        path,
+        &mut flow_id,
+    )
+    .await;
+    let response = apply_content_filter(&state, flow_id.clone(), response).await;
+    finish_idempotent_request(guard, flow_id, response).await
+}
+
+/// Chat Completions 请求的实际处理流程
+///
+/// `identity` 为已验证的调用方身份。开始捕获 Flow 后将其 ID 写入 `flow_slot`，供内容过滤记录结果。
+#[allow(clippy::too_many_arguments)]
+async fn process_chat_completions(
+    state: AppState,
+    identity: ApiKeyIdentity,
+    query: Option<String>,
+    headers: HeaderMap,
+    mut request: ChatCompletionRequest,
+    client_fields: HashSet<String>,
+    path: &str,
+    flow_slot: &mut Option<String>,
+) -> Response {
+    // 客户端要求缓冲时，上游以流式请求，整体返回非流式 JSON
+    let accumulate_stream = wants_accumulated_stream(&headers, query.as_deref());
+    if accumulate_stream {
+        request.stream = true;
+        request
+            .stream_options
+            .get_or_insert_with(|| serde_json::json!({"include_usage": true}));
     }
 
     // 创建请求上下文
-    let mut ctx = RequestContext::new(request.model.clone()).with_stream(request.stream);
+    let mut ctx = RequestContext::new(request.model.clone())
+        .with_stream(request.stream)
+        .with_no_remap(wants_no_remap(&headers));
+    ctx.set_metadata(API_KEY_LABEL_KEY, serde_json::json!(identity.label));
 
     state.logs.write().await.add(
         "info",
         &format!(
-            "POST /v1/chat/completions request_id={} model={} stream={}",
-            ctx.request_id, request.model, request.stream
+            "POST {} request_id={} model={} stream={} accumulate_stream={}",
+            path, ctx.request_id, request.model, request.stream, accumulate_stream
         ),
     );
 
@@ -655,6 +1932,73 @@ pub async fn chat_completions(
         );
     }
 
+    // 按别名规则截断过长的消息历史（在降级判断之前）
+    {
+        let mut payload = serde_json::to_value(&request).unwrap_or_default();
+        if let Some(truncation) = state
+            .processor
+            .apply_history_truncation(&mut ctx, &mut payload)
+            .await
+        {
+            state.logs.write().await.add(
+                "info",
+                &format!(
+                    "[TRUNCATE] request_id={} alias={} messages={} removed={}",
+                    ctx.request_id,
+                    ctx.original_model,
+                    truncation.original_messages,
+                    truncation.removed_messages
+                ),
+            );
+            if let Ok(updated) = serde_json::from_value(payload) {
+                request = updated;
+            }
+        }
+    }
+
+    // 提示词过大时按别名规则降级模型（按降级后的模型重新选择 Provider）
+    let provider = {
+        let payload = serde_json::to_value(&request).unwrap_or_default();
+        match state
+            .processor
+            .apply_size_downgrade(&mut ctx, &payload)
+            .await
+        {
+            Some(downgrade) => {
+                request.model = downgrade.to.clone();
+                state.logs.write().await.add(
+                    "info",
+                    &format!(
+                        "[DOWNGRADE] request_id={} alias={} {} -> {} estimated_tokens={} threshold={}",
+                        ctx.request_id,
+                        ctx.original_model,
+                        downgrade.from,
+                        downgrade.to,
+                        downgrade.estimated_tokens,
+                        downgrade.threshold_tokens
+                    ),
+                );
+                ctx.provider.unwrap_or(provider)
+            }
+            None => provider,
+        }
+    };
+
+    // 检查调用方是否有权访问解析后的模型
+    if let Err(e) = check_api_key_scope(&identity, &ctx.resolved_model, None) {
+        state.logs.write().await.add(
+            "warn",
+            &format!(
+                "[AUTH] request_id={} api_key={} 无权访问模型 {}",
+                ctx.request_id, identity.label, ctx.resolved_model
+            ),
+        );
+        return e.into_response();
+    }
+
+    // 客户端未发送的参数使用模型默认值（在别名解析和降级之后、参数注入之前）
+    apply_model_defaults(&state, &mut ctx, &mut request, &client_fields).await;
+
     // 应用参数注入
     let injection_enabled = *state.injection_enabled.read().await;
     if injection_enabled {
@@ -676,6 +2020,41 @@ pub async fn chat_completions(
         }
     }
 
+    // 应用别名参数约束（钳制超出范围的参数，而不是拒绝）
+    {
+        let mut payload = serde_json::to_value(&request).unwrap_or_default();
+        let adjustments = state
+            .processor
+            .apply_param_constraints(&mut ctx, &mut payload)
+            .await;
+        if !adjustments.is_empty() {
+            state.logs.write().await.add(
+                "info",
+                &format!(
+                    "[CONSTRAINT] request_id={} alias={} adjusted_params={:?}",
+                    ctx.request_id,
+                    ctx.original_model,
+                    adjustments.iter().map(|a| &a.param).collect::<Vec<_>>()
+                ),
+            );
+            if let Ok(updated) = serde_json::from_value(payload) {
+                request = updated;
+            }
+        }
+    }
+
+    // 离线 Mock 模式：用捕获的 Flow 响应，不选择凭证也不调用上游
+    if let Some(mock_config) = mock_config_for(&state, &headers).await {
+        let mut llm_request =
+            build_llm_request_from_openai(&request, path, HashMap::new(), ctx.timestamp);
+        if let Err(response) = apply_request_plugins(&state, &mut llm_request, &mut request).await {
+            return response;
+        }
+        let streaming = request.stream && !accumulate_stream;
+        return serve_mock_response(&state, &ctx, &mock_config, &llm_request, streaming, false)
+            .await;
+    }
+
     // 根据客户端类型选择 Provider
     // **Validates: Requirements 3.1, 3.3, 3.4**
     let (selected_provider, client_type) = select_provider_for_client(&headers, &state).await;
@@ -733,32 +2112,33 @@ pub async fn chat_completions(
     if final_provider_type != provider {
         ctx.set_provider(final_provider_type);
     }
+    if let Err(e) = check_api_key_scope(&identity, &ctx.resolved_model, Some(&final_provider)) {
+        state.logs.write().await.add(
+            "warn",
+            &format!(
+                "[AUTH] request_id={} api_key={} 无权使用 Provider {}",
+                ctx.request_id, identity.label, final_provider
+            ),
+        );
+        return e.into_response();
+    }
 
-    // 优先按最终选择的 provider 选择凭证；如果没有可用凭证，再回退到默认 provider。
-    let credential = match &state.db {
-        Some(db) => state
-            .pool_service
-            .select_credential(db, &final_provider, Some(&request.model))
-            .ok()
-            .flatten()
-            .or_else(|| {
-                if final_provider != default_provider {
-                    state
-                        .pool_service
-                        .select_credential(db, &default_provider, Some(&request.model))
-                        .ok()
-                        .flatten()
-                } else {
-                    None
-                }
-            }),
-        None => None,
-    };
+    let session_key = request_session_key(&state, &headers, &request);
+    let credential = select_pool_credential(
+        &state,
+        &mut ctx,
+        &identity,
+        &final_provider,
+        &default_provider,
+        &request.model,
+        session_key.as_deref(),
+    );
 
     // 如果找到凭证池中的凭证，使用它
     if let Some(cred) = credential {
         ctx.set_provider(cred.provider_type);
         ctx.set_credential_id(cred.uuid.clone());
+        apply_reasoning_effort(&state, &mut ctx, cred.provider_type, &mut request).await;
 
         state.logs.write().await.add(
             "info",
@@ -773,18 +2153,24 @@ pub async fn chat_completions(
         );
 
         // 启动 Flow 捕获
-        let llm_request = build_llm_request_from_openai(&request, "/v1/chat/completions", &headers);
+        let captured_headers = state.flow_monitor.capture_request_headers(&headers).await;
+        let mut llm_request =
+            build_llm_request_from_openai(&request, path, captured_headers, ctx.timestamp);
+        if let Err(response) = apply_request_plugins(&state, &mut llm_request, &mut request).await {
+            return response;
+        }
         let flow_metadata = build_flow_metadata(
             cred.provider_type,
             Some(&cred.uuid),
             cred.name.as_deref(),
             &headers,
-            &ctx.request_id,
-        );
-        let flow_id = state
-            .flow_monitor
-            .start_flow(llm_request.clone(), flow_metadata.clone())
-            .await;
+            &ctx,
+        );
+        let flow_id = match start_flow_capture(&state, &llm_request, &flow_metadata).await {
+            Ok(flow_id) => flow_id,
+            Err(response) => return response,
+        };
+        flow_slot.clone_from(&flow_id);
 
         // 检查是否需要拦截请求
         // **Validates: Requirements 2.1, 2.3, 2.5**
@@ -814,7 +2200,30 @@ pub async fn chat_completions(
             }
         }
 
-        let response = call_provider_openai(&state, &cred, &request, flow_id.as_deref()).await;
+        let deadline = request_deadline(&state, &headers, &ctx, request.stream);
+        mark_upstream_dispatch(&state, flow_id.as_deref()).await;
+        if let Some(response) = inject_chaos(
+            &state,
+            &mut ctx,
+            flow_id.as_deref(),
+            StreamingFormat::OpenAiSse,
+        )
+        .await
+        {
+            return response;
+        }
+        let call = call_provider_openai(
+            &state,
+            &cred,
+            &request,
+            flow_id.as_deref(),
+            accumulate_stream,
+        );
+        let response =
+            match with_request_deadline(&state, &ctx, flow_id.as_deref(), deadline, call).await {
+                Ok(response) => response,
+                Err(response) => return response,
+            };
 
         // 记录请求统计
         let is_success = response.status().is_success();
@@ -915,13 +2324,11 @@ pub async fn chat_completions(
                     .flow_monitor
                     .complete_flow(&fid, Some(llm_response))
                     .await;
+                return mirror_to_shadow(&state, &mut ctx, path, &fid, &request, response).await;
             } else {
-                let error = FlowError::new(
-                    FlowErrorType::from_status_code(response.status().as_u16()),
-                    "Request failed",
-                )
-                .with_status_code(response.status().as_u16());
+                let (error, rebuilt) = flow_error_from_response(response).await;
                 state.flow_monitor.fail_flow(&fid, error).await;
+                return rebuilt;
             }
         }
 
@@ -961,13 +2368,18 @@ pub async fn chat_completions(
     );
 
     // 启动 Flow 捕获（legacy mode）
-    let llm_request = build_llm_request_from_openai(&request, "/v1/chat/completions", &headers);
-    let flow_metadata =
-        build_flow_metadata(final_provider_type, None, None, &headers, &ctx.request_id);
-    let flow_id = state
-        .flow_monitor
-        .start_flow(llm_request.clone(), flow_metadata.clone())
-        .await;
+    let captured_headers = state.flow_monitor.capture_request_headers(&headers).await;
+    let mut llm_request =
+        build_llm_request_from_openai(&request, path, captured_headers, ctx.timestamp);
+    if let Err(response) = apply_request_plugins(&state, &mut llm_request, &mut request).await {
+        return response;
+    }
+    let flow_metadata = build_flow_metadata(final_provider_type, None, None, &headers, &ctx);
+    let flow_id = match start_flow_capture(&state, &llm_request, &flow_metadata).await {
+        Ok(flow_id) => flow_id,
+        Err(response) => return response,
+    };
+    flow_slot.clone_from(&flow_id);
 
     // 检查是否需要拦截请求（legacy mode）
     // **Validates: Requirements 2.1, 2.3, 2.5**
@@ -1025,11 +2437,33 @@ pub async fn chat_completions(
 
     let kiro = state.kiro.read().await;
 
-    match kiro.call_api(&request).await {
+    let deadline = request_deadline(&state, &headers, &ctx, request.stream);
+    mark_upstream_dispatch(&state, flow_id.as_deref()).await;
+    if let Some(response) = inject_chaos(
+        &state,
+        &mut ctx,
+        flow_id.as_deref(),
+        StreamingFormat::OpenAiSse,
+    )
+    .await
+    {
+        return response;
+    }
+    let call = kiro.call_api(&request);
+    let upstream =
+        match with_request_deadline(&state, &ctx, flow_id.as_deref(), deadline, call).await {
+            Ok(upstream) => upstream,
+            Err(response) => return response,
+        };
+    match upstream {
         Ok(resp) => {
+            record_upstream_response(&state, flow_id.as_deref(), &resp).await;
             let status = resp.status();
             if status.is_success() {
-                match resp.text().await {
+                match read_upstream_body(&state, flow_id.as_deref(), resp)
+                    .await
+                    .map(|decoded| decoded.lossy_text())
+                {
                     Ok(body) => {
                         let parsed = parse_cw_response(&body);
                         let has_tool_calls = !parsed.tool_calls.is_empty();
@@ -1192,7 +2626,10 @@ pub async fn chat_completions(
                         );
                         // 标记 Flow 失败
                         if let Some(fid) = &flow_id {
-                            let error = FlowError::new(FlowErrorType::Network, &e.to_string());
+                            let error = FlowError::new(
+                                FlowErrorType::Network,
+                                state.upstream_proxies.describe_error("kiro", &e),
+                            );
                             state.flow_monitor.fail_flow(fid, error).await;
                         }
                         (
@@ -1233,10 +2670,29 @@ pub async fn chat_completions(
                         // 重试请求
                         drop(kiro);
                         let kiro = state.kiro.read().await;
-                        match kiro.call_api(&request).await {
+                        mark_upstream_dispatch(&state, flow_id.as_deref()).await;
+                        let call = kiro.call_api(&request);
+                        let retry = match with_request_deadline(
+                            &state,
+                            &ctx,
+                            flow_id.as_deref(),
+                            deadline,
+                            call,
+                        )
+                        .await
+                        {
+                            Ok(retry) => retry,
+                            Err(response) => return response,
+                        };
+                        match retry {
                             Ok(retry_resp) => {
+                                record_upstream_response(&state, flow_id.as_deref(), &retry_resp)
+                                    .await;
                                 if retry_resp.status().is_success() {
-                                    match retry_resp.text().await {
+                                    match read_upstream_body(&state, flow_id.as_deref(), retry_resp)
+                                        .await
+                                        .map(|decoded| decoded.lossy_text())
+                                    {
                                         Ok(body) => {
                                             let parsed = parse_cw_response(&body);
                                             let has_tool_calls = !parsed.tool_calls.is_empty();
@@ -1359,7 +2815,9 @@ pub async fn chat_completions(
                                             if let Some(fid) = &flow_id {
                                                 let error = FlowError::new(
                                                     FlowErrorType::Network,
-                                                    &e.to_string(),
+                                                    state
+                                                        .upstream_proxies
+                                                        .describe_error("kiro", &e),
                                                 );
                                                 state.flow_monitor.fail_flow(fid, error).await;
                                             }
@@ -1387,8 +2845,10 @@ pub async fn chat_completions(
                             Err(e) => {
                                 // 标记 Flow 失败
                                 if let Some(fid) = &flow_id {
-                                    let error =
-                                        FlowError::new(FlowErrorType::Network, &e.to_string());
+                                    let error = FlowError::new(
+                                        FlowErrorType::Network,
+                                        state.upstream_proxies.describe_error("kiro", e.as_ref()),
+                                    );
                                     state.flow_monitor.fail_flow(fid, error).await;
                                 }
                                 (
@@ -1421,6 +2881,11 @@ pub async fn chat_completions(
                     }
                 }
             } else {
+                let retry_after = resp
+                    .headers()
+                    .get(header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_retry_after);
                 let body = resp.text().await.unwrap_or_default();
                 state.logs.write().await.add(
                     "error",
@@ -1430,7 +2895,9 @@ pub async fn chat_completions(
                 if let Some(fid) = &flow_id {
                     let error =
                         FlowError::new(FlowErrorType::from_status_code(status.as_u16()), &body)
-                            .with_status_code(status.as_u16());
+                            .with_status_code(status.as_u16())
+                            .with_retry_after(retry_after)
+                            .with_provider_body(&body);
                     state.flow_monitor.fail_flow(fid, error).await;
                 }
                 (
@@ -1447,7 +2914,10 @@ pub async fn chat_completions(
                 .add("error", &format!("API call failed: {e}"));
             // 标记 Flow 失败
             if let Some(fid) = &flow_id {
-                let error = FlowError::new(FlowErrorType::Network, &e.to_string());
+                let error = FlowError::new(
+                    FlowErrorType::Network,
+                    state.upstream_proxies.describe_error("kiro", e.as_ref()),
+                );
                 state.flow_monitor.fail_flow(fid, error).await;
             }
             (
@@ -1459,23 +2929,295 @@ pub async fn chat_completions(
     }
 }
 
-pub async fn anthropic_messages(
+/// OpenAI Responses API（`/v1/responses`）
+///
+/// 请求转换为 Chat Completions 格式后复用同一处理流程（路由、注入、Flow 捕获），
+/// 响应再转换回 Responses 格式，流式响应逐块转换为 Responses 事件。错误响应原样返回。
+pub async fn responses(
     State(state): State<AppState>,
+    RawQuery(query): RawQuery,
     headers: HeaderMap,
-    Json(mut request): Json<AnthropicMessagesRequest>,
+    JsonWithFields(request, client_fields): JsonWithFields<ResponsesRequest>,
 ) -> Response {
-    // 使用 Anthropic 格式的认证验证（优先检查 x-api-key）
-    if let Err(e) = verify_api_key_anthropic(&headers, &state.api_key).await {
+    // 代理不保存历史响应，无法按 ID 续接会话
+    if request.previous_response_id.is_some() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": {
+                    "message": "previous_response_id is not supported, send the full conversation in input",
+                    "type": "invalid_request_error",
+                    "param": "previous_response_id",
+                }
+            })),
+        )
+            .into_response();
+    }
+
+    let model = request.model.clone();
+    let response = handle_chat_completions(
+        state,
+        query,
+        headers,
+        convert_responses_to_openai(&request),
+        responses_fields_to_openai(&client_fields),
+        "/v1/responses",
+    )
+    .await;
+    if !response.status().is_success() {
+        return response;
+    }
+
+    let event_stream = is_event_stream(&response);
+    let (mut parts, body) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+
+    if event_stream {
+        let stream = responses_event_stream(body, model);
+        return Response::from_parts(parts, Body::from_stream(stream));
+    }
+
+    let bytes = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .unwrap_or_default();
+    match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(value) => {
+            let converted = convert_openai_to_responses(&value, &model);
+            Response::from_parts(parts, Json(converted).into_response().into_body())
+        }
+        // 无法解析的响应体（如故障注入的畸形响应）原样返回
+        Err(_) => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
+
+/// 将 Chat Completions 流式响应体转换为 Responses 事件流
+fn responses_event_stream(
+    body: Body,
+    model: String,
+) -> impl Stream<Item = std::io::Result<String>> {
+    async_stream::try_stream! {
+        let mut converter = ResponsesStreamConverter::new(&model);
+        let mut data = body.into_data_stream();
+        while let Some(chunk) = data.next().await {
+            let chunk = chunk.map_err(|e| std::io::Error::other(e.to_string()))?;
+            for event in converter.push(&chunk) {
+                yield event;
+            }
+        }
+        for event in converter.finish() {
+            yield event;
+        }
+    }
+}
+
+/// POST /v1/audio/transcriptions、/v1/audio/translations、/v1/files - multipart 上传
+///
+/// 请求体以流的形式透传到 OpenAI 兼容凭证，转发的同时增量捕获各个 part 的摘要，
+/// 不在内存中缓冲整个上传内容。
+pub async fn openai_uploads(
+    State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    let path = uri.path().to_string();
+    if let Err(e) = verify_api_key(&headers, &state.api_keys).await {
         state
             .logs
             .write()
             .await
-            .add("warn", "Unauthorized request to /v1/messages");
+            .add("warn", &format!("Unauthorized request to {}", path));
         return e.into_response();
     }
 
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !is_multipart(&content_type) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": {
+                    "message": "Expected a multipart/form-data request body",
+                    "type": "invalid_request_error"
+                }
+            })),
+        )
+            .into_response();
+    }
+
+    let credential = state.db.as_ref().and_then(|db| {
+        state
+            .pool_service
+            .select_credential(db, "openai", None)
+            .ok()
+            .flatten()
+    });
+    let Some(credential) = credential else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "error": {"message": "No OpenAI credential available for uploads"}
+            })),
+        )
+            .into_response();
+    };
+
+    let ctx = RequestContext::new(String::new());
+    state.logs.write().await.add(
+        "info",
+        &format!("POST {} request_id={} (multipart)", path, ctx.request_id),
+    );
+
+    let llm_request = LLMRequest {
+        method: "POST".to_string(),
+        path: path.clone(),
+        headers: state.flow_monitor.capture_request_headers(&headers).await,
+        timestamp: ctx.timestamp,
+        ..Default::default()
+    };
+    let flow_metadata = build_flow_metadata(
+        ProviderType::OpenAI,
+        Some(&credential.uuid),
+        credential.name.as_deref(),
+        &headers,
+        &ctx,
+    );
+    let flow_id = match start_flow_capture(&state, &llm_request, &flow_metadata).await {
+        Ok(flow_id) => flow_id,
+        Err(response) => return response,
+    };
+
+    // 转发请求体的同时逐块输入捕获器
+    let capture = match flow_id {
+        Some(_) => state.flow_monitor.multipart_capture(&content_type).await,
+        None => None,
+    }
+    .map(|capture| Arc::new(Mutex::new(Some(capture))));
+    let feed = capture.clone();
+    let upload = body.into_data_stream().inspect(move |chunk| {
+        if let (Some(capture), Ok(bytes)) = (&feed, chunk) {
+            if let Some(capture) = capture.lock().as_mut() {
+                capture.feed(bytes);
+            }
+        }
+    });
+
+    if let Some(ref fid) = flow_id {
+        state.flow_monitor.mark_request_sent(fid).await;
+    }
+    let response = call_provider_multipart(
+        &state,
+        &credential,
+        &path,
+        &content_type,
+        reqwest::Body::wrap_stream(upload),
+        flow_id.as_deref(),
+    )
+    .await;
+
+    let Some(fid) = flow_id else {
+        return response;
+    };
+    if let Some(capture) = capture.and_then(|capture| capture.lock().take()) {
+        state
+            .flow_monitor
+            .set_multipart_request(&fid, capture)
+            .await;
+    }
+    if response.status().is_success() {
+        let llm_response = LLMResponse {
+            status_code: response.status().as_u16(),
+            ..Default::default()
+        };
+        state
+            .flow_monitor
+            .complete_flow(&fid, Some(llm_response))
+            .await;
+        response
+    } else {
+        let (error, rebuilt) = flow_error_from_response(response).await;
+        state.flow_monitor.fail_flow(&fid, error).await;
+        rebuilt
+    }
+}
+
+pub async fn anthropic_messages(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    JsonWithFields(request, client_fields): JsonWithFields<AnthropicMessagesRequest>,
+) -> Response {
+    handle_anthropic_messages(state, headers, request, client_fields).await
+}
+
+/// 处理 Anthropic Messages 格式的请求
+///
+/// 响应在返回前经过内容过滤，携带幂等键的非流式请求按键缓存响应。
+pub(crate) async fn handle_anthropic_messages(
+    state: AppState,
+    headers: HeaderMap,
+    request: AnthropicMessagesRequest,
+    client_fields: HashSet<String>,
+) -> Response {
+    // 使用 Anthropic 格式的认证验证（优先检查 x-api-key）
+    let identity = match verify_api_key_anthropic(&headers, &state.api_keys).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            state
+                .logs
+                .write()
+                .await
+                .add("warn", "Unauthorized request to /v1/messages");
+            return e.into_response();
+        }
+    };
+
+    let guard = match begin_idempotent_request(
+        &state,
+        &headers,
+        &identity,
+        "/v1/messages",
+        request.stream,
+        &request,
+    )
+    .await
+    {
+        Ok(guard) => guard,
+        Err(replay) => return replay,
+    };
+
+    let mut flow_id = None;
+    let response = process_anthropic_messages(
+        state.clone(),
+        identity,
+        headers,
+        request,
+        client_fields,
+        &mut flow_id,
+    )
+    .await;
+    let response = apply_content_filter(&state, flow_id.clone(), response).await;
+    finish_idempotent_request(guard, flow_id, response).await
+}
+
+/// Anthropic Messages 请求的实际处理流程
+///
+/// `identity` 为已验证的调用方身份。开始捕获 Flow 后将其 ID 写入 `flow_slot`，供内容过滤记录结果。
+async fn process_anthropic_messages(
+    state: AppState,
+    identity: ApiKeyIdentity,
+    headers: HeaderMap,
+    mut request: AnthropicMessagesRequest,
+    client_fields: HashSet<String>,
+    flow_slot: &mut Option<String>,
+) -> Response {
     // 创建请求上下文
-    let mut ctx = RequestContext::new(request.model.clone()).with_stream(request.stream);
+    let mut ctx = RequestContext::new(request.model.clone())
+        .with_stream(request.stream)
+        .with_no_remap(wants_no_remap(&headers));
+    ctx.set_metadata(API_KEY_LABEL_KEY, serde_json::json!(identity.label));
 
     // 详细记录请求信息
     let msg_count = request.messages.len();
@@ -1504,6 +3246,70 @@ pub async fn anthropic_messages(
         );
     }
 
+    // 按别名规则截断过长的消息历史（在降级判断之前）
+    {
+        let mut payload = serde_json::to_value(&request).unwrap_or_default();
+        if let Some(truncation) = state
+            .processor
+            .apply_history_truncation(&mut ctx, &mut payload)
+            .await
+        {
+            state.logs.write().await.add(
+                "info",
+                &format!(
+                    "[TRUNCATE] request_id={} alias={} messages={} removed={}",
+                    ctx.request_id,
+                    ctx.original_model,
+                    truncation.original_messages,
+                    truncation.removed_messages
+                ),
+            );
+            if let Ok(updated) = serde_json::from_value(payload) {
+                request = updated;
+            }
+        }
+    }
+
+    // 提示词过大时按别名规则降级模型（按降级后的模型重新选择 Provider）
+    let provider = {
+        let payload = serde_json::to_value(&request).unwrap_or_default();
+        match state
+            .processor
+            .apply_size_downgrade(&mut ctx, &payload)
+            .await
+        {
+            Some(downgrade) => {
+                request.model = downgrade.to.clone();
+                state.logs.write().await.add(
+                    "info",
+                    &format!(
+                        "[DOWNGRADE] request_id={} alias={} {} -> {} estimated_tokens={} threshold={}",
+                        ctx.request_id,
+                        ctx.original_model,
+                        downgrade.from,
+                        downgrade.to,
+                        downgrade.estimated_tokens,
+                        downgrade.threshold_tokens
+                    ),
+                );
+                ctx.provider.unwrap_or(provider)
+            }
+            None => provider,
+        }
+    };
+
+    // 检查调用方是否有权访问解析后的模型
+    if let Err(e) = check_api_key_scope_anthropic(&identity, &ctx.resolved_model, None) {
+        state.logs.write().await.add(
+            "warn",
+            &format!(
+                "[AUTH] request_id={} api_key={} 无权访问模型 {}",
+                ctx.request_id, identity.label, ctx.resolved_model
+            ),
+        );
+        return e.into_response();
+    }
+
     // 记录最后一条消息的角色和内容预览
     if let Some(last_msg) = request.messages.last() {
         let content_preview = match &last_msg.content {
@@ -1530,6 +3336,9 @@ pub async fn anthropic_messages(
         );
     }
 
+    // 客户端未发送的参数使用模型默认值（在别名解析和降级之后、参数注入之前）
+    apply_model_defaults(&state, &mut ctx, &mut request, &client_fields).await;
+
     // 应用参数注入
     let injection_enabled = *state.injection_enabled.read().await;
     if injection_enabled {
@@ -1551,6 +3360,45 @@ pub async fn anthropic_messages(
         }
     }
 
+    // 应用别名参数约束（钳制超出范围的参数，而不是拒绝）
+    {
+        let mut payload = serde_json::to_value(&request).unwrap_or_default();
+        let adjustments = state
+            .processor
+            .apply_param_constraints(&mut ctx, &mut payload)
+            .await;
+        if !adjustments.is_empty() {
+            state.logs.write().await.add(
+                "info",
+                &format!(
+                    "[CONSTRAINT] request_id={} alias={} adjusted_params={:?}",
+                    ctx.request_id,
+                    ctx.original_model,
+                    adjustments.iter().map(|a| &a.param).collect::<Vec<_>>()
+                ),
+            );
+            if let Ok(updated) = serde_json::from_value(payload) {
+                request = updated;
+            }
+        }
+    }
+
+    // 离线 Mock 模式：用捕获的 Flow 响应，不选择凭证也不调用上游
+    if let Some(mock_config) = mock_config_for(&state, &headers).await {
+        let mut llm_request = build_llm_request_from_anthropic(
+            &request,
+            "/v1/messages",
+            HashMap::new(),
+            ctx.timestamp,
+        );
+        if let Err(response) = apply_request_plugins(&state, &mut llm_request, &mut request).await {
+            return response;
+        }
+        let streaming = request.stream;
+        return serve_mock_response(&state, &ctx, &mock_config, &llm_request, streaming, true)
+            .await;
+    }
+
     // 根据客户端类型选择 Provider
     // **Validates: Requirements 3.1, 3.3, 3.4**
     let (selected_provider, client_type) = select_provider_for_client(&headers, &state).await;
@@ -1608,32 +3456,35 @@ pub async fn anthropic_messages(
     if final_provider_type != provider {
         ctx.set_provider(final_provider_type);
     }
+    if let Err(e) =
+        check_api_key_scope_anthropic(&identity, &ctx.resolved_model, Some(&final_provider))
+    {
+        state.logs.write().await.add(
+            "warn",
+            &format!(
+                "[AUTH] request_id={} api_key={} 无权使用 Provider {}",
+                ctx.request_id, identity.label, final_provider
+            ),
+        );
+        return e.into_response();
+    }
 
-    // 优先按最终选择的 provider 选择凭证；如果没有可用凭证，再回退到默认 provider。
-    let credential = match &state.db {
-        Some(db) => state
-            .pool_service
-            .select_credential(db, &final_provider, Some(&request.model))
-            .ok()
-            .flatten()
-            .or_else(|| {
-                if final_provider != default_provider {
-                    state
-                        .pool_service
-                        .select_credential(db, &default_provider, Some(&request.model))
-                        .ok()
-                        .flatten()
-                } else {
-                    None
-                }
-            }),
-        None => None,
-    };
+    let session_key = request_session_key(&state, &headers, &request);
+    let credential = select_pool_credential(
+        &state,
+        &mut ctx,
+        &identity,
+        &final_provider,
+        &default_provider,
+        &request.model,
+        session_key.as_deref(),
+    );
 
     // 如果找到凭证池中的凭证，使用它
     if let Some(cred) = credential {
         ctx.set_provider(cred.provider_type);
         ctx.set_credential_id(cred.uuid.clone());
+        apply_reasoning_effort(&state, &mut ctx, cred.provider_type, &mut request).await;
 
         state.logs.write().await.add(
             "info",
@@ -1648,18 +3499,28 @@ pub async fn anthropic_messages(
         );
 
         // 启动 Flow 捕获
-        let llm_request = build_llm_request_from_anthropic(&request, "/v1/messages", &headers);
+        let captured_headers = state.flow_monitor.capture_request_headers(&headers).await;
+        let mut llm_request = build_llm_request_from_anthropic(
+            &request,
+            "/v1/messages",
+            captured_headers,
+            ctx.timestamp,
+        );
+        if let Err(response) = apply_request_plugins(&state, &mut llm_request, &mut request).await {
+            return response;
+        }
         let flow_metadata = build_flow_metadata(
             cred.provider_type,
             Some(&cred.uuid),
             cred.name.as_deref(),
             &headers,
-            &ctx.request_id,
+            &ctx,
         );
-        let flow_id = state
-            .flow_monitor
-            .start_flow(llm_request.clone(), flow_metadata.clone())
-            .await;
+        let flow_id = match start_flow_capture(&state, &llm_request, &flow_metadata).await {
+            Ok(flow_id) => flow_id,
+            Err(response) => return response,
+        };
+        flow_slot.clone_from(&flow_id);
 
         // 检查是否需要拦截请求
         // **Validates: Requirements 2.1, 2.3, 2.5**
@@ -1693,7 +3554,24 @@ pub async fn anthropic_messages(
             }
         }
 
-        let response = call_provider_anthropic(&state, &cred, &request, flow_id.as_deref()).await;
+        let deadline = request_deadline(&state, &headers, &ctx, request.stream);
+        mark_upstream_dispatch(&state, flow_id.as_deref()).await;
+        if let Some(response) = inject_chaos(
+            &state,
+            &mut ctx,
+            flow_id.as_deref(),
+            StreamingFormat::AnthropicSse,
+        )
+        .await
+        {
+            return response;
+        }
+        let call = call_provider_anthropic(&state, &cred, &request, flow_id.as_deref());
+        let response =
+            match with_request_deadline(&state, &ctx, flow_id.as_deref(), deadline, call).await {
+                Ok(response) => response,
+                Err(response) => return response,
+            };
 
         // 记录请求统计
         let is_success = response.status().is_success();
@@ -1792,13 +3670,19 @@ pub async fn anthropic_messages(
                     .flow_monitor
                     .complete_flow(&fid, Some(llm_response))
                     .await;
-            } else {
-                let error = FlowError::new(
-                    FlowErrorType::from_status_code(response.status().as_u16()),
-                    "Request failed",
+                return mirror_to_shadow(
+                    &state,
+                    &mut ctx,
+                    "/v1/messages",
+                    &fid,
+                    &request,
+                    response,
                 )
-                .with_status_code(response.status().as_u16());
+                .await;
+            } else {
+                let (error, rebuilt) = flow_error_from_response(response).await;
                 state.flow_monitor.fail_flow(&fid, error).await;
+                return rebuilt;
             }
         }
 
@@ -1838,13 +3722,18 @@ pub async fn anthropic_messages(
     );
 
     // 启动 Flow 捕获（legacy mode）
-    let llm_request = build_llm_request_from_anthropic(&request, "/v1/messages", &headers);
-    let flow_metadata =
-        build_flow_metadata(final_provider_type, None, None, &headers, &ctx.request_id);
-    let flow_id = state
-        .flow_monitor
-        .start_flow(llm_request.clone(), flow_metadata.clone())
-        .await;
+    let captured_headers = state.flow_monitor.capture_request_headers(&headers).await;
+    let mut llm_request =
+        build_llm_request_from_anthropic(&request, "/v1/messages", captured_headers, ctx.timestamp);
+    if let Err(response) = apply_request_plugins(&state, &mut llm_request, &mut request).await {
+        return response;
+    }
+    let flow_metadata = build_flow_metadata(final_provider_type, None, None, &headers, &ctx);
+    let flow_id = match start_flow_capture(&state, &llm_request, &flow_metadata).await {
+        Ok(flow_id) => flow_id,
+        Err(response) => return response,
+    };
+    flow_slot.clone_from(&flow_id);
 
     // 检查是否需要拦截请求（legacy mode）
     // **Validates: Requirements 2.1, 2.3, 2.5**
@@ -1932,8 +3821,27 @@ pub async fn anthropic_messages(
 
     let kiro = state.kiro.read().await;
 
-    match kiro.call_api(&openai_request).await {
+    let deadline = request_deadline(&state, &headers, &ctx, request.stream);
+    mark_upstream_dispatch(&state, flow_id.as_deref()).await;
+    if let Some(response) = inject_chaos(
+        &state,
+        &mut ctx,
+        flow_id.as_deref(),
+        StreamingFormat::AnthropicSse,
+    )
+    .await
+    {
+        return response;
+    }
+    let call = kiro.call_api(&openai_request);
+    let upstream =
+        match with_request_deadline(&state, &ctx, flow_id.as_deref(), deadline, call).await {
+            Ok(upstream) => upstream,
+            Err(response) => return response,
+        };
+    match upstream {
         Ok(resp) => {
+            record_upstream_response(&state, flow_id.as_deref(), &resp).await;
             let status = resp.status();
             state
                 .logs
@@ -1942,15 +3850,15 @@ pub async fn anthropic_messages(
                 .add("info", &format!("[RESP] Upstream status: {status}"));
 
             if status.is_success() {
-                match resp.bytes().await {
-                    Ok(bytes) => {
-                        // 使用 lossy 转换，避免无效 UTF-8 导致崩溃
-                        let body = String::from_utf8_lossy(&bytes).to_string();
+                match read_upstream_body(&state, flow_id.as_deref(), resp).await {
+                    Ok(decoded) => {
+                        // AWS event stream 是二进制格式，按字节 lossy 转换后解析
+                        let body = decoded.lossy_text();
 
                         // 记录原始响应长度
                         state.logs.write().await.add(
                             "debug",
-                            &format!("[RESP] Raw body length: {} bytes", bytes.len()),
+                            &format!("[RESP] Raw body length: {} bytes", decoded.bytes.len()),
                         );
 
                         // 保存原始响应到文件用于调试
@@ -2122,7 +4030,10 @@ pub async fn anthropic_messages(
                             .add("error", &format!("[ERROR] Response body read failed: {e}"));
                         // 标记 Flow 失败
                         if let Some(fid) = &flow_id {
-                            let error = FlowError::new(FlowErrorType::Network, &e.to_string());
+                            let error = FlowError::new(
+                                FlowErrorType::Network,
+                                state.upstream_proxies.describe_error("kiro", &e),
+                            );
                             state.flow_monitor.fail_flow(fid, error).await;
                         }
                         (
@@ -2161,17 +4072,35 @@ pub async fn anthropic_messages(
                         );
                         drop(kiro);
                         let kiro = state.kiro.read().await;
-                        match kiro.call_api(&openai_request).await {
+                        mark_upstream_dispatch(&state, flow_id.as_deref()).await;
+                        let call = kiro.call_api(&openai_request);
+                        let retry = match with_request_deadline(
+                            &state,
+                            &ctx,
+                            flow_id.as_deref(),
+                            deadline,
+                            call,
+                        )
+                        .await
+                        {
+                            Ok(retry) => retry,
+                            Err(response) => return response,
+                        };
+                        match retry {
                             Ok(retry_resp) => {
+                                record_upstream_response(&state, flow_id.as_deref(), &retry_resp)
+                                    .await;
                                 let retry_status = retry_resp.status();
                                 state.logs.write().await.add(
                                     "info",
                                     &format!("[RETRY] Response status: {retry_status}"),
                                 );
                                 if retry_resp.status().is_success() {
-                                    match retry_resp.bytes().await {
-                                        Ok(bytes) => {
-                                            let body = String::from_utf8_lossy(&bytes).to_string();
+                                    match read_upstream_body(&state, flow_id.as_deref(), retry_resp)
+                                        .await
+                                    {
+                                        Ok(decoded) => {
+                                            let body = decoded.lossy_text();
                                             let parsed = parse_cw_response(&body);
                                             state.logs.write().await.add(
                                                 "info",
@@ -2283,7 +4212,9 @@ pub async fn anthropic_messages(
                                             if let Some(fid) = &flow_id {
                                                 let error = FlowError::new(
                                                     FlowErrorType::Network,
-                                                    &e.to_string(),
+                                                    state
+                                                        .upstream_proxies
+                                                        .describe_error("kiro", &e),
                                                 );
                                                 state.flow_monitor.fail_flow(fid, error).await;
                                             }
@@ -2329,8 +4260,10 @@ pub async fn anthropic_messages(
                                     .add("error", &format!("[RETRY] Request failed: {e}"));
                                 // 标记 Flow 失败
                                 if let Some(fid) = &flow_id {
-                                    let error =
-                                        FlowError::new(FlowErrorType::Network, &e.to_string());
+                                    let error = FlowError::new(
+                                        FlowErrorType::Network,
+                                        state.upstream_proxies.describe_error("kiro", e.as_ref()),
+                                    );
                                     state.flow_monitor.fail_flow(fid, error).await;
                                 }
                                 (
@@ -2363,6 +4296,11 @@ pub async fn anthropic_messages(
                     }
                 }
             } else {
+                let retry_after = resp
+                    .headers()
+                    .get(header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_retry_after);
                 let body = resp.text().await.unwrap_or_default();
                 state.logs.write().await.add(
                     "error",
@@ -2376,7 +4314,9 @@ pub async fn anthropic_messages(
                 if let Some(fid) = &flow_id {
                     let error =
                         FlowError::new(FlowErrorType::from_status_code(status.as_u16()), &body)
-                            .with_status_code(status.as_u16());
+                            .with_status_code(status.as_u16())
+                            .with_retry_after(retry_after)
+                            .with_provider_body(&body);
                     state.flow_monitor.fail_flow(fid, error).await;
                 }
                 (
@@ -2403,7 +4343,10 @@ pub async fn anthropic_messages(
             );
             // 标记 Flow 失败
             if let Some(fid) = &flow_id {
-                let error = FlowError::new(FlowErrorType::Network, &e.to_string());
+                let error = FlowError::new(
+                    FlowErrorType::Network,
+                    state.upstream_proxies.describe_error("kiro", e.as_ref()),
+                );
                 state.flow_monitor.fail_flow(fid, error).await;
             }
             (
@@ -2452,9 +4395,7 @@ fn get_target_stream_format(path: &str) -> StreamingFormat {
 /// # 注意
 /// 当前所有 Provider 都返回 false，因为 StreamingProvider trait 尚未实现。
 /// 一旦任务 6 完成，此函数将根据凭证类型返回适当的值。
-fn should_use_true_streaming(
-    credential: &crate::models::provider_pool_model::ProviderCredential,
-) -> bool {
+fn should_use_true_streaming(credential: &ProviderCredential) -> bool {
     use crate::models::provider_pool_model::CredentialData;
 
     // TODO: 当 StreamingProvider trait 实现后，根据凭证类型返回 true
@@ -2505,7 +4446,19 @@ fn build_stream_error_response(
                 })
             )
         }
-        // TODO: 任务 6 完成后，添加 GeminiStream 分支
+        StreamingFormat::GeminiSse => {
+            // Gemini 的错误体：{"error": {"code", "message", "status"}}
+            format!(
+                "data: {}\n\n",
+                serde_json::json!({
+                    "error": {
+                        "code": 500,
+                        "message": message,
+                        "status": error_type
+                    }
+                })
+            )
+        }
         StreamingFormat::OpenAiSse => {
             format!(
                 "data: {}\n\n",
@@ -1,6 +1,6 @@
 //! Management API 处理器
 //!
-//! 提供服务器状态查询、凭证管理、配置管理等功能
+//! 提供服务器状态查询、并发状态查询、凭证管理、配置管理等功能
 
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use serde::{Deserialize, Serialize};
@@ -8,6 +8,9 @@ use serde::{Deserialize, Serialize};
 use crate::database::dao::provider_pool::ProviderPoolDao;
 use crate::server::AppState;
 
+/// 审计日志中 Management API 操作的操作者
+const MANAGEMENT_ACTOR: &str = "management-api";
+
 // ============ Types ============
 
 /// 管理 API 状态响应
@@ -170,6 +173,11 @@ pub async fn management_status(State(state): State<AppState>) -> impl IntoRespon
     Json(response)
 }
 
+/// GET /v0/management/concurrency - 获取各 Provider 的自适应并发状态
+pub async fn management_concurrency(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.processor.concurrency.snapshot())
+}
+
 /// GET /v0/management/credentials - 获取凭证列表
 pub async fn management_list_credentials(State(state): State<AppState>) -> impl IntoResponse {
     let mut credentials = Vec::new();
@@ -446,6 +454,13 @@ pub async fn management_add_credential(
                         request.id,
                         request.provider_type
                     );
+                    crate::audit::record_serialized(
+                        MANAGEMENT_ACTOR,
+                        crate::audit::AuditAction::CredentialAdd,
+                        Some(credential.uuid.clone()),
+                        None,
+                        Some(&credential),
+                    );
                     return (
                         StatusCode::CREATED,
                         Json(AddCredentialResponse {
@@ -21,12 +21,16 @@ use axum::{
     Json,
 };
 use futures::StreamExt;
+use std::sync::Arc;
 
 use crate::converter::anthropic_to_openai::convert_anthropic_to_openai;
 use crate::converter::openai_to_antigravity::{
     convert_antigravity_to_openai_response, convert_openai_to_antigravity_with_context,
 };
 use crate::flow_monitor::stream_rebuilder::StreamFormat;
+use crate::flow_monitor::{
+    decode_body, upstream_request_id_from_headers, DecodedBody, FlowMonitor,
+};
 use crate::models::anthropic::AnthropicMessagesRequest;
 use crate::models::openai::ChatCompletionRequest;
 use crate::models::provider_pool_model::{CredentialData, ProviderCredential};
@@ -34,14 +38,15 @@ use crate::providers::{
     AntigravityProvider, ClaudeCustomProvider, ClaudeOAuthProvider, CodexProvider, IFlowProvider,
     KiroProvider, OpenAICustomProvider, VertexProvider,
 };
+use crate::resilience::RateLimitHeaders;
 use crate::server::AppState;
 use crate::server_utils::{
     build_anthropic_response, build_anthropic_stream_response, parse_cw_response, safe_truncate,
     CWParsedResponse,
 };
 use crate::streaming::{
-    StreamConfig, StreamContext, StreamError, StreamFormat as StreamingFormat, StreamManager,
-    StreamResponse,
+    parse_sse_block, with_keepalive, SseEvent, StreamAccumulator, StreamConfig, StreamContext,
+    StreamError, StreamFormat as StreamingFormat, StreamManager, StreamResponse,
 };
 
 // ============================================================================
@@ -324,6 +329,181 @@ fn convert_codex_response_to_openai(
     })
 }
 
+/// 为 Provider 应用上游代理客户端（未配置代理时保留默认客户端）
+pub(crate) fn apply_upstream_proxy(state: &AppState, provider: &str, client: &mut reqwest::Client) {
+    match state.upstream_proxies.client_for(provider) {
+        Ok(Some(proxied)) => *client = proxied,
+        Ok(None) => {}
+        Err(e) => tracing::warn!("[PROXY] 创建 {} 代理客户端失败: {}", provider, e),
+    }
+}
+
+/// 为上游 Provider 客户端应用代理，返回需要逐请求附加的请求头
+///
+/// 配置了 `request_id_header` 时返回的请求头包含 Flow ID，由 Provider 在构建请求时附加，
+/// 共享的客户端（及其连接池）保持不变。Provider 配置的附加请求头记录到 Flow 的路由信息中。
+async fn prepare_upstream_client(
+    state: &AppState,
+    provider: &str,
+    flow_id: Option<&str>,
+    client: &mut reqwest::Client,
+) -> reqwest::header::HeaderMap {
+    apply_upstream_proxy(state, provider, client);
+
+    if let Some(fid) = flow_id {
+        let extra_headers = state.upstream_proxies.extra_headers_for(provider);
+        if !extra_headers.is_empty() {
+            state
+                .flow_monitor
+                .set_extra_headers(fid, &extra_headers)
+                .await;
+        }
+    }
+
+    let mut headers = reqwest::header::HeaderMap::new();
+    let (Some(fid), Some(header)) = (flow_id, state.flow_monitor.request_id_header().await) else {
+        return headers;
+    };
+    match (
+        reqwest::header::HeaderName::from_bytes(header.as_bytes()),
+        reqwest::header::HeaderValue::from_str(fid),
+    ) {
+        (Ok(name), Ok(value)) => {
+            headers.insert(name, value);
+        }
+        _ => tracing::warn!("[FLOW] 无效的 Flow ID 透传请求头: {}", header),
+    }
+    headers
+}
+
+/// 记录上游响应中的请求 ID 和白名单内的响应头（限流信息等），
+/// 便于与 Provider 侧的日志对应
+pub(crate) async fn record_upstream_response(
+    state: &AppState,
+    flow_id: Option<&str>,
+    resp: &reqwest::Response,
+) {
+    let Some(fid) = flow_id else {
+        return;
+    };
+    if let Some(request_id) = upstream_request_id_from_headers(resp.headers()) {
+        state
+            .flow_monitor
+            .set_upstream_request_id(fid, request_id)
+            .await;
+    }
+    state
+        .flow_monitor
+        .set_response_headers(fid, resp.headers())
+        .await;
+}
+
+/// Provider 调用过程中收到的最后一个上游响应的状态码和限流头
+///
+/// 限流头只存在于上游原始响应中，代理自身构造的响应不包含这些信息，
+/// 因此由收到上游响应的调用处记录，再交给 [`with_concurrency_limit`] 调整并发上限。
+#[derive(Default)]
+struct UpstreamObservation(parking_lot::Mutex<Option<(u16, RateLimitHeaders)>>);
+
+impl UpstreamObservation {
+    fn record(&self, resp: &reqwest::Response) {
+        let rate_limit =
+            RateLimitHeaders::parse(|name| resp.headers().get(name).and_then(|v| v.to_str().ok()));
+        *self.0.lock() = Some((resp.status().as_u16(), rate_limit));
+    }
+
+    fn take(&self) -> Option<(u16, RateLimitHeaders)> {
+        self.0.lock().take()
+    }
+}
+
+/// 记录 Provider 调用收到的上游响应（写入 Flow，并保留状态码和限流头）
+async fn record_provider_response(
+    state: &AppState,
+    upstream: &UpstreamObservation,
+    flow_id: Option<&str>,
+    resp: &reqwest::Response,
+) {
+    record_upstream_response(state, flow_id, resp).await;
+    upstream.record(resp);
+}
+
+/// 读取上游响应体
+///
+/// 按 `Content-Encoding` 解压，并把内容类型和解码结果记录到 Flow。
+pub(crate) async fn read_upstream_body(
+    state: &AppState,
+    flow_id: Option<&str>,
+    resp: reqwest::Response,
+) -> Result<DecodedBody, reqwest::Error> {
+    let header = |name: reqwest::header::HeaderName| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let content_type = header(reqwest::header::CONTENT_TYPE);
+    let content_encoding = header(reqwest::header::CONTENT_ENCODING);
+    let bytes = resp.bytes().await?;
+    let decoded = decode_body(content_type.as_deref(), content_encoding.as_deref(), &bytes);
+    if let Some(fid) = flow_id {
+        state
+            .flow_monitor
+            .set_response_body_info(fid, decoded.info.clone())
+            .await;
+    }
+    Ok(decoded)
+}
+
+/// 读取上游响应文本（非文本内容返回说明，不会因无效 UTF-8 失败）
+pub(crate) async fn read_upstream_text(
+    state: &AppState,
+    flow_id: Option<&str>,
+    resp: reqwest::Response,
+) -> Result<String, reqwest::Error> {
+    Ok(read_upstream_body(state, flow_id, resp)
+        .await?
+        .text_or_note())
+}
+
+/// 按 Provider 的自适应并发上限排队执行调用
+///
+/// 用上游响应的状态码、限流头和调用耗时调整上限；调用未收到上游原始响应时
+/// （如 Provider 客户端只返回解析后的结果）按最终响应的状态码调整。
+/// 流式响应的许可随响应体结束才释放。
+async fn with_concurrency_limit(
+    state: &AppState,
+    credential: &ProviderCredential,
+    upstream: &UpstreamObservation,
+    call: impl std::future::Future<Output = Response>,
+) -> Response {
+    let concurrency = &state.processor.concurrency;
+    let provider_key = credential.provider_type.to_string();
+    let permit = concurrency.acquire(&provider_key).await;
+
+    let start = std::time::Instant::now();
+    let response = call.await;
+    concurrency.record_latency(&provider_key, start.elapsed());
+    let (status_code, rate_limit) = upstream
+        .take()
+        .unwrap_or_else(|| (response.status().as_u16(), RateLimitHeaders::default()));
+    concurrency.observe(&provider_key, status_code, &rate_limit);
+
+    let is_event_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"));
+    if !is_event_stream {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let body = body.into_data_stream().inspect(move |_| {
+        let _ = &permit;
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
 /// 根据凭证调用 Provider (Anthropic 格式)
 ///
 /// # 参数
@@ -336,6 +516,23 @@ pub async fn call_provider_anthropic(
     credential: &ProviderCredential,
     request: &AnthropicMessagesRequest,
     flow_id: Option<&str>,
+) -> Response {
+    let upstream = UpstreamObservation::default();
+    with_concurrency_limit(
+        state,
+        credential,
+        &upstream,
+        call_provider_anthropic_inner(state, credential, request, flow_id, &upstream),
+    )
+    .await
+}
+
+async fn call_provider_anthropic_inner(
+    state: &AppState,
+    credential: &ProviderCredential,
+    request: &AnthropicMessagesRequest,
+    flow_id: Option<&str>,
+    upstream: &UpstreamObservation,
 ) -> Response {
     // 如果是流式请求且有 flow_id，设置流式状态
     if request.stream {
@@ -375,6 +572,8 @@ pub async fn call_provider_anthropic(
                     tracing::warn!("[POOL] Token cache miss, loading from source: {}", e);
                     // 回退到从源文件加载
                     let mut kiro = KiroProvider::new();
+                    kiro.request_headers =
+                        prepare_upstream_client(state, "kiro", flow_id, &mut kiro.client).await;
                     if let Err(e) = kiro.load_credentials_from_path(creds_file_path).await {
                         // 记录凭证加载失败
                         let _ = state.pool_service.mark_unhealthy(
@@ -406,31 +605,38 @@ pub async fn call_provider_anthropic(
             };
             // 使用获取到的 token 创建 KiroProvider
             let mut kiro = KiroProvider::new();
+            kiro.request_headers =
+                prepare_upstream_client(state, "kiro", flow_id, &mut kiro.client).await;
             kiro.credentials.access_token = Some(token);
             // 从源文件加载其他配置（region, profile_arn 等）
             let _ = kiro.load_credentials_from_path(creds_file_path).await;
             let openai_request = convert_anthropic_to_openai(request);
             let resp = match kiro.call_api(&openai_request).await {
-                Ok(r) => r,
+                Ok(r) => {
+                    record_provider_response(state, upstream, flow_id, &r).await;
+                    r
+                }
                 Err(e) => {
+                    let message = state.upstream_proxies.describe_error("kiro", e.as_ref());
                     // 记录 API 调用失败
                     let _ = state.pool_service.mark_unhealthy(
                         db,
                         &credential.uuid,
-                        Some(&e.to_string()),
+                        Some(&message),
                     );
                     return (
                         StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(serde_json::json!({"error": {"message": e.to_string()}})),
+                        Json(serde_json::json!({"error": {"message": message}})),
                     )
                         .into_response();
                 }
             };
             let status = resp.status();
             if status.is_success() {
-                match resp.bytes().await {
-                    Ok(bytes) => {
-                        let body = String::from_utf8_lossy(&bytes).to_string();
+                match read_upstream_body(state, flow_id, resp).await {
+                    Ok(decoded) => {
+                        // AWS event stream 是二进制格式，按字节 lossy 转换后解析
+                        let body = decoded.lossy_text();
                         let parsed = parse_cw_response(&body);
                         // 记录成功
                         let _ = state.pool_service.mark_healthy(
@@ -489,10 +695,11 @@ pub async fn call_provider_anthropic(
                 kiro.credentials.access_token = Some(new_token);
                 match kiro.call_api(&openai_request).await {
                     Ok(retry_resp) => {
+                        record_provider_response(state, upstream, flow_id, &retry_resp).await;
                         if retry_resp.status().is_success() {
-                            match retry_resp.bytes().await {
-                                Ok(bytes) => {
-                                    let body = String::from_utf8_lossy(&bytes).to_string();
+                            match read_upstream_body(state, flow_id, retry_resp).await {
+                                Ok(decoded) => {
+                                    let body = decoded.lossy_text();
                                     let parsed = parse_cw_response(&body);
                                     // 记录重试成功
                                     let _ = state.pool_service.mark_healthy(
@@ -535,14 +742,15 @@ pub async fn call_provider_anthropic(
                         }
                     }
                     Err(e) => {
+                        let message = state.upstream_proxies.describe_error("kiro", e.as_ref());
                         let _ = state.pool_service.mark_unhealthy(
                             db,
                             &credential.uuid,
-                            Some(&e.to_string()),
+                            Some(&message),
                         );
                         (
                             StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(serde_json::json!({"error": {"message": e.to_string()}})),
+                            Json(serde_json::json!({"error": {"message": message}})),
                         )
                             .into_response()
                     }
@@ -675,12 +883,15 @@ pub async fn call_provider_anthropic(
             }
         }
         CredentialData::OpenAIKey { api_key, base_url } => {
-            let openai = OpenAICustomProvider::with_config(api_key.clone(), base_url.clone());
+            let mut openai = OpenAICustomProvider::with_config(api_key.clone(), base_url.clone());
+            openai.request_headers =
+                prepare_upstream_client(state, "openai", flow_id, &mut openai.client).await;
             let openai_request = convert_anthropic_to_openai(request);
             match openai.call_api(&openai_request).await {
                 Ok(resp) => {
+                    record_provider_response(state, upstream, flow_id, &resp).await;
                     if resp.status().is_success() {
-                        match resp.text().await {
+                        match read_upstream_text(state, flow_id, resp).await {
                             Ok(body) => {
                                 if let Ok(openai_resp) =
                                     serde_json::from_str::<serde_json::Value>(&body)
@@ -757,16 +968,17 @@ pub async fn call_provider_anthropic(
                     }
                 }
                 Err(e) => {
+                    let message = state.upstream_proxies.describe_error("openai", e.as_ref());
                     if let Some(db) = &state.db {
                         let _ = state.pool_service.mark_unhealthy(
                             db,
                             &credential.uuid,
-                            Some(&e.to_string()),
+                            Some(&message),
                         );
                     }
                     (
                         StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(serde_json::json!({"error": {"message": e.to_string()}})),
+                        Json(serde_json::json!({"error": {"message": message}})),
                     )
                         .into_response()
                 }
@@ -775,7 +987,9 @@ pub async fn call_provider_anthropic(
         CredentialData::ClaudeKey { api_key, base_url } => {
             // 打印 Claude 代理 URL 用于调试
             let actual_base_url = base_url.as_deref().unwrap_or("https://api.anthropic.com");
-            let claude = ClaudeCustomProvider::with_config(api_key.clone(), base_url.clone());
+            let mut claude = ClaudeCustomProvider::with_config(api_key.clone(), base_url.clone());
+            claude.request_headers =
+                prepare_upstream_client(state, "claude", flow_id, &mut claude.client).await;
             let request_url = claude.get_base_url();
             state.logs.write().await.add(
                 "info",
@@ -797,6 +1011,7 @@ pub async fn call_provider_anthropic(
             );
             match claude.call_api(request).await {
                 Ok(resp) => {
+                    record_provider_response(state, upstream, flow_id, &resp).await;
                     let status = resp.status();
                     // 打印响应状态
                     state.logs.write().await.add(
@@ -807,7 +1022,7 @@ pub async fn call_provider_anthropic(
                             request.model
                         ),
                     );
-                    match resp.text().await {
+                    match read_upstream_text(state, flow_id, resp).await {
                         Ok(body) => {
                             if status.is_success() {
                                 // 打印响应内容预览
@@ -883,16 +1098,17 @@ pub async fn call_provider_anthropic(
                     }
                 }
                 Err(e) => {
+                    let message = state.upstream_proxies.describe_error("claude", e.as_ref());
                     if let Some(db) = &state.db {
                         let _ = state.pool_service.mark_unhealthy(
                             db,
                             &credential.uuid,
-                            Some(&e.to_string()),
+                            Some(&message),
                         );
                     }
                     (
                         StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(serde_json::json!({"error": {"message": e.to_string()}})),
+                        Json(serde_json::json!({"error": {"message": message}})),
                     )
                         .into_response()
                 }
@@ -904,8 +1120,9 @@ pub async fn call_provider_anthropic(
             let vertex = VertexProvider::with_config(api_key.clone(), base_url.clone());
             match vertex.chat_completions(&serde_json::to_value(&openai_request).unwrap_or_default()).await {
                 Ok(resp) => {
+                    record_provider_response(state, upstream, flow_id, &resp).await;
                     let status = resp.status();
-                    match resp.text().await {
+                    match read_upstream_text(state, flow_id, resp).await {
                         Ok(body) => {
                             if status.is_success() {
                                 if let Some(db) = &state.db {
@@ -971,16 +1188,45 @@ pub async fn call_provider_anthropic(
 /// - `credential`: 凭证信息
 /// - `request`: OpenAI 格式请求
 /// - `flow_id`: Flow ID（可选，用于流式响应处理）
+/// - `accumulate_stream`: 上游以流式请求，但缓冲为非流式 JSON 返回给客户端
 pub async fn call_provider_openai(
     state: &AppState,
     credential: &ProviderCredential,
     request: &ChatCompletionRequest,
     flow_id: Option<&str>,
+    accumulate_stream: bool,
+) -> Response {
+    let upstream = UpstreamObservation::default();
+    with_concurrency_limit(
+        state,
+        credential,
+        &upstream,
+        call_provider_openai_inner(
+            state,
+            credential,
+            request,
+            flow_id,
+            accumulate_stream,
+            &upstream,
+        ),
+    )
+    .await
+}
+
+async fn call_provider_openai_inner(
+    state: &AppState,
+    credential: &ProviderCredential,
+    request: &ChatCompletionRequest,
+    flow_id: Option<&str>,
+    accumulate_stream: bool,
+    upstream: &UpstreamObservation,
 ) -> Response {
     let _start_time = std::time::Instant::now();
     match &credential.credential {
         CredentialData::KiroOAuth { creds_file_path } => {
             let mut kiro = KiroProvider::new();
+            kiro.request_headers =
+                prepare_upstream_client(state, "kiro", flow_id, &mut kiro.client).await;
             if let Err(e) = kiro.load_credentials_from_path(creds_file_path).await {
                 // 记录凭证加载失败
                 if let Some(db) = &state.db {
@@ -1005,6 +1251,7 @@ pub async fn call_provider_openai(
             }
             match kiro.call_api(request).await {
                 Ok(resp) => {
+                    record_provider_response(state, upstream, flow_id, &resp).await;
                     let status = resp.status();
                     if status.is_success() {
                         // 记录成功
@@ -1012,7 +1259,10 @@ pub async fn call_provider_openai(
                             let _ = state.pool_service.mark_healthy(db, &credential.uuid, Some(&request.model));
                             let _ = state.pool_service.record_usage(db, &credential.uuid);
                         }
-                        match resp.text().await {
+                        match read_upstream_body(state, flow_id, resp)
+                            .await
+                            .map(|decoded| decoded.lossy_text())
+                        {
                             Ok(body) => {
                                 let parsed = parse_cw_response(&body);
                                 let has_tool_calls = !parsed.tool_calls.is_empty();
@@ -1078,13 +1328,14 @@ pub async fn call_provider_openai(
                     }
                 }
                 Err(e) => {
+                    let message = state.upstream_proxies.describe_error("kiro", e.as_ref());
                     // 记录请求错误
                     if let Some(db) = &state.db {
-                        let _ = state.pool_service.mark_unhealthy(db, &credential.uuid, Some(&e.to_string()));
+                        let _ = state.pool_service.mark_unhealthy(db, &credential.uuid, Some(&message));
                     }
                     (
                         StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(serde_json::json!({"error": {"message": e.to_string()}})),
+                        Json(serde_json::json!({"error": {"message": message}})),
                     )
                         .into_response()
                 }
@@ -1146,11 +1397,26 @@ pub async fn call_provider_openai(
             }
         }
         CredentialData::OpenAIKey { api_key, base_url } => {
-            let openai = OpenAICustomProvider::with_config(api_key.clone(), base_url.clone());
+            let mut openai = OpenAICustomProvider::with_config(api_key.clone(), base_url.clone());
+            openai.request_headers =
+                prepare_upstream_client(state, "openai", flow_id, &mut openai.client).await;
             match openai.call_api(request).await {
                 Ok(resp) => {
+                    record_provider_response(state, upstream, flow_id, &resp).await;
+                    if accumulate_stream && request.stream && resp.status().is_success() {
+                        return handle_buffered_streaming_response(
+                            state,
+                            flow_id,
+                            response_to_stream(resp),
+                            StreamingFormat::OpenAiSse,
+                            StreamingFormat::OpenAiSse,
+                            &request.model,
+                            300_000,
+                        )
+                        .await;
+                    }
                     if resp.status().is_success() {
-                        match resp.text().await {
+                        match read_upstream_text(state, flow_id, resp).await {
                             Ok(body) => {
                                 if let Ok(json) = serde_json::from_str::<serde_json::Value>(&body) {
                                     Json(json).into_response()
@@ -1177,11 +1443,14 @@ pub async fn call_provider_openai(
                             .into_response()
                     }
                 }
-                Err(e) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(serde_json::json!({"error": {"message": e.to_string()}})),
-                )
-                    .into_response(),
+                Err(e) => {
+                    let message = state.upstream_proxies.describe_error("openai", e.as_ref());
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(serde_json::json!({"error": {"message": message}})),
+                    )
+                        .into_response()
+                }
             }
         }
         CredentialData::ClaudeKey { api_key, base_url } => {
@@ -1192,14 +1461,19 @@ pub async fn call_provider_openai(
                 actual_base_url,
                 &credential.uuid[..8]
             );
-            let claude = ClaudeCustomProvider::with_config(api_key.clone(), base_url.clone());
+            let mut claude = ClaudeCustomProvider::with_config(api_key.clone(), base_url.clone());
+            claude.request_headers =
+                prepare_upstream_client(state, "claude", flow_id, &mut claude.client).await;
             match claude.call_openai_api(request).await {
                 Ok(resp) => Json(resp).into_response(),
-                Err(e) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(serde_json::json!({"error": {"message": e.to_string()}})),
-                )
-                    .into_response(),
+                Err(e) => {
+                    let message = state.upstream_proxies.describe_error("claude", e.as_ref());
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(serde_json::json!({"error": {"message": message}})),
+                    )
+                        .into_response()
+                }
             }
         }
         CredentialData::VertexKey { api_key, base_url, model_aliases } => {
@@ -1210,8 +1484,9 @@ pub async fn call_provider_openai(
             let vertex = VertexProvider::with_config(api_key.clone(), base_url.clone());
             match vertex.chat_completions(&serde_json::to_value(&modified_request).unwrap_or_default()).await {
                 Ok(resp) => {
+                    record_provider_response(state, upstream, flow_id, &resp).await;
                     if resp.status().is_success() {
-                        match resp.text().await {
+                        match read_upstream_text(state, flow_id, resp).await {
                             Ok(body) => {
                                 if let Ok(json) = serde_json::from_str::<serde_json::Value>(&body) {
                                     Json(json).into_response()
@@ -1269,8 +1544,9 @@ pub async fn call_provider_openai(
             let request_json = serde_json::to_value(request).unwrap_or_default();
             match codex.call_api(&request_json).await {
                 Ok(resp) => {
+                    record_provider_response(state, upstream, flow_id, &resp).await;
                     if resp.status().is_success() {
-                        match resp.text().await {
+                        match read_upstream_text(state, flow_id, resp).await {
                             Ok(body) => {
                                 if let Ok(json) = serde_json::from_str::<serde_json::Value>(&body) {
                                     if let Some(db) = &state.db {
@@ -1419,6 +1695,7 @@ pub async fn call_provider_openai(
 
             match resp {
                 Ok(resp) => {
+                    record_provider_response(state, upstream, flow_id, &resp).await;
                     let status = resp.status();
 
                     // 流式：将 Anthropic SSE 转换为 OpenAI SSE
@@ -1452,6 +1729,18 @@ pub async fn call_provider_openai(
                             let _ = state.pool_service.record_usage(db, &credential.uuid);
                         }
 
+                        if accumulate_stream {
+                            return handle_buffered_streaming_response(
+                                state,
+                                flow_id,
+                                response_to_stream(resp),
+                                StreamingFormat::AnthropicSse,
+                                StreamingFormat::OpenAiSse,
+                                &request.model,
+                                300_000,
+                            )
+                            .await;
+                        }
                         return handle_streaming_response_with_timeout(
                             state,
                             flow_id,
@@ -1584,8 +1873,9 @@ pub async fn call_provider_openai(
             let request_json = serde_json::to_value(request).unwrap_or_default();
             match iflow.call_api(&request_json).await {
                 Ok(resp) => {
+                    record_provider_response(state, upstream, flow_id, &resp).await;
                     if resp.status().is_success() {
-                        match resp.text().await {
+                        match read_upstream_text(state, flow_id, resp).await {
                             Ok(body) => {
                                 if let Ok(json) = serde_json::from_str::<serde_json::Value>(&body) {
                                     if let Some(db) = &state.db {
@@ -1675,6 +1965,82 @@ pub async fn call_provider_openai(
     }
 }
 
+/// 透传 multipart 上传请求（音频转写、文件上传）
+///
+/// 仅支持 OpenAI 兼容的 API Key 凭证。请求体以流的形式转发，不在内存中缓冲整个上传内容；
+/// 上游响应按原状态码和 Content-Type 返回。
+///
+/// # 参数
+/// - `path`: 客户端请求的端点（如 `/v1/audio/transcriptions`）
+/// - `content_type`: 客户端请求的 Content-Type（包含 boundary）
+pub async fn call_provider_multipart(
+    state: &AppState,
+    credential: &ProviderCredential,
+    path: &str,
+    content_type: &str,
+    body: reqwest::Body,
+    flow_id: Option<&str>,
+) -> Response {
+    let upstream = &UpstreamObservation::default();
+    with_concurrency_limit(state, credential, upstream, async {
+        let CredentialData::OpenAIKey { api_key, base_url } = &credential.credential else {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": {"message": format!("{} credentials do not support multipart uploads", credential.provider_type)}})),
+            )
+                .into_response();
+        };
+
+        let mut openai = OpenAICustomProvider::with_config(api_key.clone(), base_url.clone());
+        openai.request_headers =
+            prepare_upstream_client(state, "openai", flow_id, &mut openai.client).await;
+        let endpoint = path.trim_start_matches('/').trim_start_matches("v1/");
+        let resp = match openai.forward_multipart(endpoint, content_type, body).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                if let Some(db) = &state.db {
+                    let _ =
+                        state
+                            .pool_service
+                            .mark_unhealthy(db, &credential.uuid, Some(&e.to_string()));
+                }
+                return (
+                    StatusCode::BAD_GATEWAY,
+                    Json(serde_json::json!({"error": {"message": e.to_string()}})),
+                )
+                    .into_response();
+            }
+        };
+
+        record_provider_response(state, upstream, flow_id, &resp).await;
+        let status = StatusCode::from_u16(resp.status().as_u16())
+            .unwrap_or(StatusCode::BAD_GATEWAY);
+        let response_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("application/json")
+            .to_string();
+        if status.is_success() {
+            if let Some(db) = &state.db {
+                let _ = state.pool_service.mark_healthy(db, &credential.uuid, None);
+                let _ = state.pool_service.record_usage(db, &credential.uuid);
+            }
+        }
+
+        match read_upstream_body(state, flow_id, resp).await {
+            Ok(decoded) => (status, [(header::CONTENT_TYPE, response_type)], decoded.bytes)
+                .into_response(),
+            Err(e) => (
+                StatusCode::BAD_GATEWAY,
+                Json(serde_json::json!({"error": {"message": e.to_string()}})),
+            )
+                .into_response(),
+        }
+    })
+    .await
+}
+
 // ============================================================================
 // 流式传输支持
 // ============================================================================
@@ -1703,6 +2069,12 @@ pub fn get_stream_format_for_credential(credential: &ProviderCredential) -> Stre
     }
 }
 
+/// 按应用配置创建流式配置
+async fn stream_config(state: &AppState) -> StreamConfig {
+    StreamConfig::new()
+        .with_passthrough_unknown_events(*state.stream_passthrough_unknown_events.read().await)
+}
+
 /// 处理流式响应
 ///
 /// 使用 StreamManager 处理流式响应，集成 Flow Monitor。
@@ -1729,8 +2101,9 @@ pub async fn handle_streaming_response(
     target_format: StreamingFormat,
     model: &str,
 ) -> Response {
+    let source_stream = track_active_flow(state, flow_id, source_stream).await;
     // 创建流式管理器
-    let manager = StreamManager::with_default_config();
+    let manager = StreamManager::new(stream_config(state).await);
 
     // 创建流式上下文
     let context = StreamContext::new(
@@ -1747,37 +2120,10 @@ pub async fn handle_streaming_response(
     // 创建带回调的流式处理
     let managed_stream = if let Some(fid) = flow_id_for_callback {
         // 使用带回调的流式处理，集成 Flow Monitor
-        let on_chunk = move |event: &str, _metrics: &crate::streaming::StreamMetrics| {
-            // 解析 SSE 事件并调用 process_chunk
-            // SSE 格式: "event: xxx\ndata: {...}\n\n"
-            let lines: Vec<&str> = event.lines().collect();
-            let mut event_type: Option<&str> = None;
-            let mut data: Option<&str> = None;
-
-            for line in lines {
-                if line.starts_with("event: ") {
-                    event_type = Some(&line[7..]);
-                } else if line.starts_with("data: ") {
-                    data = Some(&line[6..]);
-                }
-            }
-
-            if let Some(d) = data {
-                // 使用 tokio::spawn 异步调用 process_chunk
-                let flow_monitor_clone = flow_monitor.clone();
-                let fid_clone = fid.clone();
-                let event_type_owned = event_type.map(|s| s.to_string());
-                let data_owned = d.to_string();
-
-                tokio::spawn(async move {
-                    flow_monitor_clone
-                        .process_chunk(&fid_clone, event_type_owned.as_deref(), &data_owned)
-                        .await;
-                });
-            }
-        };
+        let on_chunk = flow_chunk_forwarder(flow_monitor, fid);
 
         let stream = manager.handle_stream_with_callback(context, source_stream, on_chunk);
+        let stream = with_keepalive(stream, manager.config(), target_format);
 
         // 转换为 Body 流
         let body_stream = stream.map(|result| -> Result<axum::body::Bytes, std::io::Error> {
@@ -1791,6 +2137,7 @@ pub async fn handle_streaming_response(
     } else {
         // 没有 flow_id，使用普通流式处理
         let stream = manager.handle_stream(context, source_stream);
+        let stream = with_keepalive(stream, manager.config(), target_format);
 
         let body_stream = stream.map(|result| -> Result<axum::body::Bytes, std::io::Error> {
             match result {
@@ -1821,6 +2168,57 @@ pub async fn handle_streaming_response(
         })
 }
 
+/// 关联活跃 Flow：统计从上游收到的字节数，并在 Flow 被强制取消时结束上游流
+async fn track_active_flow(
+    state: &AppState,
+    flow_id: Option<&str>,
+    source_stream: StreamResponse,
+) -> StreamResponse {
+    let handle = match flow_id {
+        Some(fid) => state.flow_monitor.active_flow_handle(fid).await,
+        None => None,
+    };
+    let Some(handle) = handle else {
+        return source_stream;
+    };
+    let cancelled = handle.cancel_token.clone().cancelled_owned();
+    Box::pin(
+        source_stream
+            .inspect(move |chunk| {
+                if let Ok(bytes) = chunk {
+                    handle.record_bytes(bytes.len());
+                }
+            })
+            .take_until(cancelled),
+    )
+}
+
+/// 创建把转换后的流式输出交给 Flow Monitor 的回调
+///
+/// 回调是同步的，每个 chunk 单独 spawn 会让 `process_chunk` 乱序执行，重建的内容因此
+/// 不稳定；这里经通道由单个任务按到达顺序处理。一次输出可能包含多个事件（直通时为
+/// 整段上游数据），逐个解析，不只取最后一个 `data:` 行。
+fn flow_chunk_forwarder(
+    flow_monitor: Arc<FlowMonitor>,
+    flow_id: String,
+) -> impl FnMut(&str, &crate::streaming::StreamMetrics) + Send + 'static {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<SseEvent>();
+    tokio::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            flow_monitor
+                .process_chunk(&flow_id, event.event.as_deref(), &event.data)
+                .await;
+        }
+    });
+
+    move |output: &str, _metrics: &crate::streaming::StreamMetrics| {
+        let output = output.replace("\r\n", "\n");
+        for event in output.split("\n\n").filter_map(parse_sse_block) {
+            let _ = tx.send(event);
+        }
+    }
+}
+
 /// 处理流式响应（带超时）
 ///
 /// 与 `handle_streaming_response` 类似，但添加了超时保护。
@@ -1849,10 +2247,12 @@ pub async fn handle_streaming_response_with_timeout(
     model: &str,
     timeout_ms: u64,
 ) -> Response {
+    let source_stream = track_active_flow(state, flow_id, source_stream).await;
     use futures::stream::BoxStream;
 
     // 创建带超时配置的流式管理器
-    let config = StreamConfig::new()
+    let config = stream_config(state)
+        .await
         .with_timeout_ms(timeout_ms)
         .with_chunk_timeout_ms(30_000); // 30 秒 chunk 超时
 
@@ -1873,32 +2273,7 @@ pub async fn handle_streaming_response_with_timeout(
     // 创建带超时的流式处理，使用 BoxStream 统一类型
     let timeout_stream: BoxStream<'static, Result<String, crate::streaming::StreamError>> =
         if let Some(fid) = flow_id_for_callback {
-            let on_chunk = move |event: &str, _metrics: &crate::streaming::StreamMetrics| {
-                let lines: Vec<&str> = event.lines().collect();
-                let mut event_type: Option<&str> = None;
-                let mut data: Option<&str> = None;
-
-                for line in lines {
-                    if line.starts_with("event: ") {
-                        event_type = Some(&line[7..]);
-                    } else if line.starts_with("data: ") {
-                        data = Some(&line[6..]);
-                    }
-                }
-
-                if let Some(d) = data {
-                    let flow_monitor_clone = flow_monitor.clone();
-                    let fid_clone = fid.clone();
-                    let event_type_owned = event_type.map(|s| s.to_string());
-                    let data_owned = d.to_string();
-
-                    tokio::spawn(async move {
-                        flow_monitor_clone
-                            .process_chunk(&fid_clone, event_type_owned.as_deref(), &data_owned)
-                            .await;
-                    });
-                }
-            };
+            let on_chunk = flow_chunk_forwarder(flow_monitor, fid);
 
             let stream = manager.handle_stream_with_callback(context, source_stream, on_chunk);
             Box::pin(crate::streaming::with_timeout(stream, &config))
@@ -1907,6 +2282,9 @@ pub async fn handle_streaming_response_with_timeout(
             Box::pin(crate::streaming::with_timeout(stream, &config))
         };
 
+    // 上游空闲时插入保活注释
+    let timeout_stream = with_keepalive(timeout_stream, &config, target_format);
+
     // 转换为 Body 流
     let body_stream = timeout_stream.map(|result| -> Result<axum::body::Bytes, std::io::Error> {
         match result {
@@ -1934,6 +2312,90 @@ pub async fn handle_streaming_response_with_timeout(
         })
 }
 
+/// 处理流式响应并缓冲为非流式 JSON
+///
+/// 上游以流式返回，逐 chunk 转换为目标格式并交给 Flow Monitor（记录 TTFB 和逐 chunk 指标），
+/// 流结束后把累积的内容作为一个完整的非流式响应返回，用于不支持 SSE 的客户端。
+/// 响应中的 usage 取自流中的用量 chunk。
+///
+/// # 参数
+/// - `state`: 应用状态
+/// - `flow_id`: Flow ID
+/// - `source_stream`: 源字节流
+/// - `source_format`: 源流格式
+/// - `target_format`: 目标流格式（决定返回的 JSON 格式）
+/// - `model`: 模型名称
+/// - `timeout_ms`: 超时时间（毫秒）
+///
+/// # 返回
+/// JSON 格式的 HTTP 响应
+pub async fn handle_buffered_streaming_response(
+    state: &AppState,
+    flow_id: Option<&str>,
+    source_stream: StreamResponse,
+    source_format: StreamingFormat,
+    target_format: StreamingFormat,
+    model: &str,
+    timeout_ms: u64,
+) -> Response {
+    let source_stream = track_active_flow(state, flow_id, source_stream).await;
+    let config = stream_config(state)
+        .await
+        .with_timeout_ms(timeout_ms)
+        .with_chunk_timeout_ms(30_000);
+    let manager = StreamManager::new(config);
+    let context = StreamContext::new(
+        flow_id.map(|s| s.to_string()),
+        source_format,
+        target_format,
+        model,
+    );
+
+    // 按目标格式重建 Flow 响应，usage 取自流中的用量 chunk
+    if let Some(fid) = flow_id {
+        let rebuild_format = match target_format {
+            StreamingFormat::OpenAiSse => StreamFormat::OpenAI,
+            StreamingFormat::AnthropicSse => StreamFormat::Anthropic,
+            StreamingFormat::GeminiSse => StreamFormat::Gemini,
+            StreamingFormat::AwsEventStream => StreamFormat::Unknown,
+        };
+        state.flow_monitor.set_streaming(fid, rebuild_format).await;
+    }
+
+    let mut stream = Box::pin(manager.handle_stream_with_timeout(context, source_stream));
+    let mut accumulator = StreamAccumulator::new(target_format, model);
+    while let Some(result) = stream.next().await {
+        let text = match result {
+            Ok(event) => event,
+            Err(e) => e.to_sse_error(),
+        };
+        // 逐 chunk 按到达顺序交给 Flow Monitor，首个 chunk 的时间即 TTFB
+        for event in accumulator.push(&text) {
+            if let Some(fid) = flow_id {
+                state
+                    .flow_monitor
+                    .process_chunk(fid, event.event.as_deref(), &event.data)
+                    .await;
+            }
+        }
+    }
+
+    match accumulator.finish() {
+        Ok(body) => Json(body).into_response(),
+        Err(e) => {
+            let status = e
+                .status_code()
+                .and_then(|code| StatusCode::from_u16(code).ok())
+                .unwrap_or(StatusCode::BAD_GATEWAY);
+            (
+                status,
+                Json(serde_json::json!({"error": {"message": e.to_string()}})),
+            )
+                .into_response()
+        }
+    }
+}
+
 /// 将 reqwest 响应转换为 StreamResponse
 ///
 /// 用于将 Provider 的 HTTP 响应转换为统一的流式响应类型。
@@ -1983,8 +2445,10 @@ pub async fn handle_streaming_with_disconnect_detection(
 ) -> Response {
     use futures::StreamExt;
 
+    let source_stream = track_active_flow(state, flow_id, source_stream).await;
+
     // 创建流式管理器
-    let manager = StreamManager::with_default_config();
+    let manager = StreamManager::new(stream_config(state).await);
 
     // 创建流式上下文
     let context = StreamContext::new(
@@ -2006,38 +2470,14 @@ pub async fn handle_streaming_with_disconnect_detection(
         'static,
         Result<String, crate::streaming::StreamError>,
     > = if let Some(fid) = flow_id_for_callback {
-        let on_chunk = move |event: &str, _metrics: &crate::streaming::StreamMetrics| {
-            let lines: Vec<&str> = event.lines().collect();
-            let mut event_type: Option<&str> = None;
-            let mut data: Option<&str> = None;
-
-            for line in lines {
-                if line.starts_with("event: ") {
-                    event_type = Some(&line[7..]);
-                } else if line.starts_with("data: ") {
-                    data = Some(&line[6..]);
-                }
-            }
-
-            if let Some(d) = data {
-                let flow_monitor_clone = flow_monitor.clone();
-                let fid_clone = fid.clone();
-                let event_type_owned = event_type.map(|s| s.to_string());
-                let data_owned = d.to_string();
-
-                tokio::spawn(async move {
-                    flow_monitor_clone
-                        .process_chunk(&fid_clone, event_type_owned.as_deref(), &data_owned)
-                        .await;
-                });
-            }
-        };
+        let on_chunk = flow_chunk_forwarder(flow_monitor, fid);
 
         Box::pin(manager.handle_stream_with_callback(context, source_stream, on_chunk))
     } else {
         // 没有 flow_id，使用普通流式处理
         Box::pin(manager.handle_stream(context, source_stream))
     };
+    let managed_stream = with_keepalive(managed_stream, manager.config(), target_format);
 
     // 如果有取消令牌，创建一个可取消的流
     let body_stream = if let Some(token) = cancel_token {
@@ -27,10 +27,12 @@ use crate::processor::RequestContext;
 use crate::providers::{
     AntigravityProvider, ClaudeCustomProvider, KiroProvider, OpenAICustomProvider,
 };
+use crate::server::handlers::apply_upstream_proxy;
 use crate::server::AppState;
 use crate::server_utils::parse_cw_response;
 use crate::websocket::{
-    WsApiRequest, WsApiResponse, WsEndpoint, WsError, WsFlowEvent, WsMessage as WsProtoMessage,
+    negotiate_subprotocol, MessageProcessor, WsApiRequest, WsApiResponse, WsEndpoint, WsError,
+    WsFlowEvent, WsMessage as WsProtoMessage, WsStreamKind, WsSubprotocol,
 };
 
 /// WebSocket 查询参数
@@ -72,7 +74,7 @@ pub async fn ws_upgrade_handler(
         }
     };
 
-    if key != state.api_key {
+    if state.api_keys.resolve(key).is_none() {
         return axum::http::Response::builder()
             .status(401)
             .body(Body::from("Invalid API key"))
@@ -86,18 +88,25 @@ pub async fn ws_upgrade_handler(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
-    ws.on_upgrade(move |socket| handle_websocket(socket, state, client_info))
+    let (ws, subprotocol) = negotiate_subprotocol(ws, &headers);
+    ws.on_upgrade(move |socket| handle_websocket(socket, state, client_info, subprotocol))
 }
 
 /// 处理 WebSocket 连接
-pub async fn handle_websocket(socket: WebSocket, state: AppState, client_info: Option<String>) {
+pub async fn handle_websocket(
+    socket: WebSocket,
+    state: AppState,
+    client_info: Option<String>,
+    subprotocol: Option<WsSubprotocol>,
+) {
     let conn_id = uuid::Uuid::new_v4().to_string();
 
     // 注册连接
-    if let Err(e) = state
-        .ws_manager
-        .register(conn_id.clone(), client_info.clone())
-    {
+    if let Err(e) = state.ws_manager.register_with_subprotocol(
+        conn_id.clone(),
+        client_info.clone(),
+        subprotocol,
+    ) {
         state.logs.write().await.add(
             "error",
             &format!("[WS] Failed to register connection: {}", e.message),
@@ -120,54 +129,83 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState, client_info: O
     // Flow 事件订阅状态
     let flow_subscribed = Arc::new(std::sync::atomic::AtomicBool::new(false));
 
-    // 启动 Flow 事件转发任务
+    // 启动 Flow 事件转发任务（按配置的背压策略推送）
     let flow_sender = sender.clone();
     let flow_subscribed_clone = flow_subscribed.clone();
-    let flow_monitor = state.flow_monitor.clone();
+    let flow_receiver = state.flow_monitor.subscribe();
     let conn_id_clone = conn_id.clone();
-    let _logs_clone = state.logs.clone();
+    let flow_forwarder = state
+        .ws_manager
+        .stream_forwarder(format!("flow-events-{}", conn_id), WsStreamKind::Events);
 
     let flow_task = tokio::spawn(async move {
-        let mut flow_receiver = flow_monitor.subscribe();
-
-        loop {
-            match flow_receiver.recv().await {
-                Ok(event) => {
-                    // 只有在订阅状态下才转发事件
-                    if !flow_subscribed_clone.load(std::sync::atomic::Ordering::Relaxed) {
-                        continue;
-                    }
+        let forwarder = match flow_forwarder {
+            Ok(forwarder) => forwarder,
+            Err(e) => {
+                tracing::warn!(
+                    "[WS] Flow event forwarding disabled for connection {}: {}",
+                    &conn_id_clone[..8],
+                    e.message
+                );
+                return;
+            }
+        };
 
-                    // 转换为 WebSocket 消息
-                    let ws_event: WsFlowEvent = event.into();
-                    let ws_msg = WsProtoMessage::FlowEvent(ws_event);
+        // 只有在订阅状态下才转发事件
+        let events = Box::pin(futures::stream::unfold(
+            flow_receiver,
+            move |mut flow_receiver| {
+                let subscribed = flow_subscribed_clone.clone();
+                async move {
+                    loop {
+                        match flow_receiver.recv().await {
+                            Ok(event) => {
+                                if !subscribed.load(std::sync::atomic::Ordering::Relaxed) {
+                                    continue;
+                                }
+                                let ws_event: WsFlowEvent = event.into();
+                                return Some((WsProtoMessage::FlowEvent(ws_event), flow_receiver));
+                            }
+                            Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                                tracing::warn!("[WS] Flow event receiver lagged by {} messages", n);
+                            }
+                            Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+                        }
+                    }
+                }
+            },
+        ));
 
-                    if let Ok(msg_text) = serde_json::to_string(&ws_msg) {
+        let (tx, mut rx) = forwarder.create_channel();
+        // 接收端随发送失败一起释放，使转发端感知通道关闭
+        let drain = {
+            let flow_sender = flow_sender.clone();
+            let conn_id = conn_id_clone.clone();
+            async move {
+                while let Some(ws_msg) = rx.recv().await {
+                    if let Ok(msg_text) = MessageProcessor::serialize(&ws_msg, subprotocol) {
                         let mut sender_guard = flow_sender.lock().await;
                         if sender_guard.send(WsMessage::Text(msg_text)).await.is_err() {
                             tracing::debug!(
                                 "[WS] Flow event send failed for connection {}",
-                                &conn_id_clone[..8]
+                                &conn_id[..8]
                             );
                             break;
                         }
                     }
                 }
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
-                    tracing::warn!(
-                        "[WS] Flow event receiver lagged by {} messages for connection {}",
-                        n,
-                        &conn_id_clone[..8]
-                    );
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                    tracing::debug!(
-                        "[WS] Flow event channel closed for connection {}",
-                        &conn_id_clone[..8]
-                    );
-                    break;
-                }
             }
+        };
+
+        let (result, _) = tokio::join!(forwarder.forward_messages(events, tx), drain);
+        if let Err(e) = result {
+            // Disconnect 策略下客户端消费过慢，主动关闭连接
+            tracing::warn!(
+                "[WS] Flow event forwarding stopped for connection {}: {}",
+                &conn_id_clone[..8],
+                e.message
+            );
+            let _ = flow_sender.lock().await.send(WsMessage::Close(None)).await;
         }
     });
 
@@ -184,7 +222,8 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState, client_info: O
                         text.len(),
                         MAX_MESSAGE_SIZE
                     )));
-                    let error_text = serde_json::to_string(&error).unwrap_or_default();
+                    let error_text =
+                        MessageProcessor::serialize(&error, subprotocol).unwrap_or_default();
                     let mut sender_guard = sender.lock().await;
                     let _ = sender_guard.send(WsMessage::Text(error_text)).await;
                     break;
@@ -198,7 +237,8 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState, client_info: O
                         let response =
                             handle_ws_message(&state, &conn_id, ws_msg, &flow_subscribed).await;
                         if let Some(resp) = response {
-                            let resp_text = serde_json::to_string(&resp).unwrap_or_default();
+                            let resp_text =
+                                MessageProcessor::serialize(&resp, subprotocol).unwrap_or_default();
                             let mut sender_guard = sender.lock().await;
                             if sender_guard.send(WsMessage::Text(resp_text)).await.is_err() {
                                 break;
@@ -211,7 +251,8 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState, client_info: O
                             "Failed to parse message: {}",
                             e
                         )));
-                        let error_text = serde_json::to_string(&error).unwrap_or_default();
+                        let error_text =
+                            MessageProcessor::serialize(&error, subprotocol).unwrap_or_default();
                         let mut sender_guard = sender.lock().await;
                         if sender_guard
                             .send(WsMessage::Text(error_text))
@@ -228,7 +269,8 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState, client_info: O
                 let error = WsProtoMessage::Error(WsError::invalid_message(
                     "Binary messages not supported",
                 ));
-                let error_text = serde_json::to_string(&error).unwrap_or_default();
+                let error_text =
+                    MessageProcessor::serialize(&error, subprotocol).unwrap_or_default();
                 let mut sender_guard = sender.lock().await;
                 if sender_guard
                     .send(WsMessage::Text(error_text))
@@ -327,6 +369,14 @@ async fn handle_ws_message(
             )))
         }
         WsProtoMessage::Request(request) => {
+            // 请求速率超限时直接回复错误，不转发请求
+            if let Err(e) = state
+                .ws_manager
+                .check_rate_limit(conn_id, &request.request_id)
+            {
+                return Some(WsProtoMessage::Error(e));
+            }
+
             state.logs.write().await.add(
                 "info",
                 &format!(
@@ -676,6 +726,7 @@ pub async fn call_provider_openai_for_ws(
     match &credential.credential {
         CredentialData::KiroOAuth { creds_file_path } => {
             let mut kiro = KiroProvider::new();
+            apply_upstream_proxy(state, "kiro", &mut kiro.client);
             if let Err(e) = kiro.load_credentials_from_path(creds_file_path).await {
                 if let Some(db) = &state.db {
                     let _ = state.pool_service.mark_unhealthy(
@@ -776,7 +827,8 @@ pub async fn call_provider_openai_for_ws(
             }
         }
         CredentialData::OpenAIKey { api_key, base_url } => {
-            let provider = OpenAICustomProvider::with_config(api_key.clone(), base_url.clone());
+            let mut provider = OpenAICustomProvider::with_config(api_key.clone(), base_url.clone());
+            apply_upstream_proxy(state, "openai", &mut provider.client);
             let resp = match provider.call_api(request).await {
                 Ok(r) => r,
                 Err(e) => {
@@ -820,7 +872,8 @@ pub async fn call_provider_openai_for_ws(
                 actual_base_url,
                 &credential.uuid[..8]
             );
-            let provider = ClaudeCustomProvider::with_config(api_key.clone(), base_url.clone());
+            let mut provider = ClaudeCustomProvider::with_config(api_key.clone(), base_url.clone());
+            apply_upstream_proxy(state, "claude", &mut provider.client);
             match provider.call_openai_api(request).await {
                 Ok(result) => {
                     // 记录成功
@@ -940,7 +993,8 @@ pub async fn call_provider_anthropic_for_ws(
                 actual_base_url,
                 &credential.uuid[..8]
             );
-            let provider = ClaudeCustomProvider::with_config(api_key.clone(), base_url.clone());
+            let mut provider = ClaudeCustomProvider::with_config(api_key.clone(), base_url.clone());
+            apply_upstream_proxy(state, "claude", &mut provider.client);
             let resp = match provider.call_api(request).await {
                 Ok(r) => r,
                 Err(e) => {
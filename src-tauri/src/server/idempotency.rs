@@ -0,0 +1,426 @@
+//! 幂等键模块
+//!
+//! 客户端在网络错误后重试时可能导致重复的上游调用。客户端通过 `Idempotency-Key`
+//! 请求头标识同一个逻辑请求：同一调用方（API Key）在 TTL 内使用相同的键重复请求时，
+//! 直接返回首次请求的响应而不再调用上游；首次请求仍在处理中时，重复请求等待其完成。
+//!
+//! 只缓存成功的非流式响应。首次请求失败或被取消时不缓存，等待中的请求会重新发起调用。
+//!
+//! 每个键记录首次请求体的摘要，同一个键携带不同请求体时视为客户端错误，不重放也不调用上游。
+//! 摘要按 Flow 监控的内容规范化配置计算（见 [`ContentNormalizer`]），只有注入的时间戳等
+//! 被规范化规则移除的差异不算作不同的请求体。
+
+use crate::flow_monitor::ContentNormalizer;
+use axum::{
+    body::{Body, Bytes},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// 幂等键请求头
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// 重放响应上附加的响应头，值为 `true`
+pub const IDEMPOTENT_REPLAY_HEADER: &str = "x-idempotent-replay";
+
+/// 幂等键的最大长度
+const MAX_KEY_LEN: usize = 255;
+
+/// 缓存响应的默认有效期
+const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+/// 默认最多缓存的响应数
+const DEFAULT_CAPACITY: usize = 1000;
+
+/// 单个响应体的缓存上限，超过时不缓存
+pub const MAX_CACHED_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// 缓存的响应，足以原样重建首次请求的响应
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    /// 首次请求对应的 Flow ID（未捕获时为空）
+    pub flow_id: Option<String>,
+}
+
+impl StoredResponse {
+    /// 重建响应
+    pub fn to_response(&self) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+    }
+}
+
+/// 请求体摘要
+pub type RequestFingerprint = [u8; 32];
+
+#[derive(Debug)]
+enum Slot {
+    /// 首次请求处理中，发送端在其结束时释放
+    InFlight {
+        pending: watch::Receiver<()>,
+        fingerprint: RequestFingerprint,
+    },
+    Done {
+        response: Arc<StoredResponse>,
+        fingerprint: RequestFingerprint,
+        expires_at: Instant,
+    },
+}
+
+type Entries = Arc<Mutex<HashMap<String, Slot>>>;
+
+/// 幂等键查找结果
+#[derive(Debug)]
+pub enum IdempotencyStart {
+    /// 命中缓存，直接返回已有响应
+    Replay(Arc<StoredResponse>),
+    /// 首次请求，由调用方调用上游并通过守卫写入结果
+    Lead(IdempotencyGuard),
+    /// 键已被请求体不同的请求使用
+    Mismatch,
+}
+
+/// 幂等响应缓存
+#[derive(Debug)]
+pub struct IdempotencyCache {
+    entries: Entries,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL, DEFAULT_CAPACITY)
+    }
+}
+
+impl IdempotencyCache {
+    /// 创建缓存
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// 从请求头读取幂等键（为空或过长时忽略）
+    pub fn key_from_headers(headers: &HeaderMap) -> Option<&str> {
+        headers
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LEN)
+    }
+
+    /// 按调用方和端点限定幂等键的作用域
+    ///
+    /// `scope` 应唯一标识调用方的 Key（如 Key 的摘要），标签可能被多个 Key 共用。
+    pub fn scoped_key(scope: &str, path: &str, key: &str) -> String {
+        format!("{}\u{0}{}\u{0}{}", scope, path, key)
+    }
+
+    /// 计算规范化后的请求体摘要
+    pub fn fingerprint(normalizer: &ContentNormalizer, body: &str) -> RequestFingerprint {
+        Sha256::digest(normalizer.normalize(body).as_bytes()).into()
+    }
+
+    /// 查找幂等键
+    ///
+    /// 已有未过期的响应时返回 `Replay`；首次请求仍在处理中时等待其结束后重新查找；
+    /// 否则登记为处理中并返回 `Lead`。已登记的请求体摘要与 `fingerprint` 不同时返回 `Mismatch`。
+    pub async fn begin(&self, key: String, fingerprint: RequestFingerprint) -> IdempotencyStart {
+        loop {
+            let mut pending = {
+                let mut entries = self.entries.lock();
+                match entries.get(&key) {
+                    Some(Slot::Done {
+                        response,
+                        fingerprint: stored,
+                        expires_at,
+                    }) if *expires_at > Instant::now() => {
+                        if *stored != fingerprint {
+                            return IdempotencyStart::Mismatch;
+                        }
+                        return IdempotencyStart::Replay(response.clone());
+                    }
+                    Some(Slot::InFlight {
+                        pending,
+                        fingerprint: stored,
+                    }) => {
+                        if *stored != fingerprint {
+                            return IdempotencyStart::Mismatch;
+                        }
+                        pending.clone()
+                    }
+                    _ => {
+                        let (done, pending) = watch::channel(());
+                        entries.insert(
+                            key.clone(),
+                            Slot::InFlight {
+                                pending,
+                                fingerprint,
+                            },
+                        );
+                        return IdempotencyStart::Lead(IdempotencyGuard {
+                            entries: self.entries.clone(),
+                            key,
+                            fingerprint,
+                            ttl: self.ttl,
+                            capacity: self.capacity,
+                            completed: false,
+                            _done: done,
+                        });
+                    }
+                }
+            };
+            // 发送端从不发送，释放时 changed() 返回错误，此时首次请求已结束
+            let _ = pending.changed().await;
+        }
+    }
+
+    /// 当前缓存的响应数（不含处理中的请求）
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .values()
+            .filter(|slot| matches!(slot, Slot::Done { .. }))
+            .count()
+    }
+
+    /// 是否没有缓存的响应
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 首次请求的守卫
+///
+/// 调用 `complete` 缓存响应；未调用就释放（请求失败或被取消）时移除处理中的登记。
+/// 两种情况都会唤醒等待中的重复请求。
+#[derive(Debug)]
+pub struct IdempotencyGuard {
+    entries: Entries,
+    key: String,
+    fingerprint: RequestFingerprint,
+    ttl: Duration,
+    capacity: usize,
+    completed: bool,
+    /// 在守卫释放时释放，唤醒等待者（必须在更新缓存之后释放，因此放在最后）
+    _done: watch::Sender<()>,
+}
+
+impl IdempotencyGuard {
+    /// 缓存首次请求的响应
+    pub fn complete(mut self, response: StoredResponse) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        entries.retain(|_, slot| match slot {
+            Slot::InFlight { .. } => true,
+            Slot::Done { expires_at, .. } => *expires_at > now,
+        });
+
+        // 达到上限时淘汰最早过期的响应
+        let mut done: Vec<(Instant, String)> = entries
+            .iter()
+            .filter_map(|(key, slot)| match slot {
+                Slot::Done { expires_at, .. } => Some((*expires_at, key.clone())),
+                Slot::InFlight { .. } => None,
+            })
+            .collect();
+        if done.len() >= self.capacity {
+            done.sort();
+            let excess = done.len() + 1 - self.capacity;
+            for (_, key) in done.into_iter().take(excess) {
+                entries.remove(&key);
+            }
+        }
+
+        entries.insert(
+            self.key.clone(),
+            Slot::Done {
+                response: Arc::new(response),
+                fingerprint: self.fingerprint,
+                expires_at: now + self.ttl,
+            },
+        );
+        drop(entries);
+        self.completed = true;
+    }
+}
+
+impl Drop for IdempotencyGuard {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        if matches!(entries.get(&self.key), Some(Slot::InFlight { .. })) {
+            entries.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::ContentNormalizationConfig;
+
+    const BODY: RequestFingerprint = [0; 32];
+
+    fn stored(body: &str) -> StoredResponse {
+        StoredResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from(body.to_string()),
+            flow_id: Some("flow-1".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_after_complete() {
+        let cache = IdempotencyCache::default();
+        let IdempotencyStart::Lead(guard) = cache.begin("k".to_string(), BODY).await else {
+            panic!("first request should lead");
+        };
+        guard.complete(stored("hello"));
+
+        let IdempotencyStart::Replay(response) = cache.begin("k".to_string(), BODY).await else {
+            panic!("repeat should replay");
+        };
+        assert_eq!(response.body, Bytes::from("hello"));
+        assert_eq!(response.flow_id.as_deref(), Some("flow-1"));
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_in_flight_request() {
+        let cache = Arc::new(IdempotencyCache::default());
+        let IdempotencyStart::Lead(guard) = cache.begin("k".to_string(), BODY).await else {
+            panic!("first request should lead");
+        };
+
+        let waiter = {
+            let cache = cache.clone();
+            tokio::spawn(async move { cache.begin("k".to_string(), BODY).await })
+        };
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        guard.complete(stored("done"));
+        match waiter.await.unwrap() {
+            IdempotencyStart::Replay(response) => assert_eq!(response.body, Bytes::from("done")),
+            _ => panic!("waiter should replay"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dropped_guard_lets_waiter_lead() {
+        let cache = Arc::new(IdempotencyCache::default());
+        let IdempotencyStart::Lead(guard) = cache.begin("k".to_string(), BODY).await else {
+            panic!("first request should lead");
+        };
+
+        let waiter = {
+            let cache = cache.clone();
+            tokio::spawn(async move { cache.begin("k".to_string(), BODY).await })
+        };
+        tokio::task::yield_now().await;
+        drop(guard);
+
+        assert!(matches!(waiter.await.unwrap(), IdempotencyStart::Lead(_)));
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_capacity_and_ttl() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60), 2);
+        for key in ["a", "b", "c"] {
+            let IdempotencyStart::Lead(guard) = cache.begin(key.to_string(), BODY).await else {
+                panic!("{} should lead", key);
+            };
+            guard.complete(stored(key));
+        }
+        assert_eq!(cache.len(), 2);
+        assert!(matches!(
+            cache.begin("a".to_string(), BODY).await,
+            IdempotencyStart::Lead(_)
+        ));
+
+        let expired = IdempotencyCache::new(Duration::ZERO, 10);
+        let IdempotencyStart::Lead(guard) = expired.begin("k".to_string(), BODY).await else {
+            panic!("first request should lead");
+        };
+        guard.complete(stored("old"));
+        assert!(matches!(
+            expired.begin("k".to_string(), BODY).await,
+            IdempotencyStart::Lead(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_body_is_rejected() {
+        let cache = IdempotencyCache::default();
+        let other =
+            IdempotencyCache::fingerprint(&ContentNormalizer::default(), r#"{"model":"other"}"#);
+        let IdempotencyStart::Lead(guard) = cache.begin("k".to_string(), BODY).await else {
+            panic!("first request should lead");
+        };
+        // 处理中和已缓存时都拒绝不同的请求体
+        assert!(matches!(
+            cache.begin("k".to_string(), other).await,
+            IdempotencyStart::Mismatch
+        ));
+        guard.complete(stored("hello"));
+        assert!(matches!(
+            cache.begin("k".to_string(), other).await,
+            IdempotencyStart::Mismatch
+        ));
+        assert!(matches!(
+            cache.begin("k".to_string(), BODY).await,
+            IdempotencyStart::Replay(_)
+        ));
+    }
+
+    #[test]
+    fn test_fingerprint_uses_content_normalization() {
+        let first = r#"{"messages":[{"role":"user","content":"now 2024-05-01T12:30:45Z hi"}]}"#;
+        let retry = r#"{"messages":[{"role":"user","content":"now 2024-05-01T12:31:02Z hi"}]}"#;
+        assert_ne!(
+            IdempotencyCache::fingerprint(&ContentNormalizer::default(), first),
+            IdempotencyCache::fingerprint(&ContentNormalizer::default(), retry)
+        );
+
+        let normalizer = ContentNormalizer::new(&ContentNormalizationConfig {
+            strip_timestamps: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            IdempotencyCache::fingerprint(&normalizer, first),
+            IdempotencyCache::fingerprint(&normalizer, retry)
+        );
+    }
+
+    #[test]
+    fn test_key_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert!(IdempotencyCache::key_from_headers(&headers).is_none());
+        headers.insert(IDEMPOTENCY_KEY_HEADER, " retry-1 ".parse().unwrap());
+        assert_eq!(
+            IdempotencyCache::key_from_headers(&headers),
+            Some("retry-1")
+        );
+        headers.insert(IDEMPOTENCY_KEY_HEADER, "x".repeat(300).parse().unwrap());
+        assert!(IdempotencyCache::key_from_headers(&headers).is_none());
+    }
+}
@@ -1,10 +1,14 @@
 //! HTTP API 服务器
 
+pub mod api_keys;
 pub mod client_detector;
+pub mod grpc;
+pub mod idempotency;
+pub mod routing_gate;
 
 use crate::config::{
-    Config, ConfigChangeEvent, ConfigChangeKind, ConfigManager, EndpointProvidersConfig,
-    FileWatcher, HotReloadManager, ReloadResult,
+    Config, ConfigChangeEvent, ConfigChangeKind, ConfigManager, ConfigSection,
+    EndpointProvidersConfig, FileWatcher, HotReloadManager, ReloadResult,
 };
 use crate::converter::anthropic_to_openai::convert_anthropic_to_openai;
 use crate::credential::CredentialSyncService;
@@ -17,13 +21,14 @@ use crate::models::anthropic::*;
 use crate::models::openai::*;
 use crate::models::provider_pool_model::CredentialData;
 use crate::models::route_model::{RouteInfo, RouteListResponse};
-use crate::processor::{RequestContext, RequestProcessor};
+use crate::processor::{RequestContext, RequestProcessor, ShadowStep};
 use crate::providers::antigravity::AntigravityProvider;
 use crate::providers::claude_custom::ClaudeCustomProvider;
 use crate::providers::gemini::GeminiProvider;
 use crate::providers::kiro::KiroProvider;
 use crate::providers::openai_custom::OpenAICustomProvider;
 use crate::providers::qwen::QwenProvider;
+use crate::proxy::UpstreamProxies;
 use crate::server_utils::{
     build_anthropic_response, build_anthropic_stream_response, build_gemini_native_request, health,
     models, parse_cw_response,
@@ -31,6 +36,7 @@ use crate::server_utils::{
 use crate::services::provider_pool_service::ProviderPoolService;
 use crate::services::token_cache_service::TokenCacheService;
 use crate::websocket::{WsConfig, WsConnectionManager, WsStats};
+use api_keys::ApiKeyStore;
 use axum::{
     body::Body,
     extract::{DefaultBodyLimit, Path, State},
@@ -39,6 +45,8 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
+use idempotency::IdempotencyCache;
+use routing_gate::{routing_gate_middleware, RoutingGate};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -168,6 +176,8 @@ pub struct ServerState {
     /// 服务器运行时使用的 API key（启动时从配置复制）
     /// 用于 test_api 命令，确保测试使用的 API key 和服务器一致
     pub running_api_key: Option<String>,
+    /// 路由闸门（跨服务器重启保持状态）
+    pub routing_gate: Arc<RoutingGate>,
 }
 
 impl ServerState {
@@ -192,6 +202,7 @@ impl ServerState {
             default_provider_ref,
             shutdown_tx: None,
             running_api_key: None,
+            routing_gate: Arc::new(RoutingGate::new()),
         }
     }
 
@@ -273,6 +284,7 @@ impl ServerState {
         let api_key = self.config.server.api_key.clone();
         let api_key_for_state = api_key.clone(); // 用于保存到 running_api_key
         let default_provider_ref = self.default_provider_ref.clone();
+        let routing_gate = self.routing_gate.clone();
 
         // 重新加载凭证
         let _ = self.kiro_provider.load_credentials().await;
@@ -312,6 +324,7 @@ impl ServerState {
                 shared_logger,
                 shared_flow_monitor,
                 shared_flow_interceptor,
+                routing_gate,
                 Some(config),
                 Some(config_path),
             )
@@ -344,6 +357,7 @@ impl Clone for KiroProvider {
             credentials: self.credentials.clone(),
             client: reqwest::Client::new(),
             creds_path: self.creds_path.clone(),
+            request_headers: self.request_headers.clone(),
         }
     }
 }
@@ -354,6 +368,8 @@ pub mod handlers;
 #[allow(dead_code)]
 pub struct AppState {
     pub api_key: String,
+    /// 客户端 API Key 存储
+    pub api_keys: Arc<ApiKeyStore>,
     pub base_url: String,
     pub default_provider: Arc<RwLock<String>>,
     pub kiro: Arc<RwLock<KiroProvider>>,
@@ -386,6 +402,16 @@ pub struct AppState {
     pub flow_interceptor: Arc<FlowInterceptor>,
     /// 端点 Provider 配置
     pub endpoint_providers: Arc<RwLock<EndpointProvidersConfig>>,
+    /// 路由闸门
+    pub routing_gate: Arc<RoutingGate>,
+    /// 上游代理
+    pub upstream_proxies: Arc<UpstreamProxies>,
+    /// 幂等键响应缓存
+    pub idempotency: Arc<IdempotencyCache>,
+    /// 影子镜像步骤（按请求附加调用器后执行）
+    pub shadow: Arc<ShadowStep>,
+    /// 是否透传无法识别的 SSE 事件
+    pub stream_passthrough_unknown_events: Arc<RwLock<bool>>,
 }
 
 /// 启动配置文件监控
@@ -399,6 +425,7 @@ pub struct AppState {
 /// - 使用 RwLock 进行原子性更新，不会阻塞正在处理的请求
 /// - 服务器继续运行，不需要重启
 /// - HTTP 和 WebSocket 连接保持活跃
+#[allow(clippy::too_many_arguments)]
 async fn start_config_watcher(
     config_path: PathBuf,
     hot_reload_manager: Option<Arc<HotReloadManager>>,
@@ -406,6 +433,11 @@ async fn start_config_watcher(
     logs: Arc<RwLock<LogStore>>,
     db: Option<DbConnection>,
     config_manager: Option<Arc<std::sync::RwLock<ConfigManager>>>,
+    upstream_proxies: Arc<UpstreamProxies>,
+    kiro: Arc<RwLock<KiroProvider>>,
+    api_keys: Arc<ApiKeyStore>,
+    shadow: Arc<ShadowStep>,
+    stream_passthrough_unknown_events: Arc<RwLock<bool>>,
 ) -> Option<FileWatcher> {
     let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<ConfigChangeEvent>();
 
@@ -450,21 +482,60 @@ async fn start_config_watcher(
             if let Some(ref manager) = hot_reload_manager_clone {
                 let result = manager.reload();
                 match &result {
-                    ReloadResult::Success { .. } => {
-                        tracing::info!("[HOT_RELOAD] 配置热重载成功");
-                        logs_clone
-                            .write()
-                            .await
-                            .add("info", "[HOT_RELOAD] 配置热重载成功");
-
-                        // 更新处理器中的组件
+                    ReloadResult::Success {
+                        changed_sections, ..
+                    } => {
+                        let sections = changed_sections
+                            .iter()
+                            .map(ConfigSection::as_str)
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        tracing::info!("[HOT_RELOAD] 配置热重载成功，变更分区: [{}]", sections);
+                        logs_clone.write().await.add(
+                            "info",
+                            &format!("[HOT_RELOAD] 配置热重载成功，变更分区: [{}]", sections),
+                        );
+
+                        // 只更新受变更分区影响的组件，其余组件（及其连接）保持不变
+                        let changed = |section: ConfigSection| changed_sections.contains(&section);
                         let new_config = manager.config();
-                        update_processor_config(&processor_clone, &new_config).await;
+                        update_processor_config(&processor_clone, &new_config, changed_sections)
+                            .await;
 
-                        // 同步凭证池
-                        if let (Some(ref db), Some(ref cfg_manager)) =
-                            (&db_clone, &config_manager_clone)
+                        // 更新上游代理
+                        if changed(ConfigSection::Proxy)
+                            || changed(ConfigSection::Tls)
+                            || changed(ConfigSection::Providers)
                         {
+                            upstream_proxies.update(&new_config);
+                            match upstream_proxies.client_for("kiro") {
+                                Ok(client) => {
+                                    kiro.write().await.client = client.unwrap_or_default()
+                                }
+                                Err(e) => {
+                                    tracing::warn!("[HOT_RELOAD] 创建 Kiro 代理客户端失败: {}", e)
+                                }
+                            }
+                        }
+
+                        // 更新客户端 API Key
+                        if changed(ConfigSection::Server) {
+                            api_keys.update(&new_config);
+                        }
+
+                        // 更新影子镜像和流式响应配置
+                        if changed(ConfigSection::Other) {
+                            shadow.update_config(new_config.shadow.clone());
+                            *stream_passthrough_unknown_events.write().await =
+                                new_config.streaming.passthrough_unknown_events;
+                        }
+
+                        // 同步凭证池
+                        if let (true, Some(ref db), Some(ref cfg_manager)) = (
+                            changed(ConfigSection::Providers),
+                            &db_clone,
+                            &config_manager_clone,
+                        ) {
                             match sync_credential_pool_from_config(db, cfg_manager, &logs_clone)
                                 .await
                             {
@@ -491,6 +562,14 @@ async fn start_config_watcher(
                             }
                         }
                     }
+                    ReloadResult::Rejected { validation, .. } => {
+                        let errors = validation.errors.join("; ");
+                        tracing::warn!("[HOT_RELOAD] 新配置未通过验证，保留当前配置: {}", errors);
+                        logs_clone.write().await.add(
+                            "warn",
+                            &format!("[HOT_RELOAD] 新配置未通过验证，保留当前配置: {}", errors),
+                        );
+                    }
                     ReloadResult::RolledBack { error, .. } => {
                         tracing::warn!("[HOT_RELOAD] 配置热重载失败，已回滚: {}", error);
                         logs_clone.write().await.add(
@@ -534,9 +613,17 @@ async fn start_config_watcher(
 /// - 正在处理的请求不会看到部分更新的状态
 /// - 更新过程不会阻塞新请求的处理
 /// - 现有连接不受影响
-async fn update_processor_config(processor: &RequestProcessor, config: &Config) {
+///
+/// 只重建 `sections` 中发生变化的分区对应的组件。
+async fn update_processor_config(
+    processor: &RequestProcessor,
+    config: &Config,
+    sections: &[ConfigSection],
+) {
+    let changed = |section: ConfigSection| sections.contains(&section);
+
     // 更新注入器规则
-    {
+    if changed(ConfigSection::Injection) {
         let mut injector = processor.injector.write().await;
         injector.clear();
         for rule in &config.injection.rules {
@@ -549,7 +636,7 @@ async fn update_processor_config(processor: &RequestProcessor, config: &Config)
     }
 
     // 更新路由器规则
-    {
+    if changed(ConfigSection::Routing) {
         let mut router = processor.router.write().await;
         router.clear_rules();
         for rule in &config.routing.rules {
@@ -572,25 +659,70 @@ async fn update_processor_config(processor: &RequestProcessor, config: &Config)
     }
 
     // 更新模型映射器
-    {
+    if changed(ConfigSection::Routing) {
         let mut mapper = processor.mapper.write().await;
         mapper.clear();
         for (alias, model) in &config.routing.model_aliases {
             mapper.add_alias(alias, model);
         }
+        mapper.set_param_constraints(crate::router::ParamConstraints::from_map(
+            config.routing.param_constraints.clone(),
+        ));
+        mapper.set_size_downgrades(crate::router::SizeDowngrades::from_map(
+            config.routing.size_downgrades.clone(),
+        ));
+        mapper.set_history_truncations(crate::router::HistoryTruncations::from_map(
+            config.routing.history_truncation.clone(),
+        ));
+        mapper.set_model_defaults(crate::router::ModelDefaults::from_map(
+            config.routing.model_defaults.clone(),
+        ));
+        mapper.set_reasoning_effort(crate::router::ReasoningEffortMappings::from_map(
+            config.routing.reasoning_effort.clone(),
+        ));
         tracing::debug!(
             "[HOT_RELOAD] 模型别名已更新: {} 个别名",
             config.routing.model_aliases.len()
         );
     }
 
+    // 更新会话亲和配置（已有的固定关系保留）
+    if changed(ConfigSection::Routing) {
+        processor
+            .session_affinity
+            .update(config.routing.session_affinity.clone());
+        tracing::debug!(
+            "[HOT_RELOAD] 会话亲和配置已更新: enabled={}",
+            config.routing.session_affinity.enabled
+        );
+    }
+
+    if changed(ConfigSection::Other) {
+        // 更新故障注入配置（启用时输出警告）
+        processor.chaos.update_config(config.chaos.clone());
+
+        // 更新内容过滤规则
+        processor
+            .content_filter
+            .update_config(config.content_filter.clone());
+
+        // 更新 Flow 插件
+        processor.apply_flow_plugins_config(&config.flow_plugins);
+        tracing::debug!(
+            "[HOT_RELOAD] Flow 插件已更新: {:?}",
+            processor.flow_plugins.names()
+        );
+    }
+
     // 注意：重试配置目前不支持热更新，因为 Retrier 是不可变的
     // 如果需要更新重试配置，需要重启服务器
-    tracing::debug!(
-        "[HOT_RELOAD] 重试配置: max_retries={}, base_delay={}ms (需重启生效)",
-        config.retry.max_retries,
-        config.retry.base_delay_ms
-    );
+    if changed(ConfigSection::Retry) {
+        tracing::debug!(
+            "[HOT_RELOAD] 重试配置: max_retries={}, base_delay={}ms (需重启生效)",
+            config.retry.max_retries,
+            config.retry.base_delay_ms
+        );
+    }
 
     tracing::info!("[HOT_RELOAD] 处理器配置更新完成");
 }
@@ -652,7 +784,7 @@ async fn run_server(
     port: u16,
     api_key: &str,
     default_provider: Arc<RwLock<String>>,
-    kiro: KiroProvider,
+    mut kiro: KiroProvider,
     logs: Arc<RwLock<LogStore>>,
     shutdown: oneshot::Receiver<()>,
     pool_service: Arc<ProviderPoolService>,
@@ -665,6 +797,7 @@ async fn run_server(
     shared_logger: Option<Arc<crate::telemetry::RequestLogger>>,
     shared_flow_monitor: Option<Arc<FlowMonitor>>,
     shared_flow_interceptor: Option<Arc<FlowInterceptor>>,
+    routing_gate: Arc<RoutingGate>,
     config: Option<Config>,
     config_path: Option<PathBuf>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -688,6 +821,18 @@ async fn run_server(
         }
     }
 
+    // 注册内置 Flow 插件并应用会话亲和、故障注入、内容过滤配置
+    if let Some(cfg) = &config {
+        processor.apply_flow_plugins_config(&cfg.flow_plugins);
+        processor
+            .session_affinity
+            .update(cfg.routing.session_affinity.clone());
+        processor.chaos.update_config(cfg.chaos.clone());
+        processor
+            .content_filter
+            .update_config(cfg.content_filter.clone());
+    }
+
     // 初始化 WebSocket 管理器
     let ws_manager = Arc::new(WsConnectionManager::new(WsConfig::default()));
     let ws_stats = ws_manager.stats().clone();
@@ -722,6 +867,8 @@ async fn run_server(
     let flow_monitor = shared_flow_monitor
         .unwrap_or_else(|| Arc::new(FlowMonitor::new(FlowMonitorConfig::default(), None)));
 
+    let otlp_monitor = flow_monitor.clone();
+
     // 使用共享的 Flow 拦截器，如果没有则创建新的
     let flow_interceptor =
         shared_flow_interceptor.unwrap_or_else(|| Arc::new(FlowInterceptor::default()));
@@ -734,11 +881,51 @@ async fn run_server(
             .unwrap_or_default(),
     ));
 
+    // 初始化上游代理
+    let upstream_proxies = Arc::new(
+        config
+            .as_ref()
+            .map(UpstreamProxies::from_config)
+            .unwrap_or_default(),
+    );
+    match upstream_proxies.client_for("kiro") {
+        Ok(Some(client)) => kiro.client = client,
+        Ok(None) => {}
+        Err(e) => tracing::warn!("[PROXY] 创建 Kiro 代理客户端失败: {}", e),
+    }
+    let kiro = Arc::new(RwLock::new(kiro));
+
+    // 初始化客户端 API Key
+    let api_keys = Arc::new(
+        config
+            .as_ref()
+            .map(ApiKeyStore::from_config)
+            .unwrap_or_else(|| ApiKeyStore::new(api_key)),
+    );
+
+    // 初始化影子镜像
+    let shadow = Arc::new(ShadowStep::new(
+        config
+            .as_ref()
+            .map(|c| c.shadow.clone())
+            .unwrap_or_default(),
+        flow_monitor.clone(),
+    ));
+
+    // 初始化流式响应配置
+    let stream_passthrough_unknown_events = Arc::new(RwLock::new(
+        config
+            .as_ref()
+            .map(|c| c.streaming.passthrough_unknown_events)
+            .unwrap_or_default(),
+    ));
+
     let state = AppState {
         api_key: api_key.to_string(),
+        api_keys: api_keys.clone(),
         base_url,
         default_provider,
-        kiro: Arc::new(RwLock::new(kiro)),
+        kiro: kiro.clone(),
         logs,
         kiro_refresh_lock: Arc::new(tokio::sync::Mutex::new(())),
         gemini_refresh_lock: Arc::new(tokio::sync::Mutex::new(())),
@@ -757,6 +944,11 @@ async fn run_server(
         flow_monitor,
         flow_interceptor,
         endpoint_providers,
+        routing_gate,
+        upstream_proxies: upstream_proxies.clone(),
+        idempotency: Arc::new(IdempotencyCache::default()),
+        shadow: shadow.clone(),
+        stream_passthrough_unknown_events: stream_passthrough_unknown_events.clone(),
     };
 
     // 启动配置文件监控
@@ -768,6 +960,11 @@ async fn run_server(
             logs_clone,
             db_clone,
             config_manager,
+            upstream_proxies,
+            kiro,
+            api_keys,
+            shadow,
+            stream_passthrough_unknown_events,
         )
         .await
     } else {
@@ -785,6 +982,10 @@ async fn run_server(
 
     let management_routes = Router::new()
         .route("/v0/management/status", get(handlers::management_status))
+        .route(
+            "/v0/management/concurrency",
+            get(handlers::management_concurrency),
+        )
         .route(
             "/v0/management/credentials",
             get(handlers::management_list_credentials),
@@ -805,13 +1006,15 @@ async fn run_server(
             management_config,
         ));
 
-    let app = Router::new()
-        .route("/health", get(health))
-        .route("/v1/models", get(models))
-        .route("/v1/routes", get(list_routes))
+    // 上游 LLM 路由（受路由闸门控制）
+    let gated_routes = Router::new()
         .route("/v1/chat/completions", post(handlers::chat_completions))
+        .route("/v1/responses", post(handlers::responses))
         .route("/v1/messages", post(handlers::anthropic_messages))
-        .route("/v1/messages/count_tokens", post(count_tokens))
+        // multipart 上传路由
+        .route("/v1/audio/transcriptions", post(handlers::openai_uploads))
+        .route("/v1/audio/translations", post(handlers::openai_uploads))
+        .route("/v1/files", post(handlers::openai_uploads))
         // Gemini 原生协议路由
         .route("/v1/gemini/*path", post(gemini_generate_content))
         // WebSocket 路由
@@ -832,6 +1035,23 @@ async fn run_server(
             post(amp_chat_completions),
         )
         .route("/api/provider/:provider/v1/messages", post(amp_messages))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            routing_gate_middleware,
+        ));
+
+    // gRPC 服务共享 AppState，经路由闸门后直接调用与 HTTP 端点相同的处理函数
+    let grpc_config = config
+        .as_ref()
+        .map(|c| c.server.grpc.clone())
+        .unwrap_or_default();
+    let grpc_state = grpc_config.enable.then(|| state.clone());
+
+    let app = Router::new()
+        .route("/health", get(health))
+        .route("/v1/models", get(models))
+        .route("/v1/routes", get(list_routes))
+        .route("/v1/messages/count_tokens", post(count_tokens))
         // Amp CLI 管理代理路由
         .route(
             "/api/auth/*path",
@@ -841,6 +1061,7 @@ async fn run_server(
             "/api/user/*path",
             axum::routing::any(amp_management_proxy_user),
         )
+        .merge(gated_routes)
         // 管理 API 路由
         .merge(management_routes)
         .layer(DefaultBodyLimit::max(body_limit))
@@ -851,12 +1072,41 @@ async fn run_server(
 
     tracing::info!("Server listening on {}", addr);
 
-    axum::serve(listener, app)
+    // 启用 OTLP 导出时，把完成或失败的 Flow 作为 Span 发送到采集器
+    let otlp_handle = config
+        .as_ref()
+        .and_then(|c| crate::telemetry::spawn_otlp_exporter(&c.server.otlp, &otlp_monitor));
+
+    let (grpc_shutdown_tx, grpc_shutdown_rx) = oneshot::channel();
+    let grpc_handle = match grpc_state {
+        Some(grpc_state) => {
+            let grpc_addr: std::net::SocketAddr = format!("{host}:{}", grpc_config.port).parse()?;
+            Some(tokio::spawn(async move {
+                if let Err(e) = grpc::serve(grpc_addr, grpc_state, grpc_shutdown_rx).await {
+                    tracing::error!("gRPC server error: {}", e);
+                }
+            }))
+        }
+        None => None,
+    };
+
+    // 关闭 Nagle 算法，流式 chunk 到达后立即发送给客户端
+    let result = axum::serve(listener, app)
+        .tcp_nodelay(true)
         .with_graceful_shutdown(async move {
             let _ = shutdown.await;
         })
-        .await?;
+        .await;
 
+    let _ = grpc_shutdown_tx.send(());
+    if let Some(handle) = grpc_handle {
+        let _ = handle.await;
+    }
+    if let Some(handle) = otlp_handle {
+        handle.abort();
+    }
+
+    result?;
     Ok(())
 }
 
@@ -865,7 +1115,7 @@ async fn count_tokens(
     headers: HeaderMap,
     Json(_request): Json<serde_json::Value>,
 ) -> Response {
-    if let Err(e) = handlers::verify_api_key(&headers, &state.api_key).await {
+    if let Err(e) = handlers::verify_api_key(&headers, &state.api_keys).await {
         return e.into_response();
     }
 
@@ -885,9 +1135,10 @@ async fn gemini_generate_content(
     Path(path): Path<String>,
     Json(request): Json<serde_json::Value>,
 ) -> Response {
-    if let Err(e) = handlers::verify_api_key(&headers, &state.api_key).await {
-        return e.into_response();
-    }
+    let identity = match handlers::verify_api_key(&headers, &state.api_keys).await {
+        Ok(identity) => identity,
+        Err(e) => return e.into_response(),
+    };
 
     // 解析路径: {model}:{method}
     // 例如: gemini-3-pro-preview:generateContent
@@ -906,6 +1157,9 @@ async fn gemini_generate_content(
 
     let model = parts[0];
     let method = parts[1];
+    if let Err(e) = handlers::check_api_key_scope(&identity, model, None) {
+        return e.into_response();
+    }
 
     state.logs.write().await.add(
         "info",
@@ -1162,13 +1416,16 @@ async fn anthropic_messages_with_selector(
     Json(request): Json<AnthropicMessagesRequest>,
 ) -> Response {
     // 使用 Anthropic 格式的认证验证
-    if let Err(e) = handlers::verify_api_key_anthropic(&headers, &state.api_key).await {
-        state.logs.write().await.add(
-            "warn",
-            &format!("Unauthorized request to /{}/v1/messages", selector),
-        );
-        return e.into_response();
-    }
+    let identity = match handlers::verify_api_key_anthropic(&headers, &state.api_keys).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            state.logs.write().await.add(
+                "warn",
+                &format!("Unauthorized request to /{}/v1/messages", selector),
+            );
+            return e.into_response();
+        }
+    };
 
     state.logs.write().await.add(
         "info",
@@ -1177,6 +1434,9 @@ async fn anthropic_messages_with_selector(
             selector, request.model, request.stream
         ),
     );
+    if let Err(e) = handlers::check_api_key_scope_anthropic(&identity, &request.model, None) {
+        return e.into_response();
+    }
 
     // 尝试解析凭证
     let credential = match &state.db {
@@ -1241,13 +1501,16 @@ async fn chat_completions_with_selector(
     headers: HeaderMap,
     Json(request): Json<ChatCompletionRequest>,
 ) -> Response {
-    if let Err(e) = handlers::verify_api_key(&headers, &state.api_key).await {
-        state.logs.write().await.add(
-            "warn",
-            &format!("Unauthorized request to /{}/v1/chat/completions", selector),
-        );
-        return e.into_response();
-    }
+    let identity = match handlers::verify_api_key(&headers, &state.api_keys).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            state.logs.write().await.add(
+                "warn",
+                &format!("Unauthorized request to /{}/v1/chat/completions", selector),
+            );
+            return e.into_response();
+        }
+    };
 
     state.logs.write().await.add(
         "info",
@@ -1256,6 +1519,9 @@ async fn chat_completions_with_selector(
             selector, request.model, request.stream
         ),
     );
+    if let Err(e) = handlers::check_api_key_scope(&identity, &request.model, None) {
+        return e.into_response();
+    }
 
     // 尝试解析凭证
     let credential = match &state.db {
@@ -1290,7 +1556,7 @@ async fn chat_completions_with_selector(
             );
 
             // 注意：这里没有 Flow 捕获，因为是通过 selector 路由的请求
-            handlers::call_provider_openai(&state, &cred, &request, None).await
+            handlers::call_provider_openai(&state, &cred, &request, None, false).await
         }
         None => {
             state.logs.write().await.add(
@@ -1317,16 +1583,19 @@ async fn amp_chat_completions(
     headers: HeaderMap,
     Json(mut request): Json<ChatCompletionRequest>,
 ) -> Response {
-    if let Err(e) = handlers::verify_api_key(&headers, &state.api_key).await {
-        state.logs.write().await.add(
-            "warn",
-            &format!(
-                "Unauthorized request to /api/provider/{}/v1/chat/completions",
-                provider
-            ),
-        );
-        return e.into_response();
-    }
+    let identity = match handlers::verify_api_key(&headers, &state.api_keys).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            state.logs.write().await.add(
+                "warn",
+                &format!(
+                    "Unauthorized request to /api/provider/{}/v1/chat/completions",
+                    provider
+                ),
+            );
+            return e.into_response();
+        }
+    };
 
     // 应用模型映射
     let original_model = request.model.clone();
@@ -1342,6 +1611,10 @@ async fn amp_chat_completions(
         request.model = mapped_model;
     }
 
+    if let Err(e) = handlers::check_api_key_scope(&identity, &request.model, None) {
+        return e.into_response();
+    }
+
     state.logs.write().await.add(
         "info",
         &format!(
@@ -1387,7 +1660,7 @@ async fn amp_chat_completions(
                 ),
             );
             // 注意：这里没有 Flow 捕获，因为是通过 AMP CLI 路由的请求
-            handlers::call_provider_openai(&state, &cred, &request, None).await
+            handlers::call_provider_openai(&state, &cred, &request, None, false).await
         }
         None => {
             state.logs.write().await.add(
@@ -1413,16 +1686,19 @@ async fn amp_messages(
     Json(mut request): Json<AnthropicMessagesRequest>,
 ) -> Response {
     // 使用 Anthropic 格式的认证验证
-    if let Err(e) = handlers::verify_api_key_anthropic(&headers, &state.api_key).await {
-        state.logs.write().await.add(
-            "warn",
-            &format!(
-                "Unauthorized request to /api/provider/{}/v1/messages",
-                provider
-            ),
-        );
-        return e.into_response();
-    }
+    let identity = match handlers::verify_api_key_anthropic(&headers, &state.api_keys).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            state.logs.write().await.add(
+                "warn",
+                &format!(
+                    "Unauthorized request to /api/provider/{}/v1/messages",
+                    provider
+                ),
+            );
+            return e.into_response();
+        }
+    };
 
     // 应用模型映射
     let original_model = request.model.clone();
@@ -1438,6 +1714,10 @@ async fn amp_messages(
         request.model = mapped_model;
     }
 
+    if let Err(e) = handlers::check_api_key_scope_anthropic(&identity, &request.model, None) {
+        return e.into_response();
+    }
+
     state.logs.write().await.add(
         "info",
         &format!(
@@ -0,0 +1,458 @@
+//! 路由闸门模块
+//!
+//! 在维护期间（轮换凭证、排空流量等）暂停向上游发送新请求，而无需停止进程。
+//!
+//! 闸门有三种模式：
+//! - `Open`：正常放行
+//! - `Drain`：进行中的请求继续完成，新请求返回 503 并携带 `Retry-After`
+//! - `Reject`：所有新请求立即返回 503，进行中的请求（包括流式响应）立即中止
+//!
+//! 被闸门拒绝的请求会以 `FlowErrorType::GateRejected` 记录到 Flow 监控中，
+//! 以便与上游失败区分。
+
+use super::AppState;
+use crate::flow_monitor::{FlowError, FlowErrorType, FlowMetadata, LLMRequest};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+/// 默认的 Retry-After 秒数
+const DEFAULT_RETRY_AFTER_SECONDS: u64 = 30;
+
+/// 记录被拒绝请求时读取请求体的上限
+const MAX_REJECTED_BODY_BYTES: usize = 1024 * 1024;
+
+/// 闸门模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingGateMode {
+    /// 正常放行
+    #[default]
+    Open,
+    /// 排空：进行中的请求继续完成，新请求返回 503
+    Drain,
+    /// 拒绝：所有新请求立即返回 503，并中止进行中的请求
+    Reject,
+}
+
+impl std::fmt::Display for RoutingGateMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoutingGateMode::Open => write!(f, "open"),
+            RoutingGateMode::Drain => write!(f, "drain"),
+            RoutingGateMode::Reject => write!(f, "reject"),
+        }
+    }
+}
+
+/// 闸门状态（用于前端展示）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingGateStatus {
+    /// 当前模式
+    pub mode: RoutingGateMode,
+    /// 进行中的请求数
+    pub in_flight: usize,
+    /// 是否已排空（非 Open 模式且没有进行中的请求）
+    pub drained: bool,
+    /// 自上次切换模式以来被拒绝的请求数
+    pub rejected_count: u64,
+    /// Drain 模式下返回的 Retry-After 秒数
+    pub retry_after_seconds: u64,
+    /// 最近一次切换模式的时间
+    pub changed_at: DateTime<Utc>,
+}
+
+/// 路由闸门
+#[derive(Debug)]
+pub struct RoutingGate {
+    /// 当前模式及切换时间
+    mode: RwLock<(RoutingGateMode, DateTime<Utc>)>,
+    /// 进行中的请求数
+    in_flight: Arc<AtomicUsize>,
+    /// 被拒绝的请求数
+    rejected_count: AtomicU64,
+    /// 进行中请求的中止令牌（切换到 Reject 时取消并替换）
+    abort: RwLock<CancellationToken>,
+    /// Retry-After 秒数
+    retry_after_seconds: AtomicU64,
+}
+
+impl Default for RoutingGate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RoutingGate {
+    /// 创建新的闸门（默认 Open）
+    pub fn new() -> Self {
+        Self {
+            mode: RwLock::new((RoutingGateMode::Open, Utc::now())),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            rejected_count: AtomicU64::new(0),
+            abort: RwLock::new(CancellationToken::new()),
+            retry_after_seconds: AtomicU64::new(DEFAULT_RETRY_AFTER_SECONDS),
+        }
+    }
+
+    /// 获取当前模式
+    pub fn mode(&self) -> RoutingGateMode {
+        self.mode.read().0
+    }
+
+    /// 切换模式
+    ///
+    /// 切换到 Reject 时中止所有进行中的请求
+    pub fn set_mode(&self, mode: RoutingGateMode) -> RoutingGateStatus {
+        {
+            let mut current = self.mode.write();
+            if current.0 != mode {
+                tracing::info!(
+                    "[GATE] 路由闸门切换: {} -> {} in_flight={}",
+                    current.0,
+                    mode,
+                    self.in_flight.load(Ordering::SeqCst)
+                );
+                *current = (mode, Utc::now());
+                self.rejected_count.store(0, Ordering::SeqCst);
+                if mode == RoutingGateMode::Reject {
+                    std::mem::replace(&mut *self.abort.write(), CancellationToken::new()).cancel();
+                }
+            }
+        }
+        self.status()
+    }
+
+    /// 设置 Drain 模式下的 Retry-After 秒数
+    pub fn set_retry_after_seconds(&self, seconds: u64) {
+        self.retry_after_seconds.store(seconds, Ordering::SeqCst);
+    }
+
+    /// 获取闸门状态
+    pub fn status(&self) -> RoutingGateStatus {
+        let (mode, changed_at) = *self.mode.read();
+        let in_flight = self.in_flight.load(Ordering::SeqCst);
+        RoutingGateStatus {
+            mode,
+            in_flight,
+            drained: mode != RoutingGateMode::Open && in_flight == 0,
+            rejected_count: self.rejected_count.load(Ordering::SeqCst),
+            retry_after_seconds: self.retry_after_seconds.load(Ordering::SeqCst),
+            changed_at,
+        }
+    }
+
+    /// 尝试通过闸门
+    ///
+    /// 通过时返回进行中请求守卫，守卫释放时自动减少进行中计数
+    pub fn try_enter(&self) -> Result<InFlightGuard, GateRejection> {
+        // 持有模式读锁，避免放行的请求错过随后切换到 Reject 时的中止
+        let current = self.mode.read();
+        let mode = current.0;
+        match mode {
+            RoutingGateMode::Open => {
+                self.in_flight.fetch_add(1, Ordering::SeqCst);
+                Ok(InFlightGuard {
+                    in_flight: self.in_flight.clone(),
+                    abort: self.abort.read().clone(),
+                })
+            }
+            RoutingGateMode::Drain | RoutingGateMode::Reject => {
+                self.rejected_count.fetch_add(1, Ordering::SeqCst);
+                let retry_after_seconds = (mode == RoutingGateMode::Drain)
+                    .then(|| self.retry_after_seconds.load(Ordering::SeqCst));
+                Err(GateRejection {
+                    mode,
+                    retry_after_seconds,
+                })
+            }
+        }
+    }
+}
+
+/// 进行中请求守卫
+#[derive(Debug)]
+pub struct InFlightGuard {
+    in_flight: Arc<AtomicUsize>,
+    abort: CancellationToken,
+}
+
+impl InFlightGuard {
+    /// 闸门切换到 Reject 时取消的令牌
+    pub fn abort_token(&self) -> CancellationToken {
+        self.abort.clone()
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 闸门拒绝
+#[derive(Debug, Clone)]
+pub struct GateRejection {
+    /// 拒绝时的闸门模式
+    pub mode: RoutingGateMode,
+    /// Retry-After 秒数（仅 Drain 模式）
+    pub retry_after_seconds: Option<u64>,
+}
+
+impl GateRejection {
+    /// 错误消息
+    pub fn message(&self) -> String {
+        match self.mode {
+            RoutingGateMode::Drain => "代理正在排空流量，暂不接受新请求".to_string(),
+            _ => "代理已暂停路由，拒绝所有请求".to_string(),
+        }
+    }
+}
+
+/// 实现 IntoResponse 以便直接返回 503 响应
+impl IntoResponse for GateRejection {
+    fn into_response(self) -> Response {
+        let json_body = serde_json::json!({
+            "error": {
+                "message": self.message(),
+                "type": "routing_gate_closed",
+                "code": 503,
+                "gate_mode": self.mode,
+            }
+        });
+
+        let mut response = (StatusCode::SERVICE_UNAVAILABLE, Json(json_body)).into_response();
+
+        if let Some(seconds) = self.retry_after_seconds {
+            if let Ok(header_value) = seconds.to_string().parse() {
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, header_value);
+            }
+        }
+
+        response
+    }
+}
+
+/// 路由闸门中间件
+///
+/// 放行的请求在响应体发送完毕前都计入进行中请求（覆盖流式响应）。
+/// 闸门切换到 Reject 时，尚未返回的请求立即返回 503，已开始发送的响应体被截断。
+pub async fn routing_gate_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match state.routing_gate.try_enter() {
+        Ok(guard) => run_gated(guard, next.run(request)).await,
+        Err(rejection) => {
+            tracing::warn!(
+                "[GATE] 拒绝请求: {} {} mode={}",
+                request.method(),
+                request.uri().path(),
+                rejection.mode
+            );
+            record_gate_rejection(&state, request, &rejection).await;
+            rejection.into_response()
+        }
+    }
+}
+
+/// 在闸门守卫下执行已放行的请求
+///
+/// 守卫随响应体一起释放；闸门切换到 Reject 时中止请求或截断响应体。
+pub async fn run_gated(
+    guard: InFlightGuard,
+    handler: impl std::future::Future<Output = Response>,
+) -> Response {
+    let abort = guard.abort_token();
+    let response = tokio::select! {
+        response = handler => response,
+        _ = abort.cancelled() => {
+            tracing::warn!("[GATE] 闸门已切换到 reject，中止进行中的请求");
+            return GateRejection {
+                mode: RoutingGateMode::Reject,
+                retry_after_seconds: None,
+            }
+            .into_response();
+        }
+    };
+    let (parts, body) = response.into_parts();
+    let stream = body
+        .into_data_stream()
+        .take_until(async move { abort.cancelled().await })
+        .map(move |chunk| {
+            let _ = &guard;
+            chunk
+        });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+/// 将被闸门拒绝的请求记录为失败的 Flow
+async fn record_gate_rejection(state: &AppState, request: Request, rejection: &GateRejection) {
+    let (parts, body) = request.into_parts();
+    let bytes = axum::body::to_bytes(body, MAX_REJECTED_BODY_BYTES)
+        .await
+        .unwrap_or_default();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+    record_rejected_flow(
+        state,
+        parts.method.as_str(),
+        parts.uri.path(),
+        body,
+        bytes.len(),
+        rejection,
+    )
+    .await;
+}
+
+/// 记录被闸门拒绝的请求（请求体已解析为 JSON）
+pub(crate) async fn record_rejected_flow(
+    state: &AppState,
+    method: &str,
+    path: &str,
+    body: serde_json::Value,
+    size_bytes: usize,
+    rejection: &GateRejection,
+) {
+    let model = body
+        .get("model")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown")
+        .to_string();
+
+    let llm_request = LLMRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers: HashMap::new(),
+        body,
+        model,
+        size_bytes,
+        timestamp: Utc::now(),
+        ..Default::default()
+    };
+
+    if let Some(flow_id) = state
+        .flow_monitor
+        .start_flow(llm_request, FlowMetadata::default())
+        .await
+    {
+        let error =
+            FlowError::new(FlowErrorType::GateRejected, rejection.message()).with_status_code(503);
+        state.flow_monitor.fail_flow(&flow_id, error).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_gate_tracks_in_flight() {
+        let gate = RoutingGate::new();
+        let guard = gate.try_enter().unwrap();
+        assert_eq!(gate.status().in_flight, 1);
+        drop(guard);
+        assert_eq!(gate.status().in_flight, 0);
+    }
+
+    #[test]
+    fn test_drain_rejects_new_requests_with_retry_after() {
+        let gate = RoutingGate::new();
+        let guard = gate.try_enter().unwrap();
+
+        gate.set_mode(RoutingGateMode::Drain);
+        let rejection = gate.try_enter().unwrap_err();
+        assert_eq!(rejection.mode, RoutingGateMode::Drain);
+        assert_eq!(
+            rejection.retry_after_seconds,
+            Some(DEFAULT_RETRY_AFTER_SECONDS)
+        );
+
+        // 进行中的请求不受影响，完成后闸门进入已排空状态
+        let status = gate.status();
+        assert_eq!(status.in_flight, 1);
+        assert!(!status.drained);
+        assert_eq!(status.rejected_count, 1);
+        drop(guard);
+        assert!(gate.status().drained);
+
+        let response = rejection.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers().get(header::RETRY_AFTER).unwrap(),
+            &DEFAULT_RETRY_AFTER_SECONDS.to_string()
+        );
+    }
+
+    #[test]
+    fn test_reject_mode() {
+        let gate = RoutingGate::new();
+        gate.set_mode(RoutingGateMode::Reject);
+        let rejection = gate.try_enter().unwrap_err();
+        assert!(rejection.retry_after_seconds.is_none());
+
+        let response = rejection.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(response.headers().get(header::RETRY_AFTER).is_none());
+
+        let status = gate.set_mode(RoutingGateMode::Open);
+        assert_eq!(status.mode, RoutingGateMode::Open);
+        assert_eq!(status.rejected_count, 0);
+        assert!(gate.try_enter().is_ok());
+    }
+
+    #[test]
+    fn test_reject_aborts_in_flight_but_drain_does_not() {
+        let gate = RoutingGate::new();
+        let guard = gate.try_enter().unwrap();
+        let abort = guard.abort_token();
+
+        gate.set_mode(RoutingGateMode::Drain);
+        assert!(!abort.is_cancelled());
+
+        gate.set_mode(RoutingGateMode::Reject);
+        assert!(abort.is_cancelled());
+        drop(guard);
+
+        // 重新打开后放行的请求使用新的令牌
+        gate.set_mode(RoutingGateMode::Open);
+        let guard = gate.try_enter().unwrap();
+        assert!(!guard.abort_token().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_reject_truncates_streaming_body() {
+        let gate = RoutingGate::new();
+        let guard = gate.try_enter().unwrap();
+        let abort = guard.abort_token();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<&'static str>();
+        let mut stream = Box::pin(
+            futures::stream::unfold(rx, |mut rx| async move {
+                rx.recv().await.map(|chunk| (chunk, rx))
+            })
+            .take_until(async move { abort.cancelled().await }),
+        );
+
+        tx.send("first").unwrap();
+        assert_eq!(stream.next().await, Some("first"));
+
+        gate.set_mode(RoutingGateMode::Reject);
+        tx.send("second").unwrap();
+        assert_eq!(stream.next().await, None);
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! 提供凭证池的选择、健康检测、负载均衡等功能。
 
+use crate::audit::{self, AuditAction};
 use crate::database::dao::provider_pool::ProviderPoolDao;
 use crate::database::DbConnection;
 use crate::models::provider_pool_model::{
@@ -35,6 +36,22 @@ impl Default for ProviderPoolService {
     }
 }
 
+/// 将本地用户的凭证变更写入审计日志
+pub(crate) fn audit_credential(
+    action: AuditAction,
+    uuid: &str,
+    before: Option<&ProviderCredential>,
+    after: Option<&ProviderCredential>,
+) {
+    audit::record_serialized(
+        &audit::local_actor(),
+        action,
+        Some(uuid.to_string()),
+        before,
+        after,
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::ProviderPoolService;
@@ -185,6 +202,7 @@ impl ProviderPoolService {
 
         let conn = db.lock().map_err(|e| e.to_string())?;
         ProviderPoolDao::insert(&conn, &cred).map_err(|e| e.to_string())?;
+        audit_credential(AuditAction::CredentialAdd, &cred.uuid, None, Some(&cred));
 
         Ok(cred)
     }
@@ -204,6 +222,7 @@ impl ProviderPoolService {
         let mut cred = ProviderPoolDao::get_by_uuid(&conn, uuid)
             .map_err(|e| e.to_string())?
             .ok_or_else(|| format!("Credential not found: {}", uuid))?;
+        let before = cred.clone();
 
         // 处理 name：空字符串表示清除，None 表示不修改
         if let Some(n) = name {
@@ -225,13 +244,24 @@ impl ProviderPoolService {
         cred.updated_at = Utc::now();
 
         ProviderPoolDao::update(&conn, &cred).map_err(|e| e.to_string())?;
+        audit_credential(
+            AuditAction::CredentialUpdate,
+            uuid,
+            Some(&before),
+            Some(&cred),
+        );
         Ok(cred)
     }
 
     /// 删除凭证
     pub fn delete_credential(&self, db: &DbConnection, uuid: &str) -> Result<bool, String> {
         let conn = db.lock().map_err(|e| e.to_string())?;
-        ProviderPoolDao::delete(&conn, uuid).map_err(|e| e.to_string())
+        let before = ProviderPoolDao::get_by_uuid(&conn, uuid).ok().flatten();
+        let deleted = ProviderPoolDao::delete(&conn, uuid).map_err(|e| e.to_string())?;
+        if deleted {
+            audit_credential(AuditAction::CredentialRemove, uuid, before.as_ref(), None);
+        }
+        Ok(deleted)
     }
 
     /// 选择一个可用的凭证（轮询负载均衡）
@@ -315,6 +345,21 @@ impl ProviderPoolService {
         Ok(Some(selected))
     }
 
+    /// 获取会话亲和固定的凭证
+    ///
+    /// 凭证不存在、不可用或不支持该模型时返回 `None`，由调用方重新选择。
+    pub fn get_pinned_credential(
+        &self,
+        db: &DbConnection,
+        uuid: &str,
+        model: Option<&str>,
+    ) -> Option<ProviderCredential> {
+        let conn = db.lock().ok()?;
+        let credential = ProviderPoolDao::get_by_uuid(&conn, uuid).ok().flatten()?;
+        (credential.is_available() && model.is_none_or(|m| credential.supports_model(m)))
+            .then_some(credential)
+    }
+
     /// 记录凭证使用
     pub fn record_usage(&self, db: &DbConnection, uuid: &str) -> Result<(), String> {
         let conn = db.lock().map_err(|e| e.to_string())?;
@@ -1556,6 +1601,7 @@ impl ProviderPoolService {
 
         let conn = db.lock().map_err(|e| e.to_string())?;
         ProviderPoolDao::insert(&conn, &cred).map_err(|e| e.to_string())?;
+        audit_credential(AuditAction::CredentialAdd, &cred.uuid, None, Some(&cred));
 
         Ok(cred)
     }
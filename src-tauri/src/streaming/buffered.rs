@@ -0,0 +1,563 @@
+//! 流式响应缓冲
+//!
+//! 把目标格式的 SSE 事件累积为一个完整的非流式 JSON 响应，
+//! 用于不支持 SSE 的客户端：上游仍以流式请求（保留 TTFB 和逐 chunk 指标），
+//! 客户端收到的是一次性返回的 JSON。
+
+use std::collections::BTreeMap;
+
+use serde_json::{json, Map, Value};
+
+use super::converter::StreamFormat;
+use super::error::StreamError;
+
+/// 请求缓冲流式响应的请求头
+pub const ACCUMULATE_STREAM_HEADER: &str = "x-accumulate-stream";
+
+/// 请求缓冲流式响应的查询参数
+pub const ACCUMULATE_STREAM_QUERY: &str = "accumulate_stream";
+
+/// 判断请求头或查询参数的值是否表示开启
+pub fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+/// 解析后的 SSE 事件
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    /// 事件类型（`event:` 行）
+    pub event: Option<String>,
+    /// 事件数据（多个 `data:` 行以换行连接）
+    pub data: String,
+}
+
+/// OpenAI 单个 choice 的累积状态
+#[derive(Debug, Default)]
+struct ChoiceState {
+    role: Option<String>,
+    content: String,
+    tool_calls: BTreeMap<u64, ToolCallState>,
+    finish_reason: Option<Value>,
+    logprobs: Vec<Value>,
+}
+
+/// OpenAI 工具调用的累积状态
+#[derive(Debug, Default)]
+struct ToolCallState {
+    id: Option<String>,
+    call_type: Option<String>,
+    name: String,
+    arguments: String,
+}
+
+/// Anthropic 内容块的累积状态
+#[derive(Debug, Default)]
+struct BlockState {
+    block: Map<String, Value>,
+    partial_json: String,
+}
+
+/// 流式响应累积器
+///
+/// 逐段喂入目标格式的 SSE 文本（允许事件跨段），结束后生成对应格式的非流式响应体。
+#[derive(Debug)]
+pub struct StreamAccumulator {
+    format: StreamFormat,
+    model: String,
+    /// 尚未构成完整事件的文本
+    pending: String,
+    id: Option<String>,
+    created: Option<i64>,
+    system_fingerprint: Option<String>,
+    /// 流中的用量（OpenAI 为最后的 usage chunk，Anthropic 为 message_start 与 message_delta 合并）
+    usage: Option<Map<String, Value>>,
+    error: Option<Value>,
+    choices: BTreeMap<u64, ChoiceState>,
+    blocks: BTreeMap<u64, BlockState>,
+    stop_reason: Option<Value>,
+    stop_sequence: Option<Value>,
+}
+
+impl StreamAccumulator {
+    /// 创建累积器
+    ///
+    /// # 参数
+    /// - `format`: 喂入的 SSE 格式（也是生成的响应格式）
+    /// - `model`: 模型名称（流中未携带时使用）
+    pub fn new(format: StreamFormat, model: &str) -> Self {
+        Self {
+            format,
+            model: model.to_string(),
+            pending: String::new(),
+            id: None,
+            created: None,
+            system_fingerprint: None,
+            usage: None,
+            error: None,
+            choices: BTreeMap::new(),
+            blocks: BTreeMap::new(),
+            stop_reason: None,
+            stop_sequence: None,
+        }
+    }
+
+    /// 喂入一段 SSE 文本，返回其中完整的事件（供 Flow Monitor 逐 chunk 处理）
+    pub fn push(&mut self, text: &str) -> Vec<SseEvent> {
+        self.pending.push_str(&text.replace("\r\n", "\n"));
+
+        let mut events = Vec::new();
+        while let Some(end) = self.pending.find("\n\n") {
+            let block: String = self.pending.drain(..end + 2).collect();
+            if let Some(event) = parse_sse_block(&block) {
+                self.apply(&event);
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// 结束累积，生成非流式响应体
+    ///
+    /// 流中出现错误事件时返回 `StreamError::ProviderError`。
+    pub fn finish(mut self) -> Result<Value, StreamError> {
+        // 末尾缺少空行的事件
+        let rest = std::mem::take(&mut self.pending);
+        if let Some(event) = parse_sse_block(&rest) {
+            self.apply(&event);
+        }
+
+        if let Some(error) = &self.error {
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string());
+            return Err(StreamError::provider_error(502, message));
+        }
+
+        match self.format {
+            StreamFormat::OpenAiSse => Ok(self.finish_openai()),
+            StreamFormat::AnthropicSse => Ok(self.finish_anthropic()),
+            StreamFormat::AwsEventStream => Err(StreamError::internal(
+                "AWS Event Stream 不支持缓冲为非流式响应",
+            )),
+            StreamFormat::GeminiSse => {
+                Err(StreamError::internal("Gemini SSE 不支持缓冲为非流式响应"))
+            }
+        }
+    }
+
+    /// 处理单个事件
+    fn apply(&mut self, event: &SseEvent) {
+        let data = event.data.trim();
+        if data.is_empty() || data == "[DONE]" {
+            return;
+        }
+        let Ok(value) = serde_json::from_str::<Value>(data) else {
+            return;
+        };
+
+        // 两种格式的错误事件：{"error": {...}} 或 {"type": "error", "error": {...}}
+        if let Some(error) = value.get("error").filter(|e| !e.is_null()) {
+            self.error = Some(error.clone());
+            return;
+        }
+
+        match self.format {
+            StreamFormat::OpenAiSse => self.apply_openai(&value),
+            StreamFormat::AnthropicSse => self.apply_anthropic(event.event.as_deref(), &value),
+            StreamFormat::AwsEventStream | StreamFormat::GeminiSse => {}
+        }
+    }
+
+    /// 处理 OpenAI chunk
+    fn apply_openai(&mut self, chunk: &Value) {
+        if self.id.is_none() {
+            self.id = chunk["id"].as_str().map(str::to_string);
+        }
+        if self.created.is_none() {
+            self.created = chunk["created"].as_i64();
+        }
+        if let Some(model) = chunk["model"].as_str().filter(|m| !m.is_empty()) {
+            self.model = model.to_string();
+        }
+        if let Some(fingerprint) = chunk["system_fingerprint"].as_str() {
+            self.system_fingerprint = Some(fingerprint.to_string());
+        }
+        if let Some(usage) = chunk["usage"].as_object() {
+            self.usage = Some(usage.clone());
+        }
+
+        for (position, choice) in chunk["choices"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            let index = choice["index"].as_u64().unwrap_or(position as u64);
+            let state = self.choices.entry(index).or_default();
+            let delta = &choice["delta"];
+
+            if let Some(role) = delta["role"].as_str() {
+                state.role = Some(role.to_string());
+            }
+            if let Some(content) = delta["content"].as_str() {
+                state.content.push_str(content);
+            }
+            for (position, call) in delta["tool_calls"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .enumerate()
+            {
+                let call_index = call["index"].as_u64().unwrap_or(position as u64);
+                let call_state = state.tool_calls.entry(call_index).or_default();
+                if let Some(id) = call["id"].as_str() {
+                    call_state.id = Some(id.to_string());
+                }
+                if let Some(call_type) = call["type"].as_str() {
+                    call_state.call_type = Some(call_type.to_string());
+                }
+                if let Some(name) = call["function"]["name"].as_str() {
+                    call_state.name.push_str(name);
+                }
+                if let Some(arguments) = call["function"]["arguments"].as_str() {
+                    call_state.arguments.push_str(arguments);
+                }
+            }
+            if let Some(content) = choice["logprobs"]["content"].as_array() {
+                state.logprobs.extend(content.iter().cloned());
+            }
+            // 转换器在流末尾补发的结束 chunk 不覆盖上游给出的结束原因
+            if state.finish_reason.is_none() && !choice["finish_reason"].is_null() {
+                state.finish_reason = Some(choice["finish_reason"].clone());
+            }
+        }
+    }
+
+    /// 处理 Anthropic 事件
+    fn apply_anthropic(&mut self, event: Option<&str>, value: &Value) {
+        let event_type = event.or_else(|| value["type"].as_str()).unwrap_or_default();
+        match event_type {
+            "message_start" => {
+                let message = &value["message"];
+                self.id = message["id"].as_str().map(str::to_string);
+                if let Some(model) = message["model"].as_str().filter(|m| !m.is_empty()) {
+                    self.model = model.to_string();
+                }
+                self.merge_usage(&message["usage"]);
+            }
+            "content_block_start" => {
+                let index = value["index"].as_u64().unwrap_or(self.blocks.len() as u64);
+                let block = value["content_block"]
+                    .as_object()
+                    .cloned()
+                    .unwrap_or_default();
+                self.blocks.insert(
+                    index,
+                    BlockState {
+                        block,
+                        partial_json: String::new(),
+                    },
+                );
+            }
+            "content_block_delta" => {
+                let index = value["index"].as_u64().unwrap_or(0);
+                let state = self.blocks.entry(index).or_default();
+                let delta = &value["delta"];
+                match delta["type"].as_str().unwrap_or_default() {
+                    "text_delta" => append_str(&mut state.block, "text", &delta["text"]),
+                    "thinking_delta" => {
+                        append_str(&mut state.block, "thinking", &delta["thinking"])
+                    }
+                    "signature_delta" => {
+                        state
+                            .block
+                            .insert("signature".to_string(), delta["signature"].clone());
+                    }
+                    "input_json_delta" => {
+                        if let Some(partial) = delta["partial_json"].as_str() {
+                            state.partial_json.push_str(partial);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            "message_delta" => {
+                // 转换器在流末尾补发的 message_delta 不覆盖上游给出的停止原因
+                let delta = &value["delta"];
+                if self.stop_reason.is_none() && !delta["stop_reason"].is_null() {
+                    self.stop_reason = Some(delta["stop_reason"].clone());
+                }
+                if self.stop_sequence.is_none() && !delta["stop_sequence"].is_null() {
+                    self.stop_sequence = Some(delta["stop_sequence"].clone());
+                }
+                self.merge_usage(&value["usage"]);
+            }
+            _ => {}
+        }
+    }
+
+    /// 合并用量字段（后到的值覆盖先到的值，补发事件中的 0 不覆盖已有值）
+    fn merge_usage(&mut self, usage: &Value) {
+        if let Some(fields) = usage.as_object() {
+            let merged = self.usage.get_or_insert_with(Map::new);
+            for (key, value) in fields {
+                if value.is_null() || (value.as_u64() == Some(0) && merged.contains_key(key)) {
+                    continue;
+                }
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    /// 生成 OpenAI chat.completion 响应体
+    fn finish_openai(self) -> Value {
+        let choices: Vec<Value> = self
+            .choices
+            .into_iter()
+            .map(|(index, state)| {
+                // 只有工具调用时 content 为 null
+                let content = if state.content.is_empty() && !state.tool_calls.is_empty() {
+                    Value::Null
+                } else {
+                    Value::String(state.content)
+                };
+                let mut message = json!({
+                    "role": state.role.unwrap_or_else(|| "assistant".to_string()),
+                    "content": content,
+                });
+                if !state.tool_calls.is_empty() {
+                    message["tool_calls"] = state
+                        .tool_calls
+                        .into_values()
+                        .map(|call| {
+                            json!({
+                                "id": call.id.unwrap_or_default(),
+                                "type": call.call_type.unwrap_or_else(|| "function".to_string()),
+                                "function": {
+                                    "name": call.name,
+                                    "arguments": call.arguments,
+                                },
+                            })
+                        })
+                        .collect();
+                }
+                let mut choice = json!({
+                    "index": index,
+                    "message": message,
+                    "finish_reason": state.finish_reason.unwrap_or_else(|| json!("stop")),
+                });
+                if !state.logprobs.is_empty() {
+                    choice["logprobs"] = json!({ "content": state.logprobs });
+                }
+                choice
+            })
+            .collect();
+
+        let mut response = json!({
+            "id": self
+                .id
+                .unwrap_or_else(|| format!("chatcmpl-{}", uuid::Uuid::new_v4())),
+            "object": "chat.completion",
+            "created": self.created.unwrap_or_else(|| chrono::Utc::now().timestamp()),
+            "model": self.model,
+            "choices": choices,
+        });
+        if let Some(usage) = self.usage {
+            response["usage"] = Value::Object(usage);
+        }
+        if let Some(fingerprint) = self.system_fingerprint {
+            response["system_fingerprint"] = Value::String(fingerprint);
+        }
+        response
+    }
+
+    /// 生成 Anthropic message 响应体
+    fn finish_anthropic(self) -> Value {
+        let content: Vec<Value> = self
+            .blocks
+            .into_values()
+            .map(|mut state| {
+                if !state.partial_json.is_empty() {
+                    let input = serde_json::from_str(&state.partial_json)
+                        .unwrap_or(Value::String(state.partial_json));
+                    state.block.insert("input".to_string(), input);
+                }
+                Value::Object(state.block)
+            })
+            .collect();
+
+        json!({
+            "id": self
+                .id
+                .unwrap_or_else(|| format!("msg_{}", uuid::Uuid::new_v4().simple())),
+            "type": "message",
+            "role": "assistant",
+            "model": self.model,
+            "content": content,
+            "stop_reason": self.stop_reason.unwrap_or_else(|| json!("end_turn")),
+            "stop_sequence": self.stop_sequence.unwrap_or(Value::Null),
+            "usage": self.usage.map(Value::Object).unwrap_or_else(|| json!({})),
+        })
+    }
+}
+
+/// 解析单个 SSE 事件块，没有 `data:` 行时返回 `None`
+pub fn parse_sse_block(block: &str) -> Option<SseEvent> {
+    let mut event = None;
+    let mut data: Vec<&str> = Vec::new();
+    for line in block.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            event = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    if data.is_empty() {
+        return None;
+    }
+    Some(SseEvent {
+        event,
+        data: data.join("\n"),
+    })
+}
+
+/// 向对象的字符串字段追加文本
+fn append_str(block: &mut Map<String, Value>, key: &str, text: &Value) {
+    let Some(text) = text.as_str() else {
+        return;
+    };
+    match block.get_mut(key) {
+        Some(Value::String(existing)) => existing.push_str(text),
+        _ => {
+            block.insert(key.to_string(), Value::String(text.to_string()));
+        }
+    }
+}
+
+// ============================================================================
+// 测试模块
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_truthy() {
+        assert!(is_truthy("true"));
+        assert!(is_truthy(" 1 "));
+        assert!(is_truthy("Yes"));
+        assert!(!is_truthy("false"));
+        assert!(!is_truthy(""));
+    }
+
+    #[test]
+    fn test_accumulate_openai_stream() {
+        let mut accumulator = StreamAccumulator::new(StreamFormat::OpenAiSse, "gpt-4o");
+        let chunks = [
+            r#"{"id":"chatcmpl-1","created":1700000000,"model":"gpt-4o-2024","system_fingerprint":"fp_1","choices":[{"index":0,"delta":{"role":"assistant","content":"Hel"}}]}"#,
+            r#"{"id":"chatcmpl-1","choices":[{"index":0,"delta":{"content":"lo"}}]}"#,
+            r#"{"id":"chatcmpl-1","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"lookup","arguments":"{\"q\":"}}]}}]}"#,
+            r#"{"id":"chatcmpl-1","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"x\"}"}}]},"finish_reason":"tool_calls"}]}"#,
+            r#"{"id":"chatcmpl-1","choices":[],"usage":{"prompt_tokens":12,"completion_tokens":5,"total_tokens":17}}"#,
+        ];
+        let mut text: String = chunks.iter().map(|c| format!("data: {}\n\n", c)).collect();
+        text.push_str("data: [DONE]\n\n");
+
+        // 事件跨段喂入
+        let (head, tail) = text.split_at(37);
+        let mut events = accumulator.push(head);
+        events.extend(accumulator.push(tail));
+        assert_eq!(events.len(), 6);
+
+        let response = accumulator.finish().unwrap();
+        assert_eq!(response["object"], "chat.completion");
+        assert_eq!(response["id"], "chatcmpl-1");
+        assert_eq!(response["model"], "gpt-4o-2024");
+        assert_eq!(response["system_fingerprint"], "fp_1");
+        let choice = &response["choices"][0];
+        assert_eq!(choice["message"]["content"], "Hello");
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        let call = &choice["message"]["tool_calls"][0];
+        assert_eq!(call["id"], "call_1");
+        assert_eq!(call["function"]["arguments"], r#"{"q":"x"}"#);
+        assert_eq!(response["usage"]["total_tokens"], 17);
+    }
+
+    #[test]
+    fn test_accumulate_anthropic_stream() {
+        let events = [
+            (
+                "message_start",
+                r#"{"type":"message_start","message":{"id":"msg_1","model":"claude-sonnet-4","usage":{"input_tokens":20,"output_tokens":1}}}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi "}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"there"}}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"lookup","input":{}}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"q\": 1}"}}"#,
+            ),
+            (
+                "message_delta",
+                r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":9}}"#,
+            ),
+            ("message_stop", r#"{"type":"message_stop"}"#),
+            // 转换器补发的结束事件
+            (
+                "message_delta",
+                r#"{"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null},"usage":{"output_tokens":0}}"#,
+            ),
+        ];
+        let text: String = events
+            .iter()
+            .map(|(event, data)| format!("event: {}\ndata: {}\n\n", event, data))
+            .collect();
+
+        let mut accumulator = StreamAccumulator::new(StreamFormat::AnthropicSse, "claude");
+        let parsed = accumulator.push(&text);
+        assert_eq!(parsed[0].event.as_deref(), Some("message_start"));
+
+        let response = accumulator.finish().unwrap();
+        assert_eq!(response["id"], "msg_1");
+        assert_eq!(response["model"], "claude-sonnet-4");
+        assert_eq!(response["content"][0]["text"], "Hi there");
+        assert_eq!(response["content"][1]["input"]["q"], 1);
+        assert_eq!(response["stop_reason"], "tool_use");
+        assert_eq!(response["usage"]["input_tokens"], 20);
+        assert_eq!(response["usage"]["output_tokens"], 9);
+    }
+
+    #[test]
+    fn test_accumulate_error_event() {
+        let mut accumulator = StreamAccumulator::new(StreamFormat::OpenAiSse, "gpt-4o");
+        accumulator.push("data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"a\"}}]}\n\n");
+        accumulator.push("event: error\ndata: {\"error\":{\"type\":\"timeout\",\"message\":\"流式响应超时\"}}\n\n");
+
+        match accumulator.finish() {
+            Err(StreamError::ProviderError { status, message }) => {
+                assert_eq!(status, 502);
+                assert_eq!(message, "流式响应超时");
+            }
+            other => panic!("应该返回 ProviderError: {:?}", other),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 //! 流式格式转换器
 //!
-//! 在不同流式格式之间转换，支持 AWS Event Stream、Anthropic SSE 和 OpenAI SSE。
+//! 在不同流式格式之间转换，支持 AWS Event Stream、Anthropic SSE、OpenAI SSE 和 Gemini SSE。
 //!
 //! # 需求覆盖
 //!
@@ -8,13 +8,21 @@
 //! - 需求 3.2: AWS Event Stream 到 OpenAI SSE 转换
 //! - 需求 3.3: Anthropic SSE 到 OpenAI SSE 转换
 //! - 需求 3.5: 处理工具调用参数中的部分 JSON
+//! - Gemini SSE（`data: {"candidates": [...]}`）与 OpenAI / Anthropic SSE 互相转换
 
 use crate::streaming::aws_parser::{AwsEvent, AwsEventStreamParser};
+use crate::streaming::buffered::parse_sse_block;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
+/// 转换器能够解析的 Anthropic SSE 事件类型
+///
+/// 透传模式下，其他类型的事件原样转发给客户端。
+const ANTHROPIC_CONVERTED_EVENTS: &[&str] =
+    &["content_block_start", "content_block_delta", "message_stop"];
+
 /// 流式格式类型
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StreamFormat {
@@ -24,6 +32,8 @@ pub enum StreamFormat {
     AnthropicSse,
     /// OpenAI SSE 格式
     OpenAiSse,
+    /// Gemini SSE 格式（`data: {"candidates": [...]}`，函数调用整体出现在一个 part 中）
+    GeminiSse,
 }
 
 /// 转换器状态
@@ -135,6 +145,181 @@ impl PartialJsonAccumulator {
     pub fn is_empty(&self) -> bool {
         self.buffer.is_empty()
     }
+
+    /// 结束累积，返回最终的 JSON
+    ///
+    /// 流在参数中途中断时，尽力补全被截断的 JSON（闭合字符串、对象和数组，
+    /// 丢弃不完整的键）；无法修复时保留原始片段。空缓冲区视为完整。
+    pub fn finish(&self) -> FinishedJson {
+        if self.buffer.trim().is_empty()
+            || serde_json::from_str::<serde_json::Value>(&self.buffer).is_ok()
+        {
+            return FinishedJson::Complete(self.buffer.clone());
+        }
+        match repair_truncated_json(&self.buffer) {
+            Some(repaired) => FinishedJson::Repaired(repaired),
+            None => FinishedJson::Raw(self.buffer.clone()),
+        }
+    }
+}
+
+/// 累积结束后的 JSON
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinishedJson {
+    /// 完整的 JSON
+    Complete(String),
+    /// 被截断，已补全为合法 JSON
+    Repaired(String),
+    /// 被截断且无法修复，保留原始片段
+    Raw(String),
+}
+
+impl FinishedJson {
+    /// 是否被截断（包括已修复和无法修复）
+    pub fn is_truncated(&self) -> bool {
+        !matches!(self, FinishedJson::Complete(_))
+    }
+
+    /// 获取 JSON 字符串
+    pub fn into_string(self) -> String {
+        match self {
+            FinishedJson::Complete(json)
+            | FinishedJson::Repaired(json)
+            | FinishedJson::Raw(json) => json,
+        }
+    }
+}
+
+/// 修复截断 JSON 时回退到更早断点的最大次数
+const MAX_JSON_REPAIR_ATTEMPTS: usize = 16;
+
+/// 尽力修复被截断的 JSON
+///
+/// 仅处理合法 JSON 的前缀。先直接补全末尾；仍无法解析时（如截断在键名中），
+/// 回退到上一个 `,`、`{` 或 `[` 处丢弃不完整的成员后重试。
+fn repair_truncated_json(input: &str) -> Option<String> {
+    let scan = scan_json_prefix(input)?;
+    if scan.closers.is_empty() && !scan.in_string {
+        return None;
+    }
+    let mut end = input.len();
+    for _ in 0..MAX_JSON_REPAIR_ATTEMPTS {
+        if let Some(candidate) = close_truncated_json(&input[..end]) {
+            if serde_json::from_str::<serde_json::Value>(&candidate).is_ok() {
+                return Some(candidate);
+            }
+        }
+        end = last_member_boundary(&input[..end])?;
+    }
+    None
+}
+
+/// JSON 前缀的扫描状态
+struct JsonPrefixScan {
+    /// 未闭合容器对应的闭合符（由外到内）
+    closers: Vec<char>,
+    /// 是否停在字符串内
+    in_string: bool,
+    /// 是否停在转义符之后
+    escape_next: bool,
+}
+
+/// 扫描 JSON 前缀，括号不匹配时返回 `None`
+fn scan_json_prefix(input: &str) -> Option<JsonPrefixScan> {
+    let mut closers = Vec::new();
+    let mut in_string = false;
+    let mut escape_next = false;
+    for ch in input.chars() {
+        if escape_next {
+            escape_next = false;
+            continue;
+        }
+        match ch {
+            '\\' if in_string => escape_next = true,
+            '"' => in_string = !in_string,
+            '{' if !in_string => closers.push('}'),
+            '[' if !in_string => closers.push(']'),
+            // 闭合符与最近的未闭合容器不匹配时不是合法前缀
+            '}' | ']' if !in_string && closers.pop() != Some(ch) => return None,
+            _ => {}
+        }
+    }
+    Some(JsonPrefixScan {
+        closers,
+        in_string,
+        escape_next,
+    })
+}
+
+/// 补全截断的 JSON 末尾：闭合字符串、补齐字面量/数字/缺失的值，再闭合容器
+fn close_truncated_json(input: &str) -> Option<String> {
+    let JsonPrefixScan {
+        closers,
+        in_string,
+        escape_next,
+    } = scan_json_prefix(input)?;
+
+    let mut out = input.to_string();
+    if in_string {
+        if escape_next {
+            out.pop();
+        }
+        // 丢弃不完整的 \uXXXX 转义
+        if let Some(pos) = out.rfind("\\u") {
+            if out.len() - pos < 6 && out[pos + 2..].chars().all(|c| c.is_ascii_hexdigit()) {
+                out.truncate(pos);
+            }
+        }
+        out.push('"');
+    } else {
+        out.truncate(out.trim_end().len());
+        // 不完整的字面量补齐，不完整的数字去掉末尾符号
+        let word_start = out
+            .rfind(|c: char| !c.is_ascii_alphabetic())
+            .map_or(0, |i| i + 1);
+        let word = &out[word_start..];
+        let in_number = out[..word_start].ends_with(|c: char| c.is_ascii_digit() || c == '.');
+        if !word.is_empty() && !in_number {
+            let literal = ["true", "false", "null"]
+                .into_iter()
+                .find(|lit| lit.starts_with(word))?;
+            out.replace_range(word_start.., literal);
+        } else {
+            while out.ends_with(['.', '-', '+', 'e', 'E']) {
+                out.pop();
+            }
+        }
+        if out.ends_with(',') {
+            out.pop();
+        } else if out.ends_with(':') {
+            out.push_str("null");
+        }
+    }
+    out.extend(closers.iter().rev());
+    Some(out)
+}
+
+/// 查找字符串外最后一个成员边界，返回截断位置
+///
+/// `,` 处截断到逗号之前，`{`/`[` 处截断到括号之后；只返回比当前更短的位置。
+fn last_member_boundary(input: &str) -> Option<usize> {
+    let mut boundary = None;
+    let mut in_string = false;
+    let mut escape_next = false;
+    for (i, ch) in input.char_indices() {
+        if escape_next {
+            escape_next = false;
+            continue;
+        }
+        match ch {
+            '\\' if in_string => escape_next = true,
+            '"' => in_string = !in_string,
+            ',' if !in_string => boundary = Some(i),
+            '{' | '[' if !in_string && i + 1 < input.len() => boundary = Some(i + 1),
+            _ => {}
+        }
+    }
+    boundary
 }
 
 /// 流式格式转换器
@@ -162,6 +347,16 @@ pub struct StreamConverter {
     message_started: bool,
     /// 累积的内容（用于重建完整响应）
     accumulated_content: String,
+    /// 是否原样透传无法识别的 SSE 事件
+    passthrough_unknown: bool,
+    /// 已透传的事件类型（按出现顺序）
+    passed_through_events: Vec<String>,
+    /// 当前打开的 Anthropic 文本块索引（Gemini SSE 转换为 Anthropic SSE 时使用）
+    open_text_block: Option<u32>,
+    /// 尚未完整接收的 SSE 数据（Anthropic / Gemini SSE 源，或 OpenAI SSE 转 Gemini SSE）
+    ///
+    /// 网络 chunk 可能在事件或多字节字符中间断开，按字节缓冲到事件结束的空行。
+    sse_buffer: Vec<u8>,
 }
 
 impl StreamConverter {
@@ -184,6 +379,10 @@ impl StreamConverter {
             next_content_block_index: 0,
             message_started: false,
             accumulated_content: String::new(),
+            passthrough_unknown: false,
+            passed_through_events: Vec::new(),
+            open_text_block: None,
+            sse_buffer: Vec::new(),
         }
     }
 
@@ -194,6 +393,15 @@ impl StreamConverter {
         converter
     }
 
+    /// 设置是否透传无法识别的 SSE 事件
+    ///
+    /// 启用后，转换器不理解的事件（如 Anthropic 的 `ping`、`message_start`）
+    /// 会原样转发给客户端，能识别的事件仍照常转换并提取内容。
+    pub fn with_passthrough(mut self, enabled: bool) -> Self {
+        self.passthrough_unknown = enabled;
+        self
+    }
+
     /// 获取当前状态
     pub fn state(&self) -> &ConverterState {
         &self.state
@@ -209,6 +417,11 @@ impl StreamConverter {
         &self.accumulated_content
     }
 
+    /// 获取已透传的事件类型
+    pub fn passed_through_events(&self) -> &[String] {
+        &self.passed_through_events
+    }
+
     /// 重置转换器
     pub fn reset(&mut self) {
         if let Some(parser) = &mut self.aws_parser {
@@ -220,6 +433,9 @@ impl StreamConverter {
         self.next_content_block_index = 0;
         self.message_started = false;
         self.accumulated_content.clear();
+        self.passed_through_events.clear();
+        self.open_text_block = None;
+        self.sse_buffer.clear();
     }
 
     /// 转换 chunk
@@ -242,6 +458,7 @@ impl StreamConverter {
             StreamFormat::AwsEventStream => self.convert_aws_event_stream(chunk),
             StreamFormat::AnthropicSse => self.convert_anthropic_sse(chunk),
             StreamFormat::OpenAiSse => self.convert_openai_sse(chunk),
+            StreamFormat::GeminiSse => self.convert_gemini_sse(chunk),
         }
     }
 
@@ -259,6 +476,13 @@ impl StreamConverter {
             }
         }
 
+        // 处理末尾缺少空行的 SSE 事件
+        let rest = std::mem::take(&mut self.sse_buffer);
+        let rest = String::from_utf8_lossy(&rest);
+        if !rest.trim().is_empty() {
+            events.extend(self.convert_sse_events(format!("{}\n\n", rest.trim_end())));
+        }
+
         // 生成结束事件
         events.extend(self.generate_end_events());
 
@@ -283,6 +507,7 @@ impl StreamConverter {
         match self.target_format {
             StreamFormat::AnthropicSse => self.aws_to_anthropic(event),
             StreamFormat::OpenAiSse => self.aws_to_openai(event),
+            StreamFormat::GeminiSse => self.aws_to_gemini(event),
             StreamFormat::AwsEventStream => {
                 // 源和目标相同，直接序列化
                 if let Some(json) = crate::streaming::aws_parser::serialize_event(event) {
@@ -442,23 +667,99 @@ impl StreamConverter {
         sse_events
     }
 
-    /// 转换 Anthropic SSE（直通或转换为 OpenAI）
+    /// AWS Event Stream 到 Gemini SSE 转换
+    ///
+    /// Gemini 的函数调用不分片，工具参数累积到 `ToolUseStop` 后整体发送。
+    fn aws_to_gemini(&mut self, event: &AwsEvent) -> Vec<String> {
+        match event {
+            AwsEvent::Content { text } => {
+                self.accumulated_content.push_str(text);
+                vec![self.create_gemini_text_chunk(text)]
+            }
+            AwsEvent::ToolUseStart { id, name } => {
+                let index = self.tool_accumulators.len() as u32;
+                self.tool_accumulators.insert(
+                    id.clone(),
+                    ToolCallAccumulator {
+                        id: id.clone(),
+                        name: name.clone(),
+                        input: String::new(),
+                        started: true,
+                        index,
+                    },
+                );
+                vec![]
+            }
+            AwsEvent::ToolUseInput { id, input } => {
+                if let Some(acc) = self.tool_accumulators.get_mut(id) {
+                    acc.input.push_str(input);
+                }
+                vec![]
+            }
+            AwsEvent::ToolUseStop { id } => self
+                .tool_accumulators
+                .remove(id)
+                .map(|acc| self.create_gemini_function_call_chunk(&acc))
+                .into_iter()
+                .collect(),
+            AwsEvent::Stop
+            | AwsEvent::Usage { .. }
+            | AwsEvent::FollowupPrompt { .. }
+            | AwsEvent::ParseError { .. } => {
+                // 结束事件在 finish() 中处理，其余事件忽略
+                vec![]
+            }
+        }
+    }
+
+    /// 缓冲 SSE 数据，返回已完整接收的事件（不含末尾未结束的事件）
+    fn take_complete_sse(&mut self, chunk: &[u8]) -> Option<String> {
+        // `\r` 在 JSON 字符串中总是转义的，只会出现在行尾，去掉后行尾统一为 `\n`
+        self.sse_buffer
+            .extend(chunk.iter().copied().filter(|&b| b != b'\r'));
+        let end = self
+            .sse_buffer
+            .windows(2)
+            .rposition(|w| w == b"\n\n")
+            .map(|i| i + 2)?;
+        // 事件边界是换行符，已完整接收的部分不会截断多字节字符
+        let complete: Vec<u8> = self.sse_buffer.drain(..end).collect();
+        Some(String::from_utf8_lossy(&complete).into_owned())
+    }
+
+    /// 按源格式转换完整的 SSE 事件
+    fn convert_sse_events(&mut self, data: String) -> Vec<String> {
+        match self.source_format {
+            StreamFormat::AnthropicSse => self.convert_anthropic_events(data),
+            StreamFormat::GeminiSse => self.convert_gemini_events(data),
+            StreamFormat::OpenAiSse => self.openai_to_gemini(&data),
+            StreamFormat::AwsEventStream => vec![],
+        }
+    }
+
+    /// 转换 Anthropic SSE（直通或转换为 OpenAI / Gemini）
     fn convert_anthropic_sse(&mut self, chunk: &[u8]) -> Vec<String> {
-        // 解析 SSE 数据
-        let data = match String::from_utf8(chunk.to_vec()) {
-            Ok(s) => s,
-            Err(_) => return vec![],
-        };
+        match self.take_complete_sse(chunk) {
+            Some(data) => self.convert_anthropic_events(data),
+            None => vec![],
+        }
+    }
 
+    /// 转换完整的 Anthropic SSE 事件（直通或转换为 OpenAI / Gemini）
+    fn convert_anthropic_events(&mut self, data: String) -> Vec<String> {
         match self.target_format {
             StreamFormat::AnthropicSse => {
-                // 直通
+                // 直通（透传模式下同时提取内容用于 Flow 捕获）
+                if self.passthrough_unknown {
+                    self.track_anthropic_passthrough(&data);
+                }
                 vec![data]
             }
             StreamFormat::OpenAiSse => {
                 // 转换为 OpenAI 格式
                 self.anthropic_to_openai(&data)
             }
+            StreamFormat::GeminiSse => self.anthropic_to_gemini(&data),
             StreamFormat::AwsEventStream => {
                 // 不支持反向转换
                 vec![]
@@ -472,113 +773,415 @@ impl StreamConverter {
     fn anthropic_to_openai(&mut self, data: &str) -> Vec<String> {
         let mut sse_events = Vec::new();
 
+        for block in data.split("\n\n").filter(|b| !b.trim().is_empty()) {
+            // 透传无法识别的事件
+            if self.passthrough_unknown {
+                if let Some(event_type) = sse_event_type(block) {
+                    if !ANTHROPIC_CONVERTED_EVENTS.contains(&event_type.as_str()) {
+                        sse_events.push(format!("{}\n\n", block.trim_end()));
+                        self.passed_through_events.push(event_type);
+                        continue;
+                    }
+                }
+            }
+            sse_events.extend(self.anthropic_block_to_openai(block));
+        }
+
+        sse_events
+    }
+
+    /// 直通 Anthropic SSE 时提取文本内容并记录无法识别的事件
+    fn track_anthropic_passthrough(&mut self, data: &str) {
+        for block in data.split("\n\n").filter(|b| !b.trim().is_empty()) {
+            let Some(event_type) = sse_event_type(block) else {
+                continue;
+            };
+            if !ANTHROPIC_CONVERTED_EVENTS.contains(&event_type.as_str()) {
+                self.passed_through_events.push(event_type);
+                continue;
+            }
+            if let Some(text) = parse_sse_block(block)
+                .and_then(|e| serde_json::from_str::<serde_json::Value>(&e.data).ok())
+                .and_then(|e| anthropic_event_text(&e).map(str::to_string))
+            {
+                self.accumulated_content.push_str(&text);
+            }
+        }
+    }
+
+    /// 转换单个 Anthropic SSE 事件块
+    fn anthropic_block_to_openai(&mut self, data: &str) -> Vec<String> {
+        let mut sse_events = Vec::new();
+
         // 解析 SSE 事件
-        for line in data.lines() {
-            if let Some(json_str) = line.strip_prefix("data: ") {
-                if json_str == "[DONE]" {
-                    sse_events.push("data: [DONE]\n\n".to_string());
-                    continue;
+        let Some(sse) = parse_sse_block(data) else {
+            return sse_events;
+        };
+        if sse.data == "[DONE]" {
+            sse_events.push("data: [DONE]\n\n".to_string());
+            return sse_events;
+        }
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(&sse.data) else {
+            return sse_events;
+        };
+
+        // 文本增量（文本块开始时携带的初始文本同样计入内容）
+        if let Some(text) = anthropic_event_text(&event) {
+            if !text.is_empty() {
+                self.accumulated_content.push_str(text);
+                sse_events.push(self.create_openai_content_chunk(text, false));
+            }
+            return sse_events;
+        }
+
+        match event.get("type").and_then(|t| t.as_str()) {
+            Some("content_block_delta") => {
+                // 工具调用参数增量
+                if let Some(partial_json) = event
+                    .pointer("/delta/partial_json")
+                    .and_then(|t| t.as_str())
+                {
+                    let index = event.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as u32;
+                    let tool_info = self
+                        .tool_accumulators
+                        .values_mut()
+                        .find(|a| a.index == index)
+                        .map(|acc| {
+                            acc.input.push_str(partial_json);
+                            (acc.index, acc.id.clone(), acc.name.clone())
+                        });
+                    if let Some((idx, tool_id, tool_name)) = tool_info {
+                        sse_events.push(self.create_openai_tool_call_chunk(
+                            idx,
+                            &tool_id,
+                            &tool_name,
+                            partial_json,
+                            false,
+                        ));
+                    }
                 }
+            }
+            Some("content_block_start") => {
+                if let Some(content_block) = event
+                    .get("content_block")
+                    .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+                {
+                    let id = content_block
+                        .get("id")
+                        .and_then(|i| i.as_str())
+                        .unwrap_or("");
+                    let name = content_block
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .unwrap_or("");
+                    let index = event.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as u32;
+                    self.tool_accumulators.insert(
+                        id.to_string(),
+                        ToolCallAccumulator {
+                            id: id.to_string(),
+                            name: name.to_string(),
+                            input: String::new(),
+                            started: true,
+                            index,
+                        },
+                    );
+                    sse_events.push(self.create_openai_tool_call_chunk(index, id, name, "", true));
+                }
+            }
+            Some("message_stop") => {
+                sse_events.push(self.create_openai_finish_chunk("stop"));
+                sse_events.push("data: [DONE]\n\n".to_string());
+            }
+            _ => {}
+        }
 
-                if let Ok(event) = serde_json::from_str::<serde_json::Value>(json_str) {
-                    if let Some(event_type) = event.get("type").and_then(|t| t.as_str()) {
-                        match event_type {
-                            "content_block_delta" => {
-                                if let Some(delta) = event.get("delta") {
-                                    if let Some(text) = delta.get("text").and_then(|t| t.as_str()) {
-                                        self.accumulated_content.push_str(text);
-                                        sse_events
-                                            .push(self.create_openai_content_chunk(text, false));
-                                    } else if let Some(partial_json) =
-                                        delta.get("partial_json").and_then(|t| t.as_str())
-                                    {
-                                        // 工具调用参数增量
-                                        let index = event
-                                            .get("index")
-                                            .and_then(|i| i.as_u64())
-                                            .unwrap_or(0)
-                                            as u32;
-                                        let tool_info = self
-                                            .tool_accumulators
-                                            .values_mut()
-                                            .find(|a| a.index == index)
-                                            .map(|acc| {
-                                                acc.input.push_str(partial_json);
-                                                (acc.index, acc.id.clone(), acc.name.clone())
-                                            });
-                                        if let Some((idx, tool_id, tool_name)) = tool_info {
-                                            sse_events.push(self.create_openai_tool_call_chunk(
-                                                idx,
-                                                &tool_id,
-                                                &tool_name,
-                                                partial_json,
-                                                false,
-                                            ));
-                                        }
-                                    }
-                                }
-                            }
-                            "content_block_start" => {
-                                if let Some(content_block) = event.get("content_block") {
-                                    if content_block.get("type").and_then(|t| t.as_str())
-                                        == Some("tool_use")
-                                    {
-                                        let id = content_block
-                                            .get("id")
-                                            .and_then(|i| i.as_str())
-                                            .unwrap_or("");
-                                        let name = content_block
-                                            .get("name")
-                                            .and_then(|n| n.as_str())
-                                            .unwrap_or("");
-                                        let index = event
-                                            .get("index")
-                                            .and_then(|i| i.as_u64())
-                                            .unwrap_or(0)
-                                            as u32;
-                                        self.tool_accumulators.insert(
-                                            id.to_string(),
-                                            ToolCallAccumulator {
-                                                id: id.to_string(),
-                                                name: name.to_string(),
-                                                input: String::new(),
-                                                started: true,
-                                                index,
-                                            },
-                                        );
-                                        sse_events.push(self.create_openai_tool_call_chunk(
-                                            index, id, name, "", true,
-                                        ));
-                                    }
-                                }
-                            }
-                            "message_stop" => {
-                                sse_events.push(self.create_openai_finish_chunk("stop"));
-                                sse_events.push("data: [DONE]\n\n".to_string());
-                            }
-                            _ => {}
+        sse_events
+    }
+
+    /// Anthropic SSE 到 Gemini SSE 转换
+    ///
+    /// 文本增量逐条转换，工具调用参数累积到 `content_block_stop` 后整体发送。
+    fn anthropic_to_gemini(&mut self, data: &str) -> Vec<String> {
+        let mut sse_events = Vec::new();
+
+        for event in sse_json_events(data) {
+            if let Some(text) = anthropic_event_text(&event) {
+                if !text.is_empty() {
+                    self.accumulated_content.push_str(text);
+                    sse_events.push(self.create_gemini_text_chunk(text));
+                }
+                continue;
+            }
+
+            let index = event.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as u32;
+            match event.get("type").and_then(|t| t.as_str()) {
+                Some("content_block_start") => {
+                    if let Some(content_block) = event
+                        .get("content_block")
+                        .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+                    {
+                        let id = content_block
+                            .get("id")
+                            .and_then(|i| i.as_str())
+                            .unwrap_or("");
+                        let name = content_block
+                            .get("name")
+                            .and_then(|n| n.as_str())
+                            .unwrap_or("");
+                        self.tool_accumulators.insert(
+                            id.to_string(),
+                            ToolCallAccumulator {
+                                id: id.to_string(),
+                                name: name.to_string(),
+                                input: String::new(),
+                                started: true,
+                                index,
+                            },
+                        );
+                    }
+                }
+                Some("content_block_delta") => {
+                    if let Some(partial_json) = event
+                        .pointer("/delta/partial_json")
+                        .and_then(|t| t.as_str())
+                    {
+                        if let Some(acc) = self
+                            .tool_accumulators
+                            .values_mut()
+                            .find(|a| a.index == index)
+                        {
+                            acc.input.push_str(partial_json);
                         }
                     }
                 }
+                Some("content_block_stop") => {
+                    let id = self
+                        .tool_accumulators
+                        .values()
+                        .find(|a| a.index == index)
+                        .map(|a| a.id.clone());
+                    if let Some(acc) = id.and_then(|id| self.tool_accumulators.remove(&id)) {
+                        sse_events.push(self.create_gemini_function_call_chunk(&acc));
+                    }
+                }
+                _ => {}
             }
         }
 
         sse_events
     }
 
-    /// 转换 OpenAI SSE（直通）
+    /// 转换 OpenAI SSE（直通，或转换为 Gemini）
     fn convert_openai_sse(&mut self, chunk: &[u8]) -> Vec<String> {
+        if self.target_format == StreamFormat::GeminiSse {
+            return match self.take_complete_sse(chunk) {
+                Some(data) => self.openai_to_gemini(&data),
+                None => vec![],
+            };
+        }
         match String::from_utf8(chunk.to_vec()) {
             Ok(s) => vec![s],
             Err(_) => vec![],
         }
     }
 
+    /// OpenAI SSE 到 Gemini SSE 转换
+    ///
+    /// 工具调用按 `index` 累积参数，收到 `finish_reason` 时整体发送。
+    fn openai_to_gemini(&mut self, data: &str) -> Vec<String> {
+        let mut sse_events = Vec::new();
+
+        for chunk in sse_json_events(data) {
+            let Some(choice) = chunk.pointer("/choices/0") else {
+                continue;
+            };
+            if let Some(text) = choice
+                .pointer("/delta/content")
+                .and_then(|c| c.as_str())
+                .filter(|t| !t.is_empty())
+            {
+                self.accumulated_content.push_str(text);
+                sse_events.push(self.create_gemini_text_chunk(text));
+            }
+
+            let tool_calls = choice
+                .pointer("/delta/tool_calls")
+                .and_then(|t| t.as_array());
+            for tool_call in tool_calls.into_iter().flatten() {
+                let index = tool_call.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as u32;
+                if let Some(id) = tool_call.get("id").and_then(|i| i.as_str()) {
+                    let name = tool_call
+                        .pointer("/function/name")
+                        .and_then(|n| n.as_str())
+                        .unwrap_or("");
+                    self.tool_accumulators.insert(
+                        id.to_string(),
+                        ToolCallAccumulator {
+                            id: id.to_string(),
+                            name: name.to_string(),
+                            input: String::new(),
+                            started: true,
+                            index,
+                        },
+                    );
+                }
+                if let Some(arguments) = tool_call
+                    .pointer("/function/arguments")
+                    .and_then(|a| a.as_str())
+                {
+                    if let Some(acc) = self
+                        .tool_accumulators
+                        .values_mut()
+                        .find(|a| a.index == index)
+                    {
+                        acc.input.push_str(arguments);
+                    }
+                }
+            }
+
+            if choice.get("finish_reason").is_some_and(|r| !r.is_null()) {
+                sse_events.extend(self.flush_gemini_function_calls());
+            }
+        }
+
+        sse_events
+    }
+
+    /// 转换 Gemini SSE（直通或转换为 OpenAI / Anthropic）
+    fn convert_gemini_sse(&mut self, chunk: &[u8]) -> Vec<String> {
+        match self.take_complete_sse(chunk) {
+            Some(data) => self.convert_gemini_events(data),
+            None => vec![],
+        }
+    }
+
+    /// 转换完整的 Gemini SSE 事件
+    fn convert_gemini_events(&mut self, data: String) -> Vec<String> {
+        match self.target_format {
+            StreamFormat::GeminiSse => {
+                // 直通，同时提取内容用于 Flow 捕获
+                for event in sse_json_events(&data) {
+                    for part in gemini_parts(&event) {
+                        if let Some(text) = gemini_part_text(part) {
+                            self.accumulated_content.push_str(text);
+                        }
+                    }
+                }
+                vec![data]
+            }
+            StreamFormat::OpenAiSse | StreamFormat::AnthropicSse => sse_json_events(&data)
+                .iter()
+                .flat_map(|event| self.gemini_event_to_sse(event))
+                .collect(),
+            StreamFormat::AwsEventStream => {
+                // 不支持反向转换
+                vec![]
+            }
+        }
+    }
+
+    /// 单个 Gemini 事件转换为 OpenAI 或 Anthropic SSE
+    ///
+    /// 文本 part 转换为文本增量（跳过思考摘要），`functionCall` part 转换为
+    /// 一次性给出完整参数的工具调用。
+    fn gemini_event_to_sse(&mut self, event: &serde_json::Value) -> Vec<String> {
+        let mut sse_events = Vec::new();
+        let to_anthropic = self.target_format == StreamFormat::AnthropicSse;
+
+        if to_anthropic && !self.message_started {
+            sse_events.push(self.create_anthropic_message_start());
+            self.message_started = true;
+        }
+
+        for part in gemini_parts(event) {
+            if let Some(text) = gemini_part_text(part) {
+                if text.is_empty() {
+                    continue;
+                }
+                self.accumulated_content.push_str(text);
+                if to_anthropic {
+                    let index = match self.open_text_block {
+                        Some(index) => index,
+                        None => {
+                            let index = self.next_content_block_index;
+                            self.next_content_block_index += 1;
+                            self.open_text_block = Some(index);
+                            sse_events.push(self.create_anthropic_content_block_start_text(index));
+                            index
+                        }
+                    };
+                    sse_events.push(self.create_anthropic_text_delta(index, text));
+                } else {
+                    sse_events.push(self.create_openai_content_chunk(text, false));
+                }
+            } else if let Some(call) = part.get("functionCall") {
+                let name = call.get("name").and_then(|n| n.as_str()).unwrap_or("");
+                let id = call
+                    .get("id")
+                    .and_then(|i| i.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("call_{}", Uuid::new_v4()));
+                let arguments = call
+                    .get("args")
+                    .map(|a| a.to_string())
+                    .unwrap_or_else(|| "{}".to_string());
+
+                let index = if to_anthropic {
+                    // 工具块开始前先关闭文本块
+                    if let Some(text_index) = self.open_text_block.take() {
+                        sse_events.push(self.create_anthropic_content_block_stop(text_index));
+                    }
+                    let index = self.next_content_block_index;
+                    self.next_content_block_index += 1;
+                    sse_events
+                        .push(self.create_anthropic_content_block_start_tool(index, &id, name));
+                    sse_events.push(self.create_anthropic_input_json_delta(index, &arguments));
+                    sse_events.push(self.create_anthropic_content_block_stop(index));
+                    index
+                } else {
+                    let index = self.tool_accumulators.len() as u32;
+                    sse_events.push(
+                        self.create_openai_tool_call_chunk(index, &id, name, &arguments, true),
+                    );
+                    index
+                };
+
+                self.tool_accumulators.insert(
+                    id.clone(),
+                    ToolCallAccumulator {
+                        id,
+                        name: name.to_string(),
+                        input: arguments,
+                        started: true,
+                        index,
+                    },
+                );
+            }
+        }
+
+        sse_events
+    }
+
+    /// 按索引顺序把尚未发送的工具调用转换为 Gemini 函数调用
+    fn flush_gemini_function_calls(&mut self) -> Vec<String> {
+        let mut pending: Vec<ToolCallAccumulator> =
+            self.tool_accumulators.drain().map(|(_, acc)| acc).collect();
+        pending.sort_by_key(|acc| acc.index);
+        pending
+            .iter()
+            .map(|acc| self.create_gemini_function_call_chunk(acc))
+            .collect()
+    }
+
     /// 生成结束事件
     fn generate_end_events(&mut self) -> Vec<String> {
         match self.target_format {
             StreamFormat::AnthropicSse => {
                 let mut events = Vec::new();
+                // 关闭 Gemini 源打开的文本块
+                if let Some(index) = self.open_text_block.take() {
+                    events.push(self.create_anthropic_content_block_stop(index));
+                }
                 // message_delta
                 events.push(self.create_anthropic_message_delta());
                 // message_stop
@@ -596,6 +1199,15 @@ impl StreamConverter {
                     "data: [DONE]\n\n".to_string(),
                 ]
             }
+            StreamFormat::GeminiSse => {
+                // 直通时上游已发送 finishReason
+                if self.source_format == StreamFormat::GeminiSse {
+                    return vec![];
+                }
+                let mut events = self.flush_gemini_function_calls();
+                events.push(self.create_gemini_finish_chunk());
+                events
+            }
             StreamFormat::AwsEventStream => {
                 vec![]
             }
@@ -796,54 +1408,158 @@ impl StreamConverter {
         });
         format!("data: {}\n\n", chunk)
     }
+
+    // ========================================================================
+    // Gemini SSE 事件创建辅助方法
+    // ========================================================================
+
+    fn create_gemini_chunk(&self, parts: serde_json::Value, finish_reason: Option<&str>) -> String {
+        let mut candidate = serde_json::json!({
+            "content": {
+                "role": "model",
+                "parts": parts
+            },
+            "index": 0
+        });
+        if let Some(finish_reason) = finish_reason {
+            candidate["finishReason"] = serde_json::json!(finish_reason);
+        }
+        let chunk = serde_json::json!({
+            "candidates": [candidate],
+            "modelVersion": self.model
+        });
+        format!("data: {}\n\n", chunk)
+    }
+
+    fn create_gemini_text_chunk(&self, text: &str) -> String {
+        self.create_gemini_chunk(serde_json::json!([{ "text": text }]), None)
+    }
+
+    fn create_gemini_function_call_chunk(&self, acc: &ToolCallAccumulator) -> String {
+        // Gemini 的 args 是 JSON 对象，无法解析的参数退化为空对象
+        let args = serde_json::from_str::<serde_json::Value>(&acc.input)
+            .ok()
+            .filter(|a| a.is_object())
+            .unwrap_or_else(|| serde_json::json!({}));
+        self.create_gemini_chunk(
+            serde_json::json!([{
+                "functionCall": {
+                    "id": acc.id,
+                    "name": acc.name,
+                    "args": args
+                }
+            }]),
+            None,
+        )
+    }
+
+    fn create_gemini_finish_chunk(&self) -> String {
+        self.create_gemini_chunk(serde_json::json!([]), Some("STOP"))
+    }
 }
 
 // ============================================================================
 // 辅助函数
 // ============================================================================
 
+/// 获取 Anthropic 事件携带的回答文本
+///
+/// 文本来自 `text_delta` 增量，或文本块开始时 `content_block.text` 中的初始文本
+/// （部分上游在块开始时就给出第一段文本）。
+fn anthropic_event_text(event: &serde_json::Value) -> Option<&str> {
+    match event.get("type")?.as_str()? {
+        "content_block_delta" => event.pointer("/delta/text")?.as_str(),
+        "content_block_start" => {
+            let block = event.get("content_block")?;
+            (block.get("type")?.as_str()? == "text")
+                .then(|| block.get("text")?.as_str())
+                .flatten()
+        }
+        _ => None,
+    }
+}
+
+/// 解析 SSE 数据中各事件的 JSON 负载（跳过 `[DONE]` 和无法解析的事件）
+fn sse_json_events(data: &str) -> Vec<serde_json::Value> {
+    data.split("\n\n")
+        .filter_map(parse_sse_block)
+        .filter_map(|sse| serde_json::from_str::<serde_json::Value>(&sse.data).ok())
+        .collect()
+}
+
+/// 获取 Gemini 事件首个候选的 part 列表
+fn gemini_parts(event: &serde_json::Value) -> &[serde_json::Value] {
+    event
+        .pointer("/candidates/0/content/parts")
+        .and_then(|p| p.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// 获取 Gemini part 中的回答文本（思考摘要 `thought: true` 不计入回答）
+fn gemini_part_text(part: &serde_json::Value) -> Option<&str> {
+    if part.get("thought").and_then(|t| t.as_bool()) == Some(true) {
+        return None;
+    }
+    part.get("text")?.as_str()
+}
+
+/// 获取 SSE 事件块的事件类型
+///
+/// 优先使用 `event:` 行，其次使用 `data:` 中 JSON 的 `type` 字段
+fn sse_event_type(block: &str) -> Option<String> {
+    block
+        .lines()
+        .find_map(|line| line.strip_prefix("event:"))
+        .map(|event| event.trim().to_string())
+        .or_else(|| {
+            block
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .find_map(|json_str| {
+                    serde_json::from_str::<serde_json::Value>(json_str.trim())
+                        .ok()?
+                        .get("type")?
+                        .as_str()
+                        .map(str::to_string)
+                })
+        })
+        .filter(|event| !event.is_empty())
+}
+
 /// 从 SSE 事件列表中提取所有文本内容
 pub fn extract_content_from_sse(events: &[String], format: StreamFormat) -> String {
     let mut content = String::new();
 
-    for event in events {
+    let blocks = events
+        .iter()
+        .flat_map(|event| event.split("\n\n"))
+        .filter_map(parse_sse_block)
+        .filter(|sse| sse.data != "[DONE]")
+        .filter_map(|sse| serde_json::from_str::<serde_json::Value>(&sse.data).ok());
+
+    for event in blocks {
         match format {
             StreamFormat::OpenAiSse => {
-                for line in event.lines() {
-                    if let Some(json_str) = line.strip_prefix("data: ") {
-                        if json_str == "[DONE]" {
-                            continue;
-                        }
-                        if let Ok(chunk) = serde_json::from_str::<serde_json::Value>(json_str) {
-                            if let Some(choices) = chunk.get("choices").and_then(|c| c.as_array()) {
-                                for choice in choices {
-                                    if let Some(delta) = choice.get("delta") {
-                                        if let Some(text) =
-                                            delta.get("content").and_then(|c| c.as_str())
-                                        {
-                                            content.push_str(text);
-                                        }
-                                    }
-                                }
-                            }
+                if let Some(choices) = event.get("choices").and_then(|c| c.as_array()) {
+                    for choice in choices {
+                        if let Some(text) =
+                            choice.pointer("/delta/content").and_then(|c| c.as_str())
+                        {
+                            content.push_str(text);
                         }
                     }
                 }
             }
             StreamFormat::AnthropicSse => {
-                for line in event.lines() {
-                    if let Some(json_str) = line.strip_prefix("data: ") {
-                        if let Ok(evt) = serde_json::from_str::<serde_json::Value>(json_str) {
-                            if evt.get("type").and_then(|t| t.as_str())
-                                == Some("content_block_delta")
-                            {
-                                if let Some(delta) = evt.get("delta") {
-                                    if let Some(text) = delta.get("text").and_then(|t| t.as_str()) {
-                                        content.push_str(text);
-                                    }
-                                }
-                            }
-                        }
+                if let Some(text) = anthropic_event_text(&event) {
+                    content.push_str(text);
+                }
+            }
+            StreamFormat::GeminiSse => {
+                for part in gemini_parts(&event) {
+                    if let Some(text) = gemini_part_text(part) {
+                        content.push_str(text);
                     }
                 }
             }
@@ -916,6 +1632,20 @@ pub fn extract_tool_calls_from_sse(
                     }
                 }
             }
+            StreamFormat::GeminiSse => {
+                // Gemini 的函数调用整体出现在一个 part 中
+                for chunk in sse_json_events(event) {
+                    for part in gemini_parts(&chunk) {
+                        let Some(call) = part.get("functionCall") else {
+                            continue;
+                        };
+                        let name = call.get("name").and_then(|n| n.as_str()).unwrap_or("");
+                        let id = call.get("id").and_then(|i| i.as_str()).unwrap_or(name);
+                        let args = call.get("args").map(|a| a.to_string()).unwrap_or_default();
+                        tool_calls.insert(id.to_string(), (name.to_string(), args));
+                    }
+                }
+            }
             StreamFormat::AnthropicSse | StreamFormat::AwsEventStream => {
                 // 简化处理
             }
@@ -1124,6 +1854,64 @@ mod tests {
         assert!(!acc.is_complete());
     }
 
+    fn finish(partial: &str) -> FinishedJson {
+        let mut acc = PartialJsonAccumulator::new();
+        acc.append(partial);
+        acc.finish()
+    }
+
+    #[test]
+    fn test_partial_json_finish_complete() {
+        assert_eq!(
+            finish("{\"a\":1}"),
+            FinishedJson::Complete("{\"a\":1}".to_string())
+        );
+        assert!(!finish("").is_truncated());
+    }
+
+    #[test]
+    fn test_partial_json_finish_repairs_truncation() {
+        let cases = [
+            // 字符串值中途截断
+            ("{\"city\":\"New Yo", "{\"city\":\"New Yo\"}"),
+            // 转义符后截断
+            ("{\"path\":\"C:\\", "{\"path\":\"C:\"}"),
+            // 数字中途截断
+            ("{\"n\":12", "{\"n\":12}"),
+            ("{\"n\":1.", "{\"n\":1}"),
+            ("{\"n\":-", "{\"n\":null}"),
+            ("{\"n\":1.5e", "{\"n\":1.5}"),
+            // 键名中途截断
+            ("{\"a\":1,\"lo", "{\"a\":1}"),
+            ("{\"lo", "{}"),
+            // 冒号后截断
+            ("{\"a\":", "{\"a\":null}"),
+            // 字面量中途截断
+            ("{\"ok\":tr", "{\"ok\":true}"),
+            ("{\"ok\":fal", "{\"ok\":false}"),
+            // 嵌套容器与尾随逗号
+            ("{\"items\":[1,2,", "{\"items\":[1,2]}"),
+            ("{\"a\":[{\"b\":\"x", "{\"a\":[{\"b\":\"x\"}]}"),
+        ];
+        for (input, expected) in cases {
+            let result = finish(input);
+            assert_eq!(
+                result,
+                FinishedJson::Repaired(expected.to_string()),
+                "{}",
+                input
+            );
+            assert!(result.is_truncated());
+        }
+    }
+
+    #[test]
+    fn test_partial_json_finish_keeps_raw_when_unrepairable() {
+        let result = finish("{\"a\":1]}");
+        assert_eq!(result, FinishedJson::Raw("{\"a\":1]}".to_string()));
+        assert!(result.is_truncated());
+    }
+
     #[test]
     fn test_extract_content_from_openai_sse() {
         let events = vec![
@@ -1147,6 +1935,185 @@ mod tests {
         assert_eq!(content, "Hello, world!");
     }
 
+    #[test]
+    fn test_anthropic_to_openai_passthrough_unknown_events() {
+        let chunk = concat!(
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\"}}\n\n",
+            "event: ping\ndata: {\"type\":\"ping\"}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n",
+        );
+
+        // 默认模式下丢弃无法识别的事件
+        let mut converter =
+            StreamConverter::new(StreamFormat::AnthropicSse, StreamFormat::OpenAiSse);
+        let events = converter.convert(chunk.as_bytes());
+        assert_eq!(events.len(), 1);
+        assert!(converter.passed_through_events().is_empty());
+
+        let mut converter =
+            StreamConverter::new(StreamFormat::AnthropicSse, StreamFormat::OpenAiSse)
+                .with_passthrough(true);
+        let events = converter.convert(chunk.as_bytes());
+        assert_eq!(events.len(), 3);
+        assert!(events[0].starts_with("event: message_start\n"));
+        assert_eq!(events[1], "event: ping\ndata: {\"type\":\"ping\"}\n\n");
+        assert!(events[2].contains("\"content\":\"Hi\""));
+        assert_eq!(converter.accumulated_content(), "Hi");
+        assert_eq!(converter.passed_through_events(), ["message_start", "ping"]);
+
+        // 直通模式下同样提取内容并记录透传事件
+        let mut converter =
+            StreamConverter::new(StreamFormat::AnthropicSse, StreamFormat::AnthropicSse)
+                .with_passthrough(true);
+        assert_eq!(converter.convert(chunk.as_bytes()), vec![chunk.to_string()]);
+        assert_eq!(converter.accumulated_content(), "Hi");
+        assert_eq!(converter.passed_through_events(), ["message_start", "ping"]);
+    }
+
+    #[test]
+    fn test_anthropic_sse_split_across_chunks() {
+        let stream = concat!(
+            "event: content_block_start\r\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"你\"}}\r\n\r\n",
+            "event: content_block_delta\ndata:{\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"好 \"}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"world\"}}",
+        );
+
+        // 按 7 字节切分，事件和多字节字符都会跨 chunk
+        let mut converter =
+            StreamConverter::new(StreamFormat::AnthropicSse, StreamFormat::OpenAiSse);
+        let mut events = Vec::new();
+        for chunk in stream.as_bytes().chunks(7) {
+            events.extend(converter.convert(chunk));
+        }
+        events.extend(converter.finish());
+
+        assert_eq!(converter.accumulated_content(), "你好 world");
+        assert_eq!(
+            extract_content_from_sse(&events, StreamFormat::OpenAiSse),
+            "你好 world"
+        );
+    }
+
+    /// 录制的 Gemini 流：思考摘要、文本、函数调用，最后一个事件缺少结尾空行
+    const GEMINI_STREAM: &str = concat!(
+        "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"想一想\",\"thought\":true}],\"role\":\"model\"},\"index\":0}]}\r\n\r\n",
+        "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"我来查询\"}],\"role\":\"model\"},\"index\":0}]}\n\n",
+        "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"天气。\"},{\"functionCall\":{\"id\":\"fc_1\",\"name\":\"get_weather\",\"args\":{\"city\":\"Paris\"}}}],\"role\":\"model\"},\"index\":0}]}\n\n",
+        "data: {\"candidates\":[{\"content\":{\"parts\":[],\"role\":\"model\"},\"finishReason\":\"STOP\",\"index\":0}]}",
+    );
+
+    fn convert_in_chunks(
+        converter: &mut StreamConverter,
+        stream: &str,
+        size: usize,
+    ) -> Vec<String> {
+        let mut events = Vec::new();
+        for chunk in stream.as_bytes().chunks(size) {
+            events.extend(converter.convert(chunk));
+        }
+        events.extend(converter.finish());
+        events
+    }
+
+    #[test]
+    fn test_gemini_to_openai() {
+        let mut converter = StreamConverter::new(StreamFormat::GeminiSse, StreamFormat::OpenAiSse);
+        let events = convert_in_chunks(&mut converter, GEMINI_STREAM, 9);
+
+        assert_eq!(converter.accumulated_content(), "我来查询天气。");
+        assert_eq!(
+            extract_content_from_sse(&events, StreamFormat::OpenAiSse),
+            "我来查询天气。"
+        );
+        assert_eq!(
+            extract_tool_calls_from_sse(&events, StreamFormat::OpenAiSse),
+            vec![(
+                "fc_1".to_string(),
+                "get_weather".to_string(),
+                r#"{"city":"Paris"}"#.to_string()
+            )]
+        );
+        assert!(events
+            .iter()
+            .any(|e| e.contains("\"finish_reason\":\"tool_calls\"")));
+        assert_eq!(events.last().map(String::as_str), Some("data: [DONE]\n\n"));
+    }
+
+    #[test]
+    fn test_gemini_to_anthropic() {
+        let mut converter =
+            StreamConverter::new(StreamFormat::GeminiSse, StreamFormat::AnthropicSse);
+        let events = convert_in_chunks(&mut converter, GEMINI_STREAM, 9);
+
+        assert_eq!(
+            extract_content_from_sse(&events, StreamFormat::AnthropicSse),
+            "我来查询天气。"
+        );
+        let event_types: Vec<String> = events.iter().filter_map(|e| sse_event_type(e)).collect();
+        assert_eq!(
+            event_types,
+            vec![
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop",
+            ]
+        );
+        // 文本块在工具块开始前关闭，工具块使用下一个索引
+        assert!(events[4].contains("\"index\":0"));
+        assert!(
+            events[5].contains("\"name\":\"get_weather\"") && events[5].contains("\"index\":1")
+        );
+        assert!(events[6].contains(r#"\"city\":\"Paris\""#));
+    }
+
+    #[test]
+    fn test_openai_to_gemini() {
+        let stream = concat!(
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Let me \"}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"check.\"}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"get_weather\",\"arguments\":\"{\\\"ci\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"ty\\\":\\\"Paris\\\"}\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+
+        let mut converter = StreamConverter::new(StreamFormat::OpenAiSse, StreamFormat::GeminiSse);
+        let events = convert_in_chunks(&mut converter, stream, 11);
+
+        assert_eq!(
+            extract_content_from_sse(&events, StreamFormat::GeminiSse),
+            "Let me check."
+        );
+        assert_eq!(
+            extract_tool_calls_from_sse(&events, StreamFormat::GeminiSse),
+            vec![(
+                "call_1".to_string(),
+                "get_weather".to_string(),
+                r#"{"city":"Paris"}"#.to_string()
+            )]
+        );
+        assert!(events
+            .last()
+            .is_some_and(|e| e.contains("\"finishReason\":\"STOP\"")));
+    }
+
+    #[test]
+    fn test_gemini_passthrough_tracks_content() {
+        let mut converter = StreamConverter::new(StreamFormat::GeminiSse, StreamFormat::GeminiSse);
+        let events = convert_in_chunks(&mut converter, GEMINI_STREAM, 9);
+
+        assert_eq!(converter.accumulated_content(), "我来查询天气。");
+        // 直通不追加结束事件
+        assert_eq!(events.concat().trim_end(), GEMINI_STREAM.replace('\r', ""));
+    }
+
     #[test]
     fn test_incremental_conversion() {
         let mut converter = StreamConverter::with_model(
@@ -16,11 +16,12 @@
 
 use crate::streaming::converter::{StreamConverter, StreamFormat};
 use crate::streaming::error::StreamError;
-use crate::streaming::metrics::StreamMetrics;
+use crate::streaming::metrics::{stream_latency_histograms, StreamMetrics};
 use crate::streaming::traits::StreamResponse;
 use bytes::Bytes;
 use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::Duration;
@@ -62,6 +63,20 @@ pub struct StreamConfig {
     /// 两个 chunk 之间的最大等待时间。
     #[serde(default = "default_chunk_timeout_ms")]
     pub chunk_timeout_ms: u64,
+
+    /// 是否透传无法识别的 SSE 事件
+    ///
+    /// 启用后，转换器不理解的 Provider 特有事件会原样转发给客户端，
+    /// 以保持完整的事件序列（如 Anthropic SDK 会校验事件顺序）。
+    #[serde(default)]
+    pub passthrough_unknown_events: bool,
+
+    /// 保活间隔（毫秒，0 表示禁用）
+    ///
+    /// 上游长时间没有输出时，按此间隔向客户端发送 SSE 注释 `: keepalive`，
+    /// 避免客户端或中间代理因缓冲或空闲超时而断开。只用于 SSE 格式的响应。
+    #[serde(default = "default_keepalive_interval_ms")]
+    pub keepalive_interval_ms: u64,
 }
 
 fn default_buffer_size() -> usize {
@@ -80,6 +95,10 @@ fn default_chunk_timeout_ms() -> u64 {
     30_000 // 30 秒
 }
 
+fn default_keepalive_interval_ms() -> u64 {
+    15_000 // 15 秒
+}
+
 impl Default for StreamConfig {
     fn default() -> Self {
         Self {
@@ -87,6 +106,8 @@ impl Default for StreamConfig {
             timeout_ms: default_timeout_ms(),
             throttle_ms: default_throttle_ms(),
             chunk_timeout_ms: default_chunk_timeout_ms(),
+            passthrough_unknown_events: false,
+            keepalive_interval_ms: default_keepalive_interval_ms(),
         }
     }
 }
@@ -121,6 +142,18 @@ impl StreamConfig {
         self
     }
 
+    /// 设置是否透传无法识别的 SSE 事件
+    pub fn with_passthrough_unknown_events(mut self, enabled: bool) -> Self {
+        self.passthrough_unknown_events = enabled;
+        self
+    }
+
+    /// 设置保活间隔（0 表示禁用）
+    pub fn with_keepalive_interval_ms(mut self, keepalive_interval_ms: u64) -> Self {
+        self.keepalive_interval_ms = keepalive_interval_ms;
+        self
+    }
+
     /// 获取超时 Duration
     pub fn timeout_duration(&self) -> Duration {
         Duration::from_millis(self.timeout_ms)
@@ -135,6 +168,15 @@ impl StreamConfig {
     pub fn throttle_duration(&self) -> Duration {
         Duration::from_millis(self.throttle_ms)
     }
+
+    /// 获取保活间隔（禁用时返回 `None`）
+    pub fn keepalive_interval(&self) -> Option<Duration> {
+        if self.keepalive_interval_ms > 0 {
+            Some(Duration::from_millis(self.keepalive_interval_ms))
+        } else {
+            None
+        }
+    }
 }
 
 // ============================================================================
@@ -417,7 +459,8 @@ impl ManagedStream {
             context.source_format,
             context.target_format,
             &context.model,
-        );
+        )
+        .with_passthrough(config.passthrough_unknown_events);
 
         Self {
             context,
@@ -442,6 +485,11 @@ impl ManagedStream {
         &self.context
     }
 
+    /// 获取已透传的 SSE 事件类型
+    pub fn passed_through_events(&self) -> &[String] {
+        self.converter.passed_through_events()
+    }
+
     /// 处理接收到的字节
     ///
     /// # 有界缓冲区检查（需求 7.1）
@@ -493,6 +541,7 @@ impl ManagedStream {
     fn finish_stream(&mut self) -> Vec<String> {
         self.finished = true;
         self.context.metrics.finish();
+        stream_latency_histograms().record(&self.context.model, &self.context.metrics);
 
         // 记录详细指标（需求 7.5）
         self.context
@@ -518,6 +567,7 @@ impl ManagedStream {
         self.finished = true;
         self.context.metrics.finish();
         self.context.metrics.record_parse_error();
+        stream_latency_histograms().record(&self.context.model, &self.context.metrics);
 
         error!(
             flow_id = ?self.context.flow_id,
@@ -874,6 +924,110 @@ where
     }
 }
 
+/// SSE 保活注释
+///
+/// 以 `:` 开头的行是 SSE 注释，遵循规范的客户端会直接忽略，不影响事件解析。
+pub const SSE_KEEPALIVE: &str = ": keepalive\n\n";
+
+/// 创建带保活的流
+///
+/// 上游空闲超过保活间隔时插入 SSE 保活注释。保活只插在两个完整事件之间，
+/// 不会拆分事件；非 SSE 格式（AWS Event Stream）或未配置间隔时原样透传。
+///
+/// # 参数
+///
+/// * `stream` - 目标格式的 SSE 事件流
+/// * `config` - 流式配置
+/// * `format` - 目标格式
+pub fn with_keepalive<S>(
+    stream: S,
+    config: &StreamConfig,
+    format: StreamFormat,
+) -> KeepaliveStream<S>
+where
+    S: Stream<Item = Result<String, StreamError>> + Unpin,
+{
+    let interval = match format {
+        StreamFormat::AwsEventStream => None,
+        StreamFormat::AnthropicSse | StreamFormat::OpenAiSse | StreamFormat::GeminiSse => {
+            config.keepalive_interval()
+        }
+    };
+    KeepaliveStream::new(stream, interval)
+}
+
+/// 带保活的流包装器
+pub struct KeepaliveStream<S>
+where
+    S: Stream<Item = Result<String, StreamError>> + Unpin,
+{
+    inner: S,
+    interval: Option<Duration>,
+    sleep: Option<Pin<Box<tokio::time::Sleep>>>,
+    finished: bool,
+}
+
+impl<S> KeepaliveStream<S>
+where
+    S: Stream<Item = Result<String, StreamError>> + Unpin,
+{
+    /// 创建新的保活流（`interval` 为 `None` 时不发送保活）
+    pub fn new(inner: S, interval: Option<Duration>) -> Self {
+        Self {
+            inner,
+            interval,
+            sleep: interval.map(|d| Box::pin(tokio::time::sleep(d))),
+            finished: false,
+        }
+    }
+
+    /// 重新开始计时
+    fn reset_sleep(&mut self) {
+        if let (Some(sleep), Some(interval)) = (self.sleep.as_mut(), self.interval) {
+            sleep.as_mut().reset(Instant::now() + interval);
+        }
+    }
+}
+
+impl<S> Stream for KeepaliveStream<S>
+where
+    S: Stream<Item = Result<String, StreamError>> + Unpin,
+{
+    type Item = Result<String, StreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.finished {
+            return Poll::Ready(None);
+        }
+
+        // 真实事件优先，收到后立即转发并重新计时
+        match Pin::new(&mut self.inner).poll_next(cx) {
+            Poll::Ready(Some(item)) => {
+                self.reset_sleep();
+                return Poll::Ready(Some(item));
+            }
+            Poll::Ready(None) => {
+                // 流结束后不再发送保活
+                self.finished = true;
+                self.sleep = None;
+                return Poll::Ready(None);
+            }
+            Poll::Pending => {}
+        }
+
+        let Some(sleep) = self.sleep.as_mut() else {
+            return Poll::Pending;
+        };
+        match sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                self.reset_sleep();
+                Poll::Ready(Some(Ok(SSE_KEEPALIVE.to_string())))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
 /// 从流中收集所有内容
 ///
 /// 用于测试和调试。
@@ -1058,6 +1212,39 @@ mod tests {
         assert!(events.iter().any(|e| e.contains("error")));
     }
 
+    #[tokio::test]
+    async fn test_keepalive_during_slow_upstream() {
+        // 模拟慢上游：两个事件之间间隔 200ms
+        let events = ["data: {\"id\":1}\n\n", "data: [DONE]\n\n"];
+        let slow = stream::iter(events).then(|event| async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok::<_, StreamError>(event.to_string())
+        });
+        let config = StreamConfig::new().with_keepalive_interval_ms(50);
+        let stream = with_keepalive(Box::pin(slow), &config, StreamFormat::OpenAiSse);
+        let items: Vec<String> = stream.map(|r| r.unwrap()).collect().await;
+
+        // 空闲期间插入保活，真实事件完整且顺序不变
+        let first = items.iter().position(|e| e == events[0]).unwrap();
+        let second = items.iter().position(|e| e == events[1]).unwrap();
+        assert!(first >= 2, "首个事件前应有保活: {:?}", items);
+        assert!(second - first >= 3, "事件之间应有保活: {:?}", items);
+        assert!(items
+            .iter()
+            .all(|e| events.contains(&e.as_str()) || e == SSE_KEEPALIVE));
+        // 流结束后不再发送保活
+        assert_eq!(items.last().map(String::as_str), Some(events[1]));
+
+        // AWS Event Stream 不插入保活
+        let slow = stream::iter(events).then(|event| async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok::<_, StreamError>(event.to_string())
+        });
+        let stream = with_keepalive(Box::pin(slow), &config, StreamFormat::AwsEventStream);
+        let items: Vec<String> = stream.map(|r| r.unwrap()).collect().await;
+        assert_eq!(items.len(), 2);
+    }
+
     #[tokio::test]
     async fn test_managed_stream_metrics() {
         let context = StreamContext::new(
@@ -6,11 +6,150 @@
 //!
 //! - 需求 4.5: 跟踪 chunk 数量和接收的总字节数
 //! - 需求 7.5: 记录流式指标（吞吐量、延迟、错误率）
+//!
+//! 另外按模型汇总 TTFB 与 chunk 间隔的直方图，用于区分"首包慢"和"中途卡顿"的模型。
 
+use crate::flow_monitor::Distribution;
 use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
 use tracing::info;
 
+/// 延迟直方图的桶边界（毫秒）
+pub const LATENCY_BUCKET_BOUNDS_MS: [u64; 12] = [
+    10, 25, 50, 100, 250, 500, 1000, 2000, 5000, 10000, 30000, 60000,
+];
+
+/// 桶数量（最后一个桶为溢出桶）
+const LATENCY_BUCKET_COUNT: usize = LATENCY_BUCKET_BOUNDS_MS.len() + 1;
+
+/// 固定桶延迟直方图
+///
+/// 每次记录只需一次桶定位和计数递增，适合逐 chunk 更新。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    /// 各桶计数，下标与 `LATENCY_BUCKET_BOUNDS_MS` 对应，最后一个为溢出桶
+    counts: [u64; LATENCY_BUCKET_COUNT],
+    /// 样本总数
+    total: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; LATENCY_BUCKET_COUNT],
+            total: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    /// 创建空直方图
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一个样本（毫秒）
+    pub fn record(&mut self, value_ms: u64) {
+        let idx = LATENCY_BUCKET_BOUNDS_MS.partition_point(|&bound| bound <= value_ms);
+        self.counts[idx] += 1;
+        self.total += 1;
+    }
+
+    /// 合并另一个直方图
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other_count;
+        }
+        self.total += other.total;
+    }
+
+    /// 样本总数
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// 重置所有计数
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// 估算分位数（毫秒）
+    ///
+    /// 返回样本所在桶的上边界；落入溢出桶时返回最大边界。
+    /// `quantile` 取值范围为 0.0 - 1.0，空直方图返回 None。
+    pub fn percentile(&self, quantile: f64) -> Option<u64> {
+        if self.total == 0 {
+            return None;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * self.total as f64).ceil() as u64).max(1);
+        let mut cumulative = 0;
+        for (idx, count) in self.counts.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= rank {
+                let bound_idx = idx.min(LATENCY_BUCKET_BOUNDS_MS.len() - 1);
+                return Some(LATENCY_BUCKET_BOUNDS_MS[bound_idx]);
+            }
+        }
+        LATENCY_BUCKET_BOUNDS_MS.last().copied()
+    }
+
+    /// 计算 p50/p95/p99
+    pub fn percentiles(&self) -> LatencyPercentiles {
+        LatencyPercentiles {
+            count: self.total,
+            p50_ms: self.percentile(0.50),
+            p95_ms: self.percentile(0.95),
+            p99_ms: self.percentile(0.99),
+        }
+    }
+
+    /// 转换为分布数据（桶标签与延迟直方图统计一致）
+    pub fn to_distribution(&self) -> Distribution {
+        let bounds = &LATENCY_BUCKET_BOUNDS_MS;
+        let buckets = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| {
+                let label = if i == 0 {
+                    format!("<{}ms", bounds[0])
+                } else if i == bounds.len() {
+                    format!(">={}ms", bounds[bounds.len() - 1])
+                } else {
+                    format!("{}-{}ms", bounds[i - 1], bounds[i])
+                };
+                (label, *count)
+            })
+            .collect();
+
+        Distribution {
+            buckets,
+            total: self.total,
+        }
+    }
+}
+
+/// 延迟分位数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyPercentiles {
+    /// 样本数
+    pub count: u64,
+    /// p50（毫秒）
+    pub p50_ms: Option<u64>,
+    /// p95（毫秒）
+    pub p95_ms: Option<u64>,
+    /// p99（毫秒）
+    pub p99_ms: Option<u64>,
+}
+
 /// 流式传输指标
 ///
 /// 记录流式传输过程中的各种性能指标。
@@ -89,6 +228,12 @@ pub struct StreamMetrics {
     ///
     /// 对应需求 4.6: 事件节流
     pub throttled_event_count: u32,
+
+    /// chunk 间隔直方图
+    ///
+    /// 仅记录相邻 chunk 之间的间隔，首个 chunk 的等待时间计入 TTFB。
+    #[serde(default)]
+    pub chunk_gap_histogram: LatencyHistogram,
 }
 
 impl Default for StreamMetrics {
@@ -107,6 +252,7 @@ impl Default for StreamMetrics {
             max_chunk_size: None,
             buffer_overflow_count: 0,
             throttled_event_count: 0,
+            chunk_gap_histogram: LatencyHistogram::default(),
         }
     }
 }
@@ -131,9 +277,16 @@ impl StreamMetrics {
     /// 更新 chunk 计数、字节数和最后 chunk 时间。
     /// 同时更新最小/最大 chunk 大小统计。
     pub fn record_chunk(&mut self, bytes: usize) {
+        let now = Utc::now();
         self.chunk_count += 1;
         self.total_bytes += bytes;
-        self.last_chunk_time = Some(Utc::now());
+
+        // 记录 chunk 间隔（首个 chunk 没有上一个 chunk，只计入 TTFB）
+        if let Some(last) = self.last_chunk_time {
+            let gap_ms = (now - last).num_milliseconds().max(0) as u64;
+            self.chunk_gap_histogram.record(gap_ms);
+        }
+        self.last_chunk_time = Some(now);
 
         // 更新最小/最大 chunk 大小（需求 7.5）
         match self.min_chunk_size {
@@ -322,6 +475,91 @@ impl StreamMetrics {
     }
 }
 
+// ============================================================================
+// 按模型汇总的流式延迟直方图
+// ============================================================================
+
+/// 单个模型的流式延迟直方图
+#[derive(Debug, Clone, Default)]
+struct ModelStreamHistograms {
+    /// 流数量
+    stream_count: u64,
+    /// TTFB 直方图
+    ttfb: LatencyHistogram,
+    /// chunk 间隔直方图
+    chunk_gap: LatencyHistogram,
+}
+
+/// 单个模型的流式延迟统计（用于前端展示）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelStreamLatency {
+    /// 模型名称
+    pub model: String,
+    /// 流数量
+    pub stream_count: u64,
+    /// TTFB 分位数
+    pub ttfb: LatencyPercentiles,
+    /// chunk 间隔分位数
+    pub chunk_gap: LatencyPercentiles,
+    /// TTFB 分布
+    pub ttfb_histogram: Distribution,
+    /// chunk 间隔分布
+    pub chunk_gap_histogram: Distribution,
+}
+
+/// 按模型汇总的流式延迟直方图
+#[derive(Debug, Default)]
+pub struct StreamLatencyHistograms {
+    by_model: RwLock<HashMap<String, ModelStreamHistograms>>,
+}
+
+impl StreamLatencyHistograms {
+    /// 创建空的汇总
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 汇总一个已结束流的指标
+    pub fn record(&self, model: &str, metrics: &StreamMetrics) {
+        let mut by_model = self.by_model.write();
+        let entry = by_model.entry(model.to_string()).or_default();
+        entry.stream_count += 1;
+        if let Some(ttfb_ms) = metrics.ttfb_ms {
+            entry.ttfb.record(ttfb_ms);
+        }
+        entry.chunk_gap.merge(&metrics.chunk_gap_histogram);
+    }
+
+    /// 获取各模型的统计（按模型名排序）
+    pub fn snapshot(&self) -> Vec<ModelStreamLatency> {
+        let by_model = self.by_model.read();
+        let mut result: Vec<ModelStreamLatency> = by_model
+            .iter()
+            .map(|(model, histograms)| ModelStreamLatency {
+                model: model.clone(),
+                stream_count: histograms.stream_count,
+                ttfb: histograms.ttfb.percentiles(),
+                chunk_gap: histograms.chunk_gap.percentiles(),
+                ttfb_histogram: histograms.ttfb.to_distribution(),
+                chunk_gap_histogram: histograms.chunk_gap.to_distribution(),
+            })
+            .collect();
+        result.sort_by(|a, b| a.model.cmp(&b.model));
+        result
+    }
+
+    /// 重置所有直方图
+    pub fn reset(&self) {
+        self.by_model.write().clear();
+    }
+}
+
+/// 获取全局流式延迟直方图
+pub fn stream_latency_histograms() -> &'static StreamLatencyHistograms {
+    static HISTOGRAMS: OnceLock<StreamLatencyHistograms> = OnceLock::new();
+    HISTOGRAMS.get_or_init(StreamLatencyHistograms::new)
+}
+
 // ============================================================================
 // 测试模块
 // ============================================================================
@@ -564,4 +802,73 @@ mod tests {
         metrics.log_metrics(Some("test-flow-id"));
         metrics.log_metrics(None);
     }
+
+    #[test]
+    fn test_first_chunk_recorded_as_ttfb_not_gap() {
+        let mut metrics = StreamMetrics::new();
+        sleep(Duration::from_millis(20));
+
+        metrics.record_chunk(100);
+        assert!(metrics.ttfb_ms.unwrap() >= 20);
+        assert!(metrics.chunk_gap_histogram.is_empty());
+
+        metrics.record_chunk(100);
+        metrics.record_chunk(100);
+        assert_eq!(metrics.chunk_gap_histogram.total(), 2);
+    }
+
+    #[test]
+    fn test_latency_histogram_percentiles() {
+        let mut histogram = LatencyHistogram::new();
+        assert!(histogram.percentile(0.5).is_none());
+
+        for _ in 0..90 {
+            histogram.record(5);
+        }
+        for _ in 0..9 {
+            histogram.record(300);
+        }
+        histogram.record(120_000);
+
+        let percentiles = histogram.percentiles();
+        assert_eq!(percentiles.count, 100);
+        assert_eq!(percentiles.p50_ms, Some(10));
+        assert_eq!(percentiles.p95_ms, Some(500));
+        assert_eq!(percentiles.p99_ms, Some(500));
+        assert_eq!(histogram.percentile(1.0), Some(60000));
+
+        let distribution = histogram.to_distribution();
+        assert_eq!(distribution.total, 100);
+        assert_eq!(distribution.buckets[0], ("<10ms".to_string(), 90));
+        assert_eq!(
+            distribution.buckets.last().unwrap(),
+            &(">=60000ms".to_string(), 1)
+        );
+
+        histogram.reset();
+        assert!(histogram.is_empty());
+    }
+
+    #[test]
+    fn test_stream_latency_histograms_by_model() {
+        let histograms = StreamLatencyHistograms::new();
+
+        let mut metrics = StreamMetrics::new();
+        metrics.ttfb_ms = Some(800);
+        metrics.chunk_gap_histogram.record(30);
+        metrics.chunk_gap_histogram.record(40);
+        histograms.record("claude", &metrics);
+        histograms.record("gpt", &StreamMetrics::new());
+
+        let snapshot = histograms.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].model, "claude");
+        assert_eq!(snapshot[0].ttfb.p50_ms, Some(1000));
+        assert_eq!(snapshot[0].chunk_gap.count, 2);
+        assert_eq!(snapshot[1].ttfb.count, 0);
+        assert!(snapshot[1].ttfb.p99_ms.is_none());
+
+        histograms.reset();
+        assert!(histograms.snapshot().is_empty());
+    }
 }
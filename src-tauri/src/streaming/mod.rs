@@ -11,8 +11,10 @@
 //! - `converter`: 流式格式转换器
 //! - `traits`: StreamingProvider trait 定义
 //! - `manager`: 流式管理器
+//! - `buffered`: 流式响应缓冲（内部流式、整体返回）
 
 pub mod aws_parser;
+pub mod buffered;
 pub mod converter;
 pub mod error;
 pub mod manager;
@@ -24,17 +26,24 @@ pub use aws_parser::{
     extract_content, extract_tool_calls, serialize_event, AwsEvent, AwsEventStreamParser,
     ParserState,
 };
+pub use buffered::{
+    is_truthy, parse_sse_block, SseEvent, StreamAccumulator, ACCUMULATE_STREAM_HEADER,
+    ACCUMULATE_STREAM_QUERY,
+};
 pub use converter::{
-    extract_content_from_sse, extract_tool_calls_from_sse, ConverterState, PartialJsonAccumulator,
-    StreamConverter, StreamFormat,
+    extract_content_from_sse, extract_tool_calls_from_sse, ConverterState, FinishedJson,
+    PartialJsonAccumulator, StreamConverter, StreamFormat,
 };
 pub use error::StreamError;
 pub use manager::{
-    collect_stream_content, create_flow_monitor_callback, with_timeout, FlowMonitorCallback,
-    ManagedStream, ManagedStreamWithCallback, StreamConfig, StreamContext, StreamEvent,
-    StreamManager, TimeoutStream,
+    collect_stream_content, create_flow_monitor_callback, with_keepalive, with_timeout,
+    FlowMonitorCallback, KeepaliveStream, ManagedStream, ManagedStreamWithCallback, StreamConfig,
+    StreamContext, StreamEvent, StreamManager, TimeoutStream, SSE_KEEPALIVE,
+};
+pub use metrics::{
+    stream_latency_histograms, LatencyHistogram, LatencyPercentiles, ModelStreamLatency,
+    StreamLatencyHistograms, StreamMetrics,
 };
-pub use metrics::StreamMetrics;
 pub use traits::{
     reqwest_stream_to_stream_response, StreamFormat as TraitsStreamFormat, StreamResponse,
     StreamingProvider,
@@ -1,17 +1,19 @@
 //! 监控与日志模块
 //!
-//! 提供请求日志记录、统计聚合和 Token 追踪功能
+//! 提供请求日志记录、统计聚合和 Token 追踪功能，并可将 Flow 以 OTLP Span 导出
 
 mod logger;
+mod otlp;
 mod stats;
 mod tokens;
 mod types;
 
 pub use logger::{LogRotationConfig, LoggerError, RequestLogger};
+pub use otlp::{build_export_request, flow_to_span, spawn_otlp_exporter, OtlpError, OtlpExporter};
 pub use stats::StatsAggregator;
 pub use tokens::{
-    ModelTokenStats, PeriodTokenStats, ProviderTokenStats, TokenSource, TokenStatsSummary,
-    TokenTracker, TokenUsageRecord,
+    ChatMessage, ModelTokenStats, PeriodTokenStats, ProviderTokenStats, TokenEstimator,
+    TokenSource, TokenStatsSummary, TokenTracker, TokenUsageRecord,
 };
 pub use types::{ModelStats, ProviderStats, RequestLog, RequestStatus, StatsSummary, TimeRange};
 
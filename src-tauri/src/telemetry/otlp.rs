@@ -0,0 +1,338 @@
+//! OpenTelemetry OTLP Span 导出
+//!
+//! 订阅 Flow 监控事件，把每个完成或失败的 Flow 转换为一个 OTLP Span，
+//! 以 OTLP/HTTP 的 JSON 编码 POST 到配置的采集器（如 OpenTelemetry Collector 的 `/v1/traces`）。
+//!
+//! - `traceId` 由客户端请求 ID 派生（没有请求 ID 时使用 Flow ID），同一请求 ID 的 Flow 落在同一条 Trace 中
+//! - `spanId` 由 Flow ID 派生
+//! - 属性使用 GenAI 语义约定（`gen_ai.request.model`、`gen_ai.usage.input_tokens` 等）
+//! - 状态：完成为 OK，失败或取消为 ERROR
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::config::OtlpConfig;
+use crate::flow_monitor::{FlowEvent, FlowMonitor, FlowState, LLMFlow};
+
+/// OTLP `SPAN_KIND_CLIENT`
+const SPAN_KIND_CLIENT: u8 = 3;
+/// OTLP `STATUS_CODE_UNSET`
+const STATUS_CODE_UNSET: u8 = 0;
+/// OTLP `STATUS_CODE_OK`
+const STATUS_CODE_OK: u8 = 1;
+/// OTLP `STATUS_CODE_ERROR`
+const STATUS_CODE_ERROR: u8 = 2;
+
+/// OTLP 导出错误
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum OtlpError {
+    /// 请求发送失败
+    #[error("OTLP 请求失败: {0}")]
+    Request(String),
+    /// 采集器返回非成功状态码
+    #[error("OTLP 采集器返回非成功状态码: {0}")]
+    Status(u16),
+}
+
+/// 把 Flow 转换为 OTLP/JSON 格式的 Span
+pub fn flow_to_span(flow: &LLMFlow) -> Value {
+    let request_id = flow.metadata.client_info.request_id.as_deref();
+    let start = flow.timestamps.request_start;
+    let end = flow.timestamps.response_end.unwrap_or_else(|| {
+        start + chrono::Duration::milliseconds(flow.timestamps.duration_ms as i64)
+    });
+
+    let mut attributes = vec![
+        string_attribute("proxycast.flow_id", &flow.id),
+        string_attribute("gen_ai.request.model", &flow.request.model),
+        string_attribute("gen_ai.system", &flow.metadata.provider.to_string()),
+        int_attribute("proxycast.duration_ms", flow.timestamps.duration_ms),
+    ];
+    if let Some(request_id) = request_id {
+        attributes.push(string_attribute("proxycast.request_id", request_id));
+    }
+    if let Some(response) = &flow.response {
+        attributes.push(int_attribute(
+            "gen_ai.usage.input_tokens",
+            response.usage.input_tokens as u64,
+        ));
+        attributes.push(int_attribute(
+            "gen_ai.usage.output_tokens",
+            response.usage.output_tokens as u64,
+        ));
+    }
+    if let Some(error) = &flow.error {
+        attributes.push(string_attribute(
+            "error.type",
+            &format!("{:?}", error.error_type),
+        ));
+    }
+
+    let status = match flow.state {
+        FlowState::Completed if flow.error.is_none() => json!({ "code": STATUS_CODE_OK }),
+        FlowState::Completed | FlowState::Failed => json!({
+            "code": STATUS_CODE_ERROR,
+            "message": flow.error.as_ref().map(|e| e.message.as_str()).unwrap_or_default(),
+        }),
+        FlowState::Cancelled => json!({ "code": STATUS_CODE_ERROR, "message": "cancelled" }),
+        FlowState::Pending | FlowState::Streaming => json!({ "code": STATUS_CODE_UNSET }),
+    };
+
+    json!({
+        "traceId": trace_id(request_id.unwrap_or(&flow.id)),
+        "spanId": span_id(&flow.id),
+        "name": format!("{} {}", flow.request.path, flow.request.model),
+        "kind": SPAN_KIND_CLIENT,
+        "startTimeUnixNano": unix_nanos(start),
+        "endTimeUnixNano": unix_nanos(end),
+        "attributes": attributes,
+        "status": status,
+    })
+}
+
+/// 构建 OTLP `ExportTraceServiceRequest` 请求体
+pub fn build_export_request(service_name: &str, spans: Vec<Value>) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [string_attribute("service.name", service_name)],
+            },
+            "scopeSpans": [{
+                "scope": {
+                    "name": "proxycast.flow_monitor",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+/// 由请求 ID 派生 32 位十六进制的 Trace ID
+///
+/// 请求 ID 本身就是合法的 W3C Trace ID 时直接沿用，便于与上游调用方的 Trace 关联。
+fn trace_id(request_id: &str) -> String {
+    let is_trace_id = request_id.len() == 32
+        && request_id.chars().all(|c| c.is_ascii_hexdigit())
+        && request_id.chars().any(|c| c != '0');
+    if is_trace_id {
+        return request_id.to_ascii_lowercase();
+    }
+    hex_digest(request_id, 16)
+}
+
+/// 由 Flow ID 派生 16 位十六进制的 Span ID
+fn span_id(flow_id: &str) -> String {
+    hex_digest(flow_id, 8)
+}
+
+fn hex_digest(input: &str, len: usize) -> String {
+    let digest = Sha256::digest(input.as_bytes());
+    digest[..len].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// OTLP/JSON 中 64 位整数以字符串表示
+fn unix_nanos(time: DateTime<Utc>) -> String {
+    time.timestamp_nanos_opt().unwrap_or_default().to_string()
+}
+
+fn string_attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+fn int_attribute(key: &str, value: u64) -> Value {
+    json!({ "key": key, "value": { "intValue": value.to_string() } })
+}
+
+/// OTLP Span 导出器
+#[derive(Debug, Clone)]
+pub struct OtlpExporter {
+    client: reqwest::Client,
+    config: OtlpConfig,
+}
+
+impl OtlpExporter {
+    /// 创建新的导出器
+    pub fn new(config: OtlpConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    /// 订阅 Flow 事件，在 Flow 完成或失败时导出 Span
+    ///
+    /// 返回后台任务句柄，事件通道关闭时任务结束。
+    pub fn spawn(self, monitor: &FlowMonitor) -> JoinHandle<()> {
+        let mut receiver = monitor.subscribe();
+        let memory_store = monitor.memory_store();
+
+        tokio::spawn(async move {
+            loop {
+                let flow_id = match receiver.recv().await {
+                    Ok(FlowEvent::FlowCompleted { id, .. } | FlowEvent::FlowFailed { id, .. }) => {
+                        id
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(n)) => {
+                        tracing::warn!("[OTLP] Flow 事件接收器落后 {} 条消息，对应 Span 未导出", n);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                let flow_lock = memory_store.read().await.get(&flow_id);
+                let Some(flow) = flow_lock.and_then(|lock| lock.read().ok().map(|f| f.clone()))
+                else {
+                    continue;
+                };
+
+                let exporter = self.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = exporter.export(&[flow]).await {
+                        tracing::warn!(
+                            "[OTLP] Flow {} 的 Span 导出到 {} 失败: {}",
+                            flow_id,
+                            exporter.config.endpoint,
+                            e
+                        );
+                    }
+                });
+            }
+        })
+    }
+
+    /// 把 Flow 作为 Span 发送到采集器
+    pub async fn export(&self, flows: &[LLMFlow]) -> Result<(), OtlpError> {
+        let spans = flows.iter().map(flow_to_span).collect();
+        let body = build_export_request(&self.config.service_name, spans);
+
+        let mut request = self
+            .client
+            .post(&self.config.endpoint)
+            .timeout(Duration::from_millis(self.config.timeout_ms))
+            .json(&body);
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| OtlpError::Request(e.to_string()))?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(OtlpError::Status(response.status().as_u16()))
+        }
+    }
+}
+
+/// 配置启用时启动 OTLP 导出任务
+pub fn spawn_otlp_exporter(config: &OtlpConfig, monitor: &FlowMonitor) -> Option<JoinHandle<()>> {
+    if !config.enable {
+        return None;
+    }
+    tracing::info!("[OTLP] Flow Span 导出到 {}", config.endpoint);
+    Some(OtlpExporter::new(config.clone()).spawn(monitor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::{FlowMetadata, FlowMonitorConfig, LLMRequest, LLMResponse};
+    use crate::ProviderType;
+    use std::sync::{Arc, Mutex};
+
+    fn create_test_request(model: &str) -> LLMRequest {
+        LLMRequest {
+            method: "POST".to_string(),
+            path: "/v1/chat/completions".to_string(),
+            model: model.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_trace_id_from_request_id() {
+        let w3c = "4BF92F3577B34DA6A3CE929D0E0E4736";
+        assert_eq!(trace_id(w3c), w3c.to_ascii_lowercase());
+
+        let derived = trace_id("req-123");
+        assert_eq!(derived.len(), 32);
+        assert_eq!(derived, trace_id("req-123"));
+        assert_eq!(span_id("flow-1").len(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_exports_completed_flow_to_collector() {
+        let received: Arc<Mutex<Vec<Value>>> = Arc::new(Mutex::new(Vec::new()));
+        let (notify_tx, mut notify_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let app = {
+            let received = received.clone();
+            axum::Router::new().route(
+                "/v1/traces",
+                axum::routing::post(move |axum::Json(body): axum::Json<Value>| async move {
+                    received.lock().unwrap().push(body);
+                    let _ = notify_tx.send(());
+                    axum::http::StatusCode::OK
+                }),
+            )
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let config = OtlpConfig {
+            enable: true,
+            endpoint: format!("http://{}/v1/traces", addr),
+            ..Default::default()
+        };
+        let monitor = Arc::new(FlowMonitor::new(FlowMonitorConfig::default(), None));
+        let handle = spawn_otlp_exporter(&config, &monitor).unwrap();
+
+        let metadata = FlowMetadata {
+            provider: ProviderType::OpenAI,
+            ..Default::default()
+        };
+        let flow_id = monitor
+            .start_flow(create_test_request("gpt-4o"), metadata)
+            .await
+            .unwrap();
+        monitor
+            .complete_flow(&flow_id, Some(LLMResponse::default()))
+            .await;
+
+        tokio::time::timeout(Duration::from_secs(5), notify_rx.recv())
+            .await
+            .expect("采集器未收到 Span");
+        handle.abort();
+
+        let body = received.lock().unwrap().remove(0);
+        let resource_spans = &body["resourceSpans"][0];
+        assert_eq!(
+            resource_spans["resource"]["attributes"][0]["value"]["stringValue"],
+            "proxycast"
+        );
+        let span = &resource_spans["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["spanId"], span_id(&flow_id));
+        assert_eq!(span["status"]["code"], STATUS_CODE_OK);
+
+        let model = span["attributes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|attr| attr["key"] == "gen_ai.request.model")
+            .map(|attr| attr["value"]["stringValue"].clone());
+        assert_eq!(model, Some(json!("gpt-4o")));
+    }
+}
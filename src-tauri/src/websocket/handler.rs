@@ -3,14 +3,15 @@
 //! 处理 WebSocket 连接和消息
 
 use super::{
-    WsApiRequest, WsApiResponse, WsConfig, WsConnectionManager, WsEndpoint, WsError, WsMessage,
+    MessageProcessor, WsApiRequest, WsApiResponse, WsConfig, WsConnectionManager, WsEndpoint,
+    WsError, WsMessage, WsSubprotocol,
 };
 use axum::{
     extract::{
         ws::{Message, WebSocket},
         State, WebSocketUpgrade,
     },
-    http::HeaderMap,
+    http::{header, HeaderMap},
     response::IntoResponse,
 };
 use futures::{SinkExt, StreamExt};
@@ -43,6 +44,24 @@ impl WsHandlerState {
     }
 }
 
+/// 协商 WebSocket 子协议
+///
+/// 按客户端在 `Sec-WebSocket-Protocol` 中给出的顺序选择第一个支持的子协议，
+/// 并在握手响应中回显；没有已知子协议时不回显，使用默认消息格式。
+pub fn negotiate_subprotocol(
+    ws: WebSocketUpgrade,
+    headers: &HeaderMap,
+) -> (WebSocketUpgrade, Option<WsSubprotocol>) {
+    let subprotocol = headers
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|v| v.to_str().ok())
+        .and_then(WsSubprotocol::negotiate);
+    match subprotocol {
+        Some(protocol) => (ws.protocols([protocol.as_str()]), subprotocol),
+        None => (ws, None),
+    }
+}
+
 /// WebSocket 升级处理器
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
@@ -81,15 +100,25 @@ pub async fn ws_handler(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
-    ws.on_upgrade(move |socket| handle_socket(socket, state, client_info))
+    let (ws, subprotocol) = negotiate_subprotocol(ws, &headers);
+    ws.on_upgrade(move |socket| handle_socket(socket, state, client_info, subprotocol))
 }
 
 /// 处理 WebSocket 连接
-async fn handle_socket(socket: WebSocket, state: WsHandlerState, client_info: Option<String>) {
+async fn handle_socket(
+    socket: WebSocket,
+    state: WsHandlerState,
+    client_info: Option<String>,
+    subprotocol: Option<WsSubprotocol>,
+) {
     let conn_id = uuid::Uuid::new_v4().to_string();
 
     // 注册连接
-    if let Err(e) = state.manager.register(conn_id.clone(), client_info.clone()) {
+    if let Err(e) =
+        state
+            .manager
+            .register_with_subprotocol(conn_id.clone(), client_info.clone(), subprotocol)
+    {
         state.logs.write().await.add(
             "error",
             &format!("[WS] Failed to register connection: {}", e.message),
@@ -135,7 +164,8 @@ async fn handle_socket(socket: WebSocket, state: WsHandlerState, client_info: Op
                         text.len(),
                         MAX_MESSAGE_SIZE
                     )));
-                    let error_text = serde_json::to_string(&error).unwrap_or_default();
+                    let error_text =
+                        MessageProcessor::serialize(&error, subprotocol).unwrap_or_default();
                     if sender.send(Message::Text(error_text.into())).await.is_err() {
                         break;
                     }
@@ -149,7 +179,8 @@ async fn handle_socket(socket: WebSocket, state: WsHandlerState, client_info: Op
                     Ok(ws_msg) => {
                         let response = handle_message(&state, &conn_id, ws_msg).await;
                         if let Some(resp) = response {
-                            let resp_text = serde_json::to_string(&resp).unwrap_or_default();
+                            let resp_text =
+                                MessageProcessor::serialize(&resp, subprotocol).unwrap_or_default();
                             if sender.send(Message::Text(resp_text)).await.is_err() {
                                 break;
                             }
@@ -161,7 +192,8 @@ async fn handle_socket(socket: WebSocket, state: WsHandlerState, client_info: Op
                             "Failed to parse message: {}",
                             e
                         )));
-                        let error_text = serde_json::to_string(&error).unwrap_or_default();
+                        let error_text =
+                            MessageProcessor::serialize(&error, subprotocol).unwrap_or_default();
                         if sender.send(Message::Text(error_text)).await.is_err() {
                             break;
                         }
@@ -173,7 +205,8 @@ async fn handle_socket(socket: WebSocket, state: WsHandlerState, client_info: Op
                 state.manager.on_error();
                 let error =
                     WsMessage::Error(WsError::invalid_message("Binary messages not supported"));
-                let error_text = serde_json::to_string(&error).unwrap_or_default();
+                let error_text =
+                    MessageProcessor::serialize(&error, subprotocol).unwrap_or_default();
                 if sender.send(Message::Text(error_text)).await.is_err() {
                     break;
                 }
@@ -221,6 +254,11 @@ async fn handle_message(
             None
         }
         WsMessage::Request(request) => {
+            // 请求速率超限时直接回复错误，不转发请求
+            if let Err(e) = state.manager.check_rate_limit(conn_id, &request.request_id) {
+                return Some(WsMessage::Error(e));
+            }
+
             state.logs.write().await.add(
                 "info",
                 &format!(
@@ -5,22 +5,28 @@
 //! - 消息解析和处理
 //! - 流式响应转发
 //! - 心跳检测和连接生命周期管理
+//! - 按连接的请求限流（令牌桶）
 
 mod handler;
 mod lifecycle;
 mod processor;
+mod rate_limit;
 mod stream;
 mod types;
 
-pub use handler::{parse_message, serialize_message, ws_handler, WsHandlerState};
+pub use handler::{
+    negotiate_subprotocol, parse_message, serialize_message, ws_handler, WsHandlerState,
+};
 pub use lifecycle::{
     ConnectionLifecycle, GracefulShutdown, HeartbeatManager, LifecycleState, ResourceCleaner,
 };
 pub use processor::MessageProcessor;
+pub use rate_limit::TokenBucket;
 pub use stream::{BackpressureController, StreamForwarder};
 pub use types::{
-    WsApiRequest, WsApiResponse, WsConfig, WsConnection, WsConnectionStatus, WsEndpoint, WsError,
-    WsErrorCode, WsFlowEvent, WsMessage, WsStats, WsStatsSnapshot, WsStreamChunk, WsStreamEnd,
+    BackpressureStrategy, WsApiRequest, WsApiResponse, WsConfig, WsConnection, WsConnectionStatus,
+    WsEndpoint, WsError, WsErrorCode, WsFlowEvent, WsMessage, WsStats, WsStatsSnapshot,
+    WsStreamChunk, WsStreamEnd, WsStreamKind, WsSubprotocol,
 };
 
 use dashmap::DashMap;
@@ -31,6 +37,8 @@ use std::sync::Arc;
 pub struct WsConnectionManager {
     /// 活跃连接映射
     connections: DashMap<String, WsConnection>,
+    /// 每个连接的请求令牌桶
+    rate_limiters: DashMap<String, TokenBucket>,
     /// 配置
     config: WsConfig,
     /// 统计信息
@@ -42,6 +50,7 @@ impl WsConnectionManager {
     pub fn new(config: WsConfig) -> Self {
         Self {
             connections: DashMap::new(),
+            rate_limiters: DashMap::new(),
             config,
             stats: Arc::new(WsStats::new()),
         }
@@ -54,6 +63,16 @@ impl WsConnectionManager {
 
     /// 注册新连接
     pub fn register(&self, id: String, client_info: Option<String>) -> Result<(), WsError> {
+        self.register_with_subprotocol(id, client_info, None)
+    }
+
+    /// 注册新连接并记录握手时协商的子协议
+    pub fn register_with_subprotocol(
+        &self,
+        id: String,
+        client_info: Option<String>,
+        subprotocol: Option<WsSubprotocol>,
+    ) -> Result<(), WsError> {
         // 检查连接数限制
         if self.connections.len() >= self.config.max_connections {
             return Err(WsError::internal(
@@ -65,7 +84,13 @@ impl WsConnectionManager {
             ));
         }
 
-        let conn = WsConnection::new(id.clone(), client_info);
+        let conn = WsConnection::new(id.clone(), client_info).with_subprotocol(subprotocol);
+        if self.config.max_requests_per_second > 0 {
+            self.rate_limiters.insert(
+                id.clone(),
+                TokenBucket::new(self.config.max_requests_per_second, self.config.burst),
+            );
+        }
         self.connections.insert(id, conn);
         self.stats.on_connect();
         Ok(())
@@ -73,6 +98,7 @@ impl WsConnectionManager {
 
     /// 注销连接
     pub fn unregister(&self, id: &str) -> Option<WsConnection> {
+        self.rate_limiters.remove(id);
         let removed = self.connections.remove(id).map(|(_, conn)| conn);
         if removed.is_some() {
             self.stats.on_disconnect();
@@ -92,6 +118,26 @@ impl WsConnectionManager {
         }
     }
 
+    /// 为连接的一个请求取令牌
+    ///
+    /// 令牌耗尽时返回 `RateLimited` 错误，调用方应直接回复该错误而不转发请求。
+    pub fn check_rate_limit(&self, id: &str, request_id: &str) -> Result<(), WsError> {
+        let Some(mut bucket) = self.rate_limiters.get_mut(id) else {
+            return Ok(());
+        };
+        if bucket.try_acquire() {
+            return Ok(());
+        }
+        self.stats.on_error();
+        Err(WsError::rate_limited(
+            Some(request_id.to_string()),
+            format!(
+                "Rate limit exceeded: {} requests/s (burst {})",
+                self.config.max_requests_per_second, self.config.burst
+            ),
+        ))
+    }
+
     /// 获取活跃连接数
     pub fn active_count(&self) -> usize {
         self.connections.len()
@@ -112,6 +158,19 @@ impl WsConnectionManager {
         &self.config
     }
 
+    /// 按配置的背压策略创建流式转发器
+    ///
+    /// 配置为 `DropOldest` 时 LLM Token 流会被拒绝（丢弃块会丢失内容）。
+    pub fn stream_forwarder(
+        &self,
+        request_id: String,
+        kind: WsStreamKind,
+    ) -> Result<StreamForwarder, WsError> {
+        Ok(StreamForwarder::new(request_id)
+            .with_backpressure(self.config.backpressure_strategy, kind)?
+            .with_stats(self.stats.clone()))
+    }
+
     /// 记录消息
     pub fn on_message(&self) {
         self.stats.on_message();
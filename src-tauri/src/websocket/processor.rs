@@ -3,11 +3,13 @@
 //! 解析 WebSocket 消息为 API 请求并复用现有请求处理逻辑
 
 use super::{
-    WsApiRequest, WsApiResponse, WsEndpoint, WsError, WsMessage, WsStreamChunk, WsStreamEnd,
+    serialize_message, WsApiRequest, WsApiResponse, WsEndpoint, WsError, WsErrorCode, WsMessage,
+    WsStreamChunk, WsStreamEnd, WsSubprotocol,
 };
 use crate::models::anthropic::AnthropicMessagesRequest;
 use crate::models::openai::ChatCompletionRequest;
-use serde_json::Value;
+use crate::streaming::buffered::parse_sse_block;
+use serde_json::{json, Value};
 
 /// 消息处理器
 pub struct MessageProcessor;
@@ -162,6 +164,99 @@ impl MessageProcessor {
             total_chunks,
         })
     }
+
+    /// 按协商的子协议序列化服务端消息
+    ///
+    /// 未协商子协议时使用默认的 `{"type": ...}` 信封。协商后响应和流式块直接发送
+    /// 响应体 / chunk 的 JSON（附加 `request_id` 关联请求），错误使用对应方言的
+    /// 错误结构；流结束、心跳和 Flow 事件仍使用默认信封。
+    pub fn serialize(
+        msg: &WsMessage,
+        subprotocol: Option<WsSubprotocol>,
+    ) -> Result<String, WsError> {
+        let dialect_value = subprotocol.and_then(|dialect| match msg {
+            WsMessage::Response(response) => Some(with_request_id(
+                response.payload.clone(),
+                &response.request_id,
+            )),
+            WsMessage::StreamChunk(chunk) => Some(with_request_id(
+                stream_chunk_json(&chunk.data),
+                &chunk.request_id,
+            )),
+            WsMessage::Error(error) => Some(dialect_error(error, dialect)),
+            _ => None,
+        });
+
+        match dialect_value {
+            Some(value) => Ok(value.to_string()),
+            None => serialize_message(msg),
+        }
+    }
+}
+
+/// 在 JSON 对象上附加 `request_id`，非对象的值包装在 `data` 字段中
+fn with_request_id(value: Value, request_id: &str) -> Value {
+    match value {
+        Value::Object(mut object) => {
+            object.insert("request_id".to_string(), json!(request_id));
+            Value::Object(object)
+        }
+        other => json!({ "request_id": request_id, "data": other }),
+    }
+}
+
+/// 解析流式块中的 JSON（SSE `data:` 内容或裸 JSON），无法解析时保留原始文本
+fn stream_chunk_json(data: &str) -> Value {
+    let payload = parse_sse_block(data)
+        .map(|event| event.data)
+        .unwrap_or_else(|| data.trim().to_string());
+    serde_json::from_str(&payload).unwrap_or(Value::String(payload))
+}
+
+/// 按方言构建错误消息
+fn dialect_error(error: &WsError, dialect: WsSubprotocol) -> Value {
+    let code = serde_json::to_value(error.code).unwrap_or(Value::Null);
+    match dialect {
+        WsSubprotocol::OpenAiV1 => {
+            let error_type = match error.code {
+                WsErrorCode::InvalidMessage | WsErrorCode::InvalidRequest => {
+                    "invalid_request_error"
+                }
+                WsErrorCode::Unauthorized => "authentication_error",
+                WsErrorCode::InternalError => "server_error",
+                WsErrorCode::UpstreamError => "upstream_error",
+                WsErrorCode::Timeout => "timeout",
+                WsErrorCode::RateLimited => "rate_limit_error",
+            };
+            json!({
+                "request_id": error.request_id,
+                "error": {
+                    "message": error.message,
+                    "type": error_type,
+                    "code": code
+                }
+            })
+        }
+        WsSubprotocol::AnthropicV1 => {
+            let error_type = match error.code {
+                WsErrorCode::InvalidMessage | WsErrorCode::InvalidRequest => {
+                    "invalid_request_error"
+                }
+                WsErrorCode::Unauthorized => "authentication_error",
+                WsErrorCode::InternalError | WsErrorCode::UpstreamError => "api_error",
+                WsErrorCode::Timeout => "timeout_error",
+                WsErrorCode::RateLimited => "rate_limit_error",
+            };
+            json!({
+                "type": "error",
+                "request_id": error.request_id,
+                "error": {
+                    "type": error_type,
+                    "message": error.message
+                }
+            })
+        }
+    }
 }
 
 #[cfg(test)]
@@ -308,6 +403,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_serialize_in_negotiated_dialect() {
+        let response =
+            MessageProcessor::create_response("req-1", serde_json::json!({"object": "list"}));
+        let chunk = MessageProcessor::create_stream_chunk(
+            "req-1",
+            0,
+            "data: {\"type\":\"content_block_delta\",\"index\":0}",
+        );
+        let error = WsMessage::Error(WsError::upstream(Some("req-1".to_string()), "boom"));
+        let to_json = |msg: &WsMessage, subprotocol| -> Value {
+            serde_json::from_str(&MessageProcessor::serialize(msg, subprotocol).unwrap()).unwrap()
+        };
+
+        // 未协商时保持默认信封
+        let default = to_json(&response, None);
+        assert_eq!(default["type"], "response");
+        assert_eq!(default["payload"]["object"], "list");
+
+        let openai = Some(WsSubprotocol::OpenAiV1);
+        assert_eq!(
+            to_json(&response, openai),
+            serde_json::json!({"object": "list", "request_id": "req-1"})
+        );
+        assert_eq!(to_json(&chunk, openai)["type"], "content_block_delta");
+        assert_eq!(to_json(&chunk, openai)["request_id"], "req-1");
+        let openai_error = to_json(&error, openai);
+        assert_eq!(openai_error["error"]["type"], "upstream_error");
+        assert_eq!(openai_error["error"]["code"], "upstream_error");
+
+        let anthropic_error = to_json(&error, Some(WsSubprotocol::AnthropicV1));
+        assert_eq!(anthropic_error["type"], "error");
+        assert_eq!(anthropic_error["error"]["type"], "api_error");
+        assert_eq!(anthropic_error["request_id"], "req-1");
+
+        // 流结束不属于模型响应，仍使用默认信封
+        let end = MessageProcessor::create_stream_end("req-1", 3);
+        assert_eq!(to_json(&end, openai)["type"], "stream_end");
+    }
+
     #[test]
     fn test_parse_chat_completions() {
         let payload = serde_json::json!({
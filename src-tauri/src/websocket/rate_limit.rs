@@ -0,0 +1,79 @@
+//! WebSocket 连接级请求限流
+//!
+//! 每个连接一个令牌桶：桶容量为突发请求数，令牌按经过的时间连续补充。
+
+use std::time::Instant;
+
+/// 令牌桶
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    /// 桶容量（允许的突发请求数）
+    capacity: f64,
+    /// 当前令牌数
+    tokens: f64,
+    /// 每秒补充的令牌数
+    refill_per_sec: f64,
+    /// 上次补充时间
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// 创建装满令牌的桶
+    ///
+    /// `burst` 为 0 时按 1 处理，保证补充的令牌可以被使用。
+    pub fn new(refill_per_sec: u32, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: f64::from(refill_per_sec),
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// 尝试取出一个令牌
+    pub fn try_acquire(&mut self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    /// 按指定时间补充令牌后尝试取出一个令牌
+    pub fn try_acquire_at(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.refill_per_sec).min(self.capacity);
+        self.last_refill = self.last_refill.max(now);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_token_bucket_refills_by_elapsed_time() {
+        let mut bucket = TokenBucket::new(2, 3);
+        let start = bucket.last_refill;
+
+        // 突发容量用完后拒绝
+        assert!((0..3).all(|_| bucket.try_acquire_at(start)));
+        assert!(!bucket.try_acquire_at(start));
+
+        // 每秒 2 个令牌：250ms 后不足一个，500ms 后补充一个
+        assert!(!bucket.try_acquire_at(start + Duration::from_millis(250)));
+        assert!(bucket.try_acquire_at(start + Duration::from_millis(500)));
+        assert!(!bucket.try_acquire_at(start + Duration::from_millis(500)));
+
+        // 长时间空闲后最多补满到突发容量
+        let later = start + Duration::from_secs(60);
+        assert!((0..3).all(|_| bucket.try_acquire_at(later)));
+        assert!(!bucket.try_acquire_at(later));
+    }
+}
@@ -1,10 +1,17 @@
 //! WebSocket 流式响应处理
 //!
 //! 将 SSE 流转换为 WebSocket 消息，实现背压控制
+//!
+//! 客户端消费过慢（通道已满）时按 `BackpressureStrategy` 处理：
+//! - `Block`: 暂停读取上游，等待客户端消费
+//! - `DropOldest`: 丢弃最早的未发送块，只允许用于非 LLM 事件流
+//! - `Disconnect`: 返回错误，由调用方断开客户端
 
-use super::{MessageProcessor, WsError, WsMessage};
+use super::{BackpressureStrategy, MessageProcessor, WsError, WsMessage, WsStats, WsStreamKind};
 use futures::{Stream, StreamExt};
-use tokio::sync::mpsc;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, error::TrySendError};
 
 /// 流式响应转发器
 pub struct StreamForwarder {
@@ -12,6 +19,12 @@ pub struct StreamForwarder {
     request_id: String,
     /// 背压缓冲区大小
     buffer_size: usize,
+    /// 背压策略
+    strategy: BackpressureStrategy,
+    /// 流类型
+    kind: WsStreamKind,
+    /// 统计信息（记录背压事件和丢弃的块）
+    stats: Option<Arc<WsStats>>,
 }
 
 impl StreamForwarder {
@@ -20,6 +33,9 @@ impl StreamForwarder {
         Self {
             request_id,
             buffer_size: 32, // 默认缓冲区大小
+            strategy: BackpressureStrategy::default(),
+            kind: WsStreamKind::default(),
+            stats: None,
         }
     }
 
@@ -29,6 +45,44 @@ impl StreamForwarder {
         self
     }
 
+    /// 设置背压策略和流类型
+    ///
+    /// `DropOldest` 会丢失内容，对 LLM Token 流返回错误。
+    pub fn with_backpressure(
+        mut self,
+        strategy: BackpressureStrategy,
+        kind: WsStreamKind,
+    ) -> Result<Self, WsError> {
+        if !strategy.allowed_for(kind) {
+            return Err(WsError::invalid_request(
+                Some(self.request_id),
+                format!(
+                    "Backpressure strategy {:?} is not allowed for {:?} streams",
+                    strategy, kind
+                ),
+            ));
+        }
+        self.strategy = strategy;
+        self.kind = kind;
+        Ok(self)
+    }
+
+    /// 设置统计信息
+    pub fn with_stats(mut self, stats: Arc<WsStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// 获取背压策略
+    pub fn strategy(&self) -> BackpressureStrategy {
+        self.strategy
+    }
+
+    /// 获取流类型
+    pub fn kind(&self) -> WsStreamKind {
+        self.kind
+    }
+
     /// 将 SSE 数据行转换为 WebSocket 消息
     ///
     /// SSE 格式: "data: {...}\n\n"
@@ -103,6 +157,8 @@ impl StreamForwarder {
     {
         let mut index = 0u32;
         let mut buffer = String::new();
+        // DropOldest 策略下等待发送的块
+        let mut pending: VecDeque<WsMessage> = VecDeque::new();
 
         while let Some(chunk_result) = stream.next().await {
             match chunk_result {
@@ -115,13 +171,8 @@ impl StreamForwarder {
                         buffer = buffer[pos + 1..].to_string();
 
                         if let Some(msg) = self.convert_sse_line(&line, index) {
-                            // 发送消息，如果通道满则等待（背压）
-                            if sender.send(msg).await.is_err() {
-                                return Err(WsError::internal(
-                                    Some(self.request_id.clone()),
-                                    "Channel closed",
-                                ));
-                            }
+                            // 按背压策略发送消息
+                            self.deliver(&sender, &mut pending, msg).await?;
                             index += 1;
                         }
                     }
@@ -138,17 +189,111 @@ impl StreamForwarder {
         // 处理缓冲区中剩余的数据
         if !buffer.is_empty() {
             if let Some(msg) = self.convert_sse_line(&buffer, index) {
-                let _ = sender.send(msg).await;
+                self.deliver(&sender, &mut pending, msg).await?;
                 index += 1;
             }
         }
 
+        // 发送积压的块，结束消息不会被丢弃
+        for msg in pending {
+            let _ = sender.send(msg).await;
+        }
+
         // 发送结束消息
         let end_msg = MessageProcessor::create_stream_end(&self.request_id, index);
         let _ = sender.send(end_msg).await;
 
         Ok(index)
     }
+
+    /// 异步转发已构造好的消息流
+    ///
+    /// 用于非 SSE 来源的推送（如 Flow 事件），不做格式转换，也不追加结束消息。
+    /// 返回成功进入转发流程的消息数（`DropOldest` 下可能包含被丢弃的消息）。
+    pub async fn forward_messages<S>(
+        &self,
+        mut stream: S,
+        sender: mpsc::Sender<WsMessage>,
+    ) -> Result<u32, WsError>
+    where
+        S: Stream<Item = WsMessage> + Unpin,
+    {
+        let mut count = 0u32;
+        let mut pending: VecDeque<WsMessage> = VecDeque::new();
+
+        while let Some(msg) = stream.next().await {
+            self.deliver(&sender, &mut pending, msg).await?;
+            count += 1;
+        }
+
+        for msg in pending {
+            sender.send(msg).await.map_err(|_| self.channel_closed())?;
+        }
+
+        Ok(count)
+    }
+
+    /// 按背压策略发送消息
+    async fn deliver(
+        &self,
+        sender: &mpsc::Sender<WsMessage>,
+        pending: &mut VecDeque<WsMessage>,
+        msg: WsMessage,
+    ) -> Result<(), WsError> {
+        match self.strategy {
+            BackpressureStrategy::Block => match sender.try_send(msg) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(msg)) => {
+                    self.record(WsStats::on_backpressure);
+                    sender.send(msg).await.map_err(|_| self.channel_closed())
+                }
+                Err(TrySendError::Closed(_)) => Err(self.channel_closed()),
+            },
+            BackpressureStrategy::Disconnect => match sender.try_send(msg) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(_)) => {
+                    self.record(WsStats::on_backpressure);
+                    Err(WsError::internal(
+                        Some(self.request_id.clone()),
+                        "Client is too slow, disconnecting",
+                    ))
+                }
+                Err(TrySendError::Closed(_)) => Err(self.channel_closed()),
+            },
+            BackpressureStrategy::DropOldest => {
+                // 按顺序发送积压的块，通道满时保留最新的 buffer_size 个
+                pending.push_back(msg);
+                while let Some(front) = pending.pop_front() {
+                    match sender.try_send(front) {
+                        Ok(()) => {}
+                        Err(TrySendError::Full(front)) => {
+                            pending.push_front(front);
+                            break;
+                        }
+                        Err(TrySendError::Closed(_)) => return Err(self.channel_closed()),
+                    }
+                }
+                if !pending.is_empty() {
+                    self.record(WsStats::on_backpressure);
+                }
+                while pending.len() > self.buffer_size.max(1) {
+                    pending.pop_front();
+                    self.record(WsStats::on_dropped_chunk);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn record(&self, f: fn(&WsStats)) {
+        if let Some(stats) = &self.stats {
+            f(stats);
+        }
+    }
+
+    fn channel_closed(&self) -> WsError {
+        WsError::internal(Some(self.request_id.clone()), "Channel closed")
+    }
 }
 
 /// 背压控制器
@@ -308,6 +453,71 @@ mod tests {
         assert_eq!(forwarder.buffer_size, 64);
     }
 
+    #[test]
+    fn test_drop_oldest_rejected_for_llm_stream() {
+        let result = StreamForwarder::new("req-1".to_string())
+            .with_backpressure(BackpressureStrategy::DropOldest, WsStreamKind::LlmTokens);
+        assert!(result.is_err());
+
+        let forwarder = StreamForwarder::new("req-1".to_string())
+            .with_backpressure(BackpressureStrategy::DropOldest, WsStreamKind::Events)
+            .unwrap();
+        assert_eq!(forwarder.strategy(), BackpressureStrategy::DropOldest);
+    }
+
+    fn sse_stream(count: usize) -> impl Stream<Item = Result<String, String>> + Unpin {
+        futures::stream::iter((0..count).map(|i| Ok(format!("data: {{\"n\": {}}}\n\n", i))))
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_strategy_on_full_channel() {
+        let stats = Arc::new(WsStats::new());
+        let forwarder = StreamForwarder::new("req-1".to_string())
+            .with_buffer_size(1)
+            .with_backpressure(BackpressureStrategy::Disconnect, WsStreamKind::LlmTokens)
+            .unwrap()
+            .with_stats(stats.clone());
+        let (tx, _rx) = forwarder.create_channel();
+
+        let result = forwarder.forward_string_stream(sse_stream(3), tx).await;
+        assert!(result.is_err());
+        assert_eq!(stats.snapshot().backpressure_events, 1);
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest_strategy_keeps_latest_chunks() {
+        let stats = Arc::new(WsStats::new());
+        let forwarder = StreamForwarder::new("req-1".to_string())
+            .with_buffer_size(1)
+            .with_backpressure(BackpressureStrategy::DropOldest, WsStreamKind::Events)
+            .unwrap()
+            .with_stats(stats.clone());
+        let (tx, mut rx) = forwarder.create_channel();
+
+        // 单线程运行时下转发任务在接收端让出时才执行，通道满后后续块全部积压
+        let task =
+            tokio::spawn(async move { forwarder.forward_string_stream(sse_stream(5), tx).await });
+        let mut received = Vec::new();
+        while let Some(msg) = rx.recv().await {
+            received.push(msg);
+        }
+        assert_eq!(task.await.unwrap().unwrap(), 5);
+
+        let indices: Vec<_> = received
+            .iter()
+            .map(|m| match m {
+                WsMessage::StreamChunk(c) => c.index,
+                WsMessage::StreamEnd(e) => e.total_chunks,
+                _ => panic!("Expected stream message"),
+            })
+            .collect();
+        // 块 0 进入通道，块 1-3 被丢弃，块 4 和结束消息最终送达
+        assert_eq!(indices, vec![0, 4, 5]);
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.dropped_chunks, 3);
+        assert_eq!(snapshot.backpressure_events, 4);
+    }
+
     #[test]
     fn test_create_channel() {
         let forwarder = StreamForwarder::new("req-1".to_string()).with_buffer_size(16);
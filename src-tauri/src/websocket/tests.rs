@@ -92,6 +92,8 @@ fn test_ws_config_defaults() {
     assert_eq!(config.heartbeat_timeout_secs, 60);
     assert_eq!(config.max_connections, 100);
     assert_eq!(config.max_message_size, 16 * 1024 * 1024);
+    assert_eq!(config.max_requests_per_second, 20);
+    assert_eq!(config.burst, 40);
 }
 
 #[test]
@@ -171,6 +173,126 @@ fn test_ws_connection_manager_max_connections() {
     assert!(result.is_err());
 }
 
+#[test]
+fn test_ws_connection_manager_stream_forwarder_strategy() {
+    let manager = WsConnectionManager::new(WsConfig {
+        backpressure_strategy: BackpressureStrategy::DropOldest,
+        ..Default::default()
+    });
+
+    // DropOldest 会丢失内容，不允许用于 LLM Token 流
+    assert!(manager
+        .stream_forwarder("req-1".to_string(), WsStreamKind::LlmTokens)
+        .is_err());
+    let forwarder = manager
+        .stream_forwarder("req-2".to_string(), WsStreamKind::Events)
+        .unwrap();
+    assert_eq!(forwarder.strategy(), BackpressureStrategy::DropOldest);
+    assert_eq!(forwarder.kind(), WsStreamKind::Events);
+}
+
+fn ping_stream(count: i64) -> impl futures::Stream<Item = WsMessage> + Unpin {
+    futures::stream::iter((0..count).map(|timestamp| WsMessage::Ping { timestamp }))
+}
+
+fn ping_timestamps(messages: &[WsMessage]) -> Vec<i64> {
+    messages
+        .iter()
+        .map(|m| match m {
+            WsMessage::Ping { timestamp } => *timestamp,
+            _ => panic!("Expected Ping"),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_ws_connection_manager_event_stream_blocks_when_full() {
+    let manager = WsConnectionManager::new(WsConfig::default());
+    let forwarder = manager
+        .stream_forwarder("flow-events".to_string(), WsStreamKind::Events)
+        .unwrap()
+        .with_buffer_size(1);
+    let (tx, mut rx) = forwarder.create_channel();
+
+    let task = tokio::spawn(async move { forwarder.forward_messages(ping_stream(3), tx).await });
+    for _ in 0..5 {
+        tokio::task::yield_now().await;
+    }
+
+    // 缓冲区已满，转发端等待客户端消费而不是丢弃
+    assert!(!task.is_finished());
+    assert_eq!(manager.stats().snapshot().backpressure_events, 1);
+
+    let mut received = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        received.push(msg);
+    }
+    assert_eq!(task.await.unwrap().unwrap(), 3);
+    assert_eq!(ping_timestamps(&received), vec![0, 1, 2]);
+    assert_eq!(manager.stats().snapshot().dropped_chunks, 0);
+}
+
+#[tokio::test]
+async fn test_ws_connection_manager_event_stream_drops_oldest_when_full() {
+    let manager = WsConnectionManager::new(WsConfig {
+        backpressure_strategy: BackpressureStrategy::DropOldest,
+        ..Default::default()
+    });
+    let forwarder = manager
+        .stream_forwarder("flow-events".to_string(), WsStreamKind::Events)
+        .unwrap()
+        .with_buffer_size(1);
+    let (tx, mut rx) = forwarder.create_channel();
+
+    // 单线程运行时下转发任务在接收端让出时才执行，通道满后后续消息全部积压
+    let task = tokio::spawn(async move { forwarder.forward_messages(ping_stream(5), tx).await });
+    let mut received = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        received.push(msg);
+    }
+    assert_eq!(task.await.unwrap().unwrap(), 5);
+
+    // 消息 0 进入通道，1-3 被丢弃，4 最终送达
+    assert_eq!(ping_timestamps(&received), vec![0, 4]);
+    assert_eq!(manager.stats().snapshot().dropped_chunks, 3);
+}
+
+#[test]
+fn test_ws_connection_manager_rate_limit() {
+    const BURST: usize = 5;
+    const OVERFLOW: usize = 3;
+    let manager = WsConnectionManager::new(WsConfig {
+        max_requests_per_second: 1,
+        burst: BURST as u32,
+        ..Default::default()
+    });
+    manager.register("conn-1".to_string(), None).unwrap();
+    manager.register("conn-2".to_string(), None).unwrap();
+
+    // 瞬间发出 burst + N 个请求，超出突发容量的请求被限流
+    let results: Vec<_> = (0..BURST + OVERFLOW)
+        .map(|i| manager.check_rate_limit("conn-1", &format!("req-{}", i)))
+        .collect();
+    assert!(results[..BURST].iter().all(Result::is_ok));
+    for (i, result) in results[BURST..].iter().enumerate() {
+        let err = result.as_ref().unwrap_err();
+        assert_eq!(err.code, WsErrorCode::RateLimited);
+        assert_eq!(err.request_id, Some(format!("req-{}", BURST + i)));
+    }
+
+    // 限流按连接独立计算
+    assert!(manager.check_rate_limit("conn-2", "req-0").is_ok());
+
+    // max_requests_per_second 为 0 时不限流
+    let unlimited = WsConnectionManager::new(WsConfig {
+        max_requests_per_second: 0,
+        burst: 1,
+        ..Default::default()
+    });
+    unlimited.register("conn-1".to_string(), None).unwrap();
+    assert!((0..100).all(|_| unlimited.check_rate_limit("conn-1", "req").is_ok()));
+}
+
 #[test]
 fn test_ws_connection_manager_list_connections() {
     let manager = WsConnectionManager::with_defaults();
@@ -247,6 +369,113 @@ fn test_ws_stream_chunk_serialization() {
     assert_eq!(parsed.index, 5);
 }
 
+#[test]
+fn test_ws_subprotocol_negotiate() {
+    // 按客户端给出的顺序选择第一个支持的子协议
+    assert_eq!(
+        WsSubprotocol::negotiate("graphql-ws, proxycast.anthropic.v1, proxycast.openai.v1"),
+        Some(WsSubprotocol::AnthropicV1)
+    );
+    assert_eq!(
+        WsSubprotocol::negotiate("proxycast.openai.v1"),
+        Some(WsSubprotocol::OpenAiV1)
+    );
+    assert_eq!(WsSubprotocol::negotiate("graphql-ws"), None);
+    assert_eq!(WsSubprotocol::negotiate("PROXYCAST.OPENAI.V1"), None);
+}
+
+#[test]
+fn test_ws_connection_manager_register_with_subprotocol() {
+    let manager = WsConnectionManager::with_defaults();
+
+    manager
+        .register_with_subprotocol("conn-1".to_string(), None, Some(WsSubprotocol::OpenAiV1))
+        .unwrap();
+    manager.register("conn-2".to_string(), None).unwrap();
+
+    assert_eq!(
+        manager.get("conn-1").unwrap().subprotocol,
+        Some(WsSubprotocol::OpenAiV1)
+    );
+    assert_eq!(manager.get("conn-2").unwrap().subprotocol, None);
+}
+
+/// 向 `ws_handler` 发起握手，返回状态码和响应中的 `Sec-WebSocket-Protocol`
+async fn handshake(offered: Option<&str>) -> (u16, Option<String>) {
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let state = WsHandlerState::new(
+        WsConfig::default(),
+        "test-key".to_string(),
+        Arc::new(tokio::sync::RwLock::new(crate::logger::LogStore::new())),
+    );
+    let app = axum::Router::new()
+        .route("/ws", axum::routing::get(ws_handler))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let mut request = format!(
+        "GET /ws HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+         Authorization: Bearer test-key\r\n",
+        addr
+    );
+    if let Some(offered) = offered {
+        request.push_str(&format!("Sec-WebSocket-Protocol: {}\r\n", offered));
+    }
+    request.push_str("\r\n");
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !response.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+    server.abort();
+
+    let response = String::from_utf8_lossy(&response);
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_default();
+    let protocol = response.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.eq_ignore_ascii_case("sec-websocket-protocol")
+            .then(|| value.trim().to_string())
+    });
+    (status, protocol)
+}
+
+#[tokio::test]
+async fn test_ws_handler_echoes_negotiated_subprotocol() {
+    assert_eq!(
+        handshake(Some(
+            "graphql-ws, proxycast.anthropic.v1, proxycast.openai.v1"
+        ))
+        .await,
+        (101, Some("proxycast.anthropic.v1".to_string()))
+    );
+    assert_eq!(
+        handshake(Some("proxycast.openai.v1")).await,
+        (101, Some("proxycast.openai.v1".to_string()))
+    );
+
+    // 没有已知子协议时照常升级，但不回显子协议
+    assert_eq!(handshake(Some("graphql-ws")).await, (101, None));
+    assert_eq!(handshake(None).await, (101, None));
+}
+
 // ============ Property-Based Tests ============
 
 use proptest::prelude::*;
@@ -269,6 +498,7 @@ fn arb_error_code() -> impl Strategy<Value = WsErrorCode> {
         Just(WsErrorCode::InternalError),
         Just(WsErrorCode::UpstreamError),
         Just(WsErrorCode::Timeout),
+        Just(WsErrorCode::RateLimited),
     ]
 }
 
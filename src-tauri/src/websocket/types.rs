@@ -10,6 +10,7 @@ use crate::flow_monitor::models::FlowError;
 use crate::flow_monitor::monitor::{
     FlowEvent, FlowSummary, FlowUpdate, NotificationEvent, ThresholdCheckResult,
 };
+use crate::flow_monitor::slo::SloStatus;
 
 /// WebSocket 连接信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +25,9 @@ pub struct WsConnection {
     pub request_count: u64,
     /// 连接状态
     pub status: WsConnectionStatus,
+    /// 握手时协商的子协议（未协商时使用默认消息格式）
+    #[serde(default)]
+    pub subprotocol: Option<WsSubprotocol>,
 }
 
 impl WsConnection {
@@ -35,9 +39,16 @@ impl WsConnection {
             client_info,
             request_count: 0,
             status: WsConnectionStatus::Connected,
+            subprotocol: None,
         }
     }
 
+    /// 设置协商的子协议
+    pub fn with_subprotocol(mut self, subprotocol: Option<WsSubprotocol>) -> Self {
+        self.subprotocol = subprotocol;
+        self
+    }
+
     /// 增加请求计数
     pub fn increment_request_count(&mut self) {
         self.request_count += 1;
@@ -56,6 +67,45 @@ pub enum WsConnectionStatus {
     Closed,
 }
 
+/// WebSocket 子协议（`Sec-WebSocket-Protocol`）
+///
+/// 决定服务端响应、流式块和错误消息使用的 JSON 方言。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WsSubprotocol {
+    /// OpenAI 风格
+    #[serde(rename = "proxycast.openai.v1")]
+    OpenAiV1,
+    /// Anthropic 风格
+    #[serde(rename = "proxycast.anthropic.v1")]
+    AnthropicV1,
+}
+
+impl WsSubprotocol {
+    /// 子协议名称
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WsSubprotocol::OpenAiV1 => "proxycast.openai.v1",
+            WsSubprotocol::AnthropicV1 => "proxycast.anthropic.v1",
+        }
+    }
+
+    /// 按名称解析子协议（大小写敏感）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "proxycast.openai.v1" => Some(WsSubprotocol::OpenAiV1),
+            "proxycast.anthropic.v1" => Some(WsSubprotocol::AnthropicV1),
+            _ => None,
+        }
+    }
+
+    /// 从 `Sec-WebSocket-Protocol` 请求头协商子协议
+    ///
+    /// 按客户端给出的顺序选择第一个支持的子协议，都不支持时返回 `None`。
+    pub fn negotiate(offered: &str) -> Option<Self> {
+        offered.split(',').map(str::trim).find_map(Self::from_name)
+    }
+}
+
 /// WebSocket 消息类型
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -161,6 +211,8 @@ pub enum WsErrorCode {
     UpstreamError,
     /// 请求超时
     Timeout,
+    /// 连接请求速率超限
+    RateLimited,
 }
 
 impl WsError {
@@ -208,6 +260,15 @@ impl WsError {
             message: message.into(),
         }
     }
+
+    /// 创建限流错误
+    pub fn rate_limited(request_id: Option<String>, message: impl Into<String>) -> Self {
+        Self {
+            request_id,
+            code: WsErrorCode::RateLimited,
+            message: message.into(),
+        }
+    }
 }
 
 /// WebSocket 配置
@@ -228,6 +289,46 @@ pub struct WsConfig {
     /// 消息大小限制（字节）
     #[serde(default = "default_max_message_size")]
     pub max_message_size: usize,
+    /// 客户端消费过慢时的背压策略
+    #[serde(default)]
+    pub backpressure_strategy: BackpressureStrategy,
+    /// 单个连接每秒允许的请求数（0 表示不限流）
+    #[serde(default = "default_max_requests_per_second")]
+    pub max_requests_per_second: u32,
+    /// 单个连接允许的突发请求数（令牌桶容量）
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+}
+
+/// 背压策略（客户端消费速度跟不上上游时的处理方式）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackpressureStrategy {
+    /// 暂停读取上游，等待客户端消费
+    #[default]
+    Block,
+    /// 丢弃最早的未发送块（会丢失内容，仅允许用于非 LLM 事件流）
+    DropOldest,
+    /// 断开客户端连接
+    Disconnect,
+}
+
+/// 流的类型，决定允许使用的背压策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WsStreamKind {
+    /// LLM Token 流（每个块都不可丢失）
+    #[default]
+    LlmTokens,
+    /// 非 LLM 事件流（如监控事件推送，允许丢弃中间块）
+    Events,
+}
+
+impl BackpressureStrategy {
+    /// 检查策略是否适用于指定类型的流
+    pub fn allowed_for(self, kind: WsStreamKind) -> bool {
+        !(self == BackpressureStrategy::DropOldest && kind == WsStreamKind::LlmTokens)
+    }
 }
 
 fn default_enabled() -> bool {
@@ -250,6 +351,14 @@ fn default_max_message_size() -> usize {
     16 * 1024 * 1024 // 16MB
 }
 
+fn default_max_requests_per_second() -> u32 {
+    20
+}
+
+fn default_burst() -> u32 {
+    40
+}
+
 impl Default for WsConfig {
     fn default() -> Self {
         Self {
@@ -258,6 +367,9 @@ impl Default for WsConfig {
             heartbeat_timeout_secs: default_heartbeat_timeout(),
             max_connections: default_max_connections(),
             max_message_size: default_max_message_size(),
+            backpressure_strategy: BackpressureStrategy::default(),
+            max_requests_per_second: default_max_requests_per_second(),
+            burst: default_burst(),
         }
     }
 }
@@ -273,6 +385,10 @@ pub struct WsStats {
     pub total_messages: AtomicU64,
     /// 总错误数
     pub total_errors: AtomicU64,
+    /// 背压丢弃的块数
+    pub dropped_chunks: AtomicU64,
+    /// 背压事件数（发送时缓冲区已满）
+    pub backpressure_events: AtomicU64,
 }
 
 impl WsStats {
@@ -302,6 +418,16 @@ impl WsStats {
         self.total_errors.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// 记录背压事件
+    pub fn on_backpressure(&self) {
+        self.backpressure_events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录丢弃的块
+    pub fn on_dropped_chunk(&self) {
+        self.dropped_chunks.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// 获取活跃连接数
     pub fn active_count(&self) -> u64 {
         self.active_connections.load(Ordering::Relaxed)
@@ -314,6 +440,8 @@ impl WsStats {
             active_connections: self.active_connections.load(Ordering::Relaxed),
             total_messages: self.total_messages.load(Ordering::Relaxed),
             total_errors: self.total_errors.load(Ordering::Relaxed),
+            dropped_chunks: self.dropped_chunks.load(Ordering::Relaxed),
+            backpressure_events: self.backpressure_events.load(Ordering::Relaxed),
         }
     }
 }
@@ -325,6 +453,10 @@ pub struct WsStatsSnapshot {
     pub active_connections: u64,
     pub total_messages: u64,
     pub total_errors: u64,
+    #[serde(default)]
+    pub dropped_chunks: u64,
+    #[serde(default)]
+    pub backpressure_events: u64,
 }
 
 /// WebSocket Flow 事件
@@ -350,6 +482,8 @@ pub enum WsFlowEvent {
     Notification { notification: NotificationEvent },
     /// 请求速率更新
     RequestRateUpdate { rate: f64, count: usize },
+    /// SLO 告警状态变化
+    SloStatusChanged { status: SloStatus },
 }
 
 impl From<FlowEvent> for WsFlowEvent {
@@ -366,6 +500,7 @@ impl From<FlowEvent> for WsFlowEvent {
             FlowEvent::RequestRateUpdate { rate, count } => {
                 WsFlowEvent::RequestRateUpdate { rate, count }
             }
+            FlowEvent::SloStatusChanged { status } => WsFlowEvent::SloStatusChanged { status },
         }
     }
 }
@@ -91,11 +91,13 @@ impl E2ETestContext {
                     stop: None,
                     stream: false,
                     extra: HashMap::new(),
+                    ..Default::default()
                 },
                 model: model.to_string(),
                 original_model: Some(model.to_string()),
                 size_bytes: 100,
                 tools: None,
+                ..Default::default()
             },
             response: Some(LLMResponse {
                 status_code: 200,
@@ -124,6 +126,7 @@ impl E2ETestContext {
                 size_bytes: 200,
                 timestamp_start: now,
                 timestamp_end: now,
+                ..Default::default()
             }),
             error: None,
             metadata: FlowMetadata {
@@ -135,6 +138,7 @@ impl E2ETestContext {
                 context_usage_percentage: None,
                 client_info: ClientInfo::default(),
                 routing_info: RoutingInfo::default(),
+                ..Default::default()
             },
             timestamps: FlowTimestamps {
                 created: now,
@@ -144,6 +148,7 @@ impl E2ETestContext {
                 response_end: Some(now),
                 duration_ms: 500,
                 ttfb_ms: Some(100),
+                ..Default::default()
             },
             state: FlowState::Completed,
             annotations: FlowAnnotations::default(),
@@ -291,6 +296,7 @@ mod tests {
             redact_sensitive: false,
             redaction_rules: Vec::new(),
             compress: false,
+            ..Default::default()
         };
         let json_exporter = FlowExporter::new(json_options);
         let json_data = json_exporter.export_json(&all_flows);
@@ -308,6 +314,7 @@ mod tests {
             redact_sensitive: false,
             redaction_rules: Vec::new(),
             compress: false,
+            ..Default::default()
         };
         let jsonl_exporter = FlowExporter::new(jsonl_options);
         let jsonl_data = jsonl_exporter.export_jsonl(&all_flows);
@@ -323,6 +330,7 @@ mod tests {
             redact_sensitive: false,
             redaction_rules: Vec::new(),
             compress: false,
+            ..Default::default()
         };
         let har_exporter = FlowExporter::new(har_options);
         let har_archive = har_exporter.export_har(&all_flows);
@@ -337,6 +345,7 @@ mod tests {
             redact_sensitive: true,
             redaction_rules: Vec::new(),
             compress: false,
+            ..Default::default()
         };
         let md_exporter = FlowExporter::new(md_options);
         let md_data = md_exporter.export_markdown_multiple(&all_flows);
@@ -351,6 +360,7 @@ mod tests {
             redact_sensitive: false,
             redaction_rules: Vec::new(),
             compress: false,
+            ..Default::default()
         };
         let csv_exporter = FlowExporter::new(csv_options);
         let csv_data = csv_exporter.export_csv(&all_flows);
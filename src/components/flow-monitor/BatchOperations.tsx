@@ -560,6 +560,8 @@ export function BatchOperations({
               <option value="har">HAR</option>
               <option value="markdown">Markdown</option>
               <option value="csv">CSV</option>
+              <option value="openai_batch">OpenAI Batch</option>
+              <option value="anthropic_batch">Anthropic Batch</option>
             </select>
             <p className="text-xs text-muted-foreground">
               将导出 {selectedCount} 个 Flow
@@ -0,0 +1,94 @@
+/**
+ * 捕获诊断组件
+ *
+ * 查询指定模型和路径的请求当前是否会被捕获，不捕获时显示原因
+ * （监控未启用、采样、排除规则、活跃 Flow 上限）。
+ */
+
+import { useState } from "react";
+import { CheckCircle2, Loader2, Search, XCircle } from "lucide-react";
+import { flowMonitorApi, type CaptureDecision } from "@/lib/api/flowMonitor";
+import { cn } from "@/lib/utils";
+
+interface CaptureDiagnosticProps {
+  /** 默认模型 */
+  defaultModel?: string;
+  /** 默认路径 */
+  defaultPath?: string;
+  className?: string;
+}
+
+export function CaptureDiagnostic({
+  defaultModel = "",
+  defaultPath = "/v1/chat/completions",
+  className,
+}: CaptureDiagnosticProps) {
+  const [model, setModel] = useState(defaultModel);
+  const [path, setPath] = useState(defaultPath);
+  const [decision, setDecision] = useState<CaptureDecision | null>(null);
+  const [loading, setLoading] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  const handleCheck = async () => {
+    try {
+      setLoading(true);
+      setError(null);
+      setDecision(await flowMonitorApi.explainCaptureDecision(model, path));
+    } catch (e) {
+      console.error("检查捕获状态失败:", e);
+      setError(e instanceof Error ? e.message : String(e));
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  return (
+    <div className={cn("space-y-2 text-left text-sm", className)}>
+      <div className="text-xs text-muted-foreground">
+        请求没有出现？检查它是否会被捕获：
+      </div>
+      <div className="flex items-center gap-2">
+        <input
+          value={model}
+          onChange={(e) => setModel(e.target.value)}
+          placeholder="模型，如 gpt-4o"
+          className="flex-1 rounded border bg-background px-2 py-1 text-sm"
+        />
+        <input
+          value={path}
+          onChange={(e) => setPath(e.target.value)}
+          placeholder="路径"
+          className="flex-1 rounded border bg-background px-2 py-1 text-sm"
+        />
+        <button
+          onClick={handleCheck}
+          disabled={loading || !model.trim()}
+          className="flex items-center gap-1 rounded border px-2 py-1 text-sm hover:bg-muted disabled:opacity-50"
+        >
+          {loading ? (
+            <Loader2 className="h-3 w-3 animate-spin" />
+          ) : (
+            <Search className="h-3 w-3" />
+          )}
+          检查
+        </button>
+      </div>
+      {error && <div className="text-xs text-red-500">{error}</div>}
+      {decision && (
+        <div
+          className={cn(
+            "flex items-center gap-1 text-xs",
+            decision.would_capture ? "text-green-600" : "text-red-500",
+          )}
+        >
+          {decision.would_capture ? (
+            <CheckCircle2 className="h-3 w-3" />
+          ) : (
+            <XCircle className="h-3 w-3" />
+          )}
+          {decision.message}
+        </div>
+      )}
+    </div>
+  );
+}
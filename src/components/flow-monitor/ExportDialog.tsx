@@ -74,6 +74,24 @@ const FORMAT_OPTIONS: FormatOption[] = [
     description: "表格格式，仅包含元数据，适合 Excel 分析",
     icon: <FileSpreadsheet className="h-5 w-5" />,
   },
+  {
+    value: "openai_batch",
+    label: "OpenAI Batch",
+    description: "OpenAI Batch API 输入，可按批处理价格重放请求",
+    icon: <FileCode className="h-5 w-5" />,
+  },
+  {
+    value: "anthropic_batch",
+    label: "Anthropic Batch",
+    description: "Anthropic Message Batches 输入，可按批处理价格重放请求",
+    icon: <FileCode className="h-5 w-5" />,
+  },
+  {
+    value: "parquet",
+    label: "Parquet",
+    description: "列式格式，仅包含元数据和请求消息，适合导入数据仓库",
+    icon: <FileSpreadsheet className="h-5 w-5" />,
+  },
 ];
 
 const DEFAULT_REDACTION_RULES: RedactionRule[] = [
@@ -115,6 +133,7 @@ export function ExportDialog({
   const [includeRaw, setIncludeRaw] = useState(true);
   const [includeStreamChunks, setIncludeStreamChunks] = useState(false);
   const [redactSensitive, setRedactSensitive] = useState(false);
+  const [verifyRedaction, setVerifyRedaction] = useState(false);
   const [redactionRules, setRedactionRules] = useState<RedactionRule[]>(
     DEFAULT_REDACTION_RULES,
   );
@@ -137,6 +156,7 @@ export function ExportDialog({
         include_raw: includeRaw,
         include_stream_chunks: includeStreamChunks,
         redact_sensitive: redactSensitive,
+        verify_redaction: verifyRedaction,
         redaction_rules: redactSensitive
           ? redactionRules.filter((r) => r.enabled)
           : undefined,
@@ -175,6 +195,7 @@ export function ExportDialog({
     includeRaw,
     includeStreamChunks,
     redactSensitive,
+    verifyRedaction,
     redactionRules,
     flowIds,
     filter,
@@ -275,6 +296,12 @@ export function ExportDialog({
               label="脱敏敏感数据"
               description="自动替换 API 密钥、邮箱、手机号等敏感信息"
             />
+            <OptionCheckbox
+              checked={verifyRedaction}
+              onChange={setVerifyRedaction}
+              label="安全分享校验"
+              description="脱敏后再扫描一遍，发现残留密钥或高熵字符串时拒绝导出"
+            />
 
             {/* 脱敏规则 */}
             {redactSensitive && (
@@ -470,7 +497,12 @@ function OptionCheckbox({
  * 下载文件
  */
 function downloadFile(data: string, filename: string, mimeType: string) {
-  const blob = new Blob([data], { type: mimeType });
+  // Parquet 以 base64 编码传输
+  const content =
+    mimeType === "application/vnd.apache.parquet"
+      ? Uint8Array.from(atob(data), (c) => c.charCodeAt(0))
+      : data;
+  const blob = new Blob([content], { type: mimeType });
   const url = URL.createObjectURL(blob);
   const a = document.createElement("a");
   a.href = url;
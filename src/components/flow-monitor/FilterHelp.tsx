@@ -117,6 +117,13 @@ const FILTER_CATEGORIES: FilterCategory[] = [
         example: "~bs assistant",
         hasArg: true,
       },
+      {
+        syntax: "<field> ~= <regex>",
+        description:
+          "字段正则匹配（model / provider / content / request / response）",
+        example: 'content ~= "error.*timeout"',
+        hasArg: true,
+      },
     ],
   },
   {
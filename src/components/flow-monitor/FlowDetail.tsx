@@ -938,6 +938,26 @@ function ResponseTab({
         </CollapsibleSection>
       )}
 
+      {/* 工具往返 */}
+      {response.turns && response.turns.length > 0 && (
+        <CollapsibleSection
+          title={`工具往返 (${response.turns.length})`}
+          icon={<Wrench className="h-4 w-4" />}
+          expanded={expandedSections.has("toolTurns")}
+          onToggle={() => toggleSection("toolTurns")}
+        >
+          <div className="space-y-2">
+            {response.turns.map((turn, index) => (
+              <MessageItem
+                key={index}
+                message={turn}
+                onCopy={(content) => onCopy(content, `轮次 ${index + 1}`)}
+              />
+            ))}
+          </div>
+        </CollapsibleSection>
+      )}
+
       {/* 响应头 */}
       <CollapsibleSection
         title="响应头"
@@ -1109,6 +1129,15 @@ function MetadataTab({ flow, onCopy }: MetadataTabProps) {
               {(metadata.context_usage_percentage * 100).toFixed(1)}%
             </div>
           )}
+          {metadata.content_filter && (
+            <div className="col-span-2">
+              <span className="text-muted-foreground">内容过滤:</span>{" "}
+              {metadata.content_filter.action === "block"
+                ? "已拦截"
+                : `已替换 ${metadata.content_filter.redactions} 处`}
+              （{metadata.content_filter.rules.join(", ")}）
+            </div>
+          )}
         </div>
       </div>
 
@@ -1149,7 +1178,11 @@ function MetadataTab({ flow, onCopy }: MetadataTabProps) {
       {/* 路由信息 */}
       {(metadata.routing_info.target_url ||
         metadata.routing_info.route_rule ||
-        metadata.routing_info.load_balance_strategy) && (
+        metadata.routing_info.load_balance_strategy ||
+        metadata.routing_info.model_downgrade ||
+        metadata.routing_info.remap_bypassed ||
+        metadata.routing_info.routing_trace ||
+        metadata.routing_info.extra_headers) && (
         <div className="rounded-lg border bg-card p-4">
           <h3 className="text-sm font-medium mb-3 flex items-center gap-2">
             <Zap className="h-4 w-4" />
@@ -1176,6 +1209,65 @@ function MetadataTab({ flow, onCopy }: MetadataTabProps) {
                 {metadata.routing_info.load_balance_strategy}
               </div>
             )}
+            {metadata.routing_info.model_downgrade && (
+              <div>
+                <span className="text-muted-foreground">模型降级:</span>{" "}
+                {metadata.routing_info.model_downgrade.from} →{" "}
+                {metadata.routing_info.model_downgrade.to}
+                <span className="text-muted-foreground">
+                  {" "}
+                  (估算 {metadata.routing_info.model_downgrade.estimated_tokens}{" "}
+                  tokens &gt; 阈值{" "}
+                  {metadata.routing_info.model_downgrade.threshold_tokens})
+                </span>
+              </div>
+            )}
+            {metadata.routing_info.remap_bypassed && (
+              <div>
+                <span className="text-muted-foreground">模型改写:</span>{" "}
+                已跳过（x-no-remap，按字面模型名转发）
+              </div>
+            )}
+            {metadata.routing_info.routing_trace && (
+              <div>
+                <span className="text-muted-foreground">解析轨迹:</span>{" "}
+                <code>{metadata.routing_info.routing_trace.requested_model}</code>
+                {(metadata.routing_info.routing_trace.steps ?? []).map(
+                  (step, i) => (
+                    <span key={i}>
+                      {" "}
+                      →{" "}
+                      <span className="text-muted-foreground">
+                        ({step.kind === "alias" ? "别名" : "降级"})
+                      </span>{" "}
+                      <code>{step.to}</code>
+                    </span>
+                  ),
+                )}
+                <span className="text-muted-foreground">
+                  {" "}
+                  {metadata.routing_info.routing_trace.matched_rule
+                    ? `命中规则 ${metadata.routing_info.routing_trace.matched_rule.pattern}（优先级 ${metadata.routing_info.routing_trace.matched_rule.priority}）`
+                    : "未命中规则，使用默认 Provider"}
+                  {metadata.routing_info.routing_trace.provider &&
+                    ` → ${metadata.routing_info.routing_trace.provider}`}
+                </span>
+              </div>
+            )}
+            {metadata.routing_info.extra_headers && (
+              <div>
+                <span className="text-muted-foreground">附加请求头:</span>
+                <div className="mt-1 space-y-0.5 font-mono text-xs">
+                  {Object.entries(metadata.routing_info.extra_headers).map(
+                    ([name, value]) => (
+                      <div key={name} className="break-all">
+                        {name}: {value}
+                      </div>
+                    ),
+                  )}
+                </div>
+              </div>
+            )}
           </div>
         </div>
       )}
@@ -1194,6 +1286,20 @@ function MetadataTab({ flow, onCopy }: MetadataTabProps) {
           </div>
         )}
 
+      {/* 默认参数 */}
+      {metadata.defaulted_params &&
+        Object.keys(metadata.defaulted_params).length > 0 && (
+          <div className="rounded-lg border bg-card p-4">
+            <h3 className="text-sm font-medium mb-3 flex items-center gap-2">
+              <Code className="h-4 w-4" />
+              默认参数
+            </h3>
+            <pre className="text-xs font-mono whitespace-pre-wrap break-words bg-muted/50 rounded p-3">
+              {JSON.stringify(metadata.defaulted_params, null, 2)}
+            </pre>
+          </div>
+        )}
+
       {/* Flow ID */}
       <div className="rounded-lg border bg-card p-4">
         <h3 className="text-sm font-medium mb-3">Flow ID</h3>
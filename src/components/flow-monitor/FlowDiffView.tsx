@@ -66,6 +66,53 @@ export interface TokenDiff {
   total_diff: number;
 }
 
+/**
+ * 流式 Chunk 差异项（按重建内容偏移对齐的区间）
+ */
+export interface StreamChunkDiffItem {
+  offset: number;
+  diff_type: DiffType;
+  left_chunk_index: number | null;
+  right_chunk_index: number | null;
+  left_text: string | null;
+  right_text: string | null;
+}
+
+/**
+ * 流式 Chunk 序列差异
+ */
+export interface StreamChunkDiff {
+  left_chunk_count: number;
+  right_chunk_count: number;
+  first_divergence_offset: number | null;
+  first_divergence_left_index: number | null;
+  first_divergence_right_index: number | null;
+  chunk_diffs: StreamChunkDiffItem[];
+}
+
+/**
+ * 对数概率差异项（按输出位置对齐）
+ */
+export interface LogprobDiffItem {
+  index: number;
+  left_token: string | null;
+  right_token: string | null;
+  left_logprob: number | null;
+  right_logprob: number | null;
+  delta: number | null;
+}
+
+/**
+ * Token 级对数概率差异
+ */
+export interface LogprobDiff {
+  first_divergence_index: number | null;
+  max_abs_delta: number;
+  mean_abs_delta: number;
+  truncated: boolean;
+  items: LogprobDiffItem[];
+}
+
 /**
  * 差异配置
  */
@@ -73,6 +120,8 @@ export interface DiffConfig {
   ignore_fields: string[];
   ignore_timestamps: boolean;
   ignore_ids: boolean;
+  /** 按角色和内容对齐消息，插入或删除的消息不会让后续消息全部显示为修改 */
+  align_by_role?: boolean;
 }
 
 /**
@@ -86,6 +135,8 @@ export interface FlowDiffResult {
   metadata_diffs: DiffItem[];
   message_diffs: MessageDiffItem[];
   token_diff: TokenDiff;
+  stream_chunk_diff?: StreamChunkDiff;
+  logprob_diff?: LogprobDiff;
 }
 
 /**
@@ -139,6 +190,7 @@ export function FlowDiffView({
     ignore_fields: [],
     ignore_timestamps: true,
     ignore_ids: true,
+    align_by_role: false,
   });
   const [showConfig, setShowConfig] = useState(false);
   const [activeSection, setActiveSection] = useState<string>("request");
@@ -465,6 +517,17 @@ function DiffConfigPanel({ config, onChange }: DiffConfigPanelProps) {
           />
           <span className="text-sm">忽略 ID</span>
         </label>
+        <label className="flex items-center gap-2 cursor-pointer">
+          <input
+            type="checkbox"
+            checked={config.align_by_role ?? false}
+            onChange={(e) =>
+              onChange({ ...config, align_by_role: e.target.checked })
+            }
+            className="rounded border-gray-300"
+          />
+          <span className="text-sm">按角色对齐消息</span>
+        </label>
       </div>
     </div>
   );
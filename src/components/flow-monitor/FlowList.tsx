@@ -40,6 +40,7 @@ import {
 import { useFlowEvents } from "@/hooks/useFlowEvents";
 import { useFlowNotifications } from "@/hooks/useFlowNotifications";
 import { NotificationSettings } from "./NotificationSettings";
+import { CaptureDiagnostic } from "./CaptureDiagnostic";
 import { cn } from "@/lib/utils";
 
 interface FlowListProps {
@@ -141,6 +142,7 @@ export function FlowList({
             },
             annotations: {
               tags: [],
+              auto_tags: [],
               starred: false,
             },
           };
@@ -512,6 +514,7 @@ export function FlowList({
             <option value="duration">按耗时</option>
             <option value="total_tokens">按 Token</option>
             <option value="model">按模型</option>
+            <option value="relevance">按相关度</option>
           </select>
           <button
             onClick={() => setSortDesc(!sortDesc)}
@@ -535,6 +538,10 @@ export function FlowList({
         {flows.length === 0 ? (
           <div className="p-8 text-center text-muted-foreground">
             暂无 Flow 记录
+            <CaptureDiagnostic
+              defaultModel={filter.models?.[0] ?? ""}
+              className="mx-auto mt-4 max-w-lg"
+            />
           </div>
         ) : (
           <div className="divide-y max-h-[600px] overflow-y-auto">
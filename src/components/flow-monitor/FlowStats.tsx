@@ -13,6 +13,8 @@ import {
   Loader2,
   AlertCircle,
   LineChart,
+  Octagon,
+  Database,
 } from "lucide-react";
 import {
   flowMonitorApi,
@@ -488,6 +490,37 @@ function TrendsTab({ enhancedStats }: TrendsTabProps) {
           <SuccessRateChart data={enhancedStats.success_by_provider} />
         </div>
       )}
+
+      {/* 未正常结束的流式响应（按提供商） */}
+      {(enhancedStats.incomplete_stream_by_provider ?? []).some(
+        ([, rate]) => rate > 0,
+      ) && (
+        <div className="rounded-lg border bg-card p-4">
+          <h3 className="text-sm font-medium mb-4 flex items-center gap-2">
+            <AlertCircle className="h-4 w-4 text-yellow-500" />
+            流式响应截断率
+          </h3>
+          <div className="space-y-2 text-sm">
+            {(enhancedStats.incomplete_stream_by_provider ?? []).map(
+              ([provider, rate]) => (
+                <div
+                  key={provider}
+                  className="flex items-center justify-between"
+                >
+                  <span className="font-medium">{provider}</span>
+                  <span
+                    className={cn(
+                      rate > 0.05 ? "text-red-600" : "text-muted-foreground",
+                    )}
+                  >
+                    {(rate * 100).toFixed(1)}%
+                  </span>
+                </div>
+              ),
+            )}
+          </div>
+        </div>
+      )}
     </div>
   );
 }
@@ -545,6 +578,55 @@ function DistributionTab({ enhancedStats }: DistributionTabProps) {
           />
         </div>
       )}
+
+      {/* 停止原因分布 */}
+      {enhancedStats.stop_reason_distribution &&
+        enhancedStats.stop_reason_distribution.buckets.length > 0 && (
+          <div className="rounded-lg border bg-card p-4">
+            <h3 className="text-sm font-medium mb-4 flex items-center gap-2">
+              <Octagon className="h-4 w-4 text-amber-500" />
+              停止原因分布
+            </h3>
+            <DistributionChart
+              data={enhancedStats.stop_reason_distribution}
+              color="bg-amber-500"
+            />
+          </div>
+        )}
+
+      {/* 提示词缓存 */}
+      {enhancedStats.cache_effectiveness &&
+        enhancedStats.cache_effectiveness.flows_with_cache_usage > 0 && (
+          <div className="rounded-lg border bg-card p-4">
+            <h3 className="text-sm font-medium mb-4 flex items-center gap-2">
+              <Database className="h-4 w-4 text-emerald-500" />
+              提示词缓存
+            </h3>
+            <div className="grid grid-cols-2 gap-2 text-sm">
+              <div className="text-muted-foreground">命中请求</div>
+              <div>
+                {enhancedStats.cache_effectiveness.cache_hit_flows} /{" "}
+                {enhancedStats.cache_effectiveness.flows_with_cache_usage}
+              </div>
+              <div className="text-muted-foreground">命中率</div>
+              <div>
+                {(enhancedStats.cache_effectiveness.hit_ratio * 100).toFixed(1)}%
+              </div>
+              <div className="text-muted-foreground">缓存读取 / 写入</div>
+              <div>
+                {enhancedStats.cache_effectiveness.cache_read_tokens.toLocaleString()}{" "}
+                /{" "}
+                {enhancedStats.cache_effectiveness.cache_write_tokens.toLocaleString()}
+              </div>
+              <div className="text-muted-foreground">节省（等价输入 Token）</div>
+              <div>
+                {Math.round(
+                  enhancedStats.cache_effectiveness.saved_input_tokens,
+                ).toLocaleString()}
+              </div>
+            </div>
+          </div>
+        )}
     </div>
   );
 }
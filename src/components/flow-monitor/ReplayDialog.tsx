@@ -50,6 +50,9 @@ export interface ReplayConfig {
   credential_id?: string;
   modify_request?: RequestModification;
   interval_ms: number;
+  credential_interval_ms?: number;
+  concurrency?: number;
+  ordered_results?: boolean;
 }
 
 /**
@@ -65,6 +68,21 @@ export interface ReplayResult {
   duration_ms: number;
 }
 
+/**
+ * 流式重放事件（`replay-stream-event`）
+ */
+export type ReplayStreamEvent =
+  | { type: "started"; original_flow_id: string; replay_flow_id: string }
+  | {
+      type: "chunk";
+      replay_flow_id: string;
+      index: number;
+      event?: string;
+      data: string;
+      content_delta?: string;
+    }
+  | { type: "finished"; replay_flow_id: string; result: ReplayResult };
+
 /**
  * 批量重放结果
  */
@@ -76,6 +94,27 @@ export interface BatchReplayResult {
   started_at: string;
   completed_at: string;
   total_duration_ms: number;
+  throughput_per_second?: number;
+  latency?: LatencyDistribution;
+  stopped_by?: StopCondition;
+  cancelled?: boolean;
+}
+
+/**
+ * 循环重放的停止条件
+ */
+export type StopCondition = "first_error" | "first_success" | "never";
+
+/**
+ * 重放耗时分布（毫秒）
+ */
+export interface LatencyDistribution {
+  min_ms: number;
+  max_ms: number;
+  mean_ms: number;
+  p50_ms: number;
+  p90_ms: number;
+  p99_ms: number;
 }
 
 // ============================================================================
@@ -156,6 +195,7 @@ export function ReplayDialog({
   const buildConfig = useCallback((): ReplayConfig => {
     const replayConfig: ReplayConfig = {
       interval_ms: config.interval_ms,
+      concurrency: config.concurrency,
     };
 
     // 构建请求修改
@@ -185,6 +225,7 @@ export function ReplayDialog({
     return replayConfig;
   }, [
     config.interval_ms,
+    config.concurrency,
     modifyModel,
     newModel,
     modifyTemperature,
@@ -460,11 +501,36 @@ export function ReplayDialog({
                     </div>
                   )}
 
+                  {/* 并发数 */}
+                  {isBatchReplay && (
+                    <div className="space-y-2">
+                      <label className="text-sm font-medium">并发数</label>
+                      <div className="flex items-center gap-2">
+                        <input
+                          type="number"
+                          value={config.concurrency ?? 1}
+                          onChange={(e) =>
+                            setConfig({
+                              ...config,
+                              concurrency: parseInt(e.target.value) || 1,
+                            })
+                          }
+                          min={1}
+                          max={32}
+                          className="w-32 rounded border bg-background px-3 py-2 text-sm"
+                        />
+                        <span className="text-xs text-muted-foreground">
+                          同时进行的重放数
+                        </span>
+                      </div>
+                    </div>
+                  )}
+
                   <div className="text-xs text-muted-foreground">
                     <p>• 重放会创建新的 Flow 并标记为 "replay"</p>
                     <p>• 重放完成后可以对比原始 Flow 和重放 Flow</p>
                     {isBatchReplay && (
-                      <p>• 批量重放会按顺序执行，每个请求之间有间隔</p>
+                      <p>• 批量重放按并发数执行，相邻请求的开始时间之间有间隔</p>
                     )}
                   </div>
                 </div>
@@ -563,6 +629,11 @@ function ReplayResultDisplay({
           <div className="mt-3 pt-3 border-t text-center text-sm text-muted-foreground">
             <Clock className="h-4 w-4 inline mr-1" />
             总耗时: {(batchResult.total_duration_ms / 1000).toFixed(2)}s
+            {batchResult.throughput_per_second !== undefined && (
+              <span className="ml-3">
+                吞吐量: {batchResult.throughput_per_second.toFixed(2)}/s
+              </span>
+            )}
           </div>
         </div>
 
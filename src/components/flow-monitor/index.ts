@@ -16,6 +16,7 @@ export { QuickFilterPanel } from "./QuickFilterPanel";
 export { RelatedFlows } from "./RelatedFlows";
 export { BookmarkPanel } from "./BookmarkPanel";
 export { StatsExport, StatsExportDropdown } from "./StatsExport";
+export { CaptureDiagnostic } from "./CaptureDiagnostic";
 export { useFlowEvents } from "@/hooks/useFlowEvents";
 export { useFlowActions } from "@/hooks/useFlowActions";
 
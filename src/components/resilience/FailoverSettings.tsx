@@ -21,6 +21,7 @@ export function FailoverSettings({ onSave }: FailoverSettingsProps) {
   const [config, setConfig] = useState<FailoverConfig>({
     auto_switch: true,
     switch_on_quota: true,
+    switch_on_overload: true,
   });
   const [switchLog, setSwitchLog] = useState<SwitchLogEntry[]>([]);
   const [loading, setLoading] = useState(false);
@@ -188,6 +189,29 @@ export function FailoverSettings({ onSave }: FailoverSettingsProps) {
             className="w-5 h-5 rounded border-gray-300"
           />
         </label>
+
+        <label
+          className={`flex items-center justify-between p-4 rounded-lg border cursor-pointer hover:bg-muted/50 ${
+            !config.auto_switch ? "opacity-50 pointer-events-none" : ""
+          }`}
+        >
+          <div>
+            <span className="text-sm font-medium">上游过载时切换</span>
+            <p className="text-xs text-muted-foreground">
+              当 Provider 持续返回过载错误 (503/529) 且重试耗尽时，自动切换到其他
+              Provider
+            </p>
+          </div>
+          <input
+            type="checkbox"
+            checked={config.switch_on_overload ?? true}
+            onChange={(e) =>
+              updateConfig({ switch_on_overload: e.target.checked })
+            }
+            disabled={!config.auto_switch}
+            className="w-5 h-5 rounded border-gray-300"
+          />
+        </label>
       </div>
 
       {/* Actions */}
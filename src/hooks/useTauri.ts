@@ -8,12 +8,41 @@ export interface ServerStatus {
   uptime_secs: number;
 }
 
+// 路由闸门模式（drain 等待进行中的请求完成，reject 立即中止进行中的请求）
+export type RoutingGateMode = "open" | "drain" | "reject";
+
+// 路由闸门状态
+export interface RoutingGateStatus {
+  mode: RoutingGateMode;
+  in_flight: number;
+  drained: boolean;
+  rejected_count: number;
+  retry_after_seconds: number;
+  changed_at: string;
+}
+
 // TLS Configuration
 export interface TlsConfig {
   enable: boolean;
   cert_path: string | null;
   key_path: string | null;
-}
+  /** 访问上游时出示的客户端证书（PEM 或 .p12 / .pfx） */
+  client_cert_path?: string | null;
+  client_key_path?: string | null;
+  /** PKCS#12 客户端证书的密码 */
+  client_cert_password?: string | null;
+  /** 额外信任的 CA 证书包（PEM） */
+  ca_bundle_path?: string | null;
+}
+
+/** 按 Provider 覆盖的上游客户端证书 */
+export type ClientTlsConfig = Pick<
+  TlsConfig,
+  | "client_cert_path"
+  | "client_key_path"
+  | "client_cert_password"
+  | "ca_bundle_path"
+>;
 
 // Remote Management Configuration
 export interface RemoteManagementConfig {
@@ -120,24 +149,34 @@ export interface Config {
       enabled: boolean;
       credentials_path: string | null;
       region: string | null;
+      proxy?: string | null;
+      tls?: ClientTlsConfig | null;
     };
     gemini: {
       enabled: boolean;
       credentials_path: string | null;
+      proxy?: string | null;
+      tls?: ClientTlsConfig | null;
     };
     qwen: {
       enabled: boolean;
       credentials_path: string | null;
+      proxy?: string | null;
+      tls?: ClientTlsConfig | null;
     };
     openai: {
       enabled: boolean;
       api_key: string | null;
       base_url: string | null;
+      proxy?: string | null;
+      tls?: ClientTlsConfig | null;
     };
     claude: {
       enabled: boolean;
       api_key: string | null;
       base_url: string | null;
+      proxy?: string | null;
+      tls?: ClientTlsConfig | null;
     };
   };
   default_provider: string;
@@ -146,6 +185,8 @@ export interface Config {
   ampcode: AmpConfig;
   credential_pool: CredentialPoolConfig;
   proxy_url: string | null;
+  /** 不走代理的主机列表（域名、IP 或 CIDR） */
+  no_proxy?: string[];
   /** 关闭时最小化到托盘（而不是退出应用） */
   minimize_to_tray: boolean;
 }
@@ -168,6 +209,17 @@ export async function getServerStatus(): Promise<ServerStatus> {
   return invoke("get_server_status");
 }
 
+export async function getRoutingGateStatus(): Promise<RoutingGateStatus> {
+  return invoke("get_routing_gate_status");
+}
+
+export async function setRoutingGateMode(
+  mode: RoutingGateMode,
+  retryAfterSeconds?: number,
+): Promise<RoutingGateStatus> {
+  return invoke("set_routing_gate_mode", { mode, retryAfterSeconds });
+}
+
 export async function getConfig(): Promise<Config> {
   return invoke("get_config");
 }
@@ -0,0 +1,60 @@
+/**
+ * 审计日志 API
+ *
+ * 查询配置与凭证变更记录，并校验审计日志的哈希链
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+export type AuditAction =
+  | "config_save"
+  | "config_import"
+  | "config_export"
+  | "credential_add"
+  | "credential_update"
+  | "credential_rotate"
+  | "credential_remove";
+
+/**
+ * 单个字段的变更（密钥类字段已脱敏）
+ */
+export interface AuditChange {
+  path: string;
+  before?: unknown;
+  after?: unknown;
+}
+
+/**
+ * 审计记录
+ */
+export interface AuditEntry {
+  seq: number;
+  timestamp: string;
+  actor: string;
+  action: AuditAction;
+  target?: string;
+  changes: AuditChange[];
+  prev_hash: string;
+  hash: string;
+  signature: string;
+}
+
+/**
+ * 审计日志校验结果
+ */
+export interface AuditVerifyReport {
+  valid: boolean;
+  verified_entries: number;
+  /** 第一条无效记录所在的行号（从 1 开始） */
+  first_invalid_line?: number;
+  error?: string;
+}
+
+export const auditApi = {
+  /** 校验审计日志的哈希链与签名 */
+  verify: (): Promise<AuditVerifyReport> => invoke("verify_audit_log"),
+
+  /** 获取最近的审计记录（按时间倒序） */
+  getEntries: (limit?: number): Promise<AuditEntry[]> =>
+    invoke("get_audit_entries", { limit }),
+};
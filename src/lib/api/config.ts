@@ -5,6 +5,32 @@ export interface ServerConfig {
   host: string;
   port: number;
   api_key: string;
+  api_keys?: ClientApiKey[];
+  api_keys_file?: string;
+  grpc?: GrpcConfig;
+  otlp?: OtlpConfig;
+}
+
+export interface GrpcConfig {
+  enable: boolean;
+  port: number;
+}
+
+/** OpenTelemetry OTLP 导出配置（OTLP/HTTP，JSON 编码） */
+export interface OtlpConfig {
+  enable: boolean;
+  /** 采集器的 Traces 端点，如 http://127.0.0.1:4318/v1/traces */
+  endpoint: string;
+  headers?: Record<string, string>;
+  service_name: string;
+  timeout_ms: number;
+}
+
+export interface ClientApiKey {
+  key: string;
+  label: string;
+  allowed_models?: string[];
+  allowed_providers?: string[];
 }
 
 export interface ProviderConfig {
@@ -12,12 +38,43 @@ export interface ProviderConfig {
   credentials_path?: string;
   region?: string;
   project_id?: string;
+  proxy?: string;
+  tls?: ClientTlsConfig;
+  connection_pool?: ConnectionPoolConfig;
+  /** 非流式请求的总超时（毫秒），可被请求头 x-request-timeout-ms 覆盖 */
+  request_timeout_ms?: number;
+  /** 附加到每个上游请求的请求头，值支持 ${ENV_VAR} 插值 */
+  extra_headers?: Record<string, string>;
+}
+
+export interface ClientTlsConfig {
+  client_cert_path?: string;
+  client_key_path?: string;
+  /** PKCS#12 客户端证书的密码 */
+  client_cert_password?: string;
+  ca_bundle_path?: string;
+}
+
+/** 上游连接池配置，未设置的项沿用默认值 */
+export interface ConnectionPoolConfig {
+  pool_max_idle_per_host?: number;
+  pool_idle_timeout_secs?: number;
+  tcp_keepalive_secs?: number;
+  /** 直接以 HTTP/2 连接（部分网关仅支持 HTTP/1.1） */
+  http2_prior_knowledge?: boolean;
 }
 
 export interface CustomProviderConfig {
   enabled: boolean;
   api_key?: string;
   base_url?: string;
+  proxy?: string;
+  tls?: ClientTlsConfig;
+  connection_pool?: ConnectionPoolConfig;
+  /** 非流式请求的总超时（毫秒），可被请求头 x-request-timeout-ms 覆盖 */
+  request_timeout_ms?: number;
+  /** 附加到每个上游请求的请求头，值支持 ${ENV_VAR} 插值 */
+  extra_headers?: Record<string, string>;
 }
 
 export interface ProvidersConfig {
@@ -34,11 +91,54 @@ export interface RoutingRuleConfig {
   priority: number;
 }
 
+// 别名参数约束（min/max 钳制或 force 强制覆盖）
+export interface ParamConstraint {
+  min?: number;
+  max?: number;
+  force?: unknown;
+}
+
+// 按请求大小的模型降级（提示词估算 Token 超过阈值时改用替代模型）
+export interface SizeDowngradeRule {
+  threshold_tokens: number;
+  target_model: string;
+}
+
+// 按别名的消息历史截断（转发前只保留最近的消息，不拆分工具调用和结果）
+export interface HistoryTruncationRule {
+  max_messages?: number;
+  max_tokens?: number;
+  keep_first_user_message?: boolean;
+}
+
+// 按 Provider 的推理强度映射（reasoning_effort 与 thinking.budget_tokens 互转）
+export interface ReasoningEffortMapping {
+  format: "effort" | "budget_tokens";
+  low_budget_tokens?: number;
+  medium_budget_tokens?: number;
+  high_budget_tokens?: number;
+}
+
 export interface RoutingConfig {
   default_provider: string;
   rules: RoutingRuleConfig[];
   model_aliases: Record<string, string>;
   exclusions: Record<string, string[]>;
+  param_constraints?: Record<string, Record<string, ParamConstraint>>;
+  size_downgrades?: Record<string, SizeDowngradeRule>;
+  history_truncation?: Record<string, HistoryTruncationRule>;
+  /** 按模型的默认参数（模型模式 -> 参数名 -> 默认值），仅在客户端未发送时应用 */
+  model_defaults?: Record<string, Record<string, unknown>>;
+  /** 按 Provider 的推理强度映射（Provider 名称 -> 映射），覆盖内置的 OpenAI/Claude 映射 */
+  reasoning_effort?: Record<string, ReasoningEffortMapping>;
+  session_affinity?: SessionAffinityConfig;
+}
+
+export interface SessionAffinityConfig {
+  enabled: boolean;
+  ttl_secs: number;
+  key_source: "auto" | "header" | "conversation";
+  header: string;
 }
 
 export interface RetrySettings {
@@ -86,6 +186,76 @@ export interface Config {
   logging: LoggingConfig;
   auth_dir: string;
   credential_pool: CredentialPoolConfig;
+  flow_plugins?: FlowPluginsConfig;
+  /** Flow Monitor 启动配置 */
+  flow_monitor?: FlowMonitorSettings;
+  /** 流式响应 */
+  streaming?: StreamingSettings;
+  /** 故障注入（混沌测试），默认关闭，切勿在生产环境启用 */
+  chaos?: ChaosConfig;
+  /** 响应内容过滤，默认关闭 */
+  content_filter?: ContentFilterConfig;
+  /** 影子镜像，默认关闭 */
+  shadow?: ShadowConfig;
+}
+
+export interface FlowPluginsConfig {
+  system_prompt_prefix?: string;
+}
+
+export interface FlowMonitorSettings {
+  /** 请求预写日志路径，为空表示不启用 */
+  wal_path?: string;
+}
+
+export interface StreamingSettings {
+  /** 透传无法识别的 SSE 事件，默认丢弃 */
+  passthrough_unknown_events?: boolean;
+}
+
+export type ChaosFault =
+  | { type: "delay"; ms: number }
+  | { type: "rate_limit"; retry_after_secs?: number }
+  | { type: "server_error"; status_code?: number }
+  | { type: "drop_stream"; after_chunks?: number }
+  | { type: "malformed_body" };
+
+export interface ChaosRule {
+  provider?: string;
+  model?: string;
+  probability: number;
+  fault: ChaosFault;
+}
+
+export interface ChaosConfig {
+  enabled: boolean;
+  rules?: ChaosRule[];
+}
+
+export type ContentFilterAction = "redact" | "block";
+
+export interface ContentFilterRule {
+  name: string;
+  pattern: string;
+  action?: ContentFilterAction;
+  replacement?: string;
+}
+
+export interface ContentFilterConfig {
+  enabled: boolean;
+  rules?: ContentFilterRule[];
+  block_message?: string;
+  stream_boundary_chars?: number;
+}
+
+export interface ShadowConfig {
+  enabled: boolean;
+  /** 影子 Provider，为空时沿用主请求的 Provider */
+  provider?: string;
+  /** 影子模型，为空时沿用主请求的模型 */
+  model?: string;
+  /** 采样率（0.0 - 1.0） */
+  sample_rate?: number;
 }
 
 // Export result
@@ -110,6 +280,13 @@ export interface UnifiedExportResult {
   has_credentials: boolean;
 }
 
+// Planned change reported by an import dry run
+export interface PlannedChange {
+  kind: "credential" | "token_file" | "routing_rule" | "model_mapping";
+  name: string;
+  action: "added" | "updated" | "unchanged" | "skipped" | "removed";
+}
+
 // Validation result
 export interface ValidationResult {
   valid: boolean;
@@ -119,6 +296,8 @@ export interface ValidationResult {
   has_credentials: boolean;
   errors: string[];
   warnings: string[];
+  changes: PlannedChange[];
+  conflicts: string[];
 }
 
 // Import result
@@ -158,6 +337,11 @@ export const configApi = {
     return invoke("validate_import", { content });
   },
 
+  // Validate a candidate config file with hot-reload rules, without applying it
+  async reloadValidateOnly(path?: string): Promise<ValidationResult> {
+    return invoke("reload_validate_only", { path });
+  },
+
   // Validate YAML config
   async validateConfigYaml(yamlContent: string): Promise<Config> {
     return invoke("validate_config_yaml", { yamlContent });
@@ -172,13 +356,28 @@ export const configApi = {
     return invoke("import_config", { currentConfig, yamlContent, merge });
   },
 
+  // Dry-run an import: list planned changes and conflicts without applying
+  async previewImport(
+    currentConfig: Config,
+    content: string,
+    merge: boolean,
+  ): Promise<ValidationResult> {
+    return invoke("preview_import", { currentConfig, content, merge });
+  },
+
   // Import bundle (JSON bundle or YAML config)
   async importBundle(
     currentConfig: Config,
     content: string,
     merge: boolean,
+    failOnConflict = false,
   ): Promise<ImportResult> {
-    return invoke("import_bundle", { currentConfig, content, merge });
+    return invoke("import_bundle", {
+      currentConfig,
+      content,
+      merge,
+      failOnConflict,
+    });
   },
 
   // Get config file paths
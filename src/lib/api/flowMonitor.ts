@@ -36,9 +36,12 @@ export type ProviderType =
  */
 export type FlowType =
   | "ChatCompletions"
+  | "Responses"
   | "AnthropicMessages"
   | "GeminiGenerateContent"
   | "Embeddings"
+  | "AudioTranscription"
+  | "FileUpload"
   | { Other: string };
 
 /**
@@ -78,9 +81,13 @@ export type FlowErrorType =
   | "rate_limit"
   | "content_filter"
   | "server_error"
+  | "overloaded"
   | "bad_request"
   | "model_unavailable"
   | "token_limit_exceeded"
+  | "gate_rejected"
+  | "incomplete_stream"
+  | "unknown_outcome"
   | "other";
 
 // ============================================================================
@@ -118,6 +125,8 @@ export interface ToolCall {
     name: string;
     arguments: string;
   };
+  /** 流式参数被截断（arguments 为补全后的 JSON 或原始片段） */
+  arguments_truncated?: boolean;
 }
 
 /**
@@ -165,9 +174,26 @@ export interface RequestParameters {
   max_tokens?: number;
   stop?: string[];
   stream: boolean;
+  /** OpenAI response_format（JSON 模式 / JSON Schema 结构化输出） */
+  response_format?: Record<string, unknown>;
+  /** 随机种子（用于可复现的采样） */
+  seed?: number;
   [key: string]: unknown;
 }
 
+/**
+ * 请求附件（multipart/form-data 中的字段或文件）
+ */
+export interface RequestAttachment {
+  field_name: string;
+  file_name?: string;
+  content_type?: string;
+  size_bytes: number;
+  value?: string;
+  data?: string;
+  truncated: boolean;
+}
+
 /**
  * LLM 请求
  */
@@ -184,6 +210,7 @@ export interface LLMRequest {
   parameters: RequestParameters;
   size_bytes: number;
   timestamp: string;
+  attachments?: RequestAttachment[];
 }
 
 /**
@@ -193,6 +220,18 @@ export interface ThinkingContent {
   text: string;
   tokens?: number;
   signature?: string;
+  /** 从正文中提取的内联推理片段（用于还原原始正文） */
+  inline_segments?: InlineReasoningSegment[];
+}
+
+/**
+ * 从响应正文中移除的内联推理片段
+ */
+export interface InlineReasoningSegment {
+  /** 片段在提取后正文中的位置（字节偏移） */
+  offset: number;
+  /** 原始片段（含标签及其后的空白） */
+  raw: string;
 }
 
 /**
@@ -215,6 +254,8 @@ export interface StreamInfo {
   first_chunk_latency_ms: number;
   avg_chunk_interval_ms: number;
   raw_chunks?: StreamChunk[];
+  /** 流是否以终止事件正常结束（否则响应可能被截断） */
+  completed_cleanly?: boolean;
 }
 
 /**
@@ -253,10 +294,53 @@ export interface LLMResponse {
   tool_calls: ToolCall[];
   usage: TokenUsage;
   stop_reason?: StopReason;
+  /** 上游返回的原始停止原因 */
+  raw_stop_reason?: string;
   size_bytes: number;
   timestamp_start: string;
   timestamp_end: string;
   stream_info?: StreamInfo;
+  /** 内容是否符合请求中的 JSON Schema（未提供 Schema 时为空） */
+  schema_valid?: boolean;
+  /** Token 级对数概率（请求了 logprobs 时才有） */
+  logprobs?: ResponseLogprobs;
+  /** 上游响应体的内容类型和解码结果 */
+  body_info?: ResponseBodyInfo;
+  /** 上游后端配置指纹（OpenAI system_fingerprint） */
+  system_fingerprint?: string;
+  /** 单个 Flow 内的工具往返序列（流中包含内联工具结果时才有）：助手调用 → 工具结果 → 助手继续 */
+  turns?: Message[];
+}
+
+/**
+ * 上游响应体的内容类型和解码结果
+ */
+export interface ResponseBodyInfo {
+  content_type?: string;
+  content_encoding?: string;
+  decoded: boolean;
+  binary: boolean;
+  encoded_size_bytes: number;
+  decoded_size_bytes: number;
+  note?: string;
+}
+
+/**
+ * 单个输出 Token 的对数概率
+ */
+export interface TokenLogprob {
+  token: string;
+  logprob: number;
+  top_logprobs?: { token: string; logprob: number }[];
+}
+
+/**
+ * 响应的 Token 级对数概率（超出上限时截断）
+ */
+export interface ResponseLogprobs {
+  tokens: TokenLogprob[];
+  total_tokens: number;
+  truncated: boolean;
 }
 
 // ============================================================================
@@ -270,6 +354,7 @@ export interface ClientInfo {
   ip?: string;
   user_agent?: string;
   request_id?: string;
+  api_key_label?: string;
 }
 
 /**
@@ -279,6 +364,57 @@ export interface RoutingInfo {
   target_url?: string;
   route_rule?: string;
   load_balance_strategy?: string;
+  session_key?: string;
+  session_affinity?: "hit" | "pinned" | "repinned";
+  model_downgrade?: ModelDowngrade;
+  history_truncation?: HistoryTruncation;
+  routing_trace?: RoutingTrace;
+  /** 客户端要求按字面模型名转发（x-no-remap），跳过了别名解析和降级规则 */
+  remap_bypassed?: boolean;
+  /** Provider 配置的附加上游请求头（敏感头的值已脱敏） */
+  extra_headers?: Record<string, string>;
+}
+
+/**
+ * 按请求大小的模型降级记录
+ */
+export interface ModelDowngrade {
+  from: string;
+  to: string;
+  estimated_tokens: number;
+  threshold_tokens: number;
+}
+
+/**
+ * 转发前的消息历史截断记录
+ */
+export interface HistoryTruncation {
+  original_messages: number;
+  removed_messages: number;
+  estimated_tokens?: number;
+}
+
+/**
+ * 模型解析轨迹中的一步改写
+ */
+export interface ResolutionStep {
+  kind: "alias" | "size_downgrade";
+  from: string;
+  to: string;
+}
+
+/**
+ * 模型路由解析轨迹
+ */
+export interface RoutingTrace {
+  requested_model: string;
+  steps?: ResolutionStep[];
+  resolved_model: string;
+  provider?: ProviderType;
+  matched_rule?: {
+    pattern: string;
+    priority: number;
+  };
 }
 
 /**
@@ -292,9 +428,34 @@ export interface FlowMetadata {
   client_info: ClientInfo;
   routing_info: RoutingInfo;
   injected_params?: Record<string, unknown>;
+  /** 客户端未发送、按模型默认值填充的参数 */
+  defaulted_params?: Record<string, unknown>;
   context_usage_percentage?: number;
+  shadow_of?: string;
+  /** 幂等重放 Flow 对应的原始 Flow ID */
+  idempotent_replay_of?: string;
+  /** 上游返回的请求 ID（x-request-id / request-id） */
+  upstream_request_id?: string;
+  /** 流式响应 Token 用量来源 */
+  usage_source?: UsageSource;
+  /** 内容过滤结果（仅在命中规则时存在） */
+  content_filter?: ContentFilterOutcome;
+}
+
+/**
+ * 内容过滤结果
+ */
+export interface ContentFilterOutcome {
+  action: "redact" | "block";
+  rules: string[];
+  redactions: number;
 }
 
+/**
+ * Token 用量来源（estimated: 按内容估算，upstream: 上游返回的准确值）
+ */
+export type UsageSource = "estimated" | "upstream";
+
 /**
  * 时间戳集合
  */
@@ -306,6 +467,10 @@ export interface FlowTimestamps {
   response_end?: string;
   duration_ms: number;
   ttfb_ms?: number;
+  /** 代理内部处理耗时（路由、凭证选择、参数注入等） */
+  pipeline_ms?: number;
+  /** 生成耗时（首字节到响应结束） */
+  generation_ms?: number;
 }
 
 /**
@@ -315,7 +480,20 @@ export interface FlowAnnotations {
   marker?: string;
   comment?: string;
   tags: string[];
+  /** 自动标签（由自动标签规则生成） */
+  auto_tags: string[];
   starred: boolean;
+  /** 优先级（越大越靠前，用于相关度排序） */
+  priority?: number;
+}
+
+/**
+ * 标签及使用次数
+ */
+export interface FlowTagCount {
+  tag: string;
+  /** 带有该标签的 Flow 数量 */
+  count: number;
 }
 
 /**
@@ -328,6 +506,14 @@ export interface FlowError {
   raw_response?: string;
   timestamp: string;
   retryable: boolean;
+  /** Provider 返回的错误类型（如 invalid_request_error） */
+  provider_error_type?: string;
+  /** Provider 返回的错误码（如 context_length_exceeded） */
+  provider_error_code?: string;
+  /** Provider 指出的出错参数 */
+  provider_error_param?: string;
+  /** 建议的重试等待（秒） */
+  retry_after?: number;
 }
 
 // ============================================================================
@@ -349,6 +535,60 @@ export interface LLMFlow {
   annotations: FlowAnnotations;
 }
 
+/**
+ * mitmproxy Flow 文件导入统计
+ */
+export interface MitmImportSummary {
+  /** 导入的 Flow 数 */
+  imported: number;
+  /** 跳过的 Flow 数（非 LLM 端点或请求体无法解析） */
+  skipped: number;
+}
+
+/**
+ * 活跃 Flow 概要
+ */
+export interface ActiveFlowSummary {
+  id: string;
+  model: string;
+  provider: ProviderType;
+  state: FlowState;
+  streaming: boolean;
+  started_at: string;
+  age_ms: number;
+  bytes_received: number;
+  chunk_count: number;
+}
+
+/**
+ * 不捕获 Flow 的原因
+ */
+export type CaptureSkipReason =
+  | { type: "disabled" }
+  | { type: "sampled_out"; sampling_rate: number }
+  | { type: "excluded_model"; pattern: string }
+  | { type: "excluded_path"; pattern: string }
+  | {
+      type: "active_flow_cap";
+      max: number;
+      active: number;
+      overflow: ActiveFlowOverflow;
+    };
+
+/**
+ * Flow 捕获判定结果
+ */
+export interface CaptureDecision {
+  /** 请求是否会被捕获（按采样率随机捕获时也为 true） */
+  would_capture: boolean;
+  /** 被捕获的概率（0.0-1.0） */
+  capture_probability: number;
+  /** 不捕获的原因 */
+  reason?: CaptureSkipReason;
+  /** 可读的说明 */
+  message: string;
+}
+
 // ============================================================================
 // 过滤和查询类型
 // ============================================================================
@@ -377,6 +617,11 @@ export interface LatencyRange {
   max_ms?: number;
 }
 
+/**
+ * 标签匹配模式
+ */
+export type TagMatchMode = "any" | "all";
+
 /**
  * Flow 过滤器
  */
@@ -389,17 +634,277 @@ export interface FlowFilter {
   has_tool_calls?: boolean;
   has_thinking?: boolean;
   is_streaming?: boolean;
+  schema_valid?: boolean;
+  /** 是否命中提示词缓存 */
+  cache_hit?: boolean;
   content_search?: string;
   request_search?: string;
   token_range?: TokenRange;
   latency_range?: LatencyRange;
   tags?: string[];
+  tag_match?: TagMatchMode;
   starred_only?: boolean;
+  starred?: boolean;
+  has_comment?: boolean;
+  marker?: string;
   credential_id?: string;
+  upstream_request_id?: string;
   flow_types?: FlowType[];
   filter_expression?: string;
 }
 
+/**
+ * multipart/form-data 上传捕获配置
+ */
+export interface MultipartCaptureConfig {
+  enabled: boolean;
+  save_file_content: boolean;
+  max_file_content_bytes: number;
+  max_field_value_bytes: number;
+  max_attachments: number;
+}
+
+/**
+ * Flow Monitor 配置
+ */
+export interface FlowMonitorConfig {
+  enabled: boolean;
+  max_memory_flows: number;
+  /** 内存中 Flow 的最长保留分钟数（按创建时间，为空时只按数量驱逐） */
+  memory_retention_minutes?: number | null;
+  persist_to_file: boolean;
+  retention_days: number;
+  save_stream_chunks: boolean;
+  /** 流结束时合并连续的同类增量 chunk（仅在保存 chunks 时生效） */
+  compact_stream_chunks?: boolean;
+  max_request_body_size: number;
+  max_response_body_size: number;
+  save_image_content: boolean;
+  thumbnail_size: [number, number];
+  sampling_rate: number;
+  excluded_models: string[];
+  excluded_paths: string[];
+  multipart: MultipartCaptureConfig;
+  /** 向上游透传 Flow ID 的请求头名称，为空时不透传 */
+  request_id_header?: string | null;
+  /** 最大活跃 Flow 数量，为空时不限制 */
+  max_active_flows?: number | null;
+  /** 活跃 Flow 达到上限时跳过捕获或拒绝请求 */
+  active_flow_overflow?: ActiveFlowOverflow;
+  /** 按类别的保留策略（定时清理） */
+  retention_policy?: RetentionPolicy;
+  /** 捕获的上游响应头白名单（支持 * 通配符，敏感头始终排除） */
+  response_header_allowlist?: string[];
+  /** 每个响应最多保存的对数概率 Token 数（超出截断，0 表示不保存） */
+  max_logprob_tokens?: number;
+  /** 不捕获的敏感头名称（请求头和响应头共用，支持 * 通配符） */
+  sensitive_headers?: string[];
+  /** 额外的敏感头名称正则（忽略大小写） */
+  sensitive_header_regex?: string | null;
+  /** 每个请求或响应最多捕获的头数量 */
+  max_captured_headers?: number;
+  /** 单个头值最多保存的字节数 */
+  max_header_value_bytes?: number;
+  /** 正常退出时保存内存 Flow 快照，下次启动时恢复 */
+  snapshot_on_shutdown?: boolean;
+  /** 相关度排序的评分权重 */
+  relevance_weights?: RelevanceWeights;
+  /** 统计成本估算的单价表（键为模型名通配符，如 "claude-*"） */
+  model_pricing?: Record<string, TokenPricing>;
+  /** 磁盘空间保护（剩余空间不足时暂停持久化） */
+  disk_guard?: DiskGuardConfig;
+  /** 成功率 SLO（按 Provider 和模型告警） */
+  slo?: SloConfig;
+  /** 计算内容哈希（模板聚类、缓存、去重）前的规范化规则 */
+  content_normalization?: ContentNormalizationConfig;
+  read_cache?: ReadCacheConfig;
+  /** 把响应正文中的内联推理（如 `<think>...</think>`）提取到思维链 */
+  extract_inline_reasoning?: InlineReasoningConfig;
+  /** 请求预写日志路径，设置后崩溃时进行中的请求会在下次启动时恢复 */
+  wal_path?: string | null;
+  /** 离线 Mock 模式（用捕获的 Flow 响应请求，不调用上游） */
+  mock?: MockConfig;
+}
+
+/**
+ * 离线 Mock 模式配置
+ */
+export interface MockConfig {
+  /** 对所有请求启用（单个请求可用 `x-mock` 请求头覆盖） */
+  enabled: boolean;
+  /** 没有精确匹配时返回 404 或内容最接近的 Flow */
+  fallback: "not_found" | "nearest";
+  /** 流式响应按捕获时的间隔推送 */
+  replay_timing: boolean;
+  /** 单次等待的上限（毫秒） */
+  max_chunk_delay_ms: number;
+}
+
+/**
+ * 内联推理提取配置
+ */
+export interface InlineReasoningConfig {
+  enabled: boolean;
+  /** 推理片段的标签名（`think` 对应 `<think>...</think>`） */
+  tags: string[];
+  /** 按 Provider 的标签名，配置后替代该 Provider 的 tags */
+  provider_tags: Record<string, string[]>;
+}
+
+/**
+ * 文件存储读缓存配置
+ */
+export interface ReadCacheConfig {
+  /** 内存预算（MB，0 表示不缓存） */
+  max_memory_mb: number;
+}
+
+/**
+ * 内容规范化配置
+ */
+export interface ContentNormalizationConfig {
+  /** 去除首尾空白 */
+  trim: boolean;
+  /** 将连续空白合并为一个空格 */
+  collapse_whitespace: boolean;
+  /** 移除 ISO 8601 时间戳 */
+  strip_timestamps: boolean;
+  /** 移除 UUID */
+  strip_uuids: boolean;
+  /** 额外移除的正则 */
+  strip_patterns: string[];
+}
+
+/**
+ * 磁盘空间保护配置
+ */
+export interface DiskGuardConfig {
+  /** 存储目录所在磁盘的最小剩余空间（MB，0 表示不检查） */
+  min_free_mb: number;
+  /** 剩余空间的检查间隔（秒） */
+  check_interval_secs: number;
+}
+
+/**
+ * 磁盘状态
+ */
+export interface DiskStatus {
+  enabled: boolean;
+  /** 持久化是否因空间不足暂停 */
+  persistence_paused: boolean;
+  /** 最近一次检查时的剩余空间（字节） */
+  available_bytes?: number | null;
+  min_free_bytes: number;
+  checked_at?: string | null;
+}
+
+/**
+ * 文件存储读缓存统计
+ */
+export interface ReadCacheStats {
+  entries: number;
+  /** 估算占用（字节） */
+  memory_bytes: number;
+  max_memory_bytes: number;
+  hits: number;
+  misses: number;
+  /** 命中率（尚无读取时为空） */
+  hit_rate?: number | null;
+  /** 因超出预算被淘汰的条目数 */
+  evictions: number;
+}
+
+/**
+ * 成功率 SLO 配置
+ */
+export interface SloConfig {
+  enabled: boolean;
+  /** 目标成功率（如 0.99） */
+  target: number;
+  /** 短窗口（秒） */
+  short_window_secs: number;
+  /** 长窗口（秒） */
+  long_window_secs: number;
+  /** 告警的消耗速率阈值（两个窗口都达到时告警） */
+  burn_rate_threshold: number;
+  /** 恢复比例：短窗口消耗速率低于阈值 × 该比例时恢复 */
+  recovery_ratio: number;
+  /** 短窗口内至少有多少请求才会告警 */
+  min_requests: number;
+}
+
+/**
+ * SLO 单个窗口的统计
+ */
+export interface SloWindowStats {
+  window_secs: number;
+  total: number;
+  failures: number;
+  /** 成功率（窗口内没有请求时为空） */
+  success_rate?: number | null;
+  /** 错误预算消耗速率（窗口内没有请求时为空） */
+  burn_rate?: number | null;
+}
+
+/**
+ * Provider 或 Provider + 模型的 SLO 状态
+ */
+export interface SloStatus {
+  provider: string;
+  /** 模型（为空时为 Provider 汇总） */
+  model?: string | null;
+  target: number;
+  short_window: SloWindowStats;
+  long_window: SloWindowStats;
+  alerting: boolean;
+  alerting_since?: string | null;
+}
+
+/**
+ * 相关度排序的评分权重
+ *
+ * 分数 = recency × 0.5^(距今秒数 / recency_half_life_secs) + priority × 优先级
+ * + starred × 是否收藏 + error × 是否出错
+ */
+export interface RelevanceWeights {
+  recency: number;
+  recency_half_life_secs: number;
+  priority: number;
+  starred: number;
+  error: number;
+}
+
+/**
+ * 模型 Token 单价（美元 / 千 Token）
+ */
+export interface TokenPricing {
+  input_per_1k: number;
+  output_per_1k: number;
+  /** 缓存读取单价（为空时按输入单价计） */
+  cache_read_per_1k?: number;
+  /** 缓存写入单价（为空时按输入单价计） */
+  cache_write_per_1k?: number;
+}
+
+/**
+ * 活跃 Flow 达到上限时的处理方式
+ */
+export type ActiveFlowOverflow = "skip_capture" | "reject_request";
+
+/**
+ * Flow 保留策略（已收藏的 Flow 永久保留）
+ */
+export interface RetentionPolicy {
+  /** 是否启用定时清理 */
+  enabled: boolean;
+  /** 每天运行的本地时间（整点，0-23） */
+  run_at_hour: number;
+  /** 普通 Flow 保留天数，为空时永久保留 */
+  normal_days?: number | null;
+  /** 错误 Flow 保留天数，为空时永久保留 */
+  error_days?: number | null;
+}
+
 /**
  * 排序字段
  */
@@ -408,7 +913,8 @@ export type FlowSortBy =
   | "duration"
   | "total_tokens"
   | "content_length"
-  | "model";
+  | "model"
+  | "relevance";
 
 /**
  * 查询结果
@@ -423,6 +929,18 @@ export interface FlowQueryResult {
   has_prev: boolean;
 }
 
+/**
+ * 过滤表达式试运行结果
+ */
+export interface FilterTestResult {
+  /** 最近的匹配 Flow（按创建时间降序） */
+  flows: LLMFlow[];
+  total_matches: number;
+  scanned: number;
+  parse_ms: number;
+  eval_ms: number;
+}
+
 /**
  * 搜索结果
  */
@@ -457,6 +975,8 @@ export interface ModelStats {
   count: number;
   success_rate: number;
   avg_latency_ms: number;
+  /** 按单价表估算的成本（美元） */
+  estimated_cost_usd?: number;
 }
 
 /**
@@ -467,6 +987,31 @@ export interface StateStats {
   count: number;
 }
 
+/**
+ * 会话线程中的一轮请求
+ */
+export interface FlowThreadTurn {
+  flow_id: string;
+  created_at: string;
+  model: string;
+  state: FlowState;
+  /** 相对上一轮新增的消息 */
+  new_messages: Message[];
+  response?: string;
+}
+
+/**
+ * 会话线程
+ */
+export interface FlowThread {
+  id: string;
+  /** 分叉点 Flow ID */
+  forked_from?: string;
+  started_at: string;
+  updated_at: string;
+  turns: FlowThreadTurn[];
+}
+
 /**
  * Flow 统计信息
  */
@@ -478,10 +1023,17 @@ export interface FlowStats {
   avg_latency_ms: number;
   min_latency_ms: number;
   max_latency_ms: number;
+  avg_pipeline_ms: number;
+  avg_ttfb_ms: number;
+  avg_generation_ms: number;
   total_input_tokens: number;
   total_output_tokens: number;
   avg_input_tokens: number;
   avg_output_tokens: number;
+  /** 按单价表估算的总成本（美元） */
+  estimated_cost_usd?: number;
+  content_filtered_count?: number;
+  content_blocked_count?: number;
   by_provider: ProviderStats[];
   by_model: ModelStats[];
   by_state: StateStats[];
@@ -494,12 +1046,27 @@ export interface FlowStats {
 /**
  * 导出格式
  */
-export type ExportFormat = "har" | "json" | "jsonl" | "markdown" | "csv";
+export type ExportFormat =
+  | "har"
+  | "json"
+  | "jsonl"
+  | "markdown"
+  | "csv"
+  | "openai_batch"
+  | "anthropic_batch"
+  | "parquet";
 
 /**
  * 代码导出格式
  */
-export type CodeExportFormat = "curl" | "python" | "typescript" | "javascript";
+export type CodeExportFormat =
+  | "curl"
+  | "python"
+  | "typescript"
+  | "javascript"
+  | "pythonopenai"
+  | "pythonanthropic"
+  | "httpie";
 
 /**
  * 脱敏规则
@@ -509,6 +1076,8 @@ export interface RedactionRule {
   pattern: string;
   replacement: string;
   enabled: boolean;
+  /** 请求体 / 响应体中要脱敏的位置（JSONPath，如 `$.messages[*].content`），设置后不使用 pattern */
+  json_path?: string;
 }
 
 /**
@@ -521,16 +1090,71 @@ export interface ExportOptions {
   include_stream_chunks?: boolean;
   redact_sensitive?: boolean;
   redaction_rules?: RedactionRule[];
+  /** 脱敏后校验输出，残留疑似密钥时导出失败 */
+  verify_redaction?: boolean;
   compress?: boolean;
+  /** 按稳定顺序输出（对象键按字典序），便于 diff 对比导出结果 */
+  stable_key_order?: boolean;
+  /** JSON / HAR 缩进输出，默认 true */
+  pretty?: boolean;
+  /** 把提取出的内联推理放回响应正文 */
+  restore_inline_reasoning?: boolean;
+  /** JSON / JSONL 只导出的字段（点分路径，如 `request.model`） */
+  field_mask?: string[];
+  /** JSON / JSONL 不导出的字段（点分路径） */
+  exclude_fields?: string[];
 }
 
 /**
  * 导出结果
  */
 export interface ExportResult {
+  /** 导出的数据（Parquet 为 base64 编码的文件内容） */
   data: string;
   filename: string;
   mime_type: string;
+  /** 导出清单（随数据一起分享，接收方可用 verifyExport 校验） */
+  manifest?: ExportManifest;
+}
+
+/**
+ * 导出清单
+ */
+export interface ExportManifest {
+  manifest_version: number;
+  /** 生成导出的 ProxyCast 版本 */
+  generator_version: string;
+  created_at: string;
+  options: {
+    format: ExportFormat;
+    include_raw: boolean;
+    include_stream_chunks: boolean;
+    redact_sensitive: boolean;
+    verify_redaction: boolean;
+    stable_key_order: boolean;
+    pretty: boolean;
+    restore_inline_reasoning: boolean;
+    field_mask?: string[];
+    exclude_fields?: string[];
+  };
+  /** 应用过的脱敏规则（未脱敏时为空） */
+  redaction_rules: { name: string; pattern: string; replacement: string }[];
+  flow_count: number;
+  hash_algorithm: string;
+  /** 导出数据的哈希（十六进制） */
+  content_hash: string;
+  content_bytes: number;
+}
+
+/**
+ * 导出校验结果
+ */
+export interface ExportVerification {
+  valid: boolean;
+  expected_hash: string;
+  actual_hash: string;
+  /** 不一致的原因 */
+  error?: string | null;
 }
 
 // ============================================================================
@@ -592,7 +1216,8 @@ export type FlowEvent =
   | { type: "FlowUpdated"; id: string; update: FlowUpdate }
   | { type: "FlowCompleted"; id: string; summary: FlowSummary }
   | { type: "FlowFailed"; id: string; error: FlowError }
-  | { type: "ThresholdWarning"; id: string; result: ThresholdCheckResult };
+  | { type: "ThresholdWarning"; id: string; result: ThresholdCheckResult }
+  | { type: "SloStatusChanged"; status: SloStatus };
 
 /**
  * 阈值检测结果（用于事件）
@@ -630,6 +1255,9 @@ function getFormatExtension(format: ExportFormat): string {
     har: "har",
     markdown: "md",
     csv: "csv",
+    openai_batch: "jsonl",
+    anthropic_batch: "jsonl",
+    parquet: "parquet",
   };
   return extMap[format] || "txt";
 }
@@ -644,10 +1272,53 @@ function getFormatMimeType(format: ExportFormat): string {
     har: "application/json",
     markdown: "text/markdown",
     csv: "text/csv",
+    openai_batch: "application/x-ndjson",
+    anthropic_batch: "application/x-ndjson",
+    parquet: "application/vnd.apache.parquet",
   };
   return mimeMap[format] || "text/plain";
 }
 
+/**
+ * 按过滤条件删除的预览
+ */
+export interface DeletePreview {
+  count: number;
+  confirm_token: string;
+  expires_at: string;
+}
+
+/**
+ * 按过滤条件删除的结果
+ */
+export interface DeleteByFilterResult {
+  memory_deleted: number;
+  file_deleted: number;
+  files_deleted: number;
+  bytes_freed: number;
+}
+
+/**
+ * 单个 Flow 操作（flowBatchOps 中可混合多种操作）
+ */
+export type FlowOp =
+  | { op: "get"; flow_id: string }
+  | { op: "annotate"; flow_id: string; annotations: FlowAnnotations }
+  | { op: "tag"; flow_id: string; add?: string[]; remove?: string[] }
+  | { op: "delete"; flow_id: string }
+  | { op: "add_to_session"; flow_id: string; session_id: string };
+
+/**
+ * 单个 Flow 操作的结果
+ */
+export interface FlowOpResult {
+  flow_id: string;
+  success: boolean;
+  /** get 操作返回的 Flow */
+  flow?: LLMFlow;
+  error?: string;
+}
+
 // ============================================================================
 // API 接口
 // ============================================================================
@@ -702,6 +1373,20 @@ export const flowMonitorApi = {
     });
   },
 
+  /**
+   * 试运行过滤表达式
+   *
+   * @param expression - 过滤表达式
+   * @param limit - 返回的匹配 Flow 数量上限
+   * @returns 匹配总数、最近的匹配 Flow 和耗时
+   */
+  async testFilter(
+    expression: string,
+    limit: number = 20,
+  ): Promise<FilterTestResult> {
+    return invoke("test_filter", { expression, limit });
+  },
+
   /**
    * 获取单个 Flow 的详细信息
    *
@@ -712,6 +1397,18 @@ export const flowMonitorApi = {
     return invoke("get_flow_detail", { flowId: id });
   },
 
+  /**
+   * 获取 Flow 的模型路由解析说明
+   *
+   * @param id - Flow ID
+   * @returns 解析轨迹和可读描述，Flow 不存在或未记录轨迹时返回 null
+   */
+  async explainFlowRouting(
+    id: string,
+  ): Promise<{ trace: RoutingTrace; description: string } | null> {
+    return invoke("explain_flow_routing", { flowId: id });
+  },
+
   /**
    * 全文搜索 Flow
    *
@@ -736,6 +1433,16 @@ export const flowMonitorApi = {
     return invoke("get_flow_stats", { filter });
   },
 
+  /**
+   * 重建会话线程
+   *
+   * @param filter - 过滤条件（可选）
+   * @returns 按开始时间排序的线程列表
+   */
+  async getFlowThreads(filter: FlowFilter = {}): Promise<FlowThread[]> {
+    return invoke("get_flow_threads", { filter });
+  },
+
   /**
    * 导出 Flow
    *
@@ -748,6 +1455,7 @@ export const flowMonitorApi = {
       data: string;
       count: number;
       format: ExportFormat;
+      manifest: ExportManifest;
     }>("export_flows", {
       request: {
         format: options.format,
@@ -755,6 +1463,12 @@ export const flowMonitorApi = {
         include_raw: options.include_raw ?? true,
         include_stream_chunks: options.include_stream_chunks ?? false,
         redact_sensitive: options.redact_sensitive ?? false,
+        verify_redaction: options.verify_redaction ?? false,
+        stable_key_order: options.stable_key_order ?? false,
+        pretty: options.pretty ?? true,
+        restore_inline_reasoning: options.restore_inline_reasoning ?? false,
+        field_mask: options.field_mask ?? [],
+        exclude_fields: options.exclude_fields ?? [],
         flow_ids: null,
       },
     });
@@ -769,9 +1483,24 @@ export const flowMonitorApi = {
       data: response.data,
       filename,
       mime_type: mimeType,
+      manifest: response.manifest,
     };
   },
 
+  /**
+   * 按导出清单校验导出数据是否被修改
+   *
+   * @param data - 导出的数据
+   * @param manifest - 随数据一起导出的清单
+   * @returns 校验结果
+   */
+  async verifyExport(
+    data: string,
+    manifest: ExportManifest,
+  ): Promise<ExportVerification> {
+    return invoke("verify_export", { data, manifest });
+  },
+
   /**
    * 更新 Flow 标注
    *
@@ -901,9 +1630,19 @@ export const flowMonitorApi = {
   /**
    * 获取所有可用的标签
    *
-   * @returns 标签列表
+   * @returns 标签列表（按使用次数降序）
    */
   async getAllTags(): Promise<string[]> {
+    const counts = await invoke<FlowTagCount[]>("get_all_flow_tags");
+    return counts.map((c) => c.tag);
+  },
+
+  /**
+   * 获取所有标签及使用次数
+   *
+   * @returns 标签及使用次数（按次数降序）
+   */
+  async getTagCounts(): Promise<FlowTagCount[]> {
     return invoke("get_all_flow_tags");
   },
 
@@ -923,6 +1662,7 @@ export const flowMonitorApi = {
       data: string;
       count: number;
       format: ExportFormat;
+      manifest: ExportManifest;
     }>("export_flows", {
       request: {
         format: options.format,
@@ -930,6 +1670,12 @@ export const flowMonitorApi = {
         include_raw: options.include_raw ?? true,
         include_stream_chunks: options.include_stream_chunks ?? false,
         redact_sensitive: options.redact_sensitive ?? false,
+        verify_redaction: options.verify_redaction ?? false,
+        stable_key_order: options.stable_key_order ?? false,
+        pretty: options.pretty ?? true,
+        restore_inline_reasoning: options.restore_inline_reasoning ?? false,
+        field_mask: options.field_mask ?? [],
+        exclude_fields: options.exclude_fields ?? [],
         flow_ids: ids,
       },
     });
@@ -944,6 +1690,36 @@ export const flowMonitorApi = {
       data: response.data,
       filename,
       mime_type: mimeType,
+      manifest: response.manifest,
+    };
+  },
+
+  /**
+   * 将多个 Flow 合并导出为一份会话记录（去除每轮重复的历史消息）
+   *
+   * @param ids - 按会话顺序排列的 Flow ID 列表
+   * @param format - 导出格式（markdown 或 json）
+   * @param redactSensitive - 是否脱敏敏感数据
+   * @returns 导出结果
+   */
+  async exportConversation(
+    ids: string[],
+    format: "markdown" | "json",
+    redactSensitive = false,
+  ): Promise<ExportResult> {
+    const data = await invoke<string>("export_flow_conversation", {
+      request: {
+        flow_ids: ids,
+        format,
+        redact_sensitive: redactSensitive,
+      },
+    });
+
+    const timestamp = new Date().toISOString().replace(/[:.]/g, "-");
+    return {
+      data,
+      filename: `conversation_${timestamp}.${getFormatExtension(format)}`,
+      mime_type: getFormatMimeType(format),
     };
   },
 
@@ -967,6 +1743,67 @@ export const flowMonitorApi = {
     return invoke("delete_flows", { ids });
   },
 
+  /**
+   * 列出正在进行中的 Flow
+   *
+   * @returns 活跃 Flow 概要（按开始时间从早到晚排序）
+   */
+  async listActiveFlows(): Promise<ActiveFlowSummary[]> {
+    return invoke("list_active_flows");
+  },
+
+  /**
+   * 说明请求当前是否会被捕获（排查“请求没有出现在 Flow 列表中”）
+   *
+   * @param model - 请求的模型
+   * @param path - 请求路径（如 `/v1/chat/completions`）
+   * @returns 捕获判定结果，不捕获时包含原因
+   */
+  async explainCaptureDecision(
+    model: string,
+    path: string,
+  ): Promise<CaptureDecision> {
+    return invoke("explain_flow_capture_decision", { model, path });
+  },
+
+  /**
+   * 强制取消正在进行中的 Flow
+   *
+   * @param flowId - Flow ID
+   * @returns 是否已取消（Flow 不存在或已结束时返回 false）
+   */
+  async forceCancelActiveFlow(flowId: string): Promise<boolean> {
+    return invoke("force_cancel_active_flow", { flowId });
+  },
+
+  /**
+   * 导入 mitmproxy 保存的 Flow 文件
+   *
+   * @param path - Flow 文件路径（`mitmdump -w` 的输出或 `.mitm` 文件）
+   * @returns 导入和跳过的 Flow 数
+   */
+  async importMitmFlows(path: string): Promise<MitmImportSummary> {
+    return invoke("import_mitm_flows", { path });
+  },
+
+  /**
+   * 获取 Flow Monitor 配置
+   *
+   * @returns Flow Monitor 配置
+   */
+  async getFlowMonitorConfig(): Promise<FlowMonitorConfig> {
+    return invoke("get_flow_monitor_config");
+  },
+
+  /**
+   * 更新 Flow Monitor 配置
+   *
+   * @param config - 新的配置
+   */
+  async setFlowMonitorConfig(config: FlowMonitorConfig): Promise<void> {
+    return invoke("set_flow_monitor_config", { config });
+  },
+
   /**
    * 获取 Flow Monitor 调试信息
    *
@@ -983,6 +1820,15 @@ export const flowMonitorApi = {
     return invoke("get_flow_monitor_debug_info");
   },
 
+  /**
+   * 获取各 Provider 和模型的成功率 SLO 状态
+   *
+   * @returns SLO 状态列表
+   */
+  async getSloStatus(): Promise<SloStatus[]> {
+    return invoke("get_slo_status");
+  },
+
   /**
    * 创建测试 Flow 数据（仅用于调试）
    *
@@ -992,6 +1838,42 @@ export const flowMonitorApi = {
   async createTestFlows(count?: number): Promise<number> {
     return invoke("create_test_flows", { count });
   },
+
+  /**
+   * 预览按过滤条件删除
+   *
+   * @param filter - 过滤条件
+   * @returns 匹配数量和短期有效的确认令牌
+   */
+  async previewDeleteFlowsByFilter(filter: FlowFilter): Promise<DeletePreview> {
+    return invoke("preview_delete_flows_by_filter", { filter });
+  },
+
+  /**
+   * 按过滤条件删除 Flow（内存和文件存储）
+   *
+   * @param filter - 过滤条件（需与预览时一致）
+   * @param confirmToken - 预览返回的确认令牌
+   * @returns 删除结果
+   */
+  async deleteFlowsByFilter(
+    filter: FlowFilter,
+    confirmToken: string,
+  ): Promise<DeleteByFilterResult> {
+    return invoke("delete_flows_by_filter", {
+      request: { filter, confirm_token: confirmToken },
+    });
+  },
+
+  /**
+   * 一次执行多个单 Flow 操作，某个操作失败不影响其余操作
+   *
+   * @param ops - 操作列表
+   * @returns 按请求顺序返回的各操作结果
+   */
+  async flowBatchOps(ops: FlowOp[]): Promise<FlowOpResult[]> {
+    return invoke("flow_batch_ops", { ops });
+  },
 };
 
 // ============================================================================
@@ -1039,9 +1921,13 @@ export function formatErrorType(errorType: FlowErrorType): string {
     rate_limit: "速率限制",
     content_filter: "内容过滤",
     server_error: "服务器错误",
+    overloaded: "上游过载",
     bad_request: "请求错误",
     model_unavailable: "模型不可用",
     token_limit_exceeded: "Token 限制超出",
+    gate_rejected: "路由闸门拒绝",
+    incomplete_stream: "流未正常结束",
+    unknown_outcome: "结果未知",
     other: "其他错误",
   };
   return errorMap[errorType] || errorType;
@@ -1160,6 +2046,28 @@ export interface Distribution {
   total: number;
 }
 
+/**
+ * 延迟分位数（毫秒）
+ */
+export interface LatencyPercentiles {
+  count: number;
+  p50_ms: number | null;
+  p95_ms: number | null;
+  p99_ms: number | null;
+}
+
+/**
+ * 单个模型的流式延迟统计
+ */
+export interface ModelStreamLatency {
+  model: string;
+  stream_count: number;
+  ttfb: LatencyPercentiles;
+  chunk_gap: LatencyPercentiles;
+  ttfb_histogram: Distribution;
+  chunk_gap_histogram: Distribution;
+}
+
 /**
  * 趋势数据
  */
@@ -1176,6 +2084,20 @@ export interface StatsTimeRange {
   end: string;
 }
 
+/**
+ * 提示词缓存效果
+ */
+export interface CacheEffectiveness {
+  flows_with_cache_usage: number;
+  cache_hit_flows: number;
+  input_tokens: number;
+  cache_read_tokens: number;
+  cache_write_tokens: number;
+  hit_ratio: number;
+  /** 节省的成本（等价全价输入 Token） */
+  saved_input_tokens: number;
+}
+
 /**
  * 增强统计结果
  */
@@ -1183,17 +2105,93 @@ export interface EnhancedStats {
   request_trend: TrendData;
   token_by_model: Distribution;
   success_by_provider: [string, number][];
+  /** 按提供商的未正常结束的流式响应比例 */
+  incomplete_stream_by_provider?: [string, number][];
   latency_histogram: Distribution;
   error_distribution: Distribution;
+  stop_reason_distribution?: Distribution;
+  cache_effectiveness?: CacheEffectiveness;
   request_rate: number;
   time_range: StatsTimeRange;
 }
 
+/**
+ * 提示词模板聚类依据的消息
+ */
+export type PromptClusterRole = "system" | "first_user" | "system_and_first_user";
+
+/**
+ * 模型单价（美元 / 百万 Token）
+ */
+export interface ModelPrice {
+  input_per_million: number;
+  output_per_million: number;
+}
+
+/**
+ * 提示词模板聚合选项
+ */
+export interface PromptTemplateOptions {
+  role?: PromptClusterRole;
+  /** 按模型名的单价表 */
+  prices?: Record<string, ModelPrice>;
+  /** 单价表未覆盖的模型使用的单价 */
+  default_price?: ModelPrice;
+  limit?: number;
+}
+
+/**
+ * 单个提示词模板的聚合统计
+ */
+export interface PromptTemplateStats {
+  /** 跨运行稳定的模板哈希 */
+  template_hash: string;
+  /** 规范化后的模板文本（截断） */
+  sample: string;
+  count: number;
+  input_tokens: number;
+  output_tokens: number;
+  total_tokens: number;
+  /** 成本（美元） */
+  cost: number;
+  cost_share: number;
+  token_share: number;
+  models: string[];
+}
+
+/**
+ * 提示词模板成本报告
+ */
+export interface PromptTemplateReport {
+  role: PromptClusterRole;
+  templates: PromptTemplateStats[];
+  total_flows: number;
+  clustered_flows: number;
+  total_cost: number;
+  total_tokens: number;
+  unpriced_flows: number;
+}
+
 /**
  * 报告格式
  */
 export type ReportFormat = "json" | "markdown" | "csv";
 
+/**
+ * 统计输出格式（json 为带单位后缀的稳定结构）
+ */
+export type StatsOutputFormat = "text" | "json";
+
+/**
+ * 结构化的过滤字段帮助
+ */
+export interface FilterFieldHelp {
+  field: string;
+  operators: string[];
+  example: string;
+  description: string;
+}
+
 /**
  * 增强统计 API
  */
@@ -1300,6 +2298,22 @@ export const enhancedStatsApi = {
     });
   },
 
+  /**
+   * 获取各模型的流式延迟分位数（TTFB 与 chunk 间隔）
+   *
+   * @returns 各模型的流式延迟统计
+   */
+  async getStreamLatencyPercentiles(): Promise<ModelStreamLatency[]> {
+    return invoke("get_stream_latency_percentiles");
+  },
+
+  /**
+   * 重置流式延迟直方图
+   */
+  async resetStreamLatencyHistograms(): Promise<void> {
+    return invoke("reset_stream_latency_histograms");
+  },
+
   /**
    * 导出统计报告
    *
@@ -1326,6 +2340,69 @@ export const enhancedStatsApi = {
       },
     });
   },
+
+  /**
+   * 获取提示词模板成本报告
+   *
+   * @param filter - 过滤条件
+   * @param timeRange - 时间范围
+   * @param options - 聚类依据和单价表
+   * @returns 按成本降序排列的模板报告
+   */
+  async getPromptTemplateStats(
+    filter: FlowFilter = {},
+    timeRange?: StatsTimeRange,
+    options: PromptTemplateOptions = {},
+  ): Promise<PromptTemplateReport> {
+    const now = new Date();
+    const defaultTimeRange: StatsTimeRange = {
+      start: new Date(now.getTime() - 24 * 60 * 60 * 1000).toISOString(),
+      end: now.toISOString(),
+    };
+    return invoke("get_prompt_template_stats", {
+      request: {
+        filter,
+        time_range: timeRange || defaultTimeRange,
+        options,
+      },
+    });
+  },
+
+  /**
+   * 获取统计输出（供 CLI 和脚本使用）
+   *
+   * @param filter - 过滤条件
+   * @param timeRange - 时间范围
+   * @param format - 输出格式
+   * @returns 文本或 JSON 字符串
+   */
+  async getStatsOutput(
+    filter: FlowFilter = {},
+    timeRange?: StatsTimeRange,
+    format: StatsOutputFormat = "json",
+  ): Promise<string> {
+    const now = new Date();
+    const defaultTimeRange: StatsTimeRange = {
+      start: new Date(now.getTime() - 24 * 60 * 60 * 1000).toISOString(),
+      end: now.toISOString(),
+    };
+    return invoke("get_stats_output", {
+      request: {
+        filter,
+        time_range: timeRange || defaultTimeRange,
+        format,
+      },
+    });
+  },
+
+  /**
+   * 获取结构化的过滤字段帮助
+   *
+   * @returns 每个过滤字段的匹配方式和示例
+   */
+  async getFilterHelpFields(): Promise<FilterFieldHelp[]> {
+    return invoke("get_filter_help_fields");
+  },
 };
 
 export default flowMonitorApi;
@@ -1402,3 +2479,56 @@ export const realtimeMonitorApi = {
     return invoke("set_rate_window", { windowSeconds });
   },
 };
+
+// ============================================================================
+// 自动标签类型
+// ============================================================================
+
+/**
+ * 自动标签规则
+ */
+export interface AutoTagRule {
+  /** 规则名称 */
+  name: string;
+  /** 匹配条件（过滤表达式，如 `~latency >5s`） */
+  condition: string;
+  /** 匹配时应用的标签 */
+  tags: string[];
+  /** 匹配时是否自动收藏 */
+  star: boolean;
+  /** 是否启用 */
+  enabled: boolean;
+}
+
+/**
+ * 自动标签配置
+ */
+export interface AutoTagConfig {
+  /** 是否启用自动标签 */
+  enabled: boolean;
+  /** 规则列表 */
+  rules: AutoTagRule[];
+}
+
+/**
+ * 自动标签 API
+ */
+export const autoTagApi = {
+  /**
+   * 获取自动标签配置
+   *
+   * @returns 自动标签配置
+   */
+  async getAutoTagConfig(): Promise<AutoTagConfig> {
+    return invoke("get_auto_tag_config");
+  },
+
+  /**
+   * 更新自动标签配置（立即生效）
+   *
+   * @param config - 新的自动标签配置
+   */
+  async updateAutoTagConfig(config: AutoTagConfig): Promise<void> {
+    return invoke("update_auto_tag_config", { config });
+  },
+};
@@ -6,12 +6,18 @@ export interface RetryConfig {
   base_delay_ms: number;
   max_delay_ms: number;
   retryable_codes: number[];
+  /** Base delay for overloaded upstreams (503 / 529) */
+  overload_base_delay_ms?: number;
+  /** Max delay for overloaded upstreams, also caps Retry-After */
+  overload_max_delay_ms?: number;
 }
 
 // Failover configuration
 export interface FailoverConfig {
   auto_switch: boolean;
   switch_on_quota: boolean;
+  /** Switch provider when retries on an overloaded upstream are exhausted */
+  switch_on_overload?: boolean;
 }
 
 // Switch log entry
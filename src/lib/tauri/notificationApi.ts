@@ -20,6 +20,36 @@ export interface NotificationSettings {
   sound: boolean;
   /** 声音文件路径（可选） */
   sound_file?: string;
+  /** Webhook 地址（可选） */
+  webhook_url?: string;
+}
+
+/**
+ * Webhook 投递设置
+ */
+export interface WebhookSettings {
+  /** 载荷模板（支持 {{type}}、{{title}}、{{message}}、{{flow_id}}、{{model}}、{{timestamp}}、{{count}}） */
+  payload_template?: string;
+  /** HMAC 签名密钥 */
+  secret?: string;
+  /** 最大重试次数 */
+  max_retries: number;
+  /** 首次重试等待时间（毫秒） */
+  initial_backoff_ms: number;
+  /** 单次请求超时（毫秒） */
+  timeout_ms: number;
+}
+
+/**
+ * 错误通知合并设置
+ */
+export interface CoalescingSettings {
+  /** 是否启用合并 */
+  enabled: boolean;
+  /** 合并窗口（秒） */
+  window_secs: number;
+  /** 窗口内单独通知的次数，超出部分合并为汇总通知 */
+  threshold: number;
 }
 
 /**
@@ -36,6 +66,10 @@ export interface NotificationConfig {
   latency_warning: NotificationSettings;
   /** Token 警告通知配置 */
   token_warning: NotificationSettings;
+  /** Webhook 投递设置 */
+  webhook?: WebhookSettings;
+  /** 错误通知合并设置 */
+  coalescing?: CoalescingSettings;
 }
 
 /**